@@ -0,0 +1,105 @@
+//! 帧转发的内存分配对比
+//!
+//! 运行：`cargo bench --bench frame_fanout`
+//!
+//! 统计把一帧转发给 N 个对端、以及对 CBOR 帧反复取签名字节时的分配次数与分配字节数：
+//! 逐个复制 `Vec<u8>` 与共享 `bytes::Bytes` 的差别。
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use aex::tcp::types::Codec;
+use bytes::Bytes;
+use zz_account::address::FreeWebMovementAddress;
+use zz_p2p::protocols::{
+    command::{Action, Entity, P2PCommand},
+    frame::P2PFrame,
+    version::CURRENT_PROTOCOL_VERSION,
+    wire_format::WireFormat,
+};
+
+struct Counting;
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+static ALLOC_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        ALLOC_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const PEERS: usize = 32;
+const ROUNDS: usize = 1_000;
+
+fn measure<F: FnMut()>(name: &str, mut f: F) {
+    let allocs = ALLOCS.load(Ordering::Relaxed);
+    let bytes = ALLOC_BYTES.load(Ordering::Relaxed);
+    let start = Instant::now();
+    f();
+    let elapsed = start.elapsed();
+    println!(
+        "{:<32} allocs={:>8} bytes={:>12} time={:?}",
+        name,
+        ALLOCS.load(Ordering::Relaxed) - allocs,
+        ALLOC_BYTES.load(Ordering::Relaxed) - bytes,
+        elapsed
+    );
+}
+
+#[tokio::main]
+async fn main() {
+    let address = FreeWebMovementAddress::random();
+    let cmd = P2PCommand::new(Entity::Node, Action::Ping, vec![0xA5; 64 * 1024]);
+
+    let frame = P2PFrame::build(&address, cmd.clone(), CURRENT_PROTOCOL_VERSION)
+        .await
+        .unwrap();
+    let encoded: Vec<u8> = Codec::encode(&frame).unwrap();
+    println!(
+        "fan-out of a {} byte frame to {} peers, {} rounds",
+        encoded.len(),
+        PEERS,
+        ROUNDS
+    );
+
+    measure("Vec<u8> clone per peer", || {
+        for _ in 0..ROUNDS {
+            let copies: Vec<Vec<u8>> = (0..PEERS).map(|_| encoded.clone()).collect();
+            std::hint::black_box(copies);
+        }
+    });
+
+    let shared = Bytes::from(encoded.clone());
+    measure("Bytes clone per peer", || {
+        for _ in 0..ROUNDS {
+            let copies: Vec<Bytes> = (0..PEERS).map(|_| shared.clone()).collect();
+            std::hint::black_box(copies);
+        }
+    });
+
+    let cbor = P2PFrame::build_as(
+        &address,
+        cmd,
+        CURRENT_PROTOCOL_VERSION,
+        None,
+        WireFormat::Cbor,
+    )
+    .await
+    .unwrap();
+    measure("CBOR signing_bytes", || {
+        for _ in 0..ROUNDS {
+            std::hint::black_box(cbor.signing_bytes().unwrap());
+        }
+    });
+}
@@ -0,0 +1,30 @@
+//! 构建时把当前 git 提交写入 `ZZ_P2P_GIT_HASH`，握手中作为软件信息的一部分发送。
+//! 构建环境已设置该变量时（例如从源码包构建）直接使用；不在 git 仓库中时为空。
+
+use std::{path::Path, process::Command};
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=ZZ_P2P_GIT_HASH");
+    if Path::new(".git/HEAD").exists() {
+        println!("cargo:rerun-if-changed=.git/HEAD");
+        println!("cargo:rerun-if-changed=.git/refs");
+    }
+
+    let hash = std::env::var("ZZ_P2P_GIT_HASH")
+        .ok()
+        .filter(|hash| !hash.is_empty())
+        .or_else(|| {
+            let output = Command::new("git")
+                .args(["rev-parse", "--short=12", "HEAD"])
+                .output()
+                .ok()?;
+            if !output.status.success() {
+                return None;
+            }
+            String::from_utf8(output.stdout)
+                .ok()
+                .map(|hash| hash.trim().to_string())
+        })
+        .unwrap_or_default();
+    println!("cargo:rustc-env=ZZ_P2P_GIT_HASH={}", hash);
+}
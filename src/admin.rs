@@ -0,0 +1,574 @@
+//! 远程管理通道
+//!
+//! 管理命令（重新加载配置、封禁对端、调整保留策略、轮换会话密钥、关闭节点、查看审计日志）通过 Web API 的
+//! `POST /api/admin` 或控制接口的 `POST /admin` 提交。请求必须由配置 `[admin]` 中登记的管理
+//! 密钥签名：管理密钥是独立于节点身份的 secp256k1 密钥对，由 `zzp2p admin keygen` 生成，
+//! 节点只保存其公钥与角色。
+//!
+//! 请求体为 `{"request", "public_key", "signature"}`：`request` 是 [`AdminRequest`] 的 JSON
+//! 文本，签名覆盖 `zz-p2p-admin-v1\n` 前缀加上该文本，因此无需规范化 JSON。签发时间与本机
+//! 时间的偏差不得超过 `max_clock_skew_secs`，同一 nonce 在有效期内只接受一次。
+//!
+//! 角色逐级包含：`auditor` 只能查看审计日志与中继记账，`operator` 还可以重新加载配置、封禁对端与调整保留策略，
+//! `owner` 可以执行全部命令。每次请求（包括被拒绝的）都追加一行 JSON 到存储目录下的
+//! `admin_audit.log`。未配置任何管理密钥时通道关闭。
+
+use std::{
+    fmt,
+    fs::OpenOptions,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use aex::connection::global::GlobalContext;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::sync::Notify;
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::{
+    cli::{AdminCommand, Opt},
+    config::{self, AdminConfig, AdminKey, ConfigFile, SharedConfig},
+    connections, control,
+    protocols::{
+        acl::{self, AclTarget},
+        commands::rekey::{self, SessionTable},
+    },
+    relay,
+    retention::{self, RetentionConfig},
+};
+
+/// 审计日志文件名（位于存储目录下）
+pub const ADMIN_AUDIT_FILE: &str = "admin_audit.log";
+/// 默认允许的时钟偏差
+pub const DEFAULT_MAX_CLOCK_SKEW_SECS: u64 = 60;
+/// `audit_log` 默认返回的条数
+pub const DEFAULT_AUDIT_TAIL: usize = 100;
+/// nonce 的最大长度
+pub const MAX_NONCE_LEN: usize = 64;
+/// 签名前缀，避免管理签名被挪用到其它场景
+const SIGNING_DOMAIN: &[u8] = b"zz-p2p-admin-v1\n";
+
+/// 管理角色，后者包含前者的全部权限
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminRole {
+    Auditor,
+    Operator,
+    Owner,
+}
+
+impl AdminRole {
+    pub fn permits(&self, action: AdminAction) -> bool {
+        *self >= action.required_role()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminAction {
+    /// 重新读取启动时指定的配置文件
+    ReloadConfig,
+    /// 封禁对端并断开匹配的连接，参数 `{"target"}`
+    BanPeer,
+    /// 与所有对端轮换会话密钥
+    RotateKeys,
+    /// 通知对端下线后关闭节点
+    Shutdown,
+    /// 查看最近的审计记录，参数 `{"limit"?}`
+    AuditLog,
+    /// 查看或替换保留策略（只在内存中生效），参数 `{"policy"?, "prune"?}`，`prune` 为 true 时立即清理一次
+    Retention,
+    /// 查看中继配额与每个地址的中继记账，参数 `{"limit"?}`
+    RelayStats,
+}
+
+impl AdminAction {
+    pub fn required_role(&self) -> AdminRole {
+        match self {
+            AdminAction::AuditLog | AdminAction::RelayStats => AdminRole::Auditor,
+            AdminAction::ReloadConfig | AdminAction::BanPeer | AdminAction::Retention => {
+                AdminRole::Operator
+            }
+            AdminAction::RotateKeys | AdminAction::Shutdown => AdminRole::Owner,
+        }
+    }
+}
+
+/// 被签名的管理请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminRequest {
+    pub action: AdminAction,
+    #[serde(default)]
+    pub params: Value,
+    /// 签发时间（秒）
+    pub issued_at: u64,
+    pub nonce: String,
+}
+
+/// 提交给节点的请求体
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedAdminRequest {
+    /// [`AdminRequest`] 的 JSON 文本
+    pub request: String,
+    /// 管理公钥（hex）
+    pub public_key: String,
+    /// 紧凑格式签名（hex）
+    pub signature: String,
+}
+
+fn signing_bytes(request: &str) -> Vec<u8> {
+    [SIGNING_DOMAIN, request.as_bytes()].concat()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// 管理密钥的公钥（hex），填入配置的 `public_key`
+pub fn public_key_hex(key: &FreeWebMovementAddress) -> String {
+    hex(&key.public_key.to_bytes())
+}
+
+impl SignedAdminRequest {
+    pub fn sign(key: &FreeWebMovementAddress, request: &AdminRequest) -> anyhow::Result<Self> {
+        let request = serde_json::to_string(request)?;
+        let signature =
+            FreeWebMovementAddress::sign_message(&key.private_key, &signing_bytes(&request))
+                .serialize_compact()
+                .to_vec();
+        Ok(Self {
+            request,
+            public_key: public_key_hex(key),
+            signature: hex(&signature),
+        })
+    }
+
+    /// 校验签名并解析请求，不检查授权
+    pub fn verify(&self) -> Result<AdminRequest, AdminDenied> {
+        let public_key = unhex(&self.public_key)
+            .filter(|k| bitcoin::PublicKey::from_slice(k).is_ok())
+            .ok_or_else(|| AdminDenied::Malformed("invalid public key".into()))?;
+        let signature = unhex(&self.signature)
+            .filter(|s| bitcoin::secp256k1::ecdsa::Signature::from_compact(s).is_ok())
+            .ok_or_else(|| AdminDenied::Malformed("invalid signature encoding".into()))?;
+        let public_key = FreeWebMovementAddress::to_public_key(&public_key);
+        let signature = FreeWebMovementAddress::to_signature(&signature);
+        if !FreeWebMovementAddress::verify_message(
+            &public_key,
+            &signing_bytes(&self.request),
+            &signature,
+        ) {
+            return Err(AdminDenied::Unauthorized("bad signature".into()));
+        }
+        serde_json::from_str(&self.request).map_err(|e| AdminDenied::Malformed(e.to_string()))
+    }
+}
+
+/// 请求被拒绝的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdminDenied {
+    /// 通道未启用（没有配置管理密钥）
+    Disabled,
+    /// 请求格式错误
+    Malformed(String),
+    /// 未登记的密钥、签名错误、过期或重放
+    Unauthorized(String),
+    /// 角色不足
+    Forbidden {
+        role: AdminRole,
+        action: AdminAction,
+    },
+}
+
+impl AdminDenied {
+    pub fn status(&self) -> u16 {
+        match self {
+            AdminDenied::Disabled => 404,
+            AdminDenied::Malformed(_) => 400,
+            AdminDenied::Unauthorized(_) => 401,
+            AdminDenied::Forbidden { .. } => 403,
+        }
+    }
+}
+
+impl fmt::Display for AdminDenied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdminDenied::Disabled => write!(f, "admin channel is disabled"),
+            AdminDenied::Malformed(reason) => write!(f, "malformed admin request: {}", reason),
+            AdminDenied::Unauthorized(reason) => write!(f, "unauthorized: {}", reason),
+            AdminDenied::Forbidden { role, action } => {
+                write!(f, "role {:?} may not perform {:?}", role, action)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Ok,
+    Denied,
+    Failed,
+}
+
+/// 审计日志中的一行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// 记录时间（秒）
+    pub at: i64,
+    /// 管理密钥名称，未登记的密钥为空
+    pub admin: Option<String>,
+    pub public_key: String,
+    pub action: Option<AdminAction>,
+    pub params: Value,
+    pub outcome: AuditOutcome,
+    pub detail: String,
+}
+
+/// 管理通道的运行状态
+pub struct AdminChannel {
+    audit_path: PathBuf,
+    audit_lock: std::sync::Mutex<()>,
+    /// 已使用的 nonce → 签发时间
+    nonces: DashMap<String, u64>,
+    shutdown: Notify,
+}
+
+pub type SharedAdmin = Arc<AdminChannel>;
+
+impl AdminChannel {
+    pub fn new(audit_path: impl Into<PathBuf>) -> Self {
+        Self {
+            audit_path: audit_path.into(),
+            audit_lock: std::sync::Mutex::new(()),
+            nonces: DashMap::new(),
+            shutdown: Notify::new(),
+        }
+    }
+
+    pub fn audit_path(&self) -> &Path {
+        &self.audit_path
+    }
+
+    /// 校验签名、登记的密钥、签发时间、nonce 与角色
+    pub fn authorize(
+        &self,
+        config: &AdminConfig,
+        signed: &SignedAdminRequest,
+        now: u64,
+    ) -> Result<(AdminKey, AdminRequest), AdminDenied> {
+        if config.keys.is_empty() {
+            return Err(AdminDenied::Disabled);
+        }
+        let key = config
+            .keys
+            .iter()
+            .find(|k| k.public_key.eq_ignore_ascii_case(&signed.public_key))
+            .cloned()
+            .ok_or_else(|| AdminDenied::Unauthorized("unknown admin key".into()))?;
+        let request = signed.verify()?;
+
+        if request.issued_at.abs_diff(now) > config.max_clock_skew_secs {
+            return Err(AdminDenied::Unauthorized("request expired".into()));
+        }
+        if request.nonce.is_empty() || request.nonce.len() > MAX_NONCE_LEN {
+            return Err(AdminDenied::Malformed("invalid nonce".into()));
+        }
+        // 超出时钟偏差窗口的 nonce 已无法重放，不必再保留
+        let horizon = now.saturating_sub(config.max_clock_skew_secs * 2);
+        self.nonces.retain(|_, issued_at| *issued_at >= horizon);
+        if self
+            .nonces
+            .insert(request.nonce.clone(), request.issued_at)
+            .is_some()
+        {
+            return Err(AdminDenied::Unauthorized("replayed nonce".into()));
+        }
+
+        if !key.role.permits(request.action) {
+            return Err(AdminDenied::Forbidden {
+                role: key.role,
+                action: request.action,
+            });
+        }
+        Ok((key, request))
+    }
+
+    /// 追加一条审计记录
+    pub fn record(&self, entry: &AuditEntry) {
+        let _guard = self.audit_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let line = match serde_json::to_string(entry) {
+            Ok(l) => l,
+            Err(e) => {
+                tracing::error!("Failed to encode audit entry: {}", e);
+                return;
+            }
+        };
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.audit_path)
+            .and_then(|mut f| writeln!(f, "{}", line));
+        if let Err(e) = written {
+            tracing::error!(
+                "Failed to write admin audit log {}: {}",
+                self.audit_path.display(),
+                e
+            );
+        }
+    }
+
+    /// 最近的 `limit` 条审计记录（按时间先后）
+    pub fn tail(&self, limit: usize) -> Vec<AuditEntry> {
+        let _guard = self.audit_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let Ok(file) = std::fs::File::open(&self.audit_path) else {
+            return Vec::new();
+        };
+        let entries: Vec<AuditEntry> = BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|l| serde_json::from_str(&l).ok())
+            .collect();
+        let skip = entries.len().saturating_sub(limit);
+        entries.into_iter().skip(skip).collect()
+    }
+
+    /// 等待管理员发出的关闭请求
+    pub async fn shutdown_requested(&self) {
+        self.shutdown.notified().await
+    }
+}
+
+/// 等待管理员发出的关闭请求；管理通道未初始化时永不返回
+pub async fn shutdown_requested(gctx: &Arc<GlobalContext>) {
+    match gctx.get::<SharedAdmin>().await {
+        Some(admin) => admin.shutdown_requested().await,
+        None => std::future::pending().await,
+    }
+}
+
+fn now_secs() -> u64 {
+    chrono::Utc::now().timestamp().max(0) as u64
+}
+
+/// 处理一个管理请求体，返回 (HTTP 状态码, JSON)
+pub async fn handle(gctx: &Arc<GlobalContext>, body: &[u8]) -> (u16, Value) {
+    let (Some(admin), Some(config)) = (
+        gctx.get::<SharedAdmin>().await,
+        gctx.get::<SharedConfig>().await,
+    ) else {
+        let denied = AdminDenied::Disabled;
+        return (
+            denied.status(),
+            json!({"success": false, "error": denied.to_string()}),
+        );
+    };
+    let admin_config = config.read().await.admin.clone();
+
+    let signed: SignedAdminRequest = match serde_json::from_slice(body) {
+        Ok(s) => s,
+        Err(e) => {
+            let denied = AdminDenied::Malformed(e.to_string());
+            return (
+                denied.status(),
+                json!({"success": false, "error": denied.to_string()}),
+            );
+        }
+    };
+
+    let (key, request) = match admin.authorize(&admin_config, &signed, now_secs()) {
+        Ok(granted) => granted,
+        Err(denied) => {
+            if denied != AdminDenied::Disabled {
+                tracing::warn!("🛡️ Admin request denied: {}", denied);
+                let request: Option<AdminRequest> = serde_json::from_str(&signed.request).ok();
+                admin.record(&AuditEntry {
+                    at: chrono::Utc::now().timestamp(),
+                    admin: admin_config
+                        .keys
+                        .iter()
+                        .find(|k| k.public_key.eq_ignore_ascii_case(&signed.public_key))
+                        .map(|k| k.name.clone()),
+                    public_key: signed.public_key.clone(),
+                    action: request.as_ref().map(|r| r.action),
+                    params: request.map(|r| r.params).unwrap_or_default(),
+                    outcome: AuditOutcome::Denied,
+                    detail: denied.to_string(),
+                });
+            }
+            return (
+                denied.status(),
+                json!({"success": false, "error": denied.to_string()}),
+            );
+        }
+    };
+
+    tracing::info!(
+        "🛡️ Admin {} ({:?}) runs {:?}",
+        key.name,
+        key.role,
+        request.action
+    );
+    let result = execute(gctx, &admin, request.action, &request.params).await;
+    let (outcome, detail, response) = match result {
+        Ok(value) => (
+            AuditOutcome::Ok,
+            value.to_string(),
+            (200, json!({"success": true, "result": value})),
+        ),
+        Err(e) => (
+            AuditOutcome::Failed,
+            e.to_string(),
+            (500, json!({"success": false, "error": e.to_string()})),
+        ),
+    };
+    admin.record(&AuditEntry {
+        at: chrono::Utc::now().timestamp(),
+        admin: Some(key.name),
+        public_key: signed.public_key,
+        action: Some(request.action),
+        params: request.params,
+        outcome,
+        detail,
+    });
+    response
+}
+
+async fn execute(
+    gctx: &Arc<GlobalContext>,
+    admin: &AdminChannel,
+    action: AdminAction,
+    params: &Value,
+) -> anyhow::Result<Value> {
+    match action {
+        AdminAction::ReloadConfig => {
+            let Some(ConfigFile(path)) = gctx.get::<ConfigFile>().await else {
+                anyhow::bail!("Node was started without --config");
+            };
+            config::reload(&path, gctx).await?;
+            Ok(json!({"reloaded": path}))
+        }
+        AdminAction::BanPeer => {
+            let arg = params.get("target").and_then(|v| v.as_str()).unwrap_or("");
+            let target = AclTarget::parse(arg).map_err(|e| anyhow::anyhow!(e))?;
+            let added = acl::update(gctx, |acl| acl.ban(target.clone())).await;
+            let closed = connections::disconnect(gctx, arg).await;
+            Ok(json!({"target": target, "changed": added, "closed": closed.len()}))
+        }
+        AdminAction::RotateKeys => {
+            let peers: Vec<String> = match gctx.get::<SessionTable>().await {
+                Some(table) => table.iter().map(|e| e.key().clone()).collect(),
+                None => Vec::new(),
+            };
+            let total = peers.len();
+            let started = rekey::rotate(gctx, peers).await;
+            Ok(json!({"peers": total, "started": started}))
+        }
+        AdminAction::Shutdown => {
+            admin.shutdown.notify_one();
+            Ok(json!({"shutting_down": true}))
+        }
+        AdminAction::AuditLog => {
+            let limit = params
+                .get("limit")
+                .and_then(|v| v.as_u64())
+                .map(|n| n as usize)
+                .unwrap_or(DEFAULT_AUDIT_TAIL);
+            Ok(json!({"entries": admin.tail(limit)}))
+        }
+        AdminAction::Retention => {
+            if let Some(policy) = params.get("policy") {
+                let policy: RetentionConfig = serde_json::from_value(policy.clone())?;
+                let Some(config) = gctx.get::<SharedConfig>().await else {
+                    anyhow::bail!("Node has no shared config");
+                };
+                config.write().await.retention = policy;
+                tracing::info!("🔧 Retention policy replaced by admin command");
+            }
+            let pruned = match params.get("prune").and_then(|v| v.as_bool()) {
+                Some(true) => Some(retention::run_once(gctx).await?),
+                _ => None,
+            };
+            Ok(json!({
+                "policy": retention::policy(gctx).await,
+                "stats": retention::stats(gctx).await,
+                "pruned": pruned,
+            }))
+        }
+        AdminAction::RelayStats => {
+            let mut report = relay::report(gctx).await;
+            if let Some(limit) = params.get("limit").and_then(|v| v.as_u64()) {
+                report.clients.truncate(limit as usize);
+            }
+            Ok(serde_json::to_value(report)?)
+        }
+    }
+}
+
+/// `zzp2p admin` 子命令（离线生成密钥，或签名后发送到节点）
+pub async fn run(opt: &Opt, command: &AdminCommand) -> anyhow::Result<()> {
+    match command {
+        AdminCommand::Keygen { file } => {
+            let path = Path::new(file);
+            if path.exists() {
+                anyhow::bail!("{} already exists", path.display());
+            }
+            let key = FreeWebMovementAddress::random();
+            std::fs::write(path, serde_json::to_string_pretty(&key)?)?;
+            println!("Admin key written to {}", path.display());
+            println!();
+            println!("[[admin.keys]]");
+            println!("name = \"admin\"");
+            println!("public_key = \"{}\"", public_key_hex(&key));
+            println!("role = \"owner\"");
+            Ok(())
+        }
+        AdminCommand::Exec {
+            action,
+            params,
+            key,
+            node,
+            path,
+        } => {
+            let key: FreeWebMovementAddress = serde_json::from_str(&std::fs::read_to_string(key)?)?;
+            let action: AdminAction = serde_json::from_value(Value::String(action.clone()))
+                .map_err(|_| anyhow::anyhow!("Unknown admin action: {}", action))?;
+            let params = match params {
+                Some(p) => serde_json::from_str(p)?,
+                None => Value::Null,
+            };
+            let request = AdminRequest {
+                action,
+                params,
+                issued_at: now_secs(),
+                nonce: hex(&rand::random::<[u8; 16]>()),
+            };
+            let signed = SignedAdminRequest::sign(&key, &request)?;
+            let addr = match node {
+                Some(n) => n.parse()?,
+                None => opt.control_addr()?,
+            };
+            let (status, body) =
+                control::request(addr, "POST", path, Some(serde_json::to_value(signed)?)).await?;
+            println!("{}", serde_json::to_string_pretty(&body)?);
+            if status != 200 {
+                anyhow::bail!("node returned HTTP {}", status);
+            }
+            Ok(())
+        }
+    }
+}
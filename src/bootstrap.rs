@@ -0,0 +1,185 @@
+//! 引导节点
+//!
+//! 启动时的引导来源有三种：`--seeds`（或配置文件中的 `bootstrap`）、可重复的
+//! `--bootstrap addr:port`，以及 `--dns-seed` 给出的 DNS 种子域名（解析出的每个地址都是
+//! 一个候选节点）。解析结果合并进 `Node.external`，随后后台任务不断拨号，
+//! 直到已连接的节点数达到 `--min-peers`；每轮失败后按指数退避等待并重新解析 DNS。
+//! 第一轮拨号结束后节点的就绪信号（[`crate::readiness`]）才会触发。
+
+use std::{collections::HashSet, net::SocketAddr, sync::Arc, time::Duration};
+
+use aex::connection::global::GlobalContext;
+
+use crate::{cli::Opt, node::Node as P2pNode, protocols::commands::ack};
+
+/// 默认的目标连接数
+pub const DEFAULT_MIN_PEERS: usize = 3;
+/// DNS 种子未指定端口时使用的端口
+pub const DEFAULT_DNS_SEED_PORT: u16 = 1090;
+/// 保存在 GlobalContext 中的目标连接数（`--min-peers`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TargetPeers(pub usize);
+/// 保存在 GlobalContext 中的引导来源，网络分区后重新引导时使用（[`crate::partition`]）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BootstrapSources(pub Vec<BootstrapSource>);
+/// 第一次重试前的等待时间
+pub const BOOTSTRAP_RETRY_INITIAL_SECS: u64 = 5;
+/// 重试间隔上限
+pub const BOOTSTRAP_RETRY_MAX_SECS: u64 = 300;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BootstrapSource {
+    Addr(SocketAddr),
+    Dns { host: String, port: u16 },
+}
+
+impl BootstrapSource {
+    /// 解析 `ip:port`、`host:port` 或 `host`（使用 `default_port`）
+    pub fn parse(s: &str, default_port: u16) -> anyhow::Result<Self> {
+        let s = s.trim();
+        if s.is_empty() {
+            anyhow::bail!("empty bootstrap address");
+        }
+        if let Ok(addr) = s.parse::<SocketAddr>() {
+            return Ok(BootstrapSource::Addr(addr));
+        }
+        let (host, port) = match s.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse::<u16>()
+                    .map_err(|e| anyhow::anyhow!("invalid port in {}: {}", s, e))?,
+            ),
+            None => (s, default_port),
+        };
+        if host.is_empty() || host.contains(':') || host.contains('/') {
+            anyhow::bail!("invalid bootstrap host: {}", s);
+        }
+        Ok(BootstrapSource::Dns {
+            host: host.to_string(),
+            port,
+        })
+    }
+}
+
+/// 命令行中的全部引导来源（去重，无法解析的条目记录日志后忽略）
+pub fn sources(opt: &Opt) -> Vec<BootstrapSource> {
+    let seeds = opt
+        .seeds
+        .iter()
+        .flat_map(|s| s.split(','))
+        .chain(opt.bootstrap.iter().map(String::as_str))
+        .map(|s| (s, opt.port));
+    let dns = opt
+        .dns_seeds
+        .iter()
+        .map(|s| (s.as_str(), DEFAULT_DNS_SEED_PORT));
+
+    let mut seen = HashSet::new();
+    let mut out = Vec::new();
+    for (s, default_port) in seeds.chain(dns) {
+        if s.trim().is_empty() {
+            continue;
+        }
+        match BootstrapSource::parse(s, default_port) {
+            Ok(source) => {
+                if seen.insert(source.clone()) {
+                    out.push(source);
+                }
+            }
+            Err(e) => tracing::warn!("⚠️ Ignoring bootstrap entry {:?}: {}", s, e),
+        }
+    }
+    out
+}
+
+/// 解析全部来源；DNS 查询失败的来源被跳过
+pub async fn resolve(sources: &[BootstrapSource]) -> Vec<SocketAddr> {
+    let mut seen = HashSet::new();
+    let mut out = Vec::new();
+    for source in sources {
+        match source {
+            BootstrapSource::Addr(addr) => {
+                if seen.insert(*addr) {
+                    out.push(*addr);
+                }
+            }
+            BootstrapSource::Dns { host, port } => {
+                match tokio::net::lookup_host((host.as_str(), *port)).await {
+                    Ok(addrs) => {
+                        for addr in addrs {
+                            if seen.insert(addr) {
+                                out.push(addr);
+                            }
+                        }
+                    }
+                    Err(e) => tracing::warn!("⚠️ DNS seed {} lookup failed: {}", host, e),
+                }
+            }
+        }
+    }
+    out
+}
+
+/// 第 `attempt` 次（从 0 开始）失败后的等待时间
+pub fn retry_delay(attempt: u32) -> Duration {
+    let secs = BOOTSTRAP_RETRY_INITIAL_SECS.saturating_mul(1u64 << attempt.min(16));
+    Duration::from_secs(secs.min(BOOTSTRAP_RETRY_MAX_SECS))
+}
+
+async fn connected_peers(gctx: &Arc<GlobalContext>) -> usize {
+    match gctx.get::<Arc<P2pNode>>().await {
+        Some(node) => node.registry.get_connected_nodes().len(),
+        None => 0,
+    }
+}
+
+pub(crate) fn is_self(addr: &SocketAddr, local: &SocketAddr) -> bool {
+    addr.port() == local.port()
+        && (addr.ip() == local.ip() || addr.ip().is_loopback() || addr.ip().is_unspecified())
+}
+
+/// 后台拨号引导节点，直到已连接节点数达到 `min_peers`
+pub fn spawn(
+    gctx: Arc<GlobalContext>,
+    sources: Vec<BootstrapSource>,
+    min_peers: usize,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let readiness = crate::readiness::of(&gctx).await;
+        let mut attempt = 0u32;
+        loop {
+            let connected = connected_peers(&gctx).await;
+            if connected >= min_peers {
+                tracing::info!(
+                    "🌐 Bootstrap complete: {} peer(s) connected (min {})",
+                    connected,
+                    min_peers
+                );
+                readiness.mark_bootstrapped();
+                return;
+            }
+
+            for addr in resolve(&sources).await {
+                if is_self(&addr, &gctx.addr) || gctx.manager.find_entry(&addr).is_some() {
+                    continue;
+                }
+                match ack::dial_peer(gctx.clone(), addr).await {
+                    Ok(_) => tracing::info!("🌐 Bootstrap dialed {}", addr),
+                    Err(e) => tracing::warn!("⚠️ Bootstrap dial {} failed: {}", addr, e),
+                }
+            }
+            // 第一轮拨号结束即视为就绪，之后的重试在后台继续
+            readiness.mark_bootstrapped();
+
+            let delay = retry_delay(attempt);
+            attempt = attempt.saturating_add(1);
+            tracing::info!(
+                "🌐 Bootstrap: {} of {} peer(s) connected, checking again in {:?}",
+                connected_peers(&gctx).await,
+                min_peers,
+                delay
+            );
+            tokio::time::sleep(delay).await;
+        }
+    })
+}
@@ -0,0 +1,265 @@
+//! 线路抓包（`--capture <path>`）
+//!
+//! 启用后，每个收到与发出的帧以一行 JSON 追加到抓包文件：时间、方向、对端 socket 地址、
+//! 发送方与目的地址、命令（entity/action）、协议版本、线路格式、负载长度，以及编码后的
+//! 完整帧（base64）。`zzp2p inspect <file>` 逐行打印抓包，`--verify` 重新解码并校验每一帧的签名。
+//!
+//! 收到的帧在分发前记录（分片按 `Node/Fragment` 记录，重组后的帧再记录一次）；发出的帧在
+//! 分片前记录，中继转发的预编码帧同样记录。抓包包含完整负载，只应在排查协议问题时开启。
+
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader, Write},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use aex::{
+    connection::{context::Context, global::GlobalContext},
+    tcp::types::Codec,
+};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    log_file::open_append,
+    protocols::{frame::P2PFrame, wire_format::WireFormat},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureDirection {
+    In,
+    Out,
+}
+
+/// 抓包文件中的一行
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaptureRecord {
+    pub at: DateTime<Utc>,
+    pub direction: CaptureDirection,
+    /// 对端 socket 地址
+    pub peer: SocketAddr,
+    /// 帧的发送方地址
+    pub from: String,
+    pub destination: Option<String>,
+    /// `Entity/Action`，命令无法解码时为 `?`
+    pub command: String,
+    pub version: u8,
+    pub format: WireFormat,
+    pub ttl: u8,
+    /// 命令负载长度
+    pub payload_len: usize,
+    /// 编码后的完整帧长度
+    pub frame_len: usize,
+    /// 编码后的完整帧（base64）
+    pub raw: String,
+}
+
+impl CaptureRecord {
+    pub fn new(
+        direction: CaptureDirection,
+        peer: SocketAddr,
+        frame: &P2PFrame,
+        raw: &[u8],
+    ) -> Self {
+        let (command, payload_len) = match frame.body.command_as(frame.format) {
+            Ok(cmd) => (format!("{:?}/{:?}", cmd.entity, cmd.action), cmd.data.len()),
+            Err(_) => ("?".to_string(), frame.body.data.len()),
+        };
+        CaptureRecord {
+            at: Utc::now(),
+            direction,
+            peer,
+            from: frame.body.address.clone(),
+            destination: frame.body.destination.clone(),
+            command,
+            version: frame.body.version,
+            format: frame.format,
+            ttl: frame.ttl,
+            payload_len,
+            frame_len: raw.len(),
+            raw: base64::engine::general_purpose::STANDARD.encode(raw),
+        }
+    }
+
+    pub fn raw_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(base64::engine::general_purpose::STANDARD.decode(&self.raw)?)
+    }
+
+    /// 按命令名、对端地址或发送方地址过滤（不区分大小写的子串匹配）
+    pub fn matches(&self, filter: &str) -> bool {
+        let filter = filter.to_ascii_lowercase();
+        [&self.command, &self.from, &self.peer.to_string()]
+            .iter()
+            .any(|s| s.to_ascii_lowercase().contains(&filter))
+    }
+}
+
+impl std::fmt::Display for CaptureRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let arrow = match self.direction {
+            CaptureDirection::In => "←",
+            CaptureDirection::Out => "→",
+        };
+        write!(
+            f,
+            "{} {} {: <21} {: <24} v{} {:?} payload={}B frame={}B from={}",
+            self.at.format("%H:%M:%S%.3f"),
+            arrow,
+            self.peer,
+            self.command,
+            self.version,
+            self.format,
+            self.payload_len,
+            self.frame_len,
+            self.from
+        )?;
+        if let Some(dest) = &self.destination {
+            write!(f, " to={} ttl={}", dest, self.ttl)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Capture {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+pub type SharedCapture = Arc<Capture>;
+
+impl Capture {
+    /// 打开（或创建）抓包文件，新记录追加在末尾
+    pub fn open(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let file = open_append(&path)?;
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, record: &CaptureRecord) -> anyhow::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        let mut file = match self.file.lock() {
+            Ok(g) => g,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        file.write_all(&line)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    fn write(&self, record: CaptureRecord) {
+        if let Err(e) = self.append(&record) {
+            tracing::warn!("Failed to write capture {}: {:?}", self.path.display(), e);
+        }
+    }
+}
+
+/// 记录一个收到的帧
+pub async fn inbound(ctx: &Arc<tokio::sync::Mutex<Context>>, frame: &P2PFrame) {
+    let (gctx, peer) = {
+        let guard = ctx.lock().await;
+        (guard.global.clone(), guard.addr)
+    };
+    let Some(capture) = gctx.get::<SharedCapture>().await else {
+        return;
+    };
+    match Codec::encode(frame) {
+        Ok(raw) => capture.write(CaptureRecord::new(CaptureDirection::In, peer, frame, &raw)),
+        Err(e) => tracing::warn!("Failed to encode captured frame: {:?}", e),
+    }
+}
+
+/// 记录一个发出的帧，`raw` 为其编码
+pub async fn outbound(gctx: &Arc<GlobalContext>, peer: SocketAddr, frame: &P2PFrame, raw: &[u8]) {
+    if let Some(capture) = gctx.get::<SharedCapture>().await {
+        capture.write(CaptureRecord::new(CaptureDirection::Out, peer, frame, raw));
+    }
+}
+
+/// 记录一段发出的预编码帧（中继转发）
+pub async fn outbound_bytes(gctx: &Arc<GlobalContext>, peer: SocketAddr, raw: &[u8]) {
+    let Some(capture) = gctx.get::<SharedCapture>().await else {
+        return;
+    };
+    match Codec::decode(raw) {
+        Ok(frame) => capture.write(CaptureRecord::new(CaptureDirection::Out, peer, &frame, raw)),
+        Err(e) => tracing::warn!("Failed to decode forwarded frame for capture: {:?}", e),
+    }
+}
+
+/// 读取抓包文件，返回记录与无法解析的行数
+pub fn read(path: &Path) -> anyhow::Result<(Vec<CaptureRecord>, usize)> {
+    let file = File::open(path)?;
+    let mut records = Vec::new();
+    let mut corrupt = 0;
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<CaptureRecord>(&line) {
+            Ok(record) => records.push(record),
+            Err(_) => corrupt += 1,
+        }
+    }
+    Ok((records, corrupt))
+}
+
+/// 重新解码记录中的帧并校验签名
+pub fn verify(record: &CaptureRecord) -> anyhow::Result<P2PFrame> {
+    P2PFrame::verify_bytes(&record.raw_bytes()?)
+}
+
+/// `zzp2p inspect`：逐行打印抓包
+pub fn inspect(path: &Path, verify_frames: bool, filter: Option<&str>) -> anyhow::Result<()> {
+    let (records, corrupt) = read(path)?;
+    let mut shown = 0;
+    let mut failed = 0;
+    for record in records
+        .iter()
+        .filter(|r| filter.is_none_or(|f| r.matches(f)))
+    {
+        shown += 1;
+        if !verify_frames {
+            println!("{}", record);
+            continue;
+        }
+        match verify(record) {
+            Ok(_) => println!("{} ✓", record),
+            Err(e) => {
+                failed += 1;
+                println!("{} ✗ {}", record, e);
+            }
+        }
+    }
+    println!(
+        "{} frame(s) shown of {}{}{}",
+        shown,
+        records.len(),
+        if verify_frames {
+            format!(", {} failed verification", failed)
+        } else {
+            String::new()
+        },
+        if corrupt > 0 {
+            format!(", {} unreadable line(s) skipped", corrupt)
+        } else {
+            String::new()
+        }
+    );
+    Ok(())
+}
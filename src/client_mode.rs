@@ -0,0 +1,123 @@
+//! 仅出站（客户端）模式
+//!
+//! 严格防火墙后的节点无法接受入站连接。以 `--client-only`（或配置文件 `client_only = true`）
+//! 启动时：
+//!
+//! - 不绑定任何监听端口：P2P server、`--listen` 的额外地址、通话媒体的 UDP 端口与端口映射都不
+//!   启动（守护进程仍在回环地址上提供控制接口）；
+//! - Online 公告与在线状态记录不携带本机地址，对端不会尝试回拨；
+//! - 拨号时不再让地址较大的一方等待对端发起；
+//! - 后台任务每 [`CLIENT_REDIAL_INTERVAL_SECS`] 秒检查一次，已连接节点少于 `min_peers`（至少 1）
+//!   时，按固定的优先、评分从高到低拨号服务器列表中尚未连接的记录。
+//!
+//! 发送、接收、事件与各接口都经由这些出站连接完成：主动拨号建立的连接与入站连接一样处理收到的帧。
+
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use aex::connection::global::GlobalContext;
+
+use crate::{
+    bootstrap::{self, TargetPeers},
+    node::Node,
+    protocols::commands::ack,
+    record::NodeRecord,
+};
+
+/// 检查连接数并补充拨号的间隔
+pub const CLIENT_REDIAL_INTERVAL_SECS: u64 = 30;
+
+/// 保存在 GlobalContext 中，表示本节点运行在仅出站模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientOnly;
+
+pub async fn is_enabled(gctx: &GlobalContext) -> bool {
+    gctx.get::<ClientOnly>().await.is_some()
+}
+
+/// 拨号顺序：固定的记录在前，其余按评分从高到低；重复的地址只保留一次
+pub fn candidates(records: Vec<NodeRecord>) -> Vec<SocketAddr> {
+    let mut records = records;
+    records.sort_by(|a, b| {
+        b.pinned
+            .cmp(&a.pinned)
+            .then(b.score().total_cmp(&a.score()))
+            .then(a.endpoint.cmp(&b.endpoint))
+    });
+    let mut addrs: Vec<SocketAddr> = Vec::new();
+    for record in records {
+        if !addrs.contains(&record.endpoint) {
+            addrs.push(record.endpoint);
+        }
+    }
+    addrs
+}
+
+/// 补充拨号一轮，直到已连接节点数达到目标；返回成功拨号的数量
+pub async fn redial_once(gctx: &Arc<GlobalContext>) -> usize {
+    let Some(node) = gctx.get::<Arc<Node>>().await else {
+        return 0;
+    };
+    let target = gctx
+        .get::<TargetPeers>()
+        .await
+        .map(|t| t.0)
+        .unwrap_or(bootstrap::DEFAULT_MIN_PEERS)
+        .max(1);
+    let mut connected = node.registry.get_connected_nodes().len();
+    if connected >= target {
+        return 0;
+    }
+
+    let records: Vec<NodeRecord> = [&node.inner, &node.external]
+        .into_iter()
+        .flat_map(|registry| {
+            registry
+                .read()
+                .get_available_nodes()
+                .into_iter()
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    let mut dialed = 0;
+    for addr in candidates(records) {
+        if connected >= target {
+            break;
+        }
+        if bootstrap::is_self(&addr, &gctx.addr) || gctx.manager.find_entry(&addr).is_some() {
+            continue;
+        }
+        let ok = match ack::dial_peer(gctx.clone(), addr).await {
+            Ok(_) => true,
+            Err(e) => {
+                tracing::warn!("⚠️ Client-only dial {} failed: {}", addr, e);
+                false
+            }
+        };
+        for registry in [&node.inner, &node.external] {
+            registry.write().record_probe(addr, ok);
+        }
+        if ok {
+            dialed += 1;
+            connected += 1;
+        }
+    }
+    if let Err(e) = node.save_registries().await {
+        tracing::warn!("Failed to save peer lists: {}", e);
+    }
+    dialed
+}
+
+/// 后台维持出站连接
+pub fn spawn(gctx: Arc<GlobalContext>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(CLIENT_REDIAL_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let dialed = redial_once(&gctx).await;
+            if dialed > 0 {
+                tracing::info!("🔌 Client-only: dialed {} server(s)", dialed);
+            }
+        }
+    })
+}
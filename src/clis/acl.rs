@@ -0,0 +1,104 @@
+use aex::connection::global::GlobalContext;
+use std::sync::Arc;
+
+use crate::{cli_error, cli_println};
+use crate::{
+    node::Node as P2pNode,
+    protocols::acl::{self, AclMode, AclTarget},
+};
+
+/// 规则参数：别名解析为节点地址，其余按 IP / 网段 / 地址解析
+async fn target(arg: &str, context: &Arc<GlobalContext>) -> Option<AclTarget> {
+    let arg = match context.get::<Arc<P2pNode>>().await {
+        Some(node) => node.registry.resolve_alias(arg),
+        None => arg.to_string(),
+    };
+    match AclTarget::parse(&arg) {
+        Ok(t) => Some(t),
+        Err(e) => {
+            cli_error!("{}", e);
+            None
+        }
+    }
+}
+
+pub async fn ban(args: Vec<String>, context: Arc<GlobalContext>) {
+    let Some(arg) = args.first() else {
+        cli_error!("Usage: ban <address|alias|ip|cidr>");
+        return;
+    };
+    let Some(t) = target(arg, &context).await else {
+        return;
+    };
+    let added = acl::update(&context, |acl| acl.ban(t.clone())).await;
+    if added {
+        cli_println!("Banned {}", t);
+    } else {
+        cli_println!("{} is already banned", t);
+    }
+}
+
+pub async fn unban(args: Vec<String>, context: Arc<GlobalContext>) {
+    let Some(arg) = args.first() else {
+        cli_error!("Usage: unban <address|alias|ip|cidr>");
+        return;
+    };
+    let Some(t) = target(arg, &context).await else {
+        return;
+    };
+    if acl::update(&context, |acl| acl.unban(&t)).await {
+        cli_println!("Unbanned {}", t);
+    } else {
+        cli_println!("{} is not banned", t);
+    }
+}
+
+pub async fn allow(args: Vec<String>, context: Arc<GlobalContext>) {
+    let Some(arg) = args.first() else {
+        cli_error!("Usage: allow <address|alias|ip|cidr>");
+        return;
+    };
+    let Some(t) = target(arg, &context).await else {
+        return;
+    };
+    if acl::update(&context, |acl| acl.allow(t.clone())).await {
+        cli_println!("Allowed {}", t);
+    } else {
+        cli_println!("{} is already allowed", t);
+    }
+}
+
+pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
+    match args.first().map(|s| s.as_str()) {
+        Some("ls") | None => {
+            let list = acl::snapshot(&context).await;
+            cli_println!("Mode: {:?}", list.mode);
+            cli_println!("Banned ({}):", list.banned.len());
+            for t in &list.banned {
+                cli_println!("  {}", t);
+            }
+            cli_println!("Allowed ({}):", list.allowed.len());
+            for t in &list.allowed {
+                cli_println!("  {}", t);
+            }
+        }
+        Some("mode") if args.len() >= 2 => match args[1].parse::<AclMode>() {
+            Ok(mode) => {
+                acl::update(&context, |acl| acl.mode = mode).await;
+                cli_println!("ACL mode: {:?}", mode);
+            }
+            Err(e) => cli_error!("{}", e),
+        },
+        Some("disallow") if args.len() >= 2 => {
+            let Some(t) = target(&args[1], &context).await else {
+                return;
+            };
+            if acl::update(&context, |acl| acl.disallow(&t)).await {
+                cli_println!("Removed {} from allowlist", t);
+            } else {
+                cli_println!("{} is not in allowlist", t);
+            }
+        }
+        _ => cli_error!("Usage: acl ls | acl mode <blacklist|allowlist> | acl disallow <target>"),
+    }
+}
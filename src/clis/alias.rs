@@ -0,0 +1,70 @@
+use aex::connection::global::GlobalContext;
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::{cli_error, cli_println};
+use crate::{
+    io_storage::{IOStorage, STORAGE_ALIASES},
+    node::Node as P2pNode,
+    safety_number::{self, VerificationStatus},
+};
+
+pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
+    let node = match context.get::<Arc<P2pNode>>().await {
+        Some(n) => n,
+        None => {
+            cli_error!("Node not initialized");
+            return;
+        }
+    };
+
+    match args.first().map(|s| s.as_str()) {
+        Some("add") if args.len() >= 3 => {
+            if let Err(e) = node.registry.add_alias(&args[1], &args[2]) {
+                cli_error!("alias add failed: {}", e);
+                return;
+            }
+            save(&context, &node).await;
+            cli_println!("{} -> {}", args[1], args[2]);
+        }
+        Some("rm") if args.len() >= 2 => match node.registry.remove_alias(&args[1]) {
+            Some(address) => {
+                save(&context, &node).await;
+                cli_println!("Removed {} ({})", args[1], address);
+            }
+            None => cli_error!("No such alias: {}", args[1]),
+        },
+        Some("ls") => {
+            let aliases = node.registry.aliases();
+            if aliases.is_empty() {
+                cli_println!("(no aliases)");
+                return;
+            }
+            for (name, address) in aliases {
+                let online = if node.registry.is_connected(&address) {
+                    "online"
+                } else {
+                    "offline"
+                };
+                match safety_number::status(&context, &address).await {
+                    VerificationStatus::Unverified => {
+                        cli_println!("  {:<16} {} [{}]", name, address, online)
+                    }
+                    status => cli_println!("  {:<16} {} [{}] [{}]", name, address, online, status),
+                }
+            }
+        }
+        _ => {
+            cli_error!("Usage: alias add <name> <address> | alias rm <name> | alias ls");
+        }
+    }
+}
+
+async fn save(context: &Arc<GlobalContext>, node: &P2pNode) {
+    match context.get::<IOStorage>().await {
+        Some(ios) => {
+            ios.save::<BTreeMap<String, String>>(&node.registry.aliases(), STORAGE_ALIASES)
+                .await
+        }
+        None => tracing::error!("IOStorage not found in context, aliases not persisted"),
+    }
+}
@@ -0,0 +1,64 @@
+use aex::connection::global::GlobalContext;
+use std::sync::Arc;
+
+use crate::node::Node as P2pNode;
+use crate::protocols::commands::telephone::{self, CallDirection, CallEvent, EndReason};
+use crate::{cli_error, cli_println};
+
+pub async fn call(args: Vec<String>, context: Arc<GlobalContext>) {
+    if args.is_empty() {
+        cli_error!("Usage: call <address|alias>");
+        return;
+    }
+    let peer = match context.get::<Arc<P2pNode>>().await {
+        Some(node) => node.registry.resolve_alias(&args[0]),
+        None => args[0].clone(),
+    };
+    match telephone::call(context, &peer).await {
+        Ok(id) => cli_println!("Calling {} (call id {})...", peer, id),
+        Err(e) => cli_error!("Failed to call {}: {}", peer, e),
+    }
+}
+
+pub async fn accept(_args: Vec<String>, context: Arc<GlobalContext>) {
+    match telephone::accept(context).await {
+        Ok(call) => cli_println!("Accepted call from {}", call.peer),
+        Err(e) => cli_error!("Failed to accept: {}", e),
+    }
+}
+
+pub async fn hangup(_args: Vec<String>, context: Arc<GlobalContext>) {
+    match telephone::hang_up(context).await {
+        Ok(call) => cli_println!("Hung up call with {}", call.peer),
+        Err(e) => cli_error!("Failed to hang up: {}", e),
+    }
+}
+
+/// 把通话事件打印到 REPL
+pub fn print_event(event: &CallEvent) {
+    match event {
+        CallEvent::Ringing(call) => match call.direction {
+            CallDirection::Incoming => cli_println!(
+                "📞 Incoming call from {} - type 'accept' to answer or 'hangup' to reject",
+                call.peer
+            ),
+            CallDirection::Outgoing => cli_println!("📞 Ringing {}...", call.peer),
+        },
+        CallEvent::Accepted(call) => cli_println!("📞 Call with {} connected", call.peer),
+        CallEvent::Ended {
+            call,
+            reason,
+            by_peer,
+        } => {
+            let why = match (reason, by_peer) {
+                (EndReason::HungUp, true) => "peer hung up",
+                (EndReason::HungUp, false) => "hung up",
+                (EndReason::Rejected, true) => "rejected by peer",
+                (EndReason::Rejected, false) => "rejected",
+                (EndReason::Busy, _) => "peer is busy",
+                (EndReason::Timeout, _) => "no answer",
+            };
+            cli_println!("📞 Call with {} ended ({})", call.peer, why);
+        }
+    }
+}
@@ -1,122 +1,137 @@
-use aex::connection::{global::GlobalContext, scope::NetworkScope};
+use aex::connection::global::GlobalContext;
 use std::{net::SocketAddr, sync::Arc};
+use tokio::net::TcpStream;
 
+use crate::dialer;
+use crate::ip_scope;
 use crate::node::Node as P2pNode;
-use crate::protocols::commands::ack::{SeedRecord, SeedsCommand};
 use crate::protocols::{
     command::{Action, Entity, P2PCommand},
     commands::online::OnlineCommand,
     frame::P2PFrame,
+    privacy,
 };
+use crate::proxy::{self, ProxyEndpoint};
+use crate::resolver;
+use crate::{cli_error, cli_println};
 
 pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
     if args.len() < 2 {
-        println!("Usage: connect <ip> <port>");
+        cli_error!("Usage: connect <host> <port>");
         return;
     }
-    let addr_str = format!("{}:{}", args[0], args[1]);
-    match addr_str.parse::<SocketAddr>() {
-        Ok(addr) => {
-            let manager = context.manager.clone();
-            let global = context.clone();
+    let port = match args[1].parse::<u16>() {
+        Ok(port) => port,
+        Err(_) => {
+            cli_error!("Invalid port: {}", args[1]);
+            return;
+        }
+    };
+    let addrs = match resolver::resolve(&context, &args[0], port).await {
+        Ok(addrs) => addrs,
+        Err(e) => {
+            cli_error!("Failed to resolve {}: {}", args[0], e);
+            return;
+        }
+    };
+    if addrs.len() > 1 {
+        cli_println!("Resolved {} to {} address(es)", args[0], addrs.len());
+    }
+    match pick(&context, &addrs).await {
+        Ok((addr, dialed)) => dial(context, addr, dialed).await,
+        Err(e) => cli_error!("Failed to connect: {:?}", e),
+    }
+}
+
+/// 竞速拨号胜出的连接及其经由的代理
+type Dialed = (TcpStream, Option<ProxyEndpoint>);
 
-            // Register peer in NodeRegistry
-            if let Some(node) = global.get::<Arc<P2pNode>>().await {
-                let self_node_id = global.local_node.read().await.id.clone();
-                let self_address = String::from_utf8(self_node_id).unwrap_or_default();
-                let scope = NetworkScope::from_ip(&addr.ip());
-                node.registry.register(self_address, addr, scope);
-            }
+/// 只有一个地址时直接使用，多个地址时竞速拨号选出最先可达的地址，并保留胜出的连接
+async fn pick(
+    context: &Arc<GlobalContext>,
+    addrs: &[SocketAddr],
+) -> anyhow::Result<(SocketAddr, Option<Dialed>)> {
+    let record = resolver::to_record(addrs).ok_or_else(|| anyhow::anyhow!("No address"))?;
+    if record.alt_endpoints.is_empty() {
+        return Ok((record.endpoint, None));
+    }
+    let via = proxy::for_peer(context, record.endpoint).await?;
+    let winner = dialer::dial(context, &record, via.as_ref()).await?;
+    Ok((winner.attempt.endpoint, Some((winner.stream, via))))
+}
+
+/// `dialed` 为竞速拨号胜出的连接时直接接管，否则拨号 `addr`
+async fn dial(context: Arc<GlobalContext>, addr: SocketAddr, dialed: Option<Dialed>) {
+    let global = context.clone();
+
+    // Register peer in NodeRegistry
+    if let Some(node) = global.get::<Arc<P2pNode>>().await {
+        let self_node_id = global.local_node.read().await.id.clone();
+        let self_address = String::from_utf8(self_node_id).unwrap_or_default();
+        let scope = ip_scope::classify(&addr.ip());
+        node.registry.register(self_address, addr, scope);
+    }
 
-            match manager
-                .connect::<P2PFrame, P2PCommand, _, _>(
-                    addr,
-                    global.clone(),
-                    move |ctx| {
-                        let peer = addr;
-                        let ctx_for_seeds = ctx.clone();
-                        Box::pin(async move {
-                            println!("Connected to {}!", peer);
+    let on_connected = move |ctx: Arc<tokio::sync::Mutex<aex::connection::context::Context>>| {
+        let peer = addr;
+        Box::pin(async move {
+            cli_println!("Connected to {}!", peer);
 
-                            let psk = {
-                                let guard = ctx.lock().await;
-                                let g = guard.global.clone();
-                                g.paired_session_keys.clone().unwrap()
-                            };
+            let psk = {
+                let guard = ctx.lock().await;
+                let g = guard.global.clone();
+                g.paired_session_keys.clone().unwrap()
+            };
 
-                            let (id, key) = {
-                                let cloned = psk.clone();
-                                let guard = cloned.lock().await;
-                                guard.create(false).await
-                            };
+            let (id, key) = {
+                let cloned = psk.clone();
+                let guard = cloned.lock().await;
+                guard.create(false).await
+            };
 
-                            let aex_node = {
-                                let guard = ctx.lock().await;
-                                guard.global.local_node.read().await.clone()
-                            };
-                            let (intranet_ips, wan_ips) = {
-                                let mut inner = Vec::new();
-                                let mut outer = Vec::new();
-                                for (scope, ip) in &aex_node.ips {
-                                    match scope {
-                                        aex::connection::scope::NetworkScope::Intranet => {
-                                            inner.push(ip.to_string())
-                                        }
-                                        aex::connection::scope::NetworkScope::Extranet => {
-                                            outer.push(ip.to_string())
-                                        }
-                                    }
-                                }
-                                (inner, outer)
-                            };
+            let aex_node = {
+                let guard = ctx.lock().await;
+                guard.global.local_node.read().await.clone()
+            };
+            let gctx = ctx.lock().await.global.clone();
+            let (intranet_ips, wan_ips) =
+                privacy::announced_ips(&gctx, &aex_node.ips, &peer.ip()).await;
 
-                            // Build seeds from NodeRegistry
-                            let seeds_to_send = {
-                                let guard = ctx_for_seeds.lock().await;
-                                let seeds =
-                                    if let Some(node) = guard.global.get::<Arc<P2pNode>>().await {
-                                        let all_seeds: Vec<SeedRecord> = node
-                                            .registry
-                                            .get_all_seeds()
-                                            .into_iter()
-                                            .map(|(s, na)| SeedRecord::new(s.to_string(), na))
-                                            .collect();
-                                        SeedsCommand::new(all_seeds)
-                                    } else {
-                                        SeedsCommand::new(vec![])
-                                    };
-                                drop(guard);
-                                seeds
-                            };
+            // Build seeds from NodeRegistry
+            let seeds_to_send = privacy::gossip_seeds(&gctx, Some(&peer.ip())).await;
 
-                            let cmd = OnlineCommand {
-                                session_id: id,
-                                node: aex_node,
-                                ephemeral_public_key: key.to_bytes(),
-                                intranet_ips,
-                                wan_ips,
-                                seeds: Some(seeds_to_send),
-                            };
-                            P2PFrame::send::<OnlineCommand>(
-                                ctx.clone(),
-                                &Some(cmd),
-                                Entity::Node,
-                                Action::OnLine,
-                                false,
-                            )
-                            .await
-                            .expect("Online Command Sending Failed!");
-                            println!("message send!");
-                        })
-                    },
-                    Some(10),
-                )
-                .await
-            {
-                Ok(_) => println!("Connection attempt started..."),
-                Err(e) => println!("Failed to connect: {:?}", e),
-            }
+            let cmd = OnlineCommand {
+                session_id: id,
+                node: aex_node,
+                ephemeral_public_key: key.to_bytes(),
+                intranet_ips,
+                wan_ips,
+                seeds: Some(seeds_to_send),
+                capabilities: crate::protocols::compression::LOCAL_CAPABILITIES,
+                protocol_version: crate::protocols::version::CURRENT_PROTOCOL_VERSION,
+                max_frame_size: crate::protocols::capabilities::LOCAL_MAX_FRAME_SIZE,
+                agent: crate::protocols::agent::local(&gctx).await,
+            };
+            P2PFrame::send::<OnlineCommand>(
+                ctx.clone(),
+                &Some(cmd),
+                Entity::Node,
+                Action::OnLine,
+                false,
+            )
+            .await
+            .expect("Online Command Sending Failed!");
+            cli_println!("message send!");
+        })
+    };
+    let connected = match dialed {
+        Some((stream, via)) => {
+            proxy::adopt_peer(global.clone(), addr, stream, via, on_connected).await
         }
-        Err(_) => println!("Invalid address: {}", addr_str),
+        None => proxy::connect_peer(global.clone(), addr, on_connected).await,
+    };
+    match connected {
+        Ok(_) => cli_println!("Connection attempt started..."),
+        Err(e) => cli_error!("Failed to connect: {:?}", e),
     }
 }
@@ -0,0 +1,52 @@
+use aex::connection::global::GlobalContext;
+use std::sync::Arc;
+
+use crate::{cli_error, cli_println};
+use crate::{connections, script};
+
+/// `conns`：逐条列出当前连接
+pub async fn handle(_args: Vec<String>, context: Arc<GlobalContext>) {
+    let conns = connections::list(&context).await;
+    script::attach(&conns);
+    cli_println!("=== Connections ({}) ===", conns.len());
+    for c in conns {
+        cli_println!(
+            " {: <22} {: <8} {: <12} in={}B out={}B up={}s rtt={} peer={} features={} agent={} cipher={}",
+            c.addr,
+            format!("{:?}", c.direction),
+            c.transport.to_string(),
+            c.bytes_in,
+            c.bytes_out,
+            c.uptime_secs,
+            c.rtt_ms
+                .map(|ms| format!("{}ms", ms))
+                .unwrap_or_else(|| "-".to_string()),
+            c.peer.as_deref().unwrap_or("-"),
+            if c.features.is_empty() {
+                "-".to_string()
+            } else {
+                c.features.join(",")
+            },
+            c.agent
+                .map(|a| a.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            c.cipher_suite.map_or("-", |s| s.name()),
+        );
+    }
+}
+
+/// `disconnect <ip:port|ip|address|alias>`：关闭匹配的连接
+pub async fn disconnect(args: Vec<String>, context: Arc<GlobalContext>) {
+    let Some(target) = args.first() else {
+        cli_error!("Usage: disconnect <ip:port|ip|address|alias>");
+        return;
+    };
+    let closed = connections::disconnect(&context, target).await;
+    if closed.is_empty() {
+        cli_error!("No connection matches {}", target);
+        return;
+    }
+    for c in closed {
+        cli_println!("Closed {:?} connection {}", c.direction, c.addr);
+    }
+}
@@ -0,0 +1,14 @@
+use aex::connection::global::GlobalContext;
+use std::sync::Arc;
+
+use crate::cli_println;
+use crate::doctor;
+
+pub async fn handle(_args: Vec<String>, context: Arc<GlobalContext>) {
+    let report = doctor::run(&context).await;
+    for check in &report.checks {
+        cli_println!("[{:<4}] {:<16} {}", check.status, check.name, check.detail);
+    }
+    cli_println!("NAT type: {:?}", report.nat);
+    cli_println!("Overall: {}", report.status);
+}
@@ -0,0 +1,35 @@
+use aex::connection::global::GlobalContext;
+use std::sync::Arc;
+
+use crate::journal::{DEFAULT_EVENTS_SHOWN, SharedJournal};
+use crate::{cli_error, cli_println};
+
+/// `events [n] [category]`：显示最近的生命周期事件，可按事件名或类别过滤
+pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
+    let journal = match context.get::<SharedJournal>().await {
+        Some(j) => j,
+        None => {
+            cli_error!("Event journal not enabled");
+            return;
+        }
+    };
+
+    let mut count = DEFAULT_EVENTS_SHOWN;
+    let mut filter = None;
+    for arg in &args {
+        match arg.parse::<usize>() {
+            Ok(n) => count = n,
+            Err(_) => filter = Some(arg.as_str()),
+        }
+    }
+
+    match journal.tail(count, filter) {
+        Ok(entries) if entries.is_empty() => cli_println!("(no events)"),
+        Ok(entries) => {
+            for entry in entries {
+                cli_println!(" {}", entry);
+            }
+        }
+        Err(e) => cli_error!("Failed to read {}: {}", journal.path().display(), e),
+    }
+}
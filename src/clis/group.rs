@@ -0,0 +1,81 @@
+use aex::connection::global::GlobalContext;
+use std::sync::Arc;
+
+use crate::protocols::commands::group;
+use crate::{cli_error, cli_println};
+
+pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
+    match args.first().map(|s| s.as_str()) {
+        None | Some("ls") => {
+            let (groups, invites) = group::list(&context).await;
+            if groups.is_empty() && invites.is_empty() {
+                cli_println!("(no groups)");
+                return;
+            }
+            for state in groups {
+                cli_println!(
+                    "  {:<16} {} v{} ({} member(s), owner {})",
+                    state.name,
+                    state.group_id,
+                    state.version,
+                    state.members.len(),
+                    state.owner
+                );
+                for member in &state.members {
+                    cli_println!("    - {}", member.address);
+                }
+            }
+            for invite in invites {
+                cli_println!(
+                    "  invite: {} {} from {} (group join {})",
+                    invite.name,
+                    invite.group_id,
+                    invite.sender,
+                    invite.group_id
+                );
+            }
+        }
+        Some("create") if args.len() >= 2 => {
+            match group::create(&context, &args[1..].join(" ")).await {
+                Ok(state) => cli_println!("Created group {} ({})", state.name, state.group_id),
+                Err(e) => cli_error!("group create failed: {}", e),
+            }
+        }
+        Some("invite") if args.len() >= 3 => {
+            match group::invite(&context, &args[1], &args[2]).await {
+                Ok(()) => cli_println!("Invited {} to {}", args[2], args[1]),
+                Err(e) => cli_error!("group invite failed: {}", e),
+            }
+        }
+        Some("join") if args.len() >= 2 => match group::join(&context, &args[1]).await {
+            Ok(()) => cli_println!("Join request sent for group {}", args[1]),
+            Err(e) => cli_error!("group join failed: {}", e),
+        },
+        Some("remove") if args.len() >= 3 => {
+            match group::remove(&context, &args[1], &args[2]).await {
+                Ok(()) => cli_println!("Removed {} from {}", args[2], args[1]),
+                Err(e) => cli_error!("group remove failed: {}", e),
+            }
+        }
+        Some("leave") if args.len() >= 2 => match group::leave(&context, &args[1]).await {
+            Ok(()) => cli_println!("Left group {}", args[1]),
+            Err(e) => cli_error!("group leave failed: {}", e),
+        },
+        _ => {
+            cli_println!(
+                "Usage: group ls | group create <name> | group invite <group> <address> | group join <group_id> | group remove <group> <address> | group leave <group>"
+            );
+        }
+    }
+}
+
+pub async fn sendgroup(args: Vec<String>, context: Arc<GlobalContext>) {
+    if args.len() < 2 {
+        cli_error!("Usage: sendgroup <group> <message>");
+        return;
+    }
+    match group::send(&context, &args[0], &args[1..].join(" ")).await {
+        Ok(delivered) => cli_println!("Sent to {} member(s)", delivered),
+        Err(e) => cli_error!("sendgroup failed: {}", e),
+    }
+}
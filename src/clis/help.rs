@@ -1,10 +1,86 @@
 use aex::connection::global::GlobalContext;
 use std::sync::Arc;
 
+use crate::cli_println;
+
 pub async fn handle(_args: Vec<String>, _context: Arc<GlobalContext>) {
-    println!("Commands:");
-    println!(" send <address> <message>   - send text message");
-    println!(" connect <ip> <port>        - connect to a new node");
-    println!(" status                     - show node status");
-    println!(" exit                       - exit program");
+    cli_println!("Commands:");
+    cli_println!(
+        " send <address|alias> <msg> - send text message (queued in the outbox if unreachable)"
+    );
+    cli_println!(" send --e2e <address> <msg> - encrypt end-to-end to the receiver's key");
+    cli_println!(" send --sealed <address> <msg> - same, and hide the sender from relays");
+    cli_println!(" outbox [ls]                - list queued messages waiting for a route");
+    cli_println!(" outbox rm <id>             - discard a queued message");
+    cli_println!(" outbox flush               - try to send queued messages now");
+    cli_println!(" sendbin <address> <path>   - send a file as binary message");
+    cli_println!(" sendfile <address> <path>  - stream a large file (saved to downloads/)");
+    cli_println!(" sendtext <address> <path>  - stream a long text or log file as a text message");
+    cli_println!(" connect <host> <port>      - connect to a new node (IP or hostname)");
+    cli_println!(" status                     - show node status");
+    cli_println!(
+        " doctor                     - run self-checks (listeners, NAT, peers, storage, clock)"
+    );
+    cli_println!(" conns                      - list open connections with traffic and RTT");
+    cli_println!(" disconnect <ip:port|ip|address|alias> - close matching connections");
+    cli_println!(" peers                      - list known peers with score and latency");
+    cli_println!(" peer ls                    - list the persisted server list (pinned marked)");
+    cli_println!(" peer add <ip:port> [--pin] - add a server record, save it and dial it now");
+    cli_println!(" peer rm <ip:port>          - remove a server record");
+    cli_println!(" peer pin|unpin <ip:port>   - keep a record from being pruned, always dial it");
+    cli_println!(" ping <ip:port|address|alias> - measure round-trip time to a peer");
+    cli_println!(" alias add <name> <address> - save a human-readable alias");
+    cli_println!(" alias rm <name>            - remove an alias");
+    cli_println!(" alias ls                   - list aliases");
+    cli_println!(" verify <address|alias>     - show the safety number to compare with a peer");
+    cli_println!(
+        " verify <address> confirm [number] - mark as verified (compares the number if given)"
+    );
+    cli_println!(" verify <address> clear     - remove the verified mark");
+    cli_println!(" verify ls                  - list verified peers");
+    cli_println!(" identity [ls]              - list local identities (* = in use)");
+    cli_println!(" identity new <name>        - generate an extra identity");
+    cli_println!(" identity use <name>        - send as this identity from now on");
+    cli_println!(" identity rm <name>         - delete an extra identity");
+    cli_println!(
+        " identity rotate [grace_secs] - switch to a new node identity, old one kept for grace"
+    );
+    cli_println!(" identity send <name> <address> <msg> - send text as a specific identity");
+    cli_println!(" name publish <name> [ttl]  - publish a signed name for this node");
+    cli_println!(" name ls                    - list known names");
+    cli_println!(" resolve <name>             - resolve a name to a node address");
+    cli_println!(
+        " presence [address]         - show cached presence records or one node's reachability"
+    );
+    cli_println!(" group [ls]                 - list groups and pending invites");
+    cli_println!(" group create <name>        - create an encrypted group");
+    cli_println!(" group invite <group> <address> - invite a node (owner only)");
+    cli_println!(" group join <group_id>      - accept an invite");
+    cli_println!(" group remove <group> <address> - remove a member (owner only)");
+    cli_println!(" group leave <group>        - leave a group (the owner disbands it)");
+    cli_println!(" sendgroup <group> <msg>    - send an end-to-end encrypted group message");
+    cli_println!(" sub <topic>                - subscribe to a topic");
+    cli_println!(" unsub <topic>              - unsubscribe from a topic");
+    cli_println!(" pub <topic> <message>      - publish a message to a topic");
+    cli_println!(" call <address|alias>       - start a call");
+    cli_println!(" accept                     - answer the incoming call");
+    cli_println!(" hangup                     - end or reject the current call");
+    cli_println!(" ban <address|ip|cidr>      - block a peer or IP range");
+    cli_println!(" unban <address|ip|cidr>    - remove a block");
+    cli_println!(" allow <address|ip|cidr>    - add a peer or IP range to the allowlist");
+    cli_println!(" acl ls                     - show access control rules");
+    cli_println!(" acl mode <blacklist|allowlist> - switch access control mode");
+    cli_println!(" acl disallow <target>      - remove an allowlist entry");
+    cli_println!(" webuser [ls]               - list web users and their tokens");
+    cli_println!(" webuser add <name> <role> [password] - add a web user (viewer, user or admin)");
+    cli_println!(" webuser rm <name>          - remove a web user");
+    cli_println!(" webuser passwd <name> [password] - set or clear the basic auth password");
+    cli_println!(" webuser token <name> [label] - issue an API token (shown once)");
+    cli_println!(" webuser revoke <name> <label> - revoke an API token");
+    cli_println!(
+        " events [n] [category]      - show recent lifecycle events (e.g. events 50 peer)"
+    );
+    cli_println!(" exit                       - exit program");
+    cli_println!("Quote arguments with spaces: send bob \"see you at 5\"");
+    cli_println!("Tab completes commands, addresses and aliases; history is kept in history.txt");
 }
@@ -0,0 +1,82 @@
+use aex::connection::global::GlobalContext;
+use std::{sync::Arc, time::Duration};
+
+use crate::clis::send::send_text_as;
+use crate::identities::{self, SharedIdentities};
+use crate::protocols::commands::successor::{self, SUCCESSOR_DEFAULT_GRACE_SECS};
+use crate::{cli_error, cli_println};
+
+pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
+    let Some(identities) = context.get::<SharedIdentities>().await else {
+        cli_error!("Identities not initialized");
+        return;
+    };
+
+    match args.first().map(|s| s.as_str()) {
+        None | Some("ls") => {
+            let active = identities.active().name;
+            for identity in identities.list() {
+                let marker = if identity.name == active { "*" } else { " " };
+                cli_println!(
+                    "{} {:<16} {} ({} session(s))",
+                    marker,
+                    identity.name,
+                    identity.address,
+                    identity.peers.len()
+                );
+            }
+        }
+        Some("new") if args.len() >= 2 => match identities.generate(&args[1]) {
+            Ok(identity) => {
+                identities::save(&context).await;
+                cli_println!("{} -> {}", identity.name, identity.address);
+            }
+            Err(e) => cli_error!("identity new failed: {}", e),
+        },
+        Some("use") if args.len() >= 2 => match identities.set_active(&args[1]) {
+            Ok(identity) => cli_println!("Using {} ({})", identity.name, identity.address),
+            Err(e) => cli_error!("identity use failed: {}", e),
+        },
+        Some("rm") if args.len() >= 2 => match identities.remove(&args[1]) {
+            Ok(identity) => {
+                identities::save(&context).await;
+                cli_println!("Removed {} ({})", identity.name, identity.address);
+            }
+            Err(e) => cli_error!("identity rm failed: {}", e),
+        },
+        Some("rotate") => {
+            let grace = match args.get(1).map(|s| s.parse::<u64>()) {
+                None => SUCCESSOR_DEFAULT_GRACE_SECS,
+                Some(Ok(secs)) if secs > 0 => secs,
+                Some(_) => {
+                    cli_error!("Invalid grace period: {}", args[1]);
+                    return;
+                }
+            };
+            match successor::rotate(&context, Duration::from_secs(grace)).await {
+                Ok(record) => cli_println!(
+                    "Rotated {} -> {}; old address accepted for {}s",
+                    record.old,
+                    record.new,
+                    (record.grace_until - record.issued_at) / 1000
+                ),
+                Err(e) => cli_error!("identity rotate failed: {}", e),
+            }
+        }
+        Some("send") if args.len() >= 4 => {
+            let Some(identity) = identities.get(&args[1]) else {
+                cli_error!("No such identity: {}", args[1]);
+                return;
+            };
+            let message = args[3..].join(" ");
+            if let Err(e) = send_text_as(context, &identity, args[2].clone(), message).await {
+                cli_error!("Send failed: {}", e);
+            }
+        }
+        _ => {
+            cli_println!(
+                "Usage: identity ls | identity new <name> | identity use <name> | identity rm <name> | identity rotate [grace_secs] | identity send <name> <address> <message>"
+            );
+        }
+    }
+}
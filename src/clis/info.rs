@@ -2,30 +2,24 @@ use aex::connection::global::GlobalContext;
 use std::sync::Arc;
 use zz_account::address::FreeWebMovementAddress;
 
-use crate::{io_storage::IOStorage, node};
+use crate::node;
+use crate::{cli_error, cli_println};
 
 pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
     let verbose = args.iter().any(|a| a == "-v" || a == "--verbose");
 
-    let io_storage = match context.get::<IOStorage>().await {
-        Some(ios) => ios,
-        None => {
-            eprintln!("Error: IOStorage not found in context");
-            return;
-        }
-    };
-
-    let address = match io_storage.read::<FreeWebMovementAddress>("address").await {
+    // 身份可能来自加密 keystore，以 GlobalContext 中已加载的为准
+    let address = match context.get::<FreeWebMovementAddress>().await {
         Some(addr) => addr,
         None => {
-            eprintln!("Error: Failed to read address");
+            cli_error!("Error: Failed to read address");
             return;
         }
     };
 
-    println!("=== Node Information ===");
-    println!("Address: {}", address);
-    println!("Local: {}", context.addr);
+    cli_println!("=== Node Information ===");
+    cli_println!("Address: {}", address);
+    cli_println!("Local: {}", context.addr);
 
     if verbose {
         let mut total_clients = 0usize;
@@ -38,8 +32,8 @@ pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
             total_clients += bi_conn.clients.len();
             total_servers += bi_conn.servers.len();
         }
-        println!("Total connections: {}", total_clients + total_servers);
-        println!("Inbound (clients): {}", total_clients);
-        println!("Outbound (servers): {}", total_servers);
+        cli_println!("Total connections: {}", total_clients + total_servers);
+        cli_println!("Inbound (clients): {}", total_clients);
+        cli_println!("Outbound (servers): {}", total_servers);
     }
 }
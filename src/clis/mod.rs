@@ -1,7 +1,26 @@
+pub mod acl;
+pub mod alias;
+pub mod call;
 pub mod connect;
+pub mod conns;
+pub mod doctor;
+pub mod events;
+pub mod group;
 pub mod help;
+pub mod identity;
 pub mod info;
+pub mod name;
+pub mod outbox;
+pub mod peer;
 pub mod peers;
+pub mod ping;
+pub mod presence;
 pub mod send;
+pub mod sendbin;
+pub mod sendfile;
+pub mod sendtext;
 pub mod status;
 pub mod sync;
+pub mod topic;
+pub mod verify;
+pub mod webuser;
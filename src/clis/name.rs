@@ -0,0 +1,56 @@
+use aex::connection::global::GlobalContext;
+use std::{sync::Arc, time::Duration};
+
+use crate::{cli_error, cli_println};
+use crate::{
+    consts::DEFAULT_TIMEOUT_MS,
+    protocols::commands::naming::{self, NAME_DEFAULT_TTL_SECS},
+};
+
+pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
+    match args.first().map(|s| s.as_str()) {
+        Some("publish") if args.len() >= 2 => {
+            let ttl = match args.get(2).map(|s| s.parse::<u64>()) {
+                None => NAME_DEFAULT_TTL_SECS,
+                Some(Ok(secs)) if secs > 0 => secs,
+                Some(_) => {
+                    cli_error!("Invalid ttl: {}", args[2]);
+                    return;
+                }
+            };
+            match naming::publish(context, &args[1], Duration::from_secs(ttl)).await {
+                Ok(record) => cli_println!(
+                    "Published {} -> {} (expires in {}s)",
+                    record.name,
+                    record.address,
+                    (record.expires_at - record.issued_at) / 1000
+                ),
+                Err(e) => cli_error!("Failed to publish name: {}", e),
+            }
+        }
+        Some("ls") => {
+            let records = naming::list(&context).await;
+            if records.is_empty() {
+                cli_println!("(no names)");
+                return;
+            }
+            for record in records {
+                cli_println!("  {:<24} {}", record.name, record.address);
+            }
+        }
+        _ => cli_error!("Usage: name publish <name> [ttl_secs] | name ls"),
+    }
+}
+
+pub async fn resolve(args: Vec<String>, context: Arc<GlobalContext>) {
+    if args.is_empty() {
+        cli_error!("Usage: resolve <name>");
+        return;
+    }
+    let timeout = Duration::from_millis(DEFAULT_TIMEOUT_MS);
+    match naming::resolve(context, &args[0], timeout).await {
+        Ok(Some(record)) => cli_println!("{} -> {}", record.name, record.address),
+        Ok(None) => cli_error!("Name {} not found", args[0]),
+        Err(e) => cli_error!("Failed to resolve {}: {}", args[0], e),
+    }
+}
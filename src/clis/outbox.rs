@@ -0,0 +1,54 @@
+use aex::{connection::global::GlobalContext, time::SystemTime};
+use std::sync::Arc;
+
+use crate::outbox;
+use crate::{cli_error, cli_println};
+
+const USAGE: &str = "Usage: outbox [ls] | outbox rm <id> | outbox flush";
+
+/// 查看与管理发件箱，见 [`crate::outbox`]
+pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
+    match args.first().map(|s| s.as_str()) {
+        Some("ls") | None => list(&context).await,
+        Some("rm") => {
+            let Some(id) = args
+                .get(1)
+                .and_then(|s| s.trim_start_matches('#').parse().ok())
+            else {
+                cli_error!("{}", USAGE);
+                return;
+            };
+            match outbox::remove(&context, id).await {
+                Some(draft) => cli_println!("Discarded draft #{} to {}", draft.id, draft.receiver),
+                None => cli_error!("No draft #{}", id),
+            }
+        }
+        Some("flush") => {
+            let sent = outbox::flush_once(&context).await;
+            let left = outbox::list(&context).await.len();
+            cli_println!("Sent {} draft(s), {} still pending", sent, left);
+        }
+        Some(_) => cli_error!("{}", USAGE),
+    }
+}
+
+async fn list(context: &Arc<GlobalContext>) {
+    let drafts = outbox::list(context).await;
+    cli_println!("=== Outbox ({} pending) ===", drafts.len());
+    let now = SystemTime::timestamp();
+    for draft in drafts {
+        let age = now.saturating_sub(draft.queued_at) / 1000;
+        let error = match &draft.last_error {
+            Some(e) => format!(" last error: {} ({} attempt(s))", e, draft.attempts),
+            None => String::new(),
+        };
+        cli_println!(
+            " #{: <4} → {} queued {}s ago: {}{}",
+            draft.id,
+            draft.receiver,
+            age,
+            draft.message,
+            error
+        );
+    }
+}
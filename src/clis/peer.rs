@@ -0,0 +1,97 @@
+use aex::connection::global::GlobalContext;
+use std::{net::SocketAddr, sync::Arc};
+
+use crate::server_list;
+use crate::{cli_error, cli_println};
+
+const USAGE: &str =
+    "Usage: peer ls | peer add <ip:port> [--pin] | peer rm <ip:port> | peer pin|unpin <ip:port>";
+
+fn endpoint(args: &[String]) -> Option<SocketAddr> {
+    let Some(arg) = args.iter().find(|a| !a.starts_with("--")) else {
+        cli_error!("{}", USAGE);
+        return None;
+    };
+    match server_list::parse_endpoint(arg) {
+        Ok(e) => Some(e),
+        Err(e) => {
+            cli_error!("{}", e);
+            None
+        }
+    }
+}
+
+/// 编辑持久化的服务器列表，见 [`crate::server_list`]
+pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
+    let rest = args.get(1..).unwrap_or_default();
+    match args.first().map(|s| s.as_str()) {
+        Some("ls") | None => list(&context).await,
+        Some("add") => {
+            let Some(endpoint) = endpoint(rest) else {
+                return;
+            };
+            let pin = rest.iter().any(|a| a == "--pin");
+            match server_list::add(&context, endpoint, pin).await {
+                Ok(added) => {
+                    if added {
+                        cli_println!("Added {}, dialing...", endpoint);
+                    } else {
+                        cli_println!("{} is already in the server list", endpoint);
+                    }
+                    if pin {
+                        cli_println!("Pinned {}", endpoint);
+                    }
+                }
+                Err(e) => cli_error!("Failed: {}", e),
+            }
+        }
+        Some("rm") => {
+            let Some(endpoint) = endpoint(rest) else {
+                return;
+            };
+            match server_list::remove(&context, endpoint).await {
+                Ok(true) => cli_println!("Removed {}", endpoint),
+                Ok(false) => cli_error!("{} is not in the server list", endpoint),
+                Err(e) => cli_error!("Failed: {}", e),
+            }
+        }
+        Some(op @ ("pin" | "unpin")) => {
+            let Some(endpoint) = endpoint(rest) else {
+                return;
+            };
+            match server_list::set_pinned(&context, endpoint, op == "pin").await {
+                Ok(true) => cli_println!("{}ned {}", op, endpoint),
+                Ok(false) => cli_error!("{} is not in the server list", endpoint),
+                Err(e) => cli_error!("Failed: {}", e),
+            }
+        }
+        Some(_) => cli_error!("{}", USAGE),
+    }
+}
+
+async fn list(context: &Arc<GlobalContext>) {
+    let entries = match server_list::list(context).await {
+        Ok(entries) => entries,
+        Err(e) => {
+            cli_error!("Failed: {}", e);
+            return;
+        }
+    };
+    cli_println!("=== Server List ({}) ===", entries.len());
+    for entry in entries {
+        let pinned = if entry.pinned { " pinned" } else { "" };
+        let available = if entry.is_available {
+            ""
+        } else {
+            " (unavailable)"
+        };
+        cli_println!(
+            " {: <22} {: <15} score={:.2}{}{}",
+            entry.endpoint,
+            entry.lists.join(","),
+            entry.score,
+            pinned,
+            available
+        );
+    }
+}
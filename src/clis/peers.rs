@@ -1,7 +1,10 @@
 use aex::connection::{global::GlobalContext, scope::NetworkScope};
 use std::sync::Arc;
 
-use crate::node;
+use crate::cli_println;
+use crate::node::{self, Node as P2pNode};
+use crate::protocols::commands::ping;
+use crate::record::NodeRecord;
 
 pub async fn handle(_args: Vec<String>, context: Arc<GlobalContext>) {
     let mut total_clients = 0usize;
@@ -24,9 +27,68 @@ pub async fn handle(_args: Vec<String>, context: Arc<GlobalContext>) {
         total_servers += bi_conn.servers.len();
     }
 
-    println!("=== Connection Status ===");
-    println!("Intranet connections: {}", intranet_conns);
-    println!("Extranet connections: {}", extranet_conns);
-    println!("Inbound (clients): {}", total_clients);
-    println!("Outbound (servers): {}", total_servers);
+    cli_println!("=== Connection Status ===");
+    cli_println!("Intranet connections: {}", intranet_conns);
+    cli_println!("Extranet connections: {}", extranet_conns);
+    cli_println!("Inbound (clients): {}", total_clients);
+    cli_println!("Outbound (servers): {}", total_servers);
+
+    print_known_peers(&context).await;
+}
+
+/// 列出所有已知的 NodeRecord（内网 + 外网），附带评分、协议、最近活跃时间、延迟与软件版本
+async fn print_known_peers(context: &Arc<GlobalContext>) {
+    let node = match context.get::<Arc<P2pNode>>().await {
+        Some(n) => n,
+        None => return,
+    };
+
+    let (inner, external) = (node.inner.snapshot(), node.external.snapshot());
+    let mut records: Vec<(&str, &NodeRecord)> = inner
+        .iter()
+        .map(|r| ("inner", r))
+        .chain(external.iter().map(|r| ("external", r)))
+        .collect();
+    records.sort_by(|a, b| b.1.last_seen.cmp(&a.1.last_seen));
+
+    cli_println!("=== Known Peers ({}) ===", records.len());
+    for (kind, record) in records {
+        let mut protocols: Vec<String> = record
+            .protocols
+            .iter()
+            .map(|p| format!("{:?}", p))
+            .collect();
+        protocols.sort();
+        let latency = ping::latency(context, record.endpoint)
+            .await
+            .map(|ms| format!("{}ms", ms))
+            .unwrap_or_else(|| "-".to_string());
+        cli_println!(
+            " {: <22} {: <8} score={:.2} protocols=[{}] last_seen={} latency={} agent={}{}{}{}",
+            record.endpoint,
+            kind,
+            record.score(),
+            protocols.join(","),
+            record.last_seen.format("%Y-%m-%d %H:%M:%S"),
+            latency,
+            record
+                .agent
+                .as_ref()
+                .map(|a| a.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            if record.alt_endpoints.is_empty() {
+                String::new()
+            } else {
+                let alts: Vec<String> =
+                    record.alt_endpoints.iter().map(|a| a.to_string()).collect();
+                format!(" also=[{}]", alts.join(","))
+            },
+            if record.pinned { " pinned" } else { "" },
+            if record.is_available {
+                ""
+            } else {
+                " (unavailable)"
+            }
+        );
+    }
 }
@@ -0,0 +1,50 @@
+use aex::connection::global::GlobalContext;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use crate::{cli_error, cli_println};
+use crate::{consts::DEFAULT_TIMEOUT_MS, node::Node as P2pNode, protocols::commands::ping};
+
+pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
+    if args.is_empty() {
+        cli_error!("Usage: ping <ip:port | node-address>");
+        return;
+    }
+    let target = args[0].clone();
+
+    // 支持直接使用 socket 地址，或者通过节点地址 / 别名在 NodeRegistry 中查找其 seeds
+    let candidates: Vec<SocketAddr> = match target.parse::<SocketAddr>() {
+        Ok(addr) => vec![addr],
+        Err(_) => match context.get::<Arc<P2pNode>>().await {
+            Some(node) => node
+                .registry
+                .get_seeds_for_node(&node.registry.resolve_alias(&target)),
+            None => vec![],
+        },
+    };
+
+    let peer_ctx = candidates.iter().find_map(|addr| {
+        context
+            .manager
+            .find_entry(addr)
+            .and_then(|entry| entry.context.clone())
+    });
+
+    let peer_ctx = match peer_ctx {
+        Some(c) => c,
+        None => {
+            cli_error!("Peer {} is not connected", target);
+            return;
+        }
+    };
+
+    match ping::ping(
+        context.clone(),
+        peer_ctx,
+        Duration::from_millis(DEFAULT_TIMEOUT_MS),
+    )
+    .await
+    {
+        Ok(rtt) => cli_println!("Pong from {}: time={}ms", target, rtt.as_millis()),
+        Err(e) => cli_println!("Ping {} failed: {}", target, e),
+    }
+}
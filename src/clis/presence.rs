@@ -0,0 +1,42 @@
+use aex::{connection::global::GlobalContext, time::SystemTime};
+use std::sync::Arc;
+
+use crate::cli_println;
+use crate::{node::Node as P2pNode, protocols::commands::presence};
+
+pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
+    if let Some(target) = args.first() {
+        let address = match context.get::<Arc<P2pNode>>().await {
+            Some(node) => node.registry.resolve_alias(target),
+            None => target.clone(),
+        };
+        let reachability = presence::reachability(&context, &address).await;
+        cli_println!("{}: {}", address, reachability);
+        if let presence::Reachability::LikelyOnline { endpoints, .. } = reachability {
+            for endpoint in endpoints {
+                cli_println!("  {}", endpoint);
+            }
+        }
+        return;
+    }
+
+    let entries = presence::list(&context).await;
+    if entries.is_empty() {
+        cli_println!("(no presence records)");
+        return;
+    }
+    let now = SystemTime::timestamp();
+    for entry in entries {
+        cli_println!(
+            "  {:<44} {:<8} {:>5}s ago  via {}",
+            entry.record.address,
+            if entry.record.online {
+                "online"
+            } else {
+                "offline"
+            },
+            now.saturating_sub(entry.seen_at.max(entry.record.issued_at)) / 1000,
+            entry.seen_by.as_deref().unwrap_or("-")
+        );
+    }
+}
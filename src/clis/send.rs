@@ -1,38 +1,158 @@
-use std::sync::Arc;
+use std::{
+    fmt,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 
-use crate::protocols::commands::message::{next_request_id, send_text_message};
+use crate::identities::{self, LocalIdentity};
+use crate::node::Node as P2pNode;
+use crate::outbox;
+use crate::protocols::commands::message::{
+    next_request_id, send_text_message, send_text_message_as,
+};
+use crate::protocols::commands::{presence, sealed};
+use crate::protocols::routing;
+use crate::{cli_error, cli_println};
 use aex::connection::global::GlobalContext;
-use zz_account::address::FreeWebMovementAddress;
+
+/// 接收方既没有直连也没有路由；[`send_text`] 以此区分可以放入发件箱的失败
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotConnected {
+    pub receiver: String,
+    pub reachability: String,
+}
+
+impl fmt::Display for NotConnected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} is not connected ({})",
+            self.receiver, self.reachability
+        )
+    }
+}
+
+impl std::error::Error for NotConnected {}
 
 pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
+    // `--e2e` 加密给接收方身份公钥，`--sealed` 同时对中继隐藏发送方
+    let (mode, args) = match args.first().map(|s| s.as_str()) {
+        Some("--e2e") => (Some(false), &args[1..]),
+        Some("--sealed") => (Some(true), &args[1..]),
+        _ => (None, &args[..]),
+    };
     if args.len() < 2 {
-        println!("Usage: send <address> <message>");
+        cli_error!("Usage: send [--e2e|--sealed] <address> <message>");
         return;
     }
-    let receiver = args[0].clone();
-    let msg = args[1].clone();
-    let request_id = next_request_id();
+    let Some(hide_sender) = mode else {
+        // 没有可用的连接时放入发件箱，连上后自动发出
+        match outbox::send_or_queue(&context, args[0].clone(), args[1..].join(" ")).await {
+            Ok(outbox::Delivery::Sent(_)) => {}
+            Ok(outbox::Delivery::Queued(draft)) => cli_println!(
+                "📥 {} is unreachable, queued as draft #{} (see `outbox`)",
+                draft.receiver,
+                draft.id
+            ),
+            Err(e) => cli_error!("Send failed: {}", e),
+        }
+        return;
+    };
+    let sent = send_sealed(context, args[0].clone(), args[1..].join(" "), hide_sender).await;
+    if let Err(e) = sent {
+        cli_error!("Send failed: {}", e);
+    }
+}
 
-    let sender = context
-        .get::<FreeWebMovementAddress>()
+/// 以当前身份发送端到端加密的文本消息，返回 request_id
+pub async fn send_sealed(
+    context: Arc<GlobalContext>,
+    receiver: String,
+    msg: String,
+    hide_sender: bool,
+) -> anyhow::Result<u64> {
+    let identity = identities::active(&context)
         .await
-        .map(|a| a.to_string())
-        .unwrap_or_default();
+        .ok_or_else(|| anyhow::anyhow!("Address not set"))?;
+    let receiver = match context.get::<Arc<P2pNode>>().await {
+        Some(node) => node.registry.resolve_alias(&receiver),
+        None => receiver,
+    };
+    sealed::send(&context, &identity, &receiver, &msg, hide_sender).await
+}
+
+/// 以当前身份（`identity use`）向指定节点（地址或别名）发送文本消息，返回 request_id
+pub async fn send_text(
+    context: Arc<GlobalContext>,
+    receiver: String,
+    msg: String,
+) -> anyhow::Result<u64> {
+    let identity = identities::active(&context)
+        .await
+        .ok_or_else(|| anyhow::anyhow!("Address not set"))?;
+    send_text_as(context, &identity, receiver, msg).await
+}
+
+/// 以指定身份发送文本消息，返回 request_id
+pub async fn send_text_as(
+    context: Arc<GlobalContext>,
+    identity: &LocalIdentity,
+    receiver: String,
+    msg: String,
+) -> anyhow::Result<u64> {
+    let request_id = next_request_id();
+    let receiver = match context.get::<Arc<P2pNode>>().await {
+        Some(node) => node.registry.resolve_alias(&receiver),
+        None => receiver,
+    };
 
+    let identity_for_closure = identity.clone();
+    let msg_for_closure = msg.clone();
+    let sent = Arc::new(AtomicBool::new(false));
+    let sent_in_closure = sent.clone();
     let receiver_for_closure = receiver.clone();
     context
         .manager
         .notify(receiver.as_bytes(), |entries| async move {
-            if let Some(entry) = entries.into_iter().next() {
-                let _ = send_text_message(
-                    sender.clone(),
-                    receiver_for_closure.clone(),
-                    request_id,
-                    entry.context.as_ref().expect("Context missing").clone(),
-                    &msg,
-                )
-                .await;
+            let Some(entry) = entries.into_iter().next() else {
+                return;
+            };
+            let Some(ctx) = entry.context.clone() else {
+                return;
+            };
+            let sent_as = send_text_message_as(
+                &identity_for_closure,
+                receiver_for_closure,
+                request_id,
+                ctx,
+                &msg_for_closure,
+            );
+            match sent_as.await {
+                Ok(_) => sent_in_closure.store(true, Ordering::SeqCst),
+                Err(e) => tracing::error!("Failed to send text message: {:?}", e),
             }
         })
         .await;
+
+    // 未直连（例如对端的附加身份）：经路由表的下一跳中继
+    if !sent.load(Ordering::SeqCst) && identity.is_primary() {
+        if let Some(ctx) = routing::route_context(&context, &receiver).await {
+            let sender = identity.address.to_string();
+            send_text_message(sender, receiver.clone(), request_id, ctx, &msg).await?;
+            return Ok(request_id);
+        }
+    }
+
+    if sent.load(Ordering::SeqCst) {
+        Ok(request_id)
+    } else {
+        let reachability = presence::reachability(&context, &receiver).await;
+        Err(NotConnected {
+            receiver,
+            reachability: reachability.to_string(),
+        }
+        .into())
+    }
 }
@@ -0,0 +1,47 @@
+use aex::connection::global::GlobalContext;
+use std::{path::Path, sync::Arc};
+
+use crate::{cli_error, cli_println};
+use crate::{node::Node as P2pNode, protocols::commands::binary::guess_content_type};
+
+pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
+    if args.len() < 2 {
+        cli_error!("Usage: sendbin <address> <path>");
+        return;
+    }
+    let receiver = args[0].clone();
+    let path = Path::new(&args[1]);
+
+    let data = match tokio::fs::read(path).await {
+        Ok(d) => d,
+        Err(e) => {
+            cli_error!("Failed to read {}: {}", path.display(), e);
+            return;
+        }
+    };
+    let filename = path.file_name().map(|n| n.to_string_lossy().to_string());
+    let content_type = guess_content_type(filename.as_deref().unwrap_or_default());
+
+    let node = match context.get::<Arc<P2pNode>>().await {
+        Some(n) => n,
+        None => {
+            cli_error!("Error: Node not found in context");
+            return;
+        }
+    };
+
+    let size = data.len();
+    match node
+        .send_binary(&receiver, content_type, filename, data)
+        .await
+    {
+        Ok(id) => cli_println!(
+            "Sent {} bytes ({}) to {} (request_id={})",
+            size,
+            content_type,
+            receiver,
+            id
+        ),
+        Err(e) => cli_error!("Failed to send binary message: {}", e),
+    }
+}
@@ -0,0 +1,22 @@
+use aex::connection::global::GlobalContext;
+use std::{path::Path, sync::Arc};
+
+use crate::protocols::commands::stream::send_file;
+use crate::{cli_error, cli_println};
+
+pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
+    if args.len() < 2 {
+        cli_error!("Usage: sendfile <address> <path>");
+        return;
+    }
+    let path = Path::new(&args[1]);
+    match send_file(context, &args[0], path).await {
+        Ok(size) => cli_println!(
+            "Streamed {} ({} bytes) to {}",
+            path.display(),
+            size,
+            args[0]
+        ),
+        Err(e) => cli_error!("Failed to stream {}: {}", path.display(), e),
+    }
+}
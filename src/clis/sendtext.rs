@@ -0,0 +1,34 @@
+use aex::connection::global::GlobalContext;
+use std::{path::Path, sync::Arc};
+
+use crate::protocols::commands::stream::send_text_stream;
+use crate::{cli_error, cli_println};
+
+pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
+    if args.len() < 2 {
+        cli_error!("Usage: sendtext <address> <path>");
+        return;
+    }
+    let path = Path::new(&args[1]);
+    let file = match tokio::fs::File::open(path).await {
+        Ok(f) => f,
+        Err(e) => {
+            cli_error!("Failed to open {}: {}", path.display(), e);
+            return;
+        }
+    };
+    let len = file.metadata().await.ok().map(|m| m.len());
+    let mut metadata = vec![];
+    if let Some(name) = path.file_name() {
+        metadata.push(("filename".to_string(), name.to_string_lossy().to_string()));
+    }
+    match send_text_stream(context, &args[0], len, metadata, file).await {
+        Ok(size) => cli_println!(
+            "Streamed text {} ({} bytes) to {}",
+            path.display(),
+            size,
+            args[0]
+        ),
+        Err(e) => cli_error!("Failed to stream {}: {}", path.display(), e),
+    }
+}
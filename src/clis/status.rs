@@ -1,47 +1,35 @@
-use aex::connection::{global::GlobalContext, scope::NetworkScope};
+use aex::connection::global::GlobalContext;
 use std::sync::Arc;
-use zz_account::address::FreeWebMovementAddress;
 
-use crate::{io_storage::IOStorage, node};
+use crate::{cli_error, cli_println};
+use crate::{reachability::Reachability, script, status};
 
 pub async fn handle(_args: Vec<String>, context: Arc<GlobalContext>) {
-    let io_storage = match context.get::<IOStorage>().await {
-        Some(ios) => ios,
-        None => {
-            eprintln!("Error: IOStorage not found in context");
-            return;
-        }
-    };
-
-    match io_storage.read::<FreeWebMovementAddress>("address").await {
-        Some(addr) => println!("Node address: {}", addr),
-        None => eprintln!("Error: Failed to read or generate node address"),
-    }
-
-    let mut total_ips = 0usize;
-    let mut intranet_conns = 0usize;
-    let mut extranet_conns = 0usize;
-    let mut total_clients = 0usize;
-    let mut total_servers = 0usize;
-
-    for bucket_ref in context.manager.connections.iter() {
-        let (key, bi_conn) = bucket_ref.pair();
-        if !node::is_public_ip(&key.0) {
-            continue;
-        }
-        total_ips += 1;
-        let client_count = bi_conn.clients.len();
-        let server_count = bi_conn.servers.len();
-        match key.1 {
-            NetworkScope::Intranet => intranet_conns += client_count + server_count,
-            _ => extranet_conns += client_count + server_count,
-        }
-        total_clients += client_count;
-        total_servers += server_count;
+    let snapshot = status::collect(&context).await;
+    script::attach(&snapshot);
+    if snapshot.address.is_empty() {
+        cli_error!("Error: Failed to read or generate node address");
+    } else {
+        cli_println!("Node address: {}", snapshot.address);
     }
+    cli_println!(
+        "Version {}{} (protocol v{}, storage v{}), up {}s, {}",
+        snapshot.versions.package,
+        if snapshot.versions.git_hash.is_empty() {
+            String::new()
+        } else {
+            format!("+{}", snapshot.versions.git_hash)
+        },
+        snapshot.versions.protocol,
+        snapshot.versions.storage_schema,
+        snapshot.uptime_secs,
+        snapshot.startup
+    );
+    cli_println!("User agent: {}", snapshot.user_agent);
 
-    let total_conns = total_clients + total_servers;
-    println!(
+    let conns = snapshot.connections;
+    let total_conns = conns.inbound + conns.outbound;
+    cli_println!(
         "\
 ┏━━━━━━━━━━━━━━━━ AEX Connection Profile ━━━━━━━━━━━━━━━┓
 ┃  Nodes (IPs):      {: <40} ┃
@@ -50,6 +38,141 @@ pub async fn handle(_args: Vec<String>, context: Arc<GlobalContext>) {
 ┃  Direction:        Inbound: {: <10} Outbound: {: <10} ┃
 ┃  Network Scope:    Intra:   {: <10} Extra:    {: <10} ┃
 ┗━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━┛",
-        total_ips, total_conns, total_clients, total_servers, intranet_conns, extranet_conns
+        conns.peer_ips,
+        total_conns,
+        conns.inbound,
+        conns.outbound,
+        conns.intranet,
+        conns.extranet
     );
+
+    let usage = &snapshot.bandwidth;
+    cli_println!(
+        "Bandwidth: up {} B/s ({} B total), down {} B/s ({} B total)",
+        usage.upload_rate,
+        usage.upload_bytes,
+        usage.download_rate,
+        usage.download_bytes
+    );
+    for peer in &usage.peers {
+        cli_println!(
+            "  {:<22} up {:>8} B/s  down {:>8} B/s",
+            peer.peer,
+            peer.upload_rate,
+            peer.download_rate
+        );
+    }
+
+    let queues = snapshot.queues;
+    if queues.outbox > 0 || queues.wal_pending > 0 {
+        cli_println!(
+            "Queued: {} in outbox, {} awaiting ack in WAL",
+            queues.outbox,
+            queues.wal_pending
+        );
+    }
+
+    let reach = &snapshot.reachability;
+    if !reach.peers.is_empty() {
+        let count = |state: Reachability| reach.peers.iter().filter(|p| p.state == state).count();
+        cli_println!(
+            "Reachability: {} passive, {} active, {} idle, {} unresponsive ({} probes, {} failed, {} skipped)",
+            count(Reachability::Passive),
+            count(Reachability::Active),
+            count(Reachability::Idle),
+            count(Reachability::Unresponsive),
+            reach.probes,
+            reach.probe_failures,
+            reach.skipped
+        );
+    }
+
+    let partition = &snapshot.partition;
+    if partition.partitioned || partition.partitions > 0 {
+        cli_println!(
+            "Partition: {} ({} detected, {} re-bootstrap(s))",
+            if partition.partitioned {
+                "isolated"
+            } else {
+                "recovered"
+            },
+            partition.partitions,
+            partition.rebootstraps
+        );
+    }
+
+    let downgrades = &snapshot.capability_downgrades;
+    if downgrades.total > 0 {
+        let peers: Vec<String> = downgrades
+            .by_peer
+            .iter()
+            .map(|(peer, n)| format!("{}={}", peer, n))
+            .collect();
+        cli_println!(
+            "Capability downgrades: {} ({})",
+            downgrades.total,
+            peers.join(", ")
+        );
+    }
+
+    let errors = &snapshot.protocol_errors;
+    if errors.total > 0 {
+        let kinds: Vec<String> = errors
+            .by_kind
+            .iter()
+            .map(|(kind, n)| format!("{}={}", kind, n))
+            .collect();
+        cli_println!("Protocol errors: {} ({})", errors.total, kinds.join(", "));
+    }
+
+    if !snapshot.peers.is_empty() {
+        cli_println!("Peers:");
+    }
+    for entry in &snapshot.peers {
+        let ms = |v: Option<f64>| v.map(|ms| format!("{:.0}ms", ms)).unwrap_or("-".into());
+        cli_println!(
+            "  {:<22} in {:>6} out {:>6} err {:>4}  rtt {:>7}  send {:>7}{}",
+            entry.addr,
+            entry.stats.frames_in,
+            entry.stats.frames_out,
+            entry.stats.errors,
+            ms(entry.stats.rtt_ms),
+            ms(entry.stats.send_latency_ms),
+            if entry.stats.degraded {
+                "  (degraded)"
+            } else {
+                ""
+            }
+        );
+    }
+
+    let failing: Vec<&str> = snapshot
+        .tasks
+        .iter()
+        .filter(|task| task.failures > 0)
+        .map(|task| task.name.as_str())
+        .collect();
+    if !snapshot.tasks.is_empty() {
+        cli_println!(
+            "Scheduled tasks: {} ({} runs){}",
+            snapshot.tasks.len(),
+            snapshot.tasks.iter().map(|task| task.runs).sum::<u64>(),
+            if failing.is_empty() {
+                String::new()
+            } else {
+                format!(", failed: {}", failing.join(", "))
+            }
+        );
+    }
+
+    if !snapshot.peer_agents.is_empty() {
+        cli_println!("Peer software:");
+        for (peer, agent) in &snapshot.peer_agents {
+            cli_println!("  {:<22} {}", peer, agent);
+        }
+    }
+
+    for listener in &snapshot.listeners {
+        cli_println!("Listener {}: {}", listener.name, listener.health);
+    }
 }
@@ -1,14 +1,17 @@
-use aex::connection::{global::GlobalContext, scope::NetworkScope};
+use aex::connection::global::GlobalContext;
 use sha2::{Digest, Sha256};
 use std::{net::SocketAddr, sync::Arc};
 
+use crate::ip_scope;
 use crate::node::{self, Node as P2pNode};
-use crate::protocols::commands::ack::{SeedRecord, SeedsCommand};
 use crate::protocols::{
     command::{Action, Entity, P2PCommand},
     commands::online::OnlineCommand,
     frame::P2PFrame,
+    privacy,
 };
+use crate::proxy;
+use crate::{cli_error, cli_println};
 
 pub const SYNC_ROUNDS_PER_TICK: u32 = 3;
 pub const SYNC_INTERVAL_MS: u64 = 200;
@@ -23,16 +26,16 @@ pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
     };
 
     if target_addrs.is_empty() {
-        println!("Usage: sync [<ip>:<port> ...]");
-        println!("  Without args: sync with all connected peers");
-        println!("  With args: sync with specified peers");
+        cli_error!("Usage: sync [<ip>:<port> ...]");
+        cli_println!("  Without args: sync with all connected peers");
+        cli_println!("  With args: sync with specified peers");
         return;
     };
 
     let mut prev_hash: [u8; 32] = [0u8; 32];
     let mut stable_count = 0;
 
-    println!("🔄 Sync start: {} seeds", target_addrs.len());
+    cli_println!("🔄 Sync start: {} seeds", target_addrs.len());
 
     for round in 0..SYNC_ROUNDS_PER_TICK {
         let mut pending = Vec::new();
@@ -56,7 +59,7 @@ pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
         let current_seeds: Vec<String> = get_connected_seeds(&context);
         let new_hash = compute_witness_hash(&current_seeds);
 
-        println!(
+        cli_println!(
             "  📊 Round {}: {} connected, hash={:?}",
             round + 1,
             current_seeds.len(),
@@ -66,7 +69,7 @@ pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
         if new_hash == prev_hash && !current_seeds.is_empty() {
             stable_count += 1;
             if stable_count >= 2 {
-                println!("✅ Sync complete: {} connected", current_seeds.len());
+                cli_println!("✅ Sync complete: {} connected", current_seeds.len());
                 return;
             }
         } else {
@@ -77,7 +80,7 @@ pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
     }
 
     let final_seeds = get_connected_seeds(&context);
-    println!("⚠️ Final: {:?}", final_seeds);
+    cli_println!("⚠️ Final: {:?}", final_seeds);
 }
 
 fn is_connected(ctx: &Arc<GlobalContext>, addr: SocketAddr) -> bool {
@@ -106,105 +109,74 @@ async fn connect_to_peer_sync(
     addr: SocketAddr,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let (tx, rx) = tokio::sync::oneshot::channel();
+    // 重试时每次尝试共用同一个通知
+    let tx = Arc::new(std::sync::Mutex::new(Some(tx)));
 
     // Register peer in NodeRegistry
     if let Some(node) = context.get::<Arc<P2pNode>>().await {
         let self_node_id = context.local_node.read().await.id.clone();
         let self_address = String::from_utf8(self_node_id).unwrap_or_default();
-        let scope = NetworkScope::from_ip(&addr.ip());
+        let scope = ip_scope::classify(&addr.ip());
         node.registry.register(self_address, addr, scope);
     }
 
     let gctx_for_reader = context.clone();
-    let _ = gctx_for_reader
-        .manager
-        .clone()
-        .connect::<P2PFrame, P2PCommand, _, _>(
-            addr,
-            context.clone(),
-            move |ctx| {
-                let tx = tx;
-                let ctx_for_seeds = ctx.clone();
-                let reader_gctx = gctx_for_reader.clone();
-                Box::pin(async move {
-                    let psk = {
-                        let guard = ctx.lock().await;
-                        guard.global.paired_session_keys.clone().unwrap()
-                    };
-                    let (id, key) = {
-                        let guard = psk.lock().await;
-                        guard.create(false).await
-                    };
-
-                    let aex_node = {
-                        let guard = ctx.lock().await;
-                        guard.global.local_node.read().await.clone()
-                    };
-
-                    // Build seeds from NodeRegistry
-                    let seeds_to_send = {
-                        let guard = ctx_for_seeds.lock().await;
-                        let seeds = if let Some(node) = guard.global.get::<Arc<P2pNode>>().await {
-                            let all_seeds: Vec<SeedRecord> = node
-                                .registry
-                                .get_all_seeds()
-                                .into_iter()
-                                .map(|(s, na)| SeedRecord::new(s.to_string(), na))
-                                .collect();
-                            SeedsCommand::new(all_seeds)
-                        } else {
-                            SeedsCommand::new(vec![])
-                        };
-                        drop(guard);
-                        seeds
-                    };
-
-                    let (intranet_ips, wan_ips) = {
-                        let mut inner = Vec::new();
-                        let mut outer = Vec::new();
-                        for (scope, ip) in &aex_node.ips {
-                            match scope {
-                                aex::connection::scope::NetworkScope::Intranet => {
-                                    inner.push(ip.to_string())
-                                }
-                                aex::connection::scope::NetworkScope::Extranet => {
-                                    outer.push(ip.to_string())
-                                }
-                            }
-                        }
-                        (inner, outer)
-                    };
-                    let cmd = OnlineCommand {
-                        session_id: id,
-                        node: aex_node,
-                        ephemeral_public_key: key.to_bytes(),
-                        intranet_ips,
-                        wan_ips,
-                        seeds: Some(seeds_to_send),
-                    };
-                    let _ = P2PFrame::send::<OnlineCommand>(
-                        ctx.clone(),
-                        &Some(cmd),
-                        Entity::Node,
-                        Action::OnLine,
-                        false,
-                    )
-                    .await;
-                    let _ = tx.send(());
-
-                    // Start reader loop
-                    if let Some(router) = aex::connection::context::get_tcp_router::<
-                        P2PFrame,
-                        P2PCommand,
-                    >(&reader_gctx.routers)
-                    {
-                        let _ = router.handle(ctx).await;
-                    }
-                })
-            },
-            Some(5),
-        )
-        .await;
+    let _ = proxy::connect_peer(context.clone(), addr, move |ctx| {
+        let tx = tx.clone();
+        let reader_gctx = gctx_for_reader.clone();
+        Box::pin(async move {
+            let psk = {
+                let guard = ctx.lock().await;
+                guard.global.paired_session_keys.clone().unwrap()
+            };
+            let (id, key) = {
+                let guard = psk.lock().await;
+                guard.create(false).await
+            };
+
+            let aex_node = {
+                let guard = ctx.lock().await;
+                guard.global.local_node.read().await.clone()
+            };
+
+            // Build seeds from NodeRegistry
+            let seeds_to_send = privacy::gossip_seeds(&reader_gctx, Some(&addr.ip())).await;
+
+            let (intranet_ips, wan_ips) =
+                privacy::announced_ips(&reader_gctx, &aex_node.ips, &addr.ip()).await;
+            let cmd = OnlineCommand {
+                session_id: id,
+                node: aex_node,
+                ephemeral_public_key: key.to_bytes(),
+                intranet_ips,
+                wan_ips,
+                seeds: Some(seeds_to_send),
+                capabilities: crate::protocols::compression::LOCAL_CAPABILITIES,
+                protocol_version: crate::protocols::version::CURRENT_PROTOCOL_VERSION,
+                max_frame_size: crate::protocols::capabilities::LOCAL_MAX_FRAME_SIZE,
+                agent: crate::protocols::agent::local(&reader_gctx).await,
+            };
+            let _ = P2PFrame::send::<OnlineCommand>(
+                ctx.clone(),
+                &Some(cmd),
+                Entity::Node,
+                Action::OnLine,
+                false,
+            )
+            .await;
+            if let Some(tx) = tx.lock().unwrap().take() {
+                let _ = tx.send(());
+            }
+
+            // Start reader loop
+            if let Some(router) = aex::connection::context::get_tcp_router::<P2PFrame, P2PCommand>(
+                &reader_gctx.routers,
+            ) {
+                let _ = router.handle(ctx).await;
+            }
+        })
+    })
+    .await;
 
     rx.await.map_err(|e| e.into())
 }
@@ -0,0 +1,39 @@
+use aex::connection::global::GlobalContext;
+use std::sync::Arc;
+
+use crate::protocols::commands::topic;
+use crate::{cli_error, cli_println};
+
+pub async fn subscribe(args: Vec<String>, context: Arc<GlobalContext>) {
+    if args.is_empty() {
+        cli_error!("Usage: sub <topic>");
+        return;
+    }
+    match topic::subscribe(context, &args[0]).await {
+        Ok(_) => cli_println!("Subscribed to '{}'", args[0]),
+        Err(e) => cli_error!("Failed to subscribe: {}", e),
+    }
+}
+
+pub async fn unsubscribe(args: Vec<String>, context: Arc<GlobalContext>) {
+    if args.is_empty() {
+        cli_error!("Usage: unsub <topic>");
+        return;
+    }
+    match topic::unsubscribe(context, &args[0]).await {
+        Ok(_) => cli_println!("Unsubscribed from '{}'", args[0]),
+        Err(e) => cli_error!("Failed to unsubscribe: {}", e),
+    }
+}
+
+pub async fn publish(args: Vec<String>, context: Arc<GlobalContext>) {
+    if args.len() < 2 {
+        cli_error!("Usage: pub <topic> <message>");
+        return;
+    }
+    let message = args[1..].join(" ");
+    match topic::publish(context, &args[0], message.into_bytes()).await {
+        Ok(id) => cli_println!("Published to '{}' (id={})", args[0], id),
+        Err(e) => cli_error!("Failed to publish: {}", e),
+    }
+}
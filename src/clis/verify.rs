@@ -0,0 +1,83 @@
+use aex::connection::global::GlobalContext;
+use std::sync::Arc;
+
+use crate::{cli_error, cli_println};
+use crate::{
+    node::Node as P2pNode,
+    safety_number::{self, PeerVerification, VerificationStatus},
+};
+
+const USAGE: &str = "Usage: verify <address|alias> [confirm [safety number] | clear] | verify ls";
+
+async fn resolve(arg: &str, context: &Arc<GlobalContext>) -> String {
+    match context.get::<Arc<P2pNode>>().await {
+        Some(node) => node.registry.resolve_alias(arg),
+        None => arg.to_string(),
+    }
+}
+
+fn print(v: &PeerVerification) {
+    cli_println!("Peer:          {}", v.address);
+    cli_println!("Status:        {}", v.status);
+    match (&v.safety_number, &v.fingerprint) {
+        (Some(number), Some(fingerprint)) => {
+            cli_println!("Key:           {}", fingerprint);
+            cli_println!("Safety number: {}", number);
+        }
+        _ => cli_println!("Safety number: unavailable (public key unknown, connect first)"),
+    }
+    if v.status == VerificationStatus::KeyChanged {
+        cli_println!(
+            "⚠️  The peer's key changed since it was verified; compare the new number again"
+        );
+    }
+}
+
+pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
+    match args.first().map(|s| s.as_str()) {
+        None => cli_error!("{}", USAGE),
+        Some("ls") => {
+            let contacts = safety_number::list(&context).await;
+            if contacts.is_empty() {
+                cli_println!("(no verified peers)");
+                return;
+            }
+            for address in contacts.keys() {
+                let status = safety_number::status(&context, address).await;
+                cli_println!("  {} [{}]", address, status);
+            }
+        }
+        Some(arg) => {
+            let address = resolve(arg, &context).await;
+            match args.get(1).map(|s| s.as_str()) {
+                None => match safety_number::inspect(&context, &address).await {
+                    Ok(v) => {
+                        print(&v);
+                        if v.safety_number.is_some() && v.status != VerificationStatus::Verified {
+                            cli_println!(
+                                "Compare it with the peer, then run: verify {} confirm",
+                                arg
+                            );
+                        }
+                    }
+                    Err(e) => cli_error!("verify failed: {}", e),
+                },
+                Some("confirm") => {
+                    let expected = (args.len() > 2).then(|| args[2..].join(" "));
+                    match safety_number::confirm(&context, &address, expected.as_deref()).await {
+                        Ok(_) => cli_println!("✅ {} marked as verified", address),
+                        Err(e) => cli_error!("verify failed: {}", e),
+                    }
+                }
+                Some("clear") => {
+                    if safety_number::clear(&context, &address).await {
+                        cli_println!("Cleared verification of {}", address);
+                    } else {
+                        cli_println!("{} is not verified", address);
+                    }
+                }
+                Some(_) => cli_error!("{}", USAGE),
+            }
+        }
+    }
+}
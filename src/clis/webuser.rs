@@ -0,0 +1,88 @@
+use aex::{connection::global::GlobalContext, time::SystemTime};
+use std::sync::Arc;
+
+use crate::web::auth::{self, WebRole};
+use crate::{cli_error, cli_println};
+
+const USAGE: &str = "Usage: webuser [ls] | webuser add <name> <viewer|user|admin> [password] | webuser rm <name> | webuser passwd <name> [password] | webuser token <name> [label] | webuser revoke <name> <label>";
+
+/// 管理 Web 页面与 API 的用户与 token，见 [`crate::web::auth`]
+pub async fn handle(args: Vec<String>, context: Arc<GlobalContext>) {
+    let arg = |i: usize| args.get(i).map(|s| s.as_str());
+    match (arg(0), arg(1)) {
+        (Some("ls") | None, _) => list(&context).await,
+        (Some("add"), Some(name)) => {
+            let Some(role) = arg(2) else {
+                cli_error!("{}", USAGE);
+                return;
+            };
+            let role = match role.parse::<WebRole>() {
+                Ok(r) => r,
+                Err(e) => {
+                    cli_error!("{}", e);
+                    return;
+                }
+            };
+            match auth::update(&context, |users| users.add(name, role, arg(3))).await {
+                Ok(()) => cli_println!("Added web user {} ({})", name, role),
+                Err(e) => cli_error!("{}", e),
+            }
+        }
+        (Some("rm"), Some(name)) => {
+            match auth::update(&context, |users| users.remove(name)).await {
+                Some(user) => cli_println!("Removed web user {}", user.name),
+                None => cli_error!("No web user {}", name),
+            }
+        }
+        (Some("passwd"), Some(name)) => {
+            let password = arg(2);
+            match auth::update(&context, |users| users.set_password(name, password)).await {
+                Ok(()) if password.is_some() => cli_println!("Password set for {}", name),
+                Ok(()) => cli_println!("Password cleared for {}, basic auth disabled", name),
+                Err(e) => cli_error!("{}", e),
+            }
+        }
+        (Some("token"), Some(name)) => {
+            let label = arg(2).unwrap_or("default");
+            let now = SystemTime::timestamp();
+            match auth::update(&context, |users| users.issue_token(name, label, now)).await {
+                Ok(token) => {
+                    cli_println!("Token {} for {} (shown only once):", label, name);
+                    cli_println!("{}", token);
+                }
+                Err(e) => cli_error!("{}", e),
+            }
+        }
+        (Some("revoke"), Some(name)) => {
+            let Some(label) = arg(2) else {
+                cli_error!("{}", USAGE);
+                return;
+            };
+            if auth::update(&context, |users| users.revoke_token(name, label)).await {
+                cli_println!("Revoked token {} of {}", label, name);
+            } else {
+                cli_error!("{} has no token {}", name, label);
+            }
+        }
+        _ => cli_error!("{}", USAGE),
+    }
+}
+
+async fn list(context: &Arc<GlobalContext>) {
+    let users = auth::list(context).await;
+    cli_println!("=== Web users ({}) ===", users.len());
+    for user in users {
+        let labels: Vec<&str> = user.tokens.iter().map(|t| t.label.as_str()).collect();
+        cli_println!(
+            " {: <16} {: <7} password: {: <3} tokens: {}",
+            user.name,
+            user.role.to_string(),
+            if user.password.is_some() { "yes" } else { "no" },
+            if labels.is_empty() {
+                "-".to_string()
+            } else {
+                labels.join(", ")
+            }
+        );
+    }
+}
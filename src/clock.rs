@@ -0,0 +1,189 @@
+//! 对端时钟偏差估算
+//!
+//! 消息与在线状态记录中的时间戳来自各自节点的本地时钟，彼此并不同步。借助 Ping/Pong
+//! 做类似 NTP 的估算：Ping 携带本端发送时间 `t1`，对端在 Pong 中回显 `t1` 并附上收到
+//! Ping 的时间 `t2` 与回复时间 `t3`，本端收到 Pong 的时间为 `t4`：
+//!
+//! - 偏差 `offset = ((t2 − t1) + (t3 − t4)) / 2`，即对端时钟减本端时钟；
+//! - 往返延迟 `delay = (t4 − t1) − (t3 − t2)`。
+//!
+//! 每个对端保留最近 [`CLOCK_SAMPLES_PER_PEER`] 个样本，取延迟最小的样本的偏差（延迟越小，
+//! 路径不对称带来的误差越小）。后台任务每 [`CLOCK_SYNC_INTERVAL_SECS`] 秒 ping 一次所有
+//! 直连对端。
+//!
+//! 收到的消息按偏差换算为本地时间后再交给上层应用排序；判断对端签发的在线状态记录是否
+//! 过期时，用对端时钟下的当前时间比较。没有样本的对端不做校正。
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
+
+use aex::{connection::global::GlobalContext, time::SystemTime};
+use dashmap::DashMap;
+use serde::Serialize;
+
+use crate::{
+    connections, consts::DEFAULT_TIMEOUT_MS, protocols::commands::ping, scheduler::Scheduler,
+};
+
+/// 每个对端保留的样本数
+pub const CLOCK_SAMPLES_PER_PEER: usize = 8;
+/// 定期测量的间隔
+pub const CLOCK_SYNC_INTERVAL_SECS: u64 = 5 * 60;
+
+/// 一次 Ping/Pong 交换得到的样本
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ClockSample {
+    /// 对端时钟减本端时钟（毫秒）
+    pub offset_ms: i64,
+    /// 扣除对端处理时间后的往返延迟（毫秒）
+    pub delay_ms: u64,
+}
+
+impl ClockSample {
+    /// 由四个时间戳（毫秒）计算；时间戳自相矛盾时返回 None
+    pub fn from_exchange(t1: u128, t2: u128, t3: u128, t4: u128) -> Option<Self> {
+        if t4 < t1 || t3 < t2 {
+            return None;
+        }
+        let (t1, t2, t3, t4) = (t1 as i128, t2 as i128, t3 as i128, t4 as i128);
+        let offset = ((t2 - t1) + (t3 - t4)) / 2;
+        let delay = ((t4 - t1) - (t3 - t2)).max(0);
+        Some(Self {
+            offset_ms: i64::try_from(offset).ok()?,
+            delay_ms: u64::try_from(delay).ok()?,
+        })
+    }
+}
+
+/// 一个对端的最近样本
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PeerClock {
+    samples: VecDeque<ClockSample>,
+    /// 最近一次记录样本的本地时间（毫秒）
+    pub updated_at: u128,
+}
+
+impl PeerClock {
+    pub fn record(&mut self, sample: ClockSample, now: u128) {
+        if self.samples.len() == CLOCK_SAMPLES_PER_PEER {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        self.updated_at = now;
+    }
+
+    /// 延迟最小的样本的偏差；延迟相同时取较新的样本
+    pub fn offset_ms(&self) -> Option<i64> {
+        self.samples
+            .iter()
+            .rev()
+            .min_by_key(|s| s.delay_ms)
+            .map(|s| s.offset_ms)
+    }
+
+    pub fn samples(&self) -> usize {
+        self.samples.len()
+    }
+}
+
+/// 各对端（节点地址）的时钟，保存在 GlobalContext 中
+pub type PeerClocks = Arc<DashMap<String, PeerClock>>;
+
+async fn clocks(gctx: &Arc<GlobalContext>) -> PeerClocks {
+    match gctx.get::<PeerClocks>().await {
+        Some(clocks) => clocks,
+        None => {
+            let clocks = PeerClocks::default();
+            gctx.set(clocks.clone()).await;
+            clocks
+        }
+    }
+}
+
+/// 对端时间戳换算为本地时间
+pub fn to_local(timestamp: u128, offset_ms: i64) -> u128 {
+    timestamp.saturating_add_signed(-(offset_ms as i128))
+}
+
+/// 本地时间换算为对端时钟下的时间
+pub fn to_peer(now: u128, offset_ms: i64) -> u128 {
+    now.saturating_add_signed(offset_ms as i128)
+}
+
+/// 记录对端 `peer` 的一个样本
+pub async fn record(gctx: &Arc<GlobalContext>, peer: &str, sample: ClockSample) {
+    let clocks = clocks(gctx).await;
+    let mut clock = clocks.entry(peer.to_string()).or_default();
+    clock.record(sample, SystemTime::timestamp());
+    tracing::debug!(
+        "🕰️ Clock sample from {}: offset={}ms delay={}ms",
+        peer,
+        sample.offset_ms,
+        sample.delay_ms
+    );
+}
+
+/// 对端 `peer` 的时钟偏差；没有样本时为 None
+pub async fn offset_ms(gctx: &Arc<GlobalContext>, peer: &str) -> Option<i64> {
+    let clocks = gctx.get::<PeerClocks>().await?;
+    clocks.get(peer).and_then(|c| c.offset_ms())
+}
+
+/// 所有已测量对端的偏差，按地址排序
+pub async fn offsets(gctx: &Arc<GlobalContext>) -> BTreeMap<String, i64> {
+    match gctx.get::<PeerClocks>().await {
+        Some(clocks) => clocks
+            .iter()
+            .filter_map(|c| c.offset_ms().map(|o| (c.key().clone(), o)))
+            .collect(),
+        None => BTreeMap::new(),
+    }
+}
+
+/// `peer` 签发的时间戳对应的本地时间
+pub async fn local_time_of(gctx: &Arc<GlobalContext>, peer: &str, timestamp: u128) -> u128 {
+    match offset_ms(gctx, peer).await {
+        Some(offset) => to_local(timestamp, offset),
+        None => timestamp,
+    }
+}
+
+/// 本地时间 `now` 在 `peer` 时钟下的值，用于判断其签发的记录是否过期
+pub async fn peer_now(gctx: &Arc<GlobalContext>, peer: &str, now: u128) -> u128 {
+    match offset_ms(gctx, peer).await {
+        Some(offset) => to_peer(now, offset),
+        None => now,
+    }
+}
+
+/// 定期 ping 所有直连对端以更新偏差
+pub fn schedule(scheduler: &Scheduler, gctx: Arc<GlobalContext>) {
+    scheduler.register_interval(
+        "clock_sync",
+        Duration::from_secs(CLOCK_SYNC_INTERVAL_SECS),
+        move || {
+            let gctx = gctx.clone();
+            async move {
+                for conn in connections::list(&gctx).await {
+                    if conn.peer.is_none() {
+                        continue;
+                    }
+                    let Some(ctx) = gctx
+                        .manager
+                        .find_entry(&conn.addr)
+                        .and_then(|entry| entry.context.clone())
+                    else {
+                        continue;
+                    };
+                    let timeout = Duration::from_millis(DEFAULT_TIMEOUT_MS);
+                    if let Err(e) = ping::ping(gctx.clone(), ctx, timeout).await {
+                        tracing::debug!("Clock sync ping to {} failed: {}", conn.addr, e);
+                    }
+                }
+            }
+        },
+    );
+}
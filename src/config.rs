@@ -0,0 +1,508 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use aex::connection::global::GlobalContext;
+use clap::Parser;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tracing_subscriber::{
+    EnvFilter, Registry, layer::SubscriberExt, reload, util::SubscriberInitExt,
+};
+
+use crate::{
+    admin::AdminRole,
+    cli::Opt,
+    fanout::FanoutConfig,
+    log_file::RotatingFile,
+    partition::PartitionConfig,
+    peer_maintenance::PeerMaintenanceConfig,
+    protocols::{
+        agent::AgentConfig,
+        limits::{EvictionPolicy, SharedMessageLimits},
+        ordering::OrderingConfig,
+        privacy::PrivacyConfig,
+        signing::SigningConfig,
+        wire_format::CodecConfig,
+    },
+    proxy::ProxyConfig,
+    reachability::ReachabilityConfig,
+    relay::RelayConfig,
+    resolver::ResolverConfig,
+    retention::RetentionConfig,
+    retry::NetworkConfig,
+    secure_link::SecureLinkConfig,
+    web::{auth::WebAuthConfig, keep_alive::KeepAliveConfig},
+};
+
+pub const DEFAULT_LOG_LEVEL: &str = "info";
+/// 配置文件变更检测间隔
+pub const CONFIG_RELOAD_INTERVAL_SECS: u64 = 5;
+
+/// 运行期可热更新的限流配置
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    /// 最大并发连接数（0 表示不限制）
+    pub max_connections: usize,
+    /// 最大入站连接数（0 表示不限制）
+    pub max_inbound: usize,
+    /// 最大出站连接数（0 表示不限制）
+    pub max_outbound: usize,
+    /// 连接数已满时的处理方式
+    pub eviction: EvictionPolicy,
+    /// 单条消息最大字节数（0 表示不限制）
+    pub max_message_bytes: usize,
+    /// 每个对端每秒允许的消息数（0 表示不限制）
+    pub messages_per_second: u32,
+    /// 单个对端累计多少次协议错误后断开连接（0 表示只记录不断开）
+    pub max_protocol_errors: u32,
+    /// HTTP 请求体最大字节数（0 表示使用默认的 16 MiB）
+    pub max_http_body_bytes: usize,
+}
+
+/// 会话密钥轮换策略
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionConfig {
+    /// 会话密钥最长使用时间（秒，0 表示不按时间轮换）
+    pub ttl_secs: u64,
+    /// 单个会话密钥最多加密的字节数（0 表示不按流量轮换）
+    pub max_bytes: u64,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 60 * 60,
+            max_bytes: 1024 * 1024 * 1024,
+        }
+    }
+}
+
+/// 带宽限速（字节/秒，0 表示不限制）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BandwidthConfig {
+    /// 全局上行
+    pub upload_bytes_per_sec: u64,
+    /// 全局下行
+    pub download_bytes_per_sec: u64,
+    /// 单连接上行
+    pub peer_upload_bytes_per_sec: u64,
+    /// 单连接下行
+    pub peer_download_bytes_per_sec: u64,
+}
+
+/// 允许使用远程管理通道的密钥
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdminKey {
+    /// 审计日志中显示的名称
+    pub name: String,
+    /// 压缩格式 secp256k1 公钥（hex），由 `zzp2p admin keygen` 生成
+    pub public_key: String,
+    pub role: AdminRole,
+}
+
+/// 远程管理通道（未配置任何密钥时关闭）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdminConfig {
+    pub keys: Vec<AdminKey>,
+    /// 请求签发时间与本机时间允许的最大偏差（秒）
+    pub max_clock_skew_secs: u64,
+}
+
+impl Default for AdminConfig {
+    fn default() -> Self {
+        Self {
+            keys: Vec::new(),
+            max_clock_skew_secs: crate::admin::DEFAULT_MAX_CLOCK_SKEW_SECS,
+        }
+    }
+}
+
+/// 节点配置文件（TOML，扩展名为 .json 时按 JSON 解析）
+///
+/// ```toml
+/// name = "node-1"
+/// ip = "0.0.0.0"
+/// port = 1090
+/// listen = ["[::]:1090", "1091"]
+/// data_dir = "/var/lib/zz"
+/// bootstrap = ["1.2.3.4:1090"]
+/// dns_seeds = ["seed.example.org"]
+/// min_peers = 3
+/// client_only = false
+/// log_level = "info"
+///
+/// [limits]
+/// max_connections = 128
+/// max_inbound = 96
+/// eviction = "lowest_score"
+/// messages_per_second = 50
+///
+/// [session]
+/// ttl_secs = 3600
+///
+/// [bandwidth]
+/// upload_bytes_per_sec = 262144
+/// peer_download_bytes_per_sec = 65536
+///
+/// [ordering]
+/// reorder_window = 64
+/// reorder_timeout_ms = 2000
+///
+/// [proxy]
+/// default = "socks5://127.0.0.1:1080"
+///
+/// [codec]
+/// prefer = "cbor"
+///
+/// [signing]
+/// policy = "layered"
+///
+/// [secure_link]
+/// mode = "required"
+///
+/// [resolver]
+/// doh = "https://cloudflare-dns.com/dns-query"
+///
+/// [retention]
+/// max_age_secs = 2592000
+/// max_total_bytes = 104857600
+///
+/// [peer_maintenance]
+/// interval_secs = 3600
+/// prune_after_days = 30
+///
+/// [reachability]
+/// idle_secs = 120
+///
+/// [partition]
+/// after_secs = 30
+///
+/// [fanout]
+/// threshold_bytes = 65536
+/// max_hops = 3
+///
+/// [relay]
+/// enabled = true
+/// client_bytes_per_sec = 65536
+///
+/// [web_auth]
+/// enabled = true
+/// allow_basic = true
+///
+/// [agent]
+/// user_agent = "my-app/1.2"
+/// min_version = "0.1.5"
+///
+/// [privacy]
+/// lan = "lan"
+///
+/// [[privacy.rules]]
+/// target = "10.8.0.0/16"
+/// visibility = "private"
+///
+/// [[admin.keys]]
+/// name = "alice"
+/// public_key = "02…"
+/// role = "owner"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub name: Option<String>,
+    pub ip: Option<String>,
+    pub port: Option<u16>,
+    /// 额外的监听地址，见 [`crate::listen`]
+    pub listen: Vec<String>,
+    pub data_dir: Option<String>,
+    pub bootstrap: Vec<String>,
+    /// DNS 种子域名
+    pub dns_seeds: Vec<String>,
+    /// 引导阶段的目标连接数
+    pub min_peers: Option<usize>,
+    /// 仅出站模式，见 [`crate::client_mode`]
+    pub client_only: bool,
+    pub log_level: Option<String>,
+    pub limits: LimitsConfig,
+    pub session: SessionConfig,
+    pub bandwidth: BandwidthConfig,
+    pub ordering: OrderingConfig,
+    pub proxy: ProxyConfig,
+    pub codec: CodecConfig,
+    /// 转发时的帧签名策略，见 [`crate::protocols::signing`]
+    pub signing: SigningConfig,
+    /// 节点间链路加密的协商模式，见 [`crate::secure_link`]
+    pub secure_link: SecureLinkConfig,
+    pub admin: AdminConfig,
+    pub network: NetworkConfig,
+    /// 端点可见范围，见 [`crate::protocols::privacy`]
+    pub privacy: PrivacyConfig,
+    /// 连接目标的域名解析，见 [`crate::resolver`]
+    pub resolver: ResolverConfig,
+    /// 消息保留策略与存储配额，见 [`crate::retention`]
+    pub retention: RetentionConfig,
+    /// 服务器列表的定期维护，见 [`crate::peer_maintenance`]
+    pub peer_maintenance: PeerMaintenanceConfig,
+    /// 直连对端的可达性探测，见 [`crate::reachability`]
+    pub reachability: ReachabilityConfig,
+    /// 网络分区检测与重新引导，见 [`crate::partition`]
+    pub partition: PartitionConfig,
+    /// 大帧转发的扇出限制，见 [`crate::fanout`]
+    pub fanout: FanoutConfig,
+    /// 中继节点模式的配额，见 [`crate::relay`]
+    pub relay: RelayConfig,
+    /// Web 页面与 API 的身份认证，见 [`crate::web::auth`]
+    pub web_auth: WebAuthConfig,
+    /// 握手中的 User-Agent 与对端最低软件版本，见 [`crate::protocols::agent`]
+    pub agent: AgentConfig,
+    /// 控制接口的持久连接，见 [`crate::web::keep_alive`]
+    pub keep_alive: KeepAliveConfig,
+}
+
+/// 保存在 GlobalContext 中的当前配置，热更新时整体替换
+pub type SharedConfig = Arc<RwLock<Config>>;
+
+/// 启动时指定的配置文件路径，保存在 GlobalContext 中供管理命令重新加载
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigFile(pub PathBuf);
+
+pub type LogReloadHandle = reload::Handle<EnvFilter, Registry>;
+
+static LOG_RELOAD_HANDLE: OnceCell<LogReloadHandle> = OnceCell::new();
+
+impl Config {
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        Self::parse(&text, path)
+    }
+
+    pub fn parse(text: &str, path: &Path) -> anyhow::Result<Self> {
+        let is_json = path
+            .extension()
+            .map(|ext| ext.eq_ignore_ascii_case("json"))
+            .unwrap_or(false);
+        if is_json {
+            Ok(serde_json::from_str(text)?)
+        } else {
+            Ok(toml::from_str(text)?)
+        }
+    }
+
+    pub fn log_level(&self) -> &str {
+        self.log_level.as_deref().unwrap_or(DEFAULT_LOG_LEVEL)
+    }
+
+    /// 将配置文件合并进命令行参数：命令行显式给出的值优先
+    pub fn merge_into(&self, opt: &mut Opt) {
+        let defaults = Opt::parse_from(["zzp2p"]);
+        if let Some(ref name) = self.name {
+            if opt.name == defaults.name {
+                opt.name = name.clone();
+            }
+        }
+        if let Some(ref ip) = self.ip {
+            if opt.ip == defaults.ip {
+                opt.ip = ip.clone();
+            }
+        }
+        if let Some(port) = self.port {
+            if opt.port == defaults.port {
+                opt.port = port;
+            }
+        }
+        if opt.listen.is_empty() {
+            opt.listen = self.listen.clone();
+        }
+        if opt.data_dir.is_none() {
+            opt.data_dir = self.data_dir.clone();
+        }
+        if opt.seeds.is_none() && !self.bootstrap.is_empty() {
+            opt.seeds = Some(self.bootstrap.join(","));
+        }
+        if opt.dns_seeds.is_empty() {
+            opt.dns_seeds = self.dns_seeds.clone();
+        }
+        if let Some(min_peers) = self.min_peers {
+            if opt.min_peers == defaults.min_peers {
+                opt.min_peers = min_peers;
+            }
+        }
+        if self.client_only {
+            opt.client_only = true;
+        }
+    }
+}
+
+/// 初始化全局日志，并保留可在运行期替换过滤级别的句柄
+pub fn init_tracing(level: &str) {
+    let filter = EnvFilter::try_new(level).unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_LEVEL));
+    let (layer, handle) = reload::Layer::new(filter);
+    if tracing_subscriber::registry()
+        .with(layer)
+        .with(tracing_subscriber::fmt::layer())
+        .try_init()
+        .is_ok()
+    {
+        let _ = LOG_RELOAD_HANDLE.set(handle);
+    }
+}
+
+/// 同 [`init_tracing`]，日志写入按大小轮转的文件（守护进程模式）
+pub fn init_tracing_to_file(level: &str, file: RotatingFile) {
+    init_tracing_to_writer(level, file);
+}
+
+/// 同 [`init_tracing`]，日志写入 `writer` 且不带颜色（日志文件、终端仪表盘）
+pub fn init_tracing_to_writer<W: std::io::Write + Send + 'static>(level: &str, writer: W) {
+    let filter = EnvFilter::try_new(level).unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_LEVEL));
+    let (layer, handle) = reload::Layer::new(filter);
+    if tracing_subscriber::registry()
+        .with(layer)
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(std::sync::Mutex::new(writer)),
+        )
+        .try_init()
+        .is_ok()
+    {
+        let _ = LOG_RELOAD_HANDLE.set(handle);
+    }
+}
+
+/// 运行期修改日志级别（未通过 `init_tracing` 初始化时无效果）
+pub fn set_log_level(level: &str) -> anyhow::Result<()> {
+    let handle = match LOG_RELOAD_HANDLE.get() {
+        Some(h) => h,
+        None => return Ok(()),
+    };
+    let filter = EnvFilter::try_new(level)?;
+    handle.reload(filter)?;
+    Ok(())
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// 后台轮询配置文件，发生变化时按 [`apply`] 热更新。
+pub fn watch(path: PathBuf, gctx: Arc<GlobalContext>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut last_modified = modified_at(&path);
+        let mut interval = tokio::time::interval(Duration::from_secs(CONFIG_RELOAD_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let current = modified_at(&path);
+            if current == last_modified {
+                continue;
+            }
+            last_modified = current;
+
+            if let Err(e) = reload(&path, &gctx).await {
+                tracing::error!("Failed to reload config {}: {:?}", path.display(), e);
+            }
+        }
+    })
+}
+
+/// 立即重新读取配置文件并应用
+pub async fn reload(path: &Path, gctx: &Arc<GlobalContext>) -> anyhow::Result<()> {
+    let next = Config::load(path)?;
+    apply(gctx, next).await;
+    Ok(())
+}
+
+/// 只在启动时读取、修改后需要重启才能生效的配置项
+pub fn restart_required(current: &Config, next: &Config) -> Vec<&'static str> {
+    let mut keys = Vec::new();
+    let mut check = |changed: bool, key: &'static str| {
+        if changed {
+            keys.push(key);
+        }
+    };
+    check(next.name != current.name, "name");
+    check(next.ip != current.ip, "ip");
+    check(next.port != current.port, "port");
+    check(next.listen != current.listen, "listen");
+    check(next.data_dir != current.data_dir, "data_dir");
+    check(next.bootstrap != current.bootstrap, "bootstrap");
+    check(next.dns_seeds != current.dns_seeds, "dns_seeds");
+    check(next.min_peers != current.min_peers, "min_peers");
+    check(next.client_only != current.client_only, "client_only");
+    keys
+}
+
+/// 应用新的配置。日志级别与消息限流推送给各自的运行时对象；其余各节
+/// （`[limits]`、`[session]`、`[bandwidth]`、`[relay]`、`[web_auth]`、`[keep_alive]` 等）
+/// 由使用方每次从 [`SharedConfig`] 读取，替换后立即生效。
+/// 只在启动时读取的配置项（见 [`restart_required`]）记录警告，重启后生效。
+pub async fn apply(gctx: &Arc<GlobalContext>, next: Config) {
+    let Some(shared) = gctx.get::<SharedConfig>().await else {
+        tracing::warn!("⚠️ No configuration in GlobalContext, ignoring reload");
+        return;
+    };
+    let mut guard = shared.write().await;
+    if next.log_level() != guard.log_level() {
+        match set_log_level(next.log_level()) {
+            Ok(_) => tracing::info!("🔧 Log level changed to {}", next.log_level()),
+            Err(e) => tracing::error!("Invalid log level {}: {:?}", next.log_level(), e),
+        }
+    }
+    if next.limits != guard.limits {
+        if let Some(limits) = gctx.get::<SharedMessageLimits>().await {
+            limits.update(&next.limits);
+        }
+    }
+
+    let sections = [
+        (next.limits != guard.limits, "limits"),
+        (next.session != guard.session, "session"),
+        (next.bandwidth != guard.bandwidth, "bandwidth"),
+        (next.ordering != guard.ordering, "ordering"),
+        (next.proxy != guard.proxy, "proxy"),
+        (next.codec != guard.codec, "codec"),
+        (next.signing != guard.signing, "signing"),
+        (next.secure_link != guard.secure_link, "secure_link"),
+        (next.admin != guard.admin, "admin"),
+        (next.network != guard.network, "network"),
+        (next.privacy != guard.privacy, "privacy"),
+        (next.resolver != guard.resolver, "resolver"),
+        (next.retention != guard.retention, "retention"),
+        (
+            next.peer_maintenance != guard.peer_maintenance,
+            "peer_maintenance",
+        ),
+        (next.reachability != guard.reachability, "reachability"),
+        (next.partition != guard.partition, "partition"),
+        (next.fanout != guard.fanout, "fanout"),
+        (next.relay != guard.relay, "relay"),
+        (next.web_auth != guard.web_auth, "web_auth"),
+        (next.agent != guard.agent, "agent"),
+        (next.keep_alive != guard.keep_alive, "keep_alive"),
+    ];
+    let updated: Vec<&str> = sections
+        .iter()
+        .filter(|(changed, _)| *changed)
+        .map(|(_, name)| *name)
+        .collect();
+    if !updated.is_empty() {
+        tracing::info!("🔧 Config updated: [{}]", updated.join("], ["));
+    }
+
+    let restart = restart_required(&guard, &next);
+    if !restart.is_empty() {
+        tracing::warn!(
+            "⚠️ Changes to {} require a restart and are not applied until then",
+            restart.join(", ")
+        );
+    }
+    *guard = next;
+}
@@ -0,0 +1,203 @@
+//! 当前连接的查询与手动断开
+//!
+//! `status` 只给出连接数，这里把 ConnectionManager 中的每个连接（入站 clients 与出站 servers）
+//! 整理成 [`PeerConnection`]：对端地址、传输方式、方向、累计流量、在线时长、最近一次 RTT
+//! 与握手中声明的特性和软件版本、链路协商出的密码套件，
+//! 供 `conns` 命令、控制接口 `GET /connections` 与 [`crate::node::Node::connections`] 使用。
+//! [`disconnect`] 按 `ip:port`、`ip`、节点地址或别名关闭匹配的连接。
+
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+
+use aex::{
+    connection::{entry::ConnectionEntry, global::GlobalContext},
+    time::SystemTime,
+};
+use serde::Serialize;
+
+use crate::{
+    journal, node,
+    protocols::{
+        agent::{PeerAgent, SoftwareInfo},
+        bandwidth,
+        capabilities::PeerMaxFrameSize,
+        commands::{node_registry::ConnectionDirection, ping::PeerLatency},
+        compression::PeerCapabilities,
+    },
+    secure_link::{CipherSuite, LinkSession},
+    transport::{self, Connection},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transport {
+    Tcp,
+    /// 经由 SOCKS5 代理的 TCP
+    Socks5,
+    /// 经由 HTTP CONNECT 代理的 TCP
+    HttpConnect,
+    Udp,
+    WebSocket,
+}
+
+impl std::fmt::Display for Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Transport::Tcp => "tcp",
+            Transport::Socks5 => "socks5",
+            Transport::HttpConnect => "http-connect",
+            Transport::Udp => "udp",
+            Transport::WebSocket => "websocket",
+        };
+        f.write_str(name)
+    }
+}
+
+/// 一个已建立连接的快照
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeerConnection {
+    /// 连接的对端 socket 地址
+    pub addr: SocketAddr,
+    /// 握手完成后得到的对端节点地址
+    pub peer: Option<String>,
+    pub transport: Transport,
+    pub direction: ConnectionDirection,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub uptime_secs: u64,
+    /// 最近一次 ping 的往返时间
+    pub rtt_ms: Option<u64>,
+    /// 对端声明的特性，见 `protocols::capabilities`
+    pub features: Vec<&'static str>,
+    /// 对端声明可接收的最大帧
+    pub max_frame_size: Option<u32>,
+    /// 对端声明的软件版本与 User-Agent，见 `protocols::agent`
+    pub agent: Option<SoftwareInfo>,
+    /// 链路握手协商出的密码套件，见 `secure_link`
+    pub cipher_suite: Option<CipherSuite>,
+}
+
+impl PeerConnection {
+    /// `target` 为 `ip:port`、`ip` 或节点地址时是否指向该连接
+    pub fn matches(&self, target: &str) -> bool {
+        if let Ok(addr) = target.parse::<SocketAddr>() {
+            return addr == self.addr;
+        }
+        if let Ok(ip) = target.parse::<std::net::IpAddr>() {
+            return ip == self.addr.ip();
+        }
+        self.peer.as_deref() == Some(target)
+    }
+}
+
+fn entries(gctx: &Arc<GlobalContext>) -> Vec<(Arc<ConnectionEntry>, ConnectionDirection)> {
+    let mut entries = Vec::new();
+    for bucket_ref in gctx.manager.connections.iter() {
+        let bi_conn = bucket_ref.value();
+        for e in bi_conn.clients.iter() {
+            entries.push((e.value().clone(), ConnectionDirection::Inbound));
+        }
+        for e in bi_conn.servers.iter() {
+            entries.push((e.value().clone(), ConnectionDirection::Outbound));
+        }
+    }
+    entries
+}
+
+/// 当前所有连接，按地址排序
+pub async fn list(gctx: &Arc<GlobalContext>) -> Vec<PeerConnection> {
+    let now_secs = (SystemTime::timestamp() / 1000) as u64;
+    let traffic: HashMap<String, (u64, u64)> = bandwidth::usage(gctx)
+        .await
+        .peers
+        .into_iter()
+        .map(|p| (p.peer, (p.download_bytes, p.upload_bytes)))
+        .collect();
+
+    let mut out = Vec::new();
+    for (entry, direction) in entries(gctx) {
+        let (peer, transport, caps, max_frame, agent, link, latency) = match &entry.context {
+            Some(ctx) => {
+                let guard = ctx.lock().await;
+                (
+                    guard.get::<String>(),
+                    transport::transport_of(&guard),
+                    guard.get::<PeerCapabilities>(),
+                    guard.get::<PeerMaxFrameSize>(),
+                    guard.get::<PeerAgent>(),
+                    guard.get::<LinkSession>(),
+                    guard.get::<PeerLatency>(),
+                )
+            }
+            None => (None, Transport::Tcp, None, None, None, None, None),
+        };
+        let (bytes_in, bytes_out) = traffic
+            .get(&entry.addr.to_string())
+            .copied()
+            .unwrap_or_default();
+        out.push(PeerConnection {
+            addr: entry.addr,
+            peer,
+            transport,
+            direction,
+            bytes_in,
+            bytes_out,
+            uptime_secs: now_secs.saturating_sub(entry.connected_at),
+            rtt_ms: latency.map(|l| l.0),
+            features: caps.map(|c| c.features()).unwrap_or_default(),
+            max_frame_size: max_frame.map(|m| m.0),
+            agent: agent.map(|a| a.0),
+            cipher_suite: link.map(|l| l.suite),
+        });
+    }
+    out.sort_by_key(|c| c.addr);
+    out
+}
+
+/// 关闭与 `target`（`ip:port`、`ip`、节点地址或别名）匹配的所有连接，返回被关闭的连接
+pub async fn disconnect(gctx: &Arc<GlobalContext>, target: &str) -> Vec<PeerConnection> {
+    let node = gctx.get::<Arc<node::Node>>().await;
+    let target = match &node {
+        Some(n) => n.registry.resolve_alias(target),
+        None => target.to_string(),
+    };
+    let matched: Vec<PeerConnection> = list(gctx)
+        .await
+        .into_iter()
+        .filter(|c| c.matches(&target))
+        .collect();
+
+    for conn in &matched {
+        close(gctx, conn, "closed by operator").await;
+    }
+    matched
+}
+
+/// 关闭地址为 `addr` 的连接（如超时），返回是否找到该连接
+pub async fn close_addr(gctx: &Arc<GlobalContext>, addr: SocketAddr, reason: &str) -> bool {
+    let Some(conn) = list(gctx).await.into_iter().find(|c| c.addr == addr) else {
+        return false;
+    };
+    close(gctx, &conn, reason).await;
+    true
+}
+
+async fn close(gctx: &Arc<GlobalContext>, conn: &PeerConnection, reason: &str) {
+    tracing::info!(
+        "✂️ Closing {:?} connection {} ({})",
+        conn.direction,
+        conn.addr,
+        reason
+    );
+    let inbound = conn.direction == ConnectionDirection::Inbound;
+    // 先关闭写端，对端立即感知断开而不必等待心跳超时
+    if let Some(entry) = gctx.manager.find_entry(&conn.addr) {
+        if let Some(ctx) = &entry.context {
+            ctx.close().await;
+        }
+    }
+    gctx.manager.remove(conn.addr, inbound);
+    if let (Some(node), Some(peer)) = (gctx.get::<Arc<node::Node>>().await, conn.peer.as_deref()) {
+        node.registry.disconnect(peer);
+    }
+    journal::record_disconnect(gctx, conn.peer.as_deref(), conn.addr, reason).await;
+}
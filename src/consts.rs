@@ -22,5 +22,16 @@ pub const DEFAULT_APP_DIR: &str = ".zz";
 pub const DEFAULT_APP_DIR_ADDRESS_JSON_FILE: &str = "address.json";
 pub const DEFAULT_APP_DIR_EXTERNAL_SERVER_LIST_JSON_FILE: &str = "external-server-list.json";
 pub const DEFAULT_APP_DIR_INNER_SERVER_LIST_JSON_FILE: &str = "inner-server-list.json";
+pub const DEFAULT_APP_DIR_ALIASES_JSON_FILE: &str = "aliases.json";
+pub const DEFAULT_APP_DIR_ACL_JSON_FILE: &str = "acl.json";
+pub const DEFAULT_APP_DIR_WEBHOOKS_JSON_FILE: &str = "webhooks.json";
+pub const DEFAULT_APP_DIR_IDENTITIES_JSON_FILE: &str = "identities.json";
+pub const DEFAULT_APP_DIR_GROUPS_JSON_FILE: &str = "groups.json";
+pub const DEFAULT_APP_DIR_VERIFIED_JSON_FILE: &str = "verified.json";
+pub const DEFAULT_APP_DIR_OUTBOX_JSON_FILE: &str = "outbox.json";
+pub const DEFAULT_APP_DIR_WEB_USERS_JSON_FILE: &str = "web_users.json";
+pub const DEFAULT_APP_DIR_SUCCESSORS_JSON_FILE: &str = "successors.json";
+pub const DEFAULT_APP_DIR_STORAGE_VERSION_FILE: &str = "storage-version.json";
+pub const DEFAULT_APP_DIR_HISTORY_FILE: &str = "history.txt";
 
 pub static PRE_HASH: std::sync::LazyLock<String> = std::sync::LazyLock::new(|| "0".repeat(32));
@@ -0,0 +1,534 @@
+//! 本地控制接口
+//!
+//! `zzp2p daemon` 在回环地址上监听一个极简的 HTTP/1.1 JSON 接口，
+//! `zzp2p send | status | peers` 等一次性子命令通过它与运行中的节点通信后退出。
+//!
+//! | 方法 | 路径      | 说明                                   |
+//! |------|-----------|----------------------------------------|
+//! | GET  | /status   | 节点状态快照 [`crate::status::NodeStatus`]：地址、版本、运行时长、启动阶段、监听地址、连接统计、已知节点及评分、待发送队列、各对端的帧数、错误、RTT 与时钟偏差、保留策略、中继与转发去重统计 |
+//! | GET  | /peers    | NodeRegistry 中的已知节点，按地址排序；支持 `limit`、`offset`、`since`、`filter`，见 [`crate::web::params`] |
+//! | GET  | /health   | 自检报告，见 [`crate::doctor`]；不健康时返回 503 |
+//! | GET  | /connections | 当前连接：方向、传输方式、流量、RTT |
+//! | POST | /disconnect | 关闭匹配的连接，body: `{"peer"}`     |
+//! | POST | /send     | 发送文本消息，body: `{"to","message"}`；接收方不可达时放入发件箱（`queued` 为 true） |
+//! | GET  | /outbox   | 发件箱中待发送的消息，见 [`crate::outbox`] |
+//! | GET  | /delivery/{request_id} | 发送的投递状态：`failed`（附对端回复的错误码）、`pending` 或 `unknown`，见 [`crate::protocols::commands::error`] |
+//! | GET  | /acl      | 访问控制规则                           |
+//! | POST | /acl/ban、/acl/unban、/acl/allow、/acl/disallow | 修改规则，body: `{"target"}` |
+//! | POST | /acl/mode | 切换模式，body: `{"mode"}`             |
+//! | GET  | /webhooks | 已登记的 webhook（不含密钥）           |
+//! | POST | /webhooks | 登记 webhook，body: `{"url","secret"?,"events"?}`，返回密钥 |
+//! | DELETE | /webhooks/{id} | 删除 webhook                      |
+//! | POST | /admin    | 签名的管理命令，见 [`crate::admin`]    |
+//! | GET  | /servers  | 持久化的服务器列表，见 [`crate::server_list`] |
+//! | POST | /servers  | 添加记录并立即拨号，body: `{"endpoint","pin"?}` |
+//! | POST | /servers/pin、/servers/unpin | 固定 / 取消固定记录，body: `{"endpoint"}` |
+//! | DELETE | /servers/{ip:port} | 删除记录                       |
+//! | GET  | /relay    | 中继配额与每个地址的中继记账，见 [`crate::relay`] |
+
+use std::{net::SocketAddr, sync::Arc};
+
+use aex::connection::global::GlobalContext;
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{
+    admin, connections, doctor, node, outbox,
+    protocols::{
+        acl::{self, AclMode, AclTarget},
+        commands::error as remote_error,
+    },
+    relay, server_list, status,
+    web::{
+        keep_alive::{self, RequestHead},
+        params::{self, ListQuery},
+    },
+    webhook::{self, Webhook},
+};
+
+/// 控制接口默认端口 = P2P 端口 + 偏移
+pub const CONTROL_PORT_OFFSET: u16 = 1;
+/// 控制请求的最大长度
+pub const MAX_CONTROL_REQUEST: usize = 1024 * 1024;
+
+/// 由 P2P 端口推导默认控制地址（仅监听回环地址）
+pub fn default_addr(p2p_port: u16) -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], p2p_port.wrapping_add(CONTROL_PORT_OFFSET)))
+}
+
+/// 启动控制接口，直到监听失败才返回
+pub async fn serve(addr: SocketAddr, gctx: Arc<GlobalContext>) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    serve_listener(listener, gctx).await
+}
+
+/// 在已绑定的监听 socket 上提供控制接口：函数返回前连接就已可以建立，
+/// 调用方（如测试）绑定端口 0 后无需等待
+pub async fn serve_listener(listener: TcpListener, gctx: Arc<GlobalContext>) -> anyhow::Result<()> {
+    tracing::info!(
+        "🛠️ Control API listening on http://{}",
+        listener.local_addr()?
+    );
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let gctx = gctx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, gctx).await {
+                tracing::warn!("Control request from {} failed: {:?}", peer, e);
+            }
+        });
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    head: RequestHead,
+    body: Vec<u8>,
+}
+
+fn find_header_end(buf: &[u8]) -> Option<usize> {
+    buf.windows(4).position(|w| w == b"\r\n\r\n").map(|p| p + 4)
+}
+
+async fn read_request<R: AsyncBufRead + Unpin>(reader: &mut R) -> anyhow::Result<Request> {
+    let mut head = Vec::new();
+    loop {
+        let start = head.len();
+        let n = reader.read_until(b'\n', &mut head).await?;
+        if n == 0 {
+            return Err(anyhow::anyhow!("Connection closed before request head"));
+        }
+        if head.len() > MAX_CONTROL_REQUEST {
+            return Err(anyhow::anyhow!("Request head too large"));
+        }
+        if matches!(&head[start..], b"\r\n" | b"\n") {
+            // 请求之间多余的空行忽略，请求头之后的空行结束请求头
+            if start == 0 {
+                head.clear();
+                continue;
+            }
+            break;
+        }
+    }
+
+    let head = String::from_utf8_lossy(&head).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+    let version = request_line.next().unwrap_or("HTTP/1.1").to_string();
+    let headers: Vec<(&str, &str)> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim(), v.trim()))
+        .collect();
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.to_string())
+    };
+    let content_length = header("content-length")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    if content_length > MAX_CONTROL_REQUEST {
+        return Err(anyhow::anyhow!(
+            "Request body too large: {}",
+            content_length
+        ));
+    }
+
+    // 只读取本请求的 body，之后的字节属于同一连接上的下一个请求
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body).await?;
+
+    Ok(Request {
+        method,
+        path,
+        head: RequestHead {
+            version,
+            connection: header("connection"),
+        },
+        body,
+    })
+}
+
+/// 写出 JSON 响应；`keep_open` 为 false 时回写 `Connection: close`
+async fn write_response<W: AsyncWrite + Unpin>(
+    stream: &mut W,
+    status: u16,
+    body: &Value,
+    keep_open: bool,
+) -> anyhow::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    let body = body.to_string();
+    let connection = if keep_open { "keep-alive" } else { "close" };
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: {}\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        connection,
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// 连接上的请求循环，见 [`keep_alive`]
+async fn handle_connection(stream: TcpStream, gctx: Arc<GlobalContext>) -> anyhow::Result<()> {
+    let policy = keep_alive::policy(&gctx).await;
+    let mut stream = BufReader::new(stream);
+    let mut served = 0;
+    while keep_alive::wait_for_request(&mut stream, policy.idle_timeout()).await {
+        let request = read_request(&mut stream).await?;
+        served += 1;
+        let keep_open = policy.keep_open(served, &request.head);
+        let (status, body) = route(&gctx, &request).await;
+        write_response(stream.get_mut(), status, &body, keep_open).await?;
+        if !keep_open {
+            break;
+        }
+    }
+    Ok(())
+}
+
+async fn route(gctx: &Arc<GlobalContext>, request: &Request) -> (u16, Value) {
+    match (request.method.as_str(), params::path_only(&request.path)) {
+        ("GET", "/status") => (200, status_json(gctx).await),
+        ("GET", "/peers") => peers_json(gctx, &request.path).await,
+        ("GET", "/health") => {
+            let report = doctor::run(gctx).await;
+            let status = if report.is_healthy() { 200 } else { 503 };
+            (
+                status,
+                json!({"success": report.is_healthy(), "report": report}),
+            )
+        }
+        ("GET", "/connections") => (
+            200,
+            json!({"success": true, "connections": connections::list(gctx).await}),
+        ),
+        ("POST", "/disconnect") => disconnect_json(gctx, &request.body).await,
+        ("POST", "/send") => send_json(gctx, &request.body).await,
+        ("GET", "/acl") => (
+            200,
+            json!({"success": true, "acl": acl::snapshot(gctx).await}),
+        ),
+        ("POST", "/acl/mode") => acl_mode_json(gctx, &request.body).await,
+        ("POST", path) if path.starts_with("/acl/") => {
+            acl_json(gctx, &path["/acl/".len()..], &request.body).await
+        }
+        ("GET", "/webhooks") => {
+            let hooks: Vec<Value> = webhook::list(gctx)
+                .await
+                .iter()
+                .map(Webhook::redacted)
+                .collect();
+            (200, json!({"success": true, "webhooks": hooks}))
+        }
+        ("POST", "/webhooks") => webhook_json(gctx, &request.body).await,
+        ("POST", "/admin") => admin::handle(gctx, &request.body).await,
+        ("GET", "/servers") => match server_list::list(gctx).await {
+            Ok(servers) => (200, json!({"success": true, "servers": servers})),
+            Err(e) => (503, json!({"success": false, "error": e.to_string()})),
+        },
+        ("POST", "/servers") => server_add_json(gctx, &request.body).await,
+        ("POST", path) if path.starts_with("/servers/") => {
+            server_pin_json(gctx, &path["/servers/".len()..], &request.body).await
+        }
+        ("GET", "/outbox") => {
+            let drafts = outbox::list(gctx).await;
+            (200, json!({"success": true, "drafts": drafts}))
+        }
+        ("GET", path) if path.starts_with("/delivery/") => {
+            delivery_json(gctx, &path["/delivery/".len()..]).await
+        }
+        ("GET", "/relay") => {
+            let report = relay::report(gctx).await;
+            (200, json!({"success": true, "relay": report}))
+        }
+        ("DELETE", path) if path.starts_with("/servers/") => {
+            server_remove_json(gctx, &path["/servers/".len()..]).await
+        }
+        ("DELETE", path) if path.starts_with("/webhooks/") => {
+            match webhook::remove(gctx, &path["/webhooks/".len()..]).await {
+                Some(hook) => (200, json!({"success": true, "removed": hook.redacted()})),
+                None => (404, json!({"success": false, "error": "No such webhook"})),
+            }
+        }
+        _ => (404, json!({"success": false, "error": "Not found"})),
+    }
+}
+
+async fn status_json(gctx: &Arc<GlobalContext>) -> Value {
+    match serde_json::to_value(status::collect(gctx).await) {
+        Ok(Value::Object(mut fields)) => {
+            fields.insert("success".to_string(), json!(true));
+            Value::Object(fields)
+        }
+        Ok(_) => json!({"success": false, "error": "Unexpected status shape"}),
+        Err(e) => json!({"success": false, "error": e.to_string()}),
+    }
+}
+
+async fn peers_json(gctx: &Arc<GlobalContext>, path: &str) -> (u16, Value) {
+    let query = match ListQuery::parse(path) {
+        Ok(q) => q,
+        Err(e) => return (400, json!({"success": false, "error": e})),
+    };
+    let mut entries = match gctx.get::<Arc<node::Node>>().await {
+        Some(n) => n.registry.get_nodes(),
+        None => vec![],
+    };
+    entries.sort_by(|a, b| a.address.cmp(&b.address));
+    let peers: Vec<(_, Vec<String>)> = entries
+        .into_iter()
+        .map(|entry| {
+            let mut seeds: Vec<String> = entry.seeds.keys().map(|s| s.to_string()).collect();
+            seeds.sort();
+            (entry, seeds)
+        })
+        .collect();
+    let page = query.page(peers, |(entry, seeds)| {
+        let mut fields = vec![entry.address.as_str()];
+        fields.extend(seeds.iter().map(|s| s.as_str()));
+        query.is_since(entry.last_seen as i64) && query.is_match(&fields)
+    });
+    let peers: Vec<Value> = page
+        .items
+        .iter()
+        .map(|(entry, seeds)| {
+            json!({
+                "address": entry.address,
+                "connected": entry.is_connected,
+                "scope": format!("{:?}", entry.scope),
+                "last_seen": entry.last_seen,
+                "seeds": seeds,
+            })
+        })
+        .collect();
+    (
+        200,
+        json!({"success": true, "peers": peers, "page": page.meta()}),
+    )
+}
+
+async fn send_json(gctx: &Arc<GlobalContext>, body: &[u8]) -> (u16, Value) {
+    let req: Value = serde_json::from_slice(body).unwrap_or_default();
+    let to = req.get("to").and_then(|v| v.as_str()).unwrap_or("");
+    let message = req.get("message").and_then(|v| v.as_str()).unwrap_or("");
+    if to.is_empty() || message.is_empty() {
+        return (
+            400,
+            json!({"success": false, "error": "Missing 'to' or 'message'"}),
+        );
+    }
+    match outbox::send_or_queue(gctx, to.to_string(), message.to_string()).await {
+        Ok(outbox::Delivery::Sent(request_id)) => {
+            (200, json!({"success": true, "request_id": request_id}))
+        }
+        Ok(outbox::Delivery::Queued(draft)) => (
+            200,
+            json!({"success": true, "queued": true, "draft": draft.id, "receiver": draft.receiver}),
+        ),
+        Err(e) => (500, json!({"success": false, "error": e.to_string()})),
+    }
+}
+
+async fn delivery_json(gctx: &Arc<GlobalContext>, request_id: &str) -> (u16, Value) {
+    let Ok(request_id) = request_id.parse::<u64>() else {
+        return (
+            400,
+            json!({"success": false, "error": "Invalid request_id"}),
+        );
+    };
+    match serde_json::to_value(remote_error::delivery_status(gctx, request_id).await) {
+        Ok(Value::Object(mut fields)) => {
+            fields.insert("success".to_string(), json!(true));
+            fields.insert("request_id".to_string(), json!(request_id));
+            (200, Value::Object(fields))
+        }
+        Ok(_) => (
+            500,
+            json!({"success": false, "error": "Unexpected status shape"}),
+        ),
+        Err(e) => (500, json!({"success": false, "error": e.to_string()})),
+    }
+}
+
+async fn disconnect_json(gctx: &Arc<GlobalContext>, body: &[u8]) -> (u16, Value) {
+    let req: Value = serde_json::from_slice(body).unwrap_or_default();
+    let peer = req.get("peer").and_then(|v| v.as_str()).unwrap_or("");
+    if peer.is_empty() {
+        return (400, json!({"success": false, "error": "Missing 'peer'"}));
+    }
+    let closed = connections::disconnect(gctx, peer).await;
+    if closed.is_empty() {
+        return (
+            404,
+            json!({"success": false, "error": format!("No connection matches {}", peer)}),
+        );
+    }
+    (200, json!({"success": true, "closed": closed}))
+}
+
+async fn acl_json(gctx: &Arc<GlobalContext>, op: &str, body: &[u8]) -> (u16, Value) {
+    let req: Value = serde_json::from_slice(body).unwrap_or_default();
+    let target = match AclTarget::parse(req.get("target").and_then(|v| v.as_str()).unwrap_or("")) {
+        Ok(t) => t,
+        Err(e) => return (400, json!({"success": false, "error": e})),
+    };
+    let changed = match op {
+        "ban" => acl::update(gctx, |acl| acl.ban(target.clone())).await,
+        "unban" => acl::update(gctx, |acl| acl.unban(&target)).await,
+        "allow" => acl::update(gctx, |acl| acl.allow(target.clone())).await,
+        "disallow" => acl::update(gctx, |acl| acl.disallow(&target)).await,
+        _ => return (404, json!({"success": false, "error": "Not found"})),
+    };
+    (
+        200,
+        json!({"success": true, "target": target, "changed": changed}),
+    )
+}
+
+fn endpoint_of(body: &[u8]) -> Result<SocketAddr, String> {
+    let req: Value = serde_json::from_slice(body).unwrap_or_default();
+    server_list::parse_endpoint(req.get("endpoint").and_then(|v| v.as_str()).unwrap_or(""))
+        .map_err(|e| e.to_string())
+}
+
+async fn server_add_json(gctx: &Arc<GlobalContext>, body: &[u8]) -> (u16, Value) {
+    let endpoint = match endpoint_of(body) {
+        Ok(e) => e,
+        Err(e) => return (400, json!({"success": false, "error": e})),
+    };
+    let req: Value = serde_json::from_slice(body).unwrap_or_default();
+    let pin = req.get("pin").and_then(|v| v.as_bool()).unwrap_or(false);
+    match server_list::add(gctx, endpoint, pin).await {
+        Ok(added) => (
+            200,
+            json!({"success": true, "endpoint": endpoint, "added": added, "pinned": pin}),
+        ),
+        Err(e) => (503, json!({"success": false, "error": e.to_string()})),
+    }
+}
+
+async fn server_pin_json(gctx: &Arc<GlobalContext>, op: &str, body: &[u8]) -> (u16, Value) {
+    let pinned = match op {
+        "pin" => true,
+        "unpin" => false,
+        _ => return (404, json!({"success": false, "error": "Not found"})),
+    };
+    let endpoint = match endpoint_of(body) {
+        Ok(e) => e,
+        Err(e) => return (400, json!({"success": false, "error": e})),
+    };
+    match server_list::set_pinned(gctx, endpoint, pinned).await {
+        Ok(true) => (
+            200,
+            json!({"success": true, "endpoint": endpoint, "pinned": pinned}),
+        ),
+        Ok(false) => (404, json!({"success": false, "error": "No such server"})),
+        Err(e) => (503, json!({"success": false, "error": e.to_string()})),
+    }
+}
+
+async fn server_remove_json(gctx: &Arc<GlobalContext>, endpoint: &str) -> (u16, Value) {
+    let endpoint = match server_list::parse_endpoint(endpoint) {
+        Ok(e) => e,
+        Err(e) => return (400, json!({"success": false, "error": e.to_string()})),
+    };
+    match server_list::remove(gctx, endpoint).await {
+        Ok(true) => (200, json!({"success": true, "removed": endpoint})),
+        Ok(false) => (404, json!({"success": false, "error": "No such server"})),
+        Err(e) => (503, json!({"success": false, "error": e.to_string()})),
+    }
+}
+
+async fn acl_mode_json(gctx: &Arc<GlobalContext>, body: &[u8]) -> (u16, Value) {
+    let req: Value = serde_json::from_slice(body).unwrap_or_default();
+    match req
+        .get("mode")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .parse::<AclMode>()
+    {
+        Ok(mode) => {
+            acl::update(gctx, |acl| acl.mode = mode).await;
+            (200, json!({"success": true, "mode": mode}))
+        }
+        Err(e) => (400, json!({"success": false, "error": e})),
+    }
+}
+
+async fn webhook_json(gctx: &Arc<GlobalContext>, body: &[u8]) -> (u16, Value) {
+    let req: Value = serde_json::from_slice(body).unwrap_or_default();
+    let url = req.get("url").and_then(|v| v.as_str()).unwrap_or("");
+    if url.is_empty() {
+        return (400, json!({"success": false, "error": "Missing 'url'"}));
+    }
+    let secret = req
+        .get("secret")
+        .and_then(|v| v.as_str())
+        .map(str::to_string);
+    let events: Vec<String> = req
+        .get("events")
+        .and_then(|v| v.as_array())
+        .map(|a| {
+            a.iter()
+                .filter_map(|e| e.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+    match webhook::register(gctx, url, secret, events).await {
+        Ok(hook) => (200, json!({"success": true, "webhook": hook})),
+        Err(e) => (400, json!({"success": false, "error": e.to_string()})),
+    }
+}
+
+/// 一次性子命令使用的客户端：发送请求并返回 (状态码, JSON)
+pub async fn request(
+    addr: SocketAddr,
+    method: &str,
+    path: &str,
+    body: Option<Value>,
+) -> anyhow::Result<(u16, Value)> {
+    let mut stream = TcpStream::connect(addr)
+        .await
+        .map_err(|e| anyhow::anyhow!("Cannot reach daemon at {}: {}", addr, e))?;
+    let body = body.map(|b| b.to_string()).unwrap_or_default();
+    let request = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        method,
+        path,
+        addr,
+        body.len(),
+        body
+    );
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    let head_end = find_header_end(&response)
+        .ok_or_else(|| anyhow::anyhow!("Malformed response from daemon"))?;
+    let status = String::from_utf8_lossy(&response[..head_end])
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse::<u16>().ok())
+        .ok_or_else(|| anyhow::anyhow!("Missing status code in daemon response"))?;
+    let json = serde_json::from_slice(&response[head_end..])?;
+    Ok((status, json))
+}
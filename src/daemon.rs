@@ -0,0 +1,114 @@
+//! 守护进程模式的运行支撑：pidfile 与日志文件位置
+//!
+//! `zzp2p daemon` 不读取标准输入，启动时写入 pidfile（默认 `<data_dir>/zzp2p.pid`），
+//! 日志写入按大小轮转的文件（默认 `<data_dir>/zzp2p.log`，`--log-file -` 输出到标准输出，
+//! 交给 systemd/launchd 收集）。进程不会自行 fork，由服务管理器负责放到后台，
+//! 示例见 `contrib/`。
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use crate::{cli::Opt, log_file::DAEMON_LOG_FILE};
+
+/// pidfile 默认文件名
+pub const PID_FILE: &str = "zzp2p.pid";
+
+pub fn pid_file_path(opt: &Opt) -> PathBuf {
+    match opt.pid_file.as_deref() {
+        Some(path) => PathBuf::from(path),
+        None => opt.app_dir().join(PID_FILE),
+    }
+}
+
+/// 守护进程的日志文件；`None` 表示输出到标准输出
+pub fn log_file_path(opt: &Opt) -> Option<PathBuf> {
+    match opt.log_file.as_deref() {
+        Some("-") => None,
+        Some(path) => Some(PathBuf::from(path)),
+        None => Some(opt.app_dir().join(DAEMON_LOG_FILE)),
+    }
+}
+
+pub fn read_pid(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// 进程是否仍在运行
+#[cfg(unix)]
+pub fn process_alive(pid: u32) -> bool {
+    Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// 进程是否仍在运行
+#[cfg(windows)]
+pub fn process_alive(pid: u32) -> bool {
+    Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+        .stderr(Stdio::null())
+        .output()
+        .is_ok_and(|o| String::from_utf8_lossy(&o.stdout).contains(&pid.to_string()))
+}
+
+/// 进程是否仍在运行（无法判断的平台一律视为已退出）
+#[cfg(not(any(unix, windows)))]
+pub fn process_alive(_pid: u32) -> bool {
+    false
+}
+
+/// 进程存活期间持有的 pidfile，drop 时删除
+pub struct PidFile {
+    path: PathBuf,
+    pid: u32,
+}
+
+impl PidFile {
+    /// 写入当前进程号；pidfile 中记录的进程仍在运行时返回错误，已退出的视为残留并覆盖
+    pub fn create(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        let pid = std::process::id();
+        if let Some(existing) = read_pid(&path) {
+            if existing != pid && process_alive(existing) {
+                anyhow::bail!(
+                    "another instance is already running (pid {}, {})",
+                    existing,
+                    path.display()
+                );
+            }
+            tracing::warn!(
+                "Replacing stale pidfile {} (pid {})",
+                path.display(),
+                existing
+            );
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, format!("{}\n", pid))?;
+        Ok(Self { path, pid })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        // 文件已被新实例接管时不删除
+        if read_pid(&self.path) == Some(self.pid) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
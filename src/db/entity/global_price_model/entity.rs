@@ -1,6 +1,5 @@
-
-use sea_orm::entity::prelude::*;
 use chrono::Utc;
+use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "global_price_model")]
@@ -34,4 +33,3 @@ impl RelationTrait for Relation {
 }
 
 impl ActiveModelBehavior for ActiveModel {}
-
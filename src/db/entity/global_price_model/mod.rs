@@ -1,4 +1,2 @@
-
 pub mod entity;
 pub mod store;
-
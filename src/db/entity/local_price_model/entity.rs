@@ -1,6 +1,5 @@
-
-use sea_orm::entity::prelude::*;
 use chrono::Utc;
+use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "local_price_model")]
@@ -31,4 +30,3 @@ impl RelationTrait for Relation {
 }
 
 impl ActiveModelBehavior for ActiveModel {}
-
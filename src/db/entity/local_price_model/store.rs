@@ -1,6 +1,5 @@
-
-use sea_orm::entity::*;
 use super::entity::Model;
+use sea_orm::entity::*;
 
 pub struct Store {}
 
@@ -9,4 +8,3 @@ impl Store {
         Self {}
     }
 }
-
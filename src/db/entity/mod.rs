@@ -7,6 +7,7 @@ pub mod epoch_allocation;
 pub mod epoch_reward;
 pub mod meta;
 pub mod node;
+pub mod peer_record;
 pub mod public_server;
 pub mod tick;
 pub mod transaction;
@@ -0,0 +1,31 @@
+use sea_orm::entity::prelude::*;
+
+/// 持久化的 `NodeRecord`：`data` 为完整记录（JSON），其余列用于索引与查询
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "peer_record")]
+pub struct Model {
+    /// `inner` 或 `external`
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub scope: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub endpoint: String,
+    /// 最近一次成功通信（秒）
+    pub last_seen: i64,
+    pub score: f64,
+    pub is_available: bool,
+    /// 支持的协议（JSON 数组）
+    pub protocols: String,
+    pub data: String,
+    pub updated_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        match *self {}
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
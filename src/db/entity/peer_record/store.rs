@@ -0,0 +1,263 @@
+use std::collections::HashSet;
+use std::net::SocketAddr;
+
+use aex::connection::protocol::Protocol;
+use anyhow::Result;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder,
+    QuerySelect, Schema, Set, Statement,
+};
+
+use crate::db::defines::StoreFromConnection;
+use crate::record::NodeRecord;
+
+pub use super::entity::{ActiveModel, Column, Entity, Model};
+
+pub struct PeerRecordStore<'a, C: ConnectionTrait + Send + Sync + 'a> {
+    db: &'a C,
+}
+
+impl<'a, C: ConnectionTrait + Send + Sync + 'a> StoreFromConnection<'a, C>
+    for PeerRecordStore<'a, C>
+{
+    fn new(db: &'a C) -> Self {
+        Self { db }
+    }
+}
+
+fn to_active_model(scope: &str, record: &NodeRecord) -> Result<ActiveModel> {
+    Ok(ActiveModel {
+        scope: Set(scope.to_string()),
+        endpoint: Set(record.endpoint.to_string()),
+        last_seen: Set(record.last_seen.timestamp()),
+        score: Set(record.score()),
+        is_available: Set(record.is_available),
+        protocols: Set(serde_json::to_string(&record.protocols)?),
+        data: Set(serde_json::to_string(record)?),
+        updated_at: Set(chrono::Utc::now().timestamp()),
+    })
+}
+
+fn decode_rows(rows: Vec<Model>) -> Vec<NodeRecord> {
+    rows.into_iter()
+        .filter_map(|m| match serde_json::from_str(&m.data) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                tracing::warn!("Skipping corrupt peer record {}: {}", m.endpoint, e);
+                None
+            }
+        })
+        .collect()
+}
+
+impl<'a, C: ConnectionTrait + Send + Sync + 'a> PeerRecordStore<'a, C> {
+    /// 建表并创建 last_seen / score 索引
+    pub async fn ensure_schema(&self) -> Result<()> {
+        let backend = self.db.get_database_backend();
+        let schema = Schema::new(backend);
+        let mut table = schema.create_table_from_entity(Entity);
+        self.db
+            .execute(backend.build(table.if_not_exists()))
+            .await?;
+        for sql in [
+            "CREATE INDEX IF NOT EXISTS idx_peer_record_last_seen ON peer_record (scope, last_seen)",
+            "CREATE INDEX IF NOT EXISTS idx_peer_record_score ON peer_record (scope, score)",
+        ] {
+            self.db
+                .execute(Statement::from_string(backend, sql.to_string()))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// 插入或覆盖一条记录
+    pub async fn upsert(&self, scope: &str, record: &NodeRecord) -> Result<()> {
+        Entity::insert(to_active_model(scope, record)?)
+            .on_conflict(
+                OnConflict::columns([Column::Scope, Column::Endpoint])
+                    .update_columns([
+                        Column::LastSeen,
+                        Column::Score,
+                        Column::IsAvailable,
+                        Column::Protocols,
+                        Column::Data,
+                        Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec(self.db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn remove(&self, scope: &str, endpoint: &SocketAddr) -> Result<()> {
+        Entity::delete_many()
+            .filter(Column::Scope.eq(scope))
+            .filter(Column::Endpoint.eq(endpoint.to_string()))
+            .exec(self.db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn load(&self, scope: &str) -> Result<HashSet<NodeRecord>> {
+        let rows = Entity::find()
+            .filter(Column::Scope.eq(scope))
+            .all(self.db)
+            .await?;
+        Ok(decode_rows(rows).into_iter().collect())
+    }
+
+    pub async fn count(&self, scope: &str) -> Result<u64> {
+        Ok(Entity::find()
+            .filter(Column::Scope.eq(scope))
+            .count(self.db)
+            .await?)
+    }
+
+    /// 评分最高的 `limit` 个可用记录（评分相同时最近见过的优先），可按协议过滤
+    pub async fn best(
+        &self,
+        scope: &str,
+        limit: u64,
+        protocol: Option<&Protocol>,
+    ) -> Result<Vec<NodeRecord>> {
+        let mut query = Entity::find()
+            .filter(Column::Scope.eq(scope))
+            .filter(Column::IsAvailable.eq(true));
+        if let Some(protocol) = protocol {
+            query = query.filter(Column::Protocols.contains(serde_json::to_string(protocol)?));
+        }
+        let rows = query
+            .order_by_desc(Column::Score)
+            .order_by_desc(Column::LastSeen)
+            .limit(limit)
+            .all(self.db)
+            .await?;
+        Ok(decode_rows(rows))
+    }
+
+    /// 最近见过的 `limit` 个记录
+    pub async fn recent(&self, scope: &str, limit: u64) -> Result<Vec<NodeRecord>> {
+        let rows = Entity::find()
+            .filter(Column::Scope.eq(scope))
+            .order_by_desc(Column::LastSeen)
+            .limit(limit)
+            .all(self.db)
+            .await?;
+        Ok(decode_rows(rows))
+    }
+
+    /// 删除 `before`（秒）之前最后见过的记录并回收空间，返回删除的条数
+    pub async fn compact(&self, before: i64) -> Result<u64> {
+        let deleted = Entity::delete_many()
+            .filter(Column::LastSeen.lt(before))
+            .exec(self.db)
+            .await?
+            .rows_affected;
+        let backend = self.db.get_database_backend();
+        if backend == sea_orm::DatabaseBackend::Sqlite {
+            self.db
+                .execute(Statement::from_string(backend, "VACUUM".to_string()))
+                .await?;
+        }
+        Ok(deleted)
+    }
+}
+
+/// ----------------------
+/// 内置单元测试
+/// ----------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use sea_orm::{Database, DatabaseConnection};
+
+    async fn setup_db() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        PeerRecordStore::new(&db).ensure_schema().await.unwrap();
+        db
+    }
+
+    fn record(port: u16, ok: u64, failed: u64, days_ago: i64) -> NodeRecord {
+        let mut r = NodeRecord::new(format!("10.0.0.1:{}", port).parse().unwrap());
+        r.tries = (ok, failed);
+        r.last_seen = Utc::now() - Duration::days(days_ago);
+        r
+    }
+
+    #[tokio::test]
+    async fn test_upsert_and_load() {
+        let db = setup_db().await;
+        let store = PeerRecordStore::new(&db);
+
+        let mut r = record(1, 1, 0, 0);
+        store.upsert("inner", &r).await.unwrap();
+        r.tries = (5, 5);
+        store.upsert("inner", &r).await.unwrap();
+        store.upsert("external", &r).await.unwrap();
+
+        let inner = store.load("inner").await.unwrap();
+        assert_eq!(inner.len(), 1);
+        assert_eq!(inner.iter().next().unwrap().tries, (5, 5));
+        assert_eq!(store.count("external").await.unwrap(), 1);
+
+        store.remove("inner", &r.endpoint).await.unwrap();
+        assert_eq!(store.count("inner").await.unwrap(), 0);
+        assert_eq!(store.count("external").await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_best_orders_by_score() {
+        let db = setup_db().await;
+        let store = PeerRecordStore::new(&db);
+        store.upsert("external", &record(1, 1, 9, 0)).await.unwrap();
+        store.upsert("external", &record(2, 9, 1, 0)).await.unwrap();
+        store.upsert("external", &record(3, 5, 5, 0)).await.unwrap();
+        let mut unavailable = record(4, 10, 0, 0);
+        unavailable.is_available = false;
+        store.upsert("external", &unavailable).await.unwrap();
+        store.upsert("inner", &record(5, 10, 0, 0)).await.unwrap();
+
+        let best = store.best("external", 2, None).await.unwrap();
+        let ports: Vec<u16> = best.iter().map(|r| r.endpoint.port()).collect();
+        assert_eq!(ports, vec![2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_best_filters_by_protocol() {
+        let db = setup_db().await;
+        let store = PeerRecordStore::new(&db);
+        let mut http_only = record(1, 9, 0, 0);
+        http_only.protocols = [Protocol::Http].into_iter().collect();
+        store.upsert("external", &http_only).await.unwrap();
+        let mut tcp_only = record(2, 1, 0, 0);
+        tcp_only.protocols = [Protocol::Tcp].into_iter().collect();
+        store.upsert("external", &tcp_only).await.unwrap();
+
+        let tcp = store
+            .best("external", 10, Some(&Protocol::Tcp))
+            .await
+            .unwrap();
+        assert_eq!(tcp.len(), 1);
+        assert_eq!(tcp[0].endpoint.port(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_recent_and_compact() {
+        let db = setup_db().await;
+        let store = PeerRecordStore::new(&db);
+        store.upsert("inner", &record(1, 1, 0, 40)).await.unwrap();
+        store.upsert("inner", &record(2, 1, 0, 1)).await.unwrap();
+        store.upsert("inner", &record(3, 1, 0, 10)).await.unwrap();
+
+        let recent = store.recent("inner", 2).await.unwrap();
+        let ports: Vec<u16> = recent.iter().map(|r| r.endpoint.port()).collect();
+        assert_eq!(ports, vec![2, 3]);
+
+        let cutoff = (Utc::now() - Duration::days(30)).timestamp();
+        assert_eq!(store.compact(cutoff).await.unwrap(), 1);
+        assert_eq!(store.count("inner").await.unwrap(), 2);
+    }
+}
@@ -0,0 +1,173 @@
+//! 多地址竞速拨号（happy eyeballs）
+//!
+//! 按 [`NodeRecord`] 中的所有地址生成拨号计划，IPv6 与 IPv4 交替、错开启动，取最先建立的连接，
+//! 并把胜出的地址与传输方式记回 `NodeRecord`。每次尝试的超时与整轮失败后的退避重试取自
+//! `[network]` 的建连策略（[`retry::Operation::Connect`]）。
+//!
+//! 竞速只覆盖 TCP（含经由代理的 TCP）：节点的帧循环、链路握手与 ConnectionManager 都建立在
+//! 流式连接上，UDP 数据报连接不经过它们，QUIC 没有实现（见 [`crate::transport`]）。只声明了
+//! UDP / QUIC 的记录不产生拨号计划。
+
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use aex::connection::{global::GlobalContext, protocol::Protocol};
+use tokio::{net::TcpStream, task::JoinSet};
+
+use crate::{
+    proxy::{self, ProxyEndpoint},
+    record::NodeRecord,
+    retry::{self, Operation},
+};
+
+/// 相邻两次拨号尝试之间的错开延迟（RFC 8305 建议 150~250ms）
+pub const DIAL_STAGGER_MS: u64 = 250;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DialAttempt {
+    pub endpoint: SocketAddr,
+    pub protocol: Protocol,
+}
+
+/// 竞速胜出的尝试与其已建立的连接，交给连接管理器接管而不是重新拨号
+#[derive(Debug)]
+pub struct Dialed {
+    pub attempt: DialAttempt,
+    pub stream: TcpStream,
+}
+
+/// 根据 NodeRecord 生成拨号计划：
+/// - IPv6 与 IPv4 交替排列，IPv6 优先
+/// - 目前只有 TCP 传输可以拨出（Http 与 Tcp 共用同一监听端口），
+///   其它协议在对应传输实现前不参与竞速
+pub fn plan(record: &NodeRecord) -> Vec<DialAttempt> {
+    let supports_tcp = record.protocols.is_empty()
+        || record.protocols.contains(&Protocol::Tcp)
+        || record.protocols.contains(&Protocol::Http);
+    if !supports_tcp {
+        return vec![];
+    }
+
+    // 最近一次胜出的地址放在最前面
+    let mut endpoints = record.all_endpoints();
+    if let Some((winner, _)) = &record.last_dial {
+        if let Some(pos) = endpoints.iter().position(|e| e == winner) {
+            let winner = endpoints.remove(pos);
+            endpoints.insert(0, winner);
+        }
+    }
+
+    let (v6, v4): (Vec<SocketAddr>, Vec<SocketAddr>) =
+        endpoints.into_iter().partition(|e| e.is_ipv6());
+    // 上次胜出的地址族先行
+    let (primary, secondary) = match &record.last_dial {
+        Some((winner, _)) if winner.is_ipv4() => (v4, v6),
+        _ => (v6, v4),
+    };
+
+    let mut ordered = Vec::new();
+    let mut first = primary.into_iter();
+    let mut second = secondary.into_iter();
+    loop {
+        let a = first.next();
+        let b = second.next();
+        if a.is_none() && b.is_none() {
+            break;
+        }
+        ordered.extend(a);
+        ordered.extend(b);
+    }
+
+    ordered
+        .into_iter()
+        .map(|endpoint| DialAttempt {
+            endpoint,
+            protocol: Protocol::Tcp,
+        })
+        .collect()
+}
+
+async fn try_attempt(
+    attempt: DialAttempt,
+    delay: Duration,
+    via: Option<ProxyEndpoint>,
+    timeout: Option<Duration>,
+) -> anyhow::Result<Dialed> {
+    tokio::time::sleep(delay).await;
+    let what = format!("dial {}", attempt.endpoint);
+    let stream = retry::within(timeout, &what, async {
+        match &via {
+            Some(proxy) => proxy::dial(proxy, attempt.endpoint).await,
+            None => TcpStream::connect(attempt.endpoint)
+                .await
+                .map_err(|e| anyhow::anyhow!("{} unreachable: {}", attempt.endpoint, e)),
+        }
+    })
+    .await?;
+    Ok(Dialed { attempt, stream })
+}
+
+/// 按当前 `[network]` 的建连策略竞速拨号：每次尝试限时，整轮都失败时按退避重试
+pub async fn dial(
+    gctx: &Arc<GlobalContext>,
+    record: &NodeRecord,
+    via: Option<&ProxyEndpoint>,
+) -> anyhow::Result<Dialed> {
+    let policy = retry::policy(gctx, Operation::Connect).await;
+    let what = format!("dial {}", record.endpoint);
+    retry::retry(&policy.retry, &what, |_| {
+        happy_eyeballs_via(record, via, policy.timeout)
+    })
+    .await
+}
+
+/// "Happy eyeballs" 竞速拨号：按计划错开启动所有尝试，
+/// 取第一个成功的连接，其余尝试立即取消。每次尝试使用默认的建连超时，不重试。
+pub async fn happy_eyeballs(record: &NodeRecord) -> anyhow::Result<Dialed> {
+    let timeout = Duration::from_millis(retry::DEFAULT_CONNECT_TIMEOUT_MS);
+    happy_eyeballs_via(record, None, Some(timeout)).await
+}
+
+/// 同 [`happy_eyeballs`]，`via` 不为空时每次尝试都经由该代理拨号，每次尝试限时 `timeout`
+/// （`None` 表示不限时）
+pub async fn happy_eyeballs_via(
+    record: &NodeRecord,
+    via: Option<&ProxyEndpoint>,
+    timeout: Option<Duration>,
+) -> anyhow::Result<Dialed> {
+    let attempts = plan(record);
+    if attempts.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No dialable transport for {}",
+            record.endpoint
+        ));
+    }
+
+    let mut set = JoinSet::new();
+    for (i, attempt) in attempts.into_iter().enumerate() {
+        let delay = Duration::from_millis(DIAL_STAGGER_MS * i as u64);
+        set.spawn(try_attempt(attempt, delay, via.cloned(), timeout));
+    }
+
+    let mut last_err = None;
+    while let Some(res) = set.join_next().await {
+        match res {
+            Ok(Ok(winner)) => {
+                set.abort_all();
+                tracing::info!(
+                    "🏁 Dial winner for {}: {} via {:?}",
+                    record.endpoint,
+                    winner.attempt.endpoint,
+                    winner.attempt.protocol
+                );
+                return Ok(winner);
+            }
+            Ok(Err(e)) => {
+                tracing::debug!("Dial attempt failed: {:?}", e);
+                last_err = Some(e);
+            }
+            Err(e) => last_err = Some(anyhow::anyhow!("Dial task failed: {}", e)),
+        }
+    }
+
+    Err(last_err.unwrap_or_else(|| anyhow::anyhow!("All dial attempts failed")))
+}
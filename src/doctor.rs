@@ -0,0 +1,355 @@
+//! 节点自检
+//!
+//! `doctor` 命令与控制接口 `GET /health` 运行同一组检查，结果为结构化的 [`DoctorReport`]：
+//!
+//! - `listeners`：每个监听器（P2P server、控制接口）都在运行；仅出站模式下不要求 P2P server
+//! - `public_endpoint`：本机网卡的公网地址、端口映射或被对端确认的反射地址
+//! - `nat`：由上面三者推断的 NAT 类型
+//! - `peers`：已连接节点数达到 `--min-peers`
+//! - `storage`：数据目录可写
+//! - `clock`：与直连对端的时钟偏差（由对端直接发来的在线状态记录的签发时间估算）
+//!
+//! 任一检查为 `fail` 时报告不健康，`GET /health` 返回 503。
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    sync::Arc,
+};
+
+use aex::connection::global::GlobalContext;
+use serde::Serialize;
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::{
+    bootstrap::{DEFAULT_MIN_PEERS, TargetPeers},
+    connections, io_storage, ip_scope,
+    listener::Health,
+    node::Node,
+    port_mapping,
+    protocols::commands::{observed, presence},
+};
+
+/// 时钟偏差超过该值时警告
+pub const CLOCK_SKEW_WARN_MS: i128 = 30 * 1000;
+/// 时钟偏差超过该值时签名记录会被对端拒绝，视为失败
+pub const CLOCK_SKEW_FAIL_MS: i128 = presence::PRESENCE_CLOCK_SKEW_MS as i128;
+/// 检查数据目录是否可写时写入的临时文件
+pub const STORAGE_PROBE_FILE: &str = ".doctor";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Warn,
+    Fail,
+}
+
+impl std::fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            CheckStatus::Ok => "ok",
+            CheckStatus::Warn => "warn",
+            CheckStatus::Fail => "fail",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: CheckStatus, detail: String) -> Self {
+        Self {
+            name,
+            status,
+            detail,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NatType {
+    /// 本机网卡直接持有公网地址
+    Open,
+    /// 位于 NAT 之后，已通过 UPnP / NAT-PMP 映射端口
+    Mapped,
+    /// 位于 NAT 之后，对端确认了反射地址但没有端口映射，入站连接可能失败
+    Nat,
+    /// 没有公网地址，也没有对端确认的反射地址
+    Unknown,
+}
+
+impl NatType {
+    pub fn classify(local_public: bool, mapped: bool, reflexive: bool) -> Self {
+        if local_public {
+            NatType::Open
+        } else if mapped {
+            NatType::Mapped
+        } else if reflexive {
+            NatType::Nat
+        } else {
+            NatType::Unknown
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DoctorReport {
+    /// 所有检查中最差的结果
+    pub status: CheckStatus,
+    pub nat: NatType,
+    pub checks: Vec<Check>,
+}
+
+impl DoctorReport {
+    pub fn new(nat: NatType, checks: Vec<Check>) -> Self {
+        let status = checks
+            .iter()
+            .map(|c| c.status)
+            .max()
+            .unwrap_or(CheckStatus::Ok);
+        Self {
+            status,
+            nat,
+            checks,
+        }
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.status != CheckStatus::Fail
+    }
+}
+
+pub fn check_listeners(listeners: &[(String, Health)], addrs: &[SocketAddr]) -> Check {
+    let bound = addrs
+        .iter()
+        .map(|a| a.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    if listeners.is_empty() {
+        return Check::new("listeners", CheckStatus::Fail, "no listener started".into());
+    }
+    let failed: Vec<String> = listeners
+        .iter()
+        .filter(|(_, h)| matches!(h, Health::Failed { .. } | Health::Stopped))
+        .map(|(name, h)| format!("{} {}", name, h))
+        .collect();
+    if !failed.is_empty() {
+        return Check::new("listeners", CheckStatus::Fail, failed.join("; "));
+    }
+    let pending: Vec<String> = listeners
+        .iter()
+        .filter(|(_, h)| *h != Health::Running)
+        .map(|(name, h)| format!("{} {}", name, h))
+        .collect();
+    if !pending.is_empty() {
+        return Check::new("listeners", CheckStatus::Warn, pending.join("; "));
+    }
+    Check::new(
+        "listeners",
+        CheckStatus::Ok,
+        format!("{} listener(s) running on {}", listeners.len(), bound),
+    )
+}
+
+pub fn check_public_endpoint(local: &[IpAddr], mapped: &[IpAddr], reflexive: &[IpAddr]) -> Check {
+    let mut ips: Vec<IpAddr> = Vec::new();
+    for ip in local.iter().chain(mapped).chain(reflexive) {
+        if !ips.contains(ip) {
+            ips.push(*ip);
+        }
+    }
+    if ips.is_empty() {
+        return Check::new(
+            "public_endpoint",
+            CheckStatus::Warn,
+            "no public address yet; waiting for peers to confirm one".into(),
+        );
+    }
+    let list = ips
+        .iter()
+        .map(|ip| ip.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    Check::new("public_endpoint", CheckStatus::Ok, list)
+}
+
+pub fn check_nat(nat: NatType) -> Check {
+    let (status, detail) = match nat {
+        NatType::Open => (CheckStatus::Ok, "public address on a local interface"),
+        NatType::Mapped => (CheckStatus::Ok, "behind NAT with a port mapping"),
+        NatType::Nat => (
+            CheckStatus::Warn,
+            "behind NAT without a port mapping; inbound connections may fail",
+        ),
+        NatType::Unknown => (CheckStatus::Warn, "not enough observations"),
+    };
+    Check::new("nat", status, detail.into())
+}
+
+pub fn check_peers(connected: usize, min_peers: usize) -> Check {
+    let detail = format!("{} connected (min {})", connected, min_peers);
+    let status = if connected == 0 && min_peers > 0 {
+        CheckStatus::Fail
+    } else if connected < min_peers {
+        CheckStatus::Warn
+    } else {
+        CheckStatus::Ok
+    };
+    Check::new("peers", status, detail)
+}
+
+/// 在数据目录写入并删除一个临时文件
+pub async fn check_storage(dir: &Path) -> Check {
+    let probe = dir.join(STORAGE_PROBE_FILE);
+    let written = match io_storage::write_atomic(&probe, b"ok").await {
+        Ok(()) => tokio::fs::remove_file(&probe)
+            .await
+            .map_err(anyhow::Error::from),
+        Err(e) => Err(e),
+    };
+    match written {
+        Ok(()) => Check::new(
+            "storage",
+            CheckStatus::Ok,
+            format!("{} is writable", dir.display()),
+        ),
+        Err(e) => Check::new(
+            "storage",
+            CheckStatus::Fail,
+            format!("{} is not writable: {}", dir.display(), e),
+        ),
+    }
+}
+
+/// 样本（对端时钟 − 本机时钟，毫秒）的中位数
+pub fn estimate_clock_skew(samples: &[i128]) -> Option<i128> {
+    if samples.is_empty() {
+        return None;
+    }
+    let mut sorted = samples.to_vec();
+    sorted.sort_unstable();
+    Some(sorted[sorted.len() / 2])
+}
+
+pub fn check_clock(skew_ms: Option<i128>, samples: usize) -> Check {
+    let Some(skew) = skew_ms else {
+        return Check::new(
+            "clock",
+            CheckStatus::Warn,
+            "no clock samples or presence records from peers yet".into(),
+        );
+    };
+    let status = match skew.abs() {
+        s if s > CLOCK_SKEW_FAIL_MS => CheckStatus::Fail,
+        s if s > CLOCK_SKEW_WARN_MS => CheckStatus::Warn,
+        _ => CheckStatus::Ok,
+    };
+    Check::new(
+        "clock",
+        status,
+        format!(
+            "peers are {:+} ms from local time ({} sample(s))",
+            skew, samples
+        ),
+    )
+}
+
+/// 对端时钟与本机之差：优先使用 Ping/Pong 测得的偏差（[`crate::clock`]），
+/// 否则取当前直连的对端直接发来的在线状态记录的签发时间与本机收到时间之差
+async fn clock_samples(gctx: &Arc<GlobalContext>) -> Vec<i128> {
+    let measured: Vec<i128> = crate::clock::offsets(gctx)
+        .await
+        .into_values()
+        .map(i128::from)
+        .collect();
+    if !measured.is_empty() {
+        return measured;
+    }
+    let Some(local) = gctx.get::<FreeWebMovementAddress>().await else {
+        return Vec::new();
+    };
+    let local = local.to_string();
+    let peers: Vec<String> = connections::list(gctx)
+        .await
+        .into_iter()
+        .filter_map(|c| c.peer)
+        .collect();
+    presence::list(gctx)
+        .await
+        .into_iter()
+        .filter(|e| e.seen_by.as_deref() == Some(local.as_str()))
+        .filter(|e| peers.contains(&e.record.address))
+        .map(|e| e.record.issued_at as i128 - e.seen_at as i128)
+        .collect()
+}
+
+/// 运行全部检查
+pub async fn run(gctx: &Arc<GlobalContext>) -> DoctorReport {
+    let node = gctx.get::<Arc<Node>>().await;
+    let listen = crate::listen::current(gctx).await;
+
+    let mut addrs = vec![listen.primary()];
+    addrs.extend_from_slice(listen.extra());
+    let listeners = node
+        .as_ref()
+        .map(|n| n.handlers.health())
+        .unwrap_or_default();
+    let listeners_check = if node.as_ref().is_some_and(|n| n.client_only) {
+        Check::new(
+            "listeners",
+            CheckStatus::Ok,
+            format!(
+                "client-only mode, no P2P listener ({} local listener(s))",
+                listeners.len()
+            ),
+        )
+    } else {
+        check_listeners(&listeners, &addrs)
+    };
+
+    let local: Vec<IpAddr> = aex::connection::node::Node::system_ips()
+        .into_iter()
+        .map(|(_, ip)| ip)
+        .filter(|ip| !ip_scope::is_inner_ip(ip) && !ip.is_unspecified())
+        .collect();
+    let mapped = port_mapping::external_ips(gctx).await;
+    let reflexive = observed::reflexive_ips(gctx).await;
+    let nat = NatType::classify(!local.is_empty(), !mapped.is_empty(), !reflexive.is_empty());
+
+    let connected = node
+        .as_ref()
+        .map(|n| n.registry.get_connected_nodes().len())
+        .unwrap_or(0);
+    let min_peers = gctx
+        .get::<TargetPeers>()
+        .await
+        .map(|t| t.0)
+        .unwrap_or(DEFAULT_MIN_PEERS);
+
+    let storage = match &node {
+        Some(n) => check_storage(&n.io_storage.dir).await,
+        None => Check::new("storage", CheckStatus::Fail, "node not started".into()),
+    };
+
+    let samples = clock_samples(gctx).await;
+    let skew = estimate_clock_skew(&samples);
+
+    DoctorReport::new(
+        nat,
+        vec![
+            listeners_check,
+            check_public_endpoint(&local, &mapped, &reflexive),
+            check_nat(nat),
+            check_peers(connected, min_peers),
+            storage,
+            check_clock(skew, samples.len()),
+        ],
+    )
+}
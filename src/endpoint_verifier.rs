@@ -0,0 +1,241 @@
+//! 公告地址的可达性验证
+//!
+//! 对端 gossip 来的 seed 地址不能直接信任：后台任务每 `VERIFY_INTERVAL_SECS` 拨号一次
+//! NodeRegistry 中的所有 seed，以及本节点由对端观测到的外部地址（自拨号）。
+//! 只有在 `VERIFY_FRESHNESS_SECS` 内验证通过的地址才会被 gossip 出去；
+//! 拨号失败的地址保留在注册表中，但标记为未验证。主动连接成功的地址同样视为验证通过。
+
+use std::{
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use aex::connection::global::GlobalContext;
+use dashmap::DashMap;
+use futures::{StreamExt, stream};
+use serde::Serialize;
+use tokio::net::TcpStream;
+
+use crate::{
+    ip_scope, listen, node::Node, port_mapping, protocols::commands::observed, scheduler::Scheduler,
+};
+
+/// 两次验证同一地址的最小间隔
+pub const VERIFY_INTERVAL_SECS: u64 = 5 * 60;
+/// 验证结果的有效期，超过后不再 gossip
+pub const VERIFY_FRESHNESS_SECS: u64 = 30 * 60;
+/// 同时进行的验证拨号数
+pub const VERIFY_CONCURRENCY: usize = 8;
+/// 单次验证拨号的超时时间
+pub const PROBE_TIMEOUT_MS: u64 = 5_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct EndpointStatus {
+    /// 最近一次验证通过的时间（秒）
+    pub verified_at: Option<u64>,
+    /// 最近一次尝试验证的时间（秒）
+    pub last_attempt: u64,
+    /// 上次通过后连续失败的次数
+    pub failures: u32,
+}
+
+impl EndpointStatus {
+    pub fn is_fresh(&self, now: u64) -> bool {
+        self.verified_at
+            .is_some_and(|t| now.saturating_sub(t) <= VERIFY_FRESHNESS_SECS)
+    }
+}
+
+/// 各地址的验证状态
+#[derive(Clone, Default)]
+pub struct EndpointVerifier {
+    endpoints: Arc<DashMap<SocketAddr, EndpointStatus>>,
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl EndpointVerifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mark_verified(&self, addr: SocketAddr, now: u64) {
+        let mut status = self.endpoints.entry(addr).or_default();
+        status.verified_at = Some(now);
+        status.last_attempt = now;
+        status.failures = 0;
+    }
+
+    pub fn mark_failed(&self, addr: SocketAddr, now: u64) {
+        let mut status = self.endpoints.entry(addr).or_default();
+        status.last_attempt = now;
+        status.failures = status.failures.saturating_add(1);
+    }
+
+    pub fn status(&self, addr: &SocketAddr) -> Option<EndpointStatus> {
+        self.endpoints.get(addr).map(|s| *s)
+    }
+
+    /// 地址是否在有效期内验证通过
+    pub fn is_verified(&self, addr: &SocketAddr, now: u64) -> bool {
+        self.status(addr).is_some_and(|s| s.is_fresh(now))
+    }
+
+    /// 需要（重新）验证的地址：从未尝试过，或距上次尝试超过 `VERIFY_INTERVAL_SECS`
+    pub fn due(&self, candidates: &[SocketAddr], now: u64) -> Vec<SocketAddr> {
+        candidates
+            .iter()
+            .filter(|addr| match self.status(addr) {
+                Some(s) => now.saturating_sub(s.last_attempt) >= VERIFY_INTERVAL_SECS,
+                None => true,
+            })
+            .copied()
+            .collect()
+    }
+
+    /// 在后台立即验证新学到的地址；最近已尝试过的跳过
+    pub fn spawn_verify(&self, addr: SocketAddr) {
+        let now = now_secs();
+        if addr.port() == 0 || self.due(&[addr], now).is_empty() {
+            return;
+        }
+        self.endpoints.entry(addr).or_default().last_attempt = now;
+        let verifier = self.clone();
+        tokio::spawn(async move {
+            if probe(addr).await {
+                verifier.mark_verified(addr, now_secs());
+            } else {
+                verifier.mark_failed(addr, now_secs());
+            }
+        });
+    }
+
+    /// 只保留有效期内验证通过的 seed
+    pub fn retain_verified(
+        &self,
+        seeds: Vec<(SocketAddr, String)>,
+        now: u64,
+    ) -> Vec<(SocketAddr, String)> {
+        seeds
+            .into_iter()
+            .filter(|(addr, _)| self.is_verified(addr, now))
+            .collect()
+    }
+
+    /// 不再出现在候选中的地址不必保留
+    pub fn prune(&self, keep: &[SocketAddr]) {
+        self.endpoints.retain(|addr, _| keep.contains(addr));
+    }
+
+    /// (验证通过, 未验证) 的地址数
+    pub fn counts(&self, now: u64) -> (usize, usize) {
+        let verified = self.endpoints.iter().filter(|e| e.is_fresh(now)).count();
+        (verified, self.endpoints.len() - verified)
+    }
+}
+
+/// 拨号探测：能建立 TCP 连接即视为可达
+pub async fn probe(addr: SocketAddr) -> bool {
+    let timeout = Duration::from_millis(PROBE_TIMEOUT_MS);
+    matches!(
+        tokio::time::timeout(timeout, TcpStream::connect(addr)).await,
+        Ok(Ok(_))
+    )
+}
+
+/// 本节点的外部地址：已确认的反射 IP + 各监听端口，以及网关映射的 TCP 端点
+async fn own_external_endpoints(gctx: &Arc<GlobalContext>) -> Vec<SocketAddr> {
+    let listen = listen::current(gctx).await;
+    let mut own: Vec<SocketAddr> = observed::reflexive_ips(gctx)
+        .await
+        .into_iter()
+        .flat_map(|ip| {
+            listen
+                .external_ports(&ip)
+                .into_iter()
+                .map(move |port| SocketAddr::new(ip, port))
+        })
+        .collect();
+    for addr in port_mapping::external_tcp_endpoints(gctx).await {
+        if !own.contains(&addr) {
+            own.push(addr);
+        }
+    }
+    own
+}
+
+/// 验证一轮到期的地址，返回 (通过, 失败) 数
+pub async fn verify_round(gctx: &Arc<GlobalContext>) -> (usize, usize) {
+    let Some(node) = gctx.get::<Arc<Node>>().await else {
+        return (0, 0);
+    };
+    let verifier = node.registry.endpoints();
+    let own = own_external_endpoints(gctx).await;
+    let mut candidates: Vec<SocketAddr> = node
+        .registry
+        .get_all_seeds()
+        .into_iter()
+        .map(|(addr, _)| addr)
+        .filter(|addr| addr.port() != 0)
+        .collect();
+    for addr in &own {
+        if !candidates.contains(addr) {
+            candidates.push(*addr);
+        }
+    }
+    verifier.prune(&candidates);
+
+    let now = now_secs();
+    let due = verifier.due(&candidates, now);
+    let results: Vec<(SocketAddr, bool)> = stream::iter(due)
+        .map(|addr| async move { (addr, probe(addr).await) })
+        .buffer_unordered(VERIFY_CONCURRENCY)
+        .collect()
+        .await;
+
+    let now = now_secs();
+    let self_address = node.id.to_string();
+    let mut passed = 0;
+    for (addr, ok) in &results {
+        if *ok {
+            verifier.mark_verified(*addr, now);
+            passed += 1;
+            // 自拨号通过的外部地址作为本节点的 seed 公告出去
+            if own.contains(addr) {
+                node.registry
+                    .register(self_address.clone(), *addr, ip_scope::classify(&addr.ip()));
+            }
+        } else {
+            tracing::debug!("Endpoint {} failed verification", addr);
+            verifier.mark_failed(*addr, now);
+        }
+    }
+    (passed, results.len() - passed)
+}
+
+/// 后台定期验证
+pub fn schedule(scheduler: &Scheduler, gctx: Arc<GlobalContext>) {
+    scheduler.register_interval(
+        "endpoint_verification",
+        Duration::from_secs(VERIFY_INTERVAL_SECS / 5),
+        move || {
+            let gctx = gctx.clone();
+            async move {
+                let (passed, failed) = verify_round(&gctx).await;
+                if passed + failed > 0 {
+                    tracing::info!(
+                        "🔎 Endpoint verification: {} passed, {} failed",
+                        passed,
+                        failed
+                    );
+                }
+            }
+        },
+    );
+}
@@ -0,0 +1,46 @@
+//! 节点事件总线
+//!
+//! 生命周期事件（与事件日志相同的 [`Event`]）与投递给应用的消息、群消息在这里广播，
+//! 终端仪表盘等订阅方通过 [`crate::node::Node::subscribe_events`] 接收。
+//! 没有订阅方时发送为空操作；订阅方处理不及时会丢失最旧的事件（`RecvError::Lagged`）。
+
+use std::sync::Arc;
+
+use aex::connection::global::GlobalContext;
+use tokio::sync::broadcast;
+
+use crate::{
+    journal::Event,
+    protocols::commands::{
+        group::IncomingGroupMessage, message::IncomingMessage, stream::IncomingTextStream,
+    },
+};
+
+/// 每个订阅方最多积压的事件数
+pub const EVENT_BUS_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub enum NodeEvent {
+    /// 写入事件日志的生命周期事件（未启用事件日志时同样广播）
+    Journal(Event),
+    /// 按序投递给应用的文本消息
+    Message(IncomingMessage),
+    /// 解密后的群消息
+    GroupMessage(IncomingGroupMessage),
+    /// 以流的方式到达的长文本，订阅方取走读端后按块读取
+    TextStream(IncomingTextStream),
+}
+
+/// 保存在 GlobalContext 中的事件发送端
+pub type EventBus = broadcast::Sender<NodeEvent>;
+
+pub fn new_bus() -> EventBus {
+    broadcast::channel(EVENT_BUS_CAPACITY).0
+}
+
+/// 广播一条事件；总线未初始化时为空操作
+pub async fn publish(gctx: &Arc<GlobalContext>, event: NodeEvent) {
+    if let Some(bus) = gctx.get::<EventBus>().await {
+        let _ = bus.send(event);
+    }
+}
@@ -0,0 +1,168 @@
+//! 大帧转发的扇出限制
+//!
+//! 没有路由时中继帧会泛洪给除来源外的每个已连接节点，大帧（文件分块、媒体等）因此成倍占用上行带宽。
+//! 编码后超过 `threshold_bytes` 的帧只转发给 `max_hops` 个最佳下一跳：先比发送成功率，
+//! 再比平均往返时间（没有测量值的排在后面）。阈值与跳数可以按实体类型覆盖，`max_hops = 0`
+//! 表示不限制。
+//!
+//! ```toml
+//! [fanout]
+//! threshold_bytes = 65536
+//! max_hops = 3
+//!
+//! [fanout.entities.file]
+//! threshold_bytes = 16384
+//! max_hops = 2
+//!
+//! [fanout.entities.message]
+//! max_hops = 0
+//! ```
+
+use std::{cmp::Ordering, collections::BTreeMap, sync::Arc};
+
+use aex::connection::global::GlobalContext;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::SharedConfig,
+    protocols::{
+        broadcast::{PeerReachability, Target},
+        command::Entity,
+        peer_stats::PeerStatsTable,
+    },
+};
+
+/// 默认超过该大小的帧限制扇出
+pub const DEFAULT_FANOUT_THRESHOLD_BYTES: usize = 64 * 1024;
+/// 默认大帧最多转发给几个下一跳
+pub const DEFAULT_FANOUT_MAX_HOPS: usize = 3;
+
+/// 单个实体类型覆盖的参数，未给出的沿用 `[fanout]`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FanoutOverride {
+    pub threshold_bytes: Option<usize>,
+    pub max_hops: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FanoutConfig {
+    /// 编码后超过该大小的帧限制扇出
+    pub threshold_bytes: usize,
+    /// 大帧最多转发给几个下一跳，0 表示不限制
+    pub max_hops: usize,
+    /// 按实体类型（小写，如 `file`、`stream`）覆盖
+    pub entities: BTreeMap<String, FanoutOverride>,
+}
+
+impl Default for FanoutConfig {
+    fn default() -> Self {
+        Self {
+            threshold_bytes: DEFAULT_FANOUT_THRESHOLD_BYTES,
+            max_hops: DEFAULT_FANOUT_MAX_HOPS,
+            entities: BTreeMap::new(),
+        }
+    }
+}
+
+/// 配置中实体类型的名称
+pub fn entity_key(entity: Entity) -> String {
+    format!("{:?}", entity).to_lowercase()
+}
+
+impl FanoutConfig {
+    /// `entity` 类型、编码后 `len` 字节的帧最多转发给几个下一跳；`None` 表示不限制
+    pub fn limit(&self, entity: Option<Entity>, len: usize) -> Option<usize> {
+        let mut threshold = self.threshold_bytes;
+        let mut max_hops = self.max_hops;
+        if let Some(o) = entity.and_then(|e| self.entities.get(&entity_key(e))) {
+            threshold = o.threshold_bytes.unwrap_or(threshold);
+            max_hops = o.max_hops.unwrap_or(max_hops);
+        }
+        (max_hops > 0 && len > threshold).then_some(max_hops)
+    }
+}
+
+/// 候选下一跳的排序依据
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HopMetrics {
+    /// 发送成功率，0.0 ~ 1.0
+    pub success: f64,
+    /// 平均往返时间
+    pub rtt_ms: Option<f64>,
+}
+
+impl Default for HopMetrics {
+    fn default() -> Self {
+        Self {
+            success: 1.0,
+            rtt_ms: None,
+        }
+    }
+}
+
+fn compare(a: &HopMetrics, b: &HopMetrics) -> Ordering {
+    b.success
+        .total_cmp(&a.success)
+        .then(match (a.rtt_ms, b.rtt_ms) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        })
+}
+
+/// 按成功率从高到低、往返时间从低到高取前 `k` 个；同分时保持原顺序
+pub fn select<T>(mut candidates: Vec<(T, HopMetrics)>, k: usize) -> Vec<T> {
+    candidates.sort_by(|a, b| compare(&a.1, &b.1));
+    candidates.into_iter().take(k).map(|(t, _)| t).collect()
+}
+
+pub async fn policy(gctx: &GlobalContext) -> FanoutConfig {
+    match gctx.get::<SharedConfig>().await {
+        Some(config) => config.read().await.fanout.clone(),
+        None => FanoutConfig::default(),
+    }
+}
+
+/// 按配置限制转发目标；帧不够大或目标不多于上限时原样返回
+pub async fn limit(
+    gctx: &Arc<GlobalContext>,
+    entity: Option<Entity>,
+    len: usize,
+    targets: Vec<Target>,
+) -> Vec<Target> {
+    let Some(k) = policy(gctx).await.limit(entity, len) else {
+        return targets;
+    };
+    if targets.len() <= k {
+        return targets;
+    }
+    let stats = gctx.get::<PeerStatsTable>().await;
+    let reachability = gctx.get::<PeerReachability>().await;
+    let total = targets.len();
+    let candidates = targets
+        .into_iter()
+        .map(|t| {
+            let metrics = HopMetrics {
+                success: reachability
+                    .as_ref()
+                    .and_then(|r| r.get(&t.addr).map(|r| r.score()))
+                    .unwrap_or(1.0),
+                rtt_ms: stats
+                    .as_ref()
+                    .and_then(|s| s.get(&t.addr).and_then(|s| s.rtt_ms)),
+            };
+            (t, metrics)
+        })
+        .collect();
+    let selected = select(candidates, k);
+    tracing::debug!(
+        "Fan-out limited to {} of {} peer(s) for {}-byte frame",
+        selected.len(),
+        total,
+        len
+    );
+    selected
+}
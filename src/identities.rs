@@ -0,0 +1,383 @@
+//! 同一进程内的多个身份
+//!
+//! 启动时加载的身份是主身份（`default`），负责握手、路由与连接级的会话密钥。
+//! `identity new <name>` 生成附加身份，明文保存在存储目录的 `identities.json`
+//! （与未加密的 `address.json` 相同；`--encrypt-key` 只保护主身份）。
+//!
+//! 每个附加身份有自己的会话密钥表和已建立会话的对端列表。以附加身份向直连对端发送加密
+//! 消息前，先以该身份签名发起一次 Rekey 交换：对端把密钥记在附加身份的地址下，本节点记在
+//! 该身份自己的表中，并据此学到「附加身份经由本节点可达」的路由。收到发往附加身份的帧时
+//! 按帧的 `destination` 选择密钥表。附加身份只能与直连节点交换密钥。
+//!
+//! `identity use <name>` 切换 `send` 等命令使用的身份。`identity rotate` 轮换主身份，
+//! 旧身份在宽限期内作为附加身份保留（见 [`crate::protocols::commands::successor`]）。
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use aex::{
+    connection::{context::Context, global::GlobalContext},
+    crypto::session_key_manager::PairedSessionKey,
+};
+use dashmap::{DashMap, DashSet};
+use tokio::sync::{Mutex, oneshot};
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::{
+    io_storage::{IOStorage, STORAGE_IDENTITIES},
+    protocols::{
+        command::{Action, Entity},
+        commands::rekey::RekeyCommand,
+        frame::P2PFrame,
+    },
+};
+
+/// 主身份的名称
+pub const DEFAULT_IDENTITY: &str = "default";
+/// 等待附加身份 RekeyAck 的时间
+pub const SESSION_TIMEOUT_SECS: u64 = 10;
+/// 附加身份会话密钥表的容量
+const SESSION_KEYS_CAPACITY: usize = 16;
+
+#[derive(Clone)]
+pub struct LocalIdentity {
+    pub name: String,
+    pub address: FreeWebMovementAddress,
+    /// 该身份与各对端的会话密钥（主身份即全局表）
+    pub session_keys: Arc<Mutex<PairedSessionKey>>,
+    /// 已与该身份建立会话的对端地址
+    pub peers: Arc<DashSet<String>>,
+}
+
+impl LocalIdentity {
+    fn new(name: &str, address: FreeWebMovementAddress) -> Self {
+        Self::with_keys(
+            name,
+            address,
+            Arc::new(Mutex::new(PairedSessionKey::new(SESSION_KEYS_CAPACITY))),
+        )
+    }
+
+    fn with_keys(
+        name: &str,
+        address: FreeWebMovementAddress,
+        session_keys: Arc<Mutex<PairedSessionKey>>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            address,
+            session_keys,
+            peers: Arc::new(DashSet::new()),
+        }
+    }
+
+    pub fn is_primary(&self) -> bool {
+        self.name == DEFAULT_IDENTITY
+    }
+}
+
+/// 本进程持有的全部身份
+pub struct Identities {
+    entries: RwLock<BTreeMap<String, LocalIdentity>>,
+    active: RwLock<String>,
+    /// 附加身份发起、等待 RekeyAck 的会话：session_id → (身份名, 完成通知)
+    pending: DashMap<Vec<u8>, (String, oneshot::Sender<()>)>,
+}
+
+pub type SharedIdentities = Arc<Identities>;
+
+impl Identities {
+    pub fn new(
+        primary: FreeWebMovementAddress,
+        session_keys: Arc<Mutex<PairedSessionKey>>,
+    ) -> Self {
+        let mut entries = BTreeMap::new();
+        entries.insert(
+            DEFAULT_IDENTITY.to_string(),
+            LocalIdentity::with_keys(DEFAULT_IDENTITY, primary, session_keys),
+        );
+        Self {
+            entries: RwLock::new(entries),
+            active: RwLock::new(DEFAULT_IDENTITY.to_string()),
+            pending: DashMap::new(),
+        }
+    }
+
+    /// 恢复保存的附加身份，跳过与已有身份冲突的条目
+    pub fn load(&self, stored: BTreeMap<String, FreeWebMovementAddress>) {
+        for (name, address) in stored {
+            if let Err(e) = self.add(&name, address) {
+                tracing::warn!("Skipping stored identity {}: {}", name, e);
+            }
+        }
+    }
+
+    /// 需要持久化的附加身份
+    pub fn stored(&self) -> BTreeMap<String, FreeWebMovementAddress> {
+        self.entries
+            .read()
+            .unwrap()
+            .values()
+            .filter(|i| !i.is_primary())
+            .map(|i| (i.name.clone(), i.address.clone()))
+            .collect()
+    }
+
+    pub fn add(
+        &self,
+        name: &str,
+        address: FreeWebMovementAddress,
+    ) -> anyhow::Result<LocalIdentity> {
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            anyhow::bail!("Invalid identity name: {:?}", name);
+        }
+        let mut entries = self.entries.write().unwrap();
+        if entries.contains_key(name) {
+            anyhow::bail!("Identity {} already exists", name);
+        }
+        let text = address.to_string();
+        if entries.values().any(|i| i.address.to_string() == text) {
+            anyhow::bail!("Address {} is already loaded", text);
+        }
+        let identity = LocalIdentity::new(name, address);
+        entries.insert(name.to_string(), identity.clone());
+        Ok(identity)
+    }
+
+    /// 主身份换为 `address`；原主身份以 `retired` 为名保留为附加身份，继续使用全局会话密钥，
+    /// 见 [`crate::protocols::commands::successor`]
+    pub fn rotate_primary(
+        &self,
+        retired: &str,
+        address: FreeWebMovementAddress,
+    ) -> anyhow::Result<LocalIdentity> {
+        let mut entries = self.entries.write().unwrap();
+        if entries.contains_key(retired) {
+            anyhow::bail!("Identity {} already exists", retired);
+        }
+        let text = address.to_string();
+        if entries.values().any(|i| i.address.to_string() == text) {
+            anyhow::bail!("Address {} is already loaded", text);
+        }
+        let primary = entries
+            .get_mut(DEFAULT_IDENTITY)
+            .expect("primary identity is always present");
+        let old = std::mem::replace(&mut primary.address, address);
+        let identity = LocalIdentity::with_keys(retired, old, primary.session_keys.clone());
+        entries.insert(retired.to_string(), identity.clone());
+        Ok(identity)
+    }
+
+    /// 生成新的附加身份
+    pub fn generate(&self, name: &str) -> anyhow::Result<LocalIdentity> {
+        self.add(name, FreeWebMovementAddress::random())
+    }
+
+    /// 删除附加身份；正在使用时切回主身份
+    pub fn remove(&self, name: &str) -> anyhow::Result<LocalIdentity> {
+        if name == DEFAULT_IDENTITY {
+            anyhow::bail!("The primary identity cannot be removed");
+        }
+        let removed = self
+            .entries
+            .write()
+            .unwrap()
+            .remove(name)
+            .ok_or_else(|| anyhow::anyhow!("No such identity: {}", name))?;
+        let mut active = self.active.write().unwrap();
+        if *active == name {
+            *active = DEFAULT_IDENTITY.to_string();
+        }
+        Ok(removed)
+    }
+
+    /// 按名称或地址查找
+    pub fn get(&self, name_or_address: &str) -> Option<LocalIdentity> {
+        let entries = self.entries.read().unwrap();
+        entries.get(name_or_address).cloned().or_else(|| {
+            entries
+                .values()
+                .find(|i| i.address.to_string() == name_or_address)
+                .cloned()
+        })
+    }
+
+    pub fn is_local(&self, address: &str) -> bool {
+        self.entries
+            .read()
+            .unwrap()
+            .values()
+            .any(|i| i.address.to_string() == address)
+    }
+
+    pub fn list(&self) -> Vec<LocalIdentity> {
+        self.entries.read().unwrap().values().cloned().collect()
+    }
+
+    pub fn primary(&self) -> LocalIdentity {
+        self.entries.read().unwrap()[DEFAULT_IDENTITY].clone()
+    }
+
+    pub fn active(&self) -> LocalIdentity {
+        let name = self.active.read().unwrap().clone();
+        self.get(&name).unwrap_or_else(|| self.primary())
+    }
+
+    pub fn set_active(&self, name: &str) -> anyhow::Result<LocalIdentity> {
+        let identity = self
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("No such identity: {}", name))?;
+        *self.active.write().unwrap() = identity.name.clone();
+        Ok(identity)
+    }
+}
+
+/// `address` 是否为本进程的某个身份
+pub async fn is_local(gctx: &Arc<GlobalContext>, address: &str) -> bool {
+    if let Some(identities) = gctx.get::<SharedIdentities>().await {
+        return identities.is_local(address);
+    }
+    match gctx.get::<FreeWebMovementAddress>().await {
+        Some(a) => a.to_string() == address,
+        None => false,
+    }
+}
+
+/// 发往 / 来自本地身份 `local` 的帧使用的会话密钥表；非附加身份使用全局表
+pub async fn session_keys_for(
+    gctx: &Arc<GlobalContext>,
+    local: Option<&str>,
+) -> Option<Arc<Mutex<PairedSessionKey>>> {
+    if let (Some(local), Some(identities)) = (local, gctx.get::<SharedIdentities>().await) {
+        if let Some(identity) = identities.get(local) {
+            if !identity.is_primary() {
+                return Some(identity.session_keys);
+            }
+        }
+    }
+    gctx.paired_session_keys.clone()
+}
+
+/// 当前使用的身份；未初始化多身份时为主身份
+pub async fn active(gctx: &Arc<GlobalContext>) -> Option<LocalIdentity> {
+    match gctx.get::<SharedIdentities>().await {
+        Some(identities) => Some(identities.active()),
+        None => {
+            let address = gctx.get::<FreeWebMovementAddress>().await?;
+            let session_keys = gctx.paired_session_keys.clone()?;
+            Some(LocalIdentity::with_keys(
+                DEFAULT_IDENTITY,
+                address,
+                session_keys,
+            ))
+        }
+    }
+}
+
+/// 持久化附加身份
+pub async fn save(gctx: &Arc<GlobalContext>) {
+    let (Some(identities), Some(ios)) = (
+        gctx.get::<SharedIdentities>().await,
+        gctx.get::<IOStorage>().await,
+    ) else {
+        tracing::error!("Identities or IOStorage not found in context, identities not persisted");
+        return;
+    };
+    ios.save::<BTreeMap<String, FreeWebMovementAddress>>(&identities.stored(), STORAGE_IDENTITIES)
+        .await;
+}
+
+/// 确保附加身份与直连对端 `peer` 之间有会话密钥；主身份的密钥在握手时已建立
+pub async fn ensure_session(
+    gctx: &Arc<GlobalContext>,
+    ctx: Arc<Mutex<Context>>,
+    identity: &LocalIdentity,
+    peer: &str,
+) -> anyhow::Result<()> {
+    if identity.is_primary() || identity.peers.contains(peer) {
+        return Ok(());
+    }
+    let identities = gctx
+        .get::<SharedIdentities>()
+        .await
+        .ok_or_else(|| anyhow::anyhow!("Identities not set in GlobalContext"))?;
+
+    let (session_id, ephemeral_public) = identity.session_keys.lock().await.create(false).await;
+    let (tx, rx) = oneshot::channel();
+    identities
+        .pending
+        .insert(session_id.clone(), (identity.name.clone(), tx));
+
+    let cmd = RekeyCommand {
+        session_id: session_id.clone(),
+        ephemeral_public_key: ephemeral_public.to_bytes(),
+    };
+    tracing::info!("🔑 Establishing session {} ↔ {}", identity.name, peer);
+    let sent = P2PFrame::send_as(
+        ctx,
+        &identity.address,
+        &Some(cmd),
+        Entity::Node,
+        Action::Rekey,
+        false,
+    )
+    .await;
+    if let Err(e) = sent {
+        identities.pending.remove(&session_id);
+        return Err(e);
+    }
+
+    match tokio::time::timeout(Duration::from_secs(SESSION_TIMEOUT_SECS), rx).await {
+        Ok(Ok(())) => Ok(()),
+        _ => {
+            identities.pending.remove(&session_id);
+            Err(anyhow::anyhow!(
+                "No session key exchange with {} for identity {}",
+                peer,
+                identity.name
+            ))
+        }
+    }
+}
+
+/// 处理发给附加身份的 RekeyAck；不是附加身份发起的会话时返回 false
+pub async fn finish_session(
+    gctx: &Arc<GlobalContext>,
+    session_id: &[u8],
+    peer: &str,
+    ephemeral_public_key: &[u8; 32],
+) -> bool {
+    let Some(identities) = gctx.get::<SharedIdentities>().await else {
+        return false;
+    };
+    let Some((_, (name, done))) = identities.pending.remove(session_id) else {
+        return false;
+    };
+    let Some(identity) = identities.get(&name) else {
+        return true;
+    };
+    let established = identity
+        .session_keys
+        .lock()
+        .await
+        .establish_ends(
+            session_id.to_vec(),
+            peer.as_bytes().to_vec(),
+            identity.address.to_string().as_bytes().to_vec(),
+            &ephemeral_public_key.to_vec(),
+        )
+        .await;
+    match established {
+        Ok(true) => {
+            identity.peers.insert(peer.to_string());
+            let _ = done.send(());
+            tracing::info!("🔑 Session established {} ↔ {}", identity.name, peer);
+        }
+        Ok(false) => tracing::error!("Session for identity {} not found", identity.name),
+        Err(e) => tracing::error!("❌ Session for identity {} failed: {:?}", identity.name, e),
+    }
+    true
+}
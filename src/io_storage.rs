@@ -1,19 +1,51 @@
+//! 本地持久化（身份地址、附加身份、服务器列表、地址簿、访问控制列表、webhook、群聊、已验证对端、发件箱、身份继任记录）
+//!
+//! 所有文件经 `tokio::fs` 读写，不阻塞运行时。写入时先写同目录下的临时文件并 fsync，
+//! 再 rename 覆盖目标文件，进程在写入中途被杀也不会留下半个 JSON。
+//!
+//! 文件内容带版本号：`{ "version": 1, "data": ... }`。旧版本的文件（没有版本信封的裸 JSON）
+//! 在读取时迁移到当前版本并重写；版本号高于当前支持的文件不会被覆盖。整个数据目录的版本与
+//! 跨文件的迁移步骤见 [`crate::migration`]。
+//!
+//! 服务器列表变化频繁，通过 [`IOStorage::schedule_save`] 合并，由后台任务每
+//! `FLUSH_INTERVAL_MS` 落盘一次；退出前调用 [`IOStorage::flush`] 写出剩余内容。
+
 use std::{
     any::Any,
-    collections::{HashMap, HashSet},
-    sync::Arc,
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
 };
 
-use aex::storage::Storage;
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::{fs, io::AsyncWriteExt};
 use zz_account::address::FreeWebMovementAddress;
 
 use crate::{
     cli::Opt,
     consts::{
-        DEFAULT_APP_DIR_ADDRESS_JSON_FILE, DEFAULT_APP_DIR_EXTERNAL_SERVER_LIST_JSON_FILE,
-        DEFAULT_APP_DIR_INNER_SERVER_LIST_JSON_FILE,
+        DEFAULT_APP_DIR_ACL_JSON_FILE, DEFAULT_APP_DIR_ADDRESS_JSON_FILE,
+        DEFAULT_APP_DIR_ALIASES_JSON_FILE, DEFAULT_APP_DIR_EXTERNAL_SERVER_LIST_JSON_FILE,
+        DEFAULT_APP_DIR_GROUPS_JSON_FILE, DEFAULT_APP_DIR_IDENTITIES_JSON_FILE,
+        DEFAULT_APP_DIR_INNER_SERVER_LIST_JSON_FILE, DEFAULT_APP_DIR_OUTBOX_JSON_FILE,
+        DEFAULT_APP_DIR_SUCCESSORS_JSON_FILE, DEFAULT_APP_DIR_VERIFIED_JSON_FILE,
+        DEFAULT_APP_DIR_WEB_USERS_JSON_FILE, DEFAULT_APP_DIR_WEBHOOKS_JSON_FILE,
+    },
+    migration::MigrationReport,
+    outbox::OutboxList,
+    protocols::{
+        acl::AccessList,
+        commands::{group::GroupStore, successor::SuccessorRecord},
     },
     record::NodeRecord,
+    safety_number::VerifiedContacts,
+    scheduler::Scheduler,
+    web::auth::WebUserList,
+    webhook::WebhookList,
 };
 
 use crate::storage;
@@ -21,39 +53,109 @@ use crate::storage;
 pub static STORAGE_ADDRESS: &str = "address";
 pub static STORAGE_INNER_SERVER: &str = "inner_server";
 pub static STORAGE_EXTERNAL_SERVER: &str = "external_server";
+pub static STORAGE_ALIASES: &str = "aliases";
+pub static STORAGE_ACL: &str = "acl";
+pub static STORAGE_WEBHOOKS: &str = "webhooks";
+pub static STORAGE_IDENTITIES: &str = "identities";
+pub static STORAGE_GROUPS: &str = "groups";
+pub static STORAGE_VERIFIED: &str = "verified";
+pub static STORAGE_OUTBOX: &str = "outbox";
+pub static STORAGE_WEB_USERS: &str = "web_users";
+pub static STORAGE_SUCCESSORS: &str = "successors";
 
-pub async fn read<T, F1, F2>(storage: Arc<Storage>, file: &String, f1: F1, f2: F2) -> T
-where
-    T: for<'de> serde::Deserialize<'de> + serde::Serialize,
-    F1: Fn(&T) + Send + Sync,
-    F2: Fn(&String) -> T + Send + Sync,
-{
-    match storage.read::<T>(file) {
-        Ok(Some(v)) => {
-            f1(&v);
-            v
-        }
-        Ok(None) => {
-            let v = f2(file);
-            if let Err(e) = storage.save::<T>(file, &v) {
-                tracing::error!("Failed to save default value to {}: {:?}", file, e);
-            }
-            v
-        }
-        Err(e) => {
-            tracing::error!("Failed to read {}: {:?}, using default", file, e);
-            let v = f2(file);
-            v
+/// 当前的磁盘格式版本
+pub const STORAGE_SCHEMA_VERSION: u64 = 1;
+/// 合并写入的落盘间隔
+pub const FLUSH_INTERVAL_MS: u64 = 2000;
+
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Serialize)]
+struct Envelope<'a, T> {
+    version: u64,
+    data: &'a T,
+}
+
+/// 序列化为当前版本的磁盘格式
+pub fn encode<T: Serialize>(value: &T) -> anyhow::Result<Vec<u8>> {
+    Ok(serde_json::to_vec_pretty(&Envelope {
+        version: STORAGE_SCHEMA_VERSION,
+        data: value,
+    })?)
+}
+
+/// 文件内容的版本；没有版本信封的裸 JSON 视为版本 0
+pub fn schema_version(value: &serde_json::Value) -> u64 {
+    match value.as_object() {
+        Some(obj) if obj.len() == 2 && obj.contains_key("data") => {
+            obj.get("version").and_then(|v| v.as_u64()).unwrap_or(0)
         }
+        _ => 0,
+    }
+}
+
+/// 把磁盘内容逐级迁移到当前版本，返回 (数据, 是否发生了迁移)
+pub fn migrate(mut value: serde_json::Value) -> anyhow::Result<(serde_json::Value, bool)> {
+    let mut version = schema_version(&value);
+    if version > STORAGE_SCHEMA_VERSION {
+        anyhow::bail!(
+            "schema version {} is newer than supported version {}",
+            version,
+            STORAGE_SCHEMA_VERSION
+        );
     }
+    let migrated = version < STORAGE_SCHEMA_VERSION;
+    while version < STORAGE_SCHEMA_VERSION {
+        value = match version {
+            // v0 → v1：加上版本信封
+            0 => serde_json::json!({ "version": 1, "data": value }),
+            v => anyhow::bail!("no migration from schema version {}", v),
+        };
+        version = schema_version(&value);
+    }
+    let data = match value {
+        serde_json::Value::Object(mut obj) => obj.remove("data").unwrap_or_default(),
+        other => other,
+    };
+    Ok((data, migrated))
+}
+
+/// 解析任意版本的磁盘内容，返回 (数据, 是否需要重写)
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> anyhow::Result<(T, bool)> {
+    let (data, migrated) = migrate(serde_json::from_slice(bytes)?)?;
+    Ok((serde_json::from_value(data)?, migrated))
 }
 
-// 定义一个 Trait 来包装你的逻辑，抹除具体的 T
-pub trait StorageTask: Send + Sync {
-    fn execute(&self, storage: &Storage, key: &str);
+fn tmp_path(path: &Path) -> PathBuf {
+    let n = TMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.{}.tmp", std::process::id(), n));
+    path.with_file_name(name)
 }
 
-// 为具体的泛型实现这个 Trait
+/// 原子写入：临时文件 + fsync + rename
+pub async fn write_atomic(path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent).await?;
+        }
+    }
+    let tmp = tmp_path(path);
+    let result = async {
+        let mut file = fs::File::create(&tmp).await?;
+        file.write_all(bytes).await?;
+        file.sync_all().await?;
+        drop(file);
+        fs::rename(&tmp, path).await
+    }
+    .await;
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp).await;
+    }
+    Ok(result?)
+}
+
+/// 一个持久化条目：文件名、读取成功后的回调、文件不存在时的默认值
 pub struct IOEntry<T> {
     pub file: String,
     pub f1: Box<dyn Fn(&T) + Send + Sync>,
@@ -64,14 +166,21 @@ pub struct IOEntry<T> {
 pub struct IOStorage {
     // Key 是文件名，Value 是被抹除类型的对象
     pub stores: HashMap<String, Arc<dyn Any + Send + Sync>>,
-    pub storage: Arc<Storage>,
+    /// 相对路径所在的数据目录
+    pub dir: PathBuf,
+    /// 每个条目对应的文件名，供数据目录迁移遍历
+    files: BTreeMap<String, String>,
+    /// 等待合并写入的内容：文件路径 → 序列化后的内容
+    pending: Arc<std::sync::Mutex<HashMap<PathBuf, Vec<u8>>>>,
 }
 
 impl IOStorage {
-    pub fn new(storage: Arc<Storage>) -> Self {
+    pub fn new(dir: PathBuf) -> Self {
         Self {
             stores: HashMap::new(),
-            storage,
+            dir,
+            files: BTreeMap::new(),
+            pending: Arc::new(std::sync::Mutex::new(HashMap::new())),
         }
     }
     pub fn insert<T: Send + Sync + 'static>(
@@ -81,39 +190,153 @@ impl IOStorage {
         f1: Box<dyn Fn(&T) + Send + Sync>,
         f2: Box<dyn Fn(&String) -> T + Send + Sync>,
     ) {
+        self.files.insert(key.clone(), file.clone());
         self.stores.insert(key, Arc::new(IOEntry { file, f1, f2 }));
     }
 
+    /// 所有登记的文件的完整路径
+    pub fn files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = self.files.values().map(|f| self.path(f)).collect();
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    /// 把数据目录升级到当前格式版本，见 [`crate::migration`]
+    pub async fn migrate_data_dir(&self, dry_run: bool) -> anyhow::Result<MigrationReport> {
+        crate::migration::run(self, dry_run).await
+    }
+
     pub fn get<T: 'static>(&self, key: &str) -> Option<&IOEntry<T>> {
         self.stores.get(key)?.downcast_ref::<IOEntry<T>>()
     }
 
+    /// 文件的完整路径：绝对路径原样使用，相对路径位于数据目录下
+    pub fn path(&self, file: &str) -> PathBuf {
+        let file = Path::new(file);
+        if file.is_absolute() {
+            file.to_path_buf()
+        } else {
+            self.dir.join(file)
+        }
+    }
+
+    fn pending(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, Vec<u8>>> {
+        match self.pending.lock() {
+            Ok(g) => g,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// 读取 `key` 对应的文件；文件不存在时生成默认值并写入，旧版本文件迁移后重写
     pub async fn read<T: 'static>(&self, key: &str) -> Option<T>
     where
-        T: for<'de> serde::Deserialize<'de> + serde::Serialize,
+        T: DeserializeOwned + Serialize,
     {
-        match self.get::<T>(key) {
-            Some(v) => Some(read(self.storage.clone(), &v.file, &v.f1, &v.f2).await),
-            None => None,
+        let entry = self.get::<T>(key)?;
+        let path = self.path(&entry.file);
+        let value = match fs::read(&path).await {
+            Ok(bytes) => match decode::<T>(&bytes) {
+                Ok((v, migrated)) => {
+                    (entry.f1)(&v);
+                    if migrated {
+                        tracing::info!(
+                            "Migrating {} to schema version {}",
+                            path.display(),
+                            STORAGE_SCHEMA_VERSION
+                        );
+                        self.write(&path, &v).await;
+                    }
+                    v
+                }
+                Err(e) => {
+                    tracing::error!("Failed to read {}: {:?}, using default", path.display(), e);
+                    (entry.f2)(&entry.file)
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let v = (entry.f2)(&entry.file);
+                self.write(&path, &v).await;
+                v
+            }
+            Err(e) => {
+                tracing::error!("Failed to read {}: {:?}, using default", path.display(), e);
+                (entry.f2)(&entry.file)
+            }
+        };
+        Some(value)
+    }
+
+    async fn write<T: Serialize>(&self, path: &Path, t: &T) {
+        let result = match encode(t) {
+            Ok(bytes) => write_atomic(path, &bytes).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            tracing::error!("Failed to save {}: {:?}", path.display(), e);
         }
     }
 
+    /// 立即写入 `key` 对应的文件
     pub async fn save<T: 'static>(&self, t: &T, key: &str)
     where
-        T: for<'de> serde::Deserialize<'de> + serde::Serialize,
+        T: DeserializeOwned + Serialize,
+    {
+        if let Some(entry) = self.get::<T>(key) {
+            let path = self.path(&entry.file);
+            // 立即写入的内容比等待合并的更新
+            self.pending().remove(&path);
+            self.write(&path, t).await;
+        }
+    }
+
+    /// 登记一次写入，由 `flush` 合并落盘；同一文件只保留最后一次的内容
+    pub fn schedule_save<T: 'static>(&self, t: &T, key: &str)
+    where
+        T: DeserializeOwned + Serialize,
     {
-        match self.get::<T>(key) {
-            Some(v) => {
-                let _ = self.storage.save::<T>(&v.file, t);
+        let Some(entry) = self.get::<T>(key) else {
+            return;
+        };
+        let path = self.path(&entry.file);
+        match encode(t) {
+            Ok(bytes) => {
+                self.pending().insert(path, bytes);
             }
-            None => {}
+            Err(e) => tracing::error!("Failed to encode {}: {:?}", path.display(), e),
         }
     }
+
+    /// 写出全部等待合并的内容，返回写出的文件数
+    pub async fn flush(&self) -> usize {
+        let pending = std::mem::take(&mut *self.pending());
+        let mut written = 0;
+        for (path, bytes) in pending {
+            match write_atomic(&path, &bytes).await {
+                Ok(()) => written += 1,
+                Err(e) => tracing::error!("Failed to save {}: {:?}", path.display(), e),
+            }
+        }
+        written
+    }
+
+    /// 后台定期落盘
+    pub fn schedule_flush(&self, scheduler: &Scheduler) {
+        let storage = self.clone();
+        scheduler.register_interval(
+            "storage_flush",
+            Duration::from_millis(FLUSH_INTERVAL_MS),
+            move || {
+                let storage = storage.clone();
+                async move { storage.flush().await }
+            },
+        );
+    }
 }
 
-pub fn io_storage_init(opt: &Opt, storage: Arc<Storage>) -> IOStorage {
-    let mut ios = IOStorage::new(storage.clone());
-    storage!(ios, storage, [
+pub fn io_storage_init(opt: &Opt) -> IOStorage {
+    let mut ios = IOStorage::new(opt.app_dir());
+    storage!(ios, [
         (
             STORAGE_ADDRESS,
             opt.address_file.clone().unwrap_or(DEFAULT_APP_DIR_ADDRESS_JSON_FILE.into()),
@@ -135,6 +358,69 @@ pub fn io_storage_init(opt: &Opt, storage: Arc<Storage>) -> IOStorage {
             |_| {},
             HashSet::new()
         ),
+        (
+            STORAGE_ALIASES,
+            DEFAULT_APP_DIR_ALIASES_JSON_FILE.to_string(),
+            BTreeMap<String, String>,
+            |v| tracing::info!("Loaded {} alias(es)", v.len()),
+            BTreeMap::new()
+        ),
+        (
+            STORAGE_ACL,
+            DEFAULT_APP_DIR_ACL_JSON_FILE.to_string(),
+            AccessList,
+            |v| tracing::info!("Loaded {} acl rule(s)", v.banned.len() + v.allowed.len()),
+            AccessList::default()
+        ),
+        (
+            STORAGE_WEBHOOKS,
+            DEFAULT_APP_DIR_WEBHOOKS_JSON_FILE.to_string(),
+            WebhookList,
+            |v| tracing::info!("Loaded {} webhook(s)", v.hooks.len()),
+            WebhookList::default()
+        ),
+        (
+            STORAGE_IDENTITIES,
+            DEFAULT_APP_DIR_IDENTITIES_JSON_FILE.to_string(),
+            BTreeMap<String, FreeWebMovementAddress>,
+            |v| tracing::info!("Loaded {} extra identit(ies)", v.len()),
+            BTreeMap::new()
+        ),
+        (
+            STORAGE_GROUPS,
+            DEFAULT_APP_DIR_GROUPS_JSON_FILE.to_string(),
+            GroupStore,
+            |v| tracing::info!("Loaded {} group(s)", v.groups.len()),
+            GroupStore::default()
+        ),
+        (
+            STORAGE_VERIFIED,
+            DEFAULT_APP_DIR_VERIFIED_JSON_FILE.to_string(),
+            VerifiedContacts,
+            |v| tracing::info!("Loaded {} verified contact(s)", v.contacts.len()),
+            VerifiedContacts::default()
+        ),
+        (
+            STORAGE_OUTBOX,
+            DEFAULT_APP_DIR_OUTBOX_JSON_FILE.to_string(),
+            OutboxList,
+            |v| tracing::info!("Loaded {} queued draft(s)", v.drafts.len()),
+            OutboxList::default()
+        ),
+        (
+            STORAGE_WEB_USERS,
+            DEFAULT_APP_DIR_WEB_USERS_JSON_FILE.to_string(),
+            WebUserList,
+            |v| tracing::info!("Loaded {} web user(s)", v.users.len()),
+            WebUserList::default()
+        ),
+        (
+            STORAGE_SUCCESSORS,
+            DEFAULT_APP_DIR_SUCCESSORS_JSON_FILE.to_string(),
+            BTreeMap<String, SuccessorRecord>,
+            |v| tracing::info!("Loaded {} identity successor record(s)", v.len()),
+            BTreeMap::new()
+        ),
     ]);
     ios
 }
@@ -0,0 +1,77 @@
+//! 内网 / 外网地址分类
+//!
+//! 以下地址视为内网（Intranet）：
+//! - IPv4：RFC 1918 私有地址（10/8、172.16/12、192.168/16）、
+//!   运营商级 NAT 100.64/10、链路本地 169.254/16、环回地址
+//! - IPv6：唯一本地地址 ULA fc00::/7、链路本地 fe80::/10、环回地址，
+//!   以及映射到上述 IPv4 地址的 `::ffff:a.b.c.d`
+//!
+//! 注意：aex 的连接管理器以 `NetworkScope::from_ip` 作为连接表的键，
+//! 查询 `manager.connections` 时仍需使用 aex 的分类，不能替换为这里的结果。
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use aex::connection::scope::NetworkScope;
+
+fn is_inner_v4(ip: &Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        // 100.64.0.0/10 运营商级 NAT
+        || (a == 100 && (b & 0xc0) == 64)
+}
+
+fn is_inner_v6(ip: &Ipv6Addr) -> bool {
+    if let Some(v4) = ip.to_ipv4_mapped() {
+        return is_inner_v4(&v4);
+    }
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        // fc00::/7 唯一本地地址
+        || (first & 0xfe00) == 0xfc00
+        // fe80::/10 链路本地地址
+        || (first & 0xffc0) == 0xfe80
+}
+
+/// 判断地址是否属于内网
+pub fn is_inner_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_inner_v4(v4),
+        IpAddr::V6(v6) => is_inner_v6(v6),
+    }
+}
+
+/// 字符串形式的地址分类，无法解析时视为外网
+pub fn is_inner_ip_str(ip: &str) -> bool {
+    ip.parse::<IpAddr>()
+        .map(|ip| is_inner_ip(&ip))
+        .unwrap_or(false)
+}
+
+/// 地址所属的网络范围
+pub fn classify(ip: &IpAddr) -> NetworkScope {
+    if is_inner_ip(ip) {
+        NetworkScope::Intranet
+    } else {
+        NetworkScope::Extranet
+    }
+}
+
+/// 将本机地址按内网 / 外网拆分为用于 Online 公告的两个列表。
+/// 忽略 aex 给出的范围，统一按本模块重新分类。
+pub fn split_ips<'a, I>(ips: I) -> (Vec<String>, Vec<String>)
+where
+    I: IntoIterator<Item = &'a (NetworkScope, IpAddr)>,
+{
+    let mut inner = Vec::new();
+    let mut outer = Vec::new();
+    for (_, ip) in ips {
+        if is_inner_ip(ip) {
+            inner.push(ip.to_string());
+        } else {
+            outer.push(ip.to_string());
+        }
+    }
+    (inner, outer)
+}
@@ -0,0 +1,392 @@
+//! 节点生命周期事件日志
+//!
+//! 节点启动/停止、对端连接/断开、握手失败、消息转发等事件以一行 JSON 追加到
+//! `<data_dir>/events.log`，供事后排查网络行为。事件名按 `类别.事件` 分层
+//! （如 `peer.connected`），查询时可以只看某一类。日志超过 `max_bytes` 时按
+//! [`crate::log_file`] 的规则轮转，最多保留 `max_files` 个旧文件。
+
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader, Write},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use aex::connection::{context::Context, global::GlobalContext};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cli::Opt,
+    events::{self, NodeEvent},
+    log_file::{self, open_append, rotated_path},
+    peer_capabilities::CapabilityRegression,
+    protocols::{limits, peer_stats},
+    reachability,
+};
+
+/// 日志文件名
+pub const JOURNAL_FILE: &str = "events.log";
+/// 默认轮转阈值
+pub const DEFAULT_JOURNAL_MAX_BYTES: u64 = 4 * 1024 * 1024;
+/// 默认保留的旧日志个数
+pub const DEFAULT_JOURNAL_MAX_FILES: usize = 5;
+/// `events` 命令默认显示的条数
+pub const DEFAULT_EVENTS_SHOWN: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "event")]
+pub enum Event {
+    #[serde(rename = "node.started")]
+    Started { address: String, listen: String },
+    #[serde(rename = "node.stopped")]
+    Stopped { address: String },
+    #[serde(rename = "peer.connected")]
+    PeerConnected {
+        peer: String,
+        addr: String,
+        inbound: bool,
+    },
+    #[serde(rename = "peer.disconnected")]
+    PeerDisconnected {
+        peer: Option<String>,
+        addr: String,
+        reason: String,
+    },
+    #[serde(rename = "peer.handshake_failed")]
+    HandshakeFailed {
+        peer: String,
+        addr: String,
+        reason: String,
+    },
+    #[serde(rename = "message.forwarded")]
+    MessageForwarded {
+        from: String,
+        to: String,
+        next_hops: usize,
+        ttl: u8,
+    },
+    /// 对端的能力比上一次会话少，见 [`crate::peer_capabilities`]
+    #[serde(rename = "peer.capability_downgraded")]
+    CapabilityDowngraded {
+        peer: String,
+        addr: String,
+        #[serde(flatten)]
+        change: CapabilityRegression,
+    },
+    /// 连续一段时间没有任何已连接节点，见 [`crate::partition`]
+    #[serde(rename = "network.partition_detected")]
+    PartitionDetected { isolated_secs: u64 },
+    #[serde(rename = "network.partition_recovered")]
+    PartitionRecovered {
+        duration_secs: u64,
+        rebootstraps: u32,
+        peers: usize,
+    },
+    /// 对端回复本节点发出的某一帧处理失败，见 [`crate::protocols::commands::error`]
+    #[serde(rename = "message.remote_error")]
+    RemoteError {
+        from: String,
+        code: String,
+        nonce: u64,
+        request_id: Option<u64>,
+        detail: String,
+    },
+    /// 本节点（`local`）或对端轮换了身份，见 [`crate::protocols::commands::successor`]
+    #[serde(rename = "identity.rotated")]
+    IdentityRotated {
+        old: String,
+        new: String,
+        grace_secs: u64,
+        local: bool,
+    },
+}
+
+/// 事件名 `name` 是否属于 `filter`（完整事件名或上级类别）
+pub fn name_matches(name: &str, filter: &str) -> bool {
+    name == filter
+        || name
+            .strip_prefix(filter)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+impl Event {
+    /// 分层事件名，与序列化后的 `event` 字段一致
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::Started { .. } => "node.started",
+            Event::Stopped { .. } => "node.stopped",
+            Event::PeerConnected { .. } => "peer.connected",
+            Event::PeerDisconnected { .. } => "peer.disconnected",
+            Event::HandshakeFailed { .. } => "peer.handshake_failed",
+            Event::MessageForwarded { .. } => "message.forwarded",
+            Event::CapabilityDowngraded { .. } => "peer.capability_downgraded",
+            Event::PartitionDetected { .. } => "network.partition_detected",
+            Event::PartitionRecovered { .. } => "network.partition_recovered",
+            Event::RemoteError { .. } => "message.remote_error",
+            Event::IdentityRotated { .. } => "identity.rotated",
+        }
+    }
+
+    /// 事件是否属于 `filter`：完整事件名，或其任一上级类别（`peer` 匹配 `peer.connected`）
+    pub fn matches(&self, filter: &str) -> bool {
+        name_matches(self.name(), filter)
+    }
+
+    fn details(&self) -> String {
+        match self {
+            Event::Started { address, listen } => format!("{} listening on {}", address, listen),
+            Event::Stopped { address } => address.clone(),
+            Event::PeerConnected {
+                peer,
+                addr,
+                inbound,
+            } => format!(
+                "{} ({}, {})",
+                peer,
+                addr,
+                if *inbound { "inbound" } else { "outbound" }
+            ),
+            Event::PeerDisconnected { peer, addr, reason } => match peer {
+                Some(peer) => format!("{} ({}): {}", peer, addr, reason),
+                None => format!("{}: {}", addr, reason),
+            },
+            Event::HandshakeFailed { peer, addr, reason } => {
+                format!("{} ({}): {}", peer, addr, reason)
+            }
+            Event::MessageForwarded {
+                from,
+                to,
+                next_hops,
+                ttl,
+            } => format!("{} → {} via {} peer(s), ttl={}", from, to, next_hops, ttl),
+            Event::CapabilityDowngraded { peer, addr, change } => {
+                format!("{} ({}): {}", peer, addr, change)
+            }
+            Event::PartitionDetected { isolated_secs } => {
+                format!("no connected peers for {}s", isolated_secs)
+            }
+            Event::PartitionRecovered {
+                duration_secs,
+                rebootstraps,
+                peers,
+            } => format!(
+                "{} peer(s) after {}s, {} re-bootstrap(s)",
+                peers, duration_secs, rebootstraps
+            ),
+            Event::RemoteError {
+                from,
+                code,
+                nonce,
+                detail,
+                ..
+            } => format!("{} rejected nonce={}: {} {}", from, nonce, code, detail),
+            Event::IdentityRotated {
+                old,
+                new,
+                grace_secs,
+                ..
+            } => format!(
+                "{} → {}, old address accepted for {}s",
+                old, new, grace_secs
+            ),
+        }
+    }
+}
+
+/// 日志中的一行
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JournalEntry {
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+impl std::fmt::Display for JournalEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {: <22} {}",
+            self.at.format("%Y-%m-%d %H:%M:%S"),
+            self.event.name(),
+            self.event.details()
+        )
+    }
+}
+
+struct JournalState {
+    file: File,
+    size: u64,
+}
+
+pub struct Journal {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    state: Mutex<JournalState>,
+}
+
+pub type SharedJournal = Arc<Journal>;
+
+pub fn journal_path(opt: &Opt) -> PathBuf {
+    opt.app_dir().join(JOURNAL_FILE)
+}
+
+/// 读取单个日志文件；损坏或被截断的行跳过
+fn read_entries(path: &Path) -> anyhow::Result<Vec<JournalEntry>> {
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Ok(entry) = serde_json::from_str::<JournalEntry>(&line) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+impl Journal {
+    /// 打开（或创建）日志
+    pub fn open(
+        path: impl Into<PathBuf>,
+        max_bytes: u64,
+        max_files: usize,
+    ) -> anyhow::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = open_append(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_bytes,
+            max_files: max_files.max(1),
+            state: Mutex::new(JournalState { file, size }),
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, JournalState> {
+        match self.state.lock() {
+            Ok(g) => g,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 追加一条事件
+    pub fn append(&self, event: Event) -> anyhow::Result<()> {
+        let entry = JournalEntry {
+            at: Utc::now(),
+            event,
+        };
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        let mut state = self.lock();
+        if state.size > 0 && state.size + line.len() as u64 > self.max_bytes {
+            self.rotate(&mut state)?;
+        }
+        state.file.write_all(&line)?;
+        state.file.flush()?;
+        state.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&self, state: &mut JournalState) -> anyhow::Result<()> {
+        log_file::rotate(&self.path, self.max_files)?;
+        state.file = open_append(&self.path)?;
+        state.size = 0;
+        Ok(())
+    }
+
+    /// 最近的 `n` 条事件（按时间先后排列），`filter` 为事件名或类别
+    pub fn tail(&self, n: usize, filter: Option<&str>) -> anyhow::Result<Vec<JournalEntry>> {
+        // 持锁读取，避免读到轮转一半的文件
+        let _state = self.lock();
+        let mut out: Vec<JournalEntry> = Vec::new();
+        for i in 0..=self.max_files {
+            if out.len() >= n {
+                break;
+            }
+            let path = if i == 0 {
+                self.path.clone()
+            } else {
+                rotated_path(&self.path, i)
+            };
+            let mut older: Vec<JournalEntry> = read_entries(&path)?
+                .into_iter()
+                .filter(|e| filter.is_none_or(|f| e.event.matches(f)))
+                .collect();
+            let keep = n - out.len();
+            if older.len() > keep {
+                older.drain(..older.len() - keep);
+            }
+            older.append(&mut out);
+            out = older;
+        }
+        Ok(out)
+    }
+}
+
+/// 记录一条事件并广播到事件总线；未启用日志时只广播，写入失败只记警告
+pub async fn record(gctx: &Arc<GlobalContext>, event: Event) {
+    events::publish(gctx, NodeEvent::Journal(event.clone())).await;
+    let Some(journal) = gctx.get::<SharedJournal>().await else {
+        return;
+    };
+    if let Err(e) = journal.append(event) {
+        tracing::warn!(
+            "Failed to write event journal {}: {:?}",
+            journal.path().display(),
+            e
+        );
+    }
+}
+
+/// 记录对端断开
+pub async fn record_disconnect(
+    gctx: &Arc<GlobalContext>,
+    peer: Option<&str>,
+    addr: SocketAddr,
+    reason: &str,
+) {
+    // 连接断开后其统计不再有意义
+    peer_stats::forget(gctx, addr).await;
+    reachability::forget(gctx, addr).await;
+    limits::forget(gctx, addr).await;
+    let event = Event::PeerDisconnected {
+        peer: peer.map(str::to_string),
+        addr: addr.to_string(),
+        reason: reason.to_string(),
+    };
+    record(gctx, event).await;
+}
+
+/// 记录握手失败
+pub async fn record_handshake_failed(
+    ctx: &Arc<tokio::sync::Mutex<Context>>,
+    peer: &str,
+    reason: &str,
+) {
+    let (gctx, addr) = {
+        let guard = ctx.lock().await;
+        (guard.global.clone(), guard.addr)
+    };
+    let event = Event::HandshakeFailed {
+        peer: peer.to_string(),
+        addr: addr.to_string(),
+        reason: reason.to_string(),
+    };
+    record(&gctx, event).await;
+}
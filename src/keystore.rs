@@ -0,0 +1,324 @@
+//! 私钥加密存储
+//!
+//! 默认情况下节点身份（含私钥）以明文 JSON 保存在 `address.json`。启用 `--encrypt-key`
+//! 后改为保存到 `<data_dir>/keystore.json`：用 Argon2id 从口令派生 256 位密钥，
+//! 再以 XChaCha20-Poly1305 加密序列化后的 `FreeWebMovementAddress`，公开地址作为附加数据，
+//! 明文文件在迁移成功后删除。
+//!
+//! 口令来源依次为环境变量 `ZZ_P2P_PASSPHRASE`、`--passphrase-file`、终端提示输入。
+//! `zzp2p key export | import | rotate` 用于导出、导入身份与更换口令。
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use chacha20poly1305::{
+    XChaCha20Poly1305, XNonce,
+    aead::{Aead, KeyInit, Payload},
+};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::{
+    cli::{KeyAction, Opt},
+    consts::DEFAULT_APP_DIR_ADDRESS_JSON_FILE,
+    io_storage::{IOStorage, STORAGE_ADDRESS},
+};
+
+pub const KEYSTORE_FILE: &str = "keystore.json";
+pub const KEYSTORE_VERSION: u8 = 1;
+/// 提供口令的环境变量
+pub const PASSPHRASE_ENV: &str = "ZZ_P2P_PASSPHRASE";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const KEY_LEN: usize = 32;
+
+/// Argon2id 参数，随密钥文件保存以便日后调整
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// 内存开销（KiB）
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            m_cost: 19 * 1024,
+            t_cost: 2,
+            p_cost: 1,
+        }
+    }
+}
+
+/// `keystore.json` 的内容
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u8,
+    /// 公开地址，便于不解锁即可识别身份；同时作为 AEAD 附加数据
+    pub address: String,
+    pub kdf: KdfParams,
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], params: &KdfParams) -> anyhow::Result<[u8; KEY_LEN]> {
+    let params = Params::new(params.m_cost, params.t_cost, params.p_cost, Some(KEY_LEN))
+        .map_err(|e| anyhow::anyhow!("Invalid argon2 params: {}", e))?;
+    let mut key = [0u8; KEY_LEN];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow::anyhow!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+impl Keystore {
+    /// 用口令加密身份
+    pub fn seal(identity: &FreeWebMovementAddress, passphrase: &str) -> anyhow::Result<Self> {
+        Self::seal_with_params(identity, passphrase, KdfParams::default())
+    }
+
+    pub fn seal_with_params(
+        identity: &FreeWebMovementAddress,
+        passphrase: &str,
+        kdf: KdfParams,
+    ) -> anyhow::Result<Self> {
+        if passphrase.is_empty() {
+            return Err(anyhow::anyhow!("Passphrase must not be empty"));
+        }
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut salt);
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+
+        let address = identity.to_string();
+        let plaintext = serde_json::to_vec(identity)?;
+        let key = derive_key(passphrase, &salt, &kdf)?;
+        let cipher = XChaCha20Poly1305::new(&key.into());
+        let ciphertext = cipher
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: address.as_bytes(),
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("Keystore encryption failed"))?;
+
+        Ok(Self {
+            version: KEYSTORE_VERSION,
+            address,
+            kdf,
+            salt: BASE64.encode(salt),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        })
+    }
+
+    /// 用口令解锁；口令错误或文件被篡改时返回错误
+    pub fn unlock(&self, passphrase: &str) -> anyhow::Result<FreeWebMovementAddress> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(anyhow::anyhow!(
+                "Unsupported keystore version {}",
+                self.version
+            ));
+        }
+        let salt = BASE64.decode(&self.salt)?;
+        let nonce = BASE64.decode(&self.nonce)?;
+        let ciphertext = BASE64.decode(&self.ciphertext)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(anyhow::anyhow!("Malformed keystore nonce"));
+        }
+
+        let key = derive_key(passphrase, &salt, &self.kdf)?;
+        let cipher = XChaCha20Poly1305::new(&key.into());
+        let plaintext = cipher
+            .decrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: self.address.as_bytes(),
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("Wrong passphrase or corrupted keystore"))?;
+
+        let identity: FreeWebMovementAddress = serde_json::from_slice(&plaintext)?;
+        if identity.to_string() != self.address {
+            return Err(anyhow::anyhow!("Keystore address does not match its key"));
+        }
+        Ok(identity)
+    }
+
+    /// 用新口令重新加密（新的 salt 与 nonce）
+    pub fn rotate(&self, old: &str, new: &str) -> anyhow::Result<Self> {
+        let identity = self.unlock(old)?;
+        Self::seal_with_params(&identity, new, self.kdf)
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// 先写临时文件再原子替换，避免写到一半丢失身份
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&tmp, fs::Permissions::from_mode(0o600))?;
+        }
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+pub fn keystore_path(opt: &Opt) -> PathBuf {
+    opt.app_dir().join(KEYSTORE_FILE)
+}
+
+/// 明文身份文件路径（与 io_storage 中 `STORAGE_ADDRESS` 条目一致）
+fn plain_address_path(opt: &Opt) -> PathBuf {
+    let file = PathBuf::from(
+        opt.address_file
+            .clone()
+            .unwrap_or(DEFAULT_APP_DIR_ADDRESS_JSON_FILE.into()),
+    );
+    if file.is_absolute() {
+        file
+    } else {
+        opt.app_dir().join(file)
+    }
+}
+
+/// 取得节点口令：环境变量 → 口令文件 → 终端输入（`confirm` 时需输入两次）
+pub fn obtain_passphrase(opt: &Opt, prompt: &str, confirm: bool) -> anyhow::Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        if !passphrase.is_empty() {
+            return Ok(passphrase);
+        }
+    }
+    if let Some(file) = opt.passphrase_file.as_deref() {
+        let text = fs::read_to_string(file)
+            .map_err(|e| anyhow::anyhow!("Cannot read passphrase file {}: {}", file, e))?;
+        return Ok(text.trim_end_matches(['\r', '\n']).to_string());
+    }
+    prompt_passphrase(prompt, confirm)
+}
+
+/// 仅从终端读取口令（用于导入/导出时的另一个口令）
+pub fn prompt_passphrase(prompt: &str, confirm: bool) -> anyhow::Result<String> {
+    let passphrase = rpassword::prompt_password(prompt)?;
+    if confirm {
+        let again = rpassword::prompt_password("Repeat passphrase: ")?;
+        if again != passphrase {
+            return Err(anyhow::anyhow!("Passphrases do not match"));
+        }
+    }
+    Ok(passphrase)
+}
+
+fn remove_plain_address(opt: &Opt) {
+    let path = plain_address_path(opt);
+    match fs::remove_file(&path) {
+        Ok(()) => tracing::info!("🗑️ Removed plaintext identity {}", path.display()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => tracing::warn!(
+            "Failed to remove plaintext identity {}: {}",
+            path.display(),
+            e
+        ),
+    }
+}
+
+/// 启动时加载身份：存在 keystore 则要求口令解锁；否则读取明文身份，
+/// 若指定了 `--encrypt-key` 则迁移到 keystore
+pub async fn load_identity(
+    opt: &Opt,
+    io_storage: &IOStorage,
+) -> anyhow::Result<FreeWebMovementAddress> {
+    let path = keystore_path(opt);
+    if path.exists() {
+        let keystore = Keystore::load(&path)?;
+        let passphrase = obtain_passphrase(
+            opt,
+            &format!("Passphrase for {}: ", keystore.address),
+            false,
+        )?;
+        let identity = keystore.unlock(&passphrase)?;
+        tracing::info!("🔐 Unlocked keystore {}", path.display());
+        return Ok(identity);
+    }
+
+    let identity = match io_storage
+        .read::<FreeWebMovementAddress>(STORAGE_ADDRESS)
+        .await
+    {
+        Some(v) => v,
+        None => {
+            tracing::error!("Failed to read address from storage in init, generating random");
+            FreeWebMovementAddress::random()
+        }
+    };
+
+    if opt.encrypt_key {
+        let passphrase = obtain_passphrase(opt, "New keystore passphrase: ", true)?;
+        Keystore::seal(&identity, &passphrase)?.save(&path)?;
+        tracing::info!("🔐 Identity encrypted into {}", path.display());
+        remove_plain_address(opt);
+    }
+    Ok(identity)
+}
+
+/// `zzp2p key ...` 离线子命令
+pub fn run(opt: &Opt, action: &KeyAction) -> anyhow::Result<()> {
+    let path = keystore_path(opt);
+    match action {
+        KeyAction::Export { file } => {
+            let keystore = Keystore::load(&path)
+                .map_err(|e| anyhow::anyhow!("No keystore at {}: {}", path.display(), e))?;
+            let identity =
+                keystore.unlock(&obtain_passphrase(opt, "Keystore passphrase: ", false)?)?;
+            let export_pass = prompt_passphrase("Export passphrase: ", true)?;
+            Keystore::seal_with_params(&identity, &export_pass, keystore.kdf)?
+                .save(Path::new(file))?;
+            println!("Exported {} to {}", identity, file);
+        }
+        KeyAction::Import { file } => {
+            if path.exists() {
+                return Err(anyhow::anyhow!(
+                    "A keystore already exists at {}; move it away first",
+                    path.display()
+                ));
+            }
+            let source = Keystore::load(Path::new(file))?;
+            let identity = source.unlock(&prompt_passphrase(
+                &format!("Passphrase for {}: ", source.address),
+                false,
+            )?)?;
+            let passphrase = obtain_passphrase(opt, "New keystore passphrase: ", true)?;
+            Keystore::seal(&identity, &passphrase)?.save(&path)?;
+            remove_plain_address(opt);
+            println!("Imported {} into {}", identity, path.display());
+        }
+        KeyAction::Rotate => {
+            let keystore = Keystore::load(&path)
+                .map_err(|e| anyhow::anyhow!("No keystore at {}: {}", path.display(), e))?;
+            let old = obtain_passphrase(opt, "Current passphrase: ", false)?;
+            let new = prompt_passphrase("New passphrase: ", true)?;
+            keystore.rotate(&old, &new)?.save(&path)?;
+            println!("Passphrase changed for {}", keystore.address);
+        }
+    }
+    Ok(())
+}
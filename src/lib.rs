@@ -1,12 +1,65 @@
+pub mod admin;
+pub mod bootstrap;
+pub mod capture;
 pub mod cli;
+pub mod client_mode;
 pub mod clis;
+pub mod clock;
+pub mod config;
+pub mod connections;
 pub mod consts;
+pub mod control;
+pub mod daemon;
 pub mod db;
+pub mod dialer;
+pub mod doctor;
+pub mod endpoint_verifier;
+pub mod events;
+pub mod fanout;
+pub mod identities;
 pub mod io_storage;
+pub mod ip_scope;
+pub mod journal;
+pub mod keystore;
+pub mod listen;
+pub mod listener;
+pub mod log_file;
 pub mod macros;
+pub mod media;
+pub mod migration;
 pub mod network_type;
 pub mod node;
+pub mod outbox;
+pub mod partition;
+pub mod peer_capabilities;
+pub mod peer_maintenance;
+pub mod peer_store;
+pub mod port_mapping;
 pub mod protocols;
+pub mod proxy;
+pub mod reachability;
+pub mod readiness;
 pub mod record;
+pub mod relay;
+pub mod reliable_udp;
+pub mod repl;
+pub mod resolver;
+pub mod retention;
+pub mod retry;
+pub mod safety_number;
+pub mod scheduler;
+pub mod script;
+pub mod secure_link;
+pub mod server_list;
+#[cfg(feature = "simulation")]
+pub mod simulation;
+pub mod status;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod transport;
+#[cfg(feature = "tui")]
+pub mod tui;
 pub mod user_store;
+pub mod wal;
 pub mod web;
+pub mod webhook;
@@ -0,0 +1,164 @@
+//! 多地址监听
+//!
+//! 主地址来自 `--ip` / `--port`，`--listen`（或配置文件中的 `listen`）追加更多地址：
+//! 例如 `[::]:1090` 让只有 IPv6 的对端也能连入，或者只写端口号在主 IP 上再开放一个端口。
+//! 每个地址各自运行一个受监管的 P2P server，所有地址都写入本节点的 seed 与在线状态记录。
+//!
+//! Linux 默认 `net.ipv6.bindv6only = 0`，此时 `[::]:p` 同时接收 IPv4 连接，再绑定
+//! `0.0.0.0:p` 会因端口占用而失败；两者同时配置时只保留 `[::]:p`。
+
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+
+use aex::connection::global::GlobalContext;
+
+/// 实际绑定的监听地址（第一个为主地址），启动时保存在 GlobalContext 中
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListenAddrs {
+    pub bindings: Vec<SocketAddr>,
+    /// `[::]` 是否同时接收 IPv4 连接
+    pub dual_stack: bool,
+}
+
+/// 解析一个监听地址：`ip:port`、`[v6]:port`，或只有端口（使用 `default_ip`）
+pub fn parse(entry: &str, default_ip: IpAddr) -> anyhow::Result<SocketAddr> {
+    let entry = entry.trim();
+    if let Ok(port) = entry.parse::<u16>() {
+        return Ok(SocketAddr::new(default_ip, port));
+    }
+    entry
+        .parse::<SocketAddr>()
+        .map_err(|_| anyhow::anyhow!("invalid listen address: {}", entry))
+}
+
+/// 当前节点的监听地址；启动时未保存则只有 GlobalContext 的地址
+pub async fn current(gctx: &GlobalContext) -> ListenAddrs {
+    match gctx.get::<ListenAddrs>().await {
+        Some(listen) => listen,
+        None => ListenAddrs::single(gctx.addr),
+    }
+}
+
+/// 本机 `[::]` 是否同时接收 IPv4 连接（读取 Linux 的 `bindv6only`，其它平台按否处理）
+pub fn dual_stack() -> bool {
+    match std::fs::read_to_string("/proc/sys/net/ipv6/bindv6only") {
+        Ok(value) => value.trim() == "0",
+        Err(_) => false,
+    }
+}
+
+impl ListenAddrs {
+    /// 主地址加上额外地址，去重；双栈时 `0.0.0.0:p` 并入同端口的 `[::]:p`
+    pub fn new(primary: SocketAddr, extra: &[String], dual_stack: bool) -> anyhow::Result<Self> {
+        let mut bindings = vec![primary];
+        for entry in extra {
+            bindings.push(parse(entry, primary.ip())?);
+        }
+        if dual_stack {
+            let v6_ports: Vec<u16> = bindings
+                .iter()
+                .filter(|a| a.is_ipv6() && a.ip().is_unspecified())
+                .map(|a| a.port())
+                .collect();
+            for binding in bindings.iter_mut() {
+                if binding.is_ipv4()
+                    && binding.ip().is_unspecified()
+                    && v6_ports.contains(&binding.port())
+                {
+                    *binding = SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), binding.port());
+                }
+            }
+        }
+        let mut seen = Vec::new();
+        bindings.retain(|a| {
+            if seen.contains(a) {
+                false
+            } else {
+                seen.push(*a);
+                true
+            }
+        });
+        Ok(Self {
+            bindings,
+            dual_stack,
+        })
+    }
+
+    /// 只监听一个地址
+    pub fn single(addr: SocketAddr) -> Self {
+        Self {
+            bindings: vec![addr],
+            dual_stack: false,
+        }
+    }
+
+    pub fn primary(&self) -> SocketAddr {
+        self.bindings[0]
+    }
+
+    /// 除主地址外的其它监听地址
+    pub fn extra(&self) -> &[SocketAddr] {
+        &self.bindings[1..]
+    }
+
+    /// 所有监听端口（去重，按配置顺序）
+    pub fn ports(&self) -> Vec<u16> {
+        self.ports_where(|_| true)
+    }
+
+    /// 连接本机地址 `ip` 时可用的监听端口
+    pub fn ports_for(&self, ip: &IpAddr) -> Vec<u16> {
+        self.ports_where(|binding| {
+            if binding.ip().is_unspecified() {
+                self.same_family(binding, ip)
+            } else {
+                binding.ip() == *ip
+            }
+        })
+    }
+
+    /// NAT 外的反射地址 `ip` 可能转发到的监听端口：只要求地址族一致
+    pub fn external_ports(&self, ip: &IpAddr) -> Vec<u16> {
+        self.ports_where(|binding| self.same_family(binding, ip))
+    }
+
+    /// 本机地址与监听地址组合出的可公告端点（跳过回环与未指定地址）
+    pub fn advertised(&self, ips: &[IpAddr]) -> Vec<SocketAddr> {
+        let mut endpoints = Vec::new();
+        let specific = self
+            .bindings
+            .iter()
+            .filter(|b| !b.ip().is_unspecified())
+            .copied();
+        let expanded = ips.iter().flat_map(|ip| {
+            self.ports_for(ip)
+                .into_iter()
+                .map(|p| SocketAddr::new(*ip, p))
+        });
+        for addr in specific.chain(expanded) {
+            if addr.ip().is_loopback() || addr.ip().is_unspecified() || endpoints.contains(&addr) {
+                continue;
+            }
+            endpoints.push(addr);
+        }
+        endpoints
+    }
+
+    fn same_family(&self, binding: &SocketAddr, ip: &IpAddr) -> bool {
+        match (binding.ip(), ip) {
+            (IpAddr::V4(_), IpAddr::V4(_)) | (IpAddr::V6(_), IpAddr::V6(_)) => true,
+            // 双栈的 `[::]` 也接收 IPv4
+            (IpAddr::V6(v6), IpAddr::V4(_)) => self.dual_stack && v6.is_unspecified(),
+            (IpAddr::V4(_), IpAddr::V6(_)) => false,
+        }
+    }
+
+    fn ports_where(&self, accepts: impl Fn(&SocketAddr) -> bool) -> Vec<u16> {
+        let mut ports = Vec::new();
+        for binding in self.bindings.iter().filter(|b| accepts(b)) {
+            if !ports.contains(&binding.port()) {
+                ports.push(binding.port());
+            }
+        }
+        ports
+    }
+}
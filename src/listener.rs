@@ -0,0 +1,260 @@
+//! 监听器与生命周期管理
+//!
+//! 节点上长期运行的服务（P2P server、本地控制接口）都实现 [`Listener`]，
+//! 由 [`HandlerSet`] 统一启动、停止并报告健康状态。监听任务返回错误或 panic 时按指数退避重启；
+//! 连续失败次数超过上限后标记为 `Failed` 不再重启。
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use aex::{connection::global::GlobalContext, server::Server};
+use futures::future::BoxFuture;
+use serde::Serialize;
+use tokio::{sync::watch, task::JoinHandle};
+
+use crate::protocols::{command::P2PCommand, frame::P2PFrame};
+
+/// 首次重启前的等待时间
+pub const RESTART_BACKOFF_MS: u64 = 500;
+/// 重启等待时间上限
+pub const MAX_RESTART_BACKOFF_MS: u64 = 30_000;
+/// 连续失败多少次后放弃
+pub const MAX_RESTARTS: u32 = 10;
+/// 运行超过该时长后认为已恢复，连续失败计数清零
+pub const STABLE_AFTER: Duration = Duration::from_secs(60);
+
+pub trait Listener: Send + Sync + 'static {
+    fn name(&self) -> &str;
+
+    /// 运行监听器，直到出错或被停止；正常返回视为监听器主动退出，不再重启
+    fn start(self: Arc<Self>) -> BoxFuture<'static, anyhow::Result<()>>;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum Health {
+    Starting,
+    Running,
+    /// 等待重启；`attempts` 为连续失败次数
+    Restarting {
+        attempts: u32,
+        last_error: String,
+    },
+    Stopped,
+    Failed {
+        last_error: String,
+    },
+}
+
+impl std::fmt::Display for Health {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Health::Starting => write!(f, "starting"),
+            Health::Running => write!(f, "running"),
+            Health::Restarting {
+                attempts,
+                last_error,
+            } => write!(f, "restarting (attempt {}): {}", attempts, last_error),
+            Health::Stopped => write!(f, "stopped"),
+            Health::Failed { last_error } => write!(f, "failed: {}", last_error),
+        }
+    }
+}
+
+struct Supervised {
+    name: String,
+    health: Arc<Mutex<Health>>,
+    stop: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+/// 节点持有的监听器集合
+#[derive(Clone, Default)]
+pub struct HandlerSet {
+    entries: Arc<Mutex<Vec<Supervised>>>,
+}
+
+fn set_health(health: &Mutex<Health>, value: Health) {
+    match health.lock() {
+        Ok(mut g) => *g = value,
+        Err(poisoned) => *poisoned.into_inner() = value,
+    }
+}
+
+fn backoff(attempts: u32) -> Duration {
+    let ms = RESTART_BACKOFF_MS.saturating_mul(1 << attempts.saturating_sub(1).min(16));
+    Duration::from_millis(ms.min(MAX_RESTART_BACKOFF_MS))
+}
+
+async fn supervise<L: Listener>(
+    listener: Arc<L>,
+    health: Arc<Mutex<Health>>,
+    mut stop: watch::Receiver<bool>,
+) {
+    let mut attempts = 0u32;
+    loop {
+        set_health(&health, Health::Running);
+        let started = Instant::now();
+        let mut task = tokio::spawn(listener.clone().start());
+        let result = tokio::select! {
+            r = &mut task => r,
+            _ = stop.changed() => {
+                task.abort();
+                set_health(&health, Health::Stopped);
+                return;
+            }
+        };
+        let error = match result {
+            Ok(Ok(())) => {
+                tracing::info!("Listener {} exited", listener.name());
+                set_health(&health, Health::Stopped);
+                return;
+            }
+            Ok(Err(e)) => format!("{:?}", e),
+            Err(e) if e.is_panic() => "panicked".to_string(),
+            Err(e) => e.to_string(),
+        };
+
+        if started.elapsed() >= STABLE_AFTER {
+            attempts = 0;
+        }
+        attempts += 1;
+        if attempts > MAX_RESTARTS {
+            tracing::error!(
+                "Listener {} failed {} times, giving up: {}",
+                listener.name(),
+                MAX_RESTARTS,
+                error
+            );
+            set_health(&health, Health::Failed { last_error: error });
+            return;
+        }
+        let delay = backoff(attempts);
+        tracing::warn!(
+            "Listener {} crashed ({}), restarting in {:?}",
+            listener.name(),
+            error,
+            delay
+        );
+        set_health(
+            &health,
+            Health::Restarting {
+                attempts,
+                last_error: error,
+            },
+        );
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = stop.changed() => {
+                set_health(&health, Health::Stopped);
+                return;
+            }
+        }
+    }
+}
+
+impl HandlerSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Supervised>> {
+        match self.entries.lock() {
+            Ok(g) => g,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// 启动并监管一个监听器；同名监听器已在运行时先停止旧的
+    pub fn start<L: Listener>(&self, listener: L) {
+        let name = listener.name().to_string();
+        self.stop(&name);
+        let health = Arc::new(Mutex::new(Health::Starting));
+        let (stop, stop_rx) = watch::channel(false);
+        let task = tokio::spawn(supervise(Arc::new(listener), health.clone(), stop_rx));
+        self.lock().push(Supervised {
+            name,
+            health,
+            stop,
+            task,
+        });
+    }
+
+    /// 停止指定监听器；返回是否存在
+    pub fn stop(&self, name: &str) -> bool {
+        let mut entries = self.lock();
+        let Some(pos) = entries.iter().position(|e| e.name == name) else {
+            return false;
+        };
+        let entry = entries.remove(pos);
+        let _ = entry.stop.send(true);
+        set_health(&entry.health, Health::Stopped);
+        true
+    }
+
+    /// 停止所有监听器并等待监管任务退出
+    pub async fn stop_all(&self) {
+        let entries: Vec<Supervised> = self.lock().drain(..).collect();
+        for entry in &entries {
+            let _ = entry.stop.send(true);
+        }
+        for entry in entries {
+            let _ = entry.task.await;
+        }
+    }
+
+    /// 各监听器的当前状态（按启动顺序）
+    pub fn health(&self) -> Vec<(String, Health)> {
+        self.lock()
+            .iter()
+            .map(|e| {
+                let health = match e.health.lock() {
+                    Ok(g) => g.clone(),
+                    Err(poisoned) => poisoned.into_inner().clone(),
+                };
+                (e.name.clone(), health)
+            })
+            .collect()
+    }
+}
+
+/// P2P TCP/UDP server（每个监听地址一个）
+pub struct ServerListener {
+    pub name: String,
+    pub server: Server,
+}
+
+impl Listener for ServerListener {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn start(self: Arc<Self>) -> BoxFuture<'static, anyhow::Result<()>> {
+        Box::pin(async move {
+            self.server
+                .clone()
+                .start_with_protocols::<P2PFrame, P2PCommand>()
+                .await
+                .map_err(|e| anyhow::anyhow!("{:?}", e))
+        })
+    }
+}
+
+/// 守护进程的本地控制接口
+pub struct ControlListener {
+    pub addr: SocketAddr,
+    pub context: Arc<GlobalContext>,
+}
+
+impl Listener for ControlListener {
+    fn name(&self) -> &str {
+        "control"
+    }
+
+    fn start(self: Arc<Self>) -> BoxFuture<'static, anyhow::Result<()>> {
+        Box::pin(async move { crate::control::serve(self.addr, self.context.clone()).await })
+    }
+}
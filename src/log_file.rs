@@ -0,0 +1,91 @@
+//! 按大小轮转的日志文件
+//!
+//! 当前文件写满 `max_bytes` 后重命名为 `<file>.1`，已有的 `.1..N` 依次后移，
+//! 最多保留 `max_files` 个旧文件。事件日志与守护进程的运行日志共用这套轮转规则。
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// 守护进程运行日志的默认文件名
+pub const DAEMON_LOG_FILE: &str = "zzp2p.log";
+/// 运行日志默认轮转阈值
+pub const DEFAULT_LOG_MAX_BYTES: u64 = 16 * 1024 * 1024;
+/// 运行日志默认保留的旧文件个数
+pub const DEFAULT_LOG_MAX_FILES: usize = 5;
+
+/// 第 `n` 个旧文件的路径（`<file>.n`）
+pub fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+/// 旧文件依次后移一位，当前文件变为 `.1`，超出 `max_files` 的最旧文件被删除
+pub fn rotate(path: &Path, max_files: usize) -> io::Result<()> {
+    let max_files = max_files.max(1);
+    let _ = fs::remove_file(rotated_path(path, max_files));
+    for n in (1..max_files).rev() {
+        let from = rotated_path(path, n);
+        if from.exists() {
+            fs::rename(&from, rotated_path(path, n + 1))?;
+        }
+    }
+    fs::rename(path, rotated_path(path, 1))
+}
+
+pub fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// 写满后自动轮转的文件，可直接作为 tracing 的输出
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    pub fn open(path: impl Into<PathBuf>, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = open_append(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_bytes,
+            max_files,
+            file,
+            size,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // 一次写入（一行日志）不会被拆到两个文件里
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_bytes {
+            self.file.flush()?;
+            rotate(&self.path, self.max_files)?;
+            self.file = open_append(&self.path)?;
+            self.size = 0;
+        }
+        let n = self.file.write(buf)?;
+        self.size += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
@@ -1,20 +1,35 @@
 #[macro_export]
 macro_rules! storage {
-    ($ios:expr, $storage:expr, [ $( ($key:expr, $path:expr, $type:ty, $f1:expr, $f2:expr) ),* $(,)? ]) => {
+    ($ios:expr, [ $( ($key:expr, $path:expr, $type:ty, $f1:expr, $f2:expr) ),* $(,)? ]) => {
         $(
-            let s_clone = $storage.clone();
             $ios.insert::<$type>(
                 $key.to_string(),
                 $path,
                 Box::new($f1),
-                Box::new(move |file| {
-                    let v = $f2;
-                    if let Err(e) = s_clone.save(file, &v) {
-                        tracing::error!("Failed to save {}: {:?}", file, e);
-                    }
-                    v
-                }),
+                Box::new(|_file| $f2),
             );
         )*
     };
 }
+
+/// 输出 CLI 命令的一行结果；脚本模式下记入该命令的 JSON 结果，见 [`crate::script`]
+#[macro_export]
+macro_rules! cli_println {
+    () => {
+        $crate::script::emit(String::new())
+    };
+    ($($arg:tt)*) => {
+        $crate::script::emit(format!($($arg)*))
+    };
+}
+
+/// 输出 CLI 命令的一行错误并把命令记为失败；脚本模式下记入该命令的 JSON 结果
+#[macro_export]
+macro_rules! cli_error {
+    () => {
+        $crate::script::emit_error(String::new())
+    };
+    ($($arg:tt)*) => {
+        $crate::script::emit_error(format!($($arg)*))
+    };
+}
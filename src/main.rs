@@ -1,13 +1,156 @@
 use clap::Parser;
-use tokio::io::{self, BufReader};
+use std::io::IsTerminal;
+use tokio::io::{AsyncBufRead, BufReader};
 // src/main.rs
-use zz_p2p::{cli::Opt, node::Node};
+use zz_p2p::{
+    admin, capture,
+    cli::{Command, Opt},
+    config::{self, Config},
+    control, daemon, io_storage, keystore,
+    log_file::{DEFAULT_LOG_MAX_BYTES, DEFAULT_LOG_MAX_FILES, RotatingFile},
+    node::Node,
+    script,
+};
+
+/// 一次性子命令：请求运行中的守护进程，打印 JSON 结果后退出
+async fn run_oneshot(opt: &Opt, command: &Command) -> anyhow::Result<()> {
+    let addr = opt.control_addr()?;
+    let (status, body) = match command {
+        Command::Send { address, message } => {
+            let body = serde_json::json!({"to": address, "message": message});
+            control::request(addr, "POST", "/send", Some(body)).await?
+        }
+        Command::Status => control::request(addr, "GET", "/status", None).await?,
+        Command::Peers => control::request(addr, "GET", "/peers", None).await?,
+        Command::Doctor => control::request(addr, "GET", "/health", None).await?,
+        Command::Connections => control::request(addr, "GET", "/connections", None).await?,
+        Command::Disconnect { peer } => {
+            let body = serde_json::json!({"peer": peer});
+            control::request(addr, "POST", "/disconnect", Some(body)).await?
+        }
+        Command::Daemon | Command::Key { .. } | Command::Admin { .. } | Command::Inspect { .. } => {
+            unreachable!("{:?} is not a daemon request", command)
+        }
+    };
+    println!("{}", serde_json::to_string_pretty(&body)?);
+    if status != 200 {
+        return Err(anyhow::anyhow!("daemon returned HTTP {}", status));
+    }
+    Ok(())
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let stdin = io::stdin();
-    let reader = BufReader::new(stdin);
-    let mut node = Node::init(Opt::parse()).await;
-    node.start(reader).await;
+    let mut opt = Opt::parse();
+    let config = match opt.config.as_deref() {
+        Some(path) => match Config::load(path) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("Failed to load config {}: {:?}", path, e);
+                Config::default()
+            }
+        },
+        None => Config::default(),
+    };
+    config.merge_into(&mut opt);
+
+    if opt.migrate_dry_run {
+        let storage = io_storage::io_storage_init(&opt);
+        match storage.migrate_data_dir(true).await {
+            Ok(report) => println!("{}", report),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    match opt.command.clone() {
+        #[cfg(feature = "tui")]
+        None if opt.tui => {
+            // 日志写入仪表盘的日志面板，不直接输出到终端
+            let logs = zz_p2p::tui::LogBuffer::default();
+            config::init_tracing_to_writer(config.log_level(), logs.clone());
+            let mut node = Node::init(opt, config).await;
+            node.run_tui(logs).await;
+        }
+        None if opt.script.is_some() || !std::io::stdin().is_terminal() => {
+            // 标准输出只留给每条命令的 JSON 结果，日志改写到标准错误
+            config::init_tracing_to_writer(config.log_level(), std::io::stderr());
+            let reader: Box<dyn AsyncBufRead + Unpin + Send> = match opt.script.as_deref() {
+                None | Some("-") => Box::new(BufReader::new(tokio::io::stdin())),
+                Some(path) => match tokio::fs::File::open(path).await {
+                    Ok(file) => Box::new(BufReader::new(file)),
+                    Err(e) => {
+                        eprintln!("Failed to open script {}: {}", path, e);
+                        std::process::exit(script::EXIT_UNREADABLE);
+                    }
+                },
+            };
+            let keep_going = opt.keep_going;
+            let mut node = Node::init(opt, config).await;
+            let code = node.run_script(reader, keep_going).await;
+            std::process::exit(code);
+        }
+        None => {
+            if opt.tui {
+                eprintln!(
+                    "This build has no TUI support (rebuild with --features tui), using the REPL"
+                );
+            }
+            config::init_tracing(config.log_level());
+            let mut node = Node::init(opt, config).await;
+            node.start_interactive().await;
+        }
+        Some(Command::Daemon) => {
+            match daemon::log_file_path(&opt) {
+                Some(path) => {
+                    let file =
+                        RotatingFile::open(&path, DEFAULT_LOG_MAX_BYTES, DEFAULT_LOG_MAX_FILES)?;
+                    config::init_tracing_to_file(config.log_level(), file);
+                }
+                None => config::init_tracing(config.log_level()),
+            }
+            let pid_file = daemon::PidFile::create(daemon::pid_file_path(&opt))?;
+            tracing::info!(
+                "Daemon pid {} ({})",
+                pid_file.pid(),
+                pid_file.path().display()
+            );
+            let control = opt.control_addr()?;
+            let mut node = Node::init(opt, config).await;
+            node.run_daemon(control).await;
+        }
+        Some(Command::Inspect {
+            file,
+            verify,
+            filter,
+        }) => {
+            if let Err(e) = capture::inspect(std::path::Path::new(&file), verify, filter.as_deref())
+            {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Key { action }) => {
+            if let Err(e) = keystore::run(&opt, &action) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Admin { action }) => {
+            if let Err(e) = admin::run(&opt, &action).await {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(command) => {
+            if let Err(e) = run_oneshot(&opt, &command).await {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
     Ok(())
 }
@@ -0,0 +1,415 @@
+//! 通话媒体通道（UDP）
+//!
+//! 通话接通后，双方通过各自的 UDP 媒体端口直接交换音频帧。信令阶段 Call / Accept
+//! 携带本端媒体端口，对端 IP 取自信令所在连接的地址。
+//!
+//! 每个数据报的格式：
+//!
+//! ```text
+//! MediaDatagram { call_id, sender, ciphertext }
+//!                                    └─ 会话密钥加密的 MediaPacket { call_id, frame }
+//! ```
+//!
+//! `MediaFrame` 携带 RTP 风格的序号与时间戳（以采样数计），接收端经 [`JitterBuffer`]
+//! 重排、丢弃迟到与重复的帧，再由上层通过 [`MediaChannel::pull`] 取出。
+
+use std::{collections::BTreeMap, net::SocketAddr, sync::Arc};
+
+use aex::{connection::global::GlobalContext, tcp::types::Codec};
+use bincode::{Decode, Encode};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use tokio::{net::UdpSocket, sync::Notify};
+
+use crate::protocols::commands::rekey;
+
+/// 16 位有符号小端 PCM
+pub const PAYLOAD_PCM_S16LE: u8 = 0;
+/// 开始播放前缓冲的帧数
+pub const JITTER_TARGET_FRAMES: usize = 3;
+/// 缓冲帧数达到上限后跳过缺失的帧
+pub const JITTER_MAX_FRAMES: usize = 32;
+/// 单个数据报的最大长度
+const MAX_DATAGRAM_LEN: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct MediaFrame {
+    pub seq: u32,
+    /// 以采样数计的时间戳
+    pub timestamp: u32,
+    pub payload_type: u8,
+    pub payload: Vec<u8>,
+}
+
+impl MediaFrame {
+    /// 按 `PAYLOAD_PCM_S16LE` 解出采样
+    pub fn pcm(&self) -> Vec<i16> {
+        self.payload
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect()
+    }
+}
+
+/// 加密前的媒体包；call_id 放在密文内，防止数据报头被改写到其它通话
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct MediaPacket {
+    pub call_id: u64,
+    pub frame: MediaFrame,
+}
+
+impl Codec for MediaPacket {}
+
+/// UDP 上传输的数据报
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct MediaDatagram {
+    pub call_id: u64,
+    /// 发送方地址，用于选择会话密钥
+    pub sender: String,
+    pub ciphertext: Vec<u8>,
+}
+
+impl Codec for MediaDatagram {}
+
+/// 为发出的帧分配序号与时间戳
+#[derive(Debug, Default)]
+pub struct Packetizer {
+    seq: u32,
+    timestamp: u32,
+}
+
+impl Packetizer {
+    /// `samples` 为该帧包含的采样数，决定下一帧的时间戳
+    pub fn next(&mut self, payload_type: u8, payload: Vec<u8>, samples: u32) -> MediaFrame {
+        let frame = MediaFrame {
+            seq: self.seq,
+            timestamp: self.timestamp,
+            payload_type,
+            payload,
+        };
+        self.seq = self.seq.wrapping_add(1);
+        self.timestamp = self.timestamp.wrapping_add(samples);
+        frame
+    }
+}
+
+/// 接收端抖动缓冲区
+#[derive(Debug)]
+pub struct JitterBuffer {
+    frames: BTreeMap<u32, MediaFrame>,
+    next: Option<u32>,
+    target: usize,
+    max: usize,
+    /// 被跳过（视为丢失）的帧数
+    pub lost: u64,
+    /// 迟到或重复而被丢弃的帧数
+    pub dropped: u64,
+}
+
+impl Default for JitterBuffer {
+    fn default() -> Self {
+        Self::new(JITTER_TARGET_FRAMES, JITTER_MAX_FRAMES)
+    }
+}
+
+impl JitterBuffer {
+    pub fn new(target: usize, max: usize) -> Self {
+        Self {
+            frames: BTreeMap::new(),
+            next: None,
+            target: target.max(1),
+            max: max.max(target.max(1)),
+            lost: 0,
+            dropped: 0,
+        }
+    }
+
+    /// 放入一帧；迟到或重复的帧返回 false
+    pub fn push(&mut self, frame: MediaFrame) -> bool {
+        let late = self.next.is_some_and(|next| frame.seq < next);
+        if late || self.frames.contains_key(&frame.seq) {
+            self.dropped += 1;
+            return false;
+        }
+        self.frames.insert(frame.seq, frame);
+        true
+    }
+
+    /// 按序取出下一帧；下一帧尚未到达时返回 None，
+    /// 缓冲区满时跳过缺失的帧
+    pub fn pop(&mut self) -> Option<MediaFrame> {
+        let next = match self.next {
+            Some(next) => next,
+            None => {
+                // 首帧到达后先缓冲 target 帧再开始播放
+                if self.frames.len() < self.target {
+                    return None;
+                }
+                *self.frames.keys().next()?
+            }
+        };
+        let next = match self.frames.contains_key(&next) {
+            true => next,
+            false if self.frames.len() >= self.max => {
+                let first = *self.frames.keys().next()?;
+                self.lost += u64::from(first.wrapping_sub(next));
+                first
+            }
+            false => return None,
+        };
+        let frame = self.frames.remove(&next)?;
+        self.next = Some(next.wrapping_add(1));
+        Some(frame)
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+async fn seal(gctx: &GlobalContext, peer: &str, packet: &MediaPacket) -> anyhow::Result<Vec<u8>> {
+    let psk = gctx
+        .paired_session_keys
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("PairedSessionKeys not set in GlobalContext"))?;
+    let data = Codec::encode(packet)?;
+    rekey::encrypt(gctx, &psk, peer, &data).await
+}
+
+async fn open_packet(
+    gctx: &GlobalContext,
+    datagram: &MediaDatagram,
+) -> anyhow::Result<MediaPacket> {
+    let psk = gctx
+        .paired_session_keys
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("PairedSessionKeys not set in GlobalContext"))?;
+    let plaintext = rekey::decrypt(gctx, &psk, &datagram.sender, &datagram.ciphertext).await?;
+    Codec::decode(&plaintext)
+}
+
+/// 一个通话的媒体通道
+pub struct MediaChannel {
+    pub call_id: u64,
+    pub peer: String,
+    pub remote: SocketAddr,
+    local_address: String,
+    socket: Arc<UdpSocket>,
+    gctx: Arc<GlobalContext>,
+    packetizer: std::sync::Mutex<Packetizer>,
+    jitter: std::sync::Mutex<JitterBuffer>,
+    arrived: Notify,
+}
+
+impl MediaChannel {
+    fn packetizer(&self) -> std::sync::MutexGuard<'_, Packetizer> {
+        match self.packetizer.lock() {
+            Ok(g) => g,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn jitter(&self) -> std::sync::MutexGuard<'_, JitterBuffer> {
+        match self.jitter.lock() {
+            Ok(g) => g,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// 发送一帧 PCM 采样
+    pub async fn push_pcm(&self, samples: &[i16]) -> anyhow::Result<()> {
+        let payload = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        self.push_encoded(PAYLOAD_PCM_S16LE, payload, samples.len() as u32)
+            .await
+    }
+
+    /// 发送一帧已编码的音频；`samples` 为该帧对应的采样数
+    pub async fn push_encoded(
+        &self,
+        payload_type: u8,
+        payload: Vec<u8>,
+        samples: u32,
+    ) -> anyhow::Result<()> {
+        let frame = self.packetizer().next(payload_type, payload, samples);
+        let packet = MediaPacket {
+            call_id: self.call_id,
+            frame,
+        };
+        let datagram = MediaDatagram {
+            call_id: self.call_id,
+            sender: self.local_address.clone(),
+            ciphertext: seal(&self.gctx, &self.peer, &packet).await?,
+        };
+        self.socket
+            .send_to(&Codec::encode(&datagram)?, self.remote)
+            .await?;
+        Ok(())
+    }
+
+    /// 取出下一帧（不等待）
+    pub fn try_pull(&self) -> Option<MediaFrame> {
+        self.jitter().pop()
+    }
+
+    /// 等待并取出下一帧
+    pub async fn pull(&self) -> MediaFrame {
+        loop {
+            let arrived = self.arrived.notified();
+            if let Some(frame) = self.try_pull() {
+                return frame;
+            }
+            arrived.await;
+        }
+    }
+
+    /// (丢失帧数, 丢弃帧数)
+    pub fn stats(&self) -> (u64, u64) {
+        let jitter = self.jitter();
+        (jitter.lost, jitter.dropped)
+    }
+
+    fn receive(&self, frame: MediaFrame) {
+        if self.jitter().push(frame) {
+            self.arrived.notify_waiters();
+        }
+    }
+}
+
+/// 节点的媒体端口及全部媒体通道，保存在 GlobalContext 中
+pub struct MediaEngine {
+    socket: Arc<UdpSocket>,
+    /// call_id → (对端地址, 对端媒体端点)，由信令登记
+    remotes: DashMap<u64, (String, SocketAddr)>,
+    channels: DashMap<u64, Arc<MediaChannel>>,
+}
+
+pub type SharedMediaEngine = Arc<MediaEngine>;
+
+impl MediaEngine {
+    pub async fn bind(addr: SocketAddr) -> anyhow::Result<SharedMediaEngine> {
+        let socket = UdpSocket::bind(addr).await?;
+        Ok(Arc::new(Self {
+            socket: Arc::new(socket),
+            remotes: DashMap::new(),
+            channels: DashMap::new(),
+        }))
+    }
+
+    pub fn local_port(&self) -> u16 {
+        self.socket.local_addr().map(|a| a.port()).unwrap_or(0)
+    }
+
+    /// 后台接收数据报并分发到对应的通道
+    pub fn spawn(self: Arc<Self>, gctx: Arc<GlobalContext>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; MAX_DATAGRAM_LEN];
+            loop {
+                let (len, from) = match self.socket.recv_from(&mut buf).await {
+                    Ok(r) => r,
+                    Err(e) => {
+                        tracing::warn!("Media socket receive failed: {:?}", e);
+                        continue;
+                    }
+                };
+                let datagram: MediaDatagram = match Codec::decode(&buf[..len]) {
+                    Ok(d) => d,
+                    Err(_) => continue,
+                };
+                let Some(channel) = self.channels.get(&datagram.call_id).map(|c| c.clone()) else {
+                    continue;
+                };
+                if datagram.sender != channel.peer || from.ip() != channel.remote.ip() {
+                    tracing::debug!(
+                        "Dropping media datagram from {} for call {}",
+                        from,
+                        datagram.call_id
+                    );
+                    continue;
+                }
+                match open_packet(&gctx, &datagram).await {
+                    Ok(packet) if packet.call_id == datagram.call_id => {
+                        channel.receive(packet.frame)
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::debug!("Media packet from {} rejected: {:?}", from, e),
+                }
+            }
+        })
+    }
+}
+
+async fn engine(gctx: &Arc<GlobalContext>) -> Option<SharedMediaEngine> {
+    gctx.get::<SharedMediaEngine>().await
+}
+
+/// 本端媒体端口；未启用媒体时为 0
+pub async fn local_port(gctx: &Arc<GlobalContext>) -> u16 {
+    match engine(gctx).await {
+        Some(engine) => engine.local_port(),
+        None => 0,
+    }
+}
+
+/// 登记对端在信令中给出的媒体端点
+pub async fn set_remote(gctx: &Arc<GlobalContext>, call_id: u64, peer: &str, remote: SocketAddr) {
+    if remote.port() == 0 {
+        return;
+    }
+    if let Some(engine) = engine(gctx).await {
+        engine.remotes.insert(call_id, (peer.to_string(), remote));
+    }
+}
+
+/// 通话接通后打开媒体通道
+pub async fn open(gctx: &Arc<GlobalContext>, call_id: u64) -> anyhow::Result<Arc<MediaChannel>> {
+    let engine = engine(gctx)
+        .await
+        .ok_or_else(|| anyhow::anyhow!("media engine not initialized"))?;
+    let (peer, remote) = engine
+        .remotes
+        .get(&call_id)
+        .map(|r| r.clone())
+        .ok_or_else(|| anyhow::anyhow!("no media endpoint for call {}", call_id))?;
+    let local_address = gctx
+        .get::<zz_account::address::FreeWebMovementAddress>()
+        .await
+        .ok_or_else(|| anyhow::anyhow!("Address not set"))?
+        .to_string();
+    let channel = Arc::new(MediaChannel {
+        call_id,
+        peer,
+        remote,
+        local_address,
+        socket: engine.socket.clone(),
+        gctx: gctx.clone(),
+        packetizer: std::sync::Mutex::new(Packetizer::default()),
+        jitter: std::sync::Mutex::new(JitterBuffer::default()),
+        arrived: Notify::new(),
+    });
+    engine.channels.insert(call_id, channel.clone());
+    tracing::info!("🎙️ Media channel for call {} open to {}", call_id, remote);
+    Ok(channel)
+}
+
+/// 取得已打开的媒体通道
+pub async fn channel(gctx: &Arc<GlobalContext>, call_id: u64) -> Option<Arc<MediaChannel>> {
+    engine(gctx)
+        .await?
+        .channels
+        .get(&call_id)
+        .map(|c| c.clone())
+}
+
+/// 通话结束后关闭媒体通道
+pub async fn close(gctx: &Arc<GlobalContext>, call_id: u64) {
+    if let Some(engine) = engine(gctx).await {
+        engine.remotes.remove(&call_id);
+        if engine.channels.remove(&call_id).is_some() {
+            tracing::info!("🎙️ Media channel for call {} closed", call_id);
+        }
+    }
+}
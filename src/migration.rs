@@ -0,0 +1,278 @@
+//! 数据目录的格式版本与迁移
+//!
+//! 数据目录下的 `storage-version.json` 记录整个目录的格式版本，与文件信封中的版本号
+//! （[`STORAGE_SCHEMA_VERSION`]）一致。启动时 [`IOStorage::migrate_data_dir`] 先读出该版本：
+//! 文件不存在时按已有存储文件中最旧的信封版本推断（全新目录直接视为当前版本），再按顺序执行
+//! [`migrations`] 中登记的步骤，每一步把目录从 `from` 升级到 `from + 1`，完成后立即写入新的版本号，
+//! 中途失败时下次启动从失败的那一步继续。
+//!
+//! 执行前先把将被改写的文件复制到 `backups/v<from>-<毫秒时间戳>/`（数据目录内的文件保留相对路径，
+//! 目录外的文件按文件名存放），只用复制不用重命名，跨分区、跨平台都可用。`--migrate-dry-run` 只列出
+//! 各步骤将改写的文件，不做任何修改。版本号高于当前支持版本的目录拒绝启动，不会被覆盖。
+//!
+//! 以后修改身份地址、服务器列表或聊天记录的格式时，提升 [`STORAGE_SCHEMA_VERSION`] 并在
+//! [`migrations`] 末尾追加一个实现 [`DataMigration`] 的步骤。
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::{
+    consts::DEFAULT_APP_DIR_STORAGE_VERSION_FILE,
+    io_storage::{self, IOStorage, STORAGE_SCHEMA_VERSION},
+};
+
+/// 迁移前备份所在的子目录
+pub const BACKUP_DIR: &str = "backups";
+
+/// `storage-version.json` 的内容
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataDirVersion {
+    pub version: u64,
+}
+
+/// 一个迁移步骤：把数据目录从 `from_version()` 升级到下一个版本
+#[async_trait]
+pub trait DataMigration: Send + Sync {
+    fn from_version(&self) -> u64;
+    fn description(&self) -> &'static str;
+    /// 执行迁移并返回改写的文件；`dry_run` 为 true 时只返回将改写的文件，不做修改
+    async fn run(&self, storage: &IOStorage, dry_run: bool) -> anyhow::Result<Vec<PathBuf>>;
+}
+
+/// v0 → v1：给没有版本信封的存储文件加上信封
+pub struct AddVersionEnvelope;
+
+#[async_trait]
+impl DataMigration for AddVersionEnvelope {
+    fn from_version(&self) -> u64 {
+        0
+    }
+
+    fn description(&self) -> &'static str {
+        "wrap storage files in a versioned envelope"
+    }
+
+    async fn run(&self, storage: &IOStorage, dry_run: bool) -> anyhow::Result<Vec<PathBuf>> {
+        let mut changed = Vec::new();
+        for path in storage.files() {
+            let Some(value) = read_json(&path).await? else {
+                continue;
+            };
+            if io_storage::schema_version(&value) != 0 {
+                continue;
+            }
+            if !dry_run {
+                let (data, _) = io_storage::migrate(value)?;
+                io_storage::write_atomic(&path, &io_storage::encode(&data)?).await?;
+            }
+            changed.push(path);
+        }
+        Ok(changed)
+    }
+}
+
+/// 按顺序登记的迁移步骤，第 i 个步骤从版本 i 开始
+pub fn migrations() -> Vec<Box<dyn DataMigration>> {
+    vec![Box::new(AddVersionEnvelope)]
+}
+
+/// 读取 JSON 文件；不存在或无法解析（交给读取时的错误处理）时返回 None
+async fn read_json(path: &Path) -> anyhow::Result<Option<serde_json::Value>> {
+    let bytes = match fs::read(path).await {
+        Ok(b) => b,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    match serde_json::from_slice(&bytes) {
+        Ok(v) => Ok(Some(v)),
+        Err(e) => {
+            tracing::warn!("Skipping unreadable {}: {}", path.display(), e);
+            Ok(None)
+        }
+    }
+}
+
+pub fn version_file(dir: &Path) -> PathBuf {
+    dir.join(DEFAULT_APP_DIR_STORAGE_VERSION_FILE)
+}
+
+/// 版本文件中记录的版本
+pub async fn read_version(dir: &Path) -> anyhow::Result<Option<u64>> {
+    match read_json(&version_file(dir)).await? {
+        Some(value) => Ok(Some(
+            serde_json::from_value::<DataDirVersion>(value)?.version,
+        )),
+        None => Ok(None),
+    }
+}
+
+async fn write_version(dir: &Path, version: u64) -> anyhow::Result<()> {
+    let bytes = serde_json::to_vec_pretty(&DataDirVersion { version })?;
+    io_storage::write_atomic(&version_file(dir), &bytes).await
+}
+
+/// 没有版本文件时按已有存储文件推断版本；全新目录为当前版本
+async fn detect_version(storage: &IOStorage) -> anyhow::Result<u64> {
+    if let Some(version) = read_version(&storage.dir).await? {
+        return Ok(version);
+    }
+    let mut detected: Option<u64> = None;
+    for path in storage.files() {
+        if let Some(value) = read_json(&path).await? {
+            let version = io_storage::schema_version(&value);
+            detected = Some(detected.map_or(version, |d| d.min(version)));
+        }
+    }
+    Ok(detected.unwrap_or(STORAGE_SCHEMA_VERSION))
+}
+
+/// 一个步骤的结果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MigrationStep {
+    pub from: u64,
+    pub description: &'static str,
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MigrationReport {
+    pub dir: PathBuf,
+    pub from: u64,
+    pub to: u64,
+    pub dry_run: bool,
+    pub steps: Vec<MigrationStep>,
+    /// 迁移前的备份目录
+    pub backup: Option<PathBuf>,
+}
+
+impl fmt::Display for MigrationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Data directory {}: format v{}, current v{}",
+            self.dir.display(),
+            self.from,
+            self.to
+        )?;
+        if self.steps.is_empty() {
+            return write!(f, "Nothing to migrate");
+        }
+        for step in &self.steps {
+            writeln!(
+                f,
+                " v{} → v{}: {} ({} file(s))",
+                step.from,
+                step.from + 1,
+                step.description,
+                step.files.len()
+            )?;
+            for file in &step.files {
+                writeln!(f, "   {}", file.display())?;
+            }
+        }
+        match (&self.backup, self.dry_run) {
+            (_, true) => write!(f, "Dry run: nothing was changed"),
+            (Some(backup), false) => write!(f, "Backup saved to {}", backup.display()),
+            (None, false) => write!(f, "No files needed a backup"),
+        }
+    }
+}
+
+/// 把文件复制到备份目录；数据目录内的文件保留相对路径
+async fn backup(dir: &Path, backup_dir: &Path, files: &[PathBuf]) -> anyhow::Result<()> {
+    for file in files {
+        let relative = match file.strip_prefix(dir) {
+            Ok(r) => r.to_path_buf(),
+            Err(_) => PathBuf::from(file.file_name().unwrap_or_default()),
+        };
+        let target = backup_dir.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::copy(file, &target).await?;
+    }
+    Ok(())
+}
+
+/// 把数据目录升级到当前版本，见模块文档
+pub async fn run(storage: &IOStorage, dry_run: bool) -> anyhow::Result<MigrationReport> {
+    let dir = storage.dir.clone();
+    let from = detect_version(storage).await?;
+    if from > STORAGE_SCHEMA_VERSION {
+        anyhow::bail!(
+            "data directory {} has format v{}, newer than supported v{}",
+            dir.display(),
+            from,
+            STORAGE_SCHEMA_VERSION
+        );
+    }
+    let pending: Vec<Box<dyn DataMigration>> = migrations()
+        .into_iter()
+        .filter(|m| m.from_version() >= from && m.from_version() < STORAGE_SCHEMA_VERSION)
+        .collect();
+
+    // 先演练一遍得到将改写的文件
+    let mut steps = Vec::with_capacity(pending.len());
+    for migration in &pending {
+        steps.push(MigrationStep {
+            from: migration.from_version(),
+            description: migration.description(),
+            files: migration.run(storage, true).await?,
+        });
+    }
+    let mut report = MigrationReport {
+        dir: dir.clone(),
+        from,
+        to: STORAGE_SCHEMA_VERSION,
+        dry_run,
+        steps,
+        backup: None,
+    };
+    if dry_run {
+        return Ok(report);
+    }
+
+    let mut files: Vec<PathBuf> = report.steps.iter().flat_map(|s| s.files.clone()).collect();
+    files.sort();
+    files.dedup();
+    if !files.is_empty() {
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let backup_dir = dir.join(BACKUP_DIR).join(format!("v{}-{}", from, now_ms));
+        let version = version_file(&dir);
+        if fs::try_exists(&version).await.unwrap_or(false) {
+            files.push(version);
+        }
+        backup(&dir, &backup_dir, &files).await?;
+        tracing::info!(
+            "💾 Backed up {} file(s) to {}",
+            files.len(),
+            backup_dir.display()
+        );
+        report.backup = Some(backup_dir);
+    }
+
+    for (migration, step) in pending.iter().zip(report.steps.iter_mut()) {
+        step.files = migration.run(storage, false).await?;
+        write_version(&dir, migration.from_version() + 1).await?;
+        tracing::info!(
+            "🔧 Migrated data directory to v{}: {} ({} file(s))",
+            migration.from_version() + 1,
+            migration.description(),
+            step.files.len()
+        );
+    }
+    if read_version(&dir).await? != Some(STORAGE_SCHEMA_VERSION) {
+        write_version(&dir, STORAGE_SCHEMA_VERSION).await?;
+    }
+    Ok(report)
+}
@@ -5,30 +5,127 @@ use aex::{
     },
     crypto::session_key_manager::PairedSessionKey,
     server::{HTTPServer, Server},
-    storage::Storage,
     tcp::router::Router as TcpRouter,
     unified::UnifiedServer,
 };
 use chrono::Utc;
 use futures::future::FutureExt;
-use std::{collections::HashSet, net::SocketAddr, sync::Arc};
-use tokio::sync::{Mutex, RwLock};
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::IsTerminal,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+};
+use tokio::{
+    sync::{Mutex, RwLock},
+    task::JoinSet,
+};
 use zz_account::address::FreeWebMovementAddress;
 
 use crate::{
+    bootstrap,
     cli::{Cli, Opt},
+    config::{self, Config, SharedConfig},
+    connections::{self, PeerConnection},
+    consts::DEFAULT_APP_DIR_HISTORY_FILE,
+    dialer, endpoint_verifier,
+    identities::{Identities, SharedIdentities},
     io_storage::{
-        IOStorage, STORAGE_ADDRESS, STORAGE_EXTERNAL_SERVER, STORAGE_INNER_SERVER, io_storage_init,
+        IOStorage, STORAGE_ACL, STORAGE_ALIASES, STORAGE_EXTERNAL_SERVER, STORAGE_GROUPS,
+        STORAGE_IDENTITIES, STORAGE_INNER_SERVER, STORAGE_OUTBOX, STORAGE_SUCCESSORS,
+        STORAGE_VERIFIED, STORAGE_WEB_USERS, STORAGE_WEBHOOKS, io_storage_init,
     },
+    ip_scope,
+    listen::{self, ListenAddrs},
+    listener::{ControlListener, HandlerSet, ServerListener},
+    peer_store::{PEER_DB_FILE, PeerScope, PeerStore, SharedPeerStore},
+    port_mapping::{self, MappingProtocol, PortMappings},
+    protocols::commands::group::{GroupStore, SharedGroups},
+    protocols::commands::http_tunnel::ExposedService,
     protocols::commands::node_registry::NodeRegistry,
+    protocols::commands::observed::ObservedAddresses,
+    protocols::commands::offline,
+    protocols::commands::stream,
     protocols::{
+        acl::{AccessList, SharedAccessList},
         command::{Action, Entity, P2PCommand},
         frame::P2PFrame,
+        limits::{MessageLimits, SharedMessageLimits},
+        privacy,
         registry::register,
     },
+    proxy,
+    readiness::{Readiness, StartupPhase},
     record::{self, NodeRecord},
+    safety_number::{SharedVerifiedContacts, VerifiedContacts},
+    scheduler::Scheduler,
+    secure_link,
+    web::peer_proxy::{DEFAULT_PEER_PREFIX, PeerProxy},
+    web::static_files::{DEFAULT_STATIC_PREFIX, StaticDir, StaticMount},
 };
 
+/// 监听 `addr` 的 P2P server，与主 server 共用 GlobalContext
+fn p2p_server(addr: SocketAddr, global: Arc<GlobalContext>) -> Server {
+    let tcp_router = Arc::new(register(TcpRouter::<P2PFrame, P2PCommand>::new()));
+    HTTPServer::new(addr, Some(global))
+        .tcp(register(TcpRouter::<P2PFrame, P2PCommand>::new()))
+        .tcp_handler(Arc::new(move |ctx| accept_inbound(tcp_router.clone(), ctx)))
+}
+
+/// 入站 P2P 连接：先注册到 manager，完成链路握手（见 [`crate::secure_link`]）后再交给
+/// TCP router 处理帧；握手失败时关闭连接
+fn accept_inbound(
+    router: Arc<TcpRouter<P2PFrame, P2PCommand>>,
+    ctx: aex::connection::context::Context,
+) -> tokio::task::JoinHandle<()> {
+    let peer_addr = ctx.addr;
+    let manager = ctx.global.manager.clone();
+    let ctx_arc = Arc::new(Mutex::new(ctx));
+    let ctx_for_add = ctx_arc.clone();
+
+    let child_token = manager.cancel_token.child_token();
+    let task_token = child_token.clone();
+
+    // Signal channel: router must NOT process frames until the
+    // entry is registered in the manager, otherwise online_handler
+    // will fail to find the entry for update_node (race condition).
+    let (entry_added_tx, entry_added_rx) = tokio::sync::oneshot::channel::<()>();
+
+    let handle = tokio::spawn(async move {
+        // Wait for manager.add() to complete before processing frames
+        let _ = entry_added_rx.await;
+        tokio::select! {
+            _ = task_token.cancelled() => {}
+            _ = async {
+                match secure_link::establish_inbound(&ctx_arc).await {
+                    Ok(()) => {
+                        let _ = router.handle(ctx_arc).await;
+                    }
+                    Err(e) => {
+                        tracing::debug!("Secure link from {} failed: {:?}", peer_addr, e);
+                        let gctx = ctx_arc.lock().await.global.clone();
+                        gctx.manager.remove(peer_addr, true);
+                    }
+                }
+            } => {}
+        }
+    });
+
+    let abort_handle = handle.abort_handle();
+    manager.add(
+        peer_addr,
+        abort_handle,
+        child_token,
+        true,
+        Some(ctx_for_add),
+    );
+    // Signal that the entry is now visible in the manager
+    let _ = entry_added_tx.send(());
+
+    handle
+}
+
 pub type WebHandler = Arc<
     dyn Fn(&mut aex::connection::context::Context) -> futures::future::BoxFuture<'_, bool>
         + Send
@@ -41,16 +138,28 @@ pub type WebHandler = Arc<
 #[derive(Clone)]
 pub struct Node {
     pub id: FreeWebMovementAddress,
-    pub inner: record::NodeRegistry,
-    pub external: record::NodeRegistry,
+    pub inner: record::SharedNodeRegistry,
+    pub external: record::SharedNodeRegistry,
     pub registry: NodeRegistry,
     pub peer_addrs: Arc<RwLock<Vec<SocketAddr>>>,
     pub io_storage: IOStorage,
     pub name: String,
     pub addr: SocketAddr,
+    /// 所有监听地址，`addr` 是其中的主地址
+    pub listen: ListenAddrs,
     pub context: Arc<GlobalContext>,
     pub server: Server,
     pub cli: Arc<Cli>,
+    /// 受监管的监听任务（P2P server、控制接口）
+    pub handlers: HandlerSet,
+    /// 启动阶段与就绪信号
+    pub readiness: Readiness,
+    /// 仅出站模式：不绑定任何监听端口，见 [`crate::client_mode`]
+    pub client_only: bool,
+    /// 启动时间（毫秒）
+    pub started_at: u128,
+    /// 定期维护任务，见 [`crate::scheduler`]
+    pub scheduler: Scheduler,
 }
 
 impl Node {
@@ -58,38 +167,55 @@ impl Node {
         name: String,
         io_storage: IOStorage,
         addr: SocketAddr,
+        listen: ListenAddrs,
         context: Arc<GlobalContext>,
         server: Server,
         cli: Arc<Cli>,
         registry: NodeRegistry,
         peer_addrs: Arc<RwLock<Vec<SocketAddr>>>,
     ) -> Self {
-        let id = match io_storage
-            .read::<FreeWebMovementAddress>(STORAGE_ADDRESS)
-            .await
-        {
+        // 身份已在 init 中加载（可能来自加密 keystore），不再从明文文件读取
+        let id = match context.get::<FreeWebMovementAddress>().await {
             Some(v) => v,
             None => {
-                tracing::error!("Failed to read address from storage, generating random");
+                tracing::error!("Address not set in GlobalContext, generating random");
                 FreeWebMovementAddress::random()
             }
         };
-        let inner_nodes = match io_storage
+        let legacy_inner = io_storage
             .read::<HashSet<NodeRecord>>(STORAGE_INNER_SERVER)
-            .await
-        {
-            Some(v) => v,
-            None => HashSet::new(),
-        };
-        let external_nodes = match io_storage
+            .await;
+        let legacy_external = io_storage
             .read::<HashSet<NodeRecord>>(STORAGE_EXTERNAL_SERVER)
-            .await
-        {
-            Some(v) => v,
-            None => HashSet::new(),
+            .await;
+        let (inner_nodes, external_nodes) = match context.get::<SharedPeerStore>().await {
+            Some(store) => {
+                let inner = store
+                    .load_or_import(PeerScope::Inner, legacy_inner)
+                    .await
+                    .unwrap_or_else(|e| {
+                        tracing::error!("Failed to load inner peers: {}", e);
+                        HashSet::new()
+                    });
+                let external = store
+                    .load_or_import(PeerScope::External, legacy_external)
+                    .await
+                    .unwrap_or_else(|e| {
+                        tracing::error!("Failed to load external peers: {}", e);
+                        HashSet::new()
+                    });
+                (inner, external)
+            }
+            None => (
+                legacy_inner.unwrap_or_default(),
+                legacy_external.unwrap_or_default(),
+            ),
         };
-        let inner = record::NodeRegistry::new(inner_nodes);
-        let external = record::NodeRegistry::new(external_nodes);
+        let inner = record::SharedNodeRegistry::new(inner_nodes);
+        let external = record::SharedNodeRegistry::new(external_nodes);
+        let readiness = crate::readiness::of(&context).await;
+        let client_only = crate::client_mode::is_enabled(&context).await;
+        let scheduler = crate::scheduler::of(&context).await;
         Self {
             name,
             id,
@@ -99,27 +225,45 @@ impl Node {
             peer_addrs,
             io_storage,
             addr,
+            listen,
             context,
             server,
             cli,
+            handlers: HandlerSet::new(),
+            readiness,
+            client_only,
+            started_at: aex::time::SystemTime::timestamp(),
+            scheduler,
         }
     }
 
+    /// 当前状态的快照，见 [`crate::status`]
+    pub async fn snapshot(&self) -> crate::status::NodeStatus {
+        crate::status::collect(&self.context).await
+    }
+
     pub async fn connect(&mut self) {
-        let manager = self.context.manager.clone();
         let global = self.context.clone();
-        let self_registry = self.registry.clone();
         let local_addr = self.addr;
 
-        let nodes: Vec<record::NodeRecord> = self.inner.nodes.iter().cloned().collect();
+        // 已失效（长期未见）的记录不在启动时重试，由后台维护任务重新验证
+        let nodes: Vec<record::NodeRecord> = self
+            .inner
+            .read()
+            .get_available_nodes()
+            .into_iter()
+            .cloned()
+            .collect();
 
+        let mut dials = JoinSet::new();
         for record in nodes {
             let endpoint = record.endpoint;
 
             // Tiebreaker: only initiate if our SocketAddr is less than the peer's.
             // This prevents both sides from simultaneously creating outbound connections,
             // which would leave each side with 0 inbound entries.
-            if local_addr >= endpoint {
+            // 仅出站模式下对端无法连进来，总是由本端发起
+            if !self.client_only && local_addr >= endpoint {
                 tracing::info!(
                     "⏭️ Tiebreaker: {} >= {}, letting peer initiate",
                     local_addr,
@@ -128,139 +272,169 @@ impl Node {
                 continue;
             }
 
-            let g = global.clone();
-            let registry = self_registry.clone();
-
-            let _ = manager
-                .connect::<P2PFrame, P2PCommand, _, _>(
-                    endpoint,
-                    g,
-                    move |ctx| {
-                        let peer = endpoint;
-                        let self_registry = registry.clone();
-                        Box::pin(async move {
-                            tracing::info!("✅ Connected to peer: {}", peer);
-
-                            let psk =
-                                match ctx.lock().await.global.clone().paired_session_keys.clone() {
-                                    Some(psk) => psk,
-                                    None => {
-                                        tracing::error!(
-                                            "PairedSessionKeys not set in GlobalContext"
-                                        );
-                                        return;
-                                    }
-                                };
-
-                            let (id, key) = {
-                                let guard = psk.lock().await;
-                                guard.create(false).await
-                            };
-
-                            // Get local_node.id (FreeWebMovementAddress bytes)
-                            let self_node_id = {
-                                let guard = ctx.lock().await;
-                                guard.global.local_node.read().await.id.clone()
-                            };
-
-                            let self_port = {
-                                let guard = ctx.lock().await;
-                                guard.global.addr.port()
-                            };
-                            let aex_node = AexNode::from_system(self_port, self_node_id.clone(), 1);
-
-                            // Generate seeds from NodeRegistry
-                            let seeds_to_send = {
-                                let all_seeds: Vec<crate::protocols::commands::ack::SeedRecord> =
-                                    self_registry
-                                        .get_all_seeds()
-                                        .into_iter()
-                                        .map(|(s, na)| {
-                                            crate::protocols::commands::ack::SeedRecord::new(
-                                                s.to_string(),
-                                                na,
-                                            )
-                                        })
-                                        .collect();
-                                crate::protocols::commands::ack::SeedsCommand::new(all_seeds)
-                            };
-
-                            let (intranet_ips, wan_ips) = {
-                                let mut inner = Vec::new();
-                                let mut outer = Vec::new();
-                                for (scope, ip) in &aex_node.ips {
-                                    match scope {
-                                        aex::connection::scope::NetworkScope::Intranet => {
-                                            inner.push(ip.to_string())
-                                        }
-                                        aex::connection::scope::NetworkScope::Extranet => {
-                                            outer.push(ip.to_string())
-                                        }
-                                    }
-                                }
-                                (inner, outer)
-                            };
-                            let cmd = crate::protocols::commands::online::OnlineCommand {
-                                session_id: id,
-                                node: aex_node,
-                                ephemeral_public_key: key.to_bytes(),
-                                intranet_ips,
-                                wan_ips,
-                                seeds: Some(seeds_to_send),
-                            };
-                            if let Err(e) =
-                                P2PFrame::send::<crate::protocols::commands::online::OnlineCommand>(
-                                    ctx.clone(),
-                                    &Some(cmd),
-                                    Entity::Node,
-                                    Action::OnLine,
-                                    false,
-                                )
-                                .await
-                            {
-                                tracing::error!("Failed to send OnlineCommand: {:?}", e);
+            // 各对端并发拨号，一个不可达的对端不会拖住其余对端
+            let global = global.clone();
+            let inner = self.inner.clone();
+            dials.spawn(async move {
+                // 多地址竞速拨号，选出最先可达的地址
+                let via = match proxy::for_peer(&global, endpoint).await {
+                    Ok(via) => via,
+                    Err(e) => {
+                        tracing::warn!("⚠️ Invalid proxy rule for {}: {:?}", endpoint, e);
+                        return;
+                    }
+                };
+                let dialed = match dialer::dial(&global, &record, via.as_ref()).await {
+                    Ok(dialed) => {
+                        inner.write().record_dial(
+                            endpoint,
+                            dialed.attempt.endpoint,
+                            dialed.attempt.protocol.clone(),
+                        );
+                        dialed
+                    }
+                    Err(e) => {
+                        tracing::warn!("⚠️ Dial {} failed: {:?}", endpoint, e);
+                        inner.write().upsert(endpoint, false);
+                        return;
+                    }
+                };
+
+                let target = dialed.attempt.endpoint;
+                let g = global.clone();
+
+                let on_connected = move |ctx: Arc<Mutex<aex::connection::context::Context>>| {
+                    let peer = target;
+                    Box::pin(async move {
+                        tracing::info!("✅ Connected to peer: {}", peer);
+
+                        let psk = match ctx.lock().await.global.clone().paired_session_keys.clone()
+                        {
+                            Some(psk) => psk,
+                            None => {
+                                tracing::error!("PairedSessionKeys not set in GlobalContext");
+                                return;
                             }
+                        };
+
+                        let (id, key) = {
+                            let guard = psk.lock().await;
+                            guard.create(false).await
+                        };
+
+                        // Get local_node.id (FreeWebMovementAddress bytes)
+                        let self_node_id = {
+                            let guard = ctx.lock().await;
+                            guard.global.local_node.read().await.id.clone()
+                        };
+
+                        let self_port = {
+                            let guard = ctx.lock().await;
+                            guard.global.addr.port()
+                        };
+                        let aex_node = AexNode::from_system(self_port, self_node_id.clone(), 1);
+
+                        // Generate seeds from NodeRegistry
+                        let (gctx, peer_ip) = {
+                            let guard = ctx.lock().await;
+                            (guard.global.clone(), guard.addr.ip())
+                        };
+                        let seeds_to_send = privacy::gossip_seeds(&gctx, Some(&peer_ip)).await;
+                        let (intranet_ips, wan_ips) =
+                            privacy::announced_ips(&gctx, &aex_node.ips, &peer_ip).await;
+                        let cmd = crate::protocols::commands::online::OnlineCommand {
+                            session_id: id,
+                            node: aex_node,
+                            ephemeral_public_key: key.to_bytes(),
+                            intranet_ips,
+                            wan_ips,
+                            seeds: Some(seeds_to_send),
+                            capabilities: crate::protocols::compression::LOCAL_CAPABILITIES,
+                            protocol_version: crate::protocols::version::CURRENT_PROTOCOL_VERSION,
+                            max_frame_size: crate::protocols::capabilities::LOCAL_MAX_FRAME_SIZE,
+                            agent: crate::protocols::agent::local(&gctx).await,
+                        };
+                        if let Err(e) =
+                            P2PFrame::send::<crate::protocols::commands::online::OnlineCommand>(
+                                ctx.clone(),
+                                &Some(cmd),
+                                Entity::Node,
+                                Action::OnLine,
+                                false,
+                            )
+                            .await
+                        {
+                            tracing::error!("Failed to send OnlineCommand: {:?}", e);
+                        }
 
-                            // Start reader loop to process responses (OnlineAck, seeds, etc.)
-                            let g = {
-                                let guard = ctx.lock().await;
-                                guard.global.clone()
-                            };
-                            if let Some(router) = aex::connection::context::get_tcp_router::<
-                                P2PFrame,
-                                P2PCommand,
-                            >(&g.routers)
-                            {
-                                let _ = router.handle(ctx).await;
-                            }
-                        })
-                    },
-                    Some(10),
-                )
-                .await;
+                        // Start reader loop to process responses (OnlineAck, seeds, etc.)
+                        let g = {
+                            let guard = ctx.lock().await;
+                            guard.global.clone()
+                        };
+                        if let Some(router) = aex::connection::context::get_tcp_router::<
+                            P2PFrame,
+                            P2PCommand,
+                        >(&g.routers)
+                        {
+                            let _ = router.handle(ctx).await;
+                        }
+                    })
+                };
+                // 接管竞速胜出的连接（直连或已建立的代理隧道），不再重新拨号；
+                // 链路握手在后台进行，connect 只等待拨号结果
+                tokio::spawn(async move {
+                    if let Err(e) =
+                        proxy::adopt_peer(g, target, dialed.stream, via, on_connected).await
+                    {
+                        tracing::warn!("⚠️ Handshake with {} failed: {:?}", target, e);
+                    }
+                });
+            });
         }
+        while dials.join_next().await.is_some() {}
+        let _ = self.save_registries().await;
     }
 
     pub async fn stop(&mut self) {
         tracing::info!("🛑 Shutting down node {} ({})...", self.name, self.addr);
         // 1. Shutdown all connections via GlobalContext
         self.context.shutdown_all().await;
+        self.scheduler.shutdown().await;
         // 2. Save registries to persistent storage
         let _ = self.save_registries().await;
+        self.record_stopped().await;
         tracing::info!("✅ Node {} shutdown complete", self.name);
     }
 
-    pub async fn init(opt: Opt) -> Self {
-        let storage = Arc::new(Storage::new(opt.data_dir.as_deref()));
-        let io_storage = io_storage_init(&opt, storage.clone());
+    /// `config` 为启动时读取的配置文件（未指定 `--config` 时为默认值），由调用方只读取一次
+    pub async fn init(opt: Opt, config: Config) -> Self {
+        let io_storage = io_storage_init(&opt);
+        // 读取任何存储文件之前先把数据目录升级到当前格式
+        match io_storage.migrate_data_dir(false).await {
+            Ok(report) if !report.steps.is_empty() => tracing::info!("{}", report),
+            Ok(_) => {}
+            Err(e) => {
+                tracing::error!("Failed to migrate data directory: {:?}", e);
+                std::process::exit(1);
+            }
+        }
 
-        let addr = match format!("{}:{}", opt.ip.clone(), opt.port).parse::<SocketAddr>() {
-            Ok(a) => a,
+        let primary = match opt.ip.parse::<IpAddr>() {
+            Ok(ip) => SocketAddr::new(ip, opt.port),
             Err(e) => {
                 tracing::error!("Failed to parse address {}:{}: {}", opt.ip, opt.port, e);
                 std::process::exit(1);
             }
         };
+        let listen = match ListenAddrs::new(primary, &opt.listen, listen::dual_stack()) {
+            Ok(l) => l,
+            Err(e) => {
+                tracing::error!("Invalid --listen: {:?}", e);
+                std::process::exit(1);
+            }
+        };
+        let addr = listen.primary();
         let psk = Arc::new(Mutex::new(PairedSessionKey::new(16)));
 
         let heartbeat_config = HeartbeatConfig::new()
@@ -284,16 +458,14 @@ impl Node {
         // a separate watcher monitors the node registry and publishes offline events
         // for peers that disappear from the active connection list.
 
-        let address: FreeWebMovementAddress = match io_storage
-            .read::<FreeWebMovementAddress>(&STORAGE_ADDRESS)
-            .await
-        {
-            Some(v) => v,
-            None => {
-                tracing::error!("Failed to read address from storage in init, generating random");
-                FreeWebMovementAddress::random()
-            }
-        };
+        let address: FreeWebMovementAddress =
+            match crate::keystore::load_identity(&opt, &io_storage).await {
+                Ok(v) => v,
+                Err(e) => {
+                    tracing::error!("Failed to load node identity: {:?}", e);
+                    std::process::exit(1);
+                }
+            };
 
         // Set local_node.id = FreeWebMovementAddress bytes
         {
@@ -302,6 +474,18 @@ impl Node {
         }
 
         global.set(address.clone()).await;
+        global.set(Readiness::new()).await;
+        // 定期维护任务的调度器，随连接管理器一起取消
+        let scheduler = Scheduler::new(global.manager.cancel_token.child_token());
+        global.set(scheduler.clone()).await;
+        if opt.client_only {
+            tracing::info!("🔌 Client-only mode: no listening sockets, outbound connections only");
+            global.set(crate::client_mode::ClientOnly).await;
+        }
+        if opt.relay {
+            tracing::info!("🔀 Relay mode: forwarding for clients with per-client quotas");
+            global.set(crate::relay::RelayMode).await;
+        }
 
         let address_1 = match global.get::<FreeWebMovementAddress>().await {
             Some(v) => v,
@@ -311,46 +495,290 @@ impl Node {
             }
         };
         assert_eq!(address.to_string(), address_1.to_string());
-        global.set(storage.clone()).await;
+        // 主身份之外的附加身份
+        if let Some(session_keys) = global.paired_session_keys.clone() {
+            let identities = Identities::new(address.clone(), session_keys);
+            if let Some(stored) = io_storage
+                .read::<BTreeMap<String, FreeWebMovementAddress>>(STORAGE_IDENTITIES)
+                .await
+            {
+                identities.load(stored);
+            }
+            global.set(SharedIdentities::new(identities)).await;
+        }
+        // 群聊的成员列表与密钥
+        let groups = io_storage
+            .read::<GroupStore>(STORAGE_GROUPS)
+            .await
+            .unwrap_or_default();
+        global.set(SharedGroups::new(Mutex::new(groups))).await;
+        // 已核对安全码的对端
+        let verified = io_storage
+            .read::<VerifiedContacts>(STORAGE_VERIFIED)
+            .await
+            .unwrap_or_default();
+        global
+            .set(SharedVerifiedContacts::new(std::sync::RwLock::new(
+                verified,
+            )))
+            .await;
         global.set(io_storage.clone()).await;
+        io_storage.schedule_flush(&scheduler);
+        // 服务器列表数据库；打开失败时退回 JSON 文件
+        match PeerStore::open(&io_storage.path(PEER_DB_FILE)).await {
+            Ok(store) => {
+                let store: SharedPeerStore = Arc::new(store);
+                global.set(store).await;
+            }
+            Err(e) => tracing::error!("Failed to open peer database: {}", e),
+        }
+        // 当前配置与消息上限，并启动热更新监听
+        let limits: SharedMessageLimits = Arc::new(MessageLimits::new(&config.limits));
+        global.set(limits).await;
+        let config: SharedConfig = Arc::new(RwLock::new(config));
+        global.set(config).await;
+        if let Some(ref path) = opt.config {
+            global.set(config::ConfigFile(PathBuf::from(path))).await;
+            config::watch(PathBuf::from(path), global.clone());
+        }
+        // 远程管理通道：审计日志写入存储目录
+        global
+            .set(crate::admin::SharedAdmin::new(
+                crate::admin::AdminChannel::new(io_storage.path(crate::admin::ADMIN_AUDIT_FILE)),
+            ))
+            .await;
+        // 命令行指定的出站代理
+        if let Some(ref proxy) = opt.proxy {
+            match proxy.parse::<crate::proxy::ProxyEndpoint>() {
+                Ok(endpoint) => {
+                    tracing::info!("🧦 Outbound connections use proxy {}", endpoint);
+                    global.set(crate::proxy::DefaultProxy(endpoint)).await;
+                }
+                Err(e) => {
+                    tracing::error!("Invalid --proxy {}: {:?}", proxy, e);
+                    std::process::exit(1);
+                }
+            }
+        }
         // 初始化消息去重集合
-        let seen: crate::protocols::commands::message::SeenMessages =
-            Arc::new(std::sync::Mutex::new(std::collections::HashSet::new()));
-        global.set(seen).await;
+        global
+            .set(crate::protocols::commands::message::SeenMessages::default())
+            .await;
         // 初始化待确认回执表
         global
             .set(crate::protocols::commands::message::PendingAcks::default())
             .await;
+        // 初始化 Ping 等待表
+        global
+            .set(crate::protocols::commands::ping::PendingPings::default())
+            .await;
+        // 初始化二进制消息重组表
+        global
+            .set(crate::protocols::commands::binary::BinaryAssemblies::default())
+            .await;
+        // 初始化超大帧的分片重组表
+        global
+            .set(crate::protocols::commands::fragment::Reassemblies::default())
+            .await;
+        // 初始化主题订阅表
+        global
+            .set(crate::protocols::commands::topic::TopicSubscriptions::default())
+            .await;
+        // 链路握手失败、以明文连接的旧版本节点
+        global
+            .set(crate::secure_link::PlaintextPeers::default())
+            .await;
+        // 初始化名称记录缓存与待应答的名称查询
+        global
+            .set(crate::protocols::commands::naming::NameRecords::default())
+            .await;
+        global
+            .set(crate::protocols::commands::naming::PendingNameQueries::default())
+            .await;
+        // 等待响应的 HTTP 隧道请求
+        global
+            .set(crate::protocols::commands::http_tunnel::PendingHttpRequests::default())
+            .await;
+        // 流式传输：收发中的流与按类型注册的 handler，收到的文件写入 downloads 目录
+        global.set(stream::IncomingStreams::default()).await;
+        global.set(stream::OutgoingStreams::default()).await;
+        stream::register(
+            &global,
+            stream::STREAM_KIND_FILE,
+            stream::save_files_to(io_storage.path("downloads")),
+        )
+        .await;
+        // 其它节点的在线状态记录
+        global
+            .set(crate::protocols::commands::presence::PresenceTable::default())
+            .await;
+        // 初始化多跳路由表
+        global
+            .set(crate::protocols::routing::RoutingTable::default())
+            .await;
+        // 初始化种子列表增量同步状态
+        global
+            .set(crate::protocols::commands::seed_delta::SharedSeedDeltas::default())
+            .await;
+        // 初始化协议错误统计
+        global
+            .set(crate::protocols::error::ProtocolErrors::default())
+            .await;
+        // 初始化带宽限速与用量统计
+        global
+            .set(crate::protocols::bandwidth::Bandwidth::default())
+            .await;
+        // 初始化每个连接的协议统计
+        global
+            .set(crate::protocols::peer_stats::PeerStatsTable::default())
+            .await;
+        // 初始化广播可达性统计
+        global
+            .set(crate::protocols::broadcast::PeerReachability::default())
+            .await;
+        // 初始化会话表并启动密钥轮换检查
+        global
+            .set(crate::protocols::commands::rekey::SessionTable::default())
+            .await;
+        global
+            .set(crate::protocols::commands::rekey::SharedKeyRotation::default())
+            .await;
+        crate::protocols::commands::rekey::schedule_rotation(&scheduler, global.clone());
+        // 对端回送的观测地址，用于公告反射（公网）地址
+        global.set(ObservedAddresses::default()).await;
+        // 挑战-应答验证过的地址与公钥绑定
+        global
+            .set(crate::protocols::commands::identity::IdentityBindings::default())
+            .await;
+        // 身份继任记录：认可对端的新地址，宽限期结束后拒绝旧地址
+        let successors: crate::protocols::commands::successor::SuccessorRecords = Arc::new(
+            io_storage
+                .read::<BTreeMap<String, crate::protocols::commands::successor::SuccessorRecord>>(
+                    STORAGE_SUCCESSORS,
+                )
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .collect(),
+        );
+        global.set(successors).await;
+        crate::protocols::commands::successor::schedule(&scheduler, global.clone());
+        // 消息序号与接收端重排缓冲区
+        global
+            .set(crate::protocols::ordering::new_outbound_sequences())
+            .await;
+        global
+            .set(crate::protocols::commands::message::InboundReorder::default())
+            .await;
+        crate::protocols::commands::message::schedule_reorder_flush(&scheduler, global.clone());
+        // 通话信令：当前通话与振铃超时
+        global
+            .set(crate::protocols::commands::telephone::Calls::default())
+            .await;
+        crate::protocols::commands::telephone::schedule_ring_timeout(&scheduler, global.clone());
+        // 通话媒体 UDP 端口（仅出站模式下不绑定，无法通话）
+        if !opt.client_only {
+            match crate::media::MediaEngine::bind(SocketAddr::new(addr.ip(), opt.media_port)).await
+            {
+                Ok(engine) => {
+                    tracing::info!("🎙️ Media port {}", engine.local_port());
+                    engine.clone().spawn(global.clone());
+                    global.set(engine).await;
+                }
+                Err(e) => tracing::error!("Failed to bind media port {}: {:?}", opt.media_port, e),
+            }
+        }
+        // 可选：线路抓包
+        if let Some(path) = opt.capture.as_deref() {
+            match crate::capture::Capture::open(path) {
+                Ok(capture) => {
+                    tracing::warn!("📼 Capturing all frames to {}", path);
+                    let capture: crate::capture::SharedCapture = Arc::new(capture);
+                    global.set(capture).await;
+                }
+                Err(e) => tracing::error!("Failed to open capture file {}: {:?}", path, e),
+            }
+        }
+        // 事件总线（终端仪表盘等订阅方使用），收到的文本流发布到总线上
+        let bus = crate::events::new_bus();
+        global.set(bus.clone()).await;
+        stream::register(
+            &global,
+            stream::STREAM_KIND_TEXT,
+            stream::publish_text_streams(bus),
+        )
+        .await;
+        // 生命周期事件日志
+        let journal_path = crate::journal::journal_path(&opt);
+        match crate::journal::Journal::open(
+            &journal_path,
+            crate::journal::DEFAULT_JOURNAL_MAX_BYTES,
+            crate::journal::DEFAULT_JOURNAL_MAX_FILES,
+        ) {
+            Ok(journal) => {
+                let journal: crate::journal::SharedJournal = Arc::new(journal);
+                global.set(journal).await;
+            }
+            Err(e) => tracing::error!(
+                "Failed to open event journal {}: {:?}",
+                journal_path.display(),
+                e
+            ),
+        }
+        // 可选：出站消息预写日志，重放未确认的消息并启动补发
+        if opt.wal {
+            let path = crate::wal::wal_path(&opt);
+            match crate::wal::Wal::open(&path, crate::wal::DEFAULT_WAL_MAX_BYTES) {
+                Ok(wal) => {
+                    let pending = wal.pending().len();
+                    tracing::info!("📝 WAL enabled at {} ({} pending)", path.display(), pending);
+                    let wal: crate::wal::SharedWal = Arc::new(wal);
+                    global.set(wal).await;
+                    crate::wal::spawn_replay(global.clone());
+                }
+                Err(e) => tracing::error!("Failed to open WAL {}: {:?}", path.display(), e),
+            }
+        }
+        // 可选：静态 Web UI 目录，由 start_with_web 挂载
+        if let Some(ref root) = opt.web_root {
+            global
+                .set(StaticMount::new(DEFAULT_STATIC_PREFIX, root))
+                .await;
+        }
+        // 可选：经 HTTP 隧道暴露的本地服务
+        if let Some(ref url) = opt.expose {
+            match url.parse::<ExposedService>() {
+                Ok(service) => {
+                    tracing::info!("🚇 Exposing {} through the HTTP tunnel", service.base);
+                    global.set(service).await;
+                }
+                Err(e) => {
+                    tracing::error!("Invalid --expose {}: {:?}", url, e);
+                    std::process::exit(1);
+                }
+            }
+        }
         let cli = Cli::new();
 
-        let server = HTTPServer::new(addr, Some(global.clone()));
-
-        let router = TcpRouter::<P2PFrame, P2PCommand>::new();
-
-        let router = register(router);
-        let server = server.tcp(router);
+        let server = p2p_server(addr, global.clone());
+        global.set(listen.clone()).await;
 
-        // Create NodeRegistry and register self seeds
+        // Create NodeRegistry and register self seeds（每个监听地址对应的本机端点）
         let node_registry = NodeRegistry::new();
-        let all_ips = aex::connection::node::Node::system_ips();
+        let all_ips: Vec<IpAddr> = aex::connection::node::Node::system_ips()
+            .into_iter()
+            .map(|(_, ip)| ip)
+            .collect();
         let self_address = address.to_string();
 
-        let is_loopback = addr.ip().is_loopback();
-        if !is_loopback {
-            let scope = NetworkScope::from_ip(&addr.ip());
-            node_registry.register(self_address.clone(), addr, scope);
-            tracing::info!("🌱 Registered self seed: {} (node: {})", addr, self_address);
-        }
-
-        for (_, ip) in &all_ips {
-            if ip == &addr.ip() {
-                continue;
-            }
-            if ip.is_loopback() {
-                continue;
-            }
-            let seed_addr = SocketAddr::new(*ip, addr.port());
-            let scope = NetworkScope::from_ip(ip);
+        // 仅出站模式下本机端点不可达，不登记
+        let self_seeds = if opt.client_only {
+            vec![]
+        } else {
+            listen.advertised(&all_ips)
+        };
+        for seed_addr in self_seeds {
+            let scope = ip_scope::classify(&seed_addr.ip());
             node_registry.register(self_address.clone(), seed_addr, scope);
             tracing::info!(
                 "🌱 Registered self seed: {} (node: {})",
@@ -369,10 +797,70 @@ impl Node {
             vec![]
         };
 
+        let bootstrap_sources = bootstrap::sources(&opt);
+        let min_peers = opt.min_peers;
+        global.set(bootstrap::TargetPeers(min_peers)).await;
+        global
+            .set(bootstrap::BootstrapSources(bootstrap_sources.clone()))
+            .await;
+
+        // 恢复地址簿
+        if let Some(aliases) = io_storage
+            .read::<BTreeMap<String, String>>(STORAGE_ALIASES)
+            .await
+        {
+            node_registry.load_aliases(aliases);
+        }
+
+        // 恢复访问控制列表
+        let acl = io_storage
+            .read::<AccessList>(STORAGE_ACL)
+            .await
+            .unwrap_or_default();
+        global
+            .set(SharedAccessList::new(std::sync::RwLock::new(acl)))
+            .await;
+
+        // 恢复 webhook 并启动事件推送
+        let webhooks = io_storage
+            .read::<crate::webhook::WebhookList>(STORAGE_WEBHOOKS)
+            .await
+            .unwrap_or_default();
+        global
+            .set(crate::webhook::SharedWebhooks::new(std::sync::RwLock::new(
+                webhooks,
+            )))
+            .await;
+        crate::webhook::spawn(global.clone()).await;
+
+        // 恢复发件箱，接收方可达后自动发出
+        let outbox = io_storage
+            .read::<crate::outbox::OutboxList>(STORAGE_OUTBOX)
+            .await
+            .unwrap_or_default();
+        global
+            .set(crate::outbox::SharedOutbox::new(std::sync::RwLock::new(
+                outbox,
+            )))
+            .await;
+        crate::outbox::spawn(global.clone());
+
+        // 恢复 Web 用户与 API token
+        let web_users = io_storage
+            .read::<crate::web::auth::WebUserList>(STORAGE_WEB_USERS)
+            .await
+            .unwrap_or_default();
+        global
+            .set(crate::web::auth::SharedWebUsers::new(
+                std::sync::RwLock::new(web_users),
+            ))
+            .await;
+
         let mut node = Node::new(
             opt.name,
             io_storage,
             addr,
+            listen,
             global.clone(),
             server,
             Arc::new(cli),
@@ -381,20 +869,78 @@ impl Node {
         )
         .await;
 
+        // 引导节点：解析后合并进 external
+        let bootstrap_addrs = bootstrap::resolve(&bootstrap_sources).await;
+        for saddr in &bootstrap_addrs {
+            node.external.write().upsert(*saddr, true);
+        }
+
         // Store Arc<Node> in GlobalContext
         let node_arc = Arc::new(node.clone());
         global.set(node_arc).await;
 
+        // 后台拨号引导节点，直到达到目标连接数
+        if !bootstrap_sources.is_empty() {
+            tracing::info!(
+                "🌐 Bootstrapping from {} source(s) ({} address(es)), min peers {}",
+                bootstrap_sources.len(),
+                bootstrap_addrs.len(),
+                min_peers
+            );
+            let _ = node.save_registries().await;
+            bootstrap::spawn(global.clone(), bootstrap_sources, min_peers);
+        } else {
+            node.readiness.mark_bootstrapped();
+        }
+
         // Save CLI seeds to persistent registries
         if opt.seeds.is_some() {
             for saddr in &seed_addrs {
-                node.inner.upsert(*saddr, true);
-                node.external.upsert(*saddr, true);
+                node.inner.write().upsert(*saddr, true);
+                node.external.write().upsert(*saddr, true);
                 tracing::info!("Adding seed: {}", saddr);
             }
             let _ = node.save_registries().await;
         }
 
+        // 可选：向网关申请端口映射
+        if opt.port_mapping && opt.client_only {
+            tracing::warn!("--port-mapping is ignored in client-only mode");
+        } else if opt.port_mapping {
+            let mut ports: Vec<(MappingProtocol, u16)> = listen
+                .ports()
+                .into_iter()
+                .map(|port| (MappingProtocol::Tcp, port))
+                .collect();
+            if let Some(engine) = global.get::<crate::media::SharedMediaEngine>().await {
+                ports.push((MappingProtocol::Udp, engine.local_port()));
+            }
+            global.set(PortMappings::default()).await;
+            port_mapping::spawn(global.clone(), ports);
+        }
+
+        // 定期验证 seed 地址（包括本节点的外部地址）的可达性
+        endpoint_verifier::schedule(&scheduler, global.clone());
+        // 按保留策略定期清理聊天记录与离线队列
+        global
+            .set(crate::retention::SharedRetentionMetrics::default())
+            .await;
+        crate::retention::schedule(&scheduler, global.clone());
+        // 定期重新验证、清理服务器列表并压缩数据库
+        crate::peer_maintenance::schedule(&scheduler, global.clone());
+        // 定期重新签发并传播本节点的在线状态
+        crate::protocols::commands::presence::schedule_refresh(&scheduler, global.clone());
+        // 定期 ping 直连对端，估算各自的时钟偏差
+        crate::clock::schedule(&scheduler, global.clone());
+        // 只对空闲的直连对端发送 Ping，维持服务器列表中的可达性评分
+        crate::reachability::schedule(&scheduler, global.clone());
+        // 全部连接断开一段时间后重新拨号服务器列表与引导节点
+        crate::partition::schedule(&scheduler, global.clone());
+        // 仅出站模式：连接数不足时补充拨号服务器列表
+        if opt.client_only {
+            crate::client_mode::spawn(global.clone());
+        }
+
         if opt.test {
             tracing::info!("Test mode: node {} ready (displayed via manager)", opt.port);
         }
@@ -406,35 +952,202 @@ impl Node {
     where
         R: tokio::io::AsyncBufRead + Unpin,
     {
-        // 1. 克隆需要的资源
-        let server = self.server.clone();
         let cli = self.cli.clone();
         let ctx = self.context.clone();
+        self.run_repl(async move { cli.run(reader, ctx).await })
+            .await;
+    }
 
-        // 2. 启动 Server (后台运行)
-        // 使用 tokio::spawn 确保 server 不会阻塞主线程对 CLI 的处理
-        let server_handle = tokio::spawn(async move {
-            if let Err(e) = server.start_with_protocols::<P2PFrame, P2PCommand>().await {
-                tracing::error!("Server error: {:?}", e);
+    /// 交互式启动：标准输入是终端时使用带历史记录与 Tab 补全的行编辑器，
+    /// 否则（管道输入）逐行读取标准输入
+    pub async fn start_interactive(&mut self) {
+        if !std::io::stdin().is_terminal() {
+            self.start(tokio::io::BufReader::new(tokio::io::stdin()))
+                .await;
+            return;
+        }
+        let cli = self.cli.clone();
+        let ctx = self.context.clone();
+        let history = self.io_storage.path(DEFAULT_APP_DIR_HISTORY_FILE);
+        self.run_repl(crate::repl::run(cli, ctx, history)).await;
+    }
+
+    async fn run_repl<F>(&mut self, repl: F)
+    where
+        F: Future<Output = anyhow::Result<()>>,
+    {
+        let ctx = self.context.clone();
+
+        // 1. 启动 Server (后台运行，崩溃后自动重启)
+        // server 由 HandlerSet 监管，不会阻塞主线程对 CLI 的处理
+        self.start_servers(true);
+        self.record_started().await;
+
+        // 2. 在 REPL 中打印通话事件
+        let mut calls = self.subscribe_calls().await;
+        tokio::spawn(async move {
+            while let Some(event) = calls.recv().await {
+                crate::clis::call::print_event(&event);
             }
         });
 
         // 3. 启动 CLI (前台运行)
         // CLI 的退出（输入 exit）将决定 start 函数的结束
         tracing::info!("CLI started. Type 'help' for commands.");
-        let _ = cli.run(reader, ctx).await;
+        tokio::select! {
+            result = repl => {
+                if let Err(e) = result {
+                    tracing::error!("CLI exited with error: {:?}", e);
+                }
+            }
+            _ = crate::admin::shutdown_requested(&ctx) => {
+                tracing::info!("Shutdown requested by admin");
+            }
+        }
+
+        // 4. CLI 退出后通知对端下线、停止 server，并写出尚未落盘的服务器列表
+        self.shutdown().await;
+    }
+
+    /// 终端仪表盘模式：用 TUI 代替 REPL，按 `q` 退出
+    #[cfg(feature = "tui")]
+    pub async fn run_tui(&mut self, logs: crate::tui::LogBuffer) {
+        self.start_servers(true);
+        self.record_started().await;
+
+        if let Err(e) = crate::tui::run(self.context.clone(), logs).await {
+            tracing::error!("TUI exited with error: {:?}", e);
+        }
+        self.shutdown().await;
+    }
+
+    /// 脚本模式：逐行执行 `reader` 中的命令，每条命令输出一行 JSON，返回进程退出码，
+    /// 见 [`crate::script`]
+    pub async fn run_script<R>(&mut self, reader: R, keep_going: bool) -> i32
+    where
+        R: tokio::io::AsyncBufRead + Unpin,
+    {
+        self.start_servers(true);
+        self.record_started().await;
+
+        let code = crate::script::run(
+            &self.cli,
+            reader,
+            self.context.clone(),
+            keep_going,
+            &mut std::io::stdout(),
+        )
+        .await;
+        self.shutdown().await;
+        code
+    }
+
+    /// 守护进程模式：不启动 REPL，改为在 `control` 上提供本地控制接口，
+    /// 收到 Ctrl-C / SIGTERM 后退出
+    pub async fn run_daemon(&mut self, control: SocketAddr) {
+        self.start_servers(true);
+        self.handlers.start(ControlListener {
+            addr: control,
+            context: self.context.clone(),
+        });
+
+        tracing::info!("Daemon started, control API on {}", control);
+        self.record_started().await;
+        tokio::select! {
+            _ = wait_for_shutdown() => {}
+            _ = crate::admin::shutdown_requested(&self.context) => {
+                tracing::info!("Shutdown requested by admin");
+            }
+        }
+        tracing::info!("Shutting down daemon");
+        self.shutdown().await;
+    }
+
+    /// 为每个监听地址启动受监管的 P2P server；`with_primary` 为 false 时主地址由
+    /// 统一的 Web + P2P server 负责。仅出站模式下什么都不启动
+    pub(crate) fn start_servers(&self, with_primary: bool) {
+        if self.client_only {
+            self.readiness.mark_listening();
+            return;
+        }
+        if with_primary {
+            self.handlers.start(ServerListener {
+                name: "p2p".to_string(),
+                server: self.server.clone(),
+            });
+        }
+        for addr in self.listen.extra() {
+            tracing::info!("👂 Also listening on {}", addr);
+            self.handlers.start(ServerListener {
+                name: format!("p2p {}", addr),
+                server: p2p_server(*addr, self.context.clone()),
+            });
+        }
+        crate::readiness::spawn_listen_probe(
+            self.readiness.clone(),
+            self.listen.bindings.clone(),
+            self.handlers.clone(),
+        );
+    }
+
+    /// 当前启动阶段
+    pub fn startup_phase(&self) -> StartupPhase {
+        self.readiness.phase()
+    }
+
+    /// 等待所有监听地址绑定、引导节点完成第一轮拨号；监听器启动失败时返回错误
+    pub async fn wait_ready(&self) -> anyhow::Result<()> {
+        self.readiness.wait().await
+    }
+
+    /// 通知对端下线、停止 server，并写出尚未落盘的服务器列表
+    pub(crate) async fn shutdown(&self) {
+        crate::protocols::commands::presence::publish(&self.context, false).await;
+        offline::notify_offline(&self.context).await;
+        self.handlers.stop_all().await;
+        // 等待正在执行的维护任务结束，之后再落盘
+        self.scheduler.shutdown().await;
+        let _ = self.save_registries().await;
+        self.io_storage.flush().await;
+        port_mapping::release(&self.context).await;
+        self.record_stopped().await;
+    }
 
-        // 4. (可选) 当 CLI 退出后，可以尝试关闭或等待 server
-        server_handle.abort(); // 如果希望立即停止 server
+    async fn record_started(&self) {
+        let event = crate::journal::Event::Started {
+            address: self.id.to_string(),
+            listen: self
+                .listen
+                .bindings
+                .iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+        crate::journal::record(&self.context, event).await;
+    }
+
+    async fn record_stopped(&self) {
+        let event = crate::journal::Event::Stopped {
+            address: self.id.to_string(),
+        };
+        crate::journal::record(&self.context, event).await;
     }
 
     pub async fn start_with_web<R>(self, _reader: R, web_handler: WebHandler)
     where
         R: tokio::io::AsyncBufRead + Unpin + Send + 'static,
     {
+        if self.client_only {
+            tracing::error!(
+                "The web server needs a listening socket, not available in client-only mode"
+            );
+            return;
+        }
         let addr = self.addr;
         let globals = self.context.clone();
         let handler = Arc::new(web_handler);
+        let static_mount = globals.get::<StaticMount>().await;
 
         let tcp_router = Arc::new(register(TcpRouter::<P2PFrame, P2PCommand>::new()));
 
@@ -471,6 +1184,14 @@ impl Node {
                 });
                 router.get("/api/data", api_executor).register();
 
+                if let Some(mount) = static_mount {
+                    tracing::info!("🌐 Serving {} at {}", mount.root.display(), mount.prefix);
+                    router.static_dir(&mount.prefix, mount.root);
+                }
+
+                // 经 HTTP 隧道访问其它节点暴露的服务
+                router.peer_proxy(DEFAULT_PEER_PREFIX);
+
                 // Catch-all so API endpoints (POST /api/send_chat, etc.) reach the web handler
                 let h3 = handler.clone();
                 let catch_all_executor: std::sync::Arc<
@@ -500,52 +1221,31 @@ impl Node {
                 > = std::sync::Arc::new(move |_ctx: &mut aex::connection::context::Context| {
                     async move { true }.boxed()
                 });
+                // 认证在 WebSocket 升级之前进行，启用 Web 认证时要求 User 角色
                 router
                     .get("/ws", ws_executor)
-                    .middleware(Arc::from(ws_middleware))
+                    .middleware(crate::web::auth::require(
+                        crate::web::auth::Access::User,
+                        Arc::from(ws_middleware),
+                    ))
                     .register();
                 router
             })
-            .tcp_handler(Arc::new(move |ctx| {
-                let router = tcp_router.clone();
-                let peer_addr = ctx.addr;
-                let manager = ctx.global.manager.clone();
-                let ctx_arc = Arc::new(Mutex::new(ctx));
-                let ctx_for_add = ctx_arc.clone();
-
-                let child_token = manager.cancel_token.child_token();
-                let task_token = child_token.clone();
-
-                // Signal channel: router must NOT process frames until the
-                // entry is registered in the manager, otherwise online_handler
-                // will fail to find the entry for update_node (race condition).
-                let (entry_added_tx, entry_added_rx) = tokio::sync::oneshot::channel::<()>();
-
-                let handle = tokio::spawn(async move {
-                    // Wait for manager.add() to complete before processing frames
-                    let _ = entry_added_rx.await;
-                    tokio::select! {
-                        _ = task_token.cancelled() => {}
-                        _ = router.handle(ctx_arc) => {}
-                    }
-                });
-
-                let abort_handle = handle.abort_handle();
-                manager.add(
-                    peer_addr,
-                    abort_handle,
-                    child_token,
-                    true,
-                    Some(ctx_for_add),
-                );
-                // Signal that the entry is now visible in the manager
-                let _ = entry_added_tx.send(());
-
-                handle
-            }));
+            .tcp_handler(Arc::new(move |ctx| accept_inbound(tcp_router.clone(), ctx)));
 
+        self.start_servers(false);
         tracing::info!("Server running. Press Ctrl+C to stop.");
-        let _ = unified.start().await;
+        self.record_started().await;
+        let admin_ctx = self.context.clone();
+        tokio::select! {
+            _ = unified.start() => {}
+            _ = crate::admin::shutdown_requested(&admin_ctx) => {
+                tracing::info!("Shutdown requested by admin");
+                self.shutdown().await;
+                return;
+            }
+        }
+        self.record_stopped().await;
     }
 
     /// 核心功能：深度同步活跃连接的元数据到注册表
@@ -561,18 +1261,18 @@ impl Node {
             let entry = entry_ref.value();
 
             // 1. 识别网络范围
-            let current_scope = NetworkScope::from_ip(&addr.ip());
+            let current_scope = ip_scope::classify(&addr.ip());
             let registry = if current_scope == NetworkScope::Extranet {
                 &mut self.external
             } else {
                 &mut self.inner
             };
 
-            // 2. 获取或创建 NodeRecord
+            // 2. 获取或创建 NodeRecord（地址可能是某个节点记录的备用地址）
             // 使用 take 取出以修改（因为 HashSet 元素具有不可变性限制）
             let mut record = registry
-                .nodes
-                .take(&NodeRecord::new(addr))
+                .write()
+                .take(addr)
                 .unwrap_or_else(|| NodeRecord::new(addr));
 
             // 3. 基础状态更新
@@ -605,37 +1305,50 @@ impl Node {
             } // 锁在此处释放
 
             // 5. 放回注册表
-            registry.nodes.insert(record);
+            registry.write().insert(record);
+        }
+        let _ = self.save_registries().await;
+    }
+
+    /// 握手完成后把对端地址归入其节点的记录，同一节点的 IPv4 / IPv6 地址合并为一条；
+    /// 列表有变化时写回存储
+    pub async fn bind_identity(&self, endpoint: SocketAddr, address: &str) {
+        let mut changed = false;
+        for registry in [&self.inner, &self.external] {
+            changed |= registry.write().bind_identity(endpoint, address);
+        }
+        if changed {
+            tracing::debug!("📝 Paired {} with node {}", endpoint, address);
+            if let Err(e) = self.save_registries().await {
+                tracing::warn!("Failed to save peer lists: {}", e);
+            }
         }
-        let _ = self.save_registries();
     }
 
-    async fn save_registries(&self) -> anyhow::Result<()> {
+    /// 服务器列表增量写入数据库；数据库不可用时由后台任务合并落盘到 JSON
+    pub(crate) async fn save_registries(&self) -> anyhow::Result<()> {
+        let (inner, external) = (self.inner.snapshot(), self.external.snapshot());
+        if let Some(store) = self.context.get::<SharedPeerStore>().await {
+            store.save_changed(PeerScope::Inner, &inner).await?;
+            store.save_changed(PeerScope::External, &external).await?;
+            return Ok(());
+        }
         self.io_storage
-            .save::<HashSet<NodeRecord>>(&self.inner.nodes, STORAGE_INNER_SERVER)
-            .await;
+            .schedule_save::<HashSet<NodeRecord>>(&inner, STORAGE_INNER_SERVER);
         self.io_storage
-            .save::<HashSet<NodeRecord>>(&self.external.nodes, STORAGE_EXTERNAL_SERVER)
-            .await;
+            .schedule_save::<HashSet<NodeRecord>>(&external, STORAGE_EXTERNAL_SERVER);
         Ok(())
     }
     pub async fn connect_to(&mut self, peer_addr: &str) -> Result<(), String> {
         let endpoint = peer_addr.parse::<SocketAddr>().map_err(|e| e.to_string())?;
 
         // Add to both inner and external seeds
-        self.inner.upsert(endpoint, true);
-        self.external.upsert(endpoint, true);
+        self.inner.write().upsert(endpoint, true);
+        self.external.write().upsert(endpoint, true);
 
-        let manager = self.context.manager.clone();
         let global = self.context.clone();
 
-        manager
-            .connect::<P2PFrame, P2PCommand, _, _>(
-                endpoint,
-                global,
-                move |_ctx| Box::pin(async move {}),
-                Some(10),
-            )
+        proxy::connect(global, endpoint, move |_ctx| Box::pin(async move {}))
             .await
             .map_err(|e| e.to_string())?;
 
@@ -646,6 +1359,161 @@ impl Node {
 
         Ok(())
     }
+
+    /// 当前所有连接（对端、传输方式、方向、流量、在线时长、RTT）
+    pub async fn connections(&self) -> Vec<PeerConnection> {
+        connections::list(&self.context).await
+    }
+
+    /// 关闭与 `peer`（`ip:port`、`ip`、节点地址或别名）匹配的连接，返回被关闭的连接
+    pub async fn disconnect(&self, peer: &str) -> Vec<PeerConnection> {
+        connections::disconnect(&self.context, peer).await
+    }
+
+    /// 向指定地址发送二进制消息（自动分片）
+    pub async fn send_binary(
+        &self,
+        receiver: &str,
+        content_type: &str,
+        filename: Option<String>,
+        data: Vec<u8>,
+    ) -> anyhow::Result<u64> {
+        use crate::protocols::commands::{binary::send_binary_message, message::next_request_id};
+
+        let request_id = next_request_id();
+        let sender = self.id.to_string();
+        let receiver = receiver.to_string();
+        let content_type = content_type.to_string();
+        let result: Arc<Mutex<Option<anyhow::Result<()>>>> = Arc::new(Mutex::new(None));
+        let result_for_closure = result.clone();
+        let receiver_for_closure = receiver.clone();
+        self.context
+            .manager
+            .notify(receiver.as_bytes(), |entries| async move {
+                if let Some(ctx) = entries.into_iter().find_map(|e| e.context.clone()) {
+                    let res = send_binary_message(
+                        sender.clone(),
+                        receiver_for_closure.clone(),
+                        request_id,
+                        ctx,
+                        &content_type,
+                        filename.clone(),
+                        &data,
+                    )
+                    .await;
+                    *result_for_closure.lock().await = Some(res);
+                }
+            })
+            .await;
+
+        match result.lock().await.take() {
+            Some(Ok(())) => Ok(request_id),
+            Some(Err(e)) => Err(e),
+            None => Err(anyhow::anyhow!("Peer {} is not connected", receiver)),
+        }
+    }
+
+    /// 发布本节点的名称记录（有效期最长 7 天，到期前需重新发布）
+    pub async fn publish_name(
+        &self,
+        name: &str,
+        ttl: std::time::Duration,
+    ) -> anyhow::Result<crate::protocols::commands::naming::NameRecord> {
+        crate::protocols::commands::naming::publish(self.context.clone(), name, ttl).await
+    }
+
+    /// 解析名称为节点地址；本地没有缓存时向已连接的对端查询
+    pub async fn resolve_name(
+        &self,
+        name: &str,
+    ) -> anyhow::Result<Option<crate::protocols::commands::naming::NameRecord>> {
+        crate::protocols::commands::naming::resolve(
+            self.context.clone(),
+            name,
+            std::time::Duration::from_millis(crate::consts::DEFAULT_TIMEOUT_MS),
+        )
+        .await
+    }
+
+    /// 注册二进制消息接收通道（替换之前注册的通道）
+    pub async fn subscribe_binary(
+        &self,
+    ) -> tokio::sync::mpsc::UnboundedReceiver<crate::protocols::commands::binary::IncomingBinary>
+    {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        self.context.set(tx).await;
+        rx
+    }
+
+    /// 注册某一类流的 handler，handler 以 `AsyncRead` 边收边读
+    pub async fn on_stream(&self, kind: &str, handler: stream::StreamHandler) {
+        stream::register(&self.context, kind, handler).await;
+    }
+
+    /// 订阅节点事件总线（生命周期事件与收到的消息）
+    pub async fn subscribe_events(
+        &self,
+    ) -> tokio::sync::broadcast::Receiver<crate::events::NodeEvent> {
+        match self.context.get::<crate::events::EventBus>().await {
+            Some(bus) => bus.subscribe(),
+            None => {
+                let bus = crate::events::new_bus();
+                let rx = bus.subscribe();
+                self.context.set(bus).await;
+                rx
+            }
+        }
+    }
+
+    /// 注册通话事件接收通道（替换之前注册的通道）
+    pub async fn subscribe_calls(
+        &self,
+    ) -> tokio::sync::mpsc::UnboundedReceiver<crate::protocols::commands::telephone::CallEvent>
+    {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        self.context.set(tx).await;
+        rx
+    }
+}
+
+async fn wait_for_shutdown() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = term.recv() => {}
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    // 服务停止、控制台关闭、注销与关机都按正常退出处理
+    #[cfg(windows)]
+    {
+        use tokio::signal::windows::{ctrl_break, ctrl_close, ctrl_shutdown};
+        match (ctrl_break(), ctrl_close(), ctrl_shutdown()) {
+            (Ok(mut brk), Ok(mut close), Ok(mut shutdown)) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = brk.recv() => {}
+                    _ = close.recv() => {}
+                    _ = shutdown.recv() => {}
+                }
+            }
+            _ => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
 }
 
 pub fn is_public_ip(ip: &std::net::IpAddr) -> bool {
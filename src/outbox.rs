@@ -0,0 +1,252 @@
+//! 发件箱：暂存无法送达的文本消息
+//!
+//! `send` 时接收方既没有直连也没有路由（例如本节点还没有任何连接）时，消息不再直接失败，而是
+//! 作为草稿放入发件箱并持久化到 `outbox.json`。后台任务每 [`OUTBOX_FLUSH_INTERVAL_SECS`] 秒
+//! 检查一次，一旦到某个接收方有了直连或路由，就按入队顺序以入队时的身份发出。
+//!
+//! 草稿在 CLI 的 `outbox` 命令、控制接口的 `GET /outbox` 与 `GET /status` 的 `outbox` 中显示为
+//! 待发送；`outbox rm <id>` 放弃一条草稿。端到端加密的消息不进入发件箱。
+
+use std::sync::{Arc, RwLock};
+
+use aex::{connection::global::GlobalContext, time::SystemTime};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clis::send::{self, NotConnected},
+    identities::{self, LocalIdentity, SharedIdentities},
+    io_storage::{IOStorage, STORAGE_OUTBOX},
+    node::Node as P2pNode,
+};
+
+/// 检查接收方是否可达的间隔
+pub const OUTBOX_FLUSH_INTERVAL_SECS: u64 = 5;
+/// 发件箱最多保存的草稿数
+pub const OUTBOX_MAX: usize = 1000;
+
+/// 一条待发送的消息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Draft {
+    pub id: u64,
+    /// 入队时使用的身份地址
+    pub sender: String,
+    pub receiver: String,
+    pub message: String,
+    /// 入队时间（毫秒）
+    pub queued_at: u128,
+    /// 接收方可达但发送失败的次数
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
+
+/// 持久化的发件箱
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OutboxList {
+    pub next_id: u64,
+    pub drafts: Vec<Draft>,
+}
+
+impl OutboxList {
+    /// 追加一条草稿
+    pub fn push(&mut self, sender: &str, receiver: &str, message: &str, now: u128) -> Draft {
+        self.next_id += 1;
+        let draft = Draft {
+            id: self.next_id,
+            sender: sender.to_string(),
+            receiver: receiver.to_string(),
+            message: message.to_string(),
+            queued_at: now,
+            attempts: 0,
+            last_error: None,
+        };
+        self.drafts.push(draft.clone());
+        draft
+    }
+
+    pub fn remove(&mut self, id: u64) -> Option<Draft> {
+        let index = self.drafts.iter().position(|d| d.id == id)?;
+        Some(self.drafts.remove(index))
+    }
+}
+
+/// `send` 的结果：已发出（request_id）或已放入发件箱
+#[derive(Debug, Clone, PartialEq)]
+pub enum Delivery {
+    Sent(u64),
+    Queued(Draft),
+}
+
+/// 保存在 GlobalContext 中的发件箱
+pub type SharedOutbox = Arc<RwLock<OutboxList>>;
+
+async fn shared(gctx: &Arc<GlobalContext>) -> SharedOutbox {
+    match gctx.get::<SharedOutbox>().await {
+        Some(outbox) => outbox,
+        None => {
+            let outbox = SharedOutbox::default();
+            gctx.set(outbox.clone()).await;
+            outbox
+        }
+    }
+}
+
+fn update<R>(outbox: &SharedOutbox, f: impl FnOnce(&mut OutboxList) -> R) -> (R, OutboxList) {
+    let mut guard = match outbox.write() {
+        Ok(g) => g,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let result = f(&mut guard);
+    (result, guard.clone())
+}
+
+async fn persist(gctx: &Arc<GlobalContext>, list: &OutboxList) {
+    match gctx.get::<IOStorage>().await {
+        Some(ios) => ios.save::<OutboxList>(list, STORAGE_OUTBOX).await,
+        None => tracing::error!("IOStorage not found in context, outbox not persisted"),
+    }
+}
+
+/// 待发送的草稿（按入队顺序）
+pub async fn list(gctx: &Arc<GlobalContext>) -> Vec<Draft> {
+    let outbox = shared(gctx).await;
+    let guard = match outbox.read() {
+        Ok(g) => g,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    guard.drafts.clone()
+}
+
+/// 放入发件箱
+pub async fn queue(
+    gctx: &Arc<GlobalContext>,
+    sender: &str,
+    receiver: &str,
+    message: &str,
+) -> anyhow::Result<Draft> {
+    let now = SystemTime::timestamp();
+    let (draft, current) = update(&shared(gctx).await, |list| {
+        if list.drafts.len() >= OUTBOX_MAX {
+            return Err(anyhow::anyhow!("Outbox is full (max {})", OUTBOX_MAX));
+        }
+        Ok(list.push(sender, receiver, message, now))
+    });
+    let draft = draft?;
+    persist(gctx, &current).await;
+    tracing::info!("📥 Queued draft #{} for {}", draft.id, draft.receiver);
+    Ok(draft)
+}
+
+/// 放弃一条草稿
+pub async fn remove(gctx: &Arc<GlobalContext>, id: u64) -> Option<Draft> {
+    let (removed, current) = update(&shared(gctx).await, |list| list.remove(id));
+    let removed = removed?;
+    persist(gctx, &current).await;
+    Some(removed)
+}
+
+/// 以当前身份发送文本消息；接收方不可达时放入发件箱
+pub async fn send_or_queue(
+    gctx: &Arc<GlobalContext>,
+    receiver: String,
+    message: String,
+) -> anyhow::Result<Delivery> {
+    let identity = identities::active(gctx)
+        .await
+        .ok_or_else(|| anyhow::anyhow!("Address not set"))?;
+    match send::send_text_as(gctx.clone(), &identity, receiver, message.clone()).await {
+        Ok(request_id) => Ok(Delivery::Sent(request_id)),
+        Err(e) => match e.downcast_ref::<NotConnected>() {
+            Some(unreachable) => {
+                let sender = identity.address.to_string();
+                let draft = queue(gctx, &sender, &unreachable.receiver, &message).await?;
+                Ok(Delivery::Queued(draft))
+            }
+            None => Err(e),
+        },
+    }
+}
+
+async fn identity_for(gctx: &Arc<GlobalContext>, sender: &str) -> Option<LocalIdentity> {
+    match gctx.get::<SharedIdentities>().await {
+        Some(identities) => identities.get(sender),
+        None => identities::active(gctx)
+            .await
+            .filter(|i| i.address.to_string() == sender),
+    }
+}
+
+/// 尝试发出所有草稿，返回发出的条数；同一接收方的草稿一旦有一条发不出，其后的留到下一轮
+pub async fn flush_once(gctx: &Arc<GlobalContext>) -> usize {
+    let drafts = list(gctx).await;
+    if drafts.is_empty() || gctx.get::<Arc<P2pNode>>().await.is_none() {
+        return 0;
+    }
+    let mut blocked: Vec<String> = Vec::new();
+    let mut flushed = 0;
+    for draft in drafts {
+        if blocked.contains(&draft.receiver) {
+            continue;
+        }
+        let Some(identity) = identity_for(gctx, &draft.sender).await else {
+            blocked.push(draft.receiver.clone());
+            record_failure(gctx, draft.id, "sending identity no longer exists").await;
+            continue;
+        };
+        let sent = send::send_text_as(
+            gctx.clone(),
+            &identity,
+            draft.receiver.clone(),
+            draft.message.clone(),
+        )
+        .await;
+        match sent {
+            Ok(request_id) => {
+                tracing::info!(
+                    "📤 Sent draft #{} to {} (request_id={})",
+                    draft.id,
+                    draft.receiver,
+                    request_id
+                );
+                remove(gctx, draft.id).await;
+                flushed += 1;
+            }
+            Err(e) => {
+                blocked.push(draft.receiver.clone());
+                if e.downcast_ref::<NotConnected>().is_none() {
+                    tracing::warn!("Failed to send draft #{}: {}", draft.id, e);
+                    record_failure(gctx, draft.id, &e.to_string()).await;
+                }
+            }
+        }
+    }
+    flushed
+}
+
+async fn record_failure(gctx: &Arc<GlobalContext>, id: u64, error: &str) {
+    let (found, current) = update(&shared(gctx).await, |list| {
+        let draft = list.drafts.iter_mut().find(|d| d.id == id)?;
+        draft.attempts += 1;
+        draft.last_error = Some(error.to_string());
+        Some(())
+    });
+    if found.is_some() {
+        persist(gctx, &current).await;
+    }
+}
+
+/// 后台定期发出可达接收方的草稿
+pub fn spawn(gctx: Arc<GlobalContext>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(OUTBOX_FLUSH_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let flushed = flush_once(&gctx).await;
+            if flushed > 0 {
+                tracing::info!("📤 Outbox: sent {} queued message(s)", flushed);
+            }
+        }
+    })
+}
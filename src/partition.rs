@@ -0,0 +1,337 @@
+//! 网络分区检测与重新引导
+//!
+//! 所有连接同时断开（例如运营商网络闪断）后，节点不会再主动拨号，只能等别的节点连进来。
+//! 后台任务每 `check_interval_secs` 秒检查一次已连接的节点数：曾经有过连接、随后连续
+//! `after_secs` 秒没有任何已连接节点时判定为分区，记录 `network.partition_detected` 事件，
+//! 并立即按服务器列表（固定的优先、评分从高到低）与引导来源重新拨号。仍未恢复时按
+//! `min_retry_secs` 起指数退避、最长 `max_retry_secs` 再次重新引导。重新连上任意节点后
+//! 记录 `network.partition_recovered` 事件（含分区持续时间与重新引导的次数）。
+//!
+//! ```toml
+//! [partition]
+//! after_secs = 30
+//! max_retry_secs = 600
+//! ```
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use aex::{connection::global::GlobalContext, time::SystemTime};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bootstrap::{self, BootstrapSources},
+    client_mode,
+    config::SharedConfig,
+    journal::{self, Event},
+    node::Node,
+    protocols::commands::ack,
+    record::NodeRecord,
+    scheduler::Scheduler,
+};
+
+/// 默认检查间隔
+pub const DEFAULT_PARTITION_CHECK_INTERVAL_SECS: u64 = 5;
+/// 默认没有已连接节点多久后判定为分区
+pub const DEFAULT_PARTITION_AFTER_SECS: u64 = 30;
+/// 默认第二次重新引导前的等待时间
+pub const DEFAULT_REBOOTSTRAP_MIN_RETRY_SECS: u64 = 30;
+/// 默认重新引导的间隔上限
+pub const DEFAULT_REBOOTSTRAP_MAX_RETRY_SECS: u64 = 600;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PartitionConfig {
+    /// 检查间隔（秒）
+    pub check_interval_secs: u64,
+    /// 没有已连接节点超过该时间后判定为分区（秒），0 表示关闭检测
+    pub after_secs: u64,
+    /// 第一次重新引导失败后的等待时间（秒），之后每次翻倍
+    pub min_retry_secs: u64,
+    /// 重新引导的间隔上限（秒）
+    pub max_retry_secs: u64,
+}
+
+impl Default for PartitionConfig {
+    fn default() -> Self {
+        Self {
+            check_interval_secs: DEFAULT_PARTITION_CHECK_INTERVAL_SECS,
+            after_secs: DEFAULT_PARTITION_AFTER_SECS,
+            min_retry_secs: DEFAULT_REBOOTSTRAP_MIN_RETRY_SECS,
+            max_retry_secs: DEFAULT_REBOOTSTRAP_MAX_RETRY_SECS,
+        }
+    }
+}
+
+impl PartitionConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.check_interval_secs.max(1))
+    }
+
+    /// 已重新引导 `attempts` 次后，到下一次的等待时间
+    pub fn retry_delay(&self, attempts: u32) -> Duration {
+        let factor = 1u64 << attempts.saturating_sub(1).min(16);
+        let secs = self.min_retry_secs.max(1).saturating_mul(factor);
+        Duration::from_secs(secs.min(self.max_retry_secs.max(self.min_retry_secs).max(1)))
+    }
+}
+
+/// 状态变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    /// 连续 `isolated_secs` 秒没有已连接节点
+    Detected { isolated_secs: u64 },
+    /// 分区持续 `duration_secs` 秒后恢复，期间重新引导了 `rebootstraps` 次
+    Recovered {
+        duration_secs: u64,
+        rebootstraps: u32,
+        peers: usize,
+    },
+}
+
+/// 分区检测的状态，时间均为毫秒时间戳
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PartitionDetector {
+    /// 启动后是否连上过节点；从未连上时交给引导任务，不判定为分区
+    had_peers: bool,
+    isolated_since: Option<u128>,
+    partitioned_since: Option<u128>,
+    last_rebootstrap: Option<u128>,
+    /// 本次分区中的重新引导次数
+    attempts: u32,
+    partitions: u64,
+    rebootstraps: u64,
+}
+
+impl PartitionDetector {
+    /// 记录一次检查时的已连接节点数，返回状态变化
+    pub fn observe(
+        &mut self,
+        now: u128,
+        peers: usize,
+        policy: &PartitionConfig,
+    ) -> Option<Transition> {
+        if peers > 0 {
+            self.had_peers = true;
+            self.isolated_since = None;
+            let since = self.partitioned_since.take()?;
+            let rebootstraps = std::mem::take(&mut self.attempts);
+            self.last_rebootstrap = None;
+            return Some(Transition::Recovered {
+                duration_secs: (now.saturating_sub(since) / 1000) as u64,
+                rebootstraps,
+                peers,
+            });
+        }
+        if !self.had_peers || policy.after_secs == 0 {
+            return None;
+        }
+        let since = *self.isolated_since.get_or_insert(now);
+        let isolated_ms = now.saturating_sub(since);
+        if self.partitioned_since.is_some() || isolated_ms < policy.after_secs as u128 * 1000 {
+            return None;
+        }
+        self.partitioned_since = Some(now);
+        self.partitions += 1;
+        Some(Transition::Detected {
+            isolated_secs: (isolated_ms / 1000) as u64,
+        })
+    }
+
+    pub fn is_partitioned(&self) -> bool {
+        self.partitioned_since.is_some()
+    }
+
+    /// 分区中、且距上次重新引导已超过退避时间
+    pub fn rebootstrap_due(&self, now: u128, policy: &PartitionConfig) -> bool {
+        if !self.is_partitioned() {
+            return false;
+        }
+        self.last_rebootstrap.is_none_or(|last| {
+            now.saturating_sub(last) >= policy.retry_delay(self.attempts).as_millis()
+        })
+    }
+
+    pub fn record_rebootstrap(&mut self, now: u128) {
+        self.last_rebootstrap = Some(now);
+        self.attempts += 1;
+        self.rebootstraps += 1;
+    }
+
+    pub fn report(&self) -> PartitionReport {
+        PartitionReport {
+            partitioned: self.is_partitioned(),
+            isolated_since: self.isolated_since,
+            partitioned_since: self.partitioned_since,
+            partitions: self.partitions,
+            rebootstraps: self.rebootstraps,
+        }
+    }
+}
+
+/// `status` 中的分区状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PartitionReport {
+    pub partitioned: bool,
+    /// 最近一次失去全部连接的时间
+    pub isolated_since: Option<u128>,
+    pub partitioned_since: Option<u128>,
+    /// 启动以来判定为分区的次数
+    pub partitions: u64,
+    /// 启动以来重新引导的次数
+    pub rebootstraps: u64,
+}
+
+pub type SharedPartition = Arc<Mutex<PartitionDetector>>;
+
+fn lock(shared: &SharedPartition) -> std::sync::MutexGuard<'_, PartitionDetector> {
+    match shared.lock() {
+        Ok(g) => g,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+async fn detector(gctx: &GlobalContext) -> SharedPartition {
+    match gctx.get::<SharedPartition>().await {
+        Some(detector) => detector,
+        None => {
+            let detector = SharedPartition::default();
+            gctx.set(detector.clone()).await;
+            detector
+        }
+    }
+}
+
+pub async fn policy(gctx: &GlobalContext) -> PartitionConfig {
+    match gctx.get::<SharedConfig>().await {
+        Some(config) => config.read().await.partition.clone(),
+        None => PartitionConfig::default(),
+    }
+}
+
+pub async fn report(gctx: &GlobalContext) -> PartitionReport {
+    lock(&detector(gctx).await).report()
+}
+
+/// 重新引导一轮：拨号服务器列表中的全部记录与引导来源，返回成功拨号的数量
+pub async fn rebootstrap(gctx: &Arc<GlobalContext>) -> usize {
+    let Some(node) = gctx.get::<Arc<Node>>().await else {
+        return 0;
+    };
+    // 分区期间的失败可能让记录被标记为不可用，这里不按可用性过滤
+    let records: Vec<NodeRecord> = [&node.inner, &node.external]
+        .into_iter()
+        .flat_map(|registry| registry.read().nodes.iter().cloned().collect::<Vec<_>>())
+        .collect();
+    let mut addrs = client_mode::candidates(records);
+    if let Some(sources) = gctx.get::<BootstrapSources>().await {
+        for addr in bootstrap::resolve(&sources.0).await {
+            if !addrs.contains(&addr) {
+                addrs.push(addr);
+            }
+        }
+    }
+
+    let mut dialed = 0;
+    for addr in addrs {
+        if bootstrap::is_self(&addr, &gctx.addr) || gctx.manager.find_entry(&addr).is_some() {
+            continue;
+        }
+        let ok = match ack::dial_peer(gctx.clone(), addr).await {
+            Ok(_) => true,
+            Err(e) => {
+                tracing::debug!("Re-bootstrap dial {} failed: {}", addr, e);
+                false
+            }
+        };
+        for registry in [&node.inner, &node.external] {
+            registry.write().record_probe(addr, ok);
+        }
+        if ok {
+            dialed += 1;
+        }
+    }
+    if let Err(e) = node.save_registries().await {
+        tracing::warn!("Failed to save peer lists: {}", e);
+    }
+    dialed
+}
+
+async fn connected_peers(gctx: &GlobalContext) -> usize {
+    match gctx.get::<Arc<Node>>().await {
+        Some(node) => node.registry.get_connected_nodes().len(),
+        None => 0,
+    }
+}
+
+/// 检查一次，必要时记录事件并重新引导
+pub async fn run_once(gctx: &Arc<GlobalContext>) {
+    let policy = policy(gctx).await;
+    let detector = detector(gctx).await;
+    let peers = connected_peers(gctx).await;
+    let now = SystemTime::timestamp();
+
+    let transition = lock(&detector).observe(now, peers, &policy);
+    match transition {
+        Some(Transition::Detected { isolated_secs }) => {
+            tracing::warn!(
+                "🧱 Network partition: no connected peers for {}s, re-bootstrapping",
+                isolated_secs
+            );
+            journal::record(gctx, Event::PartitionDetected { isolated_secs }).await;
+        }
+        Some(Transition::Recovered {
+            duration_secs,
+            rebootstraps,
+            peers,
+        }) => {
+            tracing::info!(
+                "🌐 Network partition recovered after {}s ({} re-bootstrap(s)), {} peer(s) connected",
+                duration_secs,
+                rebootstraps,
+                peers
+            );
+            journal::record(
+                gctx,
+                Event::PartitionRecovered {
+                    duration_secs,
+                    rebootstraps,
+                    peers,
+                },
+            )
+            .await;
+        }
+        None => {}
+    }
+
+    let due = {
+        let mut detector = lock(&detector);
+        let due = detector.rebootstrap_due(now, &policy);
+        if due {
+            detector.record_rebootstrap(now);
+        }
+        due
+    };
+    if due {
+        let dialed = rebootstrap(gctx).await;
+        tracing::info!("🌐 Re-bootstrap dialed {} peer(s)", dialed);
+    }
+}
+
+/// 后台定期检查；间隔随配置热更新
+pub fn schedule(scheduler: &Scheduler, gctx: Arc<GlobalContext>) {
+    let period_gctx = gctx.clone();
+    scheduler.register_dynamic(
+        "partition",
+        move || {
+            let gctx = period_gctx.clone();
+            async move { policy(&gctx).await.interval() }
+        },
+        move || {
+            let gctx = gctx.clone();
+            async move { run_once(&gctx).await }
+        },
+    );
+}
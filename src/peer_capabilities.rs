@@ -0,0 +1,182 @@
+//! 对端能力的跨会话追踪
+//!
+//! 每次握手完成后，把对端声明的传输协议（`node.protocols`）、特性位与协议版本记入服务器列表中
+//! 该端点的记录（[`crate::record::NodeRecord::capabilities`]，随 `peers.db` 持久化）。
+//! 与上一次会话相比出现退化——例如之前声明 UDP / QUIC 的节点现在只声明 TCP、某个特性位消失、
+//! 协议版本降低——时记录 `peer.capability_downgraded` 事件（事件日志、事件总线与 webhook
+//! 均可见），并计入 `status` 中的 `capability_downgrades`。这类退化通常意味着对端所在的
+//! NAT 或防火墙规则发生了变化。
+//!
+//! 对端未声明的部分（空的协议集合、为 0 的特性位）视为未知，不参与比较。
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use aex::connection::{global::GlobalContext, protocol::Protocol};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{journal, node::Node, protocols::capabilities};
+
+/// 一次会话中对端声明的能力
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapabilityProfile {
+    /// 对端声明支持的传输协议
+    pub protocols: HashSet<Protocol>,
+    /// 握手中声明的能力位
+    pub features: u32,
+    /// 握手中声明的最高协议版本
+    pub protocol_version: u8,
+    pub observed_at: DateTime<Utc>,
+}
+
+/// 与上一次会话相比失去的能力
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapabilityRegression {
+    /// 不再声明的传输协议
+    pub lost_protocols: Vec<String>,
+    /// 不再声明的特性
+    pub lost_features: Vec<String>,
+    /// 协议版本降低时为 (之前, 现在)
+    pub protocol_version: Option<(u8, u8)>,
+}
+
+fn protocol_name(protocol: &Protocol) -> String {
+    format!("{:?}", protocol).to_lowercase()
+}
+
+impl CapabilityProfile {
+    pub fn new(protocols: HashSet<Protocol>, features: u32, protocol_version: u8) -> Self {
+        Self {
+            protocols,
+            features,
+            protocol_version,
+            observed_at: Utc::now(),
+        }
+    }
+
+    /// 相对 `previous` 的退化；没有退化时返回 None
+    pub fn regression_since(&self, previous: &CapabilityProfile) -> Option<CapabilityRegression> {
+        let mut regression = CapabilityRegression::default();
+        if !self.protocols.is_empty() {
+            regression.lost_protocols = previous
+                .protocols
+                .difference(&self.protocols)
+                .map(protocol_name)
+                .collect();
+            regression.lost_protocols.sort();
+        }
+        if self.features != 0 {
+            regression.lost_features =
+                capabilities::feature_names(previous.features & !self.features)
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect();
+        }
+        if self.protocol_version < previous.protocol_version {
+            regression.protocol_version = Some((previous.protocol_version, self.protocol_version));
+        }
+        (!regression.is_empty()).then_some(regression)
+    }
+}
+
+impl CapabilityRegression {
+    pub fn is_empty(&self) -> bool {
+        self.lost_protocols.is_empty()
+            && self.lost_features.is_empty()
+            && self.protocol_version.is_none()
+    }
+}
+
+impl fmt::Display for CapabilityRegression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if !self.lost_protocols.is_empty() {
+            parts.push(format!(
+                "lost transports {}",
+                self.lost_protocols.join(", ")
+            ));
+        }
+        if !self.lost_features.is_empty() {
+            parts.push(format!("lost features {}", self.lost_features.join(", ")));
+        }
+        if let Some((before, now)) = self.protocol_version {
+            parts.push(format!("protocol v{} → v{}", before, now));
+        }
+        write!(f, "{}", parts.join("; "))
+    }
+}
+
+/// `status` 中的退化统计
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DowngradeStats {
+    pub total: u64,
+    /// 按对端地址统计
+    pub by_peer: BTreeMap<String, u64>,
+}
+
+/// 保存在 GlobalContext 中的退化统计
+pub type SharedDowngradeStats = Arc<Mutex<DowngradeStats>>;
+
+async fn downgrades(gctx: &GlobalContext) -> SharedDowngradeStats {
+    match gctx.get::<SharedDowngradeStats>().await {
+        Some(stats) => stats,
+        None => {
+            let stats = SharedDowngradeStats::default();
+            gctx.set(stats.clone()).await;
+            stats
+        }
+    }
+}
+
+pub async fn stats(gctx: &GlobalContext) -> DowngradeStats {
+    downgrades(gctx)
+        .await
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// 握手完成后调用：记录 `endpoint` 的能力，退化时告警并记录事件；返回退化内容
+pub async fn observe(
+    gctx: &Arc<GlobalContext>,
+    peer: &str,
+    endpoint: SocketAddr,
+    profile: CapabilityProfile,
+) -> Option<CapabilityRegression> {
+    let node = gctx.get::<Arc<Node>>().await?;
+    let mut regression = None;
+    for registry in [&node.inner, &node.external] {
+        if let Some(change) = registry
+            .write()
+            .record_capabilities(endpoint, profile.clone())
+        {
+            regression = Some(change);
+        }
+    }
+    let regression = regression?;
+
+    tracing::warn!(
+        "📉 Peer {} ({}) capabilities regressed: {}",
+        peer,
+        endpoint,
+        regression
+    );
+    {
+        let stats = downgrades(gctx).await;
+        let mut stats = stats.lock().unwrap_or_else(|e| e.into_inner());
+        stats.total += 1;
+        *stats.by_peer.entry(peer.to_string()).or_insert(0) += 1;
+    }
+    let event = journal::Event::CapabilityDowngraded {
+        peer: peer.to_string(),
+        addr: endpoint.to_string(),
+        change: regression.clone(),
+    };
+    journal::record(gctx, event).await;
+    Some(regression)
+}
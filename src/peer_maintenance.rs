@@ -0,0 +1,254 @@
+//! 服务器列表的定期维护
+//!
+//! 持久化的内网 / 外网服务器列表（[`crate::record::NodeRegistry`]）只增不减，长期下线的节点
+//! 每次启动都会被重新拨号。后台任务每 `interval_secs` 秒：
+//!
+//! 1. 重新拨号 `revalidate_after_secs` 内没有成功通信过的记录（每轮最多 `max_probes` 个，
+//!    最久未见的优先），成功则恢复为可用，失败计入失败次数，超过 5 天未见的标记为失效；
+//! 2. 删除超过 `prune_after_days` 天未见的记录；
+//! 3. 对超过 `decay_after_secs` 未见的记录按 `decay_factor` 衰减成功次数，评分随之降低；
+//! 4. 写回列表，并每天压缩一次服务器数据库（删除过期记录、回收空间）。
+//!
+//! 启动时只拨号可用的记录，失效的记录等待这里重新验证。
+//!
+//! ```toml
+//! [peer_maintenance]
+//! interval_secs = 3600
+//! prune_after_days = 30
+//! decay_factor = 0.9
+//! ```
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use aex::connection::global::GlobalContext;
+use chrono::{DateTime, Utc};
+use futures::{StreamExt, stream};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::SharedConfig,
+    endpoint_verifier::{self, VERIFY_CONCURRENCY},
+    node::Node,
+    peer_store::{COMPACTION_INTERVAL, PEER_RETENTION_DAYS, SharedPeerStore},
+    record::NodeRegistry,
+    scheduler::Scheduler,
+};
+
+/// 默认维护间隔
+pub const DEFAULT_MAINTENANCE_INTERVAL_SECS: u64 = 60 * 60;
+/// 默认在多久未成功通信后重新验证
+pub const DEFAULT_REVALIDATE_AFTER_SECS: u64 = 6 * 60 * 60;
+/// 默认每轮最多重新验证的记录数
+pub const DEFAULT_MAX_PROBES: usize = 32;
+/// 默认在多久未见后开始衰减评分
+pub const DEFAULT_DECAY_AFTER_SECS: u64 = 24 * 60 * 60;
+/// 默认每轮衰减后保留的成功次数比例
+pub const DEFAULT_DECAY_FACTOR: f64 = 0.9;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerMaintenanceConfig {
+    /// 维护间隔（秒）
+    pub interval_secs: u64,
+    /// 超过该时间未成功通信的记录重新拨号验证（秒）
+    pub revalidate_after_secs: u64,
+    /// 每轮最多重新验证的记录数
+    pub max_probes: usize,
+    /// 超过该天数未见的记录删除，0 表示不删除
+    pub prune_after_days: i64,
+    /// 超过该时间未见的记录开始衰减评分（秒）
+    pub decay_after_secs: u64,
+    /// 每轮衰减后保留的成功次数比例，1 表示不衰减
+    pub decay_factor: f64,
+}
+
+impl Default for PeerMaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: DEFAULT_MAINTENANCE_INTERVAL_SECS,
+            revalidate_after_secs: DEFAULT_REVALIDATE_AFTER_SECS,
+            max_probes: DEFAULT_MAX_PROBES,
+            prune_after_days: PEER_RETENTION_DAYS,
+            decay_after_secs: DEFAULT_DECAY_AFTER_SECS,
+            decay_factor: DEFAULT_DECAY_FACTOR,
+        }
+    }
+}
+
+impl PeerMaintenanceConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs.max(1))
+    }
+}
+
+/// 一轮维护的结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct MaintenanceReport {
+    /// 重新验证通过的记录数
+    pub revived: usize,
+    /// 重新验证失败的记录数
+    pub failed: usize,
+    pub pruned: usize,
+    pub decayed: usize,
+    /// 压缩数据库时删除的记录数
+    pub compacted: u64,
+}
+
+impl MaintenanceReport {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+fn ago(now: DateTime<Utc>, secs: u64) -> DateTime<Utc> {
+    now - chrono::Duration::seconds(secs.min(i64::MAX as u64) as i64)
+}
+
+/// 需要重新验证的地址：最久未见的优先，最多 `max_probes` 个
+pub fn due_for_probe(
+    registry: &NodeRegistry,
+    policy: &PeerMaintenanceConfig,
+    now: DateTime<Utc>,
+) -> Vec<SocketAddr> {
+    let before = ago(now, policy.revalidate_after_secs);
+    let mut due: Vec<(DateTime<Utc>, SocketAddr)> = registry
+        .nodes
+        .iter()
+        .filter(|n| n.last_seen < before)
+        .map(|n| (n.last_seen, n.endpoint))
+        .collect();
+    due.sort();
+    due.into_iter()
+        .take(policy.max_probes)
+        .map(|(_, addr)| addr)
+        .collect()
+}
+
+/// 删除过期记录并衰减评分，返回 (删除数, 衰减数)
+pub fn prune_and_decay(
+    registry: &mut NodeRegistry,
+    policy: &PeerMaintenanceConfig,
+    now: DateTime<Utc>,
+) -> (usize, usize) {
+    let pruned = if policy.prune_after_days > 0 {
+        let before = now - chrono::Duration::days(policy.prune_after_days);
+        registry.prune_before(before).len()
+    } else {
+        0
+    };
+    let decayed = if policy.decay_factor < 1.0 {
+        registry.decay_before(ago(now, policy.decay_after_secs), policy.decay_factor)
+    } else {
+        0
+    };
+    (pruned, decayed)
+}
+
+/// 当前配置中的维护策略
+pub async fn policy(gctx: &GlobalContext) -> PeerMaintenanceConfig {
+    match gctx.get::<SharedConfig>().await {
+        Some(config) => config.read().await.peer_maintenance.clone(),
+        None => PeerMaintenanceConfig::default(),
+    }
+}
+
+/// 维护一轮；`compact` 为 true 时同时压缩服务器数据库
+pub async fn run_once(gctx: &Arc<GlobalContext>, compact: bool) -> MaintenanceReport {
+    let mut report = MaintenanceReport::default();
+    let Some(node) = gctx.get::<Arc<Node>>().await else {
+        return report;
+    };
+    let policy = policy(gctx).await;
+    let registries = [&node.inner, &node.external];
+
+    let now = Utc::now();
+    let mut due: Vec<SocketAddr> = Vec::new();
+    for registry in registries {
+        for addr in due_for_probe(&registry.read(), &policy, now) {
+            if !due.contains(&addr) {
+                due.push(addr);
+            }
+        }
+    }
+    due.truncate(policy.max_probes);
+    let results: Vec<(SocketAddr, bool)> = stream::iter(due)
+        .map(|addr| async move { (addr, endpoint_verifier::probe(addr).await) })
+        .buffer_unordered(VERIFY_CONCURRENCY)
+        .collect()
+        .await;
+    for (addr, ok) in &results {
+        for registry in registries {
+            registry.write().record_probe(*addr, *ok);
+        }
+        if *ok {
+            report.revived += 1;
+        } else {
+            report.failed += 1;
+        }
+    }
+
+    let now = Utc::now();
+    for registry in registries {
+        let (pruned, decayed) = prune_and_decay(&mut registry.write(), &policy, now);
+        report.pruned += pruned;
+        report.decayed += decayed;
+    }
+
+    if let Err(e) = node.save_registries().await {
+        tracing::warn!("Failed to save peer lists: {}", e);
+    }
+    if compact && policy.prune_after_days > 0 {
+        if let Some(store) = gctx.get::<SharedPeerStore>().await {
+            match store.compact(policy.prune_after_days).await {
+                Ok(n) => report.compacted = n,
+                Err(e) => tracing::warn!("Peer database compaction failed: {}", e),
+            }
+            // 压缩清空了已落盘记录的缓存，重新写入内存中的列表
+            if let Err(e) = node.save_registries().await {
+                tracing::warn!("Failed to save peer lists: {}", e);
+            }
+        }
+    }
+    report
+}
+
+/// 后台定期维护；间隔随配置热更新
+pub fn schedule(scheduler: &Scheduler, gctx: Arc<GlobalContext>) {
+    let period_gctx = gctx.clone();
+    let last_compaction: Arc<Mutex<Option<Instant>>> = Arc::default();
+    scheduler.register_dynamic(
+        "peer_maintenance",
+        move || {
+            let gctx = period_gctx.clone();
+            async move { policy(&gctx).await.interval() }
+        },
+        move || {
+            let gctx = gctx.clone();
+            let compact = {
+                let mut last = last_compaction.lock().unwrap_or_else(|e| e.into_inner());
+                let due = last.is_none_or(|at| at.elapsed() >= COMPACTION_INTERVAL);
+                if due {
+                    *last = Some(Instant::now());
+                }
+                due
+            };
+            async move {
+                let report = run_once(&gctx, compact).await;
+                if !report.is_empty() {
+                    tracing::info!(
+                        "🧰 Peer maintenance: {} revived, {} failed, {} pruned, {} decayed, {} compacted",
+                        report.revived,
+                        report.failed,
+                        report.pruned,
+                        report.decayed,
+                        report.compacted
+                    );
+                }
+            }
+        },
+    );
+}
@@ -0,0 +1,153 @@
+//! 基于 SQLite 的服务器列表持久化
+//!
+//! 内网 / 外网服务器记录存放在 `peers.db` 的 `peer_record` 表中，按 `(scope, last_seen)`
+//! 与 `(scope, score)` 建索引。保存时只写入内容有变化的记录、删除已移除的记录，
+//! 不再整体重写 JSON 文件；[`crate::peer_maintenance`] 定期清理长期未见的记录并回收空间。
+//!
+//! 首次启动时若表为空，会从旧的 `inner_server` / `external_server` JSON 导入。
+
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use aex::connection::protocol::Protocol;
+use dashmap::DashMap;
+use sea_orm::{Database, DatabaseConnection};
+
+use crate::db::defines::StoreFromConnection;
+use crate::db::entity::peer_record::store::PeerRecordStore;
+use crate::record::NodeRecord;
+
+/// 数据库文件名（位于存储目录下）
+pub const PEER_DB_FILE: &str = "peers.db";
+/// 超过该天数未见的记录在压缩时删除（默认值，见 `[peer_maintenance] prune_after_days`）
+pub const PEER_RETENTION_DAYS: i64 = 30;
+/// 压缩间隔
+pub const COMPACTION_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PeerScope {
+    Inner,
+    External,
+}
+
+impl PeerScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PeerScope::Inner => "inner",
+            PeerScope::External => "external",
+        }
+    }
+}
+
+pub type SharedPeerStore = Arc<PeerStore>;
+
+pub struct PeerStore {
+    db: DatabaseConnection,
+    /// 已落盘的记录内容，用于跳过未变化的记录
+    written: DashMap<(PeerScope, SocketAddr), String>,
+}
+
+impl PeerStore {
+    pub async fn open(path: &Path) -> anyhow::Result<Self> {
+        let url = format!("sqlite://{}?mode=rwc", path.to_string_lossy());
+        Self::connect(&url).await
+    }
+
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let db = Database::connect(url).await?;
+        PeerRecordStore::new(&db).ensure_schema().await?;
+        Ok(Self {
+            db,
+            written: DashMap::new(),
+        })
+    }
+
+    fn store(&self) -> PeerRecordStore<'_, DatabaseConnection> {
+        PeerRecordStore::new(&self.db)
+    }
+
+    /// 读取某一类记录；表为空且提供了旧数据时先导入
+    pub async fn load_or_import(
+        &self,
+        scope: PeerScope,
+        legacy: Option<HashSet<NodeRecord>>,
+    ) -> anyhow::Result<HashSet<NodeRecord>> {
+        if self.store().count(scope.as_str()).await? == 0 {
+            if let Some(legacy) = legacy {
+                tracing::info!(
+                    "Importing {} {} peer records into database",
+                    legacy.len(),
+                    scope.as_str()
+                );
+                self.save_changed(scope, &legacy).await?;
+                return Ok(legacy);
+            }
+        }
+        let records = self.store().load(scope.as_str()).await?;
+        for record in &records {
+            self.written
+                .insert((scope, record.endpoint), serde_json::to_string(record)?);
+        }
+        Ok(records)
+    }
+
+    /// 增量保存：只写入内容变化的记录，删除不再存在的记录；返回写入 + 删除的条数
+    pub async fn save_changed(
+        &self,
+        scope: PeerScope,
+        records: &HashSet<NodeRecord>,
+    ) -> anyhow::Result<usize> {
+        let mut changed = 0;
+        for record in records {
+            let data = serde_json::to_string(record)?;
+            let key = (scope, record.endpoint);
+            if self.written.get(&key).is_some_and(|old| *old == data) {
+                continue;
+            }
+            self.store().upsert(scope.as_str(), record).await?;
+            self.written.insert(key, data);
+            changed += 1;
+        }
+
+        let current: HashSet<SocketAddr> = records.iter().map(|r| r.endpoint).collect();
+        let removed: Vec<SocketAddr> = self
+            .written
+            .iter()
+            .filter(|e| e.key().0 == scope && !current.contains(&e.key().1))
+            .map(|e| e.key().1)
+            .collect();
+        for endpoint in removed {
+            self.store().remove(scope.as_str(), &endpoint).await?;
+            self.written.remove(&(scope, endpoint));
+            changed += 1;
+        }
+        Ok(changed)
+    }
+
+    /// 评分最高的 `limit` 个可用记录，可按协议过滤
+    pub async fn best(
+        &self,
+        scope: PeerScope,
+        limit: u64,
+        protocol: Option<&Protocol>,
+    ) -> anyhow::Result<Vec<NodeRecord>> {
+        self.store().best(scope.as_str(), limit, protocol).await
+    }
+
+    /// 最近见过的 `limit` 个记录
+    pub async fn recent(&self, scope: PeerScope, limit: u64) -> anyhow::Result<Vec<NodeRecord>> {
+        self.store().recent(scope.as_str(), limit).await
+    }
+
+    /// 删除超过 `retention_days` 天未见的记录并回收空间
+    pub async fn compact(&self, retention_days: i64) -> anyhow::Result<u64> {
+        let before = (chrono::Utc::now() - chrono::Duration::days(retention_days)).timestamp();
+        let deleted = self.store().compact(before).await?;
+        // 被删除的记录下次保存时若仍在内存中会重新写入
+        self.written.clear();
+        Ok(deleted)
+    }
+}
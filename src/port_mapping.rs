@@ -0,0 +1,457 @@
+//! 自动端口映射（`--port-mapping`）
+//!
+//! 家庭网络中的节点通常无法接受入站连接。启用后，节点启动时向网关申请把监听的 TCP 端口与
+//! 通话媒体 UDP 端口映射到公网：先尝试 NAT-PMP（RFC 6886，向默认网关的 5351 端口发 UDP 请求），
+//! 不支持时再通过 SSDP 查找 UPnP IGD 并调用 `AddPortMapping`。租约过半时续期，失败时每
+//! `MAPPING_RETRY_SECS` 秒重试；退出时删除映射。
+//!
+//! 映射得到的公网 IP 与观测地址一样并入 Online 公告的 `wan_ips`，TCP 映射端点交给
+//! [`crate::endpoint_verifier`] 自拨号验证，通过后作为本节点的 seed 公告出去。
+
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use aex::{connection::global::GlobalContext, time::SystemTime};
+use igd_next::{
+    PortMappingProtocol, SearchOptions,
+    aio::{Gateway, tokio::Tokio},
+};
+use serde::Serialize;
+use tokio::net::UdpSocket;
+
+use crate::ip_scope;
+
+/// 申请的租约时长
+pub const MAPPING_LEASE_SECS: u32 = 60 * 60;
+/// 映射失败后的重试间隔
+pub const MAPPING_RETRY_SECS: u64 = 5 * 60;
+/// NAT-PMP 服务端口
+pub const NATPMP_PORT: u16 = 5351;
+/// NAT-PMP 请求的重发次数（首次等待 250ms，之后每次翻倍）
+pub const NATPMP_ATTEMPTS: u32 = 4;
+/// UPnP 网关搜索超时
+pub const UPNP_SEARCH_TIMEOUT_MS: u64 = 3_000;
+/// UPnP 映射描述
+pub const MAPPING_DESCRIPTION: &str = "zz-p2p";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MappingProtocol {
+    Tcp,
+    Udp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MappingMethod {
+    NatPmp,
+    Upnp,
+}
+
+/// 一条已建立的端口映射
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PortMapping {
+    pub protocol: MappingProtocol,
+    pub internal_port: u16,
+    /// 网关的公网 IP 与映射端口
+    pub external: SocketAddr,
+    pub method: MappingMethod,
+    pub lease_secs: u32,
+    /// 租约到期时间（毫秒）
+    pub expires_at: u128,
+}
+
+impl PortMapping {
+    /// 是否该续期：租约剩余不足一半
+    pub fn needs_renewal(&self, now: u128) -> bool {
+        let half = u128::from(self.lease_secs) * 500;
+        self.expires_at.saturating_sub(now) <= half
+    }
+
+    /// 公网 IP 是否可以公告（双层 NAT 时网关拿到的仍是内网地址）
+    pub fn is_public(&self) -> bool {
+        !ip_scope::is_inner_ip(&self.external.ip()) && !self.external.ip().is_unspecified()
+    }
+}
+
+/// 保存在 GlobalContext 中的映射表
+pub type PortMappings = Arc<Mutex<Vec<PortMapping>>>;
+
+fn lock(mappings: &PortMappings) -> std::sync::MutexGuard<'_, Vec<PortMapping>> {
+    match mappings.lock() {
+        Ok(g) => g,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// NAT-PMP 报文
+// ---------------------------------------------------------------------------
+
+/// NAT-PMP 响应中的错误码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NatPmpError {
+    UnsupportedVersion,
+    NotAuthorized,
+    NetworkFailure,
+    OutOfResources,
+    UnsupportedOpcode,
+    Unknown(u16),
+}
+
+impl NatPmpError {
+    fn from_code(code: u16) -> Self {
+        match code {
+            1 => Self::UnsupportedVersion,
+            2 => Self::NotAuthorized,
+            3 => Self::NetworkFailure,
+            4 => Self::OutOfResources,
+            5 => Self::UnsupportedOpcode,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+impl std::fmt::Display for NatPmpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NAT-PMP error: {:?}", self)
+    }
+}
+
+impl std::error::Error for NatPmpError {}
+
+fn natpmp_opcode(protocol: MappingProtocol) -> u8 {
+    match protocol {
+        MappingProtocol::Udp => 1,
+        MappingProtocol::Tcp => 2,
+    }
+}
+
+/// 公网地址请求
+pub fn natpmp_external_request() -> [u8; 2] {
+    [0, 0]
+}
+
+/// 映射请求；`lifetime` 为 0 时删除映射
+pub fn natpmp_mapping_request(
+    protocol: MappingProtocol,
+    internal_port: u16,
+    suggested_port: u16,
+    lifetime: u32,
+) -> [u8; 12] {
+    let mut buf = [0u8; 12];
+    buf[1] = natpmp_opcode(protocol);
+    buf[4..6].copy_from_slice(&internal_port.to_be_bytes());
+    buf[6..8].copy_from_slice(&suggested_port.to_be_bytes());
+    buf[8..12].copy_from_slice(&lifetime.to_be_bytes());
+    buf
+}
+
+fn natpmp_header(buf: &[u8], opcode: u8, len: usize) -> anyhow::Result<()> {
+    if buf.len() < len {
+        return Err(anyhow::anyhow!(
+            "NAT-PMP response too short: {} bytes",
+            buf.len()
+        ));
+    }
+    if buf[0] != 0 || buf[1] != 128 + opcode {
+        return Err(anyhow::anyhow!(
+            "Unexpected NAT-PMP response version {} opcode {}",
+            buf[0],
+            buf[1]
+        ));
+    }
+    match u16::from_be_bytes([buf[2], buf[3]]) {
+        0 => Ok(()),
+        code => Err(NatPmpError::from_code(code).into()),
+    }
+}
+
+/// 解析公网地址响应
+pub fn natpmp_parse_external(buf: &[u8]) -> anyhow::Result<Ipv4Addr> {
+    natpmp_header(buf, 0, 12)?;
+    Ok(Ipv4Addr::new(buf[8], buf[9], buf[10], buf[11]))
+}
+
+/// 解析映射响应，返回 (内部端口, 映射端口, 租约秒数)
+pub fn natpmp_parse_mapping(
+    buf: &[u8],
+    protocol: MappingProtocol,
+) -> anyhow::Result<(u16, u16, u32)> {
+    natpmp_header(buf, natpmp_opcode(protocol), 16)?;
+    Ok((
+        u16::from_be_bytes([buf[8], buf[9]]),
+        u16::from_be_bytes([buf[10], buf[11]]),
+        u32::from_be_bytes([buf[12], buf[13], buf[14], buf[15]]),
+    ))
+}
+
+/// 从 `/proc/net/route` 的内容中找出 IPv4 默认网关
+pub fn parse_default_gateway(route_table: &str) -> Option<Ipv4Addr> {
+    route_table.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 3 || fields[1] != "00000000" {
+            return None;
+        }
+        let gateway = u32::from_str_radix(fields[2], 16).ok()?;
+        (gateway != 0).then(|| Ipv4Addr::from(gateway.to_le_bytes()))
+    })
+}
+
+/// 本机的 IPv4 默认网关（目前只支持 Linux）
+pub fn default_gateway() -> Option<Ipv4Addr> {
+    std::fs::read_to_string("/proc/net/route")
+        .ok()
+        .and_then(|table| parse_default_gateway(&table))
+}
+
+async fn natpmp_exchange(
+    socket: &UdpSocket,
+    gateway: SocketAddr,
+    request: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    let mut wait = Duration::from_millis(250);
+    let mut buf = [0u8; 64];
+    for _ in 0..NATPMP_ATTEMPTS {
+        socket.send_to(request, gateway).await?;
+        if let Ok(received) = tokio::time::timeout(wait, socket.recv_from(&mut buf)).await {
+            let (len, from) = received?;
+            if from.ip() == gateway.ip() {
+                return Ok(buf[..len].to_vec());
+            }
+        }
+        wait *= 2;
+    }
+    Err(anyhow::anyhow!("No NAT-PMP response from {}", gateway))
+}
+
+async fn natpmp_map(
+    gateway: Ipv4Addr,
+    protocol: MappingProtocol,
+    internal_port: u16,
+    lifetime: u32,
+) -> anyhow::Result<PortMapping> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    let gateway = SocketAddr::new(IpAddr::V4(gateway), NATPMP_PORT);
+    let external_ip = natpmp_parse_external(
+        &natpmp_exchange(&socket, gateway, &natpmp_external_request()).await?,
+    )?;
+    let request = natpmp_mapping_request(protocol, internal_port, internal_port, lifetime);
+    let (_, external_port, lease_secs) = natpmp_parse_mapping(
+        &natpmp_exchange(&socket, gateway, &request).await?,
+        protocol,
+    )?;
+    Ok(PortMapping {
+        protocol,
+        internal_port,
+        external: SocketAddr::new(IpAddr::V4(external_ip), external_port),
+        method: MappingMethod::NatPmp,
+        lease_secs,
+        expires_at: SystemTime::timestamp() + u128::from(lease_secs) * 1000,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// UPnP IGD
+// ---------------------------------------------------------------------------
+
+fn igd_protocol(protocol: MappingProtocol) -> PortMappingProtocol {
+    match protocol {
+        MappingProtocol::Tcp => PortMappingProtocol::TCP,
+        MappingProtocol::Udp => PortMappingProtocol::UDP,
+    }
+}
+
+async fn upnp_gateway() -> anyhow::Result<Gateway<Tokio>> {
+    let options = SearchOptions {
+        timeout: Some(Duration::from_millis(UPNP_SEARCH_TIMEOUT_MS)),
+        ..Default::default()
+    };
+    Ok(igd_next::aio::tokio::search_gateway(options).await?)
+}
+
+/// 本机面向 `gateway` 的局域网地址
+async fn local_ip_towards(gateway: SocketAddr) -> anyhow::Result<IpAddr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    socket.connect(gateway).await?;
+    Ok(socket.local_addr()?.ip())
+}
+
+async fn upnp_map(protocol: MappingProtocol, internal_port: u16) -> anyhow::Result<PortMapping> {
+    let gateway = upnp_gateway().await?;
+    let local = SocketAddr::new(local_ip_towards(gateway.addr).await?, internal_port);
+    gateway
+        .add_port(
+            igd_protocol(protocol),
+            internal_port,
+            local,
+            MAPPING_LEASE_SECS,
+            MAPPING_DESCRIPTION,
+        )
+        .await?;
+    let external_ip = gateway.get_external_ip().await?;
+    Ok(PortMapping {
+        protocol,
+        internal_port,
+        external: SocketAddr::new(external_ip, internal_port),
+        method: MappingMethod::Upnp,
+        lease_secs: MAPPING_LEASE_SECS,
+        expires_at: SystemTime::timestamp() + u128::from(MAPPING_LEASE_SECS) * 1000,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// 生命周期
+// ---------------------------------------------------------------------------
+
+/// 申请（或续期）一条映射：先 NAT-PMP，再 UPnP
+pub async fn map_port(
+    protocol: MappingProtocol,
+    internal_port: u16,
+) -> anyhow::Result<PortMapping> {
+    let natpmp = match default_gateway() {
+        Some(gateway) => natpmp_map(gateway, protocol, internal_port, MAPPING_LEASE_SECS).await,
+        None => Err(anyhow::anyhow!("No default gateway found")),
+    };
+    match natpmp {
+        Ok(mapping) => Ok(mapping),
+        Err(natpmp_err) => upnp_map(protocol, internal_port)
+            .await
+            .map_err(|e| e.context(format!("NAT-PMP also failed: {}", natpmp_err))),
+    }
+}
+
+/// 删除一条映射（尽力而为）
+pub async fn unmap(mapping: &PortMapping) -> anyhow::Result<()> {
+    match mapping.method {
+        MappingMethod::NatPmp => {
+            let gateway = default_gateway().ok_or_else(|| anyhow::anyhow!("No default gateway"))?;
+            let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+            let request = natpmp_mapping_request(mapping.protocol, mapping.internal_port, 0, 0);
+            let gateway = SocketAddr::new(IpAddr::V4(gateway), NATPMP_PORT);
+            natpmp_exchange(&socket, gateway, &request).await?;
+        }
+        MappingMethod::Upnp => {
+            upnp_gateway()
+                .await?
+                .remove_port(igd_protocol(mapping.protocol), mapping.external.port())
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// 当前未过期的映射
+pub async fn mappings(gctx: &Arc<GlobalContext>) -> Vec<PortMapping> {
+    let now = SystemTime::timestamp();
+    match gctx.get::<PortMappings>().await {
+        Some(m) => lock(&m)
+            .iter()
+            .filter(|m| m.expires_at > now)
+            .cloned()
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// 映射得到的可公告公网 IP
+pub async fn external_ips(gctx: &Arc<GlobalContext>) -> Vec<IpAddr> {
+    let mut ips: Vec<IpAddr> = Vec::new();
+    for mapping in mappings(gctx).await {
+        if mapping.is_public() && !ips.contains(&mapping.external.ip()) {
+            ips.push(mapping.external.ip());
+        }
+    }
+    ips
+}
+
+/// 映射得到的可公告 TCP 端点（供自拨号验证）
+pub async fn external_tcp_endpoints(gctx: &Arc<GlobalContext>) -> Vec<SocketAddr> {
+    mappings(gctx)
+        .await
+        .into_iter()
+        .filter(|m| m.protocol == MappingProtocol::Tcp && m.is_public())
+        .map(|m| m.external)
+        .collect()
+}
+
+fn store(mappings: &PortMappings, mapping: PortMapping) {
+    let mut guard = lock(mappings);
+    guard.retain(|m| !(m.protocol == mapping.protocol && m.internal_port == mapping.internal_port));
+    guard.push(mapping);
+}
+
+/// 后台申请并续期 `ports` 的映射
+pub fn spawn(
+    gctx: Arc<GlobalContext>,
+    ports: Vec<(MappingProtocol, u16)>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let table = match gctx.get::<PortMappings>().await {
+            Some(t) => t,
+            None => {
+                let t = PortMappings::default();
+                gctx.set(t.clone()).await;
+                t
+            }
+        };
+        loop {
+            let now = SystemTime::timestamp();
+            for (protocol, port) in &ports {
+                let current = lock(&table)
+                    .iter()
+                    .find(|m| m.protocol == *protocol && m.internal_port == *port)
+                    .cloned();
+                if current.as_ref().is_some_and(|m| !m.needs_renewal(now)) {
+                    continue;
+                }
+                match map_port(*protocol, *port).await {
+                    Ok(mapping) => {
+                        if current.as_ref().map(|m| m.external) != Some(mapping.external) {
+                            tracing::info!(
+                                "🚪 Mapped {:?} port {} to {} via {:?}",
+                                protocol,
+                                port,
+                                mapping.external,
+                                mapping.method
+                            );
+                        }
+                        store(&table, mapping);
+                    }
+                    Err(e) => {
+                        tracing::warn!("Port mapping for {:?} {} failed: {:#}", protocol, port, e)
+                    }
+                }
+            }
+
+            let next = lock(&table)
+                .iter()
+                .map(|m| u64::from(m.lease_secs / 2))
+                .min()
+                .unwrap_or(MAPPING_RETRY_SECS)
+                .clamp(30, MAPPING_RETRY_SECS);
+            tokio::time::sleep(Duration::from_secs(next)).await;
+        }
+    })
+}
+
+/// 删除所有映射（退出时调用）
+pub async fn release(gctx: &Arc<GlobalContext>) {
+    let Some(table) = gctx.get::<PortMappings>().await else {
+        return;
+    };
+    let current: Vec<PortMapping> = lock(&table).drain(..).collect();
+    for mapping in current {
+        match unmap(&mapping).await {
+            Ok(()) => tracing::info!("🚪 Removed port mapping {}", mapping.external),
+            Err(e) => tracing::warn!(
+                "Failed to remove port mapping {}: {:?}",
+                mapping.external,
+                e
+            ),
+        }
+    }
+}
@@ -0,0 +1,346 @@
+//! 节点访问控制（黑名单 / 白名单）
+//!
+//! 规则可以是节点地址，也可以是 IP 或 CIDR 网段（`10.0.0.0/8`、`fd00::/8`）。
+//! 黑名单中的规则总是生效；`allowlist` 模式下只接受命中白名单的节点。
+//! 规则保存在 `acl.json`，可通过 CLI（`ban` / `unban` / `allow` / `acl`）或控制接口修改，
+//! 修改后立即断开不再允许的已有连接。
+//!
+//! TCP accept 由 aex 完成，本模块在连接上的每一帧分发前检查对端 IP 与帧的签名地址：
+//! 新连接的第一帧（通常是握手）就会被检查，被拒绝的握手会先收到 `Busy` 再被关闭。
+//! 主动拨号前同样按 IP 检查。
+
+use std::{
+    collections::BTreeSet,
+    fmt,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use aex::connection::{context::Context, global::GlobalContext};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    io_storage::{IOStorage, STORAGE_ACL},
+    journal,
+    protocols::{
+        command::{Action, Entity, P2PCommand},
+        commands::busy::BusyCommand,
+        frame::P2PFrame,
+    },
+};
+
+/// 被拒绝的握手收到的重试间隔
+pub const ACL_RETRY_AFTER_SECS: u32 = 3600;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AclMode {
+    /// 只拒绝黑名单中的节点
+    #[default]
+    Blacklist,
+    /// 只接受白名单中的节点（黑名单仍然生效）
+    Allowlist,
+}
+
+impl FromStr for AclMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "blacklist" => Ok(AclMode::Blacklist),
+            "allowlist" => Ok(AclMode::Allowlist),
+            other => Err(format!("unknown acl mode: {}", other)),
+        }
+    }
+}
+
+/// 一条规则：节点地址或 IP 网段
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum AclTarget {
+    Address(String),
+    Network { ip: IpAddr, prefix: u8 },
+}
+
+impl AclTarget {
+    /// 解析规则：`a.b.c.d/n`、IP、`ip:port`（忽略端口）或节点地址
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err("empty acl target".to_string());
+        }
+        if let Some((ip, prefix)) = s.split_once('/') {
+            let ip: IpAddr = ip.parse().map_err(|_| format!("invalid network: {}", s))?;
+            let prefix: u8 = prefix
+                .parse()
+                .map_err(|_| format!("invalid prefix length: {}", s))?;
+            if prefix > max_prefix(&ip) {
+                return Err(format!("invalid prefix length: {}", s));
+            }
+            return Ok(AclTarget::Network {
+                ip: mask(ip, prefix),
+                prefix,
+            });
+        }
+        if let Ok(ip) = s.parse::<IpAddr>() {
+            return Ok(AclTarget::host(ip));
+        }
+        if let Ok(sock) = s.parse::<SocketAddr>() {
+            return Ok(AclTarget::host(sock.ip()));
+        }
+        Ok(AclTarget::Address(s.to_string()))
+    }
+
+    pub fn host(ip: IpAddr) -> Self {
+        AclTarget::Network {
+            prefix: max_prefix(&ip),
+            ip,
+        }
+    }
+
+    pub fn matches_ip(&self, ip: &IpAddr) -> bool {
+        match self {
+            AclTarget::Network { ip: net, prefix } => {
+                net.is_ipv4() == ip.is_ipv4() && mask(*ip, *prefix) == *net
+            }
+            AclTarget::Address(_) => false,
+        }
+    }
+
+    pub fn matches_address(&self, address: &str) -> bool {
+        matches!(self, AclTarget::Address(a) if a == address)
+    }
+}
+
+fn max_prefix(ip: &IpAddr) -> u8 {
+    if ip.is_ipv4() { 32 } else { 128 }
+}
+
+/// 保留前 `prefix` 位
+fn mask(ip: IpAddr, prefix: u8) -> IpAddr {
+    match ip {
+        IpAddr::V4(v4) => {
+            let bits = u32::from(v4);
+            let m = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            IpAddr::V4((bits & m).into())
+        }
+        IpAddr::V6(v6) => {
+            let bits = u128::from(v6);
+            let m = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            IpAddr::V6((bits & m).into())
+        }
+    }
+}
+
+impl fmt::Display for AclTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AclTarget::Address(a) => write!(f, "{}", a),
+            AclTarget::Network { ip, prefix } if *prefix == max_prefix(ip) => write!(f, "{}", ip),
+            AclTarget::Network { ip, prefix } => write!(f, "{}/{}", ip, prefix),
+        }
+    }
+}
+
+impl TryFrom<String> for AclTarget {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        AclTarget::parse(&s)
+    }
+}
+
+impl From<AclTarget> for String {
+    fn from(t: AclTarget) -> Self {
+        t.to_string()
+    }
+}
+
+/// 检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AclDecision {
+    Allow,
+    /// 命中黑名单
+    Banned,
+    /// 白名单模式下未命中白名单
+    NotAllowed,
+}
+
+/// 持久化的访问控制列表
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessList {
+    #[serde(default)]
+    pub mode: AclMode,
+    #[serde(default)]
+    pub banned: BTreeSet<AclTarget>,
+    #[serde(default)]
+    pub allowed: BTreeSet<AclTarget>,
+}
+
+impl AccessList {
+    /// 加入黑名单，返回是否为新规则
+    pub fn ban(&mut self, target: AclTarget) -> bool {
+        self.banned.insert(target)
+    }
+
+    pub fn unban(&mut self, target: &AclTarget) -> bool {
+        self.banned.remove(target)
+    }
+
+    /// 加入白名单，返回是否为新规则
+    pub fn allow(&mut self, target: AclTarget) -> bool {
+        self.allowed.insert(target)
+    }
+
+    pub fn disallow(&mut self, target: &AclTarget) -> bool {
+        self.allowed.remove(target)
+    }
+
+    /// 检查对端；握手前还不知道节点地址时 `address` 为 `None`，
+    /// 此时白名单模式下只要还可能按地址命中就暂时放行
+    pub fn check(&self, ip: Option<IpAddr>, address: Option<&str>) -> AclDecision {
+        let hit = |t: &AclTarget| {
+            ip.is_some_and(|ip| t.matches_ip(&ip)) || address.is_some_and(|a| t.matches_address(a))
+        };
+        if self.banned.iter().any(hit) {
+            return AclDecision::Banned;
+        }
+        if self.mode == AclMode::Allowlist && !self.allowed.iter().any(hit) {
+            let pending = address.is_none()
+                && self
+                    .allowed
+                    .iter()
+                    .any(|t| matches!(t, AclTarget::Address(_)));
+            if !pending {
+                return AclDecision::NotAllowed;
+            }
+        }
+        AclDecision::Allow
+    }
+
+    pub fn permits(&self, ip: Option<IpAddr>, address: Option<&str>) -> bool {
+        self.check(ip, address) == AclDecision::Allow
+    }
+}
+
+/// 运行期共享的访问控制列表
+pub type SharedAccessList = Arc<RwLock<AccessList>>;
+
+fn read(acl: &SharedAccessList) -> std::sync::RwLockReadGuard<'_, AccessList> {
+    match acl.read() {
+        Ok(g) => g,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// 当前规则的快照；未初始化时为空
+pub async fn snapshot(gctx: &Arc<GlobalContext>) -> AccessList {
+    match gctx.get::<SharedAccessList>().await {
+        Some(acl) => read(&acl).clone(),
+        None => AccessList::default(),
+    }
+}
+
+/// 检查对端是否允许连接；未初始化时全部允许
+pub async fn permits(gctx: &Arc<GlobalContext>, ip: Option<IpAddr>, address: Option<&str>) -> bool {
+    match gctx.get::<SharedAccessList>().await {
+        Some(acl) => read(&acl).permits(ip, address),
+        None => true,
+    }
+}
+
+/// 修改规则、持久化并断开不再允许的连接，返回 `f` 的结果
+pub async fn update<R>(gctx: &Arc<GlobalContext>, f: impl FnOnce(&mut AccessList) -> R) -> R {
+    let acl = match gctx.get::<SharedAccessList>().await {
+        Some(acl) => acl,
+        None => {
+            let acl = SharedAccessList::default();
+            gctx.set(acl.clone()).await;
+            acl
+        }
+    };
+    let (result, current) = {
+        let mut guard = match acl.write() {
+            Ok(g) => g,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let result = f(&mut guard);
+        (result, guard.clone())
+    };
+    match gctx.get::<IOStorage>().await {
+        Some(ios) => ios.save::<AccessList>(&current, STORAGE_ACL).await,
+        None => tracing::error!("IOStorage not found in context, acl not persisted"),
+    }
+    enforce(gctx).await;
+    result
+}
+
+/// 断开所有不再允许的连接，返回断开的数量
+pub async fn enforce(gctx: &Arc<GlobalContext>) -> usize {
+    let acl = snapshot(gctx).await;
+    let mut entries = Vec::new();
+    for bucket_ref in gctx.manager.connections.iter() {
+        let bi_conn = bucket_ref.value();
+        for e in bi_conn.clients.iter() {
+            entries.push((e.value().clone(), true));
+        }
+        for e in bi_conn.servers.iter() {
+            entries.push((e.value().clone(), false));
+        }
+    }
+    let mut closed = 0;
+    for (entry, inbound) in entries {
+        let peer = match &entry.context {
+            Some(ctx) => ctx.lock().await.get::<String>(),
+            None => None,
+        };
+        if !acl.permits(Some(entry.addr.ip()), peer.as_deref()) {
+            tracing::info!("🚫 Closing connection {} (access denied)", entry.addr);
+            if let (Some(node), Some(peer)) =
+                (gctx.get::<Arc<crate::node::Node>>().await, peer.as_deref())
+            {
+                node.registry.disconnect(peer);
+            }
+            gctx.manager.remove(entry.addr, inbound);
+            journal::record_disconnect(gctx, peer.as_deref(), entry.addr, "access denied").await;
+            closed += 1;
+        }
+    }
+    closed
+}
+
+/// 帧分发前调用；返回 false 表示对端被拒绝、连接已关闭，不应再处理该帧
+pub async fn admit_frame(ctx: &Arc<Mutex<Context>>, frame: &P2PFrame, cmd: &P2PCommand) -> bool {
+    let (gctx, peer) = {
+        let guard = ctx.lock().await;
+        (guard.global.clone(), guard.addr)
+    };
+    if permits(&gctx, Some(peer.ip()), Some(&frame.body.address)).await {
+        return true;
+    }
+    tracing::warn!(
+        "🚫 Rejecting {} ({}): access denied",
+        peer,
+        frame.body.address
+    );
+    let handshake = cmd.entity == Entity::Node && cmd.action == Action::OnLine;
+    if handshake {
+        let busy = BusyCommand {
+            reason: "access denied".to_string(),
+            retry_after_secs: ACL_RETRY_AFTER_SECS,
+        };
+        let _ = P2PFrame::send(ctx.clone(), &Some(busy), Entity::Node, Action::Busy, false).await;
+        // 留一点时间让 Busy 写出后再关闭
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    // 被拒绝的握手一定来自入站连接；其它帧两个方向都可能
+    gctx.manager.remove(peer, true);
+    if !handshake {
+        gctx.manager.remove(peer, false);
+    }
+    journal::record_disconnect(&gctx, Some(&frame.body.address), peer, "access denied").await;
+    false
+}
@@ -0,0 +1,255 @@
+//! 握手中的软件版本与 User-Agent
+//!
+//! Online / OnlineAck 携带 [`SoftwareInfo`]：crate 版本、构建时的 git 提交与可配置的
+//! User-Agent。对端的信息保存在连接上（`conns`、`status` 显示），并记入服务器列表中该端点的
+//! 记录（`peers` 显示）。
+//!
+//! `[agent] min_version` 设置对端的最低软件版本，低于该版本或没有声明版本的对端按 `below_min`
+//! 处理：`warn`（默认）只记录告警，`refuse` 拒绝握手并断开连接。嵌入方还可以用 [`set_hook`]
+//! 注册自己的检查，两者中更严格的结果生效。
+//!
+//! ```toml
+//! [agent]
+//! user_agent = "my-app/1.2"
+//! min_version = "0.1.5"
+//! below_min = "refuse"
+//! ```
+
+use std::{fmt, net::SocketAddr, sync::Arc, time::Duration};
+
+use aex::connection::{context::Context, global::GlobalContext};
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    config::SharedConfig,
+    journal,
+    node::Node,
+    protocols::{
+        command::{Action, Entity},
+        commands::busy::BusyCommand,
+        frame::P2PFrame,
+    },
+    transport::Connection,
+};
+
+/// 本 crate 的版本
+pub const PACKAGE_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// 构建时的 git 提交（见 `build.rs`），不在 git 仓库中构建时为空
+pub const GIT_HASH: &str = env!("ZZ_P2P_GIT_HASH");
+/// 因版本过低被拒绝的对端建议多久之后再重试
+pub const REFUSED_RETRY_AFTER_SECS: u32 = 3600;
+
+/// 未配置时使用的 User-Agent
+pub fn default_user_agent() -> String {
+    format!("zz-p2p/{}", PACKAGE_VERSION)
+}
+
+/// 握手中声明的软件信息
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+#[serde(default)]
+pub struct SoftwareInfo {
+    /// crate 版本，例如 `0.1.5`
+    pub version: String,
+    /// 构建时的 git 提交，未知时为空
+    pub git_hash: String,
+    pub user_agent: String,
+}
+
+impl SoftwareInfo {
+    /// 本节点的软件信息
+    pub fn new(user_agent: impl Into<String>) -> Self {
+        Self {
+            version: PACKAGE_VERSION.to_string(),
+            git_hash: GIT_HASH.to_string(),
+            user_agent: user_agent.into(),
+        }
+    }
+}
+
+impl fmt::Display for SoftwareInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let agent = if self.user_agent.is_empty() {
+            "unknown"
+        } else {
+            &self.user_agent
+        };
+        match (self.version.is_empty(), self.git_hash.is_empty()) {
+            (true, _) => write!(f, "{}", agent),
+            (false, true) => write!(f, "{} (v{})", agent, self.version),
+            (false, false) => write!(f, "{} (v{}+{})", agent, self.version, self.git_hash),
+        }
+    }
+}
+
+/// 解析 `major.minor.patch`：允许前缀 `v`，忽略 `-` / `+` 之后的部分，缺少的段视为 0
+pub fn parse_version(s: &str) -> Option<(u64, u64, u64)> {
+    let s = s.trim();
+    let s = s.strip_prefix('v').unwrap_or(s);
+    let core = s.split(['-', '+']).next()?;
+    if core.is_empty() {
+        return None;
+    }
+    let mut parts = [0u64; 3];
+    for (i, part) in core.split('.').enumerate() {
+        if i >= 3 {
+            return None;
+        }
+        parts[i] = part.parse().ok()?;
+    }
+    Some((parts[0], parts[1], parts[2]))
+}
+
+/// 对端版本低于 `min_version` 时的处理
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BelowMinAction {
+    #[default]
+    Warn,
+    Refuse,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AgentConfig {
+    /// 握手中发送的 User-Agent；为空时使用 `zz-p2p/<版本>`
+    pub user_agent: String,
+    /// 对端的最低软件版本，为空表示不检查
+    pub min_version: String,
+    pub below_min: BelowMinAction,
+}
+
+impl AgentConfig {
+    pub fn user_agent(&self) -> String {
+        if self.user_agent.trim().is_empty() {
+            default_user_agent()
+        } else {
+            self.user_agent.trim().to_string()
+        }
+    }
+}
+
+/// 对端软件信息的检查结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Accept,
+    Warn(String),
+    Refuse(String),
+}
+
+impl Verdict {
+    fn severity(&self) -> u8 {
+        match self {
+            Verdict::Accept => 0,
+            Verdict::Warn(_) => 1,
+            Verdict::Refuse(_) => 2,
+        }
+    }
+
+    /// 两者中更严格的结果
+    pub fn stricter(self, other: Verdict) -> Verdict {
+        if other.severity() > self.severity() {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+/// 按配置检查对端的软件版本；`min_version` 为空或无法解析时不检查
+pub fn check(config: &AgentConfig, info: &SoftwareInfo) -> Verdict {
+    let Some(min) = parse_version(&config.min_version) else {
+        return Verdict::Accept;
+    };
+    let reason = match parse_version(&info.version) {
+        Some(version) if version >= min => return Verdict::Accept,
+        Some(_) => format!(
+            "software version {} is below minimum {}",
+            info.version, config.min_version
+        ),
+        None => format!(
+            "software version not declared (minimum {})",
+            config.min_version
+        ),
+    };
+    match config.below_min {
+        BelowMinAction::Warn => Verdict::Warn(reason),
+        BelowMinAction::Refuse => Verdict::Refuse(reason),
+    }
+}
+
+/// 嵌入方注册的检查：参数为对端节点地址与其软件信息
+pub type AgentHook = Arc<dyn Fn(&str, &SoftwareInfo) -> Verdict + Send + Sync>;
+
+/// 注册自定义检查（替换之前注册的检查）
+pub async fn set_hook(gctx: &GlobalContext, hook: AgentHook) {
+    gctx.set(hook).await;
+}
+
+/// 连接上保存的对端软件信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerAgent(pub SoftwareInfo);
+
+pub async fn policy(gctx: &GlobalContext) -> AgentConfig {
+    match gctx.get::<SharedConfig>().await {
+        Some(config) => config.read().await.agent.clone(),
+        None => AgentConfig::default(),
+    }
+}
+
+/// 本节点在握手中声明的软件信息
+pub async fn local(gctx: &GlobalContext) -> SoftwareInfo {
+    SoftwareInfo::new(policy(gctx).await.user_agent())
+}
+
+/// 握手时调用：保存对端的软件信息并按策略检查；返回 false 时握手已被拒绝、连接已关闭。
+/// `endpoint` 为对端在服务器列表中的地址，`inbound` 表示对端是发起方
+pub async fn admit(
+    ctx: &Arc<Mutex<Context>>,
+    peer: &str,
+    endpoint: SocketAddr,
+    info: SoftwareInfo,
+    inbound: bool,
+) -> bool {
+    let gctx = {
+        let mut guard = ctx.lock().await;
+        guard.set(PeerAgent(info.clone()));
+        guard.global.clone()
+    };
+    if let Some(node) = gctx.get::<Arc<Node>>().await {
+        for registry in [&node.inner, &node.external] {
+            registry.write().record_agent(endpoint, info.clone());
+        }
+    }
+
+    let mut verdict = check(&policy(&gctx).await, &info);
+    if let Some(hook) = gctx.get::<AgentHook>().await {
+        verdict = verdict.stricter(hook(peer, &info));
+    }
+    let reason = match verdict {
+        Verdict::Accept => return true,
+        Verdict::Warn(reason) => {
+            tracing::warn!("⚠️ Peer {} runs {}: {}", peer, info, reason);
+            return true;
+        }
+        Verdict::Refuse(reason) => reason,
+    };
+
+    tracing::warn!("🚫 Refusing peer {} ({}): {}", peer, info, reason);
+    journal::record_handshake_failed(ctx, peer, &reason).await;
+    if inbound {
+        let busy = BusyCommand {
+            reason: reason.clone(),
+            retry_after_secs: REFUSED_RETRY_AFTER_SECS,
+        };
+        let _ = P2PFrame::send(ctx.clone(), &Some(busy), Entity::Node, Action::Busy, false).await;
+        // 留一点时间让 Busy 写出后再关闭
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    ctx.close().await;
+    if let Some(node) = gctx.get::<Arc<Node>>().await {
+        node.registry.disconnect(peer);
+    }
+    false
+}
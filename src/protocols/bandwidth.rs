@@ -0,0 +1,321 @@
+//! 带宽限速
+//!
+//! 上行在 `P2PFrame::send` / 中继写出前、下行在路由分发到 handler 前，按帧长度向
+//! 全局与该连接各自的漏桶登记字节数；桶溢出时等待到水位回落，从而把平均速率压在
+//! `[bandwidth]` 配置的上限以内（0 表示不限）。限速值读取自 `SharedConfig`，可热更新。
+//! 同时按方向统计累计字节数与最近一秒的速率，供 `status` 与控制接口展示。
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use aex::connection::{context::Context, global::GlobalContext};
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+use crate::config::{BandwidthConfig, SharedConfig};
+
+/// 漏桶容量 = 速率 × 该时长，允许的短时突发
+pub const BURST_WINDOW: Duration = Duration::from_secs(1);
+/// 超过该时长没有流量的对端统计会被清理
+pub const PEER_IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+/// 帧头、签名等不计入 `data` 的开销估计
+pub const FRAME_OVERHEAD_BYTES: usize = 160;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Upload,
+    Download,
+}
+
+/// 漏桶：水位按速率匀速下降，登记的字节使水位上升，超过容量的部分需要等待
+#[derive(Debug, Clone)]
+pub struct LeakyBucket {
+    level: f64,
+    last: Instant,
+}
+
+impl LeakyBucket {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            level: 0.0,
+            last: now,
+        }
+    }
+
+    /// 登记 `bytes` 字节，返回需要等待的时长；`rate` 为 0 表示不限速
+    pub fn reserve(&mut self, bytes: usize, rate: u64, now: Instant) -> Duration {
+        if rate == 0 {
+            self.level = 0.0;
+            self.last = now;
+            return Duration::ZERO;
+        }
+        let rate = rate as f64;
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.level = (self.level - elapsed * rate).max(0.0) + bytes as f64;
+        self.last = now;
+
+        let capacity = rate * BURST_WINDOW.as_secs_f64();
+        if self.level <= capacity {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((self.level - capacity) / rate)
+        }
+    }
+}
+
+/// 累计字节数与最近一个完整秒的速率
+#[derive(Debug, Clone)]
+pub struct RateMeter {
+    total: u64,
+    window_start: Instant,
+    window_bytes: u64,
+    last_rate: u64,
+    last_activity: Instant,
+}
+
+impl RateMeter {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            total: 0,
+            window_start: now,
+            window_bytes: 0,
+            last_rate: 0,
+            last_activity: now,
+        }
+    }
+
+    fn roll(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed >= Duration::from_secs(2) {
+            // 上一个窗口之后整秒无流量
+            self.last_rate = 0;
+            self.window_bytes = 0;
+            self.window_start = now;
+        } else if elapsed >= Duration::from_secs(1) {
+            self.last_rate = self.window_bytes;
+            self.window_bytes = 0;
+            self.window_start = now;
+        }
+    }
+
+    pub fn record(&mut self, bytes: usize, now: Instant) {
+        self.roll(now);
+        self.total += bytes as u64;
+        self.window_bytes += bytes as u64;
+        self.last_activity = now;
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// 最近一秒的速率（字节/秒）
+    pub fn rate(&mut self, now: Instant) -> u64 {
+        self.roll(now);
+        self.last_rate
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Lane {
+    bucket: LeakyBucket,
+    meter: RateMeter,
+}
+
+impl Lane {
+    fn new(now: Instant) -> Self {
+        Self {
+            bucket: LeakyBucket::new(now),
+            meter: RateMeter::new(now),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct LanePair {
+    upload: Lane,
+    download: Lane,
+}
+
+impl LanePair {
+    fn new(now: Instant) -> Self {
+        Self {
+            upload: Lane::new(now),
+            download: Lane::new(now),
+        }
+    }
+
+    fn lane(&mut self, direction: Direction) -> &mut Lane {
+        match direction {
+            Direction::Upload => &mut self.upload,
+            Direction::Download => &mut self.download,
+        }
+    }
+
+    fn last_activity(&self) -> Instant {
+        self.upload
+            .meter
+            .last_activity
+            .max(self.download.meter.last_activity)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PeerUsage {
+    pub peer: String,
+    pub upload_bytes: u64,
+    pub download_bytes: u64,
+    pub upload_rate: u64,
+    pub download_rate: u64,
+}
+
+/// `status` / 控制接口展示的用量快照
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BandwidthUsage {
+    pub upload_bytes: u64,
+    pub download_bytes: u64,
+    pub upload_rate: u64,
+    pub download_rate: u64,
+    pub peers: Vec<PeerUsage>,
+}
+
+pub struct BandwidthState {
+    global: LanePair,
+    peers: HashMap<SocketAddr, LanePair>,
+}
+
+impl Default for BandwidthState {
+    fn default() -> Self {
+        Self {
+            global: LanePair::new(Instant::now()),
+            peers: HashMap::new(),
+        }
+    }
+}
+
+impl BandwidthState {
+    /// 登记一帧流量，返回需要等待的时长（全局与单连接中较长者）
+    pub fn reserve(
+        &mut self,
+        policy: &BandwidthConfig,
+        peer: SocketAddr,
+        direction: Direction,
+        bytes: usize,
+        now: Instant,
+    ) -> Duration {
+        let (global_rate, peer_rate) = match direction {
+            Direction::Upload => (
+                policy.upload_bytes_per_sec,
+                policy.peer_upload_bytes_per_sec,
+            ),
+            Direction::Download => (
+                policy.download_bytes_per_sec,
+                policy.peer_download_bytes_per_sec,
+            ),
+        };
+
+        if !self.peers.contains_key(&peer) {
+            self.peers.retain(|_, lanes| {
+                now.saturating_duration_since(lanes.last_activity()) < PEER_IDLE_TIMEOUT
+            });
+        }
+        let peer_lane = self
+            .peers
+            .entry(peer)
+            .or_insert_with(|| LanePair::new(now))
+            .lane(direction);
+        peer_lane.meter.record(bytes, now);
+        let peer_wait = peer_lane.bucket.reserve(bytes, peer_rate, now);
+
+        let global_lane = self.global.lane(direction);
+        global_lane.meter.record(bytes, now);
+        let global_wait = global_lane.bucket.reserve(bytes, global_rate, now);
+
+        peer_wait.max(global_wait)
+    }
+
+    pub fn usage(&mut self, now: Instant) -> BandwidthUsage {
+        let mut peers: Vec<PeerUsage> = self
+            .peers
+            .iter_mut()
+            .map(|(peer, lanes)| PeerUsage {
+                peer: peer.to_string(),
+                upload_bytes: lanes.upload.meter.total(),
+                download_bytes: lanes.download.meter.total(),
+                upload_rate: lanes.upload.meter.rate(now),
+                download_rate: lanes.download.meter.rate(now),
+            })
+            .collect();
+        peers.sort_by(|a, b| a.peer.cmp(&b.peer));
+        BandwidthUsage {
+            upload_bytes: self.global.upload.meter.total(),
+            download_bytes: self.global.download.meter.total(),
+            upload_rate: self.global.upload.meter.rate(now),
+            download_rate: self.global.download.meter.rate(now),
+            peers,
+        }
+    }
+}
+
+/// 保存在 GlobalContext 中的限速与用量状态
+pub type Bandwidth = Arc<std::sync::Mutex<BandwidthState>>;
+
+/// 按配置限速：登记 `bytes` 字节并在需要时等待
+pub async fn throttle(
+    gctx: &Arc<GlobalContext>,
+    peer: SocketAddr,
+    direction: Direction,
+    bytes: usize,
+) {
+    let Some(bandwidth) = gctx.get::<Bandwidth>().await else {
+        return;
+    };
+    let policy = match gctx.get::<SharedConfig>().await {
+        Some(config) => config.read().await.bandwidth.clone(),
+        None => BandwidthConfig::default(),
+    };
+    let wait = {
+        let mut state = match bandwidth.lock() {
+            Ok(g) => g,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        state.reserve(&policy, peer, direction, bytes, Instant::now())
+    };
+    if !wait.is_zero() {
+        tracing::debug!("⏳ Throttling {:?} for {} by {:?}", direction, peer, wait);
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// 路由分发前的下行限速
+pub async fn throttle_inbound(ctx: &Arc<Mutex<Context>>, data_len: usize) {
+    let (gctx, peer) = {
+        let guard = ctx.lock().await;
+        (guard.global.clone(), guard.addr)
+    };
+    throttle(
+        &gctx,
+        peer,
+        Direction::Download,
+        data_len + FRAME_OVERHEAD_BYTES,
+    )
+    .await;
+}
+
+/// 当前用量快照；未初始化时为空
+pub async fn usage(gctx: &Arc<GlobalContext>) -> BandwidthUsage {
+    match gctx.get::<Bandwidth>().await {
+        Some(bandwidth) => {
+            let mut state = match bandwidth.lock() {
+                Ok(g) => g,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            state.usage(Instant::now())
+        }
+        None => BandwidthUsage::default(),
+    }
+}
@@ -0,0 +1,268 @@
+//! 并发广播
+//!
+//! 向多个连接发送时，每个连接的发送放进 `buffer_unordered`，同时最多
+//! `BROADCAST_CONCURRENCY` 个，单次发送限时（`[network.timeouts] forward_ms`，默认
+//! `BROADCAST_SEND_TIMEOUT_MS`），卡住的对端不会拖住其它对端。每个对端的结果记入 [`PeerReachability`]：
+//! 连续失败会降低该连接在 `limits` 中的评分，连接数满时优先被淘汰。
+
+use std::{collections::HashSet, net::SocketAddr, sync::Arc};
+
+use aex::connection::{context::Context, entry::ConnectionEntry, global::GlobalContext};
+use bytes::Bytes;
+use dashmap::DashMap;
+use futures::{StreamExt, stream};
+use tokio::sync::Mutex;
+
+use crate::capture;
+use crate::protocols::bandwidth::{self, Direction};
+use crate::protocols::commands::fragment;
+use crate::protocols::lanes::{self, Lane};
+use crate::protocols::peer_stats;
+use crate::retry::{self, Operation};
+use crate::transport::Connection;
+
+/// 同时进行的发送数上限
+pub const BROADCAST_CONCURRENCY: usize = 16;
+/// 单个对端的默认发送超时
+pub const BROADCAST_SEND_TIMEOUT_MS: u64 = 5_000;
+/// 每次连续失败扣除的连接评分
+pub const REACHABILITY_PENALTY: i64 = 5;
+
+/// 一个发送目标
+#[derive(Clone)]
+pub struct Target {
+    pub addr: SocketAddr,
+    pub ctx: Arc<Mutex<Context>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendOutcome {
+    Sent,
+    Failed(String),
+    TimedOut,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerResult {
+    pub addr: SocketAddr,
+    pub outcome: SendOutcome,
+}
+
+/// 一次广播中每个对端的结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BroadcastReport {
+    pub results: Vec<PeerResult>,
+}
+
+impl BroadcastReport {
+    pub fn sent(&self) -> usize {
+        self.count(|o| *o == SendOutcome::Sent)
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(|o| matches!(o, SendOutcome::Failed(_)))
+    }
+
+    pub fn timed_out(&self) -> usize {
+        self.count(|o| *o == SendOutcome::TimedOut)
+    }
+
+    fn count(&self, f: impl Fn(&SendOutcome) -> bool) -> usize {
+        self.results.iter().filter(|r| f(&r.outcome)).count()
+    }
+
+    /// 记录汇总日志，失败的对端逐个记录
+    pub fn log(&self, what: &str) {
+        for r in &self.results {
+            match &r.outcome {
+                SendOutcome::Sent => {}
+                SendOutcome::Failed(e) => {
+                    tracing::warn!("  ❌ {} to {} failed: {}", what, r.addr, e)
+                }
+                SendOutcome::TimedOut => tracing::warn!("  ⏱️ {} to {} timed out", what, r.addr),
+            }
+        }
+        tracing::debug!(
+            "{}: {} sent, {} failed, {} timed out",
+            what,
+            self.sent(),
+            self.failed(),
+            self.timed_out()
+        );
+    }
+}
+
+/// 对端的发送成功率
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Reachability {
+    pub successes: u64,
+    pub failures: u64,
+    pub consecutive_failures: u32,
+}
+
+impl Reachability {
+    pub fn record(&mut self, ok: bool) {
+        if ok {
+            self.successes += 1;
+            self.consecutive_failures = 0;
+        } else {
+            self.failures += 1;
+            self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        }
+    }
+
+    /// 成功次数占总次数的比例，无记录时为 1
+    pub fn score(&self) -> f64 {
+        let total = self.successes + self.failures;
+        if total == 0 {
+            return 1.0;
+        }
+        self.successes as f64 / total as f64
+    }
+}
+
+/// 每个连接的发送成功率
+pub type PeerReachability = Arc<DashMap<SocketAddr, Reachability>>;
+
+/// 把结果记入可达性统计
+pub fn record(reachability: &PeerReachability, results: &[PeerResult]) {
+    for r in results {
+        reachability
+            .entry(r.addr)
+            .or_default()
+            .record(r.outcome == SendOutcome::Sent);
+    }
+}
+
+/// 连接评分的扣分：连续失败次数 × `REACHABILITY_PENALTY`
+pub fn penalty(reachability: &PeerReachability, addr: &SocketAddr) -> i64 {
+    reachability
+        .get(addr)
+        .map(|r| r.consecutive_failures as i64 * REACHABILITY_PENALTY)
+        .unwrap_or(0)
+}
+
+/// 所有带 Context 的连接
+pub fn all_peers(entries: Vec<Arc<ConnectionEntry>>) -> Vec<Target> {
+    entries
+        .into_iter()
+        .filter_map(|e| e.context.clone().map(|ctx| Target { addr: e.addr, ctx }))
+        .collect()
+}
+
+/// 每个节点只取一条连接，并排除来源连接与 `skip_node` 节点
+pub async fn unique_peers(
+    entries: Vec<Arc<ConnectionEntry>>,
+    origin: Option<&Arc<Mutex<Context>>>,
+    skip_node: Option<&str>,
+) -> Vec<Target> {
+    let mut seen_nodes = HashSet::new();
+    let mut out = Vec::new();
+    for entry in entries {
+        let Some(ctx) = entry.context.clone() else {
+            continue;
+        };
+        if origin.is_some_and(|o| Arc::ptr_eq(o, &ctx)) {
+            continue;
+        }
+        let node = entry.node.read().await;
+        if let Some(n) = node.as_ref() {
+            let nid = String::from_utf8_lossy(&n.id).to_string();
+            if skip_node == Some(nid.as_str()) || !seen_nodes.insert(nid) {
+                continue;
+            }
+        }
+        out.push(Target {
+            addr: entry.addr,
+            ctx,
+        });
+    }
+    out
+}
+
+/// 并发地对每个目标执行 `send`，收集结果并更新可达性统计
+pub async fn send_all<F, Fut>(
+    gctx: &Arc<GlobalContext>,
+    targets: Vec<Target>,
+    send: F,
+) -> BroadcastReport
+where
+    F: Fn(Arc<Mutex<Context>>) -> Fut,
+    Fut: Future<Output = anyhow::Result<()>>,
+{
+    let limit = retry::policy(gctx, Operation::Forward).await.timeout;
+    let results: Vec<PeerResult> = stream::iter(targets)
+        .map(|target| {
+            let fut = send(target.ctx);
+            async move {
+                let result = match limit {
+                    Some(limit) => tokio::time::timeout(limit, fut).await.ok(),
+                    None => Some(fut.await),
+                };
+                let outcome = match result {
+                    Some(Ok(())) => SendOutcome::Sent,
+                    Some(Err(e)) => SendOutcome::Failed(e.to_string()),
+                    None => SendOutcome::TimedOut,
+                };
+                PeerResult {
+                    addr: target.addr,
+                    outcome,
+                }
+            }
+        })
+        .buffer_unordered(BROADCAST_CONCURRENCY)
+        .collect()
+        .await;
+    if let Some(reachability) = gctx.get::<PeerReachability>().await {
+        record(&reachability, &results);
+    }
+    BroadcastReport { results }
+}
+
+/// 按 `lane` 排队，向连接写入已编码的帧（计入上行带宽）
+pub async fn write_bytes(
+    ctx: &Arc<Mutex<Context>>,
+    bytes: &[u8],
+    lane: Lane,
+) -> anyhow::Result<()> {
+    let (gctx, peer) = {
+        let guard = ctx.lock().await;
+        (guard.global.clone(), guard.addr)
+    };
+    capture::outbound_bytes(&gctx, peer, bytes).await;
+    // 预先编码的大帧（如中继转发）按该连接分片
+    let chunks = fragment::fragment_for(ctx, bytes).await?;
+    let chunks: Vec<&[u8]> = match &chunks {
+        Some(chunks) => chunks.iter().map(|c| c.as_slice()).collect(),
+        None => vec![bytes],
+    };
+    let lane = lane.fragmented(chunks.len() > 1);
+    let lanes = lanes::for_connection(ctx).await;
+    let total: usize = chunks.iter().map(|c| c.len()).sum();
+    let started = std::time::Instant::now();
+    bandwidth::throttle(&gctx, peer, Direction::Upload, total).await;
+    let result = async {
+        for chunk in chunks {
+            let _permit = lanes.acquire(lane).await;
+            ctx.send(chunk).await?;
+        }
+        Ok(())
+    }
+    .await;
+    peer_stats::record_sent(&gctx, peer, started.elapsed(), result.is_ok()).await;
+    result
+}
+
+/// 并发地向每个目标写入同一段字节；各目标共享同一块缓冲区，不逐个复制
+pub async fn write_all(
+    gctx: &Arc<GlobalContext>,
+    targets: Vec<Target>,
+    bytes: Bytes,
+    lane: Lane,
+) -> BroadcastReport {
+    send_all(gctx, targets, |ctx| {
+        let bytes = bytes.clone();
+        async move { write_bytes(&ctx, &bytes, lane).await }
+    })
+    .await
+}
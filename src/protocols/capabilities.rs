@@ -0,0 +1,134 @@
+//! 对端能力声明
+//!
+//! 握手时 `OnlineCommand` / `OnlineAckCommand` 的 `capabilities` 除压缩与线路格式外，
+//! 还声明节点支持的特性：
+//!
+//! - 中继（`CAP_RELAY`）、接收文件（`CAP_FILE_TRANSFER`）、流式传输（`CAP_STREAM`）；
+//! - 主题订阅（`CAP_PUBSUB`）、名称解析（`CAP_NAMING`）、在线状态（`CAP_PRESENCE`）；
+//! - HTTP 隧道（`CAP_HTTP_TUNNEL`）、种子列表增量同步（`CAP_SEED_DELTA`）；
+//! - 加密群聊（`CAP_GROUPS`）、端到端加密消息（`CAP_SEALED`）、身份继任记录（`CAP_SUCCESSOR`）
+//!   与加密链路（`CAP_SECURE_LINK`）。
+//!
+//! `max_frame_size` 声明可接收的最大帧。
+//!
+//! 结果保存在连接 Context 中（`PeerCapabilities` / `PeerMaxFrameSize`）：发送方据此跳过
+//! 对端无法处理的命令，超过对端帧长上限的帧会被分片，`conns` 命令与 `GET /connections`
+//! 显示对端支持的特性。握手完成前能力未知，按支持处理。
+
+use std::sync::Arc;
+
+use aex::connection::context::Context;
+use tokio::sync::Mutex;
+
+use crate::protocols::{
+    commands::fragment::FRAGMENT_THRESHOLD,
+    compression::{CAP_COMPRESSION_LZ4, PeerCapabilities},
+    wire_format::{CAP_FORMAT_CBOR, CAP_FORMAT_JSON},
+};
+
+/// 为其他节点中继帧
+pub const CAP_RELAY: u32 = 1 << 3;
+/// 接收二进制消息（文件传输）
+pub const CAP_FILE_TRANSFER: u32 = 1 << 4;
+/// 参与主题订阅与发布
+pub const CAP_PUBSUB: u32 = 1 << 5;
+/// 传播与应答名称记录
+pub const CAP_NAMING: u32 = 1 << 6;
+/// 缓存与传播在线状态记录
+pub const CAP_PRESENCE: u32 = 1 << 7;
+/// 接收 HTTP 隧道请求
+pub const CAP_HTTP_TUNNEL: u32 = 1 << 8;
+/// 接收流式传输
+pub const CAP_STREAM: u32 = 1 << 9;
+/// 以增量方式同步种子列表
+pub const CAP_SEED_DELTA: u32 = 1 << 10;
+/// 接收加密群聊命令
+pub const CAP_GROUPS: u32 = 1 << 11;
+/// 接收端到端加密的点对点消息
+pub const CAP_SEALED: u32 = 1 << 12;
+/// 认可与传播身份继任记录
+pub const CAP_SUCCESSOR: u32 = 1 << 13;
+/// 支持链路加密握手，见 [`crate::secure_link`]
+pub const CAP_SECURE_LINK: u32 = 1 << 14;
+/// 本节点声明的特性位
+pub const LOCAL_FEATURES: u32 = CAP_RELAY
+    | CAP_FILE_TRANSFER
+    | CAP_PUBSUB
+    | CAP_NAMING
+    | CAP_PRESENCE
+    | CAP_HTTP_TUNNEL
+    | CAP_STREAM
+    | CAP_SEED_DELTA
+    | CAP_GROUPS
+    | CAP_SEALED
+    | CAP_SUCCESSOR
+    | CAP_SECURE_LINK;
+
+/// 本节点声明可接收的最大帧
+pub const LOCAL_MAX_FRAME_SIZE: u32 = FRAGMENT_THRESHOLD as u32;
+/// 对端声明的帧长上限低于该值时按该值处理
+pub const MIN_MAX_FRAME_SIZE: u32 = 16 * 1024;
+
+const FEATURE_NAMES: &[(u32, &str)] = &[
+    (CAP_COMPRESSION_LZ4, "lz4"),
+    (CAP_FORMAT_CBOR, "cbor"),
+    (CAP_FORMAT_JSON, "json"),
+    (CAP_RELAY, "relay"),
+    (CAP_FILE_TRANSFER, "file-transfer"),
+    (CAP_PUBSUB, "pubsub"),
+    (CAP_NAMING, "naming"),
+    (CAP_PRESENCE, "presence"),
+    (CAP_HTTP_TUNNEL, "http-tunnel"),
+    (CAP_STREAM, "stream"),
+    (CAP_SEED_DELTA, "seed-delta"),
+    (CAP_GROUPS, "groups"),
+    (CAP_SEALED, "sealed"),
+    (CAP_SUCCESSOR, "successor"),
+    (CAP_SECURE_LINK, "secure-link"),
+];
+
+/// 能力位对应的特性名，未知的位被忽略
+pub fn feature_names(bits: u32) -> Vec<&'static str> {
+    FEATURE_NAMES
+        .iter()
+        .filter(|(bit, _)| bits & bit != 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+impl PeerCapabilities {
+    pub fn supports(&self, capability: u32) -> bool {
+        self.0 & capability == capability
+    }
+
+    pub fn features(&self) -> Vec<&'static str> {
+        feature_names(self.0)
+    }
+}
+
+/// 对端在握手中声明的最大帧长度，保存在连接 Context 中
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerMaxFrameSize(pub u32);
+
+impl PeerMaxFrameSize {
+    /// 0 表示对端未声明（旧版本节点）
+    pub fn from_declared(declared: u32) -> Option<Self> {
+        (declared != 0).then(|| PeerMaxFrameSize(declared.max(MIN_MAX_FRAME_SIZE)))
+    }
+}
+
+/// 保存握手中声明的能力
+pub fn store(guard: &mut Context, capabilities: u32, max_frame_size: u32) {
+    guard.set(PeerCapabilities(capabilities));
+    if let Some(max) = PeerMaxFrameSize::from_declared(max_frame_size) {
+        guard.set(max);
+    }
+}
+
+/// 该连接的对端是否支持 `capability`；握手尚未完成时视为支持
+pub async fn peer_supports(ctx: &Arc<Mutex<Context>>, capability: u32) -> bool {
+    match ctx.lock().await.get::<PeerCapabilities>() {
+        Some(caps) => caps.supports(capability),
+        None => true,
+    }
+}
@@ -9,6 +9,13 @@ pub enum Entity {
     Witness,
     Telephone,
     File,
+    Topic,
+    Name,
+    Presence,
+    Http,
+    Stream,
+    Group,
+    Error,
 }
 
 #[derive(Debug, Clone, Copy, Deserialize, Serialize, Hash, PartialEq, Eq, Encode, Decode)]
@@ -51,6 +58,66 @@ pub enum Action {
     HangUp,
     Accept,
     Reject,
+
+    // Liveness Actions
+    Ping,
+    Pong,
+
+    // Topic Actions
+    Subscribe,
+    Unsubscribe,
+    Publish,
+
+    // Session Actions
+    Rekey,
+    RekeyAck,
+
+    // Connection Actions
+    Busy,
+    ObservedAddress,
+
+    // Identity Actions
+    IdentityChallenge,
+    IdentityProof,
+
+    // Transport Actions
+    Fragment,
+
+    // Naming Actions
+    NamePublish,
+    NameQuery,
+    NameAnswer,
+
+    // Presence Actions
+    PresenceAnnounce,
+
+    // HTTP Tunnel Actions
+    HttpRequest,
+    HttpResponse,
+
+    // Stream Actions
+    StreamOpen,
+    StreamData,
+    StreamWindow,
+    StreamClose,
+
+    // Seed delta Actions
+    SeedsDelta,
+    SeedsResync,
+
+    // Group Actions
+    GroupInvite,
+    GroupJoin,
+    GroupLeave,
+    GroupUpdate,
+    GroupKey,
+    GroupMessage,
+
+    // Error Actions
+    Error,
+
+    // Identity rotation Actions
+    IdentitySuccessor,
 }
 
 #[derive(Clone, PartialEq, Serialize, Deserialize, Encode, Decode, Debug)]
@@ -2,23 +2,42 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 
 use aex::{
-    connection::{context::Context, node::Node as AexNode, scope::NetworkScope},
+    connection::{context::Context, global::GlobalContext, node::Node as AexNode},
     tcp::types::Codec,
 };
 use bincode::{Decode, Encode};
+use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use tokio::io::AsyncWriteExt;
 use tokio::sync::Mutex;
 use zz_account::address::FreeWebMovementAddress;
 
+use crate::endpoint_verifier;
+use crate::ip_scope;
+use crate::journal;
 use crate::node::Node;
+use crate::peer_capabilities::{self, CapabilityProfile};
+use crate::protocols::agent::{self, SoftwareInfo};
+use crate::protocols::capabilities::{self, LOCAL_MAX_FRAME_SIZE};
 use crate::protocols::commands::online::OnlineCommand;
+use crate::protocols::commands::rekey::{SessionTable, record_established, reset_keys};
+use crate::protocols::commands::seed_delta::{self, SharedSeedDeltas};
+use crate::protocols::commands::{identity, presence};
+use crate::protocols::compression::LOCAL_CAPABILITIES;
+use crate::protocols::version::{CURRENT_PROTOCOL_VERSION, negotiate_version};
+use crate::protocols::wire_format::{PeerWireFormat, WireFormat};
 use crate::protocols::{
+    acl, broadcast,
     command::P2PCommand,
     command::{Action, Entity},
+    error::{self, ProtocolError},
     frame::P2PFrame,
+    lanes::Lane,
+    limits, privacy,
+    routing::{self, RoutingTable},
 };
+use crate::proxy;
+use crate::transport::Connection;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
 pub struct SeedRecord {
@@ -97,6 +116,15 @@ pub struct OnlineAckCommand {
     pub intranet_ips: Vec<String>,
     pub wan_ips: Vec<String>,
     pub seeds: Option<SeedsCommand>,
+    /// 能力位，见 `protocols::compression` 与 `protocols::capabilities`
+    pub capabilities: u32,
+    /// 本端支持的最高协议版本，见 `protocols::version`
+    pub protocol_version: u8,
+    /// 本端可接收的最大帧长度，0 表示未声明
+    pub max_frame_size: u32,
+    /// 本端的软件版本与 User-Agent，见 `protocols::agent`
+    #[serde(default)]
+    pub agent: SoftwareInfo,
 }
 
 impl Codec for OnlineAckCommand {}
@@ -109,10 +137,15 @@ pub async fn onlineack_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P
     );
 
     tracing::info!("received ack: {:?}", cmd.data);
-    let ack: OnlineAckCommand = match Codec::decode(&cmd.data) {
+    let ack: OnlineAckCommand = match frame.decode_payload(&cmd.data) {
         Ok(cmd) => cmd,
         Err(e) => {
-            tracing::error!("❌ decode OnlineAckCommand failed: {e}");
+            error::report(
+                &ctx,
+                &frame.body.address,
+                ProtocolError::decode("OnlineAckCommand", e),
+            )
+            .await;
             return;
         }
     };
@@ -161,7 +194,7 @@ pub async fn onlineack_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P
     );
     if !is_zero_key {
         let guard = psk.lock().await;
-        match guard
+        let established = match guard
             .establish_ends(
                 ack.session_id.clone(),
                 peer_address.as_bytes().to_vec(),
@@ -170,16 +203,33 @@ pub async fn onlineack_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P
             )
             .await
         {
-            Ok(true) => tracing::info!("🔑 establish_ends OK for address='{}'", local_address),
-            Ok(false) => tracing::warn!(
-                "⚠️ establish_ends FAILED (temp not found) for address='{}'",
-                local_address
-            ),
-            Err(e) => tracing::error!(
-                "❌ establish_ends error for address='{}': {:?}",
-                local_address,
-                e
-            ),
+            Ok(true) => {
+                tracing::info!("🔑 establish_ends OK for address='{}'", local_address);
+                true
+            }
+            Ok(false) => {
+                tracing::warn!(
+                    "⚠️ establish_ends FAILED (temp not found) for address='{}'",
+                    local_address
+                );
+                false
+            }
+            Err(e) => {
+                tracing::error!(
+                    "❌ establish_ends error for address='{}': {:?}",
+                    local_address,
+                    e
+                );
+                false
+            }
+        };
+        drop(guard);
+        if established {
+            let gctx = ctx.lock().await.global.clone();
+            reset_keys(&gctx, &peer_address).await;
+            if let Some(table) = gctx.get::<SessionTable>().await {
+                record_established(&table, &peer_address);
+            }
         }
     } else {
         tracing::info!(
@@ -193,13 +243,25 @@ pub async fn onlineack_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P
         ack.session_id
     );
 
+    // 应答中声称的地址必须与签名帧的地址一致，并通过挑战-应答证明持有其密钥
+    if ack.address != frame.body.address {
+        let err = ProtocolError::IdentityMismatch {
+            claimed: ack.address.clone(),
+        };
+        journal::record_handshake_failed(&ctx, &frame.body.address, &err.to_string()).await;
+        error::report(&ctx, &frame.body.address, err).await;
+        ctx.close().await;
+        return;
+    }
+    identity::send_challenge(ctx.clone(), &frame).await;
+
     // Register peer node in NodeRegistry
     let peer_addr = {
         let guard = ctx.lock().await;
         guard.addr
     };
     let peer_address = ack.address.clone();
-    let scope = NetworkScope::from_ip(&peer_addr.ip());
+    let scope = ip_scope::classify(&peer_addr.ip());
     {
         let guard = ctx.lock().await;
         if let Some(node) = guard.global.get::<Arc<Node>>().await {
@@ -224,6 +286,48 @@ pub async fn onlineack_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P
     {
         let mut guard = ctx.lock().await;
         guard.set(peer_address.clone());
+        capabilities::store(&mut guard, ack.capabilities, ack.max_frame_size);
+        if frame.format != WireFormat::Bincode {
+            guard.set(PeerWireFormat(frame.format));
+        }
+    }
+    if !negotiate_version(&ctx, &peer_address, ack.protocol_version).await {
+        return;
+    }
+    // 对端软件版本低于要求时按策略告警或拒绝
+    if !agent::admit(&ctx, &peer_address, peer_addr, ack.agent.clone(), false).await {
+        return;
+    }
+
+    // 同一节点的 IPv4 / IPv6 地址合并为一条服务器记录
+    {
+        let gctx = ctx.lock().await.global.clone();
+        if let Some(node) = gctx.get::<Arc<Node>>().await {
+            node.bind_identity(peer_addr, &peer_address).await;
+        }
+    }
+
+    // 与上一次会话比较对端声明的能力，退化时告警
+    {
+        let gctx = ctx.lock().await.global.clone();
+        let profile = CapabilityProfile::new(
+            ack.node.protocols.iter().cloned().collect(),
+            ack.capabilities,
+            ack.protocol_version,
+        );
+        peer_capabilities::observe(&gctx, &peer_address, peer_addr, profile).await;
+    }
+
+    // 学习路由：对端直连，对端公告的节点经由对端可达
+    {
+        let gctx = ctx.lock().await.global.clone();
+        if let Some(table) = gctx.get::<RoutingTable>().await {
+            let announced = ack
+                .seeds
+                .iter()
+                .flat_map(|s| s.seeds.iter().map(|r| r.node_address.as_str()));
+            routing::learn_from_handshake(&table, &local_address, &peer_address, announced);
+        }
     }
 
     // Store peer's Node info in ConnectionEntry so get_connection_info() can read it
@@ -248,11 +352,21 @@ pub async fn onlineack_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P
     // update() would replace the ConnectionEntry and drop the old one, which
     // triggers abort_handle.abort() and kills the router task).
     // The node info was already stored separately via entry.update_node() above.
-    {
+    let gctx = {
         let guard = ctx.lock().await;
         guard.global.manager.mark_active(peer_addr, false);
-    }
+        guard.global.clone()
+    };
     tracing::info!("Updated peer {} as inbound in manager", peer_addr);
+    let event = journal::Event::PeerConnected {
+        peer: peer_address.clone(),
+        addr: peer_addr.to_string(),
+        inbound: false,
+    };
+    journal::record(&gctx, event).await;
+
+    // 把本节点的在线状态交给对端缓存与传播
+    presence::send_own(ctx.clone()).await;
 
     // Store the announced IPs from peer as external seeds
     for ip in ack.intranet_ips.iter().chain(ack.wan_ips.iter()) {
@@ -291,9 +405,10 @@ pub async fn onlineack_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P
 
                 for seed in &seeds_cmd.seeds {
                     if let Ok(seed_addr) = seed.address.parse::<SocketAddr>() {
-                        let scope = NetworkScope::from_ip(&seed_addr.ip());
+                        let scope = ip_scope::classify(&seed_addr.ip());
                         node.registry
                             .register(seed.node_address.clone(), seed_addr, scope);
+                        node.registry.endpoints().spawn_verify(seed_addr);
                         tracing::info!(
                             "  + Registered seed from ack: {} (node: {})",
                             seed.address,
@@ -310,7 +425,7 @@ pub async fn onlineack_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P
 
                     let all_seeds: Vec<SeedRecord> = node
                         .registry
-                        .get_all_seeds()
+                        .get_gossip_seeds()
                         .into_iter()
                         .map(|(s, na)| SeedRecord::new(s.to_string(), na))
                         .collect();
@@ -410,6 +525,10 @@ pub async fn broadcast_seeds_to_peers(ctx: Arc<Mutex<Context>>, seeds: &SeedsCom
         intranet_ips: vec![],
         wan_ips: vec![],
         seeds: Some(seeds.clone()),
+        capabilities: LOCAL_CAPABILITIES,
+        protocol_version: CURRENT_PROTOCOL_VERSION,
+        max_frame_size: LOCAL_MAX_FRAME_SIZE,
+        agent: agent::local(&gctx).await,
     };
 
     let cmd_bytes = match Codec::encode(&cmd) {
@@ -420,7 +539,7 @@ pub async fn broadcast_seeds_to_peers(ctx: Arc<Mutex<Context>>, seeds: &SeedsCom
         }
     };
     let p2p_cmd = P2PCommand::new(Entity::Node, Action::OnLine, cmd_bytes);
-    let frame = match P2PFrame::build(&address, p2p_cmd, 1).await {
+    let frame = match P2PFrame::build(&address, p2p_cmd, CURRENT_PROTOCOL_VERSION).await {
         Ok(f) => f,
         Err(e) => {
             tracing::error!("Failed to build seed broadcast frame: {:?}", e);
@@ -431,25 +550,56 @@ pub async fn broadcast_seeds_to_peers(ctx: Arc<Mutex<Context>>, seeds: &SeedsCom
 
     let manager = gctx.manager.clone();
     let frame_bytes = match Codec::encode(&frame) {
-        Ok(b) => b,
+        Ok(b) => Bytes::from(b),
         Err(e) => {
             tracing::error!("Failed to encode seed broadcast frame: {:?}", e);
             return;
         }
     };
 
+    // 支持增量同步的对端只收到变化部分，其余对端收到完整列表；
+    // 部分端点对某个对端不可见时（见 `protocols::privacy`），为它单独过滤后发送
+    let deltas = gctx.get::<SharedSeedDeltas>().await;
+    let policy = privacy::policy(&gctx).await;
+    let records = seeds.seeds.clone();
+    let gctx_for_send = gctx.clone();
     manager
         .forward(|entries| async move {
-            for entry in entries {
-                if let Some(peer_ctx) = &entry.context {
-                    let mut guard = peer_ctx.lock().await;
-                    if let Some(writer) = &mut guard.writer {
-                        if let Err(e) = writer.write_all(&frame_bytes).await {
-                            tracing::error!("  ❌ Failed to broadcast seeds: {:?}", e);
-                        } else {
-                            let _ = writer.flush().await;
-                        }
+            let targets = broadcast::all_peers(entries);
+            let full_targets = match deltas {
+                Some(deltas) => {
+                    let (delta_targets, full_targets) = seed_delta::partition(targets).await;
+                    for (target, peer) in delta_targets {
+                        let visible = policy.filter_seeds(&records, Some(&target.addr.ip()));
+                        seed_delta::send_deltas(&deltas, vec![(target, peer)], &visible).await;
                     }
+                    full_targets
+                }
+                None => targets,
+            };
+            let (shared, restricted): (Vec<_>, Vec<_>) =
+                full_targets.into_iter().partition(|target| {
+                    policy.filter_seeds(&records, Some(&target.addr.ip())).len() == records.len()
+                });
+            broadcast::write_all(&gctx_for_send, shared, frame_bytes, Lane::Messaging)
+                .await
+                .log("broadcast seeds");
+            for target in restricted {
+                let visible = policy.filter_seeds(&records, Some(&target.addr.ip()));
+                let filtered = OnlineCommand {
+                    seeds: Some(SeedsCommand::new(visible)),
+                    ..cmd.clone()
+                };
+                if let Err(e) = P2PFrame::send(
+                    target.ctx,
+                    &Some(filtered),
+                    Entity::Node,
+                    Action::OnLine,
+                    false,
+                )
+                .await
+                {
+                    tracing::warn!("Failed to send filtered seeds to {}: {:?}", target.addr, e);
                 }
             }
         })
@@ -466,6 +616,20 @@ pub async fn connect_to_new_peer(
         let guard = ctx.lock().await;
         guard.global.clone()
     };
+    dial_peer(gctx, addr).await
+}
+
+/// 主动连接 `addr` 并发送 OnlineCommand 完成握手
+pub async fn dial_peer(
+    gctx: Arc<GlobalContext>,
+    addr: SocketAddr,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if !acl::permits(&gctx, Some(addr.ip()), None).await {
+        return Err(format!("{} is blocked by acl", addr).into());
+    }
+    if !limits::reserve_outbound(&gctx).await {
+        return Err("outbound connection limit reached".into());
+    }
 
     let psk = match gctx.paired_session_keys.clone() {
         Some(psk) => psk,
@@ -479,37 +643,13 @@ pub async fn connect_to_new_peer(
         guard.create(false).await
     };
 
-    let (seeds_to_send, _self_node_id) = {
-        let self_node_id = gctx.local_node.read().await.id.clone();
-        let seeds = if let Some(node) = gctx.get::<Arc<Node>>().await {
-            let all_seeds: Vec<SeedRecord> = node
-                .registry
-                .get_all_seeds()
-                .into_iter()
-                .map(|(s, na)| SeedRecord::new(s.to_string(), na))
-                .collect();
-            SeedsCommand::new(all_seeds)
-        } else {
-            SeedsCommand::new(vec![])
-        };
-        (seeds, self_node_id)
-    };
+    let seeds_to_send = privacy::gossip_seeds(&gctx, Some(&addr.ip())).await;
 
     let aex_node = {
         let guard = gctx.local_node.read().await;
         guard.clone()
     };
-    let (intranet_ips, wan_ips) = {
-        let mut inner = Vec::new();
-        let mut outer = Vec::new();
-        for (scope, ip) in &aex_node.ips {
-            match scope {
-                aex::connection::scope::NetworkScope::Intranet => inner.push(ip.to_string()),
-                aex::connection::scope::NetworkScope::Extranet => outer.push(ip.to_string()),
-            }
-        }
-        (inner, outer)
-    };
+    let (intranet_ips, wan_ips) = privacy::announced_ips(&gctx, &aex_node.ips, &addr.ip()).await;
     let cmd = Arc::new(OnlineCommand {
         session_id: id,
         node: aex_node,
@@ -517,46 +657,47 @@ pub async fn connect_to_new_peer(
         intranet_ips,
         wan_ips,
         seeds: Some(seeds_to_send),
+        capabilities: LOCAL_CAPABILITIES,
+        protocol_version: CURRENT_PROTOCOL_VERSION,
+        max_frame_size: LOCAL_MAX_FRAME_SIZE,
+        agent: agent::local(&gctx).await,
     });
 
     let gctx_clone = gctx.clone();
     let cmd_clone = cmd.clone();
 
-    match gctx
-        .manager
-        .clone()
-        .connect::<P2PFrame, P2PCommand, _, _>(
-            addr,
-            gctx.clone(),
-            move |new_ctx| {
-                let cmd = cmd_clone.clone();
-                let g = gctx_clone.clone();
-                Box::pin(async move {
-                    if let Err(e) = P2PFrame::send::<OnlineCommand>(
-                        new_ctx.clone(),
-                        &Some((*cmd).clone()),
-                        Entity::Node,
-                        Action::OnLine,
-                        false,
-                    )
-                    .await
-                    {
-                        tracing::error!("❌ Failed to send OnlineCommand: {:?}", e);
-                        return;
-                    }
-                    if let Some(router) =
-                        aex::connection::context::get_tcp_router::<P2PFrame, P2PCommand>(&g.routers)
-                    {
-                        let _ = router.handle(new_ctx).await;
-                    }
-                })
-            },
-            Some(10),
-        )
-        .await
+    match proxy::connect_peer(gctx.clone(), addr, move |new_ctx| {
+        let cmd = cmd_clone.clone();
+        let g = gctx_clone.clone();
+        Box::pin(async move {
+            if let Err(e) = P2PFrame::send::<OnlineCommand>(
+                new_ctx.clone(),
+                &Some((*cmd).clone()),
+                Entity::Node,
+                Action::OnLine,
+                false,
+            )
+            .await
+            {
+                tracing::error!("❌ Failed to send OnlineCommand: {:?}", e);
+                return;
+            }
+            if let Some(router) =
+                aex::connection::context::get_tcp_router::<P2PFrame, P2PCommand>(&g.routers)
+            {
+                let _ = router.handle(new_ctx).await;
+            }
+        })
+    })
+    .await
     {
         Ok(_) => {
             tracing::info!("  ✅ Connected to peer via outbound: {}", addr);
+            if let Some(node) = gctx.get::<Arc<Node>>().await {
+                node.registry
+                    .endpoints()
+                    .mark_verified(addr, endpoint_verifier::now_secs());
+            }
             Ok(())
         }
         Err(e) => {
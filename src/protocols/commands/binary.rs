@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use aex::connection::context::Context;
+use aex::tcp::types::Codec;
+use aex::time::SystemTime;
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::identities;
+use crate::protocols::capabilities::{self, CAP_FILE_TRANSFER, CAP_RELAY};
+use crate::protocols::command::{Action, Entity, P2PCommand};
+use crate::protocols::commands::error as remote_error;
+use crate::protocols::commands::message::SeenMessages;
+use crate::protocols::commands::rekey;
+use crate::protocols::error::ProtocolError;
+use crate::protocols::frame::P2PFrame;
+use crate::protocols::routing;
+
+/// 单个分片携带的最大字节数
+pub const BINARY_CHUNK_SIZE: usize = 64 * 1024;
+/// 允许接收的最大二进制消息
+pub const BINARY_MAX_SIZE: u64 = 64 * 1024 * 1024;
+
+pub const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// 分片去重记录上限
+const SEEN_CHUNKS_MAX: usize = 100_000;
+
+/// 正在重组的二进制消息：(sender, request_id) → 已收到的分片
+pub type BinaryAssemblies = Arc<Mutex<HashMap<(String, u64), PartialBinary>>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct BinaryMessageCommand {
+    pub sender: String,
+    pub receiver: String,
+    pub request_id: u64,
+    pub timestamp: u128,
+    /// MIME 类型，例如 image/png
+    pub content_type: String,
+    /// 文件名提示（可选）
+    pub filename: Option<String>,
+    pub chunk_index: u32,
+    pub chunk_count: u32,
+    pub total_len: u64,
+    pub data: Vec<u8>,
+}
+
+impl Codec for BinaryMessageCommand {}
+
+/// 收到的完整二进制消息，用于通过 channel 通知上层应用
+#[derive(Debug, Clone)]
+pub struct IncomingBinary {
+    pub from: String,
+    pub content_type: String,
+    pub filename: Option<String>,
+    pub data: Vec<u8>,
+    pub timestamp: u128,
+}
+
+#[derive(Debug, Default)]
+pub struct PartialBinary {
+    pub chunks: HashMap<u32, Vec<u8>>,
+    pub chunk_count: u32,
+    pub total_len: u64,
+}
+
+impl PartialBinary {
+    fn is_complete(&self) -> bool {
+        self.chunks.len() as u32 == self.chunk_count
+    }
+
+    fn assemble(mut self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.total_len as usize);
+        for i in 0..self.chunk_count {
+            if let Some(chunk) = self.chunks.remove(&i) {
+                data.extend_from_slice(&chunk);
+            }
+        }
+        data
+    }
+}
+
+/// 根据文件扩展名推断 MIME 类型
+pub fn guess_content_type(filename: &str) -> &'static str {
+    let ext = filename
+        .rsplit('.')
+        .next()
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "txt" => "text/plain",
+        "json" => "application/json",
+        "pdf" => "application/pdf",
+        "mp3" => "audio/mpeg",
+        "mp4" => "video/mp4",
+        "zip" => "application/zip",
+        _ => DEFAULT_CONTENT_TYPE,
+    }
+}
+
+/// 将二进制负载切分为多个分片命令
+pub fn split_into_chunks(
+    sender: String,
+    receiver: String,
+    request_id: u64,
+    content_type: &str,
+    filename: Option<String>,
+    data: &[u8],
+) -> Vec<BinaryMessageCommand> {
+    let timestamp = SystemTime::timestamp();
+    let chunks: Vec<&[u8]> = if data.is_empty() {
+        vec![&[]]
+    } else {
+        data.chunks(BINARY_CHUNK_SIZE).collect()
+    };
+    let chunk_count = chunks.len() as u32;
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, chunk)| BinaryMessageCommand {
+            sender: sender.clone(),
+            receiver: receiver.clone(),
+            request_id,
+            timestamp,
+            content_type: content_type.to_string(),
+            filename: filename.clone(),
+            chunk_index: i as u32,
+            chunk_count,
+            total_len: data.len() as u64,
+            data: chunk.to_vec(),
+        })
+        .collect()
+}
+
+/// 向指定连接发送二进制消息（自动分片，端到端加密）
+pub async fn send_binary_message(
+    sender: String,
+    receiver: String,
+    request_id: u64,
+    ctx: Arc<Mutex<Context>>,
+    content_type: &str,
+    filename: Option<String>,
+    data: &[u8],
+) -> anyhow::Result<()> {
+    // 直连接收方须支持文件传输；经由对端中继时对端须支持中继
+    let peer: Option<String> = ctx.lock().await.get();
+    let required = match peer {
+        Some(peer) if peer != receiver => CAP_RELAY,
+        _ => CAP_FILE_TRANSFER,
+    };
+    if !capabilities::peer_supports(&ctx, required).await {
+        return Err(anyhow::anyhow!(
+            "Peer does not support {}",
+            capabilities::feature_names(required).join(",")
+        ));
+    }
+    if data.len() as u64 > BINARY_MAX_SIZE {
+        return Err(anyhow::anyhow!(
+            "Binary message too large: {} > {}",
+            data.len(),
+            BINARY_MAX_SIZE
+        ));
+    }
+    for chunk in split_into_chunks(sender, receiver, request_id, content_type, filename, data) {
+        P2PFrame::send(
+            ctx.clone(),
+            &Some(chunk),
+            Entity::Message,
+            Action::SendBinary,
+            true,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+pub async fn binary_message_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    // 目标不是本节点：按路由表中继，不解密
+    if routing::relay_if_not_for_us(ctx.clone(), &frame).await {
+        return;
+    }
+    let from = &frame.body.address;
+    // 发往附加身份的帧用该身份的会话密钥表
+    let gctx = { ctx.lock().await.global.clone() };
+    let psk = match identities::session_keys_for(&gctx, frame.body.destination.as_deref()).await {
+        Some(psk) => psk,
+        None => {
+            tracing::error!("PairedSessionKeys not set in GlobalContext");
+            return;
+        }
+    };
+
+    let plaintext: Vec<u8> = match rekey::decrypt(&gctx, &psk, from, &cmd.data).await {
+        Ok(data) => data,
+        Err(e) => {
+            remote_error::bad_payload(&ctx, &frame, &cmd, ProtocolError::decrypt(e)).await;
+            return;
+        }
+    };
+
+    let chunk: BinaryMessageCommand = match Codec::decode(&plaintext) {
+        Ok(c) => c,
+        Err(e) => {
+            let error = ProtocolError::decode("BinaryMessageCommand", e);
+            remote_error::bad_payload(&ctx, &frame, &cmd, error).await;
+            return;
+        }
+    };
+
+    if chunk.chunk_count == 0
+        || chunk.chunk_index >= chunk.chunk_count
+        || chunk.total_len > BINARY_MAX_SIZE
+    {
+        tracing::warn!(
+            "⚠️ Rejecting malformed binary chunk {}/{} ({} bytes) from {}",
+            chunk.chunk_index,
+            chunk.chunk_count,
+            chunk.total_len,
+            from
+        );
+        return;
+    }
+
+    let gctx = {
+        let guard = ctx.lock().await;
+        guard.global.clone()
+    };
+
+    if !identities::is_local(&gctx, &chunk.receiver).await {
+        tracing::info!(
+            "  ⏭️  Binary message not for us (receiver={}), dropping",
+            chunk.receiver
+        );
+        return;
+    }
+
+    // 去重检查（同一分片只处理一次）
+    if let Some(seen) = gctx.get::<SeenMessages>().await {
+        let key = format!(
+            "bin:{}:{}:{}",
+            chunk.sender, chunk.request_id, chunk.chunk_index
+        );
+        if !seen.first_seen(key, SEEN_CHUNKS_MAX) {
+            return;
+        }
+    }
+
+    let assemblies = match gctx.get::<BinaryAssemblies>().await {
+        Some(a) => a,
+        None => {
+            tracing::warn!("  ⚠️  No BinaryAssemblies in GlobalContext");
+            return;
+        }
+    };
+
+    let key = (chunk.sender.clone(), chunk.request_id);
+    let complete = {
+        let mut guard = assemblies.lock().await;
+        let partial = guard.entry(key.clone()).or_insert_with(|| PartialBinary {
+            chunks: HashMap::new(),
+            chunk_count: chunk.chunk_count,
+            total_len: chunk.total_len,
+        });
+        partial.chunks.insert(chunk.chunk_index, chunk.data.clone());
+        if partial.is_complete() {
+            guard.remove(&key)
+        } else {
+            None
+        }
+    };
+
+    let Some(partial) = complete else {
+        return;
+    };
+    let data = partial.assemble();
+    tracing::info!(
+        "📦 Binary message from {} complete: {} bytes ({})",
+        chunk.sender,
+        data.len(),
+        chunk.content_type
+    );
+
+    if let Some(tx) = gctx
+        .get::<tokio::sync::mpsc::UnboundedSender<IncomingBinary>>()
+        .await
+    {
+        let _ = tx.send(IncomingBinary {
+            from: chunk.sender,
+            content_type: chunk.content_type,
+            filename: chunk.filename,
+            data,
+            timestamp: chunk.timestamp,
+        });
+    } else {
+        tracing::warn!("  ⚠️  No app channel found for incoming binary message!");
+    }
+}
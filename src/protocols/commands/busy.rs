@@ -0,0 +1,55 @@
+use std::sync::Arc;
+
+use aex::connection::context::Context;
+use aex::tcp::types::Codec;
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::journal;
+use crate::node::Node as P2pNode;
+use crate::protocols::command::P2PCommand;
+use crate::protocols::error::{self, ProtocolError};
+use crate::protocols::frame::P2PFrame;
+
+/// 对端连接数已满，拒绝本次连接
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct BusyCommand {
+    pub reason: String,
+    /// 建议多久之后再重试
+    pub retry_after_secs: u32,
+}
+
+impl Codec for BusyCommand {}
+
+pub async fn busy_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    let busy: BusyCommand = match frame.decode_payload(&cmd.data) {
+        Ok(c) => c,
+        Err(e) => {
+            error::report(
+                &ctx,
+                &frame.body.address,
+                ProtocolError::decode("BusyCommand", e),
+            )
+            .await;
+            return;
+        }
+    };
+    tracing::warn!(
+        "🚫 {} rejected our connection: {} (retry after {}s)",
+        frame.body.address,
+        busy.reason,
+        busy.retry_after_secs
+    );
+
+    let (gctx, peer) = {
+        let guard = ctx.lock().await;
+        if let Some(node) = guard.global.get::<Arc<P2pNode>>().await {
+            node.registry.disconnect(&frame.body.address);
+        }
+        guard.global.manager.remove(guard.addr, false);
+        (guard.global.clone(), guard.addr)
+    };
+    let reason = format!("busy: {}", busy.reason);
+    journal::record_disconnect(&gctx, Some(&frame.body.address), peer, &reason).await;
+}
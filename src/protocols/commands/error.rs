@@ -0,0 +1,331 @@
+//! 回复给远端的结构化错误
+//!
+//! 处理某一帧失败时（接收方不可达、TTL 耗尽、超过中继配额、负载无法解密或解码、没有对应的
+//! 处理器），向该帧的原始发送方回复一条 `Error/Error` 帧：机器可读的 [`ErrorCode`]、出错帧的
+//! nonce 与命令类型。原始发送方不是直连对端时按 `destination` 经中继送回。错误帧本身处理失败时
+//! 不再回复，避免两端互相回复。
+//!
+//! 发送方记住最近发出的、带 request_id 的帧（nonce → request_id），收到错误后让对应的待确认
+//! 发送以失败结束，并把错误记入投递状态（[`delivery_status`]），可通过控制接口的
+//! `GET /delivery/<request_id>` 查询。
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::{Arc, Mutex as StdMutex},
+};
+
+use aex::{
+    connection::{context::Context, global::GlobalContext},
+    tcp::types::Codec,
+    time::SystemTime,
+};
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::journal::{self, Event};
+use crate::protocols::command::{Action, Entity, P2PCommand};
+use crate::protocols::commands::binary::BinaryMessageCommand;
+use crate::protocols::commands::message::{MessageCommand, PendingAcks};
+use crate::protocols::error::{self, ProtocolError};
+use crate::protocols::frame::P2PFrame;
+use crate::protocols::routing;
+use crate::wal::SharedWal;
+
+/// 最多记住多少个已发出帧的 nonce
+pub const SENT_REQUESTS_MAX: usize = 4096;
+/// 最多保存多少条投递失败的记录
+pub const DELIVERY_ERRORS_MAX: usize = 1024;
+
+/// 错误码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Encode, Decode)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// 没有到接收方的路由，也没有可以转发的节点
+    UnknownReceiver = 1,
+    /// 转发途中 TTL 耗尽
+    TtlExpired,
+    /// 超过中继节点给发送方的配额
+    QuotaExceeded,
+    /// 负载无法解密或解码
+    BadPayload,
+    /// 接收方没有处理该命令的处理器
+    Unsupported,
+    /// 处理器 panic 或超时
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::UnknownReceiver => "unknown_receiver",
+            ErrorCode::TtlExpired => "ttl_expired",
+            ErrorCode::QuotaExceeded => "quota_exceeded",
+            ErrorCode::BadPayload => "bad_payload",
+            ErrorCode::Unsupported => "unsupported",
+            ErrorCode::Internal => "internal",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct ErrorCommand {
+    /// 出错帧的原始发送方
+    pub receiver: String,
+    pub code: ErrorCode,
+    /// 出错帧的 nonce
+    pub nonce: u64,
+    /// 出错帧的命令类型，无法解析时为 `None`
+    pub entity: Option<Entity>,
+    pub action: Option<Action>,
+    pub detail: String,
+}
+
+impl Codec for ErrorCommand {}
+
+impl ErrorCommand {
+    /// 关于 `frame`（命令为 `cmd`）的错误
+    pub fn about(
+        frame: &P2PFrame,
+        cmd: Option<&P2PCommand>,
+        code: ErrorCode,
+        detail: impl fmt::Display,
+    ) -> Self {
+        ErrorCommand {
+            receiver: frame.body.address.clone(),
+            code,
+            nonce: frame.body.nonce,
+            entity: cmd.map(|c| c.entity),
+            action: cmd.map(|c| c.action),
+            detail: detail.to_string(),
+        }
+    }
+}
+
+/// 按插入顺序淘汰最旧条目的定长表
+#[derive(Debug, Clone)]
+pub struct BoundedMap<V> {
+    max: usize,
+    order: VecDeque<u64>,
+    items: HashMap<u64, V>,
+}
+
+impl<V> BoundedMap<V> {
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            order: VecDeque::new(),
+            items: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, key: u64, value: V) {
+        if self.items.insert(key, value).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > self.max {
+            if let Some(oldest) = self.order.pop_front() {
+                self.items.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn get(&self, key: u64) -> Option<&V> {
+        self.items.get(&key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+/// 收到的一条远端错误
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RemoteError {
+    /// 回复错误的节点
+    pub from: String,
+    pub code: ErrorCode,
+    pub nonce: u64,
+    pub entity: Option<Entity>,
+    pub action: Option<Action>,
+    pub detail: String,
+    /// 对应的发送请求
+    pub request_id: Option<u64>,
+    pub received_at: u128,
+}
+
+/// 已发出帧的 nonce → request_id
+pub type SentRequests = Arc<StdMutex<BoundedMap<u64>>>;
+/// request_id → 投递失败的原因
+pub type DeliveryErrors = Arc<StdMutex<BoundedMap<RemoteError>>>;
+
+fn lock<V>(map: &StdMutex<BoundedMap<V>>) -> std::sync::MutexGuard<'_, BoundedMap<V>> {
+    map.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+async fn sent_requests(gctx: &GlobalContext) -> SentRequests {
+    match gctx.get::<SentRequests>().await {
+        Some(sent) => sent,
+        None => {
+            let sent = Arc::new(StdMutex::new(BoundedMap::new(SENT_REQUESTS_MAX)));
+            gctx.set(sent.clone()).await;
+            sent
+        }
+    }
+}
+
+async fn delivery_errors(gctx: &GlobalContext) -> DeliveryErrors {
+    match gctx.get::<DeliveryErrors>().await {
+        Some(errors) => errors,
+        None => {
+            let errors = Arc::new(StdMutex::new(BoundedMap::new(DELIVERY_ERRORS_MAX)));
+            gctx.set(errors.clone()).await;
+            errors
+        }
+    }
+}
+
+/// 序列化前的命令中携带的 request_id
+pub fn request_id(action: Action, data: &[u8]) -> Option<u64> {
+    match action {
+        Action::SendText => Codec::decode(data)
+            .ok()
+            .map(|m: MessageCommand| m.request_id),
+        Action::SendBinary => Codec::decode(data)
+            .ok()
+            .map(|m: BinaryMessageCommand| m.request_id),
+        _ => None,
+    }
+}
+
+/// 发送带 request_id 的帧后调用，便于把之后收到的错误关联到这次发送
+pub async fn remember(gctx: &GlobalContext, nonce: u64, request_id: u64) {
+    lock(&sent_requests(gctx).await).insert(nonce, request_id);
+}
+
+/// 某次发送收到的错误
+pub async fn delivery_error(gctx: &GlobalContext, request_id: u64) -> Option<RemoteError> {
+    lock(&delivery_errors(gctx).await).get(request_id).cloned()
+}
+
+/// 一次发送的投递状态
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// 对端回复了错误
+    Failed { error: RemoteError },
+    /// 等待接收方确认
+    Pending,
+    /// 既没有待确认的记录也没有收到错误：已送达，或发送时没有登记确认（未开启 WAL）
+    Unknown,
+}
+
+pub async fn delivery_status(gctx: &GlobalContext, request_id: u64) -> DeliveryStatus {
+    if let Some(error) = delivery_error(gctx, request_id).await {
+        return DeliveryStatus::Failed { error };
+    }
+    let awaiting_ack = gctx
+        .get::<PendingAcks>()
+        .await
+        .is_some_and(|pending| pending.contains_key(&request_id));
+    let in_wal = gctx
+        .get::<SharedWal>()
+        .await
+        .is_some_and(|wal| wal.pending().iter().any(|f| f.request_id == request_id));
+    if awaiting_ack || in_wal {
+        DeliveryStatus::Pending
+    } else {
+        DeliveryStatus::Unknown
+    }
+}
+
+/// 把错误回复给出错帧的原始发送方；关于错误帧本身的错误不回复
+pub async fn reply(ctx: Arc<Mutex<Context>>, err: ErrorCommand) {
+    if err.entity == Some(Entity::Error) {
+        return;
+    }
+    tracing::info!(
+        "  ↩️  Replying {} to {} for nonce={}: {}",
+        err.code,
+        err.receiver,
+        err.nonce,
+        err.detail
+    );
+    if let Err(e) = P2PFrame::send(ctx, &Some(err), Entity::Error, Action::Error, false).await {
+        tracing::debug!("Failed to send error reply: {}", e);
+    }
+}
+
+pub async fn error_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    if routing::relay_if_not_for_us(ctx.clone(), &frame).await {
+        return;
+    }
+    let err: ErrorCommand = match error::decode_command("ErrorCommand", &frame, &cmd.data) {
+        Ok(c) => c,
+        Err(e) => {
+            error::report(&ctx, &frame.body.address, e).await;
+            return;
+        }
+    };
+    let gctx = { ctx.lock().await.global.clone() };
+    let request_id = lock(&sent_requests(&gctx).await).get(err.nonce).copied();
+    tracing::warn!(
+        "⚠️ {} rejected our frame nonce={} ({:?}/{:?}): {} {}",
+        frame.body.address,
+        err.nonce,
+        err.entity,
+        err.action,
+        err.code,
+        err.detail
+    );
+
+    let remote = RemoteError {
+        from: frame.body.address.clone(),
+        code: err.code,
+        nonce: err.nonce,
+        entity: err.entity,
+        action: err.action,
+        detail: err.detail.clone(),
+        request_id,
+        received_at: SystemTime::timestamp(),
+    };
+    if let Some(id) = request_id {
+        lock(&delivery_errors(&gctx).await).insert(id, remote);
+        if let Some(pending) = gctx.get::<PendingAcks>().await {
+            if let Some((_, tx)) = pending.remove(&id) {
+                let _ = tx.send(false);
+            }
+        }
+    }
+    let event = Event::RemoteError {
+        from: frame.body.address.clone(),
+        code: err.code.as_str().to_string(),
+        nonce: err.nonce,
+        request_id,
+        detail: err.detail,
+    };
+    journal::record(&gctx, event).await;
+}
+
+/// 负载无法解密或解码时回复 `bad_payload`，再记录协议错误（可能因此断开连接）
+pub async fn bad_payload(
+    ctx: &Arc<Mutex<Context>>,
+    frame: &P2PFrame,
+    cmd: &P2PCommand,
+    error: ProtocolError,
+) {
+    let err = ErrorCommand::about(frame, Some(cmd), ErrorCode::BadPayload, &error);
+    reply(ctx.clone(), err).await;
+    error::report(ctx, &frame.body.address, error).await;
+}
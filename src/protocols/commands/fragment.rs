@@ -0,0 +1,345 @@
+//! 命令层的透明分片
+//!
+//! 编码后超过 [`FRAGMENT_THRESHOLD`]（或对端声明的更小的 `max_frame_size`）的帧
+//! 在发送时被切成若干 `Node/Fragment` 帧，
+//! 每片携带 (message_id, index, total)，由发送这一跳的节点签名；接收方按 (发送方, message_id)
+//! 重组出原始帧字节，校验其签名后交给 [`registry::dispatch`] 按正常流程处理。
+//! 重组有超时（[`FRAGMENT_TIMEOUT_MS`]）、单条消息上限与总缓存上限，超限的消息被整体丢弃。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use aex::connection::context::Context;
+use aex::tcp::types::{Codec, Frame};
+use aex::time::SystemTime;
+use bincode::{Decode, Encode};
+use bytes::{Bytes, BytesMut};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::protocols::capabilities::{MIN_MAX_FRAME_SIZE, PeerMaxFrameSize};
+use crate::protocols::command::{Action, Entity, P2PCommand};
+use crate::protocols::error::{self, ProtocolError};
+use crate::protocols::frame::P2PFrame;
+use crate::protocols::registry;
+use crate::protocols::version::PeerVersion;
+use crate::protocols::wire_format::{self, WireFormat};
+
+/// 编码后超过该长度的帧需要分片
+pub const FRAGMENT_THRESHOLD: usize = 96 * 1024;
+/// 单个分片携带的最大字节数
+pub const FRAGMENT_CHUNK_SIZE: usize = 64 * 1024;
+/// 分片帧除数据外的开销上限（签名、公钥、地址等）
+pub const FRAGMENT_OVERHEAD: usize = 1024;
+/// 单个分片携带的最小字节数，对应对端可声明的最小帧长
+pub const FRAGMENT_MIN_CHUNK_SIZE: usize = MIN_MAX_FRAME_SIZE as usize - FRAGMENT_OVERHEAD;
+/// 允许重组的最大消息
+pub const FRAGMENT_MAX_MESSAGE: usize = 64 * 1024 * 1024;
+/// 所有未完成消息合计占用的缓存上限
+pub const FRAGMENT_MAX_BUFFERED: usize = 128 * 1024 * 1024;
+/// 每个发送方同时重组的消息数上限
+pub const FRAGMENT_MAX_PENDING_PER_PEER: usize = 16;
+/// 未完成的消息超过该时间（毫秒）后丢弃
+pub const FRAGMENT_TIMEOUT_MS: u128 = 30_000;
+
+/// 正在重组的消息，保存在 GlobalContext 中
+pub type Reassemblies = Arc<Mutex<Reassembler>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct FragmentCommand {
+    pub message_id: u64,
+    pub index: u32,
+    pub total: u32,
+    #[serde(with = "serde_bytes")]
+    pub data: Vec<u8>,
+}
+
+impl Codec for FragmentCommand {}
+
+#[derive(Debug)]
+struct PartialMessage {
+    total: u32,
+    chunks: HashMap<u32, Vec<u8>>,
+    size: usize,
+    started_at: u128,
+}
+
+impl PartialMessage {
+    fn assemble(mut self) -> Bytes {
+        let mut data = BytesMut::with_capacity(self.size);
+        for i in 0..self.total {
+            if let Some(chunk) = self.chunks.remove(&i) {
+                data.extend_from_slice(&chunk);
+            }
+        }
+        data.freeze()
+    }
+}
+
+/// 按 (发送方, message_id) 重组分片
+#[derive(Debug, Default)]
+pub struct Reassembler {
+    partial: HashMap<(String, u64), PartialMessage>,
+    buffered: usize,
+}
+
+impl Reassembler {
+    /// 未完成的消息数
+    pub fn pending(&self) -> usize {
+        self.partial.len()
+    }
+
+    /// 未完成消息占用的字节数
+    pub fn buffered_bytes(&self) -> usize {
+        self.buffered
+    }
+
+    /// 丢弃超时的消息，返回丢弃的条数
+    pub fn expire(&mut self, now: u128) -> usize {
+        let before = self.partial.len();
+        let mut freed = 0;
+        self.partial.retain(|_, m| {
+            let keep = now.saturating_sub(m.started_at) < FRAGMENT_TIMEOUT_MS;
+            if !keep {
+                freed += m.size;
+            }
+            keep
+        });
+        self.buffered -= freed;
+        before - self.partial.len()
+    }
+
+    fn drop_message(&mut self, key: &(String, u64)) {
+        if let Some(m) = self.partial.remove(key) {
+            self.buffered -= m.size;
+        }
+    }
+
+    /// 收下一个分片；消息完整时返回原始字节，重复分片返回 `None`，
+    /// 分片非法或超出上限时返回错误（该消息已收到的分片一并丢弃）
+    pub fn accept(
+        &mut self,
+        sender: &str,
+        fragment: FragmentCommand,
+        now: u128,
+    ) -> anyhow::Result<Option<Bytes>> {
+        self.expire(now);
+
+        let max_total = FRAGMENT_MAX_MESSAGE.div_ceil(FRAGMENT_MIN_CHUNK_SIZE) as u32;
+        if fragment.total == 0 || fragment.index >= fragment.total || fragment.total > max_total {
+            anyhow::bail!(
+                "invalid fragment {}/{} of message {}",
+                fragment.index,
+                fragment.total,
+                fragment.message_id
+            );
+        }
+        if fragment.data.len() > FRAGMENT_CHUNK_SIZE {
+            anyhow::bail!(
+                "fragment too large: {} > {}",
+                fragment.data.len(),
+                FRAGMENT_CHUNK_SIZE
+            );
+        }
+
+        let key = (sender.to_string(), fragment.message_id);
+        if !self.partial.contains_key(&key) {
+            let pending = self.partial.keys().filter(|(s, _)| s == sender).count();
+            if pending >= FRAGMENT_MAX_PENDING_PER_PEER {
+                anyhow::bail!("too many partial messages from {}", sender);
+            }
+            self.partial.insert(
+                key.clone(),
+                PartialMessage {
+                    total: fragment.total,
+                    chunks: HashMap::new(),
+                    size: 0,
+                    started_at: now,
+                },
+            );
+        }
+
+        let message = self.partial.get(&key).expect("inserted above");
+        if message.total != fragment.total {
+            self.drop_message(&key);
+            anyhow::bail!("fragment total changed for message {}", fragment.message_id);
+        }
+        if message.chunks.contains_key(&fragment.index) {
+            return Ok(None);
+        }
+        let len = fragment.data.len();
+        if message.size + len > FRAGMENT_MAX_MESSAGE {
+            self.drop_message(&key);
+            anyhow::bail!(
+                "message {} exceeds {} bytes",
+                fragment.message_id,
+                FRAGMENT_MAX_MESSAGE
+            );
+        }
+        if self.buffered + len > FRAGMENT_MAX_BUFFERED {
+            self.drop_message(&key);
+            anyhow::bail!("reassembly buffer full ({} bytes)", self.buffered);
+        }
+
+        let message = self.partial.get_mut(&key).expect("checked above");
+        message.chunks.insert(fragment.index, fragment.data);
+        message.size += len;
+        self.buffered += len;
+        if message.chunks.len() as u32 == message.total {
+            let message = self.partial.remove(&key).expect("checked above");
+            self.buffered -= message.size;
+            return Ok(Some(message.assemble()));
+        }
+        Ok(None)
+    }
+}
+
+/// 发往该对端时需要分片的帧长度
+pub fn threshold(peer_max: Option<PeerMaxFrameSize>) -> usize {
+    match peer_max {
+        Some(PeerMaxFrameSize(max)) => (max as usize).min(FRAGMENT_THRESHOLD),
+        None => FRAGMENT_THRESHOLD,
+    }
+}
+
+/// 分片后每帧不超过 `threshold` 时单片可携带的字节数
+pub fn chunk_size(threshold: usize) -> usize {
+    threshold
+        .saturating_sub(FRAGMENT_OVERHEAD)
+        .clamp(FRAGMENT_MIN_CHUNK_SIZE, FRAGMENT_CHUNK_SIZE)
+}
+
+/// 把编码后的帧切成每片 `chunk_size` 字节的分片帧，每片由 `address` 签名
+pub async fn split(
+    address: &FreeWebMovementAddress,
+    bytes: &[u8],
+    chunk_size: usize,
+    message_id: u64,
+    version: u8,
+    format: WireFormat,
+) -> anyhow::Result<Vec<P2PFrame>> {
+    let chunks: Vec<&[u8]> = bytes.chunks(chunk_size).collect();
+    let total = chunks.len() as u32;
+    let mut frames = Vec::with_capacity(chunks.len());
+    for (i, chunk) in chunks.into_iter().enumerate() {
+        let fragment = FragmentCommand {
+            message_id,
+            index: i as u32,
+            total,
+            data: chunk.to_vec(),
+        };
+        let data = wire_format::encode(format, &fragment)?;
+        let command = P2PCommand::new(Entity::Node, Action::Fragment, data);
+        frames.push(P2PFrame::build_as(address, command, version, None, format).await?);
+    }
+    Ok(frames)
+}
+
+/// 帧字节超过阈值时返回发往该连接的分片帧字节，否则返回 `None`（原样发送）
+pub async fn fragment_for(
+    ctx: &Arc<Mutex<Context>>,
+    bytes: &[u8],
+) -> anyhow::Result<Option<Vec<Vec<u8>>>> {
+    let (gctx, version, peer_max) = {
+        let guard = ctx.lock().await;
+        (
+            guard.global.clone(),
+            guard.get::<PeerVersion>().unwrap_or_default(),
+            guard.get::<PeerMaxFrameSize>(),
+        )
+    };
+    let threshold = threshold(peer_max);
+    if bytes.len() <= threshold {
+        return Ok(None);
+    }
+    if bytes.len() > FRAGMENT_MAX_MESSAGE {
+        anyhow::bail!(
+            "frame too large: {} > {}",
+            bytes.len(),
+            FRAGMENT_MAX_MESSAGE
+        );
+    }
+    let Some(address) = gctx.get::<FreeWebMovementAddress>().await else {
+        anyhow::bail!("Address not set");
+    };
+    let format = wire_format::for_connection(ctx).await;
+    let message_id: u64 = rand::thread_rng().r#gen();
+    let chunk_size = chunk_size(threshold);
+    let frames = split(&address, bytes, chunk_size, message_id, version.0, format).await?;
+    tracing::info!(
+        "🧩 Fragmenting {} bytes into {} frames (message {})",
+        bytes.len(),
+        frames.len(),
+        message_id
+    );
+    let mut out = Vec::with_capacity(frames.len());
+    for frame in frames {
+        out.push(Codec::encode(&frame)?);
+    }
+    Ok(Some(out))
+}
+
+pub async fn fragment_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    let from = &frame.body.address;
+    let fragment: FragmentCommand = match frame.decode_payload(&cmd.data) {
+        Ok(f) => f,
+        Err(e) => {
+            error::report(&ctx, from, ProtocolError::decode("FragmentCommand", e)).await;
+            return;
+        }
+    };
+
+    let gctx = {
+        let guard = ctx.lock().await;
+        guard.global.clone()
+    };
+    let Some(reassemblies) = gctx.get::<Reassemblies>().await else {
+        tracing::warn!("  ⚠️  No Reassemblies in GlobalContext");
+        return;
+    };
+
+    let accepted = reassemblies
+        .lock()
+        .await
+        .accept(from, fragment, SystemTime::timestamp());
+    let bytes = match accepted {
+        Ok(Some(bytes)) => bytes,
+        Ok(None) => return,
+        Err(e) => {
+            error::report(&ctx, from, ProtocolError::decode("FragmentCommand", e)).await;
+            return;
+        }
+    };
+
+    // 重组出的帧可能由别的节点签名（中继节点重新分片），按独立的帧校验
+    let inner = match P2PFrame::verify_bytes(&bytes) {
+        Ok(f) => f,
+        Err(e) => {
+            error::report(&ctx, from, ProtocolError::decode("P2PFrame", e)).await;
+            return;
+        }
+    };
+    let command: P2PCommand = match inner.command().map(|c| Codec::decode(c)) {
+        Some(Ok(c)) => c,
+        Some(Err(e)) => {
+            error::report(&ctx, from, ProtocolError::decode("P2PCommand", e)).await;
+            return;
+        }
+        None => return,
+    };
+    if command.action == Action::Fragment {
+        tracing::warn!("⚠️ Dropping nested fragment from {}", from);
+        return;
+    }
+    tracing::info!(
+        "🧩 Reassembled {:?} frame from {} ({} bytes)",
+        command.action,
+        inner.body.address,
+        bytes.len()
+    );
+    if let Err(e) = registry::dispatch(ctx, inner, command).await {
+        tracing::warn!("⚠️ Failed to dispatch reassembled frame: {:?}", e);
+    }
+}
@@ -0,0 +1,1349 @@
+//! 端到端加密群聊
+//!
+//! 群由创建者（群主）管理。成员列表 `GroupState` 带递增的版本号，由群主的身份私钥签名；
+//! 成员首次接受某个群时记下群主公钥，之后只接受同一公钥签发的更高版本。每个成员还有一个
+//! 长期的 X25519 协商公钥，随成员列表一起签名分发。
+//!
+//! 加入：群主发送 `GroupInvite`，被邀请方执行 `group join` 后回复签名的 `GroupJoin`
+//! （身份公钥与协商公钥），群主把它加入成员列表并向所有成员发送新版本的 `GroupUpdate`。
+//! 成员退出（`GroupLeave`）或被移除时群主同样发出新版本，被移除的一方也会收到，据此删除本地的群。
+//!
+//! 加密采用发送方密钥（sender keys）：每个成员为每个群生成一把对称密钥，第一次发言前用两两
+//! 协商出的密钥（X25519 + HKDF）加密后通过 `GroupKey` 分发给其它成员；之后每条 `GroupMessage`
+//! 只加密一次，同一份密文发给每个成员，并带发送方身份签名，防止成员之间互相冒充。
+//! 有成员离开时其余成员都轮换自己的发送密钥，离开的成员无法解密之后的消息。
+//!
+//! 群帧不做逐跳加密，非直连的成员经中继送达，中继只能看到密文。成员列表、密钥与未处理的
+//! 邀请保存在 `groups.json`。
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+use aex::{
+    connection::{context::Context, global::GlobalContext},
+    tcp::types::Codec,
+    time::SystemTime,
+};
+use bincode::{Decode, Encode};
+use chacha20poly1305::{
+    ChaCha20Poly1305, Key, Nonce,
+    aead::{Aead, KeyInit, Payload},
+};
+use hkdf::Hkdf;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::events::{self, NodeEvent};
+use crate::io_storage::{IOStorage, STORAGE_GROUPS};
+use crate::node::Node as P2pNode;
+use crate::protocols::capabilities::CAP_GROUPS;
+use crate::protocols::command::{Action, Entity, P2PCommand};
+use crate::protocols::commands::identity::IdentityBindings;
+use crate::protocols::commands::message::SeenMessages;
+use crate::protocols::error::{self, ProtocolError};
+use crate::protocols::filter::InboundMessage;
+use crate::protocols::frame::P2PFrame;
+use crate::protocols::routing;
+
+const GROUP_LABEL: &[u8] = b"zz-p2p-group-v1";
+/// 群名的最大长度
+pub const GROUP_NAME_MAX_LEN: usize = 64;
+/// 单个群的最多成员数（含群主）
+pub const GROUP_MAX_MEMBERS: usize = 256;
+/// 单条群消息的最大长度
+pub const GROUP_MESSAGE_MAX_LEN: usize = 64 * 1024;
+const SEEN_GROUP_MAX: usize = 10_000;
+
+/// 群状态，保存在 GlobalContext 中
+pub type SharedGroups = Arc<Mutex<GroupStore>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Encode, Decode)]
+pub struct GroupMember {
+    pub address: String,
+    /// 身份公钥，用于校验该成员的消息签名
+    pub public_key: Vec<u8>,
+    /// X25519 协商公钥
+    pub agreement_key: [u8; 32],
+}
+
+/// 群主签名的成员列表
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Encode, Decode)]
+pub struct GroupState {
+    pub group_id: String,
+    pub name: String,
+    pub owner: String,
+    pub owner_public_key: Vec<u8>,
+    /// 每次变更加一
+    pub version: u64,
+    pub members: Vec<GroupMember>,
+    /// 群主对状态哈希的签名
+    pub signature: Vec<u8>,
+}
+
+impl Codec for GroupState {}
+
+/// 发送方密钥：`generation` 在每次轮换时加一
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SenderKey {
+    pub generation: u32,
+    pub key: [u8; 32],
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct GroupInviteCommand {
+    pub sender: String,
+    pub receiver: String,
+    pub group_id: String,
+    pub name: String,
+}
+
+impl Codec for GroupInviteCommand {}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct GroupJoinCommand {
+    pub sender: String,
+    pub receiver: String,
+    pub group_id: String,
+    pub public_key: Vec<u8>,
+    pub agreement_key: [u8; 32],
+    pub signature: Vec<u8>,
+}
+
+impl Codec for GroupJoinCommand {}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct GroupLeaveCommand {
+    pub sender: String,
+    pub receiver: String,
+    pub group_id: String,
+    /// 离开时所见的成员列表版本，与群主当前版本不一致时被忽略
+    pub version: u64,
+    pub signature: Vec<u8>,
+}
+
+impl Codec for GroupLeaveCommand {}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct GroupUpdateCommand {
+    pub receiver: String,
+    pub state: GroupState,
+}
+
+impl Codec for GroupUpdateCommand {}
+
+/// 用两两协商的密钥加密的发送方密钥
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct GroupKeyCommand {
+    pub sender: String,
+    pub receiver: String,
+    pub group_id: String,
+    pub generation: u32,
+    pub nonce: [u8; 12],
+    pub sealed: Vec<u8>,
+}
+
+impl Codec for GroupKeyCommand {}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct GroupMessageCommand {
+    pub sender: String,
+    pub receiver: String,
+    pub group_id: String,
+    pub generation: u32,
+    pub nonce: [u8; 12],
+    pub ciphertext: Vec<u8>,
+    /// 发送方对密文哈希的签名
+    pub signature: Vec<u8>,
+}
+
+impl Codec for GroupMessageCommand {}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct GroupPlaintext {
+    pub timestamp: u128,
+    pub content: String,
+}
+
+impl Codec for GroupPlaintext {}
+
+/// 收到的群消息，通过事件总线通知上层应用
+#[derive(Debug, Clone)]
+pub struct IncomingGroupMessage {
+    pub group_id: String,
+    pub group: String,
+    pub from: String,
+    pub content: String,
+    pub timestamp: u128,
+    /// 接收端过滤器添加的标注
+    pub annotations: BTreeMap<String, String>,
+}
+
+/// 带标签、逐段加长度前缀的哈希
+fn digest(kind: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(GROUP_LABEL);
+    hasher.update(kind);
+    for part in parts {
+        hasher.update((part.len() as u32).to_be_bytes());
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+fn sign(identity: &FreeWebMovementAddress, digest: &[u8; 32]) -> Vec<u8> {
+    FreeWebMovementAddress::sign_message(&identity.private_key, digest)
+        .serialize_compact()
+        .to_vec()
+}
+
+fn verify(public_key: &[u8], digest: &[u8; 32], signature: &[u8]) -> Result<(), ProtocolError> {
+    bitcoin::PublicKey::from_slice(public_key).map_err(|_| ProtocolError::InvalidPublicKey)?;
+    bitcoin::secp256k1::ecdsa::Signature::from_compact(signature)
+        .map_err(|_| ProtocolError::MalformedSignature)?;
+    let public_key = FreeWebMovementAddress::to_public_key(public_key);
+    let signature = FreeWebMovementAddress::to_signature(signature);
+    if !FreeWebMovementAddress::verify_message(&public_key, digest, &signature) {
+        return Err(ProtocolError::BadSignature);
+    }
+    Ok(())
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 协商私钥对应的公钥
+pub fn agreement_public(secret: &[u8; 32]) -> [u8; 32] {
+    PublicKey::from(&StaticSecret::from(*secret)).to_bytes()
+}
+
+/// 本节点与 `peer_key` 在 `group_id` 中的两两密钥；双方算出的结果相同
+pub fn pairwise_key(
+    secret: &[u8; 32],
+    peer_key: &[u8; 32],
+    group_id: &str,
+) -> anyhow::Result<[u8; 32]> {
+    let shared = StaticSecret::from(*secret).diffie_hellman(&PublicKey::from(*peer_key));
+    if !shared.was_contributory() {
+        anyhow::bail!("Invalid agreement key");
+    }
+    let hk = Hkdf::<Sha256>::new(Some(group_id.as_bytes()), shared.as_bytes());
+    let mut key = [0u8; 32];
+    hk.expand(GROUP_LABEL, &mut key)
+        .map_err(|_| anyhow::anyhow!("HKDF expand failed"))?;
+    Ok(key)
+}
+
+/// 以随机 nonce 加密，返回 (nonce, 密文)
+pub fn seal(key: &[u8; 32], aad: &[u8], plaintext: &[u8]) -> anyhow::Result<([u8; 12], Vec<u8>)> {
+    let nonce = random_bytes::<12>();
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| anyhow::anyhow!("Group encryption failed"))?;
+    Ok((nonce, ciphertext))
+}
+
+pub fn open(
+    key: &[u8; 32],
+    nonce: &[u8; 12],
+    aad: &[u8],
+    ciphertext: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| anyhow::anyhow!("Group decryption failed"))
+}
+
+fn key_aad(group_id: &str, sender: &str, receiver: &str, generation: u32) -> Vec<u8> {
+    digest(
+        b"key",
+        &[
+            group_id.as_bytes(),
+            sender.as_bytes(),
+            receiver.as_bytes(),
+            &generation.to_be_bytes(),
+        ],
+    )
+    .to_vec()
+}
+
+fn message_aad(group_id: &str, sender: &str, generation: u32) -> Vec<u8> {
+    digest(
+        b"message",
+        &[
+            group_id.as_bytes(),
+            sender.as_bytes(),
+            &generation.to_be_bytes(),
+        ],
+    )
+    .to_vec()
+}
+
+impl GroupState {
+    /// 签名覆盖的状态哈希
+    pub fn digest(&self) -> [u8; 32] {
+        let version = self.version.to_be_bytes();
+        let mut parts: Vec<&[u8]> = vec![
+            self.group_id.as_bytes(),
+            self.name.as_bytes(),
+            self.owner.as_bytes(),
+            &self.owner_public_key,
+            &version,
+        ];
+        for member in &self.members {
+            parts.push(member.address.as_bytes());
+            parts.push(&member.public_key);
+            parts.push(&member.agreement_key);
+        }
+        digest(b"state", &parts)
+    }
+
+    /// 由群主 `owner` 签名
+    pub fn signed(mut self, owner: &FreeWebMovementAddress) -> Self {
+        self.signature = sign(owner, &self.digest());
+        self
+    }
+
+    /// 校验群名、成员数与群主签名
+    pub fn verify(&self) -> Result<(), ProtocolError> {
+        if self.name.is_empty() || self.name.len() > GROUP_NAME_MAX_LEN {
+            return Err(ProtocolError::decode("GroupState", "invalid group name"));
+        }
+        if self.members.len() > GROUP_MAX_MEMBERS {
+            return Err(ProtocolError::decode("GroupState", "too many members"));
+        }
+        verify(&self.owner_public_key, &self.digest(), &self.signature)
+    }
+
+    pub fn member(&self, address: &str) -> Option<&GroupMember> {
+        self.members.iter().find(|m| m.address == address)
+    }
+
+    /// 同一个群、同一个群主签发的更高版本才能替换当前状态
+    pub fn check_successor(&self, next: &GroupState) -> anyhow::Result<()> {
+        if next.group_id != self.group_id
+            || next.owner != self.owner
+            || next.owner_public_key != self.owner_public_key
+        {
+            anyhow::bail!("Group {} update is not from its owner", self.group_id);
+        }
+        if next.version <= self.version {
+            anyhow::bail!(
+                "Stale update for group {}: version {} <= {}",
+                self.group_id,
+                next.version,
+                self.version
+            );
+        }
+        Ok(())
+    }
+
+    /// 群主修改成员后签发的下一个版本
+    fn successor(&self, owner: &FreeWebMovementAddress, members: Vec<GroupMember>) -> Self {
+        GroupState {
+            version: self.version + 1,
+            members,
+            signature: vec![],
+            ..self.clone()
+        }
+        .signed(owner)
+    }
+}
+
+impl SenderKey {
+    pub fn generate(generation: u32) -> Self {
+        Self {
+            generation,
+            key: random_bytes::<32>(),
+        }
+    }
+}
+
+/// 本节点所在的一个群
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Group {
+    pub state: GroupState,
+    /// 本节点的发送方密钥
+    pub sender_key: SenderKey,
+    /// 其它成员的发送方密钥
+    pub peer_keys: BTreeMap<String, SenderKey>,
+    /// 已收到当前发送方密钥的成员
+    pub distributed: BTreeSet<String>,
+    /// 群主：已邀请但尚未加入的地址
+    pub invited: BTreeSet<String>,
+}
+
+impl Group {
+    pub fn new(state: GroupState) -> Self {
+        Self {
+            state,
+            sender_key: SenderKey::generate(0),
+            peer_keys: BTreeMap::new(),
+            distributed: BTreeSet::new(),
+            invited: BTreeSet::new(),
+        }
+    }
+
+    /// 换用新的发送方密钥，之后发言前重新分发
+    pub fn rotate(&mut self) {
+        self.sender_key = SenderKey::generate(self.sender_key.generation.wrapping_add(1));
+        self.distributed.clear();
+    }
+
+    /// 换用新的成员列表（调用方已校验）；有成员离开时轮换发送方密钥，返回离开的成员
+    pub fn apply(&mut self, state: GroupState) -> Vec<String> {
+        let removed: Vec<String> = self
+            .state
+            .members
+            .iter()
+            .filter(|m| state.member(&m.address).is_none())
+            .map(|m| m.address.clone())
+            .collect();
+        // 协商公钥变了的成员需要重新分发
+        for member in &state.members {
+            if let Some(old) = self.state.member(&member.address) {
+                if old.agreement_key != member.agreement_key {
+                    self.distributed.remove(&member.address);
+                }
+            }
+        }
+        for address in &removed {
+            self.peer_keys.remove(address);
+        }
+        self.state = state;
+        if !removed.is_empty() {
+            self.rotate();
+        }
+        removed
+    }
+
+    /// 除 `local` 外尚未收到当前发送方密钥的成员
+    pub fn pending_distribution(&self, local: &str) -> Vec<GroupMember> {
+        self.state
+            .members
+            .iter()
+            .filter(|m| m.address != local && !self.distributed.contains(&m.address))
+            .cloned()
+            .collect()
+    }
+}
+
+/// 持久化的群状态
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GroupStore {
+    /// X25519 协商私钥
+    pub agreement_secret: [u8; 32],
+    /// group_id → 群
+    pub groups: BTreeMap<String, Group>,
+    /// 收到但尚未接受的邀请：group_id → 邀请
+    #[serde(default)]
+    pub invites: BTreeMap<String, GroupInviteCommand>,
+}
+
+impl Default for GroupStore {
+    fn default() -> Self {
+        Self {
+            agreement_secret: random_bytes::<32>(),
+            groups: BTreeMap::new(),
+            invites: BTreeMap::new(),
+        }
+    }
+}
+
+impl GroupStore {
+    pub fn agreement_key(&self) -> [u8; 32] {
+        agreement_public(&self.agreement_secret)
+    }
+
+    /// 按 group_id 或唯一的群名查找
+    pub fn resolve(&self, group: &str) -> anyhow::Result<String> {
+        if self.groups.contains_key(group) {
+            return Ok(group.to_string());
+        }
+        let matches: Vec<&String> = self
+            .groups
+            .iter()
+            .filter(|(_, g)| g.state.name == group)
+            .map(|(id, _)| id)
+            .collect();
+        match matches.as_slice() {
+            [id] => Ok((*id).clone()),
+            [] => anyhow::bail!("Unknown group: {}", group),
+            _ => anyhow::bail!("Group name {} is ambiguous, use the group id", group),
+        }
+    }
+
+    /// 以 `owner` 为群主新建一个只有自己的群
+    pub fn create(
+        &mut self,
+        owner: &FreeWebMovementAddress,
+        name: &str,
+    ) -> anyhow::Result<GroupState> {
+        let name = name.trim();
+        if name.is_empty() || name.len() > GROUP_NAME_MAX_LEN {
+            anyhow::bail!("Invalid group name: {:?}", name);
+        }
+        let state = GroupState {
+            group_id: hex(&random_bytes::<16>()),
+            name: name.to_string(),
+            owner: owner.to_string(),
+            owner_public_key: owner.public_key.to_bytes().to_vec(),
+            version: 1,
+            members: vec![GroupMember {
+                address: owner.to_string(),
+                public_key: owner.public_key.to_bytes().to_vec(),
+                agreement_key: self.agreement_key(),
+            }],
+            signature: vec![],
+        }
+        .signed(owner);
+        self.groups
+            .insert(state.group_id.clone(), Group::new(state.clone()));
+        Ok(state)
+    }
+
+    /// 接受一份（已校验签名的）成员列表；返回 `false` 表示本节点已不在群中，群已删除
+    pub fn accept(&mut self, state: GroupState, local: &str) -> anyhow::Result<bool> {
+        let group_id = state.group_id.clone();
+        let member = state.member(local).is_some();
+        match self.groups.get_mut(&group_id) {
+            Some(group) => {
+                if group.state.owner == local {
+                    anyhow::bail!("Ignoring update for own group {}", group_id);
+                }
+                group.state.check_successor(&state)?;
+                if member {
+                    group.apply(state);
+                } else {
+                    self.groups.remove(&group_id);
+                }
+            }
+            None => {
+                let Some(invite) = self.invites.get(&group_id) else {
+                    anyhow::bail!("Not invited to group {}", group_id);
+                };
+                if invite.sender != state.owner {
+                    anyhow::bail!("Group {} is not owned by the inviter", group_id);
+                }
+                if !member {
+                    anyhow::bail!("Group {} update does not include us yet", group_id);
+                }
+                self.invites.remove(&group_id);
+                self.groups.insert(group_id, Group::new(state));
+            }
+        }
+        Ok(member)
+    }
+}
+
+pub fn join_digest(
+    group_id: &str,
+    sender: &str,
+    public_key: &[u8],
+    agreement_key: &[u8; 32],
+) -> [u8; 32] {
+    digest(
+        b"join",
+        &[
+            group_id.as_bytes(),
+            sender.as_bytes(),
+            public_key,
+            agreement_key,
+        ],
+    )
+}
+
+pub fn leave_digest(group_id: &str, sender: &str, version: u64) -> [u8; 32] {
+    digest(
+        b"leave",
+        &[
+            group_id.as_bytes(),
+            sender.as_bytes(),
+            &version.to_be_bytes(),
+        ],
+    )
+}
+
+pub fn message_digest(cmd: &GroupMessageCommand) -> [u8; 32] {
+    digest(
+        b"signed",
+        &[
+            cmd.group_id.as_bytes(),
+            cmd.sender.as_bytes(),
+            &cmd.generation.to_be_bytes(),
+            &cmd.nonce,
+            &cmd.ciphertext,
+        ],
+    )
+}
+
+/// 群命令的最终接收方，用于非直连时经中继送达
+pub fn destination(action: Action, data: &[u8]) -> Option<String> {
+    match action {
+        Action::GroupInvite => Codec::decode(data)
+            .ok()
+            .map(|c: GroupInviteCommand| c.receiver),
+        Action::GroupJoin => Codec::decode(data)
+            .ok()
+            .map(|c: GroupJoinCommand| c.receiver),
+        Action::GroupLeave => Codec::decode(data)
+            .ok()
+            .map(|c: GroupLeaveCommand| c.receiver),
+        Action::GroupUpdate => Codec::decode(data)
+            .ok()
+            .map(|c: GroupUpdateCommand| c.receiver),
+        Action::GroupKey => Codec::decode(data)
+            .ok()
+            .map(|c: GroupKeyCommand| c.receiver),
+        Action::GroupMessage => Codec::decode(data)
+            .ok()
+            .map(|c: GroupMessageCommand| c.receiver),
+        _ => None,
+    }
+}
+
+async fn shared(gctx: &Arc<GlobalContext>) -> anyhow::Result<SharedGroups> {
+    gctx.get::<SharedGroups>()
+        .await
+        .ok_or_else(|| anyhow::anyhow!("SharedGroups not set in GlobalContext"))
+}
+
+async fn local_identity(gctx: &Arc<GlobalContext>) -> anyhow::Result<FreeWebMovementAddress> {
+    gctx.get::<FreeWebMovementAddress>()
+        .await
+        .ok_or_else(|| anyhow::anyhow!("Address not set"))
+}
+
+/// 持久化群状态
+async fn save(gctx: &Arc<GlobalContext>, store: &GroupStore) {
+    match gctx.get::<IOStorage>().await {
+        Some(ios) => ios.save::<GroupStore>(store, STORAGE_GROUPS).await,
+        None => tracing::error!("IOStorage not found in context, groups not persisted"),
+    }
+}
+
+/// 已验证身份的地址只接受其绑定的公钥
+async fn check_binding(
+    gctx: &Arc<GlobalContext>,
+    address: &str,
+    public_key: &[u8],
+) -> Result<(), ProtocolError> {
+    if let Some(bindings) = gctx.get::<IdentityBindings>().await {
+        if let Some(key) = bindings.get(address) {
+            if key.value().as_slice() != public_key {
+                return Err(ProtocolError::IdentityMismatch {
+                    claimed: address.to_string(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn send_to<C: Codec + Serialize>(
+    gctx: &Arc<GlobalContext>,
+    receiver: &str,
+    cmd: C,
+    action: Action,
+) -> anyhow::Result<()> {
+    let ctx = routing::connection_to(gctx, receiver, CAP_GROUPS).await?;
+    P2PFrame::send(ctx, &Some(cmd), Entity::Group, action, false).await
+}
+
+/// 把成员列表发给 `recipients`，失败的只记录日志
+async fn send_updates(gctx: &Arc<GlobalContext>, state: &GroupState, recipients: Vec<String>) {
+    for receiver in recipients {
+        let cmd = GroupUpdateCommand {
+            receiver: receiver.clone(),
+            state: state.clone(),
+        };
+        if let Err(e) = send_to(gctx, &receiver, cmd, Action::GroupUpdate).await {
+            tracing::warn!(
+                "Failed to send group {} update to {}: {}",
+                state.group_id,
+                receiver,
+                e
+            );
+        }
+    }
+}
+
+/// 除 `local` 外的成员地址
+fn others(state: &GroupState, local: &str) -> Vec<String> {
+    state
+        .members
+        .iter()
+        .filter(|m| m.address != local)
+        .map(|m| m.address.clone())
+        .collect()
+}
+
+/// 新建群，本节点为群主
+pub async fn create(gctx: &Arc<GlobalContext>, name: &str) -> anyhow::Result<GroupState> {
+    let identity = local_identity(gctx).await?;
+    let groups = shared(gctx).await?;
+    let mut store = groups.lock().await;
+    let state = store.create(&identity, name)?;
+    save(gctx, &store).await;
+    Ok(state)
+}
+
+/// 群主邀请 `address`（地址或别名）加入
+pub async fn invite(gctx: &Arc<GlobalContext>, group: &str, address: &str) -> anyhow::Result<()> {
+    let identity = local_identity(gctx).await?;
+    let local = identity.to_string();
+    let receiver = match gctx.get::<Arc<P2pNode>>().await {
+        Some(node) => node.registry.resolve_alias(address),
+        None => address.to_string(),
+    };
+    let groups = shared(gctx).await?;
+    let cmd = {
+        let mut store = groups.lock().await;
+        let group_id = store.resolve(group)?;
+        let group = store.groups.get_mut(&group_id).expect("resolved group");
+        if group.state.owner != local {
+            anyhow::bail!("Only the owner can invite members");
+        }
+        if group.state.member(&receiver).is_some() {
+            anyhow::bail!("{} is already a member", receiver);
+        }
+        if group.state.members.len() >= GROUP_MAX_MEMBERS {
+            anyhow::bail!("Group is full");
+        }
+        group.invited.insert(receiver.clone());
+        let cmd = GroupInviteCommand {
+            sender: local,
+            receiver: receiver.clone(),
+            group_id,
+            name: group.state.name.clone(),
+        };
+        save(gctx, &store).await;
+        cmd
+    };
+    send_to(gctx, &receiver, cmd, Action::GroupInvite).await
+}
+
+/// 接受邀请，等待群主发来包含本节点的成员列表
+pub async fn join(gctx: &Arc<GlobalContext>, group_id: &str) -> anyhow::Result<()> {
+    let identity = local_identity(gctx).await?;
+    let groups = shared(gctx).await?;
+    let (invite, agreement_key) = {
+        let store = groups.lock().await;
+        let invite = store
+            .invites
+            .get(group_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No pending invite for group {}", group_id))?;
+        (invite, store.agreement_key())
+    };
+    let sender = identity.to_string();
+    let public_key = identity.public_key.to_bytes().to_vec();
+    let signature = sign(
+        &identity,
+        &join_digest(&invite.group_id, &sender, &public_key, &agreement_key),
+    );
+    let cmd = GroupJoinCommand {
+        sender,
+        receiver: invite.sender.clone(),
+        group_id: invite.group_id,
+        public_key,
+        agreement_key,
+        signature,
+    };
+    send_to(gctx, &invite.sender, cmd, Action::GroupJoin).await
+}
+
+/// 群主移除成员：签发新版本并轮换自己的发送方密钥
+pub async fn remove(gctx: &Arc<GlobalContext>, group: &str, address: &str) -> anyhow::Result<()> {
+    let identity = local_identity(gctx).await?;
+    let local = identity.to_string();
+    let receiver = match gctx.get::<Arc<P2pNode>>().await {
+        Some(node) => node.registry.resolve_alias(address),
+        None => address.to_string(),
+    };
+    let groups = shared(gctx).await?;
+    let (state, recipients) = {
+        let mut store = groups.lock().await;
+        let group_id = store.resolve(group)?;
+        let group = store.groups.get_mut(&group_id).expect("resolved group");
+        if group.state.owner != local {
+            anyhow::bail!("Only the owner can remove members");
+        }
+        if receiver == local {
+            anyhow::bail!("The owner cannot be removed, use `group leave` to disband");
+        }
+        if group.state.member(&receiver).is_none() {
+            group.invited.remove(&receiver);
+            save(gctx, &store).await;
+            anyhow::bail!("{} is not a member", receiver);
+        }
+        let members = group
+            .state
+            .members
+            .iter()
+            .filter(|m| m.address != receiver)
+            .cloned()
+            .collect();
+        let state = group.state.successor(&identity, members);
+        group.apply(state.clone());
+        // 被移除的一方也收到新版本，据此删除本地的群
+        let mut recipients = others(&state, &local);
+        recipients.push(receiver);
+        save(gctx, &store).await;
+        (state, recipients)
+    };
+    send_updates(gctx, &state, recipients).await;
+    Ok(())
+}
+
+/// 退出群；群主退出时解散该群
+pub async fn leave(gctx: &Arc<GlobalContext>, group: &str) -> anyhow::Result<()> {
+    let identity = local_identity(gctx).await?;
+    let local = identity.to_string();
+    let groups = shared(gctx).await?;
+    let mut store = groups.lock().await;
+    let group_id = match store.resolve(group) {
+        Ok(id) => id,
+        Err(e) => {
+            // 未接受的邀请直接丢弃
+            if store.invites.remove(group).is_some() {
+                save(gctx, &store).await;
+                return Ok(());
+            }
+            return Err(e);
+        }
+    };
+    let group = store.groups.remove(&group_id).expect("resolved group");
+    save(gctx, &store).await;
+    drop(store);
+
+    if group.state.owner == local {
+        let state = group.state.successor(&identity, vec![]);
+        send_updates(gctx, &state, others(&group.state, &local)).await;
+        return Ok(());
+    }
+    let version = group.state.version;
+    let cmd = GroupLeaveCommand {
+        sender: local.clone(),
+        receiver: group.state.owner.clone(),
+        group_id: group_id.clone(),
+        version,
+        signature: sign(&identity, &leave_digest(&group_id, &local, version)),
+    };
+    send_to(gctx, &group.state.owner, cmd, Action::GroupLeave).await
+}
+
+/// 向群发送文本消息，返回送达的成员数
+pub async fn send(gctx: &Arc<GlobalContext>, group: &str, content: &str) -> anyhow::Result<usize> {
+    if content.len() > GROUP_MESSAGE_MAX_LEN {
+        anyhow::bail!(
+            "Message too large: {} > {}",
+            content.len(),
+            GROUP_MESSAGE_MAX_LEN
+        );
+    }
+    let identity = local_identity(gctx).await?;
+    let local = identity.to_string();
+    let groups = shared(gctx).await?;
+
+    // 先把当前发送方密钥分发给还没有它的成员
+    let (group_id, key_cmds, sender_key, members) = {
+        let store = groups.lock().await;
+        let secret = store.agreement_secret;
+        let group_id = store.resolve(group)?;
+        let group = store.groups.get(&group_id).expect("resolved group");
+        let sender_key = group.sender_key.clone();
+        let mut key_cmds = Vec::new();
+        for member in group.pending_distribution(&local) {
+            let pairwise = pairwise_key(&secret, &member.agreement_key, &group_id)?;
+            let aad = key_aad(&group_id, &local, &member.address, sender_key.generation);
+            let (nonce, sealed) = seal(&pairwise, &aad, &sender_key.key)?;
+            key_cmds.push(GroupKeyCommand {
+                sender: local.clone(),
+                receiver: member.address,
+                group_id: group_id.clone(),
+                generation: sender_key.generation,
+                nonce,
+                sealed,
+            });
+        }
+        (group_id, key_cmds, sender_key, others(&group.state, &local))
+    };
+    let mut distributed = Vec::new();
+    for cmd in key_cmds {
+        let receiver = cmd.receiver.clone();
+        match send_to(gctx, &receiver, cmd, Action::GroupKey).await {
+            Ok(()) => distributed.push(receiver),
+            Err(e) => tracing::warn!("Failed to send group key to {}: {}", receiver, e),
+        }
+    }
+    if !distributed.is_empty() {
+        let mut store = groups.lock().await;
+        if let Some(group) = store.groups.get_mut(&group_id) {
+            // 期间发生过轮换则下次重新分发
+            if group.sender_key == sender_key {
+                group.distributed.extend(distributed);
+            }
+        }
+        save(gctx, &store).await;
+    }
+
+    // 密文只生成一次，发给每个成员
+    let plaintext = Codec::encode(&GroupPlaintext {
+        timestamp: SystemTime::timestamp(),
+        content: content.to_string(),
+    })?;
+    let aad = message_aad(&group_id, &local, sender_key.generation);
+    let (nonce, ciphertext) = seal(&sender_key.key, &aad, &plaintext)?;
+    let mut message = GroupMessageCommand {
+        sender: local,
+        receiver: String::new(),
+        group_id,
+        generation: sender_key.generation,
+        nonce,
+        ciphertext,
+        signature: vec![],
+    };
+    message.signature = sign(&identity, &message_digest(&message));
+
+    let mut delivered = 0;
+    for receiver in members {
+        let cmd = GroupMessageCommand {
+            receiver: receiver.clone(),
+            ..message.clone()
+        };
+        match send_to(gctx, &receiver, cmd, Action::GroupMessage).await {
+            Ok(()) => delivered += 1,
+            Err(e) => tracing::warn!("Failed to send group message to {}: {}", receiver, e),
+        }
+    }
+    Ok(delivered)
+}
+
+/// 本节点所在的群与未处理的邀请
+pub async fn list(gctx: &Arc<GlobalContext>) -> (Vec<GroupState>, Vec<GroupInviteCommand>) {
+    let Ok(groups) = shared(gctx).await else {
+        return (vec![], vec![]);
+    };
+    let store = groups.lock().await;
+    (
+        store.groups.values().map(|g| g.state.clone()).collect(),
+        store.invites.values().cloned().collect(),
+    )
+}
+
+/// 命令的接收方须为本节点主身份；返回 (GlobalContext, 本节点地址)
+async fn local_receiver(
+    ctx: &Arc<Mutex<Context>>,
+    receiver: &str,
+) -> Option<(Arc<GlobalContext>, FreeWebMovementAddress)> {
+    let gctx = ctx.lock().await.global.clone();
+    let identity = gctx.get::<FreeWebMovementAddress>().await?;
+    if identity.to_string() != receiver {
+        tracing::debug!(
+            "Group command for {} is not for the primary identity",
+            receiver
+        );
+        return None;
+    }
+    Some((gctx, identity))
+}
+
+pub async fn group_invite_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    if routing::relay_if_not_for_us(ctx.clone(), &frame).await {
+        return;
+    }
+    let invite: GroupInviteCommand =
+        match error::decode_command("GroupInviteCommand", &frame, &cmd.data) {
+            Ok(c) => c,
+            Err(e) => {
+                error::report(&ctx, &frame.body.address, e).await;
+                return;
+            }
+        };
+    let Some((gctx, _)) = local_receiver(&ctx, &invite.receiver).await else {
+        return;
+    };
+    let Ok(groups) = shared(&gctx).await else {
+        return;
+    };
+    let mut store = groups.lock().await;
+    if store.groups.contains_key(&invite.group_id) {
+        return;
+    }
+    tracing::info!(
+        "👥 Invited to group {} ({}) by {}, run `group join {}` to accept",
+        invite.name,
+        invite.group_id,
+        invite.sender,
+        invite.group_id
+    );
+    store.invites.insert(invite.group_id.clone(), invite);
+    save(&gctx, &store).await;
+}
+
+pub async fn group_join_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    if routing::relay_if_not_for_us(ctx.clone(), &frame).await {
+        return;
+    }
+    let peer = frame.body.address.clone();
+    let join: GroupJoinCommand = match error::decode_command("GroupJoinCommand", &frame, &cmd.data)
+    {
+        Ok(c) => c,
+        Err(e) => {
+            error::report(&ctx, &peer, e).await;
+            return;
+        }
+    };
+    let Some((gctx, identity)) = local_receiver(&ctx, &join.receiver).await else {
+        return;
+    };
+    let digest = join_digest(
+        &join.group_id,
+        &join.sender,
+        &join.public_key,
+        &join.agreement_key,
+    );
+    let checked = match verify(&join.public_key, &digest, &join.signature) {
+        Ok(()) => check_binding(&gctx, &join.sender, &join.public_key).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = checked {
+        error::report(&ctx, &peer, e).await;
+        return;
+    }
+    let Ok(groups) = shared(&gctx).await else {
+        return;
+    };
+    let local = identity.to_string();
+    let state = {
+        let mut store = groups.lock().await;
+        let Some(group) = store.groups.get_mut(&join.group_id) else {
+            return;
+        };
+        if group.state.owner != local || !group.invited.remove(&join.sender) {
+            tracing::warn!(
+                "Ignoring uninvited join of {} to group {}",
+                join.sender,
+                join.group_id
+            );
+            return;
+        }
+        if group.state.members.len() >= GROUP_MAX_MEMBERS {
+            tracing::warn!("Group {} is full, {} not added", join.group_id, join.sender);
+            save(&gctx, &store).await;
+            return;
+        }
+        let mut members = group.state.members.clone();
+        members.push(GroupMember {
+            address: join.sender.clone(),
+            public_key: join.public_key,
+            agreement_key: join.agreement_key,
+        });
+        let state = group.state.successor(&identity, members);
+        group.apply(state.clone());
+        save(&gctx, &store).await;
+        state
+    };
+    tracing::info!("👥 {} joined group {}", join.sender, state.name);
+    send_updates(&gctx, &state, others(&state, &local)).await;
+}
+
+pub async fn group_leave_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    if routing::relay_if_not_for_us(ctx.clone(), &frame).await {
+        return;
+    }
+    let peer = frame.body.address.clone();
+    let leave: GroupLeaveCommand =
+        match error::decode_command("GroupLeaveCommand", &frame, &cmd.data) {
+            Ok(c) => c,
+            Err(e) => {
+                error::report(&ctx, &peer, e).await;
+                return;
+            }
+        };
+    let Some((gctx, identity)) = local_receiver(&ctx, &leave.receiver).await else {
+        return;
+    };
+    let Ok(groups) = shared(&gctx).await else {
+        return;
+    };
+    let local = identity.to_string();
+    let (state, recipients) = {
+        let mut store = groups.lock().await;
+        let Some(group) = store.groups.get_mut(&leave.group_id) else {
+            return;
+        };
+        if group.state.owner != local || leave.version != group.state.version {
+            return;
+        }
+        let Some(member) = group.state.member(&leave.sender) else {
+            return;
+        };
+        let digest = leave_digest(&leave.group_id, &leave.sender, leave.version);
+        if let Err(e) = verify(&member.public_key, &digest, &leave.signature) {
+            drop(store);
+            error::report(&ctx, &peer, e).await;
+            return;
+        }
+        let members = group
+            .state
+            .members
+            .iter()
+            .filter(|m| m.address != leave.sender)
+            .cloned()
+            .collect();
+        let state = group.state.successor(&identity, members);
+        group.apply(state.clone());
+        save(&gctx, &store).await;
+        (state.clone(), others(&state, &local))
+    };
+    tracing::info!("👥 {} left group {}", leave.sender, state.name);
+    send_updates(&gctx, &state, recipients).await;
+}
+
+pub async fn group_update_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    if routing::relay_if_not_for_us(ctx.clone(), &frame).await {
+        return;
+    }
+    let peer = frame.body.address.clone();
+    let update: GroupUpdateCommand =
+        match error::decode_command("GroupUpdateCommand", &frame, &cmd.data) {
+            Ok(c) => c,
+            Err(e) => {
+                error::report(&ctx, &peer, e).await;
+                return;
+            }
+        };
+    let Some((gctx, identity)) = local_receiver(&ctx, &update.receiver).await else {
+        return;
+    };
+    let state = update.state;
+    let checked = match state.verify() {
+        Ok(()) => check_binding(&gctx, &state.owner, &state.owner_public_key).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = checked {
+        error::report(&ctx, &peer, e).await;
+        return;
+    }
+    let Ok(groups) = shared(&gctx).await else {
+        return;
+    };
+    let mut store = groups.lock().await;
+    let name = state.name.clone();
+    let version = state.version;
+    match store.accept(state, &identity.to_string()) {
+        Ok(true) => tracing::info!("👥 Group {} updated to version {}", name, version),
+        Ok(false) => tracing::info!("👥 No longer a member of group {}", name),
+        Err(e) => {
+            tracing::warn!("Rejected group update from {}: {}", peer, e);
+            return;
+        }
+    }
+    save(&gctx, &store).await;
+}
+
+pub async fn group_key_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    if routing::relay_if_not_for_us(ctx.clone(), &frame).await {
+        return;
+    }
+    let peer = frame.body.address.clone();
+    let key: GroupKeyCommand = match error::decode_command("GroupKeyCommand", &frame, &cmd.data) {
+        Ok(c) => c,
+        Err(e) => {
+            error::report(&ctx, &peer, e).await;
+            return;
+        }
+    };
+    let Some((gctx, _)) = local_receiver(&ctx, &key.receiver).await else {
+        return;
+    };
+    let Ok(groups) = shared(&gctx).await else {
+        return;
+    };
+    let mut store = groups.lock().await;
+    let secret = store.agreement_secret;
+    let Some(group) = store.groups.get_mut(&key.group_id) else {
+        return;
+    };
+    let Some(member) = group.state.member(&key.sender) else {
+        tracing::warn!(
+            "Group key from non-member {} for group {}",
+            key.sender,
+            key.group_id
+        );
+        return;
+    };
+    let aad = key_aad(&key.group_id, &key.sender, &key.receiver, key.generation);
+    let opened = pairwise_key(&secret, &member.agreement_key, &key.group_id)
+        .and_then(|pairwise| open(&pairwise, &key.nonce, &aad, &key.sealed));
+    let sender_key: [u8; 32] = match opened.map(<[u8; 32]>::try_from) {
+        Ok(Ok(k)) => k,
+        Ok(Err(_)) => {
+            drop(store);
+            error::report(
+                &ctx,
+                &peer,
+                ProtocolError::decrypt("invalid sender key length"),
+            )
+            .await;
+            return;
+        }
+        Err(e) => {
+            drop(store);
+            error::report(&ctx, &peer, ProtocolError::decrypt(e)).await;
+            return;
+        }
+    };
+    // 乱序到达的旧密钥不覆盖新密钥
+    if let Some(current) = group.peer_keys.get(&key.sender) {
+        if current.generation > key.generation {
+            return;
+        }
+    }
+    group.peer_keys.insert(
+        key.sender.clone(),
+        SenderKey {
+            generation: key.generation,
+            key: sender_key,
+        },
+    );
+    save(&gctx, &store).await;
+}
+
+pub async fn group_message_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    if routing::relay_if_not_for_us(ctx.clone(), &frame).await {
+        return;
+    }
+    let peer = frame.body.address.clone();
+    let message: GroupMessageCommand =
+        match error::decode_command("GroupMessageCommand", &frame, &cmd.data) {
+            Ok(c) => c,
+            Err(e) => {
+                error::report(&ctx, &peer, e).await;
+                return;
+            }
+        };
+    let Some((gctx, _)) = local_receiver(&ctx, &message.receiver).await else {
+        return;
+    };
+    let Ok(groups) = shared(&gctx).await else {
+        return;
+    };
+    let (group_name, opened) = {
+        let store = groups.lock().await;
+        let Some(group) = store.groups.get(&message.group_id) else {
+            return;
+        };
+        let Some(member) = group.state.member(&message.sender) else {
+            tracing::warn!(
+                "Group message from non-member {} for group {}",
+                message.sender,
+                message.group_id
+            );
+            return;
+        };
+        if let Err(e) = verify(
+            &member.public_key,
+            &message_digest(&message),
+            &message.signature,
+        ) {
+            drop(store);
+            error::report(&ctx, &peer, e).await;
+            return;
+        }
+        let Some(sender_key) = group
+            .peer_keys
+            .get(&message.sender)
+            .filter(|k| k.generation == message.generation)
+        else {
+            tracing::warn!(
+                "No sender key {} of {} for group {}, message dropped",
+                message.generation,
+                message.sender,
+                group.state.name
+            );
+            return;
+        };
+        let aad = message_aad(&message.group_id, &message.sender, message.generation);
+        (
+            group.state.name.clone(),
+            open(&sender_key.key, &message.nonce, &aad, &message.ciphertext),
+        )
+    };
+    let plaintext = match opened.and_then(|p| <GroupPlaintext as Codec>::decode(&p)) {
+        Ok(p) => p,
+        Err(e) => {
+            error::report(&ctx, &peer, ProtocolError::decrypt(e)).await;
+            return;
+        }
+    };
+    if let Some(seen) = gctx.get::<SeenMessages>().await {
+        let key = format!(
+            "group:{}:{}:{}",
+            message.group_id,
+            message.sender,
+            hex(&message.nonce)
+        );
+        if !seen.first_seen(key, SEEN_GROUP_MAX) {
+            return;
+        }
+    }
+
+    tracing::info!(
+        "👥 [{}] {}: {}",
+        group_name,
+        message.sender,
+        plaintext.content
+    );
+    let mut inbound = InboundMessage {
+        from: message.sender,
+        group_id: Some(message.group_id.clone()),
+        content: plaintext.content,
+        timestamp: plaintext.timestamp,
+        annotations: BTreeMap::new(),
+    };
+    if let Some(node) = gctx.get::<Arc<P2pNode>>().await {
+        match node.registry.filters().apply(&gctx, inbound).await {
+            Some(m) => inbound = m,
+            None => return,
+        }
+    }
+    events::publish(
+        &gctx,
+        NodeEvent::GroupMessage(IncomingGroupMessage {
+            group_id: message.group_id,
+            group: group_name,
+            from: inbound.from,
+            content: inbound.content,
+            timestamp: inbound.timestamp,
+            annotations: inbound.annotations,
+        }),
+    )
+    .await;
+}
@@ -0,0 +1,409 @@
+//! HTTP 隧道：经 P2P 网络访问其它节点暴露的 Web 服务
+//!
+//! 节点用 `--expose <url>` 暴露一个本地 HTTP 服务。其它节点的 Web 服务器把
+//! `/peer/<address>/<path>` 的请求封装为 `HttpRequest` 发往目标节点（非直连时经中继），
+//! 目标节点只会把请求转发到暴露的服务之下（拒绝 `..` 路径段），再把响应封装为 `HttpResponse`
+//! 原路返回。请求与响应体都受 `HTTP_TUNNEL_MAX_BODY` 限制，逐跳头部不转发。
+//! 帧带节点签名但不做端到端加密，传输敏感内容时应由服务本身加密。
+
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use aex::{
+    connection::{context::Context, global::GlobalContext},
+    tcp::types::Codec,
+};
+use bincode::{Decode, Encode};
+use dashmap::DashMap;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, oneshot};
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::node::Node as P2pNode;
+use crate::protocols::capabilities::{self, CAP_HTTP_TUNNEL, CAP_RELAY};
+use crate::protocols::command::{Action, Entity, P2PCommand};
+use crate::protocols::commands::message::SeenMessages;
+use crate::protocols::commands::presence;
+use crate::protocols::error;
+use crate::protocols::frame::P2PFrame;
+use crate::protocols::routing;
+
+/// 请求体与响应体的上限
+pub const HTTP_TUNNEL_MAX_BODY: usize = 16 * 1024 * 1024;
+/// 等待目标节点响应的时间
+pub const HTTP_TUNNEL_TIMEOUT_SECS: u64 = 30;
+/// 去重记录上限
+const SEEN_TUNNEL_MAX: usize = 10_000;
+
+/// 等待响应的隧道请求：request_id → (目标地址, oneshot)
+pub type PendingHttpRequests = Arc<DashMap<u64, (String, oneshot::Sender<HttpResponseCommand>)>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct HttpRequestCommand {
+    pub sender: String,
+    pub receiver: String,
+    pub request_id: u64,
+    pub method: String,
+    /// 相对于暴露服务的路径，包括查询串
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Codec for HttpRequestCommand {}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct HttpResponseCommand {
+    pub sender: String,
+    pub receiver: String,
+    pub request_id: u64,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Codec for HttpResponseCommand {}
+
+impl HttpResponseCommand {
+    /// 由目标节点生成的错误响应（纯文本）
+    pub fn error(request: &HttpRequestCommand, status: u16, message: &str) -> Self {
+        Self {
+            sender: request.receiver.clone(),
+            receiver: request.sender.clone(),
+            request_id: request.request_id,
+            status,
+            headers: vec![("Content-Type".into(), "text/plain; charset=utf-8".into())],
+            body: message.as_bytes().to_vec(),
+        }
+    }
+}
+
+/// 本节点暴露的 HTTP 服务，保存在 GlobalContext 中
+#[derive(Debug, Clone)]
+pub struct ExposedService {
+    pub base: reqwest::Url,
+    client: reqwest::Client,
+}
+
+impl FromStr for ExposedService {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let base = reqwest::Url::parse(s)?;
+        if !matches!(base.scheme(), "http" | "https") {
+            anyhow::bail!("exposed service must be an http(s) url: {}", s);
+        }
+        if base.query().is_some() || base.fragment().is_some() {
+            anyhow::bail!(
+                "exposed service url must not have a query or fragment: {}",
+                s
+            );
+        }
+        // 重定向原样交给请求方，由请求方的浏览器决定是否跟随
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(Duration::from_secs(HTTP_TUNNEL_TIMEOUT_SECS))
+            .build()?;
+        Ok(Self { base, client })
+    }
+}
+
+impl ExposedService {
+    /// 把隧道请求的路径映射到暴露的服务之下，含 `..` 路径段时返回 `None`
+    pub fn target_url(&self, path: &str) -> Option<reqwest::Url> {
+        let (path, query) = match path.split_once('?') {
+            Some((p, q)) => (p, Some(q)),
+            None => (path, None),
+        };
+        let path = path.split('#').next().unwrap_or("");
+        let decoded = crate::web::static_files::percent_decode(path)?;
+        if decoded.split(['/', '\\']).any(|segment| segment == "..") {
+            return None;
+        }
+        let mut url = self.base.clone();
+        let joined = format!(
+            "{}/{}",
+            self.base.path().trim_end_matches('/'),
+            path.trim_start_matches('/')
+        );
+        url.set_path(&joined);
+        url.set_query(query);
+        Some(url)
+    }
+}
+
+/// 把 `<prefix>/<address>/<path>` 拆成目标地址与转发的路径
+pub fn split_peer_path(prefix: &str, path: &str) -> Option<(String, String)> {
+    let rest = path.strip_prefix(prefix.trim_end_matches('/'))?;
+    let rest = rest.strip_prefix('/')?;
+    let end = rest.find(['/', '?']).unwrap_or(rest.len());
+    let (address, tail) = rest.split_at(end);
+    if address.is_empty() {
+        return None;
+    }
+    let tail = match tail.strip_prefix('?') {
+        Some(query) => format!("/?{}", query),
+        None if tail.is_empty() => "/".to_string(),
+        None => tail.to_string(),
+    };
+    Some((address.to_string(), tail))
+}
+
+/// 逐跳头部只对一段连接有效，不经隧道转发
+pub fn is_hop_by_hop(name: &str) -> bool {
+    matches!(
+        name.to_ascii_lowercase().as_str(),
+        "connection"
+            | "keep-alive"
+            | "proxy-authenticate"
+            | "proxy-authorization"
+            | "te"
+            | "trailer"
+            | "transfer-encoding"
+            | "upgrade"
+            | "host"
+            | "content-length"
+    )
+}
+
+/// 经隧道向 `receiver`（地址或别名）发送 HTTP 请求并等待响应，超时返回 `None`
+pub async fn request(
+    gctx: Arc<GlobalContext>,
+    receiver: &str,
+    method: &str,
+    path: &str,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+) -> anyhow::Result<Option<HttpResponseCommand>> {
+    if body.len() > HTTP_TUNNEL_MAX_BODY {
+        anyhow::bail!(
+            "Request body too large: {} > {}",
+            body.len(),
+            HTTP_TUNNEL_MAX_BODY
+        );
+    }
+    let receiver = match gctx.get::<Arc<P2pNode>>().await {
+        Some(node) => node.registry.resolve_alias(receiver),
+        None => receiver.to_string(),
+    };
+    let Some(identity) = gctx.get::<FreeWebMovementAddress>().await else {
+        anyhow::bail!("Address not set");
+    };
+    let Some(pending) = gctx.get::<PendingHttpRequests>().await else {
+        anyhow::bail!("PendingHttpRequests not set in GlobalContext");
+    };
+
+    let request_id: u64 = rand::thread_rng().r#gen();
+    let command = HttpRequestCommand {
+        sender: identity.to_string(),
+        receiver: receiver.clone(),
+        request_id,
+        method: method.to_string(),
+        path: path.to_string(),
+        headers: headers
+            .into_iter()
+            .filter(|(name, _)| !is_hop_by_hop(name))
+            .collect(),
+        body,
+    };
+    let (tx, rx) = oneshot::channel();
+    pending.insert(request_id, (receiver.clone(), tx));
+
+    let sent = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let sent_in_closure = sent.clone();
+    let receiver_for_closure = receiver.clone();
+    gctx.manager
+        .notify(receiver.as_bytes(), |entries| async move {
+            let Some(ctx) = entries.into_iter().find_map(|e| e.context.clone()) else {
+                return;
+            };
+            // 直连目标须支持隧道；经由对端中继时对端须支持中继
+            let peer: Option<String> = ctx.lock().await.get();
+            let required = match peer {
+                Some(peer) if peer != receiver_for_closure => CAP_RELAY,
+                _ => CAP_HTTP_TUNNEL,
+            };
+            if !capabilities::peer_supports(&ctx, required).await {
+                tracing::warn!(
+                    "Peer does not support {}",
+                    capabilities::feature_names(required).join(",")
+                );
+                return;
+            }
+            match P2PFrame::send(
+                ctx,
+                &Some(command),
+                Entity::Http,
+                Action::HttpRequest,
+                false,
+            )
+            .await
+            {
+                Ok(()) => sent_in_closure.store(true, std::sync::atomic::Ordering::SeqCst),
+                Err(e) => tracing::error!("Failed to send tunnel request: {:?}", e),
+            }
+        })
+        .await;
+
+    if !sent.load(std::sync::atomic::Ordering::SeqCst) {
+        pending.remove(&request_id);
+        let reachability = presence::reachability(&gctx, &receiver).await;
+        anyhow::bail!("{} is not connected ({})", receiver, reachability);
+    }
+    match tokio::time::timeout(Duration::from_secs(HTTP_TUNNEL_TIMEOUT_SECS), rx).await {
+        Ok(Ok(response)) => Ok(Some(response)),
+        _ => {
+            pending.remove(&request_id);
+            Ok(None)
+        }
+    }
+}
+
+/// 把隧道请求交给本节点暴露的服务
+async fn forward(service: &ExposedService, request: &HttpRequestCommand) -> HttpResponseCommand {
+    let Some(url) = service.target_url(&request.path) else {
+        return HttpResponseCommand::error(request, 400, "Invalid path");
+    };
+    let Ok(method) = reqwest::Method::from_bytes(request.method.as_bytes()) else {
+        return HttpResponseCommand::error(request, 405, "Invalid method");
+    };
+    let mut builder = service.client.request(method, url);
+    for (name, value) in &request.headers {
+        if !is_hop_by_hop(name) {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+    }
+    let response = match builder.body(request.body.clone()).send().await {
+        Ok(r) => r,
+        Err(e) => {
+            tracing::warn!("Exposed service request failed: {}", e);
+            return HttpResponseCommand::error(request, 502, "Exposed service unavailable");
+        }
+    };
+    if response
+        .content_length()
+        .is_some_and(|len| len > HTTP_TUNNEL_MAX_BODY as u64)
+    {
+        return HttpResponseCommand::error(request, 502, "Response too large");
+    }
+    let status = response.status().as_u16();
+    let headers: Vec<(String, String)> = response
+        .headers()
+        .iter()
+        .filter(|(name, _)| !is_hop_by_hop(name.as_str()))
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    let body = match response.bytes().await {
+        Ok(b) if b.len() <= HTTP_TUNNEL_MAX_BODY => b.to_vec(),
+        Ok(_) => return HttpResponseCommand::error(request, 502, "Response too large"),
+        Err(e) => {
+            tracing::warn!("Failed to read exposed service response: {}", e);
+            return HttpResponseCommand::error(request, 502, "Exposed service unavailable");
+        }
+    };
+    HttpResponseCommand {
+        sender: request.receiver.clone(),
+        receiver: request.sender.clone(),
+        request_id: request.request_id,
+        status,
+        headers,
+        body,
+    }
+}
+
+pub async fn http_request_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    // 目标不是本节点：按路由表中继
+    if routing::relay_if_not_for_us(ctx.clone(), &frame).await {
+        return;
+    }
+    let request: HttpRequestCommand =
+        match error::decode_command("HttpRequestCommand", &frame, &cmd.data) {
+            Ok(r) => r,
+            Err(e) => {
+                error::report(&ctx, &frame.body.address, e).await;
+                return;
+            }
+        };
+    if request.sender != frame.body.address {
+        tracing::warn!(
+            "Tunnel request sender {} does not match frame signer {}",
+            request.sender,
+            frame.body.address
+        );
+        return;
+    }
+    let gctx = ctx.lock().await.global.clone();
+    match gctx.get::<FreeWebMovementAddress>().await {
+        Some(address) if address.to_string() == request.receiver => {}
+        _ => return,
+    }
+    // 泛洪中继可能从多条路径送达同一请求
+    if let Some(seen) = gctx.get::<SeenMessages>().await {
+        let key = format!("http:{}:{}", request.sender, request.request_id);
+        if !seen.first_seen(key, SEEN_TUNNEL_MAX) {
+            return;
+        }
+    }
+    tracing::info!(
+        "🚇 Tunnel request {} {} from {}",
+        request.method,
+        request.path,
+        request.sender
+    );
+
+    // 转发可能耗时较久，不阻塞该连接上的其它帧
+    tokio::spawn(async move {
+        let response = match gctx.get::<ExposedService>().await {
+            Some(_) if request.body.len() > HTTP_TUNNEL_MAX_BODY => {
+                HttpResponseCommand::error(&request, 413, "Request body too large")
+            }
+            Some(service) => forward(&service, &request).await,
+            None => HttpResponseCommand::error(&request, 404, "No service exposed"),
+        };
+        if let Err(e) = P2PFrame::send(
+            ctx,
+            &Some(response),
+            Entity::Http,
+            Action::HttpResponse,
+            false,
+        )
+        .await
+        {
+            tracing::error!(
+                "Failed to send tunnel response to {}: {:?}",
+                request.sender,
+                e
+            );
+        }
+    });
+}
+
+pub async fn http_response_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    if routing::relay_if_not_for_us(ctx.clone(), &frame).await {
+        return;
+    }
+    let response: HttpResponseCommand =
+        match error::decode_command("HttpResponseCommand", &frame, &cmd.data) {
+            Ok(r) => r,
+            Err(e) => {
+                error::report(&ctx, &frame.body.address, e).await;
+                return;
+            }
+        };
+    let gctx = ctx.lock().await.global.clone();
+    let Some(pending) = gctx.get::<PendingHttpRequests>().await else {
+        return;
+    };
+    // 只接受由请求目标签名的响应
+    if response.sender != frame.body.address {
+        return;
+    }
+    let matched = pending
+        .remove_if(&response.request_id, |_, (target, _)| {
+            *target == response.sender
+        })
+        .map(|(_, (_, tx))| tx);
+    if let Some(tx) = matched {
+        let _ = tx.send(response);
+    }
+}
@@ -0,0 +1,358 @@
+//! 握手身份证明（挑战-应答）
+//!
+//! 帧签名只能证明发送方持有 `public_key` 对应的私钥，被截获的帧仍可能在另一条连接上重放，
+//! 连接本身也没有和声称的地址绑定。OnLine / OnLineAck 之后双方各向对端发送一个随机挑战，
+//! 对端用身份私钥对 `标签 | 挑战 | 挑战方地址 | 应答方地址 | 应答方公钥` 的哈希签名作答。
+//!
+//! 挑战方校验：应答中的地址与握手时声称的地址一致、该地址由应答中的公钥推导得出
+//! （见 [`crate::secure_link::derive_address`]）、公钥与握手帧使用的公钥一致、签名有效。
+//! 任何一项不符，或在 `CHALLENGE_TIMEOUT_SECS` 内没有应答，都会断开连接。验证通过后记录
+//! 地址与公钥的绑定（`IdentityBindings`），并在连接上标记 [`VerifiedPeer`]。
+//!
+//! 验证完成之前，连接上只接受握手与身份证明本身需要的命令（见 [`admit`]），其余帧被丢弃。
+//! 验证完成之后，直连帧（没有 `destination`，不会被中继）的作者必须是 [`VerifiedPeer`]，
+//! 带逐跳签名的中继帧的转发节点也必须是它；否则对端可以用自己的密钥签名、冒用任意地址。
+//! 主题帧由订阅者 / 发布者签名、经各节点原样转发，作者是否与命令一致由 topic 模块校验。
+
+use std::{sync::Arc, time::Duration};
+
+use aex::{
+    connection::{context::Context, global::GlobalContext},
+    tcp::types::Codec,
+};
+use bincode::{Decode, Encode};
+use dashmap::DashMap;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::journal;
+use crate::node::Node as P2pNode;
+use crate::protocols::command::{Action, Entity, P2PCommand};
+use crate::protocols::error::{self, ProtocolError};
+use crate::protocols::frame::P2PFrame;
+use crate::secure_link;
+use crate::transport::Connection;
+
+const IDENTITY_PROOF_LABEL: &[u8] = b"zz-p2p-identity-v1";
+/// 等待应答的最长时间
+pub const CHALLENGE_TIMEOUT_SECS: u64 = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct IdentityChallengeCommand {
+    pub challenge: [u8; 32],
+}
+
+impl Codec for IdentityChallengeCommand {}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct IdentityProofCommand {
+    pub address: String,
+    pub public_key: Vec<u8>,
+    /// 对挑战记录哈希的签名
+    pub signature: Vec<u8>,
+}
+
+impl Codec for IdentityProofCommand {}
+
+/// 连接上等待应答的挑战，保存在连接 Context 中
+#[derive(Debug, Clone)]
+pub struct PendingChallenge {
+    pub challenge: [u8; 32],
+    /// 对端在握手中声称的地址
+    pub expected: String,
+    /// 对端握手帧使用的公钥
+    pub public_key: Vec<u8>,
+}
+
+/// 已通过挑战-应答验证的对端地址，保存在连接 Context 中
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifiedPeer(pub String);
+
+/// 地址 → 公钥绑定，保存在 GlobalContext 中
+pub type IdentityBindings = Arc<DashMap<String, Vec<u8>>>;
+
+/// 应答方需要签名的挑战记录哈希
+pub fn transcript(
+    challenge: &[u8; 32],
+    challenger: &str,
+    prover: &str,
+    public_key: &[u8],
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(IDENTITY_PROOF_LABEL);
+    hasher.update(challenge);
+    for part in [challenger.as_bytes(), prover.as_bytes(), public_key] {
+        hasher.update((part.len() as u32).to_be_bytes());
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+pub fn new_challenge() -> [u8; 32] {
+    let mut challenge = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut challenge);
+    challenge
+}
+
+/// 以 `identity` 的身份应答 `challenger` 发来的挑战
+pub fn prove(
+    identity: &FreeWebMovementAddress,
+    challenger: &str,
+    challenge: &[u8; 32],
+) -> IdentityProofCommand {
+    let address = identity.to_string();
+    let public_key = identity.public_key.to_bytes().to_vec();
+    let digest = transcript(challenge, challenger, &address, &public_key);
+    let signature = FreeWebMovementAddress::sign_message(&identity.private_key, &digest)
+        .serialize_compact()
+        .to_vec();
+    IdentityProofCommand {
+        address,
+        public_key,
+        signature,
+    }
+}
+
+/// 校验对端对 `pending` 挑战的应答
+pub fn verify_proof(
+    proof: &IdentityProofCommand,
+    pending: &PendingChallenge,
+    challenger: &str,
+) -> Result<(), ProtocolError> {
+    if proof.address != pending.expected || proof.public_key != pending.public_key {
+        return Err(ProtocolError::IdentityMismatch {
+            claimed: pending.expected.clone(),
+        });
+    }
+    let derived =
+        secure_link::derive_address(&proof.public_key).ok_or(ProtocolError::InvalidPublicKey)?;
+    if derived != proof.address {
+        return Err(ProtocolError::IdentityMismatch {
+            claimed: proof.address.clone(),
+        });
+    }
+    bitcoin::secp256k1::ecdsa::Signature::from_compact(&proof.signature)
+        .map_err(|_| ProtocolError::MalformedSignature)?;
+
+    let digest = transcript(
+        &pending.challenge,
+        challenger,
+        &proof.address,
+        &proof.public_key,
+    );
+    let public_key = FreeWebMovementAddress::to_public_key(&proof.public_key);
+    let signature = FreeWebMovementAddress::to_signature(&proof.signature);
+    if !FreeWebMovementAddress::verify_message(&public_key, &digest, &signature) {
+        return Err(ProtocolError::BadSignature);
+    }
+    Ok(())
+}
+
+/// 记录地址与公钥的绑定。只接受由该公钥推导出的地址，因此绑定不依赖先到先得
+pub fn bind(
+    bindings: &DashMap<String, Vec<u8>>,
+    address: &str,
+    public_key: &[u8],
+) -> Result<(), ProtocolError> {
+    if secure_link::derive_address(public_key).as_deref() != Some(address) {
+        return Err(ProtocolError::IdentityMismatch {
+            claimed: address.to_string(),
+        });
+    }
+    bindings.insert(address.to_string(), public_key.to_vec());
+    Ok(())
+}
+
+/// 身份验证完成之前也要处理的命令：握手、身份证明、下线 / 繁忙通知与错误回复
+pub fn allowed_unverified(entity: Entity, action: Action) -> bool {
+    match entity {
+        Entity::Node => matches!(
+            action,
+            Action::OnLine
+                | Action::OnLineAck
+                | Action::OffLine
+                | Action::Busy
+                | Action::ObservedAddress
+                | Action::IdentityChallenge
+                | Action::IdentityProof
+        ),
+        Entity::Error => true,
+        _ => false,
+    }
+}
+
+/// 帧是否由这条连接上验证过的对端 `verified` 发出：
+/// - 带逐跳签名时，转发节点必须是对端；
+/// - 直连帧的作者必须是对端；中继帧（有 `destination`）的作者可以是其他节点；
+/// - 身份轮换时对端以新地址发送继任记录，由 successor 模块按绑定校验；
+/// - 主题帧由订阅者 / 发布者签名、逐跳原样转发，由 topic 模块校验作者
+pub fn sent_by(verified: &str, frame: &P2PFrame, cmd: &P2PCommand) -> bool {
+    if let Some(hop) = &frame.hop {
+        return hop.address == verified;
+    }
+    frame.body.address == verified
+        || frame.body.destination.is_some()
+        || (cmd.entity == Entity::Node && cmd.action == Action::IdentitySuccessor)
+        || cmd.entity == Entity::Topic
+}
+
+/// 连接尚未通过挑战-应答验证时丢弃握手以外的命令；验证之后丢弃不是由该对端发出的帧
+pub async fn admit(ctx: &Arc<Mutex<Context>>, frame: &P2PFrame, cmd: &P2PCommand) -> bool {
+    let verified = ctx.lock().await.get::<VerifiedPeer>();
+    match verified {
+        Some(VerifiedPeer(peer)) if sent_by(&peer, frame, cmd) => true,
+        Some(VerifiedPeer(peer)) => {
+            tracing::warn!(
+                "🚫 Dropping {:?}/{:?} claiming {} on the connection verified as {}",
+                cmd.entity,
+                cmd.action,
+                frame.body.address,
+                peer
+            );
+            false
+        }
+        None if allowed_unverified(cmd.entity, cmd.action) => true,
+        None => {
+            tracing::debug!(
+                "Dropping {:?}/{:?} from {} before identity verification",
+                cmd.entity,
+                cmd.action,
+                frame.body.address
+            );
+            false
+        }
+    }
+}
+
+async fn local_identity(gctx: &Arc<GlobalContext>) -> Option<FreeWebMovementAddress> {
+    gctx.get::<FreeWebMovementAddress>().await
+}
+
+/// 断开未能证明身份的连接
+async fn reject(ctx: &Arc<Mutex<Context>>, peer: &str, err: ProtocolError) {
+    tracing::warn!("🚫 Identity verification failed for {}: {}", peer, err);
+    journal::record_handshake_failed(ctx, peer, &err.to_string()).await;
+    error::report(ctx, peer, err).await;
+    ctx.close().await;
+    let gctx = ctx.lock().await.global.clone();
+    if let Some(node) = gctx.get::<Arc<P2pNode>>().await {
+        node.registry.disconnect(peer);
+    }
+}
+
+/// 握手完成后向对端发起挑战；超时未应答则断开
+pub async fn send_challenge(ctx: Arc<Mutex<Context>>, frame: &P2PFrame) {
+    let pending = PendingChallenge {
+        challenge: new_challenge(),
+        expected: frame.body.address.clone(),
+        public_key: frame.body.public_key.clone(),
+    };
+    let cmd = IdentityChallengeCommand {
+        challenge: pending.challenge,
+    };
+    ctx.lock().await.set(pending.clone());
+    if let Err(e) = P2PFrame::send(
+        ctx.clone(),
+        &Some(cmd),
+        Entity::Node,
+        Action::IdentityChallenge,
+        false,
+    )
+    .await
+    {
+        tracing::error!("Failed to send identity challenge: {:?}", e);
+        return;
+    }
+
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(CHALLENGE_TIMEOUT_SECS)).await;
+        let verified = ctx.lock().await.get::<VerifiedPeer>().is_some();
+        if !verified {
+            // 只关闭这条连接：对端可能已经通过其它连接完成了验证
+            tracing::warn!(
+                "🚫 {} did not answer identity challenge within {}s",
+                pending.expected,
+                CHALLENGE_TIMEOUT_SECS
+            );
+            ctx.close().await;
+        }
+    });
+}
+
+pub async fn identity_challenge_handler(
+    ctx: Arc<Mutex<Context>>,
+    frame: P2PFrame,
+    cmd: P2PCommand,
+) {
+    let challenge: IdentityChallengeCommand =
+        match error::decode_command("IdentityChallengeCommand", &frame, &cmd.data) {
+            Ok(c) => c,
+            Err(e) => {
+                error::report(&ctx, &frame.body.address, e).await;
+                return;
+            }
+        };
+    let gctx = ctx.lock().await.global.clone();
+    let Some(identity) = local_identity(&gctx).await else {
+        tracing::error!("FreeWebMovementAddress not set in GlobalContext");
+        return;
+    };
+    let proof = prove(&identity, &frame.body.address, &challenge.challenge);
+    if let Err(e) = P2PFrame::send(
+        ctx,
+        &Some(proof),
+        Entity::Node,
+        Action::IdentityProof,
+        false,
+    )
+    .await
+    {
+        tracing::error!("Failed to send identity proof: {:?}", e);
+    }
+}
+
+pub async fn identity_proof_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    let peer = frame.body.address.clone();
+    let proof: IdentityProofCommand =
+        match error::decode_command("IdentityProofCommand", &frame, &cmd.data) {
+            Ok(c) => c,
+            Err(e) => {
+                error::report(&ctx, &peer, e).await;
+                return;
+            }
+        };
+    let (gctx, pending) = {
+        let guard = ctx.lock().await;
+        (guard.global.clone(), guard.get::<PendingChallenge>())
+    };
+    let Some(pending) = pending else {
+        error::report(
+            &ctx,
+            &peer,
+            ProtocolError::decode("IdentityProofCommand", "no challenge pending"),
+        )
+        .await;
+        return;
+    };
+    let Some(identity) = local_identity(&gctx).await else {
+        tracing::error!("FreeWebMovementAddress not set in GlobalContext");
+        return;
+    };
+
+    let mut result = verify_proof(&proof, &pending, &identity.to_string());
+    if result.is_ok() {
+        if let Some(bindings) = gctx.get::<IdentityBindings>().await {
+            result = bind(&bindings, &proof.address, &proof.public_key);
+        }
+    }
+    match result {
+        Ok(()) => {
+            tracing::info!("🪪 Identity of {} verified", proof.address);
+            ctx.lock().await.set(VerifiedPeer(proof.address));
+        }
+        Err(e) => reject(&ctx, &pending.expected, e).await,
+    }
+}
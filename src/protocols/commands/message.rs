@@ -1,23 +1,71 @@
-use std::sync::Arc;
-
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use crate::clock;
+use crate::events::{self, NodeEvent};
+use crate::identities::{self, LocalIdentity};
+use crate::protocols::broadcast;
 use crate::protocols::command::{Action, Entity, P2PCommand};
+use crate::protocols::commands::error as remote_error;
+use crate::protocols::commands::rekey;
+use crate::protocols::error::{self, ProtocolError};
+use crate::protocols::filter::InboundMessage;
 use crate::protocols::frame::P2PFrame;
+use crate::protocols::ordering::{self, Push, ReorderBuffer, SharedOutboundSequences};
+use crate::protocols::routing;
+use crate::scheduler::Scheduler;
 use aex::connection::context::Context;
+use aex::connection::global::GlobalContext;
 use aex::tcp::types::Codec;
 use aex::time::SystemTime;
 
 use bincode::{Decode, Encode};
+use dashmap::{DashMap, DashSet};
 
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
 use zz_account::address::FreeWebMovementAddress;
 
-/// 已处理消息的去重集合（存储消息内容的 SHA-256 十六进制摘要）
-pub type SeenMessages = Arc<std::sync::Mutex<std::collections::HashSet<String>>>;
+/// 去重集合。按 key 分片加锁，各连接的收发互不串行
+#[derive(Debug, Default)]
+pub struct SeenSet {
+    entries: DashSet<String>,
+    /// 近似条目数，避免每次插入都遍历所有分片
+    count: AtomicUsize,
+}
+
+impl SeenSet {
+    /// 记录 `key`：返回 true 表示首次见到；超过 `max` 条后清空重新计数
+    pub fn first_seen(&self, key: String, max: usize) -> bool {
+        if !self.entries.insert(key) {
+            return false;
+        }
+        if self.count.fetch_add(1, Ordering::Relaxed) >= max {
+            self.entries.clear();
+            self.count.store(0, Ordering::Relaxed);
+        }
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// 已处理消息的去重集合（消息内容的 SHA-256 十六进制摘要，以及 ack / relay / 分片等 key）
+pub type SeenMessages = Arc<SeenSet>;
 
 /// 待确认的发送请求：request_id → oneshot (true=已送达)
-pub type PendingAcks =
-    Arc<Mutex<std::collections::HashMap<u64, tokio::sync::oneshot::Sender<bool>>>>;
+pub type PendingAcks = Arc<DashMap<u64, tokio::sync::oneshot::Sender<bool>>>;
 
 const SEEN_MESSAGES_MAX: usize = 10_000;
 
@@ -40,6 +88,11 @@ pub fn next_request_id() -> u64 {
     NEXT_REQUEST_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
 }
 
+/// 保证之后分配的 request_id 不小于 `min_next`（重放 WAL 时使用）
+pub fn reserve_request_ids(min_next: u64) {
+    NEXT_REQUEST_ID.fetch_max(min_next, std::sync::atomic::Ordering::Relaxed);
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
 pub struct MessageCommand {
     pub sender: String,
@@ -47,6 +100,10 @@ pub struct MessageCommand {
     pub request_id: u64,
     pub timestamp: u128,
     pub message: String,
+    /// 发送方本次启动的标识，变大表示发送方重启过
+    pub epoch: u64,
+    /// 发送方发往该接收方的序号（从 1 开始，0 表示未编号）
+    pub seq: u64,
 }
 
 impl Codec for MessageCommand {}
@@ -65,7 +122,109 @@ impl Codec for MessageAckCommand {}
 pub struct IncomingMessage {
     pub from: String,
     pub content: String,
+    /// 发送时间（毫秒），已按发送方的时钟偏差换算为本地时间
     pub timestamp: u128,
+    /// 接收端过滤器添加的标注
+    pub annotations: BTreeMap<String, String>,
+}
+
+/// 按发送方排序后再投递给上层应用的重排缓冲区
+pub type InboundReorder = Arc<std::sync::Mutex<ReorderBuffer<IncomingMessage>>>;
+
+fn lock_reorder(
+    reorder: &InboundReorder,
+) -> std::sync::MutexGuard<'_, ReorderBuffer<IncomingMessage>> {
+    match reorder.lock() {
+        Ok(g) => g,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// 经过接收端过滤器，返回仍需投递的消息
+async fn filter_for_app(
+    gctx: &Arc<GlobalContext>,
+    messages: Vec<IncomingMessage>,
+) -> Vec<IncomingMessage> {
+    let Some(node) = gctx.get::<Arc<crate::node::Node>>().await else {
+        return messages;
+    };
+    let filters = node.registry.filters();
+    if filters.is_empty() {
+        return messages;
+    }
+    let mut kept = Vec::with_capacity(messages.len());
+    for message in messages {
+        let inbound = InboundMessage {
+            from: message.from,
+            group_id: None,
+            content: message.content,
+            timestamp: message.timestamp,
+            annotations: message.annotations,
+        };
+        if let Some(m) = filters.apply(gctx, inbound).await {
+            kept.push(IncomingMessage {
+                from: m.from,
+                content: m.content,
+                timestamp: m.timestamp,
+                annotations: m.annotations,
+            });
+        }
+    }
+    kept
+}
+
+async fn deliver_to_app(gctx: &Arc<GlobalContext>, messages: Vec<IncomingMessage>) {
+    if messages.is_empty() {
+        return;
+    }
+    let messages = filter_for_app(gctx, messages).await;
+    if messages.is_empty() {
+        return;
+    }
+    for message in &messages {
+        events::publish(gctx, NodeEvent::Message(message.clone())).await;
+    }
+    match gctx
+        .get::<tokio::sync::mpsc::UnboundedSender<IncomingMessage>>()
+        .await
+    {
+        Some(tx) => {
+            for message in messages {
+                let _ = tx.send(message);
+            }
+            tracing::info!("  ✅ Message delivered to app channel");
+        }
+        None => tracing::warn!("  ⚠️  No app channel found for incoming message!"),
+    }
+}
+
+/// 后台定期投递等待缺失序号超时的消息
+pub fn schedule_reorder_flush(scheduler: &Scheduler, gctx: Arc<GlobalContext>) {
+    scheduler.register_interval(
+        "reorder_flush",
+        std::time::Duration::from_millis(ordering::REORDER_FLUSH_INTERVAL_MS),
+        move || {
+            let gctx = gctx.clone();
+            async move {
+                let Some(reorder) = gctx.get::<InboundReorder>().await else {
+                    return;
+                };
+                let config = ordering::config(&gctx).await;
+                let released: Vec<IncomingMessage> = lock_reorder(&reorder)
+                    .flush_expired(SystemTime::timestamp(), &config)
+                    .into_iter()
+                    .map(|(_, message)| message)
+                    .collect();
+                if !released.is_empty() {
+                    tracing::info!(
+                        "  ⏩ Delivering {} message(s) after reorder timeout",
+                        released.len()
+                    );
+                }
+                deliver_to_app(&gctx, released).await;
+            }
+        },
+    );
 }
 
 /// 向指定连接发送文本消息（不广播）
@@ -76,15 +235,73 @@ pub async fn send_text_message(
     ctx: Arc<Mutex<Context>>,
     message: &str,
 ) -> anyhow::Result<()> {
-    let command = MessageCommand {
+    // 启用 WAL 时先落盘再发送，崩溃后可补发
+    let gctx = { ctx.lock().await.global.clone() };
+    if let Some(wal) = gctx.get::<crate::wal::SharedWal>().await {
+        wal.append(&receiver, request_id, message)?;
+    }
+
+    let command = text_command(&gctx, sender, receiver, request_id, message).await;
+    P2PFrame::send(ctx, &Some(command), Entity::Message, Action::SendText, true).await
+}
+
+/// 以本进程的某个身份发送文本消息。附加身份只能发给直连对端，且不写入 WAL
+pub async fn send_text_message_as(
+    identity: &LocalIdentity,
+    receiver: String,
+    request_id: u64,
+    ctx: Arc<Mutex<Context>>,
+    message: &str,
+) -> anyhow::Result<()> {
+    if identity.is_primary() {
+        let sender = identity.address.to_string();
+        return send_text_message(sender, receiver, request_id, ctx, message).await;
+    }
+    let peer: Option<String> = ctx.lock().await.get();
+    if peer.as_deref() != Some(receiver.as_str()) {
+        anyhow::bail!(
+            "Identity {} can only message directly connected nodes",
+            identity.name
+        );
+    }
+    let gctx = { ctx.lock().await.global.clone() };
+    identities::ensure_session(&gctx, ctx.clone(), identity, &receiver).await?;
+
+    let sender = identity.address.to_string();
+    let command = text_command(&gctx, sender, receiver, request_id, message).await;
+    P2PFrame::send_as(
+        ctx,
+        &identity.address,
+        &Some(command),
+        Entity::Message,
+        Action::SendText,
+        true,
+    )
+    .await
+}
+
+pub(crate) async fn text_command(
+    gctx: &Arc<GlobalContext>,
+    sender: String,
+    receiver: String,
+    request_id: u64,
+    message: &str,
+) -> MessageCommand {
+    // 同一 request_id 经不同连接重发时沿用同一个序号，接收方据此去重
+    let (epoch, seq) = match gctx.get::<SharedOutboundSequences>().await {
+        Some(seqs) => (seqs.epoch(), seqs.seq_for(&receiver, request_id)),
+        None => (0, 0),
+    };
+
+    MessageCommand {
         sender,
         receiver,
         request_id,
         timestamp: SystemTime::timestamp(),
         message: message.to_string(),
-    };
-
-    P2PFrame::send(ctx, &Some(command), Entity::Message, Action::SendText, true).await
+        epoch,
+        seq,
+    }
 }
 
 /// 发送消息确认回执
@@ -100,7 +317,8 @@ pub async fn send_message_ack(
 /// 消息送达确认处理
 pub async fn message_ack_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
     let from = &frame.body.address;
-    let psk = match ctx.lock().await.global.paired_session_keys.clone() {
+    let gctx = { ctx.lock().await.global.clone() };
+    let psk = match gctx.paired_session_keys.clone() {
         Some(psk) => psk,
         None => {
             tracing::error!("PairedSessionKeys not set in GlobalContext");
@@ -108,21 +326,18 @@ pub async fn message_ack_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd:
         }
     };
 
-    let plaintext: Vec<u8> = {
-        let guard = psk.lock().await;
-        match guard.decrypt(&from.as_bytes().to_vec(), &cmd.data).await {
-            Ok(data) => data,
-            Err(e) => {
-                tracing::error!("Failed to decrypt message ack data: {:?}", e);
-                return;
-            }
+    let plaintext: Vec<u8> = match rekey::decrypt(&gctx, &psk, from, &cmd.data).await {
+        Ok(data) => data,
+        Err(e) => {
+            error::report(&ctx, from, ProtocolError::decrypt(e)).await;
+            return;
         }
     };
 
     let ack: MessageAckCommand = match Codec::decode(&plaintext) {
         Ok(c) => c,
         Err(e) => {
-            tracing::error!("❌ Invalid MessageAckCommand from {}: {:?}", from, e);
+            error::report(&ctx, from, ProtocolError::decode("MessageAckCommand", e)).await;
             return;
         }
     };
@@ -138,34 +353,31 @@ pub async fn message_ack_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd:
         guard.global.clone()
     };
 
+    if let Some(wal) = gctx.get::<crate::wal::SharedWal>().await {
+        match wal.complete(ack.request_id, from) {
+            Ok(true) => tracing::info!("  📝 WAL entry request_id={} completed", ack.request_id),
+            Ok(false) => {}
+            Err(e) => tracing::error!("Failed to mark WAL entry complete: {:?}", e),
+        }
+    }
+
     // 去重检查（用 ack:<request_id> 做 key）
     {
         let key = format!("ack:{}", ack.request_id);
         if let Some(seen) = gctx.get::<SeenMessages>().await {
-            let mut guard = match seen.lock() {
-                Ok(g) => g,
-                Err(poisoned) => {
-                    tracing::warn!("SeenMessages mutex poisoned, recovering");
-                    poisoned.into_inner()
-                }
-            };
-            if !guard.insert(key) {
+            if !seen.first_seen(key, SEEN_MESSAGES_MAX) {
                 tracing::info!(
                     "  ⏭️  Duplicate ACK request_id={}, skipping",
                     ack.request_id
                 );
                 return;
             }
-            if guard.len() > SEEN_MESSAGES_MAX {
-                guard.clear();
-            }
         }
     }
 
     let is_for_us = {
         if let Some(pending) = gctx.get::<PendingAcks>().await {
-            let mut guard = pending.lock().await;
-            if let Some(tx) = guard.remove(&ack.request_id) {
+            if let Some((_, tx)) = pending.remove(&ack.request_id) {
                 let _ = tx.send(true);
                 tracing::info!(
                     "  ✅ ACK matched pending request_id={}, delivery confirmed",
@@ -186,28 +398,38 @@ pub async fn message_ack_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd:
         tracing::info!("  🔄 Forwarding ACK request_id={} to peers", ack.request_id);
         let manager = gctx.manager.clone();
         let ack_cmd = ack.clone();
+        let gctx_for_send = gctx.clone();
         manager
             .forward(|entries| async move {
-                for entry in entries {
-                    if let Some(ctx) = &entry.context {
-                        let _ = P2PFrame::send(
-                            ctx.clone(),
-                            &Some(ack_cmd.clone()),
+                broadcast::send_all(&gctx_for_send, broadcast::all_peers(entries), |peer_ctx| {
+                    let ack_cmd = ack_cmd.clone();
+                    async move {
+                        P2PFrame::send(
+                            peer_ctx,
+                            &Some(ack_cmd),
                             Entity::Message,
                             Action::MessageAck,
                             true,
                         )
-                        .await;
+                        .await
                     }
-                }
+                })
+                .await
+                .log("forward ack");
             })
             .await;
     }
 }
 
 pub async fn message_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    // 目标不是本节点：按路由表中继，不解密
+    if routing::relay_if_not_for_us(ctx.clone(), &frame).await {
+        return;
+    }
     let from = &frame.body.address;
-    let psk = match ctx.lock().await.global.paired_session_keys.clone() {
+    // 发往附加身份的帧用该身份的会话密钥表
+    let gctx = { ctx.lock().await.global.clone() };
+    let psk = match identities::session_keys_for(&gctx, frame.body.destination.as_deref()).await {
         Some(psk) => psk,
         None => {
             tracing::error!("PairedSessionKeys not set in GlobalContext");
@@ -215,25 +437,32 @@ pub async fn message_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2P
         }
     };
 
-    let plaintext: Vec<u8> = {
-        let guard = psk.lock().await;
-        match guard.decrypt(&from.as_bytes().to_vec(), &cmd.data).await {
-            Ok(data) => data,
-            Err(e) => {
-                tracing::error!("Failed to decrypt message data: {:?}", e);
-                return;
-            }
+    let plaintext: Vec<u8> = match rekey::decrypt(&gctx, &psk, from, &cmd.data).await {
+        Ok(data) => data,
+        Err(e) => {
+            remote_error::bad_payload(&ctx, &frame, &cmd, ProtocolError::decrypt(e)).await;
+            return;
         }
     };
 
     let message: MessageCommand = match Codec::decode(&plaintext) {
         Ok(cmd) => cmd,
         Err(e) => {
-            tracing::error!("❌ Invalid MessageCommand from {}: {:?}", from, e);
+            let error = ProtocolError::decode("MessageCommand", e);
+            remote_error::bad_payload(&ctx, &frame, &cmd, error).await;
             return;
         }
     };
+    receive(ctx, gctx, from, message).await;
+}
 
+/// 投递解密后的文本消息：去重、排序、发送回执并通知上层应用
+pub(crate) async fn receive(
+    ctx: Arc<Mutex<Context>>,
+    gctx: Arc<GlobalContext>,
+    from: &str,
+    message: MessageCommand,
+) {
     tracing::info!(
         "📨 message_handler: received from {}, sender={}, receiver={}, msg_len={}",
         from,
@@ -242,8 +471,8 @@ pub async fn message_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2P
         message.message.len()
     );
 
-    // 去重检查
-    {
+    // 未编号的消息按内容去重；编号的消息在投递前按 (sender, seq) 去重
+    if message.seq == 0 {
         let gctx = { ctx.lock().await.global.clone() };
         let key = dedup_key(
             &message.sender,
@@ -252,23 +481,13 @@ pub async fn message_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2P
             message.timestamp,
         );
         if let Some(seen) = gctx.get::<SeenMessages>().await {
-            let mut guard = match seen.lock() {
-                Ok(g) => g,
-                Err(poisoned) => {
-                    tracing::warn!("SeenMessages mutex poisoned, recovering");
-                    poisoned.into_inner()
-                }
-            };
-            if !guard.insert(key) {
+            if !seen.first_seen(key, SEEN_MESSAGES_MAX) {
                 tracing::info!(
                     "  ⏭️  Duplicate message (receiver={}), skipping",
                     message.receiver
                 );
                 return;
             }
-            if guard.len() > SEEN_MESSAGES_MAX {
-                guard.clear();
-            }
         }
     }
 
@@ -290,24 +509,58 @@ pub async fn message_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2P
     };
 
     // 不处理自己发送的消息（避免被 peers 转发回来的回音）
-    if message.sender == address.to_string() {
+    if identities::is_local(&gctx, &message.sender).await {
         tracing::info!("  ⏭️  Skipping own message from {}", message.sender);
         return;
     }
 
-    // 通知上层应用收到消息
-    if receiver == address.to_string() {
+    // 通知上层应用收到消息（主身份或附加身份）
+    if identities::is_local(&gctx, &receiver).await {
         tracing::info!(
             "  ✅ Message IS for us ({}), delivering to app channel",
-            address
+            receiver
         );
 
-        // 发送回执给原始发送者
         let gctx = {
             let guard = ctx.lock().await;
             guard.global.clone()
         };
 
+        // 按发送方时钟偏差换算为本地时间，上层按时间排序时不受对端时钟影响
+        let incoming = IncomingMessage {
+            from: message.sender.clone(),
+            content: message.message.clone(),
+            timestamp: clock::local_time_of(&gctx, &message.sender, message.timestamp).await,
+            annotations: BTreeMap::new(),
+        };
+        let ready = match (message.seq, gctx.get::<InboundReorder>().await) {
+            (seq, Some(reorder)) if seq > 0 => {
+                let config = ordering::config(&gctx).await;
+                let pushed = lock_reorder(&reorder).push(
+                    &message.sender,
+                    message.epoch,
+                    seq,
+                    incoming,
+                    SystemTime::timestamp(),
+                    &config,
+                );
+                match pushed {
+                    Push::Duplicate => {
+                        tracing::info!(
+                            "  ⏭️  Duplicate message seq={} from {}, skipping",
+                            seq,
+                            message.sender
+                        );
+                        return;
+                    }
+                    Push::Deliver(ready) => ready,
+                }
+            }
+            _ => vec![incoming],
+        };
+
+        // 发送回执给原始发送者
+
         // 查找发送者的连接并发送回执
         if let Some(node) = gctx.get::<Arc<crate::node::Node>>().await {
             let seeds = node.registry.get_seeds_for_node(&sender_addr);
@@ -315,6 +568,7 @@ pub async fn message_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2P
                 let manager = gctx.manager.clone();
                 let seeds_for_direct = seeds.clone();
                 let req_id = request_id;
+                let gctx_for_fb = gctx.clone();
                 tokio::spawn(async move {
                     // 优先直连发送（全连接下必有 outbound 连接，且只发一份）
                     let mut ack_sent = false;
@@ -333,38 +587,28 @@ pub async fn message_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2P
                         let seeds_for_fb = seeds_for_direct.clone();
                         manager
                             .forward(|entries| async move {
-                                let mut sent_nodes: std::collections::HashSet<String> =
-                                    std::collections::HashSet::new();
-                                for entry in entries {
-                                    let matches_socket = seeds_for_fb.contains(&entry.addr);
-                                    let matches_peer = entry
-                                        .context
-                                        .as_ref()
-                                        .and_then(|ctx| {
-                                            ctx.try_lock().ok().and_then(|g| g.get::<String>())
-                                        })
-                                        .map(|addr| addr == sender_addr)
-                                        .unwrap_or(false);
-                                    if !matches_socket && !matches_peer {
-                                        continue;
-                                    }
-                                    // Dedup by node_id
-                                    let node_id = entry.node.read().await;
-                                    if let Some(n) = node_id.as_ref() {
-                                        let nid = String::from_utf8_lossy(&n.id).to_string();
-                                        if !sent_nodes.insert(nid) {
-                                            continue;
-                                        }
-                                    }
-                                    if let Some(ctx) = &entry.context {
-                                        let _ = send_message_ack(
-                                            sender_addr.clone(),
-                                            req_id,
-                                            ctx.clone(),
-                                        )
-                                        .await;
-                                    }
-                                }
+                                let matching: Vec<_> = entries
+                                    .into_iter()
+                                    .filter(|entry| {
+                                        seeds_for_fb.contains(&entry.addr)
+                                            || entry
+                                                .context
+                                                .as_ref()
+                                                .and_then(|ctx| {
+                                                    ctx.try_lock()
+                                                        .ok()
+                                                        .and_then(|g| g.get::<String>())
+                                                })
+                                                .is_some_and(|addr| addr == sender_addr)
+                                    })
+                                    .collect();
+                                // Dedup by node_id
+                                let targets = broadcast::unique_peers(matching, None, None).await;
+                                broadcast::send_all(&gctx_for_fb, targets, |ctx| {
+                                    send_message_ack(sender_addr.clone(), req_id, ctx)
+                                })
+                                .await
+                                .log("message ack");
                             })
                             .await;
                     }
@@ -382,61 +626,35 @@ pub async fn message_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2P
                     let manager = gctx_clone.manager.clone();
                     manager
                         .forward(|entries| async move {
-                            for entry in entries {
-                                if let Some(ctx) = &entry.context {
-                                    let _ =
-                                        send_message_ack(sender_clone.clone(), req_id, ctx.clone())
-                                            .await;
-                                }
-                            }
+                            broadcast::send_all(
+                                &gctx_clone,
+                                broadcast::all_peers(entries),
+                                |ctx| send_message_ack(sender_clone.clone(), req_id, ctx),
+                            )
+                            .await
+                            .log("broadcast ack");
                         })
                         .await;
                 });
             }
         }
 
-        if let Some(tx) = gctx
-            .get::<tokio::sync::mpsc::UnboundedSender<IncomingMessage>>()
-            .await
-        {
-            let _ = tx.send(IncomingMessage {
-                from: message.sender,
-                content: message.message.clone(),
-                timestamp: message.timestamp,
-            });
-            tracing::info!("  ✅ Message delivered to app channel");
-        } else {
-            tracing::warn!("  ⚠️  No app channel found for incoming message!");
+        if ready.is_empty() {
+            tracing::info!(
+                "  ⏸️  Message seq={} from {} buffered until earlier messages arrive",
+                message.seq,
+                message.sender
+            );
         }
+        deliver_to_app(&gctx, ready).await;
         return;
     } else {
-        // 全连接网络中目标节点与发送者直连，无需转发。
-        // 当网络变为非全连接时，取消注释以下 forward 代码进行路由转发。
+        // 需要中继的帧带有 destination，已在 relay_if_not_for_us 中处理；
+        // 走到这里说明发送方未指定 destination，直接丢弃。
         tracing::info!(
             "  ⏭️  Message not for us (us={}, receiver={}), dropping",
             address,
             receiver
         );
-
-        // let manager = { let guard = ctx.lock().await; guard.global.manager.clone() };
-        // let origin_ctx = ctx.clone();
-        // let from_clone = from.clone();
-        // manager.forward(|entries| async move {
-        //     let mut sent = std::collections::HashSet::new();
-        //     for entry in entries {
-        //         if let Some(entry_ctx) = &entry.context {
-        //             if Arc::ptr_eq(entry_ctx, &origin_ctx) { continue; }
-        //         }
-        //         let node = entry.node.read().await;
-        //         if let Some(n) = node.as_ref() {
-        //             let nid = String::from_utf8_lossy(&n.id).to_string();
-        //             if nid == from_clone || !sent.insert(nid) { continue; }
-        //         } else if !sent.insert(entry.addr.to_string()) { continue; }
-        //         if let Some(ctx) = &entry.context {
-        //             let _ = P2PFrame::send(ctx.clone(), &Some(message.clone()),
-        //                 Entity::Message, Action::SendText, true).await;
-        //         }
-        //     }
-        // }).await;
     }
 }
@@ -1,9 +1,27 @@
 pub mod ack;
+pub mod binary;
+pub mod busy;
+pub mod error;
+pub mod fragment;
+pub mod group;
+pub mod http_tunnel;
+pub mod identity;
 pub mod message;
+pub mod naming;
 pub mod node_registry;
 pub mod node_sync;
+pub mod observed;
 pub mod offline;
 pub mod online;
+pub mod ping;
+pub mod presence;
+pub mod rekey;
+pub mod sealed;
+pub mod seed_delta;
 pub mod seed_sync;
+pub mod stream;
+pub mod successor;
+pub mod telephone;
 pub mod tick;
+pub mod topic;
 pub mod witness_validate;
@@ -0,0 +1,450 @@
+//! 名称解析（DNS-over-P2P）
+//!
+//! 节点可以发布一条签名的名称记录，把人类可读的名称映射到自己的地址。记录由节点身份私钥对
+//! `标签 | 名称 | 地址 | 公钥 | 签发时间 | 过期时间` 的哈希签名，通过 `NamePublish` 在网络中
+//! 泛洪，每个节点在 `NameRecords` 中缓存校验通过的记录。
+//!
+//! 解析时先查本地缓存；未命中则向所有已连接的对端发送 `NameQuery`，采用第一条校验通过的
+//! `NameAnswer`。记录过期后不再返回，名称可被其它密钥重新登记；未过期前只有原公钥可以更新
+//! （签发时间更新的记录覆盖旧记录）。已通过身份验证的地址（`IdentityBindings`）只接受其
+//! 绑定公钥签发的记录。
+
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
+
+use aex::{
+    connection::{context::Context, global::GlobalContext},
+    tcp::types::Codec,
+    time::SystemTime,
+};
+use bincode::{Decode, Encode};
+use dashmap::{DashMap, mapref::entry::Entry};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::{Mutex, oneshot};
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::protocols::{
+    broadcast,
+    capabilities::{self, CAP_NAMING},
+    command::{Action, Entity, P2PCommand},
+    commands::identity::IdentityBindings,
+    error::{self, ProtocolError},
+    frame::P2PFrame,
+};
+
+const NAME_RECORD_LABEL: &[u8] = b"zz-p2p-name-v1";
+/// 未指定有效期时的默认值
+pub const NAME_DEFAULT_TTL_SECS: u64 = 24 * 60 * 60;
+/// 名称记录的最长有效期
+pub const NAME_MAX_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+/// 名称的最大长度
+pub const NAME_MAX_LEN: usize = 64;
+/// 本地最多缓存的名称记录数
+pub const NAME_RECORDS_MAX: usize = 10_000;
+
+/// 已知的名称记录：名称 → 记录，保存在 GlobalContext 中
+pub type NameRecords = Arc<DashMap<String, NameRecord>>;
+
+/// 等待应答的名称查询：request_id → (查询的名称, oneshot)
+pub type PendingNameQueries = Arc<Mutex<HashMap<u64, (String, oneshot::Sender<NameRecord>)>>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct NameRecord {
+    pub name: String,
+    pub address: String,
+    pub public_key: Vec<u8>,
+    /// 签发时间（毫秒）
+    pub issued_at: u128,
+    /// 过期时间（毫秒）
+    pub expires_at: u128,
+    /// 对记录哈希的签名
+    pub signature: Vec<u8>,
+}
+
+impl Codec for NameRecord {}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct NameQueryCommand {
+    pub request_id: u64,
+    pub name: String,
+}
+
+impl Codec for NameQueryCommand {}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct NameAnswerCommand {
+    pub request_id: u64,
+    /// 应答方没有该名称的有效记录时为空
+    pub record: Option<NameRecord>,
+}
+
+impl Codec for NameAnswerCommand {}
+
+/// 把名称规范为小写；名称只能包含字母、数字、`-` 与 `.`，且不能是 socket 地址
+pub fn normalize_name(name: &str) -> Option<String> {
+    let name = name.trim().to_ascii_lowercase();
+    let valid = !name.is_empty()
+        && name.len() <= NAME_MAX_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+        && !name.starts_with('.')
+        && !name.ends_with('.')
+        && name.parse::<SocketAddr>().is_err();
+    valid.then_some(name)
+}
+
+impl NameRecord {
+    /// 签名覆盖的记录哈希
+    pub fn digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(NAME_RECORD_LABEL);
+        for part in [
+            self.name.as_bytes(),
+            self.address.as_bytes(),
+            &self.public_key,
+        ] {
+            hasher.update((part.len() as u32).to_be_bytes());
+            hasher.update(part);
+        }
+        hasher.update(self.issued_at.to_be_bytes());
+        hasher.update(self.expires_at.to_be_bytes());
+        hasher.finalize().into()
+    }
+
+    /// 以 `identity` 的身份签发名称记录，有效期不超过 [`NAME_MAX_TTL_SECS`]
+    pub fn sign(
+        identity: &FreeWebMovementAddress,
+        name: &str,
+        ttl: Duration,
+        now: u128,
+    ) -> anyhow::Result<Self> {
+        let Some(name) = normalize_name(name) else {
+            anyhow::bail!("Invalid name: {:?}", name);
+        };
+        let ttl = ttl.min(Duration::from_secs(NAME_MAX_TTL_SECS));
+        let mut record = NameRecord {
+            name,
+            address: identity.to_string(),
+            public_key: identity.public_key.to_bytes().to_vec(),
+            issued_at: now,
+            expires_at: now + ttl.as_millis(),
+            signature: vec![],
+        };
+        record.signature =
+            FreeWebMovementAddress::sign_message(&identity.private_key, &record.digest())
+                .serialize_compact()
+                .to_vec();
+        Ok(record)
+    }
+
+    pub fn is_expired(&self, now: u128) -> bool {
+        self.expires_at <= now
+    }
+
+    /// 校验名称格式、有效期范围与签名
+    pub fn verify(&self) -> Result<(), ProtocolError> {
+        if normalize_name(&self.name).as_deref() != Some(self.name.as_str()) {
+            return Err(ProtocolError::decode("NameRecord", "invalid name"));
+        }
+        let max_ttl = Duration::from_secs(NAME_MAX_TTL_SECS).as_millis();
+        if self.expires_at <= self.issued_at || self.expires_at - self.issued_at > max_ttl {
+            return Err(ProtocolError::decode(
+                "NameRecord",
+                "invalid validity period",
+            ));
+        }
+        bitcoin::PublicKey::from_slice(&self.public_key)
+            .map_err(|_| ProtocolError::InvalidPublicKey)?;
+        bitcoin::secp256k1::ecdsa::Signature::from_compact(&self.signature)
+            .map_err(|_| ProtocolError::MalformedSignature)?;
+
+        let public_key = FreeWebMovementAddress::to_public_key(&self.public_key);
+        let signature = FreeWebMovementAddress::to_signature(&self.signature);
+        if !FreeWebMovementAddress::verify_message(&public_key, &self.digest(), &signature) {
+            return Err(ProtocolError::BadSignature);
+        }
+        Ok(())
+    }
+}
+
+/// [`accept`] 的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameUpdate {
+    /// 新记录或更新了已有记录
+    Inserted,
+    /// 已有相同或更新的记录
+    Unchanged,
+    /// 记录已过期
+    Expired,
+    /// 名称已被其它公钥登记且尚未过期
+    Taken,
+    /// 缓存已满
+    Full,
+}
+
+/// 按登记规则合并一条（已校验签名的）记录
+pub fn accept(records: &DashMap<String, NameRecord>, record: NameRecord, now: u128) -> NameUpdate {
+    if record.is_expired(now) {
+        return NameUpdate::Expired;
+    }
+    if records.len() >= NAME_RECORDS_MAX && !records.contains_key(&record.name) {
+        records.retain(|_, r| !r.is_expired(now));
+        if records.len() >= NAME_RECORDS_MAX {
+            return NameUpdate::Full;
+        }
+    }
+    match records.entry(record.name.clone()) {
+        Entry::Occupied(mut entry) => {
+            let current = entry.get();
+            if current.public_key != record.public_key && !current.is_expired(now) {
+                NameUpdate::Taken
+            } else if current.public_key == record.public_key
+                && record.issued_at <= current.issued_at
+            {
+                NameUpdate::Unchanged
+            } else {
+                entry.insert(record);
+                NameUpdate::Inserted
+            }
+        }
+        Entry::Vacant(entry) => {
+            entry.insert(record);
+            NameUpdate::Inserted
+        }
+    }
+}
+
+/// 查找未过期的记录，过期的记录顺带移除
+pub fn lookup(records: &DashMap<String, NameRecord>, name: &str, now: u128) -> Option<NameRecord> {
+    let record = records.get(name)?.value().clone();
+    if record.is_expired(now) {
+        records.remove_if(name, |_, r| r.is_expired(now));
+        return None;
+    }
+    Some(record)
+}
+
+/// 按名称排序的未过期记录
+pub async fn list(gctx: &Arc<GlobalContext>) -> Vec<NameRecord> {
+    let Some(records) = gctx.get::<NameRecords>().await else {
+        return vec![];
+    };
+    let now = SystemTime::timestamp();
+    let mut list: Vec<NameRecord> = records
+        .iter()
+        .filter(|r| !r.is_expired(now))
+        .map(|r| r.value().clone())
+        .collect();
+    list.sort_by(|a, b| a.name.cmp(&b.name));
+    list
+}
+
+/// 向所有支持名称解析的连接（可排除来源连接）发送命令
+async fn fan_out<C: Codec + Serialize + Clone + Send + Sync>(
+    gctx: &Arc<GlobalContext>,
+    cmd: &C,
+    action: Action,
+    origin: Option<Arc<Mutex<Context>>>,
+) {
+    let manager = gctx.manager.clone();
+    manager
+        .forward(|entries| async move {
+            let mut targets = Vec::new();
+            for target in broadcast::unique_peers(entries, origin.as_ref(), None).await {
+                if capabilities::peer_supports(&target.ctx, CAP_NAMING).await {
+                    targets.push(target);
+                }
+            }
+            broadcast::send_all(gctx, targets, |peer_ctx| {
+                let cmd = cmd.clone();
+                async move { P2PFrame::send(peer_ctx, &Some(cmd), Entity::Name, action, false).await }
+            })
+            .await
+            .log(&format!("fan out {:?}", action));
+        })
+        .await;
+}
+
+/// 已验证身份的地址只接受其绑定公钥签发的记录
+async fn check_binding(
+    gctx: &Arc<GlobalContext>,
+    record: &NameRecord,
+) -> Result<(), ProtocolError> {
+    match gctx.get::<IdentityBindings>().await {
+        Some(bindings) => match bindings.get(&record.address) {
+            Some(key) if key.value() != &record.public_key => {
+                Err(ProtocolError::IdentityMismatch {
+                    claimed: record.address.clone(),
+                })
+            }
+            _ => Ok(()),
+        },
+        None => Ok(()),
+    }
+}
+
+/// 校验并合并对端发来的记录；校验失败计入该对端的协议错误
+async fn receive(
+    ctx: &Arc<Mutex<Context>>,
+    gctx: &Arc<GlobalContext>,
+    peer: &str,
+    record: NameRecord,
+) -> Option<NameUpdate> {
+    let checked = match record.verify() {
+        Ok(()) => check_binding(gctx, &record).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = checked {
+        error::report(ctx, peer, e).await;
+        return None;
+    }
+    let records = gctx.get::<NameRecords>().await?;
+    Some(accept(&records, record, SystemTime::timestamp()))
+}
+
+/// 发布本节点的名称记录并广播到整个网络
+pub async fn publish(
+    gctx: Arc<GlobalContext>,
+    name: &str,
+    ttl: Duration,
+) -> anyhow::Result<NameRecord> {
+    let Some(identity) = gctx.get::<FreeWebMovementAddress>().await else {
+        anyhow::bail!("Address not set");
+    };
+    let Some(records) = gctx.get::<NameRecords>().await else {
+        anyhow::bail!("NameRecords not set in GlobalContext");
+    };
+    let record = NameRecord::sign(&identity, name, ttl, SystemTime::timestamp())?;
+    match accept(&records, record.clone(), record.issued_at) {
+        NameUpdate::Inserted | NameUpdate::Unchanged => {}
+        NameUpdate::Taken => anyhow::bail!("Name {} is registered by another node", record.name),
+        other => anyhow::bail!("Name {} not stored: {:?}", record.name, other),
+    }
+    fan_out(&gctx, &record, Action::NamePublish, None).await;
+    Ok(record)
+}
+
+/// 解析名称：先查本地缓存，未命中时向已连接的对端查询，`timeout` 内无应答返回 `None`
+pub async fn resolve(
+    gctx: Arc<GlobalContext>,
+    name: &str,
+    timeout: Duration,
+) -> anyhow::Result<Option<NameRecord>> {
+    let Some(name) = normalize_name(name) else {
+        anyhow::bail!("Invalid name: {:?}", name);
+    };
+    let Some(records) = gctx.get::<NameRecords>().await else {
+        anyhow::bail!("NameRecords not set in GlobalContext");
+    };
+    if let Some(record) = lookup(&records, &name, SystemTime::timestamp()) {
+        return Ok(Some(record));
+    }
+    let Some(pending) = gctx.get::<PendingNameQueries>().await else {
+        anyhow::bail!("PendingNameQueries not set in GlobalContext");
+    };
+
+    let request_id: u64 = rand::thread_rng().r#gen();
+    let (tx, rx) = oneshot::channel();
+    pending.lock().await.insert(request_id, (name.clone(), tx));
+    let query = NameQueryCommand { request_id, name };
+    fan_out(&gctx, &query, Action::NameQuery, None).await;
+
+    match tokio::time::timeout(timeout, rx).await {
+        Ok(Ok(record)) => Ok(Some(record)),
+        _ => {
+            pending.lock().await.remove(&request_id);
+            Ok(None)
+        }
+    }
+}
+
+pub async fn name_publish_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    let peer = frame.body.address.clone();
+    let record: NameRecord = match error::decode_command("NameRecord", &frame, &cmd.data) {
+        Ok(r) => r,
+        Err(e) => {
+            error::report(&ctx, &peer, e).await;
+            return;
+        }
+    };
+    let gctx = ctx.lock().await.global.clone();
+    let name = record.name.clone();
+    let address = record.address.clone();
+    match receive(&ctx, &gctx, &peer, record.clone()).await {
+        Some(NameUpdate::Inserted) => {
+            tracing::info!("📛 Name {} -> {}", name, address);
+            // 只转发新记录，重复的记录在这里终止泛洪
+            fan_out(&gctx, &record, Action::NamePublish, Some(ctx)).await;
+        }
+        Some(update) => tracing::debug!("Name record {} from {}: {:?}", name, peer, update),
+        None => {}
+    }
+}
+
+pub async fn name_query_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    let query: NameQueryCommand = match error::decode_command("NameQueryCommand", &frame, &cmd.data)
+    {
+        Ok(q) => q,
+        Err(e) => {
+            error::report(&ctx, &frame.body.address, e).await;
+            return;
+        }
+    };
+    let gctx = ctx.lock().await.global.clone();
+    let record = match gctx.get::<NameRecords>().await {
+        Some(records) => lookup(&records, &query.name, SystemTime::timestamp()),
+        None => None,
+    };
+    let answer = NameAnswerCommand {
+        request_id: query.request_id,
+        record,
+    };
+    if let Err(e) =
+        P2PFrame::send(ctx, &Some(answer), Entity::Name, Action::NameAnswer, false).await
+    {
+        tracing::error!(
+            "Failed to answer name query from {}: {:?}",
+            frame.body.address,
+            e
+        );
+    }
+}
+
+pub async fn name_answer_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    let peer = frame.body.address.clone();
+    let answer: NameAnswerCommand =
+        match error::decode_command("NameAnswerCommand", &frame, &cmd.data) {
+            Ok(a) => a,
+            Err(e) => {
+                error::report(&ctx, &peer, e).await;
+                return;
+            }
+        };
+    let Some(record) = answer.record else {
+        return;
+    };
+    let gctx = ctx.lock().await.global.clone();
+    let name = record.name.clone();
+    if receive(&ctx, &gctx, &peer, record).await.is_none() {
+        return;
+    }
+    let (Some(pending), Some(records)) = (
+        gctx.get::<PendingNameQueries>().await,
+        gctx.get::<NameRecords>().await,
+    ) else {
+        return;
+    };
+    let mut pending = pending.lock().await;
+    if !matches!(pending.get(&answer.request_id), Some((queried, _)) if *queried == name) {
+        return;
+    }
+    // 以合并后的缓存为准：被拒绝的记录不会交给查询方
+    let Some(resolved) = lookup(&records, &name, SystemTime::timestamp()) else {
+        return;
+    };
+    if let Some((_, tx)) = pending.remove(&answer.request_id) {
+        let _ = tx.send(resolved);
+    }
+}
@@ -1,11 +1,17 @@
 use aex::connection::scope::NetworkScope;
 use dashmap::DashMap;
-use std::collections::{HashMap, HashSet};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+use crate::endpoint_verifier::{self, EndpointVerifier};
+use crate::ip_scope;
+use crate::protocols::filter::{FilterVerdict, InboundMessage, MessageFilters};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ConnectionDirection {
     Inbound,
     Outbound,
@@ -26,12 +32,122 @@ pub type NodeManager = NodeRegistry;
 #[derive(Clone)]
 pub struct NodeRegistry {
     nodes: Arc<DashMap<String, NodeEntry>>,
+    /// 地址簿：别名 → 节点地址
+    aliases: Arc<DashMap<String, String>>,
+    /// seed 地址的可达性验证状态
+    endpoints: EndpointVerifier,
+    /// 投递前执行的接收端消息过滤器
+    filters: MessageFilters,
 }
 
 impl NodeRegistry {
     pub fn new() -> Self {
         Self {
             nodes: Arc::new(DashMap::new()),
+            aliases: Arc::new(DashMap::new()),
+            endpoints: EndpointVerifier::new(),
+            filters: MessageFilters::default(),
+        }
+    }
+
+    pub fn endpoints(&self) -> &EndpointVerifier {
+        &self.endpoints
+    }
+
+    pub fn filters(&self) -> &MessageFilters {
+        &self.filters
+    }
+
+    /// 注册在消息投递前执行的过滤器，见 [`crate::protocols::filter`]；
+    /// 同名的过滤器原位替换，返回是否替换了已有过滤器
+    pub fn add_filter<F, Fut>(&self, name: &str, filter: F) -> bool
+    where
+        F: Fn(Arc<aex::connection::global::GlobalContext>, InboundMessage) -> Fut
+            + Send
+            + Sync
+            + 'static,
+        Fut: Future<Output = FilterVerdict> + Send + 'static,
+    {
+        self.filters.add(name, filter)
+    }
+
+    pub fn remove_filter(&self, name: &str) -> bool {
+        self.filters.remove(name)
+    }
+
+    /// 添加或覆盖别名。别名不能为空、不能含空白，也不能是 socket 地址
+    pub fn add_alias(&self, name: &str, address: &str) -> anyhow::Result<()> {
+        if name.is_empty() || name.chars().any(char::is_whitespace) {
+            return Err(anyhow::anyhow!("Invalid alias name: {:?}", name));
+        }
+        if name.parse::<SocketAddr>().is_ok() {
+            return Err(anyhow::anyhow!(
+                "Alias {} looks like a socket address",
+                name
+            ));
+        }
+        if address.is_empty() {
+            return Err(anyhow::anyhow!("Alias {} needs an address", name));
+        }
+        self.aliases.insert(name.to_string(), address.to_string());
+        Ok(())
+    }
+
+    pub fn remove_alias(&self, name: &str) -> Option<String> {
+        self.aliases.remove(name).map(|(_, address)| address)
+    }
+
+    /// 别名解析为地址；不是别名时原样返回
+    pub fn resolve_alias(&self, name_or_address: &str) -> String {
+        self.aliases
+            .get(name_or_address)
+            .map(|a| a.value().clone())
+            .unwrap_or_else(|| name_or_address.to_string())
+    }
+
+    /// 按别名排序的地址簿快照，用于列出与持久化
+    pub fn aliases(&self) -> BTreeMap<String, String> {
+        self.aliases
+            .iter()
+            .map(|e| (e.key().clone(), e.value().clone()))
+            .collect()
+    }
+
+    /// 把指向 `old` 的别名改为指向 `new`，返回改动的别名
+    pub fn retarget_aliases(&self, old: &str, new: &str) -> Vec<String> {
+        let mut retargeted = Vec::new();
+        for mut entry in self.aliases.iter_mut() {
+            if entry.value() == old {
+                *entry.value_mut() = new.to_string();
+                retargeted.push(entry.key().clone());
+            }
+        }
+        retargeted.sort();
+        retargeted
+    }
+
+    /// 节点换用新地址：条目改记在新地址下，新地址已有条目时合并 seeds；返回是否有旧条目
+    pub fn rename_node(&self, old: &str, new: &str) -> bool {
+        let Some((_, mut entry)) = self.nodes.remove(old) else {
+            return false;
+        };
+        if let Some(mut existing) = self.nodes.get_mut(new) {
+            for (seed, directions) in entry.seeds {
+                existing.seeds.entry(seed).or_default().extend(directions);
+            }
+            existing.is_connected |= entry.is_connected;
+            existing.last_seen = existing.last_seen.max(entry.last_seen);
+            return true;
+        }
+        entry.address = new.to_string();
+        self.nodes.insert(new.to_string(), entry);
+        true
+    }
+
+    /// 从持久化的地址簿恢复
+    pub fn load_aliases(&self, aliases: BTreeMap<String, String>) {
+        for (name, address) in aliases {
+            self.aliases.insert(name, address);
         }
     }
 
@@ -179,6 +295,12 @@ impl NodeRegistry {
         seeds
     }
 
+    /// 可以 gossip 给对端的 seed：只包含有效期内验证通过的地址
+    pub fn get_gossip_seeds(&self) -> Vec<(SocketAddr, String)> {
+        self.endpoints
+            .retain_verified(self.get_all_seeds(), endpoint_verifier::now_secs())
+    }
+
     pub fn get_node_count(&self) -> usize {
         self.nodes.len()
     }
@@ -290,7 +412,7 @@ impl NodeRegistry {
         for peer in &info.outbound {
             if let Some(ref nid) = peer.node_id {
                 if let Ok(addr) = peer.addr.parse::<SocketAddr>() {
-                    let scope = NetworkScope::from_ip(&addr.ip());
+                    let scope = ip_scope::classify(&addr.ip());
                     self.nodes
                         .entry(nid.clone())
                         .and_modify(|e| {
@@ -11,6 +11,7 @@ use aex::tcp::types::Codec;
 
 use crate::protocols::{
     command::{Action, Entity, P2PCommand},
+    error::{self, ProtocolError},
     frame::P2PFrame,
 };
 
@@ -111,13 +112,18 @@ static RESPONSE_SENDER: StdMutex<Option<ResponseSender>> = StdMutex::new(None);
 // ================== 处理器函数 ==================
 
 /// 处理节点同步请求（老节点/已同步节点）
-pub async fn node_sync_handler(ctx: Arc<Mutex<Context>>, _frame: P2PFrame, cmd: P2PCommand) {
+pub async fn node_sync_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
     tracing::info!("🔄 Received node sync request");
 
-    let request: NodeSyncRequest = match Codec::decode(&cmd.data) {
+    let request: NodeSyncRequest = match frame.decode_payload(&cmd.data) {
         Ok(req) => req,
         Err(e) => {
-            tracing::error!("❌ Failed to decode NodeSyncRequest: {}", e);
+            error::report(
+                &ctx,
+                &frame.body.address,
+                ProtocolError::decode("NodeSyncRequest", e),
+            )
+            .await;
             return;
         }
     };
@@ -205,16 +211,21 @@ pub async fn node_sync_handler(ctx: Arc<Mutex<Context>>, _frame: P2PFrame, cmd:
 
 /// 处理节点同步响应（新节点/待同步节点）
 pub async fn node_sync_response_handler(
-    _ctx: Arc<Mutex<Context>>,
-    _frame: P2PFrame,
+    ctx: Arc<Mutex<Context>>,
+    frame: P2PFrame,
     cmd: P2PCommand,
 ) {
     tracing::info!("✅ Received node sync response");
 
-    let response: NodeSyncResponse = match Codec::decode(&cmd.data) {
+    let response: NodeSyncResponse = match frame.decode_payload(&cmd.data) {
         Ok(resp) => resp,
         Err(e) => {
-            tracing::error!("❌ Failed to decode NodeSyncResponse: {}", e);
+            error::report(
+                &ctx,
+                &frame.body.address,
+                ProtocolError::decode("NodeSyncResponse", e),
+            )
+            .await;
             return;
         }
     };
@@ -0,0 +1,213 @@
+//! 观测地址（"我的公网端点是什么"）
+//!
+//! 入站端完成握手后，把它看到的对端地址（`ctx.addr`）通过 `ObservedAddress` 回送给对端。
+//! NAT 后的节点据此得知自己的公网（反射）地址，并在后续 Online 公告的 `wan_ips` 中带上它。
+//! 为防止单个对端伪造，同一地址需要至少 `MIN_CONFIRMATIONS` 个不同节点报告才会被采用；
+//! 内网地址与过期的观测不会被公告。
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use aex::{
+    connection::{context::Context, global::GlobalContext, scope::NetworkScope},
+    tcp::types::Codec,
+    time::SystemTime,
+};
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    ip_scope, port_mapping,
+    protocols::{
+        command::{Action, Entity, P2PCommand},
+        error::{self, ProtocolError},
+        frame::P2PFrame,
+    },
+};
+
+/// 采用一个观测地址所需的不同报告者数量
+pub const MIN_CONFIRMATIONS: usize = 2;
+/// 观测结果的有效期
+pub const OBSERVATION_TTL_MS: u128 = 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct ObservedAddressCommand {
+    /// 对端看到的本节点地址（`ip:port`）
+    pub observed: String,
+}
+
+impl Codec for ObservedAddressCommand {}
+
+#[derive(Debug, Clone)]
+struct Observation {
+    /// 报告者地址 -> 最近一次报告时间
+    reporters: HashMap<String, u128>,
+    /// 最近一次看到的源端口（NAT 映射端口，仅供参考）
+    last_port: u16,
+}
+
+/// 收集到的观测地址
+#[derive(Debug, Default)]
+pub struct ObservedAddressBook {
+    by_ip: HashMap<IpAddr, Observation>,
+}
+
+/// 保存在 GlobalContext 中的观测地址表
+pub type ObservedAddresses = Arc<std::sync::Mutex<ObservedAddressBook>>;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ObservedEntry {
+    pub ip: IpAddr,
+    pub port: u16,
+    pub reporters: usize,
+    pub confirmed: bool,
+}
+
+impl ObservedAddressBook {
+    /// 记录 `reporter` 的一次观测；内网地址被忽略，返回是否记录
+    pub fn record(&mut self, reporter: &str, observed: SocketAddr, now: u128) -> bool {
+        if ip_scope::is_inner_ip(&observed.ip()) || observed.ip().is_unspecified() {
+            return false;
+        }
+        let entry = self
+            .by_ip
+            .entry(observed.ip())
+            .or_insert_with(|| Observation {
+                reporters: HashMap::new(),
+                last_port: observed.port(),
+            });
+        entry.reporters.insert(reporter.to_string(), now);
+        entry.last_port = observed.port();
+        true
+    }
+
+    fn prune(&mut self, now: u128) {
+        for obs in self.by_ip.values_mut() {
+            obs.reporters
+                .retain(|_, at| now.saturating_sub(*at) < OBSERVATION_TTL_MS);
+        }
+        self.by_ip.retain(|_, obs| !obs.reporters.is_empty());
+    }
+
+    /// 已被足够多节点确认的反射地址，按确认数从多到少排列
+    pub fn confirmed(&mut self, now: u128) -> Vec<IpAddr> {
+        self.entries(now)
+            .into_iter()
+            .filter(|e| e.confirmed)
+            .map(|e| e.ip)
+            .collect()
+    }
+
+    pub fn entries(&mut self, now: u128) -> Vec<ObservedEntry> {
+        self.prune(now);
+        let mut entries: Vec<ObservedEntry> = self
+            .by_ip
+            .iter()
+            .map(|(ip, obs)| ObservedEntry {
+                ip: *ip,
+                port: obs.last_port,
+                reporters: obs.reporters.len(),
+                confirmed: obs.reporters.len() >= MIN_CONFIRMATIONS,
+            })
+            .collect();
+        entries.sort_by(|a, b| b.reporters.cmp(&a.reporters).then(a.ip.cmp(&b.ip)));
+        entries
+    }
+}
+
+fn lock(book: &ObservedAddresses) -> std::sync::MutexGuard<'_, ObservedAddressBook> {
+    match book.lock() {
+        Ok(g) => g,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// 入站端调用：告诉对端我们看到的它的地址
+pub async fn send_observed(ctx: Arc<Mutex<Context>>) {
+    let peer = ctx.lock().await.addr;
+    let cmd = ObservedAddressCommand {
+        observed: peer.to_string(),
+    };
+    if let Err(e) = P2PFrame::send(
+        ctx,
+        &Some(cmd),
+        Entity::Node,
+        Action::ObservedAddress,
+        false,
+    )
+    .await
+    {
+        tracing::warn!("Failed to send observed address to {}: {:?}", peer, e);
+    }
+}
+
+pub async fn observed_address_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    let observed: ObservedAddressCommand =
+        match error::decode_command("ObservedAddressCommand", &frame, &cmd.data) {
+            Ok(c) => c,
+            Err(e) => {
+                error::report(&ctx, &frame.body.address, e).await;
+                return;
+            }
+        };
+    let addr = match observed.observed.parse::<SocketAddr>() {
+        Ok(a) => a,
+        Err(e) => {
+            error::report(
+                &ctx,
+                &frame.body.address,
+                ProtocolError::decode("ObservedAddressCommand", e),
+            )
+            .await;
+            return;
+        }
+    };
+    let gctx = ctx.lock().await.global.clone();
+    let Some(book) = gctx.get::<ObservedAddresses>().await else {
+        return;
+    };
+    if lock(&book).record(&frame.body.address, addr, SystemTime::timestamp()) {
+        tracing::info!("🪞 {} sees us as {}", frame.body.address, addr);
+    }
+}
+
+/// 已确认的反射地址
+pub async fn reflexive_ips(gctx: &Arc<GlobalContext>) -> Vec<IpAddr> {
+    match gctx.get::<ObservedAddresses>().await {
+        Some(book) => lock(&book).confirmed(SystemTime::timestamp()),
+        None => Vec::new(),
+    }
+}
+
+/// 观测地址列表（用于 status）
+pub async fn entries(gctx: &Arc<GlobalContext>) -> Vec<ObservedEntry> {
+    match gctx.get::<ObservedAddresses>().await {
+        Some(book) => lock(&book).entries(SystemTime::timestamp()),
+        None => Vec::new(),
+    }
+}
+
+/// Online 公告用的地址：本机网卡地址加上已确认的反射地址与端口映射得到的公网地址；
+/// 仅出站模式下本机不可达，不公告任何地址
+pub async fn announced_ips(
+    gctx: &Arc<GlobalContext>,
+    ips: &[(NetworkScope, IpAddr)],
+) -> (Vec<String>, Vec<String>) {
+    if crate::client_mode::is_enabled(gctx).await {
+        return (vec![], vec![]);
+    }
+    let (intranet, mut wan) = ip_scope::split_ips(ips);
+    let mut external = reflexive_ips(gctx).await;
+    external.extend(port_mapping::external_ips(gctx).await);
+    for ip in external {
+        let ip = ip.to_string();
+        if !wan.contains(&ip) {
+            wan.push(ip);
+        }
+    }
+    (intranet, wan)
+}
@@ -1,13 +1,16 @@
 use std::sync::Arc;
 
 use aex::connection::context::Context;
+use aex::connection::global::GlobalContext;
 use aex::tcp::types::Codec;
 use bincode::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
 
 // use crate::context::Context;
-use crate::protocols::command::P2PCommand;
+use crate::journal;
+use crate::protocols::broadcast;
+use crate::protocols::command::{Action, Entity, P2PCommand};
 use crate::protocols::frame::P2PFrame;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
@@ -28,6 +31,39 @@ pub async fn offline_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, _cmd: P2
 
     // should remove address info for future handler
 
-    let guard = ctx.lock().await;
-    guard.global.manager.remove(guard.addr, true);
+    let (gctx, peer) = {
+        let guard = ctx.lock().await;
+        guard.global.manager.remove(guard.addr, true);
+        (guard.global.clone(), guard.addr)
+    };
+    journal::record_disconnect(&gctx, Some(&frame.body.address), peer, "offline").await;
+}
+
+/// 下线前通知所有已连接的节点，对端收到后立即关闭连接而不必等待心跳超时
+pub async fn notify_offline(gctx: &Arc<GlobalContext>) {
+    let cmd = OfflineCommand {
+        session_id: vec![],
+        endpoints: gctx.addr.to_string().into_bytes(),
+    };
+    let manager = gctx.manager.clone();
+    manager
+        .forward(|entries| async {
+            broadcast::send_all(gctx, broadcast::all_peers(entries), |peer_ctx| {
+                let cmd = cmd.clone();
+                async move {
+                    P2PFrame::send::<OfflineCommand>(
+                        peer_ctx,
+                        &Some(cmd),
+                        Entity::Node,
+                        Action::OffLine,
+                        false,
+                    )
+                    .await
+                }
+            })
+            .await
+            .log("offline");
+        })
+        .await;
+    tracing::info!("📴 Notified peers that we are going offline");
 }
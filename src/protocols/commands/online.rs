@@ -2,18 +2,32 @@ use std::sync::Arc;
 
 use aex::connection::context::Context;
 use aex::connection::node::Node;
-use aex::connection::scope::NetworkScope;
 use aex::tcp::types::Codec;
 use bincode::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
 use zz_account::address::FreeWebMovementAddress;
 
+use crate::ip_scope;
+use crate::journal;
 use crate::node::Node as P2pNode;
+use crate::peer_capabilities::{self, CapabilityProfile};
+use crate::protocols::agent::{self, SoftwareInfo};
+use crate::protocols::capabilities::{self, LOCAL_MAX_FRAME_SIZE};
 use crate::protocols::command::P2PCommand;
 use crate::protocols::command::{Action, Entity};
 use crate::protocols::commands::ack::{OnlineAckCommand, SeedRecord, SeedsCommand};
+use crate::protocols::commands::rekey::{SessionTable, record_established, reset_keys};
+use crate::protocols::commands::{identity, observed, presence, topic};
+use crate::protocols::compression::LOCAL_CAPABILITIES;
+use crate::protocols::error::{self, ProtocolError};
 use crate::protocols::frame::P2PFrame;
+use crate::protocols::limits;
+use crate::protocols::privacy;
+use crate::protocols::routing::{self, RoutingTable};
+use crate::protocols::version::{CURRENT_PROTOCOL_VERSION, negotiate_version};
+use crate::protocols::wire_format::{PeerWireFormat, WireFormat};
+use crate::proxy;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
 pub struct OnlineCommand {
@@ -23,16 +37,30 @@ pub struct OnlineCommand {
     pub intranet_ips: Vec<String>,
     pub wan_ips: Vec<String>,
     pub seeds: Option<SeedsCommand>,
+    /// 能力位，见 `protocols::compression` 与 `protocols::capabilities`
+    pub capabilities: u32,
+    /// 本端支持的最高协议版本，见 `protocols::version`
+    pub protocol_version: u8,
+    /// 本端可接收的最大帧长度，0 表示未声明
+    pub max_frame_size: u32,
+    /// 本端的软件版本与 User-Agent，见 `protocols::agent`
+    #[serde(default)]
+    pub agent: SoftwareInfo,
 }
 
 impl Codec for OnlineCommand {}
 
 pub async fn online_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
     tracing::info!("inside online handler!");
-    let online: OnlineCommand = match Codec::decode(&cmd.data) {
+    let online: OnlineCommand = match frame.decode_payload(&cmd.data) {
         Ok(cmd) => cmd,
         Err(e) => {
-            tracing::error!("❌ decode OnlineCommand failed: {e}");
+            error::report(
+                &ctx,
+                &frame.body.address,
+                ProtocolError::decode("OnlineCommand", e),
+            )
+            .await;
             return;
         }
     };
@@ -83,9 +111,10 @@ pub async fn online_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PC
             };
             // Use the peer's advertised listening port, not the TCP source port
             let listen_addr = std::net::SocketAddr::new(gossip_sock.ip(), online.node.port);
-            let gossip_scope = aex::connection::scope::NetworkScope::from_ip(&listen_addr.ip());
+            let gossip_scope = ip_scope::classify(&listen_addr.ip());
             node.registry
                 .register(frame.body.address.clone(), listen_addr, gossip_scope);
+            node.registry.endpoints().spawn_verify(listen_addr);
         }
 
         if let Some(ref peer_seeds) = online.seeds {
@@ -94,98 +123,17 @@ pub async fn online_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PC
                     "📥 Received seed gossip, merging {} seeds",
                     peer_seeds.seeds.len()
                 );
-
-                // Register peer nodes from gossip seeds and connect (node-based dedup)
-                let node = gctx.get::<Arc<P2pNode>>().await;
-                if let Some(node) = node {
-                    let reg = &node.registry;
-                    let before_count = reg.get_all_seeds().len();
-
-                    for seed in &peer_seeds.seeds {
-                        // Register the node if not already known
-                        if !reg.is_registered(&seed.node_address) {
-                            if let Ok(seed_addr) = seed.address.parse::<std::net::SocketAddr>() {
-                                let scope = NetworkScope::from_ip(&seed_addr.ip());
-                                reg.register(seed.node_address.clone(), seed_addr, scope);
-                            }
-                        }
-
-                        // Skip if node already connected
-                        if reg.is_connected(&seed.node_address) {
-                            continue;
-                        }
-
-                        // Skip if seed is our own address (prevent self-connect loop)
-                        if let Some(local_addr) = gctx.get::<FreeWebMovementAddress>().await {
-                            if seed.node_address == local_addr.to_string() {
-                                tracing::info!("⏭️ Skipping self-connect to {}", seed.address);
-                                continue;
-                            }
-                        }
-
-                        // Tiebreaker: only the node with the smaller address initiates the connection.
-                        // This prevents the race where both sides simultaneously connect and establish
-                        // different session keys for the same peer pair.
-                        if let Some(local_addr) = gctx.get::<FreeWebMovementAddress>().await {
-                            if local_addr.to_string() > seed.node_address {
-                                tracing::info!(
-                                    "⏭️ Tiebreaker: {} > {}, letting lower address initiate",
-                                    local_addr,
-                                    seed.node_address
-                                );
-                                continue;
-                            }
-                        }
-
-                        if let Ok(seed_addr) = seed.address.parse::<std::net::SocketAddr>() {
-                            let ctx_owned = ctx.clone();
-                            let addr_str = seed.address.clone();
-                            let reg_clone = reg.clone();
-                            let node_addr = seed.node_address.clone();
-                            tokio::spawn(async move {
-                                if super::ack::connect_to_new_peer(ctx_owned, seed_addr)
-                                    .await
-                                    .is_ok()
-                                {
-                                    reg_clone.mark_connected(&node_addr, true);
-                                } else {
-                                    tracing::error!(
-                                        "  ❌ Failed to connect to gossiped seed {}",
-                                        addr_str
-                                    );
-                                }
-                            });
-                        }
-                    }
-
-                    // Propagate to other peers if we learned new seeds
-                    let after_count = reg.get_all_seeds().len();
-                    if after_count > before_count {
-                        let ctx_for_broadcast = ctx.clone();
-                        let all_seeds: Vec<crate::protocols::commands::ack::SeedRecord> = reg
-                            .get_all_seeds()
-                            .into_iter()
-                            .map(|(s, na)| {
-                                crate::protocols::commands::ack::SeedRecord::new(s.to_string(), na)
-                            })
-                            .collect();
-                        let seeds_to_broadcast =
-                            crate::protocols::commands::ack::SeedsCommand::new(all_seeds);
-
-                        tokio::spawn(async move {
-                            super::ack::broadcast_seeds_to_peers(
-                                ctx_for_broadcast,
-                                &seeds_to_broadcast,
-                            )
-                            .await;
-                        });
-                    }
-                }
+                merge_gossip_seeds(ctx.clone(), &peer_seeds.seeds).await;
             }
         }
         return;
     }
 
+    // 连接数已满时按淘汰策略腾出位置，否则回复 Busy 并关闭连接
+    if !limits::admit_inbound(&ctx).await {
+        return;
+    }
+
     // ============================================================
     // 节点去重：同一 node.id 只能有一个 inbound 连接
     // 但回连（return connection）来自对端且方向不同
@@ -219,7 +167,7 @@ pub async fn online_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PC
                 guard.addr
             };
             let listen_addr = std::net::SocketAddr::new(peer_sock.ip(), online.node.port);
-            let scope = aex::connection::scope::NetworkScope::from_ip(&listen_addr.ip());
+            let scope = ip_scope::classify(&listen_addr.ip());
             // Both first and return connections register their direction.
             // register_with_direction uses a HashSet, so duplicate directions
             // for the same (peer, seed) are idempotent.
@@ -229,6 +177,7 @@ pub async fn online_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PC
                 scope,
                 crate::protocols::commands::node_registry::ConnectionDirection::Inbound,
             );
+            node.registry.endpoints().spawn_verify(listen_addr);
         } else {
             is_return_conn = false;
         }
@@ -238,6 +187,69 @@ pub async fn online_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PC
     {
         let mut guard = ctx.lock().await;
         guard.set(frame.body.address.clone());
+        capabilities::store(&mut guard, online.capabilities, online.max_frame_size);
+        // 对端以 CBOR/JSON 握手时按同一格式回复
+        if frame.format != WireFormat::Bincode {
+            guard.set(PeerWireFormat(frame.format));
+        }
+    }
+    if !negotiate_version(&ctx, &frame.body.address, online.protocol_version).await {
+        return;
+    }
+    let listen_addr = {
+        let guard = ctx.lock().await;
+        std::net::SocketAddr::new(guard.addr.ip(), online.node.port)
+    };
+    // 对端软件版本低于要求时按策略告警或拒绝
+    if !agent::admit(
+        &ctx,
+        &frame.body.address,
+        listen_addr,
+        online.agent.clone(),
+        true,
+    )
+    .await
+    {
+        return;
+    }
+
+    // 同一节点的 IPv4 / IPv6 地址合并为一条服务器记录
+    {
+        let gctx = ctx.lock().await.global.clone();
+        if let Some(node) = gctx.get::<Arc<P2pNode>>().await {
+            node.bind_identity(listen_addr, &frame.body.address).await;
+        }
+    }
+
+    // 与上一次会话比较对端声明的能力，退化时告警
+    {
+        let gctx = ctx.lock().await.global.clone();
+        let profile = CapabilityProfile::new(
+            online.node.protocols.iter().cloned().collect(),
+            online.capabilities,
+            online.protocol_version,
+        );
+        peer_capabilities::observe(&gctx, &frame.body.address, listen_addr, profile).await;
+    }
+
+    // 学习路由：对端直连，对端公告的节点经由对端可达
+    {
+        let gctx = ctx.lock().await.global.clone();
+        if let (Some(table), Some(local)) = (
+            gctx.get::<RoutingTable>().await,
+            gctx.get::<FreeWebMovementAddress>().await,
+        ) {
+            let announced = online
+                .seeds
+                .iter()
+                .flat_map(|s| s.seeds.iter().map(|r| r.node_address.as_str()));
+            routing::learn_from_handshake(
+                &table,
+                &local.to_string(),
+                &frame.body.address,
+                announced,
+            );
+        }
     }
 
     // Store peer's Node info in ConnectionEntry so get_connection_info() can read it
@@ -383,6 +395,15 @@ pub async fn online_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PC
         }
     };
 
+    // 新建立的会话密钥交给会话表跟踪 TTL 与流量
+    if ephemeral_public.as_bytes().iter().any(|&b| b != 0) {
+        let gctx = ctx.lock().await.global.clone();
+        reset_keys(&gctx, &addr_debug).await;
+        if let Some(table) = gctx.get::<SessionTable>().await {
+            record_established(&table, &addr_debug);
+        }
+    }
+
     let address: FreeWebMovementAddress = match ctx.lock().await.global.get().await {
         Some(addr) => addr,
         None => {
@@ -401,17 +422,11 @@ pub async fn online_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PC
         guard.clone()
     };
 
-    let (intranet_ips, wan_ips) = {
-        let mut inner = Vec::new();
-        let mut outer = Vec::new();
-        for (scope, ip) in &node.ips {
-            match scope {
-                NetworkScope::Intranet => inner.push(ip.to_string()),
-                NetworkScope::Extranet => outer.push(ip.to_string()),
-            }
-        }
-        (inner, outer)
+    let (gctx, peer_ip) = {
+        let guard = ctx.lock().await;
+        (guard.global.clone(), guard.addr.ip())
     };
+    let (intranet_ips, wan_ips) = privacy::announced_ips(&gctx, &node.ips, &peer_ip).await;
     tracing::info!("Announcing intranet IPs: {:?}", intranet_ips);
     tracing::info!("Announcing wan IPs: {:?}", wan_ips);
 
@@ -436,8 +451,9 @@ pub async fn online_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PC
                             node.registry.register(
                                 seed.node_address.clone(),
                                 seed_addr,
-                                NetworkScope::from_ip(&seed_addr.ip()),
+                                ip_scope::classify(&seed_addr.ip()),
                             );
+                            node.registry.endpoints().spawn_verify(seed_addr);
                             tracing::info!(
                                 "  + Registered seed from peer: {} (node: {})",
                                 seed.address,
@@ -452,18 +468,7 @@ pub async fn online_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PC
         }
 
         // Generate seeds from NodeRegistry
-        let node = ctx.lock().await.global.get::<Arc<P2pNode>>().await;
-        let seed_records = if let Some(node) = node {
-            let all_seeds: Vec<SeedRecord> = node
-                .registry
-                .get_all_seeds()
-                .into_iter()
-                .map(|(s, na)| SeedRecord::new(s.to_string(), na))
-                .collect();
-            SeedsCommand::new(all_seeds)
-        } else {
-            SeedsCommand::new(vec![])
-        };
+        let seed_records = privacy::gossip_seeds(&gctx, Some(&peer_ip)).await;
 
         tracing::info!(
             "📊 Consensus seeds: {} seeds, hash={:?}",
@@ -481,6 +486,10 @@ pub async fn online_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PC
         intranet_ips,
         wan_ips,
         seeds: seeds_to_send,
+        capabilities: LOCAL_CAPABILITIES,
+        protocol_version: CURRENT_PROTOCOL_VERSION,
+        max_frame_size: LOCAL_MAX_FRAME_SIZE,
+        agent: agent::local(&gctx).await,
     };
 
     tracing::info!("send ack session_id : {:?}", ack.session_id);
@@ -502,6 +511,27 @@ pub async fn online_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PC
         return;
     }
     tracing::info!("end of current online!");
+    {
+        let (gctx, peer_addr) = {
+            let guard = ctx.lock().await;
+            (guard.global.clone(), guard.addr)
+        };
+        let event = journal::Event::PeerConnected {
+            peer: frame.body.address.clone(),
+            addr: peer_addr.to_string(),
+            inbound: true,
+        };
+        journal::record(&gctx, event).await;
+    }
+
+    // 告诉对端我们看到的它的地址，帮助 NAT 后的节点发现自己的公网地址
+    observed::send_observed(ctx.clone()).await;
+
+    // 把本节点的在线状态交给对端缓存与传播
+    presence::send_own(ctx.clone()).await;
+
+    // 要求对端证明它持有所声称地址的身份密钥
+    identity::send_challenge(ctx.clone(), &frame).await;
 
     // Store the announced IPs from peer as external seeds
     for ip in online.intranet_ips.iter().chain(online.wan_ips.iter()) {
@@ -582,45 +612,39 @@ pub async fn online_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PC
             intranet_ips: vec![],
             wan_ips: vec![],
             seeds: None,
+            capabilities: LOCAL_CAPABILITIES,
+            protocol_version: CURRENT_PROTOCOL_VERSION,
+            max_frame_size: LOCAL_MAX_FRAME_SIZE,
+            agent: agent::local(&gctx).await,
         });
 
         let cmd_clone = return_cmd.clone();
         let gctx_clone = gctx.clone();
         tokio::spawn(async move {
-            match gctx_clone
-                .manager
-                .clone()
-                .connect::<P2PFrame, P2PCommand, _, _>(
-                    peer_addr,
-                    gctx_clone.clone(),
-                    move |new_ctx| {
-                        let cmd = cmd_clone.clone();
-                        let g = gctx_clone.clone();
-                        Box::pin(async move {
-                            if let Err(e) = P2PFrame::send::<OnlineCommand>(
-                                new_ctx.clone(),
-                                &Some((*cmd).clone()),
-                                Entity::Node,
-                                Action::OnLine,
-                                false,
-                            )
-                            .await
-                            {
-                                tracing::error!("❌ Failed to send return OnlineCommand: {:?}", e);
-                                return;
-                            }
-                            if let Some(router) = aex::connection::context::get_tcp_router::<
-                                P2PFrame,
-                                P2PCommand,
-                            >(&g.routers)
-                            {
-                                let _ = router.handle(new_ctx).await;
-                            }
-                        })
-                    },
-                    Some(10),
-                )
-                .await
+            match proxy::connect(gctx_clone.clone(), peer_addr, move |new_ctx| {
+                let cmd = cmd_clone.clone();
+                let g = gctx_clone.clone();
+                Box::pin(async move {
+                    if let Err(e) = P2PFrame::send::<OnlineCommand>(
+                        new_ctx.clone(),
+                        &Some((*cmd).clone()),
+                        Entity::Node,
+                        Action::OnLine,
+                        false,
+                    )
+                    .await
+                    {
+                        tracing::error!("❌ Failed to send return OnlineCommand: {:?}", e);
+                        return;
+                    }
+                    if let Some(router) =
+                        aex::connection::context::get_tcp_router::<P2PFrame, P2PCommand>(&g.routers)
+                    {
+                        let _ = router.handle(new_ctx).await;
+                    }
+                })
+            })
+            .await
             {
                 Ok(_) => tracing::info!("✅ Return connection established to {}", peer_addr),
                 Err(_) => tracing::info!("↩️ Return connection already exists to {}", peer_addr),
@@ -638,7 +662,7 @@ pub async fn online_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PC
         let seed_records = if let Some(node) = node {
             let all_seeds: Vec<SeedRecord> = node
                 .registry
-                .get_all_seeds()
+                .get_gossip_seeds()
                 .into_iter()
                 .map(|(s, na)| SeedRecord::new(s.to_string(), na))
                 .collect();
@@ -712,6 +736,10 @@ pub async fn online_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PC
                 })
                 .unwrap_or(false);
             if !still_connected {
+                if let Some(table) = gctx_for_cleanup.get::<RoutingTable>().await {
+                    routing::forget_neighbor(&table, &node_id_for_cleanup);
+                }
+                topic::forget_peer(&gctx_for_cleanup, &node_id_for_cleanup).await;
                 if let Some(node) = gctx_for_cleanup.get::<Arc<P2pNode>>().await {
                     node.registry.disconnect(&node_id_for_cleanup);
                     tracing::info!(
@@ -730,6 +758,92 @@ pub async fn online_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PC
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+/// 合并对端公告的 seeds：登记未知节点并按 tiebreaker 发起连接，学到新 seed 时继续传播
+pub async fn merge_gossip_seeds(ctx: Arc<Mutex<Context>>, seeds: &[SeedRecord]) {
+    let gctx = {
+        let guard = ctx.lock().await;
+        guard.global.clone()
+    };
+
+    // Register peer nodes from gossip seeds and connect (node-based dedup)
+    let node = gctx.get::<Arc<P2pNode>>().await;
+    if let Some(node) = node {
+        let reg = &node.registry;
+        let before_count = reg.get_all_seeds().len();
+
+        for seed in seeds {
+            // Register the node if not already known
+            if !reg.is_registered(&seed.node_address) {
+                if let Ok(seed_addr) = seed.address.parse::<std::net::SocketAddr>() {
+                    let scope = ip_scope::classify(&seed_addr.ip());
+                    reg.register(seed.node_address.clone(), seed_addr, scope);
+                    reg.endpoints().spawn_verify(seed_addr);
+                }
+            }
+
+            // Skip if node already connected
+            if reg.is_connected(&seed.node_address) {
+                continue;
+            }
+
+            // Skip if seed is our own address (prevent self-connect loop)
+            if let Some(local_addr) = gctx.get::<FreeWebMovementAddress>().await {
+                if seed.node_address == local_addr.to_string() {
+                    tracing::info!("⏭️ Skipping self-connect to {}", seed.address);
+                    continue;
+                }
+            }
+
+            // Tiebreaker: only the node with the smaller address initiates the connection.
+            // This prevents the race where both sides simultaneously connect and establish
+            // different session keys for the same peer pair.
+            if let Some(local_addr) = gctx.get::<FreeWebMovementAddress>().await {
+                if local_addr.to_string() > seed.node_address {
+                    tracing::info!(
+                        "⏭️ Tiebreaker: {} > {}, letting lower address initiate",
+                        local_addr,
+                        seed.node_address
+                    );
+                    continue;
+                }
+            }
+
+            if let Ok(seed_addr) = seed.address.parse::<std::net::SocketAddr>() {
+                let ctx_owned = ctx.clone();
+                let addr_str = seed.address.clone();
+                let reg_clone = reg.clone();
+                let node_addr = seed.node_address.clone();
+                tokio::spawn(async move {
+                    if super::ack::connect_to_new_peer(ctx_owned, seed_addr)
+                        .await
+                        .is_ok()
+                    {
+                        reg_clone.mark_connected(&node_addr, true);
+                    } else {
+                        tracing::error!("  ❌ Failed to connect to gossiped seed {}", addr_str);
+                    }
+                });
+            }
+        }
+
+        // Propagate to other peers if we learned new seeds
+        let after_count = reg.get_all_seeds().len();
+        if after_count > before_count {
+            let ctx_for_broadcast = ctx.clone();
+            let all_seeds: Vec<SeedRecord> = reg
+                .get_gossip_seeds()
+                .into_iter()
+                .map(|(s, na)| SeedRecord::new(s.to_string(), na))
+                .collect();
+            let seeds_to_broadcast = SeedsCommand::new(all_seeds);
+
+            tokio::spawn(async move {
+                super::ack::broadcast_seeds_to_peers(ctx_for_broadcast, &seeds_to_broadcast).await;
+            });
+        }
+    }
+}
+
 pub struct PeerOnlineEvent {
     pub addr: String,
     pub intranet_ips: Vec<String>,
@@ -742,7 +856,7 @@ pub struct PeerOfflineEvent {
 }
 
 pub fn get_all_ips() -> (Vec<String>, Vec<String>) {
-    use std::net::Ipv4Addr;
+    use std::net::IpAddr;
     use std::process::Command;
 
     let mut intranet_ips = vec![];
@@ -766,17 +880,23 @@ pub fn get_all_ips() -> (Vec<String>, Vec<String>) {
                 {
                     continue;
                 }
-                // Parse IPv4 addresses
-                if line.trim().starts_with("inet ") {
-                    let parts: Vec<&str> = line.trim().split_whitespace().collect();
+                // Parse IPv4 / IPv6 addresses
+                let trimmed = line.trim();
+                if trimmed.starts_with("inet ") || trimmed.starts_with("inet6 ") {
+                    let parts: Vec<&str> = trimmed.split_whitespace().collect();
                     if parts.len() >= 2 {
                         let ip_with_mask = parts[1];
                         let ip_str = ip_with_mask.split('/').next().unwrap_or("");
-                        if let Ok(ip) = ip_str.parse::<Ipv4Addr>() {
-                            if ip.is_loopback() || ip_str.starts_with("169.254") {
+                        if let Ok(ip) = ip_str.parse::<IpAddr>() {
+                            // 环回与链路本地地址无法被其它节点拨通
+                            let link_local = match ip {
+                                IpAddr::V4(v4) => v4.is_link_local(),
+                                IpAddr::V6(v6) => (v6.segments()[0] & 0xffc0) == 0xfe80,
+                            };
+                            if ip.is_loopback() || link_local {
                                 continue;
                             }
-                            if ip.is_private() {
+                            if ip_scope::is_inner_ip(&ip) {
                                 intranet_ips.push(ip_str.to_string());
                             } else {
                                 wan_ips.push(ip_str.to_string());
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use aex::connection::context::Context;
+use aex::connection::global::GlobalContext;
+use aex::tcp::types::Codec;
+use aex::time::SystemTime;
+use bincode::{Decode, Encode};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, oneshot};
+
+use crate::clock::{self, ClockSample};
+use crate::protocols::command::P2PCommand;
+use crate::protocols::command::{Action, Entity};
+use crate::protocols::error::{self, ProtocolError};
+use crate::protocols::frame::P2PFrame;
+use crate::protocols::peer_stats;
+
+/// 待响应的 Ping：nonce → oneshot（收到 Pong 时触发）
+pub type PendingPings = Arc<Mutex<HashMap<u64, oneshot::Sender<()>>>>;
+
+/// 最近一次测得的往返延迟（毫秒），保存在连接 Context 中，随连接一起释放
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerLatency(pub u64);
+
+/// 连接上最近一次测得的往返延迟（毫秒）
+pub async fn latency_of(ctx: &Arc<Mutex<Context>>) -> Option<u64> {
+    ctx.lock().await.get::<PeerLatency>().map(|l| l.0)
+}
+
+/// 到 `addr` 的当前连接最近一次测得的往返延迟（毫秒）；未连接时为 None
+pub async fn latency(gctx: &GlobalContext, addr: SocketAddr) -> Option<u64> {
+    let ctx = gctx.manager.find_entry(&addr)?.context.clone()?;
+    latency_of(&ctx).await
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct PingCommand {
+    pub nonce: u64,
+    pub timestamp: u128,
+}
+
+impl Codec for PingCommand {}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct PongCommand {
+    /// 原样回显 Ping 的 nonce
+    pub nonce: u64,
+    /// 原样回显 Ping 的发送时间
+    pub timestamp: u128,
+    /// 对端收到 Ping 的时间（对端时钟，毫秒），用于估算时钟偏差
+    pub received_at: u128,
+    /// 对端发出 Pong 的时间（对端时钟，毫秒）
+    pub replied_at: u128,
+}
+
+impl Codec for PongCommand {}
+
+/// 向指定连接发送 Ping 并等待 Pong，返回往返时间。
+/// 成功后会把延迟记录在该连接的 Context 中（[`PeerLatency`]）。
+pub async fn ping(
+    gctx: Arc<GlobalContext>,
+    ctx: Arc<Mutex<Context>>,
+    timeout: Duration,
+) -> anyhow::Result<Duration> {
+    let pending = match gctx.get::<PendingPings>().await {
+        Some(p) => p,
+        None => return Err(anyhow::anyhow!("PendingPings not set in GlobalContext")),
+    };
+
+    let nonce: u64 = rand::thread_rng().r#gen();
+    let (tx, rx) = oneshot::channel();
+    pending.lock().await.insert(nonce, tx);
+
+    let cmd = PingCommand {
+        nonce,
+        timestamp: SystemTime::timestamp(),
+    };
+    let started = Instant::now();
+    if let Err(e) = P2PFrame::send(ctx.clone(), &Some(cmd), Entity::Node, Action::Ping, false).await
+    {
+        pending.lock().await.remove(&nonce);
+        return Err(e);
+    }
+
+    match tokio::time::timeout(timeout, rx).await {
+        Ok(Ok(())) => {
+            let rtt = started.elapsed();
+            let peer = {
+                let mut guard = ctx.lock().await;
+                guard.set(PeerLatency(rtt.as_millis() as u64));
+                guard.addr
+            };
+            peer_stats::record_rtt(&gctx, peer, rtt).await;
+            Ok(rtt)
+        }
+        Ok(Err(_)) => Err(anyhow::anyhow!("Ping {} cancelled", nonce)),
+        Err(_) => {
+            pending.lock().await.remove(&nonce);
+            Err(anyhow::anyhow!("Ping timed out after {:?}", timeout))
+        }
+    }
+}
+
+pub async fn ping_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    let received_at = SystemTime::timestamp();
+    let ping: PingCommand = match frame.decode_payload(&cmd.data) {
+        Ok(c) => c,
+        Err(e) => {
+            error::report(
+                &ctx,
+                &frame.body.address,
+                ProtocolError::decode("PingCommand", e),
+            )
+            .await;
+            return;
+        }
+    };
+
+    tracing::debug!("🏓 Ping from {} nonce={}", frame.body.address, ping.nonce);
+
+    let pong = PongCommand {
+        nonce: ping.nonce,
+        timestamp: ping.timestamp,
+        received_at,
+        replied_at: SystemTime::timestamp(),
+    };
+    if let Err(e) = P2PFrame::send(ctx, &Some(pong), Entity::Node, Action::Pong, false).await {
+        tracing::error!("Failed to send Pong to {}: {:?}", frame.body.address, e);
+    }
+}
+
+pub async fn pong_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    let received_at = SystemTime::timestamp();
+    let pong: PongCommand = match frame.decode_payload(&cmd.data) {
+        Ok(c) => c,
+        Err(e) => {
+            error::report(
+                &ctx,
+                &frame.body.address,
+                ProtocolError::decode("PongCommand", e),
+            )
+            .await;
+            return;
+        }
+    };
+
+    let gctx = {
+        let guard = ctx.lock().await;
+        guard.global.clone()
+    };
+
+    match gctx.get::<PendingPings>().await {
+        Some(pending) => match pending.lock().await.remove(&pong.nonce) {
+            Some(tx) => {
+                let _ = tx.send(());
+                let sample = ClockSample::from_exchange(
+                    pong.timestamp,
+                    pong.received_at,
+                    pong.replied_at,
+                    received_at,
+                );
+                if let Some(sample) = sample {
+                    clock::record(&gctx, &frame.body.address, sample).await;
+                }
+            }
+            None => tracing::debug!(
+                "Unsolicited Pong from {} nonce={}",
+                frame.body.address,
+                pong.nonce
+            ),
+        },
+        None => tracing::warn!("⚠️  No PendingPings in GlobalContext"),
+    }
+}
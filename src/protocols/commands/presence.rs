@@ -0,0 +1,562 @@
+//! 在线状态记录（presence）
+//!
+//! Online / Offline 只保存在直连服务器的内存中，其它节点无从得知某个地址是否在线。
+//! 每个节点定期签发一条在线状态记录（地址、公钥、公告端点、是否在线、签发时间、过期时间），
+//! 握手完成后直接发给对端，并每 `PRESENCE_REFRESH_SECS` 秒向全网泛洪；退出时签发一条
+//! `online = false` 的记录。
+//!
+//! 直接从记录所有者收到记录的服务器把自己填入 `seen_by` 后再转发，于是其它节点的
+//! `PresenceTable` 中可以查到「谁最后直接看到了它」。`seen_by` 不在签名范围内，仅供参考。
+//! `send` 在对方未直连时据此给出可达性判断（[`Reachability`]）。已通过身份验证的地址
+//! （`IdentityBindings`）只接受其绑定公钥签发的记录。
+//! 已测得所有者时钟偏差时（[`crate::clock`]），按所有者的时钟判断记录是否过期。
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use aex::{
+    connection::{context::Context, global::GlobalContext},
+    tcp::types::Codec,
+    time::SystemTime,
+};
+use bincode::{Decode, Encode};
+use dashmap::{DashMap, mapref::entry::Entry};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::{
+    clock, connections, listen, port_mapping,
+    protocols::{
+        broadcast,
+        capabilities::{self, CAP_PRESENCE},
+        command::{Action, Entity, P2PCommand},
+        commands::{identity::IdentityBindings, observed},
+        error::{self, ProtocolError},
+        frame::P2PFrame,
+        privacy,
+    },
+    scheduler::Scheduler,
+};
+
+const PRESENCE_RECORD_LABEL: &[u8] = b"zz-p2p-presence-v1";
+/// 在线状态记录的有效期
+pub const PRESENCE_TTL_SECS: u64 = 10 * 60;
+/// 对端记录允许的最长有效期
+pub const PRESENCE_MAX_TTL_SECS: u64 = 60 * 60;
+/// 重新签发并泛洪本节点记录的间隔
+pub const PRESENCE_REFRESH_SECS: u64 = 5 * 60;
+/// 同一记录的 `seen_by` 至少间隔这么久才会被更新并再次转发
+pub const PRESENCE_SEEN_MIN_INTERVAL_MS: u128 = 60 * 1000;
+/// 允许的时钟偏差
+pub const PRESENCE_CLOCK_SKEW_MS: u128 = 5 * 60 * 1000;
+/// 本地最多缓存的记录数
+pub const PRESENCE_RECORDS_MAX: usize = 10_000;
+/// 单条记录最多携带的端点数
+pub const PRESENCE_MAX_ENDPOINTS: usize = 16;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct PresenceRecord {
+    pub address: String,
+    pub public_key: Vec<u8>,
+    /// 公告的 `ip:port` 端点
+    pub endpoints: Vec<String>,
+    pub online: bool,
+    /// 签发时间（毫秒）
+    pub issued_at: u128,
+    /// 过期时间（毫秒）
+    pub expires_at: u128,
+    /// 对记录哈希的签名
+    pub signature: Vec<u8>,
+}
+
+impl Codec for PresenceRecord {}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct PresenceAnnounceCommand {
+    pub record: PresenceRecord,
+    /// 直接从所有者收到该记录的服务器地址
+    pub seen_by: Option<String>,
+    /// 该服务器收到记录的时间（毫秒）
+    pub seen_at: u128,
+}
+
+impl Codec for PresenceAnnounceCommand {}
+
+impl PresenceRecord {
+    /// 签名覆盖的记录哈希
+    pub fn digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(PRESENCE_RECORD_LABEL);
+        for part in [self.address.as_bytes(), &self.public_key] {
+            hasher.update((part.len() as u32).to_be_bytes());
+            hasher.update(part);
+        }
+        hasher.update((self.endpoints.len() as u32).to_be_bytes());
+        for endpoint in &self.endpoints {
+            hasher.update((endpoint.len() as u32).to_be_bytes());
+            hasher.update(endpoint.as_bytes());
+        }
+        hasher.update([u8::from(self.online)]);
+        hasher.update(self.issued_at.to_be_bytes());
+        hasher.update(self.expires_at.to_be_bytes());
+        hasher.finalize().into()
+    }
+
+    /// 以 `identity` 的身份签发记录，有效期不超过 [`PRESENCE_MAX_TTL_SECS`]
+    pub fn sign(
+        identity: &FreeWebMovementAddress,
+        endpoints: Vec<String>,
+        online: bool,
+        ttl: Duration,
+        now: u128,
+    ) -> Self {
+        let ttl = ttl.min(Duration::from_secs(PRESENCE_MAX_TTL_SECS));
+        let mut endpoints = endpoints;
+        endpoints.truncate(PRESENCE_MAX_ENDPOINTS);
+        let mut record = PresenceRecord {
+            address: identity.to_string(),
+            public_key: identity.public_key.to_bytes().to_vec(),
+            endpoints,
+            online,
+            issued_at: now,
+            expires_at: now + ttl.as_millis(),
+            signature: vec![],
+        };
+        record.signature =
+            FreeWebMovementAddress::sign_message(&identity.private_key, &record.digest())
+                .serialize_compact()
+                .to_vec();
+        record
+    }
+
+    pub fn is_expired(&self, now: u128) -> bool {
+        self.expires_at <= now
+    }
+
+    /// 校验端点格式、有效期范围与签名
+    pub fn verify(&self) -> Result<(), ProtocolError> {
+        if self.endpoints.len() > PRESENCE_MAX_ENDPOINTS
+            || self
+                .endpoints
+                .iter()
+                .any(|e| e.parse::<SocketAddr>().is_err())
+        {
+            return Err(ProtocolError::decode("PresenceRecord", "invalid endpoints"));
+        }
+        let max_ttl = Duration::from_secs(PRESENCE_MAX_TTL_SECS).as_millis();
+        if self.expires_at <= self.issued_at || self.expires_at - self.issued_at > max_ttl {
+            return Err(ProtocolError::decode(
+                "PresenceRecord",
+                "invalid validity period",
+            ));
+        }
+        bitcoin::PublicKey::from_slice(&self.public_key)
+            .map_err(|_| ProtocolError::InvalidPublicKey)?;
+        bitcoin::secp256k1::ecdsa::Signature::from_compact(&self.signature)
+            .map_err(|_| ProtocolError::MalformedSignature)?;
+
+        let public_key = FreeWebMovementAddress::to_public_key(&self.public_key);
+        let signature = FreeWebMovementAddress::to_signature(&self.signature);
+        if !FreeWebMovementAddress::verify_message(&public_key, &self.digest(), &signature) {
+            return Err(ProtocolError::BadSignature);
+        }
+        Ok(())
+    }
+}
+
+/// 缓存中的一条在线状态
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PresenceEntry {
+    pub record: PresenceRecord,
+    pub seen_by: Option<String>,
+    pub seen_at: u128,
+}
+
+/// 已知的在线状态：地址 → 记录，保存在 GlobalContext 中
+pub type PresenceTable = Arc<DashMap<String, PresenceEntry>>;
+
+/// [`accept`] 的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresenceUpdate {
+    /// 新记录，或同一记录被另一台服务器更晚看到
+    Inserted,
+    /// 已有相同或更新的记录
+    Unchanged,
+    /// 记录已过期
+    Expired,
+    /// 缓存已满
+    Full,
+}
+
+/// 合并一条（已校验签名的）记录
+pub fn accept(
+    table: &DashMap<String, PresenceEntry>,
+    entry: PresenceEntry,
+    now: u128,
+) -> PresenceUpdate {
+    if entry.record.is_expired(now) {
+        return PresenceUpdate::Expired;
+    }
+    let address = entry.record.address.clone();
+    if table.len() >= PRESENCE_RECORDS_MAX && !table.contains_key(&address) {
+        table.retain(|_, e| !e.record.is_expired(now));
+        if table.len() >= PRESENCE_RECORDS_MAX {
+            return PresenceUpdate::Full;
+        }
+    }
+    let mut entry = entry;
+    // 未签名的 seen_at 不能超前于本地时钟太多
+    entry.seen_at = entry.seen_at.min(now + PRESENCE_CLOCK_SKEW_MS);
+    match table.entry(address) {
+        Entry::Occupied(mut slot) => {
+            let current = slot.get();
+            let newer = entry.record.issued_at > current.record.issued_at;
+            let seen_again = entry.record.issued_at == current.record.issued_at
+                && entry.seen_by.is_some()
+                && entry.seen_by != current.seen_by
+                && entry.seen_at >= current.seen_at + PRESENCE_SEEN_MIN_INTERVAL_MS;
+            if newer && entry.seen_by.is_none() {
+                // 经泛洪到达的新记录沿用已知的最后一台服务器
+                entry.seen_by = current.seen_by.clone();
+                entry.seen_at = current.seen_at;
+            }
+            if newer || seen_again {
+                slot.insert(entry);
+                PresenceUpdate::Inserted
+            } else {
+                PresenceUpdate::Unchanged
+            }
+        }
+        Entry::Vacant(slot) => {
+            slot.insert(entry);
+            PresenceUpdate::Inserted
+        }
+    }
+}
+
+/// 查找未过期的记录
+pub fn lookup(
+    table: &DashMap<String, PresenceEntry>,
+    address: &str,
+    now: u128,
+) -> Option<PresenceEntry> {
+    let entry = table.get(address)?.value().clone();
+    if entry.record.is_expired(now) {
+        table.remove_if(address, |_, e| e.record.is_expired(now));
+        return None;
+    }
+    Some(entry)
+}
+
+/// 对某个地址的可达性判断
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reachability {
+    /// 当前直连
+    Connected,
+    /// 有未过期的在线记录
+    LikelyOnline {
+        seen_by: Option<String>,
+        age_ms: u128,
+        endpoints: Vec<String>,
+    },
+    /// 所有者签发了下线记录
+    Offline { age_ms: u128 },
+    /// 没有未过期的记录
+    Unknown,
+}
+
+impl Reachability {
+    pub fn assess(entry: Option<&PresenceEntry>, connected: bool, now: u128) -> Self {
+        if connected {
+            return Reachability::Connected;
+        }
+        match entry {
+            Some(e) if e.record.is_expired(now) => Reachability::Unknown,
+            Some(e) if e.record.online => Reachability::LikelyOnline {
+                seen_by: e.seen_by.clone(),
+                age_ms: now.saturating_sub(e.seen_at.max(e.record.issued_at)),
+                endpoints: e.record.endpoints.clone(),
+            },
+            Some(e) => Reachability::Offline {
+                age_ms: now.saturating_sub(e.record.issued_at),
+            },
+            None => Reachability::Unknown,
+        }
+    }
+
+    pub fn is_likely_reachable(&self) -> bool {
+        matches!(
+            self,
+            Reachability::Connected | Reachability::LikelyOnline { .. }
+        )
+    }
+}
+
+impl std::fmt::Display for Reachability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Reachability::Connected => write!(f, "connected"),
+            Reachability::LikelyOnline {
+                seen_by, age_ms, ..
+            } => {
+                write!(f, "likely online, last seen {}s ago", age_ms / 1000)?;
+                if let Some(server) = seen_by {
+                    write!(f, " by {}", server)?;
+                }
+                Ok(())
+            }
+            Reachability::Offline { age_ms } => {
+                write!(f, "went offline {}s ago", age_ms / 1000)
+            }
+            Reachability::Unknown => write!(f, "no recent presence record"),
+        }
+    }
+}
+
+/// 按地址排序的未过期记录
+pub async fn list(gctx: &Arc<GlobalContext>) -> Vec<PresenceEntry> {
+    let Some(table) = gctx.get::<PresenceTable>().await else {
+        return vec![];
+    };
+    let now = SystemTime::timestamp();
+    let mut list: Vec<PresenceEntry> = table
+        .iter()
+        .filter(|e| !e.record.is_expired(now))
+        .map(|e| e.value().clone())
+        .collect();
+    list.sort_by(|a, b| a.record.address.cmp(&b.record.address));
+    list
+}
+
+/// `address` 当前的可达性
+pub async fn reachability(gctx: &Arc<GlobalContext>, address: &str) -> Reachability {
+    let connected = connections::list(gctx)
+        .await
+        .iter()
+        .any(|c| c.peer.as_deref() == Some(address));
+    let now = clock::peer_now(gctx, address, SystemTime::timestamp()).await;
+    let entry = match gctx.get::<PresenceTable>().await {
+        Some(table) => lookup(&table, address, now),
+        None => None,
+    };
+    Reachability::assess(entry.as_ref(), connected, now)
+}
+
+/// 本节点公告的端点：网卡地址、反射地址（按每个监听地址的端口）与映射的 TCP 端点；
+/// 记录会泛洪到全网，只包含 `[privacy]` 中公开的端点
+async fn own_endpoints(gctx: &Arc<GlobalContext>, identity: &str) -> Vec<String> {
+    let listen = listen::current(gctx).await;
+    let ips = aex::connection::node::Node::system_ips();
+    let (intranet, wan) = observed::announced_ips(gctx, &ips).await;
+    let parse = |list: &[String]| -> Vec<IpAddr> {
+        list.iter()
+            .filter_map(|ip| ip.parse::<IpAddr>().ok())
+            .filter(|ip| !ip.is_loopback() && !ip.is_unspecified())
+            .collect()
+    };
+    let wan_endpoints = parse(wan.as_slice()).into_iter().flat_map(|ip| {
+        listen
+            .external_ports(&ip)
+            .into_iter()
+            .map(move |port| SocketAddr::new(ip, port))
+    });
+    let mut endpoints: Vec<String> = Vec::new();
+    for addr in wan_endpoints.chain(listen.advertised(&parse(intranet.as_slice()))) {
+        let addr = addr.to_string();
+        if !endpoints.contains(&addr) {
+            endpoints.push(addr);
+        }
+    }
+    for addr in port_mapping::external_tcp_endpoints(gctx).await {
+        let addr = addr.to_string();
+        if !endpoints.contains(&addr) {
+            endpoints.insert(0, addr);
+        }
+    }
+    let policy = privacy::policy(gctx).await;
+    endpoints.retain(|addr| {
+        addr.parse::<SocketAddr>()
+            .is_ok_and(|addr| policy.shares(&addr.ip(), Some(identity), None))
+    });
+    endpoints
+}
+
+/// 签发本节点的在线状态记录
+pub async fn sign_own(gctx: &Arc<GlobalContext>, online: bool) -> Option<PresenceRecord> {
+    let identity = gctx.get::<FreeWebMovementAddress>().await?;
+    let endpoints = own_endpoints(gctx, &identity.to_string()).await;
+    Some(PresenceRecord::sign(
+        &identity,
+        endpoints,
+        online,
+        Duration::from_secs(PRESENCE_TTL_SECS),
+        SystemTime::timestamp(),
+    ))
+}
+
+/// 向所有支持在线状态的连接（可排除来源连接）发送公告
+async fn fan_out(
+    gctx: &Arc<GlobalContext>,
+    cmd: &PresenceAnnounceCommand,
+    origin: Option<Arc<Mutex<Context>>>,
+) {
+    let manager = gctx.manager.clone();
+    manager
+        .forward(|entries| async move {
+            let mut targets = Vec::new();
+            for target in broadcast::unique_peers(entries, origin.as_ref(), None).await {
+                if capabilities::peer_supports(&target.ctx, CAP_PRESENCE).await {
+                    targets.push(target);
+                }
+            }
+            broadcast::send_all(gctx, targets, |peer_ctx| {
+                let cmd = cmd.clone();
+                async move {
+                    P2PFrame::send(
+                        peer_ctx,
+                        &Some(cmd),
+                        Entity::Presence,
+                        Action::PresenceAnnounce,
+                        false,
+                    )
+                    .await
+                }
+            })
+            .await
+            .log("fan out presence");
+        })
+        .await;
+}
+
+/// 签发并向全网泛洪本节点的在线状态
+pub async fn publish(gctx: &Arc<GlobalContext>, online: bool) -> Option<PresenceRecord> {
+    let record = sign_own(gctx, online).await?;
+    let cmd = PresenceAnnounceCommand {
+        record: record.clone(),
+        seen_by: None,
+        seen_at: record.issued_at,
+    };
+    fan_out(gctx, &cmd, None).await;
+    Some(record)
+}
+
+/// 握手完成后把本节点的在线状态直接发给对端
+pub async fn send_own(ctx: Arc<Mutex<Context>>) {
+    let gctx = ctx.lock().await.global.clone();
+    if !capabilities::peer_supports(&ctx, CAP_PRESENCE).await {
+        return;
+    }
+    let Some(record) = sign_own(&gctx, true).await else {
+        return;
+    };
+    let cmd = PresenceAnnounceCommand {
+        seen_at: record.issued_at,
+        record,
+        seen_by: None,
+    };
+    if let Err(e) = P2PFrame::send(
+        ctx,
+        &Some(cmd),
+        Entity::Presence,
+        Action::PresenceAnnounce,
+        false,
+    )
+    .await
+    {
+        tracing::warn!("Failed to send presence record: {:?}", e);
+    }
+}
+
+/// 定期重新签发本节点的在线状态
+pub fn schedule_refresh(scheduler: &Scheduler, gctx: Arc<GlobalContext>) {
+    // 第一轮在一个间隔之后，握手时会单独发送
+    scheduler.register_interval(
+        "presence_refresh",
+        Duration::from_secs(PRESENCE_REFRESH_SECS),
+        move || {
+            let gctx = gctx.clone();
+            async move { publish(&gctx, true).await }
+        },
+    );
+}
+
+/// 已验证身份的地址只接受其绑定公钥签发的记录
+async fn check_binding(
+    gctx: &Arc<GlobalContext>,
+    record: &PresenceRecord,
+) -> Result<(), ProtocolError> {
+    match gctx.get::<IdentityBindings>().await {
+        Some(bindings) => match bindings.get(&record.address) {
+            Some(key) if key.value() != &record.public_key => {
+                Err(ProtocolError::IdentityMismatch {
+                    claimed: record.address.clone(),
+                })
+            }
+            _ => Ok(()),
+        },
+        None => Ok(()),
+    }
+}
+
+pub async fn presence_announce_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    let peer = frame.body.address.clone();
+    let mut announce: PresenceAnnounceCommand =
+        match error::decode_command("PresenceAnnounceCommand", &frame, &cmd.data) {
+            Ok(a) => a,
+            Err(e) => {
+                error::report(&ctx, &peer, e).await;
+                return;
+            }
+        };
+    let gctx = ctx.lock().await.global.clone();
+    let checked = match announce.record.verify() {
+        Ok(()) => check_binding(&gctx, &announce.record).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = checked {
+        error::report(&ctx, &peer, e).await;
+        return;
+    }
+    let Some(table) = gctx.get::<PresenceTable>().await else {
+        return;
+    };
+    let now = SystemTime::timestamp();
+    // 直接来自所有者：由本节点作为最后看到它的服务器
+    if peer == announce.record.address {
+        announce.seen_by = gctx
+            .get::<FreeWebMovementAddress>()
+            .await
+            .map(|a| a.to_string());
+        announce.seen_at = now;
+    }
+    let entry = PresenceEntry {
+        record: announce.record.clone(),
+        seen_by: announce.seen_by.clone(),
+        seen_at: announce.seen_at,
+    };
+    // 按所有者的时钟判断记录是否过期
+    let now = clock::peer_now(&gctx, &announce.record.address, now).await;
+    match accept(&table, entry, now) {
+        PresenceUpdate::Inserted => {
+            tracing::debug!(
+                "👁️ Presence {} online={} via {}",
+                announce.record.address,
+                announce.record.online,
+                peer
+            );
+            // 只转发新记录，重复的记录在这里终止泛洪
+            fan_out(&gctx, &announce, Some(ctx)).await;
+        }
+        update => tracing::trace!(
+            "Presence {} from {}: {:?}",
+            announce.record.address,
+            peer,
+            update
+        ),
+    }
+}
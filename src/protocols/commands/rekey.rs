@@ -0,0 +1,549 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use aex::connection::context::Context;
+use aex::connection::global::GlobalContext;
+use aex::crypto::session_key_manager::PairedSessionKey;
+use aex::tcp::types::Codec;
+use aex::time::SystemTime;
+use bincode::{Decode, Encode};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::config::{SessionConfig, SharedConfig};
+use crate::identities;
+use crate::node::Node as P2pNode;
+use crate::protocols::command::P2PCommand;
+use crate::protocols::command::{Action, Entity};
+use crate::protocols::error::{self, ProtocolError};
+use crate::protocols::frame::P2PFrame;
+use crate::protocols::routing::{self, RoutingTable};
+use crate::scheduler::Scheduler;
+
+/// 会话过期检查间隔
+pub const SESSION_CHECK_INTERVAL_SECS: u64 = 30;
+/// 发起轮换后等待 RekeyAck 的时间，超时后允许重新发起
+pub const REKEY_PENDING_TIMEOUT_MS: u128 = 30_000;
+/// 轮换后旧密钥继续用于解密的时间
+pub const PREVIOUS_KEY_GRACE_MS: u128 = 30_000;
+/// 轮换出的新密钥单独存放，每个密钥表只保存一个对端的密钥
+const ROTATED_KEYS_CAPACITY: usize = 2;
+
+/// 每个对端会话密钥的使用情况
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionStats {
+    /// 当前密钥建立时间（毫秒时间戳）
+    pub established_at: u128,
+    /// 当前密钥已加密的字节数
+    pub bytes: u64,
+    /// 已轮换次数
+    pub generation: u32,
+    /// 已发起但尚未完成的轮换（发起时间）
+    pub rekey_started_at: Option<u128>,
+}
+
+impl SessionStats {
+    /// 按 TTL 或流量判断密钥是否需要轮换
+    pub fn is_expired(&self, policy: &SessionConfig, now: u128) -> bool {
+        let ttl_hit = policy.ttl_secs > 0
+            && now.saturating_sub(self.established_at) >= policy.ttl_secs as u128 * 1000;
+        let bytes_hit = policy.max_bytes > 0 && self.bytes >= policy.max_bytes;
+        ttl_hit || bytes_hit
+    }
+
+    fn rekey_pending(&self, now: u128) -> bool {
+        match self.rekey_started_at {
+            Some(started) => now.saturating_sub(started) < REKEY_PENDING_TIMEOUT_MS,
+            None => false,
+        }
+    }
+}
+
+/// 会话表：对端地址 → 会话密钥使用情况
+pub type SessionTable = Arc<DashMap<String, SessionStats>>;
+
+/// 记录与某个对端的会话密钥已（重新）建立
+pub fn record_established(table: &SessionTable, peer: &str) {
+    let mut entry = table.entry(peer.to_string()).or_default();
+    if entry.established_at != 0 {
+        entry.generation += 1;
+    }
+    entry.established_at = SystemTime::timestamp();
+    entry.bytes = 0;
+    entry.rekey_started_at = None;
+}
+
+/// 累加某个对端会话密钥加密的字节数
+pub fn record_bytes(table: &SessionTable, peer: &str, len: usize) {
+    if let Some(mut entry) = table.get_mut(peer) {
+        entry.bytes += len as u64;
+    }
+}
+
+/// 返回需要轮换的对端。
+/// 双方同时到期时只由地址较小的一方发起，避免两边同时轮换。
+pub fn due_for_rotation(
+    table: &SessionTable,
+    local: &str,
+    policy: &SessionConfig,
+    now: u128,
+) -> Vec<String> {
+    table
+        .iter()
+        .filter(|e| local < e.key().as_str())
+        .filter(|e| e.value().is_expired(policy, now) && !e.value().rekey_pending(now))
+        .map(|e| e.key().clone())
+        .collect()
+}
+
+/// 某个对端轮换后的密钥：新密钥与宽限期内的旧密钥
+#[derive(Clone)]
+struct RotatedKeys {
+    current: Arc<Mutex<PairedSessionKey>>,
+    /// 旧密钥表及其失效时间（毫秒时间戳）
+    previous: Option<(Arc<Mutex<PairedSessionKey>>, u128)>,
+}
+
+/// 会话密钥轮换状态。
+///
+/// 新密钥放在独立的密钥表中，与主密钥表（握手建立的密钥）并存：发送总是使用最新的密钥，
+/// 接收在新密钥解密失败时回退到旧密钥，直到宽限期结束。双方切换密钥有先后，
+/// 切换前后在途的帧因此不会解密失败。
+#[derive(Default)]
+pub struct KeyRotation {
+    /// 对端地址 → 轮换后的密钥
+    rotated: DashMap<String, RotatedKeys>,
+    /// 本节点发起、等待 RekeyAck 的轮换：session_id → (新密钥表, 发起时间)
+    pending: DashMap<Vec<u8>, (Arc<Mutex<PairedSessionKey>>, u128)>,
+}
+
+pub type SharedKeyRotation = Arc<KeyRotation>;
+
+impl KeyRotation {
+    /// 与 `peer` 通信当前使用的密钥表；未轮换过时为 `primary`
+    pub fn current(
+        &self,
+        primary: &Arc<Mutex<PairedSessionKey>>,
+        peer: &str,
+    ) -> Arc<Mutex<PairedSessionKey>> {
+        match self.rotated.get(peer) {
+            Some(keys) => keys.current.clone(),
+            None => primary.clone(),
+        }
+    }
+
+    /// 发起方：在新的密钥表中生成临时密钥，返回 session_id 与临时公钥。
+    /// 超时未收到 RekeyAck 的轮换同时被清理
+    pub async fn begin(&self, now: u128) -> (Vec<u8>, [u8; 32]) {
+        self.pending
+            .retain(|_, (_, started)| now.saturating_sub(*started) < REKEY_PENDING_TIMEOUT_MS);
+        let staged = Arc::new(Mutex::new(PairedSessionKey::new(ROTATED_KEYS_CAPACITY)));
+        let (session_id, ephemeral_public) = staged.lock().await.create(false).await;
+        self.pending.insert(session_id.clone(), (staged, now));
+        (session_id, ephemeral_public.to_bytes())
+    }
+
+    /// 响应方：用发起方的临时公钥在新的密钥表中完成 DH，返回新密钥表与自己的临时公钥。
+    /// 新密钥需在 RekeyAck 发出后经 [`KeyRotation::install`] 启用
+    pub async fn respond(
+        &self,
+        peer: &str,
+        local: &str,
+        ephemeral_public_key: &[u8; 32],
+    ) -> anyhow::Result<(Arc<Mutex<PairedSessionKey>>, [u8; 32])> {
+        let staged = Arc::new(Mutex::new(PairedSessionKey::new(ROTATED_KEYS_CAPACITY)));
+        let established = {
+            let guard = staged.lock().await;
+            let (session_id, ephemeral_public) = guard.create(false).await;
+            let established = guard
+                .establish_ends(
+                    session_id,
+                    peer.as_bytes().to_vec(),
+                    local.as_bytes().to_vec(),
+                    &ephemeral_public_key.to_vec(),
+                )
+                .await?;
+            established.then_some(ephemeral_public.to_bytes())
+        };
+        match established {
+            Some(public) => Ok((staged, public)),
+            None => Err(anyhow::anyhow!("Rekey session with {} not found", peer)),
+        }
+    }
+
+    /// 发起方收到 RekeyAck：完成 DH 并启用新密钥。`session_id` 不是本节点发起的轮换时返回 `false`
+    pub async fn complete(
+        &self,
+        primary: &Arc<Mutex<PairedSessionKey>>,
+        session_id: &[u8],
+        peer: &str,
+        local: &str,
+        ephemeral_public_key: &[u8; 32],
+        now: u128,
+    ) -> anyhow::Result<bool> {
+        let Some((_, (staged, _))) = self.pending.remove(session_id) else {
+            return Ok(false);
+        };
+        let established = staged
+            .lock()
+            .await
+            .establish_ends(
+                session_id.to_vec(),
+                peer.as_bytes().to_vec(),
+                local.as_bytes().to_vec(),
+                &ephemeral_public_key.to_vec(),
+            )
+            .await?;
+        if !established {
+            return Err(anyhow::anyhow!("Rekey session with {} not found", peer));
+        }
+        self.install(primary, peer, staged, now);
+        Ok(true)
+    }
+
+    /// 启用新密钥，此前的密钥在宽限期内继续用于解密
+    pub fn install(
+        &self,
+        primary: &Arc<Mutex<PairedSessionKey>>,
+        peer: &str,
+        keys: Arc<Mutex<PairedSessionKey>>,
+        now: u128,
+    ) {
+        let previous = self.current(primary, peer);
+        self.rotated.insert(
+            peer.to_string(),
+            RotatedKeys {
+                current: keys,
+                previous: Some((previous, now + PREVIOUS_KEY_GRACE_MS)),
+            },
+        );
+    }
+
+    /// 重新握手后主密钥表中的密钥是最新的，丢弃轮换出的密钥
+    pub fn reset(&self, peer: &str) {
+        self.rotated.remove(peer);
+    }
+
+    /// 用 `peer` 当前的密钥加密
+    pub async fn encrypt(
+        &self,
+        primary: &Arc<Mutex<PairedSessionKey>>,
+        peer: &str,
+        data: &[u8],
+    ) -> anyhow::Result<Vec<u8>> {
+        let keys = self.current(primary, peer);
+        let guard = keys.lock().await;
+        guard.encrypt(&peer.as_bytes().to_vec(), data).await
+    }
+
+    /// 用 `peer` 当前的密钥解密，失败时在宽限期内回退到旧密钥
+    pub async fn decrypt(
+        &self,
+        primary: &Arc<Mutex<PairedSessionKey>>,
+        peer: &str,
+        data: &[u8],
+        now: u128,
+    ) -> anyhow::Result<Vec<u8>> {
+        let key = peer.as_bytes().to_vec();
+        let (current, previous) = match self.rotated.get(peer) {
+            Some(keys) => (keys.current.clone(), keys.previous.clone()),
+            None => (primary.clone(), None),
+        };
+        let result = current.lock().await.decrypt(&key, data).await;
+        match (result, previous) {
+            (Err(e), Some((previous, until))) => {
+                if now >= until {
+                    return Err(e);
+                }
+                let previous = previous.lock().await;
+                previous.decrypt(&key, data).await.map_err(|_| e)
+            }
+            (result, _) => result,
+        }
+    }
+}
+
+/// 主身份的密钥表启用了轮换时返回轮换状态；附加身份的密钥表不参与轮换
+async fn rotation_for(
+    gctx: &GlobalContext,
+    psk: &Arc<Mutex<PairedSessionKey>>,
+) -> Option<SharedKeyRotation> {
+    let primary = gctx.paired_session_keys.as_ref()?;
+    if !Arc::ptr_eq(primary, psk) {
+        return None;
+    }
+    gctx.get::<SharedKeyRotation>().await
+}
+
+/// 以 `psk` 加密发往 `peer` 的数据，`peer` 的密钥轮换过时使用新密钥
+pub async fn encrypt(
+    gctx: &GlobalContext,
+    psk: &Arc<Mutex<PairedSessionKey>>,
+    peer: &str,
+    data: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    match rotation_for(gctx, psk).await {
+        Some(rotation) => rotation.encrypt(psk, peer, data).await,
+        None => {
+            let guard = psk.lock().await;
+            guard.encrypt(&peer.as_bytes().to_vec(), data).await
+        }
+    }
+}
+
+/// 以 `psk` 解密来自 `peer` 的数据，轮换宽限期内可用旧密钥解密
+pub async fn decrypt(
+    gctx: &GlobalContext,
+    psk: &Arc<Mutex<PairedSessionKey>>,
+    peer: &str,
+    data: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    match rotation_for(gctx, psk).await {
+        Some(rotation) => {
+            rotation
+                .decrypt(psk, peer, data, SystemTime::timestamp())
+                .await
+        }
+        None => {
+            let guard = psk.lock().await;
+            guard.decrypt(&peer.as_bytes().to_vec(), data).await
+        }
+    }
+}
+
+/// 与 `peer` 重新握手后调用：丢弃轮换出的密钥，改用主密钥表中新建立的密钥
+pub async fn reset_keys(gctx: &GlobalContext, peer: &str) {
+    if let Some(rotation) = gctx.get::<SharedKeyRotation>().await {
+        rotation.reset(peer);
+    }
+}
+
+/// Rekey / RekeyAck 共用：携带新的临时公钥
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct RekeyCommand {
+    /// 发起方 `create` 得到的会话 id，RekeyAck 原样回显
+    pub session_id: Vec<u8>,
+    pub ephemeral_public_key: [u8; 32],
+}
+
+impl Codec for RekeyCommand {}
+
+async fn local_address(gctx: &Arc<GlobalContext>) -> anyhow::Result<String> {
+    match gctx.get::<FreeWebMovementAddress>().await {
+        Some(a) => Ok(a.to_string()),
+        None => Err(anyhow::anyhow!("Address not set")),
+    }
+}
+
+/// 向对端发起会话密钥轮换。新密钥在收到 RekeyAck 后生效，
+/// 旧密钥在宽限期内继续用于解密，上层无需感知。
+pub async fn rekey(
+    gctx: Arc<GlobalContext>,
+    ctx: Arc<Mutex<Context>>,
+    peer: &str,
+) -> anyhow::Result<()> {
+    let rotation = match gctx.get::<SharedKeyRotation>().await {
+        Some(rotation) => rotation,
+        None => return Err(anyhow::anyhow!("KeyRotation not set in GlobalContext")),
+    };
+    let (session_id, ephemeral_public_key) = rotation.begin(SystemTime::timestamp()).await;
+
+    if let Some(table) = gctx.get::<SessionTable>().await {
+        if let Some(mut entry) = table.get_mut(peer) {
+            entry.rekey_started_at = Some(SystemTime::timestamp());
+        }
+    }
+
+    let cmd = RekeyCommand {
+        session_id,
+        ephemeral_public_key,
+    };
+    tracing::info!("🔄 Rotating session key with {}", peer);
+    P2PFrame::send(ctx, &Some(cmd), Entity::Node, Action::Rekey, false).await
+}
+
+pub async fn rekey_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    let request: RekeyCommand = match frame.decode_payload(&cmd.data) {
+        Ok(c) => c,
+        Err(e) => {
+            error::report(
+                &ctx,
+                &frame.body.address,
+                ProtocolError::decode("RekeyCommand", e),
+            )
+            .await;
+            return;
+        }
+    };
+
+    let gctx = {
+        let guard = ctx.lock().await;
+        guard.global.clone()
+    };
+    let (Some(psk), Some(rotation), Ok(local)) = (
+        gctx.paired_session_keys.clone(),
+        gctx.get::<SharedKeyRotation>().await,
+        local_address(&gctx).await,
+    ) else {
+        tracing::error!("PairedSessionKeys, KeyRotation or address not set in GlobalContext");
+        return;
+    };
+
+    // 响应方同样生成一次性临时密钥，双方各自用对方的公钥完成 DH
+    let peer = frame.body.address.clone();
+    let (keys, ephemeral_public_key) = match rotation
+        .respond(&peer, &local, &request.ephemeral_public_key)
+        .await
+    {
+        Ok(staged) => staged,
+        Err(e) => {
+            tracing::error!("❌ Rekey with {} failed: {:?}", peer, e);
+            return;
+        }
+    };
+
+    // 对端的附加身份以自己的地址签名发起：经由该连接的对端可达
+    let neighbor: Option<String> = ctx.lock().await.get();
+    if let (Some(neighbor), Some(table)) = (neighbor, gctx.get::<RoutingTable>().await) {
+        if neighbor != frame.body.address {
+            routing::learn(&table, &frame.body.address, &neighbor, 2);
+        }
+    }
+
+    let ack = RekeyCommand {
+        session_id: request.session_id,
+        ephemeral_public_key,
+    };
+    if let Err(e) = P2PFrame::send(ctx, &Some(ack), Entity::Node, Action::RekeyAck, false).await {
+        tracing::error!("Failed to send RekeyAck: {:?}", e);
+        return;
+    }
+    // RekeyAck 发出后才启用新密钥：之后发出的帧排在 RekeyAck 之后，发起方已能解密；
+    // 发起方在收到 RekeyAck 之前用旧密钥发出的帧由宽限期内的旧密钥解密
+    rotation.install(&psk, &peer, keys, SystemTime::timestamp());
+    if let Some(table) = gctx.get::<SessionTable>().await {
+        record_established(&table, &peer);
+    }
+    tracing::info!("🔑 Session key rotated with {}", peer);
+}
+
+pub async fn rekey_ack_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    let ack: RekeyCommand = match frame.decode_payload(&cmd.data) {
+        Ok(c) => c,
+        Err(e) => {
+            error::report(
+                &ctx,
+                &frame.body.address,
+                ProtocolError::decode("RekeyAck", e),
+            )
+            .await;
+            return;
+        }
+    };
+
+    let gctx = {
+        let guard = ctx.lock().await;
+        guard.global.clone()
+    };
+    // 本节点附加身份发起的会话
+    if identities::finish_session(
+        &gctx,
+        &ack.session_id,
+        &frame.body.address,
+        &ack.ephemeral_public_key,
+    )
+    .await
+    {
+        return;
+    }
+    let (Some(psk), Some(rotation), Ok(local)) = (
+        gctx.paired_session_keys.clone(),
+        gctx.get::<SharedKeyRotation>().await,
+        local_address(&gctx).await,
+    ) else {
+        tracing::error!("PairedSessionKeys, KeyRotation or address not set in GlobalContext");
+        return;
+    };
+    let peer = &frame.body.address;
+    match rotation
+        .complete(
+            &psk,
+            &ack.session_id,
+            peer,
+            &local,
+            &ack.ephemeral_public_key,
+            SystemTime::timestamp(),
+        )
+        .await
+    {
+        Ok(true) => {
+            if let Some(table) = gctx.get::<SessionTable>().await {
+                record_established(&table, peer);
+            }
+            tracing::info!("🔑 Session key rotated with {}", peer);
+        }
+        Ok(false) => tracing::warn!("⚠️ RekeyAck from {} for an unknown session", peer),
+        Err(e) => tracing::error!("❌ Rekey with {} failed: {:?}", peer, e),
+    }
+}
+
+/// 后台定期检查会话表，对到期的会话透明地发起轮换。
+/// 策略读取自 `SharedConfig`，因此可随配置文件热更新。
+pub fn schedule_rotation(scheduler: &Scheduler, gctx: Arc<GlobalContext>) {
+    scheduler.register_interval(
+        "session_rotation",
+        Duration::from_secs(SESSION_CHECK_INTERVAL_SECS),
+        move || {
+            let gctx = gctx.clone();
+            async move {
+                let (Some(table), Some(config), Ok(local)) = (
+                    gctx.get::<SessionTable>().await,
+                    gctx.get::<SharedConfig>().await,
+                    local_address(&gctx).await,
+                ) else {
+                    return;
+                };
+                let policy = config.read().await.session.clone();
+                let due = due_for_rotation(&table, &local, &policy, SystemTime::timestamp());
+                if due.is_empty() {
+                    return;
+                }
+                rotate(&gctx, due).await;
+            }
+        },
+    );
+}
+
+/// 与 `peers` 逐个发起轮换，返回成功发出 Rekey 的对端数；已断开的对端从会话表中移除
+pub async fn rotate(gctx: &Arc<GlobalContext>, peers: Vec<String>) -> usize {
+    let (Some(table), Some(node)) = (
+        gctx.get::<SessionTable>().await,
+        gctx.get::<Arc<P2pNode>>().await,
+    ) else {
+        return 0;
+    };
+    let mut started = 0;
+    for peer in peers {
+        let peer_ctx = node
+            .registry
+            .get_seeds_for_node(&peer)
+            .iter()
+            .find_map(|addr| {
+                gctx.manager
+                    .find_entry(addr)
+                    .and_then(|entry| entry.context.clone())
+            });
+        let Some(peer_ctx) = peer_ctx else {
+            // 对端已断开：下次握手会重新建立密钥
+            table.remove(&peer);
+            continue;
+        };
+        match rekey(gctx.clone(), peer_ctx, &peer).await {
+            Ok(()) => started += 1,
+            Err(e) => tracing::error!("Failed to rotate session key with {}: {:?}", peer, e),
+        }
+    }
+    started
+}
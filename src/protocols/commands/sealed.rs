@@ -0,0 +1,328 @@
+//! 端到端加密的点对点消息（sealed sender）
+//!
+//! `SendText` 的负载只用连接两端协商的会话密钥加密，消息经服务器存储转发时，中间节点能读到
+//! `MessageCommand` 的明文。`SendEncrypted` 把整条 `MessageCommand` 加密给接收方的身份公钥：
+//! 发送方生成一次性的 secp256k1 密钥，与接收方公钥做 ECDH，经 HKDF 派生出 ChaCha20-Poly1305
+//! 密钥。中继只看到接收方地址、一次性公钥与密文。
+//!
+//! 密文中带有发送方对 `接收方 | 一次性公钥 | 消息` 的签名，接收方据此确认发送方身份，
+//! 已验证身份的地址只接受其绑定的公钥。发送方从已验证的直连对端（`IdentityBindings`）
+//! 或未过期的在线状态记录中取得接收方公钥。
+//!
+//! sealed-sender 模式下帧改由每条消息新生成的一次性身份签名，中继看不到真实的发送方地址；
+//! 白名单模式（`acl`）的节点会拒绝这样的帧。端到端加密的消息不写入 WAL。
+
+use std::sync::Arc;
+
+use aex::{
+    connection::{context::Context, global::GlobalContext},
+    tcp::types::Codec,
+    time::SystemTime,
+};
+use bincode::{Decode, Encode};
+use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey, ecdh::SharedSecret};
+use chacha20poly1305::{
+    ChaCha20Poly1305, Key, Nonce,
+    aead::{Aead, KeyInit, Payload},
+};
+use hkdf::Hkdf;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::identities::{LocalIdentity, SharedIdentities};
+use crate::protocols::capabilities::CAP_SEALED;
+use crate::protocols::command::{Action, Entity, P2PCommand};
+use crate::protocols::commands::identity::IdentityBindings;
+use crate::protocols::commands::message::{self, MessageCommand};
+use crate::protocols::commands::presence::{self, PresenceTable};
+use crate::protocols::error::{self, ProtocolError};
+use crate::protocols::frame::P2PFrame;
+use crate::protocols::routing;
+
+const SEALED_LABEL: &[u8] = b"zz-p2p-sealed-v1";
+
+/// 加密给接收方身份公钥的文本消息；中继只能看到这些字段
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct SealedMessageCommand {
+    pub receiver: String,
+    /// 发送方一次性公钥（压缩格式）
+    pub ephemeral_key: Vec<u8>,
+    pub nonce: [u8; 12],
+    pub sealed: Vec<u8>,
+}
+
+impl Codec for SealedMessageCommand {}
+
+/// 密文中的内容
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct SealedContent {
+    pub message: MessageCommand,
+    /// 发送方身份公钥
+    pub public_key: Vec<u8>,
+    /// 发送方对 [`content_digest`] 的签名
+    pub signature: Vec<u8>,
+}
+
+impl Codec for SealedContent {}
+
+/// 带标签、逐段加长度前缀的哈希
+fn digest(kind: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(SEALED_LABEL);
+    hasher.update(kind);
+    for part in parts {
+        hasher.update((part.len() as u32).to_be_bytes());
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+/// 发送方签名的哈希：绑定接收方与一次性公钥，密文无法被转加密给其他人
+pub fn content_digest(
+    receiver: &str,
+    ephemeral_key: &[u8],
+    message: &MessageCommand,
+) -> anyhow::Result<[u8; 32]> {
+    let encoded = Codec::encode(message)?;
+    Ok(digest(
+        b"content",
+        &[receiver.as_bytes(), ephemeral_key, &encoded],
+    ))
+}
+
+fn aad(receiver: &str, ephemeral_key: &[u8]) -> [u8; 32] {
+    digest(b"aad", &[receiver.as_bytes(), ephemeral_key])
+}
+
+fn derive_key(
+    shared: &SharedSecret,
+    ephemeral_key: &[u8],
+    receiver: &str,
+) -> anyhow::Result<[u8; 32]> {
+    let hk = Hkdf::<Sha256>::new(Some(ephemeral_key), &shared.secret_bytes());
+    let mut key = [0u8; 32];
+    hk.expand(&[SEALED_LABEL, receiver.as_bytes()].concat(), &mut key)
+        .map_err(|_| anyhow::anyhow!("HKDF expand failed"))?;
+    Ok(key)
+}
+
+/// 把 `message` 加密给身份公钥为 `receiver_key` 的接收方，并以 `sender` 的身份签名
+pub fn seal(
+    sender: &FreeWebMovementAddress,
+    receiver_key: &[u8],
+    message: &MessageCommand,
+) -> anyhow::Result<SealedMessageCommand> {
+    let receiver_key = PublicKey::from_slice(receiver_key)
+        .map_err(|_| anyhow::anyhow!("Invalid receiver public key"))?;
+    let ephemeral = SecretKey::from_slice(&random_bytes::<32>())?;
+    let ephemeral_key = PublicKey::from_secret_key(&Secp256k1::new(), &ephemeral)
+        .serialize()
+        .to_vec();
+    let receiver = message.receiver.clone();
+    let key = derive_key(
+        &SharedSecret::new(&receiver_key, &ephemeral),
+        &ephemeral_key,
+        &receiver,
+    )?;
+
+    let signature = FreeWebMovementAddress::sign_message(
+        &sender.private_key,
+        &content_digest(&receiver, &ephemeral_key, message)?,
+    )
+    .serialize_compact()
+    .to_vec();
+    let content = SealedContent {
+        message: message.clone(),
+        public_key: sender.public_key.to_bytes().to_vec(),
+        signature,
+    };
+    let nonce = random_bytes::<12>();
+    let sealed = ChaCha20Poly1305::new(Key::from_slice(&key))
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &Codec::encode(&content)?,
+                aad: &aad(&receiver, &ephemeral_key),
+            },
+        )
+        .map_err(|_| anyhow::anyhow!("Sealed message encryption failed"))?;
+    Ok(SealedMessageCommand {
+        receiver,
+        ephemeral_key,
+        nonce,
+        sealed,
+    })
+}
+
+/// 以接收方身份 `identity` 解密并校验发送方签名
+pub fn open(
+    identity: &FreeWebMovementAddress,
+    cmd: &SealedMessageCommand,
+) -> Result<SealedContent, ProtocolError> {
+    let ephemeral =
+        PublicKey::from_slice(&cmd.ephemeral_key).map_err(|_| ProtocolError::InvalidPublicKey)?;
+    let key = derive_key(
+        &SharedSecret::new(&ephemeral, &identity.private_key.inner),
+        &cmd.ephemeral_key,
+        &cmd.receiver,
+    )
+    .map_err(ProtocolError::decrypt)?;
+    let plaintext = ChaCha20Poly1305::new(Key::from_slice(&key))
+        .decrypt(
+            Nonce::from_slice(&cmd.nonce),
+            Payload {
+                msg: &cmd.sealed,
+                aad: &aad(&cmd.receiver, &cmd.ephemeral_key),
+            },
+        )
+        .map_err(|_| ProtocolError::decrypt("sealed message authentication failed"))?;
+    let content: SealedContent =
+        Codec::decode(&plaintext).map_err(|e| ProtocolError::decode("SealedContent", e))?;
+    if content.message.receiver != cmd.receiver {
+        return Err(ProtocolError::decode("SealedContent", "receiver mismatch"));
+    }
+
+    bitcoin::PublicKey::from_slice(&content.public_key)
+        .map_err(|_| ProtocolError::InvalidPublicKey)?;
+    bitcoin::secp256k1::ecdsa::Signature::from_compact(&content.signature)
+        .map_err(|_| ProtocolError::MalformedSignature)?;
+    let digest = content_digest(&cmd.receiver, &cmd.ephemeral_key, &content.message)
+        .map_err(|e| ProtocolError::decode("MessageCommand", e))?;
+    let public_key = FreeWebMovementAddress::to_public_key(&content.public_key);
+    let signature = FreeWebMovementAddress::to_signature(&content.signature);
+    if !FreeWebMovementAddress::verify_message(&public_key, &digest, &signature) {
+        return Err(ProtocolError::BadSignature);
+    }
+    Ok(content)
+}
+
+/// 已知的 `address` 身份公钥：已验证的直连对端优先，其次是未过期的在线状态记录
+pub async fn public_key_of(gctx: &Arc<GlobalContext>, address: &str) -> Option<Vec<u8>> {
+    if let Some(bindings) = gctx.get::<IdentityBindings>().await {
+        if let Some(key) = bindings.get(address) {
+            return Some(key.value().clone());
+        }
+    }
+    let table = gctx.get::<PresenceTable>().await?;
+    presence::lookup(&table, address, SystemTime::timestamp()).map(|e| e.record.public_key)
+}
+
+/// 已验证身份的地址只接受其绑定的公钥
+async fn check_binding(
+    gctx: &Arc<GlobalContext>,
+    address: &str,
+    public_key: &[u8],
+) -> Result<(), ProtocolError> {
+    if let Some(bindings) = gctx.get::<IdentityBindings>().await {
+        if let Some(key) = bindings.get(address) {
+            if key.value().as_slice() != public_key {
+                return Err(ProtocolError::IdentityMismatch {
+                    claimed: address.to_string(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 地址为 `address` 的本地身份（主身份或附加身份）
+async fn local_identity(
+    gctx: &Arc<GlobalContext>,
+    address: &str,
+) -> Option<FreeWebMovementAddress> {
+    if let Some(identities) = gctx.get::<SharedIdentities>().await {
+        return identities.get(address).map(|i| i.address);
+    }
+    gctx.get::<FreeWebMovementAddress>()
+        .await
+        .filter(|a| a.to_string() == address)
+}
+
+/// 以 `identity` 向 `receiver` 发送端到端加密的文本消息，返回 request_id；
+/// `hide_sender` 为 true 时帧由一次性身份签名
+pub async fn send(
+    gctx: &Arc<GlobalContext>,
+    identity: &LocalIdentity,
+    receiver: &str,
+    content: &str,
+    hide_sender: bool,
+) -> anyhow::Result<u64> {
+    let receiver_key = public_key_of(gctx, receiver).await.ok_or_else(|| {
+        anyhow::anyhow!(
+            "No public key known for {} (not verified and no presence record)",
+            receiver
+        )
+    })?;
+    let ctx = routing::connection_to(gctx, receiver, CAP_SEALED).await?;
+
+    let request_id = message::next_request_id();
+    let command = message::text_command(
+        gctx,
+        identity.address.to_string(),
+        receiver.to_string(),
+        request_id,
+        content,
+    )
+    .await;
+    let sealed = seal(&identity.address, &receiver_key, &command)?;
+    let signer = if hide_sender {
+        FreeWebMovementAddress::random()
+    } else {
+        identity.address.clone()
+    };
+    P2PFrame::send_as(
+        ctx,
+        &signer,
+        &Some(sealed),
+        Entity::Message,
+        Action::SendEncrypted,
+        false,
+    )
+    .await?;
+    Ok(request_id)
+}
+
+pub async fn sealed_message_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    // 目标不是本节点：按路由表中继，中继无法解密
+    if routing::relay_if_not_for_us(ctx.clone(), &frame).await {
+        return;
+    }
+    let peer = frame.body.address.clone();
+    let sealed: SealedMessageCommand =
+        match error::decode_command("SealedMessageCommand", &frame, &cmd.data) {
+            Ok(c) => c,
+            Err(e) => {
+                error::report(&ctx, &peer, e).await;
+                return;
+            }
+        };
+    let gctx = { ctx.lock().await.global.clone() };
+    let Some(identity) = local_identity(&gctx, &sealed.receiver).await else {
+        tracing::info!(
+            "  ⏭️  Sealed message not for us (receiver={}), dropping",
+            sealed.receiver
+        );
+        return;
+    };
+    let content = match open(&identity, &sealed) {
+        Ok(content) => content,
+        Err(e) => {
+            error::report(&ctx, &peer, e).await;
+            return;
+        }
+    };
+    if let Err(e) = check_binding(&gctx, &content.message.sender, &content.public_key).await {
+        error::report(&ctx, &peer, e).await;
+        return;
+    }
+    message::receive(ctx, gctx, &peer, content.message).await;
+}
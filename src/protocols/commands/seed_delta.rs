@@ -0,0 +1,304 @@
+//! 种子列表增量同步
+//!
+//! 传播 seeds（学到新节点、tick）时原本每次都向每个对端重发完整的 `SeedsCommand`。
+//! 双方都声明 `CAP_SEED_DELTA` 时改为只发送变化：发送方为每个对端记住「对方已知的本节点
+//! 列表」，新列表与之比较后只发送新增与删除的条目（`SeedsDelta`，带基准摘要与目标摘要），
+//! 没有变化时不发送。接收方为每个发送方保存一份副本，基准摘要一致时应用增量并校验目标摘要；
+//! 不一致（丢帧、任一方重启）时清空副本并回复 `SeedsResync`，发送方随即从空列表开始重新发送
+//! 完整列表。握手中的 seeds 仍是完整列表，未声明该能力的对端照旧收到完整列表。
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use aex::{connection::context::Context, tcp::types::Codec};
+use bincode::{Decode, Encode};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+
+use crate::protocols::{
+    broadcast::Target,
+    capabilities::CAP_SEED_DELTA,
+    command::{Action, Entity, P2PCommand},
+    commands::{ack::SeedRecord, online::merge_gossip_seeds},
+    compression::PeerCapabilities,
+    error::{self, ProtocolError},
+    frame::P2PFrame,
+    privacy,
+};
+
+/// 单个增量最多携带的条目数（新增 + 删除），超过时视为异常帧
+pub const SEEDS_DELTA_MAX_ENTRIES: usize = 4096;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct SeedsDeltaCommand {
+    /// 发送方认为接收方当前持有的列表摘要
+    pub base: [u8; 32],
+    /// 应用增量后的列表摘要
+    pub target: [u8; 32],
+    pub added: Vec<SeedRecord>,
+    /// 删除的 seed 地址（`ip:port`）
+    pub removed: Vec<String>,
+}
+
+impl Codec for SeedsDeltaCommand {}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct SeedsResyncCommand {
+    /// 接收方清空副本后的摘要（空列表）
+    pub have: [u8; 32],
+}
+
+impl Codec for SeedsResyncCommand {}
+
+/// 种子列表：seed 地址 → 节点地址
+pub type SeedView = BTreeMap<String, String>;
+
+/// 列表摘要，与条目顺序和 `first_seen` 无关
+pub fn digest(view: &SeedView) -> [u8; 32] {
+    let mut hasher = Sha256::default();
+    for (address, node_address) in view {
+        hasher.update(address.as_bytes());
+        hasher.update([0u8]);
+        hasher.update(node_address.as_bytes());
+        hasher.update([b'\n']);
+    }
+    hasher.finalize().into()
+}
+
+pub fn view_of(records: &[SeedRecord]) -> SeedView {
+    records
+        .iter()
+        .map(|r| (r.address.clone(), r.node_address.clone()))
+        .collect()
+}
+
+/// 从 `old` 到 `new` 的增量；没有变化时返回 None
+pub fn diff(old: &SeedView, new: &SeedView, records: &[SeedRecord]) -> Option<SeedsDeltaCommand> {
+    if old == new {
+        return None;
+    }
+    let added = records
+        .iter()
+        .filter(|r| old.get(&r.address) != Some(&r.node_address))
+        .filter(|r| new.get(&r.address) == Some(&r.node_address))
+        .cloned()
+        .collect();
+    let removed = old
+        .keys()
+        .filter(|address| !new.contains_key(*address))
+        .cloned()
+        .collect();
+    Some(SeedsDeltaCommand {
+        base: digest(old),
+        target: digest(new),
+        added,
+        removed,
+    })
+}
+
+/// 在副本上应用增量；基准或结果摘要不符时副本保持不变并返回错误
+pub fn apply(view: &mut SeedView, delta: &SeedsDeltaCommand) -> Result<(), ProtocolError> {
+    if delta.added.len() + delta.removed.len() > SEEDS_DELTA_MAX_ENTRIES {
+        return Err(ProtocolError::decode(
+            "SeedsDeltaCommand",
+            format!(
+                "too many entries: {}",
+                delta.added.len() + delta.removed.len()
+            ),
+        ));
+    }
+    if digest(view) != delta.base {
+        return Err(ProtocolError::decode(
+            "SeedsDeltaCommand",
+            "base digest mismatch",
+        ));
+    }
+    let mut next = view.clone();
+    for address in &delta.removed {
+        next.remove(address);
+    }
+    for record in &delta.added {
+        next.insert(record.address.clone(), record.node_address.clone());
+    }
+    if digest(&next) != delta.target {
+        return Err(ProtocolError::decode(
+            "SeedsDeltaCommand",
+            "target digest mismatch",
+        ));
+    }
+    *view = next;
+    Ok(())
+}
+
+/// 每个对端的同步副本
+#[derive(Debug, Default)]
+pub struct SeedDeltas {
+    /// 对端地址 → 已发给该对端的列表
+    sent: DashMap<String, SeedView>,
+    /// 发送方地址 → 从该发送方收到的列表
+    received: DashMap<String, SeedView>,
+}
+
+pub type SharedSeedDeltas = Arc<SeedDeltas>;
+
+impl SeedDeltas {
+    /// 计算发给 `peer` 的增量并记为已发送；没有变化时返回 None
+    pub fn delta_for(&self, peer: &str, records: &[SeedRecord]) -> Option<SeedsDeltaCommand> {
+        let new = view_of(records);
+        let mut sent = self.sent.entry(peer.to_string()).or_default();
+        let delta = diff(&sent, &new, records)?;
+        *sent = new;
+        Some(delta)
+    }
+
+    /// 在 `sender` 的副本上应用增量，返回新增的条目；失败时清空副本
+    pub fn receive(
+        &self,
+        sender: &str,
+        delta: &SeedsDeltaCommand,
+    ) -> Result<Vec<SeedRecord>, ProtocolError> {
+        let mut view = self.received.entry(sender.to_string()).or_default();
+        match apply(&mut view, delta) {
+            Ok(()) => Ok(delta.added.clone()),
+            Err(e) => {
+                view.clear();
+                Err(e)
+            }
+        }
+    }
+
+    /// 对端要求重新同步：下次从空列表开始发送
+    pub fn reset_sent(&self, peer: &str) {
+        self.sent.remove(peer);
+    }
+}
+
+/// 对端握手时声明了增量同步，返回其地址
+async fn delta_peer(ctx: &Arc<Mutex<Context>>) -> Option<String> {
+    let guard = ctx.lock().await;
+    let supported = guard
+        .get::<PeerCapabilities>()
+        .is_some_and(|caps| caps.supports(CAP_SEED_DELTA));
+    if supported {
+        guard.get::<String>()
+    } else {
+        None
+    }
+}
+
+/// 把目标分为支持增量同步的（附对端地址）与需要完整列表的
+pub async fn partition(targets: Vec<Target>) -> (Vec<(Target, String)>, Vec<Target>) {
+    let mut delta = Vec::new();
+    let mut full = Vec::new();
+    for target in targets {
+        match delta_peer(&target.ctx).await {
+            Some(peer) => delta.push((target, peer)),
+            None => full.push(target),
+        }
+    }
+    (delta, full)
+}
+
+/// 向支持增量同步的对端发送增量，返回实际发送的帧数
+pub async fn send_deltas(
+    deltas: &SeedDeltas,
+    targets: Vec<(Target, String)>,
+    records: &[SeedRecord],
+) -> usize {
+    let mut sent = 0;
+    for (target, peer) in targets {
+        // 同一对端的多个连接只需发送一次
+        let Some(cmd) = deltas.delta_for(&peer, records) else {
+            continue;
+        };
+        match P2PFrame::send(
+            target.ctx,
+            &Some(cmd),
+            Entity::Node,
+            Action::SeedsDelta,
+            false,
+        )
+        .await
+        {
+            Ok(()) => sent += 1,
+            Err(e) => {
+                // 对端收到的下一个增量会因基准不符触发重新同步
+                tracing::warn!("Failed to send seed delta to {}: {:?}", peer, e);
+            }
+        }
+    }
+    sent
+}
+
+pub async fn seeds_delta_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    let delta: SeedsDeltaCommand =
+        match error::decode_command("SeedsDeltaCommand", &frame, &cmd.data) {
+            Ok(c) => c,
+            Err(e) => {
+                error::report(&ctx, &frame.body.address, e).await;
+                return;
+            }
+        };
+    let gctx = { ctx.lock().await.global.clone() };
+    let Some(deltas) = gctx.get::<SharedSeedDeltas>().await else {
+        return;
+    };
+
+    match deltas.receive(&frame.body.address, &delta) {
+        Ok(added) => {
+            tracing::info!(
+                "📥 Seed delta from {}: +{} -{}",
+                frame.body.address,
+                added.len(),
+                delta.removed.len()
+            );
+            if !added.is_empty() {
+                merge_gossip_seeds(ctx, &added).await;
+            }
+        }
+        Err(e) => {
+            tracing::info!(
+                "🔄 Seed delta from {} rejected ({}), requesting resync",
+                frame.body.address,
+                e
+            );
+            let resync = SeedsResyncCommand {
+                have: digest(&SeedView::new()),
+            };
+            if let Err(e) =
+                P2PFrame::send(ctx, &Some(resync), Entity::Node, Action::SeedsResync, false).await
+            {
+                tracing::error!("Failed to send SeedsResync: {:?}", e);
+            }
+        }
+    }
+}
+
+pub async fn seeds_resync_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    if let Err(e) =
+        error::decode_command::<SeedsResyncCommand>("SeedsResyncCommand", &frame, &cmd.data)
+    {
+        error::report(&ctx, &frame.body.address, e).await;
+        return;
+    }
+    let (gctx, peer_ip) = {
+        let guard = ctx.lock().await;
+        (guard.global.clone(), guard.addr.ip())
+    };
+    let Some(deltas) = gctx.get::<SharedSeedDeltas>().await else {
+        return;
+    };
+    let peer = frame.body.address.clone();
+    deltas.reset_sent(&peer);
+
+    // 从空列表开始重新发送完整列表
+    let records = privacy::gossip_seeds(&gctx, Some(&peer_ip)).await.seeds;
+    if let Some(full) = deltas.delta_for(&peer, &records) {
+        if let Err(e) =
+            P2PFrame::send(ctx, &Some(full), Entity::Node, Action::SeedsDelta, false).await
+        {
+            tracing::error!("Failed to resend seeds to {}: {:?}", peer, e);
+        }
+    }
+}
@@ -5,19 +5,26 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, Instant};
-use tokio::io::AsyncWriteExt;
 use tokio::sync::Mutex;
 
 use aex::connection::context::Context;
 use aex::connection::global::GlobalContext;
-use aex::connection::scope::NetworkScope;
 use aex::tcp::types::Codec;
+use bytes::Bytes;
 use tokio::sync::Semaphore;
 
+use crate::ip_scope;
 use crate::node::Node;
+use crate::protocols::broadcast;
 use crate::protocols::command::{Action, Entity, P2PCommand};
 use crate::protocols::commands::node_registry::NodeRegistry;
+use crate::protocols::error::{self, ProtocolError};
 use crate::protocols::frame::P2PFrame;
+use crate::protocols::lanes::Lane;
+use crate::protocols::privacy;
+use crate::protocols::version::CURRENT_PROTOCOL_VERSION;
+use crate::proxy;
+use crate::retry::{self, Operation};
 
 pub const SEED_SYNC_MAX_RETRIES: u32 = 3;
 pub const SEED_HASH_HEX_LENGTH: usize = 64;
@@ -166,7 +173,7 @@ pub fn derive_seed_set_from_registry(reg: &NodeRegistry) -> SeedSet {
         .into_iter()
         .filter(|(addr, _)| addr.port() != 0)
         .map(|(addr, node_addr)| {
-            let is_intranet = matches!(addr.ip(), std::net::IpAddr::V4(v4) if v4.is_private());
+            let is_intranet = ip_scope::is_inner_ip(&addr.ip());
             SeedInfo::new(addr.ip().to_string(), addr.port(), node_addr, is_intranet)
         })
         .collect();
@@ -211,15 +218,16 @@ pub struct SeedSyncCommit {
 
 impl Codec for SeedSyncCommit {}
 
-pub async fn seed_sync_request_handler(
-    ctx: Arc<Mutex<Context>>,
-    _frame: P2PFrame,
-    cmd: P2PCommand,
-) {
-    let request: SeedSyncRequest = match Codec::decode(&cmd.data) {
+pub async fn seed_sync_request_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    let request: SeedSyncRequest = match frame.decode_payload(&cmd.data) {
         Ok(req) => req,
         Err(e) => {
-            tracing::error!("❌ decode SeedSyncRequest failed: {e}");
+            error::report(
+                &ctx,
+                &frame.body.address,
+                ProtocolError::decode("SeedSyncRequest", e),
+            )
+            .await;
             return;
         }
     };
@@ -235,7 +243,7 @@ pub async fn seed_sync_request_handler(
     if let Some(node) = guard.global.get::<Arc<Node>>().await {
         for seed in &request.seed_set.seeds {
             if let Some(seed_addr) = seed.socket_addr() {
-                let scope = NetworkScope::from_ip(&seed_addr.ip());
+                let scope = ip_scope::classify(&seed_addr.ip());
                 node.registry
                     .register(seed.node_id.clone(), seed_addr, scope);
             }
@@ -264,7 +272,9 @@ pub async fn seed_sync_request_handler(
         };
 
         let seed_set = derive_seed_set_from_registry(&node.registry);
-        let filtered_set = seed_set.filter_loopback();
+        let filtered_set = privacy::policy(&guard.global)
+            .await
+            .filter_seed_set(&seed_set.filter_loopback(), Some(&guard.addr.ip()));
         tracing::info!(
             "  → response seeds (after filter_loopback): {}",
             filtered_set.len()
@@ -288,13 +298,18 @@ pub async fn seed_sync_request_handler(
 
 pub async fn seed_sync_response_handler(
     ctx: Arc<Mutex<Context>>,
-    _frame: P2PFrame,
+    frame: P2PFrame,
     cmd: P2PCommand,
 ) {
-    let response: SeedSyncResponse = match Codec::decode(&cmd.data) {
+    let response: SeedSyncResponse = match frame.decode_payload(&cmd.data) {
         Ok(resp) => resp,
         Err(e) => {
-            tracing::error!("❌ decode SeedSyncResponse failed: {e}");
+            error::report(
+                &ctx,
+                &frame.body.address,
+                ProtocolError::decode("SeedSyncResponse", e),
+            )
+            .await;
             return;
         }
     };
@@ -309,7 +324,7 @@ pub async fn seed_sync_response_handler(
     if let Some(node) = guard.global.get::<Arc<Node>>().await {
         for seed in &response.seed_set.seeds {
             if let Some(seed_addr) = seed.socket_addr() {
-                let scope = NetworkScope::from_ip(&seed_addr.ip());
+                let scope = ip_scope::classify(&seed_addr.ip());
                 node.registry
                     .register(seed.node_id.clone(), seed_addr, scope);
             }
@@ -328,11 +343,16 @@ pub async fn seed_sync_response_handler(
     drop(guard);
 }
 
-pub async fn seed_sync_commit_handler(ctx: Arc<Mutex<Context>>, _frame: P2PFrame, cmd: P2PCommand) {
-    let commit: SeedSyncCommit = match Codec::decode(&cmd.data) {
+pub async fn seed_sync_commit_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    let commit: SeedSyncCommit = match frame.decode_payload(&cmd.data) {
         Ok(c) => c,
         Err(e) => {
-            tracing::error!("❌ decode SeedSyncCommit failed: {e}");
+            error::report(
+                &ctx,
+                &frame.body.address,
+                ProtocolError::decode("SeedSyncCommit", e),
+            )
+            .await;
             return;
         }
     };
@@ -347,7 +367,7 @@ pub async fn seed_sync_commit_handler(ctx: Arc<Mutex<Context>>, _frame: P2PFrame
     if let Some(node) = guard.global.get::<Arc<Node>>().await {
         for seed in &commit.seed_set.seeds {
             if let Some(seed_addr) = seed.socket_addr() {
-                let scope = NetworkScope::from_ip(&seed_addr.ip());
+                let scope = ip_scope::classify(&seed_addr.ip());
                 node.registry
                     .register(seed.node_id.clone(), seed_addr, scope);
             }
@@ -384,9 +404,10 @@ pub async fn broadcast_seed_set_to_all_peers(ctx: Arc<Mutex<Context>>, seed_set:
         None => return,
     };
 
+    // 同一帧发给所有对端，只包含公开的端点
     let request = SeedSyncRequest {
         from_node_id: gctx.addr.to_string(),
-        seed_set: seed_set.clone(),
+        seed_set: privacy::policy(&gctx).await.filter_seed_set(seed_set, None),
         retry_count: seed_set.sync_round,
     };
 
@@ -398,7 +419,7 @@ pub async fn broadcast_seed_set_to_all_peers(ctx: Arc<Mutex<Context>>, seed_set:
         }
     };
     let p2p_cmd = P2PCommand::new(Entity::Node, Action::SeedSyncRequest, cmd_bytes);
-    let frame = match P2PFrame::build(&address, p2p_cmd, 1).await {
+    let frame = match P2PFrame::build(&address, p2p_cmd, CURRENT_PROTOCOL_VERSION).await {
         Ok(f) => f,
         Err(e) => {
             tracing::error!("Failed to build seed sync frame: {:?}", e);
@@ -406,30 +427,27 @@ pub async fn broadcast_seed_set_to_all_peers(ctx: Arc<Mutex<Context>>, seed_set:
         }
     };
     let frame_bytes = match Codec::encode(&frame) {
-        Ok(b) => b,
+        Ok(b) => Bytes::from(b),
         Err(e) => {
             tracing::error!("Failed to encode seed sync frame: {:?}", e);
             return;
         }
     };
+    let gctx_for_send = gctx.clone();
     let seed_count = seed_set.len();
 
     let manager = gctx.manager.clone();
 
     manager
         .forward(|entries| async move {
-            for entry in entries {
-                if let Some(peer_ctx) = &entry.context {
-                    let mut guard = peer_ctx.lock().await;
-                    if let Some(writer) = &mut guard.writer {
-                        if let Err(e) = writer.write_all(&frame_bytes).await {
-                            tracing::error!("  ❌ Failed to broadcast seed set: {:?}", e);
-                        } else {
-                            let _ = writer.flush().await;
-                        }
-                    }
-                }
-            }
+            broadcast::write_all(
+                &gctx_for_send,
+                broadcast::all_peers(entries),
+                frame_bytes,
+                Lane::Messaging,
+            )
+            .await
+            .log("broadcast seed set");
         })
         .await;
 
@@ -579,17 +597,20 @@ pub async fn run_seed_sync_cycle(
 }
 
 async fn try_sync_with_peer_global(gctx: Arc<GlobalContext>, addr: SocketAddr) -> bool {
-    // Try up to 3 times per peer per round
-    for attempt in 1..=3 {
-        let result = sync_once(gctx.clone(), addr, attempt).await;
-        if result {
-            return true;
-        }
-        if attempt < 3 {
-            tokio::time::sleep(Duration::from_millis(500 * attempt as u64)).await;
+    // 每轮对每个对端按建连的重试策略退避重试
+    let policy = retry::policy(&gctx, Operation::Connect).await.retry;
+    retry::retry(&policy, &format!("seed sync with {}", addr), |attempt| {
+        let gctx = gctx.clone();
+        async move {
+            if sync_once(gctx, addr, attempt).await {
+                Ok(())
+            } else {
+                anyhow::bail!("seed sync with {} failed", addr)
+            }
         }
-    }
-    false
+    })
+    .await
+    .is_ok()
 }
 
 async fn sync_once(gctx: Arc<GlobalContext>, addr: SocketAddr, attempt: u32) -> bool {
@@ -638,28 +659,20 @@ async fn sync_once(gctx: Arc<GlobalContext>, addr: SocketAddr, attempt: u32) ->
         attempt
     );
 
-    match gctx
-        .manager
-        .clone()
-        .connect::<P2PFrame, P2PCommand, _, _>(
-            addr,
-            gctx.clone(),
-            move |ctx| {
-                let result = result_clone.clone();
-                let gctx = gctx.clone();
-                let psk = psk.clone();
-                Box::pin(async move {
-                    tracing::info!(
-                        "✅ Connected to peer: {}, sending seed sync request (attempt {})",
-                        addr,
-                        attempt
-                    );
-                    send_seed_sync_request(ctx, addr, psk, gctx, result, attempt).await;
-                })
-            },
-            Some(5),
-        )
-        .await
+    match proxy::connect(gctx.clone(), addr, move |ctx| {
+        let result = result_clone.clone();
+        let gctx = gctx.clone();
+        let psk = psk.clone();
+        Box::pin(async move {
+            tracing::info!(
+                "✅ Connected to peer: {}, sending seed sync request (attempt {})",
+                addr,
+                attempt
+            );
+            send_seed_sync_request(ctx, addr, psk, gctx, result, attempt).await;
+        })
+    })
+    .await
     {
         Ok(_) => {
             tokio::time::sleep(Duration::from_millis(500)).await;
@@ -728,7 +741,9 @@ async fn send_seed_sync_via_existing_ctx(
 
     let request = SeedSyncRequest {
         from_node_id: gctx.addr.to_string(),
-        seed_set: local_set.clone(),
+        seed_set: privacy::policy(&gctx)
+            .await
+            .filter_seed_set(&local_set, Some(&addr.ip())),
         retry_count: 0,
     };
 
@@ -802,7 +817,9 @@ async fn send_seed_sync_request(
 
     let request = SeedSyncRequest {
         from_node_id: gctx.addr.to_string(),
-        seed_set: local_set.clone(),
+        seed_set: privacy::policy(&gctx)
+            .await
+            .filter_seed_set(&local_set, Some(&addr.ip())),
         retry_count: 0,
     };
 
@@ -0,0 +1,841 @@
+//! 流式传输：大负载边收边处理
+//!
+//! 普通命令的负载必须完整收到后才能交给 handler。流式传输把一个大负载拆成 `StreamData`，
+//! 接收方按 `kind` 找到注册的 handler，handler 拿到实现 `AsyncRead` 的 `StreamReader`
+//! 逐块读取，无需把整个负载放进内存。文件传输（`sendfile`）使用该接口。
+//!
+//! 流量控制：发送方开始时拥有 `STREAM_WINDOW` 字节的额度，每发送一块扣除相应额度，额度用完
+//! 后等待；接收方的 handler 每读走半个窗口就回 `StreamWindow` 归还额度。`StreamClose` 由发送方
+//! 发出表示结束，由任一方带 `error` 发出表示中止（接收方没有对应 handler、handler 提前放弃
+//! 读取、数据不连续等）。流中的帧带节点签名但不做端到端加密。
+//!
+//! 超出单帧大小的长文本（或持续产生的日志）以 `text` 类型的流发送（[`send_text_stream`]）：
+//! 内容逐块到达，`StreamClose` 即结束标记。接收方把它作为 [`NodeEvent::TextStream`] 发布到
+//! 事件总线，订阅方取走其中的 [`TextReader`] 按块读出文本；无人取走时由节点读出并写入日志。
+
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context as TaskContext, Poll},
+    time::Duration,
+};
+
+use aex::{
+    connection::{context::Context, global::GlobalContext},
+    tcp::types::Codec,
+};
+use bincode::{Decode, Encode};
+use dashmap::DashMap;
+use futures::{FutureExt, future::BoxFuture};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt, ReadBuf},
+    sync::{Mutex, Semaphore, mpsc},
+};
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::events::{EventBus, NodeEvent};
+use crate::node::Node as P2pNode;
+use crate::protocols::capabilities::{self, CAP_RELAY, CAP_STREAM};
+use crate::protocols::command::{Action, Entity, P2PCommand};
+use crate::protocols::error;
+use crate::protocols::frame::P2PFrame;
+use crate::protocols::routing;
+
+/// 每个 `StreamData` 携带的最大字节数
+pub const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+/// 流量控制窗口：未确认的字节数上限
+pub const STREAM_WINDOW: usize = 1024 * 1024;
+/// 等待额度或数据的最长时间，超时后中止
+pub const STREAM_IDLE_TIMEOUT_SECS: u64 = 60;
+/// 接收方缓冲的块数，超过说明发送方没有遵守窗口
+const STREAM_BUFFERED_CHUNKS: usize = STREAM_WINDOW / STREAM_CHUNK_SIZE * 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct StreamOpenCommand {
+    pub sender: String,
+    pub receiver: String,
+    pub stream_id: u64,
+    /// 接收方据此选择 handler，例如 `file`
+    pub kind: String,
+    /// 总长度，未知时为空
+    pub total_len: Option<u64>,
+    pub metadata: Vec<(String, String)>,
+}
+
+impl Codec for StreamOpenCommand {}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct StreamDataCommand {
+    pub sender: String,
+    pub receiver: String,
+    pub stream_id: u64,
+    /// 该块在流中的偏移
+    pub offset: u64,
+    pub data: Vec<u8>,
+}
+
+impl Codec for StreamDataCommand {}
+
+/// 接收方归还的额度
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct StreamWindowCommand {
+    pub sender: String,
+    pub receiver: String,
+    pub stream_id: u64,
+    pub credit: u64,
+}
+
+impl Codec for StreamWindowCommand {}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct StreamCloseCommand {
+    pub sender: String,
+    pub receiver: String,
+    pub stream_id: u64,
+    /// 为空表示正常结束
+    pub error: Option<String>,
+}
+
+impl Codec for StreamCloseCommand {}
+
+/// 交给 handler 的流
+pub struct IncomingStream {
+    pub from: String,
+    pub stream_id: u64,
+    pub kind: String,
+    pub total_len: Option<u64>,
+    pub metadata: Vec<(String, String)>,
+    pub reader: StreamReader,
+}
+
+impl IncomingStream {
+    pub fn meta(&self, key: &str) -> Option<&str> {
+        self.metadata
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+pub type StreamHandler = Arc<dyn Fn(IncomingStream) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// 已注册的流 handler：kind → handler，保存在 GlobalContext 中
+pub type StreamHandlers = Arc<DashMap<String, StreamHandler>>;
+
+/// 正在接收的流
+pub struct IncomingState {
+    tx: mpsc::Sender<io::Result<Vec<u8>>>,
+    received: u64,
+    total_len: Option<u64>,
+}
+
+/// 正在接收的流：(发送方, stream_id) → 状态
+pub type IncomingStreams = Arc<DashMap<(String, u64), IncomingState>>;
+
+/// 正在发送的流
+pub struct OutgoingState {
+    receiver: String,
+    credits: Arc<Semaphore>,
+    error: Arc<std::sync::Mutex<Option<String>>>,
+}
+
+/// 正在发送的流：stream_id → 状态
+pub type OutgoingStreams = Arc<DashMap<u64, OutgoingState>>;
+
+/// handler 读取的一端；读走的字节数回报给流量控制任务
+pub struct StreamReader {
+    rx: mpsc::Receiver<io::Result<Vec<u8>>>,
+    buf: Vec<u8>,
+    pos: usize,
+    consumed: mpsc::UnboundedSender<usize>,
+}
+
+impl StreamReader {
+    /// 创建一对读端与写端；`consumed` 收到每次读走的字节数
+    pub fn channel(
+        capacity: usize,
+    ) -> (
+        mpsc::Sender<io::Result<Vec<u8>>>,
+        StreamReader,
+        mpsc::UnboundedReceiver<usize>,
+    ) {
+        let (tx, rx) = mpsc::channel(capacity);
+        let (consumed, consumed_rx) = mpsc::unbounded_channel();
+        let reader = StreamReader {
+            rx,
+            buf: Vec::new(),
+            pos: 0,
+            consumed,
+        };
+        (tx, reader, consumed_rx)
+    }
+}
+
+impl AsyncRead for StreamReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        out: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        while self.pos >= self.buf.len() {
+            match self.rx.poll_recv(cx) {
+                Poll::Ready(Some(Ok(chunk))) => {
+                    self.buf = chunk;
+                    self.pos = 0;
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(e)),
+                // 写端关闭：流结束
+                Poll::Ready(None) => return Poll::Ready(Ok(())),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        let n = out.remaining().min(self.buf.len() - self.pos);
+        let start = self.pos;
+        out.put_slice(&self.buf[start..start + n]);
+        self.pos += n;
+        let _ = self.consumed.send(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// 注册某一类流的 handler（替换之前注册的 handler）
+pub async fn register(gctx: &Arc<GlobalContext>, kind: &str, handler: StreamHandler) {
+    let handlers = match gctx.get::<StreamHandlers>().await {
+        Some(h) => h,
+        None => {
+            let handlers = StreamHandlers::default();
+            gctx.set(handlers.clone()).await;
+            handlers
+        }
+    };
+    handlers.insert(kind.to_string(), handler);
+}
+
+/// 检查一块数据能否追加到流中：重复的块返回 `Ok(false)`，不连续或超长返回错误
+pub fn check_chunk(
+    received: u64,
+    total_len: Option<u64>,
+    offset: u64,
+    len: usize,
+) -> Result<bool, String> {
+    if offset < received {
+        return Ok(false);
+    }
+    if offset > received {
+        return Err(format!("gap at offset {} (expected {})", offset, received));
+    }
+    if total_len.is_some_and(|total| received + len as u64 > total) {
+        return Err("stream longer than announced".to_string());
+    }
+    Ok(true)
+}
+
+async fn send_command<C: Codec + Serialize + Send + Sync>(
+    ctx: Arc<Mutex<Context>>,
+    cmd: C,
+    action: Action,
+) -> anyhow::Result<()> {
+    P2PFrame::send(ctx, &Some(cmd), Entity::Stream, action, false).await
+}
+
+/// 找到通往 `receiver` 的连接：直连目标须支持流式传输，经由对端中继时对端须支持中继
+async fn connection_to(
+    gctx: &Arc<GlobalContext>,
+    receiver: &str,
+) -> anyhow::Result<Arc<Mutex<Context>>> {
+    let found: Arc<Mutex<Option<Arc<Mutex<Context>>>>> = Arc::new(Mutex::new(None));
+    let found_in_closure = found.clone();
+    gctx.manager
+        .notify(receiver.as_bytes(), |entries| async move {
+            *found_in_closure.lock().await = entries.into_iter().find_map(|e| e.context.clone());
+        })
+        .await;
+    let Some(ctx) = found.lock().await.take() else {
+        anyhow::bail!("Peer {} is not connected", receiver);
+    };
+    let peer: Option<String> = ctx.lock().await.get();
+    let required = match peer {
+        Some(peer) if peer != receiver => CAP_RELAY,
+        _ => CAP_STREAM,
+    };
+    if !capabilities::peer_supports(&ctx, required).await {
+        anyhow::bail!(
+            "Peer does not support {}",
+            capabilities::feature_names(required).join(",")
+        );
+    }
+    Ok(ctx)
+}
+
+/// 把 `reader` 的内容作为一个流发给 `receiver`（地址或别名），返回发送的字节数
+pub async fn send_stream<R: AsyncRead + Unpin>(
+    gctx: Arc<GlobalContext>,
+    receiver: &str,
+    kind: &str,
+    total_len: Option<u64>,
+    metadata: Vec<(String, String)>,
+    mut reader: R,
+) -> anyhow::Result<u64> {
+    let receiver = match gctx.get::<Arc<P2pNode>>().await {
+        Some(node) => node.registry.resolve_alias(receiver),
+        None => receiver.to_string(),
+    };
+    let Some(identity) = gctx.get::<FreeWebMovementAddress>().await else {
+        anyhow::bail!("Address not set");
+    };
+    let Some(outgoing) = gctx.get::<OutgoingStreams>().await else {
+        anyhow::bail!("OutgoingStreams not set in GlobalContext");
+    };
+    let ctx = connection_to(&gctx, &receiver).await?;
+    let sender = identity.to_string();
+    let stream_id: u64 = rand::thread_rng().r#gen();
+    let credits = Arc::new(Semaphore::new(STREAM_WINDOW));
+    let error = Arc::new(std::sync::Mutex::new(None));
+    outgoing.insert(
+        stream_id,
+        OutgoingState {
+            receiver: receiver.clone(),
+            credits: credits.clone(),
+            error: error.clone(),
+        },
+    );
+
+    let open = StreamOpenCommand {
+        sender: sender.clone(),
+        receiver: receiver.clone(),
+        stream_id,
+        kind: kind.to_string(),
+        total_len,
+        metadata,
+    };
+    let result: anyhow::Result<u64> = async {
+        send_command(ctx.clone(), open, Action::StreamOpen).await?;
+        let mut offset = 0u64;
+        let mut buf = vec![0u8; STREAM_CHUNK_SIZE];
+        loop {
+            let n = reader.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            let idle = Duration::from_secs(STREAM_IDLE_TIMEOUT_SECS);
+            match tokio::time::timeout(idle, credits.acquire_many(n as u32)).await {
+                Ok(Ok(permit)) => permit.forget(),
+                Ok(Err(_)) => {
+                    let reason = error.lock().unwrap().clone().unwrap_or_default();
+                    anyhow::bail!("Stream aborted by {}: {}", receiver, reason);
+                }
+                Err(_) => anyhow::bail!("Stream to {} stalled: no window update", receiver),
+            }
+            let data = StreamDataCommand {
+                sender: sender.clone(),
+                receiver: receiver.clone(),
+                stream_id,
+                offset,
+                data: buf[..n].to_vec(),
+            };
+            send_command(ctx.clone(), data, Action::StreamData).await?;
+            offset += n as u64;
+        }
+        Ok(offset)
+    }
+    .await;
+    outgoing.remove(&stream_id);
+
+    // 正常结束与发送方中止都通知接收方；被接收方中止时不必再通知
+    if credits.is_closed() {
+        return result;
+    }
+    let close = StreamCloseCommand {
+        sender,
+        receiver,
+        stream_id,
+        error: result.as_ref().err().map(|e| e.to_string()),
+    };
+    send_command(ctx, close, Action::StreamClose).await?;
+    result
+}
+
+/// 接收方中止一个流并通知发送方
+async fn abort_incoming(
+    ctx: Arc<Mutex<Context>>,
+    streams: &IncomingStreams,
+    local: &str,
+    key: &(String, u64),
+    reason: String,
+) {
+    if let Some((_, state)) = streams.remove(key) {
+        let _ = state
+            .tx
+            .try_send(Err(io::Error::other(format!("stream aborted: {}", reason))));
+    }
+    tracing::warn!("Aborting stream {} from {}: {}", key.1, key.0, reason);
+    let close = StreamCloseCommand {
+        sender: local.to_string(),
+        receiver: key.0.clone(),
+        stream_id: key.1,
+        error: Some(reason),
+    };
+    if let Err(e) = send_command(ctx, close, Action::StreamClose).await {
+        tracing::error!("Failed to send stream abort to {}: {:?}", key.0, e);
+    }
+}
+
+/// 把 handler 读走的字节按半个窗口累计后归还给发送方；handler 提前放弃读取时中止流
+async fn grant_credits(
+    ctx: Arc<Mutex<Context>>,
+    streams: IncomingStreams,
+    local: String,
+    key: (String, u64),
+    mut consumed: mpsc::UnboundedReceiver<usize>,
+) {
+    let mut pending = 0usize;
+    while let Some(n) = consumed.recv().await {
+        pending += n;
+        if pending < STREAM_WINDOW / 2 {
+            continue;
+        }
+        let window = StreamWindowCommand {
+            sender: local.clone(),
+            receiver: key.0.clone(),
+            stream_id: key.1,
+            credit: pending as u64,
+        };
+        pending = 0;
+        if let Err(e) = send_command(ctx.clone(), window, Action::StreamWindow).await {
+            tracing::error!("Failed to send stream window to {}: {:?}", key.0, e);
+        }
+    }
+    // 读端已释放但流仍在接收：handler 不再需要剩余数据
+    if streams.contains_key(&key) {
+        abort_incoming(ctx, &streams, &local, &key, "receiver closed".into()).await;
+    }
+}
+
+async fn local_address(gctx: &Arc<GlobalContext>) -> Option<String> {
+    gctx.get::<FreeWebMovementAddress>()
+        .await
+        .map(|a| a.to_string())
+}
+
+pub async fn stream_open_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    if routing::relay_if_not_for_us(ctx.clone(), &frame).await {
+        return;
+    }
+    let open: StreamOpenCommand =
+        match error::decode_command("StreamOpenCommand", &frame, &cmd.data) {
+            Ok(o) => o,
+            Err(e) => {
+                error::report(&ctx, &frame.body.address, e).await;
+                return;
+            }
+        };
+    if open.sender != frame.body.address {
+        return;
+    }
+    let gctx = ctx.lock().await.global.clone();
+    let Some(local) = local_address(&gctx).await else {
+        return;
+    };
+    let Some(streams) = gctx.get::<IncomingStreams>().await else {
+        return;
+    };
+    let key = (open.sender.clone(), open.stream_id);
+    if streams.contains_key(&key) {
+        return;
+    }
+    let handler = match gctx.get::<StreamHandlers>().await {
+        Some(handlers) => handlers.get(&open.kind).map(|h| h.value().clone()),
+        None => None,
+    };
+    let Some(handler) = handler else {
+        let reason = format!("no handler for stream kind {:?}", open.kind);
+        abort_incoming(ctx, &streams, &local, &key, reason).await;
+        return;
+    };
+    tracing::info!(
+        "🌊 Stream {} ({}) from {}, {:?} bytes",
+        open.stream_id,
+        open.kind,
+        open.sender,
+        open.total_len
+    );
+
+    let (tx, reader, consumed) = StreamReader::channel(STREAM_BUFFERED_CHUNKS);
+    streams.insert(
+        key.clone(),
+        IncomingState {
+            tx,
+            received: 0,
+            total_len: open.total_len,
+        },
+    );
+    tokio::spawn(grant_credits(ctx, streams, local, key, consumed));
+    tokio::spawn(handler(IncomingStream {
+        from: open.sender,
+        stream_id: open.stream_id,
+        kind: open.kind,
+        total_len: open.total_len,
+        metadata: open.metadata,
+        reader,
+    }));
+}
+
+pub async fn stream_data_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    if routing::relay_if_not_for_us(ctx.clone(), &frame).await {
+        return;
+    }
+    let data: StreamDataCommand =
+        match error::decode_command("StreamDataCommand", &frame, &cmd.data) {
+            Ok(d) => d,
+            Err(e) => {
+                error::report(&ctx, &frame.body.address, e).await;
+                return;
+            }
+        };
+    if data.sender != frame.body.address {
+        return;
+    }
+    let gctx = ctx.lock().await.global.clone();
+    let (Some(local), Some(streams)) = (
+        local_address(&gctx).await,
+        gctx.get::<IncomingStreams>().await,
+    ) else {
+        return;
+    };
+    let key = (data.sender.clone(), data.stream_id);
+    let outcome = {
+        let Some(mut state) = streams.get_mut(&key) else {
+            return;
+        };
+        match check_chunk(
+            state.received,
+            state.total_len,
+            data.offset,
+            data.data.len(),
+        ) {
+            Ok(false) => return,
+            // 缓冲区将满说明发送方超出了窗口；留一个位置给中止时的错误
+            Ok(true) if state.tx.capacity() <= 1 => Err("window exceeded".to_string()),
+            Ok(true) => {
+                state.received += data.data.len() as u64;
+                state
+                    .tx
+                    .try_send(Ok(data.data))
+                    .map_err(|_| "receiver closed".to_string())
+            }
+            Err(reason) => Err(reason),
+        }
+    };
+    if let Err(reason) = outcome {
+        abort_incoming(ctx, &streams, &local, &key, reason).await;
+    }
+}
+
+pub async fn stream_window_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    if routing::relay_if_not_for_us(ctx.clone(), &frame).await {
+        return;
+    }
+    let window: StreamWindowCommand =
+        match error::decode_command("StreamWindowCommand", &frame, &cmd.data) {
+            Ok(w) => w,
+            Err(e) => {
+                error::report(&ctx, &frame.body.address, e).await;
+                return;
+            }
+        };
+    let gctx = ctx.lock().await.global.clone();
+    let Some(outgoing) = gctx.get::<OutgoingStreams>().await else {
+        return;
+    };
+    if let Some(state) = outgoing.get(&window.stream_id) {
+        if state.receiver == frame.body.address {
+            let credit = (window.credit as usize).min(STREAM_WINDOW);
+            state.credits.add_permits(credit);
+        }
+    }
+}
+
+pub async fn stream_close_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    if routing::relay_if_not_for_us(ctx.clone(), &frame).await {
+        return;
+    }
+    let close: StreamCloseCommand =
+        match error::decode_command("StreamCloseCommand", &frame, &cmd.data) {
+            Ok(c) => c,
+            Err(e) => {
+                error::report(&ctx, &frame.body.address, e).await;
+                return;
+            }
+        };
+    let peer = frame.body.address.clone();
+    let gctx = ctx.lock().await.global.clone();
+
+    // 发送方结束或中止一个正在接收的流
+    if let Some(streams) = gctx.get::<IncomingStreams>().await {
+        if let Some((_, state)) = streams.remove(&(peer.clone(), close.stream_id)) {
+            let short = state.total_len.is_some_and(|total| state.received < total);
+            let failure = match close.error {
+                Some(reason) => Some(format!("stream aborted by sender: {}", reason)),
+                None if short => Some("stream ended early".to_string()),
+                None => None,
+            };
+            if let Some(failure) = failure {
+                let _ = state.tx.try_send(Err(io::Error::other(failure)));
+            }
+            return;
+        }
+    }
+    // 接收方中止一个正在发送的流
+    if let Some(outgoing) = gctx.get::<OutgoingStreams>().await {
+        if let Some(state) = outgoing.get(&close.stream_id) {
+            if state.receiver == peer {
+                *state.error.lock().unwrap() = close.error;
+                state.credits.close();
+            }
+        }
+    }
+}
+
+/// 文件传输使用的流类型，元数据 `filename` 为文件名
+pub const STREAM_KIND_FILE: &str = "file";
+
+/// 只保留文件名部分，避免写到目录之外
+pub fn safe_filename(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next()?.trim();
+    if name.is_empty() || name == "." || name == ".." || name.contains('\0') {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+/// 把收到的文件边收边写入 `dir`，同名文件存在时加上序号
+pub fn save_files_to(dir: PathBuf) -> StreamHandler {
+    Arc::new(move |mut stream: IncomingStream| {
+        let dir = dir.clone();
+        async move {
+            let name = stream
+                .meta("filename")
+                .and_then(safe_filename)
+                .unwrap_or_else(|| format!("stream-{:x}", stream.stream_id));
+            let result: io::Result<(PathBuf, u64)> = async {
+                tokio::fs::create_dir_all(&dir).await?;
+                let mut path = dir.join(&name);
+                let mut n = 1;
+                while tokio::fs::try_exists(&path).await? {
+                    path = dir.join(format!("{}.{}", name, n));
+                    n += 1;
+                }
+                let mut file = tokio::fs::File::create(&path).await?;
+                let written = tokio::io::copy(&mut stream.reader, &mut file).await?;
+                file.flush().await?;
+                Ok((path, written))
+            }
+            .await;
+            match result {
+                Ok((path, written)) => tracing::info!(
+                    "📥 Received file {} from {} ({} bytes)",
+                    path.display(),
+                    stream.from,
+                    written
+                ),
+                Err(e) => tracing::warn!("Failed to receive file from {}: {}", stream.from, e),
+            }
+        }
+        .boxed()
+    })
+}
+
+/// 以流的方式把文件发给 `receiver`
+pub async fn send_file(
+    gctx: Arc<GlobalContext>,
+    receiver: &str,
+    path: &Path,
+) -> anyhow::Result<u64> {
+    let file = tokio::fs::File::open(path).await?;
+    let len = file.metadata().await?.len();
+    let mut metadata = vec![];
+    if let Some(name) = path.file_name() {
+        metadata.push(("filename".to_string(), name.to_string_lossy().to_string()));
+    }
+    send_stream(gctx, receiver, STREAM_KIND_FILE, Some(len), metadata, file).await
+}
+
+/// 长文本（消息、日志）使用的流类型，内容为 UTF-8，元数据由发送方自定
+pub const STREAM_KIND_TEXT: &str = "text";
+/// 文本流发布到事件总线后等待订阅方取走读端的时间，超时后由节点自己读取
+pub const TEXT_STREAM_CLAIM_SECS: u64 = 5;
+
+/// 从缓冲区头部取出完整的 UTF-8 文本，被截断的多字节字符留在缓冲区中等待下一块
+pub fn take_utf8(pending: &mut Vec<u8>) -> io::Result<Option<String>> {
+    let valid = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        Err(e) if e.error_len().is_some() => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid UTF-8 in text stream",
+            ));
+        }
+        Err(e) => e.valid_up_to(),
+    };
+    if valid == 0 {
+        return Ok(None);
+    }
+    let rest = pending.split_off(valid);
+    let text = std::mem::replace(pending, rest);
+    String::from_utf8(text)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// 按块读出文本流的内容
+pub struct TextReader {
+    reader: StreamReader,
+    pending: Vec<u8>,
+}
+
+impl TextReader {
+    pub fn new(reader: StreamReader) -> Self {
+        Self {
+            reader,
+            pending: Vec::new(),
+        }
+    }
+
+    /// 读取下一段文本；流正常结束时返回 None，被中止或内容不是 UTF-8 时返回错误
+    pub async fn next_chunk(&mut self) -> io::Result<Option<String>> {
+        let mut buf = vec![0u8; STREAM_CHUNK_SIZE];
+        loop {
+            let n = self.reader.read(&mut buf).await?;
+            if n == 0 {
+                if self.pending.is_empty() {
+                    return Ok(None);
+                }
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "text stream ended inside a UTF-8 sequence",
+                ));
+            }
+            self.pending.extend_from_slice(&buf[..n]);
+            if let Some(text) = take_utf8(&mut self.pending)? {
+                return Ok(Some(text));
+            }
+        }
+    }
+
+    /// 读完整个流
+    pub async fn read_to_string(mut self) -> io::Result<String> {
+        let mut text = String::new();
+        while let Some(chunk) = self.next_chunk().await? {
+            text.push_str(&chunk);
+        }
+        Ok(text)
+    }
+}
+
+/// 通过事件总线交给应用的文本流；事件会复制给每个订阅方，读端只能被其中一个取走
+#[derive(Clone)]
+pub struct IncomingTextStream {
+    pub from: String,
+    pub stream_id: u64,
+    pub total_len: Option<u64>,
+    pub metadata: Vec<(String, String)>,
+    reader: Arc<std::sync::Mutex<Option<TextReader>>>,
+}
+
+impl IncomingTextStream {
+    pub fn new(stream: IncomingStream) -> Self {
+        Self {
+            from: stream.from,
+            stream_id: stream.stream_id,
+            total_len: stream.total_len,
+            metadata: stream.metadata,
+            reader: Arc::new(std::sync::Mutex::new(Some(TextReader::new(stream.reader)))),
+        }
+    }
+
+    pub fn meta(&self, key: &str) -> Option<&str> {
+        self.metadata
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// 取走读端；已被取走时返回 None
+    pub fn take_reader(&self) -> Option<TextReader> {
+        self.reader.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+}
+
+impl fmt::Debug for IncomingTextStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IncomingTextStream")
+            .field("from", &self.from)
+            .field("stream_id", &self.stream_id)
+            .field("total_len", &self.total_len)
+            .field("metadata", &self.metadata)
+            .finish_non_exhaustive()
+    }
+}
+
+/// 把收到的文本流发布到事件总线；`TEXT_STREAM_CLAIM_SECS` 内没有订阅方取走读端时，
+/// 逐块读出并写入日志，避免发送方因额度耗尽而停住
+pub fn publish_text_streams(bus: EventBus) -> StreamHandler {
+    Arc::new(move |stream: IncomingStream| {
+        let bus = bus.clone();
+        async move {
+            let stream = IncomingTextStream::new(stream);
+            let _ = bus.send(NodeEvent::TextStream(stream.clone()));
+            tokio::time::sleep(Duration::from_secs(TEXT_STREAM_CLAIM_SECS)).await;
+            let Some(mut reader) = stream.take_reader() else {
+                return;
+            };
+            loop {
+                match reader.next_chunk().await {
+                    Ok(Some(text)) => {
+                        tracing::info!("📜 {} [{:x}]: {}", stream.from, stream.stream_id, text)
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        tracing::warn!("Text stream from {} failed: {}", stream.from, e);
+                        break;
+                    }
+                }
+            }
+        }
+        .boxed()
+    })
+}
+
+/// 以流的方式把长文本（或持续产生的日志）发给 `receiver`，`reader` 的内容须为 UTF-8，
+/// 多字节字符可以跨块；返回发送的字节数
+pub async fn send_text_stream<R: AsyncRead + Unpin>(
+    gctx: Arc<GlobalContext>,
+    receiver: &str,
+    total_len: Option<u64>,
+    metadata: Vec<(String, String)>,
+    reader: R,
+) -> anyhow::Result<u64> {
+    send_stream(
+        gctx,
+        receiver,
+        STREAM_KIND_TEXT,
+        total_len,
+        metadata,
+        reader,
+    )
+    .await
+}
+
+/// 以流的方式发送一段完整的长文本
+pub async fn send_long_text(
+    gctx: Arc<GlobalContext>,
+    receiver: &str,
+    text: &str,
+) -> anyhow::Result<u64> {
+    let len = Some(text.len() as u64);
+    send_text_stream(gctx, receiver, len, vec![], text.as_bytes()).await
+}
@@ -0,0 +1,567 @@
+//! 节点身份轮换（继任记录）
+//!
+//! `identity rotate [grace_secs]` 为节点生成新的 `FreeWebMovementAddress`，并签发一条继任记录：
+//! 旧身份与新身份的私钥分别对 `标签 | 旧地址 | 旧公钥 | 新地址 | 新公钥 | 签发时间 | 宽限期结束时间`
+//! 的哈希签名。记录以旧身份签名发给所有已连接的对端（`Node/IdentitySuccessor`），在网络中泛洪，
+//! 每个节点在 `SuccessorRecords` 中保存校验通过的记录（持久化到 `successors.json`）。
+//!
+//! 对端认可记录后：地址簿中指向旧地址的别名改为新地址，节点表与路由表中的旧地址改记为新地址，
+//! 新地址绑定新公钥；轮换方是直连对端时，连接上记录的对端地址也随之更新。已验证身份的旧地址
+//! 只接受其绑定公钥签发的记录；同一旧地址只认第一条继任记录。
+//!
+//! 轮换方发出记录后把新身份设为主身份，与直连对端以新地址重新协商会话密钥，旧身份作为附加身份
+//! （`retired-<签发时间>`）保留到宽限期结束：宽限期内发往或来自旧地址的帧照常处理，结束后本节点
+//! 删除旧身份，对端丢弃旧地址发来的帧，并在之后的 [`SUCCESSOR_RETAIN_SECS`] 内继续记住该记录。
+//! 使用加密 keystore 时须通过 `ZZ_P2P_PASSPHRASE` 提供原口令，新身份以同一口令重新加密。
+//! `Node::id` 与 `node.started` 事件中的地址仍是启动时的身份，重启后加载新身份。
+
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use aex::{
+    connection::{context::Context, global::GlobalContext},
+    tcp::types::Codec,
+    time::SystemTime,
+};
+use bincode::{Decode, Encode};
+use dashmap::{DashMap, mapref::entry::Entry};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::identities::{self, SharedIdentities};
+use crate::io_storage::{IOStorage, STORAGE_ADDRESS, STORAGE_ALIASES, STORAGE_SUCCESSORS};
+use crate::journal::{self, Event};
+use crate::keystore::{KEYSTORE_FILE, Keystore, PASSPHRASE_ENV};
+use crate::node::Node as P2pNode;
+use crate::protocols::{
+    broadcast,
+    capabilities::{self, CAP_SUCCESSOR},
+    command::{Action, Entity, P2PCommand},
+    commands::identity::{IdentityBindings, VerifiedPeer},
+    commands::rekey,
+    error::{self, ProtocolError},
+    frame::P2PFrame,
+    routing::{self, RoutingTable},
+};
+use crate::scheduler::Scheduler;
+
+const SUCCESSOR_RECORD_LABEL: &[u8] = b"zz-p2p-successor-v1";
+/// 未指定宽限期时的默认值
+pub const SUCCESSOR_DEFAULT_GRACE_SECS: u64 = 24 * 60 * 60;
+/// 宽限期的上限
+pub const SUCCESSOR_MAX_GRACE_SECS: u64 = 7 * 24 * 60 * 60;
+/// 宽限期结束后继续记住记录（拒绝旧地址的帧）的时间
+pub const SUCCESSOR_RETAIN_SECS: u64 = 30 * 24 * 60 * 60;
+/// 本地最多保存的继任记录数
+pub const SUCCESSOR_RECORDS_MAX: usize = 10_000;
+/// 检查宽限期的间隔
+pub const SUCCESSOR_CHECK_INTERVAL_SECS: u64 = 60;
+/// 解析地址时最多沿继任链走几步
+pub const SUCCESSOR_CHAIN_MAX: usize = 8;
+
+/// 已知的继任记录：旧地址 → 记录，保存在 GlobalContext 中
+pub type SuccessorRecords = Arc<DashMap<String, SuccessorRecord>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct SuccessorRecord {
+    pub old: String,
+    pub old_public_key: Vec<u8>,
+    pub new: String,
+    pub new_public_key: Vec<u8>,
+    /// 签发时间（毫秒）
+    pub issued_at: u128,
+    /// 宽限期结束时间（毫秒），之后不再接受旧地址
+    pub grace_until: u128,
+    /// 旧身份对记录哈希的签名
+    pub old_signature: Vec<u8>,
+    /// 新身份对记录哈希的签名
+    pub new_signature: Vec<u8>,
+}
+
+impl Codec for SuccessorRecord {}
+
+fn sign_digest(identity: &FreeWebMovementAddress, digest: &[u8; 32]) -> Vec<u8> {
+    FreeWebMovementAddress::sign_message(&identity.private_key, digest)
+        .serialize_compact()
+        .to_vec()
+}
+
+fn verify_signature(
+    public_key: &[u8],
+    signature: &[u8],
+    digest: &[u8; 32],
+) -> Result<(), ProtocolError> {
+    bitcoin::PublicKey::from_slice(public_key).map_err(|_| ProtocolError::InvalidPublicKey)?;
+    bitcoin::secp256k1::ecdsa::Signature::from_compact(signature)
+        .map_err(|_| ProtocolError::MalformedSignature)?;
+    let public_key = FreeWebMovementAddress::to_public_key(public_key);
+    let signature = FreeWebMovementAddress::to_signature(signature);
+    if !FreeWebMovementAddress::verify_message(&public_key, digest, &signature) {
+        return Err(ProtocolError::BadSignature);
+    }
+    Ok(())
+}
+
+impl SuccessorRecord {
+    /// 两个签名覆盖的记录哈希
+    pub fn digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(SUCCESSOR_RECORD_LABEL);
+        for part in [
+            self.old.as_bytes(),
+            &self.old_public_key,
+            self.new.as_bytes(),
+            &self.new_public_key,
+        ] {
+            hasher.update((part.len() as u32).to_be_bytes());
+            hasher.update(part);
+        }
+        hasher.update(self.issued_at.to_be_bytes());
+        hasher.update(self.grace_until.to_be_bytes());
+        hasher.finalize().into()
+    }
+
+    /// 由 `old` 与 `new` 共同签发继任记录，宽限期不超过 [`SUCCESSOR_MAX_GRACE_SECS`]
+    pub fn sign(
+        old: &FreeWebMovementAddress,
+        new: &FreeWebMovementAddress,
+        grace: Duration,
+        now: u128,
+    ) -> anyhow::Result<Self> {
+        if old.to_string() == new.to_string() {
+            anyhow::bail!("Successor must be a different identity");
+        }
+        let grace = grace.min(Duration::from_secs(SUCCESSOR_MAX_GRACE_SECS));
+        if grace.is_zero() {
+            anyhow::bail!("Grace period must not be zero");
+        }
+        let mut record = SuccessorRecord {
+            old: old.to_string(),
+            old_public_key: old.public_key.to_bytes().to_vec(),
+            new: new.to_string(),
+            new_public_key: new.public_key.to_bytes().to_vec(),
+            issued_at: now,
+            grace_until: now + grace.as_millis(),
+            old_signature: vec![],
+            new_signature: vec![],
+        };
+        let digest = record.digest();
+        record.old_signature = sign_digest(old, &digest);
+        record.new_signature = sign_digest(new, &digest);
+        Ok(record)
+    }
+
+    /// 宽限期内旧地址仍被接受
+    pub fn in_grace(&self, now: u128) -> bool {
+        now < self.grace_until
+    }
+
+    /// 超过保留时间，可以忘记
+    pub fn is_stale(&self, now: u128) -> bool {
+        self.grace_until + Duration::from_secs(SUCCESSOR_RETAIN_SECS).as_millis() <= now
+    }
+
+    /// 校验地址、宽限期范围与两个签名
+    pub fn verify(&self) -> Result<(), ProtocolError> {
+        if self.old.is_empty() || self.new.is_empty() || self.old == self.new {
+            return Err(ProtocolError::decode(
+                "SuccessorRecord",
+                "invalid addresses",
+            ));
+        }
+        if self.old_public_key == self.new_public_key {
+            return Err(ProtocolError::decode("SuccessorRecord", "key not rotated"));
+        }
+        let max_grace = Duration::from_secs(SUCCESSOR_MAX_GRACE_SECS).as_millis();
+        if self.grace_until <= self.issued_at || self.grace_until - self.issued_at > max_grace {
+            return Err(ProtocolError::decode(
+                "SuccessorRecord",
+                "invalid grace period",
+            ));
+        }
+        let digest = self.digest();
+        verify_signature(&self.old_public_key, &self.old_signature, &digest)?;
+        verify_signature(&self.new_public_key, &self.new_signature, &digest)
+    }
+}
+
+/// [`accept`] 的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuccessorUpdate {
+    /// 新记录
+    Inserted,
+    /// 已有相同的记录
+    Unchanged,
+    /// 旧地址已有指向其它身份的记录
+    Conflict,
+    /// 记录已过保留时间
+    Stale,
+    /// 记录表已满
+    Full,
+}
+
+/// 合并一条（已校验签名的）记录：同一旧地址只认第一条
+pub fn accept(
+    records: &DashMap<String, SuccessorRecord>,
+    record: SuccessorRecord,
+    now: u128,
+) -> SuccessorUpdate {
+    if record.is_stale(now) {
+        return SuccessorUpdate::Stale;
+    }
+    if records.len() >= SUCCESSOR_RECORDS_MAX && !records.contains_key(&record.old) {
+        records.retain(|_, r| !r.is_stale(now));
+        if records.len() >= SUCCESSOR_RECORDS_MAX {
+            return SuccessorUpdate::Full;
+        }
+    }
+    match records.entry(record.old.clone()) {
+        Entry::Occupied(entry) => {
+            let current = entry.get();
+            if current.new == record.new && current.new_public_key == record.new_public_key {
+                SuccessorUpdate::Unchanged
+            } else {
+                SuccessorUpdate::Conflict
+            }
+        }
+        Entry::Vacant(entry) => {
+            entry.insert(record);
+            SuccessorUpdate::Inserted
+        }
+    }
+}
+
+/// 沿继任链找到 `address` 当前使用的地址；没有记录时原样返回
+pub fn current_address(records: &DashMap<String, SuccessorRecord>, address: &str) -> String {
+    let mut current = address.to_string();
+    for _ in 0..SUCCESSOR_CHAIN_MAX {
+        match records.get(&current) {
+            Some(record) => current = record.new.clone(),
+            None => break,
+        }
+    }
+    current
+}
+
+/// `address` 已被继任且宽限期已过
+pub fn is_retired(records: &DashMap<String, SuccessorRecord>, address: &str, now: u128) -> bool {
+    records
+        .get(address)
+        .is_some_and(|record| !record.in_grace(now))
+}
+
+/// 已验证身份的地址只接受其绑定公钥签发的记录，新地址也不能与已有绑定冲突
+async fn check_bindings(
+    gctx: &Arc<GlobalContext>,
+    record: &SuccessorRecord,
+) -> Result<(), ProtocolError> {
+    let Some(bindings) = gctx.get::<IdentityBindings>().await else {
+        return Ok(());
+    };
+    for (address, public_key) in [
+        (&record.old, &record.old_public_key),
+        (&record.new, &record.new_public_key),
+    ] {
+        match bindings.get(address) {
+            Some(key) if key.value() != public_key => {
+                return Err(ProtocolError::IdentityMismatch {
+                    claimed: address.clone(),
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// 按继任记录更新地址簿、节点表、路由表与身份绑定
+pub async fn honor(gctx: &Arc<GlobalContext>, record: &SuccessorRecord) {
+    if let Some(bindings) = gctx.get::<IdentityBindings>().await {
+        bindings.insert(record.new.clone(), record.new_public_key.clone());
+    }
+    if let Some(table) = gctx.get::<RoutingTable>().await {
+        routing::rename(&table, &record.old, &record.new);
+    }
+    let Some(node) = gctx.get::<Arc<P2pNode>>().await else {
+        return;
+    };
+    node.registry.rename_node(&record.old, &record.new);
+    let retargeted = node.registry.retarget_aliases(&record.old, &record.new);
+    if !retargeted.is_empty() {
+        tracing::info!(
+            "📒 Alias(es) {} now point to {}",
+            retargeted.join(", "),
+            record.new
+        );
+        node.io_storage
+            .save::<BTreeMap<String, String>>(&node.registry.aliases(), STORAGE_ALIASES)
+            .await;
+    }
+}
+
+/// 轮换方是这条连接的对端时，连接上记录的对端地址随之改为新地址
+async fn follow_connection(ctx: &Arc<Mutex<Context>>, record: &SuccessorRecord) {
+    let mut guard = ctx.lock().await;
+    if guard.get::<String>().as_deref() != Some(record.old.as_str()) {
+        return;
+    }
+    guard.set(record.new.clone());
+    if guard.get::<VerifiedPeer>() == Some(VerifiedPeer(record.old.clone())) {
+        guard.set(VerifiedPeer(record.new.clone()));
+    }
+}
+
+/// 持久化继任记录
+pub async fn save(gctx: &Arc<GlobalContext>) {
+    let (Some(records), Some(ios)) = (
+        gctx.get::<SuccessorRecords>().await,
+        gctx.get::<IOStorage>().await,
+    ) else {
+        tracing::error!(
+            "SuccessorRecords or IOStorage not found in context, records not persisted"
+        );
+        return;
+    };
+    let stored: BTreeMap<String, SuccessorRecord> = records
+        .iter()
+        .map(|r| (r.key().clone(), r.value().clone()))
+        .collect();
+    ios.save::<BTreeMap<String, SuccessorRecord>>(&stored, STORAGE_SUCCESSORS)
+        .await;
+}
+
+fn event(record: &SuccessorRecord, local: bool) -> Event {
+    Event::IdentityRotated {
+        old: record.old.clone(),
+        new: record.new.clone(),
+        grace_secs: (record.grace_until.saturating_sub(record.issued_at) / 1000) as u64,
+        local,
+    }
+}
+
+/// 向所有支持继任记录的连接（可排除来源连接）发送记录
+async fn fan_out(
+    gctx: &Arc<GlobalContext>,
+    record: &SuccessorRecord,
+    origin: Option<Arc<Mutex<Context>>>,
+) {
+    let manager = gctx.manager.clone();
+    manager
+        .forward(|entries| async move {
+            let mut targets = Vec::new();
+            for target in broadcast::unique_peers(entries, origin.as_ref(), None).await {
+                if capabilities::peer_supports(&target.ctx, CAP_SUCCESSOR).await {
+                    targets.push(target);
+                }
+            }
+            broadcast::send_all(gctx, targets, |peer_ctx| {
+                let record = record.clone();
+                async move {
+                    P2PFrame::send(
+                        peer_ctx,
+                        &Some(record),
+                        Entity::Node,
+                        Action::IdentitySuccessor,
+                        false,
+                    )
+                    .await
+                }
+            })
+            .await
+            .log("fan out IdentitySuccessor");
+        })
+        .await;
+}
+
+/// 保存新的主身份：使用 keystore 时以原口令重新加密，否则写入明文身份文件
+async fn persist_primary(
+    ios: &IOStorage,
+    old: &FreeWebMovementAddress,
+    new: &FreeWebMovementAddress,
+) -> anyhow::Result<()> {
+    let path = ios.path(KEYSTORE_FILE);
+    if !path.exists() {
+        ios.save::<FreeWebMovementAddress>(new, STORAGE_ADDRESS)
+            .await;
+        return Ok(());
+    }
+    let Some(passphrase) = std::env::var(PASSPHRASE_ENV).ok().filter(|p| !p.is_empty()) else {
+        anyhow::bail!(
+            "Identity is in keystore {}: set {} to rotate it",
+            path.display(),
+            PASSPHRASE_ENV
+        );
+    };
+    let (old, new) = (old.to_string(), new.clone());
+    // 解锁与加密都要做密钥派生，放到阻塞线程
+    tokio::task::spawn_blocking(move || {
+        let keystore = Keystore::load(&path)?;
+        if keystore.unlock(&passphrase)?.to_string() != old {
+            anyhow::bail!(
+                "Keystore {} does not hold the current identity",
+                path.display()
+            );
+        }
+        Keystore::seal(&new, &passphrase)?.save(&path)
+    })
+    .await?
+}
+
+/// 附加身份中保留旧身份使用的名称
+pub fn retired_name(record: &SuccessorRecord) -> String {
+    format!("retired-{}", record.issued_at / 1000)
+}
+
+/// 轮换本节点的主身份：签发并广播继任记录，切换到新身份后与直连对端重新协商会话密钥
+pub async fn rotate(gctx: &Arc<GlobalContext>, grace: Duration) -> anyhow::Result<SuccessorRecord> {
+    let (Some(old), Some(identities), Some(records), Some(node)) = (
+        gctx.get::<FreeWebMovementAddress>().await,
+        gctx.get::<SharedIdentities>().await,
+        gctx.get::<SuccessorRecords>().await,
+        gctx.get::<Arc<P2pNode>>().await,
+    ) else {
+        anyhow::bail!("Identity rotation is not initialized");
+    };
+    let new = FreeWebMovementAddress::random();
+    let record = SuccessorRecord::sign(&old, &new, grace, SystemTime::timestamp())?;
+    if identities.get(&retired_name(&record)).is_some() {
+        anyhow::bail!("Identity was rotated less than a second ago");
+    }
+    match accept(&records, record.clone(), record.issued_at) {
+        SuccessorUpdate::Inserted => {}
+        other => anyhow::bail!(
+            "Successor record for {} not stored: {:?}",
+            record.old,
+            other
+        ),
+    }
+    // 先保存新身份，失败时不改动其它状态
+    if let Err(e) = persist_primary(&node.io_storage, &old, &new).await {
+        records.remove(&record.old);
+        return Err(e);
+    }
+
+    // 以旧身份签名发出，对端据此认可新地址
+    fan_out(gctx, &record, None).await;
+    let retired = identities.rotate_primary(&retired_name(&record), new.clone())?;
+    gctx.set(new.clone()).await;
+    gctx.local_node.write().await.id = new.to_string().into_bytes();
+    identities::save(gctx).await;
+    save(gctx).await;
+    tracing::info!(
+        "🪪 Identity rotated {} -> {}, old identity kept as {} during the grace period",
+        record.old,
+        record.new,
+        retired.name
+    );
+
+    let started = rekey::rotate(gctx, node.registry.get_connected_nodes()).await;
+    tracing::info!("🔑 Renegotiating session keys with {} peer(s)", started);
+    journal::record(gctx, event(&record, true)).await;
+    Ok(record)
+}
+
+/// 宽限期已过的退役地址发来的帧不再处理；返回 false 表示丢弃
+pub async fn admit(ctx: &Arc<Mutex<Context>>, frame: &P2PFrame) -> bool {
+    let gctx = ctx.lock().await.global.clone();
+    let Some(records) = gctx.get::<SuccessorRecords>().await else {
+        return true;
+    };
+    if !is_retired(&records, &frame.body.address, SystemTime::timestamp()) {
+        return true;
+    }
+    tracing::debug!(
+        "Dropping frame from retired address {} (now {})",
+        frame.body.address,
+        current_address(&records, &frame.body.address)
+    );
+    false
+}
+
+/// 宽限期结束后删除本节点保留的旧身份，并忘记超过保留时间的记录
+pub async fn prune(gctx: &Arc<GlobalContext>) {
+    let Some(records) = gctx.get::<SuccessorRecords>().await else {
+        return;
+    };
+    let now = SystemTime::timestamp();
+    if let Some(identities) = gctx.get::<SharedIdentities>().await {
+        let mut removed = false;
+        for identity in identities.list() {
+            let address = identity.address.to_string();
+            if !identity.is_primary()
+                && is_retired(&records, &address, now)
+                && identities.remove(&identity.name).is_ok()
+            {
+                tracing::info!("🪪 Grace period over, removed retired identity {}", address);
+                removed = true;
+            }
+        }
+        if removed {
+            identities::save(gctx).await;
+        }
+    }
+    let before = records.len();
+    records.retain(|_, r| !r.is_stale(now));
+    if records.len() != before {
+        save(gctx).await;
+    }
+}
+
+pub fn schedule(scheduler: &Scheduler, gctx: Arc<GlobalContext>) {
+    scheduler.register_interval(
+        "identity_successors",
+        Duration::from_secs(SUCCESSOR_CHECK_INTERVAL_SECS),
+        move || {
+            let gctx = gctx.clone();
+            async move { prune(&gctx).await }
+        },
+    );
+}
+
+pub async fn identity_successor_handler(
+    ctx: Arc<Mutex<Context>>,
+    frame: P2PFrame,
+    cmd: P2PCommand,
+) {
+    let peer = frame.body.address.clone();
+    let record: SuccessorRecord = match error::decode_command("SuccessorRecord", &frame, &cmd.data)
+    {
+        Ok(r) => r,
+        Err(e) => {
+            error::report(&ctx, &peer, e).await;
+            return;
+        }
+    };
+    let gctx = ctx.lock().await.global.clone();
+    let checked = match record.verify() {
+        Ok(()) => check_bindings(&gctx, &record).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = checked {
+        error::report(&ctx, &peer, e).await;
+        return;
+    }
+    let Some(records) = gctx.get::<SuccessorRecords>().await else {
+        return;
+    };
+    // 记录可能先经其它节点泛洪到达，连接上的地址每次都检查
+    follow_connection(&ctx, &record).await;
+    match accept(&records, record.clone(), SystemTime::timestamp()) {
+        SuccessorUpdate::Inserted => {
+            tracing::info!("🪪 {} rotated its identity to {}", record.old, record.new);
+            honor(&gctx, &record).await;
+            save(&gctx).await;
+            journal::record(&gctx, event(&record, false)).await;
+            // 只转发新记录，重复的记录在这里终止泛洪
+            fan_out(&gctx, &record, Some(ctx)).await;
+        }
+        update => tracing::debug!(
+            "Successor record {} -> {} from {}: {:?}",
+            record.old,
+            record.new,
+            peer,
+            update
+        ),
+    }
+}
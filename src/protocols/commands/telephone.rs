@@ -0,0 +1,456 @@
+//! 通话信令
+//!
+//! `Entity::Telephone` 下的 Call / Accept / Reject / HangUp 只负责建立与结束通话，
+//! 媒体流留给后续实现。同一时间只允许一个通话：
+//!
+//! ```text
+//!  Call ──► Ringing ──Accept──► Accepted ──HangUp──► 结束
+//!              │
+//!              ├──Reject / HangUp──► 结束
+//!              └──超时（RING_TIMEOUT_SECS）──► 结束
+//! ```
+//!
+//! 状态变化以 [`CallEvent`] 推送给上层（`Node::subscribe_calls`）。接通后的音频经
+//! [`crate::media`] 的 UDP 媒体通道传输。
+
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
+
+use aex::{
+    connection::{context::Context, global::GlobalContext},
+    tcp::types::Codec,
+    time::SystemTime,
+};
+use bincode::{Decode, Encode};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::node::Node as P2pNode;
+use crate::protocols::command::{Action, Entity, P2PCommand};
+use crate::protocols::error;
+use crate::protocols::frame::P2PFrame;
+use crate::scheduler::Scheduler;
+
+/// 振铃最长时间，超时后主叫方挂断
+pub const RING_TIMEOUT_SECS: u64 = 30;
+/// 超时检查间隔
+const RING_CHECK_INTERVAL_MS: u64 = 1000;
+
+/// 四种信令共用的负载
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct CallCommand {
+    pub call_id: u64,
+    /// 仅用于 Reject：被叫方正在通话中
+    pub busy: bool,
+    /// Call / Accept 携带本端 UDP 媒体端口，0 表示不接收媒体
+    pub media_port: u16,
+}
+
+impl Codec for CallCommand {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum CallDirection {
+    Outgoing,
+    Incoming,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum CallState {
+    Ringing,
+    Accepted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum EndReason {
+    HungUp,
+    Rejected,
+    Busy,
+    Timeout,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Call {
+    pub id: u64,
+    pub peer: String,
+    pub direction: CallDirection,
+    pub state: CallState,
+    pub started_at: u128,
+}
+
+/// 推送给上层的通话事件
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallEvent {
+    /// 呼出或来电，正在振铃
+    Ringing(Call),
+    Accepted(Call),
+    Ended {
+        call: Call,
+        reason: EndReason,
+        /// 是否由对端触发
+        by_peer: bool,
+    },
+}
+
+/// 通话状态机收到的信令（本地操作或对端消息）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    Accept,
+    Reject { busy: bool },
+    HangUp,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallError {
+    AlreadyInCall,
+    NoCall,
+    UnknownCall(u64),
+    /// 信令来自通话对端以外的节点
+    WrongPeer,
+    /// 当前状态下不允许该操作
+    InvalidTransition,
+}
+
+impl std::fmt::Display for CallError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CallError::AlreadyInCall => write!(f, "already in a call"),
+            CallError::NoCall => write!(f, "no call in progress"),
+            CallError::UnknownCall(id) => write!(f, "unknown call {}", id),
+            CallError::WrongPeer => write!(f, "signal from a different peer"),
+            CallError::InvalidTransition => write!(f, "not allowed in the current call state"),
+        }
+    }
+}
+
+impl std::error::Error for CallError {}
+
+/// 通话表（同一时间最多一个通话）
+#[derive(Debug, Default)]
+pub struct CallTable {
+    calls: HashMap<u64, Call>,
+}
+
+/// 保存在 GlobalContext 中的通话表
+pub type Calls = Arc<std::sync::Mutex<CallTable>>;
+
+impl CallTable {
+    pub fn current(&self) -> Option<Call> {
+        self.calls.values().next().cloned()
+    }
+
+    fn start(
+        &mut self,
+        id: u64,
+        peer: &str,
+        direction: CallDirection,
+        now: u128,
+    ) -> Result<CallEvent, CallError> {
+        if !self.calls.is_empty() {
+            return Err(CallError::AlreadyInCall);
+        }
+        let call = Call {
+            id,
+            peer: peer.to_string(),
+            direction,
+            state: CallState::Ringing,
+            started_at: now,
+        };
+        self.calls.insert(id, call.clone());
+        Ok(CallEvent::Ringing(call))
+    }
+
+    /// 本地呼出
+    pub fn dial(&mut self, id: u64, peer: &str, now: u128) -> Result<CallEvent, CallError> {
+        self.start(id, peer, CallDirection::Outgoing, now)
+    }
+
+    /// 对端来电
+    pub fn ring(&mut self, id: u64, peer: &str, now: u128) -> Result<CallEvent, CallError> {
+        self.start(id, peer, CallDirection::Incoming, now)
+    }
+
+    /// 应用一条信令；`remote` 为对端地址，本地操作时为 None
+    pub fn apply(
+        &mut self,
+        id: u64,
+        signal: Signal,
+        remote: Option<&str>,
+    ) -> Result<CallEvent, CallError> {
+        let call = self.calls.get_mut(&id).ok_or(CallError::UnknownCall(id))?;
+        if let Some(peer) = remote {
+            if peer != call.peer {
+                return Err(CallError::WrongPeer);
+            }
+        }
+        // 接听 / 拒绝只能由被叫方发出
+        let from_callee = match call.direction {
+            CallDirection::Incoming => remote.is_none(),
+            CallDirection::Outgoing => remote.is_some(),
+        };
+        let by_peer = remote.is_some();
+
+        match signal {
+            Signal::Accept => {
+                if call.state != CallState::Ringing || !from_callee {
+                    return Err(CallError::InvalidTransition);
+                }
+                call.state = CallState::Accepted;
+                Ok(CallEvent::Accepted(call.clone()))
+            }
+            Signal::Reject { busy } => {
+                if call.state != CallState::Ringing || !from_callee {
+                    return Err(CallError::InvalidTransition);
+                }
+                let call = self.calls.remove(&id).ok_or(CallError::UnknownCall(id))?;
+                let reason = if busy {
+                    EndReason::Busy
+                } else {
+                    EndReason::Rejected
+                };
+                Ok(CallEvent::Ended {
+                    call,
+                    reason,
+                    by_peer,
+                })
+            }
+            Signal::HangUp => {
+                let call = self.calls.remove(&id).ok_or(CallError::UnknownCall(id))?;
+                Ok(CallEvent::Ended {
+                    call,
+                    reason: EndReason::HungUp,
+                    by_peer,
+                })
+            }
+        }
+    }
+
+    /// 结束振铃超时的通话
+    pub fn expire(&mut self, now: u128, timeout_ms: u128) -> Vec<CallEvent> {
+        let expired: Vec<u64> = self
+            .calls
+            .values()
+            .filter(|c| {
+                c.state == CallState::Ringing && now.saturating_sub(c.started_at) >= timeout_ms
+            })
+            .map(|c| c.id)
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|id| self.calls.remove(&id))
+            .map(|call| CallEvent::Ended {
+                call,
+                reason: EndReason::Timeout,
+                by_peer: false,
+            })
+            .collect()
+    }
+}
+
+fn lock(calls: &Calls) -> std::sync::MutexGuard<'_, CallTable> {
+    match calls.lock() {
+        Ok(g) => g,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+async fn calls(gctx: &Arc<GlobalContext>) -> anyhow::Result<Calls> {
+    gctx.get::<Calls>()
+        .await
+        .ok_or_else(|| anyhow::anyhow!("call table not initialized"))
+}
+
+async fn emit(gctx: &Arc<GlobalContext>, event: CallEvent) {
+    // 接通时打开媒体通道，结束时关闭
+    match &event {
+        CallEvent::Accepted(call) => {
+            if let Err(e) = crate::media::open(gctx, call.id).await {
+                tracing::warn!("📞 No media channel for call {}: {}", call.id, e);
+            }
+        }
+        CallEvent::Ended { call, .. } => crate::media::close(gctx, call.id).await,
+        CallEvent::Ringing(_) => {}
+    }
+    if let Some(tx) = gctx
+        .get::<tokio::sync::mpsc::UnboundedSender<CallEvent>>()
+        .await
+    {
+        let _ = tx.send(event);
+    }
+}
+
+/// 找到与 `peer` 直连的连接
+async fn peer_context(gctx: &Arc<GlobalContext>, peer: &str) -> Option<Arc<Mutex<Context>>> {
+    let node = gctx.get::<Arc<P2pNode>>().await?;
+    node.registry
+        .get_seeds_for_node(peer)
+        .iter()
+        .find_map(|addr| {
+            gctx.manager
+                .find_entry(addr)
+                .and_then(|entry| entry.context.clone())
+        })
+}
+
+async fn send_signal(
+    gctx: &Arc<GlobalContext>,
+    peer: &str,
+    action: Action,
+    cmd: CallCommand,
+) -> anyhow::Result<()> {
+    let ctx = peer_context(gctx, peer)
+        .await
+        .ok_or_else(|| anyhow::anyhow!("{} is not connected", peer))?;
+    P2PFrame::send(ctx, &Some(cmd), Entity::Telephone, action, false).await
+}
+
+/// 呼叫 `peer`，返回通话 id
+pub async fn call(gctx: Arc<GlobalContext>, peer: &str) -> anyhow::Result<u64> {
+    let calls = calls(&gctx).await?;
+    let id: u64 = rand::thread_rng().r#gen();
+    let event = lock(&calls).dial(id, peer, SystemTime::timestamp())?;
+    let cmd = CallCommand {
+        call_id: id,
+        busy: false,
+        media_port: crate::media::local_port(&gctx).await,
+    };
+    if let Err(e) = send_signal(&gctx, peer, Action::Call, cmd).await {
+        let _ = lock(&calls).apply(id, Signal::HangUp, None);
+        return Err(e);
+    }
+    emit(&gctx, event).await;
+    Ok(id)
+}
+
+/// 接听当前来电
+pub async fn accept(gctx: Arc<GlobalContext>) -> anyhow::Result<Call> {
+    let calls = calls(&gctx).await?;
+    let current = lock(&calls).current().ok_or(CallError::NoCall)?;
+    let event = lock(&calls).apply(current.id, Signal::Accept, None)?;
+    let cmd = CallCommand {
+        call_id: current.id,
+        busy: false,
+        media_port: crate::media::local_port(&gctx).await,
+    };
+    send_signal(&gctx, &current.peer, Action::Accept, cmd).await?;
+    emit(&gctx, event.clone()).await;
+    match event {
+        CallEvent::Accepted(call) => Ok(call),
+        _ => Ok(current),
+    }
+}
+
+/// 挂断当前通话；来电振铃中时为拒接
+pub async fn hang_up(gctx: Arc<GlobalContext>) -> anyhow::Result<Call> {
+    let calls = calls(&gctx).await?;
+    let current = lock(&calls).current().ok_or(CallError::NoCall)?;
+    let (signal, action) = match (current.direction, current.state) {
+        (CallDirection::Incoming, CallState::Ringing) => {
+            (Signal::Reject { busy: false }, Action::Reject)
+        }
+        _ => (Signal::HangUp, Action::HangUp),
+    };
+    let event = lock(&calls).apply(current.id, signal, None)?;
+    let cmd = CallCommand {
+        call_id: current.id,
+        busy: false,
+        media_port: 0,
+    };
+    // 本地状态已经结束，对端不可达时它会因超时或断线自行结束
+    if let Err(e) = send_signal(&gctx, &current.peer, action, cmd).await {
+        tracing::warn!("Failed to notify {} of hang-up: {:?}", current.peer, e);
+    }
+    emit(&gctx, event).await;
+    Ok(current)
+}
+
+pub async fn telephone_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    let peer = frame.body.address.clone();
+    let signal: CallCommand = match error::decode_command("CallCommand", &frame, &cmd.data) {
+        Ok(c) => c,
+        Err(e) => {
+            error::report(&ctx, &peer, e).await;
+            return;
+        }
+    };
+    let (gctx, remote) = {
+        let guard = ctx.lock().await;
+        (guard.global.clone(), guard.addr)
+    };
+    let Ok(calls) = calls(&gctx).await else {
+        return;
+    };
+
+    let now = SystemTime::timestamp();
+    let result = {
+        let mut table = lock(&calls);
+        match cmd.action {
+            Action::Call => table.ring(signal.call_id, &peer, now),
+            Action::Accept => table.apply(signal.call_id, Signal::Accept, Some(&peer)),
+            Action::Reject => table.apply(
+                signal.call_id,
+                Signal::Reject { busy: signal.busy },
+                Some(&peer),
+            ),
+            Action::HangUp => table.apply(signal.call_id, Signal::HangUp, Some(&peer)),
+            other => {
+                tracing::warn!("Unexpected telephone action {:?} from {}", other, peer);
+                return;
+            }
+        }
+    };
+
+    if cmd.action == Action::Call && result == Err(CallError::AlreadyInCall) {
+        tracing::info!("📞 Rejecting call from {}: busy", peer);
+        let busy = CallCommand {
+            call_id: signal.call_id,
+            busy: true,
+            media_port: 0,
+        };
+        let _ = P2PFrame::send(ctx, &Some(busy), Entity::Telephone, Action::Reject, false).await;
+        return;
+    }
+
+    match result {
+        Ok(event) => {
+            tracing::info!("📞 {:?} from {}: {:?}", cmd.action, peer, event);
+            if matches!(cmd.action, Action::Call | Action::Accept) {
+                let media = SocketAddr::new(remote.ip(), signal.media_port);
+                crate::media::set_remote(&gctx, signal.call_id, &peer, media).await;
+            }
+            emit(&gctx, event).await;
+        }
+        // 过期或重复的信令（例如对方挂断与超时同时发生）
+        Err(e) => tracing::warn!("📞 Ignoring {:?} from {}: {}", cmd.action, peer, e),
+    }
+}
+
+/// 后台结束振铃超时的通话；主叫方超时后通知被叫方挂断
+pub fn schedule_ring_timeout(scheduler: &Scheduler, gctx: Arc<GlobalContext>) {
+    scheduler.register_interval(
+        "ring_timeout",
+        Duration::from_millis(RING_CHECK_INTERVAL_MS),
+        move || {
+            let gctx = gctx.clone();
+            async move {
+                let Some(calls) = gctx.get::<Calls>().await else {
+                    return;
+                };
+                let expired =
+                    lock(&calls).expire(SystemTime::timestamp(), RING_TIMEOUT_SECS as u128 * 1000);
+                for event in expired {
+                    if let CallEvent::Ended { call, .. } = &event {
+                        if call.direction == CallDirection::Outgoing {
+                            let cmd = CallCommand {
+                                call_id: call.id,
+                                busy: false,
+                                media_port: 0,
+                            };
+                            let _ = send_signal(&gctx, &call.peer, Action::HangUp, cmd).await;
+                        }
+                    }
+                    emit(&gctx, event).await;
+                }
+            }
+        },
+    );
+}
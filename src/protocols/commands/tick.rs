@@ -4,19 +4,20 @@ use std::sync::Arc;
 use std::time::SystemTime;
 
 use aex::connection::context::Context;
-use aex::connection::scope::NetworkScope;
 use aex::tcp::types::Codec;
 use bincode::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use tokio::sync::Mutex;
 
+use crate::ip_scope;
 use crate::node::Node;
 use crate::protocols::command::P2PCommand;
 use crate::protocols::command::{Action, Entity};
 use crate::protocols::commands::ack::{SeedRecord, SeedsCommand, broadcast_seeds_to_peers};
 use crate::protocols::commands::node_registry::NodeRegistry;
 use crate::protocols::frame::P2PFrame;
+use crate::protocols::privacy;
 
 pub const WITNESS_RING_STABLE_ROUNDS: u32 = 2;
 
@@ -32,15 +33,19 @@ pub struct TickCommand {
 impl Codec for TickCommand {}
 
 async fn build_tick_command(ctx: Arc<Mutex<Context>>) -> TickCommand {
-    let seed_records = {
+    let (gctx, peer_ip) = {
         let guard = ctx.lock().await;
-        if let Some(node) = guard.global.get::<Arc<Node>>().await {
-            get_witness_ring_from_registry(&node.registry).await
-        } else {
-            vec![]
-        }
+        (guard.global.clone(), guard.addr.ip())
+    };
+    let ring = match gctx.get::<Arc<Node>>().await {
+        Some(node) => get_witness_ring_from_registry(&node.registry).await,
+        None => vec![],
     };
 
+    // 只携带对接收方可见的端点，摘要按实际携带的列表计算
+    let seed_records = privacy::policy(&gctx)
+        .await
+        .filter_seeds(&ring, Some(&peer_ip));
     let ring_hash = compute_witness_ring_hash(&seed_records);
 
     TickCommand {
@@ -68,7 +73,7 @@ pub fn compute_witness_ring_hash(seeds: &[SeedRecord]) -> [u8; 32] {
 }
 
 pub async fn tick_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
-    let tick: TickCommand = match Codec::decode(&cmd.data) {
+    let tick: TickCommand = match frame.decode_payload(&cmd.data) {
         Ok(cmd) => cmd,
         Err(e) => {
             eprintln!("❌ decode TickCommand failed: {e}");
@@ -256,9 +261,10 @@ async fn sync_witness_ring(ctx: Arc<Mutex<Context>>, seeds_cmd: &SeedsCommand) {
         for seed in &seeds_cmd.seeds {
             if !local_addrs.contains(&seed.address) {
                 if let Ok(seed_addr) = seed.address.parse::<SocketAddr>() {
-                    let scope = NetworkScope::from_ip(&seed_addr.ip());
+                    let scope = ip_scope::classify(&seed_addr.ip());
                     node.registry
                         .register(seed.node_address.clone(), seed_addr, scope);
+                    node.registry.endpoints().spawn_verify(seed_addr);
                     println!(
                         "  + Tick registered new seed: {} (node: {})",
                         seed.address, seed.node_address
@@ -0,0 +1,423 @@
+//! 主题订阅与发布
+//!
+//! 订阅 / 取消订阅以订阅者自己签名的帧在网格中扩散，`subscriber` 必须是帧的作者，
+//! 其他节点无法替别人订阅或取消订阅。每个节点记录订阅「经由哪个直连节点」首次到达，
+//! 发布的消息只沿这些反向路径经 [`routing::forward_frame`] 发给有订阅者的方向，
+//! 不再向所有连接扩散。订阅表按订阅者与总量限额，超出的订阅被丢弃且不再传播。
+//! 直连节点断开时，经由它学到的订阅随之清除。
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+use aex::connection::context::Context;
+use aex::connection::global::GlobalContext;
+use aex::tcp::types::Codec;
+use aex::time::SystemTime;
+use bincode::{Decode, Encode};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::protocols::broadcast;
+use crate::protocols::capabilities::{self, CAP_PUBSUB};
+use crate::protocols::command::P2PCommand;
+use crate::protocols::command::{Action, Entity};
+use crate::protocols::commands::identity::VerifiedPeer;
+use crate::protocols::commands::message::{SeenMessages, next_request_id};
+use crate::protocols::dedup;
+use crate::protocols::error::{self, ProtocolError};
+use crate::protocols::frame::P2PFrame;
+use crate::protocols::routing;
+use crate::protocols::version::CURRENT_PROTOCOL_VERSION;
+
+/// 主题订阅表：topic → 订阅者地址 → 订阅经由的直连节点（本节点自身的订阅为 `None`）
+pub type TopicSubscriptions = Arc<DashMap<String, HashMap<String, Option<String>>>>;
+
+/// 订阅表最多保存的 (主题, 订阅者) 条目数
+pub const MAX_SUBSCRIPTIONS: usize = 16_384;
+/// 每个订阅者最多订阅的主题数
+pub const MAX_TOPICS_PER_SUBSCRIBER: usize = 256;
+
+const SEEN_TOPIC_MAX: usize = 10_000;
+
+/// Subscribe / Unsubscribe 共用的命令
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct TopicCommand {
+    pub topic: String,
+    pub subscriber: String,
+    pub timestamp: u128,
+}
+
+impl Codec for TopicCommand {}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Encode, Decode)]
+pub struct PublishCommand {
+    pub topic: String,
+    pub sender: String,
+    pub message_id: u64,
+    pub timestamp: u128,
+    pub payload: Vec<u8>,
+}
+
+impl Codec for PublishCommand {}
+
+/// 收到的主题消息，用于通过 channel 通知上层应用
+#[derive(Debug, Clone)]
+pub struct IncomingTopicMessage {
+    pub topic: String,
+    pub from: String,
+    pub payload: Vec<u8>,
+    pub timestamp: u128,
+}
+
+/// 去重：返回 true 表示首次见到
+async fn first_seen(gctx: &Arc<GlobalContext>, key: String) -> bool {
+    let seen = match gctx.get::<SeenMessages>().await {
+        Some(s) => s,
+        None => return true,
+    };
+    seen.first_seen(key, SEEN_TOPIC_MAX)
+}
+
+/// 支持主题、已验证身份的直连节点（排除来源连接），每个节点只取一次
+async fn pubsub_neighbors(
+    gctx: &Arc<GlobalContext>,
+    origin: Option<&Arc<Mutex<Context>>>,
+) -> Vec<String> {
+    let entries = gctx
+        .manager
+        .get_all_entries()
+        .iter()
+        .filter_map(|addr| gctx.manager.find_entry(addr))
+        .collect();
+    let mut neighbors = BTreeSet::new();
+    for target in broadcast::unique_peers(entries, origin, None).await {
+        if !capabilities::peer_supports(&target.ctx, CAP_PUBSUB).await {
+            continue;
+        }
+        if let Some(VerifiedPeer(peer)) = target.ctx.lock().await.get::<VerifiedPeer>() {
+            neighbors.insert(peer);
+        }
+    }
+    neighbors.into_iter().collect()
+}
+
+/// 主题中除 `skip` 以外的订阅者经由的直连节点
+fn subscriber_routes(table: &TopicSubscriptions, topic: &str, skip: &[&str]) -> Vec<String> {
+    let Some(subs) = table.get(topic) else {
+        return vec![];
+    };
+    subs.iter()
+        .filter(|(subscriber, _)| !skip.contains(&subscriber.as_str()))
+        .filter_map(|(_, via)| via.clone())
+        .filter(|via| !skip.contains(&via.as_str()))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// 按订阅命令更新订阅表，`via` 为订阅到达的直连节点（本节点自身的订阅为 `None`）。
+/// 新订阅超出 [`MAX_TOPICS_PER_SUBSCRIBER`] 或 [`MAX_SUBSCRIPTIONS`] 时返回 false
+pub fn apply_subscription(
+    table: &TopicSubscriptions,
+    cmd: &TopicCommand,
+    via: Option<&str>,
+    action: Action,
+) -> bool {
+    match action {
+        Action::Subscribe => {
+            let known = table
+                .get(&cmd.topic)
+                .is_some_and(|subs| subs.contains_key(&cmd.subscriber));
+            if !known {
+                let mut total = 0;
+                let mut per_subscriber = 0;
+                for subs in table.iter() {
+                    total += subs.len();
+                    if subs.contains_key(&cmd.subscriber) {
+                        per_subscriber += 1;
+                    }
+                }
+                if total >= MAX_SUBSCRIPTIONS || per_subscriber >= MAX_TOPICS_PER_SUBSCRIBER {
+                    return false;
+                }
+            }
+            table
+                .entry(cmd.topic.clone())
+                .or_default()
+                .entry(cmd.subscriber.clone())
+                .or_insert_with(|| via.map(str::to_string));
+            true
+        }
+        Action::Unsubscribe => {
+            let empty = match table.get_mut(&cmd.topic) {
+                Some(mut subs) => {
+                    subs.remove(&cmd.subscriber);
+                    subs.is_empty()
+                }
+                None => false,
+            };
+            if empty {
+                table.remove_if(&cmd.topic, |_, subs| subs.is_empty());
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+/// 清除经由直连节点 `neighbor` 学到的订阅，在连接断开时调用
+pub fn forget_routes(table: &TopicSubscriptions, neighbor: &str) {
+    table.retain(|_, subs| {
+        subs.retain(|subscriber, via| subscriber != neighbor && via.as_deref() != Some(neighbor));
+        !subs.is_empty()
+    });
+}
+
+/// 直连节点断开后清除经由它的订阅
+pub async fn forget_peer(gctx: &Arc<GlobalContext>, neighbor: &str) {
+    if let Some(table) = gctx.get::<TopicSubscriptions>().await {
+        forget_routes(&table, neighbor);
+    }
+}
+
+async fn local_identity(gctx: &Arc<GlobalContext>) -> anyhow::Result<FreeWebMovementAddress> {
+    gctx.get::<FreeWebMovementAddress>()
+        .await
+        .ok_or_else(|| anyhow::anyhow!("Address not set"))
+}
+
+/// 以本节点身份签名主题帧
+async fn build_frame<C: Codec + Serialize>(
+    identity: &FreeWebMovementAddress,
+    cmd: &C,
+    action: Action,
+) -> anyhow::Result<P2PFrame> {
+    let command = P2PCommand::new(Entity::Topic, action, Codec::encode(cmd)?);
+    P2PFrame::build(identity, command, CURRENT_PROTOCOL_VERSION).await
+}
+
+async fn change_subscription(
+    gctx: Arc<GlobalContext>,
+    topic: &str,
+    action: Action,
+) -> anyhow::Result<()> {
+    let table = match gctx.get::<TopicSubscriptions>().await {
+        Some(t) => t,
+        None => {
+            return Err(anyhow::anyhow!(
+                "TopicSubscriptions not set in GlobalContext"
+            ));
+        }
+    };
+    let identity = local_identity(&gctx).await?;
+    let cmd = TopicCommand {
+        topic: topic.to_string(),
+        subscriber: identity.to_string(),
+        timestamp: SystemTime::timestamp(),
+    };
+    if !apply_subscription(&table, &cmd, None, action) {
+        return Err(anyhow::anyhow!(
+            "Subscription limit reached, not subscribing to '{}'",
+            topic
+        ));
+    }
+    let frame = build_frame(&identity, &cmd, action).await?;
+    first_seen(&gctx, subscription_key(&cmd, action)).await;
+    dedup::first_forward(&gctx, &subscription_key(&cmd, action)).await;
+    let neighbors = pubsub_neighbors(&gctx, None).await;
+    routing::forward_frame(&gctx, &frame, None, &neighbors).await;
+    Ok(())
+}
+
+fn subscription_key(cmd: &TopicCommand, action: Action) -> String {
+    format!(
+        "topic:{:?}:{}:{}:{}",
+        action, cmd.topic, cmd.subscriber, cmd.timestamp
+    )
+}
+
+/// 订阅主题，并把订阅关系广播到整个网络
+pub async fn subscribe(gctx: Arc<GlobalContext>, topic: &str) -> anyhow::Result<()> {
+    change_subscription(gctx, topic, Action::Subscribe).await
+}
+
+/// 取消订阅主题
+pub async fn unsubscribe(gctx: Arc<GlobalContext>, topic: &str) -> anyhow::Result<()> {
+    change_subscription(gctx, topic, Action::Unsubscribe).await
+}
+
+/// 向主题发布消息，只发往有订阅者的方向，返回消息 id
+pub async fn publish(
+    gctx: Arc<GlobalContext>,
+    topic: &str,
+    payload: Vec<u8>,
+) -> anyhow::Result<u64> {
+    let identity = local_identity(&gctx).await?;
+    let cmd = PublishCommand {
+        topic: topic.to_string(),
+        sender: identity.to_string(),
+        message_id: next_request_id(),
+        timestamp: SystemTime::timestamp(),
+        payload,
+    };
+    let frame = build_frame(&identity, &cmd, Action::Publish).await?;
+    first_seen(&gctx, publish_key(&cmd)).await;
+    dedup::first_forward(&gctx, &publish_key(&cmd)).await;
+    if let Some(table) = gctx.get::<TopicSubscriptions>().await {
+        let routes = subscriber_routes(&table, topic, &[cmd.sender.as_str()]);
+        routing::forward_frame(&gctx, &frame, None, &routes).await;
+    }
+    Ok(cmd.message_id)
+}
+
+fn publish_key(cmd: &PublishCommand) -> String {
+    format!(
+        "publish:{}:{}:{}",
+        cmd.sender, cmd.message_id, cmd.timestamp
+    )
+}
+
+pub async fn subscription_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    let sub: TopicCommand = match frame.decode_payload(&cmd.data) {
+        Ok(c) => c,
+        Err(e) => {
+            error::report(
+                &ctx,
+                &frame.body.address,
+                ProtocolError::decode("TopicCommand", e),
+            )
+            .await;
+            return;
+        }
+    };
+
+    // 只有订阅者本人签名的帧才能改变它的订阅
+    if sub.subscriber != frame.body.address {
+        error::report(
+            &ctx,
+            &frame.body.address,
+            ProtocolError::IdentityMismatch {
+                claimed: sub.subscriber.clone(),
+            },
+        )
+        .await;
+        return;
+    }
+
+    let (gctx, via) = {
+        let guard = ctx.lock().await;
+        (guard.global.clone(), guard.get::<VerifiedPeer>())
+    };
+    let Some(VerifiedPeer(via)) = via else {
+        return;
+    };
+
+    if !first_seen(&gctx, subscription_key(&sub, cmd.action)).await {
+        return;
+    }
+
+    tracing::info!(
+        "📌 {:?} topic='{}' subscriber={} via={}",
+        cmd.action,
+        sub.topic,
+        sub.subscriber,
+        via
+    );
+
+    if let Some(table) = gctx.get::<TopicSubscriptions>().await {
+        if !apply_subscription(&table, &sub, Some(&via), cmd.action) {
+            tracing::warn!(
+                "⚠️  Subscription limit reached, dropping topic='{}' subscriber={}",
+                sub.topic,
+                sub.subscriber
+            );
+            return;
+        }
+    }
+
+    // 继续在网格中传播订阅关系；环路上已转发过的不再转发
+    if dedup::first_forward(&gctx, &subscription_key(&sub, cmd.action)).await {
+        let neighbors = pubsub_neighbors(&gctx, Some(&ctx)).await;
+        routing::forward_frame(&gctx, &frame, Some(&ctx), &neighbors).await;
+    }
+}
+
+pub async fn publish_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
+    let message: PublishCommand = match frame.decode_payload(&cmd.data) {
+        Ok(c) => c,
+        Err(e) => {
+            error::report(
+                &ctx,
+                &frame.body.address,
+                ProtocolError::decode("PublishCommand", e),
+            )
+            .await;
+            return;
+        }
+    };
+
+    if message.sender != frame.body.address {
+        error::report(
+            &ctx,
+            &frame.body.address,
+            ProtocolError::IdentityMismatch {
+                claimed: message.sender.clone(),
+            },
+        )
+        .await;
+        return;
+    }
+
+    let (gctx, origin) = {
+        let guard = ctx.lock().await;
+        (guard.global.clone(), guard.get::<VerifiedPeer>())
+    };
+
+    if !first_seen(&gctx, publish_key(&message)).await {
+        return;
+    }
+
+    let address = match local_identity(&gctx).await {
+        Ok(a) => a.to_string(),
+        Err(e) => {
+            tracing::error!("{:?}", e);
+            return;
+        }
+    };
+
+    let Some(table) = gctx.get::<TopicSubscriptions>().await else {
+        return;
+    };
+    let is_local = table
+        .get(&message.topic)
+        .is_some_and(|subs| subs.contains_key(&address));
+
+    if is_local {
+        if let Some(tx) = gctx
+            .get::<tokio::sync::mpsc::UnboundedSender<IncomingTopicMessage>>()
+            .await
+        {
+            let _ = tx.send(IncomingTopicMessage {
+                topic: message.topic.clone(),
+                from: message.sender.clone(),
+                payload: message.payload.clone(),
+                timestamp: message.timestamp,
+            });
+        } else {
+            tracing::warn!("  ⚠️  No app channel found for topic '{}'", message.topic);
+        }
+    }
+
+    // 沿订阅的反向路径转发给其余订阅者，不回传给来源
+    if dedup::first_forward(&gctx, &publish_key(&message)).await {
+        let origin = origin.map(|VerifiedPeer(peer)| peer).unwrap_or_default();
+        let routes = subscriber_routes(
+            &table,
+            &message.topic,
+            &[address.as_str(), message.sender.as_str(), origin.as_str()],
+        );
+        routing::forward_frame(&gctx, &frame, Some(&ctx), &routes).await;
+    }
+}
@@ -9,6 +9,7 @@ use tokio::sync::mpsc;
 
 use crate::protocols::command::P2PCommand;
 use crate::protocols::command::{Action, Entity};
+use crate::protocols::error::{self, ProtocolError};
 use crate::protocols::frame::P2PFrame;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
@@ -46,10 +47,15 @@ fn get_validation_event_sender() -> Option<&'static ValidationSender> {
 }
 
 pub async fn witness_validate_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame, cmd: P2PCommand) {
-    let req: WitnessValidateRequest = match Codec::decode(&cmd.data) {
+    let req: WitnessValidateRequest = match frame.decode_payload(&cmd.data) {
         Ok(c) => c,
         Err(e) => {
-            tracing::error!("Failed to decode WitnessValidateRequest: {}", e);
+            error::report(
+                &ctx,
+                &frame.body.address,
+                ProtocolError::decode("WitnessValidateRequest", e),
+            )
+            .await;
             return;
         }
     };
@@ -80,14 +86,19 @@ pub async fn witness_validate_handler(ctx: Arc<Mutex<Context>>, frame: P2PFrame,
 }
 
 pub async fn witness_validate_ack_handler(
-    _ctx: Arc<Mutex<Context>>,
-    _frame: P2PFrame,
+    ctx: Arc<Mutex<Context>>,
+    frame: P2PFrame,
     cmd: P2PCommand,
 ) {
-    let resp: WitnessValidateResponse = match Codec::decode(&cmd.data) {
+    let resp: WitnessValidateResponse = match frame.decode_payload(&cmd.data) {
         Ok(c) => c,
         Err(e) => {
-            tracing::error!("Failed to decode WitnessValidateResponse: {}", e);
+            error::report(
+                &ctx,
+                &frame.body.address,
+                ProtocolError::decode("WitnessValidateResponse", e),
+            )
+            .await;
             return;
         }
     };
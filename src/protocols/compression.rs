@@ -0,0 +1,88 @@
+//! 帧压缩
+//!
+//! 握手时双方在 `OnlineCommand` / `OnlineAckCommand` 的 `capabilities` 中声明支持的算法，
+//! 协商结果以 `PeerCapabilities` 保存在连接 Context 中。发送时超过阈值且确实变小的帧
+//! 才会压缩；压缩帧在线路上以 `COMPRESSED_FRAME_MARKER | 算法 id` 开头，
+//! 与未压缩帧首字节（协议版本）区分，解码时先解压再交给 `Frame::validate` 验签。
+
+/// 支持 lz4 帧压缩
+pub const CAP_COMPRESSION_LZ4: u32 = 1 << 0;
+/// 本节点声明的能力位
+pub const LOCAL_CAPABILITIES: u32 = CAP_COMPRESSION_LZ4
+    | crate::protocols::wire_format::CAP_FORMAT_CBOR
+    | crate::protocols::wire_format::CAP_FORMAT_JSON
+    | crate::protocols::capabilities::LOCAL_FEATURES;
+
+/// 小于该长度的帧不压缩
+pub const COMPRESSION_THRESHOLD: usize = 1024;
+/// 压缩帧首字节高 4 位
+pub const COMPRESSED_FRAME_MARKER: u8 = 0xC0;
+/// 解压后允许的最大长度，防止解压炸弹
+pub const MAX_DECOMPRESSED_LEN: usize = 128 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    Lz4,
+}
+
+impl Compression {
+    pub fn id(&self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Lz4 => 1,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Compression::None),
+            1 => Some(Compression::Lz4),
+            _ => None,
+        }
+    }
+}
+
+/// 对端在握手中声明的能力位，保存在连接 Context 中
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PeerCapabilities(pub u32);
+
+impl PeerCapabilities {
+    /// 双方都支持的压缩算法
+    pub fn compression(&self) -> Compression {
+        if self.0 & LOCAL_CAPABILITIES & CAP_COMPRESSION_LZ4 != 0 {
+            Compression::Lz4
+        } else {
+            Compression::None
+        }
+    }
+}
+
+pub fn compress(algorithm: Compression, data: &[u8]) -> Vec<u8> {
+    match algorithm {
+        Compression::None => data.to_vec(),
+        Compression::Lz4 => lz4_flex::compress_prepend_size(data),
+    }
+}
+
+pub fn decompress(algorithm: Compression, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    match algorithm {
+        Compression::None => Ok(data.to_vec()),
+        Compression::Lz4 => {
+            if data.len() < 4 {
+                return Err(anyhow::anyhow!("Truncated lz4 frame"));
+            }
+            let declared = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+            if declared > MAX_DECOMPRESSED_LEN {
+                return Err(anyhow::anyhow!(
+                    "Decompressed frame too large: {} > {}",
+                    declared,
+                    MAX_DECOMPRESSED_LEN
+                ));
+            }
+            lz4_flex::decompress_size_prepended(data)
+                .map_err(|e| anyhow::anyhow!("lz4 decompress failed: {}", e))
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! 线路兼容性测试向量
+//!
+//! 重构编解码（`P2PFrame` 的手写 `Encode`/`Decode`、bincode 配置、线路格式）时，
+//! 往返测试仍会通过，但旧节点可能已经无法解析新帧。这里固定一组规范的命令与帧，
+//! 其编码以十六进制提交在 `tests/vectors/<name>.hex`，测试从两个方向比对：
+//!
+//! - 编码：[`TestVector::encode`] 的结果必须与文件逐字节相同；
+//! - 解码：文件内容必须能解码，且解码结果与规范值一致（[`TestVector::check_decode`]）。
+//!
+//! 向量使用固定的地址、公钥、签名与随机数，不做签名校验，只约束线路布局。
+//! 有意修改线路格式时（例如新增协议版本），新增向量而不是改写旧文件；
+//! 设置 `ZZ_P2P_UPDATE_VECTORS=1` 运行测试会重新写出所有文件。
+//!
+//! 文件格式：`#` 开头的行为说明，其余为十六进制，空白忽略。
+
+use aex::tcp::types::Codec;
+use serde::Serialize;
+
+use crate::protocols::{
+    command::{Action, Entity, P2PCommand},
+    frame::{FrameBody, HopSignature, P2PFrame},
+    version::{PROTOCOL_V1, PROTOCOL_V2, PROTOCOL_V3},
+    wire_format::{self, WireFormat},
+};
+
+/// 设置后测试重新写出向量文件而不是比对
+pub const UPDATE_ENV: &str = "ZZ_P2P_UPDATE_VECTORS";
+/// 向量文件的扩展名
+pub const VECTOR_EXTENSION: &str = "hex";
+
+const SENDER: &str = "1ZzConformanceVectorSender";
+const RECEIVER: &str = "1ZzConformanceVectorReceiver";
+const RELAY: &str = "1ZzConformanceVectorRelay";
+const NONCE: u64 = 0x0123_4567_89ab_cdef;
+
+/// 向量承载的值
+#[derive(Debug, Clone)]
+pub enum Vector {
+    Command(P2PCommand),
+    Frame(P2PFrame),
+}
+
+/// 一个测试向量
+#[derive(Debug, Clone)]
+pub struct TestVector {
+    /// 文件名（不含扩展名）
+    pub name: &'static str,
+    /// 写在文件首行的说明
+    pub description: &'static str,
+    pub vector: Vector,
+}
+
+impl TestVector {
+    /// 线路编码
+    pub fn encode(&self) -> anyhow::Result<Vec<u8>> {
+        match &self.vector {
+            Vector::Command(cmd) => Codec::encode(cmd),
+            Vector::Frame(frame) => Codec::encode(frame),
+        }
+    }
+
+    /// 解码 `bytes` 并与规范值比较
+    pub fn check_decode(&self, bytes: &[u8]) -> anyhow::Result<()> {
+        match &self.vector {
+            Vector::Command(expected) => {
+                let decoded: P2PCommand = Codec::decode(bytes)?;
+                if &decoded != expected {
+                    anyhow::bail!("decoded {:?}, expected {:?}", decoded, expected);
+                }
+            }
+            Vector::Frame(expected) => {
+                let decoded: P2PFrame = Codec::decode(bytes)?;
+                if decoded.format != expected.format {
+                    anyhow::bail!(
+                        "decoded format {:?}, expected {:?}",
+                        decoded.format,
+                        expected.format
+                    );
+                }
+                let (decoded, expected) = (canonical(&decoded)?, canonical(expected)?);
+                if decoded != expected {
+                    anyhow::bail!("decoded {}, expected {}", decoded, expected);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 向量文件内容
+    pub fn to_file(&self) -> anyhow::Result<String> {
+        Ok(format!(
+            "# {}\n{}",
+            self.description,
+            to_hex(&self.encode()?)
+        ))
+    }
+}
+
+/// 帧的可比较形式：body、签名与 TTL（压缩、格式等只影响线路编码的字段除外）
+fn canonical(frame: &P2PFrame) -> anyhow::Result<serde_json::Value> {
+    Ok(serde_json::to_value(frame)?)
+}
+
+/// 每行 32 字节的十六进制
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes
+        .chunks(32)
+        .map(|line| {
+            let mut hex: String = line.iter().map(|b| format!("{:02x}", b)).collect();
+            hex.push('\n');
+            hex
+        })
+        .collect()
+}
+
+/// 解析向量文件：跳过 `#` 开头的行，忽略空白
+pub fn parse_hex(content: &str) -> anyhow::Result<Vec<u8>> {
+    let digits: Vec<u8> = content
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .flat_map(|line| line.bytes())
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect();
+    if digits.len() % 2 != 0 {
+        anyhow::bail!("Odd number of hex digits");
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair)?;
+            u8::from_str_radix(pair, 16).map_err(|_| anyhow::anyhow!("Invalid hex byte '{}'", pair))
+        })
+        .collect()
+}
+
+fn ping() -> P2PCommand {
+    P2PCommand::new(Entity::Node, Action::Ping, vec![1, 2, 3])
+}
+
+fn send_text() -> P2PCommand {
+    P2PCommand::new(Entity::Message, Action::SendText, b"hello".to_vec())
+}
+
+/// 压缩格式的 secp256k1 公钥长度，内容固定
+fn public_key() -> Vec<u8> {
+    std::iter::once(0x02).chain(1..=32).collect()
+}
+
+/// 紧凑格式的签名长度，内容固定
+fn signature() -> Vec<u8> {
+    (0..64).collect()
+}
+
+fn frame(
+    version: u8,
+    destination: Option<&str>,
+    ttl: u8,
+    format: WireFormat,
+) -> anyhow::Result<P2PFrame> {
+    let data = wire_format::encode(format, &send_text())?;
+    let mut body = FrameBody::new(
+        version,
+        SENDER.to_string(),
+        public_key(),
+        NONCE,
+        data.len() as u32,
+        data,
+    );
+    body.destination = destination.map(str::to_string);
+    let mut frame = P2PFrame::new(body, signature());
+    frame.ttl = ttl;
+    frame.format = format;
+    Ok(frame)
+}
+
+fn hop() -> HopSignature {
+    HopSignature {
+        address: RELAY.to_string(),
+        public_key: public_key(),
+        signature: signature(),
+    }
+}
+
+/// 全部测试向量
+pub fn vectors() -> anyhow::Result<Vec<TestVector>> {
+    Ok(vec![
+        TestVector {
+            name: "command_ping",
+            description: "P2PCommand { entity: Node, action: Ping, data: [1, 2, 3] }",
+            vector: Vector::Command(ping()),
+        },
+        TestVector {
+            name: "command_send_text",
+            description: "P2PCommand { entity: Message, action: SendText, data: \"hello\" }",
+            vector: Vector::Command(send_text()),
+        },
+        TestVector {
+            name: "frame_v1",
+            description: "v1 bincode frame: body without destination, no ttl",
+            vector: Vector::Frame(frame(PROTOCOL_V1, None, 0, WireFormat::Bincode)?),
+        },
+        TestVector {
+            name: "frame_v2_direct",
+            description: "v2 bincode frame: destination None, ttl 8",
+            vector: Vector::Frame(frame(PROTOCOL_V2, None, 8, WireFormat::Bincode)?),
+        },
+        TestVector {
+            name: "frame_v2_relay",
+            description: "v2 bincode frame: destination set, ttl 3",
+            vector: Vector::Frame(frame(PROTOCOL_V2, Some(RECEIVER), 3, WireFormat::Bincode)?),
+        },
+        TestVector {
+            name: "frame_v2_json",
+            description: "v2 JSON frame: 0xD2 marker + length-prefixed JSON WireFrame, ttl 8",
+            vector: Vector::Frame(frame(PROTOCOL_V2, Some(RECEIVER), 8, WireFormat::Json)?),
+        },
+        TestVector {
+            name: "frame_v3_relay",
+            description: "v3 bincode frame: destination set, ttl 3, no hop signature",
+            vector: Vector::Frame(frame(PROTOCOL_V3, Some(RECEIVER), 3, WireFormat::Bincode)?),
+        },
+        TestVector {
+            name: "frame_v3_hop",
+            description: "v3 bincode frame: destination set, ttl 2, hop signature",
+            vector: Vector::Frame({
+                let mut frame = frame(PROTOCOL_V3, Some(RECEIVER), 2, WireFormat::Bincode)?;
+                frame.hop = Some(hop());
+                frame
+            }),
+        },
+    ])
+}
@@ -0,0 +1,199 @@
+//! 转发前的重复帧抑制
+//!
+//! 互相连接的服务器之间，泛洪转发的帧（中继、主题订阅与发布）可能沿环路反复转发。每次转发前按帧的
+//! 唯一标识（中继为发送方与 nonce，主题为订阅 / 发布的 key）查询一个轮换的布隆过滤器：见过的帧不再
+//! 转发。过滤器分新旧两代，每代最多登记 [`FORWARD_FILTER_CAPACITY`] 个标识，写满后旧的一代被丢弃、
+//! 当前代变为旧代，因此最近的 1～2 代标识总能被识别，不会像整体清空那样在清空后重新放行整批重复帧。
+//! 误判率约为 [`FORWARD_FILTER_FP_RATE`]，误判只会让某个帧少转发一次。
+//!
+//! 检查与抑制的次数显示在控制接口 `GET /status` 的 `duplicates` 中。
+
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicU64, Ordering},
+};
+
+use aex::connection::global::GlobalContext;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// 每代最多登记的标识数
+pub const FORWARD_FILTER_CAPACITY: usize = 50_000;
+/// 每代的目标误判率
+pub const FORWARD_FILTER_FP_RATE: f64 = 0.001;
+
+/// 固定大小的布隆过滤器，按 SHA-256 摘要做双重散列
+#[derive(Debug, Clone)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    hashes: u64,
+}
+
+impl BloomFilter {
+    /// 按容量与误判率计算位数与散列次数
+    pub fn new(capacity: usize, fp_rate: f64) -> Self {
+        let n = capacity.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-n * fp_rate.clamp(1e-9, 0.5).ln() / (ln2 * ln2)).ceil() as usize;
+        let words = bits.div_ceil(64).max(1);
+        let hashes = ((words * 64) as f64 / n * ln2).round().max(1.0) as u64;
+        Self {
+            bits: vec![0; words],
+            hashes,
+        }
+    }
+
+    fn positions(&self, digest: &[u8; 32]) -> impl Iterator<Item = usize> + use<> {
+        let m = (self.bits.len() * 64) as u64;
+        let h1 = u64::from_le_bytes(digest[0..8].try_into().unwrap_or_default());
+        let h2 = u64::from_le_bytes(digest[8..16].try_into().unwrap_or_default()) | 1;
+        (0..self.hashes).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % m) as usize)
+    }
+
+    pub fn contains(&self, digest: &[u8; 32]) -> bool {
+        self.positions(digest)
+            .all(|p| self.bits[p / 64] & (1 << (p % 64)) != 0)
+    }
+
+    pub fn insert(&mut self, digest: &[u8; 32]) {
+        for p in self.positions(digest) {
+            self.bits[p / 64] |= 1 << (p % 64);
+        }
+    }
+
+    /// 位数
+    pub fn size(&self) -> usize {
+        self.bits.len() * 64
+    }
+}
+
+/// 新旧两代轮换的布隆过滤器
+#[derive(Debug, Clone)]
+pub struct RotatingBloom {
+    current: BloomFilter,
+    previous: BloomFilter,
+    capacity: usize,
+    fp_rate: f64,
+    /// 当前代已登记的标识数
+    inserted: usize,
+    rotations: u64,
+}
+
+impl RotatingBloom {
+    pub fn new(capacity: usize, fp_rate: f64) -> Self {
+        Self {
+            current: BloomFilter::new(capacity, fp_rate),
+            previous: BloomFilter::new(capacity, fp_rate),
+            capacity: capacity.max(1),
+            fp_rate,
+            inserted: 0,
+            rotations: 0,
+        }
+    }
+
+    /// 登记 `key`：返回 true 表示首次见到（或已随旧代过期）
+    pub fn check_and_insert(&mut self, key: &[u8]) -> bool {
+        let digest: [u8; 32] = Sha256::digest(key).into();
+        if self.current.contains(&digest) || self.previous.contains(&digest) {
+            return false;
+        }
+        if self.inserted >= self.capacity {
+            let fresh = BloomFilter::new(self.capacity, self.fp_rate);
+            self.previous = std::mem::replace(&mut self.current, fresh);
+            self.inserted = 0;
+            self.rotations += 1;
+        }
+        self.current.insert(&digest);
+        self.inserted += 1;
+        true
+    }
+
+    /// 当前代已登记的标识数
+    pub fn len(&self) -> usize {
+        self.inserted
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inserted == 0
+    }
+
+    pub fn rotations(&self) -> u64 {
+        self.rotations
+    }
+}
+
+impl Default for RotatingBloom {
+    fn default() -> Self {
+        Self::new(FORWARD_FILTER_CAPACITY, FORWARD_FILTER_FP_RATE)
+    }
+}
+
+/// 转发去重的统计
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DedupStats {
+    pub checked: u64,
+    pub suppressed: u64,
+    pub rotations: u64,
+    /// 当前代已登记的标识数
+    pub tracked: usize,
+}
+
+/// 保存在 GlobalContext 中的转发过滤器
+#[derive(Debug, Default)]
+pub struct ForwardFilter {
+    bloom: Mutex<RotatingBloom>,
+    checked: AtomicU64,
+    suppressed: AtomicU64,
+}
+
+impl ForwardFilter {
+    /// 返回 true 表示应当转发
+    pub fn first_forward(&self, key: &str) -> bool {
+        let first = self
+            .bloom
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .check_and_insert(key.as_bytes());
+        self.checked.fetch_add(1, Ordering::Relaxed);
+        if !first {
+            self.suppressed.fetch_add(1, Ordering::Relaxed);
+        }
+        first
+    }
+
+    pub fn stats(&self) -> DedupStats {
+        let bloom = self.bloom.lock().unwrap_or_else(|e| e.into_inner());
+        DedupStats {
+            checked: self.checked.load(Ordering::Relaxed),
+            suppressed: self.suppressed.load(Ordering::Relaxed),
+            rotations: bloom.rotations(),
+            tracked: bloom.len(),
+        }
+    }
+}
+
+pub type SharedForwardFilter = Arc<ForwardFilter>;
+
+async fn filter(gctx: &GlobalContext) -> SharedForwardFilter {
+    match gctx.get::<SharedForwardFilter>().await {
+        Some(filter) => filter,
+        None => {
+            let filter = SharedForwardFilter::default();
+            gctx.set(filter.clone()).await;
+            filter
+        }
+    }
+}
+
+/// 转发前调用：返回 false 表示该帧已转发过，应当丢弃
+pub async fn first_forward(gctx: &GlobalContext, key: &str) -> bool {
+    let first = filter(gctx).await.first_forward(key);
+    if !first {
+        tracing::debug!("🔁 Suppressed duplicate forward of {}", key);
+    }
+    first
+}
+
+pub async fn stats(gctx: &GlobalContext) -> DedupStats {
+    filter(gctx).await.stats()
+}
@@ -0,0 +1,226 @@
+//! 协议错误
+//!
+//! 所有来自网络的字节（帧、命令、加密负载）都按不可信输入处理：解码失败返回
+//! `ProtocolError` 而不是 panic，handler 通过 [`report`] 记录错误计数，
+//! 同一对端累计错误达到 `limits.max_protocol_errors` 时断开该连接。
+
+use std::{fmt, sync::Arc};
+
+use aex::{
+    connection::{context::Context, global::GlobalContext},
+    tcp::types::Codec,
+};
+use dashmap::DashMap;
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::sync::Mutex;
+
+use crate::{
+    config::SharedConfig,
+    journal,
+    node::Node as P2pNode,
+    protocols::{frame::P2PFrame, peer_stats},
+    transport::Connection,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtocolError {
+    /// 负载无法解码为预期的命令
+    Decode { what: &'static str, reason: String },
+    /// 负载解密失败（会话密钥不匹配或数据被篡改）
+    Decrypt { reason: String },
+    /// 帧中的公钥不是合法的 secp256k1 公钥
+    InvalidPublicKey,
+    /// 帧中的签名不是合法的 64 字节紧凑签名
+    MalformedSignature,
+    /// 签名校验失败
+    BadSignature,
+    /// 中继节点附带的逐跳签名校验失败
+    BadHopSignature,
+    /// `data_length` 与实际负载长度不一致
+    LengthMismatch { declared: u32, actual: usize },
+    /// 对端声称的地址与其证明持有的密钥不符
+    IdentityMismatch { claimed: String },
+    /// 不支持的线路协议版本
+    UnsupportedVersion { version: u8 },
+    /// 负载超过 `[limits] max_message_bytes`
+    MessageTooLarge { size: usize, limit: usize },
+}
+
+impl ProtocolError {
+    pub fn decode(what: &'static str, reason: impl fmt::Display) -> Self {
+        ProtocolError::Decode {
+            what,
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn decrypt(reason: impl fmt::Display) -> Self {
+        ProtocolError::Decrypt {
+            reason: reason.to_string(),
+        }
+    }
+
+    /// 用于错误计数的稳定名称
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ProtocolError::Decode { .. } => "decode",
+            ProtocolError::Decrypt { .. } => "decrypt",
+            ProtocolError::InvalidPublicKey => "invalid_public_key",
+            ProtocolError::MalformedSignature => "malformed_signature",
+            ProtocolError::BadSignature => "bad_signature",
+            ProtocolError::BadHopSignature => "bad_hop_signature",
+            ProtocolError::LengthMismatch { .. } => "length_mismatch",
+            ProtocolError::IdentityMismatch { .. } => "identity_mismatch",
+            ProtocolError::UnsupportedVersion { .. } => "unsupported_version",
+            ProtocolError::MessageTooLarge { .. } => "message_too_large",
+        }
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::Decode { what, reason } => write!(f, "invalid {}: {}", what, reason),
+            ProtocolError::Decrypt { reason } => write!(f, "decryption failed: {}", reason),
+            ProtocolError::InvalidPublicKey => write!(f, "invalid public key"),
+            ProtocolError::MalformedSignature => write!(f, "malformed signature"),
+            ProtocolError::BadSignature => write!(f, "signature verification failed"),
+            ProtocolError::BadHopSignature => write!(f, "hop signature verification failed"),
+            ProtocolError::LengthMismatch { declared, actual } => write!(
+                f,
+                "data length mismatch: declared {}, actual {}",
+                declared, actual
+            ),
+            ProtocolError::IdentityMismatch { claimed } => {
+                write!(
+                    f,
+                    "identity proof does not match claimed address {}",
+                    claimed
+                )
+            }
+            ProtocolError::UnsupportedVersion { version } => write!(
+                f,
+                "unsupported protocol version {} (supported {}..={})",
+                version,
+                crate::protocols::version::MIN_PROTOCOL_VERSION,
+                crate::protocols::version::CURRENT_PROTOCOL_VERSION
+            ),
+            ProtocolError::MessageTooLarge { size, limit } => {
+                write!(
+                    f,
+                    "message of {} bytes exceeds limit of {} bytes",
+                    size, limit
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+/// 按帧的线路格式解码命令负载
+pub fn decode_command<T: Codec + DeserializeOwned>(
+    what: &'static str,
+    frame: &P2PFrame,
+    data: &Vec<u8>,
+) -> Result<T, ProtocolError> {
+    frame
+        .decode_payload(data)
+        .map_err(|e| ProtocolError::decode(what, e))
+}
+
+/// 协议错误计数：按错误类型与对端地址统计
+#[derive(Debug, Default)]
+pub struct ProtocolErrorStats {
+    by_kind: DashMap<&'static str, u64>,
+    by_peer: DashMap<String, u32>,
+}
+
+/// 保存在 GlobalContext 中的错误统计
+pub type ProtocolErrors = Arc<ProtocolErrorStats>;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ProtocolErrorSnapshot {
+    pub total: u64,
+    pub by_kind: Vec<(String, u64)>,
+    pub by_peer: Vec<(String, u32)>,
+}
+
+impl ProtocolErrorStats {
+    /// 记录一次错误，返回该对端当前累计的错误数
+    pub fn record(&self, peer: &str, error: &ProtocolError) -> u32 {
+        *self.by_kind.entry(error.kind()).or_insert(0) += 1;
+        let mut count = self.by_peer.entry(peer.to_string()).or_insert(0);
+        *count += 1;
+        *count
+    }
+
+    /// 对端当前累计的错误数
+    pub fn peer_errors(&self, peer: &str) -> u32 {
+        self.by_peer.get(peer).map(|n| *n).unwrap_or(0)
+    }
+
+    /// 对端被断开后清零，重连后重新计数
+    pub fn reset_peer(&self, peer: &str) {
+        self.by_peer.remove(peer);
+    }
+
+    pub fn snapshot(&self) -> ProtocolErrorSnapshot {
+        let mut by_kind: Vec<(String, u64)> = self
+            .by_kind
+            .iter()
+            .map(|e| (e.key().to_string(), *e.value()))
+            .collect();
+        by_kind.sort();
+        let mut by_peer: Vec<(String, u32)> = self
+            .by_peer
+            .iter()
+            .map(|e| (e.key().clone(), *e.value()))
+            .collect();
+        by_peer.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ProtocolErrorSnapshot {
+            total: by_kind.iter().map(|(_, n)| n).sum(),
+            by_kind,
+            by_peer,
+        }
+    }
+}
+
+/// handler 遇到协议错误时调用：记录日志与计数，超过阈值时断开连接
+pub async fn report(ctx: &Arc<Mutex<Context>>, peer: &str, error: ProtocolError) {
+    tracing::warn!("❌ Protocol error from {}: {}", peer, error);
+    let (gctx, addr) = {
+        let guard = ctx.lock().await;
+        (guard.global.clone(), guard.addr)
+    };
+    peer_stats::record_error(&gctx, addr).await;
+    let Some(stats) = gctx.get::<ProtocolErrors>().await else {
+        return;
+    };
+    let count = stats.record(peer, &error);
+
+    let threshold = match gctx.get::<SharedConfig>().await {
+        Some(config) => config.read().await.limits.max_protocol_errors,
+        None => 0,
+    };
+    if threshold == 0 || count < threshold {
+        return;
+    }
+
+    tracing::warn!("🚫 Disconnecting {} after {} protocol errors", peer, count);
+    stats.reset_peer(peer);
+    ctx.close().await;
+    let reason = format!("{} protocol errors", count);
+    journal::record_disconnect(&gctx, Some(peer), addr, &reason).await;
+    if let Some(node) = gctx.get::<Arc<P2pNode>>().await {
+        node.registry.disconnect(peer);
+    }
+}
+
+/// 错误统计快照；未初始化时为空
+pub async fn snapshot(gctx: &Arc<GlobalContext>) -> ProtocolErrorSnapshot {
+    match gctx.get::<ProtocolErrors>().await {
+        Some(stats) => stats.snapshot(),
+        None => ProtocolErrorSnapshot::default(),
+    }
+}
@@ -0,0 +1,141 @@
+//! 接收端消息过滤
+//!
+//! 嵌入方通过 [`NodeManager::add_filter`](crate::protocols::commands::node_registry::NodeRegistry::add_filter)
+//! 注册异步过滤器，在文本消息与群消息投递给应用（事件总线与应用 channel）之前按注册顺序执行：
+//! 每个过滤器可以放行、改写内容、添加标注（随事件一起交给应用），或者丢弃消息（例如垃圾评分、
+//! 关键词过滤）。过滤器拿到 [`GlobalContext`]，可以借助节点自行发送自动回复。
+//!
+//! 被丢弃的消息仍然会回执给发送方，不会被重发。过滤器 panic 或超过 [`FILTER_TIMEOUT`]
+//! 时记录错误并原样放行，后面的过滤器照常执行。
+
+use std::{
+    collections::BTreeMap,
+    panic::AssertUnwindSafe,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use aex::connection::global::GlobalContext;
+use futures::{FutureExt, future::BoxFuture};
+
+/// 单个过滤器的执行时限
+pub const FILTER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 交给过滤器的待投递消息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InboundMessage {
+    pub from: String,
+    /// 群消息所在的群 id，直接消息为 `None`
+    pub group_id: Option<String>,
+    pub content: String,
+    pub timestamp: u128,
+    /// 前面的过滤器添加的标注，投递时附在事件上
+    pub annotations: BTreeMap<String, String>,
+}
+
+impl InboundMessage {
+    pub fn annotate(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.annotations.insert(key.into(), value.into());
+    }
+}
+
+/// 过滤结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterVerdict {
+    /// 投递（可能已被改写或标注）的消息
+    Deliver(InboundMessage),
+    /// 丢弃，附带原因
+    Drop(String),
+}
+
+pub type MessageFilter = Arc<
+    dyn Fn(Arc<GlobalContext>, InboundMessage) -> BoxFuture<'static, FilterVerdict> + Send + Sync,
+>;
+
+/// 按注册顺序保存的具名过滤器
+#[derive(Clone, Default)]
+pub struct MessageFilters {
+    filters: Arc<RwLock<Vec<(String, MessageFilter)>>>,
+}
+
+impl MessageFilters {
+    /// 注册过滤器；同名的过滤器原位替换，返回是否替换了已有过滤器
+    pub fn add<F, Fut>(&self, name: &str, filter: F) -> bool
+    where
+        F: Fn(Arc<GlobalContext>, InboundMessage) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = FilterVerdict> + Send + 'static,
+    {
+        let filter: MessageFilter = Arc::new(move |gctx, message| Box::pin(filter(gctx, message)));
+        let mut filters = self.filters.write().unwrap_or_else(|e| e.into_inner());
+        match filters.iter_mut().find(|(n, _)| n == name) {
+            Some(entry) => {
+                entry.1 = filter;
+                true
+            }
+            None => {
+                filters.push((name.to_string(), filter));
+                false
+            }
+        }
+    }
+
+    /// 注销过滤器，返回是否存在
+    pub fn remove(&self, name: &str) -> bool {
+        let mut filters = self.filters.write().unwrap_or_else(|e| e.into_inner());
+        let before = filters.len();
+        filters.retain(|(n, _)| n != name);
+        filters.len() != before
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.filters
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(n, _)| n.clone())
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_empty()
+    }
+
+    /// 依次执行所有过滤器；返回 `None` 表示消息被丢弃
+    pub async fn apply(
+        &self,
+        gctx: &Arc<GlobalContext>,
+        mut message: InboundMessage,
+    ) -> Option<InboundMessage> {
+        // 先取快照，执行过滤器期间不持锁，过滤器里可以再注册或注销
+        let filters = self
+            .filters
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        for (name, filter) in filters {
+            let run = AssertUnwindSafe(filter(gctx.clone(), message.clone())).catch_unwind();
+            match tokio::time::timeout(FILTER_TIMEOUT, run).await {
+                Ok(Ok(FilterVerdict::Deliver(next))) => message = next,
+                Ok(Ok(FilterVerdict::Drop(reason))) => {
+                    tracing::info!(
+                        "  🚮 Message from {} dropped by filter {}: {}",
+                        message.from,
+                        name,
+                        reason
+                    );
+                    return None;
+                }
+                Ok(Err(_)) => tracing::error!("Message filter {} panicked, passing through", name),
+                Err(_) => tracing::warn!(
+                    "Message filter {} timed out after {:?}, passing through",
+                    name,
+                    FILTER_TIMEOUT
+                ),
+            }
+        }
+        Some(message)
+    }
+}
@@ -2,19 +2,68 @@ use aex::{
     connection::context::{AexWriter, Context},
     tcp::types::{Codec, Frame},
 };
+use bytes::Bytes;
 use rand::Rng;
-use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+};
 use tokio::{io::AsyncWriteExt, sync::Mutex};
 use zz_account::address::FreeWebMovementAddress;
 
+use crate::capture;
+use crate::connections;
+use crate::identities;
+use crate::protocols::bandwidth::{self, Direction};
+use crate::protocols::broadcast;
 use crate::protocols::command::P2PCommand;
 use crate::protocols::command::{Action, Entity};
-use bincode::{Decode, Encode};
+use crate::protocols::commands::fragment;
+use crate::protocols::compression::{
+    self, COMPRESSED_FRAME_MARKER, COMPRESSION_THRESHOLD, Compression, PeerCapabilities,
+};
+use crate::protocols::error::ProtocolError;
+use crate::protocols::lanes::{self, Lane};
+use crate::protocols::peer_stats;
+use crate::protocols::routing::DEFAULT_FRAME_TTL;
+use crate::protocols::version::{self, PeerVersion};
+use crate::protocols::wire_format::{self, FORMAT_FRAME_MARKER, WireFormat, WireFrame};
+use crate::retry::{self, Operation};
+use crate::secure_link;
+use crate::transport::Connection;
+use bincode::{
+    Decode, Encode,
+    de::Decoder,
+    enc::Encoder,
+    error::{DecodeError, EncodeError},
+};
+
+/// 解码器接受的最大负载字节数（0 表示不限制），见 [`crate::protocols::limits::MessageLimits`]
+static MAX_DATA_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+pub fn set_max_data_bytes(limit: usize) {
+    MAX_DATA_BYTES.store(limit, Ordering::Relaxed);
+}
 
-#[derive(Debug, Clone, Encode, Decode, Serialize, Deserialize)]
+pub fn max_data_bytes() -> usize {
+    MAX_DATA_BYTES.load(Ordering::Relaxed)
+}
+
+/// 在分配负载缓冲区之前拒绝超过上限的帧
+fn check_data_len(len: usize) -> Result<(), DecodeError> {
+    let limit = max_data_bytes();
+    if limit > 0 && len > limit {
+        return Err(DecodeError::OtherString(
+            ProtocolError::MessageTooLarge { size: len, limit }.to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FrameBody {
-    /// 协议版本
+    /// 协议版本，决定线路布局，见 `protocols::version`
     pub version: u8,
 
     /// 发送方地址（身份）
@@ -32,8 +81,37 @@ pub struct FrameBody {
     /// ⚠️ 加密后的数据（唯一承载业务的地方）
     // #[serde(with = "serde_bytes")]
     pub data: Vec<u8>,
+
+    /// 最终接收方地址；None 表示只发给直连对端，不参与中继（v2 起）
+    pub destination: Option<String>,
 }
 
+impl Encode for FrameBody {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        let layout =
+            version::layout(self.version).map_err(|e| EncodeError::OtherString(e.to_string()))?;
+        self.version.encode(encoder)?;
+        self.address.encode(encoder)?;
+        self.public_key.encode(encoder)?;
+        self.nonce.encode(encoder)?;
+        self.data_length.encode(encoder)?;
+        self.data.encode(encoder)?;
+        if layout.has_destination {
+            self.destination.encode(encoder)?;
+        }
+        Ok(())
+    }
+}
+
+impl<Context> Decode<Context> for FrameBody {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let version = u8::decode(decoder)?;
+        FrameBody::decode_fields(version, decoder)
+    }
+}
+
+bincode::impl_borrow_decode!(FrameBody);
+
 impl Codec for FrameBody {}
 
 impl FrameBody {
@@ -52,11 +130,13 @@ impl FrameBody {
             nonce,
             data_length,
             data,
+            destination: None,
         }
     }
 
     pub fn data_from_command(&mut self, cmd: &P2PCommand) -> anyhow::Result<()> {
         self.data = Codec::encode(cmd)?;
+        self.data_length = self.data.len() as u32;
         Ok(())
     }
 
@@ -64,56 +144,409 @@ impl FrameBody {
         let cmd: P2PCommand = Codec::decode(&self.data)?;
         Ok(cmd)
     }
+
+    /// 按线路格式解码 `data` 中的命令
+    pub fn command_as(&self, format: WireFormat) -> anyhow::Result<P2PCommand> {
+        wire_format::decode(format, &self.data)
+    }
+
+    /// 按 `version` 的布局解码版本号之后的字段
+    fn decode_fields<D: Decoder>(version: u8, decoder: &mut D) -> Result<Self, DecodeError> {
+        let layout =
+            version::layout(version).map_err(|e| DecodeError::OtherString(e.to_string()))?;
+        let address = Decode::decode(decoder)?;
+        let public_key = Decode::decode(decoder)?;
+        let nonce = Decode::decode(decoder)?;
+        let data_length: u32 = Decode::decode(decoder)?;
+        check_data_len(data_length as usize)?;
+        Ok(FrameBody {
+            version,
+            address,
+            public_key,
+            nonce,
+            data_length,
+            data: Decode::decode(decoder)?,
+            destination: if layout.has_destination {
+                Decode::decode(decoder)?
+            } else {
+                None
+            },
+        })
+    }
+
+    /// 该版本的帧是否携带 TTL
+    fn has_ttl(&self) -> bool {
+        version::layout(self.version).is_ok_and(|l| l.has_ttl)
+    }
+
+    /// 该版本的帧是否可以携带逐跳签名
+    pub fn has_hop_signature(&self) -> bool {
+        version::layout(self.version).is_ok_and(|l| l.has_hop_signature)
+    }
+}
+
+/// 转发节点的逐跳签名，覆盖作者签名之后的整帧（见 [`P2PFrame::hop_signing_bytes`]）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct HopSignature {
+    /// 转发节点地址
+    pub address: String,
+    /// 转发节点公钥
+    pub public_key: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    pub signature: Vec<u8>,
 }
 
 /// 端到端安全帧（只做加密与校验）
 
-#[derive(Debug, Clone, Encode, Decode, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct P2PFrame {
     pub body: FrameBody,
 
     /// 对 body 的签名
     #[serde(with = "serde_bytes")]
     pub signature: Vec<u8>,
+
+    /// 剩余跳数，中继时递减（不参与签名，以便中继节点修改）；v1 帧解码后为 0
+    pub ttl: u8,
+
+    /// 最近一个转发节点的签名（v3 起），作者签名不覆盖它，见 [`crate::protocols::signing`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hop: Option<HopSignature>,
+
+    /// 发送时使用的压缩算法（仅影响线路编码，不参与签名）
+    #[serde(skip)]
+    pub compression: Compression,
+
+    /// 线路编码格式；非 bincode 帧的签名针对按该格式编码的 body
+    #[serde(skip)]
+    pub format: WireFormat,
+
+    /// 非 bincode 帧签名时或收到时的 body 原始字节，重新编码时原样写出；
+    /// 验签、转发时共享同一块内存
+    #[serde(skip)]
+    raw_body: Option<Bytes>,
+
+    /// 非 bincode 帧中的命令转成 bincode 后的字节，供 router 解析
+    #[serde(skip)]
+    command_bytes: Option<Vec<u8>>,
+}
+
+impl Encode for P2PFrame {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        if self.format != WireFormat::Bincode {
+            let packed = self
+                .to_wire()
+                .map_err(|e| EncodeError::OtherString(e.to_string()))?;
+            (FORMAT_FRAME_MARKER | self.format.id()).encode(encoder)?;
+            return packed.encode(encoder);
+        }
+        if self.compression != Compression::None {
+            let plain = if self.body.has_hop_signature() {
+                bincode::encode_to_vec(
+                    (&self.body, &self.signature, self.ttl, &self.hop),
+                    *encoder.config(),
+                )?
+            } else if self.body.has_ttl() {
+                bincode::encode_to_vec((&self.body, &self.signature, self.ttl), *encoder.config())?
+            } else {
+                bincode::encode_to_vec((&self.body, &self.signature), *encoder.config())?
+            };
+            if plain.len() >= COMPRESSION_THRESHOLD {
+                let packed = compression::compress(self.compression, &plain);
+                if packed.len() < plain.len() {
+                    (COMPRESSED_FRAME_MARKER | self.compression.id()).encode(encoder)?;
+                    return packed.encode(encoder);
+                }
+            }
+        }
+        self.body.encode(encoder)?;
+        self.signature.encode(encoder)?;
+        if self.body.has_ttl() {
+            self.ttl.encode(encoder)?;
+        }
+        if self.body.has_hop_signature() {
+            self.hop.encode(encoder)?;
+        }
+        Ok(())
+    }
+}
+
+impl<Context> Decode<Context> for P2PFrame {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        // 未压缩帧首字节为 body.version，压缩帧首字节高 4 位为 COMPRESSED_FRAME_MARKER，
+        // CBOR/JSON 帧为 FORMAT_FRAME_MARKER
+        let first = u8::decode(decoder)?;
+        if first & 0xF0 == FORMAT_FRAME_MARKER {
+            let format = WireFormat::from_id(first & 0x0F)
+                .ok_or(DecodeError::Other("unknown frame format"))?;
+            let packed = Vec::<u8>::decode(decoder)?;
+            return P2PFrame::from_wire(format, &packed)
+                .map_err(|e| DecodeError::OtherString(e.to_string()));
+        }
+        if first & 0xF0 == COMPRESSED_FRAME_MARKER {
+            let algorithm = Compression::from_id(first & 0x0F)
+                .ok_or(DecodeError::Other("unknown frame compression"))?;
+            let packed = Vec::<u8>::decode(decoder)?;
+            let plain = compression::decompress(algorithm, &packed)
+                .map_err(|_| DecodeError::Other("frame decompression failed"))?;
+            let config = *decoder.config();
+            let (body, read): (FrameBody, usize) = bincode::decode_from_slice(&plain, config)?;
+            let (signature, used): (Vec<u8>, usize) =
+                bincode::decode_from_slice(&plain[read..], config)?;
+            let mut offset = read + used;
+            let ttl = if body.has_ttl() {
+                let (ttl, used) = bincode::decode_from_slice::<u8, _>(&plain[offset..], config)?;
+                offset += used;
+                ttl
+            } else {
+                0
+            };
+            let hop = if body.has_hop_signature() {
+                bincode::decode_from_slice::<Option<HopSignature>, _>(&plain[offset..], config)?.0
+            } else {
+                None
+            };
+            return Ok(P2PFrame {
+                body,
+                signature,
+                ttl,
+                hop,
+                compression: algorithm,
+                format: WireFormat::Bincode,
+                raw_body: None,
+                command_bytes: None,
+            });
+        }
+
+        let body = FrameBody::decode_fields(first, decoder)?;
+        let signature = Vec::<u8>::decode(decoder)?;
+        let ttl = if body.has_ttl() {
+            u8::decode(decoder)?
+        } else {
+            0
+        };
+        let hop = if body.has_hop_signature() {
+            Option::<HopSignature>::decode(decoder)?
+        } else {
+            None
+        };
+        Ok(P2PFrame {
+            body,
+            signature,
+            ttl,
+            hop,
+            compression: Compression::None,
+            format: WireFormat::Bincode,
+            raw_body: None,
+            command_bytes: None,
+        })
+    }
 }
 
+bincode::impl_borrow_decode!(P2PFrame);
+
 impl P2PFrame {
     pub fn new(body: FrameBody, signature: Vec<u8>) -> Self {
-        P2PFrame { body, signature }
+        P2PFrame {
+            body,
+            signature,
+            ttl: DEFAULT_FRAME_TTL,
+            hop: None,
+            compression: Compression::None,
+            format: WireFormat::Bincode,
+            raw_body: None,
+            command_bytes: None,
+        }
     }
 
     pub fn sign(body: FrameBody, signer: &FreeWebMovementAddress) -> anyhow::Result<Self> {
-        let bytes = Codec::encode(&body)?;
+        P2PFrame::sign_as(body, signer, WireFormat::Bincode)
+    }
+
+    /// 按 `format` 编码 body 并签名
+    pub fn sign_as(
+        body: FrameBody,
+        signer: &FreeWebMovementAddress,
+        format: WireFormat,
+    ) -> anyhow::Result<Self> {
+        let bytes = wire_format::encode(format, &body)?;
         let signature = FreeWebMovementAddress::sign_message(&signer.private_key, &bytes)
             .serialize_compact()
             .to_vec();
-        Ok(P2PFrame { body, signature })
+        let is_bincode = format == WireFormat::Bincode;
+        Ok(P2PFrame {
+            body,
+            signature,
+            ttl: DEFAULT_FRAME_TTL,
+            hop: None,
+            compression: Compression::None,
+            format,
+            raw_body: (!is_bincode).then_some(Bytes::from(bytes)),
+            command_bytes: None,
+        })
+    }
+
+    /// 签名对象：bincode 帧为 body 的 bincode 编码，其余为 body 的原始字节（不复制）
+    pub fn signing_bytes(&self) -> anyhow::Result<Bytes> {
+        match &self.raw_body {
+            Some(raw) => Ok(raw.clone()),
+            None => wire_format::encode(self.format, &self.body).map(Bytes::from),
+        }
+    }
+
+    /// 按本帧的线路格式解码未加密的命令负载
+    pub fn decode_payload<T: Codec + DeserializeOwned>(&self, data: &[u8]) -> anyhow::Result<T> {
+        wire_format::decode(self.format, data)
+    }
+
+    fn to_wire(&self) -> anyhow::Result<Vec<u8>> {
+        let wire = WireFrame {
+            body: self.signing_bytes()?.to_vec(),
+            signature: self.signature.clone(),
+            ttl: self.ttl,
+            hop: self.hop.clone(),
+        };
+        wire_format::encode(self.format, &wire)
+    }
+
+    fn from_wire(format: WireFormat, packed: &[u8]) -> anyhow::Result<Self> {
+        let wire: WireFrame = wire_format::decode(format, packed)?;
+        let body: FrameBody = wire_format::decode(format, &wire.body)?;
+        version::layout(body.version)?;
+        check_data_len(body.data.len()).map_err(|e| anyhow::anyhow!(e))?;
+        // router 按 bincode 解析命令，这里预先转码
+        let command = body.command_as(format)?;
+        let hop = wire.hop.filter(|_| body.has_hop_signature());
+        Ok(P2PFrame {
+            body,
+            signature: wire.signature,
+            ttl: wire.ttl,
+            hop,
+            compression: Compression::None,
+            format,
+            raw_body: Some(Bytes::from(wire.body)),
+            command_bytes: Some(Codec::encode(&command)?),
+        })
     }
 
-    pub fn verify_bytes(bytes: &Vec<u8>) -> anyhow::Result<P2PFrame> {
-        let frame: P2PFrame = Codec::decode(&bytes)?;
-        P2PFrame::verify(frame)
+    pub fn verify_bytes(bytes: &[u8]) -> anyhow::Result<P2PFrame> {
+        let frame: P2PFrame =
+            Codec::decode(bytes).map_err(|e| ProtocolError::decode("P2PFrame", e))?;
+        Ok(P2PFrame::verify(frame)?)
     }
 
-    pub fn verify(frame: P2PFrame) -> anyhow::Result<P2PFrame> {
-        let bytes = Codec::encode(&frame.body)?;
-        let bytes = bytes.as_slice();
+    /// 结构校验：在调用会 panic 的密钥解析之前拒绝畸形的长度、公钥与签名；
+    /// 作者地址必须由帧中的公钥推导得出，否则任何密钥都能以他人的地址签名
+    pub fn check(&self) -> Result<(), ProtocolError> {
+        if self.body.data_length as usize != self.body.data.len() {
+            return Err(ProtocolError::LengthMismatch {
+                declared: self.body.data_length,
+                actual: self.body.data.len(),
+            });
+        }
+        check_address(&self.body.address, &self.body.public_key)?;
+        bitcoin::secp256k1::ecdsa::Signature::from_compact(&self.signature)
+            .map_err(|_| ProtocolError::MalformedSignature)?;
+        Ok(())
+    }
+
+    /// 校验作者签名，以及存在时的逐跳签名
+    pub fn verify(frame: P2PFrame) -> Result<P2PFrame, ProtocolError> {
+        frame.check()?;
+        let bytes = frame
+            .signing_bytes()
+            .map_err(|e| ProtocolError::decode("FrameBody", e))?;
 
         let public_key = FreeWebMovementAddress::to_public_key(&frame.body.public_key);
         let signature = FreeWebMovementAddress::to_signature(&frame.signature);
 
-        if !FreeWebMovementAddress::verify_message(&public_key, bytes, &signature) {
-            return Err(anyhow::anyhow!("Frame signature verification failed"));
+        if !FreeWebMovementAddress::verify_message(&public_key, &bytes, &signature) {
+            return Err(ProtocolError::BadSignature);
         }
+        frame.verify_hop()?;
         Ok(frame)
     }
 
+    /// 逐跳签名的对象：作者签名的对象 + 作者签名 + 剩余跳数，
+    /// 因此下一跳能确认 TTL 由签名的转发节点设置
+    pub fn hop_signing_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let mut bytes = self.signing_bytes()?.to_vec();
+        bytes.extend_from_slice(&self.signature);
+        bytes.push(self.ttl);
+        Ok(bytes)
+    }
+
+    /// 以转发节点的身份附加逐跳签名，替换上一跳的签名；应在修改 TTL 之后调用
+    pub fn sign_hop(&mut self, signer: &FreeWebMovementAddress) -> anyhow::Result<()> {
+        if !self.body.has_hop_signature() {
+            anyhow::bail!(
+                "Protocol v{} frames cannot carry a hop signature",
+                self.body.version
+            );
+        }
+        let bytes = self.hop_signing_bytes()?;
+        let signature = FreeWebMovementAddress::sign_message(&signer.private_key, &bytes)
+            .serialize_compact()
+            .to_vec();
+        self.hop = Some(HopSignature {
+            address: signer.to_string(),
+            public_key: signer.public_key.to_bytes().to_vec(),
+            signature,
+        });
+        Ok(())
+    }
+
+    /// 校验逐跳签名；没有逐跳签名的帧直接通过
+    pub fn verify_hop(&self) -> Result<(), ProtocolError> {
+        let Some(hop) = &self.hop else {
+            return Ok(());
+        };
+        check_address(&hop.address, &hop.public_key)?;
+        bitcoin::secp256k1::ecdsa::Signature::from_compact(&hop.signature)
+            .map_err(|_| ProtocolError::MalformedSignature)?;
+        let bytes = self
+            .hop_signing_bytes()
+            .map_err(|e| ProtocolError::decode("FrameBody", e))?;
+
+        let public_key = FreeWebMovementAddress::to_public_key(&hop.public_key);
+        let signature = FreeWebMovementAddress::to_signature(&hop.signature);
+
+        if !FreeWebMovementAddress::verify_message(&public_key, &bytes, &signature) {
+            return Err(ProtocolError::BadHopSignature);
+        }
+        Ok(())
+    }
+
     pub async fn build(
         address: &FreeWebMovementAddress,
         cmd: P2PCommand,
         version: u8,
     ) -> anyhow::Result<Self> {
-        let cmd_bytes = Codec::encode(&cmd)?;
+        P2PFrame::build_to(address, cmd, version, None).await
+    }
+
+    /// 构建带最终接收方的帧，非接收方节点会按路由表中继；
+    /// 不支持中继的版本（v1）忽略 `destination`
+    pub async fn build_to(
+        address: &FreeWebMovementAddress,
+        cmd: P2PCommand,
+        version: u8,
+        destination: Option<String>,
+    ) -> anyhow::Result<Self> {
+        P2PFrame::build_as(address, cmd, version, destination, WireFormat::Bincode).await
+    }
+
+    /// 同 [`P2PFrame::build_to`]，命令与 body 按 `format` 编码
+    pub async fn build_as(
+        address: &FreeWebMovementAddress,
+        cmd: P2PCommand,
+        version: u8,
+        destination: Option<String>,
+        format: WireFormat,
+    ) -> anyhow::Result<Self> {
+        let layout = version::layout(version)?;
+        let destination = destination.filter(|_| layout.has_destination);
+        let cmd_bytes = wire_format::encode(format, &cmd)?;
         let body = FrameBody {
             address: address.to_string(),
             public_key: address.public_key.to_bytes().to_vec(),
@@ -121,19 +554,43 @@ impl P2PFrame {
             data_length: cmd_bytes.len() as u32,
             version,
             data: cmd_bytes,
+            destination,
         };
-        Ok(P2PFrame::sign(body, &address)?)
+        let mut frame = P2PFrame::sign_as(body, &address, format)?;
+        if format != WireFormat::Bincode {
+            frame.command_bytes = Some(Codec::encode(&cmd)?);
+        }
+        Ok(frame)
     }
 }
 
 impl Codec for P2PFrame {}
 
+/// `address` 必须是由 `public_key` 推导出的地址
+fn check_address(address: &str, public_key: &[u8]) -> Result<(), ProtocolError> {
+    let derived = secure_link::derive_address(public_key).ok_or(ProtocolError::InvalidPublicKey)?;
+    if derived != address {
+        return Err(ProtocolError::IdentityMismatch {
+            claimed: address.to_string(),
+        });
+    }
+    Ok(())
+}
+
 impl Frame for P2PFrame {
     fn validate(&self) -> bool {
-        let Ok(bytes) = Codec::encode(&self.body) else {
+        if let Err(e) = self.check() {
+            tracing::warn!(
+                "❌ Rejected malformed frame from {}: {}",
+                self.body.address,
+                e
+            );
+            return false;
+        }
+        let Ok(bytes) = self.signing_bytes() else {
             return false;
         };
-        let bytes = bytes.as_slice();
+        let bytes = &bytes[..];
 
         let public_key = FreeWebMovementAddress::to_public_key(&self.body.public_key);
         let signature = FreeWebMovementAddress::to_signature(&self.signature);
@@ -141,6 +598,16 @@ impl Frame for P2PFrame {
         if !FreeWebMovementAddress::verify_message(&public_key, bytes, &signature) {
             return false;
         }
+        if let Err(e) = self.verify_hop() {
+            let relay = self.hop.as_ref().map_or("", |h| h.address.as_str());
+            tracing::warn!(
+                "❌ Rejected frame from {} relayed by {}: {}",
+                self.body.address,
+                relay,
+                e
+            );
+            return false;
+        }
         true
     }
 
@@ -148,7 +615,7 @@ impl Frame for P2PFrame {
     where
         F: FnOnce(&[u8]) -> Vec<u8>,
     {
-        match Codec::encode(&self.body) {
+        match self.signing_bytes() {
             Ok(raw_bytes) => signer(&raw_bytes),
             Err(e) => {
                 tracing::error!("Failed to encode frame for signing: {:?}", e);
@@ -158,11 +625,11 @@ impl Frame for P2PFrame {
     }
 
     fn payload(&self) -> Option<Vec<u8>> {
-        Codec::encode(&self.body).ok()
+        self.signing_bytes().ok().map(|b| b.to_vec())
     }
 
     fn command(&self) -> Option<&Vec<u8>> {
-        Some(self.body.data.as_ref())
+        Some(self.command_bytes.as_ref().unwrap_or(&self.body.data))
     }
     fn is_flat(&self) -> bool {
         false
@@ -170,8 +637,28 @@ impl Frame for P2PFrame {
 }
 
 impl P2PFrame {
-    pub async fn send<C: Codec>(
+    pub async fn send<C: Codec + Serialize>(
+        ctx: Arc<Mutex<Context>>,
+        command: &Option<C>,
+        entity: Entity,
+        action: Action,
+        is_encrypt: bool,
+    ) -> anyhow::Result<()> {
+        let gctx = { ctx.lock().await.global.clone() };
+        let address = match gctx.get::<FreeWebMovementAddress>().await {
+            Some(a) => a,
+            None => {
+                tracing::error!("FreeWebMovementAddress not set in GlobalContext");
+                return Err(anyhow::anyhow!("Address not set"));
+            }
+        };
+        Self::send_as(ctx, &address, command, entity, action, is_encrypt).await
+    }
+
+    /// 以本进程的某个身份签名发送；加密时使用该身份的会话密钥表
+    pub async fn send_as<C: Codec + Serialize>(
         ctx: Arc<Mutex<Context>>,
+        address: &FreeWebMovementAddress,
         command: &Option<C>,
         entity: Entity,
         action: Action,
@@ -187,17 +674,78 @@ impl P2PFrame {
             (guard.global.clone(), guard.addr)
         };
 
-        let gpsk = gctx.clone().paired_session_keys.clone();
-
-        let address = match gctx.get::<FreeWebMovementAddress>().await {
-            Some(a) => a,
-            None => {
-                tracing::error!("FreeWebMovementAddress not set in GlobalContext");
-                return Err(anyhow::anyhow!("Address not set"));
+        let addr_str = address.to_string();
+        let gpsk = identities::session_keys_for(&gctx, Some(&addr_str)).await;
+
+        // 点对点消息携带最终接收方，便于非直连时经中继送达
+        let destination = match action {
+            Action::SendText => {
+                let decoded: anyhow::Result<crate::protocols::commands::message::MessageCommand> =
+                    Codec::decode(&data);
+                decoded.ok().map(|m| m.receiver)
+            }
+            Action::SendBinary => {
+                let decoded: anyhow::Result<
+                    crate::protocols::commands::binary::BinaryMessageCommand,
+                > = Codec::decode(&data);
+                decoded.ok().map(|m| m.receiver)
+            }
+            Action::SendEncrypted => {
+                let decoded: anyhow::Result<
+                    crate::protocols::commands::sealed::SealedMessageCommand,
+                > = Codec::decode(&data);
+                decoded.ok().map(|m| m.receiver)
+            }
+            Action::HttpRequest => {
+                let decoded: anyhow::Result<
+                    crate::protocols::commands::http_tunnel::HttpRequestCommand,
+                > = Codec::decode(&data);
+                decoded.ok().map(|m| m.receiver)
+            }
+            Action::HttpResponse => {
+                let decoded: anyhow::Result<
+                    crate::protocols::commands::http_tunnel::HttpResponseCommand,
+                > = Codec::decode(&data);
+                decoded.ok().map(|m| m.receiver)
+            }
+            Action::StreamOpen => {
+                let decoded: anyhow::Result<crate::protocols::commands::stream::StreamOpenCommand> =
+                    Codec::decode(&data);
+                decoded.ok().map(|m| m.receiver)
+            }
+            Action::StreamData => {
+                let decoded: anyhow::Result<crate::protocols::commands::stream::StreamDataCommand> =
+                    Codec::decode(&data);
+                decoded.ok().map(|m| m.receiver)
+            }
+            Action::StreamWindow => {
+                let decoded: anyhow::Result<
+                    crate::protocols::commands::stream::StreamWindowCommand,
+                > = Codec::decode(&data);
+                decoded.ok().map(|m| m.receiver)
+            }
+            Action::StreamClose => {
+                let decoded: anyhow::Result<
+                    crate::protocols::commands::stream::StreamCloseCommand,
+                > = Codec::decode(&data);
+                decoded.ok().map(|m| m.receiver)
+            }
+            Action::GroupInvite
+            | Action::GroupJoin
+            | Action::GroupLeave
+            | Action::GroupUpdate
+            | Action::GroupKey
+            | Action::GroupMessage => crate::protocols::commands::group::destination(action, &data),
+            Action::Error => {
+                let decoded: anyhow::Result<crate::protocols::commands::error::ErrorCommand> =
+                    Codec::decode(&data);
+                decoded.ok().map(|m| m.receiver)
             }
+            _ => None,
         };
+        // 对端回复的错误按 nonce 关联回这次发送
+        let request_id = crate::protocols::commands::error::request_id(action, &data);
 
-        let addr_str = address.to_string();
         let bytes = if is_encrypt {
             match gpsk {
                 Some(psk) => {
@@ -218,6 +766,19 @@ impl P2PFrame {
                                 addr_str.as_bytes().to_vec()
                             }
                         }
+                    } else if action == Action::SendBinary {
+                        let decoded: anyhow::Result<
+                            crate::protocols::commands::binary::BinaryMessageCommand,
+                        > = Codec::decode(&data);
+                        match decoded {
+                            Ok(msg) => msg.receiver.as_bytes().to_vec(),
+                            _ => {
+                                tracing::warn!(
+                                    "⚠️ Failed to decode BinaryMessageCommand for key lookup, falling back to self address"
+                                );
+                                addr_str.as_bytes().to_vec()
+                            }
+                        }
                     } else if action == Action::MessageAck {
                         // For MessageAck, use the peer's address (the node at the other end of
                         // this connection) as the encryption key. The session key table stores
@@ -267,8 +828,24 @@ impl P2PFrame {
                     } else {
                         addr_str.as_bytes().to_vec()
                     };
-                    match encode.encrypt(&key, &data).await {
-                        Ok(ct) => ct,
+                    drop(encode);
+                    let peer = String::from_utf8_lossy(&key).into_owned();
+                    match crate::protocols::commands::rekey::encrypt(&gctx, &psk, &peer, &data)
+                        .await
+                    {
+                        Ok(ct) => {
+                            if let Some(table) = gctx
+                                .get::<crate::protocols::commands::rekey::SessionTable>()
+                                .await
+                            {
+                                crate::protocols::commands::rekey::record_bytes(
+                                    &table,
+                                    &peer,
+                                    data.len(),
+                                );
+                            }
+                            ct
+                        }
                         Err(e) => {
                             tracing::error!(
                                 "❌ ENCRYPT FAILED for address='{}' (action={:?}): {:?}",
@@ -286,6 +863,15 @@ impl P2PFrame {
             data
         };
 
+        // 未加密的负载与帧使用同一格式，对端无需解析 bincode
+        let format = wire_format::for_connection(&ctx).await;
+        let bytes = match command {
+            Some(cmd) if !is_encrypt && format != WireFormat::Bincode => {
+                wire_format::encode(format, cmd)?
+            }
+            _ => bytes,
+        };
+
         tracing::info!(
             "📤 P2PFrame::send: {} {:?} encrypt={} data_len={}",
             addr_str,
@@ -296,14 +882,33 @@ impl P2PFrame {
 
         let command = P2PCommand::new(entity, action, bytes);
 
-        let frame = match P2PFrame::build(&address, command, 1).await {
-            Ok(f) => f,
-            Err(e) => {
-                tracing::error!("Failed to build P2PFrame: {:?}", e);
-                return Err(e);
-            }
+        // 握手协商出的版本；握手完成前使用当前版本
+        let (peer_version, compression) = {
+            let guard = ctx.lock().await;
+            (
+                guard.get::<PeerVersion>().unwrap_or_default(),
+                guard
+                    .get::<PeerCapabilities>()
+                    .map(|caps| caps.compression())
+                    .unwrap_or_default(),
+            )
         };
 
+        let mut frame =
+            match P2PFrame::build_as(address, command, peer_version.0, destination, format).await {
+                Ok(f) => f,
+                Err(e) => {
+                    tracing::error!("Failed to build P2PFrame: {:?}", e);
+                    return Err(e);
+                }
+            };
+
+        // 握手中协商过压缩的连接，大帧按协商的算法压缩（仅 bincode 帧）
+        frame.compression = compression;
+        if let Some(request_id) = request_id {
+            crate::protocols::commands::error::remember(&gctx, frame.body.nonce, request_id).await;
+        }
+
         let bytes = match Codec::encode(&frame) {
             Ok(b) => b,
             Err(e) => {
@@ -312,13 +917,50 @@ impl P2PFrame {
             }
         };
 
-        let mut guard = ctx.lock().await;
-        if let Some(ref mut writer) = guard.writer {
-            if let Err(e) = writer.write_all(&bytes).await {
-                tracing::error!("Failed to send data: {:?}", e);
-            }
+        capture::outbound(&gctx, peer_sock, &frame, &bytes).await;
 
-            let _ = writer.flush().await;
+        // 超过阈值的帧分片发送，各分片按优先级通道逐片排队写出
+        let chunks = match fragment::fragment_for(&ctx, &bytes).await? {
+            Some(chunks) => chunks,
+            None => vec![bytes],
+        };
+        let lane = Lane::of(action).fragmented(chunks.len() > 1);
+        let lanes = lanes::for_connection(&ctx).await;
+        let total: usize = chunks.iter().map(|c| c.len()).sum();
+        let started = std::time::Instant::now();
+        bandwidth::throttle(&gctx, peer_sock, Direction::Upload, total).await;
+
+        let writing = AtomicBool::new(false);
+        let write = async {
+            for chunk in &chunks {
+                let _permit = lanes.acquire(lane).await;
+                writing.store(true, Ordering::Relaxed);
+                if let Err(e) = ctx.send(chunk).await {
+                    tracing::error!("Failed to send data: {:?}", e);
+                    return false;
+                }
+                writing.store(false, Ordering::Relaxed);
+            }
+            true
+        };
+        let limit = retry::policy(&gctx, Operation::Send).await.timeout;
+        let written = match limit {
+            Some(limit) => tokio::time::timeout(limit, write).await.ok(),
+            None => Some(write.await),
+        };
+        peer_stats::record_sent(
+            &gctx,
+            peer_sock,
+            started.elapsed(),
+            written.unwrap_or(false),
+        )
+        .await;
+        if written.is_none() {
+            // 写到一半的帧使连接上的后续数据无法解析；仍在排队时只放弃本次发送
+            if writing.load(Ordering::Relaxed) {
+                connections::close_addr(&gctx, peer_sock, "send timed out").await;
+            }
+            anyhow::bail!("Sending {:?} to {} timed out", action, peer_sock);
         }
         Ok(())
     }
@@ -339,27 +981,27 @@ impl P2PFrame {
         // ===== 1️⃣ 查本地 clients ====
         {
             {
-                let manager = {
+                let gctx = {
                     let guard = ctx.lock().await;
-                    guard.global.manager.clone()
+                    guard.global.clone()
                 };
+                let manager = gctx.manager.clone();
 
                 let frame: &P2PFrame = self;
-                let Ok(bytes) = Codec::encode(frame) else {
+                let Ok(bytes) = Codec::encode(frame).map(Bytes::from) else {
                     tracing::error!("Failed to encode frame for notify");
                     return;
                 };
                 manager
                     .forward(|entries| async {
-                        for entry in entries {
-                            if let Some(ctx) = &entry.context {
-                                let mut guard = ctx.lock().await;
-                                if let Some(writer) = &mut guard.writer {
-                                    P2PFrame::send_bytes(writer, &bytes).await
-                                }
-                            }
-                            continue;
-                        }
+                        broadcast::write_all(
+                            &gctx,
+                            broadcast::all_peers(entries),
+                            bytes.clone(),
+                            Lane::Messaging,
+                        )
+                        .await
+                        .log("notify frame");
                     })
                     .await;
             };
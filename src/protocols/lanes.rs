@@ -0,0 +1,187 @@
+//! 出站优先级通道
+//!
+//! 同一连接上的写入原本按获取连接锁的先后进行，发送大文件时 Online/Ping 等控制帧会排在
+//! 一长串数据块之后。现在每个连接有一个调度器：写入前按帧所属的通道（控制 > 消息 > 批量）
+//! 排队，当前写入完成后按 [`LANE_WEIGHTS`] 加权轮询各通道，放行下一个写入。
+//! 需要分片的帧逐片排队并归入批量通道，控制帧最多等待一个分片写完；批量通道在每轮中
+//! 至少获得一次机会，不会被饿死。
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex as StdMutex},
+};
+
+use aex::connection::context::Context;
+use tokio::sync::{Mutex, oneshot};
+
+use crate::protocols::command::Action;
+
+/// 每轮中各通道（控制、消息、批量）最多放行的写入次数
+pub const LANE_WEIGHTS: [u32; 3] = [8, 4, 1];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Lane {
+    Control = 0,
+    Messaging = 1,
+    Bulk = 2,
+}
+
+impl Lane {
+    /// 按命令划分通道：连接维护、存活检测、会话密钥与流控为控制，大块数据为批量
+    pub fn of(action: Action) -> Lane {
+        match action {
+            Action::OnLine
+            | Action::OnLineAck
+            | Action::OffLine
+            | Action::Ack
+            | Action::Ping
+            | Action::Pong
+            | Action::Rekey
+            | Action::RekeyAck
+            | Action::Busy
+            | Action::ObservedAddress
+            | Action::IdentityChallenge
+            | Action::IdentityProof
+            | Action::IdentitySuccessor
+            | Action::StreamWindow
+            | Action::SeedsResync => Lane::Control,
+            Action::StreamData
+            | Action::Fragment
+            | Action::SendBinary
+            | Action::NodeSyncResponse
+            | Action::SeedSyncResponse
+            | Action::HttpResponse => Lane::Bulk,
+            _ => Lane::Messaging,
+        }
+    }
+
+    /// 需要分片的帧一律按批量发送，控制帧除外
+    pub fn fragmented(self, fragmented: bool) -> Lane {
+        match self {
+            Lane::Control => Lane::Control,
+            _ if fragmented => Lane::Bulk,
+            lane => lane,
+        }
+    }
+}
+
+#[derive(Default)]
+struct LaneState {
+    busy: bool,
+    waiting: [VecDeque<oneshot::Sender<LanePermit>>; 3],
+    /// 本轮各通道剩余的放行次数
+    credits: [u32; 3],
+}
+
+impl LaneState {
+    fn next(&mut self) -> Option<oneshot::Sender<LanePermit>> {
+        for _ in 0..2 {
+            for lane in 0..3 {
+                if self.credits[lane] == 0 {
+                    continue;
+                }
+                if let Some(waiter) = self.waiting[lane].pop_front() {
+                    self.credits[lane] -= 1;
+                    return Some(waiter);
+                }
+            }
+            if self.waiting.iter().all(|w| w.is_empty()) {
+                return None;
+            }
+            // 有等待者的通道都已用完本轮次数，开始新一轮
+            self.credits = LANE_WEIGHTS;
+        }
+        None
+    }
+}
+
+/// 单个连接的写入调度器，保存在连接 Context 中
+#[derive(Default)]
+pub struct Lanes {
+    state: StdMutex<LaneState>,
+}
+
+pub type SharedLanes = Arc<Lanes>;
+
+impl Lanes {
+    /// 等待轮到 `lane` 写入；持有返回的许可期间独占该连接的写端
+    pub async fn acquire(self: &Arc<Self>, lane: Lane) -> LanePermit {
+        loop {
+            let waiter = {
+                let mut state = self.state.lock().unwrap();
+                if !state.busy {
+                    state.busy = true;
+                    return LanePermit::new(self.clone());
+                }
+                let (tx, rx) = oneshot::channel();
+                state.waiting[lane as usize].push_back(tx);
+                rx
+            };
+            if let Ok(permit) = waiter.await {
+                return permit;
+            }
+        }
+    }
+
+    /// 各通道正在等待的写入数
+    pub fn waiting(&self) -> [usize; 3] {
+        let state = self.state.lock().unwrap();
+        [
+            state.waiting[0].len(),
+            state.waiting[1].len(),
+            state.waiting[2].len(),
+        ]
+    }
+
+    fn release(self: &Arc<Self>) {
+        loop {
+            let waiter = {
+                let mut state = self.state.lock().unwrap();
+                match state.next() {
+                    Some(waiter) => waiter,
+                    None => {
+                        state.busy = false;
+                        return;
+                    }
+                }
+            };
+            match waiter.send(LanePermit::new(self.clone())) {
+                Ok(()) => return,
+                // 等待者已放弃（如发送超时），许可交给下一个
+                Err(mut permit) => permit.lanes = None,
+            }
+        }
+    }
+}
+
+/// 写入许可，释放时放行下一个写入
+pub struct LanePermit {
+    lanes: Option<SharedLanes>,
+}
+
+impl LanePermit {
+    fn new(lanes: SharedLanes) -> Self {
+        Self { lanes: Some(lanes) }
+    }
+}
+
+impl Drop for LanePermit {
+    fn drop(&mut self) {
+        if let Some(lanes) = self.lanes.take() {
+            lanes.release();
+        }
+    }
+}
+
+/// 连接的写入调度器，首次使用时创建
+pub async fn for_connection(ctx: &Arc<Mutex<Context>>) -> SharedLanes {
+    let mut guard = ctx.lock().await;
+    match guard.get::<SharedLanes>() {
+        Some(lanes) => lanes,
+        None => {
+            let lanes = SharedLanes::default();
+            guard.set(lanes.clone());
+            lanes
+        }
+    }
+}
@@ -0,0 +1,354 @@
+//! 连接数上限与淘汰策略
+//!
+//! `[limits]` 中的 `max_inbound` / `max_outbound` / `max_connections` 限制入站、出站与总连接数
+//! （0 表示不限制）。新的入站连接在握手（OnLine）时检查，新的出站连接在拨号前检查；
+//! 超限时按 `eviction` 策略断开一个已有连接腾出位置，策略为 `reject` 或没有可淘汰的连接时
+//! 拒绝新连接：入站连接会先收到 `Busy` 再被关闭。中继模式下还会检查同一客户端的连接数，
+//! 见 [`crate::relay`]。
+//!
+//! `max_message_bytes` 与 `messages_per_second` 由 [`MessageLimits`] 执行：帧解码器拒绝
+//! 负载超过上限的帧，路由分发前再按连接检查消息大小与每秒消息数。配置热更新时
+//! [`crate::config::apply`] 刷新 GlobalContext 中的 [`SharedMessageLimits`]。
+
+use std::{
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicU32, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use aex::{
+    connection::{context::Context, entry::ConnectionEntry, global::GlobalContext},
+    time::SystemTime,
+};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    config::{LimitsConfig, SharedConfig},
+    journal,
+    protocols::{
+        broadcast::{self, PeerReachability},
+        command::{Action, Entity},
+        commands::{busy::BusyCommand, node_registry::ConnectionDirection, ping},
+        error::{self, ProtocolError, ProtocolErrors},
+        frame::{self, P2PFrame},
+    },
+    relay,
+};
+
+/// 被拒绝的对端建议的重试间隔
+pub const BUSY_RETRY_AFTER_SECS: u32 = 60;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvictionPolicy {
+    /// 淘汰评分最低的连接（延迟高、协议错误多、连接时间短）
+    #[default]
+    LowestScore,
+    /// 淘汰最久没有收到数据的连接
+    LeastRecentlyUsed,
+    /// 不淘汰，直接拒绝新连接
+    Reject,
+}
+
+/// 参与淘汰比较的连接快照
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnSnapshot {
+    pub addr: SocketAddr,
+    pub direction: ConnectionDirection,
+    pub score: i64,
+    /// 最近一次收到数据的时间（秒）
+    pub last_seen: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    Accept,
+    /// 先断开该连接再接受新连接
+    Evict(SocketAddr, ConnectionDirection),
+    Reject,
+}
+
+/// 连接评分：每分钟在线 +1，每 100ms 延迟 -1，每次协议错误 -10
+pub fn score(connected_secs: u64, latency_ms: Option<u64>, protocol_errors: u32) -> i64 {
+    (connected_secs / 60) as i64
+        - latency_ms.map(|ms| (ms / 100) as i64).unwrap_or(0)
+        - 10 * protocol_errors as i64
+}
+
+/// 判断 `direction` 方向上是否还能再建立一个连接；`conns` 不包含待加入的连接
+pub fn admit(
+    conns: &[ConnSnapshot],
+    direction: ConnectionDirection,
+    limits: &LimitsConfig,
+) -> Admission {
+    let cap = match direction {
+        ConnectionDirection::Inbound => limits.max_inbound,
+        ConnectionDirection::Outbound => limits.max_outbound,
+        ConnectionDirection::Unknown => 0,
+    };
+    let same = conns.iter().filter(|c| c.direction == direction).count();
+    let direction_full = cap > 0 && same >= cap;
+    let total_full = limits.max_connections > 0 && conns.len() >= limits.max_connections;
+    if !direction_full && !total_full {
+        return Admission::Accept;
+    }
+
+    // 同方向已满时只能淘汰同方向的连接，否则任意方向都可以
+    let candidates = conns
+        .iter()
+        .filter(|c| !direction_full || c.direction == direction);
+    let victim = match limits.eviction {
+        EvictionPolicy::Reject => None,
+        EvictionPolicy::LowestScore => candidates.min_by_key(|c| (c.score, c.last_seen)),
+        EvictionPolicy::LeastRecentlyUsed => candidates.min_by_key(|c| (c.last_seen, c.score)),
+    };
+    match victim {
+        Some(c) => Admission::Evict(c.addr, c.direction),
+        None => Admission::Reject,
+    }
+}
+
+async fn snapshot_entry(
+    gctx: &Arc<GlobalContext>,
+    entry: &Arc<ConnectionEntry>,
+    direction: ConnectionDirection,
+    now_secs: u64,
+) -> ConnSnapshot {
+    let latency = match &entry.context {
+        Some(ctx) => ping::latency_of(ctx).await,
+        None => None,
+    };
+    let mut errors = 0;
+    if let (Some(stats), Some(ctx)) = (gctx.get::<ProtocolErrors>().await, &entry.context) {
+        if let Some(peer) = ctx.lock().await.get::<String>() {
+            errors = stats.peer_errors(&peer);
+        }
+    }
+    // 广播时连续发送失败的连接优先被淘汰
+    let penalty = match gctx.get::<PeerReachability>().await {
+        Some(r) => broadcast::penalty(&r, &entry.addr),
+        None => 0,
+    };
+    let last_seen = entry.last_seen.load(std::sync::atomic::Ordering::Relaxed);
+    ConnSnapshot {
+        addr: entry.addr,
+        direction,
+        score: score(now_secs.saturating_sub(entry.connected_at), latency, errors) - penalty,
+        last_seen,
+    }
+}
+
+/// 当前所有连接（不含 `exclude`）
+pub async fn snapshot(gctx: &Arc<GlobalContext>, exclude: Option<SocketAddr>) -> Vec<ConnSnapshot> {
+    let now_secs = (SystemTime::timestamp() / 1000) as u64;
+    let mut entries = Vec::new();
+    for bucket_ref in gctx.manager.connections.iter() {
+        let bi_conn = bucket_ref.value();
+        for e in bi_conn.clients.iter() {
+            entries.push((e.value().clone(), ConnectionDirection::Inbound));
+        }
+        for e in bi_conn.servers.iter() {
+            entries.push((e.value().clone(), ConnectionDirection::Outbound));
+        }
+    }
+    let mut out = Vec::with_capacity(entries.len());
+    for (entry, direction) in entries {
+        if Some(entry.addr) == exclude {
+            continue;
+        }
+        out.push(snapshot_entry(gctx, &entry, direction, now_secs).await);
+    }
+    out
+}
+
+async fn limits(gctx: &Arc<GlobalContext>) -> LimitsConfig {
+    match gctx.get::<SharedConfig>().await {
+        Some(config) => config.read().await.limits.clone(),
+        None => LimitsConfig::default(),
+    }
+}
+
+async fn evict(gctx: &Arc<GlobalContext>, addr: SocketAddr, direction: ConnectionDirection) {
+    tracing::info!(
+        "♻️ Evicting {:?} connection {} to make room",
+        direction,
+        addr
+    );
+    gctx.manager
+        .remove(addr, direction == ConnectionDirection::Inbound);
+    journal::record_disconnect(gctx, None, addr, "evicted to make room").await;
+}
+
+/// 出站拨号前调用；返回 false 表示已达上限且无法淘汰
+pub async fn reserve_outbound(gctx: &Arc<GlobalContext>) -> bool {
+    let limits = limits(gctx).await;
+    if limits.max_outbound == 0 && limits.max_connections == 0 {
+        return true;
+    }
+    let conns = snapshot(gctx, None).await;
+    match admit(&conns, ConnectionDirection::Outbound, &limits) {
+        Admission::Accept => true,
+        Admission::Evict(addr, direction) => {
+            evict(gctx, addr, direction).await;
+            true
+        }
+        Admission::Reject => {
+            tracing::warn!(
+                "🚫 Outbound connection limit reached ({} open)",
+                conns.len()
+            );
+            false
+        }
+    }
+}
+
+async fn reject(
+    ctx: &Arc<Mutex<Context>>,
+    gctx: &Arc<GlobalContext>,
+    peer: SocketAddr,
+    reason: &str,
+) {
+    tracing::warn!("🚫 Rejecting inbound {}: {}", peer, reason);
+    let busy = BusyCommand {
+        reason: reason.to_string(),
+        retry_after_secs: BUSY_RETRY_AFTER_SECS,
+    };
+    let _ = P2PFrame::send(ctx.clone(), &Some(busy), Entity::Node, Action::Busy, false).await;
+    // 留一点时间让 Busy 写出后再关闭
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    gctx.manager.remove(peer, true);
+    journal::record_disconnect(gctx, None, peer, reason).await;
+}
+
+/// 入站握手时调用；返回 false 表示连接已被拒绝（已发送 `Busy` 并关闭）
+pub async fn admit_inbound(ctx: &Arc<Mutex<Context>>) -> bool {
+    let (gctx, peer) = {
+        let guard = ctx.lock().await;
+        (guard.global.clone(), guard.addr)
+    };
+    // 中继模式下同一客户端的连接数另有上限，见 [`crate::relay`]
+    if relay::is_enabled(&gctx).await {
+        let conns = snapshot(&gctx, Some(peer)).await;
+        if !relay::admit_connection(&gctx, &conns, peer.ip()).await {
+            reject(ctx, &gctx, peer, "per-client connection quota reached").await;
+            return false;
+        }
+    }
+    let limits = limits(&gctx).await;
+    if limits.max_inbound == 0 && limits.max_connections == 0 {
+        return true;
+    }
+    let conns = snapshot(&gctx, Some(peer)).await;
+    match admit(&conns, ConnectionDirection::Inbound, &limits) {
+        Admission::Accept => true,
+        Admission::Evict(addr, direction) => {
+            evict(&gctx, addr, direction).await;
+            true
+        }
+        Admission::Reject => {
+            reject(ctx, &gctx, peer, "connection limit reached").await;
+            false
+        }
+    }
+}
+
+/// 运行期生效的消息大小与速率上限，以及每个连接当前一秒窗口内的消息数
+#[derive(Debug, Default)]
+pub struct MessageLimits {
+    max_message_bytes: AtomicUsize,
+    messages_per_second: AtomicU32,
+    windows: DashMap<SocketAddr, (Instant, u32)>,
+}
+
+/// 保存在 GlobalContext 中的消息上限
+pub type SharedMessageLimits = Arc<MessageLimits>;
+
+impl MessageLimits {
+    pub fn new(limits: &LimitsConfig) -> Self {
+        let this = Self::default();
+        this.update(limits);
+        this
+    }
+
+    /// 应用新的上限；帧解码器不持有 GlobalContext，其上限为进程级，
+    /// 同一进程运行多个节点时以最后应用的配置为准
+    pub fn update(&self, limits: &LimitsConfig) {
+        self.max_message_bytes
+            .store(limits.max_message_bytes, Ordering::Relaxed);
+        self.messages_per_second
+            .store(limits.messages_per_second, Ordering::Relaxed);
+        frame::set_max_data_bytes(limits.max_message_bytes);
+    }
+
+    pub fn max_message_bytes(&self) -> usize {
+        self.max_message_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn messages_per_second(&self) -> u32 {
+        self.messages_per_second.load(Ordering::Relaxed)
+    }
+
+    /// 检查单条消息的负载大小
+    pub fn check_size(&self, len: usize) -> Result<(), ProtocolError> {
+        let limit = self.max_message_bytes();
+        if limit > 0 && len > limit {
+            return Err(ProtocolError::MessageTooLarge { size: len, limit });
+        }
+        Ok(())
+    }
+
+    /// 记录 `addr` 在 `now` 收到的一条消息；超过每秒上限时返回 false
+    pub fn allow(&self, addr: SocketAddr, now: Instant) -> bool {
+        let limit = self.messages_per_second();
+        if limit == 0 {
+            return true;
+        }
+        let mut window = self.windows.entry(addr).or_insert((now, 0));
+        if now.duration_since(window.0) >= Duration::from_secs(1) {
+            *window = (now, 0);
+        }
+        window.1 += 1;
+        window.1 <= limit
+    }
+
+    pub fn forget(&self, addr: &SocketAddr) {
+        self.windows.remove(addr);
+    }
+}
+
+/// 路由分发前调用；返回 false 表示消息超过大小或速率上限，已被丢弃
+pub async fn admit_message(ctx: &Arc<Mutex<Context>>, frame: &P2PFrame) -> bool {
+    let (gctx, addr) = {
+        let guard = ctx.lock().await;
+        (guard.global.clone(), guard.addr)
+    };
+    let Some(limits) = gctx.get::<SharedMessageLimits>().await else {
+        return true;
+    };
+    if let Err(e) = limits.check_size(frame.body.data.len()) {
+        error::report(ctx, &frame.body.address, e).await;
+        return false;
+    }
+    if !limits.allow(addr, Instant::now()) {
+        tracing::debug!(
+            "🚦 Dropped frame from {} ({}): over {} messages/s",
+            frame.body.address,
+            addr,
+            limits.messages_per_second()
+        );
+        return false;
+    }
+    true
+}
+
+/// 连接断开时清除速率窗口
+pub async fn forget(gctx: &Arc<GlobalContext>, addr: SocketAddr) {
+    if let Some(limits) = gctx.get::<SharedMessageLimits>().await {
+        limits.forget(&addr);
+    }
+}
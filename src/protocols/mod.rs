@@ -1,5 +1,24 @@
+pub mod acl;
+pub mod agent;
+pub mod bandwidth;
+pub mod broadcast;
+pub mod capabilities;
 pub mod command;
 pub mod commands;
+pub mod compression;
+pub mod conformance;
+pub mod dedup;
+pub mod error;
+pub mod filter;
 pub mod frame;
+pub mod lanes;
+pub mod limits;
 pub mod notify;
+pub mod ordering;
+pub mod peer_stats;
+pub mod privacy;
 pub mod registry;
+pub mod routing;
+pub mod signing;
+pub mod version;
+pub mod wire_format;
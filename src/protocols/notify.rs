@@ -4,6 +4,7 @@ use aex::{connection::context::Context, tcp::types::Command};
 use tokio::sync::Mutex;
 
 use crate::protocols::{
+    broadcast,
     command::{Action, Entity},
     frame::P2PFrame,
 };
@@ -15,29 +16,21 @@ pub async fn notify<T: Command + Clone>(
     action: Action,
     is_encrypt: bool,
 ) {
-    let manager = {
+    let gctx = {
         let guard = ctx.lock().await;
-        guard.global.manager.clone()
+        guard.global.clone()
     };
+    let manager = gctx.manager.clone();
     manager
         .forward(|entries| async {
-            for entry in entries {
-                // 1. 先把临时值固定到一个变量名上，延长它的生命周期
-                if let Some(ctx) = &entry.context {
-                    if let Err(e) = P2PFrame::send::<T>(
-                        ctx.clone(),
-                        &Some(cmd.clone()),
-                        entity,
-                        action,
-                        is_encrypt,
-                    )
-                    .await
-                    {
-                        tracing::error!("Failed to notify peer: {:?}", e);
-                    }
-                    tracing::info!("notify send!");
+            broadcast::send_all(&gctx, broadcast::all_peers(entries), |peer_ctx| {
+                let cmd = cmd.clone();
+                async move {
+                    P2PFrame::send::<T>(peer_ctx, &Some(cmd), entity, action, is_encrypt).await
                 }
-            }
+            })
+            .await
+            .log("notify");
         })
         .await;
 }
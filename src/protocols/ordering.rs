@@ -0,0 +1,230 @@
+//! 消息排序与去重
+//!
+//! 经多个服务器转发的消息可能重复或乱序到达。发送方为每个接收方维护递增的 `seq`
+//! （从 1 开始），并附带本次启动的 `epoch`；同一 `request_id` 的重发沿用同一个 `seq`。
+//! 接收方按发送方维护重排窗口：
+//!
+//! - `seq` 小于期望值或已在缓冲区中 → 重复，丢弃
+//! - `seq` 等于期望值 → 连同缓冲区中紧随其后的消息一起按序投递
+//! - `seq` 超前 → 放入缓冲区，等待缺失的消息；超出 `reorder_window` 或等待超过
+//!   `reorder_timeout_ms` 时跳过空洞，按序投递已收到的消息
+//! - `epoch` 变大说明发送方重启过，先投递旧缓冲区再从头开始
+//!
+//! `seq == 0` 表示发送方没有编号，调用方应退回按内容去重。
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use aex::{connection::global::GlobalContext, time::SystemTime};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::config::SharedConfig;
+
+/// 每个接收方缓存的 request_id → seq 映射上限，超过后清空
+const REQUEST_SEQ_CACHE_MAX: usize = 4096;
+/// 超时检查间隔
+pub const REORDER_FLUSH_INTERVAL_MS: u64 = 500;
+
+/// 重排窗口配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OrderingConfig {
+    /// 最多缓冲多少个超前的序号（0 表示不重排，收到即投递）
+    pub reorder_window: u64,
+    /// 缺失的消息最多等待多久（毫秒）
+    pub reorder_timeout_ms: u64,
+}
+
+impl Default for OrderingConfig {
+    fn default() -> Self {
+        Self {
+            reorder_window: 64,
+            reorder_timeout_ms: 2000,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ReceiverSeq {
+    next: u64,
+    by_request: HashMap<u64, u64>,
+}
+
+/// 发送方的序号分配器
+#[derive(Debug)]
+pub struct OutboundSequences {
+    epoch: u64,
+    receivers: DashMap<String, ReceiverSeq>,
+}
+
+impl OutboundSequences {
+    pub fn new(epoch: u64) -> Self {
+        Self {
+            epoch,
+            receivers: DashMap::new(),
+        }
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// 为发往 `receiver` 的 `request_id` 分配序号；同一 request_id 重发时返回相同的序号
+    pub fn seq_for(&self, receiver: &str, request_id: u64) -> u64 {
+        let mut entry = self.receivers.entry(receiver.to_string()).or_default();
+        if let Some(seq) = entry.by_request.get(&request_id) {
+            return *seq;
+        }
+        if entry.by_request.len() >= REQUEST_SEQ_CACHE_MAX {
+            entry.by_request.clear();
+        }
+        entry.next += 1;
+        let seq = entry.next;
+        entry.by_request.insert(request_id, seq);
+        seq
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Push<T> {
+    /// 重复消息，已丢弃
+    Duplicate,
+    /// 按序可投递的消息（可能为空：消息已缓冲等待前面的序号）
+    Deliver(Vec<T>),
+}
+
+#[derive(Debug)]
+struct Conversation<T> {
+    epoch: u64,
+    next: u64,
+    /// seq → (到达时间, 消息)
+    pending: BTreeMap<u64, (u128, T)>,
+}
+
+impl<T> Conversation<T> {
+    fn new(epoch: u64) -> Self {
+        Self {
+            epoch,
+            next: 1,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// 投递缓冲区中从 `next` 开始连续的消息
+    fn drain_ready(&mut self, out: &mut Vec<T>) {
+        while let Some((_, item)) = self.pending.remove(&self.next) {
+            out.push(item);
+            self.next += 1;
+        }
+    }
+
+    /// 放弃 `upto` 之前缺失的序号，投递其间已收到的消息
+    fn skip_to(&mut self, upto: u64, out: &mut Vec<T>) {
+        while self.next < upto {
+            if let Some((_, item)) = self.pending.remove(&self.next) {
+                out.push(item);
+            }
+            self.next += 1;
+        }
+        self.drain_ready(out);
+    }
+
+    fn drain_all(&mut self, out: &mut Vec<T>) {
+        let pending = std::mem::take(&mut self.pending);
+        out.extend(pending.into_values().map(|(_, item)| item));
+    }
+}
+
+/// 接收方按发送方维护的重排缓冲区
+#[derive(Debug)]
+pub struct ReorderBuffer<T> {
+    conversations: HashMap<String, Conversation<T>>,
+}
+
+impl<T> Default for ReorderBuffer<T> {
+    fn default() -> Self {
+        Self {
+            conversations: HashMap::new(),
+        }
+    }
+}
+
+impl<T> ReorderBuffer<T> {
+    pub fn push(
+        &mut self,
+        sender: &str,
+        epoch: u64,
+        seq: u64,
+        item: T,
+        now: u128,
+        config: &OrderingConfig,
+    ) -> Push<T> {
+        let mut out = Vec::new();
+        let conv = self
+            .conversations
+            .entry(sender.to_string())
+            .or_insert_with(|| Conversation::new(epoch));
+
+        if epoch < conv.epoch {
+            return Push::Duplicate;
+        }
+        if epoch > conv.epoch {
+            conv.drain_all(&mut out);
+            *conv = Conversation::new(epoch);
+        }
+        if seq < conv.next || conv.pending.contains_key(&seq) {
+            return Push::Duplicate;
+        }
+
+        conv.pending.insert(seq, (now, item));
+        if config.reorder_window == 0 {
+            conv.skip_to(seq, &mut out);
+        } else if seq >= conv.next + config.reorder_window {
+            conv.skip_to(seq + 1 - config.reorder_window, &mut out);
+        } else {
+            conv.drain_ready(&mut out);
+        }
+        Push::Deliver(out)
+    }
+
+    /// 跳过等待超时的空洞，返回 (发送方, 消息)
+    pub fn flush_expired(&mut self, now: u128, config: &OrderingConfig) -> Vec<(String, T)> {
+        let timeout = config.reorder_timeout_ms as u128;
+        let mut released = Vec::new();
+        for (sender, conv) in self.conversations.iter_mut() {
+            let mut out = Vec::new();
+            while let Some((seq, arrived)) = conv.pending.iter().next().map(|(s, (a, _))| (*s, *a))
+            {
+                if now.saturating_sub(arrived) < timeout {
+                    break;
+                }
+                conv.skip_to(seq, &mut out);
+            }
+            released.extend(out.into_iter().map(|item| (sender.clone(), item)));
+        }
+        released
+    }
+
+    /// 等待中的消息数
+    pub fn pending(&self) -> usize {
+        self.conversations.values().map(|c| c.pending.len()).sum()
+    }
+}
+
+/// 发送方序号，保存在 GlobalContext 中
+pub type SharedOutboundSequences = Arc<OutboundSequences>;
+
+pub fn new_outbound_sequences() -> SharedOutboundSequences {
+    Arc::new(OutboundSequences::new(SystemTime::timestamp() as u64))
+}
+
+/// 当前配置中的重排参数
+pub async fn config(gctx: &Arc<GlobalContext>) -> OrderingConfig {
+    match gctx.get::<SharedConfig>().await {
+        Some(config) => config.read().await.ordering.clone(),
+        None => OrderingConfig::default(),
+    }
+}
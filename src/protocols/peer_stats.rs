@@ -0,0 +1,209 @@
+//! 每个连接的协议统计与慢对端检测
+//!
+//! 对每个连接统计收到/发出的帧数、错误数（发送失败与协议错误）、平均往返时间（Ping）
+//! 与发送排队延迟（从开始发送到写入并 flush 完成，包括等待连接锁与限速的时间），
+//! 平均值为指数滑动平均。任一指标超过阈值的连接被标记为降级（degraded）：中继转发、
+//! 无路由泛洪与主题扇出等批量流量不再发往该连接，发给它本身的帧不受影响；
+//! 指标回落到阈值的一半以下后恢复。统计在 `status` 中显示，连接断开时清除。
+
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use aex::connection::{context::Context, global::GlobalContext};
+use dashmap::DashMap;
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+use crate::{protocols::broadcast::Target, reachability};
+
+/// 滑动平均中新样本的权重
+pub const EWMA_WEIGHT: f64 = 0.2;
+/// 平均往返时间超过该值时降级
+pub const SLOW_RTT_MS: f64 = 2_000.0;
+/// 平均发送排队延迟超过该值时降级
+pub const SLOW_SEND_MS: f64 = 500.0;
+/// 错误率超过该值时降级
+pub const MAX_ERROR_RATE: f64 = 0.2;
+/// 计算错误率所需的最少帧数
+pub const MIN_ERROR_SAMPLES: u64 = 20;
+/// 恢复时各指标须低于阈值的比例
+pub const RECOVERY_RATIO: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct PeerStats {
+    pub frames_in: u64,
+    pub frames_out: u64,
+    pub errors: u64,
+    /// 平均往返时间
+    pub rtt_ms: Option<f64>,
+    /// 平均发送排队延迟
+    pub send_latency_ms: Option<f64>,
+    pub degraded: bool,
+}
+
+fn ewma(current: Option<f64>, sample: f64) -> f64 {
+    match current {
+        Some(avg) => avg + EWMA_WEIGHT * (sample - avg),
+        None => sample,
+    }
+}
+
+impl PeerStats {
+    pub fn record_inbound(&mut self) {
+        self.frames_in += 1;
+    }
+
+    /// 记录一次发送；失败的发送同时计为错误
+    pub fn record_sent(&mut self, latency: Duration, ok: bool) {
+        self.frames_out += 1;
+        if !ok {
+            self.errors += 1;
+        }
+        self.send_latency_ms = Some(ewma(self.send_latency_ms, latency.as_secs_f64() * 1000.0));
+    }
+
+    pub fn record_error(&mut self) {
+        self.errors += 1;
+    }
+
+    pub fn record_rtt(&mut self, rtt: Duration) {
+        self.rtt_ms = Some(ewma(self.rtt_ms, rtt.as_secs_f64() * 1000.0));
+    }
+
+    /// 错误数占帧数的比例；样本不足时为 0
+    pub fn error_rate(&self) -> f64 {
+        let frames = self.frames_in + self.frames_out;
+        if frames < MIN_ERROR_SAMPLES {
+            return 0.0;
+        }
+        self.errors as f64 / frames as f64
+    }
+
+    /// 超过阈值的指标，`ratio` 为阈值的缩放比例
+    fn exceeds(&self, ratio: f64) -> bool {
+        self.rtt_ms.is_some_and(|ms| ms > SLOW_RTT_MS * ratio)
+            || self
+                .send_latency_ms
+                .is_some_and(|ms| ms > SLOW_SEND_MS * ratio)
+            || self.error_rate() > MAX_ERROR_RATE * ratio
+    }
+
+    /// 重新评估降级状态，状态改变时返回新状态
+    pub fn evaluate(&mut self) -> Option<bool> {
+        let degraded = if self.degraded {
+            self.exceeds(RECOVERY_RATIO)
+        } else {
+            self.exceeds(1.0)
+        };
+        if degraded == self.degraded {
+            return None;
+        }
+        self.degraded = degraded;
+        Some(degraded)
+    }
+}
+
+/// 每个连接的统计，保存在 GlobalContext 中
+pub type PeerStatsTable = Arc<DashMap<SocketAddr, PeerStats>>;
+
+/// `status` 中的一行
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeerStatsEntry {
+    pub addr: SocketAddr,
+    #[serde(flatten)]
+    pub stats: PeerStats,
+    pub error_rate: f64,
+}
+
+async fn update(gctx: &Arc<GlobalContext>, addr: SocketAddr, f: impl FnOnce(&mut PeerStats)) {
+    let Some(table) = gctx.get::<PeerStatsTable>().await else {
+        return;
+    };
+    let (changed, stats) = {
+        let mut stats = table.entry(addr).or_default();
+        f(&mut stats);
+        (stats.evaluate(), *stats)
+    };
+    match changed {
+        Some(true) => tracing::warn!(
+            "🐢 Degrading slow peer {} (rtt={:?}ms send={:?}ms errors={:.0}%)",
+            addr,
+            stats.rtt_ms.map(|ms| ms.round()),
+            stats.send_latency_ms.map(|ms| ms.round()),
+            stats.error_rate() * 100.0
+        ),
+        Some(false) => tracing::info!("🐇 Peer {} recovered, resuming bulk traffic", addr),
+        None => {}
+    }
+}
+
+/// 记录一个收到的帧
+pub async fn record_inbound(ctx: &Arc<Mutex<Context>>) {
+    let (gctx, addr) = {
+        let guard = ctx.lock().await;
+        (guard.global.clone(), guard.addr)
+    };
+    update(&gctx, addr, |s| s.record_inbound()).await;
+    reachability::observe(&gctx, addr).await;
+}
+
+pub async fn record_sent(gctx: &Arc<GlobalContext>, addr: SocketAddr, latency: Duration, ok: bool) {
+    update(gctx, addr, |s| s.record_sent(latency, ok)).await;
+}
+
+pub async fn record_error(gctx: &Arc<GlobalContext>, addr: SocketAddr) {
+    update(gctx, addr, |s| s.record_error()).await;
+}
+
+pub async fn record_rtt(gctx: &Arc<GlobalContext>, addr: SocketAddr, rtt: Duration) {
+    update(gctx, addr, |s| s.record_rtt(rtt)).await;
+}
+
+/// 连接断开时清除统计
+pub async fn forget(gctx: &Arc<GlobalContext>, addr: SocketAddr) {
+    if let Some(table) = gctx.get::<PeerStatsTable>().await {
+        table.remove(&addr);
+    }
+}
+
+pub async fn is_degraded(gctx: &Arc<GlobalContext>, addr: &SocketAddr) -> bool {
+    match gctx.get::<PeerStatsTable>().await {
+        Some(table) => table.get(addr).is_some_and(|s| s.degraded),
+        None => false,
+    }
+}
+
+/// 从批量流量的目标中去掉降级的连接
+pub async fn retain_healthy(gctx: &Arc<GlobalContext>, targets: Vec<Target>) -> Vec<Target> {
+    let Some(table) = gctx.get::<PeerStatsTable>().await else {
+        return targets;
+    };
+    let before = targets.len();
+    let healthy: Vec<Target> = targets
+        .into_iter()
+        .filter(|t| !table.get(&t.addr).is_some_and(|s| s.degraded))
+        .collect();
+    if healthy.len() < before {
+        tracing::debug!(
+            "Skipped {} degraded peer(s) for bulk traffic",
+            before - healthy.len()
+        );
+    }
+    healthy
+}
+
+/// 按地址排序的统计
+pub async fn snapshot(gctx: &Arc<GlobalContext>) -> Vec<PeerStatsEntry> {
+    let Some(table) = gctx.get::<PeerStatsTable>().await else {
+        return vec![];
+    };
+    let mut entries: Vec<PeerStatsEntry> = table
+        .iter()
+        .map(|e| PeerStatsEntry {
+            addr: *e.key(),
+            stats: *e.value(),
+            error_rate: e.value().error_rate(),
+        })
+        .collect();
+    entries.sort_by_key(|e| e.addr);
+    entries
+}
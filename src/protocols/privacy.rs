@@ -0,0 +1,193 @@
+//! 端点可见范围：决定本节点的地址与 gossip 的 seed 端点可以告诉哪些对端
+//!
+//! 配置文件 `[privacy]` 为每条端点指定可见范围：
+//!
+//! - `public`：发给所有对端（默认）
+//! - `lan`：只发给内网对端（对端的连接地址属于内网，见 [`crate::ip_scope`]）
+//! - `private`：不发给任何对端
+//!
+//! 每条端点取 `rules` 中第一条匹配（IP、网段或该端点所属的节点地址）的规则，未匹配时内网端点
+//! 使用 `lan`、公网端点使用 `public`。Online / OnlineAck 中的本机地址与 seeds、seed 广播与增量、
+//! Tick 携带的见证环以及 seed sync 的种子集合都在发送前按接收方过滤；同一帧发给所有对端的广播与
+//! 泛洪的在线状态记录只包含 `public` 端点。
+//!
+//! ```toml
+//! [privacy]
+//! lan = "lan"
+//!
+//! [[privacy.rules]]
+//! target = "10.8.0.0/16"
+//! visibility = "private"
+//! ```
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use aex::connection::{global::GlobalContext, scope::NetworkScope};
+use serde::{Deserialize, Serialize};
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::{
+    config::SharedConfig,
+    ip_scope,
+    node::Node,
+    protocols::{
+        acl::AclTarget,
+        commands::{
+            ack::{SeedRecord, SeedsCommand},
+            observed,
+            seed_sync::SeedSet,
+        },
+    },
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    Private,
+    Lan,
+    #[default]
+    Public,
+}
+
+impl Visibility {
+    /// 能否发给连接地址为 `recipient` 的对端；`None` 表示同一帧发给所有对端
+    pub fn visible_to(self, recipient: Option<&IpAddr>) -> bool {
+        match self {
+            Visibility::Public => true,
+            Visibility::Lan => recipient.is_some_and(ip_scope::is_inner_ip),
+            Visibility::Private => false,
+        }
+    }
+}
+
+/// 一条覆盖规则：IP、网段或节点地址
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VisibilityRule {
+    pub target: AclTarget,
+    pub visibility: Visibility,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrivacyConfig {
+    /// 内网端点的默认可见范围
+    pub lan: Visibility,
+    /// 公网端点的默认可见范围
+    pub public: Visibility,
+    /// 按顺序匹配，第一条生效
+    pub rules: Vec<VisibilityRule>,
+}
+
+impl PrivacyConfig {
+    /// 节点 `node` 的端点 `ip` 的可见范围
+    pub fn visibility(&self, ip: &IpAddr, node: Option<&str>) -> Visibility {
+        self.rules
+            .iter()
+            .find(|rule| {
+                rule.target.matches_ip(ip) || node.is_some_and(|n| rule.target.matches_address(n))
+            })
+            .map(|rule| rule.visibility)
+            .unwrap_or(if ip_scope::is_inner_ip(ip) {
+                self.lan
+            } else {
+                self.public
+            })
+    }
+
+    pub fn shares(&self, ip: &IpAddr, node: Option<&str>, recipient: Option<&IpAddr>) -> bool {
+        self.visibility(ip, node).visible_to(recipient)
+    }
+
+    /// 过滤节点 `node` 的地址列表（Online 中的 `intranet_ips` / `wan_ips`），无法解析的地址不发送
+    pub fn filter_ips(
+        &self,
+        ips: Vec<String>,
+        node: &str,
+        recipient: Option<&IpAddr>,
+    ) -> Vec<String> {
+        ips.into_iter()
+            .filter(|ip| {
+                ip.parse::<IpAddr>()
+                    .is_ok_and(|ip| self.shares(&ip, Some(node), recipient))
+            })
+            .collect()
+    }
+
+    pub fn filter_seeds(
+        &self,
+        seeds: &[SeedRecord],
+        recipient: Option<&IpAddr>,
+    ) -> Vec<SeedRecord> {
+        seeds
+            .iter()
+            .filter(|seed| {
+                seed.address
+                    .parse::<SocketAddr>()
+                    .is_ok_and(|addr| self.shares(&addr.ip(), Some(&seed.node_address), recipient))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// 按接收方过滤 seeds，并重新计算摘要
+    pub fn filter_seeds_command(
+        &self,
+        seeds: &SeedsCommand,
+        recipient: Option<&IpAddr>,
+    ) -> SeedsCommand {
+        SeedsCommand::new(self.filter_seeds(&seeds.seeds, recipient))
+    }
+
+    /// 按接收方过滤 seed sync 的种子集合，并重新计算摘要
+    pub fn filter_seed_set(&self, set: &SeedSet, recipient: Option<&IpAddr>) -> SeedSet {
+        let mut filtered = set.clone();
+        filtered.seeds.retain(|seed| {
+            seed.socket_addr()
+                .is_some_and(|addr| self.shares(&addr.ip(), Some(&seed.node_id), recipient))
+        });
+        filtered.recalculate_hash();
+        filtered
+    }
+}
+
+/// 当前配置中的可见范围策略
+pub async fn policy(gctx: &GlobalContext) -> PrivacyConfig {
+    match gctx.get::<SharedConfig>().await {
+        Some(config) => config.read().await.privacy.clone(),
+        None => PrivacyConfig::default(),
+    }
+}
+
+/// 发给 `recipient` 的本机地址（`intranet_ips`, `wan_ips`），见 [`observed::announced_ips`]
+pub async fn announced_ips(
+    gctx: &Arc<GlobalContext>,
+    ips: &[(NetworkScope, IpAddr)],
+    recipient: &IpAddr,
+) -> (Vec<String>, Vec<String>) {
+    let (intranet, wan) = observed::announced_ips(gctx, ips).await;
+    let policy = policy(gctx).await;
+    let local = match gctx.get::<FreeWebMovementAddress>().await {
+        Some(address) => address.to_string(),
+        None => String::new(),
+    };
+    (
+        policy.filter_ips(intranet, &local, Some(recipient)),
+        policy.filter_ips(wan, &local, Some(recipient)),
+    )
+}
+
+/// 发给 `recipient` 的 seeds：路由表中验证通过、且对接收方可见的端点；
+/// `None` 表示同一帧发给所有对端
+pub async fn gossip_seeds(gctx: &GlobalContext, recipient: Option<&IpAddr>) -> SeedsCommand {
+    let Some(node) = gctx.get::<Arc<Node>>().await else {
+        return SeedsCommand::new(vec![]);
+    };
+    let records: Vec<SeedRecord> = node
+        .registry
+        .get_gossip_seeds()
+        .into_iter()
+        .map(|(s, na)| SeedRecord::new(s.to_string(), na))
+        .collect();
+    SeedsCommand::new(policy(gctx).await.filter_seeds(&records, recipient))
+}
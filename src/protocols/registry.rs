@@ -1,202 +1,1090 @@
 use aex::tcp::router::Router as TcpRouter;
+use futures::FutureExt;
 use futures::future::BoxFuture;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::Duration;
 use tokio::sync::Mutex;
 
 use aex::connection::context::Context;
 
+use crate::capture;
 use crate::protocols::{
+    acl,
+    bandwidth::throttle_inbound,
     command::{Action, Entity, P2PCommand},
     commands::{
         ack::onlineack_handler,
+        binary::binary_message_handler,
+        busy::busy_handler,
+        error::{self, ErrorCode, ErrorCommand, error_handler},
+        fragment::fragment_handler,
+        group::{
+            group_invite_handler, group_join_handler, group_key_handler, group_leave_handler,
+            group_message_handler, group_update_handler,
+        },
+        http_tunnel::{http_request_handler, http_response_handler},
+        identity::{self, identity_challenge_handler, identity_proof_handler},
         message::{message_ack_handler, message_handler},
+        naming::{name_answer_handler, name_publish_handler, name_query_handler},
         node_sync::{node_sync_handler, node_sync_response_handler},
+        observed::observed_address_handler,
         offline::offline_handler,
         online::online_handler,
+        ping::{ping_handler, pong_handler},
+        presence::presence_announce_handler,
+        rekey::{rekey_ack_handler, rekey_handler},
+        sealed::sealed_message_handler,
+        seed_delta::{seeds_delta_handler, seeds_resync_handler},
         seed_sync::{
             seed_sync_commit_handler, seed_sync_request_handler, seed_sync_response_handler,
         },
+        stream::{
+            stream_close_handler, stream_data_handler, stream_open_handler, stream_window_handler,
+        },
+        successor::{self, identity_successor_handler},
+        telephone::telephone_handler,
         tick::tick_handler,
+        topic::{publish_handler, subscription_handler},
         witness_validate::{witness_validate_ack_handler, witness_validate_handler},
     },
     frame::P2PFrame,
+    limits, peer_stats,
 };
 
-#[allow(dead_code)]
-type P2PDoer = Box<
+pub type P2PDoer = Box<
     dyn Fn(Arc<Mutex<Context>>, P2PFrame, P2PCommand) -> BoxFuture<'static, anyhow::Result<bool>>
         + Send
         + Sync
         + 'static,
 >;
 
-fn extract_p2p_cmd_id(cmd: &P2PCommand) -> u32 {
-    P2PCommand::to_u32(cmd.entity, cmd.action)
+/// router 只用一个 key，所有帧都交给 [`dispatch`] 按 [`HandlerKey`] 查找处理器
+const DISPATCH_KEY: u32 = 0;
+
+/// 单个处理器的默认执行时限
+pub const DEFAULT_HANDLER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 处理器的索引，`None` 表示通配
+///
+/// 查找时从最具体到最宽泛依次尝试：
+/// `(entity, action, version)` → `(entity, action, *)` → `(entity, *, version)` →
+/// `(entity, *, *)` → `(*, *, version)` → `(*, *, *)`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HandlerKey {
+    pub entity: Option<Entity>,
+    pub action: Option<Action>,
+    pub version: Option<u8>,
 }
 
-pub fn register(mut router: TcpRouter<P2PFrame, P2PCommand>) -> TcpRouter<P2PFrame, P2PCommand> {
-    router = router.extractor(extract_p2p_cmd_id);
+impl HandlerKey {
+    /// 匹配所有帧的兜底处理器
+    pub const ANY: HandlerKey = HandlerKey {
+        entity: None,
+        action: None,
+        version: None,
+    };
+
+    /// 任意协议版本的 `(entity, action)`
+    pub fn new(entity: Entity, action: Action) -> Self {
+        Self {
+            entity: Some(entity),
+            action: Some(action),
+            version: None,
+        }
+    }
+
+    /// 只匹配指定协议版本的 `(entity, action)`
+    pub fn versioned(entity: Entity, action: Action, version: u8) -> Self {
+        Self {
+            version: Some(version),
+            ..Self::new(entity, action)
+        }
+    }
+
+    /// 某个 entity 下所有 action 的兜底处理器
+    pub fn entity(entity: Entity) -> Self {
+        Self {
+            entity: Some(entity),
+            ..Self::ANY
+        }
+    }
+
+    /// 指定协议版本的兜底处理器
+    pub fn version(version: u8) -> Self {
+        Self {
+            version: Some(version),
+            ..Self::ANY
+        }
+    }
+
+    fn candidates(entity: Entity, action: Action, version: u8) -> [HandlerKey; 6] {
+        [
+            HandlerKey::versioned(entity, action, version),
+            HandlerKey::new(entity, action),
+            HandlerKey {
+                version: Some(version),
+                ..HandlerKey::entity(entity)
+            },
+            HandlerKey::entity(entity),
+            HandlerKey::version(version),
+            HandlerKey::ANY,
+        ]
+    }
+}
+
+struct Registered {
+    doer: Arc<P2PDoer>,
+    timeout: Duration,
+}
+
+/// 帧处理器表，支持运行期注册与注销
+///
+/// 每次分发都有独立的时限，处理器 panic 或超时只让这一帧失败，不会卡住后续分发。
+pub struct HandlerRegistry {
+    handlers: RwLock<HashMap<HandlerKey, Registered>>,
+}
+
+impl Default for HandlerRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HandlerRegistry {
+    pub fn new() -> Self {
+        Self {
+            handlers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 注册（或替换）处理器，使用默认时限；返回是否替换了已有处理器
+    pub fn register(&self, key: HandlerKey, doer: P2PDoer) -> bool {
+        self.register_with_timeout(key, DEFAULT_HANDLER_TIMEOUT, doer)
+    }
+
+    pub fn register_with_timeout(&self, key: HandlerKey, timeout: Duration, doer: P2PDoer) -> bool {
+        let registered = Registered {
+            doer: Arc::new(doer),
+            timeout,
+        };
+        self.handlers
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key, registered)
+            .is_some()
+    }
+
+    /// 注销处理器，返回是否存在
+    pub fn unregister(&self, key: &HandlerKey) -> bool {
+        self.handlers
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(key)
+            .is_some()
+    }
 
-    router.on(
-        P2PCommand::to_u32(Entity::Node, Action::OnLine),
+    pub fn keys(&self) -> Vec<HandlerKey> {
+        self.handlers
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .keys()
+            .copied()
+            .collect()
+    }
+
+    /// 一条帧会交给哪个处理器
+    pub fn resolve(&self, entity: Entity, action: Action, version: u8) -> Option<HandlerKey> {
+        let handlers = self.handlers.read().unwrap_or_else(|e| e.into_inner());
+        HandlerKey::candidates(entity, action, version)
+            .into_iter()
+            .find(|key| handlers.contains_key(key))
+    }
+
+    fn lookup(
+        &self,
+        entity: Entity,
+        action: Action,
+        version: u8,
+    ) -> Option<(Arc<P2PDoer>, Duration)> {
+        let handlers = self.handlers.read().unwrap_or_else(|e| e.into_inner());
+        HandlerKey::candidates(entity, action, version)
+            .iter()
+            .find_map(|key| handlers.get(key))
+            .map(|r| (r.doer.clone(), r.timeout))
+    }
+
+    /// 按 `(entity, action, version)` 分发；没有处理器时返回 `Ok(false)`，
+    /// 处理器 panic 或超时返回错误
+    pub async fn dispatch(
+        &self,
+        ctx: Arc<Mutex<Context>>,
+        frame: P2PFrame,
+        cmd: P2PCommand,
+    ) -> anyhow::Result<bool> {
+        let (entity, action, version) = (cmd.entity, cmd.action, frame.body.version);
+        let Some((doer, timeout)) = self.lookup(entity, action, version) else {
+            tracing::warn!("⚠️ No handler for {:?}/{:?} (v{})", entity, action, version);
+            return Ok(false);
+        };
+        let run = AssertUnwindSafe(async move { doer(ctx, frame, cmd).await }).catch_unwind();
+        match tokio::time::timeout(timeout, run).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => {
+                tracing::error!(
+                    "Handler for {:?}/{:?} (v{}) panicked",
+                    entity,
+                    action,
+                    version
+                );
+                Err(anyhow::anyhow!(
+                    "Handler for {:?}/{:?} panicked",
+                    entity,
+                    action
+                ))
+            }
+            Err(_) => {
+                tracing::warn!(
+                    "Handler for {:?}/{:?} (v{}) timed out after {:?}",
+                    entity,
+                    action,
+                    version,
+                    timeout
+                );
+                Err(anyhow::anyhow!(
+                    "Handler for {:?}/{:?} timed out",
+                    entity,
+                    action
+                ))
+            }
+        }
+    }
+}
+
+/// 全局处理器表，初始为内置处理器
+static HANDLERS: LazyLock<HandlerRegistry> = LazyLock::new(|| {
+    let registry = HandlerRegistry::new();
+    for (key, doer) in routes() {
+        registry.register(key, doer);
+    }
+    registry
+});
+
+/// 全局处理器表，可在运行期注册额外的（按版本、通配）处理器或注销内置处理器
+pub fn handlers() -> &'static HandlerRegistry {
+    &HANDLERS
+}
+
+fn routes() -> HashMap<HandlerKey, P2PDoer> {
+    let mut routes: HashMap<HandlerKey, P2PDoer> = HashMap::new();
+
+    routes.insert(
+        HandlerKey::new(Entity::Node, Action::OnLine),
         Box::new(|ctx, _frame, cmd: P2PCommand| {
             let c = cmd.clone();
             Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
                 online_handler(ctx, _frame, c).await;
                 Ok(true)
             })
         }),
-        vec![],
     );
 
-    router.on(
-        P2PCommand::to_u32(Entity::Node, Action::OffLine),
+    routes.insert(
+        HandlerKey::new(Entity::Node, Action::OffLine),
         Box::new(|ctx, _frame, cmd: P2PCommand| {
             let c = cmd.clone();
             Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
                 offline_handler(ctx, _frame, c).await;
                 Ok(true)
             })
         }),
-        vec![],
     );
 
-    router.on(
-        P2PCommand::to_u32(Entity::Node, Action::OnLineAck),
+    routes.insert(
+        HandlerKey::new(Entity::Node, Action::OnLineAck),
         Box::new(|ctx, _frame, cmd: P2PCommand| {
             let c = cmd.clone();
             Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
                 onlineack_handler(ctx, _frame, c).await;
                 Ok(true)
             })
         }),
-        vec![],
     );
 
-    router.on(
-        P2PCommand::to_u32(Entity::Message, Action::SendText),
+    routes.insert(
+        HandlerKey::new(Entity::Message, Action::SendText),
         Box::new(|ctx, _frame, cmd: P2PCommand| {
             let c = cmd.clone();
             Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
                 message_handler(ctx, _frame, c).await;
                 Ok(true)
             })
         }),
-        vec![],
     );
 
-    router.on(
-        P2PCommand::to_u32(Entity::Message, Action::MessageAck),
+    routes.insert(
+        HandlerKey::new(Entity::Message, Action::SendBinary),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                binary_message_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Message, Action::MessageAck),
         Box::new(|ctx, _frame, cmd: P2PCommand| {
             let c = cmd.clone();
             Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
                 message_ack_handler(ctx, _frame, c).await;
                 Ok(true)
             })
         }),
-        vec![],
     );
 
-    router.on(
-        P2PCommand::to_u32(Entity::Witness, Action::Tick),
+    routes.insert(
+        HandlerKey::new(Entity::Message, Action::SendEncrypted),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                sealed_message_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Witness, Action::Tick),
         Box::new(|ctx, frame, cmd: P2PCommand| {
             let c = cmd.clone();
             Box::pin(async move {
+                if !acl::admit_frame(&ctx, &frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
                 tick_handler(ctx, frame, c).await;
                 Ok(true)
             })
         }),
-        vec![],
     );
 
-    router.on(
-        P2PCommand::to_u32(Entity::Witness, Action::Validate),
+    routes.insert(
+        HandlerKey::new(Entity::Witness, Action::Validate),
         Box::new(|ctx, frame, cmd: P2PCommand| {
             let c = cmd.clone();
             Box::pin(async move {
+                if !acl::admit_frame(&ctx, &frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
                 witness_validate_handler(ctx, frame, c).await;
                 Ok(true)
             })
         }),
-        vec![],
     );
 
-    router.on(
-        P2PCommand::to_u32(Entity::Witness, Action::ValidateAck),
+    routes.insert(
+        HandlerKey::new(Entity::Witness, Action::ValidateAck),
         Box::new(|ctx, frame, cmd: P2PCommand| {
             let c = cmd.clone();
             Box::pin(async move {
+                if !acl::admit_frame(&ctx, &frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
                 witness_validate_ack_handler(ctx, frame, c).await;
                 Ok(true)
             })
         }),
-        vec![],
     );
 
     // 注册节点同步处理器
-    router.on(
-        P2PCommand::to_u32(Entity::Node, Action::NodeSyncRequest),
+    routes.insert(
+        HandlerKey::new(Entity::Node, Action::NodeSyncRequest),
         Box::new(|ctx, _frame, cmd: P2PCommand| {
             let c = cmd.clone();
             Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
                 node_sync_handler(ctx, _frame, c).await;
                 Ok(true)
             })
         }),
-        vec![],
     );
 
-    router.on(
-        P2PCommand::to_u32(Entity::Node, Action::NodeSyncResponse),
+    routes.insert(
+        HandlerKey::new(Entity::Node, Action::NodeSyncResponse),
         Box::new(|ctx, _frame, cmd: P2PCommand| {
             let c = cmd.clone();
             Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
                 node_sync_response_handler(ctx, _frame, c).await;
                 Ok(true)
             })
         }),
-        vec![],
     );
 
-    router.on(
-        P2PCommand::to_u32(Entity::Node, Action::SeedSyncRequest),
+    routes.insert(
+        HandlerKey::new(Entity::Node, Action::SeedSyncRequest),
         Box::new(|ctx, _frame, cmd: P2PCommand| {
             let c = cmd.clone();
             Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
                 seed_sync_request_handler(ctx, _frame, c).await;
                 Ok(true)
             })
         }),
-        vec![],
     );
 
-    router.on(
-        P2PCommand::to_u32(Entity::Node, Action::SeedSyncResponse),
+    routes.insert(
+        HandlerKey::new(Entity::Node, Action::SeedSyncResponse),
         Box::new(|ctx, _frame, cmd: P2PCommand| {
             let c = cmd.clone();
             Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
                 seed_sync_response_handler(ctx, _frame, c).await;
                 Ok(true)
             })
         }),
-        vec![],
     );
 
-    router.on(
-        P2PCommand::to_u32(Entity::Node, Action::SeedSyncCommit),
+    routes.insert(
+        HandlerKey::new(Entity::Node, Action::SeedSyncCommit),
         Box::new(|ctx, _frame, cmd: P2PCommand| {
             let c = cmd.clone();
             Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
                 seed_sync_commit_handler(ctx, _frame, c).await;
                 Ok(true)
             })
         }),
-        vec![],
     );
 
-    tracing::info!(
-        "Registered handler keys: {:?}",
-        router.handlers.keys().collect::<Vec<_>>()
+    routes.insert(
+        HandlerKey::new(Entity::Node, Action::Ping),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                ping_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
     );
+
+    routes.insert(
+        HandlerKey::new(Entity::Node, Action::Pong),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                pong_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    for action in [Action::Subscribe, Action::Unsubscribe] {
+        routes.insert(
+            HandlerKey::new(Entity::Topic, action),
+            Box::new(|ctx, _frame, cmd: P2PCommand| {
+                let c = cmd.clone();
+                Box::pin(async move {
+                    if !acl::admit_frame(&ctx, &_frame, &c).await {
+                        return Ok(true);
+                    }
+                    throttle_inbound(&ctx, c.data.len()).await;
+                    subscription_handler(ctx, _frame, c).await;
+                    Ok(true)
+                })
+            }),
+        );
+    }
+
+    routes.insert(
+        HandlerKey::new(Entity::Topic, Action::Publish),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                publish_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Node, Action::Rekey),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                rekey_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Node, Action::RekeyAck),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                rekey_ack_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Node, Action::Busy),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                busy_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Node, Action::ObservedAddress),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                observed_address_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Node, Action::IdentityChallenge),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                identity_challenge_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Node, Action::IdentityProof),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                identity_proof_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Telephone, Action::Call),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                telephone_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Telephone, Action::Accept),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                telephone_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Telephone, Action::Reject),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                telephone_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Telephone, Action::HangUp),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                telephone_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Node, Action::Fragment),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                fragment_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Name, Action::NamePublish),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                name_publish_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Name, Action::NameQuery),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                name_query_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Name, Action::NameAnswer),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                name_answer_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Presence, Action::PresenceAnnounce),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                presence_announce_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Http, Action::HttpRequest),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                http_request_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Http, Action::HttpResponse),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                http_response_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Stream, Action::StreamOpen),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                stream_open_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Stream, Action::StreamData),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                stream_data_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Stream, Action::StreamWindow),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                stream_window_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Stream, Action::StreamClose),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                stream_close_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Node, Action::SeedsDelta),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                seeds_delta_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Node, Action::SeedsResync),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                seeds_resync_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Group, Action::GroupInvite),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                group_invite_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Group, Action::GroupJoin),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                group_join_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Group, Action::GroupLeave),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                group_leave_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Group, Action::GroupUpdate),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                group_update_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Group, Action::GroupKey),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                group_key_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Group, Action::GroupMessage),
+        Box::new(|ctx, _frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &_frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                group_message_handler(ctx, _frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Error, Action::Error),
+        Box::new(|ctx, frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                error_handler(ctx, frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes.insert(
+        HandlerKey::new(Entity::Node, Action::IdentitySuccessor),
+        Box::new(|ctx, frame, cmd: P2PCommand| {
+            let c = cmd.clone();
+            Box::pin(async move {
+                if !acl::admit_frame(&ctx, &frame, &c).await {
+                    return Ok(true);
+                }
+                throttle_inbound(&ctx, c.data.len()).await;
+                identity_successor_handler(ctx, frame, c).await;
+                Ok(true)
+            })
+        }),
+    );
+
+    routes
+}
+
+pub fn register(mut router: TcpRouter<P2PFrame, P2PCommand>) -> TcpRouter<P2PFrame, P2PCommand> {
+    router = router.extractor(|_: &P2PCommand| DISPATCH_KEY);
+
+    let doer: P2PDoer = Box::new(|ctx, frame, cmd| Box::pin(dispatch(ctx, frame, cmd)));
+    router.on(DISPATCH_KEY, doer, vec![]);
+
+    tracing::info!("Registered {} frame handlers", HANDLERS.keys().len());
     router
 }
+
+/// 计入对端统计、记录到抓包文件（开启 `--capture` 时），再交给全局处理器表；
+/// router 与分片重组后的帧都经过这里。没有处理器或处理器失败时向发送方回复错误
+pub async fn dispatch(
+    ctx: Arc<Mutex<Context>>,
+    frame: P2PFrame,
+    cmd: P2PCommand,
+) -> anyhow::Result<bool> {
+    peer_stats::record_inbound(&ctx).await;
+    capture::inbound(&ctx, &frame).await;
+    // 超过 `[limits]` 消息大小或每秒消息数的帧
+    if !limits::admit_message(&ctx, &frame).await {
+        return Ok(true);
+    }
+    // 已轮换身份且宽限期已过的旧地址
+    if !successor::admit(&ctx, &frame).await {
+        return Ok(true);
+    }
+    // 未通过身份验证的连接只接受握手命令
+    if !identity::admit(&ctx, &frame, &cmd).await {
+        return Ok(true);
+    }
+    let (origin, nonce) = (frame.body.address.clone(), frame.body.nonce);
+    let (entity, action) = (cmd.entity, cmd.action);
+    let result = HANDLERS.dispatch(ctx.clone(), frame, cmd).await;
+    let (code, detail) = match &result {
+        Ok(true) => return result,
+        Ok(false) => (
+            ErrorCode::Unsupported,
+            format!("no handler for {:?}/{:?}", entity, action),
+        ),
+        Err(e) => (ErrorCode::Internal, e.to_string()),
+    };
+    let err = ErrorCommand {
+        receiver: origin,
+        code,
+        nonce,
+        entity: Some(entity),
+        action: Some(action),
+        detail,
+    };
+    error::reply(ctx, err).await;
+    result
+}
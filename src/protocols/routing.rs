@@ -0,0 +1,412 @@
+//! 多跳路由
+//!
+//! 路由表记录「目标节点地址 → 下一跳（直连节点地址）」，从 Online / OnlineAck 握手中学习：
+//! 对端本身为 1 跳，对端公告的 seeds 中的节点为 2 跳。
+//! 带 `destination` 的帧到达非目标节点时，按 (sender, nonce) 去重（见 [`super::dedup`]）、递减 TTL 后
+//! 只转发给跳数最少的若干个下一跳；没有路由时退化为向除来源外的所有连接转发，
+//! 大帧只转发给其中最佳的若干个（见 [`crate::fanout`]）。
+//! 转发的帧保留作者签名，按 `[signing]` 策略附加或去掉本节点的逐跳签名（见 [`super::signing`]）。
+//! 不带 `destination` 的扇出（如主题发布）由调用方选出直连节点，经 [`forward_frame`] 同样原样转发。
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use aex::connection::context::Context;
+use aex::connection::global::GlobalContext;
+use aex::tcp::types::Codec;
+use aex::time::SystemTime;
+use bytes::Bytes;
+use dashmap::DashMap;
+use tokio::sync::Mutex;
+
+use crate::fanout;
+use crate::identities;
+use crate::journal;
+use crate::node::Node as P2pNode;
+use crate::protocols::broadcast;
+use crate::protocols::capabilities::{self, CAP_RELAY};
+use crate::protocols::commands::error::{self as remote_error, ErrorCode, ErrorCommand};
+use crate::protocols::dedup;
+use crate::protocols::frame::P2PFrame;
+use crate::protocols::lanes::Lane;
+use crate::protocols::peer_stats;
+use crate::protocols::signing;
+
+/// 新建帧的默认 TTL
+pub const DEFAULT_FRAME_TTL: u8 = 8;
+/// 每个目标最多转发给几个下一跳
+pub const MAX_NEXT_HOPS: usize = 2;
+/// 路由条目有效期
+pub const ROUTE_EXPIRY_MS: u128 = 10 * 60 * 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    /// 下一跳：直连节点地址
+    pub next_hop: String,
+    pub hops: u8,
+    pub updated_at: u128,
+}
+
+/// 路由表：目标节点地址 → 候选路由
+pub type RoutingTable = Arc<DashMap<String, Vec<Route>>>;
+
+/// 学习一条路由；同一下一跳只保留跳数最少的一条
+pub fn learn(table: &RoutingTable, destination: &str, next_hop: &str, hops: u8) {
+    let now = SystemTime::timestamp();
+    let mut routes = table.entry(destination.to_string()).or_default();
+    match routes.iter_mut().find(|r| r.next_hop == next_hop) {
+        Some(route) => {
+            if hops <= route.hops || now.saturating_sub(route.updated_at) > ROUTE_EXPIRY_MS {
+                route.hops = hops;
+            }
+            route.updated_at = now;
+        }
+        None => routes.push(Route {
+            next_hop: next_hop.to_string(),
+            hops,
+            updated_at: now,
+        }),
+    }
+}
+
+/// 从一次握手中学习路由：对端直连，其公告的节点经由对端可达
+pub fn learn_from_handshake<'a>(
+    table: &RoutingTable,
+    local: &str,
+    neighbor: &str,
+    announced: impl IntoIterator<Item = &'a str>,
+) {
+    learn(table, neighbor, neighbor, 1);
+    for destination in announced {
+        if destination != local && destination != neighbor {
+            learn(table, destination, neighbor, 2);
+        }
+    }
+}
+
+/// 断开连接后移除经由该节点的所有路由
+pub fn forget_neighbor(table: &RoutingTable, neighbor: &str) {
+    table.retain(|_, routes| {
+        routes.retain(|r| r.next_hop != neighbor);
+        !routes.is_empty()
+    });
+}
+
+/// 节点换用新地址：到旧地址的路由改记在新地址下，经由旧地址的路由改为经由新地址
+pub fn rename(table: &RoutingTable, old: &str, new: &str) {
+    let moved = table
+        .remove(old)
+        .map(|(_, routes)| routes)
+        .unwrap_or_default();
+    for mut entry in table.iter_mut() {
+        let routes = entry.value_mut();
+        if routes.iter().any(|r| r.next_hop == new) {
+            routes.retain(|r| r.next_hop != old);
+        } else {
+            for route in routes.iter_mut().filter(|r| r.next_hop == old) {
+                route.next_hop = new.to_string();
+            }
+        }
+    }
+    for route in moved {
+        let next_hop = if route.next_hop == old {
+            new
+        } else {
+            &route.next_hop
+        };
+        learn(table, new, next_hop, route.hops);
+    }
+}
+
+/// 到达目标的最佳下一跳（按跳数升序，最多 MAX_NEXT_HOPS 个，忽略过期条目）
+pub fn next_hops(table: &RoutingTable, destination: &str, now: u128) -> Vec<String> {
+    let Some(routes) = table.get(destination) else {
+        return vec![];
+    };
+    let mut fresh: Vec<&Route> = routes
+        .iter()
+        .filter(|r| now.saturating_sub(r.updated_at) <= ROUTE_EXPIRY_MS)
+        .collect();
+    fresh.sort_by_key(|r| r.hops);
+    fresh
+        .into_iter()
+        .take(MAX_NEXT_HOPS)
+        .map(|r| r.next_hop.clone())
+        .collect()
+}
+
+/// 经路由表找到通往目标的直连连接（用于发往未直连的地址）
+pub async fn route_context(
+    gctx: &Arc<GlobalContext>,
+    destination: &str,
+) -> Option<Arc<Mutex<Context>>> {
+    let table = gctx.get::<RoutingTable>().await?;
+    let node = gctx.get::<Arc<P2pNode>>().await?;
+    next_hops(&table, destination, SystemTime::timestamp())
+        .iter()
+        .flat_map(|hop| node.registry.get_seeds_for_node(hop))
+        .find_map(|addr| {
+            gctx.manager
+                .find_entry(&addr)
+                .and_then(|entry| entry.context.clone())
+        })
+}
+
+/// 找到通往 `receiver` 的连接：直连目标须支持 `required`，经由对端中继时对端须支持中继
+pub async fn connection_to(
+    gctx: &Arc<GlobalContext>,
+    receiver: &str,
+    required: u32,
+) -> anyhow::Result<Arc<Mutex<Context>>> {
+    let found: Arc<Mutex<Option<Arc<Mutex<Context>>>>> = Arc::new(Mutex::new(None));
+    let found_in_closure = found.clone();
+    gctx.manager
+        .notify(receiver.as_bytes(), |entries| async move {
+            *found_in_closure.lock().await = entries.into_iter().find_map(|e| e.context.clone());
+        })
+        .await;
+    let found = found.lock().await.take();
+    let ctx = match found {
+        Some(ctx) => ctx,
+        None => match route_context(gctx, receiver).await {
+            Some(ctx) => ctx,
+            None => anyhow::bail!("Peer {} is not connected", receiver),
+        },
+    };
+    let peer: Option<String> = ctx.lock().await.get();
+    let required = match peer {
+        Some(peer) if peer != receiver => CAP_RELAY,
+        _ => required,
+    };
+    if !capabilities::peer_supports(&ctx, required).await {
+        anyhow::bail!(
+            "Peer does not support {}",
+            capabilities::feature_names(required).join(",")
+        );
+    }
+    Ok(ctx)
+}
+
+/// 直连节点 `neighbor` 的所有连接
+pub async fn neighbor_targets(gctx: &Arc<GlobalContext>, neighbor: &str) -> Vec<broadcast::Target> {
+    let Some(node) = gctx.get::<Arc<P2pNode>>().await else {
+        return vec![];
+    };
+    let entries = node
+        .registry
+        .get_seeds_for_node(neighbor)
+        .iter()
+        .filter_map(|addr| gctx.manager.find_entry(addr))
+        .collect();
+    broadcast::all_peers(entries)
+}
+
+/// 把帧原样转发给直连节点 `neighbors`：保留作者签名，递减 TTL 并按 `[signing]` 策略处理逐跳签名，
+/// 跳过来源连接与降级的慢对端。返回写出的连接数
+pub async fn forward_frame(
+    gctx: &Arc<GlobalContext>,
+    frame: &P2PFrame,
+    origin: Option<&Arc<Mutex<Context>>>,
+    neighbors: &[String],
+) -> usize {
+    if neighbors.is_empty() {
+        return 0;
+    }
+    if frame.ttl <= 1 {
+        tracing::debug!(
+            "TTL expired for frame from {}, not forwarding",
+            frame.body.address
+        );
+        return 0;
+    }
+    let mut forwarded = frame.clone();
+    forwarded.ttl -= 1;
+    signing::prepare_relay(gctx, &mut forwarded).await;
+    let bytes = match Codec::encode(&forwarded) {
+        Ok(b) => Bytes::from(b),
+        Err(e) => {
+            tracing::error!("Failed to encode frame for forwarding: {:?}", e);
+            return 0;
+        }
+    };
+
+    let mut targets = Vec::new();
+    for neighbor in neighbors {
+        targets.extend(neighbor_targets(gctx, neighbor).await);
+    }
+    if let Some(origin) = origin {
+        targets.retain(|t| !Arc::ptr_eq(&t.ctx, origin));
+    }
+    let targets = peer_stats::retain_healthy(gctx, targets).await;
+    if targets.is_empty() {
+        return 0;
+    }
+    let report = broadcast::write_all(gctx, targets, bytes, Lane::Messaging).await;
+    report.log("forward");
+    report.sent()
+}
+
+/// (sender, nonce) 去重：返回 true 表示首次见到
+async fn first_relay(gctx: &GlobalContext, frame: &P2PFrame) -> bool {
+    let key = format!("relay:{}:{}", frame.body.address, frame.body.nonce);
+    dedup::first_forward(gctx, &key).await
+}
+
+/// 若帧的目标不是本节点则中继转发并返回 true；目标是本节点（或无目标）返回 false
+pub async fn relay_if_not_for_us(ctx: Arc<Mutex<Context>>, frame: &P2PFrame) -> bool {
+    let Some(destination) = frame.body.destination.clone() else {
+        return false;
+    };
+    let gctx = {
+        let guard = ctx.lock().await;
+        guard.global.clone()
+    };
+    // 主身份与附加身份都在本节点处理
+    if identities::is_local(&gctx, &destination).await {
+        return false;
+    }
+    relay(gctx, ctx, frame, &destination).await;
+    true
+}
+
+async fn relay(
+    gctx: Arc<GlobalContext>,
+    origin: Arc<Mutex<Context>>,
+    frame: &P2PFrame,
+    destination: &str,
+) {
+    if !first_relay(&gctx, frame).await {
+        return;
+    }
+    let command = frame.body.command_as(frame.format).ok();
+    if frame.ttl <= 1 {
+        tracing::info!(
+            "  ⏹️  TTL expired for frame {}→{}, dropping",
+            frame.body.address,
+            destination
+        );
+        let err = ErrorCommand::about(frame, command.as_ref(), ErrorCode::TtlExpired, destination);
+        remote_error::reply(origin, err).await;
+        return;
+    }
+
+    let mut forwarded = frame.clone();
+    forwarded.ttl -= 1;
+    signing::prepare_relay(&gctx, &mut forwarded).await;
+    let bytes = match Codec::encode(&forwarded) {
+        Ok(b) => Bytes::from(b),
+        Err(e) => {
+            tracing::error!("Failed to encode frame for relay: {:?}", e);
+            return;
+        }
+    };
+    // 记账并按发送方的配额限速（中继模式）
+    if !crate::relay::admit(&gctx, &frame.body.address, destination, bytes.len()).await {
+        let err = ErrorCommand::about(
+            frame,
+            command.as_ref(),
+            ErrorCode::QuotaExceeded,
+            destination,
+        );
+        remote_error::reply(origin, err).await;
+        return;
+    }
+
+    let hops = match gctx.get::<RoutingTable>().await {
+        Some(table) => next_hops(&table, destination, SystemTime::timestamp()),
+        None => vec![],
+    };
+    let node = gctx.get::<Arc<P2pNode>>().await;
+
+    // 有路由时只发往最佳下一跳
+    let mut targets = Vec::new();
+    if let Some(node) = node.as_ref() {
+        for hop in hops.iter().filter(|h| *h != &frame.body.address) {
+            let entry = node
+                .registry
+                .get_seeds_for_node(hop)
+                .iter()
+                .find_map(|addr| gctx.manager.find_entry(addr));
+            let Some(entry) = entry else {
+                continue;
+            };
+            for target in broadcast::all_peers(vec![entry]) {
+                if hop == destination {
+                    targets.push(target);
+                    continue;
+                }
+                // 下一跳不是最终接收方时须声明支持中继，且不是降级的慢对端
+                if capabilities::peer_supports(&target.ctx, CAP_RELAY).await
+                    && !peer_stats::is_degraded(&gctx, &target.addr).await
+                {
+                    targets.push(target);
+                }
+            }
+        }
+    }
+    targets.retain(|t| !Arc::ptr_eq(&t.ctx, &origin));
+    if !targets.is_empty() {
+        let report = broadcast::write_all(&gctx, targets, bytes, Lane::Messaging).await;
+        report.log("relay");
+        tracing::info!(
+            "  🔀 Relayed frame {}→{} via {} next hop(s), ttl={}",
+            frame.body.address,
+            destination,
+            report.sent(),
+            forwarded.ttl
+        );
+        let event = journal::Event::MessageForwarded {
+            from: frame.body.address.clone(),
+            to: destination.to_string(),
+            next_hops: report.sent(),
+            ttl: forwarded.ttl,
+        };
+        journal::record(&gctx, event).await;
+        return;
+    }
+
+    // 无可用路由：向除来源外的每个节点转发一次
+    let gctx_for_send = gctx.clone();
+    let sender = frame.body.address.clone();
+    let entity = command.as_ref().map(|c| c.entity);
+    let flooded = Arc::new(AtomicUsize::new(0));
+    let flooded_in = flooded.clone();
+    let flood_origin = origin.clone();
+    gctx.manager
+        .clone()
+        .forward(|entries| async move {
+            let targets =
+                broadcast::unique_peers(entries, Some(&flood_origin), Some(&sender)).await;
+            let targets = peer_stats::retain_healthy(&gctx_for_send, targets).await;
+            // 大帧只发给最佳的若干个节点
+            let targets = fanout::limit(&gctx_for_send, entity, bytes.len(), targets).await;
+            let report =
+                broadcast::write_all(&gctx_for_send, targets, bytes.clone(), Lane::Messaging).await;
+            report.log("flood");
+            flooded_in.store(report.sent(), Ordering::Relaxed);
+        })
+        .await;
+    tracing::info!(
+        "  🌊 No route to {}, flooded frame from {} (ttl={})",
+        destination,
+        frame.body.address,
+        forwarded.ttl
+    );
+    let next_hops = flooded.load(Ordering::Relaxed);
+    let event = journal::Event::MessageForwarded {
+        from: frame.body.address.clone(),
+        to: destination.to_string(),
+        next_hops,
+        ttl: forwarded.ttl,
+    };
+    journal::record(&gctx, event).await;
+    if next_hops == 0 {
+        let err = ErrorCommand::about(
+            frame,
+            command.as_ref(),
+            ErrorCode::UnknownReceiver,
+            destination,
+        );
+        remote_error::reply(origin, err).await;
+    }
+}
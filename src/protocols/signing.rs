@@ -0,0 +1,87 @@
+//! 帧签名策略：端到端与逐跳
+//!
+//! 每个帧都带有作者（`body.address`）对 body 的签名，中继节点原样转发 body 与签名，
+//! 接收方因此总能验证消息的真实来源。TTL 不在作者签名之内，任何中继都可以修改它。
+//!
+//! - `end_to_end`（默认）：转发时只保留作者签名，去掉上一跳附带的逐跳签名；
+//! - `layered`：转发 v3 帧时，本节点在递减 TTL 之后附加自己的逐跳签名
+//!   （[`HopSignature`]，覆盖作者签名与新的 TTL），下一跳可以确认帧由哪个邻居转发。
+//!
+//! 两层签名都在 [`P2PFrame::verify`] 与帧校验中检查：逐跳签名存在但无效的帧与作者签名
+//! 无效的帧一样被拒绝（`bad_hop_signature`）。没有逐跳签名的帧（直连发送、v1/v2 帧、
+//! `end_to_end` 节点转发的帧）照常接受，因此两种策略的节点可以混合部署。
+//!
+//! ```toml
+//! [signing]
+//! policy = "layered"
+//! ```
+
+use aex::connection::global::GlobalContext;
+use serde::{Deserialize, Serialize};
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::{
+    config::SharedConfig,
+    protocols::frame::{HopSignature, P2PFrame},
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SigningPolicy {
+    /// 只有作者签名
+    #[default]
+    EndToEnd,
+    /// 作者签名 + 转发节点的逐跳签名
+    Layered,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SigningConfig {
+    pub policy: SigningPolicy,
+}
+
+/// 当前配置中的签名策略
+pub async fn policy(gctx: &GlobalContext) -> SigningPolicy {
+    match gctx.get::<SharedConfig>().await {
+        Some(config) => config.read().await.signing.policy,
+        None => SigningPolicy::default(),
+    }
+}
+
+/// 按策略处理即将转发的帧（TTL 已递减）：`layered` 时以 `signer` 重新签逐跳签名，
+/// 否则去掉上一跳的签名（TTL 改变后它已失效）。返回是否附加了逐跳签名
+pub fn prepare_forward(
+    frame: &mut P2PFrame,
+    policy: SigningPolicy,
+    signer: Option<&FreeWebMovementAddress>,
+) -> bool {
+    frame.hop = None;
+    if policy != SigningPolicy::Layered || !frame.body.has_hop_signature() {
+        return false;
+    }
+    let Some(signer) = signer else {
+        return false;
+    };
+    match frame.sign_hop(signer) {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::warn!(
+                "Failed to add hop signature to frame from {}: {}",
+                frame.body.address,
+                e
+            );
+            false
+        }
+    }
+}
+
+/// 中继前调用，见 [`prepare_forward`]
+pub async fn prepare_relay(gctx: &GlobalContext, frame: &mut P2PFrame) -> bool {
+    let policy = policy(gctx).await;
+    let signer = match policy {
+        SigningPolicy::Layered => gctx.get::<FreeWebMovementAddress>().await,
+        SigningPolicy::EndToEnd => None,
+    };
+    prepare_forward(frame, policy, signer.as_ref())
+}
@@ -0,0 +1,110 @@
+//! 线路协议版本
+//!
+//! 未压缩帧的首字节即 `FrameBody.version`，决定帧的线路布局：
+//!
+//! | 版本 | FrameBody                         | P2PFrame           |
+//! |------|-----------------------------------|--------------------|
+//! | v1   | 基础字段                          | body + signature   |
+//! | v2   | 基础字段 + destination            | + ttl（多跳中继）  |
+//! | v3   | 同 v2                             | + 可选的逐跳签名   |
+//!
+//! 签名覆盖按该版本布局编码的 body，因此同一帧在任何节点上的编码都一致。
+//! v3 帧经中继时可以附带转发节点的逐跳签名，见 [`crate::protocols::signing`]。
+//! 握手时双方在 `OnlineCommand` / `OnlineAckCommand` 中声明各自支持的最高版本，
+//! 取两者较小值作为该连接的发送版本（`PeerVersion`）；接收端按帧首字节解析任何受支持的版本。
+
+use std::sync::Arc;
+
+use aex::connection::context::Context;
+use tokio::sync::Mutex;
+
+use crate::journal;
+use crate::protocols::error::{self, ProtocolError};
+
+pub const PROTOCOL_V1: u8 = 1;
+pub const PROTOCOL_V2: u8 = 2;
+pub const PROTOCOL_V3: u8 = 3;
+/// 本节点支持的最低版本
+pub const MIN_PROTOCOL_VERSION: u8 = PROTOCOL_V1;
+/// 本节点支持的最高版本，也是握手完成前使用的版本
+pub const CURRENT_PROTOCOL_VERSION: u8 = PROTOCOL_V3;
+
+/// 某个协议版本的线路布局
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WireLayout {
+    pub version: u8,
+    /// FrameBody 携带最终接收方
+    pub has_destination: bool,
+    /// P2PFrame 携带剩余跳数
+    pub has_ttl: bool,
+    /// P2PFrame 携带可选的逐跳签名
+    pub has_hop_signature: bool,
+}
+
+/// 已知版本的布局表
+pub const WIRE_LAYOUTS: &[WireLayout] = &[
+    WireLayout {
+        version: PROTOCOL_V1,
+        has_destination: false,
+        has_ttl: false,
+        has_hop_signature: false,
+    },
+    WireLayout {
+        version: PROTOCOL_V2,
+        has_destination: true,
+        has_ttl: true,
+        has_hop_signature: false,
+    },
+    WireLayout {
+        version: PROTOCOL_V3,
+        has_destination: true,
+        has_ttl: true,
+        has_hop_signature: true,
+    },
+];
+
+/// 查找版本对应的布局；不支持的版本返回 `UnsupportedVersion`
+pub fn layout(version: u8) -> Result<&'static WireLayout, ProtocolError> {
+    if !(MIN_PROTOCOL_VERSION..=CURRENT_PROTOCOL_VERSION).contains(&version) {
+        return Err(ProtocolError::UnsupportedVersion { version });
+    }
+    WIRE_LAYOUTS
+        .iter()
+        .find(|l| l.version == version)
+        .ok_or(ProtocolError::UnsupportedVersion { version })
+}
+
+/// 协商双方共同支持的最高版本；对端声明的最高版本为 0 时视为 v1（升级前的节点）
+pub fn negotiate(peer_max: u8) -> Result<u8, ProtocolError> {
+    let peer_max = peer_max.max(PROTOCOL_V1);
+    let version = peer_max.min(CURRENT_PROTOCOL_VERSION);
+    layout(version)?;
+    Ok(version)
+}
+
+/// 与对端协商出的发送版本，保存在连接 Context 中
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerVersion(pub u8);
+
+impl Default for PeerVersion {
+    fn default() -> Self {
+        PeerVersion(CURRENT_PROTOCOL_VERSION)
+    }
+}
+
+/// 握手时根据对端声明的最高版本设置该连接的发送版本；无法协商时记录错误并返回 false
+pub async fn negotiate_version(ctx: &Arc<Mutex<Context>>, peer: &str, peer_max: u8) -> bool {
+    match negotiate(peer_max) {
+        Ok(version) => {
+            tracing::debug!("Protocol version with {}: v{}", peer, version);
+            ctx.lock().await.set(PeerVersion(version));
+            true
+        }
+        Err(e) => {
+            tracing::warn!("🚫 Cannot talk to {}: {}", peer, e);
+            journal::record_handshake_failed(ctx, peer, &e.to_string()).await;
+            error::report(ctx, peer, e).await;
+            false
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! 帧的线路编码格式（bincode / CBOR / JSON）
+//!
+//! bincode 对其他语言不透明。握手时节点在 `capabilities` 中声明可以接收的格式
+//! （`CAP_FORMAT_CBOR` / `CAP_FORMAT_JSON`），对端也支持时按配置 `[codec] prefer` 选择发送格式；
+//! 对端的握手帧本身使用 CBOR/JSON 时，该连接始终以同一格式回复，
+//! 因此非 Rust 客户端只需实现一种格式即可加入网络。接收方总能识别所有格式。
+//!
+//! 非 bincode 帧在线路上以 `FORMAT_FRAME_MARKER | 格式 id` 开头（与协议版本号、
+//! `COMPRESSED_FRAME_MARKER` 区分），随后是 bincode 变长长度前缀的 [`WireFrame`]：
+//! `body` 为按同一格式编码的 `FrameBody`，签名直接针对这段字节，中继时原样转发。
+//! `body.data` 中的 `P2PCommand` 与未加密的命令负载同样使用该格式；
+//! 加密负载仍是 bincode 明文的密文。非 bincode 帧不压缩；
+//! 预先编码后群发的帧（如种子广播）仍为 bincode。
+
+use std::sync::Arc;
+
+use aex::{connection::context::Context, tcp::types::Codec};
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tokio::sync::Mutex;
+
+use crate::{
+    config::SharedConfig,
+    protocols::{
+        compression::{LOCAL_CAPABILITIES, PeerCapabilities},
+        frame::HopSignature,
+    },
+};
+
+/// 可以接收 CBOR 编码的帧
+pub const CAP_FORMAT_CBOR: u32 = 1 << 1;
+/// 可以接收 JSON 编码的帧（调试用）
+pub const CAP_FORMAT_JSON: u32 = 1 << 2;
+/// 非 bincode 帧首字节高 4 位
+pub const FORMAT_FRAME_MARKER: u8 = 0xD0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WireFormat {
+    #[default]
+    Bincode,
+    Cbor,
+    Json,
+}
+
+impl WireFormat {
+    pub fn id(&self) -> u8 {
+        match self {
+            WireFormat::Bincode => 0,
+            WireFormat::Cbor => 1,
+            WireFormat::Json => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(WireFormat::Bincode),
+            1 => Some(WireFormat::Cbor),
+            2 => Some(WireFormat::Json),
+            _ => None,
+        }
+    }
+
+    /// 对应的能力位；bincode 是所有节点都支持的基线，没有能力位
+    pub fn capability(&self) -> u32 {
+        match self {
+            WireFormat::Bincode => 0,
+            WireFormat::Cbor => CAP_FORMAT_CBOR,
+            WireFormat::Json => CAP_FORMAT_JSON,
+        }
+    }
+}
+
+/// `[codec]` 配置
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CodecConfig {
+    /// 对端支持时优先使用的发送格式
+    pub prefer: WireFormat,
+}
+
+/// 对端握手帧使用的非 bincode 格式，保存在连接 Context 中
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerWireFormat(pub WireFormat);
+
+/// 非 bincode 帧的外层结构
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct WireFrame {
+    /// 按同一格式编码的 `FrameBody`，即签名对象
+    #[serde(with = "serde_bytes")]
+    pub body: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    pub signature: Vec<u8>,
+    pub ttl: u8,
+    /// 逐跳签名（v3 起），没有时不写出
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hop: Option<HopSignature>,
+}
+
+impl Codec for WireFrame {}
+
+pub fn encode<T: Codec + Serialize>(format: WireFormat, value: &T) -> anyhow::Result<Vec<u8>> {
+    match format {
+        WireFormat::Bincode => Codec::encode(value),
+        WireFormat::Cbor => {
+            let mut out = Vec::new();
+            ciborium::into_writer(value, &mut out)
+                .map_err(|e| anyhow::anyhow!("CBOR encode failed: {}", e))?;
+            Ok(out)
+        }
+        WireFormat::Json => Ok(serde_json::to_vec(value)?),
+    }
+}
+
+pub fn decode<T: Codec + DeserializeOwned>(format: WireFormat, data: &[u8]) -> anyhow::Result<T> {
+    match format {
+        WireFormat::Bincode => Codec::decode(data),
+        WireFormat::Cbor => {
+            ciborium::from_reader(data).map_err(|e| anyhow::anyhow!("CBOR decode failed: {}", e))
+        }
+        WireFormat::Json => Ok(serde_json::from_slice(data)?),
+    }
+}
+
+/// 选择发往对端的格式：沿用对端握手所用的格式，否则在双方都支持时使用 `prefer`，否则 bincode
+pub fn negotiate(
+    peer_format: Option<PeerWireFormat>,
+    caps: Option<PeerCapabilities>,
+    prefer: WireFormat,
+) -> WireFormat {
+    if let Some(PeerWireFormat(format)) = peer_format {
+        return format;
+    }
+    match caps {
+        Some(PeerCapabilities(bits)) if bits & LOCAL_CAPABILITIES & prefer.capability() != 0 => {
+            prefer
+        }
+        _ => WireFormat::Bincode,
+    }
+}
+
+/// 该连接上发送帧使用的格式
+pub async fn for_connection(ctx: &Arc<Mutex<Context>>) -> WireFormat {
+    let (gctx, peer_format, caps) = {
+        let guard = ctx.lock().await;
+        (
+            guard.global.clone(),
+            guard.get::<PeerWireFormat>(),
+            guard.get::<PeerCapabilities>(),
+        )
+    };
+    let prefer = match gctx.get::<SharedConfig>().await {
+        Some(config) => config.read().await.codec.prefer,
+        None => WireFormat::Bincode,
+    };
+    negotiate(peer_format, caps, prefer)
+}
@@ -0,0 +1,387 @@
+//! 直连对端的可达性：被动观察与主动探测
+//!
+//! 每收到一个帧即记为一次被动观察（[`observe`]），不产生任何额外流量。后台任务每
+//! `interval_secs` 秒检查所有直连对端：
+//!
+//! - `idle_secs` 内有被动观察或探测成功的对端视为存活，不发送 Ping；
+//! - 超过 `idle_secs` 没有任何信号、且距上次探测也超过 `idle_secs` 的对端才主动 Ping
+//!   （每轮最多 `max_probes` 个，最久没有信号的优先）；
+//! - 连续 `unresponsive_after` 次探测失败且期间没有收到任何帧的对端标记为无响应。
+//!
+//! 两类信号都写回服务器列表中对应记录的成功 / 失败次数（[`crate::record::NodeRegistry::record_probe`]），
+//! 正在通信的节点因此不会被 [`crate::peer_maintenance`] 当作久未见的记录重新拨号，评分也不会因为
+//! 空闲而衰减。各对端的状态与探测计数在 `status` 中显示，连接断开时清除。
+//!
+//! ```toml
+//! [reachability]
+//! idle_secs = 120
+//! max_probes = 16
+//! ```
+
+use std::{
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use aex::{connection::global::GlobalContext, time::SystemTime};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::SharedConfig, connections, consts::DEFAULT_TIMEOUT_MS, node::Node,
+    protocols::commands::ping, scheduler::Scheduler,
+};
+
+/// 默认检查间隔
+pub const DEFAULT_REACHABILITY_INTERVAL_SECS: u64 = 30;
+/// 默认在多久没有任何信号后主动探测
+pub const DEFAULT_IDLE_SECS: u64 = 120;
+/// 默认每轮最多探测的对端数
+pub const DEFAULT_MAX_REACHABILITY_PROBES: usize = 16;
+/// 默认连续探测失败多少次后标记为无响应
+pub const DEFAULT_UNRESPONSIVE_AFTER: u32 = 3;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReachabilityConfig {
+    /// 检查间隔（秒）
+    pub interval_secs: u64,
+    /// 超过该时间没有收到帧也没有探测成功的对端才主动探测（秒）
+    pub idle_secs: u64,
+    /// 每轮最多探测的对端数，0 表示只做被动观察
+    pub max_probes: usize,
+    /// 连续探测失败多少次后标记为无响应
+    pub unresponsive_after: u32,
+    /// 单次探测的超时（毫秒）
+    pub timeout_ms: u64,
+}
+
+impl Default for ReachabilityConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: DEFAULT_REACHABILITY_INTERVAL_SECS,
+            idle_secs: DEFAULT_IDLE_SECS,
+            max_probes: DEFAULT_MAX_REACHABILITY_PROBES,
+            unresponsive_after: DEFAULT_UNRESPONSIVE_AFTER,
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+        }
+    }
+}
+
+impl ReachabilityConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs.max(1))
+    }
+
+    pub fn idle_ms(&self) -> u128 {
+        self.idle_secs as u128 * 1000
+    }
+}
+
+/// 对端当前的可达性
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Reachability {
+    /// 最近收到过帧
+    Passive,
+    /// 最近的信号来自探测成功
+    Active,
+    /// 空闲，等待探测
+    Idle,
+    /// 连续探测失败
+    Unresponsive,
+}
+
+/// 一个连接的观察与探测记录，时间均为毫秒时间戳
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Liveness {
+    pub last_passive: Option<u128>,
+    pub last_probe: Option<u128>,
+    pub last_probe_ok: Option<u128>,
+    pub passive_frames: u64,
+    pub probes: u64,
+    pub probe_failures: u64,
+    /// 最近一次成功或收到帧之后的连续失败次数
+    pub consecutive_failures: u32,
+}
+
+fn within(t: Option<u128>, now: u128, window_ms: u128) -> bool {
+    t.is_some_and(|t| now.saturating_sub(t) < window_ms)
+}
+
+impl Liveness {
+    /// 收到一个帧
+    pub fn observe(&mut self, now: u128) {
+        self.last_passive = Some(now);
+        self.passive_frames += 1;
+        self.consecutive_failures = 0;
+    }
+
+    pub fn record_probe(&mut self, now: u128, ok: bool) {
+        self.last_probe = Some(now);
+        self.probes += 1;
+        if ok {
+            self.last_probe_ok = Some(now);
+            self.consecutive_failures = 0;
+        } else {
+            self.probe_failures += 1;
+            self.consecutive_failures += 1;
+        }
+    }
+
+    /// 最近一次存活信号（收到帧或探测成功）
+    pub fn last_alive(&self) -> Option<u128> {
+        self.last_passive.max(self.last_probe_ok)
+    }
+
+    /// 空闲超过阈值、且上次探测也在阈值之前时才需要探测
+    pub fn needs_probe(&self, now: u128, idle_ms: u128) -> bool {
+        !within(self.last_alive(), now, idle_ms) && !within(self.last_probe, now, idle_ms)
+    }
+
+    pub fn state(&self, now: u128, idle_ms: u128, unresponsive_after: u32) -> Reachability {
+        if unresponsive_after > 0 && self.consecutive_failures >= unresponsive_after {
+            return Reachability::Unresponsive;
+        }
+        if !within(self.last_alive(), now, idle_ms) {
+            return Reachability::Idle;
+        }
+        if self.last_passive >= self.last_probe_ok {
+            Reachability::Passive
+        } else {
+            Reachability::Active
+        }
+    }
+}
+
+/// 本轮需要探测的地址：最久没有信号的优先，最多 `max_probes` 个
+pub fn due_for_probe(
+    peers: &[(SocketAddr, Liveness)],
+    now: u128,
+    policy: &ReachabilityConfig,
+) -> Vec<SocketAddr> {
+    let idle_ms = policy.idle_ms();
+    let mut due: Vec<(Option<u128>, SocketAddr)> = peers
+        .iter()
+        .filter(|(_, l)| l.needs_probe(now, idle_ms))
+        .map(|(addr, l)| (l.last_alive(), *addr))
+        .collect();
+    due.sort();
+    due.into_iter()
+        .take(policy.max_probes)
+        .map(|(_, addr)| addr)
+        .collect()
+}
+
+/// 所有连接的记录与探测计数，保存在 GlobalContext 中
+#[derive(Debug, Default)]
+pub struct ReachabilityTracker {
+    peers: DashMap<SocketAddr, Liveness>,
+    probes: AtomicU64,
+    probe_failures: AtomicU64,
+    /// 因为有被动信号而省去的探测
+    skipped: AtomicU64,
+}
+
+pub type SharedReachability = Arc<ReachabilityTracker>;
+
+/// `status` 中的一行
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeerReachability {
+    pub addr: SocketAddr,
+    pub state: Reachability,
+    #[serde(flatten)]
+    pub liveness: Liveness,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ReachabilityReport {
+    pub probes: u64,
+    pub probe_failures: u64,
+    pub skipped: u64,
+    pub peers: Vec<PeerReachability>,
+}
+
+impl ReachabilityTracker {
+    pub fn observe(&self, addr: SocketAddr, now: u128) {
+        self.peers.entry(addr).or_default().observe(now);
+    }
+
+    pub fn record_probe(&self, addr: SocketAddr, now: u128, ok: bool) {
+        self.peers.entry(addr).or_default().record_probe(now, ok);
+        self.probes.fetch_add(1, Ordering::Relaxed);
+        if !ok {
+            self.probe_failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_skipped(&self, n: usize) {
+        self.skipped.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    pub fn get(&self, addr: &SocketAddr) -> Option<Liveness> {
+        self.peers.get(addr).map(|l| *l)
+    }
+
+    pub fn forget(&self, addr: &SocketAddr) {
+        self.peers.remove(addr);
+    }
+
+    /// 按地址排序的报告
+    pub fn report(&self, now: u128, policy: &ReachabilityConfig) -> ReachabilityReport {
+        let idle_ms = policy.idle_ms();
+        let mut peers: Vec<PeerReachability> = self
+            .peers
+            .iter()
+            .map(|e| PeerReachability {
+                addr: *e.key(),
+                state: e.state(now, idle_ms, policy.unresponsive_after),
+                liveness: *e.value(),
+            })
+            .collect();
+        peers.sort_by_key(|p| p.addr);
+        ReachabilityReport {
+            probes: self.probes.load(Ordering::Relaxed),
+            probe_failures: self.probe_failures.load(Ordering::Relaxed),
+            skipped: self.skipped.load(Ordering::Relaxed),
+            peers,
+        }
+    }
+}
+
+async fn tracker(gctx: &GlobalContext) -> SharedReachability {
+    match gctx.get::<SharedReachability>().await {
+        Some(tracker) => tracker,
+        None => {
+            let tracker = SharedReachability::default();
+            gctx.set(tracker.clone()).await;
+            tracker
+        }
+    }
+}
+
+/// 当前配置中的探测策略
+pub async fn policy(gctx: &GlobalContext) -> ReachabilityConfig {
+    match gctx.get::<SharedConfig>().await {
+        Some(config) => config.read().await.reachability.clone(),
+        None => ReachabilityConfig::default(),
+    }
+}
+
+/// 记录收到的一个帧
+pub async fn observe(gctx: &GlobalContext, addr: SocketAddr) {
+    tracker(gctx).await.observe(addr, SystemTime::timestamp());
+}
+
+/// 连接断开时清除记录
+pub async fn forget(gctx: &GlobalContext, addr: SocketAddr) {
+    tracker(gctx).await.forget(&addr);
+}
+
+pub async fn report(gctx: &GlobalContext) -> ReachabilityReport {
+    let policy = policy(gctx).await;
+    tracker(gctx).await.report(SystemTime::timestamp(), &policy)
+}
+
+/// 一轮检查的结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ProbeRound {
+    /// 有被动信号、无需探测的对端数
+    pub passive: usize,
+    pub probed: usize,
+    pub failed: usize,
+}
+
+/// 检查一轮：只探测空闲的直连对端，并把两类信号写回服务器列表
+pub async fn run_once(gctx: &Arc<GlobalContext>) -> ProbeRound {
+    let mut round = ProbeRound::default();
+    let policy = policy(gctx).await;
+    let tracker = tracker(gctx).await;
+    let node = gctx.get::<Arc<Node>>().await;
+    let record = |addr: SocketAddr, ok: bool| {
+        if let Some(node) = &node {
+            for registry in [&node.inner, &node.external] {
+                registry.write().record_probe(addr, ok);
+            }
+        }
+    };
+
+    let now = SystemTime::timestamp();
+    let idle_ms = policy.idle_ms();
+    let mut peers: Vec<(SocketAddr, Liveness)> = Vec::new();
+    for conn in connections::list(gctx).await {
+        if conn.peer.is_none() {
+            continue;
+        }
+        let liveness = tracker.get(&conn.addr).unwrap_or_default();
+        if within(liveness.last_passive, now, idle_ms) {
+            round.passive += 1;
+            record(conn.addr, true);
+        }
+        peers.push((conn.addr, liveness));
+    }
+    tracker.record_skipped(round.passive);
+
+    let timeout = Duration::from_millis(policy.timeout_ms);
+    for addr in due_for_probe(&peers, now, &policy) {
+        let Some(ctx) = gctx
+            .manager
+            .find_entry(&addr)
+            .and_then(|entry| entry.context.clone())
+        else {
+            continue;
+        };
+        let ok = match ping::ping(gctx.clone(), ctx, timeout).await {
+            Ok(_) => true,
+            Err(e) => {
+                tracing::debug!("Reachability probe to {} failed: {}", addr, e);
+                false
+            }
+        };
+        tracker.record_probe(addr, SystemTime::timestamp(), ok);
+        record(addr, ok);
+        round.probed += 1;
+        if !ok {
+            round.failed += 1;
+            let failures = tracker.get(&addr).map_or(0, |l| l.consecutive_failures);
+            if failures == policy.unresponsive_after {
+                tracing::warn!(
+                    "📵 Peer {} unresponsive after {} probes without traffic",
+                    addr,
+                    failures
+                );
+            }
+        }
+    }
+    round
+}
+
+/// 后台定期检查；间隔随配置热更新
+pub fn schedule(scheduler: &Scheduler, gctx: Arc<GlobalContext>) {
+    let period_gctx = gctx.clone();
+    scheduler.register_dynamic(
+        "reachability",
+        move || {
+            let gctx = period_gctx.clone();
+            async move { policy(&gctx).await.interval() }
+        },
+        move || {
+            let gctx = gctx.clone();
+            async move {
+                let round = run_once(&gctx).await;
+                if round.probed > 0 {
+                    tracing::debug!(
+                        "📡 Reachability: {} passive, {} probed, {} failed",
+                        round.passive,
+                        round.probed,
+                        round.failed
+                    );
+                }
+            }
+        },
+    );
+}
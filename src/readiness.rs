@@ -0,0 +1,214 @@
+//! 启动阶段与就绪信号
+//!
+//! `Node::init` 与 `start_servers` 只是启动后台任务，监听地址何时真正绑定、引导节点何时拨号
+//! 完毕，调用方原本无从得知。[`Readiness`] 记录两个条件：
+//!
+//! - 所有监听地址都已接受 TCP 连接（`start_servers` 启动的探测任务确认；通话媒体的 UDP 端口在
+//!   `Node::init` 中同步绑定，此时已就绪；仅出站模式下没有监听地址，立即满足）；
+//! - 引导节点完成了第一轮拨号（没有引导来源时立即满足）。
+//!
+//! 两者都满足后进入 [`StartupPhase::Ready`]；监听器放弃重启或超时未绑定时进入
+//! [`StartupPhase::Failed`]。`Node::wait_ready` 等待其中之一。
+
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use aex::connection::global::GlobalContext;
+use serde::Serialize;
+use tokio::sync::watch;
+
+use crate::listener::{HandlerSet, Health};
+
+/// 等待监听地址绑定的最长时间
+pub const LISTEN_TIMEOUT: Duration = Duration::from_secs(60);
+/// 探测监听地址的间隔
+pub const LISTEN_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum StartupPhase {
+    /// 监听地址尚未全部绑定
+    Starting,
+    /// 已在监听，等待引导节点的第一轮拨号
+    Bootstrapping,
+    Ready,
+    Failed {
+        error: String,
+    },
+}
+
+impl std::fmt::Display for StartupPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StartupPhase::Starting => write!(f, "starting"),
+            StartupPhase::Bootstrapping => write!(f, "bootstrapping"),
+            StartupPhase::Ready => write!(f, "ready"),
+            StartupPhase::Failed { error } => write!(f, "failed: {}", error),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct State {
+    listening: bool,
+    bootstrapped: bool,
+    failed: Option<String>,
+}
+
+impl State {
+    fn phase(&self) -> StartupPhase {
+        match (&self.failed, self.listening, self.bootstrapped) {
+            (Some(error), _, _) => StartupPhase::Failed {
+                error: error.clone(),
+            },
+            (None, true, true) => StartupPhase::Ready,
+            (None, true, false) => StartupPhase::Bootstrapping,
+            (None, false, _) => StartupPhase::Starting,
+        }
+    }
+}
+
+/// 节点的就绪信号，克隆后共享同一状态；同时保存在 GlobalContext 中
+#[derive(Debug, Clone)]
+pub struct Readiness {
+    state: Arc<watch::Sender<State>>,
+}
+
+impl Default for Readiness {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(watch::Sender::new(State::default())),
+        }
+    }
+}
+
+impl Readiness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn phase(&self) -> StartupPhase {
+        self.state.borrow().phase()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.phase() == StartupPhase::Ready
+    }
+
+    /// 所有监听地址都已绑定
+    pub fn mark_listening(&self) {
+        self.update(|s| s.listening = true, "listeners bound");
+    }
+
+    /// 引导节点的第一轮拨号已结束
+    pub fn mark_bootstrapped(&self) {
+        self.update(|s| s.bootstrapped = true, "bootstrap round finished");
+    }
+
+    /// 启动失败；之后不会再变为就绪
+    pub fn mark_failed(&self, error: impl Into<String>) {
+        let error = error.into();
+        tracing::error!("❌ Node startup failed: {}", error);
+        self.state.send_modify(|s| {
+            if s.failed.is_none() {
+                s.failed = Some(error);
+            }
+        });
+    }
+
+    fn update(&self, f: impl FnOnce(&mut State), what: &str) {
+        let mut became_ready = false;
+        self.state.send_modify(|s| {
+            let before = s.phase();
+            f(s);
+            became_ready = before != StartupPhase::Ready && s.phase() == StartupPhase::Ready;
+        });
+        tracing::debug!("Startup: {}", what);
+        if became_ready {
+            tracing::info!("✅ Node ready");
+        }
+    }
+
+    /// 等待就绪；启动失败时返回错误
+    pub async fn wait(&self) -> anyhow::Result<()> {
+        let mut rx = self.state.subscribe();
+        let state = rx
+            .wait_for(|s| s.failed.is_some() || (s.listening && s.bootstrapped))
+            .await
+            .map_err(|_| anyhow::anyhow!("Readiness signal dropped"))?
+            .clone();
+        match state.failed {
+            Some(error) => Err(anyhow::anyhow!("Node startup failed: {}", error)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// GlobalContext 中的就绪信号；未设置时返回一个新的（不会就绪的）信号
+pub async fn of(gctx: &GlobalContext) -> Readiness {
+    gctx.get::<Readiness>().await.unwrap_or_default()
+}
+
+/// 监听地址对应的本机可连接地址：通配地址换成回环地址
+pub fn probe_addr(binding: SocketAddr) -> SocketAddr {
+    let ip = match binding.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ip => ip,
+    };
+    SocketAddr::new(ip, binding.port())
+}
+
+/// 等待所有地址接受 TCP 连接；有监听器放弃重启或超过 `timeout` 时返回错误
+pub async fn wait_listening(
+    bindings: &[SocketAddr],
+    handlers: &HandlerSet,
+    timeout: Duration,
+) -> anyhow::Result<()> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut pending: Vec<SocketAddr> = bindings.iter().copied().map(probe_addr).collect();
+    loop {
+        let mut still = Vec::new();
+        for addr in pending {
+            if tokio::net::TcpStream::connect(addr).await.is_err() {
+                still.push(addr);
+            }
+        }
+        pending = still;
+        if pending.is_empty() {
+            return Ok(());
+        }
+        if let Some((name, Health::Failed { last_error })) = handlers
+            .health()
+            .into_iter()
+            .find(|(_, h)| matches!(h, Health::Failed { .. }))
+        {
+            anyhow::bail!("listener {} failed: {}", name, last_error);
+        }
+        if tokio::time::Instant::now() >= deadline {
+            anyhow::bail!(
+                "{:?} not accepting connections after {:?}",
+                pending,
+                timeout
+            );
+        }
+        tokio::time::sleep(LISTEN_POLL_INTERVAL).await;
+    }
+}
+
+/// 后台确认监听地址已绑定，更新就绪信号
+pub fn spawn_listen_probe(
+    readiness: Readiness,
+    bindings: Vec<SocketAddr>,
+    handlers: HandlerSet,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        match wait_listening(&bindings, &handlers, LISTEN_TIMEOUT).await {
+            Ok(()) => readiness.mark_listening(),
+            Err(e) => readiness.mark_failed(e.to_string()),
+        }
+    })
+}
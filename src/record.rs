@@ -2,11 +2,18 @@ use aex::connection::entry::ConnectionEntry;
 use aex::connection::protocol::Protocol;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use std::{collections::HashSet, hash::Hasher, net::SocketAddr};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hasher,
+    net::SocketAddr,
+};
 
 use std::hash::Hash;
 
+use crate::peer_capabilities::{CapabilityProfile, CapabilityRegression};
+use crate::protocols::agent::SoftwareInfo;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NodeRecord {
     /// 使用 SocketAddr 作为唯一网络识别
@@ -26,6 +33,31 @@ pub struct NodeRecord {
     /// 连通性评分（记录当前节点连接成功率）(success, failure)
     pub tries: (u64, u64),
     pub is_available: bool,
+
+    /// 同一节点的其它可达地址（例如 IPv6 / 其它网卡）
+    #[serde(default)]
+    pub alt_endpoints: Vec<SocketAddr>,
+
+    /// 握手中得知的节点地址；同一节点的 IPv4 / IPv6 等地址合并为一条记录，
+    /// 评分、去重与拨号都按节点而不是按 socket 地址进行
+    #[serde(default)]
+    pub node_address: Option<String>,
+
+    /// 最近一次拨号胜出的地址与协议
+    #[serde(default)]
+    pub last_dial: Option<(SocketAddr, Protocol)>,
+
+    /// 运维手动固定的记录：维护任务不删除、不衰减，启动时总是拨号
+    #[serde(default)]
+    pub pinned: bool,
+
+    /// 最近一次会话中对端声明的能力，见 [`crate::peer_capabilities`]
+    #[serde(default)]
+    pub capabilities: Option<CapabilityProfile>,
+
+    /// 最近一次会话中对端声明的软件版本与 User-Agent，见 [`crate::protocols::agent`]
+    #[serde(default)]
+    pub agent: Option<SoftwareInfo>,
 }
 
 // 手动实现 PartialEq：只要 endpoint 相同，就认为是同一个节点
@@ -61,9 +93,71 @@ impl NodeRecord {
             tries: (0, 0), // 初始成功
             periods: vec![],
             is_available: true,
+            alt_endpoints: vec![],
+            node_address: None,
+            last_dial: None,
+            pinned: false,
+            capabilities: None,
+            agent: None,
         }
     }
 
+    /// 主地址与备用地址（去重，主地址在前）
+    pub fn all_endpoints(&self) -> Vec<SocketAddr> {
+        let mut endpoints = vec![self.endpoint];
+        for addr in &self.alt_endpoints {
+            if !endpoints.contains(addr) {
+                endpoints.push(*addr);
+            }
+        }
+        endpoints
+    }
+
+    /// `endpoint` 是否为该记录的主地址或备用地址
+    pub fn has_endpoint(&self, endpoint: SocketAddr) -> bool {
+        self.endpoint == endpoint || self.alt_endpoints.contains(&endpoint)
+    }
+
+    /// 合并同一节点的另一条记录：地址、协议、尝试次数与活跃区间累加，
+    /// 拨号结果、能力与软件信息取最近见过的一条；主地址保持不变
+    pub fn absorb(&mut self, other: NodeRecord) {
+        for addr in other.all_endpoints() {
+            if !self.has_endpoint(addr) {
+                self.alt_endpoints.push(addr);
+            }
+        }
+        self.protocols.extend(other.protocols);
+        self.tries.0 += other.tries.0;
+        self.tries.1 += other.tries.1;
+        self.periods.extend(other.periods);
+        self.periods.sort();
+        self.first_seen = self.first_seen.min(other.first_seen);
+        self.is_available |= other.is_available;
+        self.pinned |= other.pinned;
+        if self.node_address.is_none() {
+            self.node_address = other.node_address;
+        }
+        if other.last_seen > self.last_seen {
+            self.last_seen = other.last_seen;
+            self.last_dial = other.last_dial.or(self.last_dial.take());
+            self.capabilities = other.capabilities.or(self.capabilities.take());
+            self.agent = other.agent.or(self.agent.take());
+        } else {
+            self.last_dial = self.last_dial.take().or(other.last_dial);
+            self.capabilities = self.capabilities.take().or(other.capabilities);
+            self.agent = self.agent.take().or(other.agent);
+        }
+    }
+
+    /// 记录拨号胜出的地址与协议
+    pub fn record_dial(&mut self, endpoint: SocketAddr, protocol: Protocol) {
+        if !self.has_endpoint(endpoint) {
+            self.alt_endpoints.push(endpoint);
+        }
+        self.last_dial = Some((endpoint, protocol));
+        self.update_status(true);
+    }
+
     /// 更新节点状态
     pub fn update_status(&mut self, success: bool) {
         let now = Utc::now();
@@ -78,6 +172,20 @@ impl NodeRecord {
         }
     }
 
+    /// 连通性评分：成功次数占总尝试次数的比例，无记录时为 0
+    pub fn score(&self) -> f64 {
+        let total = self.tries.0 + self.tries.1;
+        if total == 0 {
+            return 0.0;
+        }
+        self.tries.0 as f64 / total as f64
+    }
+
+    /// 衰减成功次数，使长期未见的节点评分逐渐降低
+    pub fn decay(&mut self, factor: f64) {
+        self.tries.0 = (self.tries.0 as f64 * factor.clamp(0.0, 1.0)).floor() as u64;
+    }
+
     /// 判断是否失效（超过 5 天未见）
     pub fn is_expired(&self) -> bool {
         let now = Utc::now();
@@ -126,7 +234,7 @@ impl NodeRecord {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct NodeRegistry {
     pub nodes: HashSet<NodeRecord>,
 }
@@ -134,17 +242,116 @@ pub struct NodeRegistry {
 impl NodeRegistry {
     pub fn new(nodes: HashSet<NodeRecord>) -> Self {
         let mut registry = Self { nodes };
+        // 旧版本按地址分别保存，同一节点的记录在加载时合并
+        registry.regroup();
         // 关键需求：启动时计算并标记 5 天以上的失效节点
         registry.on_startup_maintenance();
         registry
     }
 
+    /// 取出包含 `endpoint`（主地址或备用地址）的记录，修改后用 [`Self::insert`] 放回
+    pub fn take(&mut self, endpoint: SocketAddr) -> Option<NodeRecord> {
+        if let Some(record) = self.nodes.take(&NodeRecord::new(endpoint)) {
+            return Some(record);
+        }
+        let primary = self
+            .nodes
+            .iter()
+            .find(|n| n.alt_endpoints.contains(&endpoint))?
+            .endpoint;
+        self.nodes.take(&NodeRecord::new(primary))
+    }
+
+    pub fn insert(&mut self, record: NodeRecord) {
+        self.nodes.insert(record);
+    }
+
+    /// 节点 `address` 的记录
+    pub fn by_node(&self, address: &str) -> Option<&NodeRecord> {
+        self.nodes
+            .iter()
+            .find(|n| n.node_address.as_deref() == Some(address))
+    }
+
+    /// 把 `endpoint` 归入节点 `address`：该节点已有的其它记录合并进来；
+    /// 地址不在列表中但节点已知时，作为该节点的备用地址。返回列表是否变化
+    pub fn bind_identity(&mut self, endpoint: SocketAddr, address: &str) -> bool {
+        let Some(mut record) = self.take(endpoint) else {
+            let Some(primary) = self.by_node(address).map(|n| n.endpoint) else {
+                return false;
+            };
+            let Some(mut record) = self.take(primary) else {
+                return false;
+            };
+            record.alt_endpoints.push(endpoint);
+            self.nodes.insert(record);
+            return true;
+        };
+        let mut changed = record.node_address.as_deref() != Some(address);
+        record.node_address = Some(address.to_string());
+        let others: Vec<SocketAddr> = self
+            .nodes
+            .iter()
+            .filter(|n| n.node_address.as_deref() == Some(address))
+            .map(|n| n.endpoint)
+            .collect();
+        for primary in others {
+            if let Some(other) = self.nodes.take(&NodeRecord::new(primary)) {
+                record.absorb(other);
+                changed = true;
+            }
+        }
+        self.nodes.insert(record);
+        changed
+    }
+
+    /// 合并属于同一节点的记录，以及主地址已是其它记录备用地址的重复记录；
+    /// 最近见过的记录作为合并后的主记录。返回被合并掉的条数
+    pub fn regroup(&mut self) -> usize {
+        let mut records: Vec<NodeRecord> = self.nodes.drain().collect();
+        records.sort_by(|a, b| {
+            b.last_seen
+                .cmp(&a.last_seen)
+                .then_with(|| a.endpoint.cmp(&b.endpoint))
+        });
+        let mut merged = 0;
+        let mut groups: Vec<NodeRecord> = Vec::new();
+        let mut by_node: HashMap<String, usize> = HashMap::new();
+        let mut unbound = Vec::new();
+        for record in records {
+            let Some(address) = record.node_address.clone() else {
+                unbound.push(record);
+                continue;
+            };
+            match by_node.get(&address) {
+                Some(&i) => {
+                    groups[i].absorb(record);
+                    merged += 1;
+                }
+                None => {
+                    by_node.insert(address, groups.len());
+                    groups.push(record);
+                }
+            }
+        }
+        for record in unbound {
+            match groups.iter_mut().find(|g| g.has_endpoint(record.endpoint)) {
+                Some(group) => {
+                    group.absorb(record);
+                    merged += 1;
+                }
+                None => groups.push(record),
+            }
+        }
+        self.nodes.extend(groups);
+        merged
+    }
+
     /// 添加或更新节点
     pub fn upsert(&mut self, endpoint: SocketAddr, success: bool) {
         // 尝试从集合中取出已存在的记录
         let mut record = self
-            .nodes
-            .take(&NodeRecord::new(endpoint))
+            .take(endpoint)
             .unwrap_or_else(|| NodeRecord::new(endpoint));
 
         // 更新状态
@@ -154,11 +361,127 @@ impl NodeRegistry {
         self.nodes.insert(record);
     }
 
-    /// 获取可用节点（排除手动标记为失效或逻辑上过期的）
+    /// 记录一次拨号结果
+    pub fn record_dial(&mut self, endpoint: SocketAddr, winner: SocketAddr, protocol: Protocol) {
+        let mut record = self
+            .take(endpoint)
+            .unwrap_or_else(|| NodeRecord::new(endpoint));
+        record.record_dial(winner, protocol);
+        self.nodes.insert(record);
+    }
+
+    /// 记录一次重新验证的结果；不在列表中的地址忽略
+    pub fn record_probe(&mut self, endpoint: SocketAddr, success: bool) -> bool {
+        let Some(mut record) = self.take(endpoint) else {
+            return false;
+        };
+        record.update_status(success);
+        record.check_expiry();
+        self.nodes.insert(record);
+        true
+    }
+
+    /// 记录一次会话中观察到的能力，返回相对上一次会话的退化；不在列表中的地址忽略
+    pub fn record_capabilities(
+        &mut self,
+        endpoint: SocketAddr,
+        profile: CapabilityProfile,
+    ) -> Option<CapabilityRegression> {
+        let mut record = self.take(endpoint)?;
+        let regression = record
+            .capabilities
+            .as_ref()
+            .and_then(|previous| profile.regression_since(previous));
+        record.capabilities = Some(profile);
+        self.nodes.insert(record);
+        regression
+    }
+
+    /// 记录对端声明的软件信息；不在列表中的地址忽略，返回是否已记录
+    pub fn record_agent(&mut self, endpoint: SocketAddr, agent: SoftwareInfo) -> bool {
+        let Some(mut record) = self.take(endpoint) else {
+            return false;
+        };
+        record.agent = Some(agent);
+        self.nodes.insert(record);
+        true
+    }
+
+    /// 手动添加一条记录，已存在时只更新固定标记；返回是否为新记录
+    pub fn add(&mut self, endpoint: SocketAddr, pinned: bool) -> bool {
+        let existing = self.take(endpoint);
+        let added = existing.is_none();
+        let mut record = existing.unwrap_or_else(|| NodeRecord::new(endpoint));
+        record.pinned = record.pinned || pinned;
+        self.nodes.insert(record);
+        added
+    }
+
+    /// 删除包含该地址的节点记录（连同其备用地址）；返回是否存在
+    pub fn remove(&mut self, endpoint: SocketAddr) -> bool {
+        self.take(endpoint).is_some()
+    }
+
+    /// 设置固定标记；不在列表中的地址忽略
+    pub fn set_pinned(&mut self, endpoint: SocketAddr, pinned: bool) -> bool {
+        let Some(mut record) = self.take(endpoint) else {
+            return false;
+        };
+        record.pinned = pinned;
+        self.nodes.insert(record);
+        true
+    }
+
+    /// 包含 `endpoint`（主地址或备用地址）的记录
+    pub fn get(&self, endpoint: SocketAddr) -> Option<&NodeRecord> {
+        self.nodes
+            .get(&NodeRecord::new(endpoint))
+            .or_else(|| self.nodes.iter().find(|n| n.has_endpoint(endpoint)))
+    }
+
+    /// 最近一次成功通信早于 `before` 的地址
+    pub fn seen_before(&self, before: DateTime<Utc>) -> Vec<SocketAddr> {
+        self.nodes
+            .iter()
+            .filter(|n| n.last_seen < before)
+            .map(|n| n.endpoint)
+            .collect()
+    }
+
+    /// 删除最近一次成功通信早于 `before` 的未固定记录，返回被删除的地址
+    pub fn prune_before(&mut self, before: DateTime<Utc>) -> Vec<SocketAddr> {
+        let pruned = self
+            .nodes
+            .iter()
+            .filter(|n| !n.pinned && n.last_seen < before)
+            .map(|n| n.endpoint)
+            .collect();
+        self.nodes.retain(|n| n.pinned || n.last_seen >= before);
+        pruned
+    }
+
+    /// 对最近一次成功通信早于 `before` 的未固定记录衰减评分，返回评分变化的条数
+    pub fn decay_before(&mut self, before: DateTime<Utc>, factor: f64) -> usize {
+        let old_nodes: Vec<NodeRecord> = self.nodes.drain().collect();
+        let mut decayed = 0;
+        for mut node in old_nodes {
+            if !node.pinned && node.last_seen < before {
+                let tries = node.tries;
+                node.decay(factor);
+                if node.tries != tries {
+                    decayed += 1;
+                }
+            }
+            self.nodes.insert(node);
+        }
+        decayed
+    }
+
+    /// 获取可用节点（排除手动标记为失效或逻辑上过期的；固定的记录总是包含在内）
     pub fn get_available_nodes(&self) -> Vec<&NodeRecord> {
         self.nodes
             .iter()
-            .filter(|n| n.is_available && !n.is_expired())
+            .filter(|n| n.pinned || (n.is_available && !n.is_expired()))
             .collect()
     }
 
@@ -196,3 +519,26 @@ impl NodeRegistry {
     //     storage.save(&path.to_string(), &self.nodes)
     // }
 }
+
+/// 可在节点与后台维护任务之间共享的服务器列表
+#[derive(Debug, Clone, Default)]
+pub struct SharedNodeRegistry(Arc<RwLock<NodeRegistry>>);
+
+impl SharedNodeRegistry {
+    pub fn new(nodes: HashSet<NodeRecord>) -> Self {
+        Self(Arc::new(RwLock::new(NodeRegistry::new(nodes))))
+    }
+
+    pub fn read(&self) -> RwLockReadGuard<'_, NodeRegistry> {
+        self.0.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn write(&self) -> RwLockWriteGuard<'_, NodeRegistry> {
+        self.0.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 当前全部记录的副本
+    pub fn snapshot(&self) -> HashSet<NodeRecord> {
+        self.read().nodes.clone()
+    }
+}
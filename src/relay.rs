@@ -0,0 +1,308 @@
+//! 中继节点模式：配额与记账
+//!
+//! 所有节点都会按 [`crate::protocols::routing`] 转发目标不是自己的帧。以 `--relay`（或配置
+//! `[relay] enabled = true`）启动的节点明确作为中继 / 超级节点面向大量客户端，并按配置限额：
+//!
+//! - 同一客户端（按 IP）最多 `max_connections_per_client` 条入站连接，超出的连接在握手时收到
+//!   `Busy` 后被关闭；
+//! - 每个客户端（按帧的发送方地址）经本节点中继的流量按 `client_bytes_per_sec` 限速：漏桶溢出时
+//!   等待，相当于只对该客户端的连接施加背压；当天（UTC）累计超过 `client_daily_bytes` 后，
+//!   其余的帧直接丢弃。
+//!
+//! 无论是否启用，中继的帧都按地址记账：作为发送方与作为接收方的帧数、字节数以及被丢弃的帧数。
+//! 记账显示在控制接口的 `GET /status`（合计）与 `GET /relay`（每个地址），也可通过管理命令
+//! `relay_stats` 查看。超过 [`CLIENT_IDLE_TIMEOUT`] 没有中继流量的地址不再单独列出。限额随配置
+//! 热更新，每项为 0 表示不限制。
+//!
+//! ```toml
+//! [relay]
+//! enabled = true
+//! max_connections_per_client = 4
+//! client_bytes_per_sec = 65536
+//! client_daily_bytes = 1073741824
+//! ```
+
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use aex::{connection::global::GlobalContext, time::SystemTime};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::SharedConfig,
+    protocols::{bandwidth::LeakyBucket, commands::node_registry::ConnectionDirection, limits},
+};
+
+/// 默认每个客户端的入站连接数上限
+pub const DEFAULT_MAX_CONNECTIONS_PER_CLIENT: usize = 4;
+/// 超过该时长没有中继流量的地址从记账中移除
+pub const CLIENT_IDLE_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+const MS_PER_DAY: u128 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RelayConfig {
+    /// 作为中继节点运行并执行下面的限额
+    pub enabled: bool,
+    /// 同一 IP 的入站连接数上限
+    pub max_connections_per_client: usize,
+    /// 每个发送方的中继速率上限（字节/秒）
+    pub client_bytes_per_sec: u64,
+    /// 每个发送方每天（UTC）最多中继的字节数
+    pub client_daily_bytes: u64,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_connections_per_client: DEFAULT_MAX_CONNECTIONS_PER_CLIENT,
+            client_bytes_per_sec: 0,
+            client_daily_bytes: 0,
+        }
+    }
+}
+
+/// 以 `--relay` 启动时保存在 GlobalContext 中，与配置的 `enabled` 任一为真即启用
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RelayMode;
+
+/// 一个地址的中继记账
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RelayUsage {
+    /// 作为发送方经本节点中继的帧数与字节数
+    pub frames_sent: u64,
+    pub bytes_sent: u64,
+    /// 作为接收方经本节点中继的帧数与字节数
+    pub frames_received: u64,
+    pub bytes_received: u64,
+    /// 因超出配额被丢弃的帧数（记在发送方）
+    pub dropped: u64,
+}
+
+#[derive(Debug, Clone)]
+struct ClientLedger {
+    usage: RelayUsage,
+    bucket: LeakyBucket,
+    /// `day_bytes` 所属的日期（自 UNIX 纪元起的天数）
+    day: u64,
+    day_bytes: u64,
+    last_activity: Instant,
+}
+
+impl ClientLedger {
+    fn new(now: Instant) -> Self {
+        Self {
+            usage: RelayUsage::default(),
+            bucket: LeakyBucket::new(now),
+            day: 0,
+            day_bytes: 0,
+            last_activity: now,
+        }
+    }
+}
+
+/// 一帧是否允许中继
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayVerdict {
+    /// 等待给定时长后转发
+    Forward(Duration),
+    /// 发送方当天的配额已用完
+    QuotaExceeded,
+}
+
+/// 各地址的中继记账与限速状态
+#[derive(Debug, Default)]
+pub struct RelayLedger {
+    total: RelayUsage,
+    clients: HashMap<String, ClientLedger>,
+}
+
+impl RelayLedger {
+    fn client(&mut self, address: &str, now: Instant) -> &mut ClientLedger {
+        if !self.clients.contains_key(address) {
+            self.clients.retain(|_, c| {
+                now.saturating_duration_since(c.last_activity) < CLIENT_IDLE_TIMEOUT
+            });
+        }
+        let client = self
+            .clients
+            .entry(address.to_string())
+            .or_insert_with(|| ClientLedger::new(now));
+        client.last_activity = now;
+        client
+    }
+
+    /// 登记 `from` 发往 `to` 的一帧（`bytes` 字节）；`quota` 为 None 表示不限额（未启用中继模式）
+    pub fn admit(
+        &mut self,
+        quota: Option<&RelayConfig>,
+        from: &str,
+        to: &str,
+        bytes: usize,
+        now: Instant,
+        day: u64,
+    ) -> RelayVerdict {
+        let bytes = bytes as u64;
+        let sender = self.client(from, now);
+        if sender.day != day {
+            sender.day = day;
+            sender.day_bytes = 0;
+        }
+        let daily_limit = quota.map(|q| q.client_daily_bytes).unwrap_or(0);
+        if daily_limit > 0 && sender.day_bytes.saturating_add(bytes) > daily_limit {
+            sender.usage.dropped += 1;
+            self.total.dropped += 1;
+            return RelayVerdict::QuotaExceeded;
+        }
+        let rate = quota.map(|q| q.client_bytes_per_sec).unwrap_or(0);
+        let wait = sender.bucket.reserve(bytes as usize, rate, now);
+        sender.day_bytes += bytes;
+        sender.usage.frames_sent += 1;
+        sender.usage.bytes_sent += bytes;
+
+        let receiver = self.client(to, now);
+        receiver.usage.frames_received += 1;
+        receiver.usage.bytes_received += bytes;
+
+        self.total.frames_sent += 1;
+        self.total.bytes_sent += bytes;
+        RelayVerdict::Forward(wait)
+    }
+
+    pub fn total(&self) -> RelayUsage {
+        self.total.clone()
+    }
+
+    /// 每个地址的记账，按中继的字节数（发送加接收）从多到少排序
+    pub fn clients(&self) -> Vec<RelayClient> {
+        let mut clients: Vec<RelayClient> = self
+            .clients
+            .iter()
+            .map(|(address, c)| RelayClient {
+                address: address.clone(),
+                usage: c.usage.clone(),
+            })
+            .collect();
+        clients.sort_by(|a, b| {
+            let total = |c: &RelayClient| c.usage.bytes_sent + c.usage.bytes_received;
+            total(b).cmp(&total(a)).then(a.address.cmp(&b.address))
+        });
+        clients
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RelayClient {
+    pub address: String,
+    #[serde(flatten)]
+    pub usage: RelayUsage,
+}
+
+/// `GET /relay` 与管理命令 `relay_stats` 返回的报告
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RelayReport {
+    pub enabled: bool,
+    pub policy: RelayConfig,
+    pub total: RelayUsage,
+    pub clients: Vec<RelayClient>,
+}
+
+/// 保存在 GlobalContext 中的记账状态
+pub type SharedRelayLedger = Arc<std::sync::Mutex<RelayLedger>>;
+
+async fn ledger(gctx: &GlobalContext) -> SharedRelayLedger {
+    match gctx.get::<SharedRelayLedger>().await {
+        Some(ledger) => ledger,
+        None => {
+            let ledger = SharedRelayLedger::default();
+            gctx.set(ledger.clone()).await;
+            ledger
+        }
+    }
+}
+
+/// 当前配置中的中继限额
+pub async fn policy(gctx: &GlobalContext) -> RelayConfig {
+    match gctx.get::<SharedConfig>().await {
+        Some(config) => config.read().await.relay.clone(),
+        None => RelayConfig::default(),
+    }
+}
+
+pub async fn is_enabled(gctx: &GlobalContext) -> bool {
+    gctx.get::<RelayMode>().await.is_some() || policy(gctx).await.enabled
+}
+
+/// 中继前调用：记账、按配额限速；返回 false 表示该帧应丢弃
+pub async fn admit(gctx: &GlobalContext, from: &str, to: &str, bytes: usize) -> bool {
+    let quota = match is_enabled(gctx).await {
+        true => Some(policy(gctx).await),
+        false => None,
+    };
+    let day = (SystemTime::timestamp() / MS_PER_DAY) as u64;
+    let verdict = {
+        let ledger = ledger(gctx).await;
+        let mut ledger = ledger.lock().unwrap_or_else(|e| e.into_inner());
+        ledger.admit(quota.as_ref(), from, to, bytes, Instant::now(), day)
+    };
+    match verdict {
+        RelayVerdict::Forward(wait) => {
+            if !wait.is_zero() {
+                tracing::debug!("⏳ Throttling relay for {} by {:?}", from, wait);
+                tokio::time::sleep(wait).await;
+            }
+            true
+        }
+        RelayVerdict::QuotaExceeded => {
+            tracing::info!(
+                "🚫 Relay quota exceeded for {}, dropping frame to {}",
+                from,
+                to
+            );
+            false
+        }
+    }
+}
+
+/// 同一 IP 已有的入站连接数是否低于上限（0 表示不限制）
+pub fn within_connection_quota(conns: &[limits::ConnSnapshot], ip: IpAddr, max: usize) -> bool {
+    max == 0
+        || conns
+            .iter()
+            .filter(|c| c.direction == ConnectionDirection::Inbound && c.addr.ip() == ip)
+            .count()
+            < max
+}
+
+/// 入站握手时调用：中继模式下检查该客户端的连接数；`existing` 不含新连接本身
+pub async fn admit_connection(
+    gctx: &GlobalContext,
+    existing: &[limits::ConnSnapshot],
+    ip: IpAddr,
+) -> bool {
+    if !is_enabled(gctx).await {
+        return true;
+    }
+    within_connection_quota(existing, ip, policy(gctx).await.max_connections_per_client)
+}
+
+/// 当前记账与配额
+pub async fn report(gctx: &GlobalContext) -> RelayReport {
+    let ledger = ledger(gctx).await;
+    let (total, clients) = {
+        let ledger = ledger.lock().unwrap_or_else(|e| e.into_inner());
+        (ledger.total(), ledger.clients())
+    };
+    RelayReport {
+        enabled: is_enabled(gctx).await,
+        policy: policy(gctx).await,
+        total,
+        clients,
+    }
+}
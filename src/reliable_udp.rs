@@ -0,0 +1,420 @@
+//! UDP 数据报的可靠传输层
+//!
+//! [`crate::transport::DatagramConnection`] 每次发送只是一个数据报，丢了就丢了。
+//! [`ReliableDatagramConnection`] 在其上加一层轻量的确认与重传，适合在有丢包的链路上
+//! 发送 Online、消息等需要送达的命令，而不必改用 TCP：
+//!
+//! - 每个数据报带 4 字节序号（`PACKET_DATA | seq | payload`），接收方收到后立即回复
+//!   `PACKET_ACK | seq`；
+//! - 发送方在超时（RTO）内没有收到确认就重传，最多重传 `max_retries` 次，仍无确认时
+//!   `send` 返回错误；
+//! - RTO 按 RFC 6298 由往返时间估算（SRTT + 4 × RTTVAR，限制在 `min_rto_ms` ~
+//!   `max_rto_ms`），超时后加倍；重传过的数据报不参与估算（Karn 算法）；
+//! - 接收方按序号去重，重传造成的重复数据报只确认、不再交付。交付顺序与到达顺序相同，
+//!   不保证与发送顺序一致。
+//!
+//! 连接在后台读取 socket 以处理确认，来自其它地址的数据报被丢弃，因此每个 socket
+//! 只应对应一个可靠连接。
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU32, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    net::UdpSocket,
+    sync::{Mutex, mpsc, oneshot},
+    task::JoinHandle,
+};
+
+use crate::{
+    connections::Transport,
+    transport::{Connection, RECV_BUFFER_SIZE},
+};
+
+/// 数据报类型：携带负载
+pub const PACKET_DATA: u8 = 0x01;
+/// 数据报类型：确认
+pub const PACKET_ACK: u8 = 0x02;
+/// 类型 + 序号
+pub const HEADER_LEN: usize = 5;
+/// 单个数据报可携带的最大负载（IPv4 UDP 上限减去头部）
+pub const MAX_PAYLOAD: usize = 65_507 - HEADER_LEN;
+/// 接收方记住的最近序号个数，更早的序号一律视为重复
+pub const DEDUP_WINDOW: u32 = 1024;
+/// 尚未交付给 `recv` 的数据报上限，超出时丢弃（发送方会重传）
+pub const INCOMING_QUEUE: usize = 256;
+
+pub const DEFAULT_INITIAL_RTO_MS: u64 = 1_000;
+pub const DEFAULT_MIN_RTO_MS: u64 = 200;
+pub const DEFAULT_MAX_RTO_MS: u64 = 10_000;
+pub const DEFAULT_MAX_RETRIES: u32 = 5;
+
+/// 重传参数
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReliabilityConfig {
+    /// 还没有往返时间样本时的 RTO
+    pub initial_rto_ms: u64,
+    pub min_rto_ms: u64,
+    pub max_rto_ms: u64,
+    /// 第一次发送之后最多重传的次数
+    pub max_retries: u32,
+}
+
+impl Default for ReliabilityConfig {
+    fn default() -> Self {
+        Self {
+            initial_rto_ms: DEFAULT_INITIAL_RTO_MS,
+            min_rto_ms: DEFAULT_MIN_RTO_MS,
+            max_rto_ms: DEFAULT_MAX_RTO_MS,
+            max_retries: DEFAULT_MAX_RETRIES,
+        }
+    }
+}
+
+/// 线路上的数据报
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Packet {
+    Data { seq: u32, payload: Vec<u8> },
+    Ack { seq: u32 },
+}
+
+impl Packet {
+    pub fn encode(&self) -> Vec<u8> {
+        let (kind, seq, payload): (u8, u32, &[u8]) = match self {
+            Packet::Data { seq, payload } => (PACKET_DATA, *seq, payload),
+            Packet::Ack { seq } => (PACKET_ACK, *seq, &[]),
+        };
+        let mut out = Vec::with_capacity(HEADER_LEN + payload.len());
+        out.push(kind);
+        out.extend_from_slice(&seq.to_be_bytes());
+        out.extend_from_slice(payload);
+        out
+    }
+
+    pub fn decode(bytes: &[u8]) -> anyhow::Result<Self> {
+        if bytes.len() < HEADER_LEN {
+            anyhow::bail!("Datagram too short: {} byte(s)", bytes.len());
+        }
+        let seq = u32::from_be_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]);
+        match bytes[0] {
+            PACKET_DATA => Ok(Packet::Data {
+                seq,
+                payload: bytes[HEADER_LEN..].to_vec(),
+            }),
+            PACKET_ACK if bytes.len() == HEADER_LEN => Ok(Packet::Ack { seq }),
+            PACKET_ACK => anyhow::bail!("Ack with {} trailing byte(s)", bytes.len() - HEADER_LEN),
+            kind => anyhow::bail!("Unknown datagram type 0x{:02x}", kind),
+        }
+    }
+}
+
+/// RFC 6298 的重传超时估算
+#[derive(Debug, Clone, PartialEq)]
+pub struct RtoEstimator {
+    srtt: Option<Duration>,
+    rttvar: Duration,
+    rto: Duration,
+    min: Duration,
+    max: Duration,
+}
+
+impl RtoEstimator {
+    pub fn new(config: &ReliabilityConfig) -> Self {
+        let min = Duration::from_millis(config.min_rto_ms);
+        let max = Duration::from_millis(config.max_rto_ms.max(config.min_rto_ms));
+        Self {
+            srtt: None,
+            rttvar: Duration::ZERO,
+            rto: Duration::from_millis(config.initial_rto_ms).clamp(min, max),
+            min,
+            max,
+        }
+    }
+
+    pub fn rto(&self) -> Duration {
+        self.rto
+    }
+
+    /// 平滑后的往返时间；还没有样本时为 None
+    pub fn srtt(&self) -> Option<Duration> {
+        self.srtt
+    }
+
+    /// 记录一次未经重传的往返时间
+    pub fn sample(&mut self, rtt: Duration) {
+        match self.srtt {
+            None => {
+                self.srtt = Some(rtt);
+                self.rttvar = rtt / 2;
+            }
+            Some(srtt) => {
+                let delta = srtt.abs_diff(rtt);
+                self.rttvar = (self.rttvar * 3 + delta) / 4;
+                self.srtt = Some((srtt * 7 + rtt) / 8);
+            }
+        }
+        let srtt = self.srtt.unwrap_or(rtt);
+        self.rto = (srtt + self.rttvar * 4).clamp(self.min, self.max);
+    }
+
+    /// 超时后 RTO 加倍
+    pub fn backoff(&mut self) {
+        self.rto = (self.rto * 2).clamp(self.min, self.max);
+    }
+}
+
+/// 接收方的去重窗口
+#[derive(Debug, Clone, Default)]
+pub struct ReceiveWindow {
+    highest: Option<u32>,
+    seen: BTreeSet<u32>,
+}
+
+impl ReceiveWindow {
+    /// 序号第一次出现时返回 true
+    pub fn accept(&mut self, seq: u32) -> bool {
+        if self
+            .highest
+            .is_some_and(|highest| seq < highest && highest - seq >= DEDUP_WINDOW)
+        {
+            return false;
+        }
+        if !self.seen.insert(seq) {
+            return false;
+        }
+        let highest = self.highest.map_or(seq, |h| h.max(seq));
+        self.highest = Some(highest);
+        let floor = highest.saturating_sub(DEDUP_WINDOW);
+        self.seen = self.seen.split_off(&floor);
+        true
+    }
+}
+
+/// 可靠连接的计数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ReliabilityStats {
+    /// 调用 `send` 的次数
+    pub sent: u64,
+    pub retransmitted: u64,
+    pub acked: u64,
+    /// 重传次数用尽仍未确认
+    pub failed: u64,
+    /// 收到的重复数据报
+    pub duplicates: u64,
+}
+
+type Pending = Arc<std::sync::Mutex<HashMap<u32, oneshot::Sender<()>>>>;
+type SharedStats = Arc<std::sync::Mutex<ReliabilityStats>>;
+
+/// 带确认与重传的 UDP 连接
+pub struct ReliableDatagramConnection {
+    socket: Arc<UdpSocket>,
+    peer: SocketAddr,
+    config: ReliabilityConfig,
+    next_seq: AtomicU32,
+    pending: Pending,
+    rto: std::sync::Mutex<RtoEstimator>,
+    stats: SharedStats,
+    incoming: Mutex<mpsc::Receiver<Vec<u8>>>,
+    closed: AtomicBool,
+    pump: JoinHandle<()>,
+}
+
+impl ReliableDatagramConnection {
+    /// 需要在 tokio 运行时中调用：后台任务从 `socket` 读取确认与数据
+    pub fn new(socket: Arc<UdpSocket>, peer: SocketAddr, config: ReliabilityConfig) -> Self {
+        let pending = Pending::default();
+        let stats = SharedStats::default();
+        let (tx, rx) = mpsc::channel(INCOMING_QUEUE);
+        let pump = tokio::spawn(pump(
+            socket.clone(),
+            peer,
+            pending.clone(),
+            stats.clone(),
+            tx,
+        ));
+        Self {
+            socket,
+            peer,
+            rto: std::sync::Mutex::new(RtoEstimator::new(&config)),
+            config,
+            next_seq: AtomicU32::new(0),
+            pending,
+            stats,
+            incoming: Mutex::new(rx),
+            closed: AtomicBool::new(false),
+            pump,
+        }
+    }
+
+    pub fn stats(&self) -> ReliabilityStats {
+        *self.stats.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 当前的重传超时
+    pub fn rto(&self) -> Duration {
+        self.rto.lock().unwrap_or_else(|e| e.into_inner()).rto()
+    }
+
+    fn count(&self, f: impl FnOnce(&mut ReliabilityStats)) {
+        f(&mut self.stats.lock().unwrap_or_else(|e| e.into_inner()));
+    }
+}
+
+impl Drop for ReliableDatagramConnection {
+    fn drop(&mut self) {
+        self.pump.abort();
+    }
+}
+
+/// 读取 socket：确认交给等待中的 `send`，数据回复确认后去重交付
+async fn pump(
+    socket: Arc<UdpSocket>,
+    peer: SocketAddr,
+    pending: Pending,
+    stats: SharedStats,
+    incoming: mpsc::Sender<Vec<u8>>,
+) {
+    let mut window = ReceiveWindow::default();
+    let mut buf = vec![0u8; RECV_BUFFER_SIZE];
+    loop {
+        let (n, from) = match socket.recv_from(&mut buf).await {
+            Ok(v) => v,
+            Err(e) => {
+                tracing::debug!("Reliable UDP recv from {} failed: {}", peer, e);
+                continue;
+            }
+        };
+        if from != peer {
+            continue;
+        }
+        match Packet::decode(&buf[..n]) {
+            Ok(Packet::Ack { seq }) => {
+                let waiter = pending
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .remove(&seq);
+                if let Some(waiter) = waiter {
+                    let _ = waiter.send(());
+                }
+            }
+            Ok(Packet::Data { seq, payload }) => {
+                if incoming.capacity() == 0 {
+                    // 不确认，让发送方稍后重传
+                    continue;
+                }
+                let _ = socket.send_to(&Packet::Ack { seq }.encode(), peer).await;
+                if !window.accept(seq) {
+                    stats.lock().unwrap_or_else(|e| e.into_inner()).duplicates += 1;
+                    continue;
+                }
+                if incoming.send(payload).await.is_err() {
+                    return;
+                }
+            }
+            Err(e) => tracing::debug!("Dropping malformed datagram from {}: {}", peer, e),
+        }
+    }
+}
+
+#[async_trait]
+impl Connection for ReliableDatagramConnection {
+    /// 等到对端确认才返回；重传次数用尽时返回错误
+    async fn send(&self, bytes: &[u8]) -> anyhow::Result<()> {
+        if self.closed.load(Ordering::Relaxed) {
+            anyhow::bail!("connection to {} is closed", self.peer);
+        }
+        if bytes.len() > MAX_PAYLOAD {
+            anyhow::bail!(
+                "{} byte(s) exceed the datagram limit of {}",
+                bytes.len(),
+                MAX_PAYLOAD
+            );
+        }
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        let (tx, mut rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(seq, tx);
+        self.count(|s| s.sent += 1);
+
+        let packet = Packet::Data {
+            seq,
+            payload: bytes.to_vec(),
+        }
+        .encode();
+        for attempt in 0..=self.config.max_retries {
+            if attempt > 0 {
+                self.count(|s| s.retransmitted += 1);
+                tracing::debug!(
+                    "Retransmitting datagram {} to {} ({})",
+                    seq,
+                    self.peer,
+                    attempt
+                );
+            }
+            let started = Instant::now();
+            self.socket.send_to(&packet, self.peer).await?;
+            match tokio::time::timeout(self.rto(), &mut rx).await {
+                Ok(Ok(())) => {
+                    if attempt == 0 {
+                        self.rto
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .sample(started.elapsed());
+                    }
+                    self.count(|s| s.acked += 1);
+                    return Ok(());
+                }
+                Ok(Err(_)) => anyhow::bail!("connection to {} is closed", self.peer),
+                Err(_) => self.rto.lock().unwrap_or_else(|e| e.into_inner()).backoff(),
+            }
+        }
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&seq);
+        self.count(|s| s.failed += 1);
+        anyhow::bail!(
+            "No ack from {} for datagram {} after {} attempt(s)",
+            self.peer,
+            seq,
+            self.config.max_retries + 1
+        )
+    }
+
+    async fn recv(&self) -> anyhow::Result<Option<Vec<u8>>> {
+        if self.closed.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+        Ok(self.incoming.lock().await.recv().await)
+    }
+
+    async fn peer_addr(&self) -> SocketAddr {
+        self.peer
+    }
+
+    async fn transport(&self) -> Transport {
+        Transport::Udp
+    }
+
+    /// 停止后台读取；等待确认中的 `send` 立即失败
+    async fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+        self.pump.abort();
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+}
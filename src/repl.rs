@@ -0,0 +1,156 @@
+//! 交互式 REPL 的行编辑器
+//!
+//! 终端下用 rustyline 读取命令：历史记录保存在数据目录的 `history.txt`（上下键翻阅，
+//! Ctrl-R 搜索），Tab 在行首补全命令名，其余位置补全已知节点地址与别名。rustyline 是阻塞的，
+//! 在独立线程中读行，每条命令执行完（并刷新补全候选）后才显示下一个提示符。
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use aex::connection::global::GlobalContext;
+use rustyline::{
+    Config, Context as LineContext, Editor, Helper,
+    completion::{Completer, Pair},
+    error::ReadlineError,
+    highlight::Highlighter,
+    hint::Hinter,
+    history::FileHistory,
+    validate::Validator,
+};
+
+use crate::{cli::Cli, node::Node};
+
+/// 保留的历史条数
+pub const HISTORY_SIZE: usize = 1000;
+
+const PROMPT: &str = "> ";
+
+/// 补全 `line` 中光标 `pos` 所在的词：行首补全 `commands`，其余补全 `words`；
+/// 返回词的起始位置与按字母序排列的候选
+pub fn complete(
+    commands: &[String],
+    words: &[String],
+    line: &str,
+    pos: usize,
+) -> (usize, Vec<String>) {
+    let head = &line[..pos];
+    let start = head
+        .rfind(char::is_whitespace)
+        .map(|i| i + head[i..].chars().next().map_or(1, char::len_utf8))
+        .unwrap_or(0);
+    let prefix = &head[start..];
+    let pool = if head[..start].trim().is_empty() {
+        commands
+    } else {
+        words
+    };
+    let mut candidates: Vec<String> = pool
+        .iter()
+        .filter(|w| w.starts_with(prefix))
+        .cloned()
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+    (start, candidates)
+}
+
+/// 参数补全的候选：别名、已知节点地址
+pub async fn completion_words(ctx: &GlobalContext) -> Vec<String> {
+    let Some(node) = ctx.get::<Arc<Node>>().await else {
+        return Vec::new();
+    };
+    let mut words: Vec<String> = node.registry.aliases().into_keys().collect();
+    words.extend(node.registry.get_nodes().into_iter().map(|n| n.address));
+    words
+}
+
+struct CliHelper {
+    commands: Vec<String>,
+    words: Arc<Mutex<Vec<String>>>,
+}
+
+impl Completer for CliHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &LineContext<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let words = self.words.lock().unwrap_or_else(|e| e.into_inner());
+        let (start, candidates) = complete(&self.commands, &words, line, pos);
+        let pairs = candidates
+            .into_iter()
+            .map(|c| Pair {
+                display: c.clone(),
+                replacement: c,
+            })
+            .collect();
+        Ok((start, pairs))
+    }
+}
+
+impl Hinter for CliHelper {
+    type Hint = String;
+}
+
+impl Highlighter for CliHelper {}
+
+impl Validator for CliHelper {}
+
+impl Helper for CliHelper {}
+
+/// 运行行编辑 REPL，直到输入 `exit`、Ctrl-C 或 Ctrl-D
+pub async fn run(cli: Arc<Cli>, ctx: Arc<GlobalContext>, history: PathBuf) -> anyhow::Result<()> {
+    let words = Arc::new(Mutex::new(completion_words(&ctx).await));
+    let config = Config::builder().max_history_size(HISTORY_SIZE)?.build();
+    let mut editor: Editor<CliHelper, FileHistory> = Editor::with_config(config)?;
+    editor.set_helper(Some(CliHelper {
+        commands: cli.command_names(),
+        words: words.clone(),
+    }));
+    if history.exists() {
+        if let Err(e) = editor.load_history(&history) {
+            tracing::warn!("Failed to load history {}: {}", history.display(), e);
+        }
+    }
+
+    let (line_tx, mut line_rx) = tokio::sync::mpsc::channel::<String>(1);
+    let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+    std::thread::spawn(move || {
+        loop {
+            match editor.readline(PROMPT) {
+                Ok(line) => {
+                    if !line.trim().is_empty() {
+                        let _ = editor.add_history_entry(line.as_str());
+                        if let Err(e) = editor.save_history(&history) {
+                            tracing::warn!("Failed to save history {}: {}", history.display(), e);
+                        }
+                    }
+                    // 等命令执行完再显示下一个提示符
+                    if line_tx.blocking_send(line).is_err() || done_rx.recv().is_err() {
+                        break;
+                    }
+                }
+                Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => break,
+                Err(e) => {
+                    tracing::error!("Failed to read line: {}", e);
+                    break;
+                }
+            }
+        }
+    });
+
+    println!("Type 'help' for commands. Tab completes commands, addresses and aliases.");
+    while let Some(line) = line_rx.recv().await {
+        if !cli.execute(&line, ctx.clone()).await {
+            break;
+        }
+        *words.lock().unwrap_or_else(|e| e.into_inner()) = completion_words(&ctx).await;
+        let _ = done_tx.send(());
+    }
+    Ok(())
+}
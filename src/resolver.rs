@@ -0,0 +1,190 @@
+//! 连接目标的域名解析
+//!
+//! `connect` 的目标可以是 IP 或域名。域名默认交给系统解析器（`tokio::net::lookup_host`），
+//! 配置文件 `[resolver] doh` 给出 DNS-over-HTTPS 服务（JSON 格式，如
+//! `https://cloudflare-dns.com/dns-query`）时先查询其 A 与 AAAA 记录，失败再退回系统解析器。
+//! 解析出的全部地址交给 [`crate::dialer`] 竞速拨号。
+//!
+//! ```toml
+//! [resolver]
+//! doh = "https://cloudflare-dns.com/dns-query"
+//! timeout_ms = 5000
+//! ```
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use aex::connection::global::GlobalContext;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::SharedConfig, record::NodeRecord};
+
+/// 单次解析的默认超时时间
+pub const DEFAULT_RESOLVE_TIMEOUT_MS: u64 = 5_000;
+
+/// DNS 记录类型：A / AAAA
+const RECORD_A: u16 = 1;
+const RECORD_AAAA: u16 = 28;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResolverConfig {
+    /// DNS-over-HTTPS 服务地址（JSON API），为空时只使用系统解析器
+    pub doh: Option<String>,
+    /// 单次解析超时（毫秒）
+    pub timeout_ms: u64,
+}
+
+impl Default for ResolverConfig {
+    fn default() -> Self {
+        Self {
+            doh: None,
+            timeout_ms: DEFAULT_RESOLVE_TIMEOUT_MS,
+        }
+    }
+}
+
+impl ResolverConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+}
+
+/// `host` 是否为 IP 字面量（IPv6 可带方括号）
+pub fn parse_ip(host: &str) -> Option<IpAddr> {
+    let host = host.trim();
+    let host = host
+        .strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host);
+    host.parse().ok()
+}
+
+/// 解析 DoH JSON 应答（`application/dns-json`）中的 A / AAAA 记录，忽略 CNAME 等其它记录
+pub fn parse_doh_answer(body: &str, port: u16) -> anyhow::Result<Vec<SocketAddr>> {
+    #[derive(Deserialize)]
+    struct Answer {
+        #[serde(rename = "type")]
+        kind: u16,
+        data: String,
+    }
+    #[derive(Deserialize)]
+    struct Response {
+        #[serde(rename = "Status")]
+        status: u32,
+        #[serde(rename = "Answer", default)]
+        answer: Vec<Answer>,
+    }
+
+    let response: Response = serde_json::from_str(body)?;
+    if response.status != 0 {
+        anyhow::bail!("DoH query failed with status {}", response.status);
+    }
+    Ok(response
+        .answer
+        .into_iter()
+        .filter(|a| a.kind == RECORD_A || a.kind == RECORD_AAAA)
+        .filter_map(|a| a.data.parse::<IpAddr>().ok())
+        .map(|ip| SocketAddr::new(ip, port))
+        .collect())
+}
+
+async fn doh_query(
+    client: &reqwest::Client,
+    url: &str,
+    host: &str,
+    kind: &str,
+    port: u16,
+) -> anyhow::Result<Vec<SocketAddr>> {
+    let body = client
+        .get(url)
+        .query(&[("name", host), ("type", kind)])
+        .header("Accept", "application/dns-json")
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    parse_doh_answer(&body, port)
+}
+
+/// 经 DoH 查询 `host` 的 AAAA 与 A 记录
+pub async fn resolve_doh(
+    url: &str,
+    host: &str,
+    port: u16,
+    timeout: Duration,
+) -> anyhow::Result<Vec<SocketAddr>> {
+    let client = reqwest::Client::builder().timeout(timeout).build()?;
+    let (v6, v4) = tokio::join!(
+        doh_query(&client, url, host, "AAAA", port),
+        doh_query(&client, url, host, "A", port)
+    );
+    match (v6, v4) {
+        (Err(e), Err(_)) => Err(e),
+        (v6, v4) => {
+            let mut out = v6.unwrap_or_default();
+            out.extend(v4.unwrap_or_default());
+            Ok(out)
+        }
+    }
+}
+
+/// 用系统解析器查询 `host`
+pub async fn resolve_system(
+    host: &str,
+    port: u16,
+    timeout: Duration,
+) -> anyhow::Result<Vec<SocketAddr>> {
+    match tokio::time::timeout(timeout, tokio::net::lookup_host((host, port))).await {
+        Ok(addrs) => Ok(addrs?.collect()),
+        Err(_) => anyhow::bail!("lookup {} timed out after {:?}", host, timeout),
+    }
+}
+
+/// 解析连接目标：IP 直接返回，域名先经 DoH（若已配置）再经系统解析器；结果去重，保持顺序
+pub async fn resolve(
+    gctx: &GlobalContext,
+    host: &str,
+    port: u16,
+) -> anyhow::Result<Vec<SocketAddr>> {
+    if let Some(ip) = parse_ip(host) {
+        return Ok(vec![SocketAddr::new(ip, port)]);
+    }
+    let config = match gctx.get::<SharedConfig>().await {
+        Some(config) => config.read().await.resolver.clone(),
+        None => ResolverConfig::default(),
+    };
+
+    let mut addrs = Vec::new();
+    if let Some(url) = config.doh.as_deref() {
+        match resolve_doh(url, host, port, config.timeout()).await {
+            Ok(found) => addrs = found,
+            Err(e) => tracing::warn!("⚠️ DoH lookup {} via {} failed: {}", host, url, e),
+        }
+    }
+    if addrs.is_empty() {
+        addrs = resolve_system(host, port, config.timeout()).await?;
+    }
+
+    let mut out: Vec<SocketAddr> = Vec::new();
+    for addr in addrs {
+        if !out.contains(&addr) {
+            out.push(addr);
+        }
+    }
+    if out.is_empty() {
+        anyhow::bail!("{} has no A/AAAA records", host);
+    }
+    Ok(out)
+}
+
+/// 把解析结果合成一条拨号记录：第一个地址为主地址，其余为备用地址
+pub fn to_record(addrs: &[SocketAddr]) -> Option<NodeRecord> {
+    let (first, rest) = addrs.split_first()?;
+    let mut record = NodeRecord::new(*first);
+    record.alt_endpoints = rest.to_vec();
+    Some(record)
+}
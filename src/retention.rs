@@ -0,0 +1,346 @@
+//! 消息保留策略与存储配额
+//!
+//! 聊天记录（[`UserStore`] 中每个联系人的 `chat.json`）与离线队列（WAL 中尚未被确认的出站消息）
+//! 会一直增长。配置文件 `[retention]` 给出保留策略，后台任务每 `interval_secs` 秒按策略清理：
+//!
+//! 1. 删除超过 `max_age_secs` 的聊天记录
+//! 2. 单个会话超过 `max_conversation_bytes` 时从最旧的消息开始删除
+//! 3. 所有会话合计超过 `max_total_bytes` 时删除全局最旧的消息
+//! 4. 离线队列中超过 `max_queue_age_secs` 的消息放弃发送，合计超过 `max_queue_bytes` 时放弃最旧的
+//!
+//! 每项为 0 表示不限制。删除的条数与字节数累计在 [`RetentionMetrics`] 中，显示在控制接口的
+//! `GET /status`；管理命令 `retention` 可查看、替换策略并立即清理一次。
+//!
+//! 聊天记录由嵌入方（Web UI）创建的 [`UserStore`] 保存，需通过 [`register_store`] 登记后才会被清理。
+//!
+//! ```toml
+//! [retention]
+//! max_age_secs = 2592000
+//! max_conversation_bytes = 1048576
+//! max_total_bytes = 104857600
+//! max_queue_age_secs = 604800
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use aex::{connection::global::GlobalContext, time::SystemTime};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::SharedConfig,
+    scheduler::Scheduler,
+    user_store::{ChatMessage, UserStore},
+    wal::{PendingFrame, SharedWal},
+};
+
+/// 默认清理间隔
+pub const DEFAULT_RETENTION_INTERVAL_SECS: u64 = 10 * 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionConfig {
+    /// 聊天记录最长保留时间（秒）
+    pub max_age_secs: u64,
+    /// 单个会话最多占用的字节数
+    pub max_conversation_bytes: u64,
+    /// 所有会话合计最多占用的字节数
+    pub max_total_bytes: u64,
+    /// 离线队列中的消息最长保留时间（秒）
+    pub max_queue_age_secs: u64,
+    /// 离线队列合计最多占用的字节数
+    pub max_queue_bytes: u64,
+    /// 清理间隔（秒）
+    pub interval_secs: u64,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            max_age_secs: 0,
+            max_conversation_bytes: 0,
+            max_total_bytes: 0,
+            max_queue_age_secs: 0,
+            max_queue_bytes: 0,
+            interval_secs: DEFAULT_RETENTION_INTERVAL_SECS,
+        }
+    }
+}
+
+impl RetentionConfig {
+    /// 聊天记录不受任何限制
+    pub fn keeps_all_messages(&self) -> bool {
+        self.max_age_secs == 0 && self.max_conversation_bytes == 0 && self.max_total_bytes == 0
+    }
+
+    /// 离线队列不受任何限制
+    pub fn keeps_all_queued(&self) -> bool {
+        self.max_queue_age_secs == 0 && self.max_queue_bytes == 0
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs.max(1))
+    }
+}
+
+/// 一次清理删除的内容
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PruneReport {
+    pub messages: u64,
+    pub bytes: u64,
+    pub queued: u64,
+    pub queued_bytes: u64,
+}
+
+impl PruneReport {
+    pub fn is_empty(&self) -> bool {
+        self.messages == 0 && self.queued == 0
+    }
+}
+
+/// 一条聊天记录占用的字节数（按其 JSON 编码计算）
+pub fn message_size(msg: &ChatMessage) -> u64 {
+    serde_json::to_vec(msg)
+        .map(|v| v.len())
+        .unwrap_or(msg.content.len()) as u64
+}
+
+/// 离线队列中一条消息占用的字节数
+pub fn queued_size(frame: &PendingFrame) -> u64 {
+    (frame.receiver.len() + frame.message.len()) as u64
+}
+
+/// 对聊天记录（联系人 → 按时间升序的消息）应用保留策略；`now` 为秒
+pub fn prune_conversations(
+    conversations: &mut BTreeMap<String, Vec<ChatMessage>>,
+    policy: &RetentionConfig,
+    now: i64,
+) -> PruneReport {
+    let mut report = PruneReport::default();
+    let evict = |msg: &ChatMessage, report: &mut PruneReport| {
+        report.messages += 1;
+        report.bytes += message_size(msg);
+    };
+
+    if policy.max_age_secs > 0 {
+        let cutoff = now.saturating_sub(policy.max_age_secs as i64);
+        for msgs in conversations.values_mut() {
+            msgs.retain(|m| {
+                let keep = m.timestamp >= cutoff;
+                if !keep {
+                    evict(m, &mut report);
+                }
+                keep
+            });
+        }
+    }
+
+    if policy.max_conversation_bytes > 0 {
+        for msgs in conversations.values_mut() {
+            let mut total: u64 = msgs.iter().map(message_size).sum();
+            let mut skip = 0;
+            while total > policy.max_conversation_bytes && skip < msgs.len() {
+                total -= message_size(&msgs[skip]);
+                evict(&msgs[skip], &mut report);
+                skip += 1;
+            }
+            msgs.drain(..skip);
+        }
+    }
+
+    if policy.max_total_bytes > 0 {
+        let mut all: Vec<(i64, u64, &str, u64)> = conversations
+            .iter()
+            .flat_map(|(contact, msgs)| {
+                msgs.iter()
+                    .map(move |m| (m.timestamp, m.id, contact.as_str(), message_size(m)))
+            })
+            .collect();
+        let mut total: u64 = all.iter().map(|e| e.3).sum();
+        all.sort();
+        let mut evicted: HashSet<(String, u64)> = HashSet::new();
+        for (_, id, contact, size) in all {
+            if total <= policy.max_total_bytes {
+                break;
+            }
+            total -= size;
+            report.messages += 1;
+            report.bytes += size;
+            evicted.insert((contact.to_string(), id));
+        }
+        for (contact, msgs) in conversations.iter_mut() {
+            msgs.retain(|m| !evicted.contains(&(contact.clone(), m.id)));
+        }
+    }
+
+    conversations.retain(|_, msgs| !msgs.is_empty());
+    report
+}
+
+/// 按策略应放弃的离线消息（request_id）；`now` 为毫秒
+pub fn expired_queue(pending: &[PendingFrame], policy: &RetentionConfig, now: u128) -> Vec<u64> {
+    let mut frames: Vec<&PendingFrame> = pending.iter().collect();
+    frames.sort_by_key(|f| (f.created_at, f.request_id));
+
+    let mut expired = Vec::new();
+    let max_age = Duration::from_secs(policy.max_queue_age_secs).as_millis();
+    frames.retain(|f| {
+        let keep = max_age == 0 || now.saturating_sub(f.created_at) <= max_age;
+        if !keep {
+            expired.push(f.request_id);
+        }
+        keep
+    });
+
+    if policy.max_queue_bytes > 0 {
+        let mut total: u64 = frames.iter().map(|f| queued_size(f)).sum();
+        for f in frames {
+            if total <= policy.max_queue_bytes {
+                break;
+            }
+            total -= queued_size(f);
+            expired.push(f.request_id);
+        }
+    }
+    expired
+}
+
+/// 累计的清理统计，保存在 GlobalContext 中
+#[derive(Debug, Default)]
+pub struct RetentionMetrics {
+    runs: AtomicU64,
+    messages: AtomicU64,
+    bytes: AtomicU64,
+    queued: AtomicU64,
+    queued_bytes: AtomicU64,
+    last_run: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RetentionStats {
+    pub runs: u64,
+    pub evicted_messages: u64,
+    pub evicted_bytes: u64,
+    pub evicted_queued: u64,
+    pub evicted_queued_bytes: u64,
+    /// 最近一次清理时间（毫秒，0 表示尚未清理）
+    pub last_run: u64,
+}
+
+impl RetentionMetrics {
+    pub fn record(&self, report: &PruneReport, now: u128) {
+        self.runs.fetch_add(1, Ordering::Relaxed);
+        self.messages.fetch_add(report.messages, Ordering::Relaxed);
+        self.bytes.fetch_add(report.bytes, Ordering::Relaxed);
+        self.queued.fetch_add(report.queued, Ordering::Relaxed);
+        self.queued_bytes
+            .fetch_add(report.queued_bytes, Ordering::Relaxed);
+        self.last_run.store(now as u64, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> RetentionStats {
+        RetentionStats {
+            runs: self.runs.load(Ordering::Relaxed),
+            evicted_messages: self.messages.load(Ordering::Relaxed),
+            evicted_bytes: self.bytes.load(Ordering::Relaxed),
+            evicted_queued: self.queued.load(Ordering::Relaxed),
+            evicted_queued_bytes: self.queued_bytes.load(Ordering::Relaxed),
+            last_run: self.last_run.load(Ordering::Relaxed),
+        }
+    }
+}
+
+pub type SharedRetentionMetrics = Arc<RetentionMetrics>;
+
+/// 登记需要按策略清理的聊天记录存储
+pub async fn register_store(gctx: &GlobalContext, store: Arc<UserStore>) {
+    gctx.set(store).await;
+}
+
+/// 当前配置中的保留策略
+pub async fn policy(gctx: &GlobalContext) -> RetentionConfig {
+    match gctx.get::<SharedConfig>().await {
+        Some(config) => config.read().await.retention.clone(),
+        None => RetentionConfig::default(),
+    }
+}
+
+/// 累计的清理统计
+pub async fn stats(gctx: &GlobalContext) -> RetentionStats {
+    match gctx.get::<SharedRetentionMetrics>().await {
+        Some(metrics) => metrics.snapshot(),
+        None => RetentionStats::default(),
+    }
+}
+
+/// 按当前策略清理一次
+pub async fn run_once(gctx: &GlobalContext) -> anyhow::Result<PruneReport> {
+    let policy = policy(gctx).await;
+    let now = SystemTime::timestamp();
+    let mut report = PruneReport::default();
+
+    if !policy.keeps_all_messages() {
+        if let Some(store) = gctx.get::<Arc<UserStore>>().await {
+            let pruned = store
+                .retain_messages(|conversations| {
+                    prune_conversations(conversations, &policy, (now / 1000) as i64)
+                })
+                .await?;
+            report.messages = pruned.messages;
+            report.bytes = pruned.bytes;
+        }
+    }
+
+    if !policy.keeps_all_queued() {
+        if let Some(wal) = gctx.get::<SharedWal>().await {
+            let pending = wal.pending();
+            let expired = expired_queue(&pending, &policy, now);
+            report.queued_bytes = pending
+                .iter()
+                .filter(|f| expired.contains(&f.request_id))
+                .map(queued_size)
+                .sum();
+            report.queued = wal.abandon(&expired)? as u64;
+        }
+    }
+
+    if let Some(metrics) = gctx.get::<SharedRetentionMetrics>().await {
+        metrics.record(&report, now);
+    }
+    if !report.is_empty() {
+        tracing::info!(
+            "🧹 Retention pruned {} message(s) ({} B) and {} queued message(s) ({} B)",
+            report.messages,
+            report.bytes,
+            report.queued,
+            report.queued_bytes
+        );
+    }
+    Ok(report)
+}
+
+/// 后台按配置的间隔清理；间隔随配置热更新
+pub fn schedule(scheduler: &Scheduler, gctx: Arc<GlobalContext>) {
+    let period_gctx = gctx.clone();
+    scheduler.register_dynamic(
+        "retention",
+        move || {
+            let gctx = period_gctx.clone();
+            async move { policy(&gctx).await.interval() }
+        },
+        move || {
+            let gctx = gctx.clone();
+            async move {
+                if let Err(e) = run_once(&gctx).await {
+                    tracing::warn!("Retention pass failed: {:?}", e);
+                }
+            }
+        },
+    );
+}
@@ -0,0 +1,226 @@
+//! 网络操作的超时与重试策略
+//!
+//! 建连、握手、发送与转发的超时时间以及失败后的重试退避由配置文件的 `[network]` 决定，
+//! 随配置热更新生效：
+//!
+//! - 建连（`proxy::connect`、竞速拨号 `dialer::dial` 的每次尝试，含经由代理的拨号）限时；
+//!   主动拨号（`Node::connect` 与 `connect` 命令经 `dialer::dial`，以及 `proxy::connect_peer`）
+//!   与种子同步失败后按指数退避加抖动重试；
+//! - 主动拨号建立的连接在握手超时内没有完成握手时被关闭；
+//! - 单帧发送（`P2PFrame::send_as`）超时后关闭该连接，已写出一部分的帧无法重试；
+//! - 广播与中继转发的每个目标单独限时。
+//!
+//! 超时为 0 表示不限时。重试参数目前只对建连生效。
+
+use std::{collections::BTreeMap, future::Future, sync::Arc, time::Duration};
+
+use aex::connection::global::GlobalContext;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::SharedConfig, protocols::broadcast::BROADCAST_SEND_TIMEOUT_MS};
+
+/// 默认建连超时
+pub const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 10_000;
+/// 默认握手超时
+pub const DEFAULT_HANDSHAKE_TIMEOUT_MS: u64 = 10_000;
+/// 默认单帧发送超时
+pub const DEFAULT_SEND_TIMEOUT_MS: u64 = 30_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    Connect,
+    Handshake,
+    Send,
+    Forward,
+}
+
+/// 指数退避：第 n 次重试前等待 `initial_backoff_ms * multiplier^(n-1)`，不超过
+/// `max_backoff_ms`，再上下浮动 `jitter` 比例
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// 总尝试次数（含第一次），1 表示不重试
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub multiplier: f64,
+    /// 抖动比例，0.0 ~ 1.0
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 不抖动时第 `retry` 次重试（从 1 开始）前的等待时间
+    pub fn base_delay(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(63) as i32;
+        let ms = self.initial_backoff_ms as f64 * self.multiplier.max(1.0).powi(exponent);
+        Duration::from_millis(ms.min(self.max_backoff_ms as f64) as u64)
+    }
+
+    /// 按 `unit`（-1.0 ~ 1.0）抖动后的等待时间
+    pub fn delay_with(&self, retry: u32, unit: f64) -> Duration {
+        let jitter = self.jitter.clamp(0.0, 1.0) * unit.clamp(-1.0, 1.0);
+        self.base_delay(retry).mul_f64(1.0 + jitter)
+    }
+
+    /// 随机抖动后的等待时间
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.delay_with(retry, rand::thread_rng().gen_range(-1.0..=1.0))
+    }
+}
+
+/// 各操作的超时时间（毫秒，0 表示不限时）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Timeouts {
+    pub connect_ms: u64,
+    pub handshake_ms: u64,
+    pub send_ms: u64,
+    pub forward_ms: u64,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect_ms: DEFAULT_CONNECT_TIMEOUT_MS,
+            handshake_ms: DEFAULT_HANDSHAKE_TIMEOUT_MS,
+            send_ms: DEFAULT_SEND_TIMEOUT_MS,
+            forward_ms: BROADCAST_SEND_TIMEOUT_MS,
+        }
+    }
+}
+
+impl Timeouts {
+    pub fn get(&self, op: Operation) -> u64 {
+        match op {
+            Operation::Connect => self.connect_ms,
+            Operation::Handshake => self.handshake_ms,
+            Operation::Send => self.send_ms,
+            Operation::Forward => self.forward_ms,
+        }
+    }
+}
+
+/// 单个操作覆盖的参数，未给出的沿用 `[network.timeouts]` 与 `[network.retry]`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OperationOverride {
+    pub timeout_ms: Option<u64>,
+    pub max_attempts: Option<u32>,
+    pub initial_backoff_ms: Option<u64>,
+    pub max_backoff_ms: Option<u64>,
+    pub multiplier: Option<f64>,
+    pub jitter: Option<f64>,
+}
+
+/// 配置文件中的 `[network]`
+///
+/// ```toml
+/// [network.timeouts]
+/// connect_ms = 5000
+/// send_ms = 0
+///
+/// [network.retry]
+/// max_attempts = 4
+/// initial_backoff_ms = 250
+///
+/// [network.overrides.connect]
+/// timeout_ms = 3000
+/// max_attempts = 6
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    pub timeouts: Timeouts,
+    pub retry: RetryPolicy,
+    pub overrides: BTreeMap<Operation, OperationOverride>,
+}
+
+/// 某个操作生效的超时与重试参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperationPolicy {
+    pub timeout: Option<Duration>,
+    pub retry: RetryPolicy,
+}
+
+impl NetworkConfig {
+    pub fn policy(&self, op: Operation) -> OperationPolicy {
+        let mut timeout_ms = self.timeouts.get(op);
+        let mut retry = self.retry.clone();
+        if let Some(o) = self.overrides.get(&op) {
+            timeout_ms = o.timeout_ms.unwrap_or(timeout_ms);
+            retry.max_attempts = o.max_attempts.unwrap_or(retry.max_attempts);
+            retry.initial_backoff_ms = o.initial_backoff_ms.unwrap_or(retry.initial_backoff_ms);
+            retry.max_backoff_ms = o.max_backoff_ms.unwrap_or(retry.max_backoff_ms);
+            retry.multiplier = o.multiplier.unwrap_or(retry.multiplier);
+            retry.jitter = o.jitter.unwrap_or(retry.jitter);
+        }
+        OperationPolicy {
+            timeout: (timeout_ms > 0).then(|| Duration::from_millis(timeout_ms)),
+            retry,
+        }
+    }
+}
+
+/// 当前配置下 `op` 的策略；未加载配置时使用默认值
+pub async fn policy(gctx: &Arc<GlobalContext>, op: Operation) -> OperationPolicy {
+    match gctx.get::<SharedConfig>().await {
+        Some(config) => config.read().await.network.policy(op),
+        None => NetworkConfig::default().policy(op),
+    }
+}
+
+/// 在 `timeout` 内完成 `fut`；`None` 表示不限时
+pub async fn within<T, Fut>(timeout: Option<Duration>, what: &str, fut: Fut) -> anyhow::Result<T>
+where
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, fut)
+            .await
+            .map_err(|_| anyhow::anyhow!("{} timed out after {:?}", what, timeout))?,
+        None => fut.await,
+    }
+}
+
+/// 按 `policy` 重试 `attempt`（参数为第几次尝试，从 1 开始），返回最后一次的错误
+pub async fn retry<T, F, Fut>(policy: &RetryPolicy, what: &str, mut attempt: F) -> anyhow::Result<T>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let attempts = policy.max_attempts.max(1);
+    let mut n = 1;
+    loop {
+        match attempt(n).await {
+            Ok(value) => return Ok(value),
+            Err(e) if n >= attempts => return Err(e),
+            Err(e) => {
+                let delay = policy.delay(n);
+                tracing::debug!(
+                    "{} failed (attempt {}/{}): {}, retrying in {:?}",
+                    what,
+                    n,
+                    attempts,
+                    e,
+                    delay
+                );
+                tokio::time::sleep(delay).await;
+                n += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,281 @@
+//! 安全码（safety number）：人工核对对端身份
+//!
+//! 握手的挑战-应答只能证明对端持有它声称的地址对应的私钥，无法排除首次连接时就被冒充。
+//! 双方各自由本端与对端的地址、身份公钥算出同一串 60 位数字的安全码，经电话或当面读出比对，
+//! 一致即可确认中间没有人替换公钥。
+//!
+//! 每一方的指纹为 `标签 | 公钥 | 地址` 的 SHA-256 反复迭代 [`FINGERPRINT_ITERATIONS`] 次，
+//! 取前 30 字节按 5 字节一组转为 5 位数字，得到 30 位数字；两方的数字按大小排序后拼接，
+//! 因此两端看到的安全码相同。
+//!
+//! 核对无误后可把对端标记为已验证（CLI `verify <address> confirm`、`POST /api/verify`），
+//! 记录对端当时的公钥，保存在 `verified.json`；之后对端公钥变化时状态变为 `key_changed`，
+//! 需要重新核对。
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+};
+
+use aex::connection::global::GlobalContext;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::{
+    io_storage::{IOStorage, STORAGE_VERIFIED},
+    protocols::commands::sealed,
+};
+
+const FINGERPRINT_LABEL: &[u8] = b"zz-p2p-safety-number-v1";
+/// 指纹的哈希迭代次数，增加穷举碰撞的成本
+pub const FINGERPRINT_ITERATIONS: usize = 1024;
+/// 每一方贡献的数字位数
+pub const DIGITS_PER_PARTY: usize = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationStatus {
+    Unverified,
+    Verified,
+    /// 标记为已验证之后对端公钥发生了变化
+    KeyChanged,
+}
+
+impl std::fmt::Display for VerificationStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerificationStatus::Unverified => write!(f, "unverified"),
+            VerificationStatus::Verified => write!(f, "verified"),
+            VerificationStatus::KeyChanged => write!(f, "key changed"),
+        }
+    }
+}
+
+/// 一个已验证的对端
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerifiedContact {
+    /// 标记时对端的身份公钥（十六进制）
+    pub public_key: String,
+    pub safety_number: String,
+    /// 标记时间（Unix 秒）
+    pub verified_at: i64,
+}
+
+/// 持久化的已验证对端，按地址索引
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VerifiedContacts {
+    pub contacts: BTreeMap<String, VerifiedContact>,
+}
+
+impl VerifiedContacts {
+    /// `public_key` 为对端当前的公钥，未知时只看是否标记过
+    pub fn status(&self, address: &str, public_key: Option<&[u8]>) -> VerificationStatus {
+        match (self.contacts.get(address), public_key) {
+            (None, _) => VerificationStatus::Unverified,
+            (Some(c), Some(key)) if c.public_key != hex(key) => VerificationStatus::KeyChanged,
+            (Some(_), _) => VerificationStatus::Verified,
+        }
+    }
+}
+
+/// 保存在 GlobalContext 中的已验证对端
+pub type SharedVerifiedContacts = Arc<RwLock<VerifiedContacts>>;
+
+/// 对端的核对信息，CLI 与 API 共用
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeerVerification {
+    pub address: String,
+    pub status: VerificationStatus,
+    /// 对端公钥未知（未连接且没有在线状态记录）时为 None
+    pub safety_number: Option<String>,
+    /// 对端公钥的短指纹
+    pub fingerprint: Option<String>,
+    pub verified_at: Option<i64>,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 一方的指纹
+pub fn fingerprint(address: &str, public_key: &[u8]) -> [u8; 32] {
+    let mut digest: [u8; 32] = Sha256::new()
+        .chain_update(FINGERPRINT_LABEL)
+        .chain_update(public_key)
+        .chain_update(address.as_bytes())
+        .finalize()
+        .into();
+    for _ in 1..FINGERPRINT_ITERATIONS {
+        digest = Sha256::new()
+            .chain_update(digest)
+            .chain_update(public_key)
+            .finalize()
+            .into();
+    }
+    digest
+}
+
+/// 公钥的短指纹（SHA-256 前 8 字节，十六进制分组）
+pub fn short_fingerprint(public_key: &[u8]) -> String {
+    let digest = Sha256::digest(public_key);
+    digest[..8].chunks(2).map(hex).collect::<Vec<_>>().join(":")
+}
+
+fn digits(fingerprint: &[u8; 32]) -> String {
+    fingerprint[..DIGITS_PER_PARTY]
+        .chunks(5)
+        .map(|chunk| {
+            let n = chunk.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+            format!("{:05}", n % 100_000)
+        })
+        .collect()
+}
+
+/// 双方的安全码，12 组 5 位数字，与参数顺序无关
+pub fn safety_number(
+    local_address: &str,
+    local_key: &[u8],
+    remote_address: &str,
+    remote_key: &[u8],
+) -> String {
+    let mut parts = [
+        digits(&fingerprint(local_address, local_key)),
+        digits(&fingerprint(remote_address, remote_key)),
+    ];
+    parts.sort();
+    let all = parts.concat();
+    all.as_bytes()
+        .chunks(5)
+        .map(|group| String::from_utf8_lossy(group).into_owned())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 比较两个安全码，忽略空白与分隔符
+pub fn matches(a: &str, b: &str) -> bool {
+    let normalize = |s: &str| s.chars().filter(char::is_ascii_digit).collect::<String>();
+    let a = normalize(a);
+    !a.is_empty() && a == normalize(b)
+}
+
+async fn shared(gctx: &Arc<GlobalContext>) -> SharedVerifiedContacts {
+    match gctx.get::<SharedVerifiedContacts>().await {
+        Some(contacts) => contacts,
+        None => {
+            let contacts = SharedVerifiedContacts::default();
+            gctx.set(contacts.clone()).await;
+            contacts
+        }
+    }
+}
+
+fn snapshot(contacts: &SharedVerifiedContacts) -> VerifiedContacts {
+    contacts.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+async fn persist(gctx: &Arc<GlobalContext>, contacts: &VerifiedContacts) {
+    match gctx.get::<IOStorage>().await {
+        Some(ios) => {
+            ios.save::<VerifiedContacts>(contacts, STORAGE_VERIFIED)
+                .await
+        }
+        None => tracing::error!("IOStorage not found in context, verified contacts not persisted"),
+    }
+}
+
+/// 已验证的对端
+pub async fn list(gctx: &Arc<GlobalContext>) -> BTreeMap<String, VerifiedContact> {
+    snapshot(&shared(gctx).await).contacts
+}
+
+/// 对端的验证状态；用于地址簿与联系人列表
+pub async fn status(gctx: &Arc<GlobalContext>, address: &str) -> VerificationStatus {
+    let key = sealed::public_key_of(gctx, address).await;
+    snapshot(&shared(gctx).await).status(address, key.as_deref())
+}
+
+/// 计算与 `address` 的安全码及验证状态
+pub async fn inspect(gctx: &Arc<GlobalContext>, address: &str) -> anyhow::Result<PeerVerification> {
+    Ok(inspect_with_key(gctx, address).await?.0)
+}
+
+/// 同 [`inspect`]，同时返回计算所用的对端公钥
+async fn inspect_with_key(
+    gctx: &Arc<GlobalContext>,
+    address: &str,
+) -> anyhow::Result<(PeerVerification, Option<Vec<u8>>)> {
+    let local = gctx
+        .get::<FreeWebMovementAddress>()
+        .await
+        .ok_or_else(|| anyhow::anyhow!("Local identity not initialized"))?;
+    let remote_key = sealed::public_key_of(gctx, address).await;
+    let contacts = snapshot(&shared(gctx).await);
+    let verification = PeerVerification {
+        address: address.to_string(),
+        status: contacts.status(address, remote_key.as_deref()),
+        safety_number: remote_key.as_ref().map(|key| {
+            safety_number(
+                &local.to_string(),
+                &local.public_key.to_bytes(),
+                address,
+                key,
+            )
+        }),
+        fingerprint: remote_key.as_deref().map(short_fingerprint),
+        verified_at: contacts.contacts.get(address).map(|c| c.verified_at),
+    };
+    Ok((verification, remote_key))
+}
+
+/// 把 `address` 标记为已验证。提供 `expected` 时先与本端算出的安全码比较，不一致则拒绝
+pub async fn confirm(
+    gctx: &Arc<GlobalContext>,
+    address: &str,
+    expected: Option<&str>,
+) -> anyhow::Result<PeerVerification> {
+    let (current, key) = inspect_with_key(gctx, address).await?;
+    let (Some(number), Some(key)) = (current.safety_number.clone(), key) else {
+        anyhow::bail!(
+            "Public key of {} is unknown; connect to it or wait for its presence record",
+            address
+        );
+    };
+    if let Some(expected) = expected {
+        if !matches(&number, expected) {
+            anyhow::bail!("Safety number mismatch for {}", address);
+        }
+    }
+    let contact = VerifiedContact {
+        public_key: hex(&key),
+        safety_number: number,
+        verified_at: chrono::Utc::now().timestamp(),
+    };
+    let contacts = shared(gctx).await;
+    let updated = {
+        let mut guard = contacts.write().unwrap_or_else(|e| e.into_inner());
+        guard.contacts.insert(address.to_string(), contact.clone());
+        guard.clone()
+    };
+    persist(gctx, &updated).await;
+    tracing::info!("🔏 Marked {} as verified", address);
+    Ok(PeerVerification {
+        status: VerificationStatus::Verified,
+        verified_at: Some(contact.verified_at),
+        ..current
+    })
+}
+
+/// 取消验证标记，返回之前是否标记过
+pub async fn clear(gctx: &Arc<GlobalContext>, address: &str) -> bool {
+    let contacts = shared(gctx).await;
+    let updated = {
+        let mut guard = contacts.write().unwrap_or_else(|e| e.into_inner());
+        if guard.contacts.remove(address).is_none() {
+            return false;
+        }
+        guard.clone()
+    };
+    persist(gctx, &updated).await;
+    true
+}
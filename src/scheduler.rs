@@ -0,0 +1,344 @@
+//! 进程内的定时任务调度
+//!
+//! 心跳与可达性探测、服务器列表清理、在线状态刷新、会话密钥轮换、存储落盘等维护工作都注册到
+//! 节点持有的 [`Scheduler`]（[`crate::node::Node::scheduler`]），而不是各自 `tokio::spawn`
+//! 一个循环：
+//!
+//! - [`Scheduler::register_interval`]：固定间隔；
+//! - [`Scheduler::register_dynamic`]：每轮开始前重新计算间隔，用于可热更新的配置；
+//! - [`Scheduler::register_cron`]：按 cron 表达式（UTC，`分 时 日 月 周`）。
+//!
+//! 同一任务的执行不会重叠，上一轮耗时超过间隔时下一轮在结束后才开始；任务 panic 只记为一次
+//! 失败，不影响后续执行。节点停止时取消令牌被触发，调度器不再开始新的一轮，并等待正在执行的
+//! 一轮结束。各任务的执行次数、最近一次执行时间与耗时见 [`Scheduler::tasks`] 与 `status`。
+
+use std::{
+    future::Future,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use aex::connection::global::GlobalContext;
+use anyhow::{Context as _, bail};
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use futures::{FutureExt, future::BoxFuture};
+use serde::Serialize;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// 调度器执行的任务
+pub type Job = Arc<dyn Fn() -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// 每轮开始前计算下一次间隔
+pub type PeriodFn = Arc<dyn Fn() -> BoxFuture<'static, Duration> + Send + Sync>;
+
+/// cron 表达式：`分 时 日 月 周`，支持 `*`、`a-b`、`a,b`、`*/n` 与 `a-b/n`，
+/// 周日为 0 或 7；另支持 `@hourly`、`@daily`、`@weekly` 与 `@monthly`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expr: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// 日与周都被限定时，两者满足其一即可（与 cron 相同）
+    any_day: bool,
+}
+
+fn parse_number(s: &str, field: &str) -> anyhow::Result<u32> {
+    s.parse()
+        .with_context(|| format!("invalid {} value {:?}", field, s))
+}
+
+fn parse_field(spec: &str, field: &str, min: u32, max: u32) -> anyhow::Result<u64> {
+    let mut bits = 0u64;
+    for part in spec.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, parse_number(step, field)?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            bail!("{} step must be positive", field);
+        }
+        let (lo, hi) = if range == "*" {
+            (min, max)
+        } else if let Some((lo, hi)) = range.split_once('-') {
+            (parse_number(lo, field)?, parse_number(hi, field)?)
+        } else {
+            let v = parse_number(range, field)?;
+            // `5/15` 表示从 5 开始每 15 个单位
+            (v, if part.contains('/') { max } else { v })
+        };
+        if lo < min || hi > max || lo > hi {
+            bail!("{} range {:?} outside {}-{}", field, range, min, max);
+        }
+        for v in (lo..=hi).step_by(step as usize) {
+            bits |= 1 << v;
+        }
+    }
+    Ok(bits)
+}
+
+fn has(bits: u64, v: u32) -> bool {
+    bits & (1 << v) != 0
+}
+
+impl FromStr for CronSchedule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expr = s.trim();
+        let spec = match expr {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = spec.split_whitespace().collect();
+        let &[minute, hour, day, month, weekday] = fields.as_slice() else {
+            bail!("cron expression {:?} must have 5 fields", expr);
+        };
+        let mut weekdays = parse_field(weekday, "weekday", 0, 7)?;
+        if has(weekdays, 7) {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            expr: expr.to_string(),
+            minutes: parse_field(minute, "minute", 0, 59)?,
+            hours: parse_field(hour, "hour", 0, 23)?,
+            days: parse_field(day, "day", 1, 31)?,
+            months: parse_field(month, "month", 1, 12)?,
+            weekdays,
+            any_day: day != "*" && weekday != "*",
+        })
+    }
+}
+
+impl CronSchedule {
+    fn day_matches(&self, t: &DateTime<Utc>) -> bool {
+        if !has(self.months, t.month()) {
+            return false;
+        }
+        let day = has(self.days, t.day());
+        let weekday = has(self.weekdays, t.weekday().num_days_from_sunday());
+        if self.any_day {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+
+    /// `after` 之后（不含）第一个匹配的整分钟；表达式永远不会匹配（如 2 月 30 日）时返回 None
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut t = after.with_second(0)?.with_nanosecond(0)? + chrono::Duration::minutes(1);
+        // 2 月 29 日最长四年出现一次
+        let limit = after + chrono::Duration::days(366 * 4);
+        while t <= limit {
+            if !self.day_matches(&t) {
+                t = t.date_naive().succ_opt()?.and_hms_opt(0, 0, 0)?.and_utc();
+            } else if !has(self.hours, t.hour()) {
+                t = t.with_minute(0)? + chrono::Duration::hours(1);
+            } else if !has(self.minutes, t.minute()) {
+                t += chrono::Duration::minutes(1);
+            } else {
+                return Some(t);
+            }
+        }
+        None
+    }
+}
+
+impl std::fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.expr)
+    }
+}
+
+/// 任务的执行时间表
+#[derive(Clone)]
+pub enum Schedule {
+    Interval(Duration),
+    Dynamic(PeriodFn),
+    Cron(CronSchedule),
+}
+
+impl Schedule {
+    /// 距下一次执行的时间；None 表示不再执行
+    async fn delay(&self, now: DateTime<Utc>) -> Option<Duration> {
+        match self {
+            Schedule::Interval(every) => Some(*every),
+            Schedule::Dynamic(period) => Some(period().await),
+            Schedule::Cron(cron) => cron
+                .next_after(now)
+                .map(|at| (at - now).to_std().unwrap_or_default()),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Schedule::Interval(every) => format!("every {:?}", every),
+            Schedule::Dynamic(_) => "dynamic".to_string(),
+            Schedule::Cron(cron) => format!("cron {}", cron),
+        }
+    }
+}
+
+/// 一个任务的执行情况
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaskInfo {
+    pub name: String,
+    pub schedule: String,
+    pub runs: u64,
+    /// panic 的次数
+    pub failures: u64,
+    pub last_run: Option<DateTime<Utc>>,
+    pub last_duration_ms: Option<u64>,
+    pub next_run: Option<DateTime<Utc>>,
+}
+
+struct Entry {
+    info: Arc<Mutex<TaskInfo>>,
+    token: CancellationToken,
+    task: JoinHandle<()>,
+}
+
+/// 节点持有的任务调度器
+#[derive(Clone, Default)]
+pub struct Scheduler {
+    token: CancellationToken,
+    entries: Arc<Mutex<Vec<Entry>>>,
+}
+
+fn lock<T>(m: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+async fn run(schedule: Schedule, job: Job, info: Arc<Mutex<TaskInfo>>, token: CancellationToken) {
+    loop {
+        let Some(delay) = schedule.delay(Utc::now()).await else {
+            break;
+        };
+        lock(&info).next_run = chrono::Duration::from_std(delay)
+            .ok()
+            .map(|d| Utc::now() + d);
+        tokio::select! {
+            _ = token.cancelled() => break,
+            _ = tokio::time::sleep(delay) => {}
+        }
+        let at = Utc::now();
+        let started = Instant::now();
+        // 在独立的任务中执行，panic 不会结束调度循环
+        let result = tokio::spawn(job()).await;
+        let mut stats = lock(&info);
+        stats.runs += 1;
+        stats.last_run = Some(at);
+        stats.last_duration_ms = Some(started.elapsed().as_millis() as u64);
+        if let Err(e) = result {
+            stats.failures += 1;
+            tracing::error!("Scheduled task {} failed: {}", stats.name, e);
+        }
+    }
+    lock(&info).next_run = None;
+}
+
+impl Scheduler {
+    /// `token` 被取消时所有任务停止（通常是连接管理器取消令牌的子令牌）
+    pub fn new(token: CancellationToken) -> Self {
+        Self {
+            token,
+            entries: Arc::default(),
+        }
+    }
+
+    /// 按时间表注册任务；同名任务已存在时先停止旧的
+    pub fn register(&self, name: &str, schedule: Schedule, job: Job) {
+        self.cancel(name);
+        let info = Arc::new(Mutex::new(TaskInfo {
+            name: name.to_string(),
+            schedule: schedule.describe(),
+            runs: 0,
+            failures: 0,
+            last_run: None,
+            last_duration_ms: None,
+            next_run: None,
+        }));
+        let token = self.token.child_token();
+        let task = tokio::spawn(run(schedule, job, info.clone(), token.clone()));
+        lock(&self.entries).push(Entry { info, token, task });
+    }
+
+    /// 每隔 `every` 执行一次 `job`
+    pub fn register_interval<F, Fut>(&self, name: &str, every: Duration, job: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.register(
+            name,
+            Schedule::Interval(every),
+            Arc::new(move || job().boxed()),
+        );
+    }
+
+    /// 每轮开始前调用 `period` 得到间隔，配置热更新后下一轮即生效
+    pub fn register_dynamic<P, PFut, F, Fut>(&self, name: &str, period: P, job: F)
+    where
+        P: Fn() -> PFut + Send + Sync + 'static,
+        PFut: Future<Output = Duration> + Send + 'static,
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let period: PeriodFn = Arc::new(move || period().boxed());
+        self.register(
+            name,
+            Schedule::Dynamic(period),
+            Arc::new(move || job().boxed()),
+        );
+    }
+
+    /// 按 cron 表达式执行 `job`；表达式无效时返回错误
+    pub fn register_cron<F, Fut>(&self, name: &str, expr: &str, job: F) -> anyhow::Result<()>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let cron: CronSchedule = expr.parse()?;
+        self.register(name, Schedule::Cron(cron), Arc::new(move || job().boxed()));
+        Ok(())
+    }
+
+    /// 停止指定任务（正在执行的一轮会执行完）；返回是否存在
+    pub fn cancel(&self, name: &str) -> bool {
+        let mut entries = lock(&self.entries);
+        let Some(pos) = entries.iter().position(|e| lock(&e.info).name == name) else {
+            return false;
+        };
+        entries.remove(pos).token.cancel();
+        true
+    }
+
+    /// 所有任务的执行情况，按注册顺序
+    pub fn tasks(&self) -> Vec<TaskInfo> {
+        lock(&self.entries)
+            .iter()
+            .map(|e| lock(&e.info).clone())
+            .collect()
+    }
+
+    /// 停止所有任务并等待正在执行的一轮结束
+    pub async fn shutdown(&self) {
+        self.token.cancel();
+        let entries: Vec<Entry> = lock(&self.entries).drain(..).collect();
+        for entry in entries {
+            let _ = entry.task.await;
+        }
+    }
+}
+
+/// GlobalContext 中的调度器；未设置时返回一个新的
+pub async fn of(gctx: &GlobalContext) -> Scheduler {
+    gctx.get::<Scheduler>().await.unwrap_or_default()
+}
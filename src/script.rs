@@ -0,0 +1,214 @@
+//! 非交互式脚本模式
+//!
+//! `--script <file>`（`-` 表示标准输入），或标准输入不是终端时，节点不启动 REPL，而是逐行执行
+//! 其中的 CLI 命令：空行与 `#` 开头的注释跳过，`exit` 提前结束。每条命令在标准输出写一行 JSON
+//! （[`CommandResult`]），脚本结束时再写一行汇总（[`ScriptSummary`]），日志改写到标准错误，
+//! 便于冒烟测试与节点初始化脚本解析：
+//!
+//! ```text
+//! {"line":1,"command":"alias","args":["add","bob","1A2b…"],"ok":true,"output":["Alias bob -> 1A2b…"]}
+//! {"line":2,"command":"frobnicate","args":[],"ok":false,"output":[],"errors":["Unknown command: 'frobnicate'"]}
+//! {"done":true,"commands":2,"failed":1,"exit_code":1}
+//! ```
+//!
+//! 命令的输出经 [`crate::cli_println!`] / [`crate::cli_error!`] 记入该命令的结果；后者同时把
+//! 命令记为失败。未知命令、参数无法解析与 panic 也算失败。默认遇到第一条失败的命令即停止，
+//! `--keep-going` 时继续执行。退出码见 [`EXIT_OK`]、[`EXIT_FAILED`] 与 [`EXIT_UNREADABLE`]。
+
+use std::{cell::RefCell, io::Write, sync::Arc, time::Instant};
+
+use aex::connection::global::GlobalContext;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::io::AsyncBufReadExt;
+
+use crate::cli::{Cli, split_args};
+
+/// 所有命令都成功
+pub const EXIT_OK: i32 = 0;
+/// 至少一条命令失败
+pub const EXIT_FAILED: i32 = 1;
+/// 脚本无法读取
+pub const EXIT_UNREADABLE: i32 = 2;
+
+#[derive(Debug, Default)]
+struct Capture {
+    output: Vec<String>,
+    errors: Vec<String>,
+    data: Option<Value>,
+}
+
+tokio::task_local! {
+    static CAPTURE: RefCell<Capture>;
+}
+
+/// 记录命令的一行输出；不在脚本模式下执行时直接打印
+pub fn emit(line: String) {
+    let mut line = Some(line);
+    let _ = CAPTURE.try_with(|c| c.borrow_mut().output.extend(line.take()));
+    if let Some(line) = line {
+        println!("{}", line);
+    }
+}
+
+/// 记录命令的一行错误输出并把命令记为失败；不在脚本模式下执行时直接打印
+pub fn emit_error(line: String) {
+    let mut line = Some(line);
+    let _ = CAPTURE.try_with(|c| c.borrow_mut().errors.extend(line.take()));
+    if let Some(line) = line {
+        println!("{}", line);
+    }
+}
+
+/// 附上命令的结构化结果（例如 `status` 的快照），只在脚本模式下保留
+pub fn attach<T: Serialize>(data: &T) {
+    let _ = CAPTURE.try_with(|c| c.borrow_mut().data = serde_json::to_value(data).ok());
+}
+
+/// 一条命令的执行结果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommandResult {
+    /// 在脚本中的行号，从 1 开始
+    pub line: usize,
+    pub command: String,
+    pub args: Vec<String>,
+    pub ok: bool,
+    pub output: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+    pub elapsed_ms: u64,
+}
+
+/// 脚本结束时的汇总
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScriptSummary {
+    pub done: bool,
+    pub commands: usize,
+    pub failed: usize,
+    pub exit_code: i32,
+}
+
+/// 执行一条命令并收集输出
+pub async fn execute(
+    cli: &Cli,
+    line_no: usize,
+    line: &str,
+    ctx: Arc<GlobalContext>,
+) -> CommandResult {
+    let started = Instant::now();
+    let (command, args, capture) = match split_args(line) {
+        Err(e) => (
+            String::new(),
+            Vec::new(),
+            Capture {
+                errors: vec![e.to_string()],
+                ..Capture::default()
+            },
+        ),
+        Ok(mut parts) => {
+            let command = parts.remove(0);
+            let capture = match cli.commands.get(&command) {
+                None => Capture {
+                    errors: vec![format!("Unknown command: '{}'", command)],
+                    ..Capture::default()
+                },
+                Some(handler) => {
+                    let fut = handler(parts.clone(), ctx);
+                    // 在独立的任务中执行，panic 只算这条命令失败
+                    let task =
+                        tokio::spawn(CAPTURE.scope(RefCell::new(Capture::default()), async move {
+                            fut.await;
+                            CAPTURE.with(|c| c.take())
+                        }));
+                    match task.await {
+                        Ok(capture) => capture,
+                        Err(e) => Capture {
+                            errors: vec![format!("Command panicked: {}", e)],
+                            ..Capture::default()
+                        },
+                    }
+                }
+            };
+            (command, parts, capture)
+        }
+    };
+    CommandResult {
+        line: line_no,
+        command,
+        args,
+        ok: capture.errors.is_empty(),
+        output: capture.output,
+        errors: capture.errors,
+        data: capture.data,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+fn write_json<W: Write, T: Serialize>(out: &mut W, value: &T) -> std::io::Result<()> {
+    serde_json::to_writer(&mut *out, value)?;
+    writeln!(out)?;
+    out.flush()
+}
+
+/// 逐行执行 `reader` 中的命令，结果以 JSON Lines 写入 `out`；返回进程退出码
+pub async fn run<R, W>(
+    cli: &Cli,
+    reader: R,
+    ctx: Arc<GlobalContext>,
+    keep_going: bool,
+    out: &mut W,
+) -> i32
+where
+    R: tokio::io::AsyncBufRead + Unpin,
+    W: Write,
+{
+    let mut lines = reader.lines();
+    let (mut line_no, mut commands, mut failed) = (0, 0, 0);
+    let mut exit_code = EXIT_OK;
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(e) => {
+                tracing::error!("Failed to read script: {}", e);
+                exit_code = EXIT_UNREADABLE;
+                break;
+            }
+        };
+        line_no += 1;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if trimmed == "exit" {
+            break;
+        }
+
+        let result = execute(cli, line_no, trimmed, ctx.clone()).await;
+        commands += 1;
+        if !result.ok {
+            failed += 1;
+            exit_code = EXIT_FAILED;
+        }
+        if let Err(e) = write_json(out, &result) {
+            tracing::error!("Failed to write script output: {}", e);
+            return EXIT_FAILED;
+        }
+        if !result.ok && !keep_going {
+            break;
+        }
+    }
+
+    let summary = ScriptSummary {
+        done: true,
+        commands,
+        failed,
+        exit_code,
+    };
+    if let Err(e) = write_json(out, &summary) {
+        tracing::error!("Failed to write script output: {}", e);
+    }
+    exit_code
+}
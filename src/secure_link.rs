@@ -0,0 +1,739 @@
+//! 节点间链路加密（Noise XX 风格握手）
+//!
+//! 握手流程：
+//! 1. 主动方发送 X25519 临时公钥与按偏好排序的密码套件列表；被动方回复自己的临时公钥、
+//!    选定的套件（主动方列表中第一个双方都支持的）以及自己支持的套件列表
+//! 2. 以 DH 共享密钥经 HKDF-SHA256 派生两个方向的密钥，AEAD 由选定的套件决定
+//! 3. 双方在加密通道内发送身份证明：地址、公钥、对握手记录哈希的签名，
+//!    从而把链路绑定到 `FreeWebMovementAddress`
+//!
+//! 握手记录哈希覆盖双方的套件列表与选定的套件，中间人篡改列表会使身份证明校验失败；
+//! 主动方还会按双方列表重新计算选择结果，被动方选了更弱的套件时拒绝连接（防降级）。
+//! 选定的套件记录在 [`SecureLink::suite`]。身份证明中的地址必须由其公钥推导得出，
+//! 否则持有任意密钥的对端都能冒用他人的地址。
+//!
+//! 之后所有记录均为 `u32 长度 + 密文`，每个方向使用递增计数器作为 nonce。
+//!
+//! P2P 连接在发送第一个帧之前协商链路：出站连接由 [`crate::proxy`] 在注册到
+//! ConnectionManager 之前调用 [`establish_outbound`]，入站连接由 TCP router 的入口调用
+//! [`establish_inbound`]。握手完成后连接 Context 的读写两端换成明文管道（见
+//! [`SecureLink::into_plaintext`]），帧循环与发送方不感知加密；对端身份与协商出的套件记录在
+//! [`LinkSession`]。出站握手期望的对端地址取自拨号记录（`NodeRecord::node_address`），
+//! 对端证明的身份不符时断开，路径上的中间人无法冒充所拨的节点。
+//!
+//! 链路加密按配置 `[secure_link] mode` 协商，与不支持链路握手的旧版本节点保持互通：
+//!
+//! - 主动方在握手前发送 [`LINK_MAGIC`]，其首字节 0xFF 既不是协议版本，也不是压缩 / 格式标记，
+//!   被动方据此区分链路握手与旧版本节点直接发来的明文帧；
+//! - `required`：出站总是握手，入站拒绝明文连接；
+//! - `preferred`（默认）：对端最近一次会话声明了 [`CAP_SECURE_LINK`] 时握手，声明中没有时
+//!   以明文连接；从未完成过会话的对端先尝试握手，失败后记入 [`PlaintextPeers`]，
+//!   之后的拨号以明文进行。入站两者都接受；
+//! - `disabled`：出站不握手，入站仍接受对端发起的链路握手。
+//!
+//! ```toml
+//! [secure_link]
+//! mode = "required"
+//! ```
+
+use std::{fmt, net::SocketAddr, sync::Arc};
+
+use aes_gcm::Aes256Gcm;
+use bincode::{Decode, Encode};
+use chacha20poly1305::{
+    ChaCha20Poly1305, Key, Nonce,
+    aead::{Aead, KeyInit},
+};
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::{
+    io::{
+        AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter, DuplexStream,
+        ReadHalf, WriteHalf,
+    },
+    net::TcpStream,
+    sync::Mutex,
+};
+use x25519_dalek::{EphemeralSecret, PublicKey};
+use zz_account::address::FreeWebMovementAddress;
+
+use aex::{
+    connection::{context::Context, global::GlobalContext},
+    tcp::types::Codec,
+};
+use dashmap::DashSet;
+
+use crate::config::SharedConfig;
+use crate::node::Node;
+use crate::protocols::capabilities::CAP_SECURE_LINK;
+use crate::retry::{self, Operation};
+
+const LINK_PROTOCOL_LABEL: &[u8] = b"zz-p2p-link-v2";
+/// 单条加密记录的最大长度
+pub const MAX_RECORD_LEN: usize = 16 * 1024 * 1024;
+/// 握手中最多列出的套件数
+const MAX_OFFERED_SUITES: usize = 16;
+/// 明文管道的缓冲区大小，也是单条加密记录承载的最大明文长度
+pub const LINK_PIPE_CAPACITY: usize = 64 * 1024;
+
+/// 链路密码套件：密钥交换固定为 X25519 + HKDF-SHA256，AEAD 可选
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CipherSuite {
+    X25519ChaCha20Poly1305 = 1,
+    X25519Aes256Gcm = 2,
+}
+
+/// 本节点支持的套件，按偏好排序
+pub const LOCAL_CIPHER_SUITES: &[CipherSuite] = &[
+    CipherSuite::X25519ChaCha20Poly1305,
+    CipherSuite::X25519Aes256Gcm,
+];
+
+impl CipherSuite {
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// 未知的编号返回 `None`
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(CipherSuite::X25519ChaCha20Poly1305),
+            2 => Some(CipherSuite::X25519Aes256Gcm),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CipherSuite::X25519ChaCha20Poly1305 => "x25519-chacha20poly1305",
+            CipherSuite::X25519Aes256Gcm => "x25519-aes256gcm",
+        }
+    }
+}
+
+impl fmt::Display for CipherSuite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// 按主动方的偏好选出第一个双方都支持的套件
+pub fn negotiate(offered: &[u8], supported: &[u8]) -> Option<CipherSuite> {
+    offered
+        .iter()
+        .filter(|id| supported.contains(id))
+        .find_map(|id| CipherSuite::from_id(*id))
+}
+
+fn suite_ids(suites: &[CipherSuite]) -> Vec<u8> {
+    suites.iter().map(|s| s.id()).collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct IdentityProof {
+    pub address: String,
+    pub public_key: Vec<u8>,
+    /// 对握手记录哈希的签名
+    pub signature: Vec<u8>,
+}
+
+impl Codec for IdentityProof {}
+
+impl IdentityProof {
+    fn create(identity: &FreeWebMovementAddress, transcript: &[u8; 32]) -> Self {
+        let signature = FreeWebMovementAddress::sign_message(&identity.private_key, transcript)
+            .serialize_compact()
+            .to_vec();
+        Self {
+            address: identity.to_string(),
+            public_key: identity.public_key.to_bytes().to_vec(),
+            signature,
+        }
+    }
+
+    /// 地址必须由公钥推导得出，且签名有效
+    pub fn verify(&self, transcript: &[u8; 32]) -> bool {
+        if derive_address(&self.public_key).as_deref() != Some(self.address.as_str()) {
+            return false;
+        }
+        if bitcoin::secp256k1::ecdsa::Signature::from_compact(&self.signature).is_err() {
+            return false;
+        }
+        let public_key = FreeWebMovementAddress::to_public_key(&self.public_key);
+        let signature = FreeWebMovementAddress::to_signature(&self.signature);
+        FreeWebMovementAddress::verify_message(&public_key, transcript, &signature)
+    }
+}
+
+/// 由压缩格式 secp256k1 公钥推导 `FreeWebMovementAddress` 的地址（P2PKH）；公钥非法时为 `None`
+pub fn derive_address(public_key: &[u8]) -> Option<String> {
+    let public_key = bitcoin::PublicKey::from_slice(public_key).ok()?;
+    Some(bitcoin::Address::p2pkh(public_key.pubkey_hash(), bitcoin::Network::Bitcoin).to_string())
+}
+
+enum LinkCipher {
+    ChaCha20Poly1305(ChaCha20Poly1305),
+    Aes256Gcm(Box<Aes256Gcm>),
+}
+
+impl LinkCipher {
+    fn new(suite: CipherSuite, key: &[u8; 32]) -> Self {
+        match suite {
+            CipherSuite::X25519ChaCha20Poly1305 => {
+                LinkCipher::ChaCha20Poly1305(ChaCha20Poly1305::new(Key::from_slice(key)))
+            }
+            CipherSuite::X25519Aes256Gcm => {
+                LinkCipher::Aes256Gcm(Box::new(Aes256Gcm::new(&(*key).into())))
+            }
+        }
+    }
+
+    fn encrypt(&self, nonce: &[u8; 12], plaintext: &[u8]) -> Result<Vec<u8>, aes_gcm::Error> {
+        match self {
+            LinkCipher::ChaCha20Poly1305(c) => c.encrypt(Nonce::from_slice(nonce), plaintext),
+            LinkCipher::Aes256Gcm(c) => c.encrypt(Nonce::from_slice(nonce), plaintext),
+        }
+    }
+
+    fn decrypt(&self, nonce: &[u8; 12], ciphertext: &[u8]) -> Result<Vec<u8>, aes_gcm::Error> {
+        match self {
+            LinkCipher::ChaCha20Poly1305(c) => c.decrypt(Nonce::from_slice(nonce), ciphertext),
+            LinkCipher::Aes256Gcm(c) => c.decrypt(Nonce::from_slice(nonce), ciphertext),
+        }
+    }
+}
+
+struct CipherState {
+    cipher: LinkCipher,
+    counter: u64,
+}
+
+impl CipherState {
+    fn new(suite: CipherSuite, key: &[u8; 32]) -> Self {
+        Self {
+            cipher: LinkCipher::new(suite, key),
+            counter: 0,
+        }
+    }
+
+    fn next_nonce(&mut self) -> anyhow::Result<[u8; 12]> {
+        if self.counter == u64::MAX {
+            return Err(anyhow::anyhow!(
+                "Link nonce exhausted, re-handshake required"
+            ));
+        }
+        let mut nonce = [0u8; 12];
+        nonce[4..].copy_from_slice(&self.counter.to_le_bytes());
+        self.counter += 1;
+        Ok(nonce)
+    }
+
+    fn seal(&mut self, plaintext: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let nonce = self.next_nonce()?;
+        self.cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| anyhow::anyhow!("Link encryption failed"))
+    }
+
+    fn open(&mut self, ciphertext: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let nonce = self.next_nonce()?;
+        self.cipher
+            .decrypt(&nonce, ciphertext)
+            .map_err(|_| anyhow::anyhow!("Link decryption failed"))
+    }
+}
+
+/// 已完成握手的加密链路
+pub struct SecureLink<S> {
+    stream: S,
+    tx_cipher: CipherState,
+    rx_cipher: CipherState,
+    /// 对端经过签名验证的地址
+    pub remote_address: String,
+    /// 协商出的密码套件
+    pub suite: CipherSuite,
+}
+
+/// 握手记录哈希：双方临时公钥、双方的套件列表与选定的套件
+fn transcript_hash(
+    initiator: &[u8; 32],
+    responder: &[u8; 32],
+    offered: &[u8],
+    supported: &[u8],
+    chosen: u8,
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(LINK_PROTOCOL_LABEL);
+    hasher.update(initiator);
+    hasher.update(responder);
+    for list in [offered, supported] {
+        hasher.update([list.len() as u8]);
+        hasher.update(list);
+    }
+    hasher.update([chosen]);
+    hasher.finalize().into()
+}
+
+async fn write_suites<S: AsyncWrite + Unpin>(stream: &mut S, suites: &[u8]) -> anyhow::Result<()> {
+    stream.write_all(&[suites.len() as u8]).await?;
+    stream.write_all(suites).await?;
+    Ok(())
+}
+
+async fn read_suites<S: AsyncRead + Unpin>(stream: &mut S) -> anyhow::Result<Vec<u8>> {
+    let mut len = [0u8; 1];
+    stream.read_exact(&mut len).await?;
+    let len = len[0] as usize;
+    if len == 0 || len > MAX_OFFERED_SUITES {
+        return Err(anyhow::anyhow!("Invalid cipher suite list length: {}", len));
+    }
+    let mut suites = vec![0u8; len];
+    stream.read_exact(&mut suites).await?;
+    Ok(suites)
+}
+
+fn derive_keys(shared: &[u8; 32], transcript: &[u8; 32]) -> anyhow::Result<([u8; 32], [u8; 32])> {
+    let hk = Hkdf::<Sha256>::new(Some(transcript), shared);
+    let mut okm = [0u8; 64];
+    hk.expand(LINK_PROTOCOL_LABEL, &mut okm)
+        .map_err(|_| anyhow::anyhow!("HKDF expand failed"))?;
+    let mut i2r = [0u8; 32];
+    let mut r2i = [0u8; 32];
+    i2r.copy_from_slice(&okm[..32]);
+    r2i.copy_from_slice(&okm[32..]);
+    Ok((i2r, r2i))
+}
+
+async fn write_record<S: AsyncWrite + Unpin>(stream: &mut S, data: &[u8]) -> anyhow::Result<()> {
+    stream.write_all(&(data.len() as u32).to_be_bytes()).await?;
+    stream.write_all(data).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+async fn read_record<S: AsyncRead + Unpin>(stream: &mut S) -> anyhow::Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len).await?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_RECORD_LEN {
+        return Err(anyhow::anyhow!("Link record too large: {}", len));
+    }
+    let mut buf = vec![0u8; len];
+    stream.read_exact(&mut buf).await?;
+    Ok(buf)
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> SecureLink<S> {
+    /// 主动连接方握手。`expected` 为期望的对端地址（已知时校验）。
+    pub async fn initiate(
+        stream: S,
+        identity: &FreeWebMovementAddress,
+        expected: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        Self::initiate_with(stream, identity, expected, LOCAL_CIPHER_SUITES).await
+    }
+
+    /// 以指定的套件列表（按偏好排序）主动握手
+    pub async fn initiate_with(
+        mut stream: S,
+        identity: &FreeWebMovementAddress,
+        expected: Option<&str>,
+        suites: &[CipherSuite],
+    ) -> anyhow::Result<Self> {
+        let offered = suite_ids(suites);
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let local_pk = PublicKey::from(&secret);
+        stream.write_all(local_pk.as_bytes()).await?;
+        write_suites(&mut stream, &offered).await?;
+        stream.flush().await?;
+
+        let mut remote_pk = [0u8; 32];
+        stream.read_exact(&mut remote_pk).await?;
+        let mut chosen = [0u8; 1];
+        stream.read_exact(&mut chosen).await?;
+        let supported = read_suites(&mut stream).await?;
+        let chosen = chosen[0];
+
+        // 被动方必须选出按双方列表应当选出的套件，否则视为降级
+        let expected_suite = negotiate(&offered, &supported);
+        let suite = match CipherSuite::from_id(chosen) {
+            Some(suite) if Some(suite) == expected_suite => suite,
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Cipher suite downgrade or mismatch: peer chose {}, expected {}",
+                    chosen,
+                    expected_suite.map_or("none", |s| s.name())
+                ));
+            }
+        };
+
+        let transcript = transcript_hash(
+            local_pk.as_bytes(),
+            &remote_pk,
+            &offered,
+            &supported,
+            chosen,
+        );
+        let shared = secret.diffie_hellman(&PublicKey::from(remote_pk));
+        let (i2r, r2i) = derive_keys(shared.as_bytes(), &transcript)?;
+
+        let mut link = Self {
+            stream,
+            tx_cipher: CipherState::new(suite, &i2r),
+            rx_cipher: CipherState::new(suite, &r2i),
+            remote_address: String::new(),
+            suite,
+        };
+        link.exchange_proofs(identity, &transcript, expected)
+            .await?;
+        Ok(link)
+    }
+
+    /// 被动接受方握手
+    pub async fn accept(
+        stream: S,
+        identity: &FreeWebMovementAddress,
+        expected: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        Self::accept_with(stream, identity, expected, LOCAL_CIPHER_SUITES).await
+    }
+
+    /// 以指定的支持套件被动握手；没有共同套件时回复 0 并返回错误
+    pub async fn accept_with(
+        mut stream: S,
+        identity: &FreeWebMovementAddress,
+        expected: Option<&str>,
+        suites: &[CipherSuite],
+    ) -> anyhow::Result<Self> {
+        let mut remote_pk = [0u8; 32];
+        stream.read_exact(&mut remote_pk).await?;
+        let offered = read_suites(&mut stream).await?;
+        let supported = suite_ids(suites);
+        let suite = negotiate(&offered, &supported);
+        let chosen = suite.map_or(0, |s| s.id());
+
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let local_pk = PublicKey::from(&secret);
+        stream.write_all(local_pk.as_bytes()).await?;
+        stream.write_all(&[chosen]).await?;
+        write_suites(&mut stream, &supported).await?;
+        stream.flush().await?;
+
+        let Some(suite) = suite else {
+            return Err(anyhow::anyhow!(
+                "No common cipher suite, peer offered {:?}",
+                offered
+            ));
+        };
+
+        let transcript = transcript_hash(
+            &remote_pk,
+            local_pk.as_bytes(),
+            &offered,
+            &supported,
+            chosen,
+        );
+        let shared = secret.diffie_hellman(&PublicKey::from(remote_pk));
+        let (i2r, r2i) = derive_keys(shared.as_bytes(), &transcript)?;
+
+        let mut link = Self {
+            stream,
+            tx_cipher: CipherState::new(suite, &r2i),
+            rx_cipher: CipherState::new(suite, &i2r),
+            remote_address: String::new(),
+            suite,
+        };
+        link.exchange_proofs(identity, &transcript, expected)
+            .await?;
+        Ok(link)
+    }
+
+    async fn exchange_proofs(
+        &mut self,
+        identity: &FreeWebMovementAddress,
+        transcript: &[u8; 32],
+        expected: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let proof = IdentityProof::create(identity, transcript);
+        self.send(&Codec::encode(&proof)?).await?;
+
+        let remote: IdentityProof = Codec::decode(&self.recv().await?)?;
+        if !remote.verify(transcript) {
+            return Err(anyhow::anyhow!(
+                "Identity proof from {} failed verification",
+                remote.address
+            ));
+        }
+        if let Some(expected) = expected {
+            if remote.address != expected {
+                return Err(anyhow::anyhow!(
+                    "Unexpected peer identity: expected {}, got {}",
+                    expected,
+                    remote.address
+                ));
+            }
+        }
+        tracing::info!(
+            "🔐 Secure link with {} using {}",
+            remote.address,
+            self.suite
+        );
+        self.remote_address = remote.address;
+        Ok(())
+    }
+
+    /// 加密并发送一条记录
+    pub async fn send(&mut self, plaintext: &[u8]) -> anyhow::Result<()> {
+        let sealed = self.tx_cipher.seal(plaintext)?;
+        write_record(&mut self.stream, &sealed).await
+    }
+
+    /// 接收并解密一条记录
+    pub async fn recv(&mut self) -> anyhow::Result<Vec<u8>> {
+        let sealed = read_record(&mut self.stream).await?;
+        self.rx_cipher.open(&sealed)
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Send + 'static> SecureLink<S> {
+    /// 拆成明文的读写两端：后台任务在明文管道与加密记录之间双向转发。
+    /// 对端关闭或记录解密失败时读端收到 EOF；明文写端关闭时关闭底层连接的写方向
+    pub fn into_plaintext(self) -> (ReadHalf<DuplexStream>, WriteHalf<DuplexStream>) {
+        let (plain, link_side) = tokio::io::duplex(LINK_PIPE_CAPACITY);
+        let (mut from_app, mut to_app) = tokio::io::split(link_side);
+        let (mut rx_stream, mut tx_stream) = tokio::io::split(self.stream);
+        let (mut rx_cipher, mut tx_cipher) = (self.rx_cipher, self.tx_cipher);
+        let remote = self.remote_address;
+
+        let peer = remote.clone();
+        tokio::spawn(async move {
+            loop {
+                let plaintext = match read_record(&mut rx_stream).await {
+                    Ok(sealed) => rx_cipher.open(&sealed),
+                    Err(e) => Err(e),
+                };
+                match plaintext {
+                    Ok(plaintext) => {
+                        if to_app.write_all(&plaintext).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        tracing::debug!("Secure link from {} closed: {}", peer, e);
+                        break;
+                    }
+                }
+            }
+            let _ = to_app.shutdown().await;
+        });
+
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; LINK_PIPE_CAPACITY];
+            loop {
+                let n = match from_app.read(&mut buf).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => n,
+                };
+                let sent = match tx_cipher.seal(&buf[..n]) {
+                    Ok(sealed) => write_record(&mut tx_stream, &sealed).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = sent {
+                    tracing::debug!("Secure link to {} closed: {}", remote, e);
+                    break;
+                }
+            }
+            let _ = tx_stream.shutdown().await;
+        });
+
+        tokio::io::split(plain)
+    }
+}
+
+/// 主动方在链路握手前发送的前缀
+pub const LINK_MAGIC: [u8; 4] = [0xFF, b'Z', b'L', 2];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecureLinkMode {
+    /// 只使用加密链路
+    Required,
+    /// 对端支持时使用加密链路
+    #[default]
+    Preferred,
+    /// 不主动发起链路握手
+    Disabled,
+}
+
+/// 配置文件中的 `[secure_link]`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecureLinkConfig {
+    pub mode: SecureLinkMode,
+}
+
+/// 链路握手失败、按旧版本节点以明文连接的端点，保存在 GlobalContext 中
+pub type PlaintextPeers = Arc<DashSet<SocketAddr>>;
+
+/// 当前配置中的链路加密模式
+pub async fn mode(gctx: &GlobalContext) -> SecureLinkMode {
+    match gctx.get::<SharedConfig>().await {
+        Some(config) => config.read().await.secure_link.mode,
+        None => SecureLinkMode::default(),
+    }
+}
+
+/// 出站连接是否发起链路握手。`features` 为对端最近一次会话声明的特性位（未知为 `None`），
+/// `failed_before` 表示此前与该端点的链路握手失败过
+pub fn should_initiate(mode: SecureLinkMode, features: Option<u32>, failed_before: bool) -> bool {
+    match mode {
+        SecureLinkMode::Required => true,
+        SecureLinkMode::Disabled => false,
+        SecureLinkMode::Preferred => match features {
+            Some(features) => features & CAP_SECURE_LINK != 0,
+            None => !failed_before,
+        },
+    }
+}
+
+/// 完成链路握手的连接在其 Context 中保存对端经过验证的身份与协商出的密码套件
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkSession {
+    pub remote_address: String,
+    pub suite: CipherSuite,
+}
+
+async fn local_identity(gctx: &Arc<GlobalContext>) -> anyhow::Result<FreeWebMovementAddress> {
+    gctx.get::<FreeWebMovementAddress>()
+        .await
+        .ok_or_else(|| anyhow::anyhow!("Node identity not set in GlobalContext"))
+}
+
+/// 出站连接协商后的链路
+pub enum OutboundLink {
+    /// 完成链路握手：明文读写两端与对端身份
+    Secure(ReadHalf<DuplexStream>, WriteHalf<DuplexStream>, LinkSession),
+    /// 以明文连接（对端不支持或配置不使用链路加密）
+    Plain(TcpStream),
+}
+
+/// 拨号记录中 `addr` 所属节点的地址与最近一次会话声明的特性位
+async fn dial_record(gctx: &GlobalContext, addr: SocketAddr) -> (Option<String>, Option<u32>) {
+    let Some(node) = gctx.get::<Arc<Node>>().await else {
+        return (None, None);
+    };
+    for registry in [&node.inner, &node.external] {
+        if let Some(record) = registry.read().get(addr) {
+            let features = record.capabilities.as_ref().map(|c| c.features);
+            return (record.node_address.clone(), features);
+        }
+    }
+    (None, None)
+}
+
+/// 出站连接的链路协商：按 `[secure_link] mode` 与拨号记录决定是否握手，
+/// 握手时要求对端证明的身份与拨号记录中的节点地址一致
+pub async fn establish_outbound(
+    gctx: &Arc<GlobalContext>,
+    addr: SocketAddr,
+    mut stream: TcpStream,
+) -> anyhow::Result<OutboundLink> {
+    let mode = mode(gctx).await;
+    let (expected, features) = dial_record(gctx, addr).await;
+    let plaintext_peers = gctx.get::<PlaintextPeers>().await;
+    let failed_before = plaintext_peers
+        .as_ref()
+        .is_some_and(|peers| peers.contains(&addr));
+    if !should_initiate(mode, features, failed_before) {
+        tracing::debug!("Plaintext link with {} ({:?})", addr, mode);
+        return Ok(OutboundLink::Plain(stream));
+    }
+
+    let identity = local_identity(gctx).await?;
+    let what = format!("secure link with {}", addr);
+    let timeout = retry::policy(gctx, Operation::Handshake).await.timeout;
+    let handshake = retry::within(timeout, &what, async {
+        stream.write_all(&LINK_MAGIC).await?;
+        SecureLink::initiate(stream, &identity, expected.as_deref()).await
+    })
+    .await;
+    let link = match handshake {
+        Ok(link) => link,
+        Err(e) => {
+            // 未知能力的对端可能是旧版本节点，之后的拨号以明文进行
+            if mode == SecureLinkMode::Preferred && features.is_none() {
+                if let Some(peers) = plaintext_peers {
+                    peers.insert(addr);
+                }
+            }
+            return Err(e);
+        }
+    };
+    let session = LinkSession {
+        remote_address: link.remote_address.clone(),
+        suite: link.suite,
+    };
+    let (reader, writer) = link.into_plaintext();
+    Ok(OutboundLink::Secure(reader, writer, session))
+}
+
+/// 入站连接的链路协商：先把读写两端移出 Context，握手期间该连接上的发送直接失败，
+/// 不会有明文帧混入握手；完成后换上明文管道并记录 [`LinkSession`]。
+/// 对端没有发送 [`LINK_MAGIC`] 时按明文连接放回读写两端（`required` 时拒绝）
+pub async fn establish_inbound(ctx: &Arc<Mutex<Context>>) -> anyhow::Result<()> {
+    let (gctx, addr, reader, writer) = {
+        let mut guard = ctx.lock().await;
+        (
+            guard.global.clone(),
+            guard.addr,
+            guard.reader.take(),
+            guard.writer.take(),
+        )
+    };
+    let (Some(mut reader), Some(writer)) = (reader, writer) else {
+        anyhow::bail!("connection from {} has no reader or writer", addr);
+    };
+    let what = format!("secure link from {}", addr);
+    let timeout = retry::policy(&gctx, Operation::Handshake).await.timeout;
+    let mut prefix = [0u8; LINK_MAGIC.len()];
+    retry::within(timeout, &what, async {
+        reader.read_exact(&mut prefix).await?;
+        Ok(())
+    })
+    .await?;
+    if prefix != LINK_MAGIC {
+        if mode(&gctx).await == SecureLinkMode::Required {
+            anyhow::bail!(
+                "plaintext connection from {} rejected by [secure_link] mode",
+                addr
+            );
+        }
+        tracing::debug!("Plaintext link from {}", addr);
+        let reader = std::io::Cursor::new(prefix.to_vec()).chain(reader);
+        let mut guard = ctx.lock().await;
+        guard.reader = Some(Box::new(BufReader::new(reader)));
+        guard.writer = Some(writer);
+        return Ok(());
+    }
+
+    let identity = local_identity(&gctx).await?;
+    let stream = tokio::io::join(reader, writer);
+    let link = retry::within(timeout, &what, SecureLink::accept(stream, &identity, None)).await?;
+    let session = LinkSession {
+        remote_address: link.remote_address.clone(),
+        suite: link.suite,
+    };
+    let (reader, writer) = link.into_plaintext();
+    let mut guard = ctx.lock().await;
+    guard.reader = Some(Box::new(BufReader::new(reader)));
+    guard.writer = Some(Box::new(BufWriter::new(writer)));
+    guard.set(session);
+    Ok(())
+}
@@ -0,0 +1,169 @@
+//! 运行时编辑持久化的服务器列表
+//!
+//! 运维不必手改存储文件再重启节点：REPL 的 `peer add <ip:port> [--pin]`、`peer rm`、
+//! `peer pin` / `peer unpin` 与控制接口的 `/servers` 直接修改内存中的内网 / 外网服务器列表
+//! （[`crate::record::NodeRegistry`]，与 `connect` 一样两个列表同时写入），并立即写回存储。
+//!
+//! - 新增的地址立即在后台拨号一次，之后与其它记录一样参与启动拨号和
+//!   [`crate::peer_maintenance`] 的重新验证；
+//! - 固定（pinned）的记录不会被维护任务删除或衰减，长期未见也总在启动时拨号；
+//! - 同一节点的多个地址（例如 IPv4 与 IPv6）是一条记录，用其中任一地址固定或删除都作用于整个节点；
+//! - 删除记录不会关闭已有连接，需要时另用 `disconnect`。
+
+use std::{net::SocketAddr, sync::Arc};
+
+use aex::connection::global::GlobalContext;
+use serde::Serialize;
+
+use crate::{
+    bootstrap,
+    node::Node,
+    protocols::commands::ack,
+    record::{NodeRecord, SharedNodeRegistry},
+};
+
+/// 一条服务器记录及其所在的列表
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ServerEntry {
+    pub endpoint: SocketAddr,
+    /// 同一节点的其它地址（例如 IPv6）
+    pub alt_endpoints: Vec<SocketAddr>,
+    /// 握手中得知的节点地址
+    pub node_address: Option<String>,
+    pub pinned: bool,
+    pub is_available: bool,
+    pub score: f64,
+    /// 最近一次成功通信（秒）
+    pub last_seen: i64,
+    /// `inner` / `external`
+    pub lists: Vec<&'static str>,
+}
+
+async fn node(gctx: &Arc<GlobalContext>) -> anyhow::Result<Arc<Node>> {
+    gctx.get::<Arc<Node>>()
+        .await
+        .ok_or_else(|| anyhow::anyhow!("Node is not running"))
+}
+
+fn registries(node: &Node) -> [(&'static str, &SharedNodeRegistry); 2] {
+    [("inner", &node.inner), ("external", &node.external)]
+}
+
+/// 解析 `ip:port`
+pub fn parse_endpoint(s: &str) -> anyhow::Result<SocketAddr> {
+    s.trim()
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid address '{}', expected ip:port", s))
+}
+
+/// 两个列表中的全部记录，按地址排序
+pub async fn list(gctx: &Arc<GlobalContext>) -> anyhow::Result<Vec<ServerEntry>> {
+    let node = node(gctx).await?;
+    let mut entries: Vec<ServerEntry> = Vec::new();
+    for (name, registry) in registries(&node) {
+        for record in registry.read().nodes.iter() {
+            match entries.iter_mut().find(|e| e.endpoint == record.endpoint) {
+                Some(entry) => {
+                    entry.pinned |= record.pinned;
+                    entry.lists.push(name);
+                }
+                None => entries.push(entry_of(record, name)),
+            }
+        }
+    }
+    entries.sort_by_key(|e| e.endpoint);
+    Ok(entries)
+}
+
+fn entry_of(record: &NodeRecord, list: &'static str) -> ServerEntry {
+    ServerEntry {
+        endpoint: record.endpoint,
+        alt_endpoints: record.alt_endpoints.clone(),
+        node_address: record.node_address.clone(),
+        pinned: record.pinned,
+        is_available: record.is_available,
+        score: record.score(),
+        last_seen: record.last_seen.timestamp(),
+        lists: vec![list],
+    }
+}
+
+/// 添加记录（已存在时只在 `pin` 为 true 时固定）并立即拨号；返回是否为新记录
+pub async fn add(
+    gctx: &Arc<GlobalContext>,
+    endpoint: SocketAddr,
+    pin: bool,
+) -> anyhow::Result<bool> {
+    let node = node(gctx).await?;
+    let mut added = false;
+    for (_, registry) in registries(&node) {
+        added |= registry.write().add(endpoint, pin);
+    }
+    node.save_registries().await?;
+    tracing::info!(
+        "📝 Server list: {} {}{}",
+        if added { "added" } else { "kept" },
+        endpoint,
+        if pin { " (pinned)" } else { "" }
+    );
+    spawn_dial(gctx.clone(), node, endpoint);
+    Ok(added)
+}
+
+/// 从两个列表中删除记录；返回是否存在
+pub async fn remove(gctx: &Arc<GlobalContext>, endpoint: SocketAddr) -> anyhow::Result<bool> {
+    let node = node(gctx).await?;
+    let mut removed = false;
+    for (_, registry) in registries(&node) {
+        removed |= registry.write().remove(endpoint);
+    }
+    if removed {
+        node.save_registries().await?;
+        tracing::info!("📝 Server list: removed {}", endpoint);
+    }
+    Ok(removed)
+}
+
+/// 固定或取消固定记录；返回记录是否存在
+pub async fn set_pinned(
+    gctx: &Arc<GlobalContext>,
+    endpoint: SocketAddr,
+    pinned: bool,
+) -> anyhow::Result<bool> {
+    let node = node(gctx).await?;
+    let mut found = false;
+    for (_, registry) in registries(&node) {
+        found |= registry.write().set_pinned(endpoint, pinned);
+    }
+    if found {
+        node.save_registries().await?;
+        tracing::info!(
+            "📝 Server list: {} {}",
+            if pinned { "pinned" } else { "unpinned" },
+            endpoint
+        );
+    }
+    Ok(found)
+}
+
+/// 后台拨号新加入的地址，结果记入列表；自身地址或已连接的地址跳过
+fn spawn_dial(gctx: Arc<GlobalContext>, node: Arc<Node>, endpoint: SocketAddr) {
+    if bootstrap::is_self(&endpoint, &gctx.addr) || gctx.manager.find_entry(&endpoint).is_some() {
+        return;
+    }
+    tokio::spawn(async move {
+        let ok = match ack::dial_peer(gctx.clone(), endpoint).await {
+            Ok(_) => true,
+            Err(e) => {
+                tracing::warn!("⚠️ Dial {} failed: {}", endpoint, e);
+                false
+            }
+        };
+        for (_, registry) in registries(&node) {
+            registry.write().record_probe(endpoint, ok);
+        }
+        if let Err(e) = node.save_registries().await {
+            tracing::warn!("Failed to save peer lists: {}", e);
+        }
+    });
+}
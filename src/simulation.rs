@@ -0,0 +1,459 @@
+//! 节点动荡（churn）模拟与浸泡测试
+//!
+//! 以 `--features simulation` 构建。[`Simulation`] 在 127.0.0.1 上启动若干个进程内节点
+//! （数据目录位于系统临时目录），每一轮按 [`ChurnConfig`]：
+//!
+//! 1. 重启上一轮停止的节点，再随机停止几个节点；
+//! 2. 随机断开已有连接、随机建立新连接；
+//! 3. 等待网络稳定后检查连接：运行中的节点不能在宽限期之后仍连着已停止的节点，
+//!    也不能有迟迟没有完成握手的连接；
+//! 4. 在已直连的节点之间随机发送文本消息，等待一轮的时长。
+//!
+//! 结束时停止所有节点，检查每个节点的连接都已关闭，并统计任务中的 panic 次数与消息送达率。
+//! 违反的不变量记录在 [`SoakReport::violations`] 中。随机数由 `seed` 决定，同一配置可以复现
+//! 同一串操作（网络时序仍可能不同）。
+
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::{
+        Arc, Once,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use dashmap::DashSet;
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+use serde::Serialize;
+use tokio::{sync::broadcast::error::RecvError, task::JoinHandle};
+
+use crate::{cli::Opt, clis::send, config::Config, events::NodeEvent, node::Node};
+
+static PANICS: AtomicUsize = AtomicUsize::new(0);
+static PANIC_HOOK: Once = Once::new();
+
+/// 安装 panic 计数钩子（进程内只安装一次，原有钩子照常执行）
+pub fn install_panic_counter() {
+    PANIC_HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            PANICS.fetch_add(1, Ordering::SeqCst);
+            previous(info);
+        }));
+    });
+}
+
+/// 安装钩子以来发生的 panic 次数（包括 tokio 任务中被捕获的 panic）
+pub fn panic_count() -> usize {
+    PANICS.load(Ordering::SeqCst)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChurnConfig {
+    pub nodes: usize,
+    pub rounds: usize,
+    /// 第一个节点的端口，其余节点依次加一
+    pub base_port: u16,
+    /// 每轮新建的连接数
+    pub connects_per_round: usize,
+    /// 每轮断开的连接数
+    pub disconnects_per_round: usize,
+    /// 每轮停止的节点数，下一轮重启
+    pub restarts_per_round: usize,
+    /// 每轮发送的消息数
+    pub messages_per_round: usize,
+    /// 操作之后等待网络稳定的时间
+    pub settle_ms: u64,
+    /// 发送消息后等待送达的时间
+    pub round_ms: u64,
+    /// 节点停止后，对端允许保留到它的连接的时间
+    pub leak_grace_ms: u64,
+    /// 要求的最低送达率
+    pub min_delivery_rate: f64,
+    pub seed: u64,
+}
+
+impl Default for ChurnConfig {
+    fn default() -> Self {
+        Self {
+            nodes: 24,
+            rounds: 20,
+            base_port: 23000,
+            connects_per_round: 8,
+            disconnects_per_round: 4,
+            restarts_per_round: 2,
+            messages_per_round: 20,
+            settle_ms: 500,
+            // 超过重排缓冲区的等待时间，重启过的接收方也能在本轮内投递
+            round_ms: 2500,
+            leak_grace_ms: 5000,
+            min_delivery_rate: 0.9,
+            seed: 1,
+        }
+    }
+}
+
+/// 一次模拟的统计与违反的不变量
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SoakReport {
+    pub rounds: usize,
+    pub restarts: usize,
+    pub connects: usize,
+    pub connect_failures: usize,
+    pub disconnects: usize,
+    pub messages_sent: usize,
+    pub messages_delivered: usize,
+    pub send_failures: usize,
+    /// 单个节点同时持有的最多连接数
+    pub max_connections: usize,
+    pub panics: usize,
+    pub violations: Vec<String>,
+}
+
+impl SoakReport {
+    pub fn delivery_rate(&self) -> f64 {
+        if self.messages_sent == 0 {
+            return 1.0;
+        }
+        self.messages_delivered as f64 / self.messages_sent as f64
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+struct SimNode {
+    port: u16,
+    data_dir: PathBuf,
+    node: Node,
+    address: String,
+    running: bool,
+    /// 停止的时刻，用于判断残留连接是否超过宽限期
+    stopped_at: Option<Instant>,
+    events: JoinHandle<()>,
+}
+
+impl SimNode {
+    fn endpoint(&self) -> String {
+        format!("127.0.0.1:{}", self.port)
+    }
+}
+
+pub struct Simulation {
+    config: ChurnConfig,
+    root: PathBuf,
+    nodes: Vec<SimNode>,
+    rng: StdRng,
+    /// 已收到的消息：`接收方序号|内容`
+    received: Arc<DashSet<String>>,
+    expected: HashSet<String>,
+    panics_at_start: usize,
+    report: SoakReport,
+}
+
+fn opt_for(index: usize, port: u16, data_dir: &std::path::Path) -> Opt {
+    Opt {
+        name: format!("sim-{}", index),
+        ip: "127.0.0.1".to_string(),
+        port,
+        data_dir: Some(data_dir.to_string_lossy().to_string()),
+        min_peers: 0,
+        ..Default::default()
+    }
+}
+
+/// 启动一个节点的 P2P server，并把它收到的消息记入 `received`
+async fn launch(
+    index: usize,
+    port: u16,
+    data_dir: &std::path::Path,
+    received: Arc<DashSet<String>>,
+) -> (Node, JoinHandle<()>) {
+    let node = Node::init(opt_for(index, port, data_dir), Config::default()).await;
+    node.start_servers(true);
+    if let Err(e) = node.wait_ready().await {
+        tracing::warn!("Simulated node {} not ready: {}", index, e);
+    }
+    let mut rx = node.subscribe_events().await;
+    let events = tokio::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(NodeEvent::Message(message)) => {
+                    received.insert(format!("{}|{}", index, message.content));
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+    (node, events)
+}
+
+impl Simulation {
+    /// 启动所有节点，并按环形连接成一个连通的网络
+    pub async fn start(config: ChurnConfig) -> anyhow::Result<Self> {
+        if config.nodes < 2 {
+            anyhow::bail!("Simulation needs at least 2 nodes");
+        }
+        if config.base_port as usize + config.nodes > u16::MAX as usize {
+            anyhow::bail!("Port range starting at {} is too small", config.base_port);
+        }
+        install_panic_counter();
+        let root =
+            std::env::temp_dir().join(format!("zz-p2p-sim-{}-{}", std::process::id(), config.seed));
+        let received: Arc<DashSet<String>> = Arc::default();
+        let mut nodes = Vec::with_capacity(config.nodes);
+        for index in 0..config.nodes {
+            let port = config.base_port + index as u16;
+            let data_dir = root.join(format!("node-{}", index));
+            std::fs::create_dir_all(&data_dir)?;
+            let (node, events) = launch(index, port, &data_dir, received.clone()).await;
+            nodes.push(SimNode {
+                port,
+                data_dir,
+                address: node.id.to_string(),
+                node,
+                running: true,
+                stopped_at: None,
+                events,
+            });
+        }
+        let mut sim = Self {
+            rng: StdRng::seed_from_u64(config.seed),
+            config,
+            root,
+            nodes,
+            received,
+            expected: HashSet::new(),
+            panics_at_start: panic_count(),
+            report: SoakReport::default(),
+        };
+        tokio::time::sleep(Duration::from_millis(sim.config.settle_ms)).await;
+        for index in 0..sim.nodes.len() {
+            let next = (index + 1) % sim.nodes.len();
+            sim.connect(index, next).await;
+        }
+        tokio::time::sleep(Duration::from_millis(sim.config.settle_ms)).await;
+        Ok(sim)
+    }
+
+    /// 按配置运行完整的模拟并返回报告
+    pub async fn run(config: ChurnConfig) -> anyhow::Result<SoakReport> {
+        let mut sim = Self::start(config).await?;
+        for round in 0..sim.config.rounds {
+            sim.round(round).await;
+        }
+        Ok(sim.finish().await)
+    }
+
+    fn running(&self) -> Vec<usize> {
+        (0..self.nodes.len())
+            .filter(|i| self.nodes[*i].running)
+            .collect()
+    }
+
+    async fn connect(&mut self, from: usize, to: usize) {
+        let endpoint = self.nodes[to].endpoint();
+        match self.nodes[from].node.connect_to(&endpoint).await {
+            Ok(()) => self.report.connects += 1,
+            Err(e) => {
+                tracing::debug!("sim: node {} failed to connect to {}: {}", from, to, e);
+                self.report.connect_failures += 1;
+            }
+        }
+    }
+
+    async fn stop_node(&mut self, index: usize) {
+        let sim_node = &mut self.nodes[index];
+        sim_node.node.shutdown().await;
+        sim_node.node.context.shutdown_all().await;
+        sim_node.events.abort();
+        sim_node.running = false;
+        sim_node.stopped_at = Some(Instant::now());
+    }
+
+    async fn restart_node(&mut self, index: usize) {
+        let (port, data_dir) = (self.nodes[index].port, self.nodes[index].data_dir.clone());
+        let (node, events) = launch(index, port, &data_dir, self.received.clone()).await;
+        let sim_node = &mut self.nodes[index];
+        sim_node.node = node;
+        sim_node.events = events;
+        sim_node.running = true;
+        sim_node.stopped_at = None;
+        self.report.restarts += 1;
+    }
+
+    /// 执行一轮：重启 / 停止节点、断开 / 建立连接、检查连接、发送消息
+    pub async fn round(&mut self, round: usize) {
+        let stopped: Vec<usize> = (0..self.nodes.len())
+            .filter(|i| !self.nodes[*i].running)
+            .collect();
+        for index in stopped {
+            self.restart_node(index).await;
+        }
+        // 至少保留两个运行中的节点
+        let restarts = self
+            .config
+            .restarts_per_round
+            .min(self.nodes.len().saturating_sub(2));
+        let victims: Vec<usize> = self
+            .running()
+            .choose_multiple(&mut self.rng, restarts)
+            .copied()
+            .collect();
+        for index in victims {
+            self.stop_node(index).await;
+        }
+
+        for _ in 0..self.config.disconnects_per_round {
+            let Some(&index) = self.running().choose(&mut self.rng) else {
+                break;
+            };
+            let connections = self.nodes[index].node.connections().await;
+            if let Some(conn) = connections.choose(&mut self.rng) {
+                let closed = self.nodes[index]
+                    .node
+                    .disconnect(&conn.addr.to_string())
+                    .await;
+                self.report.disconnects += closed.len();
+            }
+        }
+        for _ in 0..self.config.connects_per_round {
+            let running = self.running();
+            let pair: Vec<usize> = running.choose_multiple(&mut self.rng, 2).copied().collect();
+            if let [from, to] = pair[..] {
+                self.connect(from, to).await;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(self.config.settle_ms)).await;
+
+        self.check_connections().await;
+        self.send_traffic(round).await;
+        tokio::time::sleep(Duration::from_millis(self.config.round_ms)).await;
+        self.report.rounds += 1;
+    }
+
+    /// 运行中的节点不能在宽限期后仍连着已停止的节点，也不能有超时未握手的连接
+    async fn check_connections(&mut self) {
+        let grace = Duration::from_millis(self.config.leak_grace_ms);
+        let stale: Vec<(usize, String)> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| n.stopped_at.is_some_and(|at| at.elapsed() > grace))
+            .map(|(i, n)| (i, n.address.clone()))
+            .collect();
+        for index in self.running() {
+            let connections = self.nodes[index].node.connections().await;
+            self.report.max_connections = self.report.max_connections.max(connections.len());
+            for conn in connections {
+                match &conn.peer {
+                    Some(peer) => {
+                        if let Some((stopped, _)) = stale.iter().find(|(_, a)| a == peer) {
+                            self.report.violations.push(format!(
+                                "node {} still connected to stopped node {} ({})",
+                                index, stopped, conn.addr
+                            ));
+                        }
+                    }
+                    None if conn.uptime_secs * 1000 > self.config.leak_grace_ms => {
+                        self.report.violations.push(format!(
+                            "node {} has a connection to {} that never completed the handshake",
+                            index, conn.addr
+                        ));
+                    }
+                    None => {}
+                }
+            }
+        }
+    }
+
+    /// 在已握手的直连节点之间发送消息
+    async fn send_traffic(&mut self, round: usize) {
+        let mut links = Vec::new();
+        for from in self.running() {
+            for conn in self.nodes[from].node.connections().await {
+                let Some(peer) = conn.peer else {
+                    continue;
+                };
+                let to = self
+                    .nodes
+                    .iter()
+                    .position(|n| n.running && n.address == peer);
+                if let Some(to) = to {
+                    links.push((from, to));
+                }
+            }
+        }
+        if links.is_empty() {
+            return;
+        }
+        for k in 0..self.config.messages_per_round {
+            let Some(&(from, to)) = links.choose(&mut self.rng) else {
+                break;
+            };
+            let content = format!("soak r{} m{}", round, k);
+            let sent = send::send_text(
+                self.nodes[from].node.context.clone(),
+                self.nodes[to].address.clone(),
+                content.clone(),
+            )
+            .await;
+            match sent {
+                Ok(_) => {
+                    self.report.messages_sent += 1;
+                    self.expected.insert(format!("{}|{}", to, content));
+                }
+                Err(e) => {
+                    tracing::debug!("sim: send {} -> {} failed: {}", from, to, e);
+                    self.report.send_failures += 1;
+                }
+            }
+        }
+    }
+
+    /// 停止所有节点并检查连接都已关闭，汇总报告
+    pub async fn finish(mut self) -> SoakReport {
+        for index in self.running() {
+            self.stop_node(index).await;
+        }
+        tokio::time::sleep(Duration::from_millis(self.config.settle_ms)).await;
+        for (index, sim_node) in self.nodes.iter().enumerate() {
+            let left = sim_node.node.connections().await;
+            if !left.is_empty() {
+                self.report.violations.push(format!(
+                    "node {} kept {} connection(s) after shutdown",
+                    index,
+                    left.len()
+                ));
+            }
+        }
+
+        self.report.messages_delivered = self
+            .expected
+            .iter()
+            .filter(|key| self.received.contains(*key))
+            .count();
+        if self.report.delivery_rate() < self.config.min_delivery_rate {
+            self.report.violations.push(format!(
+                "delivery rate {:.3} below {:.3} ({}/{})",
+                self.report.delivery_rate(),
+                self.config.min_delivery_rate,
+                self.report.messages_delivered,
+                self.report.messages_sent
+            ));
+        }
+        self.report.panics = panic_count().saturating_sub(self.panics_at_start);
+        if self.report.panics > 0 {
+            self.report
+                .violations
+                .push(format!("{} panic(s) during the run", self.report.panics));
+        }
+        if let Err(e) = std::fs::remove_dir_all(&self.root) {
+            tracing::warn!("Failed to remove {}: {}", self.root.display(), e);
+        }
+        self.report
+    }
+}
@@ -0,0 +1,312 @@
+//! 节点状态快照
+//!
+//! [`collect`]（或 [`crate::node::Node::snapshot`]）把分散在 GlobalContext 中的运行状态——身份、
+//! 运行时长、监听地址、连接数、已知节点及评分、待发送队列、版本、带宽与协议统计等——汇总为一个可序列化的
+//! [`NodeStatus`]。REPL 的 `status` 命令按它打印，控制接口的 `GET /status` 直接返回它的 JSON，
+//! 测试也可以对它断言而不必解析标准输出。没有运行中的节点时（例如只有 GlobalContext 的测试）
+//! 节点相关的字段为空值。
+
+use std::{collections::BTreeMap, net::SocketAddr, sync::Arc};
+
+use aex::{
+    connection::{global::GlobalContext, scope::NetworkScope},
+    time::SystemTime,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::{
+    client_mode, clock, connections, endpoint_verifier,
+    io_storage::STORAGE_SCHEMA_VERSION,
+    listener::Health,
+    node::{self, Node},
+    outbox,
+    peer_capabilities::{self, DowngradeStats},
+    port_mapping::{self, PortMapping},
+    protocols::{
+        agent::{self, SoftwareInfo},
+        bandwidth::{self, BandwidthUsage},
+        commands::observed::{self, ObservedEntry},
+        dedup::{self, DedupStats},
+        error::{self, ProtocolErrorSnapshot},
+        peer_stats::{self, PeerStatsEntry},
+        version::{CURRENT_PROTOCOL_VERSION, MIN_PROTOCOL_VERSION},
+    },
+    reachability::{self, ReachabilityReport},
+    readiness::{self, StartupPhase},
+    record::NodeRecord,
+    relay::{self, RelayUsage},
+    retention::{self, RetentionStats},
+    scheduler::{self, TaskInfo},
+    wal::SharedWal,
+};
+
+/// 软件与格式版本
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Versions {
+    pub package: &'static str,
+    /// 构建时的 git 提交，未知时为空
+    pub git_hash: &'static str,
+    pub protocol: u8,
+    pub min_protocol: u8,
+    pub storage_schema: u64,
+}
+
+impl Default for Versions {
+    fn default() -> Self {
+        Self {
+            package: agent::PACKAGE_VERSION,
+            git_hash: agent::GIT_HASH,
+            protocol: CURRENT_PROTOCOL_VERSION,
+            min_protocol: MIN_PROTOCOL_VERSION,
+            storage_schema: STORAGE_SCHEMA_VERSION,
+        }
+    }
+}
+
+/// 公网连接的统计（不含本机回环连接）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ConnectionCounts {
+    /// 有连接的对端 IP 数
+    pub peer_ips: usize,
+    pub inbound: usize,
+    pub outbound: usize,
+    pub intranet: usize,
+    pub extranet: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct EndpointCounts {
+    pub verified: usize,
+    pub unverified: usize,
+}
+
+/// 服务器列表中的一条记录
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KnownPeer {
+    pub endpoint: SocketAddr,
+    /// `inner` 或 `external`
+    pub scope: &'static str,
+    pub score: f64,
+    pub last_seen: DateTime<Utc>,
+    pub pinned: bool,
+    pub available: bool,
+}
+
+/// 等待发送的消息
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct QueueStatus {
+    /// 发件箱中等待路由的草稿
+    pub outbox: usize,
+    /// WAL 中尚未确认的消息（未启用 `--wal` 时为 0）
+    pub wal_pending: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ListenerStatus {
+    pub name: String,
+    pub health: Health,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RelaySummary {
+    pub enabled: bool,
+    pub clients: usize,
+    pub total: RelayUsage,
+}
+
+/// 节点状态快照，见模块文档
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeStatus {
+    pub address: String,
+    pub name: String,
+    pub versions: Versions,
+    /// 启动时间（毫秒），没有运行中的节点时为 0
+    pub started_at: u128,
+    pub uptime_secs: u64,
+    pub startup: StartupPhase,
+    pub client_only: bool,
+    pub listen: Vec<SocketAddr>,
+    #[serde(flatten)]
+    pub connections: ConnectionCounts,
+    pub known_nodes: usize,
+    pub connected_nodes: usize,
+    pub endpoints: EndpointCounts,
+    /// 服务器列表，按评分从高到低
+    pub known_peers: Vec<KnownPeer>,
+    pub queues: QueueStatus,
+    pub bandwidth: BandwidthUsage,
+    pub protocol_errors: ProtocolErrorSnapshot,
+    pub listeners: Vec<ListenerStatus>,
+    pub observed_addresses: Vec<ObservedEntry>,
+    pub port_mappings: Vec<PortMapping>,
+    /// 每个连接的帧数、错误、RTT 与发送延迟
+    pub peers: Vec<PeerStatsEntry>,
+    pub retention: RetentionStats,
+    pub clock_offsets_ms: BTreeMap<String, i64>,
+    pub relay: RelaySummary,
+    pub duplicates: DedupStats,
+    /// 直连对端的被动 / 主动可达性
+    pub reachability: ReachabilityReport,
+    /// 网络分区检测与重新引导
+    pub partition: PartitionReport,
+    /// 对端能力相对上一次会话的退化次数
+    pub capability_downgrades: DowngradeStats,
+    /// 调度器中的定期维护任务
+    pub tasks: Vec<TaskInfo>,
+    /// 本节点在握手中声明的 User-Agent
+    pub user_agent: String,
+    /// 已连接对端声明的软件版本，按节点地址（握手未完成时为 socket 地址）
+    pub peer_agents: BTreeMap<String, SoftwareInfo>,
+}
+
+pub fn count_connections(gctx: &GlobalContext) -> ConnectionCounts {
+    let mut counts = ConnectionCounts::default();
+    for bucket_ref in gctx.manager.connections.iter() {
+        let (key, bi_conn) = bucket_ref.pair();
+        if !node::is_public_ip(&key.0) {
+            continue;
+        }
+        counts.peer_ips += 1;
+        let (inbound, outbound) = (bi_conn.clients.len(), bi_conn.servers.len());
+        match key.1 {
+            NetworkScope::Intranet => counts.intranet += inbound + outbound,
+            _ => counts.extranet += inbound + outbound,
+        }
+        counts.inbound += inbound;
+        counts.outbound += outbound;
+    }
+    counts
+}
+
+fn known_peers(node: &Node) -> Vec<KnownPeer> {
+    let entry = |scope: &'static str| {
+        move |r: &NodeRecord| KnownPeer {
+            endpoint: r.endpoint,
+            scope,
+            score: r.score(),
+            last_seen: r.last_seen,
+            pinned: r.pinned,
+            available: r.is_available,
+        }
+    };
+    let mut peers: Vec<KnownPeer> = node
+        .inner
+        .snapshot()
+        .iter()
+        .map(entry("inner"))
+        .chain(node.external.snapshot().iter().map(entry("external")))
+        .collect();
+    peers.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.endpoint.cmp(&b.endpoint))
+    });
+    peers
+}
+
+async fn peer_agents(gctx: &Arc<GlobalContext>) -> BTreeMap<String, SoftwareInfo> {
+    connections::list(gctx)
+        .await
+        .into_iter()
+        .filter_map(|c| {
+            let key = c.peer.unwrap_or_else(|| c.addr.to_string());
+            c.agent.map(|agent| (key, agent))
+        })
+        .collect()
+}
+
+/// 汇总当前状态
+pub async fn collect(gctx: &Arc<GlobalContext>) -> NodeStatus {
+    let address = gctx
+        .get::<FreeWebMovementAddress>()
+        .await
+        .map(|a| a.to_string())
+        .unwrap_or_default();
+    let node = gctx.get::<Arc<Node>>().await;
+    let now = SystemTime::timestamp();
+
+    let (name, started_at, listen, known, connected, endpoints, listeners, peers) = match &node {
+        Some(n) => {
+            let (verified, unverified) =
+                n.registry.endpoints().counts(endpoint_verifier::now_secs());
+            (
+                n.name.clone(),
+                n.started_at,
+                n.listen.bindings.clone(),
+                n.registry.get_node_count(),
+                n.registry.get_connected_nodes().len(),
+                EndpointCounts {
+                    verified,
+                    unverified,
+                },
+                n.handlers
+                    .health()
+                    .into_iter()
+                    .map(|(name, health)| ListenerStatus { name, health })
+                    .collect(),
+                known_peers(n),
+            )
+        }
+        None => (
+            String::new(),
+            0,
+            Vec::new(),
+            0,
+            0,
+            EndpointCounts::default(),
+            Vec::new(),
+            Vec::new(),
+        ),
+    };
+    let wal_pending = match gctx.get::<SharedWal>().await {
+        Some(wal) => wal.pending().len(),
+        None => 0,
+    };
+    let relay = relay::report(gctx).await;
+
+    NodeStatus {
+        address,
+        name,
+        versions: Versions::default(),
+        started_at,
+        uptime_secs: if started_at == 0 {
+            0
+        } else {
+            (now.saturating_sub(started_at) / 1000) as u64
+        },
+        startup: readiness::of(gctx).await.phase(),
+        client_only: client_mode::is_enabled(gctx).await,
+        listen,
+        connections: count_connections(gctx),
+        known_nodes: known,
+        connected_nodes: connected,
+        endpoints,
+        known_peers: peers,
+        queues: QueueStatus {
+            outbox: outbox::list(gctx).await.len(),
+            wal_pending,
+        },
+        bandwidth: bandwidth::usage(gctx).await,
+        protocol_errors: error::snapshot(gctx).await,
+        listeners,
+        observed_addresses: observed::entries(gctx).await,
+        port_mappings: port_mapping::mappings(gctx).await,
+        peers: peer_stats::snapshot(gctx).await,
+        retention: retention::stats(gctx).await,
+        clock_offsets_ms: clock::offsets(gctx).await,
+        relay: RelaySummary {
+            enabled: relay.enabled,
+            clients: relay.clients.len(),
+            total: relay.total,
+        },
+        duplicates: dedup::stats(gctx).await,
+        reachability: reachability::report(gctx).await,
+        partition: partition::report(gctx).await,
+        capability_downgrades: peer_capabilities::stats(gctx).await,
+        tasks: scheduler::of(gctx).await.tasks(),
+        user_agent: agent::local(gctx).await.user_agent,
+        peer_agents: peer_agents(gctx).await,
+    }
+}
@@ -0,0 +1,336 @@
+//! 多节点测试夹具
+//!
+//! 以 `--features testing` 构建。下游应用与本 crate 的集成测试可以用它在 127.0.0.1 的随机端口上
+//! 启动进程内节点（数据目录位于系统临时目录，[`TestNode`] 释放时删除），等待监听就绪、互相连接，
+//! 并记录每个节点的事件总线：
+//!
+//! ```ignore
+//! let mut net = TestNetwork::start(3).await?;
+//! net.connect_all().await?;
+//! send::send_text(net[0].context(), net[1].address(), "hi".into()).await?;
+//! net[1].events.wait_for_message("hi", DEFAULT_WAIT).await?;
+//! net.stop().await;
+//! ```
+
+use std::{
+    net::{Ipv4Addr, SocketAddr, TcpListener},
+    ops::Index,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use aex::connection::global::GlobalContext;
+use tokio::{
+    sync::{Notify, broadcast::error::RecvError},
+    task::JoinHandle,
+};
+
+use crate::{cli::Opt, config::Config, events::NodeEvent, node::Node};
+
+/// 等待就绪、连接与事件的默认超时
+pub const DEFAULT_WAIT: Duration = Duration::from_secs(10);
+/// 轮询节点状态的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// 向系统申请一个当前空闲的本地端口
+pub fn free_port() -> std::io::Result<u16> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    Ok(listener.local_addr()?.port())
+}
+
+/// 每隔一小段时间检查 `check`，直到它返回 true 或超时
+pub async fn wait_until<F, Fut>(timeout: Duration, what: &str, mut check: F) -> anyhow::Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let waited = tokio::time::timeout(timeout, async {
+        while !check().await {
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    })
+    .await;
+    waited.map_err(|_| anyhow::anyhow!("timed out after {:?} waiting for {}", timeout, what))
+}
+
+/// 某个节点从事件总线收到的全部事件
+#[derive(Clone, Default)]
+pub struct EventLog {
+    events: Arc<Mutex<Vec<NodeEvent>>>,
+    notify: Arc<Notify>,
+}
+
+impl EventLog {
+    fn push(&self, event: NodeEvent) {
+        self.events.lock().unwrap().push(event);
+        self.notify.notify_waiters();
+    }
+
+    /// 迄今收到的事件
+    pub fn events(&self) -> Vec<NodeEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// 迄今收到的文本消息内容
+    pub fn messages(&self) -> Vec<String> {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|e| match e {
+                NodeEvent::Message(m) => Some(m.content.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.events.lock().unwrap().clear();
+    }
+
+    /// 等待第一条满足 `matches` 的事件（包括已经收到的）
+    pub async fn wait_for(
+        &self,
+        timeout: Duration,
+        matches: impl Fn(&NodeEvent) -> bool,
+    ) -> anyhow::Result<NodeEvent> {
+        let found = tokio::time::timeout(timeout, async {
+            loop {
+                let notified = self.notify.notified();
+                if let Some(event) = self.events.lock().unwrap().iter().find(|e| matches(e)) {
+                    return event.clone();
+                }
+                notified.await;
+            }
+        })
+        .await;
+        found.map_err(|_| anyhow::anyhow!("timed out after {:?} waiting for event", timeout))
+    }
+
+    /// 等待内容为 `content` 的文本消息
+    pub async fn wait_for_message(&self, content: &str, timeout: Duration) -> anyhow::Result<()> {
+        self.wait_for(
+            timeout,
+            |e| matches!(e, NodeEvent::Message(m) if m.content == content),
+        )
+        .await
+        .map(|_| ())
+        .map_err(|_| anyhow::anyhow!("message {:?} not received within {:?}", content, timeout))
+    }
+}
+
+/// 一个运行在随机端口上的进程内节点
+pub struct TestNode {
+    pub node: Node,
+    pub events: EventLog,
+    data_dir: PathBuf,
+    recorder: JoinHandle<()>,
+    stopped: bool,
+}
+
+impl TestNode {
+    /// 用默认选项启动节点并等待监听就绪
+    pub async fn start() -> anyhow::Result<Self> {
+        Self::start_with(|_| {}).await
+    }
+
+    /// 启动节点前可以修改选项；`ip`、`port` 与 `data_dir` 已经设为本地随机端口与临时目录
+    pub async fn start_with(configure: impl FnOnce(&mut Opt)) -> anyhow::Result<Self> {
+        let port = free_port()?;
+        let data_dir = std::env::temp_dir().join(format!(
+            "zz-p2p-test-{}-{}",
+            std::process::id(),
+            uuid::Uuid::new_v4()
+        ));
+        std::fs::create_dir_all(&data_dir)?;
+        let mut opt = Opt {
+            name: format!("test-{}", port),
+            ip: Ipv4Addr::LOCALHOST.to_string(),
+            port,
+            data_dir: Some(data_dir.to_string_lossy().to_string()),
+            min_peers: 0,
+            ..Default::default()
+        };
+        configure(&mut opt);
+        let config = match opt.config.as_deref() {
+            Some(path) => Config::load(path)?,
+            None => Config::default(),
+        };
+
+        let node = Node::init(opt, config).await;
+        let events = EventLog::default();
+        let mut rx = node.subscribe_events().await;
+        let log = events.clone();
+        let recorder = tokio::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => log.push(event),
+                    Err(RecvError::Lagged(n)) => {
+                        tracing::warn!("test node event log lagged by {} event(s)", n)
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+        node.start_servers(true);
+
+        let test_node = Self {
+            node,
+            events,
+            data_dir,
+            recorder,
+            stopped: false,
+        };
+        test_node.wait_ready(DEFAULT_WAIT).await?;
+        Ok(test_node)
+    }
+
+    /// 节点地址
+    pub fn address(&self) -> String {
+        self.node.id.to_string()
+    }
+
+    /// 主监听地址
+    pub fn endpoint(&self) -> SocketAddr {
+        self.node.addr
+    }
+
+    pub fn context(&self) -> Arc<GlobalContext> {
+        self.node.context.clone()
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    /// 等待节点就绪（所有监听地址接受 TCP 连接）
+    pub async fn wait_ready(&self, timeout: Duration) -> anyhow::Result<()> {
+        tokio::time::timeout(timeout, self.node.wait_ready())
+            .await
+            .map_err(|_| anyhow::anyhow!("timed out after {:?} waiting for node ready", timeout))?
+    }
+
+    /// 是否已与节点 `address` 完成握手
+    pub async fn is_connected_to(&self, address: &str) -> bool {
+        self.node
+            .connections()
+            .await
+            .iter()
+            .any(|c| c.peer.as_deref() == Some(address))
+    }
+
+    /// 连接到 `other` 并等待双方都完成握手
+    pub async fn connect(&mut self, other: &TestNode) -> anyhow::Result<()> {
+        self.node
+            .connect_to(&other.endpoint().to_string())
+            .await
+            .map_err(|e| anyhow::anyhow!(e))?;
+        let (ours, theirs) = (self.address(), other.address());
+        let (this, ours, theirs) = (&*self, ours.as_str(), theirs.as_str());
+        wait_until(DEFAULT_WAIT, "handshake", || async move {
+            this.is_connected_to(theirs).await && other.is_connected_to(ours).await
+        })
+        .await
+    }
+
+    /// 通知对端下线并关闭所有连接
+    pub async fn stop(&mut self) {
+        if self.stopped {
+            return;
+        }
+        self.node.shutdown().await;
+        self.node.context.shutdown_all().await;
+        self.recorder.abort();
+        self.stopped = true;
+    }
+}
+
+impl Drop for TestNode {
+    fn drop(&mut self) {
+        self.recorder.abort();
+        // 未调用 stop 时在后台停止监听器并关闭连接
+        if !self.stopped {
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                let node = self.node.clone();
+                runtime.spawn(async move {
+                    node.handlers.stop_all().await;
+                    node.context.shutdown_all().await;
+                });
+            }
+        }
+        let _ = std::fs::remove_dir_all(&self.data_dir);
+    }
+}
+
+/// 一组测试节点
+pub struct TestNetwork {
+    pub nodes: Vec<TestNode>,
+}
+
+impl TestNetwork {
+    /// 启动 `count` 个互不连接的节点
+    pub async fn start(count: usize) -> anyhow::Result<Self> {
+        let mut nodes = Vec::with_capacity(count);
+        for _ in 0..count {
+            nodes.push(TestNode::start().await?);
+        }
+        Ok(Self { nodes })
+    }
+
+    /// 连接节点 `from` 与 `to`
+    pub async fn connect(&mut self, from: usize, to: usize) -> anyhow::Result<()> {
+        if from == to {
+            anyhow::bail!("cannot connect node {} to itself", from);
+        }
+        let (a, b) = if from < to {
+            let (head, tail) = self.nodes.split_at_mut(to);
+            (&mut head[from], &tail[0])
+        } else {
+            let (head, tail) = self.nodes.split_at_mut(from);
+            (&mut tail[0], &head[to])
+        };
+        a.connect(b).await
+    }
+
+    /// 依次连接相邻节点（0-1、1-2 …）
+    pub async fn connect_chain(&mut self) -> anyhow::Result<()> {
+        for i in 1..self.nodes.len() {
+            self.connect(i - 1, i).await?;
+        }
+        Ok(())
+    }
+
+    /// 两两连接所有节点
+    pub async fn connect_all(&mut self) -> anyhow::Result<()> {
+        for i in 0..self.nodes.len() {
+            for j in (i + 1)..self.nodes.len() {
+                self.connect(i, j).await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// 停止所有节点
+    pub async fn stop(mut self) {
+        for node in &mut self.nodes {
+            node.stop().await;
+        }
+    }
+}
+
+impl Index<usize> for TestNetwork {
+    type Output = TestNode;
+
+    fn index(&self, index: usize) -> &TestNode {
+        &self.nodes[index]
+    }
+}
@@ -0,0 +1,225 @@
+//! 与传输方式无关的连接
+//!
+//! 发送、转发与断开连接的代码只依赖 [`Connection`]：写出一段字节、读取对端数据、查询对端地址与
+//! 传输方式、关闭连接。新增传输方式只需实现该 trait，不必修改各处的发送路径。
+//!
+//! - TCP（直连或经代理）：aex 的连接上下文 `Arc<Mutex<Context>>`
+//! - UDP：[`DatagramConnection`]，每次发送为一个数据报
+//!   （需要确认与重传时使用 [`crate::reliable_udp::ReliableDatagramConnection`]）
+//! - WebSocket：[`WebSocketConnection`]，每次发送为一个二进制消息
+//!
+//! QUIC 需要额外的协议栈，目前没有实现；接入时同样实现 [`Connection`] 即可。
+
+use std::{
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use aex::connection::context::Context;
+use async_trait::async_trait;
+use futures::{
+    SinkExt, StreamExt,
+    stream::{SplitSink, SplitStream},
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::UdpSocket,
+    sync::Mutex,
+};
+use tokio_tungstenite::{WebSocketStream, tungstenite::Message};
+
+use crate::{
+    connections::Transport,
+    proxy::{ProxiedVia, ProxyKind},
+};
+
+/// 一次 `recv` 最多读取的字节数（UDP 为单个数据报的上限）
+pub const RECV_BUFFER_SIZE: usize = 64 * 1024;
+
+#[async_trait]
+pub trait Connection: Send + Sync {
+    /// 写出一段字节并刷新
+    async fn send(&self, bytes: &[u8]) -> anyhow::Result<()>;
+    /// 读取对端发来的下一段数据；连接已关闭时返回 `None`
+    async fn recv(&self) -> anyhow::Result<Option<Vec<u8>>>;
+    async fn peer_addr(&self) -> SocketAddr;
+    async fn transport(&self) -> Transport;
+    /// 关闭写端，对端立即感知断开
+    async fn close(&self);
+}
+
+/// 连接上下文的传输方式：经代理建立的连接记录了 [`ProxiedVia`]
+pub fn transport_of(ctx: &Context) -> Transport {
+    match ctx.get::<ProxiedVia>() {
+        Some(ProxiedVia(proxy)) => match proxy.kind {
+            ProxyKind::Socks5 => Transport::Socks5,
+            ProxyKind::Http => Transport::HttpConnect,
+        },
+        None => Transport::Tcp,
+    }
+}
+
+#[async_trait]
+impl Connection for Arc<Mutex<Context>> {
+    async fn send(&self, bytes: &[u8]) -> anyhow::Result<()> {
+        let mut guard = self.lock().await;
+        let Some(writer) = &mut guard.writer else {
+            anyhow::bail!("connection has no writer");
+        };
+        writer.write_all(bytes).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+
+    /// 读取期间把 reader 移出上下文，不阻塞同一连接上的发送；
+    /// 由帧循环读取的连接没有 reader，返回错误
+    async fn recv(&self) -> anyhow::Result<Option<Vec<u8>>> {
+        let Some(mut reader) = self.lock().await.reader.take() else {
+            anyhow::bail!("connection reader is owned by the frame loop");
+        };
+        let mut buf = vec![0u8; RECV_BUFFER_SIZE];
+        let read = reader.read(&mut buf).await;
+        self.lock().await.reader = Some(reader);
+        match read? {
+            0 => Ok(None),
+            n => {
+                buf.truncate(n);
+                Ok(Some(buf))
+            }
+        }
+    }
+
+    async fn peer_addr(&self) -> SocketAddr {
+        self.lock().await.addr
+    }
+
+    async fn transport(&self) -> Transport {
+        transport_of(&*self.lock().await)
+    }
+
+    async fn close(&self) {
+        let mut guard = self.lock().await;
+        if let Some(writer) = &mut guard.writer {
+            let _ = writer.shutdown().await;
+        }
+    }
+}
+
+/// 共享一个 UDP socket 的对端
+pub struct DatagramConnection {
+    socket: Arc<UdpSocket>,
+    peer: SocketAddr,
+    closed: AtomicBool,
+}
+
+impl DatagramConnection {
+    pub fn new(socket: Arc<UdpSocket>, peer: SocketAddr) -> Self {
+        Self {
+            socket,
+            peer,
+            closed: AtomicBool::new(false),
+        }
+    }
+}
+
+#[async_trait]
+impl Connection for DatagramConnection {
+    async fn send(&self, bytes: &[u8]) -> anyhow::Result<()> {
+        if self.closed.load(Ordering::Relaxed) {
+            anyhow::bail!("connection to {} is closed", self.peer);
+        }
+        self.socket.send_to(bytes, self.peer).await?;
+        Ok(())
+    }
+
+    /// 忽略来自其它地址的数据报
+    async fn recv(&self) -> anyhow::Result<Option<Vec<u8>>> {
+        let mut buf = vec![0u8; RECV_BUFFER_SIZE];
+        loop {
+            if self.closed.load(Ordering::Relaxed) {
+                return Ok(None);
+            }
+            let (n, from) = self.socket.recv_from(&mut buf).await?;
+            if from == self.peer {
+                buf.truncate(n);
+                return Ok(Some(buf));
+            }
+        }
+    }
+
+    async fn peer_addr(&self) -> SocketAddr {
+        self.peer
+    }
+
+    async fn transport(&self) -> Transport {
+        Transport::Udp
+    }
+
+    async fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+    }
+}
+
+/// WebSocket 连接；文本消息按 UTF-8 字节返回，ping / pong 由 tungstenite 处理
+pub struct WebSocketConnection<S> {
+    sink: Mutex<SplitSink<WebSocketStream<S>, Message>>,
+    stream: Mutex<SplitStream<WebSocketStream<S>>>,
+    peer: SocketAddr,
+}
+
+impl<S> WebSocketConnection<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    pub fn new(socket: WebSocketStream<S>, peer: SocketAddr) -> Self {
+        let (sink, stream) = socket.split();
+        Self {
+            sink: Mutex::new(sink),
+            stream: Mutex::new(stream),
+            peer,
+        }
+    }
+}
+
+#[async_trait]
+impl<S> Connection for WebSocketConnection<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    async fn send(&self, bytes: &[u8]) -> anyhow::Result<()> {
+        self.sink
+            .lock()
+            .await
+            .send(Message::binary(bytes.to_vec()))
+            .await?;
+        Ok(())
+    }
+
+    async fn recv(&self) -> anyhow::Result<Option<Vec<u8>>> {
+        let mut stream = self.stream.lock().await;
+        while let Some(message) = stream.next().await {
+            match message? {
+                Message::Binary(data) => return Ok(Some(data.to_vec())),
+                Message::Text(text) => return Ok(Some(text.as_str().as_bytes().to_vec())),
+                Message::Close(_) => return Ok(None),
+                Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => {}
+            }
+        }
+        Ok(None)
+    }
+
+    async fn peer_addr(&self) -> SocketAddr {
+        self.peer
+    }
+
+    async fn transport(&self) -> Transport {
+        Transport::WebSocket
+    }
+
+    async fn close(&self) {
+        let _ = self.sink.lock().await.close().await;
+    }
+}
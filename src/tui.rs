@@ -0,0 +1,433 @@
+//! 终端仪表盘（`--tui`，需启用 `tui` feature）
+//!
+//! 代替 REPL 显示实时面板：当前连接（评分、延迟、特性）、最近收到的消息与生命周期事件、
+//! 上下行速率曲线以及运行日志。消息与事件来自节点事件总线（[`crate::events`]），
+//! 连接与流量每 [`TUI_TICK_MS`] 刷新一次；日志由 tracing 写入 [`LogBuffer`]，
+//! 不直接输出到终端。按 `q`、`Esc` 或 `Ctrl-C` 退出。
+
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, Write},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use aex::connection::global::GlobalContext;
+use ratatui::{
+    Frame,
+    crossterm::event::{self, Event as TermEvent, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, List, ListItem, Paragraph, Row, Sparkline, Table},
+};
+use tokio::sync::{broadcast::error::RecvError, mpsc};
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::{
+    connections::{self, PeerConnection},
+    events::{EventBus, NodeEvent},
+    journal::Event,
+    protocols::{bandwidth::BandwidthUsage, limits},
+};
+
+/// 面板刷新间隔
+pub const TUI_TICK_MS: u64 = 500;
+/// 速率曲线保留的采样点数
+pub const RATE_HISTORY: usize = 120;
+/// 消息与事件面板保留的条数
+pub const RECENT_ITEMS: usize = 100;
+/// 日志面板保留的行数
+pub const LOG_LINES: usize = 500;
+
+/// tracing 输出的环形缓冲区，供日志面板显示
+#[derive(Clone, Default)]
+pub struct LogBuffer(Arc<Mutex<VecDeque<String>>>);
+
+impl LogBuffer {
+    /// 最近的 `n` 行，旧的在前
+    pub fn tail(&self, n: usize) -> Vec<String> {
+        let lines = match self.0.lock() {
+            Ok(g) => g,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        lines
+            .iter()
+            .skip(lines.len().saturating_sub(n))
+            .cloned()
+            .collect()
+    }
+}
+
+impl Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut lines = match self.0.lock() {
+            Ok(g) => g,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        for line in String::from_utf8_lossy(buf).lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if lines.len() == LOG_LINES {
+                lines.pop_front();
+            }
+            lines.push_back(line.to_string());
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 连接面板的一行
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeerRow {
+    pub conn: PeerConnection,
+    /// `limits` 中的连接评分
+    pub score: Option<i64>,
+}
+
+/// 仪表盘状态，与终端绘制分离
+#[derive(Debug, Default)]
+pub struct Dashboard {
+    pub address: String,
+    pub peers: Vec<PeerRow>,
+    pub messages: VecDeque<String>,
+    pub events: VecDeque<String>,
+    pub upload_rates: VecDeque<u64>,
+    pub download_rates: VecDeque<u64>,
+    pub upload_bytes: u64,
+    pub download_bytes: u64,
+}
+
+fn push_bounded<T>(queue: &mut VecDeque<T>, item: T, max: usize) {
+    if queue.len() == max {
+        queue.pop_front();
+    }
+    queue.push_back(item);
+}
+
+/// 事件面板中的一行
+pub fn describe_event(event: &Event) -> String {
+    match event {
+        Event::Started { listen, .. } => format!("node started on {}", listen),
+        Event::Stopped { .. } => "node stopped".to_string(),
+        Event::PeerConnected {
+            peer,
+            addr,
+            inbound,
+        } => format!(
+            "{} {} ({})",
+            if *inbound {
+                "⬅ connected"
+            } else {
+                "➡ connected"
+            },
+            peer,
+            addr
+        ),
+        Event::PeerDisconnected { peer, addr, reason } => format!(
+            "✂ disconnected {} ({}): {}",
+            peer.as_deref().unwrap_or("-"),
+            addr,
+            reason
+        ),
+        Event::HandshakeFailed { peer, addr, reason } => {
+            format!("✗ handshake failed {} ({}): {}", peer, addr, reason)
+        }
+        Event::MessageForwarded {
+            from,
+            to,
+            next_hops,
+            ttl,
+        } => format!(
+            "🔀 forwarded {} → {} via {} hop(s), ttl={}",
+            from, to, next_hops, ttl
+        ),
+        Event::CapabilityDowngraded { peer, addr, change } => {
+            format!("📉 capabilities regressed {} ({}): {}", peer, addr, change)
+        }
+        Event::PartitionDetected { isolated_secs } => {
+            format!("🧱 partitioned: no peers for {}s", isolated_secs)
+        }
+        Event::PartitionRecovered {
+            duration_secs,
+            peers,
+            ..
+        } => format!(
+            "🌐 partition recovered after {}s, {} peer(s)",
+            duration_secs, peers
+        ),
+        Event::RemoteError {
+            from, code, detail, ..
+        } => format!("↩ {} from {}: {}", code, from, detail),
+        Event::IdentityRotated {
+            old, new, local, ..
+        } => format!(
+            "🪪 {} rotated {} → {}",
+            if *local { "identity" } else { "peer" },
+            old,
+            new
+        ),
+    }
+}
+
+impl Dashboard {
+    pub fn on_event(&mut self, event: NodeEvent) {
+        match event {
+            NodeEvent::Journal(event) => {
+                push_bounded(&mut self.events, describe_event(&event), RECENT_ITEMS)
+            }
+            NodeEvent::Message(message) => push_bounded(
+                &mut self.messages,
+                format!("{}: {}", message.from, message.content),
+                RECENT_ITEMS,
+            ),
+            NodeEvent::GroupMessage(message) => push_bounded(
+                &mut self.messages,
+                format!("[{}] {}: {}", message.group, message.from, message.content),
+                RECENT_ITEMS,
+            ),
+            NodeEvent::TextStream(stream) => push_bounded(
+                &mut self.messages,
+                format!(
+                    "{}: 📜 text stream ({} bytes)",
+                    stream.from,
+                    stream
+                        .total_len
+                        .map(|n| n.to_string())
+                        .unwrap_or("?".into())
+                ),
+                RECENT_ITEMS,
+            ),
+        }
+    }
+
+    pub fn on_usage(&mut self, usage: &BandwidthUsage) {
+        push_bounded(&mut self.upload_rates, usage.upload_rate, RATE_HISTORY);
+        push_bounded(&mut self.download_rates, usage.download_rate, RATE_HISTORY);
+        self.upload_bytes = usage.upload_bytes;
+        self.download_bytes = usage.download_bytes;
+    }
+
+    pub fn set_peers(&mut self, conns: Vec<PeerConnection>, scores: &HashMap<String, i64>) {
+        self.peers = conns
+            .into_iter()
+            .map(|conn| PeerRow {
+                score: scores.get(&conn.addr.to_string()).copied(),
+                conn,
+            })
+            .collect();
+    }
+}
+
+/// 以人类可读的单位显示字节数
+pub fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+fn render(frame: &mut Frame, dashboard: &Dashboard, logs: &LogBuffer) {
+    let [header, middle, rates, log_area] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(8),
+        Constraint::Length(6),
+        Constraint::Length(10),
+    ])
+    .areas(frame.area());
+
+    let down = dashboard.download_rates.back().copied().unwrap_or(0);
+    let up = dashboard.upload_rates.back().copied().unwrap_or(0);
+    frame.render_widget(
+        Paragraph::new(format!(
+            " {}  peers={}  ↓ {}/s ({})  ↑ {}/s ({})  [q] quit",
+            dashboard.address,
+            dashboard.peers.len(),
+            human_bytes(down),
+            human_bytes(dashboard.download_bytes),
+            human_bytes(up),
+            human_bytes(dashboard.upload_bytes),
+        ))
+        .style(Style::default().add_modifier(Modifier::BOLD)),
+        header,
+    );
+
+    let [peers_area, side] =
+        Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(middle);
+    let rows = dashboard.peers.iter().map(|row| {
+        let c = &row.conn;
+        Row::new(vec![
+            c.addr.to_string(),
+            c.direction.to_string(),
+            c.peer.clone().unwrap_or_else(|| "-".to_string()),
+            row.score
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            c.rtt_ms
+                .map(|ms| format!("{}ms", ms))
+                .unwrap_or_else(|| "-".to_string()),
+            c.features.join(","),
+        ])
+    });
+    frame.render_widget(
+        Table::new(
+            rows,
+            [
+                Constraint::Length(22),
+                Constraint::Length(9),
+                Constraint::Min(12),
+                Constraint::Length(6),
+                Constraint::Length(7),
+                Constraint::Min(10),
+            ],
+        )
+        .header(
+            Row::new(vec!["addr", "dir", "peer", "score", "rtt", "features"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().title(" Peers ")),
+        peers_area,
+    );
+
+    let [messages_area, events_area] =
+        Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(side);
+    let recent = |items: &VecDeque<String>, height: u16| -> Vec<ListItem> {
+        let shown = height.saturating_sub(2) as usize;
+        items
+            .iter()
+            .skip(items.len().saturating_sub(shown))
+            .map(|s| ListItem::new(s.as_str()))
+            .collect()
+    };
+    frame.render_widget(
+        List::new(recent(&dashboard.messages, messages_area.height))
+            .block(Block::bordered().title(" Messages ")),
+        messages_area,
+    );
+    frame.render_widget(
+        List::new(recent(&dashboard.events, events_area.height))
+            .block(Block::bordered().title(" Events ")),
+        events_area,
+    );
+
+    let [down_area, up_area] =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(rates);
+    let down_data: Vec<u64> = dashboard.download_rates.iter().copied().collect();
+    let up_data: Vec<u64> = dashboard.upload_rates.iter().copied().collect();
+    frame.render_widget(
+        Sparkline::default()
+            .block(Block::bordered().title(" ↓ download "))
+            .data(&down_data),
+        down_area,
+    );
+    frame.render_widget(
+        Sparkline::default()
+            .block(Block::bordered().title(" ↑ upload "))
+            .data(&up_data),
+        up_area,
+    );
+
+    let lines: Vec<Line> = logs
+        .tail(log_area.height.saturating_sub(2) as usize)
+        .into_iter()
+        .map(Line::from)
+        .collect();
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(" Logs ")),
+        log_area,
+    );
+}
+
+/// 在后台线程读取按键，收到退出键后通知主循环
+fn spawn_input(quit: mpsc::Sender<()>, stop: Arc<AtomicBool>) {
+    std::thread::spawn(move || {
+        while !stop.load(Ordering::Relaxed) {
+            match event::poll(Duration::from_millis(100)) {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(_) => break,
+            }
+            let Ok(TermEvent::Key(key)) = event::read() else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let ctrl_c =
+                key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+            if ctrl_c || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                let _ = quit.blocking_send(());
+                break;
+            }
+        }
+    });
+}
+
+async fn refresh(gctx: &Arc<GlobalContext>, dashboard: &mut Dashboard) {
+    let scores: HashMap<String, i64> = limits::snapshot(gctx, None)
+        .await
+        .into_iter()
+        .map(|s| (s.addr.to_string(), s.score))
+        .collect();
+    dashboard.set_peers(connections::list(gctx).await, &scores);
+    dashboard.on_usage(&crate::protocols::bandwidth::usage(gctx).await);
+}
+
+/// 运行仪表盘直到用户退出
+pub async fn run(gctx: Arc<GlobalContext>, logs: LogBuffer) -> anyhow::Result<()> {
+    let Some(bus) = gctx.get::<EventBus>().await else {
+        anyhow::bail!("event bus not initialized");
+    };
+    let mut events = bus.subscribe();
+    let mut dashboard = Dashboard {
+        address: gctx
+            .get::<FreeWebMovementAddress>()
+            .await
+            .map(|a| a.to_string())
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+
+    let (quit_tx, mut quit_rx) = mpsc::channel(1);
+    let stop = Arc::new(AtomicBool::new(false));
+    let mut terminal = ratatui::init();
+    spawn_input(quit_tx, stop.clone());
+
+    let mut tick = tokio::time::interval(Duration::from_millis(TUI_TICK_MS));
+    let result = loop {
+        tokio::select! {
+            _ = tick.tick() => {
+                refresh(&gctx, &mut dashboard).await;
+                if let Err(e) = terminal.draw(|frame| render(frame, &dashboard, &logs)) {
+                    break Err(e.into());
+                }
+            }
+            received = events.recv() => match received {
+                Ok(event) => dashboard.on_event(event),
+                Err(RecvError::Lagged(n)) => tracing::warn!("TUI skipped {} events", n),
+                Err(RecvError::Closed) => break Ok(()),
+            },
+            _ = quit_rx.recv() => break Ok(()),
+        }
+    };
+
+    stop.store(true, Ordering::Relaxed);
+    ratatui::restore();
+    result
+}
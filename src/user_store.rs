@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::io::AsyncWriteExt;
@@ -368,6 +368,49 @@ impl UserStore {
         Ok(result)
     }
 
+    /// Apply `f` to every stored conversation (contact -> messages, oldest first) under
+    /// the store lock, then write back the conversations it changed. Conversations
+    /// that `f` removes or empties are deleted from disk.
+    pub async fn retain_messages<R>(
+        &self,
+        f: impl FnOnce(&mut BTreeMap<String, Vec<ChatMessage>>) -> R,
+    ) -> anyhow::Result<R> {
+        let _guard = self.lock.lock().await;
+        let users_dir = self.base_path.join("users");
+        let mut conversations = BTreeMap::new();
+        let mut entries = match tokio::fs::read_dir(&users_dir).await {
+            Ok(e) => e,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(f(&mut conversations));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_type().await?.is_dir() {
+                if let Some(name) = entry.file_name().to_str() {
+                    let msgs = self.read_messages_raw(name).await.unwrap_or_default();
+                    if !msgs.is_empty() {
+                        conversations.insert(name.to_string(), msgs);
+                    }
+                }
+            }
+        }
+
+        let before: Vec<(String, usize)> = conversations
+            .iter()
+            .map(|(k, v)| (k.clone(), v.len()))
+            .collect();
+        let result = f(&mut conversations);
+        for (contact, len) in before {
+            match conversations.get(&contact) {
+                Some(msgs) if msgs.len() == len => {}
+                Some(msgs) => self.write_messages_raw(&contact, msgs).await?,
+                None => self.write_messages_raw(&contact, &[]).await?,
+            }
+        }
+        Ok(result)
+    }
+
     /// Get conversation summaries across all contacts, sorted by most recent first.
     pub async fn get_conversations(&self) -> anyhow::Result<Vec<ConversationSummary>> {
         let _guard = self.lock.lock().await;
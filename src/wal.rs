@@ -0,0 +1,299 @@
+//! 出站消息预写日志（WAL）
+//!
+//! 启用 `--wal` 后，每条出站文本消息在发送前以一行 JSON 追加到 `<data_dir>/outbound.wal`，
+//! 收到接收方的 `MessageAck` 后再追加一条完成记录。进程崩溃重启时重放日志，
+//! 未完成的消息在接收方重新连上后补发（至少一次语义，接收方可能看到重复消息）。
+//! 日志超过 `max_bytes` 时压缩：只把未完成的记录写入新文件，旧文件保留为 `.1`。
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use aex::{connection::global::GlobalContext, time::SystemTime};
+use serde::{Deserialize, Serialize};
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::{cli::Opt, protocols::commands::message};
+
+/// WAL 文件名
+pub const WAL_FILE: &str = "outbound.wal";
+/// 默认日志轮转阈值
+pub const DEFAULT_WAL_MAX_BYTES: u64 = 8 * 1024 * 1024;
+/// 补发检查间隔
+pub const WAL_REPLAY_INTERVAL_SECS: u64 = 15;
+/// 同一条消息两次补发之间的最短间隔
+pub const WAL_RESEND_AFTER_MS: u128 = 30_000;
+
+/// 日志中的一行
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum WalRecord {
+    Append(PendingFrame),
+    Complete { request_id: u64 },
+}
+
+/// 已写入日志、尚未被确认的出站消息
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PendingFrame {
+    pub request_id: u64,
+    pub receiver: String,
+    pub message: String,
+    pub created_at: u128,
+    /// 最近一次发送时间，仅在内存中维护；重启后为 0 以便尽快补发
+    #[serde(skip)]
+    pub last_attempt: u128,
+}
+
+struct WalState {
+    file: File,
+    size: u64,
+    pending: BTreeMap<u64, PendingFrame>,
+}
+
+pub struct Wal {
+    path: PathBuf,
+    max_bytes: u64,
+    state: Mutex<WalState>,
+}
+
+pub type SharedWal = Arc<Wal>;
+
+pub fn wal_path(opt: &Opt) -> PathBuf {
+    opt.app_dir().join(WAL_FILE)
+}
+
+/// 重放日志得到仍未完成的消息；末尾被截断的半行（崩溃时写入一半）会被忽略
+fn replay(path: &Path) -> anyhow::Result<BTreeMap<u64, PendingFrame>> {
+    let mut pending = BTreeMap::new();
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(pending),
+        Err(e) => return Err(e.into()),
+    };
+    for (lineno, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<WalRecord>(&line) {
+            Ok(WalRecord::Append(frame)) => {
+                pending.insert(frame.request_id, frame);
+            }
+            Ok(WalRecord::Complete { request_id }) => {
+                pending.remove(&request_id);
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Skipping corrupt WAL record at {}:{}: {}",
+                    path.display(),
+                    lineno + 1,
+                    e
+                );
+            }
+        }
+    }
+    Ok(pending)
+}
+
+fn open_append(path: &Path) -> anyhow::Result<File> {
+    Ok(OpenOptions::new().create(true).append(true).open(path)?)
+}
+
+impl Wal {
+    /// 打开（或创建）日志并重放其中未完成的记录
+    pub fn open(path: impl Into<PathBuf>, max_bytes: u64) -> anyhow::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let pending = replay(&path)?;
+        let file = open_append(&path)?;
+        let size = file.metadata()?.len();
+        if let Some(max_id) = pending.keys().next_back() {
+            // 避免重启后新消息复用日志中尚未完成的 request_id
+            message::reserve_request_ids(max_id + 1);
+        }
+        let wal = Self {
+            path,
+            max_bytes,
+            state: Mutex::new(WalState {
+                file,
+                size,
+                pending,
+            }),
+        };
+        {
+            let mut state = wal.lock();
+            if state.size > wal.max_bytes {
+                wal.rotate(&mut state)?;
+            }
+        }
+        Ok(wal)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, WalState> {
+        match self.state.lock() {
+            Ok(g) => g,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn write_record(&self, state: &mut WalState, record: &WalRecord) -> anyhow::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        state.file.write_all(&line)?;
+        state.file.sync_data()?;
+        state.size += line.len() as u64;
+        if state.size > self.max_bytes {
+            self.rotate(state)?;
+        }
+        Ok(())
+    }
+
+    /// 压缩日志：未完成的记录写入临时文件后原子替换，旧日志保留为 `.1`
+    fn rotate(&self, state: &mut WalState) -> anyhow::Result<()> {
+        let tmp = self.path.with_extension("wal.tmp");
+        let mut size = 0u64;
+        {
+            let mut out = File::create(&tmp)?;
+            for frame in state.pending.values() {
+                let mut line = serde_json::to_vec(&WalRecord::Append(frame.clone()))?;
+                line.push(b'\n');
+                out.write_all(&line)?;
+                size += line.len() as u64;
+            }
+            out.sync_all()?;
+        }
+        fs::rename(&self.path, self.path.with_extension("wal.1"))?;
+        fs::rename(&tmp, &self.path)?;
+        state.file = open_append(&self.path)?;
+        state.size = size;
+        tracing::info!(
+            "🗜️ Rotated WAL {} ({} pending record(s))",
+            self.path.display(),
+            state.pending.len()
+        );
+        Ok(())
+    }
+
+    /// 发送前记录一条消息；同一 request_id 重复追加（补发、多连接发送）是空操作
+    pub fn append(&self, receiver: &str, request_id: u64, message: &str) -> anyhow::Result<()> {
+        let mut state = self.lock();
+        if let Some(frame) = state.pending.get_mut(&request_id) {
+            frame.last_attempt = SystemTime::timestamp();
+            return Ok(());
+        }
+        let now = SystemTime::timestamp();
+        let frame = PendingFrame {
+            request_id,
+            receiver: receiver.to_string(),
+            message: message.to_string(),
+            created_at: now,
+            last_attempt: now,
+        };
+        self.write_record(&mut state, &WalRecord::Append(frame.clone()))?;
+        state.pending.insert(request_id, frame);
+        Ok(())
+    }
+
+    /// 收到 `from` 对 request_id 的回执后标记完成；返回该消息是否在日志中
+    pub fn complete(&self, request_id: u64, from: &str) -> anyhow::Result<bool> {
+        let mut state = self.lock();
+        match state.pending.get(&request_id) {
+            Some(frame) if frame.receiver == from => {}
+            _ => return Ok(false),
+        }
+        state.pending.remove(&request_id);
+        self.write_record(&mut state, &WalRecord::Complete { request_id })?;
+        Ok(true)
+    }
+
+    /// 放弃发送（保留策略清理）：与收到回执一样标记完成，返回实际移除的条数
+    pub fn abandon(&self, request_ids: &[u64]) -> anyhow::Result<usize> {
+        let mut state = self.lock();
+        let mut removed = 0;
+        for &request_id in request_ids {
+            if state.pending.remove(&request_id).is_some() {
+                self.write_record(&mut state, &WalRecord::Complete { request_id })?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// 所有未完成的消息（按 request_id 升序）
+    pub fn pending(&self) -> Vec<PendingFrame> {
+        self.lock().pending.values().cloned().collect()
+    }
+
+    /// 距上次发送已超过 `WAL_RESEND_AFTER_MS` 的消息，并把它们的发送时间更新为 `now`
+    pub fn due_for_resend(&self, now: u128) -> Vec<PendingFrame> {
+        let mut state = self.lock();
+        state
+            .pending
+            .values_mut()
+            .filter(|f| now.saturating_sub(f.last_attempt) >= WAL_RESEND_AFTER_MS)
+            .map(|f| {
+                f.last_attempt = now;
+                f.clone()
+            })
+            .collect()
+    }
+}
+
+/// 后台补发：定期把未确认的消息重新发给已连接的接收方
+pub fn spawn_replay(gctx: Arc<GlobalContext>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(WAL_REPLAY_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let Some(wal) = gctx.get::<SharedWal>().await else {
+                continue;
+            };
+            let sender = match gctx.get::<FreeWebMovementAddress>().await {
+                Some(a) => a.to_string(),
+                None => continue,
+            };
+            for frame in wal.due_for_resend(SystemTime::timestamp()) {
+                let sender = sender.clone();
+                let receiver = frame.receiver.clone();
+                gctx.manager
+                    .notify(receiver.clone().as_bytes(), |entries| async move {
+                        let Some(ctx) = entries.into_iter().find_map(|e| e.context.clone()) else {
+                            return;
+                        };
+                        match message::send_text_message(
+                            sender,
+                            frame.receiver.clone(),
+                            frame.request_id,
+                            ctx,
+                            &frame.message,
+                        )
+                        .await
+                        {
+                            Ok(_) => tracing::info!(
+                                "🔁 Replayed WAL message request_id={} to {}",
+                                frame.request_id,
+                                frame.receiver
+                            ),
+                            Err(e) => tracing::warn!(
+                                "Failed to replay WAL message request_id={}: {:?}",
+                                frame.request_id,
+                                e
+                            ),
+                        }
+                    })
+                    .await;
+            }
+        }
+    });
+}
@@ -5,6 +5,8 @@ pub use aex::connection::scope::NetworkScope;
 pub use aex::http::meta::HttpMetadata;
 pub use aex::http::middlewares::websocket::WsSenderList;
 pub use aex::http::protocol::header::HeaderKey;
-pub use aex::http::protocol::media_type::SubMediaType;
+pub use aex::http::protocol::media_type::{MediaType, SubMediaType};
 pub use aex::http::protocol::method::HttpMethod;
+pub use aex::http::protocol::status::StatusCode;
+pub use aex::http::router::Router;
 pub use aex::tcp::types::Codec;
@@ -1,5 +1,11 @@
 use std::sync::Arc;
 
+use crate::config::SharedConfig;
+use crate::ip_scope;
+use crate::node::Node;
+use crate::protocols::commands::binary::guess_content_type;
+use crate::protocols::commands::node_registry::NodeRegistry;
+use crate::protocols::commands::node_sync::SeedData;
 pub use crate::web::aex_re_exports::{
     ConnectionInfo, Context, GlobalContext, HeaderKey, HttpMetadata, NetworkScope, PeerInfo,
     SubMediaType,
@@ -8,13 +14,15 @@ use askama::Template;
 use base64::Engine;
 use chrono::Utc;
 use sea_orm::DatabaseConnection;
-use crate::node::Node;
-use crate::protocols::commands::node_registry::NodeRegistry;
-use crate::protocols::commands::node_sync::SeedData;
+use tokio::io::AsyncRead;
 
 use crate::db::defines::StoreFromConnection;
 use crate::user_store::UserStore;
 
+use super::body::{self, BodyReader};
+use super::chunked::{self, BodyError};
+use super::multipart;
+use super::params::ListQuery;
 use super::templates::{
     self, AccountInfo, ChatTemplate, NetworkTemplate, ResourceInfo, TransactionInfo,
     TransactionPage, WalletTemplate, WitnessRingInfo, WitnessTableInfo,
@@ -24,19 +32,68 @@ const DEFAULT_EXPLORER_URL: &str = "https://freeweb.observer/explorer";
 
 // ===================== Helper functions =====================
 
-pub async fn read_http_body(ctx: &mut Context) -> (usize, Vec<u8>) {
-    use tokio::io::AsyncReadExt;
-    let cl = ctx
-        .local
-        .get_ref::<HttpMetadata>()
-        .and_then(|m| m.headers.get(&HeaderKey::ContentLength))
-        .and_then(|s| s.parse::<usize>().ok())
-        .unwrap_or(0);
-    let mut body = vec![0u8; cl.max(4096)];
-    if let Some(reader) = ctx.reader.as_deref_mut() {
-        let _ = reader.read_exact(&mut body[..cl]).await;
+/// 请求体的流式读取句柄
+pub type HttpBody<'a> = BodyReader<Box<dyn AsyncRead + Unpin + Send + 'a>>;
+
+/// 打开请求体：支持 Content-Length 与 chunked 两种方式，上限为 `limits.max_http_body_bytes`。
+/// 声明的长度超限时直接返回 `TooLarge`，handler 应调用 [`reject_body`] 回 413
+pub async fn http_body(ctx: &mut Context) -> Result<HttpBody<'_>, BodyError> {
+    let max = max_http_body_bytes(ctx).await;
+    http_body_limited(ctx, max)
+}
+
+/// 同 [`http_body`]，使用调用方指定的上限
+pub fn http_body_limited(ctx: &mut Context, limit: usize) -> Result<HttpBody<'_>, BodyError> {
+    let (cl, te) = match ctx.local.get_ref::<HttpMetadata>() {
+        Some(m) => (
+            m.headers.get(&HeaderKey::ContentLength).cloned(),
+            m.headers.get(&HeaderKey::TransferEncoding).cloned(),
+        ),
+        None => (None, None),
+    };
+    let Some(reader) = ctx.reader.as_deref_mut() else {
+        return Err(BodyError::Io(std::io::ErrorKind::NotConnected.into()));
+    };
+    let reader: Box<dyn AsyncRead + Unpin + Send + '_> = Box::new(reader);
+    BodyReader::from_headers(reader, cl.as_deref(), te.as_deref(), limit)
+}
+
+/// 回写请求体错误（413 或 400）并关闭连接；返回值可直接作为 handler 的返回值
+pub async fn reject_body(ctx: &mut Context, e: &BodyError) -> bool {
+    tracing::warn!("Rejected request body: {}", e);
+    if let Some(writer) = ctx.writer.as_deref_mut() {
+        if let Err(err) = body::reject(writer, e).await {
+            tracing::debug!("Body rejection aborted: {}", err);
+        }
+    }
+    false
+}
+
+/// 读完整个请求体；失败时已回写错误响应，返回 None
+pub async fn read_http_body(ctx: &mut Context) -> Option<Vec<u8>> {
+    let result = match http_body(ctx).await {
+        Ok(mut body) => body.collect().await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(body) => Some(body),
+        Err(e) => {
+            reject_body(ctx, &e).await;
+            None
+        }
+    }
+}
+
+async fn max_http_body_bytes(ctx: &Context) -> usize {
+    let configured = match ctx.global.get::<SharedConfig>().await {
+        Some(config) => config.read().await.limits.max_http_body_bytes,
+        None => 0,
+    };
+    if configured == 0 {
+        chunked::DEFAULT_MAX_HTTP_BODY_BYTES
+    } else {
+        configured
     }
-    (cl, body)
 }
 
 fn get_query_param<'a>(path: &'a str, key: &str) -> Option<&'a str> {
@@ -86,8 +143,8 @@ fn seed_type_from_ip(ip: &str) -> &'static str {
     if let Ok(addr) = ip.parse::<std::net::IpAddr>() {
         match addr {
             std::net::IpAddr::V6(_) => "ipv6",
-            std::net::IpAddr::V4(v4) => {
-                if v4.is_private() || v4.is_loopback() || v4.is_link_local() {
+            v4 => {
+                if ip_scope::is_inner_ip(&v4) {
                     "inner"
                 } else {
                     "external"
@@ -286,60 +343,58 @@ pub fn group_connections_by_direction(
             }
         }
     }
-    let resolve_nid =
-        |p: &PeerInfo, seen: &HashSet<String>| -> Option<String> {
-            if let Some(ref nid) = p.node_id {
-                return Some(nid.clone());
+    let resolve_nid = |p: &PeerInfo, seen: &HashSet<String>| -> Option<String> {
+        if let Some(ref nid) = p.node_id {
+            return Some(nid.clone());
+        }
+        if let Ok(addr) = p.addr.parse::<SocketAddr>() {
+            if let Some(nid) = by_seed.get(&addr) {
+                return Some((*nid).to_string());
             }
-            if let Ok(addr) = p.addr.parse::<SocketAddr>() {
-                if let Some(nid) = by_seed.get(&addr) {
-                    return Some((*nid).to_string());
+            if let Some(candidates) = by_ip.get(&addr.ip()) {
+                if candidates.len() == 1 {
+                    return Some(candidates[0].to_string());
                 }
-                if let Some(candidates) = by_ip.get(&addr.ip()) {
-                    if candidates.len() == 1 {
-                        return Some(candidates[0].to_string());
-                    }
-                    if let Some(out_nids) = ip_to_outbound_nids.get(&addr.ip()) {
-                        let unseen: Vec<&str> = out_nids
-                            .iter()
-                            .filter(|n| !seen.contains(n.as_str()))
-                            .map(|s| s.as_str())
-                            .collect();
-                        if unseen.len() == 1 {
-                            return Some(unseen[0].to_string());
-                        }
+                if let Some(out_nids) = ip_to_outbound_nids.get(&addr.ip()) {
+                    let unseen: Vec<&str> = out_nids
+                        .iter()
+                        .filter(|n| !seen.contains(n.as_str()))
+                        .map(|s| s.as_str())
+                        .collect();
+                    if unseen.len() == 1 {
+                        return Some(unseen[0].to_string());
                     }
                 }
             }
-            None
-        };
-    let group =
-        |peers: Vec<PeerInfo>| -> Vec<templates::NodeConnectionGroup> {
-            let mut seen = HashSet::new();
-            let mut result = Vec::new();
-            for p in peers {
-                let nid = match resolve_nid(&p, &seen) {
-                    Some(n) => n,
-                    None => continue,
-                };
-                if nid == local_node_id || !seen.insert(nid.clone()) {
-                    continue;
-                }
-                let mut passed: Vec<String> = Vec::new();
-                for ip in &p.wan_ips {
-                    passed.push(format!("{} (wan)", ip));
-                }
-                for ip in &p.intranet_ips {
-                    passed.push(format!("{} (intranet)", ip));
-                }
-                result.push(templates::NodeConnectionGroup {
-                    node_id: nid,
-                    addrs: p.addr.clone(),
-                    passed: passed.join(", "),
-                });
+        }
+        None
+    };
+    let group = |peers: Vec<PeerInfo>| -> Vec<templates::NodeConnectionGroup> {
+        let mut seen = HashSet::new();
+        let mut result = Vec::new();
+        for p in peers {
+            let nid = match resolve_nid(&p, &seen) {
+                Some(n) => n,
+                None => continue,
+            };
+            if nid == local_node_id || !seen.insert(nid.clone()) {
+                continue;
             }
-            result
-        };
+            let mut passed: Vec<String> = Vec::new();
+            for ip in &p.wan_ips {
+                passed.push(format!("{} (wan)", ip));
+            }
+            for ip in &p.intranet_ips {
+                passed.push(format!("{} (intranet)", ip));
+            }
+            result.push(templates::NodeConnectionGroup {
+                node_id: nid,
+                addrs: p.addr.clone(),
+                passed: passed.join(", "),
+            });
+        }
+        result
+    };
     templates::ConnectionsByDirection {
         inbound: group(raw.inbound),
         outbound: group(raw.outbound),
@@ -354,9 +409,10 @@ pub async fn handle_transfer(
     addr: &str,
     transfer_fn: super::types::TransferFn,
 ) -> bool {
-    let (cl, body_bytes) = read_http_body(ctx).await;
-    let transfer_req: serde_json::Value =
-        serde_json::from_slice(&body_bytes[..cl]).unwrap_or_default();
+    let Some(body_bytes) = read_http_body(ctx).await else {
+        return false;
+    };
+    let transfer_req: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap_or_default();
     let to = transfer_req
         .get("to")
         .and_then(|v| v.as_str())
@@ -401,15 +457,9 @@ pub async fn handle_address_api(
         .into_iter()
         .map(|tx| {
             let tx_type = match tx.tx_type {
-                crate::db::entity::transaction::entity::TxType::Transfer => {
-                    "transfer"
-                }
-                crate::db::entity::transaction::entity::TxType::Mint => {
-                    "mint"
-                }
-                crate::db::entity::transaction::entity::TxType::Burn => {
-                    "burn"
-                }
+                crate::db::entity::transaction::entity::TxType::Transfer => "transfer",
+                crate::db::entity::transaction::entity::TxType::Mint => "mint",
+                crate::db::entity::transaction::entity::TxType::Burn => "burn",
             };
             serde_json::json!({
                 "id": tx.id,
@@ -456,6 +506,7 @@ pub async fn handle_list_contacts(
             .load_profile(&c.address)
             .await
             .unwrap_or_default();
+        let verification = crate::safety_number::status(&gctx, &c.address).await;
         contact_map.insert(
             c.address.clone(),
             serde_json::json!({
@@ -466,6 +517,7 @@ pub async fn handle_list_contacts(
                 "unread_count": unread,
                 "nickname": profile.nickname,
                 "avatar_path": profile.avatar_path,
+                "verification": verification,
             }),
         );
     }
@@ -482,6 +534,7 @@ pub async fn handle_list_contacts(
                     .load_profile(&entry.address)
                     .await
                     .unwrap_or_default();
+                let verification = crate::safety_number::status(&gctx, &entry.address).await;
                 contact_map.insert(
                     entry.address.clone(),
                     serde_json::json!({
@@ -493,6 +546,7 @@ pub async fn handle_list_contacts(
                         "from_registry": true,
                         "nickname": profile.nickname,
                         "avatar_path": profile.avatar_path,
+                        "verification": verification,
                     }),
                 );
             }
@@ -505,9 +559,10 @@ pub async fn handle_list_contacts(
 }
 
 pub async fn handle_add_contact(ctx: &mut Context, db: &DatabaseConnection) -> bool {
-    let (cl, body_bytes) = read_http_body(ctx).await;
-    let contact_req: serde_json::Value =
-        serde_json::from_slice(&body_bytes[..cl]).unwrap_or_default();
+    let Some(body_bytes) = read_http_body(ctx).await else {
+        return false;
+    };
+    let contact_req: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap_or_default();
     let name = contact_req
         .get("name")
         .and_then(|v| v.as_str())
@@ -552,17 +607,78 @@ pub async fn handle_delete_contact(
     true
 }
 
+/// 与 `address` 的安全码与验证状态
+pub async fn handle_get_verification(
+    ctx: &mut Context,
+    gctx: Arc<GlobalContext>,
+    meta_path: &str,
+) -> bool {
+    let raw = get_query_param(meta_path, "address").unwrap_or("");
+    let addr = url_decode_query(raw);
+    let json = if addr.is_empty() {
+        serde_json::json!({"success": false, "error": "Missing 'address'"})
+    } else {
+        match crate::safety_number::inspect(&gctx, &addr).await {
+            Ok(v) => serde_json::json!({"success": true, "verification": v}),
+            Err(e) => serde_json::json!({"success": false, "error": e.to_string()}),
+        }
+    };
+    ctx.send(json.to_string(), Some(SubMediaType::Json));
+    true
+}
+
+/// `{"address": ..., "verified": true, "safety_number": "..."}` 标记为已验证（给出安全码时先比较），
+/// `"verified": false` 取消标记
+pub async fn handle_set_verification(ctx: &mut Context, gctx: Arc<GlobalContext>) -> bool {
+    let Some(body_bytes) = read_http_body(ctx).await else {
+        return false;
+    };
+    let req: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap_or_default();
+    let address = req.get("address").and_then(|v| v.as_str()).unwrap_or("");
+    let verified = req
+        .get("verified")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+    let expected = req.get("safety_number").and_then(|v| v.as_str());
+    let json = if address.is_empty() {
+        serde_json::json!({"success": false, "error": "Missing 'address'"})
+    } else if verified {
+        match crate::safety_number::confirm(&gctx, address, expected).await {
+            Ok(v) => serde_json::json!({"success": true, "verification": v}),
+            Err(e) => serde_json::json!({"success": false, "error": e.to_string()}),
+        }
+    } else {
+        let cleared = crate::safety_number::clear(&gctx, address).await;
+        serde_json::json!({"success": true, "cleared": cleared})
+    };
+    ctx.send(json.to_string(), Some(SubMediaType::Json));
+    true
+}
+
 pub async fn handle_get_chat_messages(
     ctx: &mut Context,
     user_store: &UserStore,
     meta_path: &str,
 ) -> bool {
+    let query = match ListQuery::parse(meta_path) {
+        Ok(q) => q,
+        Err(e) => {
+            let json = serde_json::json!({"success": false, "error": e});
+            ctx.send(json.to_string(), Some(SubMediaType::Json));
+            return true;
+        }
+    };
     let raw = get_query_param(meta_path, "contact").unwrap_or("");
     let addr = url_decode_query(raw);
-    let messages = user_store.get_messages(&addr).await.unwrap_or_default();
+    let mut messages = user_store.get_messages(&addr).await.unwrap_or_default();
     let _ = user_store.mark_as_read(&addr).await;
-    let messages_json: Vec<serde_json::Value> = messages
-        .into_iter()
+    messages.sort_by_key(|m| (m.timestamp, m.id));
+    let page = query.page(messages, |m| {
+        query.is_since(m.timestamp) && query.is_match(&[&m.content])
+    });
+    let messages_json: Vec<serde_json::Value> = page
+        .items
+        .iter()
         .map(|m| {
             serde_json::json!({
                 "id": m.id,
@@ -574,14 +690,39 @@ pub async fn handle_get_chat_messages(
             })
         })
         .collect();
-    let json = serde_json::json!({"success": true, "messages": messages_json});
+    let json = serde_json::json!({"success": true, "messages": messages_json, "page": page.meta()});
     ctx.send(json.to_string(), Some(SubMediaType::Json));
     true
 }
 
-pub async fn handle_get_conversations(ctx: &mut Context, user_store: &UserStore) -> bool {
-    let conversations = user_store.get_conversations().await.unwrap_or_default();
-    let json = serde_json::json!({"success": true, "conversations": conversations});
+pub async fn handle_get_conversations(
+    ctx: &mut Context,
+    user_store: &UserStore,
+    meta_path: &str,
+) -> bool {
+    let query = match ListQuery::parse(meta_path) {
+        Ok(q) => q,
+        Err(e) => {
+            let json = serde_json::json!({"success": false, "error": e});
+            ctx.send(json.to_string(), Some(SubMediaType::Json));
+            return true;
+        }
+    };
+    let mut conversations = user_store.get_conversations().await.unwrap_or_default();
+    // 最近的会话在前，时间相同时按地址排序，翻页时顺序稳定
+    conversations.sort_by(|a, b| {
+        b.last_timestamp
+            .cmp(&a.last_timestamp)
+            .then_with(|| a.contact_address.cmp(&b.contact_address))
+    });
+    let page = query.page(conversations, |c| {
+        query.is_since(c.last_timestamp) && query.is_match(&[&c.contact_address, &c.last_content])
+    });
+    let json = serde_json::json!({
+        "success": true,
+        "conversations": page.items,
+        "page": page.meta(),
+    });
     ctx.send(json.to_string(), Some(SubMediaType::Json));
     true
 }
@@ -633,12 +774,11 @@ pub async fn handle_save_profile(
     addr: &str,
     meta_path: &str,
 ) -> bool {
-    let (cl, body_bytes) = read_http_body(ctx).await;
+    let Some(body_bytes) = read_http_body(ctx).await else {
+        return false;
+    };
     let target = get_query_param(meta_path, "address").unwrap_or(addr);
-    if let Ok(mut profile) = serde_json::from_slice::<
-        crate::user_store::UserProfile,
-    >(&body_bytes[..cl])
-    {
+    if let Ok(mut profile) = serde_json::from_slice::<crate::user_store::UserProfile>(&body_bytes) {
         if profile.avatar_path.is_none() {
             if let Ok(existing) = user_store.load_profile(target).await {
                 profile.avatar_path = existing.avatar_path;
@@ -660,7 +800,9 @@ pub async fn handle_upload_avatar(
     addr: &str,
     meta_path: &str,
 ) -> bool {
-    let (_cl, body_bytes) = read_http_body(ctx).await;
+    let Some(body_bytes) = read_http_body(ctx).await else {
+        return false;
+    };
     let target = get_query_param(meta_path, "address").unwrap_or(addr);
     let name = "avatar.jpg";
     if let Err(e) = user_store.save_image(target, name, &body_bytes).await {
@@ -677,17 +819,146 @@ pub async fn handle_upload_avatar(
     true
 }
 
+/// 上传的文件：文件名、客户端声明的 Content-Type、内容
+type UploadedFile = (String, Option<String>, multipart::FileData);
+
+/// 读完整个 multipart 请求体：返回表单中的 `to` 字段与所有文件
+async fn collect_upload<R: tokio::io::AsyncRead + Unpin>(
+    mut reader: multipart::MultipartReader<R>,
+) -> Result<(Option<String>, Vec<UploadedFile>), multipart::MultipartError> {
+    let mut to = None;
+    let mut files = Vec::new();
+    while let Some(part) = reader.next_part().await? {
+        match part {
+            multipart::Part::Field { name, value } if name == "to" => to = Some(value),
+            multipart::Part::Field { .. } => {}
+            multipart::Part::File {
+                filename,
+                content_type,
+                data,
+                ..
+            } => files.push((filename, content_type, data)),
+        }
+    }
+    Ok((to, files))
+}
+
+/// `POST /api/upload?to=<address>`：接收 multipart 上传的文件，通过 P2P 二进制消息发给对端
+///
+/// 接收方也可以放在表单字段 `to` 中。
+pub async fn handle_upload(
+    ctx: &mut Context,
+    context: Arc<GlobalContext>,
+    meta_path: &str,
+) -> bool {
+    let content_type = ctx
+        .local
+        .get_ref::<HttpMetadata>()
+        .and_then(|m| m.headers.get(&HeaderKey::ContentType).cloned())
+        .unwrap_or_default();
+    let Some(boundary) = multipart::boundary(&content_type) else {
+        let json = serde_json::json!({"success": false, "error": "Expected multipart/form-data"});
+        ctx.send(json.to_string(), Some(SubMediaType::Json));
+        return true;
+    };
+    let limits = multipart::MultipartLimits::default();
+
+    // 不论 chunked 还是 Content-Length 都直接从连接流式解析；上限至少能容纳一个最大的文件
+    let limit = max_http_body_bytes(ctx)
+        .await
+        .max(usize::try_from(limits.max_file_bytes).unwrap_or(usize::MAX));
+    let parsed = match http_body_limited(ctx, limit) {
+        Ok(body) => collect_upload(multipart::MultipartReader::new(body, &boundary, limits)).await,
+        Err(e) => return reject_body(ctx, &e).await,
+    };
+    let (form_to, files) = match parsed {
+        Ok(v) => v,
+        Err(multipart::MultipartError::Io(e)) => {
+            return reject_body(ctx, &BodyError::from(e)).await;
+        }
+        Err(e) => {
+            tracing::warn!("Rejected upload: {}", e);
+            let json = serde_json::json!({"success": false, "error": e.to_string()});
+            ctx.send(json.to_string(), Some(SubMediaType::Json));
+            return true;
+        }
+    };
+
+    let to = get_query_param(meta_path, "to")
+        .map(url_decode_query)
+        .or(form_to)
+        .unwrap_or_default();
+    if to.is_empty() || files.is_empty() {
+        let json = serde_json::json!({"success": false, "error": "Missing 'to' or file"});
+        ctx.send(json.to_string(), Some(SubMediaType::Json));
+        return true;
+    }
+    let Some(node) = context.get::<Arc<Node>>().await else {
+        let json = serde_json::json!({"success": false, "error": "Node not available"});
+        ctx.send(json.to_string(), Some(SubMediaType::Json));
+        return true;
+    };
+    let to = node.registry.resolve_alias(&to);
+
+    let mut sent = Vec::new();
+    for (filename, part_type, data) in files {
+        let content_type = part_type.unwrap_or_else(|| guess_content_type(&filename).to_string());
+        let size = data.len();
+        let bytes = match data.into_bytes().await {
+            Ok(b) => b,
+            Err(e) => {
+                tracing::error!("Failed to read spooled upload {}: {}", filename, e);
+                continue;
+            }
+        };
+        let name = (!filename.is_empty()).then(|| filename.clone());
+        match node.send_binary(&to, &content_type, name, bytes).await {
+            Ok(request_id) => sent.push(serde_json::json!({
+                "filename": filename,
+                "size": size,
+                "content_type": content_type,
+                "request_id": request_id,
+            })),
+            Err(e) => {
+                let json = serde_json::json!({
+                    "success": false,
+                    "error": e.to_string(),
+                    "sent": sent,
+                });
+                ctx.send(json.to_string(), Some(SubMediaType::Json));
+                return true;
+            }
+        }
+    }
+    let json = serde_json::json!({"success": true, "to": to, "sent": sent});
+    ctx.send(json.to_string(), Some(SubMediaType::Json));
+    true
+}
+
+/// 签名的管理命令，见 [`crate::admin`]；拒绝原因对应的状态码放在 `status` 字段
+pub async fn handle_admin(ctx: &mut Context, gctx: Arc<GlobalContext>) -> bool {
+    let Some(body_bytes) = read_http_body(ctx).await else {
+        return false;
+    };
+    let (status, mut json) = crate::admin::handle(&gctx, &body_bytes).await;
+    json["status"] = status.into();
+    ctx.send(json.to_string(), Some(SubMediaType::Json));
+    true
+}
+
 pub async fn handle_send_chat(
     ctx: &mut Context,
     context: Arc<GlobalContext>,
     addr: &str,
     user_store: Arc<UserStore>,
 ) -> bool {
-    use crate::web::aex_re_exports::WsSenderList;
     use crate::protocols::commands::message::{PendingAcks, next_request_id, send_text_message};
+    use crate::web::aex_re_exports::WsSenderList;
     const ACK_TIMEOUT_SECS: u64 = 30;
-    let (cl, body_bytes) = read_http_body(ctx).await;
-    let send_req: serde_json::Value = serde_json::from_slice(&body_bytes[..cl]).unwrap_or_default();
+    let Some(body_bytes) = read_http_body(ctx).await else {
+        return false;
+    };
+    let send_req: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap_or_default();
     let to = send_req.get("to").and_then(|v| v.as_str()).unwrap_or("");
     let content = send_req
         .get("message")
@@ -726,8 +997,7 @@ pub async fn handle_send_chat(
     let (ack_tx, ack_rx) = tokio::sync::oneshot::channel::<bool>();
     {
         if let Some(pending) = context.get::<PendingAcks>().await {
-            let mut guard = pending.lock().await;
-            guard.insert(request_id, ack_tx);
+            pending.insert(request_id, ack_tx);
         } else {
             tracing::warn!("⚠️ No PendingAcks in context, message will be fire-and-forget");
         }
@@ -843,8 +1113,7 @@ pub async fn handle_send_chat(
         tracing::error!("❌ No message was sent successfully, aborting");
         {
             if let Some(pending) = context.get::<PendingAcks>().await {
-                let mut guard = pending.lock().await;
-                guard.remove(&request_id);
+                pending.remove(&request_id);
             }
         }
         let json = serde_json::json!({"success": false, "error": "Failed to send message: no matching connection or send error (check server logs)"});
@@ -861,8 +1130,7 @@ pub async fn handle_send_chat(
         let delivered =
             tokio::time::timeout(std::time::Duration::from_secs(ACK_TIMEOUT_SECS), ack_rx).await;
         if let Some(pending) = context_bg.get::<PendingAcks>().await {
-            let mut guard = pending.lock().await;
-            guard.remove(&request_id);
+            pending.remove(&request_id);
         }
         match delivered {
             Ok(Ok(true)) => {
@@ -893,11 +1161,22 @@ pub async fn handle_send_chat(
                 }
             }
             Ok(Ok(false)) => {
+                let code =
+                    crate::protocols::commands::error::delivery_error(&context_bg, request_id)
+                        .await
+                        .map(|e| e.code.to_string());
                 tracing::warn!(
-                    "❌ Message {} rejected by recipient {}",
+                    "❌ Message {} rejected by recipient {} ({})",
                     request_id,
-                    to_addr_bg
+                    to_addr_bg,
+                    code.as_deref().unwrap_or("no error code")
                 );
+                if let Err(e) = user_store_bg
+                    .update_last_sent_status(&to_addr_bg, "failed")
+                    .await
+                {
+                    tracing::error!("Failed to update message status: {}", e);
+                }
             }
             Ok(Err(_)) => {
                 tracing::warn!(
@@ -954,7 +1233,7 @@ async fn gather_page_data(
                             continue;
                         }
                         let ip_str = format!("{}:{}", addr.ip(), addr.port());
-                        match NetworkScope::from_ip(&addr.ip()) {
+                        match ip_scope::classify(&addr.ip()) {
                             NetworkScope::Intranet => intranet_ips.push(ip_str),
                             NetworkScope::Extranet => wan_ips.push(ip_str),
                         }
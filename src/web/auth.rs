@@ -0,0 +1,558 @@
+//! Web 页面与 API 的身份认证
+//!
+//! 配置 `[web_auth] enabled = true` 后，页面与 `/api/*` 请求必须带凭据：`Authorization: Bearer <token>`，
+//! 或在 `allow_basic = true` 时使用 `Authorization: Basic`（用户名与密码）。用户与 API token 保存在
+//! 存储目录的 `web_users.json`：密码只保存 Argon2id 的 PHC 字符串，token 只保存 SHA-256 摘要，
+//! 明文 token 只在签发时显示一次。早期版本保存的加盐 HMAC 仍可校验，用户下次以 Basic 认证成功时
+//! 改存为 Argon2id。用户由 REPL 的 `webuser` 命令管理。
+//!
+//! 每个路由在注册时声明所需的访问级别（[`Access`]），由 [`require`] 包装 handler：缺少或无效的凭据
+//! 返回 401 与 `WWW-Authenticate`，角色不足返回 403。`POST /api/admin` 自带签名校验，声明为
+//! [`Access::Public`]。静态文件要求 [`Access::Viewer`]，HTTP 隧道（`/peer/*`）与 `/ws` 要求
+//! [`Access::User`]。未启用时所有请求照常放行。
+
+use std::{
+    fmt,
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
+
+use argon2::{
+    Argon2,
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+};
+use base64::Engine;
+use futures::FutureExt;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+
+use super::aex_re_exports::{Context, GlobalContext, HeaderKey, HttpMetadata, StatusCode};
+use super::routes::{self, Executor};
+use crate::config::SharedConfig;
+use crate::io_storage::{IOStorage, STORAGE_WEB_USERS};
+
+/// 默认的认证域
+pub const DEFAULT_REALM: &str = "zz-p2p";
+/// API token 的前缀，便于在日志与配置中识别
+pub const TOKEN_PREFIX: &str = "zzp_";
+/// 每个用户最多持有的 token 数
+pub const MAX_TOKENS_PER_USER: usize = 16;
+
+/// `[web_auth]` 配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebAuthConfig {
+    pub enabled: bool,
+    /// 是否接受 Basic 认证
+    pub allow_basic: bool,
+    pub realm: String,
+}
+
+impl Default for WebAuthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            allow_basic: false,
+            realm: DEFAULT_REALM.to_string(),
+        }
+    }
+}
+
+/// Web 用户的角色，后者包含前者的全部权限
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebRole {
+    /// 只读：页面与查询接口
+    Viewer,
+    /// 还可以发消息、编辑联系人与资料、上传
+    User,
+    /// 还可以转账
+    Admin,
+}
+
+impl fmt::Display for WebRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            WebRole::Viewer => "viewer",
+            WebRole::User => "user",
+            WebRole::Admin => "admin",
+        })
+    }
+}
+
+impl FromStr for WebRole {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "viewer" => Ok(WebRole::Viewer),
+            "user" => Ok(WebRole::User),
+            "admin" => Ok(WebRole::Admin),
+            _ => Err(anyhow::anyhow!(
+                "Unknown role {} (viewer, user or admin)",
+                s
+            )),
+        }
+    }
+}
+
+/// 路由声明的访问级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// 无需凭据
+    Public,
+    Viewer,
+    User,
+    Admin,
+}
+
+impl Access {
+    /// 所需的最低角色；`Public` 为 None
+    pub fn required_role(&self) -> Option<WebRole> {
+        match self {
+            Access::Public => None,
+            Access::Viewer => Some(WebRole::Viewer),
+            Access::User => Some(WebRole::User),
+            Access::Admin => Some(WebRole::Admin),
+        }
+    }
+}
+
+/// 一个 API token 的登记信息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub label: String,
+    /// token 的 SHA-256（hex）
+    pub hash: String,
+    /// 签发时间（毫秒）
+    pub created_at: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebUser {
+    pub name: String,
+    pub role: WebRole,
+    /// Argon2id 的 PHC 字符串（早期版本为 `<salt>$<hmac>`），未设置时不能使用 Basic 认证
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub tokens: Vec<ApiToken>,
+}
+
+/// 持久化的用户列表
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WebUserList {
+    pub users: Vec<WebUser>,
+}
+
+/// 保存在 GlobalContext 中的用户列表
+pub type SharedWebUsers = Arc<RwLock<WebUserList>>;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex(&bytes)
+}
+
+/// 逐字节比较，耗时与内容无关
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// 早期版本的 `<salt>$<hmac>` 格式，仅用于校验尚未迁移的密码
+fn legacy_password_mac(salt: &str, password: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(salt.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(password.as_bytes());
+    hex(&mac.finalize().into_bytes())
+}
+
+/// 以随机盐生成 Argon2id 的 PHC 字符串
+pub fn hash_password(password: &str) -> String {
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let salt = SaltString::encode_b64(&salt).expect("16-byte salt is within PHC limits");
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("default Argon2 params are valid")
+        .to_string()
+}
+
+/// 保存的是否为早期的 HMAC 格式（PHC 字符串以 `$` 开头）
+pub fn is_legacy_password(stored: &str) -> bool {
+    !stored.starts_with('$')
+}
+
+pub fn verify_password(stored: &str, password: &str) -> bool {
+    if is_legacy_password(stored) {
+        let Some((salt, mac)) = stored.split_once('$') else {
+            return false;
+        };
+        return constant_time_eq(
+            legacy_password_mac(salt, password).as_bytes(),
+            mac.as_bytes(),
+        );
+    }
+    match PasswordHash::new(stored) {
+        Ok(hash) => Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+pub fn hash_token(token: &str) -> String {
+    hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn validate_name(name: &str) -> anyhow::Result<()> {
+    if name.is_empty() || name.contains(':') || name.chars().any(char::is_whitespace) {
+        return Err(anyhow::anyhow!(
+            "User name must be non-empty without spaces or ':'"
+        ));
+    }
+    Ok(())
+}
+
+impl WebUserList {
+    pub fn get(&self, name: &str) -> Option<&WebUser> {
+        self.users.iter().find(|u| u.name == name)
+    }
+
+    fn get_mut(&mut self, name: &str) -> anyhow::Result<&mut WebUser> {
+        self.users
+            .iter_mut()
+            .find(|u| u.name == name)
+            .ok_or_else(|| anyhow::anyhow!("No web user {}", name))
+    }
+
+    pub fn add(&mut self, name: &str, role: WebRole, password: Option<&str>) -> anyhow::Result<()> {
+        validate_name(name)?;
+        if self.get(name).is_some() {
+            return Err(anyhow::anyhow!("Web user {} already exists", name));
+        }
+        self.users.push(WebUser {
+            name: name.to_string(),
+            role,
+            password: password.map(hash_password),
+            tokens: Vec::new(),
+        });
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<WebUser> {
+        let index = self.users.iter().position(|u| u.name == name)?;
+        Some(self.users.remove(index))
+    }
+
+    /// 设置或清除（None）密码
+    pub fn set_password(&mut self, name: &str, password: Option<&str>) -> anyhow::Result<()> {
+        self.get_mut(name)?.password = password.map(hash_password);
+        Ok(())
+    }
+
+    /// 签发 token 并返回明文；同名 token 被替换
+    pub fn issue_token(&mut self, name: &str, label: &str, now: u128) -> anyhow::Result<String> {
+        let user = self.get_mut(name)?;
+        user.tokens.retain(|t| t.label != label);
+        if user.tokens.len() >= MAX_TOKENS_PER_USER {
+            return Err(anyhow::anyhow!(
+                "{} already has {} tokens",
+                name,
+                MAX_TOKENS_PER_USER
+            ));
+        }
+        let token = format!("{}{}", TOKEN_PREFIX, random_hex(32));
+        user.tokens.push(ApiToken {
+            label: label.to_string(),
+            hash: hash_token(&token),
+            created_at: now,
+        });
+        Ok(token)
+    }
+
+    /// 吊销 token，返回是否存在
+    pub fn revoke_token(&mut self, name: &str, label: &str) -> bool {
+        match self.get_mut(name) {
+            Ok(user) => {
+                let before = user.tokens.len();
+                user.tokens.retain(|t| t.label != label);
+                user.tokens.len() != before
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// 校验凭据，返回对应的用户
+    pub fn authenticate(&self, credentials: &Credentials) -> Option<&WebUser> {
+        match credentials {
+            Credentials::Bearer(token) => {
+                let hash = hash_token(token);
+                self.users.iter().find(|u| {
+                    u.tokens
+                        .iter()
+                        .any(|t| constant_time_eq(t.hash.as_bytes(), hash.as_bytes()))
+                })
+            }
+            Credentials::Basic { user, password } => {
+                let found = self.get(user)?;
+                let stored = found.password.as_deref()?;
+                verify_password(stored, password).then_some(found)
+            }
+        }
+    }
+}
+
+/// `Authorization` 头携带的凭据
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Credentials {
+    Bearer(String),
+    Basic { user: String, password: String },
+}
+
+impl Credentials {
+    pub fn parse(header: &str) -> Option<Self> {
+        let (scheme, value) = header.trim().split_once(' ')?;
+        let value = value.trim();
+        if scheme.eq_ignore_ascii_case("bearer") && !value.is_empty() {
+            return Some(Credentials::Bearer(value.to_string()));
+        }
+        if scheme.eq_ignore_ascii_case("basic") {
+            let decoded = base64::engine::general_purpose::STANDARD
+                .decode(value)
+                .ok()?;
+            let decoded = String::from_utf8(decoded).ok()?;
+            let (user, password) = decoded.split_once(':')?;
+            return Some(Credentials::Basic {
+                user: user.to_string(),
+                password: password.to_string(),
+            });
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthError {
+    /// 缺少或无效的凭据
+    Unauthorized,
+    /// 凭据有效但角色不足
+    Forbidden,
+}
+
+impl AuthError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AuthError::Unauthorized => StatusCode::Unauthorized,
+            AuthError::Forbidden => StatusCode::Forbidden,
+        }
+    }
+
+    /// 状态行中的原因短语
+    pub fn reason(&self) -> &'static str {
+        match self {
+            AuthError::Unauthorized => "Unauthorized",
+            AuthError::Forbidden => "Forbidden",
+        }
+    }
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AuthError::Unauthorized => "Authentication required",
+            AuthError::Forbidden => "Insufficient role",
+        })
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+/// 按路由的访问级别检查 `Authorization` 头；通过时返回用户名（未启用或公开路由为 None）
+pub fn authorize(
+    config: &WebAuthConfig,
+    users: &WebUserList,
+    header: Option<&str>,
+    access: Access,
+) -> Result<Option<String>, AuthError> {
+    let Some(required) = access.required_role() else {
+        return Ok(None);
+    };
+    if !config.enabled {
+        return Ok(None);
+    }
+    let credentials = header
+        .and_then(Credentials::parse)
+        .ok_or(AuthError::Unauthorized)?;
+    if matches!(credentials, Credentials::Basic { .. }) && !config.allow_basic {
+        return Err(AuthError::Unauthorized);
+    }
+    let user = users
+        .authenticate(&credentials)
+        .ok_or(AuthError::Unauthorized)?;
+    if user.role < required {
+        return Err(AuthError::Forbidden);
+    }
+    Ok(Some(user.name.clone()))
+}
+
+/// 401 响应的 `WWW-Authenticate` 头
+pub fn challenges(config: &WebAuthConfig) -> Vec<String> {
+    let mut challenges = vec![format!("Bearer realm=\"{}\"", config.realm)];
+    if config.allow_basic {
+        challenges.push(format!(
+            "Basic realm=\"{}\", charset=\"UTF-8\"",
+            config.realm
+        ));
+    }
+    challenges
+}
+
+async fn shared(gctx: &GlobalContext) -> SharedWebUsers {
+    match gctx.get::<SharedWebUsers>().await {
+        Some(users) => users,
+        None => {
+            let users = SharedWebUsers::default();
+            gctx.set(users.clone()).await;
+            users
+        }
+    }
+}
+
+pub async fn list(gctx: &GlobalContext) -> Vec<WebUser> {
+    let users = shared(gctx).await;
+    let guard = users.read().unwrap_or_else(|e| e.into_inner());
+    guard.users.clone()
+}
+
+/// 修改用户列表并持久化
+pub async fn update<R>(gctx: &GlobalContext, f: impl FnOnce(&mut WebUserList) -> R) -> R {
+    let users = shared(gctx).await;
+    let (result, current) = {
+        let mut guard = users.write().unwrap_or_else(|e| e.into_inner());
+        let result = f(&mut guard);
+        (result, guard.clone())
+    };
+    match gctx.get::<IOStorage>().await {
+        Some(ios) => ios.save::<WebUserList>(&current, STORAGE_WEB_USERS).await,
+        None => tracing::error!("IOStorage not found in context, web users not persisted"),
+    }
+    result
+}
+
+/// 为 handler 加上访问级别检查
+pub fn require(access: Access, handler: Executor) -> Executor {
+    Arc::new(move |ctx: &mut Context| {
+        let handler = handler.clone();
+        async move {
+            if access == Access::Public {
+                return handler(ctx).await;
+            }
+            let config = match ctx.global.get::<SharedConfig>().await {
+                Some(config) => config.read().await.web_auth.clone(),
+                None => WebAuthConfig::default(),
+            };
+            if !config.enabled {
+                return handler(ctx).await;
+            }
+            let header = ctx
+                .local
+                .get_ref::<HttpMetadata>()
+                .and_then(|m| m.headers.get(&HeaderKey::Authorization).cloned());
+            let users = shared(&ctx.global).await;
+            let result = {
+                let guard = users.read().unwrap_or_else(|e| e.into_inner());
+                authorize(&config, &guard, header.as_deref(), access)
+            };
+            match result {
+                Ok(user) => {
+                    if let Some(user) = user {
+                        upgrade_legacy_password(&ctx.global, &user, header.as_deref()).await;
+                    }
+                    handler(ctx).await
+                }
+                Err(e) => reject(ctx, &config, e).await,
+            }
+        }
+        .boxed()
+    })
+}
+
+/// 以 Basic 认证成功且保存的仍是早期 HMAC 格式时，用这次的明文密码改存为 Argon2id
+async fn upgrade_legacy_password(gctx: &GlobalContext, user: &str, header: Option<&str>) {
+    let Some(Credentials::Basic {
+        user: name,
+        password,
+    }) = header.and_then(Credentials::parse)
+    else {
+        return;
+    };
+    if name != user {
+        return;
+    }
+    let legacy = {
+        let users = shared(gctx).await;
+        let guard = users.read().unwrap_or_else(|e| e.into_inner());
+        guard
+            .get(user)
+            .and_then(|u| u.password.as_deref())
+            .is_some_and(is_legacy_password)
+    };
+    if legacy {
+        let _ = update(gctx, |users| users.set_password(user, Some(&password))).await;
+        tracing::info!("🔑 Upgraded password hash of web user {} to Argon2id", user);
+    }
+}
+
+/// 回写 401 / 403；返回值可直接作为 handler 的返回值
+async fn reject(ctx: &mut Context, config: &WebAuthConfig, e: AuthError) -> bool {
+    let path = ctx
+        .local
+        .get_ref::<HttpMetadata>()
+        .map(|m| m.path.clone())
+        .unwrap_or_default();
+    tracing::warn!("🔒 Rejected {} from {}: {}", path, ctx.addr, e);
+    let close = routes::close_after_error(ctx);
+    let code = e.status() as u16;
+    let body = serde_json::json!({"success": false, "error": e.to_string()}).to_string();
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n",
+        code,
+        e.reason(),
+        body.len()
+    );
+    if e == AuthError::Unauthorized {
+        for challenge in challenges(config) {
+            head.push_str(&format!("WWW-Authenticate: {}\r\n", challenge));
+        }
+    }
+    if close {
+        head.push_str("Connection: close\r\n");
+    }
+    head.push_str("\r\n");
+
+    let Some(writer) = ctx.writer.as_deref_mut() else {
+        return false;
+    };
+    let result = async {
+        writer.write_all(head.as_bytes()).await?;
+        writer.write_all(body.as_bytes()).await?;
+        writer.flush().await
+    }
+    .await;
+    if let Err(err) = result {
+        tracing::debug!("{} response aborted: {}", code, err);
+        return false;
+    }
+    if close {
+        let _ = writer.shutdown().await;
+    }
+    false
+}
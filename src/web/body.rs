@@ -0,0 +1,265 @@
+//! HTTP 请求体的流式读取
+//!
+//! [`BodyReader`] 按 Content-Length 或 chunked 编码边读边交付请求体，实现 `AsyncRead`，
+//! handler 可以直接把它交给解析器或写入文件，不必先把整个请求体读进内存。
+//! 声明的 Content-Length 超过上限时在读取任何数据之前就返回 `TooLarge`；chunked 请求体
+//! 在累计长度超限时中止。两种情况都应回 413 并关闭连接：请求体没有读完，连接上的后续
+//! 数据已无法对齐到下一个请求。
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+use super::chunked::{BodyError, MAX_CHUNK_LINE_BYTES, MAX_TRAILERS, is_chunked};
+
+/// [`BodyReader::collect`] 预先分配的最大容量，之后随实际到达的数据增长
+const INITIAL_CAPACITY: usize = 8 * 1024;
+
+enum State {
+    /// 按 Content-Length 读取，剩余字节数
+    Length(u64),
+    /// 块大小行
+    ChunkSize(Vec<u8>),
+    /// 当前块剩余字节数
+    ChunkData(u64),
+    /// 块数据之后的 CRLF
+    ChunkEnd(Vec<u8>),
+    /// 结束块之后的 trailer 行
+    Trailer(Vec<u8>),
+    Done,
+}
+
+/// 有长度上限的请求体读取句柄
+pub struct BodyReader<R> {
+    reader: R,
+    state: State,
+    limit: usize,
+    read: usize,
+    trailers: Vec<(String, String)>,
+}
+
+impl<R: AsyncRead + Unpin> BodyReader<R> {
+    /// 读取 `length` 字节的请求体；超过 `limit` 时直接返回 `TooLarge`
+    pub fn with_length(reader: R, length: u64, limit: usize) -> Result<Self, BodyError> {
+        if length > limit as u64 {
+            return Err(BodyError::TooLarge { limit });
+        }
+        Ok(Self::new(reader, State::Length(length), limit))
+    }
+
+    /// 读取 chunked 编码的请求体
+    pub fn chunked(reader: R, limit: usize) -> Self {
+        Self::new(reader, State::ChunkSize(Vec::new()), limit)
+    }
+
+    /// 按请求头选择读取方式：Transfer-Encoding 优先于 Content-Length（RFC 9112 §6.3），
+    /// 两者都没有时请求体为空
+    pub fn from_headers(
+        reader: R,
+        content_length: Option<&str>,
+        transfer_encoding: Option<&str>,
+        limit: usize,
+    ) -> Result<Self, BodyError> {
+        if is_chunked(transfer_encoding) {
+            return Ok(Self::chunked(reader, limit));
+        }
+        let length = match content_length {
+            Some(value) => value
+                .trim()
+                .parse::<u64>()
+                .map_err(|_| BodyError::InvalidLength)?,
+            None => 0,
+        };
+        Self::with_length(reader, length, limit)
+    }
+
+    fn new(reader: R, state: State, limit: usize) -> Self {
+        Self {
+            reader,
+            state,
+            limit,
+            read: 0,
+            trailers: Vec::new(),
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// 已交付的请求体字节数
+    pub fn bytes_read(&self) -> usize {
+        self.read
+    }
+
+    /// 请求体是否已读完（chunked 请求体包括 trailer）
+    pub fn is_done(&self) -> bool {
+        matches!(self.state, State::Done)
+    }
+
+    /// chunked 请求体的 trailer，读完之后才完整
+    pub fn trailers(&self) -> &[(String, String)] {
+        &self.trailers
+    }
+
+    /// 读完剩余的请求体
+    pub async fn collect(&mut self) -> Result<Vec<u8>, BodyError> {
+        let hint = match self.state {
+            State::Length(remaining) => usize::try_from(remaining).unwrap_or(usize::MAX),
+            _ => 0,
+        };
+        let mut data = Vec::with_capacity(hint.min(INITIAL_CAPACITY));
+        self.read_to_end(&mut data).await?;
+        Ok(data)
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for BodyReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+        loop {
+            match &mut this.state {
+                State::Done => return Poll::Ready(Ok(())),
+                State::Length(0) => this.state = State::Done,
+                State::Length(remaining) => {
+                    let n = ready!(poll_data(&mut this.reader, cx, buf, *remaining))?;
+                    *remaining -= n as u64;
+                    this.read += n;
+                    return Poll::Ready(Ok(()));
+                }
+                State::ChunkSize(line) => {
+                    let line = ready!(poll_line(&mut this.reader, cx, line))?;
+                    let size = line.split(';').next().unwrap_or("").trim();
+                    // from_str_radix 接受前导 `+`，块大小只允许十六进制数字
+                    if size.is_empty() || !size.bytes().all(|b| b.is_ascii_hexdigit()) {
+                        return Poll::Ready(Err(BodyError::Malformed("invalid chunk size").into()));
+                    }
+                    let size = usize::from_str_radix(size, 16)
+                        .map_err(|_| BodyError::Malformed("invalid chunk size"))?;
+                    if size == 0 {
+                        this.state = State::Trailer(Vec::new());
+                    } else if this.read.saturating_add(size) > this.limit {
+                        return Poll::Ready(Err(BodyError::TooLarge { limit: this.limit }.into()));
+                    } else {
+                        this.state = State::ChunkData(size as u64);
+                    }
+                }
+                State::ChunkData(0) => this.state = State::ChunkEnd(Vec::new()),
+                State::ChunkData(remaining) => {
+                    let n = ready!(poll_data(&mut this.reader, cx, buf, *remaining))?;
+                    *remaining -= n as u64;
+                    this.read += n;
+                    return Poll::Ready(Ok(()));
+                }
+                State::ChunkEnd(line) => {
+                    if !ready!(poll_line(&mut this.reader, cx, line))?.is_empty() {
+                        return Poll::Ready(Err(BodyError::Malformed(
+                            "chunk not terminated by CRLF",
+                        )
+                        .into()));
+                    }
+                    this.state = State::ChunkSize(Vec::new());
+                }
+                State::Trailer(line) => {
+                    let line = ready!(poll_line(&mut this.reader, cx, line))?;
+                    if line.is_empty() {
+                        this.state = State::Done;
+                        continue;
+                    }
+                    if this.trailers.len() >= MAX_TRAILERS {
+                        return Poll::Ready(Err(BodyError::Malformed("too many trailers").into()));
+                    }
+                    let (name, value) = line
+                        .split_once(':')
+                        .ok_or(BodyError::Malformed("invalid trailer"))?;
+                    this.trailers
+                        .push((name.trim().to_string(), value.trim().to_string()));
+                }
+            }
+        }
+    }
+}
+
+/// 从底层连接读取至多 `remaining` 字节到 `buf`；连接提前关闭视为请求体被截断
+fn poll_data<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    cx: &mut Context<'_>,
+    buf: &mut ReadBuf<'_>,
+    remaining: u64,
+) -> Poll<io::Result<usize>> {
+    let max = buf
+        .remaining()
+        .min(usize::try_from(remaining).unwrap_or(usize::MAX));
+    let mut limited = ReadBuf::new(buf.initialize_unfilled_to(max));
+    ready!(Pin::new(reader).poll_read(cx, &mut limited))?;
+    let n = limited.filled().len();
+    if n == 0 {
+        return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+    }
+    buf.advance(n);
+    Poll::Ready(Ok(n))
+}
+
+/// 逐字节读取一行（不含 CRLF）；不做预读，避免吞掉同一连接上的下一个请求。
+/// 未读完的部分保存在 `line` 中，下次调用继续
+fn poll_line<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    cx: &mut Context<'_>,
+    line: &mut Vec<u8>,
+) -> Poll<Result<String, BodyError>> {
+    loop {
+        let mut byte = [0u8; 1];
+        let mut one = ReadBuf::new(&mut byte);
+        ready!(Pin::new(&mut *reader).poll_read(cx, &mut one))?;
+        if one.filled().is_empty() {
+            return Poll::Ready(Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()));
+        }
+        if byte[0] == b'\n' {
+            break;
+        }
+        line.push(byte[0]);
+        if line.len() > MAX_CHUNK_LINE_BYTES {
+            return Poll::Ready(Err(BodyError::Malformed("line too long")));
+        }
+    }
+    let mut line = std::mem::take(line);
+    if line.pop() != Some(b'\r') {
+        return Poll::Ready(Err(BodyError::Malformed("missing CRLF")));
+    }
+    Poll::Ready(String::from_utf8(line).map_err(|_| BodyError::Malformed("non-utf8 line")))
+}
+
+/// 写出请求体错误的响应（413 或 400）并关闭写端；连接已断开（`Io`）时不写任何内容
+pub async fn reject<W>(writer: &mut W, e: &BodyError) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    let Some(status) = e.status() else {
+        return Ok(());
+    };
+    let reason = if status == 413 {
+        "Payload Too Large"
+    } else {
+        "Bad Request"
+    };
+    let body = serde_json::json!({"success": false, "error": e.to_string()}).to_string();
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason,
+        body.len()
+    );
+    writer.write_all(head.as_bytes()).await?;
+    writer.write_all(body.as_bytes()).await?;
+    writer.flush().await?;
+    writer.shutdown().await
+}
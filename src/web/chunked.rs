@@ -0,0 +1,158 @@
+//! HTTP/1.1 分块传输编码（Transfer-Encoding: chunked）
+//!
+//! 请求体按块读取并累计长度，超过上限立即中止；块扩展被忽略，结束块之后的 trailer
+//! 作为键值对返回，解码本身由 [`crate::web::body::BodyReader`] 流式完成。响应侧用
+//! [`ChunkedWriter`] 边生成边写出。
+
+use std::fmt;
+
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+
+use super::body::BodyReader;
+
+/// 未配置 `limits.max_http_body_bytes` 时的请求体上限
+pub const DEFAULT_MAX_HTTP_BODY_BYTES: usize = 16 * 1024 * 1024;
+/// 块大小行与 trailer 行的最大长度
+pub const MAX_CHUNK_LINE_BYTES: usize = 4096;
+/// 最多接受的 trailer 数量
+pub const MAX_TRAILERS: usize = 64;
+
+#[derive(Debug)]
+pub enum BodyError {
+    /// 请求体超过上限
+    TooLarge {
+        limit: usize,
+    },
+    /// 分块格式错误
+    Malformed(&'static str),
+    /// Content-Length 不是合法的十进制数
+    InvalidLength,
+    Io(std::io::Error),
+}
+
+impl BodyError {
+    /// 应回写的状态码；`Io` 表示连接已断开，无需响应
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            BodyError::TooLarge { .. } => Some(413),
+            BodyError::Malformed(_) | BodyError::InvalidLength => Some(400),
+            BodyError::Io(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for BodyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BodyError::TooLarge { limit } => write!(f, "body exceeds {} bytes", limit),
+            BodyError::Malformed(reason) => write!(f, "malformed chunked body: {}", reason),
+            BodyError::InvalidLength => write!(f, "invalid Content-Length"),
+            BodyError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for BodyError {}
+
+/// 经由 `AsyncRead` 传出的 [`BodyError`] 会还原为原来的错误
+impl From<std::io::Error> for BodyError {
+    fn from(e: std::io::Error) -> Self {
+        if !e.get_ref().is_some_and(|inner| inner.is::<BodyError>()) {
+            return BodyError::Io(e);
+        }
+        match e.into_inner().map(|inner| inner.downcast::<BodyError>()) {
+            Some(Ok(inner)) => *inner,
+            _ => unreachable!("inner error checked above"),
+        }
+    }
+}
+
+impl From<BodyError> for std::io::Error {
+    fn from(e: BodyError) -> Self {
+        match e {
+            BodyError::Io(e) => e,
+            other => std::io::Error::new(std::io::ErrorKind::InvalidData, other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChunkedBody {
+    pub data: Vec<u8>,
+    pub trailers: Vec<(String, String)>,
+}
+
+/// `Transfer-Encoding` 的最后一个编码是否为 chunked
+pub fn is_chunked(transfer_encoding: Option<&str>) -> bool {
+    transfer_encoding
+        .and_then(|v| v.rsplit(',').next())
+        .map(|last| last.trim().eq_ignore_ascii_case("chunked"))
+        .unwrap_or(false)
+}
+
+/// 读取完整的分块请求体（含 trailer），总长度超过 `max_bytes` 时返回 `TooLarge`
+pub async fn read_chunked<R>(reader: &mut R, max_bytes: usize) -> Result<ChunkedBody, BodyError>
+where
+    R: AsyncRead + Unpin + ?Sized,
+{
+    let mut body = BodyReader::chunked(reader, max_bytes);
+    let data = body.collect().await?;
+    Ok(ChunkedBody {
+        data,
+        trailers: body.trailers().to_vec(),
+    })
+}
+
+/// 编码单个数据块；空数据返回空（空块会被对端当作结束块）
+pub fn encode_chunk(data: &[u8]) -> Vec<u8> {
+    if data.is_empty() {
+        return Vec::new();
+    }
+    let mut out = format!("{:x}\r\n", data.len()).into_bytes();
+    out.extend_from_slice(data);
+    out.extend_from_slice(b"\r\n");
+    out
+}
+
+/// 结束块与 trailer
+pub fn encode_last_chunk(trailers: &[(&str, &str)]) -> Vec<u8> {
+    let mut out = String::from("0\r\n");
+    for (name, value) in trailers {
+        out.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    out.push_str("\r\n");
+    out.into_bytes()
+}
+
+/// 分块响应写出器：先写响应头（自动加上 `Transfer-Encoding: chunked`），再逐块写数据
+pub struct ChunkedWriter<'a, W: AsyncWrite + Unpin + ?Sized> {
+    writer: &'a mut W,
+}
+
+impl<'a, W: AsyncWrite + Unpin + ?Sized> ChunkedWriter<'a, W> {
+    pub async fn start(
+        writer: &'a mut W,
+        status: &str,
+        headers: &[(&str, String)],
+    ) -> std::io::Result<Self> {
+        let mut head = format!("HTTP/1.1 {}\r\n", status);
+        for (k, v) in headers {
+            head.push_str(&format!("{}: {}\r\n", k, v));
+        }
+        head.push_str("Transfer-Encoding: chunked\r\n\r\n");
+        writer.write_all(head.as_bytes()).await?;
+        Ok(Self { writer })
+    }
+
+    pub async fn write_chunk(&mut self, data: &[u8]) -> std::io::Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        self.writer.write_all(&encode_chunk(data)).await
+    }
+
+    pub async fn finish(self, trailers: &[(&str, &str)]) -> std::io::Result<()> {
+        self.writer.write_all(&encode_last_chunk(trailers)).await?;
+        self.writer.flush().await
+    }
+}
@@ -0,0 +1,108 @@
+//! HTTP/1.1 持久连接
+//!
+//! aex 的 HTTP server 自己负责连接上的请求循环；本 crate 自己监听的 HTTP 接口（控制接口，
+//! 见 [`crate::control`]）由这里的请求循环处理：同一连接上可以依次发送多个请求（包括流水线
+//! 发送），直到客户端要求 `close`、连接空闲超过 `[keep_alive] idle_timeout_secs`，或已处理
+//! `max_requests` 个请求。最后一个响应带 `Connection: close`，写完后关闭连接。
+//!
+//! 直接写出响应的 aex handler（静态文件等）同样需要遵守请求的 `Connection` 头：客户端要求
+//! `close` 时在响应中回写 `Connection: close` 并在写完后关闭写端，否则保持连接以便读取下一个
+//! 请求。响应必须带 Content-Length 或使用 chunked 编码，否则对端无法确定响应边界。
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+use super::aex_re_exports::GlobalContext;
+use crate::config::SharedConfig;
+
+/// 默认空闲超时（秒）
+pub const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 15;
+/// 默认单个连接最多处理的请求数
+pub const DEFAULT_MAX_REQUESTS: usize = 100;
+
+/// `[keep_alive]` 配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeepAliveConfig {
+    /// 连接上等待下一个请求的最长时间（秒，0 表示不限制）
+    pub idle_timeout_secs: u64,
+    /// 单个连接最多处理的请求数（0 表示不限制）
+    pub max_requests: usize,
+}
+
+impl Default for KeepAliveConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout_secs: DEFAULT_IDLE_TIMEOUT_SECS,
+            max_requests: DEFAULT_MAX_REQUESTS,
+        }
+    }
+}
+
+impl KeepAliveConfig {
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        (self.idle_timeout_secs > 0).then(|| Duration::from_secs(self.idle_timeout_secs))
+    }
+
+    /// 已处理 `served` 个请求、最近一个请求为 `request` 时，连接是否继续保持
+    pub fn keep_open(&self, served: usize, request: &RequestHead) -> bool {
+        !request.wants_close() && (self.max_requests == 0 || served < self.max_requests)
+    }
+}
+
+/// 当前配置中的持久连接策略
+pub async fn policy(gctx: &GlobalContext) -> KeepAliveConfig {
+    match gctx.get::<SharedConfig>().await {
+        Some(config) => config.read().await.keep_alive.clone(),
+        None => KeepAliveConfig::default(),
+    }
+}
+
+/// 请求行中的协议版本与 `Connection` 头
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequestHead {
+    pub version: String,
+    pub connection: Option<String>,
+}
+
+impl RequestHead {
+    /// HTTP/1.1 默认保持连接；HTTP/1.0 只有显式 `keep-alive` 时保持
+    pub fn wants_close(&self) -> bool {
+        let connection = self.connection.as_deref();
+        if self.version.eq_ignore_ascii_case("HTTP/1.0") {
+            return !has_token(connection, "keep-alive");
+        }
+        wants_close(connection)
+    }
+}
+
+fn has_token(connection: Option<&str>, token: &str) -> bool {
+    connection
+        .map(|v| v.split(',').any(|t| t.trim().eq_ignore_ascii_case(token)))
+        .unwrap_or(false)
+}
+
+/// `Connection` 头的 token 列表中是否包含 `close`
+pub fn wants_close(connection: Option<&str>) -> bool {
+    has_token(connection, "close")
+}
+
+/// 该状态码的响应是否不能带消息体（因此也不写 `Content-Length: 0`）
+pub fn is_bodyless_status(status: &str) -> bool {
+    let code = status.split(' ').next().unwrap_or("");
+    code.starts_with('1') || code == "204" || code == "304"
+}
+
+/// 等待连接上的下一个请求：有数据可读时返回 true；对端关闭、读取出错或空闲超时时返回 false
+pub async fn wait_for_request<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    idle_timeout: Option<Duration>,
+) -> bool {
+    let ready = async { matches!(reader.fill_buf().await, Ok(buf) if !buf.is_empty()) };
+    match idle_timeout {
+        Some(limit) => tokio::time::timeout(limit, ready).await.unwrap_or(false),
+        None => ready.await,
+    }
+}
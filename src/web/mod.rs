@@ -1,161 +1,390 @@
 pub mod aex_re_exports;
 pub mod api;
+pub mod auth;
+pub mod body;
+pub mod chunked;
+pub mod keep_alive;
+pub mod multipart;
+pub mod params;
+pub mod peer_proxy;
+pub mod routes;
+pub mod static_files;
 pub mod templates;
 pub mod types;
 
 use std::sync::Arc;
 
 use futures::FutureExt;
+use futures::future::BoxFuture;
 use sea_orm::DatabaseConnection;
 
 use crate::node::WebHandler;
 use crate::user_store::UserStore;
 
-use self::aex_re_exports::{Context, GlobalContext, HttpMetadata, HttpMethod};
+use self::aex_re_exports::{Context, GlobalContext, HttpMetadata};
+use self::auth::Access;
+use self::routes::{Executor, Found, Routes, method_not_allowed};
 use self::types::{MinterData, TransferFn};
 
-/// Build the web handler closure.
-///
-/// The caller (root) must store a `MinterData` snapshot and a `TransferFn` callback
-/// in `gctx` via `gctx.set(...).await` before starting the server.
-pub fn build_handler(
-    node_name: String,
-    storage_dir: String,
-    node_address: String,
-    p2p_port: u16,
+/// 各路由共用的状态
+struct WebState {
+    name: String,
+    dir: String,
+    addr: String,
+    port: u16,
     db: DatabaseConnection,
     gctx: Arc<GlobalContext>,
     user_store: Arc<UserStore>,
-) -> WebHandler {
-    let node_name = Arc::new(node_name);
-    let storage_dir = Arc::new(storage_dir);
-    let node_address = Arc::new(node_address);
-    let db = Arc::new(db);
+}
 
-    Arc::new(move |ctx: &mut Context| {
-        let name = node_name.clone();
-        let dir = storage_dir.clone();
-        let addr = node_address.clone();
-        let port = p2p_port;
-        let db = db.clone();
-        let gctx = gctx.clone();
-        let user_store = user_store.clone();
-        async move {
-            let is_post = ctx
-                .local
-                .get_ref::<HttpMetadata>()
-                .map(|m| m.method == HttpMethod::POST)
-                .unwrap_or(false);
-            let meta_path = ctx
-                .local
-                .get_ref::<HttpMetadata>()
-                .map(|m| m.path.clone())
-                .unwrap_or_default();
+/// 把带状态的 handler 包装成路由表中的 [`Executor`]，`access` 为该路由要求的访问级别（见 [`auth`]）
+fn route<F>(state: &Arc<WebState>, access: Access, f: F) -> Executor
+where
+    F: for<'a> Fn(&'a mut Context, Arc<WebState>) -> BoxFuture<'a, bool> + Send + Sync + 'static,
+{
+    let state = state.clone();
+    auth::require(
+        access,
+        Arc::new(move |ctx: &mut Context| f(ctx, state.clone())),
+    )
+}
 
-            if is_post && meta_path == "/api/transfer" {
-                let tf = match gctx.get::<TransferFn>().await {
-                    Some(f) => f,
-                    None => {
-                        ctx.send(r#"{"success":false,"error":"TransferFn not configured"}"#, None);
-                        return true;
-                    }
+/// 页面与 `/api/data` 依赖的 `MinterData`；未配置时回写错误并返回 None
+async fn minter_data(ctx: &mut Context, gctx: &GlobalContext) -> Option<MinterData> {
+    let md = gctx.get::<MinterData>().await;
+    if md.is_none() {
+        ctx.send(
+            r#"{"success":false,"error":"MinterData not configured"}"#,
+            None,
+        );
+    }
+    md
+}
+
+fn api_routes(state: &Arc<WebState>) -> Routes {
+    let mut api = Routes::new();
+    api.post(
+        "/transfer",
+        route(state, Access::Admin, |ctx, web| {
+            async move {
+                let Some(tf) = web.gctx.get::<TransferFn>().await else {
+                    ctx.send(
+                        r#"{"success":false,"error":"TransferFn not configured"}"#,
+                        None,
+                    );
+                    return true;
                 };
-                return api::handle_transfer(ctx, &*db, &addr, tf).await;
+                api::handle_transfer(ctx, &web.db, &web.addr, tf).await
             }
-            if !is_post && meta_path.starts_with("/api/address") {
-                return api::handle_address_api(ctx, &*db, &meta_path).await;
+            .boxed()
+        }),
+    )
+    .get(
+        "/address",
+        route(state, Access::Viewer, |ctx, web| {
+            async move {
+                let path = request_path(ctx);
+                api::handle_address_api(ctx, &web.db, &path).await
             }
-            if !is_post && meta_path == "/api/contacts" {
-                return api::handle_list_contacts(ctx, &*db, &addr, gctx.clone(), &user_store).await;
+            .boxed()
+        }),
+    )
+    .get(
+        "/contacts",
+        route(state, Access::Viewer, |ctx, web| {
+            async move {
+                api::handle_list_contacts(
+                    ctx,
+                    &web.db,
+                    &web.addr,
+                    web.gctx.clone(),
+                    &web.user_store,
+                )
+                .await
             }
-            if is_post && meta_path == "/api/contacts" {
-                return api::handle_add_contact(ctx, &*db).await;
+            .boxed()
+        }),
+    )
+    .post(
+        "/contacts",
+        route(state, Access::User, |ctx, web| {
+            async move { api::handle_add_contact(ctx, &web.db).await }.boxed()
+        }),
+    )
+    .delete(
+        "/contacts",
+        route(state, Access::User, |ctx, web| {
+            async move {
+                let path = request_path(ctx);
+                api::handle_delete_contact(ctx, &web.db, &path).await
             }
-            if meta_path.starts_with("/api/contacts") && meta_path.contains("?address=") {
-                return api::handle_delete_contact(ctx, &*db, &meta_path).await;
+            .boxed()
+        }),
+    )
+    .get(
+        "/verify",
+        route(state, Access::Viewer, |ctx, web| {
+            async move {
+                let path = request_path(ctx);
+                api::handle_get_verification(ctx, web.gctx.clone(), &path).await
             }
-            if meta_path.starts_with("/api/chat_messages") && meta_path.contains("?contact=") {
-                return api::handle_get_chat_messages(ctx, &user_store, &meta_path).await;
+            .boxed()
+        }),
+    )
+    .post(
+        "/verify",
+        route(state, Access::User, |ctx, web| {
+            async move { api::handle_set_verification(ctx, web.gctx.clone()).await }.boxed()
+        }),
+    )
+    .get(
+        "/chat_messages",
+        route(state, Access::Viewer, |ctx, web| {
+            async move {
+                let path = request_path(ctx);
+                api::handle_get_chat_messages(ctx, &web.user_store, &path).await
             }
-            if !is_post && meta_path == "/api/conversations" {
-                return api::handle_get_conversations(ctx, &user_store).await;
+            .boxed()
+        }),
+    )
+    .get(
+        "/conversations",
+        route(state, Access::Viewer, |ctx, web| {
+            async move {
+                let path = request_path(ctx);
+                api::handle_get_conversations(ctx, &web.user_store, &path).await
             }
-            if !is_post
-                && meta_path.starts_with("/api/profile")
-                && meta_path.contains("?address=")
-            {
-                return api::handle_get_profile(ctx, &user_store, &meta_path).await;
+            .boxed()
+        }),
+    )
+    .get(
+        "/profile",
+        route(state, Access::Viewer, |ctx, web| {
+            async move {
+                let path = request_path(ctx);
+                if path.contains("?address=") {
+                    api::handle_get_profile(ctx, &web.user_store, &path).await
+                } else {
+                    api::handle_get_self_profile(ctx, &web.user_store, &web.addr).await
+                }
             }
-            if !is_post && meta_path == "/api/profile" {
-                return api::handle_get_self_profile(ctx, &user_store, &addr).await;
+            .boxed()
+        }),
+    )
+    .post(
+        "/profile",
+        route(state, Access::User, |ctx, web| {
+            async move {
+                let path = request_path(ctx);
+                api::handle_save_profile(ctx, &web.db, &web.user_store, &web.addr, &path).await
             }
-            if is_post && meta_path == "/api/profile" {
-                return api::handle_save_profile(ctx, &*db, &user_store, &addr, &meta_path).await;
+            .boxed()
+        }),
+    )
+    .post(
+        "/profile/avatar",
+        route(state, Access::User, |ctx, web| {
+            async move {
+                let path = request_path(ctx);
+                api::handle_upload_avatar(ctx, &web.user_store, &web.addr, &path).await
             }
-            if is_post && meta_path == "/api/profile/avatar" {
-                return api::handle_upload_avatar(ctx, &user_store, &addr, &meta_path).await;
+            .boxed()
+        }),
+    )
+    .post(
+        "/upload",
+        route(state, Access::User, |ctx, web| {
+            async move {
+                let path = request_path(ctx);
+                api::handle_upload(ctx, web.gctx.clone(), &path).await
             }
-            if is_post && meta_path == "/api/send_chat" {
-                return api::handle_send_chat(ctx, gctx.clone(), &addr, user_store.clone()).await;
+            .boxed()
+        }),
+    )
+    .post(
+        "/admin",
+        route(state, Access::Public, |ctx, web| {
+            async move { api::handle_admin(ctx, web.gctx.clone()).await }.boxed()
+        }),
+    )
+    .post(
+        "/send_chat",
+        route(state, Access::User, |ctx, web| {
+            async move {
+                api::handle_send_chat(ctx, web.gctx.clone(), &web.addr, web.user_store.clone())
+                    .await
             }
-            if !is_post && meta_path == "/api/data" {
-                let md = match gctx.get::<MinterData>().await {
-                    Some(d) => d,
-                    None => {
-                        ctx.send(
-                            r#"{"success":false,"error":"MinterData not configured"}"#,
-                            None,
-                        );
-                        return true;
-                    }
+            .boxed()
+        }),
+    )
+    .get(
+        "/data",
+        route(state, Access::Viewer, |ctx, web| {
+            async move {
+                let Some(md) = minter_data(ctx, &web.gctx).await else {
+                    return true;
                 };
-                return api::handle_data_api(ctx, &*db, gctx.clone(), gctx.clone(), &md, &addr, port)
-                    .await;
+                let gctx = web.gctx.clone();
+                api::handle_data_api(ctx, &web.db, gctx.clone(), gctx, &md, &web.addr, web.port)
+                    .await
             }
-            if meta_path == "/wallet" {
-                let md = match gctx.get::<MinterData>().await {
-                    Some(d) => d,
-                    None => {
-                        ctx.send(r#"{"success":false,"error":"MinterData not configured"}"#, None);
+            .boxed()
+        }),
+    );
+    api
+}
+
+fn page_routes(state: &Arc<WebState>) -> Routes {
+    let mut pages = Routes::new();
+    pages
+        .get(
+            "/wallet",
+            route(state, Access::Viewer, |ctx, web| {
+                async move {
+                    let Some(md) = minter_data(ctx, &web.gctx).await else {
                         return true;
-                    }
-                };
-                return api::handle_wallet_page(ctx, port, &name, &addr, gctx.clone(), &md, &*db, gctx.clone()).await;
-            }
-            if meta_path == "/chat" {
-                let md = match gctx.get::<MinterData>().await {
-                    Some(d) => d,
-                    None => {
-                        ctx.send(r#"{"success":false,"error":"MinterData not configured"}"#, None);
+                    };
+                    let gctx = web.gctx.clone();
+                    api::handle_wallet_page(
+                        ctx,
+                        web.port,
+                        &web.name,
+                        &web.addr,
+                        gctx.clone(),
+                        &md,
+                        &web.db,
+                        gctx,
+                    )
+                    .await
+                }
+                .boxed()
+            }),
+        )
+        .get(
+            "/chat",
+            route(state, Access::Viewer, |ctx, web| {
+                async move {
+                    let Some(md) = minter_data(ctx, &web.gctx).await else {
                         return true;
-                    }
-                };
-                return api::handle_chat_page(ctx, port, &name, &addr, gctx.clone(), &md, &*db, gctx.clone()).await;
-            }
-            if meta_path == "/network" {
-                let md = match gctx.get::<MinterData>().await {
-                    Some(d) => d,
-                    None => {
-                        ctx.send(r#"{"success":false,"error":"MinterData not configured"}"#, None);
+                    };
+                    let gctx = web.gctx.clone();
+                    api::handle_chat_page(
+                        ctx,
+                        web.port,
+                        &web.name,
+                        &web.addr,
+                        gctx.clone(),
+                        &md,
+                        &web.db,
+                        gctx,
+                    )
+                    .await
+                }
+                .boxed()
+            }),
+        )
+        .get(
+            "/network",
+            route(state, Access::Viewer, |ctx, web| {
+                async move {
+                    let Some(md) = minter_data(ctx, &web.gctx).await else {
                         return true;
-                    }
-                };
-                return api::handle_network_page(
-                    ctx, port, &name, &dir, &*db, gctx.clone(), gctx.clone(), &md, &addr,
-                )
-                .await;
-            }
-            let md = match gctx.get::<MinterData>().await {
-                Some(d) => d,
-                None => {
-                    ctx.send(
-                        r#"{"success":false,"error":"MinterData not configured"}"#,
-                        None,
-                    );
-                    return true;
+                    };
+                    let gctx = web.gctx.clone();
+                    api::handle_network_page(
+                        ctx,
+                        web.port,
+                        &web.name,
+                        &web.dir,
+                        &web.db,
+                        gctx.clone(),
+                        gctx,
+                        &md,
+                        &web.addr,
+                    )
+                    .await
+                }
+                .boxed()
+            }),
+        );
+    pages
+}
+
+/// 未匹配任何路由的请求返回首页
+fn index_page(state: &Arc<WebState>) -> Executor {
+    route(state, Access::Viewer, |ctx, web| {
+        async move {
+            let Some(md) = minter_data(ctx, &web.gctx).await else {
+                return true;
+            };
+            let gctx = web.gctx.clone();
+            api::handle_index_page(
+                ctx,
+                web.port,
+                &web.name,
+                &web.dir,
+                &web.addr,
+                gctx.clone(),
+                &md,
+                &web.db,
+                gctx,
+            )
+            .await
+        }
+        .boxed()
+    })
+}
+
+fn request_path(ctx: &Context) -> String {
+    ctx.local
+        .get_ref::<HttpMetadata>()
+        .map(|m| m.path.clone())
+        .unwrap_or_default()
+}
+
+/// Build the web handler closure.
+///
+/// The caller (root) must store a `MinterData` snapshot and a `TransferFn` callback
+/// in `gctx` via `gctx.set(...).await` before starting the server.
+pub fn build_handler(
+    node_name: String,
+    storage_dir: String,
+    node_address: String,
+    p2p_port: u16,
+    db: DatabaseConnection,
+    gctx: Arc<GlobalContext>,
+    user_store: Arc<UserStore>,
+) -> WebHandler {
+    let state = Arc::new(WebState {
+        name: node_name,
+        dir: storage_dir,
+        addr: node_address,
+        port: p2p_port,
+        db,
+        gctx,
+        user_store,
+    });
+    let mut routes = page_routes(&state);
+    routes.mount("/api", api_routes(&state));
+    let routes = Arc::new(routes);
+    let fallback = index_page(&state);
+
+    Arc::new(move |ctx: &mut Context| {
+        let routes = routes.clone();
+        let fallback = fallback.clone();
+        async move {
+            let (method, path) = match ctx.local.get_ref::<HttpMetadata>() {
+                Some(m) => (format!("{:?}", m.method), m.path.clone()),
+                None => (String::new(), String::new()),
+            };
+            let handler = match routes.find(&method, &path) {
+                Found::Handler(handler) => handler.clone(),
+                Found::MethodNotAllowed(allow) => {
+                    return method_not_allowed(ctx, &allow).await;
                 }
+                Found::NotFound => fallback,
             };
-            api::handle_index_page(ctx, port, &name, &dir, &addr, gctx.clone(), &md, &*db, gctx.clone()).await
+            handler(ctx).await
         }
         .boxed()
     })
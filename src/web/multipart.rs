@@ -0,0 +1,424 @@
+//! multipart/form-data 解析
+//!
+//! [`MultipartReader`] 直接从连接上流式读取，每次返回一个字段或文件：普通字段保存在内存中，
+//! 文件超过 `spool_threshold` 后写入临时文件，避免大文件整体驻留内存。
+//! 每个部分与部分总数都有上限，超限时立即返回错误，不再继续读取。
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+
+use crate::protocols::commands::binary::BINARY_MAX_SIZE;
+
+/// 部分头的最大长度
+pub const MAX_PART_HEADER_BYTES: usize = 8 * 1024;
+const READ_BUF_BYTES: usize = 16 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MultipartLimits {
+    /// 普通字段最大字节数
+    pub max_field_bytes: usize,
+    /// 单个文件最大字节数
+    pub max_file_bytes: u64,
+    /// 最多允许的部分数
+    pub max_parts: usize,
+    /// 文件超过该大小后写入临时文件
+    pub spool_threshold: usize,
+}
+
+impl Default for MultipartLimits {
+    fn default() -> Self {
+        Self {
+            max_field_bytes: 64 * 1024,
+            max_file_bytes: BINARY_MAX_SIZE,
+            max_parts: 32,
+            spool_threshold: 256 * 1024,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum MultipartError {
+    /// 字段或文件超过上限
+    TooLarge {
+        name: String,
+        limit: u64,
+    },
+    TooManyParts {
+        limit: usize,
+    },
+    Malformed(&'static str),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for MultipartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MultipartError::TooLarge { name, limit } => {
+                write!(f, "part '{}' exceeds {} bytes", name, limit)
+            }
+            MultipartError::TooManyParts { limit } => write!(f, "more than {} parts", limit),
+            MultipartError::Malformed(reason) => write!(f, "malformed multipart body: {}", reason),
+            MultipartError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for MultipartError {}
+
+impl From<std::io::Error> for MultipartError {
+    fn from(e: std::io::Error) -> Self {
+        MultipartError::Io(e)
+    }
+}
+
+/// 写入磁盘的上传文件，drop 时删除
+#[derive(Debug)]
+pub struct SpooledFile {
+    path: PathBuf,
+}
+
+static SPOOL_SEQ: AtomicU64 = AtomicU64::new(0);
+
+impl SpooledFile {
+    fn new() -> Self {
+        let name = format!(
+            "zz-upload-{}-{}",
+            std::process::id(),
+            SPOOL_SEQ.fetch_add(1, Ordering::Relaxed)
+        );
+        Self {
+            path: std::env::temp_dir().join(name),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for SpooledFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[derive(Debug)]
+pub enum FileData {
+    Memory(Vec<u8>),
+    Spooled { file: SpooledFile, len: u64 },
+}
+
+impl FileData {
+    pub fn len(&self) -> u64 {
+        match self {
+            FileData::Memory(v) => v.len() as u64,
+            FileData::Spooled { len, .. } => *len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 读出全部内容
+    pub async fn into_bytes(self) -> std::io::Result<Vec<u8>> {
+        match self {
+            FileData::Memory(v) => Ok(v),
+            FileData::Spooled { file, .. } => tokio::fs::read(file.path()).await,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Part {
+    Field {
+        name: String,
+        value: String,
+    },
+    File {
+        name: String,
+        filename: String,
+        content_type: Option<String>,
+        data: FileData,
+    },
+}
+
+/// 从 `Content-Type` 中取出 boundary
+pub fn boundary(content_type: &str) -> Option<String> {
+    let mut params = content_type.split(';');
+    let mime = params.next()?.trim();
+    if !mime.eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    params.find_map(|p| {
+        let (k, v) = p.split_once('=')?;
+        if !k.trim().eq_ignore_ascii_case("boundary") {
+            return None;
+        }
+        let v = v.trim().trim_matches('"');
+        (!v.is_empty() && v.len() <= 70).then(|| v.to_string())
+    })
+}
+
+/// 解析 `Content-Disposition: form-data; name="x"; filename="y"`
+fn parse_disposition(value: &str) -> Option<(String, Option<String>)> {
+    let mut params = value.split(';');
+    if !params.next()?.trim().eq_ignore_ascii_case("form-data") {
+        return None;
+    }
+    let mut name = None;
+    let mut filename = None;
+    for p in params {
+        let Some((k, v)) = p.split_once('=') else {
+            continue;
+        };
+        let v = v.trim().trim_matches('"').to_string();
+        match k.trim().to_ascii_lowercase().as_str() {
+            "name" => name = Some(v),
+            "filename" => filename = Some(v),
+            _ => {}
+        }
+    }
+    Some((name?, filename))
+}
+
+/// 只保留文件名本身，去掉客户端可能带上的目录
+fn sanitize_filename(filename: &str) -> String {
+    filename
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+enum Sink {
+    Field(Vec<u8>),
+    File {
+        data: FileData,
+        spool: Option<tokio::fs::File>,
+    },
+}
+
+pub struct MultipartReader<R> {
+    reader: R,
+    /// `\r\n--boundary`
+    delimiter: Vec<u8>,
+    buf: Vec<u8>,
+    limits: MultipartLimits,
+    parts: usize,
+    started: bool,
+    finished: bool,
+}
+
+impl<R: AsyncRead + Unpin> MultipartReader<R> {
+    pub fn new(reader: R, boundary: &str, limits: MultipartLimits) -> Self {
+        Self {
+            reader,
+            delimiter: format!("\r\n--{}", boundary).into_bytes(),
+            buf: Vec::new(),
+            limits,
+            parts: 0,
+            started: false,
+            finished: false,
+        }
+    }
+
+    /// 再读一批数据；返回 false 表示连接已读完
+    async fn fill(&mut self) -> Result<bool, MultipartError> {
+        let mut chunk = [0u8; READ_BUF_BYTES];
+        let n = self.reader.read(&mut chunk).await?;
+        self.buf.extend_from_slice(&chunk[..n]);
+        Ok(n > 0)
+    }
+
+    /// 读到第一个分隔符，跳过前导内容
+    async fn skip_preamble(&mut self) -> Result<(), MultipartError> {
+        // 第一个分隔符前面可以没有 CRLF
+        let first = &self.delimiter[2..];
+        loop {
+            if let Some(pos) = find(&self.buf, first) {
+                self.buf.drain(..pos + first.len());
+                return Ok(());
+            }
+            let keep = self.buf.len().saturating_sub(first.len());
+            self.buf.drain(..keep);
+            if !self.fill().await? {
+                return Err(MultipartError::Malformed("missing boundary"));
+            }
+        }
+    }
+
+    /// 分隔符之后要么是 `--`（结束），要么是 CRLF（下一个部分）
+    async fn after_delimiter(&mut self) -> Result<bool, MultipartError> {
+        while self.buf.len() < 2 {
+            if !self.fill().await? {
+                return Err(MultipartError::Malformed("truncated boundary"));
+            }
+        }
+        match &self.buf[..2] {
+            b"--" => {
+                self.finished = true;
+                Ok(false)
+            }
+            b"\r\n" => {
+                self.buf.drain(..2);
+                Ok(true)
+            }
+            _ => Err(MultipartError::Malformed("invalid boundary")),
+        }
+    }
+
+    async fn read_headers(&mut self) -> Result<Vec<(String, String)>, MultipartError> {
+        let end = loop {
+            if let Some(pos) = find(&self.buf, b"\r\n\r\n") {
+                break pos;
+            }
+            if self.buf.len() > MAX_PART_HEADER_BYTES {
+                return Err(MultipartError::Malformed("part headers too long"));
+            }
+            if !self.fill().await? {
+                return Err(MultipartError::Malformed("truncated part headers"));
+            }
+        };
+        let raw: Vec<u8> = self.buf.drain(..end + 4).collect();
+        let text = std::str::from_utf8(&raw[..end])
+            .map_err(|_| MultipartError::Malformed("non-utf8 part headers"))?;
+        Ok(text
+            .split("\r\n")
+            .filter_map(|line| line.split_once(':'))
+            .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+            .collect())
+    }
+
+    async fn write_sink(
+        &mut self,
+        sink: &mut Sink,
+        name: &str,
+        bytes: &[u8],
+    ) -> Result<(), MultipartError> {
+        match sink {
+            Sink::Field(value) => {
+                if value.len() + bytes.len() > self.limits.max_field_bytes {
+                    return Err(MultipartError::TooLarge {
+                        name: name.to_string(),
+                        limit: self.limits.max_field_bytes as u64,
+                    });
+                }
+                value.extend_from_slice(bytes);
+            }
+            Sink::File { data, spool } => {
+                if data.len() + bytes.len() as u64 > self.limits.max_file_bytes {
+                    return Err(MultipartError::TooLarge {
+                        name: name.to_string(),
+                        limit: self.limits.max_file_bytes,
+                    });
+                }
+                if let FileData::Memory(mem) = data
+                    && mem.len() + bytes.len() > self.limits.spool_threshold
+                {
+                    let file = SpooledFile::new();
+                    let mut out = tokio::fs::File::create(file.path()).await?;
+                    out.write_all(mem).await?;
+                    let len = mem.len() as u64;
+                    *data = FileData::Spooled { file, len };
+                    *spool = Some(out);
+                }
+                match data {
+                    FileData::Memory(mem) => mem.extend_from_slice(bytes),
+                    FileData::Spooled { len, .. } => {
+                        if let Some(out) = spool {
+                            out.write_all(bytes).await?;
+                        }
+                        *len += bytes.len() as u64;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 读取下一个字段或文件；所有部分读完后返回 `None`
+    pub async fn next_part(&mut self) -> Result<Option<Part>, MultipartError> {
+        if self.finished {
+            return Ok(None);
+        }
+        if !self.started {
+            self.started = true;
+            self.skip_preamble().await?;
+        }
+        if !self.after_delimiter().await? {
+            return Ok(None);
+        }
+        if self.parts >= self.limits.max_parts {
+            return Err(MultipartError::TooManyParts {
+                limit: self.limits.max_parts,
+            });
+        }
+        self.parts += 1;
+
+        let headers = self.read_headers().await?;
+        let (name, filename) = headers
+            .iter()
+            .find(|(k, _)| k == "content-disposition")
+            .and_then(|(_, v)| parse_disposition(v))
+            .ok_or(MultipartError::Malformed("missing content-disposition"))?;
+        let content_type = headers
+            .iter()
+            .find(|(k, _)| k == "content-type")
+            .map(|(_, v)| v.clone());
+
+        let mut sink = match filename {
+            Some(_) => Sink::File {
+                data: FileData::Memory(Vec::new()),
+                spool: None,
+            },
+            None => Sink::Field(Vec::new()),
+        };
+
+        // 逐段搬运正文，缓冲区末尾保留可能是分隔符前缀的字节
+        loop {
+            if let Some(pos) = find(&self.buf, &self.delimiter) {
+                let body: Vec<u8> = self.buf.drain(..pos + self.delimiter.len()).collect();
+                self.write_sink(&mut sink, &name, &body[..pos]).await?;
+                break;
+            }
+            let keep = self.delimiter.len() - 1;
+            if self.buf.len() > keep {
+                let body: Vec<u8> = self.buf.drain(..self.buf.len() - keep).collect();
+                self.write_sink(&mut sink, &name, &body).await?;
+            }
+            if !self.fill().await? {
+                return Err(MultipartError::Malformed("truncated part body"));
+            }
+        }
+
+        Ok(Some(match sink {
+            Sink::Field(value) => Part::Field {
+                name,
+                value: String::from_utf8(value)
+                    .map_err(|_| MultipartError::Malformed("non-utf8 field value"))?,
+            },
+            Sink::File { data, spool } => {
+                if let Some(mut out) = spool {
+                    out.flush().await?;
+                }
+                Part::File {
+                    name,
+                    filename: sanitize_filename(&filename.unwrap_or_default()),
+                    content_type,
+                    data,
+                }
+            }
+        }))
+    }
+}
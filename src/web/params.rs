@@ -0,0 +1,191 @@
+//! 查询参数与列表分页
+//!
+//! [`Params`] 解析 URL 中 `?` 之后的查询串（百分号解码，`+` 视为空格）。列表接口在其上用
+//! [`ListQuery`] 读取统一的分页与过滤参数：
+//!
+//! | 参数 | 说明 |
+//! |------|------|
+//! | `limit`  | 最多返回的条数，不超过 [`MAX_PAGE_LIMIT`]；省略时返回剩余全部 |
+//! | `offset` | 跳过前面的条数，默认 0 |
+//! | `since`  | 只保留时间戳（Unix 秒）不早于该值的条目 |
+//! | `filter` | 只保留文本字段包含该字符串的条目（不区分大小写） |
+//!
+//! 各接口先按固定顺序排好再过滤、分页，响应带过滤后的总数 `total`，翻页时结果稳定。
+
+use std::{collections::BTreeMap, str::FromStr};
+
+use serde_json::{Value, json};
+
+/// 单页最多的条数
+pub const MAX_PAGE_LIMIT: usize = 1000;
+
+/// 解析后的查询参数；同名参数以最后一个为准
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Params(BTreeMap<String, String>);
+
+impl Params {
+    /// 从请求路径（或不带 `?` 的查询串）解析
+    pub fn parse(path: &str) -> Self {
+        let query = match path.split_once('?') {
+            Some((_, query)) => query,
+            None if path.starts_with('/') => "",
+            None => path,
+        };
+        let pairs = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| match pair.split_once('=') {
+                Some((key, value)) => (decode(key), decode(value)),
+                None => (decode(pair), String::new()),
+            })
+            .collect();
+        Self(pairs)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(|v| v.as_str())
+    }
+
+    /// 解析为 `T`；参数不存在或为空时返回 None，格式错误时返回说明
+    pub fn parsed<T: FromStr>(&self, key: &str) -> Result<Option<T>, String> {
+        match self.get(key) {
+            None | Some("") => Ok(None),
+            Some(raw) => raw
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("Invalid '{}': {}", key, raw)),
+        }
+    }
+}
+
+/// 去掉查询串后的路径
+pub fn path_only(path: &str) -> &str {
+    path.split_once('?').map_or(path, |(path, _)| path)
+}
+
+fn hex_val(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+fn decode(raw: &str) -> String {
+    let mut out = Vec::with_capacity(raw.len());
+    let bytes = raw.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => match (hex_val(bytes[i + 1]), hex_val(bytes[i + 2])) {
+                (Some(h), Some(l)) => {
+                    out.push(h << 4 | l);
+                    i += 3;
+                    continue;
+                }
+                _ => out.push(b'%'),
+            },
+            b'+' => out.push(b' '),
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8(out).unwrap_or_else(|_| raw.to_string())
+}
+
+/// 列表接口的分页与过滤参数
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListQuery {
+    pub limit: Option<usize>,
+    pub offset: usize,
+    pub since: Option<i64>,
+    /// 已转为小写
+    pub filter: Option<String>,
+}
+
+impl ListQuery {
+    pub fn from_params(params: &Params) -> Result<Self, String> {
+        let limit = params.parsed::<usize>("limit")?;
+        if let Some(limit) = limit {
+            if limit > MAX_PAGE_LIMIT {
+                return Err(format!("'limit' must be at most {}", MAX_PAGE_LIMIT));
+            }
+        }
+        Ok(Self {
+            limit,
+            offset: params.parsed("offset")?.unwrap_or(0),
+            since: params.parsed("since")?,
+            filter: params
+                .get("filter")
+                .filter(|f| !f.is_empty())
+                .map(str::to_lowercase),
+        })
+    }
+
+    /// 从请求路径解析
+    pub fn parse(path: &str) -> Result<Self, String> {
+        Self::from_params(&Params::parse(path))
+    }
+
+    /// 时间戳是否满足 `since`
+    pub fn is_since(&self, timestamp: i64) -> bool {
+        self.since.is_none_or(|since| timestamp >= since)
+    }
+
+    /// 任一字段是否包含 `filter`
+    pub fn is_match(&self, fields: &[&str]) -> bool {
+        match &self.filter {
+            None => true,
+            Some(filter) => fields.iter().any(|f| f.to_lowercase().contains(filter)),
+        }
+    }
+
+    /// 对已排好序的条目按 `keep` 过滤后分页
+    pub fn page<T>(
+        &self,
+        items: impl IntoIterator<Item = T>,
+        keep: impl Fn(&T) -> bool,
+    ) -> Page<T> {
+        let matched: Vec<T> = items.into_iter().filter(|item| keep(item)).collect();
+        let total = matched.len();
+        let items = matched
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect();
+        Page {
+            items,
+            total,
+            offset: self.offset,
+            limit: self.limit,
+        }
+    }
+}
+
+/// 一页结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// 过滤后、分页前的总数
+    pub total: usize,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl<T> Page<T> {
+    /// 后面是否还有条目
+    pub fn has_more(&self) -> bool {
+        self.offset.saturating_add(self.items.len()) < self.total
+    }
+
+    /// 响应中的分页字段
+    pub fn meta(&self) -> Value {
+        json!({
+            "total": self.total,
+            "offset": self.offset,
+            "limit": self.limit,
+            "has_more": self.has_more(),
+        })
+    }
+}
@@ -0,0 +1,134 @@
+//! 反向代理到其它节点的 Web 服务
+//!
+//! `router.peer_proxy("/peer")` 把 `/peer/<address>/<path>` 的请求经 HTTP 隧道转发给目标节点
+//! （地址或别名）暴露的服务，并把其响应原样写回。目标不可达时返回 502，超时返回 504。
+
+use std::sync::Arc;
+
+use futures::{FutureExt, future::BoxFuture};
+use tokio::io::AsyncWriteExt;
+
+use super::{
+    aex_re_exports::{Context, HeaderKey, HttpMetadata, Router},
+    api,
+    auth::{self, Access},
+    keep_alive,
+};
+use crate::protocols::commands::http_tunnel::{self, HttpResponseCommand};
+
+/// 默认挂载前缀
+pub const DEFAULT_PEER_PREFIX: &str = "/peer";
+
+/// 随请求转发的头部（其余头部只对本节点的连接有意义）
+const FORWARDED_HEADERS: &[(HeaderKey, &str)] = &[
+    (HeaderKey::ContentType, "Content-Type"),
+    (HeaderKey::Range, "Range"),
+    (HeaderKey::IfRange, "If-Range"),
+    (HeaderKey::IfNoneMatch, "If-None-Match"),
+    (HeaderKey::IfModifiedSince, "If-Modified-Since"),
+];
+
+type Executor = Arc<dyn for<'a> Fn(&'a mut Context) -> BoxFuture<'a, bool> + Send + Sync>;
+
+pub trait PeerProxy {
+    /// 把 `<prefix>/<address>/*` 转发到对应节点暴露的服务；启用 Web 认证时要求 [`Access::User`]
+    fn peer_proxy(&mut self, prefix: &str) -> &mut Self;
+}
+
+impl PeerProxy for Router {
+    fn peer_proxy(&mut self, prefix: &str) -> &mut Self {
+        let prefix = prefix.trim_end_matches('/').to_string();
+        let route = format!("{}/*", prefix);
+        let prefix = Arc::new(prefix);
+        let executor: Executor = Arc::new(move |ctx: &mut Context| {
+            let prefix = prefix.clone();
+            async move { serve(ctx, &prefix).await }.boxed()
+        });
+        self.all(&route, auth::require(Access::User, executor))
+            .register();
+        self
+    }
+}
+
+async fn serve(ctx: &mut Context, prefix: &str) -> bool {
+    let Some(meta) = ctx.local.get_ref::<HttpMetadata>() else {
+        return true;
+    };
+    let method = format!("{:?}", meta.method);
+    let close =
+        keep_alive::wants_close(meta.headers.get(&HeaderKey::Connection).map(|s| s.as_str()));
+    let headers: Vec<(String, String)> = FORWARDED_HEADERS
+        .iter()
+        .filter_map(|(key, name)| Some((name.to_string(), meta.headers.get(key)?.clone())))
+        .collect();
+    let Some((address, path)) = http_tunnel::split_peer_path(prefix, &meta.path) else {
+        return respond(ctx, 404, &[], b"Not Found", close).await;
+    };
+
+    let Some(body) = api::read_http_body(ctx).await else {
+        return false;
+    };
+    let gctx = ctx.global.clone();
+    match http_tunnel::request(gctx, &address, &method, &path, headers, body).await {
+        Ok(Some(HttpResponseCommand {
+            status,
+            headers,
+            body,
+            ..
+        })) => respond(ctx, status, &headers, &body, close).await,
+        Ok(None) => {
+            let message = format!("No response from {}", address);
+            respond(ctx, 504, &[], message.as_bytes(), close).await
+        }
+        Err(e) => {
+            tracing::warn!("Tunnel request to {} failed: {}", address, e);
+            respond(ctx, 502, &[], e.to_string().as_bytes(), close).await
+        }
+    }
+}
+
+/// 写出完整响应；返回 false 表示请求已处理完毕
+async fn respond(
+    ctx: &mut Context,
+    status: u16,
+    headers: &[(String, String)],
+    body: &[u8],
+    close: bool,
+) -> bool {
+    let reason = reqwest::StatusCode::from_u16(status)
+        .ok()
+        .and_then(|s| s.canonical_reason())
+        .unwrap_or("");
+    let status_line = format!("{} {}", status, reason);
+    let mut head = format!("HTTP/1.1 {}\r\n", status_line);
+    for (k, v) in headers {
+        head.push_str(&format!("{}: {}\r\n", k, v));
+    }
+    if !keep_alive::is_bodyless_status(&status_line) {
+        head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    if close {
+        head.push_str("Connection: close\r\n");
+    }
+    head.push_str("\r\n");
+
+    let Some(writer) = ctx.writer.as_deref_mut() else {
+        return false;
+    };
+    let result = async {
+        writer.write_all(head.as_bytes()).await?;
+        if !keep_alive::is_bodyless_status(&status_line) {
+            writer.write_all(body).await?;
+        }
+        writer.flush().await
+    }
+    .await;
+    if let Err(e) = result {
+        tracing::debug!("Tunnel response aborted: {}", e);
+        return false;
+    }
+    if close {
+        let _ = writer.shutdown().await;
+    }
+    false
+}
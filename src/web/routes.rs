@@ -0,0 +1,258 @@
+//! Web 路由表
+//!
+//! aex 的 `Router` 把页面与 API 请求整体交给 [`super::build_handler`] 生成的 handler，
+//! handler 再用 [`Routes`] 按方法与路径分派。路由按路径段编译成一棵树，查找只与路径深度
+//! 有关，与路由数量无关；`routes.mount("/api", api)` 把另一张路由表挂到指定前缀下。
+//!
+//! 路径段可以是字面量，最后一段也可以是 `*`，匹配剩余的零个或多个段；字面量优先于 `*`。
+//! 路径存在但方法不匹配时返回 [`Found::MethodNotAllowed`]，由 [`method_not_allowed`]
+//! 回写 405 与 `Allow` 头。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use tokio::io::AsyncWriteExt;
+
+use super::aex_re_exports::{Context, HeaderKey, HttpMetadata};
+use super::{chunked, keep_alive};
+
+pub type Executor = Arc<dyn for<'a> Fn(&'a mut Context) -> BoxFuture<'a, bool> + Send + Sync>;
+
+/// 匹配任意方法
+pub const ANY_METHOD: &str = "*";
+
+struct Node<H> {
+    literals: HashMap<String, Node<H>>,
+    wildcard: Option<Box<Node<H>>>,
+    /// (方法, handler)；同一方法后注册的覆盖先注册的
+    endpoints: Vec<(String, H)>,
+}
+
+impl<H> Default for Node<H> {
+    fn default() -> Self {
+        Self {
+            literals: HashMap::new(),
+            wildcard: None,
+            endpoints: Vec::new(),
+        }
+    }
+}
+
+impl<H> Node<H> {
+    fn child(&mut self, segment: &str) -> &mut Node<H> {
+        if segment == "*" {
+            self.wildcard.get_or_insert_with(Box::default)
+        } else {
+            self.literals.entry(segment.to_string()).or_default()
+        }
+    }
+
+    fn insert(&mut self, method: &str, handler: H) {
+        let method = method.to_ascii_uppercase();
+        self.endpoints.retain(|(m, _)| *m != method);
+        self.endpoints.push((method, handler));
+    }
+
+    fn merge(&mut self, other: Node<H>) {
+        for (method, handler) in other.endpoints {
+            self.insert(&method, handler);
+        }
+        for (segment, node) in other.literals {
+            self.literals.entry(segment).or_default().merge(node);
+        }
+        if let Some(node) = other.wildcard {
+            self.wildcard.get_or_insert_with(Box::default).merge(*node);
+        }
+    }
+
+    fn endpoint(&self, method: &str) -> Found<'_, H> {
+        if self.endpoints.is_empty() {
+            return Found::NotFound;
+        }
+        let matched = self
+            .endpoints
+            .iter()
+            .find(|(m, _)| m == method)
+            .or_else(|| {
+                // HEAD 可以由 GET handler 处理
+                if method == "HEAD" {
+                    self.endpoints.iter().find(|(m, _)| m == "GET")
+                } else {
+                    None
+                }
+            })
+            .or_else(|| self.endpoints.iter().find(|(m, _)| m == ANY_METHOD));
+        match matched {
+            Some((_, handler)) => Found::Handler(handler),
+            None => Found::MethodNotAllowed(allowed_methods(&self.endpoints)),
+        }
+    }
+
+    fn find(&self, method: &str, segments: &[&str]) -> Found<'_, H> {
+        let mut fallback = Found::NotFound;
+        let exact = match segments.split_first() {
+            None => self.endpoint(method),
+            Some((first, rest)) => match self.literals.get(*first) {
+                Some(node) => node.find(method, rest),
+                None => Found::NotFound,
+            },
+        };
+        match exact {
+            Found::Handler(_) => return exact,
+            Found::MethodNotAllowed(_) => fallback = exact,
+            Found::NotFound => {}
+        }
+        if let Some(node) = &self.wildcard {
+            match node.endpoint(method) {
+                found @ Found::Handler(_) => return found,
+                found @ Found::MethodNotAllowed(_) => {
+                    if matches!(fallback, Found::NotFound) {
+                        fallback = found;
+                    }
+                }
+                Found::NotFound => {}
+            }
+        }
+        fallback
+    }
+}
+
+fn allowed_methods<H>(endpoints: &[(String, H)]) -> Vec<String> {
+    let mut allow: Vec<String> = endpoints.iter().map(|(m, _)| m.clone()).collect();
+    if allow.iter().any(|m| m == "GET") && !allow.iter().any(|m| m == "HEAD") {
+        allow.push("HEAD".to_string());
+    }
+    allow.sort();
+    allow
+}
+
+/// 把请求路径拆成段：去掉查询串与片段，忽略空段
+fn segments(path: &str) -> Vec<&str> {
+    path.split(['?', '#'])
+        .next()
+        .unwrap_or("")
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// 查找结果
+pub enum Found<'a, H> {
+    Handler(&'a H),
+    /// 路径存在但不接受该方法，附带允许的方法
+    MethodNotAllowed(Vec<String>),
+    NotFound,
+}
+
+/// 编译后的路由表
+pub struct Routes<H = Executor> {
+    root: Node<H>,
+}
+
+impl<H> Default for Routes<H> {
+    fn default() -> Self {
+        Self {
+            root: Node::default(),
+        }
+    }
+}
+
+impl<H> Routes<H> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册路由；`method` 为 [`ANY_METHOD`] 时匹配任意方法
+    pub fn route(&mut self, method: &str, path: &str, handler: H) -> &mut Self {
+        let mut node = &mut self.root;
+        for segment in segments(path) {
+            node = node.child(segment);
+        }
+        node.insert(method, handler);
+        self
+    }
+
+    pub fn get(&mut self, path: &str, handler: H) -> &mut Self {
+        self.route("GET", path, handler)
+    }
+
+    pub fn post(&mut self, path: &str, handler: H) -> &mut Self {
+        self.route("POST", path, handler)
+    }
+
+    pub fn delete(&mut self, path: &str, handler: H) -> &mut Self {
+        self.route("DELETE", path, handler)
+    }
+
+    pub fn all(&mut self, path: &str, handler: H) -> &mut Self {
+        self.route(ANY_METHOD, path, handler)
+    }
+
+    /// 把 `routes` 挂到 `prefix` 下；与已有路由重叠时同一方法以 `routes` 为准
+    pub fn mount(&mut self, prefix: &str, routes: Routes<H>) -> &mut Self {
+        let mut node = &mut self.root;
+        for segment in segments(prefix) {
+            node = node.child(segment);
+        }
+        node.merge(routes.root);
+        self
+    }
+
+    pub fn find(&self, method: &str, path: &str) -> Found<'_, H> {
+        self.root
+            .find(&method.to_ascii_uppercase(), &segments(path))
+    }
+}
+
+/// 不调用 handler 直接回写错误时是否应关闭连接：客户端要求关闭，或请求体没有被读取
+pub fn close_after_error(ctx: &Context) -> bool {
+    let (close, has_body) = match ctx.local.get_ref::<HttpMetadata>() {
+        Some(m) => (
+            keep_alive::wants_close(m.headers.get(&HeaderKey::Connection).map(|s| s.as_str())),
+            chunked::is_chunked(
+                m.headers
+                    .get(&HeaderKey::TransferEncoding)
+                    .map(|s| s.as_str()),
+            ) || m
+                .headers
+                .get(&HeaderKey::ContentLength)
+                .is_some_and(|v| v.trim() != "0"),
+        ),
+        None => (false, false),
+    };
+    close || has_body
+}
+
+/// 回写 405 与 `Allow` 头；请求带有请求体时关闭连接，因为请求体没有被读取
+pub async fn method_not_allowed(ctx: &mut Context, allow: &[String]) -> bool {
+    let close = close_after_error(ctx);
+    let body = serde_json::json!({"success": false, "error": "Method Not Allowed"}).to_string();
+    let mut head = format!(
+        "HTTP/1.1 405 Method Not Allowed\r\nAllow: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n",
+        allow.join(", "),
+        body.len()
+    );
+    if close {
+        head.push_str("Connection: close\r\n");
+    }
+    head.push_str("\r\n");
+
+    let Some(writer) = ctx.writer.as_deref_mut() else {
+        return false;
+    };
+    let result = async {
+        writer.write_all(head.as_bytes()).await?;
+        writer.write_all(body.as_bytes()).await?;
+        writer.flush().await
+    }
+    .await;
+    if let Err(e) = result {
+        tracing::debug!("405 response aborted: {}", e);
+        return false;
+    }
+    if close {
+        let _ = writer.shutdown().await;
+    }
+    false
+}
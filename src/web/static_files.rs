@@ -0,0 +1,321 @@
+//! 静态文件服务
+//!
+//! `router.static_dir("/web", path)` 把一个目录挂载到指定前缀下，用于随节点分发的 Web UI。
+//! 请求路径先解码再逐段校验，拒绝 `..`、绝对路径以及通过符号链接逃出根目录的文件；
+//! 支持 ETag / Last-Modified 条件请求、单区间 Range 请求与 HEAD。
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{DateTime, Utc};
+use futures::{FutureExt, future::BoxFuture};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+use super::{
+    aex_re_exports::{Context, HeaderKey, HttpMetadata, HttpMethod, MediaType, Router},
+    auth::{self, Access},
+    keep_alive,
+};
+
+/// `--web-root` 默认挂载前缀
+pub const DEFAULT_STATIC_PREFIX: &str = "/web";
+/// 请求目录时返回的文件
+pub const INDEX_FILE: &str = "index.html";
+
+/// 启动时保存在 GlobalContext 中的挂载点，`start_with_web` 据此注册路由
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaticMount {
+    pub prefix: String,
+    pub root: PathBuf,
+}
+
+impl StaticMount {
+    pub fn new(prefix: &str, root: impl Into<PathBuf>) -> Self {
+        Self {
+            prefix: prefix.trim_end_matches('/').to_string(),
+            root: root.into(),
+        }
+    }
+}
+
+type Executor = Arc<dyn for<'a> Fn(&'a mut Context) -> BoxFuture<'a, bool> + Send + Sync>;
+
+pub trait StaticDir {
+    /// 把 `root` 目录挂载到 `prefix` 下（GET / HEAD）；启用 Web 认证时要求 [`Access::Viewer`]
+    fn static_dir(&mut self, prefix: &str, root: impl Into<PathBuf>) -> &mut Self;
+}
+
+impl StaticDir for Router {
+    fn static_dir(&mut self, prefix: &str, root: impl Into<PathBuf>) -> &mut Self {
+        let mount = Arc::new(StaticMount::new(prefix, root));
+        let executor: Executor = Arc::new(move |ctx: &mut Context| {
+            let mount = mount.clone();
+            async move { serve(ctx, &mount).await }.boxed()
+        });
+        let executor = auth::require(Access::Viewer, executor);
+        let prefix = prefix.trim_end_matches('/');
+        self.all(&format!("{}/*", prefix), executor.clone())
+            .register();
+        self.all(prefix, executor).register();
+        self
+    }
+}
+
+pub(crate) fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3)?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+/// 把请求路径（去掉挂载前缀后的部分）解析为 `root` 下的实际文件
+///
+/// 目录解析为其中的 `index.html`；任何无法确认位于 `root` 之内的路径都返回 `None`。
+pub fn resolve(root: &Path, request_path: &str) -> Option<PathBuf> {
+    let path = request_path.split(['?', '#']).next().unwrap_or("");
+    let decoded = percent_decode(path)?;
+
+    let mut candidate = root.to_path_buf();
+    for segment in decoded.split('/') {
+        match segment {
+            "" | "." => continue,
+            ".." => return None,
+            s if s.contains(['\\', '\0', ':']) => return None,
+            s => candidate.push(s),
+        }
+    }
+
+    // 规范化后再比较一次，防止符号链接指向根目录之外
+    let root = root.canonicalize().ok()?;
+    let mut file = candidate.canonicalize().ok()?;
+    if !file.starts_with(&root) {
+        return None;
+    }
+    if file.is_dir() {
+        file = file.join(INDEX_FILE).canonicalize().ok()?;
+        if !file.starts_with(&root) {
+            return None;
+        }
+    }
+    file.is_file().then_some(file)
+}
+
+/// 由文件大小与修改时间生成的强 ETag
+pub fn etag(len: u64, modified: SystemTime) -> String {
+    let secs = modified
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format!("\"{:x}-{:x}\"", len, secs)
+}
+
+pub fn http_date(t: SystemTime) -> String {
+    DateTime::<Utc>::from(t)
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
+
+fn etag_matches(header: &str, etag: &str) -> bool {
+    header
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+}
+
+/// 条件请求判断：有 `If-None-Match` 时只比较 ETag，否则比较 `If-Modified-Since`
+pub fn is_not_modified(
+    if_none_match: Option<&str>,
+    if_modified_since: Option<&str>,
+    etag: &str,
+    modified: SystemTime,
+) -> bool {
+    if let Some(header) = if_none_match {
+        return etag_matches(header, etag);
+    }
+    let Some(since) = if_modified_since.and_then(|s| DateTime::parse_from_rfc2822(s).ok()) else {
+        return false;
+    };
+    let modified = DateTime::<Utc>::from(modified).timestamp();
+    modified <= since.timestamp()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// 返回完整文件
+    Full,
+    /// 闭区间 `[start, end]`
+    Partial { start: u64, end: u64 },
+    /// 区间超出文件长度（416）
+    Unsatisfiable,
+}
+
+/// 解析 `Range` 头；只支持单个 `bytes=` 区间，多区间与无法识别的格式按完整文件处理
+pub fn parse_range(header: Option<&str>, len: u64) -> ByteRange {
+    let Some(spec) = header.and_then(|h| h.trim().strip_prefix("bytes=")) else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", "") => return ByteRange::Full,
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(n) => (len.saturating_sub(n), len.saturating_sub(1)),
+            Err(_) => return ByteRange::Full,
+        },
+        (start, end) => {
+            let Ok(start) = start.parse::<u64>() else {
+                return ByteRange::Full;
+            };
+            let end = match end {
+                "" => len.saturating_sub(1),
+                e => match e.parse::<u64>() {
+                    Ok(e) if e >= start => e.min(len.saturating_sub(1)),
+                    _ => return ByteRange::Full,
+                },
+            };
+            (start, end)
+        }
+    };
+    if len == 0 || start >= len {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial { start, end }
+}
+
+fn header(ctx: &Context, key: HeaderKey) -> Option<String> {
+    ctx.local
+        .get_ref::<HttpMetadata>()
+        .and_then(|m| m.headers.get(&key).cloned())
+}
+
+async fn serve(ctx: &mut Context, mount: &StaticMount) -> bool {
+    let (method, path) = match ctx.local.get_ref::<HttpMetadata>() {
+        Some(m) => (m.method.clone(), m.path.clone()),
+        None => return true,
+    };
+    let is_head = method == HttpMethod::HEAD;
+    if method != HttpMethod::GET && !is_head {
+        let allow = [("Allow", "GET, HEAD".to_string())];
+        return respond(ctx, "405 Method Not Allowed", &allow, None).await;
+    }
+
+    let rel = path.strip_prefix(mount.prefix.as_str()).unwrap_or("");
+    let Some(file) = resolve(&mount.root, rel) else {
+        return respond(ctx, "404 Not Found", &[], None).await;
+    };
+    let meta = match tokio::fs::metadata(&file).await {
+        Ok(m) => m,
+        Err(_) => return respond(ctx, "404 Not Found", &[], None).await,
+    };
+    let len = meta.len();
+    let modified = meta.modified().unwrap_or(UNIX_EPOCH);
+    let tag = etag(len, modified);
+
+    let mut headers = vec![
+        ("ETag", tag.clone()),
+        ("Last-Modified", http_date(modified)),
+        ("Accept-Ranges", "bytes".to_string()),
+    ];
+
+    let if_none_match = header(ctx, HeaderKey::IfNoneMatch);
+    let if_modified_since = header(ctx, HeaderKey::IfModifiedSince);
+    if is_not_modified(
+        if_none_match.as_deref(),
+        if_modified_since.as_deref(),
+        &tag,
+        modified,
+    ) {
+        return respond(ctx, "304 Not Modified", &headers, None).await;
+    }
+
+    // If-Range 与当前 ETag 不一致时忽略 Range，返回完整文件
+    let range = match header(ctx, HeaderKey::IfRange) {
+        Some(v) if v.trim() != tag => ByteRange::Full,
+        _ => parse_range(header(ctx, HeaderKey::Range).as_deref(), len),
+    };
+    headers.push(("Content-Type", MediaType::guess(&file).to_string()));
+    let (status, start, count) = match range {
+        ByteRange::Full => ("200 OK", 0, len),
+        ByteRange::Partial { start, end } => {
+            headers.push(("Content-Range", format!("bytes {}-{}/{}", start, end, len)));
+            ("206 Partial Content", start, end - start + 1)
+        }
+        ByteRange::Unsatisfiable => {
+            headers.push(("Content-Range", format!("bytes */{}", len)));
+            return respond(ctx, "416 Range Not Satisfiable", &headers, None).await;
+        }
+    };
+    headers.push(("Content-Length", count.to_string()));
+
+    let body = if is_head {
+        None
+    } else {
+        Some((file.as_path(), start, count))
+    };
+    respond(ctx, status, &headers, body).await
+}
+
+/// 直接写出完整响应（遵守请求的 `Connection: close`）；返回 false 表示请求已处理完毕，不再交给后续 handler
+async fn respond(
+    ctx: &mut Context,
+    status: &str,
+    headers: &[(&str, String)],
+    body: Option<(&Path, u64, u64)>,
+) -> bool {
+    let close = keep_alive::wants_close(header(ctx, HeaderKey::Connection).as_deref());
+    let mut head = format!("HTTP/1.1 {}\r\n", status);
+    for (k, v) in headers {
+        head.push_str(&format!("{}: {}\r\n", k, v));
+    }
+    if !keep_alive::is_bodyless_status(status)
+        && !headers.iter().any(|(k, _)| *k == "Content-Length")
+    {
+        head.push_str("Content-Length: 0\r\n");
+    }
+    if close {
+        head.push_str("Connection: close\r\n");
+    }
+    head.push_str("\r\n");
+
+    let Some(writer) = ctx.writer.as_deref_mut() else {
+        return false;
+    };
+    if let Err(e) = writer.write_all(head.as_bytes()).await {
+        tracing::debug!("Static response aborted: {}", e);
+        return false;
+    }
+    if let Some((path, start, count)) = body {
+        let result = async {
+            let mut file = tokio::fs::File::open(path).await?;
+            file.seek(std::io::SeekFrom::Start(start)).await?;
+            tokio::io::copy(&mut file.take(count), writer).await?;
+            writer.flush().await
+        }
+        .await;
+        if let Err(e) = result {
+            tracing::debug!("Failed to send {}: {}", path.display(), e);
+        }
+    }
+    if close {
+        let _ = writer.shutdown().await;
+    }
+    false
+}
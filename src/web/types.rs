@@ -44,10 +44,11 @@ pub struct WitnessEntry {
 /// Callback for creating a transfer via Minter. Returns the transaction hash.
 pub type TransferFn = Arc<
     dyn Fn(
-        String,
-        String,
-        String,
-    ) -> std::pin::Pin<Box<dyn futures::Future<Output = anyhow::Result<String>> + Send>>
+            String,
+            String,
+            String,
+        )
+            -> std::pin::Pin<Box<dyn futures::Future<Output = anyhow::Result<String>> + Send>>
         + Send
         + Sync,
 >;
@@ -0,0 +1,349 @@
+//! HTTP webhook 事件推送
+//!
+//! 运维通过控制接口（`POST /webhooks`）登记 URL，节点把事件总线上的事件——收到消息
+//! （`message.received`）、对端上线/下线（`peer.connected` / `peer.disconnected`）等——以
+//! JSON POST 到这些 URL，外部服务无需保持 WebSocket 连接。每个 webhook 可以只订阅部分事件，
+//! 过滤规则与 `events` 命令相同（`peer` 匹配 `peer.connected`）。
+//!
+//! 每次投递带以下请求头：
+//!
+//! - `X-Zz-Event`：事件名
+//! - `X-Zz-Delivery`：投递 id，重试时不变
+//! - `X-Zz-Timestamp`：签名时间（毫秒）
+//! - `X-Zz-Signature`：`sha256=<hex>`，用登记时的密钥对 `<timestamp>.<body>` 做 HMAC-SHA256
+//!
+//! 网络错误、5xx、408 与 429 按指数退避重试，最多 [`WEBHOOK_MAX_ATTEMPTS`] 次；其它 4xx
+//! 视为接收方拒绝，不再重试。登记信息持久化在 `webhooks.json`。
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use aex::{connection::global::GlobalContext, time::SystemTime};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::Sha256;
+use tokio::sync::broadcast::error::RecvError;
+use zz_account::address::FreeWebMovementAddress;
+
+use crate::{
+    events::{EventBus, NodeEvent},
+    io_storage::{IOStorage, STORAGE_WEBHOOKS},
+    journal,
+};
+
+/// 单次投递的最多尝试次数
+pub const WEBHOOK_MAX_ATTEMPTS: u32 = 5;
+/// 第一次重试前的等待时间，之后每次翻倍
+pub const WEBHOOK_BACKOFF_BASE_MS: u64 = 1_000;
+/// 重试等待的上限
+pub const WEBHOOK_BACKOFF_MAX_MS: u64 = 60_000;
+/// 单个请求的超时
+pub const WEBHOOK_TIMEOUT_MS: u64 = 10_000;
+/// 最多登记的 webhook 数
+pub const WEBHOOK_MAX: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    /// HMAC 签名密钥
+    pub secret: String,
+    /// 订阅的事件名或类别，为空时接收全部事件
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+impl Webhook {
+    pub fn wants(&self, event: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|f| journal::name_matches(event, f))
+    }
+
+    /// 去掉密钥后的 JSON，用于列出
+    pub fn redacted(&self) -> Value {
+        json!({"id": self.id, "url": self.url, "events": self.events})
+    }
+}
+
+/// 持久化的 webhook 列表
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WebhookList {
+    pub hooks: Vec<Webhook>,
+}
+
+/// 保存在 GlobalContext 中的 webhook 列表
+pub type SharedWebhooks = Arc<RwLock<WebhookList>>;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// HMAC-SHA256，十六进制小写
+pub fn hmac_hex(secret: &str, data: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    hex(&mac.finalize().into_bytes())
+}
+
+/// `X-Zz-Signature` 的值
+pub fn sign(secret: &str, timestamp: u128, body: &str) -> String {
+    format!(
+        "sha256={}",
+        hmac_hex(secret, format!("{}.{}", timestamp, body).as_bytes())
+    )
+}
+
+pub fn new_secret() -> String {
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    hex(&secret)
+}
+
+/// 只接受 http / https URL
+pub fn validate_url(url: &str) -> anyhow::Result<()> {
+    let parsed = reqwest::Url::parse(url)?;
+    match parsed.scheme() {
+        "http" | "https" if parsed.host_str().is_some() => Ok(()),
+        _ => Err(anyhow::anyhow!(
+            "Webhook URL must be http(s)://host/...: {}",
+            url
+        )),
+    }
+}
+
+/// 第 `attempt` 次失败后的等待时间（从 1 开始）
+pub fn retry_delay(attempt: u32) -> Duration {
+    let factor = 1u64 << attempt.saturating_sub(1).min(16);
+    Duration::from_millis((WEBHOOK_BACKOFF_BASE_MS * factor).min(WEBHOOK_BACKOFF_MAX_MS))
+}
+
+/// 该响应码是否值得重试
+pub fn retryable(status: u16) -> bool {
+    status >= 500 || status == 408 || status == 429
+}
+
+/// 事件名与投递的 JSON 正文
+pub fn payload(event: &NodeEvent, node: &str, timestamp: u128) -> (&'static str, Value) {
+    let (name, data) = match event {
+        NodeEvent::Journal(event) => (
+            event.name(),
+            serde_json::to_value(event).unwrap_or(Value::Null),
+        ),
+        NodeEvent::Message(message) => (
+            "message.received",
+            json!({
+                "from": message.from,
+                "content": message.content,
+                "timestamp": message.timestamp,
+                "annotations": message.annotations,
+            }),
+        ),
+        NodeEvent::GroupMessage(message) => (
+            "group.message",
+            json!({
+                "group_id": message.group_id,
+                "group": message.group,
+                "from": message.from,
+                "content": message.content,
+                "timestamp": message.timestamp,
+                "annotations": message.annotations,
+            }),
+        ),
+        NodeEvent::TextStream(stream) => (
+            "message.text_stream",
+            json!({
+                "from": stream.from,
+                "stream_id": stream.stream_id,
+                "total_len": stream.total_len,
+                "metadata": stream.metadata.iter().cloned().collect::<BTreeMap<_, _>>(),
+            }),
+        ),
+    };
+    (
+        name,
+        json!({"event": name, "node": node, "timestamp": timestamp, "data": data}),
+    )
+}
+
+async fn shared(gctx: &Arc<GlobalContext>) -> SharedWebhooks {
+    match gctx.get::<SharedWebhooks>().await {
+        Some(hooks) => hooks,
+        None => {
+            let hooks = SharedWebhooks::default();
+            gctx.set(hooks.clone()).await;
+            hooks
+        }
+    }
+}
+
+fn snapshot(hooks: &SharedWebhooks) -> WebhookList {
+    match hooks.read() {
+        Ok(g) => g.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
+    }
+}
+
+async fn persist(gctx: &Arc<GlobalContext>, list: &WebhookList) {
+    match gctx.get::<IOStorage>().await {
+        Some(ios) => ios.save::<WebhookList>(list, STORAGE_WEBHOOKS).await,
+        None => tracing::error!("IOStorage not found in context, webhooks not persisted"),
+    }
+}
+
+pub async fn list(gctx: &Arc<GlobalContext>) -> Vec<Webhook> {
+    snapshot(&shared(gctx).await).hooks
+}
+
+/// 登记 webhook；未提供密钥时随机生成。返回的记录包含密钥，只在登记时给出
+pub async fn register(
+    gctx: &Arc<GlobalContext>,
+    url: &str,
+    secret: Option<String>,
+    events: Vec<String>,
+) -> anyhow::Result<Webhook> {
+    validate_url(url)?;
+    let hook = Webhook {
+        id: uuid::Uuid::new_v4().to_string(),
+        url: url.to_string(),
+        secret: secret.filter(|s| !s.is_empty()).unwrap_or_else(new_secret),
+        events,
+    };
+    let hooks = shared(gctx).await;
+    let current = {
+        let mut guard = match hooks.write() {
+            Ok(g) => g,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if guard.hooks.len() >= WEBHOOK_MAX {
+            return Err(anyhow::anyhow!("Too many webhooks (max {})", WEBHOOK_MAX));
+        }
+        guard.hooks.push(hook.clone());
+        guard.clone()
+    };
+    persist(gctx, &current).await;
+    tracing::info!("🪝 Webhook {} registered for {}", hook.id, hook.url);
+    Ok(hook)
+}
+
+/// 删除 webhook，返回被删除的记录
+pub async fn remove(gctx: &Arc<GlobalContext>, id: &str) -> Option<Webhook> {
+    let hooks = shared(gctx).await;
+    let (removed, current) = {
+        let mut guard = match hooks.write() {
+            Ok(g) => g,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let index = guard.hooks.iter().position(|h| h.id == id)?;
+        (guard.hooks.remove(index), guard.clone())
+    };
+    persist(gctx, &current).await;
+    Some(removed)
+}
+
+/// 投递一个事件，失败时按退避重试。返回成功时用掉的尝试次数
+pub async fn deliver(
+    client: &reqwest::Client,
+    hook: &Webhook,
+    event: &str,
+    body: &str,
+) -> anyhow::Result<u32> {
+    let delivery = uuid::Uuid::new_v4().to_string();
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let timestamp = SystemTime::timestamp();
+        let result = client
+            .post(&hook.url)
+            .header("Content-Type", "application/json")
+            .header("X-Zz-Event", event)
+            .header("X-Zz-Delivery", &delivery)
+            .header("X-Zz-Timestamp", timestamp.to_string())
+            .header("X-Zz-Signature", sign(&hook.secret, timestamp, body))
+            .body(body.to_string())
+            .send()
+            .await;
+        let error = match result {
+            Ok(resp) if resp.status().is_success() => return Ok(attempt),
+            Ok(resp) if !retryable(resp.status().as_u16()) => {
+                return Err(anyhow::anyhow!(
+                    "{} rejected with {}",
+                    hook.url,
+                    resp.status()
+                ));
+            }
+            Ok(resp) => anyhow::anyhow!("{} responded {}", hook.url, resp.status()),
+            Err(e) => anyhow::anyhow!("{}: {}", hook.url, e),
+        };
+        if attempt >= WEBHOOK_MAX_ATTEMPTS {
+            return Err(error.context(format!("gave up after {} attempts", attempt)));
+        }
+        let delay = retry_delay(attempt);
+        tracing::debug!(
+            "Webhook {} attempt {} failed: {}, retrying in {:?}",
+            hook.id,
+            attempt,
+            error,
+            delay
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// 订阅事件总线并把事件推送给已登记的 webhook
+pub async fn spawn(gctx: Arc<GlobalContext>) -> Option<tokio::task::JoinHandle<()>> {
+    let bus = gctx.get::<EventBus>().await?;
+    let mut events = bus.subscribe();
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_millis(WEBHOOK_TIMEOUT_MS))
+        .build()
+    {
+        Ok(c) => c,
+        Err(e) => {
+            tracing::error!("Failed to create webhook client: {:?}", e);
+            return None;
+        }
+    };
+    let node = gctx
+        .get::<FreeWebMovementAddress>()
+        .await
+        .map(|a| a.to_string())
+        .unwrap_or_default();
+    let hooks = shared(&gctx).await;
+
+    Some(tokio::spawn(async move {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(n)) => {
+                    tracing::warn!("Webhook dispatcher skipped {} events", n);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            let (name, body) = payload(&event, &node, SystemTime::timestamp());
+            let targets: Vec<Webhook> = snapshot(&hooks)
+                .hooks
+                .into_iter()
+                .filter(|h| h.wants(name))
+                .collect();
+            if targets.is_empty() {
+                continue;
+            }
+            let body = Arc::new(body.to_string());
+            for hook in targets {
+                let client = client.clone();
+                let body = body.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = deliver(&client, &hook, name, &body).await {
+                        tracing::warn!("Webhook {} delivery of {} failed: {:?}", hook.id, name, e);
+                    }
+                });
+            }
+        }
+    }))
+}
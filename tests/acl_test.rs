@@ -0,0 +1,111 @@
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use zz_p2p::protocols::acl::{AccessList, AclDecision, AclMode, AclTarget};
+
+    fn ip(s: &str) -> Option<IpAddr> {
+        Some(s.parse().unwrap())
+    }
+
+    #[test]
+    fn test_parse_targets() {
+        assert_eq!(
+            AclTarget::parse("10.1.2.3/8").unwrap(),
+            AclTarget::Network {
+                ip: "10.0.0.0".parse().unwrap(),
+                prefix: 8
+            }
+        );
+        assert_eq!(
+            AclTarget::parse("1.2.3.4:1090").unwrap(),
+            AclTarget::parse("1.2.3.4").unwrap()
+        );
+        assert_eq!(
+            AclTarget::parse("node-addr").unwrap(),
+            AclTarget::Address("node-addr".to_string())
+        );
+        assert!(AclTarget::parse("10.0.0.0/33").is_err());
+        assert!(AclTarget::parse("").is_err());
+        assert_eq!(
+            AclTarget::parse("10.0.0.0/8").unwrap().to_string(),
+            "10.0.0.0/8"
+        );
+        assert_eq!(AclTarget::parse("fd00::1").unwrap().to_string(), "fd00::1");
+    }
+
+    #[test]
+    fn test_cidr_matching() {
+        let net = AclTarget::parse("192.168.0.0/16").unwrap();
+        assert!(net.matches_ip(&"192.168.44.1".parse().unwrap()));
+        assert!(!net.matches_ip(&"192.169.0.1".parse().unwrap()));
+        assert!(!net.matches_ip(&"::1".parse().unwrap()));
+
+        let v6 = AclTarget::parse("fd00::/8").unwrap();
+        assert!(v6.matches_ip(&"fd12::1".parse().unwrap()));
+        assert!(!v6.matches_ip(&"fe80::1".parse().unwrap()));
+
+        let all = AclTarget::parse("0.0.0.0/0").unwrap();
+        assert!(all.matches_ip(&"8.8.8.8".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_blacklist_mode() {
+        let mut acl = AccessList::default();
+        assert!(acl.ban(AclTarget::parse("bad-node").unwrap()));
+        assert!(!acl.ban(AclTarget::parse("bad-node").unwrap()));
+        acl.ban(AclTarget::parse("10.0.0.0/8").unwrap());
+
+        assert_eq!(acl.check(ip("1.1.1.1"), Some("good")), AclDecision::Allow);
+        assert_eq!(
+            acl.check(ip("1.1.1.1"), Some("bad-node")),
+            AclDecision::Banned
+        );
+        assert_eq!(acl.check(ip("10.9.9.9"), None), AclDecision::Banned);
+
+        assert!(acl.unban(&AclTarget::parse("bad-node").unwrap()));
+        assert!(acl.permits(ip("1.1.1.1"), Some("bad-node")));
+    }
+
+    #[test]
+    fn test_allowlist_mode() {
+        let mut acl = AccessList {
+            mode: AclMode::Allowlist,
+            ..Default::default()
+        };
+        acl.allow(AclTarget::parse("192.168.0.0/16").unwrap());
+        assert!(acl.permits(ip("192.168.1.1"), Some("anyone")));
+        assert_eq!(acl.check(ip("8.8.8.8"), None), AclDecision::NotAllowed);
+
+        // 有地址规则时，握手前只凭 IP 无法判断，暂时放行
+        acl.allow(AclTarget::parse("friend").unwrap());
+        assert!(acl.permits(ip("8.8.8.8"), None));
+        assert!(acl.permits(ip("8.8.8.8"), Some("friend")));
+        assert_eq!(
+            acl.check(ip("8.8.8.8"), Some("stranger")),
+            AclDecision::NotAllowed
+        );
+
+        // 黑名单优先于白名单
+        acl.ban(AclTarget::parse("friend").unwrap());
+        assert_eq!(
+            acl.check(ip("8.8.8.8"), Some("friend")),
+            AclDecision::Banned
+        );
+    }
+
+    #[test]
+    fn test_serde_roundtrip() {
+        let mut acl = AccessList::default();
+        acl.ban(AclTarget::parse("10.0.0.0/8").unwrap());
+        acl.allow(AclTarget::parse("friend").unwrap());
+        let json = serde_json::to_string(&acl).unwrap();
+        assert!(json.contains("\"10.0.0.0/8\""));
+        let back: AccessList = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, acl);
+
+        // 缺省字段
+        let empty: AccessList = serde_json::from_str("{}").unwrap();
+        assert_eq!(empty, AccessList::default());
+    }
+}
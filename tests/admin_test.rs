@@ -0,0 +1,148 @@
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use zz_account::address::FreeWebMovementAddress;
+    use zz_p2p::admin::{
+        AdminAction, AdminChannel, AdminDenied, AdminRequest, AdminRole, AuditEntry, AuditOutcome,
+        SignedAdminRequest, public_key_hex,
+    };
+    use zz_p2p::config::{AdminConfig, AdminKey};
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn config(key: &FreeWebMovementAddress, role: AdminRole) -> AdminConfig {
+        AdminConfig {
+            keys: vec![AdminKey {
+                name: "alice".into(),
+                public_key: public_key_hex(key),
+                role,
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn signed(
+        key: &FreeWebMovementAddress,
+        action: AdminAction,
+        nonce: &str,
+    ) -> SignedAdminRequest {
+        let request = AdminRequest {
+            action,
+            params: json!({"target": "10.0.0.1"}),
+            issued_at: NOW,
+            nonce: nonce.into(),
+        };
+        SignedAdminRequest::sign(key, &request).unwrap()
+    }
+
+    fn channel() -> (AdminChannel, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        (AdminChannel::new(dir.path().join("audit.log")), dir)
+    }
+
+    #[test]
+    fn test_roles_are_hierarchical() {
+        assert!(AdminRole::Owner.permits(AdminAction::Shutdown));
+        assert!(AdminRole::Operator.permits(AdminAction::BanPeer));
+        assert!(AdminRole::Operator.permits(AdminAction::AuditLog));
+        assert!(AdminRole::Operator.permits(AdminAction::Retention));
+        assert!(!AdminRole::Auditor.permits(AdminAction::Retention));
+        assert!(!AdminRole::Operator.permits(AdminAction::RotateKeys));
+        assert!(!AdminRole::Auditor.permits(AdminAction::ReloadConfig));
+    }
+
+    #[test]
+    fn test_authorized_request() {
+        let key = FreeWebMovementAddress::random();
+        let (admin, _dir) = channel();
+        let (granted, request) = admin
+            .authorize(
+                &config(&key, AdminRole::Owner),
+                &signed(&key, AdminAction::Shutdown, "n1"),
+                NOW + 5,
+            )
+            .unwrap();
+        assert_eq!(granted.name, "alice");
+        assert_eq!(request.action, AdminAction::Shutdown);
+    }
+
+    #[test]
+    fn test_rejects_unknown_key_and_tampering() {
+        let key = FreeWebMovementAddress::random();
+        let other = FreeWebMovementAddress::random();
+        let (admin, _dir) = channel();
+        let cfg = config(&key, AdminRole::Owner);
+
+        let err = admin
+            .authorize(&cfg, &signed(&other, AdminAction::Shutdown, "n1"), NOW)
+            .unwrap_err();
+        assert_eq!(err.status(), 401);
+
+        let mut tampered = signed(&key, AdminAction::AuditLog, "n2");
+        tampered.request = tampered.request.replace("audit_log", "shutdown");
+        let err = admin.authorize(&cfg, &tampered, NOW).unwrap_err();
+        assert!(matches!(err, AdminDenied::Unauthorized(_)));
+
+        assert_eq!(
+            admin
+                .authorize(
+                    &AdminConfig::default(),
+                    &signed(&key, AdminAction::AuditLog, "n3"),
+                    NOW
+                )
+                .unwrap_err(),
+            AdminDenied::Disabled
+        );
+    }
+
+    #[test]
+    fn test_rejects_stale_and_replayed_requests() {
+        let key = FreeWebMovementAddress::random();
+        let (admin, _dir) = channel();
+        let cfg = config(&key, AdminRole::Owner);
+
+        let err = admin
+            .authorize(&cfg, &signed(&key, AdminAction::AuditLog, "n1"), NOW + 3600)
+            .unwrap_err();
+        assert_eq!(err.status(), 401);
+
+        let request = signed(&key, AdminAction::AuditLog, "n2");
+        admin.authorize(&cfg, &request, NOW).unwrap();
+        let err = admin.authorize(&cfg, &request, NOW).unwrap_err();
+        assert_eq!(err, AdminDenied::Unauthorized("replayed nonce".into()));
+    }
+
+    #[test]
+    fn test_role_forbidden() {
+        let key = FreeWebMovementAddress::random();
+        let (admin, _dir) = channel();
+        let err = admin
+            .authorize(
+                &config(&key, AdminRole::Operator),
+                &signed(&key, AdminAction::Shutdown, "n1"),
+                NOW,
+            )
+            .unwrap_err();
+        assert_eq!(err.status(), 403);
+    }
+
+    #[test]
+    fn test_audit_log_tail() {
+        let (admin, _dir) = channel();
+        for i in 0..5 {
+            admin.record(&AuditEntry {
+                at: i,
+                admin: Some("alice".into()),
+                public_key: "02".into(),
+                action: Some(AdminAction::BanPeer),
+                params: json!({"target": format!("10.0.0.{}", i)}),
+                outcome: AuditOutcome::Ok,
+                detail: String::new(),
+            });
+        }
+        let tail = admin.tail(2);
+        assert_eq!(tail.len(), 2);
+        assert_eq!(tail[0].at, 3);
+        assert_eq!(tail[1].at, 4);
+    }
+}
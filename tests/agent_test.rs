@@ -0,0 +1,128 @@
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use zz_p2p::{
+        config::Config,
+        protocols::agent::{
+            AgentConfig, BelowMinAction, PACKAGE_VERSION, SoftwareInfo, Verdict, check,
+            default_user_agent, parse_version,
+        },
+        record::{NodeRecord, NodeRegistry},
+    };
+
+    fn info(version: &str) -> SoftwareInfo {
+        SoftwareInfo {
+            version: version.to_string(),
+            git_hash: String::new(),
+            user_agent: "peer/1".to_string(),
+        }
+    }
+
+    fn policy(min_version: &str, below_min: BelowMinAction) -> AgentConfig {
+        AgentConfig {
+            user_agent: String::new(),
+            min_version: min_version.to_string(),
+            below_min,
+        }
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("0.1.5"), Some((0, 1, 5)));
+        assert_eq!(parse_version("v1.2"), Some((1, 2, 0)));
+        assert_eq!(parse_version("2"), Some((2, 0, 0)));
+        assert_eq!(parse_version("1.4.0-beta.1+abc"), Some((1, 4, 0)));
+        assert_eq!(parse_version(""), None);
+        assert_eq!(parse_version("1.2.3.4"), None);
+        assert_eq!(parse_version("latest"), None);
+        assert!(parse_version(PACKAGE_VERSION).is_some());
+    }
+
+    #[test]
+    fn test_local_info_and_display() {
+        let local = SoftwareInfo::new(default_user_agent());
+        assert_eq!(local.version, PACKAGE_VERSION);
+        assert_eq!(local.user_agent, format!("zz-p2p/{}", PACKAGE_VERSION));
+        assert_eq!(
+            AgentConfig::default().user_agent(),
+            default_user_agent(),
+            "空的 user_agent 使用默认值"
+        );
+
+        let mut peer = info("0.2.0");
+        assert_eq!(peer.to_string(), "peer/1 (v0.2.0)");
+        peer.git_hash = "abc123".to_string();
+        assert_eq!(peer.to_string(), "peer/1 (v0.2.0+abc123)");
+        assert_eq!(SoftwareInfo::default().to_string(), "unknown");
+    }
+
+    #[test]
+    fn test_check_against_minimum() {
+        // 未设置最低版本时不检查
+        assert_eq!(
+            check(&AgentConfig::default(), &SoftwareInfo::default()),
+            Verdict::Accept
+        );
+
+        let warn = policy("0.2.0", BelowMinAction::Warn);
+        assert_eq!(check(&warn, &info("0.2.0")), Verdict::Accept);
+        assert_eq!(check(&warn, &info("0.10.1")), Verdict::Accept);
+        assert!(matches!(check(&warn, &info("0.1.9")), Verdict::Warn(_)));
+
+        let refuse = policy("0.2.0", BelowMinAction::Refuse);
+        match check(&refuse, &info("0.1.9")) {
+            Verdict::Refuse(reason) => assert!(reason.contains("0.1.9")),
+            other => panic!("unexpected verdict {:?}", other),
+        }
+        // 没有声明版本的旧节点视为低于最低版本
+        assert!(matches!(
+            check(&refuse, &SoftwareInfo::default()),
+            Verdict::Refuse(_)
+        ));
+    }
+
+    #[test]
+    fn test_stricter_verdict_wins() {
+        let warn = Verdict::Warn("old".to_string());
+        let refuse = Verdict::Refuse("blocked".to_string());
+        assert_eq!(Verdict::Accept.stricter(warn.clone()), warn);
+        assert_eq!(warn.clone().stricter(refuse.clone()), refuse);
+        assert_eq!(refuse.clone().stricter(Verdict::Accept), refuse);
+    }
+
+    #[test]
+    fn test_config_section() {
+        let config: Config = toml::from_str(
+            r#"
+            [agent]
+            user_agent = "my-app/1.2"
+            min_version = "0.1.5"
+            below_min = "refuse"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.agent.user_agent(), "my-app/1.2");
+        assert_eq!(config.agent.below_min, BelowMinAction::Refuse);
+        assert_eq!(Config::default().agent.below_min, BelowMinAction::Warn);
+    }
+
+    #[test]
+    fn test_registry_records_agent() {
+        let endpoint: SocketAddr = "10.0.0.2:10086".parse().unwrap();
+        let mut registry = NodeRegistry::default();
+        // 不在列表中的地址忽略
+        assert!(!registry.record_agent(endpoint, info("0.2.0")));
+
+        registry.add(endpoint, false);
+        assert!(registry.record_agent(endpoint, info("0.2.0")));
+        let record = registry.get(endpoint).unwrap();
+        assert_eq!(record.agent, Some(info("0.2.0")));
+
+        // 旧记录没有该字段
+        let mut legacy = serde_json::to_value(record).unwrap();
+        legacy.as_object_mut().unwrap().remove("agent");
+        let legacy: NodeRecord = serde_json::from_value(legacy).unwrap();
+        assert!(legacy.agent.is_none());
+    }
+}
@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use zz_p2p::protocols::commands::node_registry::NodeManager;
+
+    #[test]
+    fn test_add_and_resolve_alias() {
+        let manager = NodeManager::new();
+        manager.add_alias("alice", "addr-alice").unwrap();
+
+        assert_eq!(manager.resolve_alias("alice"), "addr-alice");
+        // 非别名原样返回
+        assert_eq!(manager.resolve_alias("addr-bob"), "addr-bob");
+    }
+
+    #[test]
+    fn test_add_alias_overwrites() {
+        let manager = NodeManager::new();
+        manager.add_alias("alice", "old").unwrap();
+        manager.add_alias("alice", "new").unwrap();
+        assert_eq!(manager.resolve_alias("alice"), "new");
+        assert_eq!(manager.aliases().len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_alias_rejected() {
+        let manager = NodeManager::new();
+        assert!(manager.add_alias("", "addr").is_err());
+        assert!(manager.add_alias("two words", "addr").is_err());
+        assert!(manager.add_alias("127.0.0.1:1090", "addr").is_err());
+        assert!(manager.add_alias("alice", "").is_err());
+        assert!(manager.aliases().is_empty());
+    }
+
+    #[test]
+    fn test_remove_alias() {
+        let manager = NodeManager::new();
+        manager.add_alias("alice", "addr-alice").unwrap();
+        assert_eq!(manager.remove_alias("alice").as_deref(), Some("addr-alice"));
+        assert_eq!(manager.remove_alias("alice"), None);
+        assert_eq!(manager.resolve_alias("alice"), "alice");
+    }
+
+    #[test]
+    fn test_aliases_round_trip() {
+        let manager = NodeManager::new();
+        manager.add_alias("bob", "addr-bob").unwrap();
+        manager.add_alias("alice", "addr-alice").unwrap();
+
+        let snapshot = manager.aliases();
+        let names: Vec<&String> = snapshot.keys().collect();
+        assert_eq!(names, vec!["alice", "bob"]);
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let restored: BTreeMap<String, String> = serde_json::from_str(&json).unwrap();
+        let other = NodeManager::new();
+        other.load_aliases(restored);
+        assert_eq!(other.resolve_alias("bob"), "addr-bob");
+    }
+}
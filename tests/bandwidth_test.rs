@@ -0,0 +1,133 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        path::Path,
+        time::{Duration, Instant},
+    };
+
+    use zz_p2p::{
+        config::{BandwidthConfig, Config},
+        protocols::bandwidth::{BandwidthState, Direction, LeakyBucket, RateMeter},
+    };
+
+    fn peer(port: u16) -> SocketAddr {
+        SocketAddr::from(([10, 0, 0, 1], port))
+    }
+
+    #[test]
+    fn test_unlimited_never_waits() {
+        let now = Instant::now();
+        let mut bucket = LeakyBucket::new(now);
+        for _ in 0..100 {
+            assert_eq!(bucket.reserve(1024 * 1024, 0, now), Duration::ZERO);
+        }
+    }
+
+    #[test]
+    fn test_burst_then_wait() {
+        let now = Instant::now();
+        let mut bucket = LeakyBucket::new(now);
+        // 容量为一秒的流量，未超出时不等待
+        assert_eq!(bucket.reserve(1000, 1000, now), Duration::ZERO);
+        // 再加 500 字节，需等待 0.5 秒
+        let wait = bucket.reserve(500, 1000, now);
+        assert_eq!(wait, Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_bucket_drains_over_time() {
+        let now = Instant::now();
+        let mut bucket = LeakyBucket::new(now);
+        bucket.reserve(1000, 1000, now);
+        let later = now + Duration::from_secs(1);
+        assert_eq!(bucket.reserve(1000, 1000, later), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_rate_meter() {
+        let now = Instant::now();
+        let mut meter = RateMeter::new(now);
+        meter.record(300, now);
+        meter.record(200, now + Duration::from_millis(500));
+        assert_eq!(meter.total(), 500);
+        assert_eq!(meter.rate(now + Duration::from_millis(1100)), 500);
+        // 长时间无流量后速率归零，总量保留
+        assert_eq!(meter.rate(now + Duration::from_secs(5)), 0);
+        assert_eq!(meter.total(), 500);
+    }
+
+    #[test]
+    fn test_per_peer_limit_is_independent() {
+        let policy = BandwidthConfig {
+            peer_upload_bytes_per_sec: 1000,
+            ..Default::default()
+        };
+        let now = Instant::now();
+        let mut state = BandwidthState::default();
+
+        assert_eq!(
+            state.reserve(&policy, peer(1), Direction::Upload, 1500, now),
+            Duration::from_millis(500)
+        );
+        // 其他连接不受影响
+        assert_eq!(
+            state.reserve(&policy, peer(2), Direction::Upload, 1000, now),
+            Duration::ZERO
+        );
+        // 下行未限速
+        assert_eq!(
+            state.reserve(&policy, peer(1), Direction::Download, 5000, now),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_global_limit_spans_peers() {
+        let policy = BandwidthConfig {
+            download_bytes_per_sec: 1000,
+            ..Default::default()
+        };
+        let now = Instant::now();
+        let mut state = BandwidthState::default();
+
+        assert_eq!(
+            state.reserve(&policy, peer(1), Direction::Download, 800, now),
+            Duration::ZERO
+        );
+        assert_eq!(
+            state.reserve(&policy, peer(2), Direction::Download, 400, now),
+            Duration::from_millis(200)
+        );
+    }
+
+    #[test]
+    fn test_usage_snapshot() {
+        let policy = BandwidthConfig::default();
+        let now = Instant::now();
+        let mut state = BandwidthState::default();
+        state.reserve(&policy, peer(2), Direction::Upload, 100, now);
+        state.reserve(&policy, peer(1), Direction::Download, 40, now);
+
+        let usage = state.usage(now);
+        assert_eq!(usage.upload_bytes, 100);
+        assert_eq!(usage.download_bytes, 40);
+        assert_eq!(usage.peers.len(), 2);
+        assert_eq!(usage.peers[0].peer, peer(1).to_string());
+        assert_eq!(usage.peers[0].download_bytes, 40);
+        assert_eq!(usage.peers[1].upload_bytes, 100);
+    }
+
+    #[test]
+    fn test_parse_bandwidth_config() {
+        let text = r#"
+[bandwidth]
+upload_bytes_per_sec = 262144
+peer_download_bytes_per_sec = 65536
+"#;
+        let config = Config::parse(text, Path::new("node.toml")).unwrap();
+        assert_eq!(config.bandwidth.upload_bytes_per_sec, 262144);
+        assert_eq!(config.bandwidth.peer_download_bytes_per_sec, 65536);
+        assert_eq!(config.bandwidth.download_bytes_per_sec, 0);
+    }
+}
@@ -0,0 +1,54 @@
+#[cfg(test)]
+mod tests {
+    use aex::tcp::types::Codec;
+    use zz_p2p::protocols::commands::binary::{
+        BINARY_CHUNK_SIZE, BinaryMessageCommand, DEFAULT_CONTENT_TYPE, guess_content_type,
+        split_into_chunks,
+    };
+
+    #[test]
+    fn test_guess_content_type() {
+        assert_eq!(guess_content_type("photo.JPG"), "image/jpeg");
+        assert_eq!(guess_content_type("notes.txt"), "text/plain");
+        assert_eq!(guess_content_type("archive.tar.zip"), "application/zip");
+        assert_eq!(guess_content_type("unknown.bin"), DEFAULT_CONTENT_TYPE);
+        assert_eq!(guess_content_type(""), DEFAULT_CONTENT_TYPE);
+    }
+
+    #[test]
+    fn test_split_into_chunks_covers_payload() {
+        let data: Vec<u8> = (0..(BINARY_CHUNK_SIZE * 2 + 10)).map(|i| i as u8).collect();
+        let chunks = split_into_chunks(
+            "sender".into(),
+            "receiver".into(),
+            7,
+            "image/png",
+            Some("a.png".into()),
+            &data,
+        );
+
+        assert_eq!(chunks.len(), 3);
+        for (i, c) in chunks.iter().enumerate() {
+            assert_eq!(c.chunk_index, i as u32);
+            assert_eq!(c.chunk_count, 3);
+            assert_eq!(c.total_len, data.len() as u64);
+            assert_eq!(c.content_type, "image/png");
+        }
+
+        let rebuilt: Vec<u8> = chunks.iter().flat_map(|c| c.data.clone()).collect();
+        assert_eq!(rebuilt, data);
+
+        // Codec 往返
+        let bytes = Codec::encode(&chunks[2]).unwrap();
+        let decoded: BinaryMessageCommand = Codec::decode(&bytes).unwrap();
+        assert_eq!(decoded, chunks[2]);
+    }
+
+    #[test]
+    fn test_split_empty_payload_yields_single_chunk() {
+        let chunks = split_into_chunks("s".into(), "r".into(), 1, "text/plain", None, &[]);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].chunk_count, 1);
+        assert!(chunks[0].data.is_empty());
+    }
+}
@@ -0,0 +1,120 @@
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+    use zz_p2p::web::body::{BodyReader, reject};
+    use zz_p2p::web::chunked::BodyError;
+
+    #[tokio::test]
+    async fn test_content_length_body() {
+        let mut reader: &[u8] = b"hello worldNEXT";
+        let mut body = BodyReader::from_headers(&mut reader, Some("11"), None, 1024).unwrap();
+        assert_eq!(body.collect().await.unwrap(), b"hello world");
+        assert!(body.is_done());
+        assert_eq!(body.bytes_read(), 11);
+        // 不读取请求体之后的数据
+        assert_eq!(reader, b"NEXT");
+
+        let mut empty: &[u8] = b"GET";
+        let mut body = BodyReader::from_headers(&mut empty, None, None, 1024).unwrap();
+        assert!(body.collect().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_declared_length_over_limit() {
+        // 声明 10 GB 的请求体在读取前就被拒绝，不会按声明的长度分配内存
+        let mut reader: &[u8] = b"";
+        let err = BodyReader::from_headers(&mut reader, Some("10000000000"), None, 1024)
+            .err()
+            .unwrap();
+        assert!(matches!(err, BodyError::TooLarge { limit: 1024 }));
+        assert_eq!(err.status(), Some(413));
+
+        let err = BodyReader::from_headers(&mut reader, Some("-1"), None, 1024)
+            .err()
+            .unwrap();
+        assert!(matches!(err, BodyError::InvalidLength));
+        assert_eq!(err.status(), Some(400));
+    }
+
+    #[tokio::test]
+    async fn test_streaming_reads() {
+        let raw = b"5\r\nhello\r\n6\r\n world\r\n0\r\nX-Sum: 1\r\n\r\nNEXT";
+        let mut reader: &[u8] = raw;
+        let mut body =
+            BodyReader::from_headers(&mut reader, Some("3"), Some("chunked"), 1024).unwrap();
+        // 小缓冲区逐段读取
+        let mut buf = [0u8; 4];
+        let mut data = Vec::new();
+        loop {
+            let n = body.read(&mut buf).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            data.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(data, b"hello world");
+        assert_eq!(
+            body.trailers(),
+            &[("X-Sum".to_string(), "1".to_string())][..]
+        );
+        assert_eq!(reader, b"NEXT");
+    }
+
+    #[tokio::test]
+    async fn test_chunked_over_limit() {
+        let raw = b"8\r\n12345678\r\n8\r\n12345678\r\n0\r\n\r\n";
+        let mut reader: &[u8] = raw;
+        let mut body = BodyReader::chunked(&mut reader, 10);
+        assert!(matches!(
+            body.collect().await,
+            Err(BodyError::TooLarge { limit: 10 })
+        ));
+        // 超限的块一个字节也不读取
+        assert_eq!(body.bytes_read(), 8);
+        assert_eq!(reader, b"12345678\r\n0\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_chunk_size_must_be_hex_digits() {
+        for raw in [
+            &b"+8\r\n12345678\r\n0\r\n\r\n"[..],
+            b"-1\r\n",
+            b" \r\n",
+            b"0x8\r\n",
+        ] {
+            let mut reader: &[u8] = raw;
+            let mut body = BodyReader::chunked(&mut reader, 1024);
+            assert!(matches!(body.collect().await, Err(BodyError::Malformed(_))));
+        }
+
+        // 块扩展与大小写不同的十六进制数字仍然接受
+        let mut reader: &[u8] = b"A;name=value\r\n0123456789\r\n0\r\n\r\n";
+        let mut body = BodyReader::chunked(&mut reader, 1024);
+        assert_eq!(body.collect().await.unwrap(), b"0123456789");
+    }
+
+    #[tokio::test]
+    async fn test_truncated_body() {
+        let mut reader: &[u8] = b"abc";
+        let mut body = BodyReader::with_length(&mut reader, 5, 1024).unwrap();
+        assert!(matches!(body.collect().await, Err(BodyError::Io(_))));
+    }
+
+    #[tokio::test]
+    async fn test_reject_response() {
+        let mut out = Vec::new();
+        reject(&mut out, &BodyError::TooLarge { limit: 10 })
+            .await
+            .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+        assert!(text.contains("Connection: close\r\n"));
+        assert!(text.ends_with("body exceeds 10 bytes\"}"));
+
+        // 连接已断开时不写响应
+        let mut out = Vec::new();
+        let gone = BodyError::Io(std::io::ErrorKind::UnexpectedEof.into());
+        reject(&mut out, &gone).await.unwrap();
+        assert!(out.is_empty());
+    }
+}
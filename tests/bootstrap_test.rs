@@ -0,0 +1,124 @@
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, path::Path, time::Duration};
+
+    use clap::Parser;
+    use zz_p2p::{
+        bootstrap::{
+            BOOTSTRAP_RETRY_MAX_SECS, BootstrapSource, DEFAULT_DNS_SEED_PORT, DEFAULT_MIN_PEERS,
+            resolve, retry_delay, sources,
+        },
+        cli::Opt,
+        config::Config,
+    };
+
+    fn addr(s: &str) -> SocketAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_source() {
+        assert_eq!(
+            BootstrapSource::parse("10.0.0.1:1090", 1).unwrap(),
+            BootstrapSource::Addr(addr("10.0.0.1:1090"))
+        );
+        assert_eq!(
+            BootstrapSource::parse("[::1]:2000", 1).unwrap(),
+            BootstrapSource::Addr(addr("[::1]:2000"))
+        );
+        assert_eq!(
+            BootstrapSource::parse("seed.example.org:3000", 1).unwrap(),
+            BootstrapSource::Dns {
+                host: "seed.example.org".to_string(),
+                port: 3000
+            }
+        );
+        assert_eq!(
+            BootstrapSource::parse(" seed.example.org ", 1090).unwrap(),
+            BootstrapSource::Dns {
+                host: "seed.example.org".to_string(),
+                port: 1090
+            }
+        );
+        assert!(BootstrapSource::parse("", 1).is_err());
+        assert!(BootstrapSource::parse("host:notaport", 1).is_err());
+        assert!(BootstrapSource::parse("http://x", 1).is_err());
+    }
+
+    #[test]
+    fn test_sources_from_cli() {
+        let opt = Opt::parse_from([
+            "zzp2p",
+            "--seeds",
+            "10.0.0.1:1090, 10.0.0.2:1090",
+            "--bootstrap",
+            "10.0.0.3:1090",
+            "--bootstrap",
+            "10.0.0.1:1090",
+            "--dns-seed",
+            "seed.example.org",
+            "--min-peers",
+            "5",
+        ]);
+        assert_eq!(opt.min_peers, 5);
+        assert_eq!(
+            sources(&opt),
+            vec![
+                BootstrapSource::Addr(addr("10.0.0.1:1090")),
+                BootstrapSource::Addr(addr("10.0.0.2:1090")),
+                BootstrapSource::Addr(addr("10.0.0.3:1090")),
+                BootstrapSource::Dns {
+                    host: "seed.example.org".to_string(),
+                    port: DEFAULT_DNS_SEED_PORT
+                },
+            ]
+        );
+        assert_eq!(Opt::parse_from(["zzp2p"]).min_peers, DEFAULT_MIN_PEERS);
+    }
+
+    #[tokio::test]
+    async fn test_resolve() {
+        let resolved = resolve(&[
+            BootstrapSource::Addr(addr("10.0.0.1:1090")),
+            BootstrapSource::Dns {
+                host: "localhost".to_string(),
+                port: 4000,
+            },
+            BootstrapSource::Addr(addr("10.0.0.1:1090")),
+        ])
+        .await;
+        assert_eq!(resolved[0], addr("10.0.0.1:1090"));
+        assert_eq!(resolved.iter().filter(|a| a.port() == 1090).count(), 1);
+        assert!(
+            resolved
+                .iter()
+                .any(|a| a.port() == 4000 && a.ip().is_loopback())
+        );
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(0), Duration::from_secs(5));
+        assert_eq!(retry_delay(1), Duration::from_secs(10));
+        assert_eq!(retry_delay(3), Duration::from_secs(40));
+        assert_eq!(
+            retry_delay(40),
+            Duration::from_secs(BOOTSTRAP_RETRY_MAX_SECS)
+        );
+    }
+
+    #[test]
+    fn test_config_merge() {
+        let text = "dns_seeds = [\"seed.example.org\"]\nmin_peers = 8\n";
+        let config = Config::parse(text, Path::new("node.toml")).unwrap();
+        let mut opt = Opt::parse_from(["zzp2p"]);
+        config.merge_into(&mut opt);
+        assert_eq!(opt.dns_seeds, vec!["seed.example.org".to_string()]);
+        assert_eq!(opt.min_peers, 8);
+
+        let mut opt = Opt::parse_from(["zzp2p", "--dns-seed", "other.org", "--min-peers", "2"]);
+        config.merge_into(&mut opt);
+        assert_eq!(opt.dns_seeds, vec!["other.org".to_string()]);
+        assert_eq!(opt.min_peers, 2);
+    }
+}
@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use zz_p2p::protocols::broadcast::{
+        self, BroadcastReport, PeerReachability, PeerResult, REACHABILITY_PENALTY, Reachability,
+        SendOutcome,
+    };
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    fn result(port: u16, outcome: SendOutcome) -> PeerResult {
+        PeerResult {
+            addr: addr(port),
+            outcome,
+        }
+    }
+
+    #[test]
+    fn test_report_counts() {
+        let report = BroadcastReport {
+            results: vec![
+                result(1, SendOutcome::Sent),
+                result(2, SendOutcome::Sent),
+                result(3, SendOutcome::Failed("broken pipe".to_string())),
+                result(4, SendOutcome::TimedOut),
+            ],
+        };
+        assert_eq!(report.sent(), 2);
+        assert_eq!(report.failed(), 1);
+        assert_eq!(report.timed_out(), 1);
+        assert_eq!(BroadcastReport::default().sent(), 0);
+    }
+
+    #[test]
+    fn test_reachability_score() {
+        let mut r = Reachability::default();
+        assert_eq!(r.score(), 1.0);
+        r.record(true);
+        r.record(false);
+        r.record(false);
+        assert_eq!(r.consecutive_failures, 2);
+        assert!((r.score() - 1.0 / 3.0).abs() < 1e-9);
+        r.record(true);
+        assert_eq!(r.consecutive_failures, 0);
+        assert_eq!((r.successes, r.failures), (2, 2));
+    }
+
+    #[test]
+    fn test_record_and_penalty() {
+        let reachability = PeerReachability::default();
+        let results = vec![
+            result(1, SendOutcome::Sent),
+            result(2, SendOutcome::TimedOut),
+        ];
+        broadcast::record(&reachability, &results);
+        broadcast::record(&reachability, &results);
+
+        assert_eq!(broadcast::penalty(&reachability, &addr(1)), 0);
+        assert_eq!(
+            broadcast::penalty(&reachability, &addr(2)),
+            2 * REACHABILITY_PENALTY
+        );
+        // 没有记录的连接不扣分
+        assert_eq!(broadcast::penalty(&reachability, &addr(3)), 0);
+
+        broadcast::record(&reachability, &[result(2, SendOutcome::Sent)]);
+        assert_eq!(broadcast::penalty(&reachability, &addr(2)), 0);
+    }
+}
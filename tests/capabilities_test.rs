@@ -0,0 +1,63 @@
+#[cfg(test)]
+mod tests {
+    use zz_p2p::protocols::{
+        capabilities::{
+            CAP_FILE_TRANSFER, CAP_PUBSUB, CAP_RELAY, LOCAL_MAX_FRAME_SIZE, MIN_MAX_FRAME_SIZE,
+            PeerMaxFrameSize, feature_names,
+        },
+        commands::fragment::{self, FRAGMENT_CHUNK_SIZE, FRAGMENT_THRESHOLD},
+        compression::{CAP_COMPRESSION_LZ4, LOCAL_CAPABILITIES, PeerCapabilities},
+    };
+
+    #[test]
+    fn test_local_capabilities_advertise_features() {
+        let local = PeerCapabilities(LOCAL_CAPABILITIES);
+        assert!(local.supports(CAP_RELAY));
+        assert!(local.supports(CAP_FILE_TRANSFER));
+        assert!(local.supports(CAP_PUBSUB));
+        assert!(local.supports(CAP_RELAY | CAP_PUBSUB));
+        assert_eq!(LOCAL_MAX_FRAME_SIZE as usize, FRAGMENT_THRESHOLD);
+    }
+
+    #[test]
+    fn test_feature_names() {
+        let caps = PeerCapabilities(CAP_COMPRESSION_LZ4 | CAP_PUBSUB);
+        assert_eq!(caps.features(), vec!["lz4", "pubsub"]);
+        assert!(caps.supports(CAP_PUBSUB));
+        assert!(!caps.supports(CAP_RELAY));
+        // 两个能力须同时具备
+        assert!(!caps.supports(CAP_PUBSUB | CAP_RELAY));
+        // 旧节点只声明了压缩
+        assert_eq!(feature_names(CAP_COMPRESSION_LZ4), vec!["lz4"]);
+        // 未知的位被忽略
+        assert!(feature_names(1 << 31).is_empty());
+    }
+
+    #[test]
+    fn test_max_frame_size() {
+        assert_eq!(PeerMaxFrameSize::from_declared(0), None);
+        assert_eq!(
+            PeerMaxFrameSize::from_declared(1024),
+            Some(PeerMaxFrameSize(MIN_MAX_FRAME_SIZE))
+        );
+        assert_eq!(
+            PeerMaxFrameSize::from_declared(40_000),
+            Some(PeerMaxFrameSize(40_000))
+        );
+
+        // 对端帧长上限决定分片阈值与分片大小
+        assert_eq!(fragment::threshold(None), FRAGMENT_THRESHOLD);
+        assert_eq!(
+            fragment::threshold(Some(PeerMaxFrameSize(1 << 30))),
+            FRAGMENT_THRESHOLD
+        );
+        assert_eq!(fragment::threshold(Some(PeerMaxFrameSize(40_000))), 40_000);
+        assert_eq!(
+            fragment::chunk_size(FRAGMENT_THRESHOLD),
+            FRAGMENT_CHUNK_SIZE
+        );
+        let chunk = fragment::chunk_size(40_000);
+        assert!(chunk < 40_000);
+        assert!(chunk + fragment::FRAGMENT_OVERHEAD <= 40_000);
+    }
+}
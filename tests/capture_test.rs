@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use aex::tcp::types::Codec;
+    use base64::Engine;
+    use zz_account::address::FreeWebMovementAddress;
+    use zz_p2p::{
+        capture::{Capture, CaptureDirection, CaptureRecord, read, verify},
+        protocols::{
+            command::{Action, Entity, P2PCommand},
+            frame::P2PFrame,
+            version::CURRENT_PROTOCOL_VERSION,
+        },
+    };
+
+    async fn ping() -> (P2PFrame, Vec<u8>) {
+        let address = FreeWebMovementAddress::random();
+        let cmd = P2PCommand::new(Entity::Node, Action::Ping, b"hello".to_vec());
+        let frame = P2PFrame::build(&address, cmd, CURRENT_PROTOCOL_VERSION)
+            .await
+            .unwrap();
+        let raw = Codec::encode(&frame).unwrap();
+        (frame, raw)
+    }
+
+    fn peer() -> SocketAddr {
+        "127.0.0.1:9000".parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_record_metadata() {
+        let (frame, raw) = ping().await;
+        let record = CaptureRecord::new(CaptureDirection::In, peer(), &frame, &raw);
+        assert_eq!(record.command, "Node/Ping");
+        assert_eq!(record.payload_len, 5);
+        assert_eq!(record.frame_len, raw.len());
+        assert_eq!(record.from, frame.body.address);
+        assert_eq!(record.raw_bytes().unwrap(), raw);
+        assert!(record.to_string().contains("Node/Ping"));
+
+        assert!(record.matches("ping"));
+        assert!(record.matches("127.0.0.1"));
+        assert!(!record.matches("Message"));
+    }
+
+    #[tokio::test]
+    async fn test_append_read_verify() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("captures/frames.jsonl");
+        let (frame, raw) = ping().await;
+
+        let capture = Capture::open(&path).unwrap();
+        let inbound = CaptureRecord::new(CaptureDirection::In, peer(), &frame, &raw);
+        let outbound = CaptureRecord::new(CaptureDirection::Out, peer(), &frame, &raw);
+        capture.append(&inbound).unwrap();
+        capture.append(&outbound).unwrap();
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .and_then(|mut f| std::io::Write::write_all(&mut f, b"not json\n"))
+            .unwrap();
+
+        let (records, corrupt) = read(&path).unwrap();
+        assert_eq!(records, vec![inbound, outbound]);
+        assert_eq!(corrupt, 1);
+        assert!(verify(&records[0]).is_ok());
+
+        // 篡改负载后签名校验失败
+        let mut tampered = records[0].clone();
+        let mut bytes = tampered.raw_bytes().unwrap();
+        let at = bytes
+            .windows(5)
+            .position(|w| w == b"hello")
+            .expect("payload present in encoding");
+        bytes[at] = b'j';
+        tampered.raw = base64::engine::general_purpose::STANDARD.encode(&bytes);
+        assert!(verify(&tampered).is_err());
+    }
+}
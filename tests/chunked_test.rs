@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+    use zz_p2p::web::chunked::{
+        BodyError, ChunkedWriter, encode_chunk, encode_last_chunk, is_chunked, read_chunked,
+    };
+
+    #[test]
+    fn test_is_chunked() {
+        assert!(is_chunked(Some("chunked")));
+        assert!(is_chunked(Some("gzip, Chunked")));
+        assert!(!is_chunked(Some("chunked, gzip")));
+        assert!(!is_chunked(None));
+    }
+
+    #[tokio::test]
+    async fn test_read_chunked_body() {
+        let raw = b"4\r\nWiki\r\n5;ext=1\r\npedia\r\nE\r\n in\r\n\r\nchunks.\r\n0\r\n\r\nNEXT";
+        let mut reader: &[u8] = raw;
+        let body = read_chunked(&mut reader, 1024).await.unwrap();
+        assert_eq!(body.data, b"Wikipedia in\r\n\r\nchunks.");
+        assert!(body.trailers.is_empty());
+        // 不读取结束块之后的数据
+        assert_eq!(reader, b"NEXT");
+    }
+
+    #[tokio::test]
+    async fn test_read_chunked_trailers() {
+        let raw = b"3\r\nabc\r\n0\r\nExpires: never\r\nX-Checksum: 42\r\n\r\n";
+        let mut reader: &[u8] = raw;
+        let body = read_chunked(&mut reader, 1024).await.unwrap();
+        assert_eq!(body.data, b"abc");
+        assert_eq!(
+            body.trailers,
+            vec![
+                ("Expires".to_string(), "never".to_string()),
+                ("X-Checksum".to_string(), "42".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_chunked_limit() {
+        let raw = b"8\r\n12345678\r\n8\r\n12345678\r\n0\r\n\r\n";
+        let mut reader: &[u8] = raw;
+        assert!(matches!(
+            read_chunked(&mut reader, 10).await,
+            Err(BodyError::TooLarge { limit: 10 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_read_chunked_malformed() {
+        for raw in [
+            &b"zz\r\nabc\r\n0\r\n\r\n"[..],
+            &b"3\r\nabcd\r\n0\r\n\r\n"[..],
+            &b"3\nabc\r\n0\r\n\r\n"[..],
+            &b"3\r\nabc\r\n0\r\nbad-trailer\r\n\r\n"[..],
+        ] {
+            let mut reader = raw;
+            assert!(matches!(
+                read_chunked(&mut reader, 1024).await,
+                Err(BodyError::Malformed(_))
+            ));
+        }
+        let mut truncated: &[u8] = b"a\r\nabc";
+        assert!(matches!(
+            read_chunked(&mut truncated, 1024).await,
+            Err(BodyError::Io(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_chunked_writer_roundtrip() {
+        let (mut client, mut server) = tokio::io::duplex(4096);
+        let mut writer = ChunkedWriter::start(
+            &mut server,
+            "200 OK",
+            &[("Content-Type", "text/plain".to_string())],
+        )
+        .await
+        .unwrap();
+        writer.write_chunk(b"hello ").await.unwrap();
+        writer.write_chunk(b"").await.unwrap();
+        writer.write_chunk(b"world").await.unwrap();
+        writer.finish(&[("X-Done", "1")]).await.unwrap();
+        drop(server);
+
+        let mut raw = Vec::new();
+        client.read_to_end(&mut raw).await.unwrap();
+        let text = String::from_utf8(raw.clone()).unwrap();
+        let (head, body) = text.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(head.contains("Transfer-Encoding: chunked"));
+
+        let mut reader = body.as_bytes();
+        let decoded = read_chunked(&mut reader, 1024).await.unwrap();
+        assert_eq!(decoded.data, b"hello world");
+        assert_eq!(
+            decoded.trailers,
+            vec![("X-Done".to_string(), "1".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_encode_helpers() {
+        assert_eq!(
+            encode_chunk(b"abcdefghijklmnop"),
+            b"10\r\nabcdefghijklmnop\r\n"
+        );
+        assert!(encode_chunk(b"").is_empty());
+        assert_eq!(encode_last_chunk(&[]), b"0\r\n\r\n");
+    }
+}
@@ -11,7 +11,7 @@ mod tests {
         },
     };
     use zz_p2p::{
-        cli::{Cli, Opt},
+        cli::{Cli, Opt, split_args},
         clis::{connect, help, send, status},
     };
 
@@ -172,6 +172,59 @@ mod tests {
         connect::handle(args.clone(), context.clone()).await;
     }
 
+    #[test]
+    fn test_split_args_quoting() {
+        let split = |line: &str| split_args(line).unwrap();
+        assert_eq!(split("  send  bob hi "), vec!["send", "bob", "hi"]);
+        assert_eq!(
+            split(r#"send bob "see you at 5""#),
+            vec!["send", "bob", "see you at 5"]
+        );
+        assert_eq!(
+            split("pub news 'it''s here'"),
+            vec!["pub", "news", "its here"]
+        );
+        assert_eq!(
+            split(r#"send bob "say \"hi\"""#),
+            vec!["send", "bob", r#"say "hi""#]
+        );
+        assert_eq!(
+            split(r"sendbin bob my\ file.bin"),
+            vec!["sendbin", "bob", "my file.bin"]
+        );
+        assert_eq!(split(r#"send bob """#), vec!["send", "bob", ""]);
+        assert!(split("").is_empty());
+
+        assert!(split_args(r#"send bob "unterminated"#).is_err());
+        assert!(split_args(r"send bob trailing\").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_quoted_arguments_reach_handler() {
+        let mut cli = Cli::new();
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen_clone = seen.clone();
+        cli.register("echo", move |args, _ctx| {
+            let seen = seen_clone.clone();
+            async move {
+                *seen.lock().unwrap() = args;
+            }
+        });
+
+        let ctx = create_mock_ctx();
+        assert!(cli.execute(r#"echo bob "two words""#, ctx.clone()).await);
+        assert_eq!(*seen.lock().unwrap(), vec!["bob", "two words"]);
+        // 引号不完整时不执行
+        assert!(cli.execute(r#"echo "oops"#, ctx.clone()).await);
+        assert_eq!(*seen.lock().unwrap(), vec!["bob", "two words"]);
+        assert!(!cli.execute("exit", ctx).await);
+
+        let names = cli.command_names();
+        assert!(names.contains(&"echo".to_string()));
+        assert!(names.contains(&"exit".to_string()));
+        assert!(names.windows(2).all(|w| w[0] <= w[1]));
+    }
+
     #[test]
     fn test_opt_parsing_logic() {
         // 覆盖 Opt 的所有参数解析分支
@@ -203,4 +256,235 @@ mod tests {
         assert_eq!(opt.inner_server_file, Some("inner.json".to_string()));
         assert_eq!(opt.external_server_file, Some("ext.json".to_string()));
     }
+
+    #[test]
+    fn test_oneshot_subcommands() {
+        use zz_p2p::cli::Command;
+
+        assert_eq!(Opt::parse_from(["zzp2p"]).command, None);
+        assert_eq!(
+            Opt::parse_from(["zzp2p", "daemon"]).command,
+            Some(Command::Daemon)
+        );
+        assert_eq!(
+            Opt::parse_from(["zzp2p", "--port", "7000", "status"]).command,
+            Some(Command::Status)
+        );
+        let opt = Opt::parse_from(["zzp2p", "send", "addr-1", "hello"]);
+        assert_eq!(
+            opt.command,
+            Some(Command::Send {
+                address: "addr-1".to_string(),
+                message: "hello".to_string(),
+            })
+        );
+        assert_eq!(
+            Opt::parse_from(["zzp2p", "connections"]).command,
+            Some(Command::Connections)
+        );
+        assert_eq!(
+            Opt::parse_from(["zzp2p", "doctor"]).command,
+            Some(Command::Doctor)
+        );
+        assert_eq!(
+            Opt::parse_from(["zzp2p", "disconnect", "10.0.0.2:1090"]).command,
+            Some(Command::Disconnect {
+                peer: "10.0.0.2:1090".to_string(),
+            })
+        );
+
+        // 控制地址默认由 P2P 端口推导，可显式覆盖
+        let opt = Opt::parse_from(["zzp2p", "--port", "7000", "peers"]);
+        assert_eq!(
+            opt.control_addr().unwrap(),
+            "127.0.0.1:7001".parse().unwrap()
+        );
+        let opt = Opt::parse_from(["zzp2p", "--control", "127.0.0.1:9999", "peers"]);
+        assert_eq!(
+            opt.control_addr().unwrap(),
+            "127.0.0.1:9999".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_key_subcommands() {
+        use zz_p2p::cli::{Command, KeyAction};
+
+        let opt = Opt::parse_from(["zzp2p", "--encrypt-key", "key", "export", "backup.json"]);
+        assert!(opt.encrypt_key);
+        assert_eq!(
+            opt.command,
+            Some(Command::Key {
+                action: KeyAction::Export {
+                    file: "backup.json".to_string()
+                }
+            })
+        );
+        assert_eq!(
+            Opt::parse_from(["zzp2p", "key", "rotate"]).command,
+            Some(Command::Key {
+                action: KeyAction::Rotate
+            })
+        );
+    }
+
+    #[test]
+    fn test_admin_subcommands() {
+        use zz_p2p::cli::{AdminCommand, Command};
+
+        let opt = Opt::parse_from([
+            "zzp2p",
+            "admin",
+            "exec",
+            "ban_peer",
+            "--key",
+            "admin.json",
+            "--params",
+            r#"{"target":"1.2.3.4"}"#,
+            "--node",
+            "10.0.0.1:1091",
+        ]);
+        assert_eq!(
+            opt.command,
+            Some(Command::Admin {
+                action: AdminCommand::Exec {
+                    action: "ban_peer".to_string(),
+                    params: Some(r#"{"target":"1.2.3.4"}"#.to_string()),
+                    key: "admin.json".to_string(),
+                    node: Some("10.0.0.1:1091".to_string()),
+                    path: "/admin".to_string(),
+                }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_control_api_roundtrip() {
+        use zz_p2p::control;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let ctx = create_mock_ctx();
+        tokio::spawn(control::serve_listener(listener, ctx));
+
+        let (status, body) = control::request(addr, "GET", "/status", None)
+            .await
+            .unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(body["success"], true);
+        assert_eq!(body["inbound"], 0);
+        assert_eq!(body["queues"]["outbox"], 0);
+        assert!(body["versions"]["protocol"].is_number());
+
+        let (status, body) = control::request(addr, "GET", "/peers", None).await.unwrap();
+        assert_eq!(status, 200);
+        assert!(body["peers"].as_array().unwrap().is_empty());
+        assert_eq!(body["page"]["total"], 0);
+
+        let (status, _) = control::request(addr, "GET", "/peers?limit=x", None)
+            .await
+            .unwrap();
+        assert_eq!(status, 400);
+
+        let (status, _) = control::request(addr, "POST", "/send", Some(serde_json::json!({})))
+            .await
+            .unwrap();
+        assert_eq!(status, 400);
+
+        let (status, _) = control::request(addr, "GET", "/nope", None).await.unwrap();
+        assert_eq!(status, 404);
+    }
+
+    /// 读出一个响应，返回状态码、`Connection` 头与 body
+    async fn read_response<R: tokio::io::AsyncBufRead + Unpin>(
+        reader: &mut R,
+    ) -> (u16, String, serde_json::Value) {
+        use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+
+        let mut status_line = String::new();
+        reader.read_line(&mut status_line).await.unwrap();
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .unwrap()
+            .parse()
+            .unwrap();
+        let (mut connection, mut length) = (String::new(), 0);
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).await.unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            let (key, value) = line.split_once(':').unwrap();
+            match key.to_ascii_lowercase().as_str() {
+                "connection" => connection = value.trim().to_string(),
+                "content-length" => length = value.trim().parse().unwrap(),
+                _ => {}
+            }
+        }
+        let mut body = vec![0u8; length];
+        reader.read_exact(&mut body).await.unwrap();
+        (status, connection, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_control_api_keep_alive() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
+        use tokio::sync::RwLock;
+        use zz_p2p::{config::Config, control};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let ctx = create_mock_ctx();
+        let mut config = Config::default();
+        config.keep_alive.idle_timeout_secs = 1;
+        config.keep_alive.max_requests = 3;
+        ctx.set(Arc::new(RwLock::new(config))).await;
+        tokio::spawn(control::serve_listener(listener, ctx));
+
+        // 同一连接上依次发送两个请求
+        let mut stream = BufReader::new(tokio::net::TcpStream::connect(addr).await.unwrap());
+        stream
+            .get_mut()
+            .write_all(b"GET /status HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let (status, connection, body) = read_response(&mut stream).await;
+        assert_eq!((status, connection.as_str()), (200, "keep-alive"));
+        assert_eq!(body["success"], true);
+
+        stream
+            .get_mut()
+            .write_all(b"GET /nope HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let (status, connection, _) = read_response(&mut stream).await;
+        assert_eq!((status, connection.as_str()), (404, "keep-alive"));
+
+        // 第三个请求达到单连接上限：响应带 close，之后连接关闭，流水线中的第四个请求不再处理
+        stream
+            .get_mut()
+            .write_all(
+                b"POST /send HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}GET /status HTTP/1.1\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        let (status, connection, _) = read_response(&mut stream).await;
+        assert_eq!((status, connection.as_str()), (400, "close"));
+        let mut rest = Vec::new();
+        stream.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+
+        // 空闲超时后服务端关闭连接
+        let mut idle = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let mut buf = [0u8; 1];
+        let n = tokio::time::timeout(std::time::Duration::from_secs(5), idle.read(&mut buf))
+            .await
+            .expect("idle connection should be closed by the server")
+            .unwrap();
+        assert_eq!(n, 0);
+    }
 }
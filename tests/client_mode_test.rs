@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{IpAddr, SocketAddr},
+        path::Path,
+        sync::Arc,
+    };
+
+    use aex::connection::{global::GlobalContext, scope::NetworkScope};
+    use clap::Parser;
+    use zz_p2p::{
+        cli::Opt,
+        client_mode::{self, ClientOnly, candidates},
+        config::Config,
+        protocols::commands::observed,
+        record::NodeRecord,
+    };
+
+    fn record(port: u16, successes: u64, failures: u64, pinned: bool) -> NodeRecord {
+        let mut record = NodeRecord::new(SocketAddr::from(([198, 51, 100, 1], port)));
+        record.tries = (successes, failures);
+        record.pinned = pinned;
+        record
+    }
+
+    #[test]
+    fn test_candidates_prefer_pinned_then_score() {
+        let records = vec![
+            record(1, 1, 9, false),
+            record(2, 9, 1, false),
+            record(3, 0, 5, true),
+            // 同一地址出现在内网与外网两个列表中
+            record(2, 9, 1, false),
+        ];
+        let ports: Vec<u16> = candidates(records).iter().map(|a| a.port()).collect();
+        assert_eq!(ports, vec![3, 2, 1]);
+        assert!(candidates(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_flag_and_config() {
+        assert!(!Opt::parse_from(["zzp2p"]).client_only);
+        assert!(Opt::parse_from(["zzp2p", "--client-only"]).client_only);
+
+        let config = Config::parse("client_only = true", Path::new("node.toml")).unwrap();
+        let mut opt = Opt::parse_from(["zzp2p"]);
+        config.merge_into(&mut opt);
+        assert!(opt.client_only);
+    }
+
+    #[tokio::test]
+    async fn test_client_only_announces_no_addresses() {
+        let gctx = Arc::new(GlobalContext::new("127.0.0.1:0".parse().unwrap(), None));
+        let ips: Vec<(NetworkScope, IpAddr)> = vec![
+            (NetworkScope::Intranet, "192.168.1.5".parse().unwrap()),
+            (NetworkScope::Extranet, "203.0.113.5".parse().unwrap()),
+        ];
+        assert!(!client_mode::is_enabled(&gctx).await);
+        let (intranet, wan) = observed::announced_ips(&gctx, &ips).await;
+        assert_eq!(intranet, vec!["192.168.1.5".to_string()]);
+        assert_eq!(wan, vec!["203.0.113.5".to_string()]);
+
+        gctx.set(ClientOnly).await;
+        assert!(client_mode::is_enabled(&gctx).await);
+        let (intranet, wan) = observed::announced_ips(&gctx, &ips).await;
+        assert!(intranet.is_empty() && wan.is_empty());
+        // 没有运行中的节点时不拨号
+        assert_eq!(client_mode::redial_once(&gctx).await, 0);
+    }
+}
@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use aex::connection::global::GlobalContext;
+    use zz_p2p::clock::{
+        CLOCK_SAMPLES_PER_PEER, ClockSample, PeerClock, local_time_of, offset_ms, offsets,
+        peer_now, record, to_local, to_peer,
+    };
+
+    fn sample(offset_ms: i64, delay_ms: u64) -> ClockSample {
+        ClockSample {
+            offset_ms,
+            delay_ms,
+        }
+    }
+
+    #[test]
+    fn test_sample_from_exchange() {
+        // 对端快 500ms，单程 20ms，对端处理 5ms
+        let t1 = 10_000;
+        let t2 = t1 + 20 + 500;
+        let t3 = t2 + 5;
+        let t4 = t1 + 20 + 5 + 20;
+        assert_eq!(
+            ClockSample::from_exchange(t1, t2, t3, t4),
+            Some(sample(500, 40))
+        );
+
+        // 对端慢 300ms
+        let t2 = t1 + 20 - 300;
+        let t3 = t2 + 5;
+        assert_eq!(
+            ClockSample::from_exchange(t1, t2, t3, t4),
+            Some(sample(-300, 40))
+        );
+
+        // 时间倒流的交换被丢弃
+        assert_eq!(ClockSample::from_exchange(10, 20, 30, 5), None);
+        assert_eq!(ClockSample::from_exchange(10, 30, 20, 40), None);
+    }
+
+    #[test]
+    fn test_peer_clock_prefers_lowest_delay() {
+        let mut clock = PeerClock::default();
+        assert_eq!(clock.offset_ms(), None);
+
+        clock.record(sample(900, 300), 1);
+        clock.record(sample(480, 20), 2);
+        clock.record(sample(700, 150), 3);
+        assert_eq!(clock.offset_ms(), Some(480));
+        assert_eq!(clock.updated_at, 3);
+
+        // 延迟相同时取较新的样本
+        clock.record(sample(490, 20), 4);
+        assert_eq!(clock.offset_ms(), Some(490));
+
+        // 只保留最近的样本
+        for i in 0..CLOCK_SAMPLES_PER_PEER {
+            clock.record(sample(100, 50 + i as u64), 5);
+        }
+        assert_eq!(clock.samples(), CLOCK_SAMPLES_PER_PEER);
+        assert_eq!(clock.offset_ms(), Some(100));
+    }
+
+    #[test]
+    fn test_conversion() {
+        assert_eq!(to_local(10_500, 500), 10_000);
+        assert_eq!(to_local(9_700, -300), 10_000);
+        assert_eq!(to_peer(10_000, 500), 10_500);
+        assert_eq!(to_peer(10_000, -300), 9_700);
+        assert_eq!(to_local(100, 500), 0);
+    }
+
+    #[tokio::test]
+    async fn test_offsets_per_peer() {
+        let gctx = Arc::new(GlobalContext::new("127.0.0.1:0".parse().unwrap(), None));
+        assert_eq!(offset_ms(&gctx, "alice").await, None);
+        // 没有样本时不校正
+        assert_eq!(local_time_of(&gctx, "alice", 1_000).await, 1_000);
+        assert_eq!(peer_now(&gctx, "alice", 1_000).await, 1_000);
+
+        record(&gctx, "alice", sample(250, 10)).await;
+        record(&gctx, "bob", sample(-40, 10)).await;
+        assert_eq!(offset_ms(&gctx, "alice").await, Some(250));
+        assert_eq!(local_time_of(&gctx, "alice", 1_250).await, 1_000);
+        assert_eq!(peer_now(&gctx, "bob", 1_000).await, 960);
+
+        let all = offsets(&gctx).await;
+        assert_eq!(all.len(), 2);
+        assert_eq!(all["bob"], -40);
+    }
+}
@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, sync::Arc};
+
+    use aex::connection::global::GlobalContext;
+    use clap::Parser;
+    use tokio::sync::RwLock;
+    use zz_p2p::{
+        cli::Opt,
+        config::{self, Config, SharedConfig},
+        relay,
+        web::keep_alive,
+    };
+
+    const SAMPLE: &str = r#"
+name = "config-node"
+port = 2090
+data_dir = "/tmp/zz-config"
+bootstrap = ["10.0.0.1:1090", "10.0.0.2:1090"]
+log_level = "debug"
+
+[limits]
+max_connections = 64
+messages_per_second = 20
+"#;
+
+    #[test]
+    fn test_parse_toml_config() {
+        let config = Config::parse(SAMPLE, Path::new("node.toml")).unwrap();
+        assert_eq!(config.name.as_deref(), Some("config-node"));
+        assert_eq!(config.port, Some(2090));
+        assert_eq!(config.bootstrap.len(), 2);
+        assert_eq!(config.log_level(), "debug");
+        assert_eq!(config.limits.max_connections, 64);
+        assert_eq!(config.limits.messages_per_second, 20);
+        // 未配置的字段使用默认值
+        assert_eq!(config.limits.max_message_bytes, 0);
+        assert!(config.ip.is_none());
+    }
+
+    #[test]
+    fn test_merge_config_keeps_explicit_cli_values() {
+        let config = Config::parse(SAMPLE, Path::new("node.toml")).unwrap();
+
+        // 命令行未指定的字段由配置文件补全
+        let mut opt = Opt::parse_from(["zzp2p"]);
+        config.merge_into(&mut opt);
+        assert_eq!(opt.name, "config-node");
+        assert_eq!(opt.port, 2090);
+        assert_eq!(opt.data_dir.as_deref(), Some("/tmp/zz-config"));
+        assert_eq!(opt.seeds.as_deref(), Some("10.0.0.1:1090,10.0.0.2:1090"));
+
+        // 命令行显式指定的值优先
+        let mut opt = Opt::parse_from(["zzp2p", "--port", "7777", "--seeds", "1.1.1.1:1"]);
+        config.merge_into(&mut opt);
+        assert_eq!(opt.port, 7777);
+        assert_eq!(opt.seeds.as_deref(), Some("1.1.1.1:1"));
+    }
+
+    #[test]
+    fn test_invalid_config_is_error() {
+        assert!(Config::parse("port = \"not a number\"", Path::new("node.toml")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reload_changes_live_values() {
+        let gctx = Arc::new(GlobalContext::new("127.0.0.1:9000".parse().unwrap(), None));
+        let current = Config::parse(SAMPLE, Path::new("node.toml")).unwrap();
+        let shared: SharedConfig = Arc::new(RwLock::new(current.clone()));
+        gctx.set(shared).await;
+        assert!(!relay::is_enabled(&gctx).await);
+
+        let mut next = current;
+        next.relay.enabled = true;
+        next.keep_alive.max_requests = 7;
+        config::apply(&gctx, next).await;
+
+        // 各节由使用方每次读取，重新加载后立即生效
+        assert!(relay::is_enabled(&gctx).await);
+        assert_eq!(keep_alive::policy(&gctx).await.max_requests, 7);
+    }
+
+    #[test]
+    fn test_restart_required_lists_startup_keys() {
+        let current = Config::parse(SAMPLE, Path::new("node.toml")).unwrap();
+        let mut next = current.clone();
+        next.relay.enabled = true;
+        next.bandwidth.upload_bytes_per_sec = 1024;
+        assert!(config::restart_required(&current, &next).is_empty());
+
+        next.client_only = true;
+        next.bootstrap.clear();
+        next.port = Some(3090);
+        assert_eq!(
+            config::restart_required(&current, &next),
+            vec!["port", "bootstrap", "client_only"]
+        );
+    }
+}
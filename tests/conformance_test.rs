@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use zz_p2p::protocols::conformance::{
+        UPDATE_ENV, VECTOR_EXTENSION, parse_hex, to_hex, vectors,
+    };
+
+    fn vector_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("vectors")
+    }
+
+    #[test]
+    fn test_vectors_match_golden_files() {
+        let dir = vector_dir();
+        let update = std::env::var_os(UPDATE_ENV).is_some();
+        let mut failures = Vec::new();
+        for vector in vectors().unwrap() {
+            let path = dir.join(format!("{}.{}", vector.name, VECTOR_EXTENSION));
+            if update {
+                std::fs::write(&path, vector.to_file().unwrap()).unwrap();
+                continue;
+            }
+            let content = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+                panic!("{}: {} (run with {}=1)", path.display(), e, UPDATE_ENV)
+            });
+            let golden = parse_hex(&content).unwrap();
+
+            // 编码方向：当前实现写出的字节与提交的文件一致
+            let encoded = vector.encode().unwrap();
+            if encoded != golden {
+                failures.push(format!(
+                    "{}: encoding changed\n  golden:  {}\n  current: {}",
+                    vector.name,
+                    to_hex(&golden).replace('\n', ""),
+                    to_hex(&encoded).replace('\n', "")
+                ));
+            }
+            // 解码方向：旧节点写出的字节仍能解码为同样的值
+            if let Err(e) = vector.check_decode(&golden) {
+                failures.push(format!("{}: decoding failed: {}", vector.name, e));
+            }
+        }
+        assert!(
+            failures.is_empty(),
+            "wire format no longer matches tests/vectors:\n{}",
+            failures.join("\n")
+        );
+    }
+
+    #[test]
+    fn test_every_golden_file_has_a_vector() {
+        let names: Vec<&str> = vectors().unwrap().iter().map(|v| v.name).collect();
+        for entry in std::fs::read_dir(vector_dir()).unwrap() {
+            let path = entry.unwrap().path();
+            let stem = path.file_stem().unwrap().to_string_lossy().to_string();
+            assert!(
+                names.contains(&stem.as_str()),
+                "{} has no matching vector; old vectors must be kept",
+                path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn test_vector_names_are_unique() {
+        let mut names: Vec<&str> = vectors().unwrap().iter().map(|v| v.name).collect();
+        let count = names.len();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), count);
+    }
+
+    #[test]
+    fn test_hex_roundtrip() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let hex = to_hex(&bytes);
+        assert_eq!(hex.lines().count(), 8);
+        assert_eq!(parse_hex(&format!("# comment\n{}", hex)).unwrap(), bytes);
+        assert_eq!(parse_hex("0a 0B\n").unwrap(), vec![0x0a, 0x0b]);
+        assert!(parse_hex("abc").is_err());
+        assert!(parse_hex("zz").is_err());
+    }
+
+    #[test]
+    fn test_golden_frame_is_rejected_when_altered() {
+        let vector = vectors()
+            .unwrap()
+            .into_iter()
+            .find(|v| v.name == "frame_v2_relay")
+            .unwrap();
+        let mut bytes = vector.encode().unwrap();
+        // 改动最后一个字节（ttl）
+        *bytes.last_mut().unwrap() ^= 0x01;
+        assert!(vector.check_decode(&bytes).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        path::Path,
+        time::{Duration, Instant},
+    };
+
+    use aex::tcp::types::Codec;
+    use zz_account::address::FreeWebMovementAddress;
+    use zz_p2p::{
+        config::{Config, LimitsConfig},
+        protocols::{
+            commands::node_registry::ConnectionDirection::{self, Inbound, Outbound},
+            error::ProtocolError,
+            frame::{FrameBody, P2PFrame},
+            limits::{Admission, ConnSnapshot, EvictionPolicy, MessageLimits, admit, score},
+        },
+    };
+
+    fn conn(port: u16, direction: ConnectionDirection, score: i64, last_seen: u64) -> ConnSnapshot {
+        ConnSnapshot {
+            addr: SocketAddr::from(([10, 0, 0, 1], port)),
+            direction,
+            score,
+            last_seen,
+        }
+    }
+
+    fn limits(max_inbound: usize, max_outbound: usize, max_connections: usize) -> LimitsConfig {
+        LimitsConfig {
+            max_inbound,
+            max_outbound,
+            max_connections,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_unlimited_accepts() {
+        let conns: Vec<_> = (0..100).map(|p| conn(p, Inbound, 0, 0)).collect();
+        assert_eq!(
+            admit(&conns, Inbound, &LimitsConfig::default()),
+            Admission::Accept
+        );
+    }
+
+    #[test]
+    fn test_evicts_lowest_score_in_same_direction() {
+        let conns = vec![
+            conn(1, Inbound, 5, 100),
+            conn(2, Inbound, -3, 200),
+            conn(3, Outbound, -50, 10),
+        ];
+        // 入站已满：只在入站连接中选择，出站的更低分连接不受影响
+        assert_eq!(
+            admit(&conns, Inbound, &limits(2, 0, 0)),
+            Admission::Evict(SocketAddr::from(([10, 0, 0, 1], 2)), Inbound)
+        );
+        assert_eq!(admit(&conns, Outbound, &limits(2, 0, 0)), Admission::Accept);
+    }
+
+    #[test]
+    fn test_total_limit_evicts_any_direction() {
+        let conns = vec![conn(1, Inbound, 5, 100), conn(2, Outbound, -1, 200)];
+        assert_eq!(
+            admit(&conns, Inbound, &limits(0, 0, 2)),
+            Admission::Evict(SocketAddr::from(([10, 0, 0, 1], 2)), Outbound)
+        );
+    }
+
+    #[test]
+    fn test_lru_policy() {
+        let conns = vec![conn(1, Outbound, -10, 500), conn(2, Outbound, 10, 100)];
+        let mut l = limits(0, 2, 0);
+        l.eviction = EvictionPolicy::LeastRecentlyUsed;
+        assert_eq!(
+            admit(&conns, Outbound, &l),
+            Admission::Evict(SocketAddr::from(([10, 0, 0, 1], 2)), Outbound)
+        );
+    }
+
+    #[test]
+    fn test_reject_policy() {
+        let conns = vec![conn(1, Inbound, 0, 0)];
+        let mut l = limits(1, 0, 0);
+        l.eviction = EvictionPolicy::Reject;
+        assert_eq!(admit(&conns, Inbound, &l), Admission::Reject);
+    }
+
+    #[test]
+    fn test_score() {
+        assert_eq!(score(0, None, 0), 0);
+        assert_eq!(score(600, Some(250), 0), 8);
+        assert_eq!(score(600, None, 2), -10);
+        assert!(score(3600, Some(20), 0) > score(60, Some(20), 0));
+    }
+
+    #[test]
+    fn test_limits_config() {
+        let text = r#"
+[limits]
+max_inbound = 8
+max_outbound = 4
+eviction = "least_recently_used"
+"#;
+        let config = Config::parse(text, Path::new("node.toml")).unwrap();
+        assert_eq!(config.limits.max_inbound, 8);
+        assert_eq!(config.limits.max_outbound, 4);
+        assert_eq!(config.limits.eviction, EvictionPolicy::LeastRecentlyUsed);
+        assert_eq!(
+            Config::default().limits.eviction,
+            EvictionPolicy::LowestScore
+        );
+    }
+
+    fn signed_frame(data: Vec<u8>) -> Vec<u8> {
+        let identity = FreeWebMovementAddress::random();
+        let body = FrameBody::new(
+            1,
+            identity.to_string(),
+            identity.public_key.to_bytes(),
+            1,
+            data.len() as u32,
+            data,
+        );
+        Codec::encode(&P2PFrame::sign(body, &identity).unwrap()).unwrap()
+    }
+
+    // 解码器的上限为进程级，放在同一个测试中避免并行测试互相影响
+    #[test]
+    fn test_message_limits() {
+        let limits = MessageLimits::new(&LimitsConfig {
+            max_message_bytes: 16,
+            messages_per_second: 2,
+            ..Default::default()
+        });
+        assert!(limits.check_size(16).is_ok());
+        assert_eq!(
+            limits.check_size(17),
+            Err(ProtocolError::MessageTooLarge {
+                size: 17,
+                limit: 16
+            })
+        );
+
+        // 帧解码器按同一上限拒绝超大负载
+        assert!(P2PFrame::verify_bytes(&signed_frame(vec![0; 16])).is_ok());
+        assert!(P2PFrame::verify_bytes(&signed_frame(vec![0; 17])).is_err());
+
+        // 每个连接独立计数，窗口满一秒后重置
+        let a = SocketAddr::from(([10, 0, 0, 1], 1));
+        let b = SocketAddr::from(([10, 0, 0, 1], 2));
+        let now = Instant::now();
+        assert!(limits.allow(a, now));
+        assert!(limits.allow(a, now));
+        assert!(!limits.allow(a, now + Duration::from_millis(500)));
+        assert!(limits.allow(b, now + Duration::from_millis(500)));
+        assert!(limits.allow(a, now + Duration::from_secs(1)));
+
+        // 热更新后立即生效
+        limits.update(&LimitsConfig::default());
+        assert!(limits.check_size(1 << 20).is_ok());
+        assert!(limits.allow(a, now + Duration::from_millis(1100)));
+        assert!(P2PFrame::verify_bytes(&signed_frame(vec![0; 1024])).is_ok());
+    }
+}
@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, sync::Arc};
+
+    use aex::connection::{context::Context, global::GlobalContext};
+    use tokio::sync::Mutex;
+    use zz_p2p::{
+        connections::{PeerConnection, Transport},
+        protocols::commands::{
+            node_registry::ConnectionDirection,
+            ping::{self, PeerLatency},
+        },
+    };
+
+    fn conn(addr: &str, peer: Option<&str>) -> PeerConnection {
+        PeerConnection {
+            addr: addr.parse().unwrap(),
+            peer: peer.map(|p| p.to_string()),
+            transport: Transport::Tcp,
+            direction: ConnectionDirection::Outbound,
+            bytes_in: 0,
+            bytes_out: 0,
+            uptime_secs: 0,
+            rtt_ms: None,
+            features: vec![],
+            max_frame_size: None,
+            agent: None,
+            cipher_suite: None,
+        }
+    }
+
+    #[test]
+    fn test_matches_by_socket_ip_or_address() {
+        let c = conn("10.0.0.2:1090", Some("node-a"));
+        assert!(c.matches("10.0.0.2:1090"));
+        assert!(!c.matches("10.0.0.2:2000"));
+        // 只给 IP 时匹配该 IP 上的所有端口
+        assert!(c.matches("10.0.0.2"));
+        assert!(!c.matches("10.0.0.3"));
+        assert!(c.matches("node-a"));
+        assert!(!c.matches("node-b"));
+
+        // 握手未完成的连接只能按地址匹配
+        let pending = conn("[::1]:1090", None);
+        assert!(pending.matches("[::1]:1090"));
+        assert!(pending.matches("::1"));
+        assert!(!pending.matches("node-a"));
+    }
+
+    #[test]
+    fn test_serialized_fields() {
+        let mut c = conn("10.0.0.2:1090", Some("node-a"));
+        c.transport = Transport::HttpConnect;
+        c.direction = ConnectionDirection::Inbound;
+        c.bytes_in = 10;
+        c.bytes_out = 20;
+        c.uptime_secs = 30;
+        c.rtt_ms = Some(42);
+        c.features = vec!["lz4", "relay"];
+        c.max_frame_size = Some(65536);
+        let json = serde_json::to_value(&c).unwrap();
+        assert_eq!(json["addr"], "10.0.0.2:1090");
+        assert_eq!(json["peer"], "node-a");
+        assert_eq!(json["transport"], "http_connect");
+        assert_eq!(json["direction"], "inbound");
+        assert_eq!(json["bytes_in"], 10);
+        assert_eq!(json["bytes_out"], 20);
+        assert_eq!(json["uptime_secs"], 30);
+        assert_eq!(json["rtt_ms"], 42);
+        assert_eq!(json["features"], serde_json::json!(["lz4", "relay"]));
+        assert_eq!(json["max_frame_size"], 65536);
+        assert_eq!(Transport::HttpConnect.to_string(), "http-connect");
+    }
+
+    #[tokio::test]
+    async fn test_latency_is_dropped_with_the_connection() {
+        let addr: SocketAddr = "127.0.0.1:9100".parse().unwrap();
+        let gctx = Arc::new(GlobalContext::new("127.0.0.1:9000".parse().unwrap(), None));
+        let ctx = Arc::new(Mutex::new(Context::new(None, None, gctx.clone(), addr)));
+        ctx.lock().await.set(PeerLatency(42));
+
+        let token = gctx.manager.cancel_token.child_token();
+        let handle = tokio::spawn(async {});
+        gctx.manager
+            .add(addr, handle.abort_handle(), token, false, Some(ctx.clone()));
+        assert_eq!(ping::latency_of(&ctx).await, Some(42));
+        assert_eq!(ping::latency(&gctx, addr).await, Some(42));
+
+        // 延迟保存在连接上，连接移除后不再残留
+        gctx.manager.remove(addr, false);
+        assert_eq!(ping::latency(&gctx, addr).await, None);
+    }
+}
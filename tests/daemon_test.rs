@@ -0,0 +1,91 @@
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use tempfile::tempdir;
+    use zz_p2p::{
+        cli::Opt,
+        daemon::{PID_FILE, PidFile, log_file_path, pid_file_path, read_pid},
+        log_file::{DAEMON_LOG_FILE, RotatingFile},
+    };
+
+    #[test]
+    fn test_default_paths_follow_data_dir() {
+        let dir = tempdir().unwrap();
+        let mut opt = Opt {
+            data_dir: Some(dir.path().to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+        assert_eq!(pid_file_path(&opt), dir.path().join(PID_FILE));
+        assert_eq!(log_file_path(&opt), Some(dir.path().join(DAEMON_LOG_FILE)));
+
+        opt.log_file = Some("-".to_string());
+        assert_eq!(log_file_path(&opt), None);
+    }
+
+    #[test]
+    fn test_pid_file_removed_on_drop() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("run").join(PID_FILE);
+        {
+            let pid_file = PidFile::create(&path).unwrap();
+            assert_eq!(pid_file.pid(), std::process::id());
+            assert_eq!(read_pid(&path), Some(std::process::id()));
+        }
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_stale_pid_file_is_replaced() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(PID_FILE);
+        // 已退出的子进程号视为残留
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let stale = child.id();
+        child.wait().unwrap();
+        std::fs::write(&path, format!("{}\n", stale)).unwrap();
+
+        let _pid_file = PidFile::create(&path).unwrap();
+        assert_eq!(read_pid(&path), Some(std::process::id()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_running_instance_is_rejected() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(PID_FILE);
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        std::fs::write(&path, format!("{}\n", child.id())).unwrap();
+
+        let err = PidFile::create(&path).err().unwrap();
+        assert!(err.to_string().contains("already running"));
+        // 不属于自己的 pidfile 保持原样
+        assert_eq!(read_pid(&path), Some(child.id()));
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn test_rotating_file_keeps_bounded_history() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(DAEMON_LOG_FILE);
+        let mut file = RotatingFile::open(&path, 64, 2).unwrap();
+        for i in 0..20 {
+            writeln!(file, "line {:02} ..........................", i).unwrap();
+        }
+        file.flush().unwrap();
+
+        assert!(path.exists());
+        assert!(dir.path().join("zzp2p.log.1").exists());
+        assert!(dir.path().join("zzp2p.log.2").exists());
+        assert!(!dir.path().join("zzp2p.log.3").exists());
+        // 每次写入完整落在同一个文件中
+        let current = std::fs::read_to_string(&path).unwrap();
+        assert!(current.ends_with("line 19 ..........................\n"));
+        assert!(std::fs::metadata(&path).unwrap().len() <= 64);
+    }
+}
@@ -0,0 +1,83 @@
+#[cfg(test)]
+mod tests {
+    use aex::connection::global::GlobalContext;
+    use zz_p2p::protocols::dedup::{self, BloomFilter, DedupStats, ForwardFilter, RotatingBloom};
+
+    #[test]
+    fn test_bloom_filter_remembers_inserted_keys() {
+        let mut bloom = RotatingBloom::new(100, 0.01);
+        assert!(bloom.is_empty());
+        assert!(bloom.check_and_insert(b"relay:a:1"));
+        assert!(!bloom.check_and_insert(b"relay:a:1"));
+        assert!(bloom.check_and_insert(b"relay:a:2"));
+        assert_eq!(bloom.len(), 2);
+        assert_eq!(bloom.rotations(), 0);
+    }
+
+    #[test]
+    fn test_rotation_keeps_previous_generation() {
+        let mut bloom = RotatingBloom::new(2, 0.001);
+        assert!(bloom.check_and_insert(b"k1"));
+        assert!(bloom.check_and_insert(b"k2"));
+        // 当前代写满后轮换，k1、k2 仍在旧代中
+        assert!(bloom.check_and_insert(b"k3"));
+        assert_eq!(bloom.rotations(), 1);
+        assert!(!bloom.check_and_insert(b"k1"));
+        assert!(bloom.check_and_insert(b"k4"));
+        // 再次轮换后 k1、k2 随最旧的一代丢弃
+        assert!(bloom.check_and_insert(b"k5"));
+        assert_eq!(bloom.rotations(), 2);
+        assert!(!bloom.check_and_insert(b"k3"));
+        assert!(bloom.check_and_insert(b"k1"));
+    }
+
+    #[test]
+    fn test_false_positive_rate_is_low() {
+        let capacity = 10_000;
+        let mut bloom = RotatingBloom::new(capacity, 0.001);
+        for i in 0..capacity {
+            assert!(bloom.check_and_insert(format!("seen:{}", i).as_bytes()));
+        }
+        let false_positives = (0..capacity)
+            .filter(|i| !bloom.check_and_insert(format!("fresh:{}", i).as_bytes()))
+            .count();
+        // 目标 0.1%，留出余量
+        assert!(false_positives < capacity / 100, "{}", false_positives);
+    }
+
+    #[test]
+    fn test_bloom_filter_sizing() {
+        let small = BloomFilter::new(1_000, 0.01);
+        let large = BloomFilter::new(1_000, 0.0001);
+        assert!(small.size() >= 9_000);
+        assert!(large.size() > small.size());
+    }
+
+    #[test]
+    fn test_forward_filter_counts_suppressed() {
+        let filter = ForwardFilter::default();
+        assert!(filter.first_forward("publish:a:1:1"));
+        assert!(!filter.first_forward("publish:a:1:1"));
+        assert!(!filter.first_forward("publish:a:1:1"));
+        assert!(filter.first_forward("publish:a:2:1"));
+        assert_eq!(
+            filter.stats(),
+            DedupStats {
+                checked: 4,
+                suppressed: 2,
+                rotations: 0,
+                tracked: 2,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_filter_is_shared_through_global_context() {
+        let gctx = GlobalContext::new("127.0.0.1:0".parse().unwrap(), None);
+        assert_eq!(dedup::stats(&gctx).await, DedupStats::default());
+        assert!(dedup::first_forward(&gctx, "relay:a:1").await);
+        assert!(!dedup::first_forward(&gctx, "relay:a:1").await);
+        let stats = dedup::stats(&gctx).await;
+        assert_eq!((stats.checked, stats.suppressed), (2, 1));
+    }
+}
@@ -0,0 +1,155 @@
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+    use aex::connection::{global::GlobalContext, protocol::Protocol};
+    use tokio::{net::TcpListener, sync::RwLock};
+    use zz_p2p::{
+        config::{Config, SharedConfig},
+        dialer,
+        record::NodeRecord,
+        retry::RetryPolicy,
+    };
+
+    #[test]
+    fn test_plan_interleaves_address_families() {
+        let v4_a: SocketAddr = "10.0.0.1:1090".parse().unwrap();
+        let v4_b: SocketAddr = "10.0.0.2:1090".parse().unwrap();
+        let v6: SocketAddr = "[fd00::1]:1090".parse().unwrap();
+
+        let mut record = NodeRecord::new(v4_a);
+        record.alt_endpoints = vec![v4_b, v6, v4_a];
+
+        let order: Vec<SocketAddr> = dialer::plan(&record)
+            .into_iter()
+            .map(|a| a.endpoint)
+            .collect();
+        // IPv6 优先，随后与 IPv4 交替，重复地址被去除
+        assert_eq!(order, vec![v6, v4_a, v4_b]);
+
+        // 上次胜出的 IPv4 地址排在最前
+        record.last_dial = Some((v4_b, Protocol::Tcp));
+        let order: Vec<SocketAddr> = dialer::plan(&record)
+            .into_iter()
+            .map(|a| a.endpoint)
+            .collect();
+        assert_eq!(order, vec![v4_b, v6, v4_a]);
+    }
+
+    #[tokio::test]
+    async fn test_happy_eyeballs_picks_reachable_endpoint() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let reachable = listener.local_addr().unwrap();
+
+        // 先绑定再释放，得到一个确定无人监听的端口
+        let closed = {
+            let l = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            l.local_addr().unwrap()
+        };
+
+        let mut record = NodeRecord::new(closed);
+        record.alt_endpoints = vec![reachable];
+
+        let dialed = dialer::happy_eyeballs(&record).await.unwrap();
+        assert_eq!(dialed.attempt.endpoint, reachable);
+        // 胜出的连接原样返回，对端只看到这一次连接
+        assert_eq!(dialed.stream.peer_addr().unwrap(), reachable);
+        let (_accepted, from) = listener.accept().await.unwrap();
+        assert_eq!(from, dialed.stream.local_addr().unwrap());
+
+        let winner = dialed.attempt;
+        record.record_dial(winner.endpoint, winner.protocol);
+        assert_eq!(record.last_dial.as_ref().map(|d| d.0), Some(reachable));
+        assert_eq!(record.tries.0, 1);
+    }
+
+    #[tokio::test]
+    async fn test_happy_eyeballs_all_fail() {
+        let closed = {
+            let l = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            l.local_addr().unwrap()
+        };
+        let record = NodeRecord::new(closed);
+        assert!(dialer::happy_eyeballs(&record).await.is_err());
+    }
+
+    async fn with_network(retry: RetryPolicy, connect_ms: u64) -> Arc<GlobalContext> {
+        let mut config = Config::default();
+        config.network.retry = retry;
+        config.network.timeouts.connect_ms = connect_ms;
+        let gctx = Arc::new(GlobalContext::new("127.0.0.1:0".parse().unwrap(), None));
+        let shared: SharedConfig = Arc::new(RwLock::new(config));
+        gctx.set(shared).await;
+        gctx
+    }
+
+    #[tokio::test]
+    async fn test_dial_retries_with_network_policy() {
+        let late = {
+            let l = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            l.local_addr().unwrap()
+        };
+        let retry = RetryPolicy {
+            max_attempts: 5,
+            initial_backoff_ms: 200,
+            max_backoff_ms: 200,
+            multiplier: 1.0,
+            jitter: 0.0,
+        };
+        let gctx = with_network(retry, 1_000).await;
+
+        // 第一轮拨号被拒绝后对端才开始监听，按 `[network]` 的退避重试的下一轮成功
+        let listener = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            TcpListener::bind(late).await.unwrap()
+        });
+        let dialed = dialer::dial(&gctx, &NodeRecord::new(late), None)
+            .await
+            .unwrap();
+        assert_eq!(dialed.attempt.endpoint, late);
+        let _listener = listener.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dial_without_retry_gives_up() {
+        let closed = {
+            let l = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            l.local_addr().unwrap()
+        };
+        let retry = RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        };
+        let gctx = with_network(retry, 1_000).await;
+        assert!(
+            dialer::dial(&gctx, &NodeRecord::new(closed), None)
+                .await
+                .is_err()
+        );
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_node_connect_reuses_dialed_stream() {
+        use zz_p2p::testing::TestNode;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let peer = listener.local_addr().unwrap();
+        // 仅出站模式不受地址大小的 tiebreaker 影响，总是由本端发起
+        let mut client = TestNode::start_with(|opt| opt.client_only = true)
+            .await
+            .unwrap();
+        client.node.inner.write().upsert(peer, true);
+        client.node.connect().await;
+
+        // 竞速拨号胜出的连接直接交给连接管理器，对端只收到一个连接
+        let (_first, _) = tokio::time::timeout(Duration::from_secs(5), listener.accept())
+            .await
+            .unwrap()
+            .unwrap();
+        let second = tokio::time::timeout(Duration::from_millis(500), listener.accept()).await;
+        assert!(second.is_err(), "peer was dialed twice");
+
+        client.stop().await;
+    }
+}
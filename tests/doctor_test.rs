@@ -0,0 +1,135 @@
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, SocketAddr};
+
+    use zz_p2p::{
+        doctor::{
+            CLOCK_SKEW_FAIL_MS, CheckStatus, DoctorReport, NatType, STORAGE_PROBE_FILE,
+            check_clock, check_listeners, check_nat, check_peers, check_public_endpoint,
+            check_storage, estimate_clock_skew,
+        },
+        listener::Health,
+    };
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_listeners_check() {
+        let addrs: Vec<SocketAddr> = vec!["0.0.0.0:1090".parse().unwrap()];
+        let running = vec![("p2p".to_string(), Health::Running)];
+        let check = check_listeners(&running, &addrs);
+        assert_eq!(check.status, CheckStatus::Ok);
+        assert!(check.detail.contains("0.0.0.0:1090"));
+
+        let restarting = vec![
+            ("p2p".to_string(), Health::Running),
+            (
+                "control".to_string(),
+                Health::Restarting {
+                    attempts: 2,
+                    last_error: "address in use".to_string(),
+                },
+            ),
+        ];
+        assert_eq!(
+            check_listeners(&restarting, &addrs).status,
+            CheckStatus::Warn
+        );
+
+        let failed = vec![(
+            "p2p".to_string(),
+            Health::Failed {
+                last_error: "address in use".to_string(),
+            },
+        )];
+        let check = check_listeners(&failed, &addrs);
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(check.detail.contains("address in use"));
+        assert_eq!(check_listeners(&[], &addrs).status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn test_nat_classification() {
+        assert_eq!(NatType::classify(true, true, true), NatType::Open);
+        assert_eq!(NatType::classify(false, true, true), NatType::Mapped);
+        assert_eq!(NatType::classify(false, false, true), NatType::Nat);
+        assert_eq!(NatType::classify(false, false, false), NatType::Unknown);
+        assert_eq!(check_nat(NatType::Mapped).status, CheckStatus::Ok);
+        assert_eq!(check_nat(NatType::Nat).status, CheckStatus::Warn);
+    }
+
+    #[test]
+    fn test_public_endpoint_check() {
+        assert_eq!(
+            check_public_endpoint(&[], &[], &[]).status,
+            CheckStatus::Warn
+        );
+        let check = check_public_endpoint(&[], &[ip("203.0.113.7")], &[ip("203.0.113.7")]);
+        assert_eq!(check.status, CheckStatus::Ok);
+        assert_eq!(check.detail, "203.0.113.7");
+    }
+
+    #[test]
+    fn test_peers_check() {
+        assert_eq!(check_peers(0, 3).status, CheckStatus::Fail);
+        assert_eq!(check_peers(1, 3).status, CheckStatus::Warn);
+        assert_eq!(check_peers(3, 3).status, CheckStatus::Ok);
+        assert_eq!(check_peers(0, 0).status, CheckStatus::Ok);
+    }
+
+    #[test]
+    fn test_clock_check() {
+        assert_eq!(estimate_clock_skew(&[]), None);
+        assert_eq!(estimate_clock_skew(&[40, -10, 5_000, 20, 30]), Some(30));
+
+        assert_eq!(check_clock(None, 0).status, CheckStatus::Warn);
+        assert_eq!(check_clock(Some(250), 3).status, CheckStatus::Ok);
+        assert_eq!(check_clock(Some(-45_000), 3).status, CheckStatus::Warn);
+        assert_eq!(
+            check_clock(Some(CLOCK_SKEW_FAIL_MS + 1), 3).status,
+            CheckStatus::Fail
+        );
+    }
+
+    #[tokio::test]
+    async fn test_storage_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let check = check_storage(dir.path()).await;
+        assert_eq!(check.status, CheckStatus::Ok);
+        assert!(!dir.path().join(STORAGE_PROBE_FILE).exists());
+
+        let file = dir.path().join("file");
+        std::fs::write(&file, b"x").unwrap();
+        // 数据目录是一个普通文件时无法写入
+        assert_eq!(check_storage(&file).await.status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn test_report_status() {
+        let report = DoctorReport::new(
+            NatType::Open,
+            vec![check_peers(3, 3), check_clock(Some(0), 1)],
+        );
+        assert_eq!(report.status, CheckStatus::Ok);
+        assert!(report.is_healthy());
+
+        let report = DoctorReport::new(
+            NatType::Nat,
+            vec![check_peers(1, 3), check_nat(NatType::Nat)],
+        );
+        assert_eq!(report.status, CheckStatus::Warn);
+        assert!(report.is_healthy());
+
+        let report = DoctorReport::new(NatType::Unknown, vec![check_peers(0, 3)]);
+        assert_eq!(report.status, CheckStatus::Fail);
+        assert!(!report.is_healthy());
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["status"], "fail");
+        assert_eq!(json["nat"], "unknown");
+        assert_eq!(json["checks"][0]["name"], "peers");
+        assert_eq!(format!("{:<4}|", CheckStatus::Ok), "ok  |");
+    }
+}
@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, net::SocketAddr};
+
+    use chrono::{Duration, Utc};
+    use zz_p2p::record::{NodeRecord, NodeRegistry};
+
+    fn v4() -> SocketAddr {
+        "203.0.113.5:10086".parse().unwrap()
+    }
+
+    fn v6() -> SocketAddr {
+        "[2001:db8::5]:10086".parse().unwrap()
+    }
+
+    #[test]
+    fn test_bind_identity_merges_records_of_same_node() {
+        let mut registry = NodeRegistry::default();
+        registry.upsert(v4(), true);
+        registry.upsert(v4(), false);
+        registry.upsert(v6(), true);
+        assert!(registry.bind_identity(v4(), "node-a"));
+        assert_eq!(registry.nodes.len(), 2);
+
+        // 第二个地址归入同一节点后两条记录合并
+        assert!(registry.bind_identity(v6(), "node-a"));
+        assert_eq!(registry.nodes.len(), 1);
+        let record = registry.by_node("node-a").unwrap();
+        assert!(record.has_endpoint(v4()) && record.has_endpoint(v6()));
+        assert_eq!(record.tries, (2, 1));
+        assert_eq!(record.all_endpoints().len(), 2);
+
+        // 重复绑定不再变化
+        assert!(!registry.bind_identity(v6(), "node-a"));
+    }
+
+    #[test]
+    fn test_unknown_endpoint_joins_known_node() {
+        let mut registry = NodeRegistry::default();
+        // 不在列表中、节点也未知时忽略
+        assert!(!registry.bind_identity(v6(), "node-a"));
+        assert!(registry.nodes.is_empty());
+
+        registry.add(v4(), false);
+        registry.bind_identity(v4(), "node-a");
+        assert!(registry.bind_identity(v6(), "node-a"));
+        assert_eq!(registry.nodes.len(), 1);
+        assert_eq!(registry.get(v6()).unwrap().endpoint, v4());
+    }
+
+    #[test]
+    fn test_operations_by_alternate_endpoint_apply_to_node() {
+        let mut registry = NodeRegistry::default();
+        registry.add(v4(), false);
+        registry.bind_identity(v4(), "node-a");
+        registry.bind_identity(v6(), "node-a");
+
+        // 备用地址上的拨号结果记入同一个节点
+        registry.upsert(v6(), true);
+        assert_eq!(registry.nodes.len(), 1);
+        assert_eq!(registry.get(v4()).unwrap().tries, (1, 0));
+
+        assert!(registry.set_pinned(v6(), true));
+        assert!(registry.get(v4()).unwrap().pinned);
+        assert_eq!(registry.get_available_nodes().len(), 1);
+
+        assert!(registry.remove(v6()));
+        assert!(registry.get(v4()).is_none());
+    }
+
+    #[test]
+    fn test_regroup_on_load() {
+        let old = Utc::now() - Duration::hours(2);
+        let mut a = NodeRecord::new(v4());
+        a.node_address = Some("node-a".to_string());
+        a.last_seen = old;
+        a.tries = (3, 1);
+        let mut b = NodeRecord::new(v6());
+        b.node_address = Some("node-a".to_string());
+        b.tries = (1, 0);
+        // 没有节点地址、但已是其它记录备用地址的旧记录
+        let alt: SocketAddr = "198.51.100.7:10086".parse().unwrap();
+        b.alt_endpoints = vec![alt];
+        let dup = NodeRecord::new(alt);
+        let other = NodeRecord::new("198.51.100.8:10086".parse().unwrap());
+
+        let registry = NodeRegistry::new(HashSet::from([a, b, dup, other]));
+        assert_eq!(registry.nodes.len(), 2);
+        let record = registry.by_node("node-a").unwrap();
+        // 最近见过的记录作为主记录
+        assert_eq!(record.endpoint, v6());
+        assert!(record.has_endpoint(v4()) && record.has_endpoint(alt));
+        assert_eq!(record.tries, (4, 1));
+
+        // 旧记录没有该字段
+        let mut legacy = serde_json::to_value(record).unwrap();
+        legacy.as_object_mut().unwrap().remove("node_address");
+        let legacy: NodeRecord = serde_json::from_value(legacy).unwrap();
+        assert!(legacy.node_address.is_none());
+    }
+}
@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use aex::connection::scope::NetworkScope;
+    use zz_p2p::endpoint_verifier::{
+        self, EndpointVerifier, VERIFY_FRESHNESS_SECS, VERIFY_INTERVAL_SECS,
+    };
+    use zz_p2p::protocols::commands::node_registry::NodeRegistry;
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([10, 0, 0, 1], port))
+    }
+
+    #[test]
+    fn test_verified_until_freshness_window_expires() {
+        let v = EndpointVerifier::new();
+        v.mark_verified(addr(1), 1000);
+        assert!(v.is_verified(&addr(1), 1000 + VERIFY_FRESHNESS_SECS));
+        assert!(!v.is_verified(&addr(1), 1001 + VERIFY_FRESHNESS_SECS));
+        assert!(!v.is_verified(&addr(2), 1000));
+    }
+
+    #[test]
+    fn test_failure_keeps_last_verification() {
+        let v = EndpointVerifier::new();
+        v.mark_verified(addr(1), 1000);
+        v.mark_failed(addr(1), 1100);
+        v.mark_failed(addr(1), 1200);
+        let status = v.status(&addr(1)).unwrap();
+        assert_eq!(status.failures, 2);
+        assert_eq!(status.verified_at, Some(1000));
+        assert_eq!(status.last_attempt, 1200);
+
+        v.mark_verified(addr(1), 1300);
+        assert_eq!(v.status(&addr(1)).unwrap().failures, 0);
+    }
+
+    #[test]
+    fn test_due_respects_interval() {
+        let v = EndpointVerifier::new();
+        v.mark_failed(addr(1), 1000);
+        let candidates = [addr(1), addr(2)];
+        assert_eq!(v.due(&candidates, 1000 + 1), vec![addr(2)]);
+        assert_eq!(
+            v.due(&candidates, 1000 + VERIFY_INTERVAL_SECS),
+            vec![addr(1), addr(2)]
+        );
+    }
+
+    #[test]
+    fn test_retain_prune_and_counts() {
+        let v = EndpointVerifier::new();
+        v.mark_verified(addr(1), 1000);
+        v.mark_failed(addr(2), 1000);
+        let seeds = vec![(addr(1), "a".to_string()), (addr(2), "b".to_string())];
+        assert_eq!(
+            v.retain_verified(seeds, 1000),
+            vec![(addr(1), "a".to_string())]
+        );
+        assert_eq!(v.counts(1000), (1, 1));
+
+        v.prune(&[addr(2)]);
+        assert_eq!(v.counts(1000), (0, 1));
+    }
+
+    #[test]
+    fn test_registry_gossips_only_verified_seeds() {
+        let registry = NodeRegistry::new();
+        registry.register("a".to_string(), addr(1), NetworkScope::Intranet);
+        registry.register("b".to_string(), addr(2), NetworkScope::Intranet);
+        assert_eq!(registry.get_all_seeds().len(), 2);
+        assert!(registry.get_gossip_seeds().is_empty());
+
+        registry
+            .endpoints()
+            .mark_verified(addr(2), endpoint_verifier::now_secs());
+        assert_eq!(
+            registry.get_gossip_seeds(),
+            vec![(addr(2), "b".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_probe() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = listener.local_addr().unwrap();
+        assert!(endpoint_verifier::probe(open).await);
+
+        drop(listener);
+        assert!(!endpoint_verifier::probe(open).await);
+    }
+}
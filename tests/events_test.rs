@@ -0,0 +1,47 @@
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, sync::Arc};
+
+    use aex::connection::global::GlobalContext;
+    use zz_p2p::{
+        events::{self, NodeEvent},
+        journal::{self, Event},
+    };
+
+    fn create_ctx() -> Arc<GlobalContext> {
+        let addr = "127.0.0.1:1080".parse::<SocketAddr>().unwrap();
+        Arc::new(GlobalContext::new(addr, None))
+    }
+
+    #[tokio::test]
+    async fn test_journal_events_reach_subscribers() {
+        let gctx = create_ctx();
+        let bus = events::new_bus();
+        gctx.set(bus.clone()).await;
+        let mut rx = bus.subscribe();
+
+        // 未启用事件日志时同样广播
+        journal::record_disconnect(&gctx, Some("peer-a"), "10.0.0.2:1".parse().unwrap(), "eof")
+            .await;
+
+        match rx.recv().await.unwrap() {
+            NodeEvent::Journal(Event::PeerDisconnected { peer, reason, .. }) => {
+                assert_eq!(peer.as_deref(), Some("peer-a"));
+                assert_eq!(reason, "eof");
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_publish_without_bus_is_noop() {
+        let gctx = create_ctx();
+        events::publish(
+            &gctx,
+            NodeEvent::Journal(Event::Stopped {
+                address: "me".to_string(),
+            }),
+        )
+        .await;
+    }
+}
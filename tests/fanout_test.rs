@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod tests {
+    use zz_p2p::{
+        fanout::{FanoutConfig, FanoutOverride, HopMetrics, entity_key, select},
+        protocols::command::Entity,
+    };
+
+    fn metrics(success: f64, rtt_ms: Option<f64>) -> HopMetrics {
+        HopMetrics { success, rtt_ms }
+    }
+
+    #[test]
+    fn test_small_frames_are_not_limited() {
+        let config = FanoutConfig {
+            threshold_bytes: 1000,
+            max_hops: 2,
+            ..Default::default()
+        };
+        assert_eq!(config.limit(Some(Entity::File), 1000), None);
+        assert_eq!(config.limit(Some(Entity::File), 1001), Some(2));
+        assert_eq!(config.limit(None, 5000), Some(2));
+
+        let unlimited = FanoutConfig {
+            max_hops: 0,
+            ..config
+        };
+        assert_eq!(unlimited.limit(None, usize::MAX), None);
+    }
+
+    #[test]
+    fn test_entity_overrides() {
+        let mut config = FanoutConfig {
+            threshold_bytes: 1000,
+            max_hops: 3,
+            ..Default::default()
+        };
+        config.entities.insert(
+            entity_key(Entity::File),
+            FanoutOverride {
+                threshold_bytes: Some(100),
+                max_hops: Some(1),
+            },
+        );
+        config.entities.insert(
+            "message".to_string(),
+            FanoutOverride {
+                max_hops: Some(0),
+                ..Default::default()
+            },
+        );
+        assert_eq!(entity_key(Entity::File), "file");
+        assert_eq!(config.limit(Some(Entity::File), 500), Some(1));
+        assert_eq!(config.limit(Some(Entity::Message), 5000), None);
+        assert_eq!(config.limit(Some(Entity::Stream), 500), None);
+        assert_eq!(config.limit(Some(Entity::Stream), 5000), Some(3));
+    }
+
+    #[test]
+    fn test_select_prefers_reliable_then_fast_peers() {
+        let candidates = vec![
+            ("unmeasured", metrics(1.0, None)),
+            ("slow", metrics(1.0, Some(300.0))),
+            ("flaky", metrics(0.5, Some(10.0))),
+            ("fast", metrics(1.0, Some(20.0))),
+        ];
+        assert_eq!(select(candidates.clone(), 2), vec!["fast", "slow"]);
+        assert_eq!(
+            select(candidates, 10),
+            vec!["fast", "slow", "unmeasured", "flaky"]
+        );
+        assert!(select(Vec::<(u8, HopMetrics)>::new(), 3).is_empty());
+    }
+
+    #[test]
+    fn test_config_from_toml() {
+        let config: FanoutConfig = toml::from_str(
+            r#"
+            max_hops = 4
+
+            [entities.file]
+            threshold_bytes = 16384
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.max_hops, 4);
+        assert_eq!(
+            config.threshold_bytes,
+            FanoutConfig::default().threshold_bytes
+        );
+        assert_eq!(config.limit(Some(Entity::File), 20_000), Some(4));
+        assert_eq!(config.limit(Some(Entity::Message), 20_000), None);
+    }
+}
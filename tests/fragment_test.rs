@@ -0,0 +1,150 @@
+#[cfg(test)]
+mod tests {
+    use aex::tcp::types::{Codec, Frame};
+    use zz_account::address::FreeWebMovementAddress;
+    use zz_p2p::protocols::{
+        command::{Action, Entity, P2PCommand},
+        commands::fragment::{
+            FRAGMENT_CHUNK_SIZE, FRAGMENT_MAX_PENDING_PER_PEER, FRAGMENT_TIMEOUT_MS,
+            FragmentCommand, Reassembler, split,
+        },
+        frame::P2PFrame,
+        version::CURRENT_PROTOCOL_VERSION,
+        wire_format::WireFormat,
+    };
+
+    fn fragment(message_id: u64, index: u32, total: u32, data: &[u8]) -> FragmentCommand {
+        FragmentCommand {
+            message_id,
+            index,
+            total,
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_reassemble_out_of_order() {
+        let mut r = Reassembler::default();
+        assert_eq!(
+            r.accept("alice", fragment(1, 2, 3, b"ef"), 0).unwrap(),
+            None
+        );
+        assert_eq!(
+            r.accept("alice", fragment(1, 0, 3, b"ab"), 0).unwrap(),
+            None
+        );
+        assert_eq!(r.pending(), 1);
+        assert_eq!(r.buffered_bytes(), 4);
+        // 重复分片被忽略
+        assert_eq!(
+            r.accept("alice", fragment(1, 0, 3, b"ab"), 0).unwrap(),
+            None
+        );
+        assert_eq!(r.buffered_bytes(), 4);
+
+        let data = r.accept("alice", fragment(1, 1, 3, b"cd"), 0).unwrap();
+        assert_eq!(data.as_deref(), Some(&b"abcdef"[..]));
+        assert_eq!(r.pending(), 0);
+        assert_eq!(r.buffered_bytes(), 0);
+    }
+
+    #[test]
+    fn test_same_id_from_different_senders() {
+        let mut r = Reassembler::default();
+        r.accept("alice", fragment(7, 0, 2, b"a"), 0).unwrap();
+        r.accept("bob", fragment(7, 0, 2, b"b"), 0).unwrap();
+        let data = r.accept("bob", fragment(7, 1, 2, b"B"), 0).unwrap();
+        assert_eq!(data.as_deref(), Some(&b"bB"[..]));
+        assert_eq!(r.pending(), 1);
+    }
+
+    #[test]
+    fn test_timeout_discards_partial() {
+        let mut r = Reassembler::default();
+        r.accept("alice", fragment(1, 0, 2, b"ab"), 0).unwrap();
+        assert_eq!(r.expire(FRAGMENT_TIMEOUT_MS - 1), 0);
+        assert_eq!(r.expire(FRAGMENT_TIMEOUT_MS), 1);
+        assert_eq!(r.buffered_bytes(), 0);
+
+        // 超时后到达的剩余分片开启一条新的未完成消息
+        assert_eq!(
+            r.accept("alice", fragment(1, 1, 2, b"cd"), FRAGMENT_TIMEOUT_MS)
+                .unwrap(),
+            None
+        );
+        assert_eq!(r.pending(), 1);
+    }
+
+    #[test]
+    fn test_rejects_invalid_fragments() {
+        let mut r = Reassembler::default();
+        assert!(r.accept("alice", fragment(1, 0, 0, b"x"), 0).is_err());
+        assert!(r.accept("alice", fragment(1, 2, 2, b"x"), 0).is_err());
+        assert!(
+            r.accept("alice", fragment(1, 0, u32::MAX, b"x"), 0)
+                .is_err()
+        );
+        let oversized = vec![0u8; FRAGMENT_CHUNK_SIZE + 1];
+        assert!(r.accept("alice", fragment(1, 0, 2, &oversized), 0).is_err());
+
+        // 同一消息的 total 前后不一致时整条丢弃
+        r.accept("alice", fragment(2, 0, 3, b"x"), 0).unwrap();
+        assert!(r.accept("alice", fragment(2, 1, 4, b"y"), 0).is_err());
+        assert_eq!(r.pending(), 0);
+        assert_eq!(r.buffered_bytes(), 0);
+    }
+
+    #[test]
+    fn test_pending_limit_per_sender() {
+        let mut r = Reassembler::default();
+        for id in 0..FRAGMENT_MAX_PENDING_PER_PEER as u64 {
+            r.accept("alice", fragment(id, 0, 2, b"x"), 0).unwrap();
+        }
+        assert!(r.accept("alice", fragment(999, 0, 2, b"x"), 0).is_err());
+        // 其他发送方不受影响
+        assert!(r.accept("bob", fragment(999, 0, 2, b"x"), 0).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_split_round_trip() {
+        let address = FreeWebMovementAddress::random();
+        let payload = vec![0xA5u8; FRAGMENT_CHUNK_SIZE * 2 + 100];
+        let cmd = P2PCommand::new(Entity::Node, Action::Ping, payload);
+        let inner = P2PFrame::build(&address, cmd.clone(), CURRENT_PROTOCOL_VERSION)
+            .await
+            .unwrap();
+        let bytes = Codec::encode(&inner).unwrap();
+
+        for format in [WireFormat::Bincode, WireFormat::Cbor] {
+            let frames = split(
+                &address,
+                &bytes,
+                FRAGMENT_CHUNK_SIZE,
+                42,
+                CURRENT_PROTOCOL_VERSION,
+                format,
+            )
+            .await
+            .unwrap();
+            assert_eq!(frames.len(), bytes.len().div_ceil(FRAGMENT_CHUNK_SIZE));
+
+            let mut r = Reassembler::default();
+            let mut assembled = None;
+            for frame in frames.iter().rev() {
+                // 分片帧经过线路编码后仍能通过签名校验
+                let wire = Codec::encode(frame).unwrap();
+                let frame = P2PFrame::verify_bytes(&wire).unwrap();
+                let fcmd: P2PCommand = Codec::decode(frame.command().unwrap()).unwrap();
+                assert_eq!(fcmd.action, Action::Fragment);
+                let fragment: FragmentCommand = frame.decode_payload(&fcmd.data).unwrap();
+                assembled = r.accept(&frame.body.address, fragment, 0).unwrap();
+            }
+
+            let assembled = assembled.expect("all fragments received");
+            assert_eq!(assembled, bytes);
+            let restored = P2PFrame::verify_bytes(&assembled).unwrap();
+            let restored_cmd: P2PCommand = Codec::decode(restored.command().unwrap()).unwrap();
+            assert_eq!(restored_cmd, cmd);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+#[cfg(test)]
+mod tests {
+    use aex::tcp::types::Codec;
+    use zz_account::address::FreeWebMovementAddress;
+    use zz_p2p::events::NodeEvent;
+    use zz_p2p::protocols::{
+        capabilities::{CAP_GROUPS, LOCAL_FEATURES, feature_names},
+        command::Action,
+        commands::group::{
+            Group, GroupInviteCommand, GroupMember, GroupMessageCommand, GroupState, GroupStore,
+            IncomingGroupMessage, destination, open, pairwise_key, seal,
+        },
+        error::ProtocolError,
+    };
+    use zz_p2p::webhook;
+
+    fn member(identity: &FreeWebMovementAddress, store: &GroupStore) -> GroupMember {
+        GroupMember {
+            address: identity.to_string(),
+            public_key: identity.public_key.to_bytes().to_vec(),
+            agreement_key: store.agreement_key(),
+        }
+    }
+
+    /// 群主在 `state` 的基础上签发下一个版本
+    fn next(
+        state: &GroupState,
+        owner: &FreeWebMovementAddress,
+        members: Vec<GroupMember>,
+    ) -> GroupState {
+        GroupState {
+            version: state.version + 1,
+            members,
+            ..state.clone()
+        }
+        .signed(owner)
+    }
+
+    fn invite(store: &mut GroupStore, state: &GroupState, receiver: &str) {
+        store.invites.insert(
+            state.group_id.clone(),
+            GroupInviteCommand {
+                sender: state.owner.clone(),
+                receiver: receiver.to_string(),
+                group_id: state.group_id.clone(),
+                name: state.name.clone(),
+            },
+        );
+    }
+
+    #[test]
+    fn test_state_signature() {
+        let owner = FreeWebMovementAddress::random();
+        let mut store = GroupStore::default();
+        let state = store.create(&owner, "friends").unwrap();
+        assert_eq!(state.version, 1);
+        assert_eq!(state.members.len(), 1);
+        state.verify().unwrap();
+
+        // 篡改成员列表后签名失效
+        let mut tampered = state.clone();
+        tampered.members.push(member(
+            &FreeWebMovementAddress::random(),
+            &GroupStore::default(),
+        ));
+        assert!(matches!(
+            tampered.verify(),
+            Err(ProtocolError::BadSignature)
+        ));
+
+        // 其它身份签发的状态不是群主签发的后继
+        let other = FreeWebMovementAddress::random();
+        let forged = GroupState {
+            owner_public_key: other.public_key.to_bytes().to_vec(),
+            ..next(&state, &other, state.members.clone())
+        }
+        .signed(&other);
+        forged.verify().unwrap();
+        assert!(state.check_successor(&forged).is_err());
+
+        assert!(store.create(&owner, "").is_err());
+        assert_eq!(store.resolve("friends").unwrap(), state.group_id);
+        assert!(store.resolve("nobody").is_err());
+    }
+
+    #[test]
+    fn test_pairwise_key_and_sealing() {
+        let alice = GroupStore::default();
+        let bob = GroupStore::default();
+        let ab = pairwise_key(&alice.agreement_secret, &bob.agreement_key(), "g1").unwrap();
+        let ba = pairwise_key(&bob.agreement_secret, &alice.agreement_key(), "g1").unwrap();
+        assert_eq!(ab, ba);
+        // 不同的群派生不同的密钥
+        assert_ne!(
+            ab,
+            pairwise_key(&alice.agreement_secret, &bob.agreement_key(), "g2").unwrap()
+        );
+        // 低阶点被拒绝
+        assert!(pairwise_key(&alice.agreement_secret, &[0u8; 32], "g1").is_err());
+
+        let (nonce, ciphertext) = seal(&ab, b"aad", b"hello").unwrap();
+        assert_eq!(open(&ba, &nonce, b"aad", &ciphertext).unwrap(), b"hello");
+        assert!(open(&ba, &nonce, b"other", &ciphertext).is_err());
+        let mut flipped = ciphertext.clone();
+        flipped[0] ^= 1;
+        assert!(open(&ba, &nonce, b"aad", &flipped).is_err());
+    }
+
+    #[test]
+    fn test_membership_updates() {
+        let owner = FreeWebMovementAddress::random();
+        let bob = FreeWebMovementAddress::random();
+        let mut owner_store = GroupStore::default();
+        let mut bob_store = GroupStore::default();
+        let local = bob.to_string();
+        let v1 = owner_store.create(&owner, "team").unwrap();
+
+        // 未受邀的群不接受
+        let v2 = next(
+            &v1,
+            &owner,
+            vec![v1.members[0].clone(), member(&bob, &bob_store)],
+        );
+        assert!(bob_store.accept(v2.clone(), &local).is_err());
+
+        invite(&mut bob_store, &v1, &local);
+        assert!(bob_store.accept(v1.clone(), &local).is_err());
+        assert!(bob_store.accept(v2.clone(), &local).unwrap());
+        assert!(bob_store.invites.is_empty());
+        assert_eq!(bob_store.groups[&v1.group_id].state, v2);
+
+        // 重放旧版本被拒绝
+        assert!(bob_store.accept(v2.clone(), &local).is_err());
+
+        // 被移除后删除本地的群
+        let v3 = next(&v2, &owner, vec![v1.members[0].clone()]);
+        assert!(!bob_store.accept(v3, &local).unwrap());
+        assert!(bob_store.groups.is_empty());
+    }
+
+    #[test]
+    fn test_removal_rotates_sender_key() {
+        let owner = FreeWebMovementAddress::random();
+        let bob = FreeWebMovementAddress::random();
+        let carol = FreeWebMovementAddress::random();
+        let mut store = GroupStore::default();
+        let v1 = store.create(&owner, "team").unwrap();
+        let members = vec![
+            v1.members[0].clone(),
+            member(&bob, &GroupStore::default()),
+            member(&carol, &GroupStore::default()),
+        ];
+        let mut group = Group::new(v1.clone());
+        assert!(group.apply(next(&v1, &owner, members.clone())).is_empty());
+        assert_eq!(group.sender_key.generation, 0);
+
+        let local = owner.to_string();
+        assert_eq!(group.pending_distribution(&local).len(), 2);
+        group.distributed.insert(bob.to_string());
+        group.distributed.insert(carol.to_string());
+        assert!(group.pending_distribution(&local).is_empty());
+
+        let key = group.sender_key.clone();
+        let v3 = next(&group.state, &owner, members[..2].to_vec());
+        assert_eq!(group.apply(v3), vec![carol.to_string()]);
+        assert_eq!(group.sender_key.generation, 1);
+        assert_ne!(group.sender_key.key, key.key);
+        // 轮换后需要重新分发给剩下的成员
+        let pending = group.pending_distribution(&local);
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].address, bob.to_string());
+    }
+
+    #[test]
+    fn test_destination_and_events() {
+        let cmd = GroupMessageCommand {
+            sender: "alice".to_string(),
+            receiver: "bob".to_string(),
+            group_id: "g1".to_string(),
+            generation: 0,
+            nonce: [0u8; 12],
+            ciphertext: vec![1, 2, 3],
+            signature: vec![],
+        };
+        let data = Codec::encode(&cmd).unwrap();
+        assert_eq!(
+            destination(Action::GroupMessage, &data),
+            Some("bob".to_string())
+        );
+        assert_eq!(destination(Action::SendText, &data), None);
+
+        assert_ne!(LOCAL_FEATURES & CAP_GROUPS, 0);
+        assert_eq!(feature_names(CAP_GROUPS), vec!["groups"]);
+
+        let event = NodeEvent::GroupMessage(IncomingGroupMessage {
+            group_id: "g1".to_string(),
+            group: "team".to_string(),
+            from: "alice".to_string(),
+            content: "hi".to_string(),
+            timestamp: 1,
+            annotations: Default::default(),
+        });
+        let (name, body) = webhook::payload(&event, "node", 2);
+        assert_eq!(name, "group.message");
+        assert_eq!(body["data"]["group"], "team");
+        assert_eq!(body["data"]["content"], "hi");
+    }
+}
@@ -0,0 +1,100 @@
+#[cfg(test)]
+mod tests {
+    use zz_p2p::protocols::{
+        capabilities::{CAP_HTTP_TUNNEL, LOCAL_FEATURES, feature_names},
+        commands::http_tunnel::{
+            ExposedService, HttpRequestCommand, HttpResponseCommand, is_hop_by_hop, split_peer_path,
+        },
+    };
+
+    fn request() -> HttpRequestCommand {
+        HttpRequestCommand {
+            sender: "alice".into(),
+            receiver: "bob".into(),
+            request_id: 7,
+            method: "GET".into(),
+            path: "/".into(),
+            headers: vec![],
+            body: vec![],
+        }
+    }
+
+    #[test]
+    fn test_split_peer_path() {
+        assert_eq!(
+            split_peer_path("/peer", "/peer/bob/app/index.html?x=1"),
+            Some(("bob".into(), "/app/index.html?x=1".into()))
+        );
+        assert_eq!(
+            split_peer_path("/peer/", "/peer/bob"),
+            Some(("bob".into(), "/".into()))
+        );
+        assert_eq!(
+            split_peer_path("/peer", "/peer/bob?x=1"),
+            Some(("bob".into(), "/?x=1".into()))
+        );
+        assert_eq!(split_peer_path("/peer", "/peer/"), None);
+        assert_eq!(split_peer_path("/peer", "/peers/bob"), None);
+        assert_eq!(split_peer_path("/peer", "/web/index.html"), None);
+    }
+
+    #[test]
+    fn test_target_url_joins_base_path() {
+        let service: ExposedService = "http://127.0.0.1:8080/app/".parse().unwrap();
+        assert_eq!(
+            service.target_url("/api/items?page=2").unwrap().as_str(),
+            "http://127.0.0.1:8080/app/api/items?page=2"
+        );
+        let root: ExposedService = "http://127.0.0.1:8080".parse().unwrap();
+        assert_eq!(
+            root.target_url("/").unwrap().as_str(),
+            "http://127.0.0.1:8080/"
+        );
+    }
+
+    #[test]
+    fn test_target_url_rejects_parent_segments() {
+        let service: ExposedService = "http://127.0.0.1:8080/app".parse().unwrap();
+        assert!(service.target_url("/../secret").is_none());
+        assert!(service.target_url("/a/%2e%2e/secret").is_none());
+        assert!(service.target_url("/a/..%5csecret").is_none());
+        assert!(service.target_url("/a/..b/ok").is_some());
+    }
+
+    #[test]
+    fn test_exposed_service_requires_http_url() {
+        assert!("ftp://127.0.0.1/".parse::<ExposedService>().is_err());
+        assert!("127.0.0.1:8080".parse::<ExposedService>().is_err());
+        assert!(
+            "http://127.0.0.1:8080/?x=1"
+                .parse::<ExposedService>()
+                .is_err()
+        );
+        assert!("https://example.com".parse::<ExposedService>().is_ok());
+    }
+
+    #[test]
+    fn test_hop_by_hop_headers() {
+        assert!(is_hop_by_hop("Connection"));
+        assert!(is_hop_by_hop("transfer-encoding"));
+        assert!(is_hop_by_hop("Host"));
+        assert!(!is_hop_by_hop("Content-Type"));
+        assert!(!is_hop_by_hop("ETag"));
+    }
+
+    #[test]
+    fn test_error_response_goes_back_to_sender() {
+        let response = HttpResponseCommand::error(&request(), 404, "No service exposed");
+        assert_eq!(response.sender, "bob");
+        assert_eq!(response.receiver, "alice");
+        assert_eq!(response.request_id, 7);
+        assert_eq!(response.status, 404);
+        assert_eq!(response.body, b"No service exposed");
+    }
+
+    #[test]
+    fn test_http_tunnel_capability() {
+        assert_ne!(LOCAL_FEATURES & CAP_HTTP_TUNNEL, 0);
+        assert_eq!(feature_names(CAP_HTTP_TUNNEL), vec!["http-tunnel"]);
+    }
+}
@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, sync::Arc};
+
+    use aex::crypto::session_key_manager::PairedSessionKey;
+    use tokio::sync::Mutex;
+    use zz_account::address::FreeWebMovementAddress;
+    use zz_p2p::identities::{DEFAULT_IDENTITY, Identities};
+
+    fn identities() -> (Identities, FreeWebMovementAddress) {
+        let primary = FreeWebMovementAddress::random();
+        let keys = Arc::new(Mutex::new(PairedSessionKey::new(16)));
+        (Identities::new(primary.clone(), keys), primary)
+    }
+
+    #[test]
+    fn test_primary_is_active_by_default() {
+        let (ids, primary) = identities();
+        let active = ids.active();
+        assert_eq!(active.name, DEFAULT_IDENTITY);
+        assert!(active.is_primary());
+        assert_eq!(active.address.to_string(), primary.to_string());
+        assert!(ids.is_local(&primary.to_string()));
+        assert!(ids.stored().is_empty());
+    }
+
+    #[test]
+    fn test_generate_and_use() {
+        let (ids, _) = identities();
+        let work = ids.generate("work").unwrap();
+        assert!(!work.is_primary());
+        assert!(ids.is_local(&work.address.to_string()));
+
+        ids.set_active("work").unwrap();
+        assert_eq!(ids.active().name, "work");
+        // 按地址同样可以找到
+        assert_eq!(ids.get(&work.address.to_string()).unwrap().name, "work");
+        assert!(ids.set_active("missing").is_err());
+        assert_eq!(ids.active().name, "work");
+    }
+
+    #[test]
+    fn test_rejects_duplicates_and_bad_names() {
+        let (ids, primary) = identities();
+        ids.generate("work").unwrap();
+        assert!(ids.generate("work").is_err());
+        assert!(ids.add("again", primary).is_err());
+        assert!(ids.generate("").is_err());
+        assert!(ids.generate("two words").is_err());
+    }
+
+    #[test]
+    fn test_remove_falls_back_to_primary() {
+        let (ids, _) = identities();
+        let work = ids.generate("work").unwrap();
+        ids.set_active("work").unwrap();
+        ids.remove("work").unwrap();
+        assert_eq!(ids.active().name, DEFAULT_IDENTITY);
+        assert!(!ids.is_local(&work.address.to_string()));
+        assert!(ids.remove(DEFAULT_IDENTITY).is_err());
+        assert!(ids.remove("work").is_err());
+    }
+
+    #[test]
+    fn test_stored_roundtrip() {
+        let (ids, _) = identities();
+        ids.generate("a").unwrap();
+        ids.generate("b").unwrap();
+        let stored: BTreeMap<String, FreeWebMovementAddress> = ids.stored();
+        assert_eq!(stored.len(), 2);
+
+        let (restored, _) = identities();
+        restored.load(stored);
+        assert_eq!(restored.list().len(), 3);
+        assert!(restored.get("a").is_some());
+        assert!(restored.get("b").is_some());
+    }
+}
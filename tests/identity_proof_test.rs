@@ -0,0 +1,185 @@
+#[cfg(test)]
+mod tests {
+    use dashmap::DashMap;
+    use zz_account::address::FreeWebMovementAddress;
+    use zz_p2p::protocols::{
+        command::{Action, Entity, P2PCommand},
+        commands::identity::{
+            PendingChallenge, allowed_unverified, bind, new_challenge, prove, sent_by, verify_proof,
+        },
+        error::ProtocolError,
+        frame::{FrameBody, P2PFrame},
+    };
+
+    fn frame_signed_by(signer: &FreeWebMovementAddress, claimed: &str) -> P2PFrame {
+        let body = FrameBody {
+            version: 1,
+            address: claimed.to_string(),
+            public_key: signer.public_key.to_bytes(),
+            nonce: 7,
+            data_length: 2,
+            data: b"hi".to_vec(),
+            destination: None,
+        };
+        P2PFrame::sign(body, signer).unwrap()
+    }
+
+    fn pending_for(identity: &FreeWebMovementAddress, challenge: [u8; 32]) -> PendingChallenge {
+        PendingChallenge {
+            challenge,
+            expected: identity.to_string(),
+            public_key: identity.public_key.to_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_valid_proof() {
+        let prover = FreeWebMovementAddress::random();
+        let challenger = FreeWebMovementAddress::random().to_string();
+        let challenge = new_challenge();
+        let proof = prove(&prover, &challenger, &challenge);
+        assert_eq!(
+            verify_proof(&proof, &pending_for(&prover, challenge), &challenger),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_proof_is_bound_to_challenge_and_challenger() {
+        let prover = FreeWebMovementAddress::random();
+        let challenger = FreeWebMovementAddress::random().to_string();
+        let challenge = new_challenge();
+        assert_ne!(challenge, new_challenge());
+        let proof = prove(&prover, &challenger, &challenge);
+
+        // 换一个挑战（重放旧应答）
+        assert_eq!(
+            verify_proof(&proof, &pending_for(&prover, new_challenge()), &challenger),
+            Err(ProtocolError::BadSignature)
+        );
+        // 转发给另一个挑战方
+        let other = FreeWebMovementAddress::random().to_string();
+        assert_eq!(
+            verify_proof(&proof, &pending_for(&prover, challenge), &other),
+            Err(ProtocolError::BadSignature)
+        );
+    }
+
+    #[test]
+    fn test_claimed_address_must_match_key() {
+        let victim = FreeWebMovementAddress::random();
+        let attacker = FreeWebMovementAddress::random();
+        let challenger = FreeWebMovementAddress::random().to_string();
+        let challenge = new_challenge();
+
+        // 攻击者用自己的密钥签名，但声称是 victim：地址不能由其公钥推导出来
+        let mut proof = prove(&attacker, &challenger, &challenge);
+        proof.address = victim.to_string();
+        let pending = PendingChallenge {
+            challenge,
+            expected: victim.to_string(),
+            public_key: attacker.public_key.to_bytes().to_vec(),
+        };
+        assert_eq!(
+            verify_proof(&proof, &pending, &challenger),
+            Err(ProtocolError::IdentityMismatch {
+                claimed: victim.to_string()
+            })
+        );
+
+        // 应答的公钥与握手帧使用的公钥不同
+        let proof = prove(&attacker, &challenger, &challenge);
+        let pending = PendingChallenge {
+            challenge,
+            expected: attacker.to_string(),
+            public_key: victim.public_key.to_bytes().to_vec(),
+        };
+        assert!(matches!(
+            verify_proof(&proof, &pending, &challenger),
+            Err(ProtocolError::IdentityMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_malformed_proof() {
+        let prover = FreeWebMovementAddress::random();
+        let challenge = new_challenge();
+        let mut proof = prove(&prover, "challenger", &challenge);
+        proof.signature = vec![0u8; 3];
+        assert_eq!(
+            verify_proof(&proof, &pending_for(&prover, challenge), "challenger"),
+            Err(ProtocolError::MalformedSignature)
+        );
+    }
+
+    #[test]
+    fn test_address_key_binding() {
+        let bindings = DashMap::new();
+        let alice = FreeWebMovementAddress::random();
+        let bob = FreeWebMovementAddress::random();
+        let alice_key = alice.public_key.to_bytes().to_vec();
+        let bob_key = bob.public_key.to_bytes().to_vec();
+
+        assert_eq!(bind(&bindings, &alice.to_string(), &alice_key), Ok(()));
+        assert_eq!(bind(&bindings, &alice.to_string(), &alice_key), Ok(()));
+        // 只能绑定由公钥推导出的地址，先到的一方也无法占用他人的地址
+        assert!(matches!(
+            bind(&bindings, &bob.to_string(), &alice_key),
+            Err(ProtocolError::IdentityMismatch { .. })
+        ));
+        assert!(!bindings.contains_key(&bob.to_string()));
+        assert_eq!(bind(&bindings, &bob.to_string(), &bob_key), Ok(()));
+        assert!(bind(&bindings, "not-an-address", &[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_only_handshake_commands_before_verification() {
+        assert!(allowed_unverified(Entity::Node, Action::OnLine));
+        assert!(allowed_unverified(Entity::Node, Action::OnLineAck));
+        assert!(allowed_unverified(Entity::Node, Action::IdentityChallenge));
+        assert!(allowed_unverified(Entity::Node, Action::IdentityProof));
+        assert!(allowed_unverified(Entity::Error, Action::Error));
+        assert!(!allowed_unverified(Entity::Message, Action::SendText));
+        assert!(!allowed_unverified(Entity::Node, Action::SeedSyncRequest));
+        assert!(!allowed_unverified(Entity::Node, Action::Rekey));
+    }
+
+    #[test]
+    fn test_frame_address_must_derive_from_key() {
+        let signer = FreeWebMovementAddress::random();
+        let victim = FreeWebMovementAddress::random().to_string();
+
+        let honest = frame_signed_by(&signer, &signer.to_string());
+        assert_eq!(honest.check(), Ok(()));
+        assert!(P2PFrame::verify(honest).is_ok());
+
+        // 以自己的密钥签名、声称他人的地址：签名本身有效，但地址不是由该公钥推导的
+        let forged = frame_signed_by(&signer, &victim);
+        assert_eq!(
+            forged.check(),
+            Err(ProtocolError::IdentityMismatch {
+                claimed: victim.clone()
+            })
+        );
+        assert!(P2PFrame::verify(forged).is_err());
+    }
+
+    #[test]
+    fn test_direct_frames_must_come_from_verified_peer() {
+        let peer = FreeWebMovementAddress::random();
+        let other = FreeWebMovementAddress::random();
+        let text = P2PCommand::new(Entity::Message, Action::SendText, vec![]);
+
+        let own = frame_signed_by(&peer, &peer.to_string());
+        assert!(sent_by(&peer.to_string(), &own, &text));
+
+        // 其他节点作者的直连帧不会经这条连接合法到达
+        let foreign = frame_signed_by(&other, &other.to_string());
+        assert!(!sent_by(&peer.to_string(), &foreign, &text));
+
+        // 中继帧带有最终接收方，作者可以是其他节点
+        let mut relayed = foreign.clone();
+        relayed.body.destination = Some(peer.to_string());
+        assert!(sent_by(&peer.to_string(), &relayed, &text));
+    }
+}
@@ -0,0 +1,223 @@
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+    use aex::{
+        connection::scope::NetworkScope, crypto::session_key_manager::PairedSessionKey,
+        tcp::types::Codec,
+    };
+    use dashmap::DashMap;
+    use tokio::sync::Mutex;
+    use zz_account::address::FreeWebMovementAddress;
+    use zz_p2p::identities::{DEFAULT_IDENTITY, Identities};
+    use zz_p2p::protocols::{
+        capabilities::{CAP_SUCCESSOR, LOCAL_FEATURES, feature_names},
+        command::Action,
+        commands::{
+            node_registry::NodeRegistry,
+            successor::{
+                SUCCESSOR_MAX_GRACE_SECS, SUCCESSOR_RETAIN_SECS, SuccessorRecord, SuccessorUpdate,
+                accept, current_address, is_retired, retired_name,
+            },
+        },
+        error::ProtocolError,
+        lanes::Lane,
+        routing::{RoutingTable, learn, next_hops, rename},
+    };
+
+    const NOW: u128 = 1_700_000_000_000;
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    fn rotated() -> (
+        FreeWebMovementAddress,
+        FreeWebMovementAddress,
+        SuccessorRecord,
+    ) {
+        let old = FreeWebMovementAddress::random();
+        let new = FreeWebMovementAddress::random();
+        let record = SuccessorRecord::sign(&old, &new, HOUR, NOW).unwrap();
+        (old, new, record)
+    }
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([10, 0, 0, 1], port))
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let (old, new, record) = rotated();
+        assert_eq!(record.old, old.to_string());
+        assert_eq!(record.new, new.to_string());
+        assert_eq!(record.grace_until, NOW + HOUR.as_millis());
+        record.verify().unwrap();
+
+        let decoded: SuccessorRecord = Codec::decode(&Codec::encode(&record).unwrap()).unwrap();
+        assert_eq!(decoded, record);
+        decoded.verify().unwrap();
+
+        assert!(SuccessorRecord::sign(&old, &old, HOUR, NOW).is_err());
+        assert!(SuccessorRecord::sign(&old, &new, Duration::ZERO, NOW).is_err());
+    }
+
+    #[test]
+    fn test_tampered_records_are_rejected() {
+        let (_, _, record) = rotated();
+
+        // 换成别人的新地址与公钥：两把密钥的签名都不再匹配
+        let other = FreeWebMovementAddress::random();
+        let mut hijacked = record.clone();
+        hijacked.new = other.to_string();
+        hijacked.new_public_key = other.public_key.to_bytes().to_vec();
+        assert_eq!(hijacked.verify(), Err(ProtocolError::BadSignature));
+
+        // 只有旧身份签名不够：新身份的签名须覆盖同一记录
+        let mut unsigned = record.clone();
+        unsigned.new_signature = record.old_signature.clone();
+        assert_eq!(unsigned.verify(), Err(ProtocolError::BadSignature));
+
+        let mut extended = record.clone();
+        extended.grace_until += 1;
+        assert_eq!(extended.verify(), Err(ProtocolError::BadSignature));
+
+        let mut malformed = record;
+        malformed.old_signature = vec![0; 3];
+        assert_eq!(malformed.verify(), Err(ProtocolError::MalformedSignature));
+    }
+
+    #[test]
+    fn test_grace_period_is_capped() {
+        let old = FreeWebMovementAddress::random();
+        let new = FreeWebMovementAddress::random();
+        let record = SuccessorRecord::sign(&old, &new, HOUR * 24 * 30, NOW).unwrap();
+        assert_eq!(
+            record.grace_until,
+            NOW + Duration::from_secs(SUCCESSOR_MAX_GRACE_SECS).as_millis()
+        );
+        let mut too_long = record;
+        too_long.grace_until += 1;
+        assert!(matches!(
+            too_long.verify(),
+            Err(ProtocolError::Decode { .. })
+        ));
+    }
+
+    #[test]
+    fn test_accept_keeps_first_successor() {
+        let (old, _, record) = rotated();
+        let records = DashMap::new();
+        assert_eq!(
+            accept(&records, record.clone(), NOW),
+            SuccessorUpdate::Inserted
+        );
+        assert_eq!(
+            accept(&records, record.clone(), NOW),
+            SuccessorUpdate::Unchanged
+        );
+
+        // 同一旧身份再指向另一个新身份：不覆盖
+        let other =
+            SuccessorRecord::sign(&old, &FreeWebMovementAddress::random(), HOUR, NOW + 1).unwrap();
+        assert_eq!(accept(&records, other, NOW), SuccessorUpdate::Conflict);
+        assert_eq!(records.get(&record.old).unwrap().new, record.new);
+
+        let retain = Duration::from_secs(SUCCESSOR_RETAIN_SECS).as_millis();
+        let (_, _, late) = rotated();
+        let later = late.grace_until + retain;
+        assert_eq!(accept(&records, late, later), SuccessorUpdate::Stale);
+    }
+
+    #[test]
+    fn test_grace_and_chain() {
+        let a = FreeWebMovementAddress::random();
+        let b = FreeWebMovementAddress::random();
+        let c = FreeWebMovementAddress::random();
+        let first = SuccessorRecord::sign(&a, &b, HOUR, NOW).unwrap();
+        let second = SuccessorRecord::sign(&b, &c, HOUR, NOW + 10).unwrap();
+        let records = DashMap::new();
+        accept(&records, first.clone(), NOW);
+        accept(&records, second, NOW);
+
+        assert_eq!(current_address(&records, &a.to_string()), c.to_string());
+        assert_eq!(current_address(&records, &c.to_string()), c.to_string());
+
+        // 宽限期内旧地址仍被接受，之后拒绝
+        assert!(first.in_grace(NOW));
+        assert!(!is_retired(&records, &a.to_string(), NOW));
+        assert!(is_retired(&records, &a.to_string(), first.grace_until));
+        assert!(!is_retired(&records, &c.to_string(), first.grace_until));
+    }
+
+    #[test]
+    fn test_rename_routes() {
+        let table = RoutingTable::default();
+        learn(&table, "old", "old", 1);
+        learn(&table, "far", "old", 2);
+        learn(&table, "other", "old", 2);
+        learn(&table, "other", "new", 2);
+
+        rename(&table, "old", "new");
+        assert!(table.get("old").is_none());
+        let now = aex::time::SystemTime::timestamp();
+        assert_eq!(next_hops(&table, "new", now), vec!["new".to_string()]);
+        assert_eq!(next_hops(&table, "far", now), vec!["new".to_string()]);
+        // 已有经由新地址的路由时，经由旧地址的那条被去掉
+        assert_eq!(next_hops(&table, "other", now), vec!["new".to_string()]);
+    }
+
+    #[test]
+    fn test_registry_follows_successor() {
+        let registry = NodeRegistry::new();
+        registry.add_alias("bob", "old").unwrap();
+        registry.add_alias("robert", "old").unwrap();
+        registry.add_alias("carol", "carol-address").unwrap();
+        registry.register("old".to_string(), addr(1), NetworkScope::Intranet);
+        registry.mark_connected("old", true);
+
+        assert_eq!(
+            registry.retarget_aliases("old", "new"),
+            vec!["bob".to_string(), "robert".to_string()]
+        );
+        assert_eq!(registry.resolve_alias("bob"), "new");
+        assert_eq!(registry.resolve_alias("carol"), "carol-address");
+
+        assert!(registry.rename_node("old", "new"));
+        assert!(!registry.is_registered("old"));
+        assert!(registry.is_connected("new"));
+        assert_eq!(registry.get_seeds_for_node("new"), vec![addr(1)]);
+        assert!(!registry.rename_node("old", "new"));
+    }
+
+    #[test]
+    fn test_rotate_primary_keeps_old_identity() {
+        let primary = FreeWebMovementAddress::random();
+        let keys = Arc::new(Mutex::new(PairedSessionKey::new(16)));
+        let ids = Identities::new(primary.clone(), keys.clone());
+        let next = FreeWebMovementAddress::random();
+        let record = SuccessorRecord::sign(&primary, &next, HOUR, NOW).unwrap();
+
+        let retired = ids
+            .rotate_primary(&retired_name(&record), next.clone())
+            .unwrap();
+        assert_eq!(retired.name, format!("retired-{}", NOW / 1000));
+        assert_eq!(retired.address.to_string(), primary.to_string());
+        assert!(Arc::ptr_eq(&retired.session_keys, &keys));
+
+        assert_eq!(ids.primary().name, DEFAULT_IDENTITY);
+        assert_eq!(ids.primary().address.to_string(), next.to_string());
+        assert!(ids.is_local(&primary.to_string()));
+        assert!(ids.is_local(&next.to_string()));
+        assert!(ids.stored().contains_key(&retired.name));
+
+        // 宽限期结束后删除旧身份
+        ids.remove(&retired.name).unwrap();
+        assert!(!ids.is_local(&primary.to_string()));
+        assert!(ids.rotate_primary("again", next).is_err());
+    }
+
+    #[test]
+    fn test_capability_and_lane() {
+        assert_ne!(LOCAL_FEATURES & CAP_SUCCESSOR, 0);
+        assert_eq!(feature_names(CAP_SUCCESSOR), vec!["successor"]);
+        assert_eq!(Lane::of(Action::IdentitySuccessor), Lane::Control);
+    }
+}
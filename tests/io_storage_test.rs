@@ -1,10 +1,19 @@
 #[cfg(test)]
 mod tests {
-    use aex::storage::Storage;
-    use std::{collections::HashSet, net::SocketAddr, sync::Arc};
+    use std::{
+        collections::{BTreeMap, HashSet},
+        net::SocketAddr,
+    };
     use tempfile::tempdir;
     use zz_account::address::FreeWebMovementAddress;
-    use zz_p2p::{cli::Opt, io_storage::io_storage_init, record::NodeRecord};
+    use zz_p2p::{
+        cli::Opt,
+        io_storage::{
+            STORAGE_ALIASES, STORAGE_SCHEMA_VERSION, decode, encode, io_storage_init, migrate,
+            write_atomic,
+        },
+        record::NodeRecord,
+    };
 
     #[tokio::test]
     async fn test_io_storage_init_persistence() {
@@ -13,16 +22,13 @@ mod tests {
         let persistent_file_path = tmp_dir.path().join("identity_address.json");
         let file_path_str = persistent_file_path.to_str().unwrap().to_string();
 
-        // 2. 模拟底层存储引擎
-        let storage = Arc::new(Storage::new(tmp_dir.path().to_str()));
-
         // ==========================================
         // 场景一：文件不存在，触发自动生成
         // ==========================================
         let mut opt_v1 = Opt::default();
         opt_v1.address_file = Some(file_path_str.clone()); // 传入自定义路径
 
-        let io_storage_v1 = io_storage_init(&opt_v1, storage.clone());
+        let io_storage_v1 = io_storage_init(&opt_v1);
 
         // 第一次读取：因为文件不存在，f2 会生成一个随机地址
         let first_addr: FreeWebMovementAddress = io_storage_v1
@@ -42,7 +48,7 @@ mod tests {
         let mut opt_v2 = Opt::default();
         opt_v2.address_file = Some(file_path_str.clone()); // 指向刚才生成的路径
 
-        let io_storage_v2 = io_storage_init(&opt_v2, storage.clone());
+        let io_storage_v2 = io_storage_init(&opt_v2);
 
         // 第二次读取：此时文件已存在，逻辑应该走 f1（读取文件）
         let second_addr: FreeWebMovementAddress = io_storage_v2
@@ -64,7 +70,6 @@ mod tests {
     async fn test_server_list_persistence_lifecycle() {
         // --- 初始化环境 ---
         let tmp_dir = tempdir().expect("Failed to create temp dir");
-        let storage = Arc::new(Storage::new(tmp_dir.path().to_str()));
 
         // 定义测试用的文件路径
         let inner_file = tmp_dir
@@ -89,7 +94,7 @@ mod tests {
         // 步骤 1：首次启动（文件不存在），验证自动初始化为空
         // ==========================================
         {
-            let io_storage = io_storage_init(&opt, storage.clone());
+            let io_storage = io_storage_init(&opt);
 
             // 读取 inner_server，预期触发 f2 生成空 HashSet
             let inner: HashSet<NodeRecord> = io_storage
@@ -119,7 +124,7 @@ mod tests {
         // ==========================================
         {
             // 重新初始化一个新的 IOStorage 实例，模拟重启
-            let io_storage_v2 = io_storage_init(&opt, storage.clone());
+            let io_storage_v2 = io_storage_init(&opt);
 
             // 再次读取 inner_server
             let inner_reloaded: HashSet<NodeRecord> = io_storage_v2
@@ -139,7 +144,7 @@ mod tests {
         // 步骤 3：验证 external_server 的独立性
         // ==========================================
         {
-            let io_storage = io_storage_init(&opt, storage.clone());
+            let io_storage = io_storage_init(&opt);
             let external: HashSet<NodeRecord> = io_storage
                 .read::<HashSet<NodeRecord>>("external_server")
                 .await
@@ -152,4 +157,82 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_schema_migration() {
+        // v0：没有版本信封的裸 JSON
+        let legacy = serde_json::json!({ "home": "addr-1" });
+        let (data, migrated) = migrate(legacy.clone()).unwrap();
+        assert!(migrated);
+        assert_eq!(data, legacy);
+
+        let current = encode(&BTreeMap::from([(
+            "home".to_string(),
+            "addr-1".to_string(),
+        )]))
+        .unwrap();
+        let (aliases, migrated): (BTreeMap<String, String>, bool) = decode(&current).unwrap();
+        assert!(!migrated);
+        assert_eq!(aliases.get("home").map(String::as_str), Some("addr-1"));
+
+        // 更新的版本拒绝读取
+        let newer = serde_json::json!({ "version": STORAGE_SCHEMA_VERSION + 1, "data": {} });
+        assert!(migrate(newer).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_file_is_rewritten() {
+        let tmp_dir = tempdir().unwrap();
+        let mut opt = Opt::default();
+        opt.data_dir = Some(tmp_dir.path().to_str().unwrap().to_string());
+        let path = tmp_dir.path().join("aliases.json");
+        std::fs::write(&path, r#"{"home":"addr-1"}"#).unwrap();
+
+        let io_storage = io_storage_init(&opt);
+        let aliases = io_storage
+            .read::<BTreeMap<String, String>>(STORAGE_ALIASES)
+            .await
+            .unwrap();
+        assert_eq!(aliases.get("home").map(String::as_str), Some("addr-1"));
+
+        let on_disk: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(on_disk["version"], STORAGE_SCHEMA_VERSION);
+        assert_eq!(on_disk["data"]["home"], "addr-1");
+    }
+
+    #[tokio::test]
+    async fn test_atomic_write_leaves_no_temp_files() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("nested").join("list.json");
+        write_atomic(&path, b"first").await.unwrap();
+        write_atomic(&path, b"second").await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"second");
+        let entries = std::fs::read_dir(path.parent().unwrap()).unwrap().count();
+        assert_eq!(entries, 1);
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_saves_are_coalesced() {
+        let tmp_dir = tempdir().unwrap();
+        let inner_file = tmp_dir.path().join("inner.json");
+        let mut opt = Opt::default();
+        opt.inner_server_file = Some(inner_file.to_str().unwrap().to_string());
+        let io_storage = io_storage_init(&opt);
+
+        let mut set = HashSet::new();
+        set.insert(NodeRecord::new("127.0.0.1:1080".parse().unwrap()));
+        io_storage.schedule_save(&set, "inner_server");
+        set.insert(NodeRecord::new("127.0.0.1:1081".parse().unwrap()));
+        io_storage.schedule_save(&set, "inner_server");
+        assert!(!inner_file.exists(), "scheduled save must wait for flush");
+
+        assert_eq!(io_storage.flush().await, 1);
+        assert_eq!(io_storage.flush().await, 0);
+        let reloaded = io_storage
+            .read::<HashSet<NodeRecord>>("inner_server")
+            .await
+            .unwrap();
+        assert_eq!(reloaded.len(), 2);
+    }
 }
@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use aex::connection::scope::NetworkScope;
+    use zz_p2p::ip_scope::{classify, is_inner_ip, is_inner_ip_str, split_ips};
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_ipv4_ranges() {
+        for inner in [
+            "10.1.2.3",
+            "172.16.0.1",
+            "172.17.0.2",
+            "172.31.255.254",
+            "192.168.1.1",
+            "100.64.0.1",
+            "100.127.255.255",
+            "169.254.10.10",
+            "127.0.0.1",
+        ] {
+            assert!(is_inner_ip(&ip(inner)), "{} should be inner", inner);
+        }
+        for outer in [
+            "172.32.0.1",
+            "172.15.0.1",
+            "100.128.0.1",
+            "8.8.8.8",
+            "1.1.1.1",
+        ] {
+            assert!(!is_inner_ip(&ip(outer)), "{} should be external", outer);
+        }
+    }
+
+    #[test]
+    fn test_ipv6_ranges() {
+        for inner in [
+            "fc00::1",
+            "fd12:3456::1",
+            "fe80::1",
+            "::1",
+            "::ffff:192.168.0.1",
+        ] {
+            assert!(is_inner_ip(&ip(inner)), "{} should be inner", inner);
+        }
+        for outer in ["2001:4860:4860::8888", "2606:4700::1111", "::ffff:8.8.8.8"] {
+            assert!(!is_inner_ip(&ip(outer)), "{} should be external", outer);
+        }
+    }
+
+    #[test]
+    fn test_classify_and_split() {
+        assert_eq!(classify(&ip("fd00::1")), NetworkScope::Intranet);
+        assert_eq!(classify(&ip("2001:db8::1")), NetworkScope::Extranet);
+        assert!(!is_inner_ip_str("not-an-ip"));
+
+        // 忽略传入的范围，按地址重新分类
+        let ips = vec![
+            (NetworkScope::Extranet, ip("172.20.0.5")),
+            (NetworkScope::Intranet, ip("2001:db8::1")),
+        ];
+        let (inner, outer) = split_ips(&ips);
+        assert_eq!(inner, vec!["172.20.0.5".to_string()]);
+        assert_eq!(outer, vec!["2001:db8::1".to_string()]);
+    }
+}
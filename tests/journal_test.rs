@@ -0,0 +1,126 @@
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use tempfile::tempdir;
+    use zz_p2p::journal::{Event, JOURNAL_FILE, Journal, JournalEntry};
+
+    fn connected(peer: &str) -> Event {
+        Event::PeerConnected {
+            peer: peer.to_string(),
+            addr: "10.0.0.2:10086".to_string(),
+            inbound: true,
+        }
+    }
+
+    fn peers(entries: &[JournalEntry]) -> Vec<String> {
+        entries
+            .iter()
+            .filter_map(|e| match &e.event {
+                Event::PeerConnected { peer, .. } => Some(peer.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_event_names_are_hierarchical() {
+        let started = Event::Started {
+            address: "me".to_string(),
+            listen: "0.0.0.0:10086".to_string(),
+        };
+        assert_eq!(started.name(), "node.started");
+        assert!(started.matches("node"));
+        assert!(started.matches("node.started"));
+        assert!(!started.matches("nod"));
+        assert!(!started.matches("peer"));
+
+        let failed = Event::HandshakeFailed {
+            peer: "p".to_string(),
+            addr: "1.2.3.4:1".to_string(),
+            reason: "unsupported version".to_string(),
+        };
+        assert!(failed.matches("peer"));
+        assert!(!failed.matches("peer.connected"));
+    }
+
+    #[test]
+    fn test_serialized_event_field_matches_name() {
+        let entry = JournalEntry {
+            at: chrono::Utc::now(),
+            event: connected("p1"),
+        };
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(json["event"], "peer.connected");
+        assert_eq!(json["peer"], "p1");
+        let back: JournalEntry = serde_json::from_value(json).unwrap();
+        assert_eq!(back, entry);
+    }
+
+    #[test]
+    fn test_tail_returns_latest_in_order() {
+        let dir = tempdir().unwrap();
+        let journal = Journal::open(dir.path().join(JOURNAL_FILE), 1024 * 1024, 3).unwrap();
+        for i in 0..5 {
+            journal.append(connected(&format!("p{}", i))).unwrap();
+        }
+        journal
+            .append(Event::Stopped {
+                address: "me".to_string(),
+            })
+            .unwrap();
+
+        let last = journal.tail(3, None).unwrap();
+        assert_eq!(last.len(), 3);
+        assert_eq!(peers(&last), vec!["p3", "p4"]);
+        assert_eq!(last[2].event.name(), "node.stopped");
+
+        let only_peers = journal.tail(10, Some("peer")).unwrap();
+        assert_eq!(peers(&only_peers), vec!["p0", "p1", "p2", "p3", "p4"]);
+    }
+
+    #[test]
+    fn test_rotation_keeps_bounded_history() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(JOURNAL_FILE);
+        // 每行约 120 字节，阈值 300 字节时每个文件最多两行
+        let journal = Journal::open(&path, 300, 2).unwrap();
+        for i in 0..10 {
+            journal.append(connected(&format!("p{}", i))).unwrap();
+        }
+
+        assert!(path.exists());
+        assert!(dir.path().join("events.log.1").exists());
+        assert!(dir.path().join("events.log.2").exists());
+        assert!(!dir.path().join("events.log.3").exists());
+
+        // 跨文件读取，最旧的记录已被轮转删除
+        let all = journal.tail(100, None).unwrap();
+        let names = peers(&all);
+        assert!(names.len() < 10);
+        assert_eq!(names.last().unwrap(), "p9");
+        let tail = journal.tail(3, None).unwrap();
+        assert_eq!(peers(&tail), vec!["p7", "p8", "p9"]);
+    }
+
+    #[test]
+    fn test_reopen_appends_and_skips_corrupt_lines() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(JOURNAL_FILE);
+        {
+            let journal = Journal::open(&path, 1024 * 1024, 3).unwrap();
+            journal.append(connected("p0")).unwrap();
+        }
+        {
+            let mut f = std::fs::OpenOptions::new()
+                .append(true)
+                .open(&path)
+                .unwrap();
+            f.write_all(b"{\"at\":\"trunc").unwrap();
+            f.write_all(b"\n").unwrap();
+        }
+        let journal = Journal::open(&path, 1024 * 1024, 3).unwrap();
+        journal.append(connected("p1")).unwrap();
+        assert_eq!(peers(&journal.tail(10, None).unwrap()), vec!["p0", "p1"]);
+    }
+}
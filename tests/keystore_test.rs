@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+    use zz_account::address::FreeWebMovementAddress;
+    use zz_p2p::{
+        cli::Opt,
+        keystore::{KEYSTORE_FILE, KdfParams, Keystore, keystore_path},
+    };
+
+    // 测试中使用低开销参数，避免 Argon2 拖慢测试
+    const FAST: KdfParams = KdfParams {
+        m_cost: 64,
+        t_cost: 1,
+        p_cost: 1,
+    };
+
+    #[test]
+    fn test_seal_and_unlock() {
+        let identity = FreeWebMovementAddress::random();
+        let keystore = Keystore::seal_with_params(&identity, "correct horse", FAST).unwrap();
+
+        assert_eq!(keystore.address, identity.to_string());
+        // 密文中不含私钥明文
+        let json = serde_json::to_string(&keystore).unwrap();
+        let plain = serde_json::to_string(&identity).unwrap();
+        assert!(!json.contains(&plain));
+
+        let unlocked = keystore.unlock("correct horse").unwrap();
+        assert_eq!(unlocked.to_string(), identity.to_string());
+    }
+
+    #[test]
+    fn test_wrong_passphrase_rejected() {
+        let identity = FreeWebMovementAddress::random();
+        let keystore = Keystore::seal_with_params(&identity, "right", FAST).unwrap();
+        assert!(keystore.unlock("wrong").is_err());
+    }
+
+    #[test]
+    fn test_empty_passphrase_rejected() {
+        let identity = FreeWebMovementAddress::random();
+        assert!(Keystore::seal_with_params(&identity, "", FAST).is_err());
+    }
+
+    #[test]
+    fn test_tampered_address_rejected() {
+        let identity = FreeWebMovementAddress::random();
+        let mut keystore = Keystore::seal_with_params(&identity, "pass", FAST).unwrap();
+        keystore.address = FreeWebMovementAddress::random().to_string();
+        assert!(keystore.unlock("pass").is_err());
+    }
+
+    #[test]
+    fn test_rotate_changes_passphrase() {
+        let identity = FreeWebMovementAddress::random();
+        let keystore = Keystore::seal_with_params(&identity, "old", FAST).unwrap();
+        let rotated = keystore.rotate("old", "new").unwrap();
+
+        assert_ne!(rotated.salt, keystore.salt);
+        assert_ne!(rotated.ciphertext, keystore.ciphertext);
+        assert!(rotated.unlock("old").is_err());
+        assert_eq!(
+            rotated.unlock("new").unwrap().to_string(),
+            identity.to_string()
+        );
+        assert!(keystore.rotate("bad", "new").is_err());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(KEYSTORE_FILE);
+        let identity = FreeWebMovementAddress::random();
+        let keystore = Keystore::seal_with_params(&identity, "pass", FAST).unwrap();
+        keystore.save(&path).unwrap();
+
+        let loaded = Keystore::load(&path).unwrap();
+        assert_eq!(loaded, keystore);
+        assert_eq!(
+            loaded.unlock("pass").unwrap().to_string(),
+            identity.to_string()
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_keystore_path_uses_data_dir() {
+        let mut opt = Opt::default();
+        opt.data_dir = Some("/tmp/zz-node".to_string());
+        assert_eq!(
+            keystore_path(&opt),
+            std::path::PathBuf::from("/tmp/zz-node").join(KEYSTORE_FILE)
+        );
+    }
+}
@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use tokio::sync::Mutex;
+    use zz_p2p::protocols::command::Action;
+    use zz_p2p::protocols::lanes::{Lane, Lanes, SharedLanes};
+
+    /// 在持有许可时按给定顺序排队，释放后返回各写入实际获得许可的通道顺序
+    async fn dequeue_order(queued: &[Lane]) -> Vec<Lane> {
+        let lanes = SharedLanes::default();
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let held = lanes.acquire(Lane::Bulk).await;
+
+        let mut tasks = Vec::new();
+        for &lane in queued {
+            let lanes = lanes.clone();
+            let order = order.clone();
+            tasks.push(tokio::spawn(async move {
+                let _permit = lanes.acquire(lane).await;
+                order.lock().await.push(lane);
+            }));
+        }
+        while lanes.waiting().iter().sum::<usize>() < queued.len() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        drop(held);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        let order = order.lock().await.clone();
+        order
+    }
+
+    #[test]
+    fn test_lane_classification() {
+        assert_eq!(Lane::of(Action::Ping), Lane::Control);
+        assert_eq!(Lane::of(Action::OnLine), Lane::Control);
+        assert_eq!(Lane::of(Action::StreamWindow), Lane::Control);
+        assert_eq!(Lane::of(Action::SendText), Lane::Messaging);
+        assert_eq!(Lane::of(Action::StreamData), Lane::Bulk);
+
+        assert_eq!(Lane::Messaging.fragmented(true), Lane::Bulk);
+        assert_eq!(Lane::Messaging.fragmented(false), Lane::Messaging);
+        assert_eq!(Lane::Control.fragmented(true), Lane::Control);
+    }
+
+    #[tokio::test]
+    async fn test_control_goes_first() {
+        let queued = [
+            Lane::Bulk,
+            Lane::Bulk,
+            Lane::Messaging,
+            Lane::Control,
+            Lane::Messaging,
+            Lane::Control,
+        ];
+        assert_eq!(
+            dequeue_order(&queued).await,
+            vec![
+                Lane::Control,
+                Lane::Control,
+                Lane::Messaging,
+                Lane::Messaging,
+                Lane::Bulk,
+                Lane::Bulk,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bulk_is_not_starved() {
+        let mut queued = vec![Lane::Control; 20];
+        queued.extend([Lane::Bulk, Lane::Bulk]);
+        let order = dequeue_order(&queued).await;
+        let bulk: Vec<usize> = order
+            .iter()
+            .enumerate()
+            .filter(|(_, lane)| **lane == Lane::Bulk)
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(bulk, vec![8, 17]);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_waiter_passes_permit_on() {
+        let lanes = Arc::new(Lanes::default());
+        let held = lanes.acquire(Lane::Bulk).await;
+
+        let waiter = lanes.clone();
+        let abandoned = tokio::spawn(async move {
+            let _permit = waiter.acquire(Lane::Control).await;
+        });
+        while lanes.waiting()[0] == 0 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        abandoned.abort();
+        let _ = abandoned.await;
+
+        drop(held);
+        let next = tokio::time::timeout(Duration::from_secs(1), lanes.acquire(Lane::Bulk)).await;
+        assert!(next.is_ok());
+    }
+}
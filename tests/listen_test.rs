@@ -0,0 +1,111 @@
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, SocketAddr};
+    use std::path::Path;
+
+    use clap::Parser;
+    use zz_p2p::{cli::Opt, config::Config, listen::ListenAddrs};
+
+    fn addr(s: &str) -> SocketAddr {
+        s.parse().unwrap()
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn extra(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_bindings() {
+        let listen = ListenAddrs::new(
+            addr("0.0.0.0:9000"),
+            &extra(&["9001", "[::1]:9002", "0.0.0.0:9000"]),
+            false,
+        )
+        .unwrap();
+        // 只有端口时使用主 IP，重复的地址去掉
+        assert_eq!(
+            listen.bindings,
+            vec![
+                addr("0.0.0.0:9000"),
+                addr("0.0.0.0:9001"),
+                addr("[::1]:9002")
+            ]
+        );
+        assert_eq!(listen.primary(), addr("0.0.0.0:9000"));
+        assert_eq!(listen.extra().len(), 2);
+        assert_eq!(listen.ports(), vec![9000, 9001, 9002]);
+
+        assert!(ListenAddrs::new(addr("0.0.0.0:9000"), &extra(&["nope"]), false).is_err());
+    }
+
+    #[test]
+    fn test_dual_stack_merges_unspecified() {
+        let configured = extra(&["[::]:9000", "9001"]);
+
+        // 非双栈：分别绑定
+        let split = ListenAddrs::new(addr("0.0.0.0:9000"), &configured, false).unwrap();
+        assert_eq!(split.bindings.len(), 3);
+
+        // 双栈：`[::]:9000` 已接收 IPv4，不再绑定 `0.0.0.0:9000`
+        let merged = ListenAddrs::new(addr("0.0.0.0:9000"), &configured, true).unwrap();
+        assert_eq!(
+            merged.bindings,
+            vec![addr("[::]:9000"), addr("0.0.0.0:9001")]
+        );
+        assert_eq!(merged.primary(), addr("[::]:9000"));
+        assert_eq!(merged.ports_for(&ip("192.168.1.5")), vec![9000, 9001]);
+        assert_eq!(merged.ports_for(&ip("2001:db8::5")), vec![9000]);
+    }
+
+    #[test]
+    fn test_advertised_endpoints() {
+        let listen = ListenAddrs::new(
+            addr("0.0.0.0:9000"),
+            &extra(&["[::]:9000", "192.168.1.5:9100"]),
+            false,
+        )
+        .unwrap();
+        let ips = [
+            ip("127.0.0.1"),
+            ip("192.168.1.5"),
+            ip("10.0.0.2"),
+            ip("2001:db8::5"),
+        ];
+        assert_eq!(
+            listen.advertised(&ips),
+            vec![
+                addr("192.168.1.5:9100"),
+                addr("192.168.1.5:9000"),
+                addr("10.0.0.2:9000"),
+                addr("[2001:db8::5]:9000"),
+            ]
+        );
+        // 反射地址只看地址族
+        assert_eq!(listen.external_ports(&ip("203.0.113.7")), vec![9000, 9100]);
+        assert_eq!(listen.external_ports(&ip("2001:db8::9")), vec![9000]);
+
+        // 只绑定回环地址时不公告任何端点
+        let local = ListenAddrs::single(addr("127.0.0.1:9000"));
+        assert!(local.advertised(&ips).is_empty());
+    }
+
+    #[test]
+    fn test_listen_from_cli_and_config() {
+        let opt = Opt::parse_from(["zzp2p", "--listen", "[::]:1090", "--listen", "1091"]);
+        assert_eq!(opt.listen, vec!["[::]:1090", "1091"]);
+
+        let config = Config::parse(r#"listen = ["[::]:2090"]"#, Path::new("node.toml")).unwrap();
+        let mut from_config = Opt::parse_from(["zzp2p"]);
+        config.merge_into(&mut from_config);
+        assert_eq!(from_config.listen, vec!["[::]:2090"]);
+
+        // 命令行显式给出的地址优先
+        let mut explicit = opt;
+        config.merge_into(&mut explicit);
+        assert_eq!(explicit.listen, vec!["[::]:1090", "1091"]);
+    }
+}
@@ -0,0 +1,119 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicU32, Ordering},
+        },
+        time::Duration,
+    };
+
+    use futures::future::BoxFuture;
+    use zz_p2p::listener::{HandlerSet, Health, Listener};
+
+    /// 前 `failures` 次启动失败（交替返回错误和 panic），之后一直运行
+    struct Flaky {
+        starts: Arc<AtomicU32>,
+        failures: u32,
+    }
+
+    impl Listener for Flaky {
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        fn start(self: Arc<Self>) -> BoxFuture<'static, anyhow::Result<()>> {
+            Box::pin(async move {
+                let n = self.starts.fetch_add(1, Ordering::SeqCst) + 1;
+                if n <= self.failures {
+                    if n % 2 == 0 {
+                        panic!("boom");
+                    }
+                    anyhow::bail!("bind failed");
+                }
+                futures::future::pending::<()>().await;
+                Ok(())
+            })
+        }
+    }
+
+    struct Once;
+
+    impl Listener for Once {
+        fn name(&self) -> &str {
+            "once"
+        }
+
+        fn start(self: Arc<Self>) -> BoxFuture<'static, anyhow::Result<()>> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    fn health_of(set: &HandlerSet, name: &str) -> Option<Health> {
+        set.health()
+            .into_iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, h)| h)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_crashed_listener_is_restarted() {
+        let starts = Arc::new(AtomicU32::new(0));
+        let set = HandlerSet::new();
+        set.start(Flaky {
+            starts: starts.clone(),
+            failures: 2,
+        });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(starts.load(Ordering::SeqCst), 1);
+        assert!(matches!(
+            health_of(&set, "flaky"),
+            Some(Health::Restarting { attempts: 1, .. })
+        ));
+
+        // 500ms 后第二次启动（panic），再等 1s 第三次启动成功
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(starts.load(Ordering::SeqCst), 3);
+        assert_eq!(health_of(&set, "flaky"), Some(Health::Running));
+
+        set.stop_all().await;
+        assert!(set.health().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_gives_up_after_max_restarts() {
+        let starts = Arc::new(AtomicU32::new(0));
+        let set = HandlerSet::new();
+        set.start(Flaky {
+            starts: starts.clone(),
+            failures: u32::MAX,
+        });
+        tokio::time::sleep(Duration::from_secs(600)).await;
+        assert_eq!(
+            starts.load(Ordering::SeqCst),
+            zz_p2p::listener::MAX_RESTARTS + 1
+        );
+        assert!(matches!(
+            health_of(&set, "flaky"),
+            Some(Health::Failed { .. })
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_clean_exit_and_stop() {
+        let set = HandlerSet::new();
+        set.start(Once);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(health_of(&set, "once"), Some(Health::Stopped));
+
+        set.start(Flaky {
+            starts: Arc::new(AtomicU32::new(0)),
+            failures: 0,
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(set.stop("flaky"));
+        assert!(!set.stop("flaky"));
+        assert!(health_of(&set, "flaky").is_none());
+    }
+}
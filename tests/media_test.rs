@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod tests {
+    use aex::tcp::types::Codec;
+    use zz_p2p::media::{
+        JitterBuffer, MediaDatagram, MediaFrame, MediaPacket, PAYLOAD_PCM_S16LE, Packetizer,
+    };
+
+    fn frame(seq: u32) -> MediaFrame {
+        MediaFrame {
+            seq,
+            timestamp: seq * 160,
+            payload_type: PAYLOAD_PCM_S16LE,
+            payload: vec![seq as u8],
+        }
+    }
+
+    #[test]
+    fn test_packetizer() {
+        let mut p = Packetizer::default();
+        let a = p.next(PAYLOAD_PCM_S16LE, vec![1], 160);
+        let b = p.next(PAYLOAD_PCM_S16LE, vec![2], 160);
+        let c = p.next(PAYLOAD_PCM_S16LE, vec![3], 80);
+        let d = p.next(PAYLOAD_PCM_S16LE, vec![4], 80);
+        assert_eq!((a.seq, a.timestamp), (0, 0));
+        assert_eq!((b.seq, b.timestamp), (1, 160));
+        assert_eq!((c.seq, c.timestamp), (2, 320));
+        assert_eq!((d.seq, d.timestamp), (3, 400));
+    }
+
+    #[test]
+    fn test_pcm_payload() {
+        let samples = [0i16, 1, -1, i16::MAX, i16::MIN];
+        let f = MediaFrame {
+            seq: 0,
+            timestamp: 0,
+            payload_type: PAYLOAD_PCM_S16LE,
+            payload: samples.iter().flat_map(|s| s.to_le_bytes()).collect(),
+        };
+        assert_eq!(f.pcm(), samples.to_vec());
+    }
+
+    #[test]
+    fn test_jitter_reorders() {
+        let mut jb = JitterBuffer::new(3, 8);
+        assert!(jb.push(frame(1)));
+        assert!(jb.push(frame(0)));
+        // 未达到初始缓冲深度
+        assert!(jb.pop().is_none());
+        assert!(jb.push(frame(2)));
+        assert_eq!(jb.pop().unwrap().seq, 0);
+        assert_eq!(jb.pop().unwrap().seq, 1);
+        assert_eq!(jb.pop().unwrap().seq, 2);
+        assert!(jb.pop().is_none());
+
+        // 迟到与重复的帧被丢弃
+        assert!(!jb.push(frame(1)));
+        assert!(jb.push(frame(4)));
+        assert!(!jb.push(frame(4)));
+        assert_eq!(jb.dropped, 2);
+        // 3 还没到，等待
+        assert!(jb.pop().is_none());
+        assert!(jb.push(frame(3)));
+        assert_eq!(jb.pop().unwrap().seq, 3);
+        assert_eq!(jb.pop().unwrap().seq, 4);
+        assert_eq!(jb.lost, 0);
+    }
+
+    #[test]
+    fn test_jitter_skips_lost_frames() {
+        let mut jb = JitterBuffer::new(1, 3);
+        jb.push(frame(0));
+        assert_eq!(jb.pop().unwrap().seq, 0);
+        // 1 和 2 丢失
+        jb.push(frame(3));
+        jb.push(frame(4));
+        assert!(jb.pop().is_none());
+        jb.push(frame(5));
+        assert_eq!(jb.pop().unwrap().seq, 3);
+        assert_eq!(jb.lost, 2);
+        assert_eq!(jb.pop().unwrap().seq, 4);
+        assert_eq!(jb.pop().unwrap().seq, 5);
+        assert!(jb.is_empty());
+    }
+
+    #[test]
+    fn test_datagram_roundtrip() {
+        let packet = MediaPacket {
+            call_id: 7,
+            frame: frame(9),
+        };
+        let bytes = Codec::encode(&packet).unwrap();
+        let decoded: MediaPacket = Codec::decode(&bytes).unwrap();
+        assert_eq!(decoded, packet);
+
+        let datagram = MediaDatagram {
+            call_id: 7,
+            sender: "alice".to_string(),
+            ciphertext: bytes,
+        };
+        let decoded: MediaDatagram = Codec::decode(&Codec::encode(&datagram).unwrap()).unwrap();
+        assert_eq!(decoded, datagram);
+    }
+}
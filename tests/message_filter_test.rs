@@ -0,0 +1,131 @@
+#[cfg(test)]
+mod tests {
+    use aex::connection::global::GlobalContext;
+    use std::{collections::BTreeMap, net::SocketAddr, sync::Arc, time::Duration};
+    use zz_p2p::{
+        events::NodeEvent,
+        protocols::{
+            commands::{message::IncomingMessage, node_registry::NodeManager},
+            filter::{FilterVerdict, InboundMessage, MessageFilters},
+        },
+        webhook,
+    };
+
+    fn create_mock_ctx() -> Arc<GlobalContext> {
+        let addr = "127.0.0.1:1080".parse::<SocketAddr>().unwrap();
+        Arc::new(GlobalContext::new(addr, None))
+    }
+
+    fn message(content: &str) -> InboundMessage {
+        InboundMessage {
+            from: "alice".to_string(),
+            group_id: None,
+            content: content.to_string(),
+            timestamp: 1,
+            annotations: BTreeMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_filters_modify_and_annotate_in_order() {
+        let filters = MessageFilters::default();
+        filters.add("upper", |_gctx, mut m: InboundMessage| async move {
+            m.content = m.content.to_uppercase();
+            FilterVerdict::Deliver(m)
+        });
+        filters.add("score", |_gctx, mut m: InboundMessage| async move {
+            // 能看到前一个过滤器的改写
+            let score = if m.content == "HELLO" { "0" } else { "1" };
+            m.annotate("spam_score", score);
+            FilterVerdict::Deliver(m)
+        });
+        assert_eq!(filters.names(), vec!["upper", "score"]);
+
+        let delivered = filters
+            .apply(&create_mock_ctx(), message("hello"))
+            .await
+            .unwrap();
+        assert_eq!(delivered.content, "HELLO");
+        assert_eq!(delivered.annotations["spam_score"], "0");
+    }
+
+    #[tokio::test]
+    async fn test_drop_stops_later_filters() {
+        let filters = MessageFilters::default();
+        filters.add("keywords", |_gctx, m: InboundMessage| async move {
+            if m.content.contains("buy now") {
+                FilterVerdict::Drop("keyword".to_string())
+            } else {
+                FilterVerdict::Deliver(m)
+            }
+        });
+        filters.add("never", |_gctx, m: InboundMessage| async move {
+            assert!(m.content.is_empty(), "must not run after a drop");
+            FilterVerdict::Deliver(m)
+        });
+        let ctx = create_mock_ctx();
+        assert!(filters.apply(&ctx, message("buy now!")).await.is_none());
+
+        // 同名过滤器原位替换，注销后不再执行
+        assert!(filters.add(
+            "keywords",
+            |_gctx, m| async move { FilterVerdict::Deliver(m) }
+        ));
+        assert!(filters.remove("never"));
+        assert!(!filters.remove("never"));
+        assert!(filters.apply(&ctx, message("buy now!")).await.is_some());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failing_filters_pass_through() {
+        let filters = MessageFilters::default();
+        filters.add("boom", |_gctx, m: InboundMessage| async move {
+            assert!(m.content.is_empty(), "boom");
+            FilterVerdict::Deliver(m)
+        });
+        filters.add("slow", |_gctx, m| async move {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            FilterVerdict::Drop(format!("too late for {}", m.from))
+        });
+        filters.add("tag", |_gctx, mut m: InboundMessage| async move {
+            m.annotate("seen", "yes");
+            FilterVerdict::Deliver(m)
+        });
+        let delivered = filters
+            .apply(&create_mock_ctx(), message("hi"))
+            .await
+            .unwrap();
+        assert_eq!(delivered.content, "hi");
+        assert_eq!(delivered.annotations["seen"], "yes");
+    }
+
+    #[tokio::test]
+    async fn test_register_through_node_manager() {
+        let manager = NodeManager::new();
+        assert!(manager.filters().is_empty());
+        assert!(!manager.add_filter(
+            "auto_reply",
+            |_gctx, m| async move { FilterVerdict::Deliver(m) }
+        ));
+        // clone 共享同一组过滤器
+        let shared = manager.clone();
+        assert_eq!(shared.filters().names(), vec!["auto_reply"]);
+        assert!(shared.remove_filter("auto_reply"));
+        assert!(manager.filters().is_empty());
+    }
+
+    #[test]
+    fn test_annotations_in_webhook_payload() {
+        let (_, body) = webhook::payload(
+            &NodeEvent::Message(IncomingMessage {
+                from: "alice".to_string(),
+                content: "hi".to_string(),
+                timestamp: 1,
+                annotations: BTreeMap::from([("spam_score".to_string(), "0.9".to_string())]),
+            }),
+            "me",
+            2,
+        );
+        assert_eq!(body["data"]["annotations"]["spam_score"], "0.9");
+    }
+}
@@ -0,0 +1,133 @@
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use aex::tcp::types::Codec;
+    use zz_p2p::{
+        config::Config,
+        protocols::{
+            commands::message::MessageCommand,
+            ordering::{OrderingConfig, OutboundSequences, Push, ReorderBuffer},
+        },
+    };
+
+    fn cfg(window: u64, timeout_ms: u64) -> OrderingConfig {
+        OrderingConfig {
+            reorder_window: window,
+            reorder_timeout_ms: timeout_ms,
+        }
+    }
+
+    fn delivered(p: Push<u64>) -> Vec<u64> {
+        match p {
+            Push::Deliver(v) => v,
+            Push::Duplicate => panic!("unexpected duplicate"),
+        }
+    }
+
+    #[test]
+    fn test_sequences_per_receiver_and_retry() {
+        let seqs = OutboundSequences::new(7);
+        assert_eq!(seqs.epoch(), 7);
+        assert_eq!(seqs.seq_for("bob", 100), 1);
+        assert_eq!(seqs.seq_for("bob", 101), 2);
+        // 同一 request_id 重发沿用原序号
+        assert_eq!(seqs.seq_for("bob", 100), 1);
+        assert_eq!(seqs.seq_for("carol", 102), 1);
+        assert_eq!(seqs.seq_for("bob", 103), 3);
+    }
+
+    #[test]
+    fn test_in_order_and_duplicates() {
+        let c = cfg(8, 1000);
+        let mut buf = ReorderBuffer::default();
+        assert_eq!(delivered(buf.push("a", 1, 1, 1, 0, &c)), vec![1]);
+        assert_eq!(delivered(buf.push("a", 1, 2, 2, 0, &c)), vec![2]);
+        assert_eq!(buf.push("a", 1, 2, 2, 0, &c), Push::Duplicate);
+        assert_eq!(buf.push("a", 1, 1, 1, 0, &c), Push::Duplicate);
+        // 不同发送方互不影响
+        assert_eq!(delivered(buf.push("b", 1, 1, 1, 0, &c)), vec![1]);
+    }
+
+    #[test]
+    fn test_reorders_within_window() {
+        let c = cfg(8, 1000);
+        let mut buf = ReorderBuffer::default();
+        assert!(delivered(buf.push("a", 1, 3, 3, 0, &c)).is_empty());
+        assert!(delivered(buf.push("a", 1, 2, 2, 0, &c)).is_empty());
+        assert_eq!(buf.push("a", 1, 3, 3, 0, &c), Push::Duplicate);
+        assert_eq!(buf.pending(), 2);
+        assert_eq!(delivered(buf.push("a", 1, 1, 1, 0, &c)), vec![1, 2, 3]);
+        assert_eq!(buf.pending(), 0);
+    }
+
+    #[test]
+    fn test_window_overflow_skips_gap() {
+        let c = cfg(4, 1000);
+        let mut buf = ReorderBuffer::default();
+        assert!(delivered(buf.push("a", 1, 2, 2, 0, &c)).is_empty());
+        // seq 6 超出窗口 [1, 5)：放弃 1、2 之前的空洞
+        assert_eq!(delivered(buf.push("a", 1, 6, 6, 0, &c)), vec![2]);
+        assert_eq!(delivered(buf.push("a", 1, 3, 3, 0, &c)), vec![3]);
+        assert_eq!(buf.push("a", 1, 1, 1, 0, &c), Push::Duplicate);
+    }
+
+    #[test]
+    fn test_timeout_flush() {
+        let c = cfg(16, 1000);
+        let mut buf = ReorderBuffer::default();
+        buf.push("a", 1, 3, 3, 100, &c);
+        buf.push("a", 1, 4, 4, 500, &c);
+        buf.push("a", 1, 6, 6, 900, &c);
+        assert!(buf.flush_expired(1000, &c).is_empty());
+        assert_eq!(
+            buf.flush_expired(1100, &c),
+            vec![("a".to_string(), 3), ("a".to_string(), 4)]
+        );
+        assert_eq!(buf.flush_expired(1900, &c), vec![("a".to_string(), 6)]);
+        assert_eq!(delivered(buf.push("a", 1, 7, 7, 2000, &c)), vec![7]);
+    }
+
+    #[test]
+    fn test_sender_restart() {
+        let c = cfg(16, 1000);
+        let mut buf = ReorderBuffer::default();
+        assert_eq!(delivered(buf.push("a", 1, 1, 1, 0, &c)), vec![1]);
+        buf.push("a", 1, 3, 3, 0, &c);
+        // 新 epoch：先投递旧缓冲区，再从 1 开始
+        assert_eq!(delivered(buf.push("a", 2, 1, 10, 0, &c)), vec![3, 10]);
+        assert_eq!(buf.push("a", 1, 2, 2, 0, &c), Push::Duplicate);
+    }
+
+    #[test]
+    fn test_window_zero_delivers_immediately() {
+        let c = cfg(0, 1000);
+        let mut buf = ReorderBuffer::default();
+        assert_eq!(delivered(buf.push("a", 1, 5, 5, 0, &c)), vec![5]);
+        assert_eq!(delivered(buf.push("a", 1, 7, 7, 0, &c)), vec![7]);
+        assert_eq!(buf.push("a", 1, 6, 6, 0, &c), Push::Duplicate);
+    }
+
+    #[test]
+    fn test_message_command_roundtrip() {
+        let cmd = MessageCommand {
+            sender: "alice".to_string(),
+            receiver: "bob".to_string(),
+            request_id: 9,
+            timestamp: 1,
+            message: "hi".to_string(),
+            epoch: 42,
+            seq: 3,
+        };
+        let decoded: MessageCommand = Codec::decode(&Codec::encode(&cmd).unwrap()).unwrap();
+        assert_eq!(decoded, cmd);
+    }
+
+    #[test]
+    fn test_ordering_config() {
+        let text = "[ordering]\nreorder_window = 8\n";
+        let config = Config::parse(text, Path::new("node.toml")).unwrap();
+        assert_eq!(config.ordering.reorder_window, 8);
+        assert_eq!(config.ordering.reorder_timeout_ms, 2000);
+    }
+}
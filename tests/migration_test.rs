@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use tempfile::tempdir;
+    use zz_p2p::{
+        cli::Opt,
+        io_storage::{IOStorage, STORAGE_SCHEMA_VERSION, io_storage_init},
+        migration::{self, BACKUP_DIR, migrations},
+    };
+
+    fn storage(dir: &Path) -> IOStorage {
+        let mut opt = Opt::default();
+        opt.data_dir = Some(dir.to_str().unwrap().to_string());
+        io_storage_init(&opt)
+    }
+
+    #[test]
+    fn test_steps_cover_every_version() {
+        let steps = migrations();
+        assert_eq!(steps.len() as u64, STORAGE_SCHEMA_VERSION);
+        for (i, step) in steps.iter().enumerate() {
+            assert_eq!(step.from_version(), i as u64);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_legacy_dir_is_backed_up_and_migrated() {
+        let tmp_dir = tempdir().unwrap();
+        let aliases = tmp_dir.path().join("aliases.json");
+        let legacy = r#"{"home":"addr-1"}"#;
+        std::fs::write(&aliases, legacy).unwrap();
+        let storage = storage(tmp_dir.path());
+
+        // 演练不修改任何文件
+        let plan = storage.migrate_data_dir(true).await.unwrap();
+        assert_eq!((plan.from, plan.to), (0, STORAGE_SCHEMA_VERSION));
+        assert_eq!(plan.steps.len(), 1);
+        assert_eq!(plan.steps[0].files, vec![aliases.clone()]);
+        assert!(plan.to_string().contains("Dry run"));
+        assert_eq!(std::fs::read_to_string(&aliases).unwrap(), legacy);
+        assert!(!migration::version_file(tmp_dir.path()).exists());
+        assert!(!tmp_dir.path().join(BACKUP_DIR).exists());
+
+        let report = storage.migrate_data_dir(false).await.unwrap();
+        assert_eq!(report.steps[0].files, vec![aliases.clone()]);
+        let backup = report.backup.unwrap();
+        assert!(backup.starts_with(tmp_dir.path().join(BACKUP_DIR)));
+        assert_eq!(
+            std::fs::read_to_string(backup.join("aliases.json")).unwrap(),
+            legacy
+        );
+        let on_disk: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&aliases).unwrap()).unwrap();
+        assert_eq!(on_disk["version"], STORAGE_SCHEMA_VERSION);
+        assert_eq!(on_disk["data"]["home"], "addr-1");
+        assert_eq!(
+            migration::read_version(tmp_dir.path()).await.unwrap(),
+            Some(STORAGE_SCHEMA_VERSION)
+        );
+
+        // 再次启动无需迁移
+        let again = storage.migrate_data_dir(false).await.unwrap();
+        assert!(again.steps.is_empty());
+        assert!(again.backup.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_fresh_dir_gets_current_version() {
+        let tmp_dir = tempdir().unwrap();
+        let report = storage(tmp_dir.path())
+            .migrate_data_dir(false)
+            .await
+            .unwrap();
+        assert_eq!(report.from, STORAGE_SCHEMA_VERSION);
+        assert!(report.steps.is_empty());
+        assert_eq!(
+            migration::read_version(tmp_dir.path()).await.unwrap(),
+            Some(STORAGE_SCHEMA_VERSION)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_newer_dir_is_refused() {
+        let tmp_dir = tempdir().unwrap();
+        let version = migration::version_file(tmp_dir.path());
+        let newer = format!(r#"{{"version": {}}}"#, STORAGE_SCHEMA_VERSION + 1);
+        std::fs::write(&version, &newer).unwrap();
+        let aliases = tmp_dir.path().join("aliases.json");
+        std::fs::write(&aliases, r#"{"home":"addr-1"}"#).unwrap();
+
+        let storage = storage(tmp_dir.path());
+        assert!(storage.migrate_data_dir(true).await.is_err());
+        assert!(storage.migrate_data_dir(false).await.is_err());
+        assert_eq!(std::fs::read_to_string(&version).unwrap(), newer);
+        assert_eq!(
+            std::fs::read_to_string(&aliases).unwrap(),
+            r#"{"home":"addr-1"}"#
+        );
+    }
+}
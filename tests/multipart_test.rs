@@ -0,0 +1,210 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    use tokio::io::{AsyncRead, ReadBuf};
+    use zz_p2p::web::multipart::{
+        FileData, MultipartError, MultipartLimits, MultipartReader, Part, boundary,
+    };
+
+    /// 每次最多返回 `step` 字节，模拟分隔符被拆在多个网络包之间
+    struct Trickle {
+        data: Vec<u8>,
+        pos: usize,
+        step: usize,
+    }
+
+    impl AsyncRead for Trickle {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            let end = (self.pos + self.step)
+                .min(self.data.len())
+                .min(self.pos + buf.remaining());
+            buf.put_slice(&self.data[self.pos..end]);
+            self.pos = end;
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    const BOUNDARY: &str = "----zzBoundary42";
+
+    fn body(file: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(b"preamble to ignore\r\n");
+        out.extend_from_slice(format!("--{}\r\n", BOUNDARY).as_bytes());
+        out.extend_from_slice(b"Content-Disposition: form-data; name=\"to\"\r\n\r\n");
+        out.extend_from_slice(b"alice\r\n");
+        out.extend_from_slice(format!("--{}\r\n", BOUNDARY).as_bytes());
+        out.extend_from_slice(
+            b"Content-Disposition: form-data; name=\"file\"; filename=\"../../etc/photo.png\"\r\n",
+        );
+        out.extend_from_slice(b"Content-Type: image/png\r\n\r\n");
+        out.extend_from_slice(file);
+        out.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+        out
+    }
+
+    async fn collect<R: AsyncRead + Unpin>(
+        mut reader: MultipartReader<R>,
+    ) -> Result<Vec<Part>, MultipartError> {
+        let mut parts = Vec::new();
+        while let Some(part) = reader.next_part().await? {
+            parts.push(part);
+        }
+        Ok(parts)
+    }
+
+    #[test]
+    fn test_boundary() {
+        assert_eq!(
+            boundary("multipart/form-data; boundary=abc").as_deref(),
+            Some("abc")
+        );
+        assert_eq!(
+            boundary("Multipart/Form-Data; charset=utf-8; boundary=\"a b\"").as_deref(),
+            Some("a b")
+        );
+        assert!(boundary("application/json").is_none());
+        assert!(boundary("multipart/form-data").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_fields_and_files() {
+        // 文件内容里包含类似分隔符的片段
+        let file = b"\x89PNG\r\n--not-the-boundary\r\n\x00\x01".to_vec();
+        for step in [1, 3, 7, 64, 4096] {
+            let reader = Trickle {
+                data: body(&file),
+                pos: 0,
+                step,
+            };
+            let parts = collect(MultipartReader::new(
+                reader,
+                BOUNDARY,
+                MultipartLimits::default(),
+            ))
+            .await
+            .unwrap();
+            assert_eq!(parts.len(), 2, "step {}", step);
+            match &parts[0] {
+                Part::Field { name, value } => {
+                    assert_eq!(name, "to");
+                    assert_eq!(value, "alice");
+                }
+                other => panic!("expected field, got {:?}", other),
+            }
+            match &parts[1] {
+                Part::File {
+                    name,
+                    filename,
+                    content_type,
+                    data: FileData::Memory(data),
+                } => {
+                    assert_eq!(name, "file");
+                    assert_eq!(filename, "photo.png");
+                    assert_eq!(content_type.as_deref(), Some("image/png"));
+                    assert_eq!(data, &file);
+                }
+                other => panic!("expected in-memory file, got {:?}", other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_large_file_is_spooled() {
+        let file: Vec<u8> = (0..50_000u32).map(|i| (i % 251) as u8).collect();
+        let limits = MultipartLimits {
+            spool_threshold: 1024,
+            ..Default::default()
+        };
+        let data = body(&file);
+        let mut parts = collect(MultipartReader::new(&data[..], BOUNDARY, limits))
+            .await
+            .unwrap();
+        let Some(Part::File { data, .. }) = parts.pop() else {
+            panic!("expected file part");
+        };
+        let path = match &data {
+            FileData::Spooled { file, len } => {
+                assert_eq!(*len, std::fs::metadata(file.path()).unwrap().len());
+                file.path().to_path_buf()
+            }
+            other => panic!("expected spooled file, got {:?}", other),
+        };
+        assert_eq!(data.len(), 50_000);
+        assert_eq!(data.into_bytes().await.unwrap(), file);
+        // 读取后临时文件被删除
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_limits() {
+        let data = body(&[0u8; 2048]);
+        let limits = MultipartLimits {
+            max_file_bytes: 1024,
+            ..Default::default()
+        };
+        assert!(matches!(
+            collect(MultipartReader::new(&data[..], BOUNDARY, limits)).await,
+            Err(MultipartError::TooLarge { limit: 1024, .. })
+        ));
+
+        let limits = MultipartLimits {
+            max_field_bytes: 3,
+            ..Default::default()
+        };
+        assert!(matches!(
+            collect(MultipartReader::new(&data[..], BOUNDARY, limits)).await,
+            Err(MultipartError::TooLarge { limit: 3, .. })
+        ));
+
+        let limits = MultipartLimits {
+            max_parts: 1,
+            ..Default::default()
+        };
+        assert!(matches!(
+            collect(MultipartReader::new(&data[..], BOUNDARY, limits)).await,
+            Err(MultipartError::TooManyParts { limit: 1 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_malformed() {
+        let data = body(b"abc");
+        let truncated = &data[..data.len() - 30];
+        assert!(matches!(
+            collect(MultipartReader::new(
+                truncated,
+                BOUNDARY,
+                MultipartLimits::default()
+            ))
+            .await,
+            Err(MultipartError::Malformed(_))
+        ));
+        assert!(matches!(
+            collect(MultipartReader::new(
+                &b"no boundary here"[..],
+                BOUNDARY,
+                MultipartLimits::default()
+            ))
+            .await,
+            Err(MultipartError::Malformed(_))
+        ));
+        let no_disposition = format!("--{b}\r\nX-Foo: 1\r\n\r\nv\r\n--{b}--\r\n", b = BOUNDARY);
+        assert!(matches!(
+            collect(MultipartReader::new(
+                no_disposition.as_bytes(),
+                BOUNDARY,
+                MultipartLimits::default()
+            ))
+            .await,
+            Err(MultipartError::Malformed(_))
+        ));
+    }
+}
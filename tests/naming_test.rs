@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use dashmap::DashMap;
+    use zz_account::address::FreeWebMovementAddress;
+    use zz_p2p::protocols::{
+        capabilities::CAP_NAMING,
+        commands::naming::{
+            NAME_MAX_TTL_SECS, NameRecord, NameUpdate, accept, lookup, normalize_name,
+        },
+        compression::{LOCAL_CAPABILITIES, PeerCapabilities},
+        error::ProtocolError,
+    };
+
+    const NOW: u128 = 1_700_000_000_000;
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("Alice.Home"), Some("alice.home".to_string()));
+        assert_eq!(normalize_name(" bob-1 "), Some("bob-1".to_string()));
+        assert_eq!(normalize_name(""), None);
+        assert_eq!(normalize_name("with space"), None);
+        assert_eq!(normalize_name(".leading"), None);
+        assert_eq!(normalize_name("名字"), None);
+        assert_eq!(normalize_name(&"a".repeat(65)), None);
+        assert!(PeerCapabilities(LOCAL_CAPABILITIES).supports(CAP_NAMING));
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let identity = FreeWebMovementAddress::random();
+        let record = NameRecord::sign(&identity, "Alice", HOUR, NOW).unwrap();
+        assert_eq!(record.name, "alice");
+        assert_eq!(record.address, identity.to_string());
+        assert_eq!(record.expires_at, NOW + HOUR.as_millis());
+        record.verify().unwrap();
+
+        // 篡改地址后签名失效
+        let mut forged = record.clone();
+        forged.address = FreeWebMovementAddress::random().to_string();
+        assert_eq!(forged.verify(), Err(ProtocolError::BadSignature));
+
+        // 延长有效期同样会使签名失效
+        let mut extended = record.clone();
+        extended.expires_at += 1;
+        assert_eq!(extended.verify(), Err(ProtocolError::BadSignature));
+
+        // 有效期超过上限的记录被拒绝
+        let capped = NameRecord::sign(&identity, "alice", HOUR * 24 * 30, NOW).unwrap();
+        assert_eq!(
+            capped.expires_at,
+            NOW + Duration::from_secs(NAME_MAX_TTL_SECS).as_millis()
+        );
+        let mut too_long = capped;
+        too_long.expires_at += 1;
+        assert!(matches!(
+            too_long.verify(),
+            Err(ProtocolError::Decode { .. })
+        ));
+    }
+
+    #[test]
+    fn test_accept_rules() {
+        let records = DashMap::new();
+        let alice = FreeWebMovementAddress::random();
+        let mallory = FreeWebMovementAddress::random();
+
+        let first = NameRecord::sign(&alice, "alice", HOUR, NOW).unwrap();
+        assert_eq!(accept(&records, first.clone(), NOW), NameUpdate::Inserted);
+        assert_eq!(accept(&records, first.clone(), NOW), NameUpdate::Unchanged);
+
+        // 未过期前其它密钥不能抢占
+        let squat = NameRecord::sign(&mallory, "alice", HOUR, NOW + 1).unwrap();
+        assert_eq!(accept(&records, squat.clone(), NOW + 1), NameUpdate::Taken);
+        assert_eq!(lookup(&records, "alice", NOW + 1), Some(first.clone()));
+
+        // 原公钥签发的新记录覆盖旧记录
+        let renewed = NameRecord::sign(&alice, "alice", HOUR, NOW + 10).unwrap();
+        assert_eq!(
+            accept(&records, renewed.clone(), NOW + 10),
+            NameUpdate::Inserted
+        );
+        assert_eq!(accept(&records, first, NOW + 10), NameUpdate::Unchanged);
+        assert_eq!(lookup(&records, "alice", NOW + 10), Some(renewed.clone()));
+
+        // 过期后不再返回，名称可被重新登记
+        let later = renewed.expires_at;
+        assert_eq!(lookup(&records, "alice", later), None);
+        assert_eq!(accept(&records, squat, later), NameUpdate::Expired);
+        let taken_over = NameRecord::sign(&mallory, "alice", HOUR, later).unwrap();
+        assert_eq!(
+            accept(&records, taken_over.clone(), later),
+            NameUpdate::Inserted
+        );
+        assert_eq!(lookup(&records, "alice", later), Some(taken_over));
+    }
+}
@@ -0,0 +1,63 @@
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, SocketAddr};
+
+    use aex::tcp::types::Codec;
+    use zz_p2p::protocols::commands::observed::{
+        MIN_CONFIRMATIONS, OBSERVATION_TTL_MS, ObservedAddressBook, ObservedAddressCommand,
+    };
+
+    fn sock(s: &str) -> SocketAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_command_roundtrip() {
+        let cmd = ObservedAddressCommand {
+            observed: "203.0.113.7:40123".to_string(),
+        };
+        let bytes = Codec::encode(&cmd).unwrap();
+        let decoded: ObservedAddressCommand = Codec::decode(&bytes).unwrap();
+        assert_eq!(decoded, cmd);
+    }
+
+    #[test]
+    fn test_requires_distinct_reporters() {
+        assert_eq!(MIN_CONFIRMATIONS, 2);
+        let mut book = ObservedAddressBook::default();
+        assert!(book.record("peer-a", sock("203.0.113.7:40123"), 0));
+        // 同一对端重复报告不算新的确认
+        assert!(book.record("peer-a", sock("203.0.113.7:40124"), 10));
+        assert!(book.confirmed(20).is_empty());
+
+        assert!(book.record("peer-b", sock("203.0.113.7:50000"), 30));
+        let ip: IpAddr = "203.0.113.7".parse().unwrap();
+        assert_eq!(book.confirmed(40), vec![ip]);
+
+        let entries = book.entries(40);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].reporters, 2);
+        assert_eq!(entries[0].port, 50000);
+        assert!(entries[0].confirmed);
+    }
+
+    #[test]
+    fn test_ignores_inner_addresses() {
+        let mut book = ObservedAddressBook::default();
+        assert!(!book.record("peer-a", sock("192.168.1.5:1000"), 0));
+        assert!(!book.record("peer-b", sock("127.0.0.1:1000"), 0));
+        assert!(!book.record("peer-c", sock("0.0.0.0:1000"), 0));
+        assert!(book.entries(0).is_empty());
+    }
+
+    #[test]
+    fn test_observations_expire() {
+        let mut book = ObservedAddressBook::default();
+        book.record("peer-a", sock("198.51.100.1:1"), 0);
+        book.record("peer-b", sock("198.51.100.1:2"), OBSERVATION_TTL_MS / 2);
+        assert_eq!(book.confirmed(OBSERVATION_TTL_MS / 2).len(), 1);
+        // peer-a 的报告过期后只剩一个报告者
+        assert!(book.confirmed(OBSERVATION_TTL_MS + 1).is_empty());
+        assert!(book.entries(2 * OBSERVATION_TTL_MS).is_empty());
+    }
+}
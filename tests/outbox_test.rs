@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use aex::connection::global::GlobalContext;
+    use zz_p2p::{
+        clis::send::NotConnected,
+        outbox::{self, OutboxList},
+    };
+
+    #[test]
+    fn test_push_and_remove_keep_order() {
+        let mut list = OutboxList::default();
+        let first = list.push("me", "alice", "hi", 1);
+        let second = list.push("me", "bob", "yo", 2);
+        let third = list.push("me", "alice", "again", 3);
+        assert_eq!((first.id, second.id, third.id), (1, 2, 3));
+
+        assert_eq!(list.remove(2), Some(second));
+        assert!(list.remove(2).is_none());
+        let ids: Vec<u64> = list.drafts.iter().map(|d| d.id).collect();
+        assert_eq!(ids, vec![1, 3]);
+        // id 不复用
+        assert_eq!(list.push("me", "carol", "new", 4).id, 4);
+    }
+
+    #[test]
+    fn test_outbox_round_trips_through_json() {
+        let mut list = OutboxList::default();
+        list.push("me", "alice", "hi", 42);
+        let json = serde_json::to_value(&list).unwrap();
+        let back: OutboxList = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(back, list);
+
+        // 失败记录字段可以省略
+        let mut draft = json["drafts"][0].clone();
+        let fields = draft.as_object_mut().unwrap();
+        fields.remove("attempts");
+        fields.remove("last_error");
+        let draft: outbox::Draft = serde_json::from_value(draft).unwrap();
+        assert_eq!(draft.attempts, 0);
+        assert!(draft.last_error.is_none());
+    }
+
+    #[test]
+    fn test_not_connected_is_recognisable() {
+        let err: anyhow::Error = NotConnected {
+            receiver: "alice".into(),
+            reachability: "unknown".into(),
+        }
+        .into();
+        assert_eq!(err.to_string(), "alice is not connected (unknown)");
+        assert!(err.downcast_ref::<NotConnected>().is_some());
+        assert!(
+            anyhow::anyhow!("Address not set")
+                .downcast_ref::<NotConnected>()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_queue_list_and_remove() {
+        let gctx = Arc::new(GlobalContext::new("127.0.0.1:0".parse().unwrap(), None));
+        assert!(outbox::list(&gctx).await.is_empty());
+        let draft = outbox::queue(&gctx, "me", "alice", "hi").await.unwrap();
+        assert_eq!(outbox::list(&gctx).await, vec![draft.clone()]);
+        // 没有运行中的节点时不尝试发送
+        assert_eq!(outbox::flush_once(&gctx).await, 0);
+        assert_eq!(outbox::remove(&gctx, draft.id).await, Some(draft));
+        assert!(outbox::list(&gctx).await.is_empty());
+    }
+}
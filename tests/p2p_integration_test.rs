@@ -11,10 +11,12 @@ use aex::{
 };
 use tokio::sync::Mutex;
 
+use zz_p2p::protocols::agent::SoftwareInfo;
 use zz_p2p::protocols::command::{Action, Entity, P2PCommand};
 use zz_p2p::protocols::commands::online::OnlineCommand;
 use zz_p2p::protocols::frame::P2PFrame;
 use zz_p2p::protocols::registry::register;
+use zz_p2p::protocols::version::CURRENT_PROTOCOL_VERSION;
 
 #[tokio::test]
 async fn test_p2p_command_encoding() {
@@ -38,6 +40,10 @@ async fn test_online_command_encoding() {
         intranet_ips: vec![],
         wan_ips: vec![],
         seeds: None,
+        capabilities: 0,
+        protocol_version: CURRENT_PROTOCOL_VERSION,
+        max_frame_size: 0,
+        agent: SoftwareInfo::default(),
     };
 
     let encoded = Codec::encode(&online_cmd).unwrap();
@@ -209,6 +215,10 @@ async fn test_online_command_all_fields() {
         intranet_ips: vec![],
         wan_ips: vec![],
         seeds: None,
+        capabilities: 0,
+        protocol_version: CURRENT_PROTOCOL_VERSION,
+        max_frame_size: 0,
+        agent: SoftwareInfo::new("test-agent/1.0"),
     };
 
     let encoded = Codec::encode(&online_cmd).unwrap();
@@ -217,4 +227,5 @@ async fn test_online_command_all_fields() {
     assert_eq!(decoded.session_id, vec![1, 2, 3, 4, 5]);
     assert_eq!(decoded.node.port, 9000);
     assert_eq!(decoded.ephemeral_public_key, [1u8; 32]);
+    assert_eq!(decoded.agent, SoftwareInfo::new("test-agent/1.0"));
 }
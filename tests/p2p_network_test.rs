@@ -12,10 +12,12 @@ use tokio::net::TcpListener;
 use tokio::sync::Mutex;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+use zz_p2p::protocols::agent::SoftwareInfo;
 use zz_p2p::protocols::command::{Action, Entity, P2PCommand};
 use zz_p2p::protocols::commands::online::OnlineCommand;
 use zz_p2p::protocols::frame::P2PFrame;
 use zz_p2p::protocols::registry::register;
+use zz_p2p::protocols::version::CURRENT_PROTOCOL_VERSION;
 
 fn setup_logging() {
     let _ = tracing_subscriber::registry()
@@ -59,6 +61,10 @@ async fn send_online_command(
         intranet_ips: vec![],
         wan_ips: vec![],
         seeds: None,
+        capabilities: 0,
+        protocol_version: CURRENT_PROTOCOL_VERSION,
+        max_frame_size: 0,
+        agent: SoftwareInfo::default(),
     };
 
     let cmd = P2PCommand::new(
@@ -0,0 +1,55 @@
+#[cfg(test)]
+mod tests {
+    use zz_p2p::web::params::{ListQuery, MAX_PAGE_LIMIT, Params, path_only};
+
+    #[test]
+    fn test_params_decode() {
+        let params = Params::parse("/api/conversations?filter=hello%20world&contact=a+b&flag");
+        assert_eq!(params.get("filter"), Some("hello world"));
+        assert_eq!(params.get("contact"), Some("a b"));
+        assert_eq!(params.get("flag"), Some(""));
+        assert_eq!(params.get("missing"), None);
+        assert_eq!(Params::parse("/peers"), Params::default());
+        assert_eq!(Params::parse("limit=5").get("limit"), Some("5"));
+        assert_eq!(path_only("/peers?limit=5"), "/peers");
+    }
+
+    #[test]
+    fn test_list_query_parse() {
+        let q = ListQuery::parse("/peers?limit=10&offset=20&since=1700000000&filter=Node").unwrap();
+        assert_eq!(q.limit, Some(10));
+        assert_eq!(q.offset, 20);
+        assert_eq!(q.since, Some(1_700_000_000));
+        assert_eq!(q.filter.as_deref(), Some("node"));
+
+        assert_eq!(ListQuery::parse("/peers").unwrap(), ListQuery::default());
+        assert!(ListQuery::parse("/peers?limit=abc").is_err());
+        assert!(ListQuery::parse("/peers?offset=-1").is_err());
+        assert!(ListQuery::parse(&format!("/peers?limit={}", MAX_PAGE_LIMIT + 1)).is_err());
+    }
+
+    #[test]
+    fn test_page_filters_then_paginates() {
+        let q = ListQuery::parse("?limit=2&offset=1&since=3").unwrap();
+        let page = q.page(1..=10i64, |n| q.is_since(*n));
+        assert_eq!(page.items, vec![4, 5]);
+        assert_eq!(page.total, 8);
+        assert!(page.has_more());
+        assert_eq!(page.meta()["total"], 8);
+
+        let last = ListQuery::parse("?offset=7&since=3").unwrap();
+        let page = last.page(1..=10i64, |n| last.is_since(*n));
+        assert_eq!(page.items, vec![10]);
+        assert!(!page.has_more());
+        assert_eq!(page.meta()["limit"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_filter_matches_any_field() {
+        let q = ListQuery::parse("?filter=LO").unwrap();
+        assert!(q.is_match(&["hello", "x"]));
+        assert!(q.is_match(&["x", "Lobby"]));
+        assert!(!q.is_match(&["abc"]));
+        assert!(ListQuery::default().is_match(&[]));
+    }
+}
@@ -0,0 +1,131 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use zz_p2p::{
+        config::Config,
+        journal::Event,
+        partition::{PartitionConfig, PartitionDetector, Transition},
+    };
+
+    const SEC: u128 = 1000;
+
+    fn policy() -> PartitionConfig {
+        PartitionConfig {
+            check_interval_secs: 5,
+            after_secs: 30,
+            min_retry_secs: 30,
+            max_retry_secs: 120,
+        }
+    }
+
+    #[test]
+    fn test_detect_after_threshold_and_recover() {
+        let policy = policy();
+        let mut detector = PartitionDetector::default();
+        // 从未连上过节点时交给引导任务
+        assert_eq!(detector.observe(0, 0, &policy), None);
+        assert_eq!(detector.observe(100 * SEC, 0, &policy), None);
+
+        assert_eq!(detector.observe(200 * SEC, 2, &policy), None);
+        assert_eq!(detector.observe(210 * SEC, 0, &policy), None);
+        assert_eq!(detector.observe(230 * SEC, 0, &policy), None);
+        assert!(!detector.is_partitioned());
+        assert_eq!(
+            detector.observe(240 * SEC, 0, &policy),
+            Some(Transition::Detected { isolated_secs: 30 })
+        );
+        assert!(detector.is_partitioned());
+        // 只报告一次
+        assert_eq!(detector.observe(300 * SEC, 0, &policy), None);
+
+        detector.record_rebootstrap(240 * SEC);
+        assert_eq!(
+            detector.observe(340 * SEC, 1, &policy),
+            Some(Transition::Recovered {
+                duration_secs: 100,
+                rebootstraps: 1,
+                peers: 1
+            })
+        );
+        let report = detector.report();
+        assert!(!report.partitioned);
+        assert_eq!((report.partitions, report.rebootstraps), (1, 1));
+    }
+
+    #[test]
+    fn test_brief_outage_is_not_a_partition() {
+        let policy = policy();
+        let mut detector = PartitionDetector::default();
+        detector.observe(0, 1, &policy);
+        detector.observe(10 * SEC, 0, &policy);
+        assert_eq!(detector.observe(20 * SEC, 1, &policy), None);
+        // 断开计时重新开始
+        assert_eq!(detector.observe(35 * SEC, 0, &policy), None);
+        assert_eq!(detector.observe(60 * SEC, 0, &policy), None);
+        assert!(detector.observe(65 * SEC, 0, &policy).is_some());
+
+        // after_secs = 0 关闭检测
+        let disabled = PartitionConfig {
+            after_secs: 0,
+            ..policy
+        };
+        let mut detector = PartitionDetector::default();
+        detector.observe(0, 1, &disabled);
+        assert_eq!(detector.observe(3600 * SEC, 0, &disabled), None);
+    }
+
+    #[test]
+    fn test_rebootstrap_frequency_is_capped() {
+        let policy = policy();
+        assert_eq!(policy.retry_delay(1), Duration::from_secs(30));
+        assert_eq!(policy.retry_delay(2), Duration::from_secs(60));
+        assert_eq!(policy.retry_delay(3), Duration::from_secs(120));
+        assert_eq!(policy.retry_delay(40), Duration::from_secs(120));
+
+        let mut detector = PartitionDetector::default();
+        assert!(!detector.rebootstrap_due(0, &policy));
+        detector.observe(0, 1, &policy);
+        detector.observe(SEC, 0, &policy);
+        detector.observe(31 * SEC, 0, &policy);
+
+        // 判定为分区后立即重新引导
+        assert!(detector.rebootstrap_due(31 * SEC, &policy));
+        detector.record_rebootstrap(31 * SEC);
+        assert!(!detector.rebootstrap_due(60 * SEC, &policy));
+        assert!(detector.rebootstrap_due(61 * SEC, &policy));
+        detector.record_rebootstrap(61 * SEC);
+        assert!(!detector.rebootstrap_due(120 * SEC, &policy));
+        assert!(detector.rebootstrap_due(121 * SEC, &policy));
+    }
+
+    #[test]
+    fn test_config_and_events() {
+        let config: Config = toml::from_str(
+            r#"
+            [partition]
+            after_secs = 10
+            max_retry_secs = 60
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.partition.after_secs, 10);
+        assert_eq!(config.partition.max_retry_secs, 60);
+        assert_eq!(
+            config.partition.min_retry_secs,
+            PartitionConfig::default().min_retry_secs
+        );
+
+        let detected = Event::PartitionDetected { isolated_secs: 30 };
+        assert_eq!(detected.name(), "network.partition_detected");
+        assert!(detected.matches("network"));
+        let recovered = Event::PartitionRecovered {
+            duration_secs: 90,
+            rebootstraps: 2,
+            peers: 3,
+        };
+        let json = serde_json::to_value(&recovered).unwrap();
+        assert_eq!(json["event"], "network.partition_recovered");
+        assert_eq!(json["rebootstraps"], 2);
+    }
+}
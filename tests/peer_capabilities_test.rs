@@ -0,0 +1,116 @@
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use aex::connection::protocol::Protocol;
+    use zz_p2p::{
+        journal::{Event, JournalEntry},
+        peer_capabilities::{CapabilityProfile, CapabilityRegression},
+        protocols::capabilities::{CAP_PUBSUB, CAP_RELAY},
+        record::{NodeRecord, NodeRegistry},
+    };
+
+    fn profile(protocols: &[Protocol], features: u32, version: u8) -> CapabilityProfile {
+        CapabilityProfile::new(protocols.iter().cloned().collect(), features, version)
+    }
+
+    fn endpoint() -> SocketAddr {
+        "10.0.0.2:10086".parse().unwrap()
+    }
+
+    #[test]
+    fn test_lost_transport_is_a_regression() {
+        let before = profile(&[Protocol::Tcp, Protocol::Http], CAP_RELAY, 3);
+        let after = profile(&[Protocol::Tcp], CAP_RELAY, 3);
+        let regression = after.regression_since(&before).unwrap();
+        assert_eq!(regression.lost_protocols, vec!["http".to_string()]);
+        assert!(regression.lost_features.is_empty());
+        assert_eq!(regression.protocol_version, None);
+        assert_eq!(regression.to_string(), "lost transports http");
+
+        // 新增能力不是退化
+        assert!(before.regression_since(&after).is_none());
+        assert!(before.regression_since(&before).is_none());
+    }
+
+    #[test]
+    fn test_lost_features_and_version() {
+        let before = profile(&[Protocol::Tcp], CAP_RELAY | CAP_PUBSUB, 3);
+        let after = profile(&[Protocol::Tcp], CAP_PUBSUB, 2);
+        let regression = after.regression_since(&before).unwrap();
+        assert_eq!(regression.lost_features, vec!["relay".to_string()]);
+        assert_eq!(regression.protocol_version, Some((3, 2)));
+        assert_eq!(
+            regression.to_string(),
+            "lost features relay; protocol v3 → v2"
+        );
+    }
+
+    #[test]
+    fn test_undeclared_capabilities_are_ignored() {
+        let before = profile(&[Protocol::Tcp, Protocol::Http], CAP_RELAY, 3);
+        let after = profile(&[], 0, 3);
+        assert!(after.regression_since(&before).is_none());
+    }
+
+    #[test]
+    fn test_registry_tracks_capabilities_across_sessions() {
+        let mut registry = NodeRegistry::default();
+        let first = profile(&[Protocol::Tcp, Protocol::Http], CAP_RELAY, 3);
+        // 不在列表中的地址忽略
+        assert!(
+            registry
+                .record_capabilities(endpoint(), first.clone())
+                .is_none()
+        );
+        assert!(registry.get(endpoint()).is_none());
+
+        registry.add(endpoint(), false);
+        assert!(registry.record_capabilities(endpoint(), first).is_none());
+        let regression = registry
+            .record_capabilities(endpoint(), profile(&[Protocol::Tcp], CAP_RELAY, 3))
+            .unwrap();
+        assert_eq!(regression.lost_protocols, vec!["http".to_string()]);
+        // 退化后以新的能力为基准
+        assert!(
+            registry
+                .record_capabilities(endpoint(), profile(&[Protocol::Tcp], CAP_RELAY, 3))
+                .is_none()
+        );
+
+        // 随记录一起持久化，旧记录没有该字段
+        let record = registry.get(endpoint()).unwrap();
+        let json = serde_json::to_string(record).unwrap();
+        let back: NodeRecord = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.capabilities, record.capabilities);
+        let mut legacy = serde_json::to_value(record).unwrap();
+        legacy.as_object_mut().unwrap().remove("capabilities");
+        let legacy: NodeRecord = serde_json::from_value(legacy).unwrap();
+        assert!(legacy.capabilities.is_none());
+    }
+
+    #[test]
+    fn test_downgrade_event_serialization() {
+        let event = Event::CapabilityDowngraded {
+            peer: "p1".to_string(),
+            addr: endpoint().to_string(),
+            change: CapabilityRegression {
+                lost_protocols: vec!["udp".to_string()],
+                lost_features: vec![],
+                protocol_version: None,
+            },
+        };
+        assert_eq!(event.name(), "peer.capability_downgraded");
+        assert!(event.matches("peer"));
+        let entry = JournalEntry {
+            at: chrono::Utc::now(),
+            event,
+        };
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(json["event"], "peer.capability_downgraded");
+        assert_eq!(json["lost_protocols"][0], "udp");
+        let back: JournalEntry = serde_json::from_value(json).unwrap();
+        assert_eq!(back, entry);
+        assert!(back.to_string().contains("lost transports udp"));
+    }
+}
@@ -0,0 +1,121 @@
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, net::SocketAddr, path::Path};
+
+    use chrono::{Duration, Utc};
+    use zz_p2p::{
+        config::Config,
+        peer_maintenance::{
+            DEFAULT_MAX_PROBES, PeerMaintenanceConfig, due_for_probe, prune_and_decay,
+        },
+        peer_store::PEER_RETENTION_DAYS,
+        record::{NodeRecord, NodeRegistry, SharedNodeRegistry},
+    };
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([203, 0, 113, 1], port))
+    }
+
+    fn record(port: u16, days_ago: i64, successes: u64) -> NodeRecord {
+        let mut record = NodeRecord::new(addr(port));
+        record.last_seen = Utc::now() - Duration::days(days_ago);
+        record.tries = (successes, 0);
+        record
+    }
+
+    fn registry(records: Vec<NodeRecord>) -> NodeRegistry {
+        NodeRegistry::new(records.into_iter().collect::<HashSet<_>>())
+    }
+
+    #[test]
+    fn test_default_policy() {
+        let policy = PeerMaintenanceConfig::default();
+        assert_eq!(policy.prune_after_days, PEER_RETENTION_DAYS);
+        assert_eq!(policy.max_probes, DEFAULT_MAX_PROBES);
+        assert!(policy.decay_factor < 1.0);
+    }
+
+    #[test]
+    fn test_due_for_probe_oldest_first_and_limited() {
+        let registry = registry(vec![record(1, 0, 1), record(2, 3, 1), record(3, 10, 1)]);
+        let policy = PeerMaintenanceConfig {
+            max_probes: 1,
+            ..Default::default()
+        };
+        assert_eq!(due_for_probe(&registry, &policy, Utc::now()), vec![addr(3)]);
+
+        let policy = PeerMaintenanceConfig::default();
+        assert_eq!(
+            due_for_probe(&registry, &policy, Utc::now()),
+            vec![addr(3), addr(2)]
+        );
+    }
+
+    #[test]
+    fn test_prune_and_decay() {
+        let mut registry = registry(vec![record(1, 0, 10), record(2, 3, 10), record(3, 40, 10)]);
+        let policy = PeerMaintenanceConfig {
+            decay_factor: 0.5,
+            ..Default::default()
+        };
+        let (pruned, decayed) = prune_and_decay(&mut registry, &policy, Utc::now());
+        assert_eq!((pruned, decayed), (1, 1));
+        assert_eq!(registry.nodes.len(), 2);
+
+        let fresh = registry.nodes.get(&NodeRecord::new(addr(1))).unwrap();
+        assert_eq!(fresh.tries.0, 10);
+        let stale = registry.nodes.get(&NodeRecord::new(addr(2))).unwrap();
+        assert_eq!(stale.tries.0, 5);
+        assert!(stale.score() > 0.0);
+    }
+
+    #[test]
+    fn test_disabled_prune_and_decay() {
+        let mut registry = registry(vec![record(1, 400, 10)]);
+        let policy = PeerMaintenanceConfig {
+            prune_after_days: 0,
+            decay_factor: 1.0,
+            ..Default::default()
+        };
+        assert_eq!(prune_and_decay(&mut registry, &policy, Utc::now()), (0, 0));
+        assert_eq!(registry.nodes.len(), 1);
+    }
+
+    #[test]
+    fn test_record_probe_revives_expired_record() {
+        let mut registry = registry(vec![record(1, 10, 1)]);
+        assert!(registry.get_available_nodes().is_empty());
+
+        assert!(registry.record_probe(addr(1), true));
+        assert_eq!(registry.get_available_nodes().len(), 1);
+        // 不在列表中的地址不会被加入
+        assert!(!registry.record_probe(addr(2), true));
+        assert_eq!(registry.nodes.len(), 1);
+    }
+
+    #[test]
+    fn test_failed_probe_counts_failure() {
+        let mut registry = registry(vec![record(1, 1, 3)]);
+        registry.record_probe(addr(1), false);
+        let record = registry.nodes.get(&NodeRecord::new(addr(1))).unwrap();
+        assert_eq!(record.tries, (3, 1));
+        assert!(record.is_available);
+    }
+
+    #[test]
+    fn test_shared_registry_is_shared_between_clones() {
+        let shared = SharedNodeRegistry::new(HashSet::new());
+        let clone = shared.clone();
+        clone.write().upsert(addr(9), true);
+        assert_eq!(shared.snapshot().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_peer_maintenance_config() {
+        let text = "[peer_maintenance]\ninterval_secs = 60\nprune_after_days = 7\n";
+        let config = Config::parse(text, Path::new("node.toml")).unwrap();
+        assert_eq!(config.peer_maintenance.interval_secs, 60);
+        assert_eq!(config.peer_maintenance.prune_after_days, 7);
+        assert_eq!(config.peer_maintenance.max_probes, DEFAULT_MAX_PROBES);
+    }
+}
@@ -0,0 +1,97 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use zz_p2p::protocols::peer_stats::{
+        EWMA_WEIGHT, MIN_ERROR_SAMPLES, PeerStats, SLOW_RTT_MS, SLOW_SEND_MS,
+    };
+
+    fn ms(v: f64) -> Duration {
+        Duration::from_secs_f64(v / 1000.0)
+    }
+
+    #[test]
+    fn test_first_sample_sets_average() {
+        let mut stats = PeerStats::default();
+        stats.record_rtt(ms(100.0));
+        assert_eq!(stats.rtt_ms, Some(100.0));
+    }
+
+    #[test]
+    fn test_ewma_moves_toward_sample() {
+        let mut stats = PeerStats::default();
+        stats.record_rtt(ms(100.0));
+        stats.record_rtt(ms(200.0));
+        let expected = 100.0 + EWMA_WEIGHT * 100.0;
+        assert!((stats.rtt_ms.unwrap() - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_failed_send_counts_as_error() {
+        let mut stats = PeerStats::default();
+        stats.record_sent(ms(1.0), true);
+        stats.record_sent(ms(1.0), false);
+        assert_eq!(stats.frames_out, 2);
+        assert_eq!(stats.errors, 1);
+    }
+
+    #[test]
+    fn test_error_rate_needs_enough_samples() {
+        let mut stats = PeerStats::default();
+        for _ in 0..MIN_ERROR_SAMPLES - 1 {
+            stats.record_sent(ms(1.0), false);
+        }
+        assert_eq!(stats.error_rate(), 0.0);
+        assert_eq!(stats.evaluate(), None);
+
+        stats.record_sent(ms(1.0), false);
+        assert_eq!(stats.error_rate(), 1.0);
+        assert_eq!(stats.evaluate(), Some(true));
+    }
+
+    #[test]
+    fn test_slow_rtt_degrades() {
+        let mut stats = PeerStats::default();
+        stats.record_rtt(ms(SLOW_RTT_MS + 1.0));
+        assert_eq!(stats.evaluate(), Some(true));
+        assert!(stats.degraded);
+        assert_eq!(stats.evaluate(), None);
+    }
+
+    #[test]
+    fn test_slow_send_degrades() {
+        let mut stats = PeerStats::default();
+        stats.record_sent(ms(SLOW_SEND_MS * 2.0), true);
+        assert_eq!(stats.evaluate(), Some(true));
+    }
+
+    #[test]
+    fn test_healthy_peer_not_degraded() {
+        let mut stats = PeerStats::default();
+        for _ in 0..50 {
+            stats.record_inbound();
+            stats.record_sent(ms(5.0), true);
+            stats.record_rtt(ms(50.0));
+        }
+        assert_eq!(stats.evaluate(), None);
+        assert!(!stats.degraded);
+    }
+
+    #[test]
+    fn test_recovery_requires_half_threshold() {
+        let mut stats = PeerStats {
+            rtt_ms: Some(SLOW_RTT_MS * 1.5),
+            ..Default::default()
+        };
+        assert_eq!(stats.evaluate(), Some(true));
+
+        // 低于阈值但高于一半：仍然降级
+        stats.rtt_ms = Some(SLOW_RTT_MS * 0.8);
+        assert_eq!(stats.evaluate(), None);
+        assert!(stats.degraded);
+
+        stats.rtt_ms = Some(SLOW_RTT_MS * 0.4);
+        assert_eq!(stats.evaluate(), Some(false));
+        assert!(!stats.degraded);
+    }
+}
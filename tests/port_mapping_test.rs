@@ -0,0 +1,79 @@
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddr};
+
+    use zz_p2p::port_mapping::{
+        MappingMethod, MappingProtocol, PortMapping, natpmp_external_request,
+        natpmp_mapping_request, natpmp_parse_external, natpmp_parse_mapping, parse_default_gateway,
+    };
+
+    #[test]
+    fn test_natpmp_requests() {
+        assert_eq!(natpmp_external_request(), [0, 0]);
+        assert_eq!(
+            natpmp_mapping_request(MappingProtocol::Tcp, 9000, 9000, 3600),
+            [0, 2, 0, 0, 0x23, 0x28, 0x23, 0x28, 0, 0, 0x0e, 0x10]
+        );
+        // lifetime 为 0 表示删除
+        assert_eq!(
+            natpmp_mapping_request(MappingProtocol::Udp, 9000, 0, 0),
+            [0, 1, 0, 0, 0x23, 0x28, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_natpmp_responses() {
+        let external = [0, 128, 0, 0, 0, 0, 0, 42, 203, 0, 113, 7];
+        assert_eq!(
+            natpmp_parse_external(&external).unwrap(),
+            Ipv4Addr::new(203, 0, 113, 7)
+        );
+
+        let mapping = [
+            0, 130, 0, 0, 0, 0, 0, 42, 0x23, 0x28, 0x23, 0x29, 0, 0, 0x0e, 0x10,
+        ];
+        assert_eq!(
+            natpmp_parse_mapping(&mapping, MappingProtocol::Tcp).unwrap(),
+            (9000, 9001, 3600)
+        );
+        // 协议不符、结果码非 0 与长度不足都是错误
+        assert!(natpmp_parse_mapping(&mapping, MappingProtocol::Udp).is_err());
+        let refused = [0, 128, 0, 2, 0, 0, 0, 42, 0, 0, 0, 0];
+        assert!(natpmp_parse_external(&refused).is_err());
+        assert!(natpmp_parse_external(&external[..8]).is_err());
+    }
+
+    #[test]
+    fn test_default_gateway_from_route_table() {
+        let table = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\n\
+                     eth0\t0001A8C0\t00000000\t0001\t0\t0\t0\t00FFFFFF\n\
+                     eth0\t00000000\t0101A8C0\t0003\t0\t0\t100\t00000000\n";
+        assert_eq!(
+            parse_default_gateway(table),
+            Some(Ipv4Addr::new(192, 168, 1, 1))
+        );
+        assert_eq!(parse_default_gateway("Iface\tDestination\tGateway\n"), None);
+    }
+
+    #[test]
+    fn test_renewal_and_public() {
+        let mapping = PortMapping {
+            protocol: MappingProtocol::Tcp,
+            internal_port: 9000,
+            external: "203.0.113.7:9000".parse::<SocketAddr>().unwrap(),
+            method: MappingMethod::NatPmp,
+            lease_secs: 3600,
+            expires_at: 3_600_000,
+        };
+        assert!(!mapping.needs_renewal(0));
+        assert!(!mapping.needs_renewal(1_799_999));
+        assert!(mapping.needs_renewal(1_800_000));
+        assert!(mapping.is_public());
+
+        let double_nat = PortMapping {
+            external: "10.0.0.2:9000".parse().unwrap(),
+            ..mapping
+        };
+        assert!(!double_nat.is_public());
+    }
+}
@@ -0,0 +1,150 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use dashmap::DashMap;
+    use zz_account::address::FreeWebMovementAddress;
+    use zz_p2p::protocols::{
+        capabilities::CAP_PRESENCE,
+        commands::presence::{
+            PRESENCE_SEEN_MIN_INTERVAL_MS, PresenceEntry, PresenceRecord, PresenceUpdate,
+            Reachability, accept, lookup,
+        },
+        compression::{LOCAL_CAPABILITIES, PeerCapabilities},
+        error::ProtocolError,
+    };
+
+    const NOW: u128 = 1_700_000_000_000;
+    const TTL: Duration = Duration::from_secs(600);
+
+    fn record(identity: &FreeWebMovementAddress, online: bool, at: u128) -> PresenceRecord {
+        PresenceRecord::sign(
+            identity,
+            vec!["203.0.113.7:9000".to_string()],
+            online,
+            TTL,
+            at,
+        )
+    }
+
+    fn entry(record: PresenceRecord, seen_by: Option<&str>, seen_at: u128) -> PresenceEntry {
+        PresenceEntry {
+            record,
+            seen_by: seen_by.map(|s| s.to_string()),
+            seen_at,
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let identity = FreeWebMovementAddress::random();
+        let record = record(&identity, true, NOW);
+        assert_eq!(record.address, identity.to_string());
+        assert_eq!(record.expires_at, NOW + TTL.as_millis());
+        record.verify().unwrap();
+        assert!(PeerCapabilities(LOCAL_CAPABILITIES).supports(CAP_PRESENCE));
+
+        // 篡改在线状态或端点后签名失效
+        let mut forged = record.clone();
+        forged.online = false;
+        assert_eq!(forged.verify(), Err(ProtocolError::BadSignature));
+        let mut redirected = record.clone();
+        redirected.endpoints = vec!["198.51.100.1:9000".to_string()];
+        assert_eq!(redirected.verify(), Err(ProtocolError::BadSignature));
+
+        let mut garbage = record;
+        garbage.endpoints = vec!["not-an-endpoint".to_string()];
+        assert!(matches!(
+            garbage.verify(),
+            Err(ProtocolError::Decode { .. })
+        ));
+    }
+
+    #[test]
+    fn test_accept_newer_and_seen_by() {
+        let identity = FreeWebMovementAddress::random();
+        let address = identity.to_string();
+        let table = DashMap::new();
+        let first = record(&identity, true, NOW);
+
+        assert_eq!(
+            accept(&table, entry(first.clone(), Some("server-a"), NOW), NOW),
+            PresenceUpdate::Inserted
+        );
+        // 同一记录重复到达
+        assert_eq!(
+            accept(&table, entry(first.clone(), Some("server-a"), NOW), NOW),
+            PresenceUpdate::Unchanged
+        );
+        // 另一台服务器稍后直接看到同一记录
+        let later = NOW + PRESENCE_SEEN_MIN_INTERVAL_MS;
+        assert_eq!(
+            accept(&table, entry(first.clone(), Some("server-b"), later), later),
+            PresenceUpdate::Inserted
+        );
+        assert_eq!(
+            lookup(&table, &address, later).unwrap().seen_by.as_deref(),
+            Some("server-b")
+        );
+
+        // 泛洪到达的新记录沿用最后一台服务器
+        let refreshed = record(&identity, true, NOW + 1000);
+        assert_eq!(
+            accept(&table, entry(refreshed, None, NOW + 1000), later),
+            PresenceUpdate::Inserted
+        );
+        let current = lookup(&table, &address, later).unwrap();
+        assert_eq!(current.record.issued_at, NOW + 1000);
+        assert_eq!(current.seen_by.as_deref(), Some("server-b"));
+
+        // 旧记录不能覆盖新记录
+        assert_eq!(
+            accept(&table, entry(first, Some("server-c"), later * 2), later),
+            PresenceUpdate::Unchanged
+        );
+
+        // 过期后查不到
+        let expired = NOW + 1000 + TTL.as_millis();
+        assert!(lookup(&table, &address, expired).is_none());
+        assert_eq!(
+            accept(
+                &table,
+                entry(record(&identity, true, NOW), None, NOW),
+                expired
+            ),
+            PresenceUpdate::Expired
+        );
+    }
+
+    #[test]
+    fn test_reachability() {
+        let identity = FreeWebMovementAddress::random();
+        let online = entry(record(&identity, true, NOW), Some("server-a"), NOW);
+        assert_eq!(
+            Reachability::assess(Some(&online), true, NOW),
+            Reachability::Connected
+        );
+
+        let likely = Reachability::assess(Some(&online), false, NOW + 30_000);
+        assert!(likely.is_likely_reachable());
+        assert_eq!(
+            likely.to_string(),
+            "likely online, last seen 30s ago by server-a"
+        );
+
+        let offline = entry(record(&identity, false, NOW), None, NOW);
+        let gone = Reachability::assess(Some(&offline), false, NOW + 5_000);
+        assert_eq!(gone, Reachability::Offline { age_ms: 5_000 });
+        assert!(!gone.is_likely_reachable());
+
+        let stale = NOW + TTL.as_millis();
+        assert_eq!(
+            Reachability::assess(Some(&online), false, stale),
+            Reachability::Unknown
+        );
+        assert_eq!(
+            Reachability::assess(None, false, NOW),
+            Reachability::Unknown
+        );
+    }
+}
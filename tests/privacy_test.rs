@@ -0,0 +1,156 @@
+#[cfg(test)]
+mod tests {
+    use std::{net::IpAddr, path::Path};
+
+    use zz_p2p::{
+        config::Config,
+        protocols::{
+            acl::AclTarget,
+            commands::{
+                ack::SeedRecord,
+                seed_sync::{SeedInfo, SeedSet},
+            },
+            privacy::{PrivacyConfig, Visibility, VisibilityRule},
+        },
+    };
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn rule(target: &str, visibility: Visibility) -> VisibilityRule {
+        VisibilityRule {
+            target: AclTarget::parse(target).unwrap(),
+            visibility,
+        }
+    }
+
+    #[test]
+    fn test_visibility_recipients() {
+        let lan_peer = ip("192.168.1.20");
+        let wan_peer = ip("8.8.8.8");
+
+        assert!(Visibility::Public.visible_to(Some(&wan_peer)));
+        assert!(Visibility::Public.visible_to(None));
+        assert!(Visibility::Lan.visible_to(Some(&lan_peer)));
+        assert!(!Visibility::Lan.visible_to(Some(&wan_peer)));
+        // 广播无法确定接收方，内网端点不发送
+        assert!(!Visibility::Lan.visible_to(None));
+        assert!(!Visibility::Private.visible_to(Some(&lan_peer)));
+    }
+
+    #[test]
+    fn test_defaults_share_everything() {
+        let policy = PrivacyConfig::default();
+        assert_eq!(policy.visibility(&ip("10.0.0.5"), None), Visibility::Public);
+        assert_eq!(policy.visibility(&ip("1.2.3.4"), None), Visibility::Public);
+        assert!(policy.shares(&ip("10.0.0.5"), None, Some(&ip("8.8.8.8"))));
+    }
+
+    #[test]
+    fn test_scope_defaults_and_rules() {
+        let policy = PrivacyConfig {
+            lan: Visibility::Lan,
+            public: Visibility::Public,
+            rules: vec![
+                rule("10.8.0.0/16", Visibility::Private),
+                rule("1.2.3.4", Visibility::Lan),
+                rule("node-b", Visibility::Private),
+                rule("10.0.0.0/8", Visibility::Public),
+            ],
+        };
+        // 第一条匹配的规则生效
+        assert_eq!(
+            policy.visibility(&ip("10.8.1.1"), None),
+            Visibility::Private
+        );
+        assert_eq!(policy.visibility(&ip("10.1.1.1"), None), Visibility::Public);
+        assert_eq!(policy.visibility(&ip("1.2.3.4"), None), Visibility::Lan);
+        assert_eq!(
+            policy.visibility(&ip("5.6.7.8"), Some("node-b")),
+            Visibility::Private
+        );
+        // 未匹配时按端点所属范围取默认值
+        assert_eq!(policy.visibility(&ip("192.168.0.1"), None), Visibility::Lan);
+        assert_eq!(
+            policy.visibility(&ip("5.6.7.8"), Some("node-a")),
+            Visibility::Public
+        );
+    }
+
+    #[test]
+    fn test_filter_ips_and_seeds() {
+        let policy = PrivacyConfig {
+            lan: Visibility::Lan,
+            ..Default::default()
+        };
+        let ips = vec![
+            "192.168.1.2".to_string(),
+            "1.2.3.4".to_string(),
+            "not-an-ip".to_string(),
+        ];
+        assert_eq!(
+            policy.filter_ips(ips.clone(), "node-a", Some(&ip("192.168.1.9"))),
+            vec!["192.168.1.2".to_string(), "1.2.3.4".to_string()]
+        );
+        assert_eq!(
+            policy.filter_ips(ips, "node-a", Some(&ip("8.8.8.8"))),
+            vec!["1.2.3.4".to_string()]
+        );
+
+        let seeds = vec![
+            SeedRecord::new("192.168.1.2:9000".to_string(), "node-a".to_string()),
+            SeedRecord::new("1.2.3.4:9000".to_string(), "node-b".to_string()),
+        ];
+        let to_wan = policy.filter_seeds(&seeds, Some(&ip("8.8.8.8")));
+        assert_eq!(to_wan.len(), 1);
+        assert_eq!(to_wan[0].address, "1.2.3.4:9000");
+        assert_eq!(policy.filter_seeds(&seeds, Some(&ip("10.0.0.1"))).len(), 2);
+        assert_eq!(policy.filter_seeds(&seeds, None).len(), 1);
+    }
+
+    #[test]
+    fn test_filter_seed_set_recalculates_hash() {
+        let policy = PrivacyConfig {
+            rules: vec![rule("node-a", Visibility::Private)],
+            ..Default::default()
+        };
+        let set = SeedSet::new(
+            vec![
+                SeedInfo::new("1.2.3.4".to_string(), 9000, "node-a".to_string(), false),
+                SeedInfo::new("5.6.7.8".to_string(), 9000, "node-b".to_string(), false),
+            ],
+            1,
+        );
+        let filtered = policy.filter_seed_set(&set, Some(&ip("8.8.8.8")));
+        assert_eq!(filtered.len(), 1);
+        assert!(filtered.contains("5.6.7.8", 9000));
+        assert!(filtered.verify());
+        assert_ne!(filtered.hash, set.hash);
+        // 原集合不变
+        assert_eq!(set.len(), 2);
+        assert!(set.verify());
+    }
+
+    #[test]
+    fn test_privacy_config_parse() {
+        let text = r#"
+[privacy]
+lan = "lan"
+
+[[privacy.rules]]
+target = "10.8.0.0/16"
+visibility = "private"
+"#;
+        let config = Config::parse(text, Path::new("node.toml")).unwrap();
+        assert_eq!(config.privacy.lan, Visibility::Lan);
+        assert_eq!(config.privacy.public, Visibility::Public);
+        assert_eq!(
+            config.privacy.rules,
+            vec![rule("10.8.0.0/16", Visibility::Private)]
+        );
+
+        let defaults = Config::parse("", Path::new("node.toml")).unwrap();
+        assert_eq!(defaults.privacy, PrivacyConfig::default());
+    }
+}
@@ -0,0 +1,118 @@
+#[cfg(test)]
+mod tests {
+    use aex::tcp::types::Codec;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+    use zz_account::address::FreeWebMovementAddress;
+    use zz_p2p::protocols::{
+        command::{Action, Entity, P2PCommand},
+        commands::ping::PingCommand,
+        error::{ProtocolError, ProtocolErrorStats, decode_command},
+        frame::P2PFrame,
+    };
+
+    async fn signed_frame() -> P2PFrame {
+        let address = FreeWebMovementAddress::random();
+        let cmd = P2PCommand::new(Entity::Node, Action::Ping, vec![1, 2, 3]);
+        P2PFrame::build(&address, cmd, 1).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_valid_frame_passes_check() {
+        let frame = signed_frame().await;
+        assert_eq!(frame.check(), Ok(()));
+        assert!(P2PFrame::verify(frame).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_garbage_public_key_is_typed_error() {
+        let mut frame = signed_frame().await;
+        frame.body.public_key = vec![0xde, 0xad];
+        assert_eq!(frame.check(), Err(ProtocolError::InvalidPublicKey));
+        assert!(!frame.validate());
+        assert_eq!(
+            P2PFrame::verify(frame).unwrap_err(),
+            ProtocolError::InvalidPublicKey
+        );
+    }
+
+    #[tokio::test]
+    async fn test_garbage_signature_is_typed_error() {
+        let mut frame = signed_frame().await;
+        frame.signature = vec![7; 10];
+        assert_eq!(frame.check(), Err(ProtocolError::MalformedSignature));
+        assert!(!frame.validate());
+    }
+
+    #[tokio::test]
+    async fn test_length_mismatch_rejected() {
+        let mut frame = signed_frame().await;
+        frame.body.data_length += 1;
+        assert!(matches!(
+            frame.check(),
+            Err(ProtocolError::LengthMismatch { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_tampered_frame_is_bad_signature() {
+        let mut frame = signed_frame().await;
+        frame.body.nonce = frame.body.nonce.wrapping_add(1);
+        assert_eq!(
+            P2PFrame::verify(frame).unwrap_err(),
+            ProtocolError::BadSignature
+        );
+    }
+
+    #[tokio::test]
+    async fn test_random_bytes_never_panic() {
+        let valid = Codec::encode(&signed_frame().await).unwrap();
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        for _ in 0..2000 {
+            let mut bytes = valid.clone();
+            // 随机翻转若干字节并随机截断
+            for _ in 0..rng.gen_range(1..8) {
+                let i = rng.gen_range(0..bytes.len());
+                bytes[i] = rng.r#gen();
+            }
+            bytes.truncate(rng.gen_range(0..=bytes.len()));
+            let _ = P2PFrame::verify_bytes(&bytes);
+        }
+        for len in 0..64 {
+            let bytes: Vec<u8> = (0..len).map(|_| rng.r#gen()).collect();
+            assert!(P2PFrame::verify_bytes(&bytes).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decode_command_error() {
+        let frame = signed_frame().await;
+        let res: Result<PingCommand, ProtocolError> =
+            decode_command("PingCommand", &frame, &vec![0xff]);
+        match res {
+            Err(ProtocolError::Decode { what, .. }) => assert_eq!(what, "PingCommand"),
+            other => panic!("expected decode error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_error_stats() {
+        let stats = ProtocolErrorStats::default();
+        assert_eq!(stats.record("peer-a", &ProtocolError::BadSignature), 1);
+        assert_eq!(
+            stats.record("peer-a", &ProtocolError::decode("PingCommand", "eof")),
+            2
+        );
+        assert_eq!(stats.record("peer-b", &ProtocolError::BadSignature), 1);
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.total, 3);
+        assert_eq!(
+            snapshot.by_kind,
+            vec![("bad_signature".to_string(), 2), ("decode".to_string(), 1)]
+        );
+        assert_eq!(snapshot.by_peer[0], ("peer-a".to_string(), 2));
+
+        stats.reset_peer("peer-a");
+        assert_eq!(stats.record("peer-a", &ProtocolError::BadSignature), 1);
+    }
+}
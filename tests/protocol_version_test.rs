@@ -0,0 +1,95 @@
+#[cfg(test)]
+mod tests {
+    use aex::tcp::types::{Codec, Frame};
+    use zz_account::address::FreeWebMovementAddress;
+    use zz_p2p::protocols::{
+        command::{Action, Entity, P2PCommand},
+        compression::Compression,
+        error::ProtocolError,
+        frame::{FrameBody, P2PFrame},
+        version::{
+            CURRENT_PROTOCOL_VERSION, PROTOCOL_V1, PROTOCOL_V2, PeerVersion, layout, negotiate,
+        },
+    };
+
+    fn command() -> P2PCommand {
+        P2PCommand::new(Entity::Message, Action::SendText, vec![1, 2, 3])
+    }
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(negotiate(PROTOCOL_V2), Ok(PROTOCOL_V2));
+        assert_eq!(negotiate(PROTOCOL_V1), Ok(PROTOCOL_V1));
+        // 未声明版本的旧节点按 v1 处理
+        assert_eq!(negotiate(0), Ok(PROTOCOL_V1));
+        // 对端更新时使用本端最高版本
+        assert_eq!(negotiate(200), Ok(CURRENT_PROTOCOL_VERSION));
+        assert_eq!(PeerVersion::default().0, CURRENT_PROTOCOL_VERSION);
+    }
+
+    #[test]
+    fn test_layouts() {
+        assert!(!layout(PROTOCOL_V1).unwrap().has_ttl);
+        assert!(layout(PROTOCOL_V2).unwrap().has_destination);
+        assert_eq!(
+            layout(9).unwrap_err(),
+            ProtocolError::UnsupportedVersion { version: 9 }
+        );
+        assert!(
+            ProtocolError::UnsupportedVersion { version: 9 }
+                .to_string()
+                .contains("unsupported protocol version 9")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_v1_frame_roundtrip() {
+        let addr = FreeWebMovementAddress::random();
+        let frame = P2PFrame::build_to(&addr, command(), PROTOCOL_V1, Some("dest".into()))
+            .await
+            .unwrap();
+        // v1 不携带 destination
+        assert!(frame.body.destination.is_none());
+        let v1 = Codec::encode(&frame).unwrap();
+
+        let v2_frame = P2PFrame::build(&addr, command(), PROTOCOL_V2)
+            .await
+            .unwrap();
+        let v2 = Codec::encode(&v2_frame).unwrap();
+        assert!(v1.len() < v2.len());
+
+        let decoded = P2PFrame::verify_bytes(&v1).unwrap();
+        assert_eq!(decoded.body.version, PROTOCOL_V1);
+        assert_eq!(decoded.ttl, 0);
+        assert_eq!(decoded.body.data, frame.body.data);
+        assert!(decoded.validate());
+    }
+
+    #[tokio::test]
+    async fn test_compressed_v1_frame_roundtrip() {
+        let addr = FreeWebMovementAddress::random();
+        let cmd = P2PCommand::new(Entity::Message, Action::SendText, vec![7u8; 16 * 1024]);
+        let mut frame = P2PFrame::build(&addr, cmd, PROTOCOL_V1).await.unwrap();
+        frame.compression = Compression::Lz4;
+        let packed = Codec::encode(&frame).unwrap();
+        let decoded: P2PFrame = Codec::decode(&packed).unwrap();
+        assert_eq!(decoded.body.version, PROTOCOL_V1);
+        assert!(decoded.validate());
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_version_is_rejected() {
+        let addr = FreeWebMovementAddress::random();
+        let frame = P2PFrame::build(&addr, command(), PROTOCOL_V1)
+            .await
+            .unwrap();
+        let mut bytes = Codec::encode(&frame).unwrap();
+        bytes[0] = 9;
+        let err = P2PFrame::verify_bytes(&bytes).unwrap_err();
+        assert!(err.to_string().contains("unsupported protocol version 9"));
+
+        assert!(P2PFrame::build(&addr, command(), 9).await.is_err());
+        let body = FrameBody::new(9, "a".into(), vec![], 0, 0, vec![]);
+        assert!(Codec::encode(&body).is_err());
+    }
+}
@@ -361,4 +361,37 @@ mod tests {
         assert_eq!(P2PCommand::to_u32(Entity::Node, Action::OnLine), 257);
         assert_eq!(P2PCommand::to_u32(Entity::File, Action::Reject), 6405);
     }
+
+    #[test]
+    fn test_ping_pong_codec_roundtrip() {
+        use zz_p2p::protocols::commands::ping::{PingCommand, PongCommand};
+
+        // 新增的 Ping/Pong 追加在枚举末尾，不影响已有命令的 ID
+        assert_eq!(
+            P2PCommand::to_u32(Entity::Node, Action::Ping),
+            (28 << 8) | 1
+        );
+        assert_eq!(
+            P2PCommand::to_u32(Entity::Node, Action::Pong),
+            (29 << 8) | 1
+        );
+
+        let ping = PingCommand {
+            nonce: 42,
+            timestamp: 1_700_000_000_000,
+        };
+        let bytes = Codec::encode(&ping).unwrap();
+        let decoded: PingCommand = Codec::decode(&bytes).unwrap();
+        assert_eq!(ping, decoded);
+
+        let pong = PongCommand {
+            nonce: ping.nonce,
+            timestamp: ping.timestamp,
+            received_at: 1_700_000_000_150,
+            replied_at: 1_700_000_000_151,
+        };
+        let bytes = Codec::encode(&pong).unwrap();
+        let decoded: PongCommand = Codec::decode(&bytes).unwrap();
+        assert_eq!(pong, decoded);
+    }
 }
@@ -65,6 +65,7 @@ mod tests {
             nonce: 42,
             data_length: 5,
             data: b"hello".to_vec(),
+            destination: None,
         };
 
         // 3️⃣ 使用身份签名生成 Frame
@@ -574,4 +575,47 @@ mod tests {
         // 5. 清理
         global.manager.shutdown();
     }
+
+    #[tokio::test]
+    async fn test_compressed_frame_roundtrip() {
+        use zz_p2p::protocols::compression::{
+            COMPRESSED_FRAME_MARKER, Compression, PeerCapabilities,
+        };
+
+        let addr = FreeWebMovementAddress::random();
+        let cmd = P2PCommand::new(Entity::Message, Action::SendText, vec![7u8; 16 * 1024]);
+        let mut frame = P2PFrame::build(&addr, cmd, 1).await.unwrap();
+        let plain = Codec::encode(&frame).unwrap();
+
+        frame.compression = PeerCapabilities(1).compression();
+        assert_eq!(frame.compression, Compression::Lz4);
+        let packed = Codec::encode(&frame).unwrap();
+        assert!(packed.len() < plain.len());
+        assert_eq!(packed[0] & 0xF0, COMPRESSED_FRAME_MARKER);
+
+        // 解码时先解压，签名仍覆盖原始 body
+        let decoded: P2PFrame = Codec::decode(&packed).unwrap();
+        assert!(decoded.validate());
+        assert_eq!(decoded.body.data, frame.body.data);
+        assert_eq!(decoded.compression, Compression::Lz4);
+    }
+
+    #[tokio::test]
+    async fn test_small_frame_stays_uncompressed() {
+        use zz_p2p::protocols::compression::{Compression, PeerCapabilities};
+
+        let addr = FreeWebMovementAddress::random();
+        let mut frame = P2PFrame::build(&addr, make_command(), 1).await.unwrap();
+        let plain = Codec::encode(&frame).unwrap();
+
+        frame.compression = Compression::Lz4;
+        assert_eq!(Codec::encode(&frame).unwrap(), plain);
+
+        // 对端未声明能力位时不压缩
+        assert_eq!(PeerCapabilities(0).compression(), Compression::None);
+
+        let decoded: P2PFrame = Codec::decode(&plain).unwrap();
+        assert!(decoded.validate());
+        assert_eq!(decoded.compression, Compression::None);
+    }
 }
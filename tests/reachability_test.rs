@@ -0,0 +1,123 @@
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use zz_p2p::reachability::{
+        self, Liveness, Reachability, ReachabilityConfig, ReachabilityTracker,
+    };
+
+    const IDLE_MS: u128 = 120_000;
+
+    fn addr(port: u16) -> SocketAddr {
+        format!("127.0.0.1:{}", port).parse().unwrap()
+    }
+
+    #[test]
+    fn test_passive_traffic_suppresses_probes() {
+        let mut l = Liveness::default();
+        // 从未收到帧也从未探测过
+        assert!(l.needs_probe(1_000_000, IDLE_MS));
+
+        l.observe(1_000_000);
+        assert!(!l.needs_probe(1_000_000 + IDLE_MS - 1, IDLE_MS));
+        assert_eq!(
+            l.state(1_000_000 + IDLE_MS - 1, IDLE_MS, 3),
+            Reachability::Passive
+        );
+        assert!(l.needs_probe(1_000_000 + IDLE_MS, IDLE_MS));
+        assert_eq!(l.state(1_000_000 + IDLE_MS, IDLE_MS, 3), Reachability::Idle);
+    }
+
+    #[test]
+    fn test_probe_results_update_state() {
+        let mut l = Liveness::default();
+        l.observe(0);
+        l.record_probe(IDLE_MS, true);
+        assert_eq!(l.state(IDLE_MS + 1, IDLE_MS, 3), Reachability::Active);
+        assert!(!l.needs_probe(IDLE_MS + 1, IDLE_MS));
+
+        // 失败后同样等待一个空闲周期再探测
+        for i in 1..=3u128 {
+            let at = IDLE_MS * (i + 1);
+            assert!(l.needs_probe(at, IDLE_MS));
+            l.record_probe(at, false);
+            assert!(!l.needs_probe(at + 1, IDLE_MS));
+        }
+        assert_eq!(l.consecutive_failures, 3);
+        assert_eq!(l.probe_failures, 3);
+        assert_eq!(l.probes, 4);
+        assert_eq!(l.state(IDLE_MS * 5, IDLE_MS, 3), Reachability::Unresponsive);
+        // 0 表示不标记无响应
+        assert_eq!(l.state(IDLE_MS * 5, IDLE_MS, 0), Reachability::Idle);
+
+        // 收到帧即恢复
+        l.observe(IDLE_MS * 5);
+        assert_eq!(l.consecutive_failures, 0);
+        assert_eq!(l.state(IDLE_MS * 5, IDLE_MS, 3), Reachability::Passive);
+    }
+
+    #[test]
+    fn test_due_for_probe_prefers_longest_idle() {
+        let now = 10 * IDLE_MS;
+        let seen = |t: u128| {
+            let mut l = Liveness::default();
+            l.observe(t);
+            l
+        };
+        let peers = vec![
+            (addr(1), seen(now - 1)),
+            (addr(2), seen(now - 3 * IDLE_MS)),
+            (addr(3), seen(now - 5 * IDLE_MS)),
+            (addr(4), Liveness::default()),
+        ];
+        let policy = ReachabilityConfig {
+            max_probes: 2,
+            ..ReachabilityConfig::default()
+        };
+        assert_eq!(
+            reachability::due_for_probe(&peers, now, &policy),
+            vec![addr(4), addr(3)]
+        );
+        let passive_only = ReachabilityConfig {
+            max_probes: 0,
+            ..ReachabilityConfig::default()
+        };
+        assert!(reachability::due_for_probe(&peers, now, &passive_only).is_empty());
+    }
+
+    #[test]
+    fn test_tracker_report() {
+        let tracker = ReachabilityTracker::default();
+        tracker.observe(addr(2), 1_000);
+        tracker.observe(addr(2), 2_000);
+        tracker.record_probe(addr(1), 1_500, false);
+        tracker.record_skipped(1);
+
+        let report = tracker.report(2_500, &ReachabilityConfig::default());
+        assert_eq!(report.probes, 1);
+        assert_eq!(report.probe_failures, 1);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.peers.len(), 2);
+        assert_eq!(report.peers[0].addr, addr(1));
+        assert_eq!(report.peers[0].state, Reachability::Idle);
+        assert_eq!(report.peers[1].state, Reachability::Passive);
+        assert_eq!(report.peers[1].liveness.passive_frames, 2);
+
+        tracker.forget(&addr(2));
+        assert!(tracker.get(&addr(2)).is_none());
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let config: ReachabilityConfig = toml::from_str("idle_secs = 60").unwrap();
+        assert_eq!(config.idle_ms(), 60_000);
+        assert_eq!(
+            config.max_probes,
+            reachability::DEFAULT_MAX_REACHABILITY_PROBES
+        );
+        assert_eq!(
+            config.unresponsive_after,
+            reachability::DEFAULT_UNRESPONSIVE_AFTER
+        );
+    }
+}
@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use zz_p2p::{
+        listener::HandlerSet,
+        readiness::{Readiness, StartupPhase, probe_addr, wait_listening},
+    };
+
+    #[test]
+    fn test_phase_transitions() {
+        let readiness = Readiness::new();
+        assert_eq!(readiness.phase(), StartupPhase::Starting);
+
+        // 引导先于监听完成时仍处于 starting
+        readiness.mark_bootstrapped();
+        assert_eq!(readiness.phase(), StartupPhase::Starting);
+
+        readiness.mark_listening();
+        assert_eq!(readiness.phase(), StartupPhase::Ready);
+        assert!(readiness.is_ready());
+        assert_eq!(readiness.phase().to_string(), "ready");
+    }
+
+    #[test]
+    fn test_bootstrapping_phase() {
+        let readiness = Readiness::new();
+        readiness.mark_listening();
+        assert_eq!(readiness.phase(), StartupPhase::Bootstrapping);
+        assert!(!readiness.is_ready());
+    }
+
+    #[test]
+    fn test_failure_is_sticky() {
+        let readiness = Readiness::new();
+        readiness.mark_failed("bind failed");
+        readiness.mark_failed("second error");
+        readiness.mark_listening();
+        readiness.mark_bootstrapped();
+        assert_eq!(
+            readiness.phase(),
+            StartupPhase::Failed {
+                error: "bind failed".into()
+            }
+        );
+        let json = serde_json::to_value(readiness.phase()).unwrap();
+        assert_eq!(json["phase"], "failed");
+        assert_eq!(json["error"], "bind failed");
+    }
+
+    #[tokio::test]
+    async fn test_wait_resolves_when_ready() {
+        let readiness = Readiness::new();
+        let clone = readiness.clone();
+        let waiter = tokio::spawn(async move { clone.wait().await });
+        readiness.mark_listening();
+        readiness.mark_bootstrapped();
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        // 已就绪后立即返回
+        readiness.wait().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wait_reports_failure() {
+        let readiness = Readiness::new();
+        let clone = readiness.clone();
+        let waiter = tokio::spawn(async move { clone.wait().await });
+        readiness.mark_failed("listener p2p failed");
+        let err = waiter.await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("listener p2p failed"));
+    }
+
+    #[test]
+    fn test_probe_addr_replaces_unspecified() {
+        let v4: SocketAddr = "0.0.0.0:9000".parse().unwrap();
+        let v6: SocketAddr = "[::]:9000".parse().unwrap();
+        let fixed: SocketAddr = "192.168.1.2:9000".parse().unwrap();
+        assert_eq!(probe_addr(v4), "127.0.0.1:9000".parse().unwrap());
+        assert_eq!(probe_addr(v6), "[::1]:9000".parse().unwrap());
+        assert_eq!(probe_addr(fixed), fixed);
+    }
+
+    #[tokio::test]
+    async fn test_wait_listening() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let bound = listener.local_addr().unwrap();
+        let handlers = HandlerSet::new();
+        wait_listening(&[bound], &handlers, Duration::from_secs(1))
+            .await
+            .unwrap();
+
+        drop(listener);
+        let err = wait_listening(&[bound], &handlers, Duration::from_millis(100))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not accepting connections"));
+    }
+}
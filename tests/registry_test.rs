@@ -0,0 +1,178 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use aex::connection::{context::Context, global::GlobalContext};
+    use tokio::sync::Mutex;
+    use zz_account::address::FreeWebMovementAddress;
+    use zz_p2p::protocols::{
+        command::{Action, Entity, P2PCommand},
+        frame::P2PFrame,
+        registry::{HandlerKey, HandlerRegistry, P2PDoer, handlers},
+        version::{CURRENT_PROTOCOL_VERSION, PROTOCOL_V1},
+    };
+
+    fn context() -> Arc<Mutex<Context>> {
+        let addr: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+        let global = Arc::new(GlobalContext::new(addr, None));
+        Arc::new(Mutex::new(Context::new(None, None, global, addr)))
+    }
+
+    async fn frame(entity: Entity, action: Action, version: u8) -> (P2PFrame, P2PCommand) {
+        let address = FreeWebMovementAddress::random();
+        let cmd = P2PCommand::new(entity, action, vec![1, 2, 3]);
+        let frame = P2PFrame::build(&address, cmd.clone(), version)
+            .await
+            .unwrap();
+        (frame, cmd)
+    }
+
+    /// 记录被调用的处理器编号
+    fn tagged(hits: Arc<AtomicUsize>, tag: usize) -> P2PDoer {
+        Box::new(move |_ctx, _frame, _cmd| {
+            let hits = hits.clone();
+            Box::pin(async move {
+                hits.store(tag, Ordering::SeqCst);
+                Ok(true)
+            })
+        })
+    }
+
+    #[test]
+    fn test_resolve_most_specific_first() {
+        let registry = HandlerRegistry::new();
+        let hits = Arc::new(AtomicUsize::new(0));
+        registry.register(HandlerKey::ANY, tagged(hits.clone(), 1));
+        registry.register(HandlerKey::entity(Entity::Node), tagged(hits.clone(), 2));
+        registry.register(
+            HandlerKey::new(Entity::Node, Action::Ping),
+            tagged(hits.clone(), 3),
+        );
+        registry.register(
+            HandlerKey::versioned(Entity::Node, Action::Ping, PROTOCOL_V1),
+            tagged(hits.clone(), 4),
+        );
+
+        let resolve = |entity, action, version| registry.resolve(entity, action, version);
+        assert_eq!(
+            resolve(Entity::Node, Action::Ping, PROTOCOL_V1),
+            Some(HandlerKey::versioned(
+                Entity::Node,
+                Action::Ping,
+                PROTOCOL_V1
+            ))
+        );
+        assert_eq!(
+            resolve(Entity::Node, Action::Ping, CURRENT_PROTOCOL_VERSION),
+            Some(HandlerKey::new(Entity::Node, Action::Ping))
+        );
+        assert_eq!(
+            resolve(Entity::Node, Action::Pong, PROTOCOL_V1),
+            Some(HandlerKey::entity(Entity::Node))
+        );
+        assert_eq!(
+            resolve(Entity::Message, Action::SendText, PROTOCOL_V1),
+            Some(HandlerKey::ANY)
+        );
+    }
+
+    #[test]
+    fn test_register_replace_and_unregister() {
+        let registry = HandlerRegistry::new();
+        let hits = Arc::new(AtomicUsize::new(0));
+        let key = HandlerKey::new(Entity::Node, Action::Ping);
+        assert!(!registry.register(key, tagged(hits.clone(), 1)));
+        assert!(registry.register(key, tagged(hits.clone(), 2)));
+        assert_eq!(registry.keys(), vec![key]);
+
+        assert!(registry.unregister(&key));
+        assert!(!registry.unregister(&key));
+        assert_eq!(
+            registry.resolve(Entity::Node, Action::Ping, PROTOCOL_V1),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_by_version() {
+        let registry = HandlerRegistry::new();
+        let hits = Arc::new(AtomicUsize::new(0));
+        registry.register(
+            HandlerKey::new(Entity::Node, Action::Ping),
+            tagged(hits.clone(), 1),
+        );
+        registry.register(
+            HandlerKey::versioned(Entity::Node, Action::Ping, CURRENT_PROTOCOL_VERSION),
+            tagged(hits.clone(), 2),
+        );
+
+        let (f, c) = frame(Entity::Node, Action::Ping, PROTOCOL_V1).await;
+        assert!(registry.dispatch(context(), f, c).await.unwrap());
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        let (f, c) = frame(Entity::Node, Action::Ping, CURRENT_PROTOCOL_VERSION).await;
+        assert!(registry.dispatch(context(), f, c).await.unwrap());
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+        // 没有处理器
+        let (f, c) = frame(Entity::Node, Action::Pong, CURRENT_PROTOCOL_VERSION).await;
+        assert!(!registry.dispatch(context(), f, c).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_panicking_handler_isolated() {
+        let registry = HandlerRegistry::new();
+        registry.register(
+            HandlerKey::new(Entity::Node, Action::Ping),
+            Box::new(|_ctx, _frame, _cmd| Box::pin(async { panic!("bad handler") })),
+        );
+        let hits = Arc::new(AtomicUsize::new(0));
+        registry.register(
+            HandlerKey::new(Entity::Node, Action::Pong),
+            tagged(hits.clone(), 1),
+        );
+
+        let (f, c) = frame(Entity::Node, Action::Ping, CURRENT_PROTOCOL_VERSION).await;
+        assert!(registry.dispatch(context(), f, c).await.is_err());
+
+        // 之后的分发不受影响
+        let (f, c) = frame(Entity::Node, Action::Pong, CURRENT_PROTOCOL_VERSION).await;
+        assert!(registry.dispatch(context(), f, c).await.unwrap());
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_handler_timeout() {
+        let registry = HandlerRegistry::new();
+        registry.register_with_timeout(
+            HandlerKey::new(Entity::Node, Action::Ping),
+            Duration::from_millis(50),
+            Box::new(|_ctx, _frame, _cmd| {
+                Box::pin(async {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    Ok(true)
+                })
+            }),
+        );
+
+        let (f, c) = frame(Entity::Node, Action::Ping, CURRENT_PROTOCOL_VERSION).await;
+        let started = std::time::Instant::now();
+        assert!(registry.dispatch(context(), f, c).await.is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_builtin_handlers_registered() {
+        let keys = handlers().keys();
+        assert!(keys.contains(&HandlerKey::new(Entity::Message, Action::SendText)));
+        assert!(keys.contains(&HandlerKey::new(Entity::Topic, Action::Unsubscribe)));
+        assert!(keys.iter().all(|key| key.version.is_none()));
+    }
+}
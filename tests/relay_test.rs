@@ -0,0 +1,186 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::IpAddr,
+        path::Path,
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use aex::connection::global::GlobalContext;
+    use clap::Parser;
+    use zz_p2p::{
+        admin::{AdminAction, AdminRole},
+        cli::Opt,
+        config::Config,
+        protocols::{commands::node_registry::ConnectionDirection, limits::ConnSnapshot},
+        relay::{self, RelayConfig, RelayLedger, RelayMode, RelayVerdict},
+    };
+
+    fn quota(bytes_per_sec: u64, daily: u64) -> RelayConfig {
+        RelayConfig {
+            enabled: true,
+            client_bytes_per_sec: bytes_per_sec,
+            client_daily_bytes: daily,
+            ..Default::default()
+        }
+    }
+
+    fn conn(addr: &str, direction: ConnectionDirection) -> ConnSnapshot {
+        ConnSnapshot {
+            addr: addr.parse().unwrap(),
+            direction,
+            score: 0,
+            last_seen: 0,
+        }
+    }
+
+    #[test]
+    fn test_ledger_accounts_both_ends() {
+        let mut ledger = RelayLedger::default();
+        let now = Instant::now();
+        assert_eq!(
+            ledger.admit(None, "alice", "bob", 100, now, 1),
+            RelayVerdict::Forward(Duration::ZERO)
+        );
+        ledger.admit(None, "alice", "carol", 50, now, 1);
+        ledger.admit(None, "bob", "alice", 10, now, 1);
+
+        let total = ledger.total();
+        assert_eq!((total.frames_sent, total.bytes_sent), (3, 160));
+        let clients = ledger.clients();
+        let order: Vec<&str> = clients.iter().map(|c| c.address.as_str()).collect();
+        assert_eq!(order, vec!["alice", "bob", "carol"]);
+        let alice = &clients[0].usage;
+        assert_eq!((alice.frames_sent, alice.bytes_sent), (2, 150));
+        assert_eq!((alice.frames_received, alice.bytes_received), (1, 10));
+        assert_eq!(clients[2].usage.bytes_received, 50);
+    }
+
+    #[test]
+    fn test_rate_limit_delays_only_the_sender() {
+        let mut ledger = RelayLedger::default();
+        let now = Instant::now();
+        let policy = quota(1000, 0);
+        let mut waited = Duration::ZERO;
+        for _ in 0..4 {
+            if let RelayVerdict::Forward(wait) =
+                ledger.admit(Some(&policy), "alice", "bob", 1000, now, 1)
+            {
+                waited = wait;
+            }
+        }
+        assert!(waited > Duration::ZERO);
+        // 其他发送方有各自的漏桶
+        assert_eq!(
+            ledger.admit(Some(&policy), "carol", "bob", 1000, now, 1),
+            RelayVerdict::Forward(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn test_daily_quota_drops_and_resets() {
+        let mut ledger = RelayLedger::default();
+        let now = Instant::now();
+        let policy = quota(0, 150);
+        assert!(matches!(
+            ledger.admit(Some(&policy), "alice", "bob", 100, now, 1),
+            RelayVerdict::Forward(_)
+        ));
+        assert_eq!(
+            ledger.admit(Some(&policy), "alice", "bob", 100, now, 1),
+            RelayVerdict::QuotaExceeded
+        );
+        // 未启用中继模式时不限额
+        assert!(matches!(
+            ledger.admit(None, "alice", "bob", 100, now, 1),
+            RelayVerdict::Forward(_)
+        ));
+        // 新的一天重新计算
+        assert!(matches!(
+            ledger.admit(Some(&policy), "alice", "bob", 100, now, 2),
+            RelayVerdict::Forward(_)
+        ));
+        let clients = ledger.clients();
+        let alice = clients.iter().find(|c| c.address == "alice").unwrap();
+        assert_eq!(alice.usage.dropped, 1);
+        assert_eq!(alice.usage.frames_sent, 3);
+        assert_eq!(ledger.total().dropped, 1);
+    }
+
+    #[test]
+    fn test_idle_clients_are_forgotten() {
+        let mut ledger = RelayLedger::default();
+        let start = Instant::now();
+        ledger.admit(None, "alice", "bob", 10, start, 1);
+        let later = start + relay::CLIENT_IDLE_TIMEOUT + Duration::from_secs(1);
+        ledger.admit(None, "carol", "dave", 10, later, 2);
+        let order: Vec<String> = ledger.clients().into_iter().map(|c| c.address).collect();
+        assert_eq!(order, vec!["carol".to_string(), "dave".to_string()]);
+        // 合计不受影响
+        assert_eq!(ledger.total().frames_sent, 2);
+    }
+
+    #[test]
+    fn test_connection_quota_counts_inbound_per_ip() {
+        let ip: IpAddr = "198.51.100.9".parse().unwrap();
+        let conns = vec![
+            conn("198.51.100.9:4000", ConnectionDirection::Inbound),
+            conn("198.51.100.9:4001", ConnectionDirection::Inbound),
+            conn("198.51.100.9:1090", ConnectionDirection::Outbound),
+            conn("198.51.100.10:4000", ConnectionDirection::Inbound),
+        ];
+        assert!(relay::within_connection_quota(&conns, ip, 3));
+        assert!(!relay::within_connection_quota(&conns, ip, 2));
+        assert!(relay::within_connection_quota(&conns, ip, 0));
+    }
+
+    #[tokio::test]
+    async fn test_quotas_apply_only_in_relay_mode() {
+        let gctx = Arc::new(GlobalContext::new("127.0.0.1:0".parse().unwrap(), None));
+        let ip: IpAddr = "198.51.100.9".parse().unwrap();
+        let conns: Vec<ConnSnapshot> = (0..8)
+            .map(|i| {
+                conn(
+                    &format!("198.51.100.9:{}", 4000 + i),
+                    ConnectionDirection::Inbound,
+                )
+            })
+            .collect();
+        assert!(!relay::is_enabled(&gctx).await);
+        assert!(relay::admit_connection(&gctx, &conns, ip).await);
+
+        gctx.set(RelayMode).await;
+        assert!(relay::is_enabled(&gctx).await);
+        assert!(!relay::admit_connection(&gctx, &conns, ip).await);
+
+        // 记账在任何模式下都进行
+        assert!(relay::admit(&gctx, "alice", "bob", 42).await);
+        let report = relay::report(&gctx).await;
+        assert!(report.enabled);
+        assert_eq!(report.total.bytes_sent, 42);
+        assert_eq!(report.clients.len(), 2);
+    }
+
+    #[test]
+    fn test_flag_config_and_admin_role() {
+        assert!(!Opt::parse_from(["zzp2p"]).relay);
+        assert!(Opt::parse_from(["zzp2p", "--relay"]).relay);
+
+        let config = Config::parse(
+            "[relay]\nenabled = true\nclient_daily_bytes = 1024",
+            Path::new("node.toml"),
+        )
+        .unwrap();
+        assert!(config.relay.enabled);
+        assert_eq!(config.relay.client_daily_bytes, 1024);
+        assert_eq!(
+            config.relay.max_connections_per_client,
+            relay::DEFAULT_MAX_CONNECTIONS_PER_CLIENT
+        );
+
+        assert!(AdminRole::Auditor.permits(AdminAction::RelayStats));
+        let action: AdminAction = serde_json::from_str("\"relay_stats\"").unwrap();
+        assert_eq!(action, AdminAction::RelayStats);
+    }
+}
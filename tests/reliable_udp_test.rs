@@ -0,0 +1,188 @@
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+    use tokio::net::UdpSocket;
+    use zz_p2p::{
+        connections::Transport,
+        reliable_udp::{
+            DEDUP_WINDOW, Packet, ReceiveWindow, ReliabilityConfig, ReliableDatagramConnection,
+            RtoEstimator,
+        },
+        transport::Connection,
+    };
+
+    fn fast() -> ReliabilityConfig {
+        ReliabilityConfig {
+            initial_rto_ms: 50,
+            min_rto_ms: 20,
+            max_rto_ms: 200,
+            max_retries: 3,
+        }
+    }
+
+    async fn socket() -> (Arc<UdpSocket>, SocketAddr) {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        (Arc::new(socket), addr)
+    }
+
+    async fn recv_packet(socket: &UdpSocket) -> (Packet, SocketAddr) {
+        let mut buf = vec![0u8; 2048];
+        let (n, from) = tokio::time::timeout(Duration::from_secs(2), socket.recv_from(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        (Packet::decode(&buf[..n]).unwrap(), from)
+    }
+
+    #[test]
+    fn test_packet_roundtrip() {
+        let data = Packet::Data {
+            seq: 0x0102_0304,
+            payload: b"online".to_vec(),
+        };
+        let bytes = data.encode();
+        assert_eq!(&bytes[..5], &[0x01, 0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(Packet::decode(&bytes).unwrap(), data);
+
+        let ack = Packet::Ack { seq: 7 };
+        assert_eq!(Packet::decode(&ack.encode()).unwrap(), ack);
+
+        assert!(Packet::decode(&[0x01, 0, 0]).is_err());
+        assert!(Packet::decode(&[0x09, 0, 0, 0, 1]).is_err());
+        assert!(Packet::decode(&[0x02, 0, 0, 0, 1, 0xff]).is_err());
+    }
+
+    #[test]
+    fn test_rto_estimation() {
+        let mut rto = RtoEstimator::new(&ReliabilityConfig::default());
+        assert_eq!(rto.rto(), Duration::from_secs(1));
+        assert!(rto.srtt().is_none());
+
+        // 第一个样本：SRTT = R，RTTVAR = R/2，RTO = R + 4 × R/2
+        rto.sample(Duration::from_millis(100));
+        assert_eq!(rto.srtt(), Some(Duration::from_millis(100)));
+        assert_eq!(rto.rto(), Duration::from_millis(300));
+
+        // 稳定的往返时间让 RTO 收敛，但不低于下限
+        for _ in 0..50 {
+            rto.sample(Duration::from_millis(10));
+        }
+        assert_eq!(rto.rto(), Duration::from_millis(200));
+
+        rto.backoff();
+        assert_eq!(rto.rto(), Duration::from_millis(400));
+        for _ in 0..10 {
+            rto.backoff();
+        }
+        assert_eq!(rto.rto(), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_receive_window_dedup() {
+        let mut window = ReceiveWindow::default();
+        assert!(window.accept(5));
+        assert!(!window.accept(5));
+        // 乱序到达的较早序号仍然交付一次
+        assert!(window.accept(3));
+        assert!(!window.accept(3));
+
+        assert!(window.accept(5 + DEDUP_WINDOW * 2));
+        // 超出窗口的旧序号视为重复
+        assert!(!window.accept(4));
+    }
+
+    #[tokio::test]
+    async fn test_send_and_receive() {
+        let (a, a_addr) = socket().await;
+        let (b, b_addr) = socket().await;
+        let a_to_b = ReliableDatagramConnection::new(a, b_addr, fast());
+        let b_to_a = ReliableDatagramConnection::new(b, a_addr, fast());
+        assert_eq!(a_to_b.transport().await, Transport::Udp);
+        assert_eq!(a_to_b.peer_addr().await, b_addr);
+
+        a_to_b.send(b"hello").await.unwrap();
+        assert_eq!(b_to_a.recv().await.unwrap(), Some(b"hello".to_vec()));
+        b_to_a.send(b"world").await.unwrap();
+        assert_eq!(a_to_b.recv().await.unwrap(), Some(b"world".to_vec()));
+
+        let stats = a_to_b.stats();
+        assert_eq!((stats.sent, stats.acked, stats.failed), (1, 1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_lost_datagram_is_retransmitted() {
+        let (a, _) = socket().await;
+        let (raw, raw_addr) = socket().await;
+        let conn = Arc::new(ReliableDatagramConnection::new(a, raw_addr, fast()));
+        let sender = conn.clone();
+        let send = tokio::spawn(async move { sender.send(b"message").await });
+
+        // 丢掉第一次发送，确认重传
+        let (first, from) = recv_packet(&raw).await;
+        let (second, _) = recv_packet(&raw).await;
+        assert_eq!(first, second);
+        let Packet::Data { seq, payload } = second else {
+            panic!("expected data, got {:?}", second);
+        };
+        assert_eq!(payload, b"message");
+        raw.send_to(&Packet::Ack { seq }.encode(), from)
+            .await
+            .unwrap();
+
+        send.await.unwrap().unwrap();
+        let stats = conn.stats();
+        assert!(stats.retransmitted >= 1);
+        assert_eq!(stats.acked, 1);
+        // 重传过的数据报不参与估算，RTO 仍是退避后的值
+        assert!(conn.rto() > Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries() {
+        let (a, _) = socket().await;
+        // 对端存在但从不确认
+        let (_silent, silent_addr) = socket().await;
+        let conn = ReliableDatagramConnection::new(a, silent_addr, fast());
+        let err = conn.send(b"lost").await.unwrap_err();
+        assert!(err.to_string().contains("after 4 attempt(s)"));
+        let stats = conn.stats();
+        assert_eq!((stats.retransmitted, stats.failed, stats.acked), (3, 1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_duplicates_are_acked_but_delivered_once() {
+        let (raw, raw_addr) = socket().await;
+        let (b, b_addr) = socket().await;
+        let conn = ReliableDatagramConnection::new(b, raw_addr, fast());
+
+        let data = Packet::Data {
+            seq: 42,
+            payload: b"once".to_vec(),
+        }
+        .encode();
+        raw.send_to(&data, b_addr).await.unwrap();
+        raw.send_to(&data, b_addr).await.unwrap();
+        assert_eq!(recv_packet(&raw).await.0, Packet::Ack { seq: 42 });
+        assert_eq!(recv_packet(&raw).await.0, Packet::Ack { seq: 42 });
+
+        assert_eq!(conn.recv().await.unwrap(), Some(b"once".to_vec()));
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), conn.recv())
+                .await
+                .is_err()
+        );
+        assert_eq!(conn.stats().duplicates, 1);
+    }
+
+    #[tokio::test]
+    async fn test_close() {
+        let (a, _) = socket().await;
+        let (_b, b_addr) = socket().await;
+        let conn = ReliableDatagramConnection::new(a, b_addr, fast());
+        conn.close().await;
+        assert!(conn.send(b"late").await.is_err());
+        assert_eq!(conn.recv().await.unwrap(), None);
+    }
+}
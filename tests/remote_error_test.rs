@@ -0,0 +1,129 @@
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, sync::Arc};
+
+    use aex::{
+        connection::{context::Context, global::GlobalContext},
+        tcp::types::Codec,
+    };
+    use tokio::sync::Mutex;
+    use zz_account::address::FreeWebMovementAddress;
+    use zz_p2p::protocols::{
+        command::{Action, Entity, P2PCommand},
+        commands::{
+            error::{
+                BoundedMap, DeliveryStatus, ErrorCode, ErrorCommand, delivery_error,
+                delivery_status, error_handler, remember, request_id,
+            },
+            message::{MessageCommand, PendingAcks},
+        },
+        frame::P2PFrame,
+        version::CURRENT_PROTOCOL_VERSION,
+    };
+
+    fn context() -> (Arc<GlobalContext>, Arc<Mutex<Context>>) {
+        let addr: SocketAddr = "127.0.0.1:9100".parse().unwrap();
+        let global = Arc::new(GlobalContext::new(addr, None));
+        let ctx = Arc::new(Mutex::new(Context::new(None, None, global.clone(), addr)));
+        (global, ctx)
+    }
+
+    fn text(request_id: u64) -> MessageCommand {
+        MessageCommand {
+            sender: "alice".to_string(),
+            receiver: "bob".to_string(),
+            request_id,
+            timestamp: 1,
+            message: "hi".to_string(),
+            epoch: 1,
+            seq: 1,
+        }
+    }
+
+    async fn frame(cmd: P2PCommand) -> P2PFrame {
+        let address = FreeWebMovementAddress::random();
+        P2PFrame::build(&address, cmd, CURRENT_PROTOCOL_VERSION)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_error_codes_are_snake_case() {
+        assert_eq!(
+            serde_json::to_value(ErrorCode::UnknownReceiver).unwrap(),
+            "unknown_receiver"
+        );
+        assert_eq!(ErrorCode::QuotaExceeded.to_string(), "quota_exceeded");
+        let code: ErrorCode = serde_json::from_str("\"bad_payload\"").unwrap();
+        assert_eq!(code, ErrorCode::BadPayload);
+    }
+
+    #[test]
+    fn test_request_id_of_outgoing_commands() {
+        let data = Codec::encode(&text(42)).unwrap();
+        assert_eq!(request_id(Action::SendText, &data), Some(42));
+        assert_eq!(request_id(Action::Ping, &data), None);
+        assert_eq!(request_id(Action::SendText, &[1, 2, 3]), None);
+    }
+
+    #[test]
+    fn test_bounded_map_evicts_oldest() {
+        let mut map = BoundedMap::new(2);
+        map.insert(1, "a");
+        map.insert(2, "b");
+        map.insert(1, "a2");
+        map.insert(3, "c");
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(1), None);
+        assert_eq!(map.get(2), Some(&"b"));
+        assert_eq!(map.get(3), Some(&"c"));
+    }
+
+    #[tokio::test]
+    async fn test_about_correlates_offending_frame() {
+        let cmd = P2PCommand::new(Entity::Message, Action::SendText, vec![1, 2, 3]);
+        let offending = frame(cmd.clone()).await;
+        let err = ErrorCommand::about(&offending, Some(&cmd), ErrorCode::BadPayload, "garbage");
+        assert_eq!(err.receiver, offending.body.address);
+        assert_eq!(err.nonce, offending.body.nonce);
+        assert_eq!(err.entity, Some(Entity::Message));
+        assert_eq!(err.action, Some(Action::SendText));
+        assert_eq!(err.detail, "garbage");
+
+        let unknown = ErrorCommand::about(&offending, None, ErrorCode::TtlExpired, "bob");
+        assert_eq!(unknown.entity, None);
+    }
+
+    #[tokio::test]
+    async fn test_error_fails_pending_delivery() {
+        let (gctx, ctx) = context();
+        gctx.set(PendingAcks::default()).await;
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        gctx.get::<PendingAcks>().await.unwrap().insert(42, tx);
+        remember(&gctx, 7, 42).await;
+        assert_eq!(delivery_status(&gctx, 42).await, DeliveryStatus::Pending);
+        assert_eq!(delivery_status(&gctx, 43).await, DeliveryStatus::Unknown);
+
+        let err = ErrorCommand {
+            receiver: "us".to_string(),
+            code: ErrorCode::UnknownReceiver,
+            nonce: 7,
+            entity: Some(Entity::Message),
+            action: Some(Action::SendText),
+            detail: "bob".to_string(),
+        };
+        let cmd = P2PCommand::new(Entity::Error, Action::Error, Codec::encode(&err).unwrap());
+        let reply = frame(cmd.clone()).await;
+        error_handler(ctx, reply.clone(), cmd).await;
+
+        assert!(!rx.await.unwrap());
+        let remote = delivery_error(&gctx, 42).await.unwrap();
+        assert_eq!(remote.code, ErrorCode::UnknownReceiver);
+        assert_eq!(remote.from, reply.body.address);
+        assert_eq!(remote.request_id, Some(42));
+        match delivery_status(&gctx, 42).await {
+            DeliveryStatus::Failed { error } => assert_eq!(error.detail, "bob"),
+            other => panic!("unexpected status {:?}", other),
+        }
+    }
+}
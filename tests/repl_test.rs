@@ -0,0 +1,45 @@
+#[cfg(test)]
+mod tests {
+    use zz_p2p::repl::complete;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_complete_command_at_line_start() {
+        let commands = strings(&["send", "sendbin", "sendfile", "status"]);
+        let words = strings(&["bob"]);
+
+        assert_eq!(
+            complete(&commands, &words, "se", 2),
+            (0, strings(&["send", "sendbin", "sendfile"]))
+        );
+        assert_eq!(
+            complete(&commands, &words, "  st", 4),
+            (2, strings(&["status"]))
+        );
+        assert_eq!(complete(&commands, &words, "x", 1), (0, vec![]));
+    }
+
+    #[test]
+    fn test_complete_addresses_and_aliases() {
+        let commands = strings(&["send"]);
+        let words = strings(&["bob", "bobby", "alice", "bob"]);
+
+        assert_eq!(
+            complete(&commands, &words, "send bo", 7),
+            (5, strings(&["bob", "bobby"]))
+        );
+        // 空前缀列出所有候选（去重、排序）
+        assert_eq!(
+            complete(&commands, &words, "send ", 5),
+            (5, strings(&["alice", "bob", "bobby"]))
+        );
+        // 光标之后的内容不参与补全
+        assert_eq!(
+            complete(&commands, &words, "send al hello", 7),
+            (5, strings(&["alice"]))
+        );
+    }
+}
@@ -0,0 +1,117 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{IpAddr, SocketAddr},
+        path::Path,
+        time::Duration,
+    };
+
+    use zz_p2p::{
+        config::Config,
+        dialer,
+        resolver::{
+            DEFAULT_RESOLVE_TIMEOUT_MS, ResolverConfig, parse_doh_answer, parse_ip, resolve_system,
+            to_record,
+        },
+    };
+
+    #[test]
+    fn test_parse_ip_literals() {
+        assert_eq!(parse_ip("1.2.3.4"), Some("1.2.3.4".parse().unwrap()));
+        assert_eq!(
+            parse_ip("::1"),
+            Some(IpAddr::from([0u16, 0, 0, 0, 0, 0, 0, 1]))
+        );
+        assert_eq!(
+            parse_ip("[2001:db8::1]"),
+            Some("2001:db8::1".parse().unwrap())
+        );
+        assert_eq!(parse_ip("seed.example.org"), None);
+        assert_eq!(parse_ip("localhost"), None);
+    }
+
+    #[test]
+    fn test_parse_doh_answer() {
+        let body = r#"{
+            "Status": 0,
+            "Answer": [
+                {"name": "seed.example.org.", "type": 5, "TTL": 300, "data": "node.example.org."},
+                {"name": "node.example.org.", "type": 1, "TTL": 300, "data": "203.0.113.7"},
+                {"name": "node.example.org.", "type": 28, "TTL": 300, "data": "2001:db8::7"}
+            ]
+        }"#;
+        let addrs = parse_doh_answer(body, 1090).unwrap();
+        assert_eq!(
+            addrs,
+            vec![
+                "203.0.113.7:1090".parse::<SocketAddr>().unwrap(),
+                "[2001:db8::7]:1090".parse::<SocketAddr>().unwrap(),
+            ]
+        );
+
+        // 没有记录
+        assert!(
+            parse_doh_answer(r#"{"Status": 0}"#, 1090)
+                .unwrap()
+                .is_empty()
+        );
+        // NXDOMAIN
+        assert!(parse_doh_answer(r#"{"Status": 3}"#, 1090).is_err());
+        assert!(parse_doh_answer("not json", 1090).is_err());
+    }
+
+    #[test]
+    fn test_to_record_feeds_dialer() {
+        assert!(to_record(&[]).is_none());
+
+        let addrs: Vec<SocketAddr> = vec![
+            "203.0.113.7:1090".parse().unwrap(),
+            "198.51.100.2:1090".parse().unwrap(),
+            "[2001:db8::7]:1090".parse().unwrap(),
+        ];
+        let record = to_record(&addrs).unwrap();
+        assert_eq!(record.endpoint, addrs[0]);
+        assert_eq!(record.all_endpoints(), addrs);
+
+        // 全部地址参与竞速，IPv6 优先、两族交替
+        let plan: Vec<SocketAddr> = dialer::plan(&record)
+            .into_iter()
+            .map(|a| a.endpoint)
+            .collect();
+        assert_eq!(plan, vec![addrs[2], addrs[0], addrs[1]]);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_system_localhost() {
+        let addrs = resolve_system("localhost", 1090, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert!(!addrs.is_empty());
+        assert!(
+            addrs
+                .iter()
+                .all(|a| a.ip().is_loopback() && a.port() == 1090)
+        );
+    }
+
+    #[test]
+    fn test_resolver_config() {
+        let defaults = ResolverConfig::default();
+        assert_eq!(defaults.doh, None);
+        assert_eq!(
+            defaults.timeout(),
+            Duration::from_millis(DEFAULT_RESOLVE_TIMEOUT_MS)
+        );
+
+        let text = r#"
+[resolver]
+doh = "https://cloudflare-dns.com/dns-query"
+"#;
+        let config = Config::parse(text, Path::new("node.toml")).unwrap();
+        assert_eq!(
+            config.resolver.doh.as_deref(),
+            Some("https://cloudflare-dns.com/dns-query")
+        );
+        assert_eq!(config.resolver.timeout_ms, DEFAULT_RESOLVE_TIMEOUT_MS);
+    }
+}
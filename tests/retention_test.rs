@@ -0,0 +1,224 @@
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, path::Path};
+
+    use tempfile::tempdir;
+    use zz_p2p::{
+        config::Config,
+        retention::{
+            DEFAULT_RETENTION_INTERVAL_SECS, PruneReport, RetentionConfig, RetentionMetrics,
+            expired_queue, message_size, prune_conversations,
+        },
+        user_store::{ChatMessage, UserStore},
+        wal::{PendingFrame, WAL_FILE, Wal},
+    };
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn msg(contact: &str, id: u64, timestamp: i64) -> ChatMessage {
+        ChatMessage {
+            id,
+            contact_address: contact.into(),
+            content: "x".repeat(64),
+            is_sent: true,
+            status: "sent".into(),
+            timestamp,
+        }
+    }
+
+    fn frame(request_id: u64, created_at: u128, message: &str) -> PendingFrame {
+        PendingFrame {
+            request_id,
+            receiver: "peer".into(),
+            message: message.into(),
+            created_at,
+            last_attempt: 0,
+        }
+    }
+
+    #[test]
+    fn test_default_policy_keeps_everything() {
+        let policy = RetentionConfig::default();
+        assert!(policy.keeps_all_messages());
+        assert!(policy.keeps_all_queued());
+        assert_eq!(policy.interval_secs, DEFAULT_RETENTION_INTERVAL_SECS);
+
+        let mut conversations = BTreeMap::new();
+        conversations.insert("alice".to_string(), vec![msg("alice", 1, 0)]);
+        let report = prune_conversations(&mut conversations, &policy, NOW);
+        assert!(report.is_empty());
+        assert_eq!(conversations["alice"].len(), 1);
+    }
+
+    #[test]
+    fn test_prune_by_age_removes_empty_conversations() {
+        let policy = RetentionConfig {
+            max_age_secs: 100,
+            ..Default::default()
+        };
+        let mut conversations = BTreeMap::new();
+        conversations.insert(
+            "alice".to_string(),
+            vec![msg("alice", 1, NOW - 500), msg("alice", 2, NOW - 10)],
+        );
+        conversations.insert("bob".to_string(), vec![msg("bob", 1, NOW - 200)]);
+
+        let report = prune_conversations(&mut conversations, &policy, NOW);
+        assert_eq!(report.messages, 2);
+        assert_eq!(
+            report.bytes,
+            message_size(&msg("alice", 1, NOW - 500)) + message_size(&msg("bob", 1, NOW - 200))
+        );
+        assert_eq!(conversations.len(), 1);
+        assert_eq!(conversations["alice"][0].id, 2);
+    }
+
+    #[test]
+    fn test_prune_conversation_bytes_drops_oldest_first() {
+        let size = message_size(&msg("alice", 1, NOW));
+        let policy = RetentionConfig {
+            max_conversation_bytes: size * 2,
+            ..Default::default()
+        };
+        let mut conversations = BTreeMap::new();
+        conversations.insert(
+            "alice".to_string(),
+            (1..=4)
+                .map(|id| msg("alice", id, NOW + id as i64))
+                .collect(),
+        );
+        conversations.insert("bob".to_string(), vec![msg("bob", 1, NOW)]);
+
+        let report = prune_conversations(&mut conversations, &policy, NOW);
+        assert_eq!(report.messages, 2);
+        let ids: Vec<u64> = conversations["alice"].iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![3, 4]);
+        assert_eq!(conversations["bob"].len(), 1);
+    }
+
+    #[test]
+    fn test_prune_total_bytes_drops_globally_oldest() {
+        let size = message_size(&msg("alice", 1, NOW));
+        let policy = RetentionConfig {
+            max_total_bytes: size * 2,
+            ..Default::default()
+        };
+        let mut conversations = BTreeMap::new();
+        conversations.insert(
+            "alice".to_string(),
+            vec![msg("alice", 1, NOW - 30), msg("alice", 2, NOW - 5)],
+        );
+        conversations.insert(
+            "bob".to_string(),
+            vec![msg("bob", 1, NOW - 20), msg("bob", 2, NOW - 10)],
+        );
+
+        let report = prune_conversations(&mut conversations, &policy, NOW);
+        assert_eq!(report.messages, 2);
+        assert_eq!(conversations["alice"][0].id, 2);
+        assert_eq!(conversations["bob"][0].id, 2);
+    }
+
+    #[test]
+    fn test_expired_queue_by_age_and_bytes() {
+        let pending = vec![
+            frame(1, 1_000, "old"),
+            frame(2, 50_000, &"a".repeat(100)),
+            frame(3, 60_000, &"b".repeat(100)),
+        ];
+
+        let by_age = RetentionConfig {
+            max_queue_age_secs: 30,
+            ..Default::default()
+        };
+        assert_eq!(expired_queue(&pending, &by_age, 61_000), vec![1]);
+
+        let by_bytes = RetentionConfig {
+            max_queue_bytes: 150,
+            ..Default::default()
+        };
+        assert_eq!(expired_queue(&pending, &by_bytes, 61_000), vec![1, 2]);
+
+        assert!(expired_queue(&pending, &RetentionConfig::default(), 61_000).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_user_store_retain_messages_rewrites_changed() {
+        let dir = tempdir().unwrap();
+        let store = UserStore::new(dir.path().to_path_buf());
+        store
+            .add_message("alice", "one", true, "sent")
+            .await
+            .unwrap();
+        store
+            .add_message("alice", "two", true, "sent")
+            .await
+            .unwrap();
+        store.add_message("bob", "hi", false, "read").await.unwrap();
+
+        let removed = store
+            .retain_messages(|conversations| {
+                assert_eq!(conversations.len(), 2);
+                conversations.get_mut("alice").unwrap().remove(0);
+                conversations.remove("bob");
+                2
+            })
+            .await
+            .unwrap();
+        assert_eq!(removed, 2);
+
+        let alice = store.get_messages("alice").await.unwrap();
+        assert_eq!(alice.len(), 1);
+        assert_eq!(alice[0].content, "two");
+        assert!(store.get_messages("bob").await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_wal_abandon_completes_pending() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(WAL_FILE);
+        {
+            let wal = Wal::open(&path, 1024 * 1024).unwrap();
+            wal.append("peer", 1, "a").unwrap();
+            wal.append("peer", 2, "b").unwrap();
+            assert_eq!(wal.abandon(&[1, 99]).unwrap(), 1);
+        }
+        let wal = Wal::open(&path, 1024 * 1024).unwrap();
+        let pending = wal.pending();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].request_id, 2);
+    }
+
+    #[test]
+    fn test_metrics_accumulate() {
+        let metrics = RetentionMetrics::default();
+        let report = PruneReport {
+            messages: 3,
+            bytes: 300,
+            queued: 1,
+            queued_bytes: 10,
+        };
+        metrics.record(&report, 1_000);
+        metrics.record(&report, 2_000);
+        let stats = metrics.snapshot();
+        assert_eq!(stats.runs, 2);
+        assert_eq!(stats.evicted_messages, 6);
+        assert_eq!(stats.evicted_bytes, 600);
+        assert_eq!(stats.evicted_queued, 2);
+        assert_eq!(stats.evicted_queued_bytes, 20);
+        assert_eq!(stats.last_run, 2_000);
+    }
+
+    #[test]
+    fn test_parse_retention_config() {
+        let text = "[retention]\nmax_age_secs = 2592000\nmax_queue_bytes = 1048576\n";
+        let config = Config::parse(text, Path::new("node.toml")).unwrap();
+        assert_eq!(config.retention.max_age_secs, 2_592_000);
+        assert_eq!(config.retention.max_queue_bytes, 1_048_576);
+        assert_eq!(config.retention.max_total_bytes, 0);
+        assert_eq!(
+            config.retention.interval_secs,
+            DEFAULT_RETENTION_INTERVAL_SECS
+        );
+    }
+}
@@ -0,0 +1,147 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::Path,
+        sync::{
+            Arc,
+            atomic::{AtomicU32, Ordering},
+        },
+        time::Duration,
+    };
+
+    use zz_p2p::config::Config;
+    use zz_p2p::protocols::broadcast::BROADCAST_SEND_TIMEOUT_MS;
+    use zz_p2p::retry::{
+        DEFAULT_CONNECT_TIMEOUT_MS, NetworkConfig, Operation, RetryPolicy, retry, within,
+    };
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 1_000,
+            multiplier: 2.0,
+            jitter: 0.0,
+        };
+        assert_eq!(policy.base_delay(1), Duration::from_millis(100));
+        assert_eq!(policy.base_delay(2), Duration::from_millis(200));
+        assert_eq!(policy.base_delay(4), Duration::from_millis(800));
+        assert_eq!(policy.base_delay(5), Duration::from_millis(1_000));
+        assert_eq!(policy.base_delay(60), Duration::from_millis(1_000));
+        assert_eq!(policy.delay(3), Duration::from_millis(400));
+    }
+
+    #[test]
+    fn test_jitter_bounds() {
+        let policy = RetryPolicy {
+            initial_backoff_ms: 1_000,
+            jitter: 0.25,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.delay_with(1, -1.0), Duration::from_millis(750));
+        assert_eq!(policy.delay_with(1, 1.0), Duration::from_millis(1_250));
+        for _ in 0..100 {
+            let delay = policy.delay(1);
+            assert!(delay >= Duration::from_millis(750) && delay <= Duration::from_millis(1_250));
+        }
+    }
+
+    #[test]
+    fn test_defaults() {
+        let network = NetworkConfig::default();
+        let connect = network.policy(Operation::Connect);
+        assert_eq!(
+            connect.timeout,
+            Some(Duration::from_millis(DEFAULT_CONNECT_TIMEOUT_MS))
+        );
+        assert_eq!(connect.retry, RetryPolicy::default());
+        assert_eq!(
+            network.policy(Operation::Forward).timeout,
+            Some(Duration::from_millis(BROADCAST_SEND_TIMEOUT_MS))
+        );
+    }
+
+    #[test]
+    fn test_overrides_from_config_file() {
+        let text = r#"
+            [network.timeouts]
+            send_ms = 0
+            handshake_ms = 4000
+
+            [network.retry]
+            max_attempts = 4
+            initial_backoff_ms = 250
+
+            [network.overrides.connect]
+            timeout_ms = 3000
+            max_attempts = 6
+            jitter = 0.0
+        "#;
+        let config = Config::parse(text, Path::new("node.toml")).unwrap();
+        let network = &config.network;
+
+        let connect = network.policy(Operation::Connect);
+        assert_eq!(connect.timeout, Some(Duration::from_millis(3_000)));
+        assert_eq!(connect.retry.max_attempts, 6);
+        assert_eq!(connect.retry.initial_backoff_ms, 250);
+        assert_eq!(connect.retry.jitter, 0.0);
+
+        let handshake = network.policy(Operation::Handshake);
+        assert_eq!(handshake.timeout, Some(Duration::from_millis(4_000)));
+        assert_eq!(handshake.retry.max_attempts, 4);
+        assert_eq!(handshake.retry.jitter, RetryPolicy::default().jitter);
+
+        assert_eq!(network.policy(Operation::Send).timeout, None);
+    }
+
+    #[tokio::test]
+    async fn test_retry_stops_after_success_or_max_attempts() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff_ms: 1,
+            max_backoff_ms: 1,
+            multiplier: 2.0,
+            jitter: 0.0,
+        };
+
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = calls.clone();
+        let result = retry(&policy, "flaky", |attempt| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if attempt < 2 {
+                    anyhow::bail!("attempt {} failed", attempt)
+                }
+                Ok(attempt)
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        calls.store(0, Ordering::SeqCst);
+        let counter = calls.clone();
+        let result: anyhow::Result<()> = retry(&policy, "broken", |attempt| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async move { anyhow::bail!("attempt {} failed", attempt) }
+        })
+        .await;
+        assert_eq!(result.unwrap_err().to_string(), "attempt 3 failed");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_within_timeout() {
+        let slow = async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        };
+        assert!(
+            within(Some(Duration::from_millis(10)), "slow", slow)
+                .await
+                .is_err()
+        );
+        assert_eq!(within(None, "fast", async { Ok(1) }).await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,100 @@
+#[cfg(test)]
+mod tests {
+    use zz_p2p::web::routes::{Found, Routes};
+
+    fn handler(found: Found<'_, &'static str>) -> Option<&'static str> {
+        match found {
+            Found::Handler(h) => Some(*h),
+            _ => None,
+        }
+    }
+
+    fn allow(found: Found<'_, &'static str>) -> Option<Vec<String>> {
+        match found {
+            Found::MethodNotAllowed(allow) => Some(allow),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_literal_routes() {
+        let mut routes = Routes::new();
+        routes
+            .get("/wallet", "wallet")
+            .post("/api/transfer", "transfer")
+            .get("/api/contacts", "list")
+            .post("/api/contacts", "add");
+
+        assert_eq!(handler(routes.find("GET", "/wallet")), Some("wallet"));
+        // 查询串、尾部斜杠与方法大小写不影响匹配
+        assert_eq!(handler(routes.find("get", "/wallet/?x=1")), Some("wallet"));
+        assert_eq!(handler(routes.find("HEAD", "/wallet")), Some("wallet"));
+        assert_eq!(
+            handler(routes.find("POST", "/api/contacts?address=a")),
+            Some("add")
+        );
+        assert!(matches!(routes.find("GET", "/nowhere"), Found::NotFound));
+        assert!(matches!(routes.find("GET", "/api"), Found::NotFound));
+    }
+
+    #[test]
+    fn test_method_not_allowed() {
+        let mut routes = Routes::new();
+        routes
+            .get("/api/contacts", "list")
+            .post("/api/contacts", "add")
+            .delete("/api/contacts", "delete");
+
+        assert_eq!(
+            allow(routes.find("PUT", "/api/contacts")),
+            Some(vec![
+                "DELETE".to_string(),
+                "GET".to_string(),
+                "HEAD".to_string(),
+                "POST".to_string(),
+            ])
+        );
+        // 同一方法后注册的覆盖先注册的
+        routes.post("/api/contacts", "add2");
+        assert_eq!(handler(routes.find("POST", "/api/contacts")), Some("add2"));
+    }
+
+    #[test]
+    fn test_wildcard() {
+        let mut routes = Routes::new();
+        routes
+            .all("/web/*", "static")
+            .get("/web/api", "api")
+            .post("/upload/*", "upload");
+
+        assert_eq!(handler(routes.find("GET", "/web/a/b.css")), Some("static"));
+        assert_eq!(handler(routes.find("DELETE", "/web")), Some("static"));
+        // 字面量优先于通配
+        assert_eq!(handler(routes.find("GET", "/web/api")), Some("api"));
+        // 字面量方法不匹配时退回通配
+        assert_eq!(handler(routes.find("POST", "/web/api")), Some("static"));
+        assert_eq!(
+            allow(routes.find("GET", "/upload/x")),
+            Some(vec!["POST".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_mount() {
+        let mut api = Routes::new();
+        api.get("/data", "data").post("/admin", "admin");
+        let mut nested = Routes::new();
+        nested.get("/info", "info");
+        api.mount("/v2", nested);
+
+        let mut routes = Routes::new();
+        routes.get("/", "index").get("/api/data", "old");
+        routes.mount("/api/", api);
+
+        assert_eq!(handler(routes.find("GET", "/")), Some("index"));
+        assert_eq!(handler(routes.find("GET", "/api/data")), Some("data"));
+        assert_eq!(handler(routes.find("POST", "/api/admin")), Some("admin"));
+        assert_eq!(handler(routes.find("GET", "/api/v2/info")), Some("info"));
+        assert!(allow(routes.find("GET", "/api/admin")).is_some());
+    }
+}
@@ -0,0 +1,71 @@
+#[cfg(test)]
+mod tests {
+    use aex::tcp::types::Codec;
+    use aex::time::SystemTime;
+    use zz_account::address::FreeWebMovementAddress;
+    use zz_p2p::protocols::{
+        command::{Action, Entity, P2PCommand},
+        frame::P2PFrame,
+        routing::{
+            DEFAULT_FRAME_TTL, MAX_NEXT_HOPS, ROUTE_EXPIRY_MS, RoutingTable, forget_neighbor,
+            learn, learn_from_handshake, next_hops,
+        },
+        version::PROTOCOL_V2,
+    };
+
+    #[test]
+    fn test_learn_from_handshake_prefers_direct_routes() {
+        let table = RoutingTable::default();
+        learn_from_handshake(&table, "self", "b", ["c", "self", "b"]);
+        learn_from_handshake(&table, "self", "c", ["b"]);
+
+        let now = SystemTime::timestamp();
+        // c 直连（1 跳）优先于经由 b（2 跳）
+        assert_eq!(
+            next_hops(&table, "c", now),
+            vec!["c".to_string(), "b".to_string()]
+        );
+        assert!(next_hops(&table, "self", now).is_empty());
+    }
+
+    #[test]
+    fn test_next_hops_limit_and_expiry() {
+        let table = RoutingTable::default();
+        for hop in ["h1", "h2", "h3", "h4"] {
+            learn(&table, "dest", hop, 3);
+        }
+        learn(&table, "dest", "h4", 2);
+
+        let now = SystemTime::timestamp();
+        let hops = next_hops(&table, "dest", now);
+        assert_eq!(hops.len(), MAX_NEXT_HOPS);
+        assert_eq!(hops[0], "h4");
+
+        assert!(next_hops(&table, "dest", now + ROUTE_EXPIRY_MS + 1).is_empty());
+    }
+
+    #[test]
+    fn test_forget_neighbor_drops_routes() {
+        let table = RoutingTable::default();
+        learn_from_handshake(&table, "self", "b", ["c"]);
+        forget_neighbor(&table, "b");
+        assert!(table.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_destination_and_ttl_roundtrip() {
+        let addr = FreeWebMovementAddress::random();
+        let cmd = P2PCommand::new(Entity::Message, Action::SendText, vec![1, 2, 3]);
+        let mut frame = P2PFrame::build_to(&addr, cmd, PROTOCOL_V2, Some("dest".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(frame.ttl, DEFAULT_FRAME_TTL);
+
+        // TTL 不参与签名，中继递减后仍可验签
+        frame.ttl -= 1;
+        let bytes = Codec::encode(&frame).unwrap();
+        let decoded: P2PFrame = P2PFrame::verify_bytes(&bytes).unwrap();
+        assert_eq!(decoded.ttl, DEFAULT_FRAME_TTL - 1);
+        assert_eq!(decoded.body.destination.as_deref(), Some("dest"));
+    }
+}
@@ -0,0 +1,171 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use aex::connection::global::GlobalContext;
+    use zz_account::address::FreeWebMovementAddress;
+    use zz_p2p::{
+        protocols::commands::identity::IdentityBindings,
+        safety_number::{
+            VerificationStatus, VerifiedContact, VerifiedContacts, confirm, fingerprint, inspect,
+            list, matches, safety_number, short_fingerprint,
+        },
+    };
+
+    fn key(id: &FreeWebMovementAddress) -> Vec<u8> {
+        id.public_key.to_bytes().to_vec()
+    }
+
+    async fn context() -> (Arc<GlobalContext>, FreeWebMovementAddress) {
+        let gctx = Arc::new(GlobalContext::new("127.0.0.1:0".parse().unwrap(), None));
+        gctx.set(FreeWebMovementAddress::random()).await;
+        gctx.set(IdentityBindings::default()).await;
+        let peer = FreeWebMovementAddress::random();
+        (gctx, peer)
+    }
+
+    async fn bind(gctx: &Arc<GlobalContext>, peer: &FreeWebMovementAddress) {
+        let bindings = gctx.get::<IdentityBindings>().await.unwrap();
+        bindings.insert(peer.to_string(), key(peer));
+    }
+
+    #[test]
+    fn test_safety_number_is_symmetric() {
+        let alice = FreeWebMovementAddress::random();
+        let bob = FreeWebMovementAddress::random();
+        let a = safety_number(
+            &alice.to_string(),
+            &key(&alice),
+            &bob.to_string(),
+            &key(&bob),
+        );
+        let b = safety_number(
+            &bob.to_string(),
+            &key(&bob),
+            &alice.to_string(),
+            &key(&alice),
+        );
+        assert_eq!(a, b);
+
+        let groups: Vec<&str> = a.split(' ').collect();
+        assert_eq!(groups.len(), 12);
+        assert!(
+            groups
+                .iter()
+                .all(|g| g.len() == 5 && g.chars().all(|c| c.is_ascii_digit()))
+        );
+    }
+
+    #[test]
+    fn test_safety_number_depends_on_keys() {
+        let alice = FreeWebMovementAddress::random();
+        let bob = FreeWebMovementAddress::random();
+        let mallory = FreeWebMovementAddress::random();
+        let real = safety_number(
+            &alice.to_string(),
+            &key(&alice),
+            &bob.to_string(),
+            &key(&bob),
+        );
+        // 地址相同但公钥被替换
+        let forged = safety_number(
+            &alice.to_string(),
+            &key(&alice),
+            &bob.to_string(),
+            &key(&mallory),
+        );
+        assert_ne!(real, forged);
+        assert_ne!(
+            fingerprint(&bob.to_string(), &key(&bob)),
+            fingerprint(&mallory.to_string(), &key(&bob))
+        );
+        assert_eq!(short_fingerprint(&key(&bob)).split(':').count(), 4);
+    }
+
+    #[test]
+    fn test_matches_ignores_separators() {
+        assert!(matches("12345 67890", "1234567890"));
+        assert!(matches("12345-67890", " 12345 67890 "));
+        assert!(!matches("12345 67890", "12345 67891"));
+        assert!(!matches("", ""));
+    }
+
+    #[test]
+    fn test_status_tracks_key_changes() {
+        let mut contacts = VerifiedContacts::default();
+        assert_eq!(contacts.status("bob", None), VerificationStatus::Unverified);
+        contacts.contacts.insert(
+            "bob".into(),
+            VerifiedContact {
+                public_key: "0102".into(),
+                safety_number: "00000".into(),
+                verified_at: 0,
+            },
+        );
+        assert_eq!(
+            contacts.status("bob", Some(&[1, 2])),
+            VerificationStatus::Verified
+        );
+        assert_eq!(contacts.status("bob", None), VerificationStatus::Verified);
+        assert_eq!(
+            contacts.status("bob", Some(&[1, 3])),
+            VerificationStatus::KeyChanged
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_key_cannot_be_confirmed() {
+        let (gctx, peer) = context().await;
+        let v = inspect(&gctx, &peer.to_string()).await.unwrap();
+        assert_eq!(v.status, VerificationStatus::Unverified);
+        assert!(v.safety_number.is_none());
+        assert!(confirm(&gctx, &peer.to_string(), None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_confirm_and_clear() {
+        let (gctx, peer) = context().await;
+        bind(&gctx, &peer).await;
+        let address = peer.to_string();
+
+        let v = inspect(&gctx, &address).await.unwrap();
+        let number = v.safety_number.clone().unwrap();
+        assert_eq!(v.status, VerificationStatus::Unverified);
+
+        assert!(confirm(&gctx, &address, Some("00000")).await.is_err());
+        assert!(list(&gctx).await.is_empty());
+
+        let confirmed = confirm(&gctx, &address, Some(&number.replace(' ', "")))
+            .await
+            .unwrap();
+        assert_eq!(confirmed.status, VerificationStatus::Verified);
+        assert!(confirmed.verified_at.is_some());
+        assert_eq!(list(&gctx).await[&address].safety_number, number);
+        assert_eq!(
+            zz_p2p::safety_number::status(&gctx, &address).await,
+            VerificationStatus::Verified
+        );
+
+        assert!(zz_p2p::safety_number::clear(&gctx, &address).await);
+        assert!(!zz_p2p::safety_number::clear(&gctx, &address).await);
+        assert_eq!(
+            inspect(&gctx, &address).await.unwrap().status,
+            VerificationStatus::Unverified
+        );
+    }
+
+    #[tokio::test]
+    async fn test_key_change_after_verification() {
+        let (gctx, peer) = context().await;
+        bind(&gctx, &peer).await;
+        let address = peer.to_string();
+        confirm(&gctx, &address, None).await.unwrap();
+
+        let bindings = gctx.get::<IdentityBindings>().await.unwrap();
+        bindings.insert(address.clone(), key(&FreeWebMovementAddress::random()));
+        assert_eq!(
+            inspect(&gctx, &address).await.unwrap().status,
+            VerificationStatus::KeyChanged
+        );
+    }
+}
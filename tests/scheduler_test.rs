@@ -0,0 +1,171 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicU64, Ordering},
+        },
+        time::Duration,
+    };
+
+    use chrono::{TimeZone, Utc};
+    use tokio_util::sync::CancellationToken;
+    use zz_p2p::scheduler::{CronSchedule, Scheduler};
+
+    fn counter(scheduler: &Scheduler, name: &str, every: Duration) -> Arc<AtomicU64> {
+        let runs = Arc::new(AtomicU64::new(0));
+        let counted = runs.clone();
+        scheduler.register_interval(name, every, move || {
+            let counted = counted.clone();
+            async move {
+                counted.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        runs
+    }
+
+    #[test]
+    fn test_cron_parsing() {
+        assert!("* * * *".parse::<CronSchedule>().is_err());
+        assert!("60 * * * *".parse::<CronSchedule>().is_err());
+        assert!("*/0 * * * *".parse::<CronSchedule>().is_err());
+        assert!("5-1 * * * *".parse::<CronSchedule>().is_err());
+        assert!("0 3 * * mon".parse::<CronSchedule>().is_err());
+        let cron: CronSchedule = "@daily".parse().unwrap();
+        assert_eq!(cron.to_string(), "@daily");
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        let at = |y, mo, d, h, mi| Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap();
+        let now = Utc.with_ymd_and_hms(2026, 3, 14, 10, 17, 42).unwrap();
+
+        let every_15: CronSchedule = "*/15 * * * *".parse().unwrap();
+        assert_eq!(every_15.next_after(now), Some(at(2026, 3, 14, 10, 30)));
+        // 恰好在匹配的时刻时取下一次
+        assert_eq!(
+            every_15.next_after(at(2026, 3, 14, 10, 30)),
+            Some(at(2026, 3, 14, 10, 45))
+        );
+
+        let nightly: CronSchedule = "30 3 * * *".parse().unwrap();
+        assert_eq!(nightly.next_after(now), Some(at(2026, 3, 15, 3, 30)));
+
+        // 2026-03-14 是周六；周日可写作 0 或 7
+        let sunday: CronSchedule = "0 4 * * 7".parse().unwrap();
+        assert_eq!(sunday.next_after(now), Some(at(2026, 3, 15, 4, 0)));
+        let weekdays: CronSchedule = "0 9 * * 1-5".parse().unwrap();
+        assert_eq!(weekdays.next_after(now), Some(at(2026, 3, 16, 9, 0)));
+
+        // 日与周都限定时满足其一即可
+        let either: CronSchedule = "0 0 20 * 0".parse().unwrap();
+        assert_eq!(either.next_after(now), Some(at(2026, 3, 15, 0, 0)));
+
+        let leap: CronSchedule = "0 0 29 2 *".parse().unwrap();
+        assert_eq!(leap.next_after(now), Some(at(2028, 2, 29, 0, 0)));
+        let never: CronSchedule = "0 0 30 2 *".parse().unwrap();
+        assert_eq!(never.next_after(now), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_interval_tasks_run_and_report() {
+        let scheduler = Scheduler::new(CancellationToken::new());
+        let runs = counter(&scheduler, "tick", Duration::from_secs(10));
+        tokio::time::sleep(Duration::from_secs(35)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+        let tasks = scheduler.tasks();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].name, "tick");
+        assert_eq!(tasks[0].schedule, "every 10s");
+        assert_eq!(tasks[0].runs, 3);
+        assert!(tasks[0].last_run.is_some());
+        assert!(tasks[0].next_run.is_some());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_dynamic_period_is_reevaluated() {
+        let scheduler = Scheduler::new(CancellationToken::new());
+        let period = Arc::new(AtomicU64::new(10));
+        let runs = Arc::new(AtomicU64::new(0));
+        let (p, r) = (period.clone(), runs.clone());
+        scheduler.register_dynamic(
+            "dynamic",
+            move || {
+                let p = p.clone();
+                async move { Duration::from_secs(p.load(Ordering::SeqCst)) }
+            },
+            move || {
+                let r = r.clone();
+                async move {
+                    r.fetch_add(1, Ordering::SeqCst);
+                }
+            },
+        );
+        tokio::time::sleep(Duration::from_secs(15)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        // 第二轮已按 10 秒排定，之后的轮次按新的间隔
+        period.store(100, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_secs(50)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        tokio::time::sleep(Duration::from_secs(100)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_panicking_task_keeps_running() {
+        let scheduler = Scheduler::new(CancellationToken::new());
+        scheduler.register_interval("boom", Duration::from_secs(1), || async {
+            panic!("boom");
+        });
+        tokio::time::sleep(Duration::from_millis(3500)).await;
+        let task = &scheduler.tasks()[0];
+        assert_eq!(task.runs, 3);
+        assert_eq!(task.failures, 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cancel_and_replace() {
+        let scheduler = Scheduler::new(CancellationToken::new());
+        let first = counter(&scheduler, "job", Duration::from_secs(1));
+        // 同名注册替换旧任务
+        let second = counter(&scheduler, "job", Duration::from_secs(1));
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        assert_eq!(first.load(Ordering::SeqCst), 0);
+        assert_eq!(second.load(Ordering::SeqCst), 2);
+        assert_eq!(scheduler.tasks().len(), 1);
+
+        assert!(scheduler.cancel("job"));
+        assert!(!scheduler.cancel("job"));
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(second.load(Ordering::SeqCst), 2);
+        assert!(scheduler.tasks().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_shutdown_waits_for_running_job() {
+        let token = CancellationToken::new();
+        let scheduler = Scheduler::new(token.child_token());
+        let finished = Arc::new(AtomicU64::new(0));
+        let done = finished.clone();
+        scheduler.register_interval("slow", Duration::from_secs(1), move || {
+            let done = done.clone();
+            async move {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                done.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        // 第一轮开始执行后停止
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        scheduler.shutdown().await;
+        assert_eq!(finished.load(Ordering::SeqCst), 1);
+        assert!(scheduler.tasks().is_empty());
+
+        // 父令牌取消同样停止调度器
+        let scheduler = Scheduler::new(token.child_token());
+        let runs = counter(&scheduler, "tick", Duration::from_secs(1));
+        token.cancel();
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+    }
+}
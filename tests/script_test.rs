@@ -0,0 +1,125 @@
+#[cfg(test)]
+mod tests {
+    use aex::connection::global::GlobalContext;
+    use serde_json::Value;
+    use std::{net::SocketAddr, sync::Arc};
+    use zz_p2p::{
+        cli::Cli,
+        cli_error, cli_println,
+        script::{self, EXIT_FAILED, EXIT_OK},
+    };
+
+    fn create_mock_ctx() -> Arc<GlobalContext> {
+        let addr = "127.0.0.1:1080".parse::<SocketAddr>().unwrap();
+        Arc::new(GlobalContext::new(addr, None))
+    }
+
+    fn test_cli() -> Cli {
+        let mut cli = Cli::new();
+        cli.register("echo", |args, _ctx| async move {
+            cli_println!("{}", args.join(" "));
+            script::attach(&args);
+        });
+        cli.register("fail", |_args, _ctx| async move {
+            cli_println!("trying");
+            cli_error!("Usage: fail <never works>");
+        });
+        cli.register("boom", |_args, _ctx| async move {
+            panic!("boom");
+        });
+        cli
+    }
+
+    async fn run(input: &str, keep_going: bool) -> (i32, Vec<Value>) {
+        let mut out = Vec::new();
+        let code = script::run(
+            &test_cli(),
+            input.as_bytes(),
+            create_mock_ctx(),
+            keep_going,
+            &mut out,
+        )
+        .await;
+        let lines = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        (code, lines)
+    }
+
+    #[tokio::test]
+    async fn test_success_outputs_one_json_line_per_command() {
+        let input = "# setup\n\necho hello world\n  echo \"a b\"  \n";
+        let (code, lines) = run(input, false).await;
+        assert_eq!(code, EXIT_OK);
+        assert_eq!(lines.len(), 3);
+
+        assert_eq!(lines[0]["line"], 3);
+        assert_eq!(lines[0]["command"], "echo");
+        assert_eq!(lines[0]["ok"], true);
+        assert_eq!(lines[0]["output"][0], "hello world");
+        assert_eq!(lines[0]["data"][1], "world");
+        assert!(lines[0].get("errors").is_none());
+        assert_eq!(lines[1]["args"][0], "a b");
+
+        assert_eq!(lines[2]["done"], true);
+        assert_eq!(lines[2]["commands"], 2);
+        assert_eq!(lines[2]["failed"], 0);
+        assert_eq!(lines[2]["exit_code"], EXIT_OK);
+    }
+
+    #[tokio::test]
+    async fn test_stops_at_first_failure() {
+        let (code, lines) = run("echo 1\nfail\necho 2\n", false).await;
+        assert_eq!(code, EXIT_FAILED);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1]["ok"], false);
+        assert_eq!(lines[1]["output"][0], "trying");
+        assert_eq!(lines[1]["errors"][0], "Usage: fail <never works>");
+        assert_eq!(lines[2]["commands"], 2);
+        assert_eq!(lines[2]["failed"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_keep_going_runs_all_commands() {
+        let input = "frobnicate\nboom\necho \"unterminated\necho ok\n";
+        let (code, lines) = run(input, true).await;
+        assert_eq!(code, EXIT_FAILED);
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0]["errors"][0], "Unknown command: 'frobnicate'");
+        // panic 只让这一条命令失败
+        assert_eq!(lines[1]["ok"], false);
+        assert!(
+            lines[1]["errors"][0]
+                .as_str()
+                .unwrap()
+                .starts_with("Command panicked")
+        );
+        assert_eq!(lines[2]["ok"], false);
+        assert_eq!(lines[3]["ok"], true);
+        assert_eq!(lines[4]["failed"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_exit_ends_script() {
+        let (code, lines) = run("echo 1\nexit\nfail\n", false).await;
+        assert_eq!(code, EXIT_OK);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["commands"], 1);
+
+        // 空脚本只输出汇总
+        let (code, lines) = run("", false).await;
+        assert_eq!(code, EXIT_OK);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["commands"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_outside_script_mode_prints() {
+        // 不在脚本模式下直接打印，不会 panic
+        cli_println!("plain {}", 1);
+        cli_error!("error {}", 2);
+        script::attach(&"ignored");
+    }
+}
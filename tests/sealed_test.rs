@@ -0,0 +1,119 @@
+#[cfg(test)]
+mod tests {
+    use aex::tcp::types::Codec;
+    use zz_account::address::FreeWebMovementAddress;
+    use zz_p2p::protocols::{
+        capabilities::{CAP_SEALED, LOCAL_FEATURES, feature_names},
+        commands::{
+            message::MessageCommand,
+            sealed::{SealedMessageCommand, open, seal},
+        },
+        error::ProtocolError,
+    };
+
+    fn message(
+        sender: &FreeWebMovementAddress,
+        receiver: &FreeWebMovementAddress,
+    ) -> MessageCommand {
+        MessageCommand {
+            sender: sender.to_string(),
+            receiver: receiver.to_string(),
+            request_id: 7,
+            timestamp: 1_700_000_000_000,
+            message: "meet at the usual place".to_string(),
+            epoch: 1,
+            seq: 3,
+        }
+    }
+
+    fn public_key(identity: &FreeWebMovementAddress) -> Vec<u8> {
+        identity.public_key.to_bytes().to_vec()
+    }
+
+    #[test]
+    fn test_seal_and_open() {
+        let alice = FreeWebMovementAddress::random();
+        let bob = FreeWebMovementAddress::random();
+        let original = message(&alice, &bob);
+
+        let sealed = seal(&alice, &public_key(&bob), &original).unwrap();
+        assert_eq!(sealed.receiver, bob.to_string());
+        let content = open(&bob, &sealed).unwrap();
+        assert_eq!(content.message, original);
+        assert_eq!(content.public_key, public_key(&alice));
+
+        // 每次加密使用新的一次性密钥
+        let again = seal(&alice, &public_key(&bob), &original).unwrap();
+        assert_ne!(again.ephemeral_key, sealed.ephemeral_key);
+        assert_ne!(again.sealed, sealed.sealed);
+    }
+
+    #[test]
+    fn test_relay_sees_only_routing_metadata() {
+        let alice = FreeWebMovementAddress::random();
+        let bob = FreeWebMovementAddress::random();
+        let original = message(&alice, &bob);
+        let sealed = seal(&alice, &public_key(&bob), &original).unwrap();
+
+        let wire = Codec::encode(&sealed).unwrap();
+        let contains = |needle: &[u8]| wire.windows(needle.len()).any(|w| w == needle);
+        assert!(contains(bob.to_string().as_bytes()));
+        assert!(!contains(original.message.as_bytes()));
+        assert!(!contains(alice.to_string().as_bytes()));
+
+        // 中继（或其它节点）无法解密
+        let relay = FreeWebMovementAddress::random();
+        assert!(matches!(
+            open(&relay, &sealed),
+            Err(ProtocolError::Decrypt { .. })
+        ));
+    }
+
+    #[test]
+    fn test_tampered_envelope_rejected() {
+        let alice = FreeWebMovementAddress::random();
+        let bob = FreeWebMovementAddress::random();
+        let sealed = seal(&alice, &public_key(&bob), &message(&alice, &bob)).unwrap();
+
+        let mut flipped = sealed.clone();
+        flipped.sealed[0] ^= 1;
+        assert!(matches!(
+            open(&bob, &flipped),
+            Err(ProtocolError::Decrypt { .. })
+        ));
+
+        // 接收方地址参与密钥派生与附加数据，改写后无法解密
+        let mut redirected = sealed.clone();
+        redirected.receiver = alice.to_string();
+        assert!(open(&bob, &redirected).is_err());
+
+        let malformed = SealedMessageCommand {
+            ephemeral_key: vec![0; 33],
+            ..sealed
+        };
+        assert_eq!(
+            open(&bob, &malformed).err(),
+            Some(ProtocolError::InvalidPublicKey)
+        );
+    }
+
+    #[test]
+    fn test_forged_sender_rejected() {
+        let alice = FreeWebMovementAddress::random();
+        let bob = FreeWebMovementAddress::random();
+        let mallory = FreeWebMovementAddress::random();
+
+        // mallory 声称 alice 发送，但只能用自己的私钥签名；alice 已绑定公钥时被接收方拒绝
+        let forged = seal(&mallory, &public_key(&bob), &message(&alice, &bob)).unwrap();
+        let content = open(&bob, &forged).unwrap();
+        assert_eq!(content.message.sender, alice.to_string());
+        assert_eq!(content.public_key, public_key(&mallory));
+        assert_ne!(content.public_key, public_key(&alice));
+    }
+
+    #[test]
+    fn test_sealed_capability() {
+        assert_ne!(LOCAL_FEATURES & CAP_SEALED, 0);
+        assert_eq!(feature_names(CAP_SEALED), vec!["sealed"]);
+    }
+}
@@ -0,0 +1,232 @@
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, duplex};
+    use zz_account::address::FreeWebMovementAddress;
+    use zz_p2p::protocols::capabilities::{CAP_SECURE_LINK, LOCAL_FEATURES, feature_names};
+    use zz_p2p::secure_link::{
+        CipherSuite, IdentityProof, LOCAL_CIPHER_SUITES, SecureLink, SecureLinkMode,
+        derive_address, negotiate, should_initiate,
+    };
+
+    #[tokio::test]
+    async fn test_handshake_and_bidirectional_records() {
+        let alice = FreeWebMovementAddress::random();
+        let bob = FreeWebMovementAddress::random();
+        let bob_addr = bob.to_string();
+        let alice_addr = alice.to_string();
+
+        let (a, b) = duplex(64 * 1024);
+        let responder = tokio::spawn(async move {
+            let mut link = SecureLink::accept(b, &bob, None).await.unwrap();
+            let msg = link.recv().await.unwrap();
+            link.send(&msg).await.unwrap();
+            link.remote_address
+        });
+
+        let mut link = SecureLink::initiate(a, &alice, Some(&bob_addr))
+            .await
+            .unwrap();
+        assert_eq!(link.remote_address, bob_addr);
+
+        link.send(b"hello over noise").await.unwrap();
+        assert_eq!(link.recv().await.unwrap(), b"hello over noise");
+
+        assert_eq!(responder.await.unwrap(), alice_addr);
+    }
+
+    #[tokio::test]
+    async fn test_handshake_rejects_unexpected_identity() {
+        let alice = FreeWebMovementAddress::random();
+        let bob = FreeWebMovementAddress::random();
+        let mallory = FreeWebMovementAddress::random().to_string();
+
+        let (a, b) = duplex(64 * 1024);
+        tokio::spawn(async move {
+            let _ = SecureLink::accept(b, &bob, None).await;
+        });
+
+        assert!(
+            SecureLink::initiate(a, &alice, Some(&mallory))
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_negotiate_follows_initiator_preference() {
+        assert_eq!(
+            negotiate(&[2, 1], &[1, 2]),
+            Some(CipherSuite::X25519Aes256Gcm)
+        );
+        assert_eq!(
+            negotiate(&[9, 1], &[1, 2]),
+            Some(CipherSuite::X25519ChaCha20Poly1305)
+        );
+        assert_eq!(negotiate(&[2], &[1]), None);
+        assert_eq!(LOCAL_CIPHER_SUITES[0], CipherSuite::X25519ChaCha20Poly1305);
+        assert_eq!(CipherSuite::from_id(0), None);
+    }
+
+    #[tokio::test]
+    async fn test_alternative_suite_and_no_common_suite() {
+        let alice = FreeWebMovementAddress::random();
+        let bob = FreeWebMovementAddress::random();
+
+        let (a, b) = duplex(64 * 1024);
+        let responder = tokio::spawn(async move {
+            let mut link = SecureLink::accept(b, &bob, None).await.unwrap();
+            let msg = link.recv().await.unwrap();
+            link.send(&msg).await.unwrap();
+            link.suite
+        });
+        let mut link = SecureLink::initiate_with(a, &alice, None, &[CipherSuite::X25519Aes256Gcm])
+            .await
+            .unwrap();
+        assert_eq!(link.suite, CipherSuite::X25519Aes256Gcm);
+        link.send(b"over aes-gcm").await.unwrap();
+        assert_eq!(link.recv().await.unwrap(), b"over aes-gcm");
+        assert_eq!(responder.await.unwrap(), CipherSuite::X25519Aes256Gcm);
+
+        let alice = FreeWebMovementAddress::random();
+        let bob = FreeWebMovementAddress::random();
+        let (a, b) = duplex(64 * 1024);
+        let responder = tokio::spawn(async move {
+            SecureLink::accept_with(b, &bob, None, &[CipherSuite::X25519ChaCha20Poly1305])
+                .await
+                .is_err()
+        });
+        assert!(
+            SecureLink::initiate_with(a, &alice, None, &[CipherSuite::X25519Aes256Gcm])
+                .await
+                .is_err()
+        );
+        assert!(responder.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_stripped_offer_is_detected() {
+        let alice = FreeWebMovementAddress::random();
+        let bob = FreeWebMovementAddress::random();
+
+        let (a, mitm_a) = duplex(64 * 1024);
+        let (mitm_b, b) = duplex(64 * 1024);
+        tokio::spawn(async move {
+            let _ = SecureLink::accept(b, &bob, None).await;
+        });
+        // 中间人把主动方的套件列表改为只剩 AES-GCM
+        tokio::spawn(async move {
+            let (mut from_a, mut to_a) = tokio::io::split(mitm_a);
+            let (mut from_b, mut to_b) = tokio::io::split(mitm_b);
+            let mut hello = [0u8; 33];
+            from_a.read_exact(&mut hello).await.unwrap();
+            let mut offered = vec![0u8; hello[32] as usize];
+            from_a.read_exact(&mut offered).await.unwrap();
+            hello[32] = 1;
+            to_b.write_all(&hello).await.unwrap();
+            to_b.write_all(&[CipherSuite::X25519Aes256Gcm.id()])
+                .await
+                .unwrap();
+            tokio::join!(
+                tokio::io::copy(&mut from_a, &mut to_b),
+                tokio::io::copy(&mut from_b, &mut to_a)
+            );
+        });
+
+        let result = SecureLink::initiate(a, &alice, None).await;
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_proof_address_must_derive_from_key() {
+        let alice = FreeWebMovementAddress::random();
+        let bob = FreeWebMovementAddress::random();
+        let alice_key = alice.public_key.to_bytes().to_vec();
+        assert_eq!(derive_address(&alice_key), Some(alice.to_string()));
+        assert_eq!(derive_address(b"not a key"), None);
+
+        // 用自己的密钥签名，却声称是 bob 的地址
+        let transcript = [7u8; 32];
+        let signature = FreeWebMovementAddress::sign_message(&alice.private_key, &transcript)
+            .serialize_compact()
+            .to_vec();
+        let honest = IdentityProof {
+            address: alice.to_string(),
+            public_key: alice_key.clone(),
+            signature: signature.clone(),
+        };
+        assert!(honest.verify(&transcript));
+
+        let forged = IdentityProof {
+            address: bob.to_string(),
+            public_key: alice_key,
+            signature,
+        };
+        assert!(!forged.verify(&transcript));
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_pipes_carry_bytes_both_ways() {
+        let alice = FreeWebMovementAddress::random();
+        let bob = FreeWebMovementAddress::random();
+
+        let (a, b) = duplex(64 * 1024);
+        let responder = tokio::spawn(async move {
+            let link = SecureLink::accept(b, &bob, None).await.unwrap();
+            let (mut reader, mut writer) = link.into_plaintext();
+            let mut buf = [0u8; 5];
+            reader.read_exact(&mut buf).await.unwrap();
+            writer.write_all(b"pong:").await.unwrap();
+            writer.write_all(&buf).await.unwrap();
+            writer.flush().await.unwrap();
+        });
+
+        let link = SecureLink::initiate(a, &alice, None).await.unwrap();
+        let (mut reader, mut writer) = link.into_plaintext();
+        writer.write_all(b"hello").await.unwrap();
+        writer.flush().await.unwrap();
+        let mut buf = [0u8; 10];
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"pong:hello");
+        responder.await.unwrap();
+    }
+
+    #[test]
+    fn test_link_mode_gates_outbound_handshake() {
+        use SecureLinkMode::*;
+
+        assert_ne!(LOCAL_FEATURES & CAP_SECURE_LINK, 0);
+        assert_eq!(feature_names(CAP_SECURE_LINK), vec!["secure-link"]);
+
+        // required / disabled 不看对端能力
+        assert!(should_initiate(Required, Some(0), true));
+        assert!(!should_initiate(Disabled, Some(CAP_SECURE_LINK), false));
+
+        // preferred：按对端上次声明的能力，未知时只在此前没有失败过时尝试
+        assert!(should_initiate(Preferred, Some(CAP_SECURE_LINK), true));
+        assert!(!should_initiate(Preferred, Some(0), false));
+        assert!(should_initiate(Preferred, None, false));
+        assert!(!should_initiate(Preferred, None, true));
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_node_connections_record_negotiated_suite() {
+        use zz_p2p::testing::TestNode;
+
+        let mut server = TestNode::start().await.unwrap();
+        let mut client = TestNode::start().await.unwrap();
+        client.connect(&server).await.unwrap();
+
+        // 经节点拨号与入站路径建立的连接，两端都记录了链路握手协商出的套件
+        for node in [&client, &server] {
+            let conns = node.node.connections().await;
+            assert!(!conns.is_empty());
+            for c in conns {
+                assert_eq!(c.cipher_suite, Some(LOCAL_CIPHER_SUITES[0]));
+            }
+        }
+
+        client.stop().await;
+        server.stop().await;
+    }
+}
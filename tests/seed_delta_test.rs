@@ -0,0 +1,85 @@
+#[cfg(test)]
+mod tests {
+    use zz_p2p::protocols::commands::ack::SeedRecord;
+    use zz_p2p::protocols::commands::seed_delta::{
+        SeedDeltas, SeedView, apply, diff, digest, view_of,
+    };
+
+    fn record(port: u16, node: &str) -> SeedRecord {
+        SeedRecord::new(format!("10.0.0.1:{}", port), node.to_string())
+    }
+
+    #[test]
+    fn test_digest_ignores_order_and_first_seen() {
+        let mut a = record(1, "a");
+        let b = record(2, "b");
+        let first = view_of(&[a.clone(), b.clone()]);
+        a.first_seen += 100;
+        let second = view_of(&[b, a]);
+        assert_eq!(digest(&first), digest(&second));
+        assert_ne!(digest(&first), digest(&SeedView::new()));
+    }
+
+    #[test]
+    fn test_diff_and_apply() {
+        let old_records = vec![record(1, "a"), record(2, "b")];
+        let new_records = vec![record(2, "b2"), record(3, "c")];
+        let old = view_of(&old_records);
+        let new = view_of(&new_records);
+
+        let delta = diff(&old, &new, &new_records).unwrap();
+        assert_eq!(delta.added.len(), 2);
+        assert_eq!(delta.removed, vec!["10.0.0.1:1".to_string()]);
+
+        let mut view = old.clone();
+        apply(&mut view, &delta).unwrap();
+        assert_eq!(view, new);
+        assert!(diff(&new, &new, &new_records).is_none());
+    }
+
+    #[test]
+    fn test_apply_rejects_wrong_base() {
+        let records = vec![record(1, "a")];
+        let delta = diff(&SeedView::new(), &view_of(&records), &records).unwrap();
+
+        let mut stale = view_of(&[record(9, "z")]);
+        let before = stale.clone();
+        assert!(apply(&mut stale, &delta).is_err());
+        assert_eq!(stale, before);
+    }
+
+    #[test]
+    fn test_sender_and_receiver_stay_in_sync() {
+        let sender = SeedDeltas::default();
+        let receiver = SeedDeltas::default();
+
+        let first = vec![record(1, "a")];
+        let delta = sender.delta_for("peer", &first).unwrap();
+        assert_eq!(receiver.receive("me", &delta).unwrap().len(), 1);
+        // 列表不变时不再发送
+        assert!(sender.delta_for("peer", &first).is_none());
+
+        let second = vec![record(1, "a"), record(2, "b")];
+        let delta = sender.delta_for("peer", &second).unwrap();
+        let added = receiver.receive("me", &delta).unwrap();
+        assert_eq!(added.len(), 1);
+        assert_eq!(added[0].address, "10.0.0.1:2");
+    }
+
+    #[test]
+    fn test_resync_after_receiver_restart() {
+        let sender = SeedDeltas::default();
+        let records = vec![record(1, "a")];
+        sender.delta_for("peer", &records).unwrap();
+
+        // 接收方重启后副本为空，下一个增量的基准不符
+        let receiver = SeedDeltas::default();
+        let more = vec![record(1, "a"), record(2, "b")];
+        let delta = sender.delta_for("peer", &more).unwrap();
+        assert!(receiver.receive("me", &delta).is_err());
+
+        sender.reset_sent("peer");
+        let full = sender.delta_for("peer", &more).unwrap();
+        assert_eq!(receiver.receive("me", &full).unwrap().len(), 2);
+    }
+}
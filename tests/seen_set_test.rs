@@ -0,0 +1,85 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        sync::{
+            Arc, Mutex,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Instant,
+    };
+
+    use zz_p2p::protocols::commands::message::SeenSet;
+
+    #[test]
+    fn test_first_seen() {
+        let seen = SeenSet::default();
+        assert!(seen.first_seen("a".to_string(), 3));
+        assert!(!seen.first_seen("a".to_string(), 3));
+        assert!(seen.first_seen("b".to_string(), 3));
+        assert!(seen.first_seen("c".to_string(), 3));
+        assert_eq!(seen.len(), 3);
+        // 超过上限后清空
+        assert!(seen.first_seen("d".to_string(), 3));
+        assert!(seen.is_empty());
+        assert!(seen.first_seen("a".to_string(), 3));
+    }
+
+    #[test]
+    fn test_concurrent_first_seen_has_single_winner() {
+        let seen = Arc::new(SeenSet::default());
+        let winners = Arc::new(AtomicUsize::new(0));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let seen = seen.clone();
+                let winners = winners.clone();
+                std::thread::spawn(move || {
+                    for i in 0..1000 {
+                        if seen.first_seen(format!("msg:{}", i), usize::MAX) {
+                            winners.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+        assert_eq!(winners.load(Ordering::Relaxed), 1000);
+    }
+
+    /// 吞吐对比：`cargo test --test seen_set_test -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_dedup_throughput() {
+        const THREADS: usize = 8;
+        const PER_THREAD: usize = 200_000;
+
+        fn run(insert: Arc<dyn Fn(String) -> bool + Send + Sync>) -> f64 {
+            let start = Instant::now();
+            let handles: Vec<_> = (0..THREADS)
+                .map(|t| {
+                    let insert = insert.clone();
+                    std::thread::spawn(move || {
+                        for i in 0..PER_THREAD {
+                            insert(format!("{}:{}", t, i));
+                        }
+                    })
+                })
+                .collect();
+            for h in handles {
+                h.join().unwrap();
+            }
+            (THREADS * PER_THREAD) as f64 / start.elapsed().as_secs_f64()
+        }
+
+        let global = Arc::new(Mutex::new(HashSet::new()));
+        let mutex_rate = run(Arc::new(move |key| global.lock().unwrap().insert(key)));
+        let sharded = Arc::new(SeenSet::default());
+        let sharded_rate = run(Arc::new(move |key| sharded.first_seen(key, usize::MAX)));
+
+        println!("Mutex<HashSet>: {:>12.0} msg/s", mutex_rate);
+        println!("SeenSet:        {:>12.0} msg/s", sharded_rate);
+        println!("speedup:        {:>12.2}x", sharded_rate / mutex_rate);
+    }
+}
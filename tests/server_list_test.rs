@@ -0,0 +1,113 @@
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, net::SocketAddr, sync::Arc};
+
+    use aex::connection::global::GlobalContext;
+    use chrono::{Duration, Utc};
+    use zz_p2p::{
+        peer_maintenance::{PeerMaintenanceConfig, prune_and_decay},
+        record::{NodeRecord, NodeRegistry},
+        server_list::{self, parse_endpoint},
+    };
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([203, 0, 113, 7], port))
+    }
+
+    fn stale(port: u16, days_ago: i64) -> NodeRecord {
+        let mut record = NodeRecord::new(addr(port));
+        record.last_seen = Utc::now() - Duration::days(days_ago);
+        record.tries = (10, 0);
+        record
+    }
+
+    #[test]
+    fn test_add_remove_and_pin() {
+        let mut registry = NodeRegistry::default();
+        assert!(registry.add(addr(1), false));
+        assert!(!registry.get(addr(1)).unwrap().pinned);
+
+        // 再次添加只会固定，不会重置记录
+        registry.upsert(addr(1), false);
+        assert!(!registry.add(addr(1), true));
+        let record = registry.get(addr(1)).unwrap();
+        assert!(record.pinned);
+        assert_eq!(record.tries.1, 1);
+        // 不带 --pin 的重复添加不会取消固定
+        registry.add(addr(1), false);
+        assert!(registry.get(addr(1)).unwrap().pinned);
+
+        assert!(registry.set_pinned(addr(1), false));
+        assert!(!registry.get(addr(1)).unwrap().pinned);
+        assert!(!registry.set_pinned(addr(2), true));
+
+        assert!(registry.remove(addr(1)));
+        assert!(!registry.remove(addr(1)));
+        assert!(registry.nodes.is_empty());
+    }
+
+    #[test]
+    fn test_pinned_records_survive_maintenance() {
+        let mut pinned = stale(1, 40);
+        pinned.pinned = true;
+        let mut registry = NodeRegistry::new(HashSet::from([pinned, stale(2, 40)]));
+        let policy = PeerMaintenanceConfig {
+            decay_factor: 0.5,
+            ..Default::default()
+        };
+        let (pruned, decayed) = prune_and_decay(&mut registry, &policy, Utc::now());
+        assert_eq!((pruned, decayed), (1, 0));
+        let record = registry.get(addr(1)).unwrap();
+        assert_eq!(record.tries.0, 10);
+        assert!(registry.get(addr(2)).is_none());
+    }
+
+    #[test]
+    fn test_pinned_records_are_always_dialed() {
+        let mut pinned = stale(1, 10);
+        pinned.pinned = true;
+        // 启动维护会把两条记录都标记为失效
+        let registry = NodeRegistry::new(HashSet::from([pinned, stale(2, 10)]));
+        let available: Vec<SocketAddr> = registry
+            .get_available_nodes()
+            .iter()
+            .map(|r| r.endpoint)
+            .collect();
+        assert_eq!(available, vec![addr(1)]);
+    }
+
+    #[test]
+    fn test_pinned_is_persisted() {
+        let mut record = NodeRecord::new(addr(1));
+        record.pinned = true;
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["pinned"], true);
+        let back: NodeRecord = serde_json::from_value(json.clone()).unwrap();
+        assert!(back.pinned);
+
+        // 旧版本写出的记录没有该字段
+        let mut old = json;
+        old.as_object_mut().unwrap().remove("pinned");
+        let back: NodeRecord = serde_json::from_value(old).unwrap();
+        assert!(!back.pinned);
+    }
+
+    #[test]
+    fn test_parse_endpoint() {
+        assert_eq!(parse_endpoint("203.0.113.7:1").unwrap(), addr(1));
+        assert_eq!(
+            parse_endpoint(" [::1]:9000 ").unwrap(),
+            "[::1]:9000".parse::<SocketAddr>().unwrap()
+        );
+        assert!(parse_endpoint("203.0.113.7").is_err());
+        assert!(parse_endpoint("example.com:80").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_requires_running_node() {
+        let gctx = Arc::new(GlobalContext::new("127.0.0.1:0".parse().unwrap(), None));
+        assert!(server_list::list(&gctx).await.is_err());
+        assert!(server_list::add(&gctx, addr(1), true).await.is_err());
+        assert!(server_list::remove(&gctx, addr(1)).await.is_err());
+    }
+}
@@ -0,0 +1,221 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use aex::crypto::session_key_manager::PairedSessionKey;
+    use aex::tcp::types::Codec;
+    use tokio::sync::Mutex;
+    use zz_p2p::config::SessionConfig;
+    use zz_p2p::protocols::command::{Action, Entity, P2PCommand};
+    use zz_p2p::protocols::commands::rekey::{
+        KeyRotation, PREVIOUS_KEY_GRACE_MS, RekeyCommand, SessionStats, SessionTable,
+        due_for_rotation, record_bytes, record_established,
+    };
+
+    #[test]
+    fn test_session_expires_by_ttl_and_bytes() {
+        let policy = SessionConfig {
+            ttl_secs: 60,
+            max_bytes: 1000,
+        };
+        let stats = SessionStats {
+            established_at: 1_000_000,
+            ..Default::default()
+        };
+        assert!(!stats.is_expired(&policy, 1_000_000 + 59_999));
+        assert!(stats.is_expired(&policy, 1_000_000 + 60_000));
+
+        let stats = SessionStats {
+            established_at: 1_000_000,
+            bytes: 1000,
+            ..Default::default()
+        };
+        assert!(stats.is_expired(&policy, 1_000_000));
+
+        // 0 表示关闭对应的轮换条件
+        let disabled = SessionConfig {
+            ttl_secs: 0,
+            max_bytes: 0,
+        };
+        assert!(!stats.is_expired(&disabled, u128::MAX));
+    }
+
+    #[test]
+    fn test_record_established_resets_usage() {
+        let table = SessionTable::default();
+        record_established(&table, "peer");
+        record_bytes(&table, "peer", 512);
+        record_bytes(&table, "unknown", 512);
+        assert_eq!(table.get("peer").unwrap().bytes, 512);
+        assert!(table.get("unknown").is_none());
+
+        record_established(&table, "peer");
+        let stats = table.get("peer").unwrap();
+        assert_eq!(stats.bytes, 0);
+        assert_eq!(stats.generation, 1);
+    }
+
+    #[test]
+    fn test_only_lower_address_initiates_rotation() {
+        let table = SessionTable::default();
+        record_established(&table, "b");
+        let policy = SessionConfig {
+            ttl_secs: 0,
+            max_bytes: 1,
+        };
+        record_bytes(&table, "b", 1);
+
+        assert_eq!(
+            due_for_rotation(&table, "a", &policy, 0),
+            vec!["b".to_string()]
+        );
+        assert!(due_for_rotation(&table, "c", &policy, 0).is_empty());
+
+        // 已发起的轮换在超时前不会重复发起
+        table.get_mut("b").unwrap().rekey_started_at = Some(0);
+        assert!(due_for_rotation(&table, "a", &policy, 1).is_empty());
+    }
+
+    #[test]
+    fn test_rekey_codec_roundtrip() {
+        assert_eq!(
+            P2PCommand::to_u32(Entity::Node, Action::Rekey),
+            (33 << 8) | 1
+        );
+        assert_eq!(
+            P2PCommand::to_u32(Entity::Node, Action::RekeyAck),
+            (34 << 8) | 1
+        );
+
+        let cmd = RekeyCommand {
+            session_id: vec![1, 2, 3],
+            ephemeral_public_key: [7u8; 32],
+        };
+        let bytes = Codec::encode(&cmd).unwrap();
+        let decoded: RekeyCommand = Codec::decode(&bytes).unwrap();
+        assert_eq!(cmd, decoded);
+    }
+
+    /// 按 Online / Ack 握手在双方的主密钥表中建立会话密钥
+    async fn handshake(
+        alice: &str,
+        bob: &str,
+    ) -> (Arc<Mutex<PairedSessionKey>>, Arc<Mutex<PairedSessionKey>>) {
+        let alice_keys = Arc::new(Mutex::new(PairedSessionKey::new(16)));
+        let bob_keys = Arc::new(Mutex::new(PairedSessionKey::new(16)));
+        let (session_id, alice_public) = alice_keys.lock().await.create(false).await;
+        let bob_public = bob_keys
+            .lock()
+            .await
+            .establish_begins(
+                alice.as_bytes().to_vec(),
+                bob.as_bytes().to_vec(),
+                &alice_public.to_bytes().to_vec(),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(
+            alice_keys
+                .lock()
+                .await
+                .establish_ends(
+                    session_id,
+                    bob.as_bytes().to_vec(),
+                    alice.as_bytes().to_vec(),
+                    &bob_public.to_bytes().to_vec(),
+                )
+                .await
+                .unwrap()
+        );
+        (alice_keys, bob_keys)
+    }
+
+    #[tokio::test]
+    async fn test_frames_in_both_directions_survive_rekey() {
+        let (alice, bob) = ("alice", "bob");
+        let (alice_keys, bob_keys) = handshake(alice, bob).await;
+        let alice_rotation = KeyRotation::default();
+        let bob_rotation = KeyRotation::default();
+        let now = 1_000_000;
+
+        // alice 发起轮换，bob 完成 DH 但在 RekeyAck 发出前仍使用旧密钥
+        let (session_id, alice_public) = alice_rotation.begin(now).await;
+        let (staged, bob_public) = bob_rotation
+            .respond(alice, bob, &alice_public)
+            .await
+            .unwrap();
+        let bob_before_ack = bob_rotation
+            .encrypt(&bob_keys, alice, b"bob old key")
+            .await
+            .unwrap();
+
+        // RekeyAck 发出后 bob 启用新密钥；alice 此前用旧密钥发出的帧仍在途
+        let alice_in_flight = alice_rotation
+            .encrypt(&alice_keys, bob, b"alice old key")
+            .await
+            .unwrap();
+        bob_rotation.install(&bob_keys, alice, staged, now);
+        let bob_after_ack = bob_rotation
+            .encrypt(&bob_keys, alice, b"bob new key")
+            .await
+            .unwrap();
+        assert_eq!(
+            bob_rotation
+                .decrypt(&bob_keys, alice, &alice_in_flight, now)
+                .await
+                .unwrap(),
+            b"alice old key"
+        );
+
+        // alice 收到 RekeyAck 后启用新密钥，先到的旧密钥帧与之后的新密钥帧都能解密
+        assert!(
+            alice_rotation
+                .complete(&alice_keys, &session_id, bob, alice, &bob_public, now)
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            alice_rotation
+                .decrypt(&alice_keys, bob, &bob_before_ack, now)
+                .await
+                .unwrap(),
+            b"bob old key"
+        );
+        assert_eq!(
+            alice_rotation
+                .decrypt(&alice_keys, bob, &bob_after_ack, now)
+                .await
+                .unwrap(),
+            b"bob new key"
+        );
+        let alice_after_ack = alice_rotation
+            .encrypt(&alice_keys, bob, b"alice new key")
+            .await
+            .unwrap();
+        assert_eq!(
+            bob_rotation
+                .decrypt(&bob_keys, alice, &alice_after_ack, now)
+                .await
+                .unwrap(),
+            b"alice new key"
+        );
+
+        // 宽限期结束后不再接受旧密钥
+        let late = now + PREVIOUS_KEY_GRACE_MS;
+        assert!(
+            bob_rotation
+                .decrypt(&bob_keys, alice, &alice_in_flight, late)
+                .await
+                .is_err()
+        );
+
+        // 未知的 session_id 不是本节点发起的轮换
+        assert!(
+            !alice_rotation
+                .complete(&alice_keys, b"unknown", bob, alice, &bob_public, now)
+                .await
+                .unwrap()
+        );
+    }
+}
@@ -0,0 +1,138 @@
+#[cfg(test)]
+mod tests {
+    use aex::tcp::types::{Codec, Frame};
+    use zz_account::address::FreeWebMovementAddress;
+    use zz_p2p::protocols::{
+        command::{Action, Entity, P2PCommand},
+        compression::Compression,
+        error::ProtocolError,
+        frame::P2PFrame,
+        signing::{self, SigningConfig, SigningPolicy},
+        version::{PROTOCOL_V2, PROTOCOL_V3, layout},
+        wire_format::WireFormat,
+    };
+
+    fn command() -> P2PCommand {
+        P2PCommand::new(Entity::Message, Action::SendText, vec![1, 2, 3])
+    }
+
+    async fn relayed(
+        format: WireFormat,
+    ) -> (FreeWebMovementAddress, FreeWebMovementAddress, P2PFrame) {
+        let author = FreeWebMovementAddress::random();
+        let relay = FreeWebMovementAddress::random();
+        let mut frame =
+            P2PFrame::build_as(&author, command(), PROTOCOL_V3, Some("dest".into()), format)
+                .await
+                .unwrap();
+        frame.ttl -= 1;
+        assert!(signing::prepare_forward(
+            &mut frame,
+            SigningPolicy::Layered,
+            Some(&relay)
+        ));
+        (author, relay, frame)
+    }
+
+    #[test]
+    fn test_only_v3_carries_hop_signatures() {
+        assert!(!layout(PROTOCOL_V2).unwrap().has_hop_signature);
+        assert!(layout(PROTOCOL_V3).unwrap().has_hop_signature);
+    }
+
+    #[tokio::test]
+    async fn test_layered_frame_verifies_both_layers() {
+        for format in [WireFormat::Bincode, WireFormat::Json] {
+            let (author, relay, frame) = relayed(format).await;
+            let hop = frame.hop.clone().unwrap();
+            assert_eq!(hop.address, relay.to_string());
+
+            let bytes = Codec::encode(&frame).unwrap();
+            let decoded = P2PFrame::verify_bytes(&bytes).unwrap();
+            assert_eq!(decoded.body.address, author.to_string());
+            assert_eq!(decoded.hop, Some(hop));
+            assert!(decoded.validate());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compressed_frame_keeps_hop_signature() {
+        let author = FreeWebMovementAddress::random();
+        let relay = FreeWebMovementAddress::random();
+        let cmd = P2PCommand::new(Entity::Message, Action::SendText, vec![7u8; 16 * 1024]);
+        let mut frame = P2PFrame::build_to(&author, cmd, PROTOCOL_V3, Some("dest".into()))
+            .await
+            .unwrap();
+        frame.sign_hop(&relay).unwrap();
+        frame.compression = Compression::Lz4;
+        let decoded = P2PFrame::verify_bytes(&Codec::encode(&frame).unwrap()).unwrap();
+        assert_eq!(decoded.compression, Compression::Lz4);
+        assert_eq!(decoded.hop, frame.hop);
+    }
+
+    #[tokio::test]
+    async fn test_tampered_ttl_breaks_hop_signature() {
+        let (_, _, mut frame) = relayed(WireFormat::Bincode).await;
+        frame.ttl += 1;
+        let bytes = Codec::encode(&frame).unwrap();
+        let err = P2PFrame::verify_bytes(&bytes).unwrap_err();
+        assert!(err.to_string().contains("hop signature"));
+        assert!(!frame.validate());
+        assert_eq!(
+            P2PFrame::verify(frame).unwrap_err(),
+            ProtocolError::BadHopSignature
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forged_hop_signature_is_rejected() {
+        let (_, _, mut frame) = relayed(WireFormat::Bincode).await;
+        let mut hop = frame.hop.clone().unwrap();
+        hop.public_key = FreeWebMovementAddress::random()
+            .public_key
+            .to_bytes()
+            .to_vec();
+        frame.hop = Some(hop);
+        assert_eq!(
+            P2PFrame::verify(frame).unwrap_err(),
+            ProtocolError::BadHopSignature
+        );
+    }
+
+    #[tokio::test]
+    async fn test_end_to_end_policy_strips_stale_hop() {
+        let (_, _, mut frame) = relayed(WireFormat::Bincode).await;
+        frame.ttl -= 1;
+        assert!(!signing::prepare_forward(
+            &mut frame,
+            SigningPolicy::EndToEnd,
+            None
+        ));
+        assert!(frame.hop.is_none());
+        assert!(P2PFrame::verify(frame).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_v2_frames_are_forwarded_without_hop() {
+        let author = FreeWebMovementAddress::random();
+        let relay = FreeWebMovementAddress::random();
+        let mut frame = P2PFrame::build_to(&author, command(), PROTOCOL_V2, Some("dest".into()))
+            .await
+            .unwrap();
+        assert!(frame.sign_hop(&relay).is_err());
+        assert!(!signing::prepare_forward(
+            &mut frame,
+            SigningPolicy::Layered,
+            Some(&relay)
+        ));
+        assert!(frame.hop.is_none());
+    }
+
+    #[test]
+    fn test_config() {
+        assert_eq!(SigningConfig::default().policy, SigningPolicy::EndToEnd);
+        let config: SigningConfig = toml::from_str("policy = \"layered\"").unwrap();
+        assert_eq!(config.policy, SigningPolicy::Layered);
+        assert_eq!(ProtocolError::BadHopSignature.kind(), "bad_hop_signature");
+    }
+}
@@ -0,0 +1,62 @@
+#[cfg(all(test, feature = "simulation"))]
+mod tests {
+    use zz_p2p::simulation::{ChurnConfig, Simulation, SoakReport};
+
+    #[test]
+    fn test_delivery_rate() {
+        let mut report = SoakReport::default();
+        assert_eq!(report.delivery_rate(), 1.0);
+        report.messages_sent = 4;
+        report.messages_delivered = 3;
+        assert_eq!(report.delivery_rate(), 0.75);
+        assert!(report.is_healthy());
+        report.violations.push("leak".to_string());
+        assert!(!report.is_healthy());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_single_node() {
+        let config = ChurnConfig {
+            nodes: 1,
+            ..Default::default()
+        };
+        assert!(Simulation::run(config).await.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_small_churn() {
+        let config = ChurnConfig {
+            nodes: 6,
+            rounds: 4,
+            base_port: 23400,
+            connects_per_round: 3,
+            disconnects_per_round: 2,
+            restarts_per_round: 1,
+            messages_per_round: 10,
+            seed: 7,
+            ..Default::default()
+        };
+        let report = Simulation::run(config).await.unwrap();
+        assert_eq!(report.rounds, 4);
+        assert_eq!(report.restarts, 3);
+        assert!(report.messages_sent > 0);
+        assert!(report.is_healthy(), "{:?}", report.violations);
+    }
+
+    /// 数十个节点的浸泡测试，耗时较长：
+    /// `cargo test --features simulation --test simulation_test -- --ignored --nocapture`
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore]
+    async fn soak_many_nodes() {
+        let config = ChurnConfig {
+            nodes: 40,
+            rounds: 30,
+            base_port: 23500,
+            seed: 42,
+            ..Default::default()
+        };
+        let report = Simulation::run(config).await.unwrap();
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        assert!(report.is_healthy(), "{:?}", report.violations);
+    }
+}
@@ -0,0 +1,152 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use tempfile::tempdir;
+    use zz_p2p::web::keep_alive::{is_bodyless_status, wants_close};
+    use zz_p2p::web::static_files::{
+        ByteRange, StaticMount, etag, http_date, is_not_modified, parse_range, resolve,
+    };
+
+    fn site() -> tempfile::TempDir {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("index.html"), "<h1>zz</h1>").unwrap();
+        fs::create_dir(dir.path().join("assets")).unwrap();
+        fs::write(dir.path().join("assets/app.js"), "console.log(1)").unwrap();
+        fs::write(dir.path().join("assets/index.html"), "assets").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_resolve_files_and_index() {
+        let dir = site();
+        let root = dir.path().canonicalize().unwrap();
+        assert_eq!(resolve(dir.path(), "/").unwrap(), root.join("index.html"));
+        assert_eq!(resolve(dir.path(), "").unwrap(), root.join("index.html"));
+        assert_eq!(
+            resolve(dir.path(), "/assets/app.js?v=3").unwrap(),
+            root.join("assets/app.js")
+        );
+        assert_eq!(
+            resolve(dir.path(), "/assets/").unwrap(),
+            root.join("assets/index.html")
+        );
+        assert_eq!(
+            resolve(dir.path(), "/assets/%61pp.js").unwrap(),
+            root.join("assets/app.js")
+        );
+        assert!(resolve(dir.path(), "/missing.css").is_none());
+    }
+
+    #[test]
+    fn test_resolve_rejects_traversal() {
+        let outer = tempdir().unwrap();
+        fs::write(outer.path().join("secret.txt"), "secret").unwrap();
+        let root = outer.path().join("www");
+        fs::create_dir(&root).unwrap();
+        fs::write(root.join("index.html"), "ok").unwrap();
+
+        for path in [
+            "/../secret.txt",
+            "/assets/../../secret.txt",
+            "/%2e%2e/secret.txt",
+            "/..%2fsecret.txt",
+            "/..\\secret.txt",
+            "/%00",
+            "/%zz",
+        ] {
+            assert!(
+                resolve(&root, path).is_none(),
+                "{} should be rejected",
+                path
+            );
+        }
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(outer.path().join("secret.txt"), root.join("link.txt"))
+                .unwrap();
+            assert!(resolve(&root, "/link.txt").is_none());
+        }
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range(None, 100), ByteRange::Full);
+        assert_eq!(
+            parse_range(Some("bytes=0-9"), 100),
+            ByteRange::Partial { start: 0, end: 9 }
+        );
+        assert_eq!(
+            parse_range(Some("bytes=90-"), 100),
+            ByteRange::Partial { start: 90, end: 99 }
+        );
+        assert_eq!(
+            parse_range(Some("bytes=-10"), 100),
+            ByteRange::Partial { start: 90, end: 99 }
+        );
+        assert_eq!(
+            parse_range(Some("bytes=50-500"), 100),
+            ByteRange::Partial { start: 50, end: 99 }
+        );
+        assert_eq!(
+            parse_range(Some("bytes=100-"), 100),
+            ByteRange::Unsatisfiable
+        );
+        assert_eq!(parse_range(Some("bytes=-0"), 100), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=9-1"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("items=0-1"), 100), ByteRange::Full);
+    }
+
+    #[test]
+    fn test_conditional_requests() {
+        let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let tag = etag(42, modified);
+
+        assert!(is_not_modified(Some(&tag), None, &tag, modified));
+        assert!(is_not_modified(
+            Some(&format!("W/{}", tag)),
+            None,
+            &tag,
+            modified
+        ));
+        assert!(is_not_modified(Some("\"x\", *"), None, &tag, modified));
+        assert!(!is_not_modified(Some("\"other\""), None, &tag, modified));
+
+        let date = http_date(modified);
+        assert_eq!(date, "Tue, 14 Nov 2023 22:13:20 GMT");
+        assert!(is_not_modified(None, Some(&date), &tag, modified));
+        let earlier = http_date(modified - Duration::from_secs(60));
+        assert!(!is_not_modified(None, Some(&earlier), &tag, modified));
+        // If-None-Match 优先于 If-Modified-Since
+        assert!(!is_not_modified(
+            Some("\"other\""),
+            Some(&date),
+            &tag,
+            modified
+        ));
+        assert!(!is_not_modified(None, Some("not a date"), &tag, modified));
+    }
+
+    #[test]
+    fn test_mount_prefix_normalized() {
+        let mount = StaticMount::new("/web/", "/srv/ui");
+        assert_eq!(mount.prefix, "/web");
+    }
+
+    #[test]
+    fn test_connection_close() {
+        assert!(wants_close(Some("close")));
+        assert!(wants_close(Some("Upgrade, Close")));
+        assert!(!wants_close(Some("keep-alive")));
+        assert!(!wants_close(None));
+
+        assert!(is_bodyless_status("304 Not Modified"));
+        assert!(is_bodyless_status("204 No Content"));
+        assert!(!is_bodyless_status("404 Not Found"));
+    }
+}
@@ -0,0 +1,54 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use aex::connection::global::GlobalContext;
+    use zz_p2p::{
+        io_storage::STORAGE_SCHEMA_VERSION,
+        outbox,
+        protocols::version::CURRENT_PROTOCOL_VERSION,
+        readiness::StartupPhase,
+        status::{self, ConnectionCounts, QueueStatus},
+    };
+
+    #[tokio::test]
+    async fn test_snapshot_without_running_node() {
+        let gctx = Arc::new(GlobalContext::new("127.0.0.1:0".parse().unwrap(), None));
+        let snapshot = status::collect(&gctx).await;
+        assert!(snapshot.address.is_empty());
+        assert_eq!(snapshot.started_at, 0);
+        assert_eq!(snapshot.uptime_secs, 0);
+        assert_eq!(snapshot.startup, StartupPhase::Starting);
+        assert_eq!(snapshot.connections, ConnectionCounts::default());
+        assert!(snapshot.known_peers.is_empty());
+        assert!(snapshot.listen.is_empty());
+        assert_eq!(snapshot.queues, QueueStatus::default());
+        assert_eq!(snapshot.versions.protocol, CURRENT_PROTOCOL_VERSION);
+        assert_eq!(snapshot.versions.storage_schema, STORAGE_SCHEMA_VERSION);
+        assert_eq!(snapshot.versions.package, env!("CARGO_PKG_VERSION"));
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_counts_queued_drafts() {
+        let gctx = Arc::new(GlobalContext::new("127.0.0.1:0".parse().unwrap(), None));
+        outbox::queue(&gctx, "me", "alice", "hi").await.unwrap();
+        outbox::queue(&gctx, "me", "bob", "yo").await.unwrap();
+        let snapshot = status::collect(&gctx).await;
+        assert_eq!(snapshot.queues.outbox, 2);
+        assert_eq!(snapshot.queues.wal_pending, 0);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_json_keeps_flat_connection_fields() {
+        let gctx = Arc::new(GlobalContext::new("127.0.0.1:0".parse().unwrap(), None));
+        let json = serde_json::to_value(status::collect(&gctx).await).unwrap();
+        for key in ["inbound", "outbound", "intranet", "extranet", "peer_ips"] {
+            assert_eq!(json[key], 0, "{}", key);
+        }
+        assert!(json.get("connections").is_none());
+        assert_eq!(json["queues"]["outbox"], 0);
+        assert_eq!(json["endpoints"]["verified"], 0);
+        assert_eq!(json["relay"]["enabled"], false);
+        assert!(json["duplicates"]["checked"].is_number());
+    }
+}
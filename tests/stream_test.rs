@@ -0,0 +1,153 @@
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use tokio::io::AsyncReadExt;
+    use zz_p2p::{
+        events::{NodeEvent, new_bus},
+        protocols::{
+            capabilities::{CAP_STREAM, LOCAL_FEATURES, feature_names},
+            commands::stream::{
+                IncomingStream, STREAM_KIND_TEXT, StreamReader, TextReader, check_chunk,
+                publish_text_streams, safe_filename, take_utf8,
+            },
+        },
+    };
+
+    #[tokio::test]
+    async fn test_reader_yields_chunks_in_order_and_reports_consumed() {
+        let (tx, mut reader, mut consumed) = StreamReader::channel(4);
+        tx.send(Ok(b"hello ".to_vec())).await.unwrap();
+        tx.send(Ok(b"world".to_vec())).await.unwrap();
+        drop(tx);
+
+        let mut out = String::new();
+        reader.read_to_string(&mut out).await.unwrap();
+        assert_eq!(out, "hello world");
+
+        let mut total = 0;
+        while let Ok(n) = consumed.try_recv() {
+            total += n;
+        }
+        assert_eq!(total, 11);
+    }
+
+    #[tokio::test]
+    async fn test_reader_small_buffer_reads_partial_chunk() {
+        let (tx, mut reader, _consumed) = StreamReader::channel(4);
+        tx.send(Ok(b"abcdef".to_vec())).await.unwrap();
+        let mut buf = [0u8; 4];
+        assert_eq!(reader.read(&mut buf).await.unwrap(), 4);
+        assert_eq!(&buf, b"abcd");
+        assert_eq!(reader.read(&mut buf).await.unwrap(), 2);
+        assert_eq!(&buf[..2], b"ef");
+    }
+
+    #[tokio::test]
+    async fn test_reader_surfaces_abort() {
+        let (tx, mut reader, _consumed) = StreamReader::channel(4);
+        tx.send(Ok(b"partial".to_vec())).await.unwrap();
+        tx.send(Err(io::Error::other("stream aborted: gap")))
+            .await
+            .unwrap();
+        let mut out = Vec::new();
+        let err = reader.read_to_end(&mut out).await.unwrap_err();
+        assert!(err.to_string().contains("gap"));
+        assert_eq!(out, b"partial");
+    }
+
+    #[test]
+    fn test_check_chunk() {
+        assert_eq!(check_chunk(0, None, 0, 10), Ok(true));
+        // 重复的块被忽略
+        assert_eq!(check_chunk(10, None, 0, 10), Ok(false));
+        assert!(check_chunk(10, None, 20, 10).is_err());
+        assert_eq!(check_chunk(10, Some(20), 10, 10), Ok(true));
+        assert!(check_chunk(10, Some(15), 10, 10).is_err());
+    }
+
+    #[test]
+    fn test_safe_filename() {
+        assert_eq!(safe_filename("report.pdf").as_deref(), Some("report.pdf"));
+        assert_eq!(safe_filename("../../etc/passwd").as_deref(), Some("passwd"));
+        assert_eq!(safe_filename("C:\\tmp\\a.txt").as_deref(), Some("a.txt"));
+        assert_eq!(safe_filename(".."), None);
+        assert_eq!(safe_filename("dir/"), None);
+    }
+
+    #[test]
+    fn test_take_utf8_keeps_split_characters() {
+        let mut pending = "日志".as_bytes().to_vec();
+        pending.truncate(4);
+        assert_eq!(take_utf8(&mut pending).unwrap().as_deref(), Some("日"));
+        assert_eq!(pending.len(), 1);
+        assert_eq!(take_utf8(&mut pending).unwrap(), None);
+        pending.extend_from_slice(&"志".as_bytes()[1..]);
+        assert_eq!(take_utf8(&mut pending).unwrap().as_deref(), Some("志"));
+        assert!(pending.is_empty());
+
+        let mut invalid = vec![b'a', 0xff, b'b'];
+        assert!(take_utf8(&mut invalid).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_text_reader_reassembles_chunks() {
+        let text = "第一行 log\n第二行 log\n";
+        let bytes = text.as_bytes();
+        let (tx, reader, _consumed) = StreamReader::channel(8);
+        // 在多字节字符中间切开
+        for chunk in [&bytes[..2], &bytes[2..7], &bytes[7..]] {
+            tx.send(Ok(chunk.to_vec())).await.unwrap();
+        }
+        drop(tx);
+        assert_eq!(
+            TextReader::new(reader).read_to_string().await.unwrap(),
+            text
+        );
+
+        // 在字符中间结束视为截断
+        let (tx, reader, _consumed) = StreamReader::channel(8);
+        tx.send(Ok(bytes[..2].to_vec())).await.unwrap();
+        drop(tx);
+        assert!(TextReader::new(reader).read_to_string().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_text_stream_is_published_to_event_bus() {
+        let bus = new_bus();
+        let mut events = bus.subscribe();
+        let (tx, reader, _consumed) = StreamReader::channel(8);
+        let handler = publish_text_streams(bus);
+        tokio::spawn(handler(IncomingStream {
+            from: "alice".to_string(),
+            stream_id: 7,
+            kind: STREAM_KIND_TEXT.to_string(),
+            total_len: Some(11),
+            metadata: vec![("filename".to_string(), "app.log".to_string())],
+            reader,
+        }));
+
+        let NodeEvent::TextStream(stream) = events.recv().await.unwrap() else {
+            panic!("expected a text stream");
+        };
+        assert_eq!(stream.from, "alice");
+        assert_eq!(stream.total_len, Some(11));
+        assert_eq!(stream.meta("filename"), Some("app.log"));
+        let mut text = stream.take_reader().unwrap();
+        // 读端只能被取走一次
+        assert!(stream.clone().take_reader().is_none());
+
+        tx.send(Ok(b"hello ".to_vec())).await.unwrap();
+        assert_eq!(text.next_chunk().await.unwrap().as_deref(), Some("hello "));
+        tx.send(Ok(b"world".to_vec())).await.unwrap();
+        drop(tx);
+        assert_eq!(text.next_chunk().await.unwrap().as_deref(), Some("world"));
+        assert_eq!(text.next_chunk().await.unwrap(), None);
+    }
+
+    #[test]
+    fn test_stream_capability() {
+        assert_ne!(LOCAL_FEATURES & CAP_STREAM, 0);
+        assert_eq!(feature_names(CAP_STREAM), vec!["stream"]);
+    }
+}
@@ -0,0 +1,130 @@
+#[cfg(test)]
+mod tests {
+    use aex::tcp::types::Codec;
+    use zz_p2p::protocols::commands::telephone::{
+        CallCommand, CallDirection, CallError, CallEvent, CallState, CallTable, EndReason, Signal,
+    };
+
+    fn ended(event: CallEvent) -> (EndReason, bool) {
+        match event {
+            CallEvent::Ended {
+                reason, by_peer, ..
+            } => (reason, by_peer),
+            other => panic!("expected Ended, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_dial_and_accept() {
+        let mut table = CallTable::default();
+        let event = table.dial(1, "bob", 100).unwrap();
+        assert!(
+            matches!(event, CallEvent::Ringing(ref c) if c.direction == CallDirection::Outgoing)
+        );
+
+        // 主叫方不能自己接听
+        assert_eq!(
+            table.apply(1, Signal::Accept, None),
+            Err(CallError::InvalidTransition)
+        );
+        // 其它节点发来的接听被拒绝
+        assert_eq!(
+            table.apply(1, Signal::Accept, Some("mallory")),
+            Err(CallError::WrongPeer)
+        );
+        let event = table.apply(1, Signal::Accept, Some("bob")).unwrap();
+        assert!(matches!(event, CallEvent::Accepted(ref c) if c.state == CallState::Accepted));
+        assert_eq!(table.current().unwrap().state, CallState::Accepted);
+
+        let (reason, by_peer) = ended(table.apply(1, Signal::HangUp, None).unwrap());
+        assert_eq!(reason, EndReason::HungUp);
+        assert!(!by_peer);
+        assert!(table.current().is_none());
+    }
+
+    #[test]
+    fn test_incoming_call() {
+        let mut table = CallTable::default();
+        table.ring(7, "alice", 0).unwrap();
+        assert_eq!(table.current().unwrap().direction, CallDirection::Incoming);
+        // 来电只能由本地接听
+        assert_eq!(
+            table.apply(7, Signal::Accept, Some("alice")),
+            Err(CallError::InvalidTransition)
+        );
+        table.apply(7, Signal::Accept, None).unwrap();
+
+        let (reason, by_peer) = ended(table.apply(7, Signal::HangUp, Some("alice")).unwrap());
+        assert_eq!(reason, EndReason::HungUp);
+        assert!(by_peer);
+        assert_eq!(
+            table.apply(7, Signal::HangUp, None),
+            Err(CallError::UnknownCall(7))
+        );
+    }
+
+    #[test]
+    fn test_single_call_and_busy() {
+        let mut table = CallTable::default();
+        table.dial(1, "bob", 0).unwrap();
+        assert_eq!(table.ring(2, "carol", 0), Err(CallError::AlreadyInCall));
+        assert_eq!(table.dial(3, "dave", 0), Err(CallError::AlreadyInCall));
+
+        let (reason, by_peer) = ended(
+            table
+                .apply(1, Signal::Reject { busy: true }, Some("bob"))
+                .unwrap(),
+        );
+        assert_eq!(reason, EndReason::Busy);
+        assert!(by_peer);
+        assert!(table.current().is_none());
+    }
+
+    #[test]
+    fn test_reject_only_while_ringing() {
+        let mut table = CallTable::default();
+        table.ring(1, "alice", 0).unwrap();
+        table.apply(1, Signal::Accept, None).unwrap();
+        assert_eq!(
+            table.apply(1, Signal::Reject { busy: false }, None),
+            Err(CallError::InvalidTransition)
+        );
+
+        let mut table = CallTable::default();
+        table.ring(2, "alice", 0).unwrap();
+        let (reason, _) = ended(
+            table
+                .apply(2, Signal::Reject { busy: false }, None)
+                .unwrap(),
+        );
+        assert_eq!(reason, EndReason::Rejected);
+    }
+
+    #[test]
+    fn test_ring_timeout() {
+        let mut table = CallTable::default();
+        table.dial(1, "bob", 1_000).unwrap();
+        assert!(table.expire(20_000, 30_000).is_empty());
+        let events = table.expire(31_000, 30_000);
+        assert_eq!(events.len(), 1);
+        assert_eq!(ended(events[0].clone()).0, EndReason::Timeout);
+        assert!(table.current().is_none());
+
+        // 已接通的通话不会超时
+        table.dial(2, "bob", 0).unwrap();
+        table.apply(2, Signal::Accept, Some("bob")).unwrap();
+        assert!(table.expire(u128::MAX, 30_000).is_empty());
+    }
+
+    #[test]
+    fn test_call_command_roundtrip() {
+        let cmd = CallCommand {
+            call_id: 42,
+            busy: true,
+            media_port: 40000,
+        };
+        let bytes = Codec::encode(&cmd).unwrap();
+        let decoded: CallCommand = Codec::decode(&bytes).unwrap();
+        assert_eq!(decoded, cmd);
+    }
+}
@@ -0,0 +1,136 @@
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use zz_p2p::{
+        clis::send,
+        outbox::{self, Delivery},
+        readiness::StartupPhase,
+        testing::{DEFAULT_WAIT, TestNetwork, TestNode, free_port},
+    };
+
+    #[test]
+    fn test_free_port_is_bindable() {
+        let port = free_port().unwrap();
+        assert_ne!(port, 0);
+        std::net::TcpListener::bind(("127.0.0.1", port)).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_nodes_use_distinct_ports_and_dirs() {
+        let a = TestNode::start().await.unwrap();
+        let b = TestNode::start().await.unwrap();
+        assert_ne!(a.endpoint(), b.endpoint());
+        assert_ne!(a.address(), b.address());
+        assert!(a.data_dir().exists());
+
+        let dir = a.data_dir().to_path_buf();
+        drop(a);
+        assert!(!dir.exists());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_network_delivers_messages() {
+        let mut net = TestNetwork::start(3).await.unwrap();
+        net.connect_chain().await.unwrap();
+        assert!(net[0].is_connected_to(&net[1].address()).await);
+        assert!(net[2].is_connected_to(&net[1].address()).await);
+
+        send::send_text(net[0].context(), net[1].address(), "hello".into())
+            .await
+            .unwrap();
+        net[1]
+            .events
+            .wait_for_message("hello", DEFAULT_WAIT)
+            .await
+            .unwrap();
+        assert_eq!(net[1].events.messages(), vec!["hello".to_string()]);
+        assert!(net[2].events.messages().is_empty());
+
+        net.stop().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_client_only_node_works_over_outbound_connection() {
+        let mut server = TestNode::start().await.unwrap();
+        let mut client = TestNode::start_with(|opt| opt.client_only = true)
+            .await
+            .unwrap();
+        assert!(client.node.client_only);
+        assert!(client.node.handlers.health().is_empty());
+        // 主地址没有被绑定
+        assert!(
+            tokio::net::TcpStream::connect(client.endpoint())
+                .await
+                .is_err()
+        );
+
+        client.connect(&server).await.unwrap();
+        send::send_text(server.context(), client.address(), "down".into())
+            .await
+            .unwrap();
+        client
+            .events
+            .wait_for_message("down", DEFAULT_WAIT)
+            .await
+            .unwrap();
+        send::send_text(client.context(), server.address(), "up".into())
+            .await
+            .unwrap();
+        server
+            .events
+            .wait_for_message("up", DEFAULT_WAIT)
+            .await
+            .unwrap();
+
+        client.stop().await;
+        server.stop().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_outbox_sends_queued_message_once_connected() {
+        let mut a = TestNode::start().await.unwrap();
+        let mut b = TestNode::start().await.unwrap();
+
+        let delivery = outbox::send_or_queue(&a.context(), b.address(), "later".into())
+            .await
+            .unwrap();
+        let Delivery::Queued(draft) = delivery else {
+            panic!("expected the message to be queued, got {:?}", delivery);
+        };
+        assert_eq!(draft.receiver, b.address());
+        assert_eq!(outbox::list(&a.context()).await, vec![draft]);
+
+        a.connect(&b).await.unwrap();
+        // 不等后台任务的下一轮
+        outbox::flush_once(&a.context()).await;
+        b.events
+            .wait_for_message("later", DEFAULT_WAIT)
+            .await
+            .unwrap();
+        assert!(outbox::list(&a.context()).await.is_empty());
+
+        a.stop().await;
+        b.stop().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_snapshot_reflects_running_node() {
+        let mut net = TestNetwork::start(2).await.unwrap();
+        net.connect(0, 1).await.unwrap();
+
+        let snapshot = net[0].node.snapshot().await;
+        assert_eq!(snapshot.address, net[0].address());
+        assert_eq!(snapshot.name, net[0].node.name);
+        assert!(snapshot.started_at > 0);
+        assert_eq!(snapshot.startup, StartupPhase::Ready);
+        assert!(snapshot.listen.contains(&net[0].endpoint()));
+
+        net.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_connect_to_self_is_rejected() {
+        let mut net = TestNetwork::start(1).await.unwrap();
+        assert!(net.connect(0, 0).await.is_err());
+        net.stop().await;
+    }
+}
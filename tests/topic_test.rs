@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod tests {
+    use zz_p2p::protocols::{
+        command::Action,
+        commands::topic::{
+            MAX_TOPICS_PER_SUBSCRIBER, TopicCommand, TopicSubscriptions, apply_subscription,
+            forget_routes,
+        },
+    };
+
+    fn cmd(topic: &str, subscriber: &str) -> TopicCommand {
+        TopicCommand {
+            topic: topic.to_string(),
+            subscriber: subscriber.to_string(),
+            timestamp: 1,
+        }
+    }
+
+    fn subscribe(
+        table: &TopicSubscriptions,
+        topic: &str,
+        subscriber: &str,
+        via: Option<&str>,
+    ) -> bool {
+        apply_subscription(table, &cmd(topic, subscriber), via, Action::Subscribe)
+    }
+
+    #[test]
+    fn test_subscription_records_route() {
+        let table = TopicSubscriptions::default();
+        assert!(subscribe(&table, "news", "alice", Some("bob")));
+        assert!(subscribe(&table, "news", "me", None));
+
+        let subs = table.get("news").unwrap();
+        assert_eq!(subs.get("alice"), Some(&Some("bob".to_string())));
+        assert_eq!(subs.get("me"), Some(&None));
+    }
+
+    #[test]
+    fn test_unsubscribe_removes_empty_topic() {
+        let table = TopicSubscriptions::default();
+        subscribe(&table, "news", "alice", Some("bob"));
+        apply_subscription(
+            &table,
+            &cmd("news", "alice"),
+            Some("bob"),
+            Action::Unsubscribe,
+        );
+        assert!(table.get("news").is_none());
+    }
+
+    #[test]
+    fn test_subscriptions_capped_per_subscriber() {
+        let table = TopicSubscriptions::default();
+        for i in 0..MAX_TOPICS_PER_SUBSCRIBER {
+            assert!(subscribe(&table, &format!("t{}", i), "alice", Some("bob")));
+        }
+        assert!(!subscribe(&table, "one-more", "alice", Some("bob")));
+        // 已有的订阅不受限额影响，其他订阅者也不受影响
+        assert!(subscribe(&table, "t0", "alice", Some("bob")));
+        assert!(subscribe(&table, "one-more", "carol", Some("bob")));
+    }
+
+    #[test]
+    fn test_forget_routes_drops_subscriptions_via_neighbor() {
+        let table = TopicSubscriptions::default();
+        subscribe(&table, "news", "alice", Some("bob"));
+        subscribe(&table, "news", "bob", Some("bob"));
+        subscribe(&table, "news", "carol", Some("dave"));
+        subscribe(&table, "sports", "alice", Some("bob"));
+
+        forget_routes(&table, "bob");
+
+        let subs = table.get("news").unwrap();
+        assert_eq!(subs.len(), 1);
+        assert!(subs.contains_key("carol"));
+        drop(subs);
+        assert!(table.get("sports").is_none());
+    }
+}
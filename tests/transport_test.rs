@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, sync::Arc};
+
+    use aex::connection::{context::Context, global::GlobalContext};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::UdpSocket,
+        sync::Mutex,
+    };
+    use tokio_tungstenite::{WebSocketStream, tungstenite::protocol::Role};
+    use zz_p2p::{
+        connections::Transport,
+        transport::{Connection, DatagramConnection, WebSocketConnection},
+    };
+
+    fn context_pair() -> (Arc<Mutex<Context>>, tokio::io::DuplexStream, SocketAddr) {
+        let addr: SocketAddr = "10.0.0.2:9000".parse().unwrap();
+        let (local, remote) = tokio::io::duplex(4096);
+        let (rx, tx) = tokio::io::split(local);
+        let global = Arc::new(GlobalContext::new("127.0.0.1:0".parse().unwrap(), None));
+        let ctx = Context::new(
+            Some(Box::new(tokio::io::BufReader::new(rx))),
+            Some(Box::new(tokio::io::BufWriter::new(tx))),
+            global,
+            addr,
+        );
+        (Arc::new(Mutex::new(ctx)), remote, addr)
+    }
+
+    #[tokio::test]
+    async fn test_context_connection_round_trip() {
+        let (conn, mut remote, addr) = context_pair();
+        assert_eq!(conn.peer_addr().await, addr);
+        assert_eq!(conn.transport().await, Transport::Tcp);
+
+        conn.send(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        remote.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        remote.write_all(b"pong").await.unwrap();
+        assert_eq!(conn.recv().await.unwrap(), Some(b"pong".to_vec()));
+
+        conn.close().await;
+        let mut rest = Vec::new();
+        remote.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_context_without_reader_cannot_recv() {
+        let (conn, _remote, _) = context_pair();
+        conn.lock().await.reader = None;
+        assert!(conn.recv().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_datagram_connection() {
+        let a = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let b = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let a_to_b = DatagramConnection::new(a.clone(), b.local_addr().unwrap());
+        let b_to_a = DatagramConnection::new(b.clone(), a.local_addr().unwrap());
+        assert_eq!(a_to_b.transport().await, Transport::Udp);
+
+        // 来自其它地址的数据报被忽略
+        let stranger = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        stranger
+            .send_to(b"noise", b.local_addr().unwrap())
+            .await
+            .unwrap();
+        a_to_b.send(b"hello").await.unwrap();
+        assert_eq!(b_to_a.recv().await.unwrap(), Some(b"hello".to_vec()));
+
+        a_to_b.close().await;
+        assert!(a_to_b.send(b"again").await.is_err());
+        assert_eq!(a_to_b.recv().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_websocket_connection() {
+        let (client, server) = tokio::io::duplex(4096);
+        let peer: SocketAddr = "192.0.2.1:443".parse().unwrap();
+        let client = WebSocketConnection::new(
+            WebSocketStream::from_raw_socket(client, Role::Client, None).await,
+            peer,
+        );
+        let server = WebSocketConnection::new(
+            WebSocketStream::from_raw_socket(server, Role::Server, None).await,
+            peer,
+        );
+        assert_eq!(client.transport().await, Transport::WebSocket);
+        assert_eq!(client.peer_addr().await, peer);
+
+        client.send(&[1, 2, 3]).await.unwrap();
+        assert_eq!(server.recv().await.unwrap(), Some(vec![1, 2, 3]));
+
+        client.close().await;
+        assert_eq!(server.recv().await.unwrap(), None);
+    }
+
+    #[test]
+    fn test_transport_names() {
+        assert_eq!(Transport::Udp.to_string(), "udp");
+        assert_eq!(Transport::WebSocket.to_string(), "websocket");
+    }
+
+    #[tokio::test]
+    async fn test_trait_objects() {
+        let (conn, _remote, addr) = context_pair();
+        let conns: Vec<Box<dyn Connection>> = vec![Box::new(conn)];
+        assert_eq!(conns[0].peer_addr().await, addr);
+    }
+}
@@ -0,0 +1,106 @@
+#[cfg(all(test, feature = "tui"))]
+mod tests {
+    use std::{collections::HashMap, io::Write};
+
+    use zz_p2p::{
+        connections::{PeerConnection, Transport},
+        events::NodeEvent,
+        journal::Event,
+        protocols::{
+            bandwidth::BandwidthUsage,
+            commands::{message::IncomingMessage, node_registry::ConnectionDirection},
+        },
+        tui::{Dashboard, LOG_LINES, LogBuffer, RATE_HISTORY, describe_event, human_bytes},
+    };
+
+    #[test]
+    fn test_log_buffer_keeps_tail() {
+        let mut logs = LogBuffer::default();
+        for i in 0..LOG_LINES + 10 {
+            writeln!(logs, "line {}", i).unwrap();
+        }
+        // 空行被忽略
+        logs.write_all(b"\n\n").unwrap();
+
+        let tail = logs.tail(3);
+        assert_eq!(
+            tail,
+            vec![
+                format!("line {}", LOG_LINES + 7),
+                format!("line {}", LOG_LINES + 8),
+                format!("line {}", LOG_LINES + 9),
+            ]
+        );
+        assert_eq!(logs.tail(usize::MAX).len(), LOG_LINES);
+    }
+
+    #[test]
+    fn test_dashboard_events_and_rates() {
+        let mut dashboard = Dashboard::default();
+        dashboard.on_event(NodeEvent::Message(IncomingMessage {
+            from: "alice".to_string(),
+            content: "hi".to_string(),
+            timestamp: 0,
+            annotations: Default::default(),
+        }));
+        dashboard.on_event(NodeEvent::Journal(Event::Stopped {
+            address: "me".to_string(),
+        }));
+        assert_eq!(dashboard.messages.back().unwrap(), "alice: hi");
+        assert_eq!(dashboard.events.back().unwrap(), "node stopped");
+
+        for i in 0..RATE_HISTORY as u64 + 5 {
+            dashboard.on_usage(&BandwidthUsage {
+                upload_bytes: i,
+                download_bytes: i * 2,
+                upload_rate: i,
+                download_rate: i * 2,
+                peers: vec![],
+            });
+        }
+        assert_eq!(dashboard.upload_rates.len(), RATE_HISTORY);
+        assert_eq!(*dashboard.upload_rates.front().unwrap(), 5);
+        assert_eq!(dashboard.download_bytes, (RATE_HISTORY as u64 + 4) * 2);
+    }
+
+    #[test]
+    fn test_dashboard_peer_scores() {
+        let conn = PeerConnection {
+            addr: "10.0.0.2:10086".parse().unwrap(),
+            peer: Some("peer-a".to_string()),
+            transport: Transport::Tcp,
+            direction: ConnectionDirection::Outbound,
+            bytes_in: 0,
+            bytes_out: 0,
+            uptime_secs: 0,
+            rtt_ms: Some(12),
+            features: vec!["relay"],
+            max_frame_size: None,
+            agent: None,
+            cipher_suite: None,
+        };
+        let scores = HashMap::from([("10.0.0.2:10086".to_string(), 42)]);
+        let mut dashboard = Dashboard::default();
+        dashboard.set_peers(vec![conn.clone()], &scores);
+        assert_eq!(dashboard.peers.len(), 1);
+        assert_eq!(dashboard.peers[0].score, Some(42));
+        assert_eq!(dashboard.peers[0].conn, conn);
+
+        dashboard.set_peers(vec![conn], &HashMap::new());
+        assert_eq!(dashboard.peers[0].score, None);
+    }
+
+    #[test]
+    fn test_formatting() {
+        assert_eq!(human_bytes(512), "512 B");
+        assert_eq!(human_bytes(1536), "1.5 KiB");
+        assert_eq!(human_bytes(3 * 1024 * 1024), "3.0 MiB");
+        let line = describe_event(&Event::PeerConnected {
+            peer: "peer-a".to_string(),
+            addr: "10.0.0.2:1".to_string(),
+            inbound: true,
+        });
+        assert!(line.contains("peer-a"));
+        assert!(line.contains("10.0.0.2:1"));
+    }
+}
@@ -0,0 +1,140 @@
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use tempfile::tempdir;
+    use zz_p2p::{
+        cli::Opt,
+        protocols::commands::message::next_request_id,
+        wal::{WAL_FILE, WAL_RESEND_AFTER_MS, Wal, wal_path},
+    };
+
+    const PEER: &str = "peer-address";
+
+    #[test]
+    fn test_pending_survives_reopen() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(WAL_FILE);
+
+        {
+            let wal = Wal::open(&path, 1024 * 1024).unwrap();
+            wal.append(PEER, 1, "hello").unwrap();
+            wal.append(PEER, 2, "world").unwrap();
+            assert!(wal.complete(1, PEER).unwrap());
+        }
+
+        let wal = Wal::open(&path, 1024 * 1024).unwrap();
+        let pending = wal.pending();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].request_id, 2);
+        assert_eq!(pending[0].message, "world");
+        // 重放后 last_attempt 归零，接收方上线后立即补发
+        assert_eq!(pending[0].last_attempt, 0);
+    }
+
+    #[test]
+    fn test_duplicate_append_is_noop() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(WAL_FILE);
+        let wal = Wal::open(&path, 1024 * 1024).unwrap();
+
+        wal.append(PEER, 7, "once").unwrap();
+        let size = std::fs::metadata(&path).unwrap().len();
+        wal.append(PEER, 7, "once").unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), size);
+        assert_eq!(wal.pending().len(), 1);
+    }
+
+    #[test]
+    fn test_complete_requires_matching_receiver() {
+        let dir = tempdir().unwrap();
+        let wal = Wal::open(dir.path().join(WAL_FILE), 1024 * 1024).unwrap();
+
+        wal.append(PEER, 3, "msg").unwrap();
+        assert!(!wal.complete(3, "someone-else").unwrap());
+        assert!(!wal.complete(99, PEER).unwrap());
+        assert!(wal.complete(3, PEER).unwrap());
+        assert!(wal.pending().is_empty());
+    }
+
+    #[test]
+    fn test_truncated_tail_is_ignored() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(WAL_FILE);
+        {
+            let wal = Wal::open(&path, 1024 * 1024).unwrap();
+            wal.append(PEER, 4, "intact").unwrap();
+        }
+        // 模拟崩溃时写了一半的记录
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"{\"op\":\"append\",\"request_").unwrap();
+        drop(file);
+
+        let wal = Wal::open(&path, 1024 * 1024).unwrap();
+        let pending = wal.pending();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].request_id, 4);
+    }
+
+    #[test]
+    fn test_rotation_keeps_only_pending() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(WAL_FILE);
+        let wal = Wal::open(&path, 512).unwrap();
+
+        for id in 1..=20u64 {
+            wal.append(PEER, id, &"x".repeat(32)).unwrap();
+            if id != 20 {
+                wal.complete(id, PEER).unwrap();
+            }
+        }
+
+        assert!(dir.path().join("outbound.wal.1").exists());
+        assert!(std::fs::metadata(&path).unwrap().len() <= 512);
+        drop(wal);
+
+        let wal = Wal::open(&path, 512).unwrap();
+        let ids: Vec<u64> = wal.pending().iter().map(|f| f.request_id).collect();
+        assert_eq!(ids, vec![20]);
+    }
+
+    #[test]
+    fn test_due_for_resend_respects_interval() {
+        let dir = tempdir().unwrap();
+        let wal = Wal::open(dir.path().join(WAL_FILE), 1024 * 1024).unwrap();
+        wal.append(PEER, 5, "retry").unwrap();
+
+        let created = wal.pending()[0].last_attempt;
+        assert!(wal.due_for_resend(created + 1).is_empty());
+
+        let later = created + WAL_RESEND_AFTER_MS;
+        assert_eq!(wal.due_for_resend(later).len(), 1);
+        // 刚补发过，下一轮不再重复
+        assert!(wal.due_for_resend(later + 1).is_empty());
+    }
+
+    #[test]
+    fn test_reopen_reserves_request_ids() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(WAL_FILE);
+        {
+            let wal = Wal::open(&path, 1024 * 1024).unwrap();
+            wal.append(PEER, 1_000_000, "high id").unwrap();
+        }
+        let _wal = Wal::open(&path, 1024 * 1024).unwrap();
+        assert!(next_request_id() > 1_000_000);
+    }
+
+    #[test]
+    fn test_wal_path_uses_data_dir() {
+        let mut opt = Opt::default();
+        opt.data_dir = Some("/tmp/zz-node".to_string());
+        assert_eq!(
+            wal_path(&opt),
+            std::path::PathBuf::from("/tmp/zz-node").join(WAL_FILE)
+        );
+    }
+}
@@ -0,0 +1,222 @@
+#[cfg(test)]
+mod tests {
+    use base64::Engine;
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+    use zz_p2p::web::{
+        aex_re_exports::StatusCode,
+        auth::{
+            self, Access, AuthError, Credentials, TOKEN_PREFIX, WebAuthConfig, WebRole, WebUserList,
+        },
+    };
+
+    fn enabled(allow_basic: bool) -> WebAuthConfig {
+        WebAuthConfig {
+            enabled: true,
+            allow_basic,
+            ..WebAuthConfig::default()
+        }
+    }
+
+    fn basic(user: &str, password: &str) -> String {
+        let encoded =
+            base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", user, password));
+        format!("Basic {}", encoded)
+    }
+
+    #[test]
+    fn test_parse_credentials() {
+        assert_eq!(
+            Credentials::parse("Bearer zzp_abc"),
+            Some(Credentials::Bearer("zzp_abc".into()))
+        );
+        assert_eq!(
+            Credentials::parse("bearer  zzp_abc "),
+            Some(Credentials::Bearer("zzp_abc".into()))
+        );
+        assert_eq!(
+            Credentials::parse(&basic("alice", "pa:ss")),
+            Some(Credentials::Basic {
+                user: "alice".into(),
+                password: "pa:ss".into(),
+            })
+        );
+        assert!(Credentials::parse("Bearer").is_none());
+        assert!(Credentials::parse("Basic not-base64!").is_none());
+        assert!(Credentials::parse("Digest x").is_none());
+    }
+
+    #[test]
+    fn test_passwords_are_salted() {
+        let a = auth::hash_password("secret");
+        let b = auth::hash_password("secret");
+        assert_ne!(a, b);
+        assert!(!a.contains("secret"));
+        assert!(a.starts_with("$argon2id$"));
+        assert!(!auth::is_legacy_password(&a));
+        assert!(auth::verify_password(&a, "secret"));
+        assert!(auth::verify_password(&b, "secret"));
+        assert!(!auth::verify_password(&a, "Secret"));
+        assert!(!auth::verify_password("garbage", "secret"));
+        assert!(!auth::verify_password("$argon2id$garbage", "secret"));
+    }
+
+    #[test]
+    fn test_legacy_hmac_passwords_still_verify() {
+        let salt = "0123456789abcdef";
+        let mut mac = Hmac::<Sha256>::new_from_slice(salt.as_bytes()).unwrap();
+        mac.update(b"secret");
+        let digest: String = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        let stored = format!("{}${}", salt, digest);
+
+        assert!(auth::is_legacy_password(&stored));
+        assert!(auth::verify_password(&stored, "secret"));
+        assert!(!auth::verify_password(&stored, "Secret"));
+    }
+
+    #[test]
+    fn test_user_list_management() {
+        let mut users = WebUserList::default();
+        users.add("alice", WebRole::User, Some("pw")).unwrap();
+        assert!(users.add("alice", WebRole::Admin, None).is_err());
+        assert!(users.add("bad name", WebRole::User, None).is_err());
+        assert!(users.add("a:b", WebRole::User, None).is_err());
+
+        let token = users.issue_token("alice", "ci", 1).unwrap();
+        assert!(token.starts_with(TOKEN_PREFIX));
+        assert!(users.issue_token("bob", "ci", 1).is_err());
+        // 只保存摘要
+        let json = serde_json::to_string(&users).unwrap();
+        assert!(!json.contains(&token));
+        assert!(!json.contains("\"pw\""));
+        let back: WebUserList = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, users);
+
+        // 同名 token 被替换
+        let renewed = users.issue_token("alice", "ci", 2).unwrap();
+        assert_eq!(users.get("alice").unwrap().tokens.len(), 1);
+        assert!(
+            users
+                .authenticate(&Credentials::Bearer(token.clone()))
+                .is_none()
+        );
+        assert_eq!(
+            users
+                .authenticate(&Credentials::Bearer(renewed.clone()))
+                .map(|u| u.name.as_str()),
+            Some("alice")
+        );
+
+        assert!(users.revoke_token("alice", "ci"));
+        assert!(!users.revoke_token("alice", "ci"));
+        assert!(users.authenticate(&Credentials::Bearer(renewed)).is_none());
+
+        users.set_password("alice", None).unwrap();
+        assert!(
+            users
+                .authenticate(&Credentials::Basic {
+                    user: "alice".into(),
+                    password: "pw".into(),
+                })
+                .is_none()
+        );
+        assert!(users.remove("alice").is_some());
+        assert!(users.remove("alice").is_none());
+    }
+
+    #[test]
+    fn test_authorize_by_route_access() {
+        let mut users = WebUserList::default();
+        users.add("viewer", WebRole::Viewer, Some("pw")).unwrap();
+        users.add("admin", WebRole::Admin, None).unwrap();
+        let viewer = users.issue_token("viewer", "t", 1).unwrap();
+        let admin = users.issue_token("admin", "t", 1).unwrap();
+        let config = enabled(false);
+        let bearer = |t: &str| format!("Bearer {}", t);
+
+        assert_eq!(
+            auth::authorize(&config, &users, None, Access::Viewer),
+            Err(AuthError::Unauthorized)
+        );
+        assert_eq!(
+            auth::authorize(&config, &users, Some("Bearer zzp_wrong"), Access::Viewer),
+            Err(AuthError::Unauthorized)
+        );
+        assert_eq!(
+            auth::authorize(&config, &users, Some(&bearer(&viewer)), Access::Viewer),
+            Ok(Some("viewer".into()))
+        );
+        assert_eq!(
+            auth::authorize(&config, &users, Some(&bearer(&viewer)), Access::User),
+            Err(AuthError::Forbidden)
+        );
+        assert_eq!(
+            auth::authorize(&config, &users, Some(&bearer(&admin)), Access::Admin),
+            Ok(Some("admin".into()))
+        );
+        // 公开路由不需要凭据
+        assert_eq!(
+            auth::authorize(&config, &users, None, Access::Public),
+            Ok(None)
+        );
+        // 未启用时全部放行
+        assert_eq!(
+            auth::authorize(&WebAuthConfig::default(), &users, None, Access::Admin),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn test_basic_auth_only_when_allowed() {
+        let mut users = WebUserList::default();
+        users.add("alice", WebRole::User, Some("pw")).unwrap();
+        let header = basic("alice", "pw");
+
+        assert_eq!(
+            auth::authorize(&enabled(false), &users, Some(&header), Access::User),
+            Err(AuthError::Unauthorized)
+        );
+        assert_eq!(
+            auth::authorize(&enabled(true), &users, Some(&header), Access::User),
+            Ok(Some("alice".into()))
+        );
+        assert_eq!(
+            auth::authorize(
+                &enabled(true),
+                &users,
+                Some(&basic("alice", "nope")),
+                Access::User
+            ),
+            Err(AuthError::Unauthorized)
+        );
+    }
+
+    #[test]
+    fn test_errors_map_to_status_codes() {
+        assert_eq!(AuthError::Unauthorized.status(), StatusCode::Unauthorized);
+        assert_eq!(AuthError::Forbidden.status(), StatusCode::Forbidden);
+        assert_eq!(AuthError::Unauthorized.status() as u16, 401);
+        assert_eq!(AuthError::Forbidden.reason(), "Forbidden");
+        assert_eq!(auth::challenges(&enabled(false)).len(), 1);
+        let challenges = auth::challenges(&enabled(true));
+        assert_eq!(challenges.len(), 2);
+        assert!(challenges[1].starts_with("Basic realm=\"zz-p2p\""));
+    }
+
+    #[test]
+    fn test_role_parsing_and_config_defaults() {
+        assert_eq!("Admin".parse::<WebRole>().unwrap(), WebRole::Admin);
+        assert!("root".parse::<WebRole>().is_err());
+        assert!(WebRole::Admin > WebRole::User && WebRole::User > WebRole::Viewer);
+
+        let config: WebAuthConfig = toml::from_str("enabled = true").unwrap();
+        assert!(config.enabled);
+        assert!(!config.allow_basic);
+        assert_eq!(config.realm, auth::DEFAULT_REALM);
+    }
+}
@@ -0,0 +1,170 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        sync::mpsc,
+    };
+    use zz_p2p::{
+        events::NodeEvent,
+        journal::Event,
+        protocols::commands::message::IncomingMessage,
+        webhook::{
+            WEBHOOK_BACKOFF_MAX_MS, Webhook, deliver, hmac_hex, payload, retry_delay, retryable,
+            sign, validate_url,
+        },
+    };
+
+    fn hook(url: &str, events: &[&str]) -> Webhook {
+        Webhook {
+            id: "hook-1".to_string(),
+            url: url.to_string(),
+            secret: "s3cret".to_string(),
+            events: events.iter().map(|e| e.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_hmac_signature() {
+        // RFC 4231 测试用例 2
+        assert_eq!(
+            hmac_hex("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            sign("Jefe", 42, "{}"),
+            format!("sha256={}", hmac_hex("Jefe", b"42.{}"))
+        );
+    }
+
+    #[test]
+    fn test_filters_and_payload() {
+        let all = hook("http://127.0.0.1/", &[]);
+        let peers = hook("http://127.0.0.1/", &["peer", "message.received"]);
+        assert!(all.wants("node.started"));
+        assert!(peers.wants("peer.connected"));
+        assert!(peers.wants("message.received"));
+        assert!(!peers.wants("message.forwarded"));
+        assert!(!peers.wants("peers.connected"));
+
+        let (name, body) = payload(
+            &NodeEvent::Message(IncomingMessage {
+                from: "alice".to_string(),
+                content: "hi".to_string(),
+                timestamp: 7,
+                annotations: Default::default(),
+            }),
+            "me",
+            100,
+        );
+        assert_eq!(name, "message.received");
+        assert_eq!(body["node"], "me");
+        assert_eq!(body["data"]["from"], "alice");
+
+        let (name, body) = payload(
+            &NodeEvent::Journal(Event::PeerConnected {
+                peer: "bob".to_string(),
+                addr: "10.0.0.2:1".to_string(),
+                inbound: true,
+            }),
+            "me",
+            100,
+        );
+        assert_eq!(name, "peer.connected");
+        assert_eq!(body["event"], "peer.connected");
+        assert_eq!(body["data"]["peer"], "bob");
+        // 密钥不出现在列表中
+        assert!(all.redacted().get("secret").is_none());
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        assert_eq!(retry_delay(1), Duration::from_secs(1));
+        assert_eq!(retry_delay(2), Duration::from_secs(2));
+        assert_eq!(retry_delay(3), Duration::from_secs(4));
+        assert_eq!(
+            retry_delay(30),
+            Duration::from_millis(WEBHOOK_BACKOFF_MAX_MS)
+        );
+        assert!(retryable(503));
+        assert!(retryable(429));
+        assert!(!retryable(404));
+
+        assert!(validate_url("https://example.com/hook").is_ok());
+        assert!(validate_url("ftp://example.com/hook").is_err());
+        assert!(validate_url("not a url").is_err());
+    }
+
+    /// 第一次返回 503，之后返回 200；把每次请求的 (签名, 时间戳, 正文) 发回测试
+    async fn flaky_server() -> (String, mpsc::UnboundedReceiver<(String, String, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut served = 0;
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 4096];
+                let (head, body) = loop {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                    let text = String::from_utf8_lossy(&buf).to_string();
+                    if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                        let length = head
+                            .lines()
+                            .filter_map(|l| l.split_once(':'))
+                            .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+                            .map(|(_, v)| v.trim().parse::<usize>().unwrap())
+                            .unwrap_or(0);
+                        if body.len() >= length {
+                            break (head.to_string(), body.to_string());
+                        }
+                    }
+                };
+                let header = |name: &str| {
+                    head.lines()
+                        .filter_map(|l| l.split_once(':'))
+                        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                        .map(|(_, v)| v.trim().to_string())
+                        .unwrap_or_default()
+                };
+                let _ = tx.send((header("x-zz-signature"), header("x-zz-timestamp"), body));
+                served += 1;
+                let status = if served == 1 {
+                    "503 Service Unavailable"
+                } else {
+                    "200 OK"
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, rx)
+    }
+
+    #[tokio::test]
+    async fn test_delivery_retries_and_signs() {
+        let (url, mut requests) = flaky_server().await;
+        let hook = hook(&url, &[]);
+        let client = reqwest::Client::new();
+        let body = r#"{"event":"peer.connected"}"#;
+
+        let attempts = deliver(&client, &hook, "peer.connected", body)
+            .await
+            .unwrap();
+        assert_eq!(attempts, 2);
+
+        for _ in 0..2 {
+            let (signature, timestamp, received) = requests.recv().await.unwrap();
+            assert_eq!(received, body);
+            let timestamp: u128 = timestamp.parse().unwrap();
+            assert_eq!(signature, sign(&hook.secret, timestamp, body));
+        }
+    }
+}
@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod tests {
+    use aex::tcp::types::{Codec, Frame};
+    use zz_account::address::FreeWebMovementAddress;
+    use zz_p2p::protocols::{
+        command::{Action, Entity, P2PCommand},
+        commands::ping::PingCommand,
+        compression::{CAP_COMPRESSION_LZ4, PeerCapabilities},
+        frame::P2PFrame,
+        version::CURRENT_PROTOCOL_VERSION,
+        wire_format::{self, CAP_FORMAT_CBOR, FORMAT_FRAME_MARKER, PeerWireFormat, WireFormat},
+    };
+
+    async fn frame_as(format: WireFormat) -> (P2PFrame, P2PCommand) {
+        let address = FreeWebMovementAddress::random();
+        let cmd = P2PCommand::new(Entity::Node, Action::Ping, vec![1, 2, 3]);
+        let frame = P2PFrame::build_as(
+            &address,
+            cmd.clone(),
+            CURRENT_PROTOCOL_VERSION,
+            None,
+            format,
+        )
+        .await
+        .unwrap();
+        (frame, cmd)
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let cbor_peer = Some(PeerCapabilities(CAP_COMPRESSION_LZ4 | CAP_FORMAT_CBOR));
+        let legacy_peer = Some(PeerCapabilities(CAP_COMPRESSION_LZ4));
+
+        assert_eq!(
+            wire_format::negotiate(None, cbor_peer, WireFormat::Bincode),
+            WireFormat::Bincode
+        );
+        assert_eq!(
+            wire_format::negotiate(None, cbor_peer, WireFormat::Cbor),
+            WireFormat::Cbor
+        );
+        // 对端未声明支持时退回 bincode
+        assert_eq!(
+            wire_format::negotiate(None, legacy_peer, WireFormat::Cbor),
+            WireFormat::Bincode
+        );
+        assert_eq!(
+            wire_format::negotiate(None, cbor_peer, WireFormat::Json),
+            WireFormat::Bincode
+        );
+        assert_eq!(
+            wire_format::negotiate(None, None, WireFormat::Cbor),
+            WireFormat::Bincode
+        );
+        // 对端以 JSON 握手时始终沿用 JSON
+        assert_eq!(
+            wire_format::negotiate(
+                Some(PeerWireFormat(WireFormat::Json)),
+                legacy_peer,
+                WireFormat::Cbor
+            ),
+            WireFormat::Json
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bincode_frames_unchanged() {
+        let (frame, _) = frame_as(WireFormat::Bincode).await;
+        let bytes = Codec::encode(&frame).unwrap();
+        assert_eq!(bytes[0], CURRENT_PROTOCOL_VERSION);
+        let decoded: P2PFrame = Codec::decode(&bytes).unwrap();
+        assert_eq!(decoded.format, WireFormat::Bincode);
+        assert!(decoded.validate());
+    }
+
+    #[tokio::test]
+    async fn test_cbor_and_json_frames_roundtrip() {
+        for format in [WireFormat::Cbor, WireFormat::Json] {
+            let (frame, cmd) = frame_as(format).await;
+            let bytes = Codec::encode(&frame).unwrap();
+            assert_eq!(bytes[0], FORMAT_FRAME_MARKER | format.id());
+
+            let decoded = P2PFrame::verify_bytes(&bytes).unwrap();
+            assert_eq!(decoded.format, format);
+            assert!(decoded.validate());
+            assert_eq!(decoded.body.address, frame.body.address);
+
+            // router 拿到的命令字节已转为 bincode
+            let routed: P2PCommand = Codec::decode(decoded.command().unwrap()).unwrap();
+            assert_eq!(routed, cmd);
+
+            // 中继时重新编码，签名对象原样保留
+            assert_eq!(Codec::encode(&decoded).unwrap(), bytes);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_signing_bytes_are_shared() {
+        // 非 bincode 帧的 body 原始字节在验签、转发间共享，不重新分配
+        let (frame, _) = frame_as(WireFormat::Cbor).await;
+        let a = frame.signing_bytes().unwrap();
+        let b = frame.clone().signing_bytes().unwrap();
+        assert_eq!(a.as_ptr(), b.as_ptr());
+    }
+
+    #[tokio::test]
+    async fn test_json_frame_is_readable() {
+        let (frame, _) = frame_as(WireFormat::Json).await;
+        let body = String::from_utf8(frame.signing_bytes().unwrap().to_vec()).unwrap();
+        assert!(body.contains(&format!("\"address\":\"{}\"", frame.body.address)));
+    }
+
+    #[tokio::test]
+    async fn test_tampered_signature_fails_validation() {
+        let (mut frame, _) = frame_as(WireFormat::Cbor).await;
+        // 修改签名后不再通过校验
+        frame.signature[0] ^= 0xff;
+        let bytes = Codec::encode(&frame).unwrap();
+        let decoded: P2PFrame = Codec::decode(&bytes).unwrap();
+        assert!(!decoded.validate());
+    }
+
+    #[tokio::test]
+    async fn test_decode_payload_follows_frame_format() {
+        let ping = PingCommand {
+            nonce: 7,
+            timestamp: 1_700_000_000_000,
+        };
+        let (frame, _) = frame_as(WireFormat::Cbor).await;
+        let data = wire_format::encode(WireFormat::Cbor, &ping).unwrap();
+        let back: PingCommand = frame.decode_payload(&data).unwrap();
+        assert_eq!(back, ping);
+        assert!(
+            frame
+                .decode_payload::<PingCommand>(&Codec::encode(&ping).unwrap())
+                .is_err()
+        );
+    }
+}